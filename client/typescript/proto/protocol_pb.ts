@@ -12,7 +12,7 @@ import type { Message } from "@bufbuild/protobuf";
  * Describes the file protocol.proto.
  */
 export const file_protocol: GenFile = /*@__PURE__*/
-  fileDesc("Cg5wcm90b2NvbC5wcm90bxIIcHJvdG9jb2wisgEKBlRyaXBsZRIWCgllbnRpdHlfaWQYASABKAxIAIgBARIZCgxhdHRyaWJ1dGVfaWQYAiABKAxIAYgBARIkCgV2YWx1ZRgDIAEoCzIVLnByb3RvY29sLlRyaXBsZVZhbHVlEigKA2hsYxgEIAEoCzIWLnByb3RvY29sLkhsY1RpbWVzdGFtcEgCiAEBQgwKCl9lbnRpdHlfaWRCDwoNX2F0dHJpYnV0ZV9pZEIGCgRfaGxjIlIKDEhsY1RpbWVzdGFtcBIYChBwaHlzaWNhbF90aW1lX21zGAEgASgEEhcKD2xvZ2ljYWxfY291bnRlchgCIAEoDRIPCgdub2RlX2lkGAMgASgNIk0KC1RyaXBsZVZhbHVlEhAKBnN0cmluZxgDIAEoCUgAEhAKBm51bWJlchgEIAEoAUgAEhEKB2Jvb2xlYW4YBSABKAhIAEIHCgV2YWx1ZSIlCg5Db25uZWN0UmVxdWVzdBITCgthcHBfYXBpX2tleRgBIAEoCSK+AgoNQ2xpZW50TWVzc2FnZRIXCgpyZXF1ZXN0X2lkGAEgASgNSAGIAQESPgoVdHJpcGxlX3VwZGF0ZV9yZXF1ZXN0GAIgASgLMh0ucHJvdG9jb2wuVHJpcGxlVXBkYXRlUmVxdWVzdEgAEicKBXF1ZXJ5GAMgASgLMhYucHJvdG9jb2wuUXVlcnlSZXF1ZXN0SAASLwoJc3Vic2NyaWJlGAQgASgLMhoucHJvdG9jb2wuU3Vic2NyaWJlUmVxdWVzdEgAEjMKC3Vuc3Vic2NyaWJlGAUgASgLMhwucHJvdG9jb2wuVW5zdWJzY3JpYmVSZXF1ZXN0SAASKwoHY29ubmVjdBgGIAEoCzIYLnByb3RvY29sLkNvbm5lY3RSZXF1ZXN0SABCCQoHcGF5bG9hZEINCgtfcmVxdWVzdF9pZCK4AQoMUXVlcnlSZXF1ZXN0EiwKBGZpbmQYASADKAsyHi5wcm90b2NvbC5RdWVyeVBhdHRlcm5WYXJpYWJsZRIlCgV3aGVyZRgCIAMoCzIWLnByb3RvY29sLlF1ZXJ5UGF0dGVybhIoCghvcHRpb25hbBgDIAMoCzIWLnByb3RvY29sLlF1ZXJ5UGF0dGVybhIpCgl3aGVyZV9ub3QYBCADKAsyFi5wcm90b2NvbC5RdWVyeVBhdHRlcm4ivAIKDFF1ZXJ5UGF0dGVybhITCgllbnRpdHlfaWQYASABKAxIABI5Cg9lbnRpdHlfdmFyaWFibGUYAiABKAsyHi5wcm90b2NvbC5RdWVyeVBhdHRlcm5WYXJpYWJsZUgAEhYKDGF0dHJpYnV0ZV9pZBgDIAEoDEgBEjwKEmF0dHJpYnV0ZV92YXJpYWJsZRgEIAEoCzIeLnByb3RvY29sLlF1ZXJ5UGF0dGVyblZhcmlhYmxlSAESJgoFdmFsdWUYBSABKAsyFS5wcm90b2NvbC5UcmlwbGVWYWx1ZUgCEjgKDnZhbHVlX3ZhcmlhYmxlGAYgASgLMh4ucHJvdG9jb2wuUXVlcnlQYXR0ZXJuVmFyaWFibGVIAkIICgZlbnRpdHlCCwoJYXR0cmlidXRlQg0KC3ZhbHVlX2dyb3VwIjQKFFF1ZXJ5UGF0dGVyblZhcmlhYmxlEhIKBWxhYmVsGAEgASgJSACIAQFCCAoGX2xhYmVsImkKEFN1YnNjcmliZVJlcXVlc3QSFwoPc3Vic2NyaXB0aW9uX2lkGAEgASgNEi4KCXNpbmNlX2hsYxgCIAEoCzIWLnByb3RvY29sLkhsY1RpbWVzdGFtcEgAiAEBQgwKCl9zaW5jZV9obGMiLQoSVW5zdWJzY3JpYmVSZXF1ZXN0EhcKD3N1YnNjcmlwdGlvbl9pZBgBIAEoDSJbCgxDaGFuZ2VSZWNvcmQSKQoLY2hhbmdlX3R5cGUYASABKA4yFC5wcm90b2NvbC5DaGFuZ2VUeXBlEiAKBnRyaXBsZRgCIAEoCzIQLnByb3RvY29sLlRyaXBsZSJWChJTdWJzY3JpcHRpb25VcGRhdGUSFwoPc3Vic2NyaXB0aW9uX2lkGAEgASgNEicKB2NoYW5nZXMYAiADKAsyFi5wcm90b2NvbC5DaGFuZ2VSZWNvcmQiOAoTVHJpcGxlVXBkYXRlUmVxdWVzdBIhCgd0cmlwbGVzGAEgAygLMhAucHJvdG9jb2wuVHJpcGxlIoUBCg1TZXJ2ZXJNZXNzYWdlEiwKCHJlc3BvbnNlGAEgASgLMhgucHJvdG9jb2wuU2VydmVyUmVzcG9uc2VIABI7ChNzdWJzY3JpcHRpb25fdXBkYXRlGAIgASgLMhwucHJvdG9jb2wuU3Vic2NyaXB0aW9uVXBkYXRlSABCCQoHcGF5bG9hZCJuChBRdWVyeVJlc3VsdFZhbHVlEgwKAmlkGAEgASgJSAASLQoMdHJpcGxlX3ZhbHVlGAIgASgLMhUucHJvdG9jb2wuVHJpcGxlVmFsdWVIABIUCgxpc191bmRlZmluZWQYAyABKAhCBwoFdmFsdWUiPAoOUXVlcnlSZXN1bHRSb3cSKgoGdmFsdWVzGAEgAygLMhoucHJvdG9jb2wuUXVlcnlSZXN1bHRWYWx1ZSLIAQoOU2VydmVyUmVzcG9uc2USFwoKcmVxdWVzdF9pZBgBIAEoDUgAiAEBEicKBnN0YXR1cxgCIAEoCzISLmdvb2dsZS5ycGMuU3RhdHVzSAGIAQESIQoHdHJpcGxlcxgDIAMoCzIQLnByb3RvY29sLlRyaXBsZRIPCgdjb2x1bW5zGAQgAygJEiYKBHJvd3MYBSADKAsyGC5wcm90b2NvbC5RdWVyeVJlc3VsdFJvd0INCgtfcmVxdWVzdF9pZEIJCgdfc3RhdHVzKnEKCkNoYW5nZVR5cGUSGwoXQ0hBTkdFX1RZUEVfVU5TUEVDSUZJRUQQABIWChJDSEFOR0VfVFlQRV9JTlNFUlQQARIWChJDSEFOR0VfVFlQRV9VUERBVEUQAhIWChJDSEFOR0VfVFlQRV9ERUxFVEUQA2IGcHJvdG8z", [file_google_rpc_status]);
+  fileDesc("Cg5wcm90b2NvbC5wcm90bxIIcHJvdG9jb2wi4gEKBlRyaXBsZRIWCgllbnRpdHlfaWQYASABKAxIAIgBARIZCgxhdHRyaWJ1dGVfaWQYAiABKAxIAYgBARIkCgV2YWx1ZRgDIAEoCzIVLnByb3RvY29sLlRyaXBsZVZhbHVlEigKA2hsYxgEIAEoCzIWLnByb3RvY29sLkhsY1RpbWVzdGFtcEgCiAEBEhsKDmF0dHJpYnV0ZV9uYW1lGAUgASgJSAOIAQFCDAoKX2VudGl0eV9pZEIPCg1fYXR0cmlidXRlX2lkQgYKBF9obGNCEQoPX2F0dHJpYnV0ZV9uYW1lIlIKDEhsY1RpbWVzdGFtcBIYChBwaHlzaWNhbF90aW1lX21zGAEgASgEEhcKD2xvZ2ljYWxfY291bnRlchgCIAEoDRIPCgdub2RlX2lkGAMgASgNIl4KC1RyaXBsZVZhbHVlEhAKBnN0cmluZxgDIAEoCUgAEhAKBm51bWJlchgEIAEoAUgAEhEKB2Jvb2xlYW4YBSABKAhIABIPCgVieXRlcxgGIAEoDEgAQgcKBXZhbHVlIk0KDkNvbm5lY3RSZXF1ZXN0EhMKC2FwcF9hcGlfa2V5GAEgASgJEhcKCmF1dGhfdG9rZW4YAiABKAlIAIgBAUINCgtfYXV0aF90b2tlbiLaBwoNQ2xpZW50TWVzc2FnZRIXCgpyZXF1ZXN0X2lkGAEgASgNSAGIAQESPgoVdHJpcGxlX3VwZGF0ZV9yZXF1ZXN0GAIgASgLMh0ucHJvdG9jb2wuVHJpcGxlVXBkYXRlUmVxdWVzdEgAEicKBXF1ZXJ5GAMgASgLMhYucHJvdG9jb2wuUXVlcnlSZXF1ZXN0SAASLwoJc3Vic2NyaWJlGAQgASgLMhoucHJvdG9jb2wuU3Vic2NyaWJlUmVxdWVzdEgAEjMKC3Vuc3Vic2NyaWJlGAUgASgLMhwucHJvdG9jb2wuVW5zdWJzY3JpYmVSZXF1ZXN0SAASKwoHY29ubmVjdBgGIAEoCzIYLnByb3RvY29sLkNvbm5lY3RSZXF1ZXN0SAASLQoIY29udGFpbnMYByABKAsyGS5wcm90b2NvbC5Db250YWluc1JlcXVlc3RIABIyCgtvcGVuX2N1cnNvchgIIAEoCzIbLnByb3RvY29sLk9wZW5DdXJzb3JSZXF1ZXN0SAASNAoMZmV0Y2hfY3Vyc29yGAkgASgLMhwucHJvdG9jb2wuRmV0Y2hDdXJzb3JSZXF1ZXN0SAASNAoMY2xvc2VfY3Vyc29yGAogASgLMhwucHJvdG9jb2wuQ2xvc2VDdXJzb3JSZXF1ZXN0SAASNAoMc2V0X2RlZmF1bHRzGAsgASgLMhwucHJvdG9jb2wuU2V0RGVmYXVsdHNSZXF1ZXN0SAASKQoGZGVsZXRlGAwgASgLMhcucHJvdG9jb2wuRGVsZXRlUmVxdWVzdEgAEjAKCnJlYWRfYmF0Y2gYDSABKAsyGi5wcm90b2NvbC5SZWFkQmF0Y2hSZXF1ZXN0SAASOgoPZGVzY3JpYmVfc2NoZW1hGA4gASgLMh8ucHJvdG9jb2wuRGVzY3JpYmVTY2hlbWFSZXF1ZXN0SAASLgoJYmVnaW5fdHhuGA8gASgLMhkucHJvdG9jb2wuQmVnaW5UeG5SZXF1ZXN0SAASMAoKY29tbWl0X3R4bhgQIAEoCzIaLnByb3RvY29sLkNvbW1pdFR4blJlcXVlc3RIABIuCglhYm9ydF90eG4YESABKAsyGS5wcm90b2NvbC5BYm9ydFR4blJlcXVlc3RIABI6Cg9zdWJzY3JpYmVfcXVlcnkYEiABKAsyHy5wcm90b2NvbC5TdWJzY3JpYmVRdWVyeVJlcXVlc3RIABIuCglidWxrX2xvYWQYEyABKAsyGS5wcm90b2NvbC5CdWxrTG9hZFJlcXVlc3RIAEIJCgdwYXlsb2FkQg0KC19yZXF1ZXN0X2lkIqUDCgxRdWVyeVJlcXVlc3QSLAoEZmluZBgBIAMoCzIeLnByb3RvY29sLlF1ZXJ5UGF0dGVyblZhcmlhYmxlEiUKBXdoZXJlGAIgAygLMhYucHJvdG9jb2wuUXVlcnlQYXR0ZXJuEigKCG9wdGlvbmFsGAMgAygLMhYucHJvdG9jb2wuUXVlcnlQYXR0ZXJuEikKCXdoZXJlX25vdBgEIAMoCzIWLnByb3RvY29sLlF1ZXJ5UGF0dGVybhIRCglhcnJvd19pcGMYBSABKAgSEgoFbGltaXQYBiABKA1IAIgBARITCgZvZmZzZXQYByABKA1IAYgBARIoCghvcmRlcl9ieRgIIAMoCzIWLnByb3RvY29sLlF1ZXJ5T3JkZXJCeRIsCgphZ2dyZWdhdGVzGAkgAygLMhgucHJvdG9jb2wuUXVlcnlBZ2dyZWdhdGUSMAoIZ3JvdXBfYnkYCiADKAsyHi5wcm90b2NvbC5RdWVyeVBhdHRlcm5WYXJpYWJsZRIQCghkaXN0aW5jdBgLIAEoCEIICgZfbGltaXRCCQoHX29mZnNldCKRAQoMUXVlcnlPcmRlckJ5EjAKCHZhcmlhYmxlGAEgASgLMh4ucHJvdG9jb2wuUXVlcnlQYXR0ZXJuVmFyaWFibGUSKgoJZGlyZWN0aW9uGAIgASgOMhcucHJvdG9jb2wuU29ydERpcmVjdGlvbhIjCgVudWxscxgDIAEoDjIULnByb3RvY29sLk51bGxzT3JkZXIicQoOUXVlcnlBZ2dyZWdhdGUSLQoIZnVuY3Rpb24YASABKA4yGy5wcm90b2NvbC5BZ2dyZWdhdGVGdW5jdGlvbhIwCgh2YXJpYWJsZRgCIAEoCzIeLnByb3RvY29sLlF1ZXJ5UGF0dGVyblZhcmlhYmxlIqwDCgxRdWVyeVBhdHRlcm4SEwoJZW50aXR5X2lkGAEgASgMSAASOQoPZW50aXR5X3ZhcmlhYmxlGAIgASgLMh4ucHJvdG9jb2wuUXVlcnlQYXR0ZXJuVmFyaWFibGVIABIrCgplbnRpdHlfaWRzGAggASgLMhUucHJvdG9jb2wuRW50aXR5SWRTZXRIABIWCgxhdHRyaWJ1dGVfaWQYAyABKAxIARI8ChJhdHRyaWJ1dGVfdmFyaWFibGUYBCABKAsyHi5wcm90b2NvbC5RdWVyeVBhdHRlcm5WYXJpYWJsZUgBEhgKDmF0dHJpYnV0ZV9uYW1lGAcgASgJSAESJgoFdmFsdWUYBSABKAsyFS5wcm90b2NvbC5UcmlwbGVWYWx1ZUgCEjgKDnZhbHVlX3ZhcmlhYmxlGAYgASgLMh4ucHJvdG9jb2wuUXVlcnlQYXR0ZXJuVmFyaWFibGVIAhInCglhbnlfdmFsdWUYCSABKAsyEi5wcm90b2NvbC5BbnlWYWx1ZUgCQggKBmVudGl0eUILCglhdHRyaWJ1dGVCDQoLdmFsdWVfZ3JvdXAiNAoUUXVlcnlQYXR0ZXJuVmFyaWFibGUSEgoFbGFiZWwYASABKAlIAIgBAUIICgZfbGFiZWwiZQoLRW50aXR5SWRTZXQSEgoKZW50aXR5X2lkcxgBIAMoDBI1Cgh2YXJpYWJsZRgCIAEoCzIeLnByb3RvY29sLlF1ZXJ5UGF0dGVyblZhcmlhYmxlSACIAQFCCwoJX3ZhcmlhYmxlIgoKCEFueVZhbHVlIv0CChBTdWJzY3JpYmVSZXF1ZXN0EhcKD3N1YnNjcmlwdGlvbl9pZBgBIAEoDRIuCglzaW5jZV9obGMYAiABKAsyFi5wcm90b2NvbC5IbGNUaW1lc3RhbXBIAIgBARIqCgVxdWVyeRgDIAEoCzIWLnByb3RvY29sLlF1ZXJ5UmVxdWVzdEgBiAEBEhwKD3dhdGNoX2VudGl0eV9pZBgEIAEoDEgCiAEBEi4KCXVudGlsX2hsYxgFIAEoCzIWLnByb3RvY29sLkhsY1RpbWVzdGFtcEgDiAEBEhoKEmluY2x1ZGVfb2xkX3ZhbHVlcxgGIAEoCBIWCgllbnRpdHlfaWQYByABKAxIBIgBARIZCgxhdHRyaWJ1dGVfaWQYCCABKAxIBYgBAUIMCgpfc2luY2VfaGxjQggKBl9xdWVyeUISChBfd2F0Y2hfZW50aXR5X2lkQgwKCl91bnRpbF9obGNCDAoKX2VudGl0eV9pZEIPCg1fYXR0cmlidXRlX2lkIlcKFVN1YnNjcmliZVF1ZXJ5UmVxdWVzdBIXCg9zdWJzY3JpcHRpb25faWQYASABKA0SJQoFcXVlcnkYAiABKAsyFi5wcm90b2NvbC5RdWVyeVJlcXVlc3QiLQoSVW5zdWJzY3JpYmVSZXF1ZXN0EhcKD3N1YnNjcmlwdGlvbl9pZBgBIAEoDSJjCg9Db250YWluc1JlcXVlc3QSFgoJZW50aXR5X2lkGAEgASgMSACIAQESGQoMYXR0cmlidXRlX2lkGAIgASgMSAGIAQFCDAoKX2VudGl0eV9pZEIPCg1fYXR0cmlidXRlX2lkIjIKDURlbGV0ZVJlcXVlc3QSIQoEa2V5cxgBIAMoCzITLnByb3RvY29sLlRyaXBsZUtleSJdCglUcmlwbGVLZXkSFgoJZW50aXR5X2lkGAEgASgMSACIAQESGQoMYXR0cmlidXRlX2lkGAIgASgMSAGIAQFCDAoKX2VudGl0eV9pZEIPCg1fYXR0cmlidXRlX2lkIjoKEU9wZW5DdXJzb3JSZXF1ZXN0EiUKBXF1ZXJ5GAEgASgLMhYucHJvdG9jb2wuUXVlcnlSZXF1ZXN0IjkKEkZldGNoQ3Vyc29yUmVxdWVzdBIRCgljdXJzb3JfaWQYASABKAQSEAoIbWF4X3Jvd3MYAiABKA0iJwoSQ2xvc2VDdXJzb3JSZXF1ZXN0EhEKCWN1cnNvcl9pZBgBIAEoBCI7ChBSZWFkQmF0Y2hSZXF1ZXN0EicKB3F1ZXJpZXMYASADKAsyFi5wcm90b2NvbC5RdWVyeVJlcXVlc3QiLwoVRGVzY3JpYmVTY2hlbWFSZXF1ZXN0EhYKDm1heF9hdHRyaWJ1dGVzGAEgASgNIiUKD0JlZ2luVHhuUmVxdWVzdBISCgp0aW1lb3V0X21zGAEgASgNIhIKEENvbW1pdFR4blJlcXVlc3QiEQoPQWJvcnRUeG5SZXF1ZXN0IkQKD0J1bGtMb2FkUmVxdWVzdBIhCgd0cmlwbGVzGAEgAygLMhAucHJvdG9jb2wuVHJpcGxlEg4KBmZpbmlzaBgCIAEoCCI/ChJTZXREZWZhdWx0c1JlcXVlc3QSKQoIZGVmYXVsdHMYASADKAsyFy5wcm90b2NvbC5EZWZhdWx0VHJpcGxlImEKDURlZmF1bHRUcmlwbGUSGQoMYXR0cmlidXRlX2lkGAEgASgMSACIAQESJAoFdmFsdWUYAiABKAsyFS5wcm90b2NvbC5UcmlwbGVWYWx1ZUIPCg1fYXR0cmlidXRlX2lkIpgBCgxDaGFuZ2VSZWNvcmQSKQoLY2hhbmdlX3R5cGUYASABKA4yFC5wcm90b2NvbC5DaGFuZ2VUeXBlEiAKBnRyaXBsZRgCIAEoCzIQLnByb3RvY29sLlRyaXBsZRItCglvbGRfdmFsdWUYAyABKAsyFS5wcm90b2NvbC5UcmlwbGVWYWx1ZUgAiAEBQgwKCl9vbGRfdmFsdWUi+QEKElN1YnNjcmlwdGlvblVwZGF0ZRIXCg9zdWJzY3JpcHRpb25faWQYASABKA0SJwoHY2hhbmdlcxgCIAMoCzIWLnByb3RvY29sLkNoYW5nZVJlY29yZBIZChFxdWVyeV9pbnZhbGlkYXRlZBgDIAEoCBIvCgpjb21taXRfaGxjGAQgASgLMhYucHJvdG9jb2wuSGxjVGltZXN0YW1wSACIAQESNQoMcXVlcnlfcmVzdWx0GAUgASgLMhoucHJvdG9jb2wuQmF0Y2hRdWVyeVJlc3VsdEgBiAEBQg0KC19jb21taXRfaGxjQg8KDV9xdWVyeV9yZXN1bHQiMwoNRW50aXR5VmVyc2lvbhIRCgllbnRpdHlfaWQYASABKAwSDwoHdmVyc2lvbhgCIAEoBCKPAQoTVHJpcGxlVXBkYXRlUmVxdWVzdBIhCgd0cmlwbGVzGAEgAygLMhAucHJvdG9jb2wuVHJpcGxlEiEKBG1vZGUYAiABKA4yEy5wcm90b2NvbC5Xcml0ZU1vZGUSMgoRZXhwZWN0ZWRfdmVyc2lvbnMYAyADKAsyFy5wcm90b2NvbC5FbnRpdHlWZXJzaW9uIpMBCgtXcml0ZVJlc3VsdBIRCgllbnRpdHlfaWQYASABKAwSFAoMYXR0cmlidXRlX2lkGAIgASgMEikKC2NoYW5nZV90eXBlGAMgASgOMhQucHJvdG9jb2wuQ2hhbmdlVHlwZRIoCgNobGMYBCABKAsyFi5wcm90b2NvbC5IbGNUaW1lc3RhbXBIAIgBAUIGCgRfaGxjIlsKDERlbGV0ZVJlc3VsdBIRCgllbnRpdHlfaWQYASABKAwSFAoMYXR0cmlidXRlX2lkGAIgASgMEiIKBnN0YXR1cxgDIAEoCzISLmdvb2dsZS5ycGMuU3RhdHVzIoUBCg1TZXJ2ZXJNZXNzYWdlEiwKCHJlc3BvbnNlGAEgASgLMhgucHJvdG9jb2wuU2VydmVyUmVzcG9uc2VIABI7ChNzdWJzY3JpcHRpb25fdXBkYXRlGAIgASgLMhwucHJvdG9jb2wuU3Vic2NyaXB0aW9uVXBkYXRlSABCCQoHcGF5bG9hZCJuChBRdWVyeVJlc3VsdFZhbHVlEgwKAmlkGAEgASgJSAASLQoMdHJpcGxlX3ZhbHVlGAIgASgLMhUucHJvdG9jb2wuVHJpcGxlVmFsdWVIABIUCgxpc191bmRlZmluZWQYAyABKAhCBwoFdmFsdWUiPAoOUXVlcnlSZXN1bHRSb3cSKgoGdmFsdWVzGAEgAygLMhoucHJvdG9jb2wuUXVlcnlSZXN1bHRWYWx1ZSKVAQoQQmF0Y2hRdWVyeVJlc3VsdBIiCgZzdGF0dXMYASABKAsyEi5nb29nbGUucnBjLlN0YXR1cxIPCgdjb2x1bW5zGAIgAygJEiYKBHJvd3MYAyADKAsyGC5wcm90b2NvbC5RdWVyeVJlc3VsdFJvdxIWCglhcnJvd19pcGMYBCABKAxIAIgBAUIMCgpfYXJyb3dfaXBjIoEBCg9BdHRyaWJ1dGVTY2hlbWESFAoMYXR0cmlidXRlX2lkGAEgASgMEhsKDmF0dHJpYnV0ZV9uYW1lGAIgASgJSACIAQESKAoLdmFsdWVfdHlwZXMYAyADKA4yEy5wcm90b2NvbC5WYWx1ZVR5cGVCEQoPX2F0dHJpYnV0ZV9uYW1lItkCCgtFcnJvckRldGFpbBIyCg9zdHJpbmdfdG9vX2xvbmcYASABKAsyFy5wcm90b2NvbC5TdHJpbmdUb29Mb25nSAASLQoMc3RyaW5nX2VtcHR5GAIgASgLMhUucHJvdG9jb2wuU3RyaW5nRW1wdHlIABIxCg53cml0ZV9jb25mbGljdBgDIAEoCzIXLnByb3RvY29sLldyaXRlQ29uZmxpY3RIABI7ChNzdWJzY3JpcHRpb25fbGFnZ2VkGAQgASgLMhwucHJvdG9jb2wuU3Vic2NyaXB0aW9uTGFnZ2VkSAASNgoRbWVzc2FnZV90b29fbGFyZ2UYBSABKAsyGS5wcm90b2NvbC5NZXNzYWdlVG9vTGFyZ2VIABI1ChB2ZXJzaW9uX21pc21hdGNoGAYgASgLMhkucHJvdG9jb2wuVmVyc2lvbk1pc21hdGNoSABCCAoGcmVhc29uIkAKDVN0cmluZ1Rvb0xvbmcSEAoIbG9jYXRpb24YASABKAkSDQoFbGltaXQYAiABKA0SDgoGbGVuZ3RoGAMgASgNIh8KC1N0cmluZ0VtcHR5EhAKCGxvY2F0aW9uGAEgASgJIjgKDVdyaXRlQ29uZmxpY3QSEQoJZW50aXR5X2lkGAEgASgMEhQKDGF0dHJpYnV0ZV9pZBgCIAEoDCIkChJTdWJzY3JpcHRpb25MYWdnZWQSDgoGbWlzc2VkGAEgASgEIiAKD01lc3NhZ2VUb29MYXJnZRINCgVsaW1pdBgBIAEoBCJGCg9WZXJzaW9uTWlzbWF0Y2gSEQoJZW50aXR5X2lkGAEgASgMEhAKCGV4cGVjdGVkGAIgASgEEg4KBmFjdHVhbBgDIAEoBCKMBQoOU2VydmVyUmVzcG9uc2USFwoKcmVxdWVzdF9pZBgBIAEoDUgAiAEBEicKBnN0YXR1cxgCIAEoCzISLmdvb2dsZS5ycGMuU3RhdHVzSAGIAQESIQoHdHJpcGxlcxgDIAMoCzIQLnByb3RvY29sLlRyaXBsZRIPCgdjb2x1bW5zGAQgAygJEiYKBHJvd3MYBSADKAsyGC5wcm90b2NvbC5RdWVyeVJlc3VsdFJvdxITCgZleGlzdHMYBiABKAhIAogBARIWCgljdXJzb3JfaWQYByABKARIA4gBARIdChBjdXJzb3JfZXhoYXVzdGVkGAggASgISASIAQESLAoNd3JpdGVfcmVzdWx0cxgJIAMoCzIVLnByb3RvY29sLldyaXRlUmVzdWx0EhYKCWFycm93X2lwYxgKIAEoDEgFiAEBEi4KDmRlbGV0ZV9yZXN1bHRzGAsgAygLMhYucHJvdG9jb2wuRGVsZXRlUmVzdWx0EjEKDWJhdGNoX3Jlc3VsdHMYDCADKAsyGi5wcm90b2NvbC5CYXRjaFF1ZXJ5UmVzdWx0Ei0KCmF0dHJpYnV0ZXMYDSADKAsyGS5wcm90b2NvbC5BdHRyaWJ1dGVTY2hlbWESHQoQc2NoZW1hX3RydW5jYXRlZBgOIAEoCEgGiAEBEhsKDnRyaXBsZXNfbG9hZGVkGA8gASgESAeIAQFCDQoLX3JlcXVlc3RfaWRCCQoHX3N0YXR1c0IJCgdfZXhpc3RzQgwKCl9jdXJzb3JfaWRCEwoRX2N1cnNvcl9leGhhdXN0ZWRCDAoKX2Fycm93X2lwY0ITChFfc2NoZW1hX3RydW5jYXRlZEIRCg9fdHJpcGxlc19sb2FkZWQqTAoNU29ydERpcmVjdGlvbhIcChhTT1JUX0RJUkVDVElPTl9BU0NFTkRJTkcQABIdChlTT1JUX0RJUkVDVElPTl9ERVNDRU5ESU5HEAEqOQoKTnVsbHNPcmRlchIVChFOVUxMU19PUkRFUl9GSVJTVBAAEhQKEE5VTExTX09SREVSX0xBU1QQASrFAQoRQWdncmVnYXRlRnVuY3Rpb24SIgoeQUdHUkVHQVRFX0ZVTkNUSU9OX1VOU1BFQ0lGSUVEEAASHAoYQUdHUkVHQVRFX0ZVTkNUSU9OX0NPVU5UEAESGgoWQUdHUkVHQVRFX0ZVTkNUSU9OX1NVTRACEhoKFkFHR1JFR0FURV9GVU5DVElPTl9NSU4QAxIaChZBR0dSRUdBVEVfRlVOQ1RJT05fTUFYEAQSGgoWQUdHUkVHQVRFX0ZVTkNUSU9OX0FWRxAFKnEKCkNoYW5nZVR5cGUSGwoXQ0hBTkdFX1RZUEVfVU5TUEVDSUZJRUQQABIWChJDSEFOR0VfVFlQRV9JTlNFUlQQARIWChJDSEFOR0VfVFlQRV9VUERBVEUQAhIWChJDSEFOR0VfVFlQRV9ERUxFVEUQAyp8CglXcml0ZU1vZGUSFQoRV1JJVEVfTU9ERV9VUFNFUlQQABIaChZXUklURV9NT0RFX0NSRUFURV9PTkxZEAESGgoWV1JJVEVfTU9ERV9VUERBVEVfT05MWRACEiAKHFdSSVRFX01PREVfQ09NTUlUX0lGX1ZFUlNJT04QAyqsAQoJVmFsdWVUeXBlEhoKFlZBTFVFX1RZUEVfVU5TUEVDSUZJRUQQABITCg9WQUxVRV9UWVBFX05VTEwQARIWChJWQUxVRV9UWVBFX0JPT0xFQU4QAhIVChFWQUxVRV9UWVBFX05VTUJFUhADEhUKEVZBTFVFX1RZUEVfU1RSSU5HEAQSEgoOVkFMVUVfVFlQRV9SRUYQBRIUChBWQUxVRV9UWVBFX0JZVEVTEAZiBnByb3RvMw==", [file_google_rpc_status]);
 
 /**
  * Represents a single triple (entity, attribute, value) in the database.
@@ -48,10 +48,21 @@ export type Triple = Message<"protocol.Triple"> & {
   /**
    * HLC timestamp for conflict resolution. Required for update requests.
    * In responses, contains the current timestamp of the stored value.
+   * The server preserves client timestamps and merges them into its own clock,
+   * rejecting any more than a minute ahead of it.
    *
    * @generated from field: optional protocol.HlcTimestamp hlc = 4;
    */
   hlc?: HlcTimestamp;
+
+  /**
+   * Human-readable attribute name, such as "email". In update requests, the
+   * server resolves it to a stable attribute ID, registering the name on first
+   * use. Ignored when attribute_id is set.
+   *
+   * @generated from field: optional string attribute_name = 5;
+   */
+  attributeName?: string;
 };
 
 /**
@@ -97,7 +108,8 @@ export const HlcTimestampSchema: GenMessage<HlcTimestamp> = /*@__PURE__*/
   messageDesc(file_protocol, 1);
 
 /**
- * The value component of a triple. Supports string, number, and boolean types.
+ * The value component of a triple. Supports string, number, boolean, and
+ * bytes types.
  *
  * @generated from message protocol.TripleValue
  */
@@ -130,6 +142,15 @@ export type TripleValue = Message<"protocol.TripleValue"> & {
      */
     value: boolean;
     case: "boolean";
+  } | {
+    /**
+     * Binary value, stored verbatim. Maximum length is 1 MiB.
+     * Values exceeding this limit will be rejected with InvalidArgument.
+     *
+     * @generated from field: bytes bytes = 6;
+     */
+    value: Uint8Array;
+    case: "bytes";
   } | { case: undefined; value?: undefined };
 };
 
@@ -155,6 +176,15 @@ export type ConnectRequest = Message<"protocol.ConnectRequest"> & {
    * @generated from field: string app_api_key = 1;
    */
   appApiKey: string;
+
+  /**
+   * JWT signed with the application's registered key. Required when the
+   * application is configured for token authentication; its `sub` claim
+   * identifies the user.
+   *
+   * @generated from field: optional string auth_token = 2;
+   */
+  authToken?: string;
 };
 
 /**
@@ -209,6 +239,110 @@ export type ClientMessage = Message<"protocol.ClientMessage"> & {
      */
     value: ConnectRequest;
     case: "connect";
+  } | {
+    /**
+     * Check whether a triple exists without reading its value.
+     *
+     * @generated from field: protocol.ContainsRequest contains = 7;
+     */
+    value: ContainsRequest;
+    case: "contains";
+  } | {
+    /**
+     * Open a server-side cursor over a query's results.
+     *
+     * @generated from field: protocol.OpenCursorRequest open_cursor = 8;
+     */
+    value: OpenCursorRequest;
+    case: "openCursor";
+  } | {
+    /**
+     * Fetch the next batch of rows from a cursor.
+     *
+     * @generated from field: protocol.FetchCursorRequest fetch_cursor = 9;
+     */
+    value: FetchCursorRequest;
+    case: "fetchCursor";
+  } | {
+    /**
+     * Close a cursor before it is exhausted.
+     *
+     * @generated from field: protocol.CloseCursorRequest close_cursor = 10;
+     */
+    value: CloseCursorRequest;
+    case: "closeCursor";
+  } | {
+    /**
+     * Set the default triples attached to entities this connection writes.
+     *
+     * @generated from field: protocol.SetDefaultsRequest set_defaults = 11;
+     */
+    value: SetDefaultsRequest;
+    case: "setDefaults";
+  } | {
+    /**
+     * Delete triples in one transaction.
+     *
+     * @generated from field: protocol.DeleteRequest delete = 12;
+     */
+    value: DeleteRequest;
+    case: "delete";
+  } | {
+    /**
+     * Run several queries against one consistent snapshot.
+     *
+     * @generated from field: protocol.ReadBatchRequest read_batch = 13;
+     */
+    value: ReadBatchRequest;
+    case: "readBatch";
+  } | {
+    /**
+     * Describe the attributes in the database from a sample of its triples.
+     *
+     * @generated from field: protocol.DescribeSchemaRequest describe_schema = 14;
+     */
+    value: DescribeSchemaRequest;
+    case: "describeSchema";
+  } | {
+    /**
+     * Begin a transaction spanning several messages on this connection.
+     *
+     * @generated from field: protocol.BeginTxnRequest begin_txn = 15;
+     */
+    value: BeginTxnRequest;
+    case: "beginTxn";
+  } | {
+    /**
+     * Commit the connection's open transaction.
+     *
+     * @generated from field: protocol.CommitTxnRequest commit_txn = 16;
+     */
+    value: CommitTxnRequest;
+    case: "commitTxn";
+  } | {
+    /**
+     * Discard the connection's open transaction.
+     *
+     * @generated from field: protocol.AbortTxnRequest abort_txn = 17;
+     */
+    value: AbortTxnRequest;
+    case: "abortTxn";
+  } | {
+    /**
+     * Subscribe to a query's results, re-sent whenever they may change.
+     *
+     * @generated from field: protocol.SubscribeQueryRequest subscribe_query = 18;
+     */
+    value: SubscribeQueryRequest;
+    case: "subscribeQuery";
+  } | {
+    /**
+     * Stream triples into a bulk load, written as one transaction.
+     *
+     * @generated from field: protocol.BulkLoadRequest bulk_load = 19;
+     */
+    value: BulkLoadRequest;
+    case: "bulkLoad";
   } | { case: undefined; value?: undefined };
 };
 
@@ -250,6 +384,77 @@ export type QueryRequest = Message<"protocol.QueryRequest"> & {
    * @generated from field: repeated protocol.QueryPattern where_not = 4;
    */
   whereNot: QueryPattern[];
+
+  /**
+   * Return the results as an Arrow IPC stream in ServerResponse.arrow_ipc
+   * instead of rows. Falls back to rows if a column mixes value types.
+   *
+   * @generated from field: bool arrow_ipc = 5;
+   */
+  arrowIpc: boolean;
+
+  /**
+   * Maximum number of rows to return, counted after `offset` rows are
+   * skipped. Unset returns every row. OpenCursorRequest rejects it with
+   * INVALID_ARGUMENT.
+   *
+   * @generated from field: optional uint32 limit = 6;
+   */
+  limit?: number;
+
+  /**
+   * Number of rows to skip, in result order, before returning any. Unset
+   * skips none. OpenCursorRequest rejects a nonzero offset with
+   * INVALID_ARGUMENT.
+   *
+   * @generated from field: optional uint32 offset = 7;
+   */
+  offset?: number;
+
+  /**
+   * Sort keys for the rows. Rows are sorted by the first key, ties by the
+   * second, and so on; rows tied on every key keep their default order.
+   * Sorting happens before offset and limit apply. Empty keeps the default
+   * order. OpenCursorRequest rejects it with INVALID_ARGUMENT.
+   *
+   * @generated from field: repeated protocol.QueryOrderBy order_by = 8;
+   */
+  orderBy: QueryOrderBy[];
+
+  /**
+   * Aggregates to return instead of rows of `find` variables. When set,
+   * `find` must be empty, and the response holds a row per group (see
+   * group_by) with one value per aggregate, in order, in columns named like
+   * `count(e)`. Offset and limit apply to those rows; order_by is ignored.
+   * OpenCursorRequest rejects it with INVALID_ARGUMENT.
+   *
+   * @generated from field: repeated protocol.QueryAggregate aggregates = 9;
+   */
+  aggregates: QueryAggregate[];
+
+  /**
+   * Variables whose bound values partition the matched rows into groups,
+   * each yielding one row: its value of each group_by variable, then its
+   * aggregates. Groups come in the order their first row is matched. Empty
+   * puts every row in a single group, returned even if nothing matched. When
+   * set, `find` must be empty. OpenCursorRequest rejects it with
+   * INVALID_ARGUMENT.
+   *
+   * @generated from field: repeated protocol.QueryPatternVariable group_by = 10;
+   */
+  groupBy: QueryPatternVariable[];
+
+  /**
+   * Drop rows equal to an earlier row, in result order, keeping the first.
+   * Rows are compared as returned, so matches differing only in variables
+   * not in `find` collapse into one. Offset and limit count distinct rows.
+   * With order_by, duplicates are dropped before sorting, and each row sorts
+   * by the values of its first match. OpenCursorRequest rejects it with
+   * INVALID_ARGUMENT.
+   *
+   * @generated from field: bool distinct = 11;
+   */
+  distinct: boolean;
 };
 
 /**
@@ -259,6 +464,76 @@ export type QueryRequest = Message<"protocol.QueryRequest"> & {
 export const QueryRequestSchema: GenMessage<QueryRequest> = /*@__PURE__*/
   messageDesc(file_protocol, 5);
 
+/**
+ * A sort key for query results: the value bound to a variable.
+ *
+ * Values of different types sort by type (null, boolean, number, string,
+ * bytes, ref); within a type, numbers sort numerically, strings and bytes
+ * lexicographically, and false before true. Entity IDs sort after values.
+ *
+ * @generated from message protocol.QueryOrderBy
+ */
+export type QueryOrderBy = Message<"protocol.QueryOrderBy"> & {
+  /**
+   * The variable whose bound value rows are sorted by. Must be bound by a
+   * where or optional pattern.
+   *
+   * @generated from field: protocol.QueryPatternVariable variable = 1;
+   */
+  variable?: QueryPatternVariable;
+
+  /**
+   * Whether the smallest or the largest value comes first.
+   *
+   * @generated from field: protocol.SortDirection direction = 2;
+   */
+  direction: SortDirection;
+
+  /**
+   * Where rows whose variable is null or unbound go, whatever the direction.
+   *
+   * @generated from field: protocol.NullsOrder nulls = 3;
+   */
+  nulls: NullsOrder;
+};
+
+/**
+ * Describes the message protocol.QueryOrderBy.
+ * Use `create(QueryOrderBySchema)` to create a new message.
+ */
+export const QueryOrderBySchema: GenMessage<QueryOrderBy> = /*@__PURE__*/
+  messageDesc(file_protocol, 6);
+
+/**
+ * An aggregate of the values bound to a variable over every matched row.
+ * Null and unbound values are skipped.
+ *
+ * @generated from message protocol.QueryAggregate
+ */
+export type QueryAggregate = Message<"protocol.QueryAggregate"> & {
+  /**
+   * The function to apply. Must not be unspecified.
+   *
+   * @generated from field: protocol.AggregateFunction function = 1;
+   */
+  function: AggregateFunction;
+
+  /**
+   * The variable whose bound values are aggregated. Must be bound by a where
+   * or optional pattern.
+   *
+   * @generated from field: protocol.QueryPatternVariable variable = 2;
+   */
+  variable?: QueryPatternVariable;
+};
+
+/**
+ * Describes the message protocol.QueryAggregate.
+ * Use `create(QueryAggregateSchema)` to create a new message.
+ */
+export const QueryAggregateSchema: GenMessage<QueryAggregate> = /*@__PURE__*/
+  messageDesc(file_protocol, 7);
+
 /**
  * @generated from message protocol.QueryPattern
  */
@@ -278,6 +553,16 @@ export type QueryPattern = Message<"protocol.QueryPattern"> & {
      */
     value: QueryPatternVariable;
     case: "entityVariable";
+  } | {
+    /**
+     * Set of entities to match, like an IN clause. The pattern matches the
+     * triples of every listed entity, each read with its own primary index
+     * lookup. Absent entities match nothing.
+     *
+     * @generated from field: protocol.EntityIdSet entity_ids = 8;
+     */
+    value: EntityIdSet;
+    case: "entityIds";
   } | { case: undefined; value?: undefined };
 
   /**
@@ -295,6 +580,15 @@ export type QueryPattern = Message<"protocol.QueryPattern"> & {
      */
     value: QueryPatternVariable;
     case: "attributeVariable";
+  } | {
+    /**
+     * Human-readable attribute name, resolved server-side to the attribute ID
+     * registered for it. Names that were never registered match no triples.
+     *
+     * @generated from field: string attribute_name = 7;
+     */
+    value: string;
+    case: "attributeName";
   } | { case: undefined; value?: undefined };
 
   /**
@@ -312,6 +606,17 @@ export type QueryPattern = Message<"protocol.QueryPattern"> & {
      */
     value: QueryPatternVariable;
     case: "valueVariable";
+  } | {
+    /**
+     * Any value, bound to nothing, so the pattern only checks that the entity
+     * has the attribute: a where pattern keeps entities that have it, and a
+     * where_not pattern keeps those that lack it. With a known attribute,
+     * values are not read.
+     *
+     * @generated from field: protocol.AnyValue any_value = 9;
+     */
+    value: AnyValue;
+    case: "anyValue";
   } | { case: undefined; value?: undefined };
 };
 
@@ -320,7 +625,7 @@ export type QueryPattern = Message<"protocol.QueryPattern"> & {
  * Use `create(QueryPatternSchema)` to create a new message.
  */
 export const QueryPatternSchema: GenMessage<QueryPattern> = /*@__PURE__*/
-  messageDesc(file_protocol, 6);
+  messageDesc(file_protocol, 8);
 
 /**
  * @generated from message protocol.QueryPatternVariable
@@ -337,7 +642,51 @@ export type QueryPatternVariable = Message<"protocol.QueryPatternVariable"> & {
  * Use `create(QueryPatternVariableSchema)` to create a new message.
  */
 export const QueryPatternVariableSchema: GenMessage<QueryPatternVariable> = /*@__PURE__*/
-  messageDesc(file_protocol, 7);
+  messageDesc(file_protocol, 9);
+
+/**
+ * A set of entity IDs for a query pattern.
+ *
+ * @generated from message protocol.EntityIdSet
+ */
+export type EntityIdSet = Message<"protocol.EntityIdSet"> & {
+  /**
+   * The entity IDs, each 16 bytes. Order and duplicates are ignored.
+   *
+   * @generated from field: repeated bytes entity_ids = 1;
+   */
+  entityIds: Uint8Array[];
+
+  /**
+   * Variable bound to the matched entity, so rows tell which entity of the
+   * set they belong to.
+   *
+   * @generated from field: optional protocol.QueryPatternVariable variable = 2;
+   */
+  variable?: QueryPatternVariable;
+};
+
+/**
+ * Describes the message protocol.EntityIdSet.
+ * Use `create(EntityIdSetSchema)` to create a new message.
+ */
+export const EntityIdSetSchema: GenMessage<EntityIdSet> = /*@__PURE__*/
+  messageDesc(file_protocol, 10);
+
+/**
+ * Marker for a pattern that matches any value. Has no fields.
+ *
+ * @generated from message protocol.AnyValue
+ */
+export type AnyValue = Message<"protocol.AnyValue"> & {
+};
+
+/**
+ * Describes the message protocol.AnyValue.
+ * Use `create(AnyValueSchema)` to create a new message.
+ */
+export const AnyValueSchema: GenMessage<AnyValue> = /*@__PURE__*/
+  messageDesc(file_protocol, 11);
 
 /**
  * Request to subscribe to triple changes.
@@ -360,6 +709,62 @@ export type SubscribeRequest = Message<"protocol.SubscribeRequest"> & {
    * @generated from field: optional protocol.HlcTimestamp since_hlc = 2;
    */
   sinceHlc?: HlcTimestamp;
+
+  /**
+   * Optional query to watch instead of raw changes. If provided, updates carry
+   * no changes; the server sets query_invalidated whenever a committed change
+   * touches a triple the query's patterns can match, and the client re-runs
+   * the query. Attribute names are resolved when subscribing.
+   *
+   * @generated from field: optional protocol.QueryRequest query = 3;
+   */
+  query?: QueryRequest;
+
+  /**
+   * Optional entity to watch instead of all changes. The server first sends
+   * the entity's current triples as INSERT changes, then streams every later
+   * change to one of its triples, deletes included. Must be exactly 16 bytes.
+   * Cannot be combined with query or since_hlc.
+   *
+   * @generated from field: optional bytes watch_entity_id = 4;
+   */
+  watchEntityId?: Uint8Array;
+
+  /**
+   * Optional HLC timestamp to end the historical changes at. If provided
+   * with since_hlc, the initial changes are only those committed with an HLC
+   * from since_hlc to until_hlc, both inclusive. Requires since_hlc.
+   *
+   * @generated from field: optional protocol.HlcTimestamp until_hlc = 5;
+   */
+  untilHlc?: HlcTimestamp;
+
+  /**
+   * Include each live change's previous value in ChangeRecord.old_value.
+   * Off by default to keep updates small.
+   *
+   * @generated from field: bool include_old_values = 6;
+   */
+  includeOldValues: boolean;
+
+  /**
+   * Optional entity to filter changes by. Only changes to this entity's
+   * triples are sent, both live and in the since_hlc history. Must be exactly
+   * 16 bytes. Cannot be combined with query or watch_entity_id.
+   *
+   * @generated from field: optional bytes entity_id = 7;
+   */
+  entityId?: Uint8Array;
+
+  /**
+   * Optional attribute to filter changes by. Only changes to triples with
+   * this attribute are sent, both live and in the since_hlc history. With
+   * entity_id, a change must match both. Must be exactly 16 bytes. Cannot be
+   * combined with query or watch_entity_id.
+   *
+   * @generated from field: optional bytes attribute_id = 8;
+   */
+  attributeId?: Uint8Array;
 };
 
 /**
@@ -367,7 +772,43 @@ export type SubscribeRequest = Message<"protocol.SubscribeRequest"> & {
  * Use `create(SubscribeRequestSchema)` to create a new message.
  */
 export const SubscribeRequestSchema: GenMessage<SubscribeRequest> = /*@__PURE__*/
-  messageDesc(file_protocol, 8);
+  messageDesc(file_protocol, 12);
+
+/**
+ * Request to subscribe to a live query.
+ *
+ * The server runs the query and sends its result in a SubscriptionUpdate
+ * before the OK response. After each committed change that touches a triple
+ * the query's patterns can match, it re-runs the query against a fresh
+ * snapshot and sends the new result. Results are eventually consistent: a
+ * re-run may already include later commits, so several changes can coalesce
+ * into one result, and a result may repeat the previous one. Cancelled with
+ * an UnsubscribeRequest, and shares its subscription IDs with SubscribeRequest.
+ *
+ * @generated from message protocol.SubscribeQueryRequest
+ */
+export type SubscribeQueryRequest = Message<"protocol.SubscribeQueryRequest"> & {
+  /**
+   * Client-assigned subscription identifier. Must be unique per connection.
+   *
+   * @generated from field: uint32 subscription_id = 1;
+   */
+  subscriptionId: number;
+
+  /**
+   * The query to run. Attribute names are resolved when subscribing.
+   *
+   * @generated from field: protocol.QueryRequest query = 2;
+   */
+  query?: QueryRequest;
+};
+
+/**
+ * Describes the message protocol.SubscribeQueryRequest.
+ * Use `create(SubscribeQueryRequestSchema)` to create a new message.
+ */
+export const SubscribeQueryRequestSchema: GenMessage<SubscribeQueryRequest> = /*@__PURE__*/
+  messageDesc(file_protocol, 13);
 
 /**
  * Request to cancel an active subscription.
@@ -388,175 +829,1006 @@ export type UnsubscribeRequest = Message<"protocol.UnsubscribeRequest"> & {
  * Use `create(UnsubscribeRequestSchema)` to create a new message.
  */
 export const UnsubscribeRequestSchema: GenMessage<UnsubscribeRequest> = /*@__PURE__*/
-  messageDesc(file_protocol, 9);
+  messageDesc(file_protocol, 14);
 
 /**
- * A single change record representing a triple modification.
+ * Request to check whether a triple exists. Cheaper than a query for large
+ * values, since the server never reads the value itself.
  *
- * @generated from message protocol.ChangeRecord
+ * @generated from message protocol.ContainsRequest
  */
-export type ChangeRecord = Message<"protocol.ChangeRecord"> & {
+export type ContainsRequest = Message<"protocol.ContainsRequest"> & {
   /**
-   * The type of change.
+   * Entity to check. Must be exactly 16 bytes.
    *
-   * @generated from field: protocol.ChangeType change_type = 1;
+   * @generated from field: optional bytes entity_id = 1;
    */
-  changeType: ChangeType;
+  entityId?: Uint8Array;
 
   /**
-   * The affected triple. For DELETE operations, only entity_id, attribute_id,
-   * and hlc are populated; value is not included.
+   * Attribute to check. Must be exactly 16 bytes.
    *
-   * @generated from field: protocol.Triple triple = 2;
+   * @generated from field: optional bytes attribute_id = 2;
    */
-  triple?: Triple;
+  attributeId?: Uint8Array;
 };
 
 /**
- * Describes the message protocol.ChangeRecord.
- * Use `create(ChangeRecordSchema)` to create a new message.
+ * Describes the message protocol.ContainsRequest.
+ * Use `create(ContainsRequestSchema)` to create a new message.
  */
-export const ChangeRecordSchema: GenMessage<ChangeRecord> = /*@__PURE__*/
-  messageDesc(file_protocol, 10);
+export const ContainsRequestSchema: GenMessage<ContainsRequest> = /*@__PURE__*/
+  messageDesc(file_protocol, 15);
 
 /**
- * Streaming update sent to subscribers when triples change.
+ * Request to delete triples. The deletes commit in one transaction, and each
+ * deleted triple is broadcast to subscribers as a DELETE change.
  *
- * @generated from message protocol.SubscriptionUpdate
+ * @generated from message protocol.DeleteRequest
  */
-export type SubscriptionUpdate = Message<"protocol.SubscriptionUpdate"> & {
-  /**
-   * The subscription this update belongs to.
-   *
-   * @generated from field: uint32 subscription_id = 1;
-   */
-  subscriptionId: number;
-
+export type DeleteRequest = Message<"protocol.DeleteRequest"> & {
   /**
-   * The change records. May contain multiple changes per message.
+   * The triples to delete.
    *
-   * @generated from field: repeated protocol.ChangeRecord changes = 2;
+   * @generated from field: repeated protocol.TripleKey keys = 1;
    */
-  changes: ChangeRecord[];
+  keys: TripleKey[];
 };
 
 /**
- * Describes the message protocol.SubscriptionUpdate.
- * Use `create(SubscriptionUpdateSchema)` to create a new message.
+ * Describes the message protocol.DeleteRequest.
+ * Use `create(DeleteRequestSchema)` to create a new message.
  */
-export const SubscriptionUpdateSchema: GenMessage<SubscriptionUpdate> = /*@__PURE__*/
-  messageDesc(file_protocol, 11);
+export const DeleteRequestSchema: GenMessage<DeleteRequest> = /*@__PURE__*/
+  messageDesc(file_protocol, 16);
+
+/**
+ * Identifies one triple.
+ *
+ * @generated from message protocol.TripleKey
+ */
+export type TripleKey = Message<"protocol.TripleKey"> & {
+  /**
+   * Entity of the triple. Must be exactly 16 bytes.
+   *
+   * @generated from field: optional bytes entity_id = 1;
+   */
+  entityId?: Uint8Array;
+
+  /**
+   * Attribute of the triple. Must be exactly 16 bytes.
+   *
+   * @generated from field: optional bytes attribute_id = 2;
+   */
+  attributeId?: Uint8Array;
+};
+
+/**
+ * Describes the message protocol.TripleKey.
+ * Use `create(TripleKeySchema)` to create a new message.
+ */
+export const TripleKeySchema: GenMessage<TripleKey> = /*@__PURE__*/
+  messageDesc(file_protocol, 17);
+
+/**
+ * Request to open a cursor. The cursor reads a snapshot taken when it opens,
+ * and survives reconnects: any connection to the same app can fetch from it.
+ * Cursors unused for several minutes expire.
+ *
+ * @generated from message protocol.OpenCursorRequest
+ */
+export type OpenCursorRequest = Message<"protocol.OpenCursorRequest"> & {
+  /**
+   * The query to page through. Must have at least one WHERE pattern, and
+   * no aggregates, group_by, order_by, limit, offset or distinct.
+   *
+   * @generated from field: protocol.QueryRequest query = 1;
+   */
+  query?: QueryRequest;
+};
+
+/**
+ * Describes the message protocol.OpenCursorRequest.
+ * Use `create(OpenCursorRequestSchema)` to create a new message.
+ */
+export const OpenCursorRequestSchema: GenMessage<OpenCursorRequest> = /*@__PURE__*/
+  messageDesc(file_protocol, 18);
+
+/**
+ * Request to fetch the next batch of rows from a cursor.
+ *
+ * @generated from message protocol.FetchCursorRequest
+ */
+export type FetchCursorRequest = Message<"protocol.FetchCursorRequest"> & {
+  /**
+   * The cursor returned by OpenCursorRequest.
+   *
+   * @generated from field: uint64 cursor_id = 1;
+   */
+  cursorId: bigint;
+
+  /**
+   * Maximum number of rows to return. Must be positive; the server caps it.
+   *
+   * @generated from field: uint32 max_rows = 2;
+   */
+  maxRows: number;
+};
+
+/**
+ * Describes the message protocol.FetchCursorRequest.
+ * Use `create(FetchCursorRequestSchema)` to create a new message.
+ */
+export const FetchCursorRequestSchema: GenMessage<FetchCursorRequest> = /*@__PURE__*/
+  messageDesc(file_protocol, 19);
+
+/**
+ * Request to close a cursor and release its snapshot.
+ *
+ * @generated from message protocol.CloseCursorRequest
+ */
+export type CloseCursorRequest = Message<"protocol.CloseCursorRequest"> & {
+  /**
+   * The cursor returned by OpenCursorRequest.
+   *
+   * @generated from field: uint64 cursor_id = 1;
+   */
+  cursorId: bigint;
+};
+
+/**
+ * Describes the message protocol.CloseCursorRequest.
+ * Use `create(CloseCursorRequestSchema)` to create a new message.
+ */
+export const CloseCursorRequestSchema: GenMessage<CloseCursorRequest> = /*@__PURE__*/
+  messageDesc(file_protocol, 20);
+
+/**
+ * Request to run several queries against one snapshot, taken when the batch
+ * starts. Writes that commit while the batch runs are visible to none of its
+ * queries, so the results are one consistent view of the database.
+ *
+ * @generated from message protocol.ReadBatchRequest
+ */
+export type ReadBatchRequest = Message<"protocol.ReadBatchRequest"> & {
+  /**
+   * The queries to run. Each gets its own result, in request order.
+   *
+   * @generated from field: repeated protocol.QueryRequest queries = 1;
+   */
+  queries: QueryRequest[];
+};
+
+/**
+ * Describes the message protocol.ReadBatchRequest.
+ * Use `create(ReadBatchRequestSchema)` to create a new message.
+ */
+export const ReadBatchRequestSchema: GenMessage<ReadBatchRequest> = /*@__PURE__*/
+  messageDesc(file_protocol, 21);
+
+/**
+ * Request to describe the attributes stored in the database and the types of
+ * their values. The description is a sample, not a guarantee: value types are
+ * read from the first few triples of each attribute, so other triples may hold
+ * other types. Attributes with no triples are left out.
+ *
+ * @generated from message protocol.DescribeSchemaRequest
+ */
+export type DescribeSchemaRequest = Message<"protocol.DescribeSchemaRequest"> & {
+  /**
+   * Maximum number of attributes to describe. 0, or a value above the
+   * server's limit of 1000, uses the limit.
+   *
+   * @generated from field: uint32 max_attributes = 1;
+   */
+  maxAttributes: number;
+};
+
+/**
+ * Describes the message protocol.DescribeSchemaRequest.
+ * Use `create(DescribeSchemaRequestSchema)` to create a new message.
+ */
+export const DescribeSchemaRequestSchema: GenMessage<DescribeSchemaRequest> = /*@__PURE__*/
+  messageDesc(file_protocol, 22);
+
+/**
+ * Request to begin an explicit transaction. Until it is committed or aborted,
+ * each TripleUpdateRequest on the connection is validated and buffered in the
+ * transaction instead of committed on its own, and the CommitTxnRequest
+ * writes them all atomically. Other requests, including queries, see only
+ * committed state. A connection has at most one open transaction.
+ *
+ * A transaction left idle longer than its timeout, or open longer than the
+ * server's maximum duration of 60 seconds, is aborted by the server.
+ *
+ * @generated from message protocol.BeginTxnRequest
+ */
+export type BeginTxnRequest = Message<"protocol.BeginTxnRequest"> & {
+  /**
+   * Milliseconds the transaction may go without a message before it is
+   * aborted. 0 uses the server's default of 10000; larger values than the
+   * maximum duration are capped to it.
+   *
+   * @generated from field: uint32 timeout_ms = 1;
+   */
+  timeoutMs: number;
+};
+
+/**
+ * Describes the message protocol.BeginTxnRequest.
+ * Use `create(BeginTxnRequestSchema)` to create a new message.
+ */
+export const BeginTxnRequestSchema: GenMessage<BeginTxnRequest> = /*@__PURE__*/
+  messageDesc(file_protocol, 23);
+
+/**
+ * Request to commit the open transaction. The response carries the triples
+ * and write results for every buffered write, as a TripleUpdateRequest's
+ * would. Fails with ABORTED, writing nothing, if another transaction wrote
+ * one of its triples after it began, or if it was aborted for running too
+ * long.
+ *
+ * @generated from message protocol.CommitTxnRequest
+ */
+export type CommitTxnRequest = Message<"protocol.CommitTxnRequest"> & {
+};
+
+/**
+ * Describes the message protocol.CommitTxnRequest.
+ * Use `create(CommitTxnRequestSchema)` to create a new message.
+ */
+export const CommitTxnRequestSchema: GenMessage<CommitTxnRequest> = /*@__PURE__*/
+  messageDesc(file_protocol, 24);
+
+/**
+ * Request to discard the open transaction without writing any of it.
+ *
+ * @generated from message protocol.AbortTxnRequest
+ */
+export type AbortTxnRequest = Message<"protocol.AbortTxnRequest"> & {
+};
+
+/**
+ * Describes the message protocol.AbortTxnRequest.
+ * Use `create(AbortTxnRequestSchema)` to create a new message.
+ */
+export const AbortTxnRequestSchema: GenMessage<AbortTxnRequest> = /*@__PURE__*/
+  messageDesc(file_protocol, 25);
+
+/**
+ * One chunk of a bulk load. The server buffers each chunk's triples, and the
+ * chunk that sets finish writes every buffered triple as a single
+ * transaction: one WAL sync, however many triples, and after a crash either
+ * the whole load is recovered or none of it. Each triple overwrites a stored
+ * value only if its HLC is newer. Connection defaults are not attached.
+ *
+ * A chunk that fails validation is rejected without being buffered; earlier
+ * chunks stay buffered. A load is limited to 1000000 triples and to what fits
+ * in the WAL, and fails with RESOURCE_EXHAUSTED otherwise. A chunk past the
+ * limit, or a finish that fails, discards the whole load, as does closing the
+ * connection before finishing. Bulk loads cannot run inside a transaction.
+ *
+ * @generated from message protocol.BulkLoadRequest
+ */
+export type BulkLoadRequest = Message<"protocol.BulkLoadRequest"> & {
+  /**
+   * The chunk's triples. Attribute names are resolved as in a
+   * TripleUpdateRequest.
+   *
+   * @generated from field: repeated protocol.Triple triples = 1;
+   */
+  triples: Triple[];
+
+  /**
+   * Whether this is the load's last chunk.
+   *
+   * @generated from field: bool finish = 2;
+   */
+  finish: boolean;
+};
+
+/**
+ * Describes the message protocol.BulkLoadRequest.
+ * Use `create(BulkLoadRequestSchema)` to create a new message.
+ */
+export const BulkLoadRequestSchema: GenMessage<BulkLoadRequest> = /*@__PURE__*/
+  messageDesc(file_protocol, 26);
+
+/**
+ * Request to set the default triples for this connection. Every entity a
+ * later TripleUpdateRequest on this connection writes also gets the defaults,
+ * in the same transaction and with the HLC of the entity's first triple in the
+ * request. A request that writes one of the default attributes for an entity
+ * keeps its own value. Replaces earlier defaults; an empty list clears them.
+ * Defaults are cleared when the connection closes.
+ *
+ * @generated from message protocol.SetDefaultsRequest
+ */
+export type SetDefaultsRequest = Message<"protocol.SetDefaultsRequest"> & {
+  /**
+   * The default triples. Each attribute may appear at most once.
+   *
+   * @generated from field: repeated protocol.DefaultTriple defaults = 1;
+   */
+  defaults: DefaultTriple[];
+};
+
+/**
+ * Describes the message protocol.SetDefaultsRequest.
+ * Use `create(SetDefaultsRequestSchema)` to create a new message.
+ */
+export const SetDefaultsRequestSchema: GenMessage<SetDefaultsRequest> = /*@__PURE__*/
+  messageDesc(file_protocol, 27);
+
+/**
+ * An attribute and value attached to every entity a connection writes.
+ *
+ * @generated from message protocol.DefaultTriple
+ */
+export type DefaultTriple = Message<"protocol.DefaultTriple"> & {
+  /**
+   * Attribute to write. Must be exactly 16 bytes.
+   *
+   * @generated from field: optional bytes attribute_id = 1;
+   */
+  attributeId?: Uint8Array;
+
+  /**
+   * Value to write. Subject to the same limits as written values.
+   *
+   * @generated from field: protocol.TripleValue value = 2;
+   */
+  value?: TripleValue;
+};
+
+/**
+ * Describes the message protocol.DefaultTriple.
+ * Use `create(DefaultTripleSchema)` to create a new message.
+ */
+export const DefaultTripleSchema: GenMessage<DefaultTriple> = /*@__PURE__*/
+  messageDesc(file_protocol, 28);
+
+/**
+ * A single change record representing a triple modification.
+ *
+ * @generated from message protocol.ChangeRecord
+ */
+export type ChangeRecord = Message<"protocol.ChangeRecord"> & {
+  /**
+   * The type of change.
+   *
+   * @generated from field: protocol.ChangeType change_type = 1;
+   */
+  changeType: ChangeType;
+
+  /**
+   * The affected triple. For DELETE operations, only entity_id, attribute_id,
+   * and hlc are populated; value is not included.
+   *
+   * @generated from field: protocol.Triple triple = 2;
+   */
+  triple?: Triple;
+
+  /**
+   * The committed value the change replaced (UPDATE) or removed (DELETE).
+   * Unset for INSERT, for historical changes sent for since_hlc, and unless
+   * the subscription set include_old_values.
+   *
+   * @generated from field: optional protocol.TripleValue old_value = 3;
+   */
+  oldValue?: TripleValue;
+};
+
+/**
+ * Describes the message protocol.ChangeRecord.
+ * Use `create(ChangeRecordSchema)` to create a new message.
+ */
+export const ChangeRecordSchema: GenMessage<ChangeRecord> = /*@__PURE__*/
+  messageDesc(file_protocol, 29);
+
+/**
+ * Streaming update sent to subscribers when triples change.
+ *
+ * @generated from message protocol.SubscriptionUpdate
+ */
+export type SubscriptionUpdate = Message<"protocol.SubscriptionUpdate"> & {
+  /**
+   * The subscription this update belongs to.
+   *
+   * @generated from field: uint32 subscription_id = 1;
+   */
+  subscriptionId: number;
+
+  /**
+   * The change records. May contain multiple changes per message. Empty for
+   * query subscriptions.
+   *
+   * @generated from field: repeated protocol.ChangeRecord changes = 2;
+   */
+  changes: ChangeRecord[];
+
+  /**
+   * Set for query subscriptions when a change may have altered the query's
+   * result. The client should re-run the query.
+   *
+   * @generated from field: bool query_invalidated = 3;
+   */
+  queryInvalidated: boolean;
+
+  /**
+   * HLC of the transaction that committed the changes. Live updates arrive
+   * in commit order, so this increases strictly across a connection's
+   * updates. Unset for backfill and entity snapshot updates, which are not
+   * tied to one transaction.
+   *
+   * @generated from field: optional protocol.HlcTimestamp commit_hlc = 4;
+   */
+  commitHlc?: HlcTimestamp;
+
+  /**
+   * The query's fresh result, for live query subscriptions. Changes are empty
+   * and query_invalidated is unset. A query that fails to run carries its
+   * error in status.
+   *
+   * @generated from field: optional protocol.BatchQueryResult query_result = 5;
+   */
+  queryResult?: BatchQueryResult;
+};
+
+/**
+ * Describes the message protocol.SubscriptionUpdate.
+ * Use `create(SubscriptionUpdateSchema)` to create a new message.
+ */
+export const SubscriptionUpdateSchema: GenMessage<SubscriptionUpdate> = /*@__PURE__*/
+  messageDesc(file_protocol, 30);
+
+/**
+ * An entity's version: the number of commits that changed it since entity
+ * versions were enabled. Read it from the entity's triple with the reserved
+ * attribute ID "\0enso:entity-ver".
+ *
+ * @generated from message protocol.EntityVersion
+ */
+export type EntityVersion = Message<"protocol.EntityVersion"> & {
+  /**
+   * @generated from field: bytes entity_id = 1;
+   */
+  entityId: Uint8Array;
+
+  /**
+   * @generated from field: uint64 version = 2;
+   */
+  version: bigint;
+};
+
+/**
+ * Describes the message protocol.EntityVersion.
+ * Use `create(EntityVersionSchema)` to create a new message.
+ */
+export const EntityVersionSchema: GenMessage<EntityVersion> = /*@__PURE__*/
+  messageDesc(file_protocol, 31);
+
+/**
+ * A batch of triples written atomically: every triple is validated before
+ * any is written, and if one is invalid or rejected by the write mode, the
+ * request fails and none of its triples are committed.
+ *
+ * @generated from message protocol.TripleUpdateRequest
+ */
+export type TripleUpdateRequest = Message<"protocol.TripleUpdateRequest"> & {
+  /**
+   * @generated from field: repeated protocol.Triple triples = 1;
+   */
+  triples: Triple[];
+
+  /**
+   * How triples that already exist are treated. Defaults to UPSERT.
+   *
+   * @generated from field: protocol.WriteMode mode = 2;
+   */
+  mode: WriteMode;
+
+  /**
+   * The version each written entity must be at, for COMMIT_IF_VERSION.
+   * Every entity the triples write must be listed; only allowed in that
+   * mode.
+   *
+   * @generated from field: repeated protocol.EntityVersion expected_versions = 3;
+   */
+  expectedVersions: EntityVersion[];
+};
+
+/**
+ * Describes the message protocol.TripleUpdateRequest.
+ * Use `create(TripleUpdateRequestSchema)` to create a new message.
+ */
+export const TripleUpdateRequestSchema: GenMessage<TripleUpdateRequest> = /*@__PURE__*/
+  messageDesc(file_protocol, 32);
+
+/**
+ * Outcome of one triple in a TripleUpdateRequest, after last-writer-wins
+ * resolution.
+ *
+ * @generated from message protocol.WriteResult
+ */
+export type WriteResult = Message<"protocol.WriteResult"> & {
+  /**
+   * The entity the triple was written to.
+   *
+   * @generated from field: bytes entity_id = 1;
+   */
+  entityId: Uint8Array;
+
+  /**
+   * The attribute the triple was written to.
+   *
+   * @generated from field: bytes attribute_id = 2;
+   */
+  attributeId: Uint8Array;
+
+  /**
+   * INSERT or UPDATE if the write changed the stored triple. UNSPECIFIED if
+   * it was dropped by an equal or newer stored HLC, or stored the value the
+   * triple already held.
+   *
+   * @generated from field: protocol.ChangeType change_type = 3;
+   */
+  changeType: ChangeType;
+
+  /**
+   * The HLC the stored triple now carries: the written HLC if the write was
+   * applied, otherwise the stored HLC. Unset if no live triple is stored.
+   *
+   * @generated from field: optional protocol.HlcTimestamp hlc = 4;
+   */
+  hlc?: HlcTimestamp;
+};
+
+/**
+ * Describes the message protocol.WriteResult.
+ * Use `create(WriteResultSchema)` to create a new message.
+ */
+export const WriteResultSchema: GenMessage<WriteResult> = /*@__PURE__*/
+  messageDesc(file_protocol, 33);
+
+/**
+ * Outcome of one triple in a DeleteRequest.
+ *
+ * @generated from message protocol.DeleteResult
+ */
+export type DeleteResult = Message<"protocol.DeleteResult"> & {
+  /**
+   * The entity of the triple.
+   *
+   * @generated from field: bytes entity_id = 1;
+   */
+  entityId: Uint8Array;
+
+  /**
+   * The attribute of the triple.
+   *
+   * @generated from field: bytes attribute_id = 2;
+   */
+  attributeId: Uint8Array;
+
+  /**
+   * OK if the triple was deleted, NOT_FOUND if no live triple was stored.
+   *
+   * @generated from field: google.rpc.Status status = 3;
+   */
+  status?: Status;
+};
+
+/**
+ * Describes the message protocol.DeleteResult.
+ * Use `create(DeleteResultSchema)` to create a new message.
+ */
+export const DeleteResultSchema: GenMessage<DeleteResult> = /*@__PURE__*/
+  messageDesc(file_protocol, 34);
+
+/**
+ * @generated from message protocol.ServerMessage
+ */
+export type ServerMessage = Message<"protocol.ServerMessage"> & {
+  /**
+   * @generated from oneof protocol.ServerMessage.payload
+   */
+  payload: {
+    /**
+     * Response to a client request (query, update, subscribe, unsubscribe).
+     *
+     * @generated from field: protocol.ServerResponse response = 1;
+     */
+    value: ServerResponse;
+    case: "response";
+  } | {
+    /**
+     * Streaming update pushed to subscribers when triples change.
+     *
+     * @generated from field: protocol.SubscriptionUpdate subscription_update = 2;
+     */
+    value: SubscriptionUpdate;
+    case: "subscriptionUpdate";
+  } | { case: undefined; value?: undefined };
+};
+
+/**
+ * Describes the message protocol.ServerMessage.
+ * Use `create(ServerMessageSchema)` to create a new message.
+ */
+export const ServerMessageSchema: GenMessage<ServerMessage> = /*@__PURE__*/
+  messageDesc(file_protocol, 35);
+
+/**
+ * A single value in a query result row
+ *
+ * @generated from message protocol.QueryResultValue
+ */
+export type QueryResultValue = Message<"protocol.QueryResultValue"> & {
+  /**
+   * @generated from oneof protocol.QueryResultValue.value
+   */
+  value: {
+    /**
+     * Entity or Field ID as string
+     *
+     * @generated from field: string id = 1;
+     */
+    value: string;
+    case: "id";
+  } | {
+    /**
+     * Actual value (string, number, boolean)
+     *
+     * @generated from field: protocol.TripleValue triple_value = 2;
+     */
+    value: TripleValue;
+    case: "tripleValue";
+  } | { case: undefined; value?: undefined };
+
+  /**
+   * True if value is missing (for optional patterns)
+   *
+   * @generated from field: bool is_undefined = 3;
+   */
+  isUndefined: boolean;
+};
+
+/**
+ * Describes the message protocol.QueryResultValue.
+ * Use `create(QueryResultValueSchema)` to create a new message.
+ */
+export const QueryResultValueSchema: GenMessage<QueryResultValue> = /*@__PURE__*/
+  messageDesc(file_protocol, 36);
+
+/**
+ * A row of query results
+ *
+ * @generated from message protocol.QueryResultRow
+ */
+export type QueryResultRow = Message<"protocol.QueryResultRow"> & {
+  /**
+   * @generated from field: repeated protocol.QueryResultValue values = 1;
+   */
+  values: QueryResultValue[];
+};
+
+/**
+ * Describes the message protocol.QueryResultRow.
+ * Use `create(QueryResultRowSchema)` to create a new message.
+ */
+export const QueryResultRowSchema: GenMessage<QueryResultRow> = /*@__PURE__*/
+  messageDesc(file_protocol, 37);
+
+/**
+ * Outcome of one query in a ReadBatchRequest.
+ *
+ * @generated from message protocol.BatchQueryResult
+ */
+export type BatchQueryResult = Message<"protocol.BatchQueryResult"> & {
+  /**
+   * OK if the query ran, or the error it failed with.
+   *
+   * @generated from field: google.rpc.Status status = 1;
+   */
+  status?: Status;
+
+  /**
+   * Columnar query results, as in ServerResponse.
+   *
+   * @generated from field: repeated string columns = 2;
+   */
+  columns: string[];
+
+  /**
+   * @generated from field: repeated protocol.QueryResultRow rows = 3;
+   */
+  rows: QueryResultRow[];
+
+  /**
+   * Query results as an Arrow IPC stream, as in ServerResponse.
+   *
+   * @generated from field: optional bytes arrow_ipc = 4;
+   */
+  arrowIpc?: Uint8Array;
+};
+
+/**
+ * Describes the message protocol.BatchQueryResult.
+ * Use `create(BatchQueryResultSchema)` to create a new message.
+ */
+export const BatchQueryResultSchema: GenMessage<BatchQueryResult> = /*@__PURE__*/
+  messageDesc(file_protocol, 38);
+
+/**
+ * One attribute in a DescribeSchemaRequest response.
+ *
+ * @generated from message protocol.AttributeSchema
+ */
+export type AttributeSchema = Message<"protocol.AttributeSchema"> & {
+  /**
+   * The attribute's 16-byte ID.
+   *
+   * @generated from field: bytes attribute_id = 1;
+   */
+  attributeId: Uint8Array;
+
+  /**
+   * The name registered for the attribute, if any.
+   *
+   * @generated from field: optional string attribute_name = 2;
+   */
+  attributeName?: string;
+
+  /**
+   * The distinct value types seen in the sampled triples.
+   *
+   * @generated from field: repeated protocol.ValueType value_types = 3;
+   */
+  valueTypes: ValueType[];
+};
+
+/**
+ * Describes the message protocol.AttributeSchema.
+ * Use `create(AttributeSchemaSchema)` to create a new message.
+ */
+export const AttributeSchemaSchema: GenMessage<AttributeSchema> = /*@__PURE__*/
+  messageDesc(file_protocol, 39);
+
+/**
+ * A machine-readable reason for an error, so clients can react to it without
+ * parsing the status message. Sent in google.rpc.Status.details as a
+ * google.protobuf.Any with type URL "type.googleapis.com/protocol.ErrorDetail".
+ * Errors without a more specific reason than their status code carry none.
+ * Details are only sent in binary frames, not in the JSON encoding.
+ *
+ * @generated from message protocol.ErrorDetail
+ */
+export type ErrorDetail = Message<"protocol.ErrorDetail"> & {
+  /**
+   * @generated from oneof protocol.ErrorDetail.reason
+   */
+  reason: {
+    /**
+     * A string value is longer than the server accepts.
+     *
+     * @generated from field: protocol.StringTooLong string_too_long = 1;
+     */
+    value: StringTooLong;
+    case: "stringTooLong";
+  } | {
+    /**
+     * A string value is empty.
+     *
+     * @generated from field: protocol.StringEmpty string_empty = 2;
+     */
+    value: StringEmpty;
+    case: "stringEmpty";
+  } | {
+    /**
+     * Another transaction wrote a triple first. Retrying may succeed.
+     *
+     * @generated from field: protocol.WriteConflict write_conflict = 3;
+     */
+    value: WriteConflict;
+    case: "writeConflict";
+  } | {
+    /**
+     * The connection's subscriptions missed changes. Resubscribe from the
+     * last HLC received.
+     *
+     * @generated from field: protocol.SubscriptionLagged subscription_lagged = 4;
+     */
+    value: SubscriptionLagged;
+    case: "subscriptionLagged";
+  } | {
+    /**
+     * A message from the client is larger than the server accepts.
+     *
+     * @generated from field: protocol.MessageTooLarge message_too_large = 5;
+     */
+    value: MessageTooLarge;
+    case: "messageTooLarge";
+  } | {
+    /**
+     * A COMMIT_IF_VERSION write found an entity at another version.
+     *
+     * @generated from field: protocol.VersionMismatch version_mismatch = 6;
+     */
+    value: VersionMismatch;
+    case: "versionMismatch";
+  } | { case: undefined; value?: undefined };
+};
+
+/**
+ * Describes the message protocol.ErrorDetail.
+ * Use `create(ErrorDetailSchema)` to create a new message.
+ */
+export const ErrorDetailSchema: GenMessage<ErrorDetail> = /*@__PURE__*/
+  messageDesc(file_protocol, 40);
+
+/**
+ * Detail for a string value over the length limit.
+ *
+ * @generated from message protocol.StringTooLong
+ */
+export type StringTooLong = Message<"protocol.StringTooLong"> & {
+  /**
+   * Which value in the request, e.g. "triple #2" or "where pattern #0".
+   *
+   * @generated from field: string location = 1;
+   */
+  location: string;
+
+  /**
+   * The longest string accepted, in bytes.
+   *
+   * @generated from field: uint32 limit = 2;
+   */
+  limit: number;
+
+  /**
+   * The string's length in bytes.
+   *
+   * @generated from field: uint32 length = 3;
+   */
+  length: number;
+};
 
 /**
- * @generated from message protocol.TripleUpdateRequest
+ * Describes the message protocol.StringTooLong.
+ * Use `create(StringTooLongSchema)` to create a new message.
  */
-export type TripleUpdateRequest = Message<"protocol.TripleUpdateRequest"> & {
+export const StringTooLongSchema: GenMessage<StringTooLong> = /*@__PURE__*/
+  messageDesc(file_protocol, 41);
+
+/**
+ * Detail for an empty string value.
+ *
+ * @generated from message protocol.StringEmpty
+ */
+export type StringEmpty = Message<"protocol.StringEmpty"> & {
   /**
-   * @generated from field: repeated protocol.Triple triples = 1;
+   * Which value in the request, as in StringTooLong.
+   *
+   * @generated from field: string location = 1;
    */
-  triples: Triple[];
+  location: string;
 };
 
 /**
- * Describes the message protocol.TripleUpdateRequest.
- * Use `create(TripleUpdateRequestSchema)` to create a new message.
+ * Describes the message protocol.StringEmpty.
+ * Use `create(StringEmptySchema)` to create a new message.
  */
-export const TripleUpdateRequestSchema: GenMessage<TripleUpdateRequest> = /*@__PURE__*/
-  messageDesc(file_protocol, 12);
+export const StringEmptySchema: GenMessage<StringEmpty> = /*@__PURE__*/
+  messageDesc(file_protocol, 42);
 
 /**
- * @generated from message protocol.ServerMessage
+ * Detail for a commit that lost a race for a triple.
+ *
+ * @generated from message protocol.WriteConflict
  */
-export type ServerMessage = Message<"protocol.ServerMessage"> & {
+export type WriteConflict = Message<"protocol.WriteConflict"> & {
   /**
-   * @generated from oneof protocol.ServerMessage.payload
+   * The entity of the first conflicting triple.
+   *
+   * @generated from field: bytes entity_id = 1;
    */
-  payload: {
-    /**
-     * Response to a client request (query, update, subscribe, unsubscribe).
-     *
-     * @generated from field: protocol.ServerResponse response = 1;
-     */
-    value: ServerResponse;
-    case: "response";
-  } | {
-    /**
-     * Streaming update pushed to subscribers when triples change.
-     *
-     * @generated from field: protocol.SubscriptionUpdate subscription_update = 2;
-     */
-    value: SubscriptionUpdate;
-    case: "subscriptionUpdate";
-  } | { case: undefined; value?: undefined };
+  entityId: Uint8Array;
+
+  /**
+   * The attribute of the first conflicting triple.
+   *
+   * @generated from field: bytes attribute_id = 2;
+   */
+  attributeId: Uint8Array;
 };
 
 /**
- * Describes the message protocol.ServerMessage.
- * Use `create(ServerMessageSchema)` to create a new message.
+ * Describes the message protocol.WriteConflict.
+ * Use `create(WriteConflictSchema)` to create a new message.
  */
-export const ServerMessageSchema: GenMessage<ServerMessage> = /*@__PURE__*/
-  messageDesc(file_protocol, 13);
+export const WriteConflictSchema: GenMessage<WriteConflict> = /*@__PURE__*/
+  messageDesc(file_protocol, 43);
 
 /**
- * A single value in a query result row
+ * Detail for subscriptions that fell behind the database's changes. The
+ * server closes the connection after sending it.
  *
- * @generated from message protocol.QueryResultValue
+ * @generated from message protocol.SubscriptionLagged
  */
-export type QueryResultValue = Message<"protocol.QueryResultValue"> & {
+export type SubscriptionLagged = Message<"protocol.SubscriptionLagged"> & {
   /**
-   * @generated from oneof protocol.QueryResultValue.value
+   * The number of change notifications missed.
+   *
+   * @generated from field: uint64 missed = 1;
    */
-  value: {
-    /**
-     * Entity or Field ID as string
-     *
-     * @generated from field: string id = 1;
-     */
-    value: string;
-    case: "id";
-  } | {
-    /**
-     * Actual value (string, number, boolean)
-     *
-     * @generated from field: protocol.TripleValue triple_value = 2;
-     */
-    value: TripleValue;
-    case: "tripleValue";
-  } | { case: undefined; value?: undefined };
+  missed: bigint;
+};
+
+/**
+ * Describes the message protocol.SubscriptionLagged.
+ * Use `create(SubscriptionLaggedSchema)` to create a new message.
+ */
+export const SubscriptionLaggedSchema: GenMessage<SubscriptionLagged> = /*@__PURE__*/
+  messageDesc(file_protocol, 44);
 
+/**
+ * Detail for a message over the size limit.
+ *
+ * @generated from message protocol.MessageTooLarge
+ */
+export type MessageTooLarge = Message<"protocol.MessageTooLarge"> & {
   /**
-   * True if value is missing (for optional patterns)
+   * The largest message accepted, in bytes.
    *
-   * @generated from field: bool is_undefined = 3;
+   * @generated from field: uint64 limit = 1;
    */
-  isUndefined: boolean;
+  limit: bigint;
 };
 
 /**
- * Describes the message protocol.QueryResultValue.
- * Use `create(QueryResultValueSchema)` to create a new message.
+ * Describes the message protocol.MessageTooLarge.
+ * Use `create(MessageTooLargeSchema)` to create a new message.
  */
-export const QueryResultValueSchema: GenMessage<QueryResultValue> = /*@__PURE__*/
-  messageDesc(file_protocol, 14);
+export const MessageTooLargeSchema: GenMessage<MessageTooLarge> = /*@__PURE__*/
+  messageDesc(file_protocol, 45);
 
 /**
- * A row of query results
+ * Detail for a COMMIT_IF_VERSION write to an entity that changed since the
+ * client read it.
  *
- * @generated from message protocol.QueryResultRow
+ * @generated from message protocol.VersionMismatch
  */
-export type QueryResultRow = Message<"protocol.QueryResultRow"> & {
+export type VersionMismatch = Message<"protocol.VersionMismatch"> & {
   /**
-   * @generated from field: repeated protocol.QueryResultValue values = 1;
+   * The first entity whose version did not match.
+   *
+   * @generated from field: bytes entity_id = 1;
    */
-  values: QueryResultValue[];
+  entityId: Uint8Array;
+
+  /**
+   * The version the request expected.
+   *
+   * @generated from field: uint64 expected = 2;
+   */
+  expected: bigint;
+
+  /**
+   * The entity's current version.
+   *
+   * @generated from field: uint64 actual = 3;
+   */
+  actual: bigint;
 };
 
 /**
- * Describes the message protocol.QueryResultRow.
- * Use `create(QueryResultRowSchema)` to create a new message.
+ * Describes the message protocol.VersionMismatch.
+ * Use `create(VersionMismatchSchema)` to create a new message.
  */
-export const QueryResultRowSchema: GenMessage<QueryResultRow> = /*@__PURE__*/
-  messageDesc(file_protocol, 15);
+export const VersionMismatchSchema: GenMessage<VersionMismatch> = /*@__PURE__*/
+  messageDesc(file_protocol, 46);
 
 /**
  * @generated from message protocol.ServerResponse
@@ -590,6 +1862,83 @@ export type ServerResponse = Message<"protocol.ServerResponse"> & {
    * @generated from field: repeated protocol.QueryResultRow rows = 5;
    */
   rows: QueryResultRow[];
+
+  /**
+   * Whether the triple exists (populated for ContainsRequest responses).
+   *
+   * @generated from field: optional bool exists = 6;
+   */
+  exists?: boolean;
+
+  /**
+   * The opened cursor (populated for OpenCursorRequest responses).
+   *
+   * @generated from field: optional uint64 cursor_id = 7;
+   */
+  cursorId?: bigint;
+
+  /**
+   * Whether the cursor has no more rows (populated for FetchCursorRequest
+   * responses). An exhausted cursor is closed automatically.
+   *
+   * @generated from field: optional bool cursor_exhausted = 8;
+   */
+  cursorExhausted?: boolean;
+
+  /**
+   * One result per triple in a TripleUpdateRequest, in request order.
+   *
+   * @generated from field: repeated protocol.WriteResult write_results = 9;
+   */
+  writeResults: WriteResult[];
+
+  /**
+   * Query results as an Arrow IPC stream with one typed column per find
+   * variable (populated for QueryRequest responses that set arrow_ipc, in
+   * which case rows is empty).
+   *
+   * @generated from field: optional bytes arrow_ipc = 10;
+   */
+  arrowIpc?: Uint8Array;
+
+  /**
+   * One result per key in a DeleteRequest, in request order.
+   *
+   * @generated from field: repeated protocol.DeleteResult delete_results = 11;
+   */
+  deleteResults: DeleteResult[];
+
+  /**
+   * One result per query in a ReadBatchRequest, in request order.
+   *
+   * @generated from field: repeated protocol.BatchQueryResult batch_results = 12;
+   */
+  batchResults: BatchQueryResult[];
+
+  /**
+   * The sampled attributes, in ascending ID order (populated for
+   * DescribeSchemaRequest responses).
+   *
+   * @generated from field: repeated protocol.AttributeSchema attributes = 13;
+   */
+  attributes: AttributeSchema[];
+
+  /**
+   * Whether attributes past max_attributes were left out (populated for
+   * DescribeSchemaRequest responses).
+   *
+   * @generated from field: optional bool schema_truncated = 14;
+   */
+  schemaTruncated?: boolean;
+
+  /**
+   * The number of triples a finished BulkLoadRequest changed. Triples whose
+   * HLC is not newer than the stored value, or that repeat it, are not
+   * counted.
+   *
+   * @generated from field: optional uint64 triples_loaded = 15;
+   */
+  triplesLoaded?: bigint;
 };
 
 /**
@@ -597,7 +1946,118 @@ export type ServerResponse = Message<"protocol.ServerResponse"> & {
  * Use `create(ServerResponseSchema)` to create a new message.
  */
 export const ServerResponseSchema: GenMessage<ServerResponse> = /*@__PURE__*/
-  messageDesc(file_protocol, 16);
+  messageDesc(file_protocol, 47);
+
+/**
+ * Direction of a query sort key.
+ *
+ * @generated from enum protocol.SortDirection
+ */
+export enum SortDirection {
+  /**
+   * Smallest first. The default.
+   *
+   * @generated from enum value: SORT_DIRECTION_ASCENDING = 0;
+   */
+  ASCENDING = 0,
+
+  /**
+   * Largest first.
+   *
+   * @generated from enum value: SORT_DIRECTION_DESCENDING = 1;
+   */
+  DESCENDING = 1,
+}
+
+/**
+ * Describes the enum protocol.SortDirection.
+ */
+export const SortDirectionSchema: GenEnum<SortDirection> = /*@__PURE__*/
+  enumDesc(file_protocol, 0);
+
+/**
+ * Where a query sort key places rows whose variable is null or unbound.
+ *
+ * @generated from enum protocol.NullsOrder
+ */
+export enum NullsOrder {
+  /**
+   * Before every other row. The default.
+   *
+   * @generated from enum value: NULLS_ORDER_FIRST = 0;
+   */
+  FIRST = 0,
+
+  /**
+   * After every other row.
+   *
+   * @generated from enum value: NULLS_ORDER_LAST = 1;
+   */
+  LAST = 1,
+}
+
+/**
+ * Describes the enum protocol.NullsOrder.
+ */
+export const NullsOrderSchema: GenEnum<NullsOrder> = /*@__PURE__*/
+  enumDesc(file_protocol, 1);
+
+/**
+ * A function folding many values into one.
+ *
+ * @generated from enum protocol.AggregateFunction
+ */
+export enum AggregateFunction {
+  /**
+   * Not a function; rejected.
+   *
+   * @generated from enum value: AGGREGATE_FUNCTION_UNSPECIFIED = 0;
+   */
+  UNSPECIFIED = 0,
+
+  /**
+   * The number of values.
+   *
+   * @generated from enum value: AGGREGATE_FUNCTION_COUNT = 1;
+   */
+  COUNT = 1,
+
+  /**
+   * The sum of the values, 0 if there are none. The query fails with
+   * INVALID_ARGUMENT if a value is not a number.
+   *
+   * @generated from enum value: AGGREGATE_FUNCTION_SUM = 2;
+   */
+  SUM = 2,
+
+  /**
+   * The smallest value, in the order of QueryOrderBy. Null if there are none.
+   *
+   * @generated from enum value: AGGREGATE_FUNCTION_MIN = 3;
+   */
+  MIN = 3,
+
+  /**
+   * The largest value, in the order of QueryOrderBy. Null if there are none.
+   *
+   * @generated from enum value: AGGREGATE_FUNCTION_MAX = 4;
+   */
+  MAX = 4,
+
+  /**
+   * The mean of the values, null if there are none. The query fails with
+   * INVALID_ARGUMENT if a value is not a number.
+   *
+   * @generated from enum value: AGGREGATE_FUNCTION_AVG = 5;
+   */
+  AVG = 5,
+}
+
+/**
+ * Describes the enum protocol.AggregateFunction.
+ */
+export const AggregateFunctionSchema: GenEnum<AggregateFunction> = /*@__PURE__*/
+  enumDesc(file_protocol, 2);
 
 /**
  * Types of changes that can occur to triples.
@@ -630,5 +2090,103 @@ export enum ChangeType {
  * Describes the enum protocol.ChangeType.
  */
 export const ChangeTypeSchema: GenEnum<ChangeType> = /*@__PURE__*/
-  enumDesc(file_protocol, 0);
+  enumDesc(file_protocol, 3);
+
+/**
+ * How a TripleUpdateRequest treats triples that already exist.
+ *
+ * @generated from enum protocol.WriteMode
+ */
+export enum WriteMode {
+  /**
+   * Insert absent triples and overwrite present ones. The default.
+   *
+   * @generated from enum value: WRITE_MODE_UPSERT = 0;
+   */
+  UPSERT = 0,
+
+  /**
+   * Only insert. The request fails with ALREADY_EXISTS if any triple is
+   * present, and nothing is written.
+   *
+   * @generated from enum value: WRITE_MODE_CREATE_ONLY = 1;
+   */
+  CREATE_ONLY = 1,
+
+  /**
+   * Only overwrite. The request fails with NOT_FOUND if any triple is
+   * absent, and nothing is written.
+   *
+   * @generated from enum value: WRITE_MODE_UPDATE_ONLY = 2;
+   */
+  UPDATE_ONLY = 2,
+
+  /**
+   * Write as UPSERT, but only if every entity written is still at the
+   * version given in expected_versions. Otherwise the request fails with
+   * ABORTED, and nothing is written. Requires entity versions to be enabled
+   * on the server.
+   *
+   * @generated from enum value: WRITE_MODE_COMMIT_IF_VERSION = 3;
+   */
+  COMMIT_IF_VERSION = 3,
+}
+
+/**
+ * Describes the enum protocol.WriteMode.
+ */
+export const WriteModeSchema: GenEnum<WriteMode> = /*@__PURE__*/
+  enumDesc(file_protocol, 4);
+
+/**
+ * Type of a stored triple value.
+ *
+ * @generated from enum protocol.ValueType
+ */
+export enum ValueType {
+  /**
+   * @generated from enum value: VALUE_TYPE_UNSPECIFIED = 0;
+   */
+  UNSPECIFIED = 0,
+
+  /**
+   * @generated from enum value: VALUE_TYPE_NULL = 1;
+   */
+  NULL = 1,
+
+  /**
+   * @generated from enum value: VALUE_TYPE_BOOLEAN = 2;
+   */
+  BOOLEAN = 2,
+
+  /**
+   * @generated from enum value: VALUE_TYPE_NUMBER = 3;
+   */
+  NUMBER = 3,
+
+  /**
+   * @generated from enum value: VALUE_TYPE_STRING = 4;
+   */
+  STRING = 4,
+
+  /**
+   * A reference to another entity.
+   *
+   * @generated from enum value: VALUE_TYPE_REF = 5;
+   */
+  REF = 5,
+
+  /**
+   * Binary data.
+   *
+   * @generated from enum value: VALUE_TYPE_BYTES = 6;
+   */
+  BYTES = 6,
+}
+
+/**
+ * Describes the enum protocol.ValueType.
+ */
+export const ValueTypeSchema: GenEnum<ValueType> = /*@__PURE__*/
+  enumDesc(file_protocol, 5);
 
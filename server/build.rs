@@ -1,7 +1,106 @@
-use std::io::Result;
+use std::env;
+use std::fs;
+use std::io::{Error, Result};
+use std::path::{Path, PathBuf};
+
+/// Optional `bytes` fields, encoded as base64 strings in JSON.
+const OPTIONAL_BYTES_FIELDS: &[&str] = &[
+    ".protocol.Triple.entity_id",
+    ".protocol.Triple.attribute_id",
+    ".protocol.ContainsRequest.entity_id",
+    ".protocol.ContainsRequest.attribute_id",
+    ".protocol.TripleKey.entity_id",
+    ".protocol.TripleKey.attribute_id",
+    ".protocol.ServerResponse.arrow_ipc",
+    ".protocol.BatchQueryResult.arrow_ipc",
+];
+
+/// Non-optional `bytes` fields, including those inside oneofs, encoded as
+/// base64 strings in JSON.
+const BYTES_FIELDS: &[&str] = &[
+    ".protocol.QueryPattern.entity.entity_id",
+    ".protocol.QueryPattern.attribute.attribute_id",
+    ".protocol.WriteResult.entity_id",
+    ".protocol.WriteResult.attribute_id",
+    ".protocol.DeleteResult.entity_id",
+    ".protocol.DeleteResult.attribute_id",
+    ".protocol.AttributeSchema.attribute_id",
+    ".protocol.WriteConflict.entity_id",
+    ".protocol.WriteConflict.attribute_id",
+    ".protocol.EntityVersion.entity_id",
+    ".protocol.VersionMismatch.entity_id",
+    ".protocol.TripleValue.value.bytes",
+];
+
+/// `repeated bytes` fields, encoded as arrays of base64 strings in JSON.
+const REPEATED_BYTES_FIELDS: &[&str] = &[".protocol.EntityIdSet.entity_ids"];
+
+/// `uint64` fields, encoded as decimal strings in JSON.
+const UINT64_FIELDS: &[&str] = &[
+    ".protocol.HlcTimestamp.physical_time_ms",
+    ".protocol.FetchCursorRequest.cursor_id",
+    ".protocol.CloseCursorRequest.cursor_id",
+    ".protocol.SubscriptionLagged.missed",
+    ".protocol.MessageTooLarge.limit",
+    ".protocol.EntityVersion.version",
+    ".protocol.VersionMismatch.expected",
+    ".protocol.VersionMismatch.actual",
+];
+
+/// `optional uint64` fields, encoded as decimal strings in JSON.
+const OPTIONAL_UINT64_FIELDS: &[&str] = &[
+    ".protocol.ServerResponse.cursor_id",
+    ".protocol.ServerResponse.triples_loaded",
+];
 
 fn main() -> Result<()> {
-    prost_build::compile_protos(
+    let mut config = prost_build::Config::new();
+
+    // Serde derives for the JSON-over-WebSocket format (see `types::proto_json`)
+    for path in [".protocol", ".google.rpc.Status"] {
+        config.message_attribute(
+            path,
+            "#[derive(serde::Serialize, serde::Deserialize)] #[serde(rename_all = \"camelCase\", default)]",
+        );
+    }
+    config.enum_attribute(
+        ".protocol",
+        "#[derive(serde::Serialize, serde::Deserialize)] #[serde(rename_all = \"camelCase\")]",
+    );
+    for path in OPTIONAL_BYTES_FIELDS {
+        config.field_attribute(
+            path,
+            "#[serde(with = \"crate::types::proto_json::optional_base64\")]",
+        );
+    }
+    for path in BYTES_FIELDS {
+        config.field_attribute(
+            path,
+            "#[serde(with = \"crate::types::proto_json::base64\")]",
+        );
+    }
+    for path in REPEATED_BYTES_FIELDS {
+        config.field_attribute(
+            path,
+            "#[serde(with = \"crate::types::proto_json::repeated_base64\")]",
+        );
+    }
+    for path in UINT64_FIELDS {
+        config.field_attribute(
+            path,
+            "#[serde(with = \"crate::types::proto_json::uint64\")]",
+        );
+    }
+    for path in OPTIONAL_UINT64_FIELDS {
+        config.field_attribute(
+            path,
+            "#[serde(with = \"crate::types::proto_json::optional_uint64\")]",
+        );
+    }
+    // `Any` has no JSON mapping here; error details are binary-only
+    config.field_attribute(".google.rpc.Status.details", "#[serde(skip)]");
+
+    config.compile_protos(
         &[
             "../proto/google/rpc/code.proto",
             "../proto/google/rpc/status.proto",
@@ -9,5 +108,23 @@ fn main() -> Result<()> {
         ],
         &["../proto/"],
     )?;
-    Ok(())
+
+    flatten_oneofs(&PathBuf::from(env::var("OUT_DIR").map_err(Error::other)?).join("protocol.rs"))
+}
+
+/// Flatten oneof fields so JSON matches the proto3 JSON mapping.
+///
+/// A `field_attribute` on a oneof path also applies to every member of the
+/// oneof, where `#[serde(flatten)]` is invalid, so the attribute is added to
+/// the generated oneof fields directly. Long `#[prost(...)]` attributes are
+/// wrapped onto several lines by the formatter, so both forms are matched.
+fn flatten_oneofs(path: &Path) -> Result<()> {
+    let generated = fs::read_to_string(path)?;
+    let flattened = generated
+        .replace("#[prost(oneof =", "#[serde(flatten)]\n    #[prost(oneof =")
+        .replace(
+            "#[prost(\n        oneof =",
+            "#[serde(flatten)]\n    #[prost(\n        oneof =",
+        );
+    fs::write(path, flattened)
 }
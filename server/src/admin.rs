@@ -0,0 +1,392 @@
+//! Admin HTTP routes.
+//!
+//! `POST /admin/maintenance/{app_api_key}` runs the database maintenance
+//! passes on demand: a checkpoint, a full garbage collection, and a vacuum
+//! that compacts the WAL region. It answers with the stats of each pass as
+//! JSON. The optional `wal_capacity` query parameter sets the WAL capacity the
+//! vacuum shrinks to; by default the capacity is kept and only the checkpoint
+//! record is moved to the start of the region.
+//!
+//! Maintenance blocks writers to the database while it runs, so the route is
+//! reserved for the admin: requests must carry the server's
+//! `admin_app_api_key` as a bearer token. Regular app API keys are rejected.
+//!
+//! # Responses
+//!
+//! - `200 OK` with a `MaintenanceStats` JSON body
+//! - `400 Bad Request` if the app API key is malformed
+//! - `401 Unauthorized` if the bearer token is missing or not the admin key
+//! - `404 Not Found` if the app has no database
+//! - `500 Internal Server Error` if a maintenance pass fails
+
+use axum::{
+    Json,
+    extract::{Path, Query, State},
+    http::{HeaderMap, StatusCode, header},
+    response::{IntoResponse, Response},
+};
+use serde::{Deserialize, Serialize};
+
+use crate::database_registry::validate_api_key;
+use crate::storage::{CheckpointResult, Database, DatabaseError, GcStats, WalCompaction};
+use crate::websocket::AppState;
+
+/// Query parameters of the maintenance route.
+#[derive(Debug, Default, Deserialize)]
+pub struct MaintenanceParams {
+    /// WAL capacity in bytes for the vacuum to shrink to. Defaults to the
+    /// current capacity.
+    pub wal_capacity: Option<u64>,
+}
+
+/// Stats of one maintenance run, in the order the passes ran.
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub struct MaintenanceStats {
+    /// Result of the checkpoint.
+    pub checkpoint: CheckpointStats,
+    /// Result of the garbage collection.
+    pub gc: GcRunStats,
+    /// Result of the vacuum.
+    pub vacuum: VacuumStats,
+}
+
+/// Result of a checkpoint pass.
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub struct CheckpointStats {
+    /// LSN of the checkpoint record.
+    pub checkpoint_lsn: u64,
+    /// Number of dirty pages flushed.
+    pub pages_flushed: usize,
+}
+
+impl From<CheckpointResult> for CheckpointStats {
+    fn from(result: CheckpointResult) -> Self {
+        Self {
+            checkpoint_lsn: result.checkpoint_lsn,
+            pages_flushed: result.pages_flushed,
+        }
+    }
+}
+
+/// Result of a garbage collection pass.
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub struct GcRunStats {
+    /// Tombstones left because an active snapshot can still see them.
+    pub pending_tombstones: u64,
+    /// The minimum active snapshot transaction ID, if any snapshot is open.
+    pub min_active_snapshot: Option<u64>,
+}
+
+impl From<GcStats> for GcRunStats {
+    fn from(stats: GcStats) -> Self {
+        Self {
+            pending_tombstones: stats.pending_tombstones,
+            min_active_snapshot: stats.min_active_snapshot,
+        }
+    }
+}
+
+/// Result of a vacuum pass.
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub struct VacuumStats {
+    /// WAL capacity in bytes before the vacuum.
+    pub wal_capacity_before: u64,
+    /// WAL capacity in bytes after the vacuum.
+    pub wal_capacity_after: u64,
+    /// Pages that left the WAL region.
+    pub pages_released: u64,
+    /// Whether the released pages were truncated from the end of the file.
+    pub file_truncated: bool,
+}
+
+impl From<WalCompaction> for VacuumStats {
+    fn from(compaction: WalCompaction) -> Self {
+        Self {
+            wal_capacity_before: compaction.capacity_before,
+            wal_capacity_after: compaction.capacity_after,
+            pages_released: compaction.pages_released,
+            file_truncated: compaction.file_truncated,
+        }
+    }
+}
+
+/// Error returned by an admin route.
+#[derive(Debug)]
+pub enum AdminError {
+    /// The request did not carry the admin key as a bearer token.
+    Unauthorized,
+    /// The app API key in the path is malformed.
+    InvalidApiKey,
+    /// No database exists for the app.
+    NotFound(String),
+    /// A database operation failed.
+    Database(DatabaseError),
+}
+
+impl AdminError {
+    /// HTTP status code for this error.
+    #[must_use]
+    pub const fn status_code(&self) -> StatusCode {
+        match self {
+            Self::Unauthorized => StatusCode::UNAUTHORIZED,
+            Self::InvalidApiKey => StatusCode::BAD_REQUEST,
+            Self::NotFound(_) => StatusCode::NOT_FOUND,
+            Self::Database(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+}
+
+impl std::fmt::Display for AdminError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Unauthorized => write!(f, "admin API key required"),
+            Self::InvalidApiKey => write!(f, "invalid app API key"),
+            Self::NotFound(app_api_key) => write!(f, "no database for app '{app_api_key}'"),
+            Self::Database(e) => write!(f, "maintenance failed: {e}"),
+        }
+    }
+}
+
+impl std::error::Error for AdminError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Database(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<DatabaseError> for AdminError {
+    fn from(e: DatabaseError) -> Self {
+        Self::Database(e)
+    }
+}
+
+impl IntoResponse for AdminError {
+    fn into_response(self) -> Response {
+        (self.status_code(), self.to_string()).into_response()
+    }
+}
+
+/// Handle `POST /admin/maintenance/{app_api_key}`.
+pub async fn maintenance_handler(
+    State(state): State<AppState>,
+    Path(app_api_key): Path<String>,
+    Query(params): Query<MaintenanceParams>,
+    headers: HeaderMap,
+) -> Result<Json<MaintenanceStats>, AdminError> {
+    authorize_admin(&headers, &state.config.admin_app_api_key)?;
+    validate_api_key(&app_api_key).map_err(|_| AdminError::InvalidApiKey)?;
+    let Some(database) = state.registry.get_existing(&app_api_key)? else {
+        return Err(AdminError::NotFound(app_api_key));
+    };
+
+    let mut db = database.write().map_err(|_| DatabaseError::LockPoisoned)?;
+    let maintenance = run_maintenance(&mut db, params.wal_capacity)?;
+    drop(db);
+    tracing::info!(
+        "Ran maintenance for app '{}': {:?}",
+        app_api_key,
+        maintenance
+    );
+    Ok(Json(maintenance))
+}
+
+/// Run a checkpoint, a full garbage collection, and a vacuum, in that order.
+///
+/// The vacuum compacts the WAL region to `wal_capacity` bytes, or to its
+/// current capacity if `None`; see `Database::compact_wal`.
+///
+/// # Errors
+///
+/// Returns the error of the first pass that fails. Earlier passes stay
+/// applied.
+pub fn run_maintenance(
+    database: &mut Database,
+    wal_capacity: Option<u64>,
+) -> Result<MaintenanceStats, DatabaseError> {
+    let checkpoint = database.checkpoint()?.into();
+    let gc = database.force_gc()?.into();
+    let capacity = wal_capacity.unwrap_or_else(|| database.wal_capacity());
+    let vacuum = database.compact_wal(capacity)?.into();
+    Ok(MaintenanceStats {
+        checkpoint,
+        gc,
+        vacuum,
+    })
+}
+
+/// Check that a request carries `admin_app_api_key` as its bearer token.
+///
+/// # Errors
+///
+/// Returns `AdminError::Unauthorized` if the `Authorization` header is
+/// missing, is not a bearer token, or holds another key.
+fn authorize_admin(headers: &HeaderMap, admin_app_api_key: &str) -> Result<(), AdminError> {
+    let token = headers
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "));
+    match token {
+        Some(token) if constant_time_eq(token.as_bytes(), admin_app_api_key.as_bytes()) => Ok(()),
+        _ => Err(AdminError::Unauthorized),
+    }
+}
+
+/// Compare two byte strings in time independent of where they differ, so the
+/// admin key cannot be guessed byte by byte from response times.
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0u8, |diff, (x, y)| diff | (x ^ y)) == 0
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+    use std::sync::Arc;
+
+    use axum::http::HeaderValue;
+    use tempfile::tempdir;
+
+    use super::*;
+    use crate::DatabaseRegistry;
+    use crate::auth::ConfigRegistry;
+    use crate::config::ServerConfig;
+    use crate::constants::{DEFAULT_MAX_MESSAGE_SIZE, DEFAULT_MAX_SUBSCRIPTIONS_PER_CONNECTION};
+    use crate::storage::wal::MIN_WAL_CAPACITY;
+    use crate::types::{AttributeId, EntityId, TripleValue, ValueLimits};
+
+    fn create_test_state(directory: PathBuf) -> AppState {
+        AppState {
+            registry: Arc::new(DatabaseRegistry::with_pool_capacity(directory, 100)),
+            config: Arc::new(ServerConfig {
+                admin_app_api_key: "admin-key".to_string(),
+                database_directory: PathBuf::new(),
+                listen_port: 0,
+                value_limits: ValueLimits::default(),
+                buffer_pool_pages: 100,
+                node_id: 1,
+                max_subscriptions_per_connection: DEFAULT_MAX_SUBSCRIPTIONS_PER_CONNECTION,
+                max_message_size: DEFAULT_MAX_MESSAGE_SIZE,
+                entity_versions: false,
+                max_wal_capacity: None,
+            }),
+            configs: Arc::new(ConfigRegistry::new()),
+        }
+    }
+
+    fn bearer(token: &str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            header::AUTHORIZATION,
+            HeaderValue::from_str(&format!("Bearer {token}")).expect("header value"),
+        );
+        headers
+    }
+
+    async fn maintain(
+        state: &AppState,
+        app_api_key: &str,
+        wal_capacity: Option<u64>,
+        headers: HeaderMap,
+    ) -> Result<MaintenanceStats, AdminError> {
+        maintenance_handler(
+            State(state.clone()),
+            Path(app_api_key.to_string()),
+            Query(MaintenanceParams { wal_capacity }),
+            headers,
+        )
+        .await
+        .map(|Json(stats)| stats)
+    }
+
+    /// Insert and delete a triple, leaving one tombstone for GC.
+    fn write_and_delete(state: &AppState, app_api_key: &str) {
+        let database = state.registry.get_or_create(app_api_key).expect("open");
+        let mut db = database.write().expect("lock");
+        let mut txn = db.begin(1).expect("begin");
+        txn.insert(
+            EntityId([1u8; 16]),
+            AttributeId([2u8; 16]),
+            TripleValue::Number(1.0),
+        )
+        .expect("insert");
+        txn.commit().expect("commit");
+        let mut txn = db.begin(1).expect("begin");
+        txn.delete(&EntityId([1u8; 16]), &AttributeId([2u8; 16]))
+            .expect("delete");
+        txn.commit().expect("commit");
+        drop(db);
+    }
+
+    #[tokio::test]
+    async fn test_maintenance_rejects_non_admin_tokens() {
+        let dir = tempdir().expect("create temp dir");
+        let state = create_test_state(dir.path().to_path_buf());
+        write_and_delete(&state, "app");
+
+        for headers in [HeaderMap::new(), bearer("app"), bearer("admin-key-2")] {
+            let result = maintain(&state, "app", None, headers).await;
+            assert!(matches!(result, Err(AdminError::Unauthorized)));
+        }
+
+        // Without the Bearer scheme the admin key is not accepted either
+        let mut headers = HeaderMap::new();
+        headers.insert(header::AUTHORIZATION, HeaderValue::from_static("admin-key"));
+        let result = maintain(&state, "app", None, headers).await;
+        assert_eq!(
+            result.expect_err("rejected").status_code(),
+            StatusCode::UNAUTHORIZED
+        );
+    }
+
+    #[tokio::test]
+    async fn test_maintenance_returns_stats_for_admin() {
+        let dir = tempdir().expect("create temp dir");
+        let state = create_test_state(dir.path().to_path_buf());
+        write_and_delete(&state, "app");
+
+        let maintenance = maintain(&state, "app", Some(MIN_WAL_CAPACITY), bearer("admin-key"))
+            .await
+            .expect("maintenance");
+
+        assert!(maintenance.checkpoint.checkpoint_lsn > 0);
+        assert_eq!(maintenance.gc.pending_tombstones, 0);
+        assert_eq!(maintenance.vacuum.wal_capacity_after, MIN_WAL_CAPACITY);
+        assert!(maintenance.vacuum.pages_released > 0);
+
+        // The body round-trips through JSON
+        let json = serde_json::to_string(&maintenance).expect("serialize");
+        let decoded: MaintenanceStats = serde_json::from_str(&json).expect("deserialize");
+        assert_eq!(decoded, maintenance);
+    }
+
+    #[tokio::test]
+    async fn test_maintenance_keeps_wal_capacity_by_default() {
+        let dir = tempdir().expect("create temp dir");
+        let state = create_test_state(dir.path().to_path_buf());
+        write_and_delete(&state, "app");
+
+        let maintenance = maintain(&state, "app", None, bearer("admin-key"))
+            .await
+            .expect("maintenance");
+
+        assert_eq!(
+            maintenance.vacuum.wal_capacity_after,
+            maintenance.vacuum.wal_capacity_before
+        );
+        assert_eq!(maintenance.vacuum.pages_released, 0);
+    }
+
+    #[tokio::test]
+    async fn test_maintenance_does_not_create_databases() {
+        let dir = tempdir().expect("create temp dir");
+        let state = create_test_state(dir.path().to_path_buf());
+
+        let result = maintain(&state, "missing", None, bearer("admin-key")).await;
+        assert!(matches!(result, Err(AdminError::NotFound(_))));
+        assert!(!dir.path().join("missing.db").exists());
+
+        let result = maintain(&state, "bad/key", None, bearer("admin-key")).await;
+        assert!(matches!(result, Err(AdminError::InvalidApiKey)));
+    }
+}
@@ -11,9 +11,15 @@
 //! - `app_api_key` is never empty.
 //! - `JwtConfig::Hs256` secrets must not be empty.
 //! - `JwtConfig::Rs256` public keys must be valid PEM-encoded RSA public keys.
+//! - An expected audience or issuer, if set, is never empty.
+//! - An app without an attribute allow-list may access every attribute.
 
 use jsonwebtoken::DecodingKey;
 
+/// Default tolerance, in seconds, for clock skew between a token's issuer and
+/// the server when checking its 'exp' and 'nbf' claims.
+pub const DEFAULT_EXP_LEEWAY_SECS: u64 = 60;
+
 /// Error returned when JWT configuration is invalid.
 #[derive(Debug)]
 pub enum JwtConfigError {
@@ -21,6 +27,10 @@ pub enum JwtConfigError {
     EmptySecret,
     /// The RS256 public key is not a valid PEM-encoded RSA public key.
     InvalidRs256PublicKey(String),
+    /// The expected audience is empty.
+    EmptyAudience,
+    /// The expected issuer is empty.
+    EmptyIssuer,
 }
 
 impl std::fmt::Display for JwtConfigError {
@@ -30,6 +40,8 @@ impl std::fmt::Display for JwtConfigError {
             Self::InvalidRs256PublicKey(reason) => {
                 write!(f, "invalid RS256 public key: {reason}")
             }
+            Self::EmptyAudience => write!(f, "expected audience must not be empty"),
+            Self::EmptyIssuer => write!(f, "expected issuer must not be empty"),
         }
     }
 }
@@ -39,6 +51,9 @@ impl std::error::Error for JwtConfigError {}
 /// JWT signing/verification configuration.
 ///
 /// Supports both symmetric (HS256) and asymmetric (RS256) algorithms.
+/// Either may also require tokens to carry a given `aud` and `iss` claim,
+/// and tolerates `exp_leeway_secs` of clock skew in the `exp` and `nbf`
+/// claims.
 #[derive(Debug)]
 pub enum JwtConfig {
     /// HMAC-SHA256 symmetric signing.
@@ -47,6 +62,13 @@ pub enum JwtConfig {
     Hs256 {
         /// The shared secret used for HMAC-SHA256.
         secret: Vec<u8>,
+        /// Audience the `aud` claim must contain, if any.
+        expected_audience: Option<String>,
+        /// Issuer the `iss` claim must name, if any.
+        expected_issuer: Option<String>,
+        /// Seconds a token is still accepted after its 'exp', or before its
+        /// 'nbf'.
+        exp_leeway_secs: u64,
     },
     /// RSA-SHA256 asymmetric signing.
     ///
@@ -54,6 +76,13 @@ pub enum JwtConfig {
     Rs256 {
         /// PEM-encoded RSA public key.
         public_key: String,
+        /// Audience the `aud` claim must contain, if any.
+        expected_audience: Option<String>,
+        /// Issuer the `iss` claim must name, if any.
+        expected_issuer: Option<String>,
+        /// Seconds a token is still accepted after its 'exp', or before its
+        /// 'nbf'.
+        exp_leeway_secs: u64,
     },
 }
 
@@ -72,7 +101,12 @@ impl JwtConfig {
         if secret.is_empty() {
             return Err(JwtConfigError::EmptySecret);
         }
-        Ok(Self::Hs256 { secret })
+        Ok(Self::Hs256 {
+            secret,
+            expected_audience: None,
+            expected_issuer: None,
+            exp_leeway_secs: DEFAULT_EXP_LEEWAY_SECS,
+        })
     }
 
     /// Create a new RS256 JWT configuration.
@@ -91,19 +125,122 @@ impl JwtConfig {
         DecodingKey::from_rsa_pem(public_key.as_bytes())
             .map_err(|e| JwtConfigError::InvalidRs256PublicKey(e.to_string()))?;
 
-        Ok(Self::Rs256 { public_key })
+        Ok(Self::Rs256 {
+            public_key,
+            expected_audience: None,
+            expected_issuer: None,
+            exp_leeway_secs: DEFAULT_EXP_LEEWAY_SECS,
+        })
+    }
+
+    /// Require tokens to carry an `aud` claim containing `audience`.
+    ///
+    /// # Errors
+    /// Returns `JwtConfigError::EmptyAudience` if the audience is empty.
+    pub fn with_expected_audience(mut self, audience: String) -> Result<Self, JwtConfigError> {
+        if audience.is_empty() {
+            return Err(JwtConfigError::EmptyAudience);
+        }
+        match &mut self {
+            Self::Hs256 {
+                expected_audience, ..
+            }
+            | Self::Rs256 {
+                expected_audience, ..
+            } => *expected_audience = Some(audience),
+        }
+        Ok(self)
+    }
+
+    /// Require tokens to carry an `iss` claim naming `issuer`.
+    ///
+    /// # Errors
+    /// Returns `JwtConfigError::EmptyIssuer` if the issuer is empty.
+    pub fn with_expected_issuer(mut self, issuer: String) -> Result<Self, JwtConfigError> {
+        if issuer.is_empty() {
+            return Err(JwtConfigError::EmptyIssuer);
+        }
+        match &mut self {
+            Self::Hs256 {
+                expected_issuer, ..
+            }
+            | Self::Rs256 {
+                expected_issuer, ..
+            } => *expected_issuer = Some(issuer),
+        }
+        Ok(self)
+    }
+
+    /// Set how many seconds of clock skew to tolerate when checking the
+    /// 'exp' and 'nbf' claims.
+    #[must_use]
+    pub const fn with_exp_leeway_secs(mut self, leeway_secs: u64) -> Self {
+        match &mut self {
+            Self::Hs256 {
+                exp_leeway_secs, ..
+            }
+            | Self::Rs256 {
+                exp_leeway_secs, ..
+            } => *exp_leeway_secs = leeway_secs,
+        }
+        self
+    }
+
+    /// Returns the audience tokens must be issued for, if any.
+    #[must_use]
+    pub fn expected_audience(&self) -> Option<&str> {
+        match self {
+            Self::Hs256 {
+                expected_audience, ..
+            }
+            | Self::Rs256 {
+                expected_audience, ..
+            } => expected_audience.as_deref(),
+        }
+    }
+
+    /// Returns the issuer tokens must come from, if any.
+    #[must_use]
+    pub fn expected_issuer(&self) -> Option<&str> {
+        match self {
+            Self::Hs256 {
+                expected_issuer, ..
+            }
+            | Self::Rs256 {
+                expected_issuer, ..
+            } => expected_issuer.as_deref(),
+        }
+    }
+
+    /// Returns the seconds of clock skew tolerated in the 'exp' and 'nbf'
+    /// claims.
+    #[must_use]
+    pub const fn exp_leeway_secs(&self) -> u64 {
+        match self {
+            Self::Hs256 {
+                exp_leeway_secs, ..
+            }
+            | Self::Rs256 {
+                exp_leeway_secs, ..
+            } => *exp_leeway_secs,
+        }
     }
 }
 
 /// Configuration for an application's authentication settings.
 ///
 /// Each application has an API key and optionally supports JWT authentication.
+/// An application may also be scoped to the attributes whose IDs start with
+/// one of a set of prefixes.
 #[derive(Debug)]
 pub struct AppConfig {
     /// API key used to authenticate requests from this application.
     app_api_key: String,
     /// Optional JWT configuration for token-based authentication.
     jwt_config: Option<JwtConfig>,
+    /// Attribute ID prefixes this application may read and write.
+    /// `None` allows every attribute.
+    allowed_attribute_prefixes: Option<Vec<Vec<u8>>>,
 }
 
 impl AppConfig {
@@ -119,9 +256,20 @@ impl AppConfig {
         Self {
             app_api_key,
             jwt_config,
+            allowed_attribute_prefixes: None,
         }
     }
 
+    /// Restrict this application to the attributes whose IDs start with one
+    /// of `prefixes`.
+    ///
+    /// An empty prefix matches every attribute; an empty list matches none.
+    #[must_use]
+    pub fn with_allowed_attribute_prefixes(mut self, prefixes: Vec<Vec<u8>>) -> Self {
+        self.allowed_attribute_prefixes = Some(prefixes);
+        self
+    }
+
     /// Returns the API key for this application.
     #[must_use]
     pub fn app_api_key(&self) -> &str {
@@ -133,6 +281,26 @@ impl AppConfig {
     pub const fn jwt_config(&self) -> Option<&JwtConfig> {
         self.jwt_config.as_ref()
     }
+
+    /// Returns the attribute ID prefixes this application is scoped to, or
+    /// `None` if it may access every attribute.
+    #[must_use]
+    pub fn allowed_attribute_prefixes(&self) -> Option<&[Vec<u8>]> {
+        self.allowed_attribute_prefixes.as_deref()
+    }
+
+    /// Check whether this application may access the attribute with the
+    /// given ID.
+    #[must_use]
+    pub fn allows_attribute(&self, attribute_id: &[u8]) -> bool {
+        self.allowed_attribute_prefixes
+            .as_ref()
+            .is_none_or(|prefixes| {
+                prefixes
+                    .iter()
+                    .any(|prefix| attribute_id.starts_with(prefix))
+            })
+    }
 }
 
 #[cfg(test)]
@@ -156,6 +324,28 @@ WQIDAQAB
 
         assert_eq!(config.app_api_key(), "test-api-key");
         assert!(config.jwt_config().is_none());
+        assert!(config.allowed_attribute_prefixes().is_none());
+        assert!(config.allows_attribute(&[0x02; 16]));
+    }
+
+    #[test]
+    fn test_app_config_attribute_prefixes() {
+        let config = AppConfig::new("test-api-key".to_string(), None)
+            .with_allowed_attribute_prefixes(vec![vec![0x01], vec![0x03, 0x04]]);
+
+        assert!(config.allows_attribute(&[0x01; 16]));
+        assert!(config.allows_attribute(&[0x03, 0x04, 0x05]));
+        assert!(!config.allows_attribute(&[0x02; 16]));
+        assert!(!config.allows_attribute(&[0x03, 0x05]));
+        assert!(!config.allows_attribute(&[]));
+    }
+
+    #[test]
+    fn test_app_config_empty_allow_list_denies_everything() {
+        let config = AppConfig::new("test-api-key".to_string(), None)
+            .with_allowed_attribute_prefixes(vec![]);
+
+        assert!(!config.allows_attribute(&[0x01; 16]));
     }
 
     #[test]
@@ -163,7 +353,7 @@ WQIDAQAB
         let result = JwtConfig::new_hs256(b"my-secret-key".to_vec());
         assert!(result.is_ok());
 
-        if let Ok(JwtConfig::Hs256 { secret }) = result {
+        if let Ok(JwtConfig::Hs256 { secret, .. }) = result {
             assert_eq!(secret, b"my-secret-key");
         } else {
             panic!("Expected Hs256 config");
@@ -182,7 +372,7 @@ WQIDAQAB
         let result = JwtConfig::new_rs256(VALID_RS256_PUBLIC_KEY.to_string());
         assert!(result.is_ok());
 
-        if let Ok(JwtConfig::Rs256 { public_key }) = result {
+        if let Ok(JwtConfig::Rs256 { public_key, .. }) = result {
             assert_eq!(public_key, VALID_RS256_PUBLIC_KEY);
         } else {
             panic!("Expected Rs256 config");
@@ -229,7 +419,7 @@ WQIDAQAB
         assert_eq!(config.app_api_key(), "test-api-key");
         assert!(config.jwt_config().is_some());
 
-        if let Some(JwtConfig::Hs256 { secret }) = config.jwt_config() {
+        if let Some(JwtConfig::Hs256 { secret, .. }) = config.jwt_config() {
             assert_eq!(secret, b"my-secret-key");
         } else {
             panic!("Expected Hs256 config");
@@ -245,13 +435,50 @@ WQIDAQAB
         assert_eq!(config.app_api_key(), "test-api-key");
         assert!(config.jwt_config().is_some());
 
-        if let Some(JwtConfig::Rs256 { public_key }) = config.jwt_config() {
+        if let Some(JwtConfig::Rs256 { public_key, .. }) = config.jwt_config() {
             assert_eq!(public_key, VALID_RS256_PUBLIC_KEY);
         } else {
             panic!("Expected Rs256 config");
         }
     }
 
+    #[test]
+    fn test_jwt_config_expected_claims() {
+        let config = JwtConfig::new_hs256(b"my-secret-key".to_vec())
+            .and_then(|config| config.with_expected_audience("my-app".to_string()))
+            .and_then(|config| config.with_expected_issuer("https://issuer.example".to_string()))
+            .expect("valid config");
+
+        assert_eq!(config.expected_audience(), Some("my-app"));
+        assert_eq!(config.expected_issuer(), Some("https://issuer.example"));
+    }
+
+    #[test]
+    fn test_jwt_config_exp_leeway() {
+        let config = JwtConfig::new_hs256(b"my-secret-key".to_vec()).expect("valid secret");
+        assert_eq!(config.exp_leeway_secs(), DEFAULT_EXP_LEEWAY_SECS);
+
+        let config = config.with_exp_leeway_secs(5);
+        assert_eq!(config.exp_leeway_secs(), 5);
+    }
+
+    #[test]
+    fn test_jwt_config_empty_expected_claims() {
+        let config = JwtConfig::new_hs256(b"my-secret-key".to_vec()).expect("valid secret");
+        assert!(config.expected_audience().is_none());
+        assert!(config.expected_issuer().is_none());
+        assert!(matches!(
+            config.with_expected_audience(String::new()),
+            Err(JwtConfigError::EmptyAudience)
+        ));
+
+        let config = JwtConfig::new_hs256(b"my-secret-key".to_vec()).expect("valid secret");
+        assert!(matches!(
+            config.with_expected_issuer(String::new()),
+            Err(JwtConfigError::EmptyIssuer)
+        ));
+    }
+
     #[test]
     fn test_jwt_config_error_display() {
         let empty_secret = JwtConfigError::EmptySecret;
@@ -262,5 +489,14 @@ WQIDAQAB
             invalid_key.to_string(),
             "invalid RS256 public key: bad format"
         );
+
+        assert_eq!(
+            JwtConfigError::EmptyAudience.to_string(),
+            "expected audience must not be empty"
+        );
+        assert_eq!(
+            JwtConfigError::EmptyIssuer.to_string(),
+            "expected issuer must not be empty"
+        );
     }
 }
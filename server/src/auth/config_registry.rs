@@ -0,0 +1,175 @@
+//! Registry of application configurations.
+//!
+//! Maps each `app_api_key` to its `AppConfig`, and authenticates connections
+//! against it: a JWT presented when connecting is verified with the app's key,
+//! and the connection is then bound to the app's configuration.
+//!
+//! # Pre-conditions
+//! - Every registered `AppConfig` has a valid, non-empty API key.
+//!
+//! # Post-conditions
+//! - A connection that authenticates successfully receives the configuration
+//!   of the app it connected to, or `None` for an unregistered app.
+//!
+//! # Invariants
+//! - At most one configuration is registered per API key.
+//! - An app with a `JwtConfig` never authenticates without a valid token.
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use super::{AppConfig, JwtError, verify_token};
+use crate::storage::SystemTimeSource;
+
+/// Error returned when a connection fails to authenticate.
+#[derive(Debug)]
+pub enum AuthError {
+    /// The app requires a JWT but none was provided.
+    MissingToken,
+    /// The provided JWT failed verification.
+    InvalidToken(JwtError),
+}
+
+impl std::fmt::Display for AuthError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::MissingToken => write!(f, "auth_token is required for this app"),
+            Self::InvalidToken(e) => write!(f, "invalid auth_token: {e}"),
+        }
+    }
+}
+
+impl std::error::Error for AuthError {}
+
+/// Application configurations, keyed by `app_api_key`.
+#[derive(Debug, Default)]
+pub struct ConfigRegistry {
+    apps: HashMap<String, Arc<AppConfig>>,
+}
+
+impl ConfigRegistry {
+    /// Create an empty registry.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Register an application's configuration, replacing any previous
+    /// configuration for the same API key.
+    pub fn register(&mut self, config: AppConfig) {
+        self.apps
+            .insert(config.app_api_key().to_owned(), Arc::new(config));
+    }
+
+    /// Get the configuration registered for `app_api_key`, if any.
+    #[must_use]
+    #[allow(clippy::disallowed_methods)] // Arc::clone is cheap
+    pub fn get(&self, app_api_key: &str) -> Option<Arc<AppConfig>> {
+        self.apps.get(app_api_key).map(Arc::clone)
+    }
+
+    /// Authenticate a connection to `app_api_key`.
+    ///
+    /// An app configured with a `JwtConfig` requires `auth_token`, which must
+    /// verify against the app's key and be valid at the current system time.
+    ///
+    /// # Returns
+    /// The app's configuration, or `None` if the app is not registered and so
+    /// is unrestricted.
+    ///
+    /// # Errors
+    /// Returns `AuthError` if a required token is missing or fails
+    /// verification.
+    pub fn authenticate(
+        &self,
+        app_api_key: &str,
+        auth_token: Option<&str>,
+    ) -> Result<Option<Arc<AppConfig>>, AuthError> {
+        let Some(config) = self.get(app_api_key) else {
+            return Ok(None);
+        };
+        if let Some(jwt_config) = config.jwt_config() {
+            let token = auth_token.ok_or(AuthError::MissingToken)?;
+            verify_token(token, jwt_config, &SystemTimeSource).map_err(AuthError::InvalidToken)?;
+        }
+        Ok(Some(config))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::auth::JwtConfig;
+    use jsonwebtoken::{Algorithm, EncodingKey, Header, encode};
+
+    const SECRET: &[u8] = b"test-secret-key-that-is-long-enough";
+
+    fn create_token(secret: &[u8]) -> String {
+        let claims = serde_json::json!({ "sub": "user-123" });
+        encode(
+            &Header::new(Algorithm::HS256),
+            &claims,
+            &EncodingKey::from_secret(secret),
+        )
+        .expect("failed to create test token")
+    }
+
+    fn registry_with_jwt_app() -> ConfigRegistry {
+        let jwt_config = JwtConfig::new_hs256(SECRET.to_vec()).expect("valid secret");
+        let mut registry = ConfigRegistry::new();
+        registry.register(AppConfig::new("jwt-app".to_string(), Some(jwt_config)));
+        registry
+    }
+
+    #[test]
+    fn test_unregistered_app_is_unrestricted() {
+        let registry = ConfigRegistry::new();
+        let config = registry
+            .authenticate("other-app", None)
+            .expect("authenticated");
+        assert!(config.is_none());
+    }
+
+    #[test]
+    fn test_app_without_jwt_needs_no_token() {
+        let mut registry = ConfigRegistry::new();
+        registry.register(AppConfig::new("open-app".to_string(), None));
+
+        let config = registry
+            .authenticate("open-app", None)
+            .expect("authenticated")
+            .expect("registered app");
+        assert_eq!(config.app_api_key(), "open-app");
+    }
+
+    #[test]
+    fn test_valid_token_resolves_app_config() {
+        let registry = registry_with_jwt_app();
+        let token = create_token(SECRET);
+
+        let config = registry
+            .authenticate("jwt-app", Some(&token))
+            .expect("authenticated")
+            .expect("registered app");
+        assert_eq!(config.app_api_key(), "jwt-app");
+    }
+
+    #[test]
+    fn test_missing_token_is_rejected() {
+        let registry = registry_with_jwt_app();
+        assert!(matches!(
+            registry.authenticate("jwt-app", None),
+            Err(AuthError::MissingToken)
+        ));
+    }
+
+    #[test]
+    fn test_token_signed_with_wrong_key_is_rejected() {
+        let registry = registry_with_jwt_app();
+        let token = create_token(b"wrong-secret-key-that-is-different");
+        assert!(matches!(
+            registry.authenticate("jwt-app", Some(&token)),
+            Err(AuthError::InvalidToken(JwtError::InvalidSignature))
+        ));
+    }
+}
@@ -9,24 +9,40 @@
 //!
 //! # Post-conditions
 //! - On success, returns the user ID extracted from the 'sub' claim.
+//! - A token is only accepted if its 'aud' and 'iss' claims match the
+//!   configured audience and issuer, when those are set.
+//! - A token is only accepted between its 'nbf' and 'exp' claims, widened by
+//!   the configured leeway. Tokens without them never expire.
 //! - On failure, returns a descriptive error indicating what went wrong.
 //!
 //! # Invariants
 //! - Verification is stateless and does not modify any external state.
-//! - The same inputs always produce the same outputs.
+//! - The same inputs, including the time source's current time, always
+//!   produce the same outputs.
 
 use jsonwebtoken::{Algorithm, DecodingKey, Validation, decode};
 use serde::{Deserialize, Serialize};
 
 use super::JwtConfig;
+use crate::storage::TimeSource;
 
 /// Claims extracted from a JWT.
 ///
 /// The 'sub' (subject) claim is required and contains the user identifier.
 #[derive(Debug, Serialize, Deserialize)]
 struct Claims {
-    /// Subject claim containing the user identifier.
+    /// Subject claim containing the user identifier. Defaulted so that a
+    /// token without one fails validation with `MissingSubClaim` rather than
+    /// as malformed.
+    #[serde(default)]
     sub: String,
+    /// Expiration time, in seconds since the Unix epoch.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    exp: Option<u64>,
+    /// Time before which the token is not valid, in seconds since the Unix
+    /// epoch.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    nbf: Option<u64>,
 }
 
 /// Error returned when JWT verification fails.
@@ -34,12 +50,21 @@ struct Claims {
 pub enum JwtError {
     /// The JWT signature is invalid.
     InvalidSignature,
-    /// The JWT has expired.
-    TokenExpired,
+    /// The JWT's 'exp' claim is further in the past than the leeway allows.
+    Expired,
+    /// The JWT's 'nbf' claim is further in the future than the leeway allows.
+    NotYetValid,
     /// The JWT is malformed or cannot be parsed.
     MalformedToken,
     /// The 'sub' claim is missing from the JWT.
     MissingSubClaim,
+    /// A claim the configuration requires, such as 'aud' or 'iss', is
+    /// missing from the JWT.
+    MissingClaim(String),
+    /// The 'aud' claim does not contain the expected audience.
+    AudienceMismatch,
+    /// The 'iss' claim does not name the expected issuer.
+    IssuerMismatch,
     /// The decoding key could not be created from the provided configuration.
     InvalidKey(String),
 }
@@ -48,9 +73,13 @@ impl std::fmt::Display for JwtError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             Self::InvalidSignature => write!(f, "invalid JWT signature"),
-            Self::TokenExpired => write!(f, "JWT has expired"),
+            Self::Expired => write!(f, "JWT has expired"),
+            Self::NotYetValid => write!(f, "JWT is not yet valid"),
             Self::MalformedToken => write!(f, "malformed JWT"),
             Self::MissingSubClaim => write!(f, "missing 'sub' claim in JWT"),
+            Self::MissingClaim(claim) => write!(f, "missing '{claim}' claim in JWT"),
+            Self::AudienceMismatch => write!(f, "JWT audience does not match"),
+            Self::IssuerMismatch => write!(f, "JWT issuer does not match"),
             Self::InvalidKey(reason) => write!(f, "invalid key: {reason}"),
         }
     }
@@ -63,17 +92,46 @@ impl std::error::Error for JwtError {}
 /// # Arguments
 /// * `token` - The JWT string to verify.
 /// * `config` - The JWT configuration containing the key material.
+/// * `time` - The source of the current time, checked against the 'exp' and
+///   'nbf' claims.
 ///
 /// # Returns
 /// The user ID from the 'sub' claim on success.
 ///
 /// # Errors
 /// Returns `JwtError` if verification fails for any reason.
-pub fn verify_token(token: &str, config: &JwtConfig) -> Result<String, JwtError> {
-    match config {
-        JwtConfig::Hs256 { secret } => verify_hs256(token, secret),
-        JwtConfig::Rs256 { public_key } => verify_rs256(token, public_key),
+pub fn verify_token(
+    token: &str,
+    config: &JwtConfig,
+    time: &impl TimeSource,
+) -> Result<String, JwtError> {
+    let claims = match config {
+        JwtConfig::Hs256 { secret, .. } => verify_hs256(token, secret, config)?,
+        JwtConfig::Rs256 { public_key, .. } => verify_rs256(token, public_key, config)?,
+    };
+    check_validity_window(&claims, config.exp_leeway_secs(), time.now_ms() / 1000)?;
+    Ok(claims.sub)
+}
+
+/// Checks that `now_secs` lies between a token's 'nbf' and 'exp' claims,
+/// widened by `leeway_secs` on both sides.
+///
+/// # Errors
+/// Returns `JwtError::Expired` or `JwtError::NotYetValid` if it does not.
+fn check_validity_window(claims: &Claims, leeway_secs: u64, now_secs: u64) -> Result<(), JwtError> {
+    if claims
+        .exp
+        .is_some_and(|exp| exp.saturating_add(leeway_secs) < now_secs)
+    {
+        return Err(JwtError::Expired);
+    }
+    if claims
+        .nbf
+        .is_some_and(|nbf| nbf > now_secs.saturating_add(leeway_secs))
+    {
+        return Err(JwtError::NotYetValid);
     }
+    Ok(())
 }
 
 /// Verifies a JWT using the HS256 algorithm.
@@ -81,23 +139,22 @@ pub fn verify_token(token: &str, config: &JwtConfig) -> Result<String, JwtError>
 /// # Arguments
 /// * `token` - The JWT string to verify.
 /// * `secret` - The shared secret for HMAC-SHA256 verification.
+/// * `config` - The JWT configuration holding the expected claims.
 ///
 /// # Returns
-/// The user ID from the 'sub' claim on success.
+/// The token's claims on success.
 ///
 /// # Errors
 /// Returns `JwtError` if verification fails.
-fn verify_hs256(token: &str, secret: &[u8]) -> Result<String, JwtError> {
+fn verify_hs256(token: &str, secret: &[u8], config: &JwtConfig) -> Result<Claims, JwtError> {
     if secret.is_empty() {
         return Err(JwtError::InvalidKey("secret must be non-empty".to_string()));
     }
 
     let key = DecodingKey::from_secret(secret);
-    let mut validation = Validation::new(Algorithm::HS256);
-    // Make exp claim optional - tokens without expiration are valid.
-    validation.required_spec_claims.remove("exp");
+    let validation = claim_validation(Algorithm::HS256, config);
 
-    decode_and_extract_sub(token, &key, &validation)
+    decode_claims(token, &key, &validation)
 }
 
 /// Verifies a JWT using the RS256 algorithm.
@@ -105,23 +162,45 @@ fn verify_hs256(token: &str, secret: &[u8]) -> Result<String, JwtError> {
 /// # Arguments
 /// * `token` - The JWT string to verify.
 /// * `public_key` - The PEM-encoded RSA public key for verification.
+/// * `config` - The JWT configuration holding the expected claims.
 ///
 /// # Returns
-/// The user ID from the 'sub' claim on success.
+/// The token's claims on success.
 ///
 /// # Errors
 /// Returns `JwtError` if verification fails or the key is invalid.
-fn verify_rs256(token: &str, public_key: &str) -> Result<String, JwtError> {
+fn verify_rs256(token: &str, public_key: &str, config: &JwtConfig) -> Result<Claims, JwtError> {
     let key = DecodingKey::from_rsa_pem(public_key.as_bytes())
         .map_err(|e| JwtError::InvalidKey(e.to_string()))?;
-    let mut validation = Validation::new(Algorithm::RS256);
-    // Make exp claim optional - tokens without expiration are valid.
-    validation.required_spec_claims.remove("exp");
+    let validation = claim_validation(Algorithm::RS256, config);
 
-    decode_and_extract_sub(token, &key, &validation)
+    decode_claims(token, &key, &validation)
 }
 
-/// Decodes a JWT and extracts the 'sub' claim.
+/// Builds the validation rules for a token signed with `algorithm`.
+///
+/// The 'sub' claim is always required, and 'aud' and 'iss' are required
+/// and checked when the configuration expects them. The 'exp' and 'nbf'
+/// claims are optional and checked against the caller's time source instead
+/// of the system clock, so their validation here is off.
+fn claim_validation(algorithm: Algorithm, config: &JwtConfig) -> Validation {
+    let mut validation = Validation::new(algorithm);
+    validation.validate_exp = false;
+    validation.validate_nbf = false;
+    let mut required_claims = vec!["sub"];
+    if let Some(audience) = config.expected_audience() {
+        validation.set_audience(&[audience]);
+        required_claims.push("aud");
+    }
+    if let Some(issuer) = config.expected_issuer() {
+        validation.set_issuer(&[issuer]);
+        required_claims.push("iss");
+    }
+    validation.set_required_spec_claims(&required_claims);
+    validation
+}
+
+/// Decodes a JWT and checks that it has a 'sub' claim.
 ///
 /// # Arguments
 /// * `token` - The JWT string to decode.
@@ -129,23 +208,22 @@ fn verify_rs256(token: &str, public_key: &str) -> Result<String, JwtError> {
 /// * `validation` - The validation configuration.
 ///
 /// # Returns
-/// The user ID from the 'sub' claim on success.
+/// The token's claims on success.
 ///
 /// # Errors
 /// Returns `JwtError` based on the type of failure.
-fn decode_and_extract_sub(
+fn decode_claims(
     token: &str,
     key: &DecodingKey,
     validation: &Validation,
-) -> Result<String, JwtError> {
+) -> Result<Claims, JwtError> {
     let token_data = decode::<Claims>(token, key, validation).map_err(|e| map_jwt_error(&e))?;
 
-    let user_id = token_data.claims.sub;
-    if user_id.is_empty() {
+    if token_data.claims.sub.is_empty() {
         return Err(JwtError::MissingSubClaim);
     }
 
-    Ok(user_id)
+    Ok(token_data.claims)
 }
 
 /// Maps jsonwebtoken errors to our `JwtError` type.
@@ -154,8 +232,12 @@ fn map_jwt_error(error: &jsonwebtoken::errors::Error) -> JwtError {
 
     match error.kind() {
         ErrorKind::InvalidSignature => JwtError::InvalidSignature,
-        ErrorKind::ExpiredSignature => JwtError::TokenExpired,
-        ErrorKind::MissingRequiredClaim(_) => JwtError::MissingSubClaim,
+        ErrorKind::MissingRequiredClaim(claim) if claim == "sub" => JwtError::MissingSubClaim,
+        ErrorKind::MissingRequiredClaim(claim) => {
+            JwtError::MissingClaim(String::from(claim.as_str()))
+        }
+        ErrorKind::InvalidAudience => JwtError::AudienceMismatch,
+        ErrorKind::InvalidIssuer => JwtError::IssuerMismatch,
         _ => JwtError::MalformedToken,
     }
 }
@@ -163,17 +245,38 @@ fn map_jwt_error(error: &jsonwebtoken::errors::Error) -> JwtError {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::auth::DEFAULT_EXP_LEEWAY_SECS;
+    use crate::simulation::SimulatedTimeSource;
+    use crate::storage::SystemTimeSource;
     use jsonwebtoken::{EncodingKey, Header, encode};
 
     fn create_hs256_token(sub: &str, secret: &[u8]) -> String {
         let claims = Claims {
             sub: sub.to_string(),
+            exp: None,
+            nbf: None,
         };
         let header = Header::new(Algorithm::HS256);
         encode(&header, &claims, &EncodingKey::from_secret(secret))
             .expect("failed to create test token")
     }
 
+    const AUDIENCE: &str = "enso-app";
+    const ISSUER: &str = "https://issuer.example";
+
+    fn create_token_with_claims(claims: &serde_json::Value, secret: &[u8]) -> String {
+        let header = Header::new(Algorithm::HS256);
+        encode(&header, claims, &EncodingKey::from_secret(secret))
+            .expect("failed to create test token")
+    }
+
+    fn config_with_expected_claims(secret: &[u8]) -> JwtConfig {
+        JwtConfig::new_hs256(secret.to_vec())
+            .and_then(|config| config.with_expected_audience(AUDIENCE.to_string()))
+            .and_then(|config| config.with_expected_issuer(ISSUER.to_string()))
+            .expect("valid config")
+    }
+
     #[test]
     fn test_verify_hs256_valid_token() {
         let secret = b"test-secret-key-that-is-long-enough";
@@ -181,8 +284,11 @@ mod tests {
 
         let config = JwtConfig::Hs256 {
             secret: secret.to_vec(),
+            expected_audience: None,
+            expected_issuer: None,
+            exp_leeway_secs: DEFAULT_EXP_LEEWAY_SECS,
         };
-        let result = verify_token(&token, &config);
+        let result = verify_token(&token, &config, &SystemTimeSource);
 
         assert!(result.is_ok());
         assert_eq!(result.expect("verified token"), "user-123");
@@ -196,8 +302,11 @@ mod tests {
 
         let config = JwtConfig::Hs256 {
             secret: wrong_secret.to_vec(),
+            expected_audience: None,
+            expected_issuer: None,
+            exp_leeway_secs: DEFAULT_EXP_LEEWAY_SECS,
         };
-        let result = verify_token(&token, &config);
+        let result = verify_token(&token, &config, &SystemTimeSource);
 
         assert!(result.is_err());
         assert!(matches!(result, Err(JwtError::InvalidSignature)));
@@ -207,8 +316,11 @@ mod tests {
     fn test_verify_malformed_token() {
         let config = JwtConfig::Hs256 {
             secret: b"secret".to_vec(),
+            expected_audience: None,
+            expected_issuer: None,
+            exp_leeway_secs: DEFAULT_EXP_LEEWAY_SECS,
         };
-        let result = verify_token("not-a-valid-jwt", &config);
+        let result = verify_token("not-a-valid-jwt", &config, &SystemTimeSource);
 
         assert!(result.is_err());
         assert!(matches!(result, Err(JwtError::MalformedToken)));
@@ -218,8 +330,11 @@ mod tests {
     fn test_verify_empty_token() {
         let config = JwtConfig::Hs256 {
             secret: b"secret".to_vec(),
+            expected_audience: None,
+            expected_issuer: None,
+            exp_leeway_secs: DEFAULT_EXP_LEEWAY_SECS,
         };
-        let result = verify_token("", &config);
+        let result = verify_token("", &config, &SystemTimeSource);
 
         assert!(result.is_err());
         assert!(matches!(result, Err(JwtError::MalformedToken)));
@@ -229,25 +344,262 @@ mod tests {
     fn test_verify_rs256_invalid_key() {
         let config = JwtConfig::Rs256 {
             public_key: "not-a-valid-pem-key".to_string(),
+            expected_audience: None,
+            expected_issuer: None,
+            exp_leeway_secs: DEFAULT_EXP_LEEWAY_SECS,
         };
-        let result = verify_token("some.jwt.token", &config);
+        let result = verify_token("some.jwt.token", &config, &SystemTimeSource);
 
         assert!(result.is_err());
         assert!(matches!(result, Err(JwtError::InvalidKey(_))));
     }
 
+    #[test]
+    fn test_verify_token_with_expected_claims() {
+        let secret = b"test-secret-key-that-is-long-enough";
+        let token = create_token_with_claims(
+            &serde_json::json!({ "sub": "user-123", "aud": AUDIENCE, "iss": ISSUER }),
+            secret,
+        );
+
+        let result = verify_token(
+            &token,
+            &config_with_expected_claims(secret),
+            &SystemTimeSource,
+        );
+
+        assert_eq!(result.expect("verified token"), "user-123");
+    }
+
+    #[test]
+    fn test_verify_token_accepts_audience_list() {
+        let secret = b"test-secret-key-that-is-long-enough";
+        let token = create_token_with_claims(
+            &serde_json::json!({ "sub": "user-123", "aud": ["other-app", AUDIENCE], "iss": ISSUER }),
+            secret,
+        );
+
+        let result = verify_token(
+            &token,
+            &config_with_expected_claims(secret),
+            &SystemTimeSource,
+        );
+
+        assert_eq!(result.expect("verified token"), "user-123");
+    }
+
+    #[test]
+    fn test_verify_token_wrong_audience() {
+        let secret = b"test-secret-key-that-is-long-enough";
+        let token = create_token_with_claims(
+            &serde_json::json!({ "sub": "user-123", "aud": "other-app", "iss": ISSUER }),
+            secret,
+        );
+
+        let result = verify_token(
+            &token,
+            &config_with_expected_claims(secret),
+            &SystemTimeSource,
+        );
+
+        assert!(matches!(result, Err(JwtError::AudienceMismatch)));
+    }
+
+    #[test]
+    fn test_verify_token_wrong_issuer() {
+        let secret = b"test-secret-key-that-is-long-enough";
+        let token = create_token_with_claims(
+            &serde_json::json!({ "sub": "user-123", "aud": AUDIENCE, "iss": "https://evil.example" }),
+            secret,
+        );
+
+        let result = verify_token(
+            &token,
+            &config_with_expected_claims(secret),
+            &SystemTimeSource,
+        );
+
+        assert!(matches!(result, Err(JwtError::IssuerMismatch)));
+    }
+
+    #[test]
+    fn test_verify_token_missing_expected_claims() {
+        let secret = b"test-secret-key-that-is-long-enough";
+        let config = config_with_expected_claims(secret);
+        let no_audience = create_token_with_claims(
+            &serde_json::json!({ "sub": "user-123", "iss": ISSUER }),
+            secret,
+        );
+        let no_issuer = create_token_with_claims(
+            &serde_json::json!({ "sub": "user-123", "aud": AUDIENCE }),
+            secret,
+        );
+
+        match verify_token(&no_audience, &config, &SystemTimeSource) {
+            Err(JwtError::MissingClaim(claim)) => assert_eq!(claim, "aud"),
+            other => panic!("expected MissingClaim, got {other:?}"),
+        }
+        match verify_token(&no_issuer, &config, &SystemTimeSource) {
+            Err(JwtError::MissingClaim(claim)) => assert_eq!(claim, "iss"),
+            other => panic!("expected MissingClaim, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn test_verify_token_missing_sub_claim() {
+        let secret = b"test-secret-key-that-is-long-enough";
+        let token = create_token_with_claims(
+            &serde_json::json!({ "aud": AUDIENCE, "iss": ISSUER }),
+            secret,
+        );
+
+        let result = verify_token(
+            &token,
+            &config_with_expected_claims(secret),
+            &SystemTimeSource,
+        );
+
+        assert!(matches!(result, Err(JwtError::MissingSubClaim)));
+    }
+
+    /// The simulated time, in seconds, that validity window tests run at.
+    const NOW_SECS: u64 = 1_700_000_000;
+
+    fn time_at(secs: u64) -> SimulatedTimeSource {
+        SimulatedTimeSource::new(secs * 1000)
+    }
+
+    fn leeway_config(secret: &[u8], leeway_secs: u64) -> JwtConfig {
+        JwtConfig::new_hs256(secret.to_vec())
+            .expect("valid secret")
+            .with_exp_leeway_secs(leeway_secs)
+    }
+
+    #[test]
+    fn test_verify_token_expired_within_leeway() {
+        let secret = b"test-secret-key-that-is-long-enough";
+        let token = create_token_with_claims(
+            &serde_json::json!({ "sub": "user-123", "exp": NOW_SECS - 30 }),
+            secret,
+        );
+
+        let result = verify_token(&token, &leeway_config(secret, 60), &time_at(NOW_SECS));
+
+        assert_eq!(result.expect("verified token"), "user-123");
+    }
+
+    #[test]
+    fn test_verify_token_expired_beyond_leeway() {
+        let secret = b"test-secret-key-that-is-long-enough";
+        let token = create_token_with_claims(
+            &serde_json::json!({ "sub": "user-123", "exp": NOW_SECS - 90 }),
+            secret,
+        );
+
+        let result = verify_token(&token, &leeway_config(secret, 60), &time_at(NOW_SECS));
+
+        assert!(matches!(result, Err(JwtError::Expired)));
+    }
+
+    #[test]
+    fn test_verify_token_expiry_follows_time_source() {
+        let secret = b"test-secret-key-that-is-long-enough";
+        let config = leeway_config(secret, 0);
+        let token = create_token_with_claims(
+            &serde_json::json!({ "sub": "user-123", "exp": NOW_SECS }),
+            secret,
+        );
+        let time = time_at(NOW_SECS);
+
+        assert!(verify_token(&token, &config, &time).is_ok());
+        time.advance(1000);
+        assert!(matches!(
+            verify_token(&token, &config, &time),
+            Err(JwtError::Expired)
+        ));
+    }
+
+    #[test]
+    fn test_verify_token_not_yet_valid() {
+        let secret = b"test-secret-key-that-is-long-enough";
+        let config = leeway_config(secret, 60);
+        let soon = create_token_with_claims(
+            &serde_json::json!({ "sub": "user-123", "nbf": NOW_SECS + 30 }),
+            secret,
+        );
+        let later = create_token_with_claims(
+            &serde_json::json!({ "sub": "user-123", "nbf": NOW_SECS + 90 }),
+            secret,
+        );
+
+        assert!(verify_token(&soon, &config, &time_at(NOW_SECS)).is_ok());
+        assert!(matches!(
+            verify_token(&later, &config, &time_at(NOW_SECS)),
+            Err(JwtError::NotYetValid)
+        ));
+    }
+
+    #[test]
+    fn test_verify_token_without_exp_never_expires() {
+        let secret = b"test-secret-key-that-is-long-enough";
+        let token = create_hs256_token("user-123", secret);
+
+        let result = verify_token(&token, &leeway_config(secret, 0), &time_at(u64::MAX / 1000));
+
+        assert_eq!(result.expect("verified token"), "user-123");
+    }
+
+    #[test]
+    fn test_verify_token_extreme_claims_do_not_overflow() {
+        let secret = b"test-secret-key-that-is-long-enough";
+        let config = leeway_config(secret, u64::MAX);
+        let token = create_token_with_claims(
+            &serde_json::json!({ "sub": "user-123", "exp": u64::MAX, "nbf": u64::MAX }),
+            secret,
+        );
+
+        let result = verify_token(&token, &config, &time_at(NOW_SECS));
+
+        assert_eq!(result.expect("verified token"), "user-123");
+    }
+
+    #[test]
+    fn test_default_leeway_is_applied() {
+        let secret = b"test-secret-key-that-is-long-enough";
+        let config = JwtConfig::new_hs256(secret.to_vec()).expect("valid secret");
+        let token = create_token_with_claims(
+            &serde_json::json!({ "sub": "user-123", "exp": NOW_SECS - DEFAULT_EXP_LEEWAY_SECS }),
+            secret,
+        );
+
+        assert!(verify_token(&token, &config, &time_at(NOW_SECS)).is_ok());
+    }
+
     #[test]
     fn test_jwt_error_display() {
         assert_eq!(
             JwtError::InvalidSignature.to_string(),
             "invalid JWT signature"
         );
-        assert_eq!(JwtError::TokenExpired.to_string(), "JWT has expired");
+        assert_eq!(JwtError::Expired.to_string(), "JWT has expired");
+        assert_eq!(JwtError::NotYetValid.to_string(), "JWT is not yet valid");
         assert_eq!(JwtError::MalformedToken.to_string(), "malformed JWT");
         assert_eq!(
             JwtError::MissingSubClaim.to_string(),
             "missing 'sub' claim in JWT"
         );
+        assert_eq!(
+            JwtError::MissingClaim("aud".to_string()).to_string(),
+            "missing 'aud' claim in JWT"
+        );
+        assert_eq!(
+            JwtError::AudienceMismatch.to_string(),
+            "JWT audience does not match"
+        );
+        assert_eq!(
+            JwtError::IssuerMismatch.to_string(),
+            "JWT issuer does not match"
+        );
         assert_eq!(
             JwtError::InvalidKey("bad key".to_string()).to_string(),
             "invalid key: bad key"
@@ -261,8 +613,11 @@ mod tests {
 
         let config = JwtConfig::Hs256 {
             secret: secret.to_vec(),
+            expected_audience: None,
+            expected_issuer: None,
+            exp_leeway_secs: DEFAULT_EXP_LEEWAY_SECS,
         };
-        let result = verify_token(&token, &config);
+        let result = verify_token(&token, &config, &SystemTimeSource);
 
         assert!(result.is_err());
         assert!(matches!(result, Err(JwtError::MissingSubClaim)));
@@ -270,8 +625,13 @@ mod tests {
 
     #[test]
     fn test_verify_hs256_empty_secret() {
-        let config = JwtConfig::Hs256 { secret: vec![] };
-        let result = verify_token("some.jwt.token", &config);
+        let config = JwtConfig::Hs256 {
+            secret: vec![],
+            expected_audience: None,
+            expected_issuer: None,
+            exp_leeway_secs: DEFAULT_EXP_LEEWAY_SECS,
+        };
+        let result = verify_token("some.jwt.token", &config, &SystemTimeSource);
 
         assert!(result.is_err());
         match result {
@@ -291,10 +651,13 @@ mod tests {
 
         let config = JwtConfig::Hs256 {
             secret: secret.to_vec(),
+            expected_audience: None,
+            expected_issuer: None,
+            exp_leeway_secs: DEFAULT_EXP_LEEWAY_SECS,
         };
 
-        let result1 = verify_token(&token1, &config);
-        let result2 = verify_token(&token2, &config);
+        let result1 = verify_token(&token1, &config, &SystemTimeSource);
+        let result2 = verify_token(&token2, &config, &SystemTimeSource);
 
         assert!(result1.is_ok());
         assert!(result2.is_ok());
@@ -12,7 +12,9 @@
 //! - All configured API keys are non-empty.
 
 pub mod app_config;
+pub mod config_registry;
 pub mod jwt;
 
-pub use app_config::{AppConfig, JwtConfig, JwtConfigError};
+pub use app_config::{AppConfig, DEFAULT_EXP_LEEWAY_SECS, JwtConfig, JwtConfigError};
+pub use config_registry::{AuthError, ConfigRegistry};
 pub use jwt::{JwtError, verify_token};
@@ -0,0 +1,143 @@
+//! Replay a deterministic simulation from the command line.
+//!
+//! Every `Simulator::run` logs a `cargo run --bin simulate -- ...` command
+//! that reproduces it. Running that command replays the same messages against
+//! a fresh database and prints the result, including malformation and error
+//! code coverage and the operation index of each invariant violation.
+//!
+//! Exits with status 1 if the simulation fails and 2 if the arguments are
+//! invalid.
+
+use server::simulation::{FaultConfig, Simulator, SimulatorConfig};
+use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
+
+/// Number of messages to send when `--messages` is not given.
+const DEFAULT_MESSAGE_COUNT: usize = 1000;
+
+const USAGE: &str = "\
+Usage: simulate --seed <SEED> [OPTIONS]
+
+Options:
+  --seed <SEED>                 Random seed for the simulation (required)
+  --messages <COUNT>            Number of messages to send [default: 1000]
+  --malformed-rate <RATE>       Probability of a malformed message
+  --fault-rate <RATE>           Probability of every kind of storage fault
+  --read-error-rate <RATE>      Probability of a read error
+  --write-error-rate <RATE>     Probability of a write error
+  --sync-error-rate <RATE>      Probability of a sync error
+  --corruption-rate <RATE>      Probability of page corruption on read
+  --partial-write-rate <RATE>   Probability of a partial write
+  --torn-write-rate <RATE>      Probability that a crash tears a committed update
+  --no-time-advance             Do not advance simulated time between messages
+  --require-malformation-coverage
+                                Fail if a malformation type was never rejected
+  --help                        Print this message";
+
+/// A parsed command line.
+struct Args {
+    config: SimulatorConfig,
+    message_count: usize,
+}
+
+/// Parse the command line, without the program name.
+///
+/// Returns `Ok(None)` if `--help` was given.
+fn parse_args(mut args: impl Iterator<Item = String>) -> Result<Option<Args>, String> {
+    let mut seed = None;
+    let mut message_count = DEFAULT_MESSAGE_COUNT;
+    let mut malformed_rate = 0.0;
+    let mut faults = FaultConfig::no_faults();
+    let mut advance_time = true;
+    let mut require_malformation_coverage = false;
+
+    while let Some(flag) = args.next() {
+        if flag == "--help" {
+            return Ok(None);
+        }
+        if flag == "--no-time-advance" {
+            advance_time = false;
+            continue;
+        }
+        if flag == "--require-malformation-coverage" {
+            require_malformation_coverage = true;
+            continue;
+        }
+        let value = args
+            .next()
+            .ok_or_else(|| format!("missing value for {flag}"))?;
+        match flag.as_str() {
+            "--seed" => seed = Some(parse_value(&flag, &value)?),
+            "--messages" => message_count = parse_value(&flag, &value)?,
+            "--malformed-rate" => malformed_rate = parse_rate(&flag, &value)?,
+            "--fault-rate" => faults = FaultConfig::uniform(parse_rate(&flag, &value)?),
+            "--read-error-rate" => faults.read_error_rate = parse_rate(&flag, &value)?,
+            "--write-error-rate" => faults.write_error_rate = parse_rate(&flag, &value)?,
+            "--sync-error-rate" => faults.sync_error_rate = parse_rate(&flag, &value)?,
+            "--corruption-rate" => faults.corruption_rate = parse_rate(&flag, &value)?,
+            "--partial-write-rate" => faults.partial_write_rate = parse_rate(&flag, &value)?,
+            "--torn-write-rate" => faults.torn_write_rate = parse_rate(&flag, &value)?,
+            _ => return Err(format!("unknown argument {flag}")),
+        }
+    }
+
+    let seed = seed.ok_or("--seed is required")?;
+    let mut config = SimulatorConfig::new(seed)
+        .with_malformed_rate(malformed_rate)
+        .with_fault_config(faults);
+    if !advance_time {
+        config = config.without_time_advance();
+    }
+    if require_malformation_coverage {
+        config = config.with_required_malformation_coverage();
+    }
+    Ok(Some(Args {
+        config,
+        message_count,
+    }))
+}
+
+/// Parse a flag's value.
+fn parse_value<T: std::str::FromStr>(flag: &str, value: &str) -> Result<T, String> {
+    value
+        .parse()
+        .map_err(|_| format!("invalid value for {flag}: {value}"))
+}
+
+/// Parse a probability between 0.0 and 1.0.
+fn parse_rate(flag: &str, value: &str) -> Result<f64, String> {
+    let rate: f64 = parse_value(flag, value)?;
+    if (0.0..=1.0).contains(&rate) {
+        Ok(rate)
+    } else {
+        Err(format!("{flag} must be between 0 and 1, got {value}"))
+    }
+}
+
+fn main() {
+    tracing_subscriber::registry()
+        .with(
+            tracing_subscriber::EnvFilter::try_from_default_env()
+                .unwrap_or_else(|_| "server=info".into()),
+        )
+        .with(tracing_subscriber::fmt::layer())
+        .init();
+
+    let args = match parse_args(std::env::args().skip(1)) {
+        Ok(Some(args)) => args,
+        Ok(None) => {
+            println!("{USAGE}");
+            return;
+        }
+        Err(e) => {
+            eprintln!("error: {e}\n\n{USAGE}");
+            std::process::exit(2);
+        }
+    };
+
+    let mut simulator = Simulator::new(args.config);
+    let result = simulator.run(args.message_count);
+    println!("{result}");
+    if !result.passed() {
+        std::process::exit(1);
+    }
+}
@@ -0,0 +1,872 @@
+//! Typed async client for the sync protocol.
+//!
+//! Wraps a WebSocket connection to an Enso server so Rust applications can
+//! insert, query, and subscribe without assembling `ClientMessage`s by hand.
+//!
+//! # Usage
+//!
+//! ```ignore
+//! use server::client::Client;
+//! use server::query::{Pattern, PatternElement, Query};
+//! use server::types::{AttributeId, EntityId, TripleValue};
+//!
+//! let client = Client::connect("ws://127.0.0.1:3000/ws", "my-app", Some(&token)).await?;
+//! client
+//!     .insert(EntityId([1; 16]), AttributeId([2; 16]), TripleValue::Number(42.0))
+//!     .await?;
+//!
+//! let query = Query::new().find("value").where_pattern(Pattern::new(
+//!     PatternElement::Entity(EntityId([1; 16])),
+//!     PatternElement::Field(AttributeId([2; 16])),
+//!     PatternElement::var("value"),
+//! ));
+//! let response = client.query(&query).await?;
+//!
+//! let mut subscription = client.subscribe(None).await?;
+//! while let Some(change) = subscription.next().await {
+//!     println!("{:?}", change);
+//! }
+//! ```
+//!
+//! # Design
+//!
+//! A background task owns the socket. `Client` methods send commands to the
+//! task over a channel and await a oneshot reply; the task assigns request IDs
+//! and matches each `ServerResponse` back to its caller by `request_id`.
+//!
+//! The task also owns the client's HLC clock, stamping every outgoing triple
+//! and merging the timestamps of incoming changes.
+//!
+//! The socket is tungstenite's client, the same WebSocket implementation the
+//! server runs on through axum. Its TLS features are not enabled, so only
+//! `ws://` URLs connect; put a TLS-terminating proxy in front for `wss://`.
+//!
+//! # Reconnection
+//!
+//! When the socket drops, in-flight requests fail with
+//! `ClientError::Disconnected` and the task reconnects with exponential
+//! backoff. After reconnecting it re-sends the `ConnectRequest` and any
+//! connection defaults, and re-subscribes every live subscription from the last
+//! HLC it delivered, so the last change before the drop may be delivered twice.
+//! Entity subscriptions are re-subscribed from the entity's current triples.
+//!
+//! # Invariants
+//!
+//! - Request IDs are unique per session
+//! - A subscription's stream ends only when the `Client` is dropped
+
+use std::collections::HashMap;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::task::{Context, Poll};
+use std::time::Duration;
+
+use futures::{SinkExt, Stream, StreamExt};
+use prost::Message as ProstMessage;
+use tokio::net::TcpStream;
+use tokio::sync::{mpsc, oneshot};
+use tokio_tungstenite::tungstenite::Message;
+use tokio_tungstenite::{MaybeTlsStream, WebSocketStream};
+
+use crate::proto;
+use crate::query::{Query, QueryBuildError};
+use crate::storage::{HlcClock, SystemTimeSource};
+use crate::types::query::QueryResponse;
+use crate::types::{
+    AttributeId, ChangeRecord, EntityId, HlcTimestamp, ProtoDeserializable, ProtoSerializable,
+    TripleValue,
+};
+
+/// First delay before reconnecting after the socket drops.
+const INITIAL_RECONNECT_DELAY: Duration = Duration::from_millis(100);
+
+/// Upper bound on the reconnect backoff.
+const MAX_RECONNECT_DELAY: Duration = Duration::from_secs(5);
+
+type Socket = WebSocketStream<MaybeTlsStream<TcpStream>>;
+
+type ResponseSender = oneshot::Sender<Result<proto::ServerResponse, ClientError>>;
+
+/// One batch of rows fetched from a server-side cursor.
+pub struct CursorPage {
+    /// The rows in this batch.
+    pub result: QueryResponse,
+    /// Whether the cursor has no more rows. The server closes exhausted cursors.
+    pub exhausted: bool,
+}
+
+/// Errors returned by `Client`.
+#[derive(Debug)]
+pub enum ClientError {
+    /// The WebSocket transport failed.
+    WebSocket(Box<tokio_tungstenite::tungstenite::Error>),
+    /// A server message could not be decoded.
+    Decode(prost::DecodeError),
+    /// A query could not be converted to a `QueryRequest`.
+    InvalidQuery(QueryBuildError),
+    /// The server rejected the request.
+    Status {
+        /// The `google.rpc.Code` returned by the server.
+        code: i32,
+        /// The server's error message.
+        message: String,
+    },
+    /// A change in a subscription update was malformed.
+    InvalidChange(String),
+    /// The connection dropped before the server responded.
+    Disconnected,
+}
+
+impl std::fmt::Display for ClientError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::WebSocket(e) => write!(f, "websocket error: {e}"),
+            Self::Decode(e) => write!(f, "failed to decode server message: {e}"),
+            Self::InvalidQuery(e) => write!(f, "invalid query: {e}"),
+            Self::Status { code, message } => {
+                write!(f, "server returned status {code}: {message}")
+            }
+            Self::InvalidChange(message) => write!(f, "invalid change record: {message}"),
+            Self::Disconnected => write!(f, "connection to server lost"),
+        }
+    }
+}
+
+impl std::error::Error for ClientError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::WebSocket(e) => Some(e.as_ref()),
+            Self::Decode(e) => Some(e),
+            Self::InvalidQuery(e) => Some(e),
+            Self::Status { .. } | Self::InvalidChange(_) | Self::Disconnected => None,
+        }
+    }
+}
+
+impl From<tokio_tungstenite::tungstenite::Error> for ClientError {
+    fn from(e: tokio_tungstenite::tungstenite::Error) -> Self {
+        Self::WebSocket(Box::new(e))
+    }
+}
+
+impl From<prost::DecodeError> for ClientError {
+    fn from(e: prost::DecodeError) -> Self {
+        Self::Decode(e)
+    }
+}
+
+impl From<QueryBuildError> for ClientError {
+    fn from(e: QueryBuildError) -> Self {
+        Self::InvalidQuery(e)
+    }
+}
+
+/// A command sent from `Client` to the connection task.
+enum Command {
+    /// Send a request and reply with the matching response. Boxed, as a
+    /// query request is far larger than the other commands.
+    Request {
+        payload: Box<proto::client_message::Payload>,
+        respond_to: ResponseSender,
+    },
+    /// Register a subscription's change channel, then send its `SubscribeRequest`.
+    Subscribe {
+        subscription_id: u32,
+        since_hlc: Option<HlcTimestamp>,
+        watch_entity_id: Option<EntityId>,
+        changes: mpsc::UnboundedSender<ChangeRecord>,
+        respond_to: ResponseSender,
+    },
+    /// Forget a subscription and tell the server, without waiting for a reply.
+    Unsubscribe { subscription_id: u32 },
+}
+
+/// An async client connected to one database on an Enso server.
+///
+/// Dropping the client closes the connection and ends all subscriptions.
+pub struct Client {
+    /// Channel to the connection task.
+    commands: mpsc::UnboundedSender<Command>,
+    /// Next client-assigned subscription ID.
+    next_subscription_id: AtomicU32,
+}
+
+impl Client {
+    /// Connect to `url` and open the database for `app_api_key`.
+    ///
+    /// `auth_token` is the JWT sent in the `ConnectRequest`, required when the
+    /// application is configured for token authentication. It is sent again on
+    /// every reconnect.
+    ///
+    /// # Post-conditions
+    /// - The server accepted the `ConnectRequest`
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the WebSocket handshake fails or the server rejects
+    /// the `ConnectRequest` (for example, an invalid `app_api_key` or a missing
+    /// or invalid `auth_token`).
+    pub async fn connect(
+        url: &str,
+        app_api_key: &str,
+        auth_token: Option<&str>,
+    ) -> Result<Self, ClientError> {
+        let mut task = ConnectionTask {
+            url: url.to_owned(),
+            app_api_key: app_api_key.to_owned(),
+            auth_token: auth_token.map(str::to_owned),
+            clock: HlcClock::new(rand::random(), SystemTimeSource),
+            next_request_id: 0,
+            pending: HashMap::new(),
+            subscriptions: HashMap::new(),
+            defaults: None,
+        };
+        let socket = task.open_session().await?;
+
+        let (commands, command_rx) = mpsc::unbounded_channel();
+        tokio::spawn(task.run(socket, command_rx));
+
+        Ok(Self {
+            commands,
+            next_subscription_id: AtomicU32::new(0),
+        })
+    }
+
+    /// Insert or overwrite a single triple.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the server rejects the triple or the connection drops
+    /// before it responds.
+    pub async fn insert(
+        &self,
+        entity_id: EntityId,
+        attribute_id: AttributeId,
+        value: TripleValue,
+    ) -> Result<(), ClientError> {
+        let triple = proto::Triple {
+            entity_id: Some(entity_id.0.to_vec()),
+            attribute_id: Some(attribute_id.0.to_vec()),
+            value: value.to_proto(),
+            // Stamped by the connection task's clock
+            hlc: None,
+            attribute_name: None,
+        };
+        let payload =
+            proto::client_message::Payload::TripleUpdateRequest(proto::TripleUpdateRequest {
+                triples: vec![triple],
+                mode: proto::WriteMode::Upsert.into(),
+                expected_versions: vec![],
+            });
+        self.request(payload).await?;
+        Ok(())
+    }
+
+    /// Run a query and return its columnar results.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the query cannot be sent (it has filters, or fails
+    /// `Query::validate`), the server rejects it, or the connection drops
+    /// before it responds.
+    pub async fn query(&self, query: &Query) -> Result<QueryResponse, ClientError> {
+        let request = proto::QueryRequest::try_from(query)?;
+        let response = self
+            .request(proto::client_message::Payload::Query(request))
+            .await?;
+        Ok(QueryResponse {
+            columns: response.columns,
+            rows: response.rows,
+        })
+    }
+
+    /// Check whether a triple exists, without transferring its value.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the server rejects the request or the connection
+    /// drops before it responds.
+    pub async fn contains(
+        &self,
+        entity_id: EntityId,
+        attribute_id: AttributeId,
+    ) -> Result<bool, ClientError> {
+        let payload = proto::client_message::Payload::Contains(proto::ContainsRequest {
+            entity_id: Some(entity_id.0.to_vec()),
+            attribute_id: Some(attribute_id.0.to_vec()),
+        });
+        let response = self.request(payload).await?;
+        Ok(response.exists.unwrap_or(false))
+    }
+
+    /// Delete a single triple.
+    ///
+    /// Returns whether a live triple was stored and has been deleted.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the server rejects the request or the connection
+    /// drops before it responds.
+    pub async fn delete(
+        &self,
+        entity_id: EntityId,
+        attribute_id: AttributeId,
+    ) -> Result<bool, ClientError> {
+        let payload = proto::client_message::Payload::Delete(proto::DeleteRequest {
+            keys: vec![proto::TripleKey {
+                entity_id: Some(entity_id.0.to_vec()),
+                attribute_id: Some(attribute_id.0.to_vec()),
+            }],
+        });
+        let response = self.request(payload).await?;
+        Ok(response
+            .delete_results
+            .first()
+            .and_then(|result| result.status.as_ref())
+            .is_some_and(|status| status.code == proto::google::rpc::Code::Ok as i32))
+    }
+
+    /// Open a server-side cursor over a query's results.
+    ///
+    /// The cursor outlives this connection: after a reconnect, keep fetching
+    /// with the same ID until the cursor is exhausted.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the query cannot be sent, the server rejects it, or
+    /// the connection drops before it responds.
+    pub async fn open_cursor(&self, query: &Query) -> Result<u64, ClientError> {
+        let payload = proto::client_message::Payload::OpenCursor(proto::OpenCursorRequest {
+            query: Some(proto::QueryRequest::try_from(query)?),
+        });
+        let response = self.request(payload).await?;
+        response.cursor_id.ok_or_else(|| ClientError::Status {
+            code: proto::google::rpc::Code::Internal.into(),
+            message: "response is missing cursor_id".to_owned(),
+        })
+    }
+
+    /// Fetch the next batch of up to `max_rows` rows from a cursor.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the cursor was closed or expired, or the connection
+    /// drops before the server responds.
+    pub async fn fetch_cursor(
+        &self,
+        cursor_id: u64,
+        max_rows: u32,
+    ) -> Result<CursorPage, ClientError> {
+        let payload = proto::client_message::Payload::FetchCursor(proto::FetchCursorRequest {
+            cursor_id,
+            max_rows,
+        });
+        let response = self.request(payload).await?;
+        Ok(CursorPage {
+            result: QueryResponse {
+                columns: response.columns,
+                rows: response.rows,
+            },
+            exhausted: response.cursor_exhausted.unwrap_or(false),
+        })
+    }
+
+    /// Close a cursor before it is exhausted.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the cursor was already closed or expired, or the
+    /// connection drops before the server responds.
+    pub async fn close_cursor(&self, cursor_id: u64) -> Result<(), ClientError> {
+        let payload =
+            proto::client_message::Payload::CloseCursor(proto::CloseCursorRequest { cursor_id });
+        self.request(payload).await?;
+        Ok(())
+    }
+
+    /// Replace the default triples attached to every entity this client writes.
+    ///
+    /// Pass an empty list to clear them. The defaults are restored after a
+    /// reconnect.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the server rejects a default (for example, two
+    /// defaults for one attribute) or the connection drops before it responds.
+    pub async fn set_defaults(
+        &self,
+        defaults: Vec<(AttributeId, TripleValue)>,
+    ) -> Result<(), ClientError> {
+        let defaults = defaults
+            .into_iter()
+            .map(|(attribute_id, value)| proto::DefaultTriple {
+                attribute_id: Some(attribute_id.0.to_vec()),
+                value: value.to_proto(),
+            })
+            .collect();
+        let payload =
+            proto::client_message::Payload::SetDefaults(proto::SetDefaultsRequest { defaults });
+        self.request(payload).await?;
+        Ok(())
+    }
+
+    /// Subscribe to changes, optionally replaying history since `since_hlc`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the server rejects the subscription or the connection
+    /// drops before it responds.
+    pub async fn subscribe(
+        &self,
+        since_hlc: Option<HlcTimestamp>,
+    ) -> Result<Subscription, ClientError> {
+        self.open_subscription(since_hlc, None).await
+    }
+
+    /// Subscribe to one entity's changes.
+    ///
+    /// The stream starts with an `Insert` for each of the entity's current
+    /// triples, then carries every later change to the entity, including
+    /// deletes. After reconnecting, the current triples are delivered again.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the server rejects the subscription or the connection
+    /// drops before it responds.
+    pub async fn watch_entity(&self, entity_id: EntityId) -> Result<Subscription, ClientError> {
+        self.open_subscription(None, Some(entity_id)).await
+    }
+
+    /// Register a subscription with the connection task and wait for the server.
+    #[allow(clippy::disallowed_methods)] // The subscription needs its own command sender
+    async fn open_subscription(
+        &self,
+        since_hlc: Option<HlcTimestamp>,
+        watch_entity_id: Option<EntityId>,
+    ) -> Result<Subscription, ClientError> {
+        let subscription_id = self.next_subscription_id.fetch_add(1, Ordering::Relaxed);
+        let (changes, change_rx) = mpsc::unbounded_channel();
+        let (respond_to, response_rx) = oneshot::channel();
+
+        self.send(Command::Subscribe {
+            subscription_id,
+            since_hlc,
+            watch_entity_id,
+            changes,
+            respond_to,
+        })?;
+        check_status(response_rx.await.map_err(|_| ClientError::Disconnected)??)?;
+
+        Ok(Subscription {
+            id: subscription_id,
+            changes: change_rx,
+            commands: self.commands.clone(),
+        })
+    }
+
+    /// Send a request and wait for a successful response.
+    async fn request(
+        &self,
+        payload: proto::client_message::Payload,
+    ) -> Result<proto::ServerResponse, ClientError> {
+        let (respond_to, response_rx) = oneshot::channel();
+        self.send(Command::Request {
+            payload: Box::new(payload),
+            respond_to,
+        })?;
+        check_status(response_rx.await.map_err(|_| ClientError::Disconnected)??)
+    }
+
+    /// Hand a command to the connection task.
+    fn send(&self, command: Command) -> Result<(), ClientError> {
+        self.commands
+            .send(command)
+            .map_err(|_| ClientError::Disconnected)
+    }
+}
+
+/// A stream of changes for one subscription.
+///
+/// Dropping the subscription unsubscribes on the server.
+pub struct Subscription {
+    /// Client-assigned subscription ID.
+    id: u32,
+    /// Changes delivered by the connection task.
+    changes: mpsc::UnboundedReceiver<ChangeRecord>,
+    /// Channel to the connection task, for unsubscribing on drop.
+    commands: mpsc::UnboundedSender<Command>,
+}
+
+impl Subscription {
+    /// The client-assigned subscription ID.
+    #[must_use]
+    pub const fn id(&self) -> u32 {
+        self.id
+    }
+
+    /// Wait for the next change.
+    ///
+    /// Returns `None` once the client has been dropped.
+    pub async fn next(&mut self) -> Option<ChangeRecord> {
+        self.changes.recv().await
+    }
+}
+
+impl Stream for Subscription {
+    type Item = ChangeRecord;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.get_mut().changes.poll_recv(cx)
+    }
+}
+
+impl Drop for Subscription {
+    fn drop(&mut self) {
+        // The task may already be gone if the client was dropped first
+        let _ = self.commands.send(Command::Unsubscribe {
+            subscription_id: self.id,
+        });
+    }
+}
+
+/// A subscription tracked by the connection task.
+struct ActiveSubscription {
+    /// Where to deliver changes.
+    changes: mpsc::UnboundedSender<ChangeRecord>,
+    /// HLC of the last delivered change, used to resume after reconnecting.
+    last_hlc: Option<HlcTimestamp>,
+    /// The entity watched, if this is an entity subscription.
+    watch_entity_id: Option<EntityId>,
+}
+
+/// Why `ConnectionTask::serve` returned.
+enum SessionEnd {
+    /// The socket failed or the server closed it.
+    Disconnected,
+    /// The `Client` was dropped.
+    ClientDropped,
+}
+
+/// Background task that owns the socket.
+struct ConnectionTask {
+    url: String,
+    app_api_key: String,
+    /// JWT sent with every `ConnectRequest`.
+    auth_token: Option<String>,
+    /// Clock used to stamp outgoing triples.
+    clock: HlcClock<SystemTimeSource>,
+    next_request_id: u32,
+    /// Requests awaiting a response, by request ID.
+    pending: HashMap<u32, ResponseSender>,
+    subscriptions: HashMap<u32, ActiveSubscription>,
+    /// Last `SetDefaultsRequest` sent, re-sent after reconnecting.
+    defaults: Option<proto::SetDefaultsRequest>,
+}
+
+impl ConnectionTask {
+    /// Drive the connection until the client is dropped, reconnecting as needed.
+    async fn run(mut self, mut socket: Socket, mut commands: mpsc::UnboundedReceiver<Command>) {
+        loop {
+            match self.serve(&mut socket, &mut commands).await {
+                SessionEnd::ClientDropped => {
+                    let _ = socket.close(None).await;
+                    return;
+                }
+                SessionEnd::Disconnected => {
+                    for (_, respond_to) in self.pending.drain() {
+                        let _ = respond_to.send(Err(ClientError::Disconnected));
+                    }
+                    match self.reconnect(&mut commands).await {
+                        Some(new_socket) => socket = new_socket,
+                        None => return,
+                    }
+                }
+            }
+        }
+    }
+
+    /// Relay commands and server messages until the session ends.
+    async fn serve(
+        &mut self,
+        socket: &mut Socket,
+        commands: &mut mpsc::UnboundedReceiver<Command>,
+    ) -> SessionEnd {
+        loop {
+            tokio::select! {
+                frame = socket.next() => {
+                    let data = match frame {
+                        Some(Ok(Message::Binary(data))) => data,
+                        Some(Ok(Message::Close(_)) | Err(_)) | None => return SessionEnd::Disconnected,
+                        // Pings are answered by tungstenite; text frames are not part of the protocol
+                        Some(Ok(_)) => continue,
+                    };
+                    match proto::ServerMessage::decode(data.as_ref()) {
+                        Ok(message) => self.handle_server_message(message),
+                        Err(e) => tracing::warn!("failed to decode ServerMessage: {e}"),
+                    }
+                }
+                command = commands.recv() => {
+                    let Some(command) = command else {
+                        return SessionEnd::ClientDropped;
+                    };
+                    if self.handle_command(socket, command).await.is_err() {
+                        return SessionEnd::Disconnected;
+                    }
+                }
+            }
+        }
+    }
+
+    /// Send a command's request over the socket.
+    async fn handle_command(
+        &mut self,
+        socket: &mut Socket,
+        command: Command,
+    ) -> Result<(), ClientError> {
+        let (payload, respond_to) = match command {
+            Command::Request {
+                payload,
+                respond_to,
+            } => {
+                if let proto::client_message::Payload::SetDefaults(request) = &*payload {
+                    #[allow(clippy::disallowed_methods)] // Kept to restore after reconnecting
+                    let request = request.clone();
+                    self.defaults = Some(request);
+                }
+                (*payload, Some(respond_to))
+            }
+            Command::Subscribe {
+                subscription_id,
+                since_hlc,
+                watch_entity_id,
+                changes,
+                respond_to,
+            } => {
+                // Register first so backfill sent before the response is not lost
+                self.subscriptions.insert(
+                    subscription_id,
+                    ActiveSubscription {
+                        changes,
+                        last_hlc: since_hlc,
+                        watch_entity_id,
+                    },
+                );
+                (
+                    subscribe_payload(subscription_id, since_hlc, watch_entity_id),
+                    Some(respond_to),
+                )
+            }
+            Command::Unsubscribe { subscription_id } => {
+                self.subscriptions.remove(&subscription_id);
+                let payload =
+                    proto::client_message::Payload::Unsubscribe(proto::UnsubscribeRequest {
+                        subscription_id,
+                    });
+                (payload, None)
+            }
+        };
+
+        let request_id = self.send_request(socket, payload).await?;
+        if let Some(respond_to) = respond_to {
+            self.pending.insert(request_id, respond_to);
+        }
+        Ok(())
+    }
+
+    /// Assign a request ID, stamp any unstamped triples, and send the message.
+    ///
+    /// # Returns
+    /// The request ID assigned to the message.
+    async fn send_request(
+        &mut self,
+        socket: &mut Socket,
+        mut payload: proto::client_message::Payload,
+    ) -> Result<u32, ClientError> {
+        if let proto::client_message::Payload::TripleUpdateRequest(request) = &mut payload {
+            for triple in &mut request.triples {
+                if triple.hlc.is_none() {
+                    triple.hlc = Some(self.clock.tick().to_proto());
+                }
+            }
+        }
+
+        let request_id = self.next_request_id;
+        self.next_request_id = self.next_request_id.wrapping_add(1);
+        let message = proto::ClientMessage {
+            request_id: Some(request_id),
+            payload: Some(payload),
+        };
+        socket
+            .send(Message::Binary(message.encode_to_vec().into()))
+            .await?;
+        Ok(request_id)
+    }
+
+    /// Route a server message to the waiting request or subscription.
+    fn handle_server_message(&mut self, message: proto::ServerMessage) {
+        match message.payload {
+            Some(proto::server_message::Payload::Response(response)) => {
+                // Responses to re-subscribes and unsubscribes have no waiter
+                if let Some(request_id) = response.request_id
+                    && let Some(respond_to) = self.pending.remove(&request_id)
+                {
+                    let _ = respond_to.send(Ok(response));
+                }
+            }
+            Some(proto::server_message::Payload::SubscriptionUpdate(update)) => {
+                self.deliver_update(update);
+            }
+            None => {}
+        }
+    }
+
+    /// Decode a subscription update and forward each change to its stream.
+    fn deliver_update(&mut self, update: proto::SubscriptionUpdate) {
+        let Some(subscription) = self.subscriptions.get_mut(&update.subscription_id) else {
+            return;
+        };
+        for proto_change in update.changes {
+            let change = match ChangeRecord::from_proto(proto_change) {
+                Ok(change) => change,
+                Err(e) => {
+                    tracing::warn!("{}", ClientError::InvalidChange(e));
+                    continue;
+                }
+            };
+            // Keep our clock ahead of every change we have observed
+            let _ = self.clock.receive(change.hlc);
+            subscription.last_hlc = Some(change.hlc);
+            if subscription.changes.send(change).is_err() {
+                // The Subscription was dropped; its Unsubscribe command is on the way
+                return;
+            }
+        }
+    }
+
+    /// Reconnect with exponential backoff, then restore subscriptions.
+    ///
+    /// Commands that arrive while disconnected fail with `ClientError::Disconnected`.
+    ///
+    /// # Returns
+    /// The new socket, or `None` if the client was dropped while reconnecting.
+    async fn reconnect(
+        &mut self,
+        commands: &mut mpsc::UnboundedReceiver<Command>,
+    ) -> Option<Socket> {
+        let mut delay = INITIAL_RECONNECT_DELAY;
+        loop {
+            let deadline = tokio::time::Instant::now() + delay;
+            loop {
+                tokio::select! {
+                    () = tokio::time::sleep_until(deadline) => break,
+                    command = commands.recv() => match command? {
+                        Command::Request { respond_to, .. } | Command::Subscribe { respond_to, .. } => {
+                            let _ = respond_to.send(Err(ClientError::Disconnected));
+                        }
+                        Command::Unsubscribe { subscription_id } => {
+                            self.subscriptions.remove(&subscription_id);
+                        }
+                    },
+                }
+            }
+
+            match self.open_session().await {
+                Ok(mut socket) => {
+                    if self.resubscribe(&mut socket).await.is_ok() {
+                        tracing::info!("Reconnected to {}", self.url);
+                        return Some(socket);
+                    }
+                }
+                Err(e) => tracing::warn!("Reconnect to {} failed: {e}", self.url),
+            }
+            delay = (delay * 2).min(MAX_RECONNECT_DELAY);
+        }
+    }
+
+    /// Re-send the connection defaults and a `SubscribeRequest` for every live
+    /// subscription.
+    #[allow(clippy::disallowed_methods)] // The defaults are re-sent on every reconnect
+    async fn resubscribe(&mut self, socket: &mut Socket) -> Result<(), ClientError> {
+        if let Some(defaults) = self.defaults.clone() {
+            self.send_request(
+                socket,
+                proto::client_message::Payload::SetDefaults(defaults),
+            )
+            .await?;
+        }
+        let resumes: Vec<proto::client_message::Payload> = self
+            .subscriptions
+            .iter()
+            .map(|(subscription_id, subscription)| {
+                // An entity subscription is backfilled with current triples instead
+                let since_hlc = subscription
+                    .last_hlc
+                    .filter(|_| subscription.watch_entity_id.is_none());
+                subscribe_payload(*subscription_id, since_hlc, subscription.watch_entity_id)
+            })
+            .collect();
+        for payload in resumes {
+            self.send_request(socket, payload).await?;
+        }
+        Ok(())
+    }
+
+    /// Open a socket and complete the `ConnectRequest` handshake.
+    #[allow(clippy::disallowed_methods)] // The key and token are re-sent on every reconnect
+    async fn open_session(&mut self) -> Result<Socket, ClientError> {
+        let (mut socket, _) = tokio_tungstenite::connect_async(self.url.as_str()).await?;
+        let connect = proto::client_message::Payload::Connect(proto::ConnectRequest {
+            app_api_key: self.app_api_key.clone(),
+            auth_token: self.auth_token.clone(),
+        });
+        let request_id = self.send_request(&mut socket, connect).await?;
+
+        loop {
+            let data = match socket.next().await {
+                Some(Ok(Message::Binary(data))) => data,
+                Some(Ok(Message::Close(_))) | None => return Err(ClientError::Disconnected),
+                Some(Err(e)) => return Err(e.into()),
+                Some(Ok(_)) => continue,
+            };
+            if let Some(proto::server_message::Payload::Response(response)) =
+                proto::ServerMessage::decode(data.as_ref())?.payload
+                && response.request_id == Some(request_id)
+            {
+                check_status(response)?;
+                return Ok(socket);
+            }
+        }
+    }
+}
+
+/// Build a `SubscribeRequest` payload.
+fn subscribe_payload(
+    subscription_id: u32,
+    since_hlc: Option<HlcTimestamp>,
+    watch_entity_id: Option<EntityId>,
+) -> proto::client_message::Payload {
+    proto::client_message::Payload::Subscribe(proto::SubscribeRequest {
+        subscription_id,
+        since_hlc: since_hlc.map(ProtoSerializable::to_proto),
+        query: None,
+        watch_entity_id: watch_entity_id.map(|entity_id| entity_id.0.to_vec()),
+        until_hlc: None,
+        include_old_values: false,
+        entity_id: None,
+        attribute_id: None,
+    })
+}
+
+/// Turn a non-OK response status into `ClientError::Status`.
+#[allow(clippy::disallowed_methods)] // Copy the message out of the borrowed status
+fn check_status(response: proto::ServerResponse) -> Result<proto::ServerResponse, ClientError> {
+    match &response.status {
+        Some(status) if status.code != proto::google::rpc::Code::Ok as i32 => {
+            Err(ClientError::Status {
+                code: status.code,
+                message: status.message.clone(),
+            })
+        }
+        _ => Ok(response),
+    }
+}
@@ -1,22 +1,38 @@
-use std::cmp::Ordering;
+use std::collections::{HashMap, HashSet};
 use std::sync::atomic::{AtomicU64, Ordering as AtomicOrdering};
-use std::sync::{Arc, RwLock};
+use std::sync::{Arc, LockResult, RwLock, RwLockReadGuard, RwLockWriteGuard};
+use std::time::{Duration, Instant};
 
 use crate::{
+    auth::{AppConfig, ConfigRegistry},
+    constants::{
+        DEFAULT_TRANSACTION_IDLE_TIMEOUT, MAX_BULK_LOAD_TRIPLES, MAX_CURSOR_FETCH_ROWS,
+        MAX_SCHEMA_ATTRIBUTES, MAX_TRANSACTION_DURATION, MAX_UPDATE_CONFLICT_RETRIES,
+    },
     database_registry::{ApiKeyValidationError, DatabaseRegistry, validate_api_key},
     proto,
-    query::{Query, QueryEngine},
-    storage::{Database, DatabaseError, HlcClock, LogRecord, SystemTimeSource},
+    query::{
+        CursorError, CursorId, CursorTable, Query, QueryEngine, QueryError, QueryResult,
+        encode_arrow_ipc,
+    },
+    storage::{CommitOutcome, Database, DatabaseError, LogRecord, OptimisticTransaction},
     subscription::{
-        ClientSubscriptions, Subscription, convert_log_records_to_changes, create_error_response,
-        create_failed_precondition_response, create_internal_error_response, create_ok_response,
-        create_subscription_update,
+        ClientSubscriptions, LiveQuery, QueryWatch, Subscription, convert_log_records_to_changes,
+        create_error_response, create_failed_precondition_response, create_internal_error_response,
+        create_ok_response, create_subscription_error_response,
     },
     types::{
-        AttributeId, ConnectionId, EntityId, HlcTimestamp, ProtoDeserializable, ProtoSerializable,
-        TripleValue,
+        AttributeId, ChangeNotification, ChangeType, ConnectionId, EntityId, HlcTimestamp,
+        PendingTripleData, ProtoDeserializable, ProtoSerializable, TripleRecord, TripleValue,
+        ValueLimits,
+        bulk_load_request::BulkLoadRequest,
         client_message::{ClientMessage, ClientMessagePayload},
-        triple_update_request::TripleUpdateRequest,
+        contains_request::ContainsRequest,
+        delete_request::DeleteRequest,
+        error_detail::add_error_reason,
+        pending_triple::validate_proto_id,
+        set_defaults_request::{DefaultTriple, SetDefaultsRequest},
+        triple_update_request::{TripleUpdateRequest, WriteMode},
     },
 };
 
@@ -40,6 +56,36 @@ pub enum ConnectionState {
     },
 }
 
+/// State of a connection's explicit transaction.
+///
+/// # Invariants
+///
+/// - Only `Open` holds a pinned snapshot; leaving it always commits or aborts
+///   the optimistic transaction.
+enum TransactionState {
+    /// No transaction is open; each update commits on its own.
+    Idle,
+    /// A `BeginTxnRequest` opened a transaction that buffers updates.
+    Open(Box<OpenTransaction>),
+    /// The server aborted the open transaction, for the given reason. Updates
+    /// fail until the client commits or aborts it.
+    Aborted(String),
+}
+
+/// An explicit transaction spanning several messages.
+struct OpenTransaction {
+    /// The buffered writes, validated when committed.
+    txn: OptimisticTransaction,
+    /// The key of each buffered write, in order, for the commit response.
+    writes: Vec<TripleKey>,
+    /// When the transaction began.
+    began_at: Instant,
+    /// When the connection last sent a message.
+    active_at: Instant,
+    /// How long the transaction may go without a message.
+    idle_timeout: Duration,
+}
+
 /// Global counter for generating unique connection IDs.
 static NEXT_CONNECTION_ID: AtomicU64 = AtomicU64::new(1);
 
@@ -59,6 +105,16 @@ static NEXT_CONNECTION_ID: AtomicU64 = AtomicU64::new(1);
 ///
 /// Each connection has a unique ID that is included in change notifications,
 /// allowing subscribers to filter out their own writes.
+///
+/// # Explicit Transactions
+///
+/// A `BeginTxnRequest` opens an optimistic transaction that buffers the
+/// connection's later updates until a `CommitTxnRequest` validates and writes
+/// them together. Building it needs no lock between messages, so an open
+/// transaction never holds up other writers; it only pins a snapshot, which
+/// holds back garbage collection. A transaction idle past its timeout, or open
+/// past `max_transaction_duration`, is therefore aborted, and one still open
+/// when the connection drops is aborted with it.
 pub struct ClientConnection {
     /// Database connection. `None` until `ConnectRequest` is processed.
     database: Option<Arc<RwLock<Database>>>,
@@ -71,6 +127,29 @@ pub struct ClientConnection {
     /// Registry for looking up databases by `app_api_key`.
     /// `None` for test connections that don't use the registry.
     registry: Option<Arc<DatabaseRegistry>>,
+    /// Limits on values accepted in update requests.
+    value_limits: ValueLimits,
+    /// Cursors of the connected database, shared with its other connections.
+    /// `None` until `ConnectRequest` is processed.
+    cursors: Option<Arc<CursorTable>>,
+    /// Default triples attached to every entity this connection writes.
+    /// Dropped with the connection.
+    defaults: Vec<DefaultTriple>,
+    /// Microseconds the current request has waited for the database lock.
+    lock_wait_us: AtomicU64,
+    /// The explicit transaction, if one is open.
+    transaction: TransactionState,
+    /// Triples of the bulk load in progress, buffered until its last chunk.
+    /// Dropped with the connection.
+    bulk_load: Vec<PendingTripleData>,
+    /// Longest an explicit transaction may stay open.
+    max_transaction_duration: Duration,
+    /// Registry that authenticates `ConnectRequest`s and resolves each app's
+    /// configuration. `None` leaves every app unrestricted.
+    config_registry: Option<Arc<ConfigRegistry>>,
+    /// Configuration of the app this connection is bound to, which scopes
+    /// the attributes it may access. `None` allows every attribute.
+    app_config: Option<Arc<AppConfig>>,
 }
 
 impl ClientConnection {
@@ -91,6 +170,15 @@ impl ClientConnection {
             subscriptions: ClientSubscriptions::new(),
             state: ConnectionState::AwaitingConnect,
             registry: Some(registry),
+            value_limits: ValueLimits::default(),
+            cursors: None,
+            defaults: Vec::new(),
+            lock_wait_us: AtomicU64::new(0),
+            transaction: TransactionState::Idle,
+            bulk_load: Vec::new(),
+            max_transaction_duration: MAX_TRANSACTION_DURATION,
+            config_registry: None,
+            app_config: None,
         }
     }
 
@@ -108,6 +196,15 @@ impl ClientConnection {
                 app_api_key: "test".to_string(),
             },
             registry: None,
+            value_limits: ValueLimits::default(),
+            cursors: Some(Arc::new(CursorTable::default())),
+            defaults: Vec::new(),
+            lock_wait_us: AtomicU64::new(0),
+            transaction: TransactionState::Idle,
+            bulk_load: Vec::new(),
+            max_transaction_duration: MAX_TRANSACTION_DURATION,
+            config_registry: None,
+            app_config: None,
         }
     }
 
@@ -127,9 +224,57 @@ impl ClientConnection {
                 app_api_key: "test".to_string(),
             },
             registry: None,
+            value_limits: ValueLimits::default(),
+            cursors: Some(Arc::new(CursorTable::default())),
+            defaults: Vec::new(),
+            lock_wait_us: AtomicU64::new(0),
+            transaction: TransactionState::Idle,
+            bulk_load: Vec::new(),
+            max_transaction_duration: MAX_TRANSACTION_DURATION,
+            config_registry: None,
+            app_config: None,
         }
     }
 
+    /// Set the limits on values accepted in update requests.
+    #[must_use]
+    pub const fn with_value_limits(mut self, value_limits: ValueLimits) -> Self {
+        self.value_limits = value_limits;
+        self
+    }
+
+    /// Set the maximum number of subscriptions the connection may hold.
+    #[must_use]
+    pub fn with_max_subscriptions(mut self, max_subscriptions: usize) -> Self {
+        let subscriptions = std::mem::take(&mut self.subscriptions);
+        self.subscriptions = subscriptions.with_max_subscriptions(max_subscriptions);
+        self
+    }
+
+    /// Set the longest an explicit transaction may stay open before the
+    /// server aborts it. Idle timeouts are capped to it.
+    #[must_use]
+    pub const fn with_max_transaction_duration(mut self, max_duration: Duration) -> Self {
+        self.max_transaction_duration = max_duration;
+        self
+    }
+
+    /// Set the registry that authenticates this connection's
+    /// `ConnectRequest` and resolves its app's configuration.
+    #[must_use]
+    pub fn with_config_registry(mut self, config_registry: Arc<ConfigRegistry>) -> Self {
+        self.config_registry = Some(config_registry);
+        self
+    }
+
+    /// Bind the connection to an app's configuration, bypassing the
+    /// `ConnectRequest` flow. Use this for test connections.
+    #[must_use]
+    pub fn with_app_config(mut self, app_config: Arc<AppConfig>) -> Self {
+        self.app_config = Some(app_config);
+        self
+    }
+
     /// Get the unique identifier for this connection.
     ///
     /// This can be used to filter out notifications from this connection's own writes.
@@ -144,6 +289,18 @@ impl ClientConnection {
         matches!(self.state, ConnectionState::Connected { .. })
     }
 
+    /// Check whether the shared database lock is poisoned.
+    ///
+    /// A handler that panicked while holding the lock poisons it. Every later
+    /// request on this connection then fails with an `Internal` error, so the
+    /// transport should close the connection.
+    #[must_use]
+    pub fn is_database_poisoned(&self) -> bool {
+        self.database
+            .as_ref()
+            .is_some_and(|database| database.is_poisoned())
+    }
+
     /// Get a clone of the shared database reference.
     ///
     /// Returns `None` if the connection is not yet established.
@@ -169,7 +326,9 @@ impl ClientConnection {
         &self,
     ) -> Result<crate::storage::FilteredChangeReceiver, DatabaseError> {
         let db_arc = self.database.as_ref().ok_or(DatabaseError::NotConnected)?;
-        let db = db_arc.read().map_err(|_| DatabaseError::LockPoisoned)?;
+        let db = self
+            .read_database(db_arc)
+            .map_err(|_| DatabaseError::LockPoisoned)?;
         Ok(db.subscribe_to_changes(self.connection_id))
     }
 
@@ -183,12 +342,40 @@ impl ClientConnection {
     /// or if reading changes fails.
     pub fn get_changes_since(&self, since: HlcTimestamp) -> Result<Vec<LogRecord>, DatabaseError> {
         let db_arc = self.database.as_ref().ok_or(DatabaseError::NotConnected)?;
-        let mut db = db_arc.write().map_err(|_| DatabaseError::LockPoisoned)?;
+        let mut db = self
+            .write_database(db_arc)
+            .map_err(|_| DatabaseError::LockPoisoned)?;
         db.changes_since(since)
     }
 
+    /// Get the committed changes with an HLC from `from` to `to`, inclusive.
+    ///
+    /// This is used for subscription backfill when a client subscribes with both
+    /// a `since_hlc` and an `until_hlc`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the connection is not established, the database lock is poisoned,
+    /// or if reading changes fails.
+    pub fn get_changes_between(
+        &self,
+        from: HlcTimestamp,
+        to: HlcTimestamp,
+    ) -> Result<Vec<LogRecord>, DatabaseError> {
+        let db_arc = self.database.as_ref().ok_or(DatabaseError::NotConnected)?;
+        let mut db = self
+            .write_database(db_arc)
+            .map_err(|_| DatabaseError::LockPoisoned)?;
+        db.changes_between(from, to)
+    }
+
     /// Handle a subscribe request.
     ///
+    /// A request with a `query` subscribes to invalidations of that query
+    /// rather than to raw changes; one with a `watch_entity_id` subscribes to
+    /// the changes to that entity. An `entity_id` and/or `attribute_id`
+    /// narrows a raw subscription, history included, to matching triples.
+    ///
     /// Returns a list of messages to send to the client:
     /// - On success: optionally a subscription update with historical changes, then an OK response
     /// - On error: an error response
@@ -198,43 +385,204 @@ impl ClientConnection {
         req: &proto::SubscribeRequest,
     ) -> Vec<proto::ServerMessage> {
         let subscription_id = req.subscription_id;
+        if let Some(entity_id) = &req.watch_entity_id {
+            if req.query.is_some()
+                || req.since_hlc.is_some()
+                || req.until_hlc.is_some()
+                || req.entity_id.is_some()
+                || req.attribute_id.is_some()
+            {
+                return vec![create_error_response(
+                    request_id,
+                    "watch_entity_id cannot be combined with query, since_hlc, until_hlc, \
+                     entity_id or attribute_id",
+                )];
+            }
+            return self.handle_watch_entity(
+                request_id,
+                subscription_id,
+                entity_id,
+                req.include_old_values,
+            );
+        }
+        if req.until_hlc.is_some() && req.since_hlc.is_none() {
+            return vec![create_error_response(
+                request_id,
+                "until_hlc requires since_hlc",
+            )];
+        }
+        let (entity_id, attribute_id) = match subscription_filter_ids(req) {
+            Ok(ids) => ids,
+            Err(e) => return vec![create_error_response(request_id, &e)],
+        };
         // HlcTimestamp::from_proto is infallible - always returns Ok
-        let since_hlc = req.since_hlc.as_ref().map(|hlc| {
+        let to_hlc = |hlc: &proto::HlcTimestamp| {
             HlcTimestamp::from_proto(hlc)
                 .unwrap_or_else(|_| unreachable!("HLC conversion is infallible"))
-        });
+        };
+        let since_hlc = req.since_hlc.as_ref().map(to_hlc);
+        let until_hlc = req.until_hlc.as_ref().map(to_hlc);
 
         // Add the subscription
-        if let Err(e) = self.subscriptions.add(subscription_id, since_hlc) {
-            return vec![create_error_response(request_id, &format!("{e}"))];
+        let added = match &req.query {
+            Some(request) => match Query::from_proto(request) {
+                Ok(query) => self.subscriptions.add_query(
+                    subscription_id,
+                    since_hlc,
+                    QueryWatch::new(&query),
+                ),
+                Err(e) => return vec![create_error_response(request_id, &e)],
+            },
+            None if entity_id.is_none() && attribute_id.is_none() => {
+                self.subscriptions.add(subscription_id, since_hlc)
+            }
+            None => {
+                self.subscriptions
+                    .add_filtered(subscription_id, since_hlc, entity_id, attribute_id)
+            }
+        };
+        let added = added.and_then(|()| {
+            if req.include_old_values {
+                self.subscriptions.include_old_values(subscription_id)
+            } else {
+                Ok(())
+            }
+        });
+        if let Err(e) = added {
+            return vec![create_subscription_error_response(request_id, &e)];
         }
 
         let mut messages = Vec::new();
 
         // If since_hlc was provided, send historical changes
-        if let Some(hlc) = since_hlc {
-            if let Some(update_msg) = self.get_backfill_update(subscription_id, hlc) {
-                messages.push(update_msg);
-            }
+        if let Some(hlc) = since_hlc
+            && let Some(update_msg) = self.get_backfill_update(subscription_id, hlc, until_hlc)
+        {
+            messages.push(update_msg);
         }
 
         // Send success response
         messages.push(create_ok_response(request_id));
-        tracing::debug!("subscription {} registered", subscription_id);
+        tracing::debug!(
+            "subscription {} registered, {} active",
+            subscription_id,
+            self.subscriptions.len()
+        );
+
+        messages
+    }
+
+    /// Subscribe to one entity's changes, backfilled with its current triples.
+    ///
+    /// The backfill reads a snapshot taken after the subscription is
+    /// registered, so a change committed around subscribing may be delivered
+    /// both in the backfill and as a live change, but never missed.
+    #[allow(clippy::disallowed_methods)] // The ID bytes are validated into an EntityId
+    fn handle_watch_entity(
+        &mut self,
+        request_id: Option<u32>,
+        subscription_id: u32,
+        entity_id: &[u8],
+        include_old_values: bool,
+    ) -> Vec<proto::ServerMessage> {
+        let entity_id = match validate_proto_id(
+            Some(entity_id.to_vec()),
+            "SubscribeRequest",
+            "watch_entity_id",
+        ) {
+            Ok(id) => EntityId(id),
+            Err(e) => return vec![create_error_response(request_id, &e)],
+        };
+        let added = self
+            .subscriptions
+            .add_entity(subscription_id, entity_id)
+            .and_then(|()| {
+                if include_old_values {
+                    self.subscriptions.include_old_values(subscription_id)
+                } else {
+                    Ok(())
+                }
+            });
+        if let Err(e) = added {
+            return vec![create_subscription_error_response(request_id, &e)];
+        }
+
+        let triples = match self.scan_entity(&entity_id) {
+            Ok(triples) => triples,
+            Err(e) => {
+                let _ = self.subscriptions.remove(subscription_id);
+                return vec![create_internal_error_response(
+                    request_id,
+                    &format!("Failed to read entity: {e}"),
+                )];
+            }
+        };
 
+        let mut messages = Vec::new();
+        if !triples.is_empty() {
+            let changes = triples
+                .iter()
+                .map(|record| proto::ChangeRecord {
+                    change_type: proto::ChangeType::Insert.into(),
+                    triple: Some(proto::Triple {
+                        entity_id: Some(record.entity_id.0.to_vec()),
+                        attribute_id: Some(record.attribute_id.0.to_vec()),
+                        value: (&record.value).to_proto(),
+                        hlc: Some(record.created_hlc.to_proto()),
+                        attribute_name: None,
+                    }),
+                    old_value: None,
+                })
+                .collect();
+            messages.push(proto::ServerMessage {
+                payload: Some(proto::server_message::Payload::SubscriptionUpdate(
+                    proto::SubscriptionUpdate {
+                        subscription_id,
+                        changes,
+                        query_invalidated: false,
+                        commit_hlc: None,
+                        query_result: None,
+                    },
+                )),
+            });
+        }
+        messages.push(create_ok_response(request_id));
+        tracing::debug!("entity subscription {} registered", subscription_id);
         messages
     }
 
+    /// Read an entity's current triples from a fresh snapshot.
+    fn scan_entity(&self, entity_id: &EntityId) -> Result<Vec<TripleRecord>, DatabaseError> {
+        let db_arc = self.database.as_ref().ok_or(DatabaseError::NotConnected)?;
+        let db = self
+            .read_database(db_arc)
+            .map_err(|_| DatabaseError::LockPoisoned)?;
+        let snapshot = db.begin_readonly()?;
+        let triples = snapshot.scan_entity(entity_id);
+        let txn_id = snapshot.close();
+        db.release_snapshot(txn_id)?;
+        triples
+    }
+
     /// Get historical changes for backfill when subscribing with `since_hlc`.
     ///
+    /// With an `until_hlc`, only the committed changes up to it are included.
+    /// A query subscription receives a single invalidation if any historical
+    /// change can affect its query.
+    ///
     /// Returns a subscription update message if there are changes, or `None` if
     /// there are no changes or an error occurred.
     fn get_backfill_update(
         &self,
         subscription_id: u32,
         since_hlc: HlcTimestamp,
+        until_hlc: Option<HlcTimestamp>,
     ) -> Option<proto::ServerMessage> {
-        let log_records = match self.get_changes_since(since_hlc) {
+        let log_records = until_hlc.map_or_else(
+            || self.get_changes_since(since_hlc),
+            |until_hlc| self.get_changes_between(since_hlc, until_hlc),
+        );
+        let log_records = match log_records {
             Ok(records) => records,
             Err(e) => {
                 tracing::warn!("failed to get changes since HLC: {e}");
@@ -243,16 +591,79 @@ impl ClientConnection {
         };
 
         let changes = convert_log_records_to_changes(&log_records);
-        if changes.is_empty() {
-            return None;
-        }
-
-        let update = create_subscription_update(subscription_id, &changes);
+        let update = self
+            .subscriptions
+            .get(subscription_id)?
+            .update_for(&changes)?;
         Some(proto::ServerMessage {
             payload: Some(proto::server_message::Payload::SubscriptionUpdate(update)),
         })
     }
 
+    /// Subscribe to a live query, sending its current result first.
+    ///
+    /// The query first runs after the subscription is registered, so a change
+    /// committed around subscribing may be reflected in the initial result
+    /// and also trigger a re-run, but is never missed.
+    fn handle_subscribe_query(
+        &mut self,
+        request_id: Option<u32>,
+        req: &proto::SubscribeQueryRequest,
+    ) -> Vec<proto::ServerMessage> {
+        let Some(request) = &req.query else {
+            return vec![create_error_response(
+                request_id,
+                "SubscribeQueryRequest must have a query",
+            )];
+        };
+        let query = match Query::from_proto(request) {
+            Ok(query) => query,
+            Err(e) => return vec![create_error_response(request_id, &e)],
+        };
+        #[allow(clippy::disallowed_methods)] // Clone needed for proto types
+        let live_query = LiveQuery::new(request.clone(), &query);
+        if let Err(e) = self
+            .subscriptions
+            .add_live_query(req.subscription_id, live_query)
+        {
+            return vec![create_subscription_error_response(request_id, &e)];
+        }
+
+        let update = self.live_query_update(req.subscription_id, request);
+        tracing::debug!("live query subscription {} registered", req.subscription_id);
+        vec![
+            proto::ServerMessage {
+                payload: Some(proto::server_message::Payload::SubscriptionUpdate(update)),
+            },
+            create_ok_response(request_id),
+        ]
+    }
+
+    /// Run a live query subscription's query and wrap its result in an
+    /// update.
+    ///
+    /// A query that fails carries its error in the result's status rather
+    /// than ending the subscription.
+    fn live_query_update(
+        &self,
+        subscription_id: u32,
+        request: &proto::QueryRequest,
+    ) -> proto::SubscriptionUpdate {
+        let response = self.query(request);
+        proto::SubscriptionUpdate {
+            subscription_id,
+            changes: Vec::new(),
+            query_invalidated: false,
+            commit_hlc: None,
+            query_result: Some(proto::BatchQueryResult {
+                status: response.status,
+                columns: response.columns,
+                rows: response.rows,
+                arrow_ipc: response.arrow_ipc,
+            }),
+        }
+    }
+
     /// Handle an unsubscribe request.
     ///
     /// Returns the response message to send to the client.
@@ -271,11 +682,63 @@ impl ClientConnection {
         create_ok_response(request_id)
     }
 
+    /// Handle a set defaults request, replacing this connection's defaults.
+    ///
+    /// The defaults were already checked against the connection's value
+    /// limits by `handle_message`.
+    ///
+    /// Returns the response message to send to the client.
+    fn handle_set_defaults(
+        &mut self,
+        request_id: Option<u32>,
+        request: SetDefaultsRequest,
+    ) -> proto::ServerMessage {
+        self.defaults = request.defaults;
+        create_ok_response(request_id)
+    }
+
     /// Iterate over all active subscriptions for this connection.
     pub fn subscriptions(&self) -> impl Iterator<Item = &Subscription> {
         self.subscriptions.iter()
     }
 
+    /// Build the update each matching subscription receives for a broadcast
+    /// notification.
+    ///
+    /// A live query subscription whose query the changes can affect has its
+    /// query re-run against a fresh snapshot, so its result may already
+    /// include later commits.
+    ///
+    /// # Post-conditions
+    /// - Every update carries the notification's commit HLC
+    #[must_use]
+    pub fn updates_for(&self, notification: &ChangeNotification) -> Vec<proto::SubscriptionUpdate> {
+        let changes: Vec<proto::ChangeRecord> = notification
+            .changes
+            .iter()
+            .map(ProtoSerializable::to_proto)
+            .collect();
+        self.subscriptions
+            .iter()
+            .filter_map(|sub| {
+                sub.live_query_for(&changes).map_or_else(
+                    || sub.update_for(&changes),
+                    |request| Some(self.live_query_update(sub.id, request)),
+                )
+            })
+            .map(|update| proto::SubscriptionUpdate {
+                commit_hlc: Some(notification.commit_hlc.to_proto()),
+                ..update
+            })
+            .collect()
+    }
+
+    /// Get the number of active subscriptions on this connection, for metrics.
+    #[must_use]
+    pub fn subscription_count(&self) -> usize {
+        self.subscriptions.len()
+    }
+
     /// Handle a client message and return response messages.
     ///
     /// # Connection State
@@ -290,9 +753,48 @@ impl ClientConnection {
     /// Returns a list of messages to send to the client. Most message types
     /// return a single response, but Subscribe may return multiple messages
     /// (backfill update + OK response).
+    ///
+    /// # Tracing
+    ///
+    /// Each call runs in a `request` span carrying `request_id`,
+    /// `message_type` and `connection_id`. When the message is handled, the
+    /// span records the response's `status`, the total `latency_us`, and
+    /// `lock_wait_us`, the time spent waiting for the database lock, then
+    /// emits a `handled request` event at debug level.
     pub fn handle_message(
         &mut self,
         proto_message: proto::ClientMessage,
+    ) -> Vec<proto::ServerMessage> {
+        let span = tracing::info_span!(
+            "request",
+            request_id = proto_message.request_id,
+            message_type = message_type(&proto_message),
+            connection_id = self.connection_id,
+            status = tracing::field::Empty,
+            latency_us = tracing::field::Empty,
+            lock_wait_us = tracing::field::Empty,
+        );
+        let _entered = span.enter();
+        let start = Instant::now();
+        self.lock_wait_us.store(0, AtomicOrdering::Relaxed);
+
+        let messages = self.dispatch_message(proto_message);
+
+        span.record("status", response_status(&messages));
+        span.record("latency_us", elapsed_us(start));
+        span.record(
+            "lock_wait_us",
+            self.lock_wait_us.load(AtomicOrdering::Relaxed),
+        );
+        tracing::debug!("handled request");
+        messages
+    }
+
+    /// Route a client message to its handler.
+    #[allow(clippy::too_many_lines)] // One arm per message type
+    fn dispatch_message(
+        &mut self,
+        mut proto_message: proto::ClientMessage,
     ) -> Vec<proto::ServerMessage> {
         let request_id = proto_message.request_id;
 
@@ -311,32 +813,106 @@ impl ClientConnection {
             )];
         }
 
-        // Deserialize and validate the message
+        // Abort an explicit transaction that outlived its timeouts; any
+        // message keeps a live one from going idle
+        self.abort_expired_transaction();
+        if let TransactionState::Open(open) = &mut self.transaction {
+            open.active_at = Instant::now();
+        }
+
+        // Replace attribute names with their IDs so validation sees raw IDs
+        if let Err(err) = self.resolve_attribute_names(&mut proto_message) {
+            return vec![match err {
+                DatabaseError::InvalidAttributeName(_) => {
+                    create_error_response(request_id, &err.to_string())
+                }
+                _ => create_internal_error_response(
+                    request_id,
+                    &format!("Failed to resolve attribute name: {err}"),
+                ),
+            }];
+        }
+
+        // Deserialize and validate the message. Every triple of an update is
+        // checked here, before any is buffered, so one malformed triple
+        // rejects the whole request and nothing is written.
         let message = match ClientMessage::from_proto(proto_message) {
             Ok(message) => message,
             Err(err) => {
                 return vec![create_error_response(request_id, &err)];
             }
         };
+        if let Err(violation) = message.check_value_limits(&self.value_limits) {
+            let mut response = status_response(
+                proto::google::rpc::Code::InvalidArgument,
+                violation.to_string(),
+            );
+            add_error_reason(&mut response, violation.error_reason());
+            return response_messages(request_id, response);
+        }
+        if let Some(app_config) = &self.app_config
+            && let Err(e) = message.check_attribute_scope(app_config)
+        {
+            return response_messages(
+                request_id,
+                status_response(proto::google::rpc::Code::PermissionDenied, e),
+            );
+        }
 
         match message.payload {
             ClientMessagePayload::TripleUpdateRequest(request) => {
-                let mut response = self.update(request);
-                response.request_id = request_id;
-                vec![proto::ServerMessage {
-                    payload: Some(proto::server_message::Payload::Response(response)),
-                }]
+                let request = request.with_defaults(&self.defaults);
+                response_messages(request_id, self.write_triples(&request))
             }
             ClientMessagePayload::Query(ref request) => {
-                let mut response = self.query(request);
-                response.request_id = request_id;
-                vec![proto::ServerMessage {
-                    payload: Some(proto::server_message::Payload::Response(response)),
-                }]
+                response_messages(request_id, self.query(request))
+            }
+            ClientMessagePayload::Contains(ref request) => {
+                response_messages(request_id, self.contains(request))
+            }
+            ClientMessagePayload::Delete(ref request) => {
+                let response = if matches!(self.transaction, TransactionState::Idle) {
+                    self.delete(request)
+                } else {
+                    status_response(
+                        proto::google::rpc::Code::FailedPrecondition,
+                        "Deletes cannot run inside a transaction".to_owned(),
+                    )
+                };
+                response_messages(request_id, response)
+            }
+            ClientMessagePayload::BulkLoad(request) => {
+                response_messages(request_id, self.bulk_load(request))
+            }
+            ClientMessagePayload::ReadBatch(ref request) => {
+                response_messages(request_id, self.read_batch(request))
+            }
+            ClientMessagePayload::DescribeSchema(request) => {
+                response_messages(request_id, self.describe_schema(request))
+            }
+            ClientMessagePayload::OpenCursor(ref request) => {
+                response_messages(request_id, self.open_cursor(request))
+            }
+            ClientMessagePayload::FetchCursor(ref request) => {
+                response_messages(request_id, self.fetch_cursor(request))
+            }
+            ClientMessagePayload::CloseCursor(request) => {
+                response_messages(request_id, self.close_cursor(request))
+            }
+            ClientMessagePayload::BeginTxn(request) => {
+                response_messages(request_id, self.begin_txn(request))
+            }
+            ClientMessagePayload::CommitTxn(_) => response_messages(request_id, self.commit_txn()),
+            ClientMessagePayload::AbortTxn(_) => response_messages(request_id, self.abort_txn()),
+            ClientMessagePayload::SetDefaults(request) => {
+                vec![self.handle_set_defaults(request_id, request)]
             }
             ClientMessagePayload::Subscribe(ref request) => {
                 self.handle_subscribe(request_id, request)
             }
+            ClientMessagePayload::SubscribeQuery(ref request) => {
+                self.handle_subscribe_query(request_id, request)
+            }
             ClientMessagePayload::Unsubscribe(request) => {
                 vec![self.handle_unsubscribe(request_id, request)]
             }
@@ -350,6 +926,154 @@ impl ClientConnection {
         }
     }
 
+    /// Take the database's read lock, counting the wait toward the current
+    /// request's `lock_wait_us`.
+    fn read_database<'a>(
+        &self,
+        database: &'a RwLock<Database>,
+    ) -> LockResult<RwLockReadGuard<'a, Database>> {
+        let start = Instant::now();
+        let guard = database.read();
+        self.lock_wait_us
+            .fetch_add(elapsed_us(start), AtomicOrdering::Relaxed);
+        guard
+    }
+
+    /// Take the database's write lock, counting the wait toward the current
+    /// request's `lock_wait_us`.
+    fn write_database<'a>(
+        &self,
+        database: &'a RwLock<Database>,
+    ) -> LockResult<RwLockWriteGuard<'a, Database>> {
+        let start = Instant::now();
+        let guard = database.write();
+        self.lock_wait_us
+            .fetch_add(elapsed_us(start), AtomicOrdering::Relaxed);
+        guard
+    }
+
+    /// Resolve human-readable attribute names in a message to attribute IDs.
+    ///
+    /// Names in update triples are registered on first use. Names in query
+    /// patterns, including subscribed queries, are only looked up; a name that
+    /// was never registered resolves to a fresh ID that matches no triples.
+    ///
+    /// # Post-conditions
+    ///
+    /// - Every triple in an update request with an `attribute_name` and no
+    ///   `attribute_id` has its `attribute_id` set.
+    /// - No query pattern uses `Attribute::AttributeName`.
+    ///
+    /// # Errors
+    ///
+    /// Returns `DatabaseError::InvalidAttributeName` if a name cannot be
+    /// registered, or an error if the name registry cannot be read or written.
+    fn resolve_attribute_names(
+        &self,
+        message: &mut proto::ClientMessage,
+    ) -> Result<(), DatabaseError> {
+        let Some(db_arc) = &self.database else {
+            return Ok(());
+        };
+        match &mut message.payload {
+            Some(
+                proto::client_message::Payload::TripleUpdateRequest(proto::TripleUpdateRequest {
+                    triples,
+                    ..
+                })
+                | proto::client_message::Payload::BulkLoad(proto::BulkLoadRequest {
+                    triples, ..
+                }),
+            ) => {
+                let mut unresolved = triples
+                    .iter_mut()
+                    .filter(|triple| triple.attribute_id.is_none())
+                    .filter_map(|triple| {
+                        let name = triple.attribute_name.take()?;
+                        Some((triple, name))
+                    })
+                    .peekable();
+                if unresolved.peek().is_none() {
+                    return Ok(());
+                }
+                let mut db = self
+                    .write_database(db_arc)
+                    .map_err(|_| DatabaseError::LockPoisoned)?;
+                for (triple, name) in unresolved {
+                    triple.attribute_id = Some(db.attribute_id_for_name(&name)?.0.to_vec());
+                }
+            }
+            Some(
+                proto::client_message::Payload::Query(request)
+                | proto::client_message::Payload::OpenCursor(proto::OpenCursorRequest {
+                    query: Some(request),
+                })
+                | proto::client_message::Payload::Subscribe(proto::SubscribeRequest {
+                    query: Some(request),
+                    ..
+                })
+                | proto::client_message::Payload::SubscribeQuery(proto::SubscribeQueryRequest {
+                    query: Some(request),
+                    ..
+                }),
+            ) => self.resolve_pattern_names(db_arc, std::iter::once(request))?,
+            Some(proto::client_message::Payload::ReadBatch(request)) => {
+                self.resolve_pattern_names(db_arc, request.queries.iter_mut())?;
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    /// Replace attribute names in the patterns of `requests` with the IDs
+    /// registered for them, or with fresh IDs for unregistered names.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the database lock is poisoned or the name registry
+    /// cannot be read.
+    fn resolve_pattern_names<'a>(
+        &self,
+        db_arc: &RwLock<Database>,
+        requests: impl Iterator<Item = &'a mut proto::QueryRequest>,
+    ) -> Result<(), DatabaseError> {
+        let mut unresolved = requests
+            .flat_map(|request| {
+                request
+                    .r#where
+                    .iter_mut()
+                    .chain(request.optional.iter_mut())
+                    .chain(request.where_not.iter_mut())
+            })
+            .filter(|pattern| {
+                matches!(
+                    pattern.attribute,
+                    Some(proto::query_pattern::Attribute::AttributeName(_))
+                )
+            })
+            .peekable();
+        if unresolved.peek().is_none() {
+            return Ok(());
+        }
+        let db = self
+            .read_database(db_arc)
+            .map_err(|_| DatabaseError::LockPoisoned)?;
+        for pattern in unresolved {
+            let Some(proto::query_pattern::Attribute::AttributeName(name)) = &pattern.attribute
+            else {
+                continue;
+            };
+            let attribute_id = db
+                .attribute_id_by_name(name)?
+                .unwrap_or_else(AttributeId::new_v4);
+            pattern.attribute = Some(proto::query_pattern::Attribute::AttributeId(
+                attribute_id.0.to_vec(),
+            ));
+        }
+        drop(db);
+        Ok(())
+    }
+
     /// Handle a `ConnectRequest` message.
     ///
     /// # Pre-conditions
@@ -358,8 +1082,10 @@ impl ClientConnection {
     ///
     /// # Post-conditions
     ///
-    /// - On success: state becomes `Connected`, database is opened/created.
-    /// - On failure: state remains unchanged.
+    /// - On success: state becomes `Connected`, database is opened/created,
+    ///   and the connection is bound to the app's configuration, if any.
+    /// - On failure: state remains unchanged. A missing or invalid
+    ///   `auth_token` fails with `Unauthenticated`.
     fn handle_connect(
         &mut self,
         request_id: Option<u32>,
@@ -386,6 +1112,21 @@ impl ClientConnection {
             return vec![create_error_response(request_id, message)];
         }
 
+        // Authenticate before opening the database, resolving the app's
+        // configuration
+        let authenticated = self.config_registry.as_ref().map_or(Ok(None), |configs| {
+            configs.authenticate(app_api_key, req.auth_token.as_deref())
+        });
+        let app_config = match authenticated {
+            Ok(app_config) => app_config,
+            Err(e) => {
+                return response_messages(
+                    request_id,
+                    status_response(proto::google::rpc::Code::Unauthenticated, e.to_string()),
+                );
+            }
+        };
+
         // Get or create the database
         let Some(registry) = &self.registry else {
             // This shouldn't happen in production, but handle gracefully
@@ -406,10 +1147,28 @@ impl ClientConnection {
             }
         };
 
+        let cursors = match registry.cursors(app_api_key) {
+            Ok(Some(cursors)) => cursors,
+            Ok(None) => {
+                return vec![create_internal_error_response(
+                    request_id,
+                    "Database closed while connecting",
+                )];
+            }
+            Err(e) => {
+                return vec![create_internal_error_response(
+                    request_id,
+                    &format!("Failed to open database: {e}"),
+                )];
+            }
+        };
+
         // Transition state
         self.database = Some(database);
+        self.cursors = Some(cursors);
+        self.app_config = app_config;
         self.state = ConnectionState::Connected {
-            app_api_key: app_api_key.to_string(),
+            app_api_key: String::from(app_api_key.as_str()),
         };
 
         tracing::info!(
@@ -421,10 +1180,20 @@ impl ClientConnection {
         vec![create_ok_response(request_id)]
     }
 
-    #[allow(clippy::too_many_lines)]
-    fn update(&self, request: TripleUpdateRequest) -> proto::ServerResponse {
-        let triples = request.triples;
-        if triples.is_empty() {
+    /// Write a request's triples in one transaction, per its write mode.
+    ///
+    /// The request is all-or-nothing: every triple is committed, or none is.
+    /// A `CreateOnly` request fails with `AlreadyExists` if any triple it sent
+    /// is present, and an `UpdateOnly` request with `NotFound` if any is
+    /// absent; nothing is written in either case. Attached defaults never
+    /// fail a request: under `CreateOnly` they only fill absent triples.
+    ///
+    /// # Pre-conditions
+    /// - Every triple passed validation in `dispatch_message`, so a malformed
+    ///   triple has already rejected the whole request before any was
+    ///   buffered
+    fn update(&self, request: &TripleUpdateRequest) -> proto::ServerResponse {
+        if request.triples.is_empty() {
             return proto::ServerResponse {
                 status: Some(proto::google::rpc::Status {
                     code: proto::google::rpc::Code::Ok.into(),
@@ -446,50 +1215,543 @@ impl ClientConnection {
             };
         };
 
-        // Acquire write lock for the duration of the transaction
-        let Ok(mut db) = db_arc.write() else {
+        // Build the transaction under a read lock so updates from other
+        // connections proceed concurrently, then validate and commit it under
+        // a short write lock. If another update wrote one of these triples in
+        // between, rebuild from a fresh snapshot.
+        let mut conflicts = 0;
+        let (db, outcome) = loop {
+            let txn = {
+                let Ok(db) = self.read_database(db_arc) else {
+                    return status_response(
+                        proto::google::rpc::Code::Internal,
+                        "Database lock poisoned".to_owned(),
+                    );
+                };
+                let mut txn = match db.begin_optimistic(self.connection_id) {
+                    Ok(txn) => txn,
+                    Err(e) => {
+                        return status_response(
+                            database_error_code(&e),
+                            format!("Failed to begin transaction: {e}"),
+                        );
+                    }
+                };
+                if let Err(response) = buffer_writes(&db, &mut txn, request) {
+                    if let Err(abort_error) = db.abort_optimistic(txn) {
+                        tracing::warn!("failed to abort transaction: {abort_error}");
+                    }
+                    return *response;
+                }
+                txn
+            };
+
+            let Ok(mut db) = self.write_database(db_arc) else {
+                return status_response(
+                    proto::google::rpc::Code::Internal,
+                    "Database lock poisoned".to_owned(),
+                );
+            };
+            if let Err(response) = receive_hlcs(&mut db, request.triples.iter().map(|t| t.hlc)) {
+                if let Err(abort_error) = db.abort_optimistic(txn) {
+                    tracing::warn!("failed to abort transaction: {abort_error}");
+                }
+                return *response;
+            }
+
+            // Commit the transaction (broadcasting happens automatically in the database)
+            match db.commit_optimistic(txn) {
+                Ok(outcome) => break (db, outcome),
+                Err(DatabaseError::WriteConflict { .. })
+                    if conflicts < MAX_UPDATE_CONFLICT_RETRIES =>
+                {
+                    conflicts += 1;
+                }
+                Err(e) => return commit_error_response(&e),
+            }
+        };
+
+        let writes: Vec<TripleKey> = request
+            .triples
+            .iter()
+            .map(|t| (t.entity_id, t.attribute_id, t.hlc))
+            .collect();
+        let response = written_triples_response(&db, &outcome, &writes);
+        drop(db);
+        response
+    }
+
+    /// Check whether a triple exists, without reading its value.
+    fn contains(&self, request: &ContainsRequest) -> proto::ServerResponse {
+        // Get the database - should always be Some since we checked is_connected()
+        let Some(db_arc) = &self.database else {
             return proto::ServerResponse {
                 status: Some(proto::google::rpc::Status {
                     code: proto::google::rpc::Code::Internal.into(),
-                    message: "Database lock poisoned".to_string(),
+                    message: "Connection not established".to_owned(),
                     ..Default::default()
                 }),
                 ..Default::default()
             };
         };
 
-        // First, read existing values to compare HLCs
-        let snapshot = db.begin_readonly();
-        // Track: (triple, should_update, is_insert)
-        let mut updates_to_apply: Vec<(_, bool, bool)> = Vec::with_capacity(triples.len());
-
-        for triple in &triples {
-            let existing = snapshot.get(&triple.entity_id, &triple.attribute_id);
-            let (should_update, is_insert) = match existing {
-                Ok(Some(record)) => {
-                    // Update only if client HLC is strictly newer than stored HLC
-                    let should =
-                        HlcClock::<SystemTimeSource>::compare(triple.hlc, record.created_hlc)
-                            == Ordering::Greater;
-                    (should, false) // exists, so it's an update
-                }
-                // No existing value or error reading - always insert
-                Ok(None) | Err(_) => (true, true),
+        // Acquire read lock (concurrent reads are allowed)
+        let Ok(db) = self.read_database(db_arc) else {
+            return proto::ServerResponse {
+                status: Some(proto::google::rpc::Status {
+                    code: proto::google::rpc::Code::Internal.into(),
+                    message: "Database lock poisoned".to_owned(),
+                    ..Default::default()
+                }),
+                ..Default::default()
             };
-            updates_to_apply.push((triple, should_update, is_insert));
-        }
+        };
 
+        let snapshot = match db.begin_readonly() {
+            Ok(snapshot) => snapshot,
+            Err(e) => {
+                return status_response(
+                    proto::google::rpc::Code::Internal,
+                    format!("Failed to begin snapshot: {e}"),
+                );
+            }
+        };
+        let result = snapshot.contains(&request.entity_id, &request.attribute_id);
         let txn_id = snapshot.close();
-        db.release_snapshot(txn_id);
+        if let Err(e) = db.release_snapshot(txn_id) {
+            return status_response(
+                proto::google::rpc::Code::Internal,
+                format!("Failed to release snapshot: {e}"),
+            );
+        }
 
-        // Begin a transaction
+        match result {
+            Ok(exists) => proto::ServerResponse {
+                status: Some(proto::google::rpc::Status {
+                    code: proto::google::rpc::Code::Ok.into(),
+                    ..Default::default()
+                }),
+                exists: Some(exists),
+                ..Default::default()
+            },
+            Err(e) => proto::ServerResponse {
+                status: Some(proto::google::rpc::Status {
+                    code: database_error_code(&e).into(),
+                    message: format!("Contains check failed: {e}"),
+                    ..Default::default()
+                }),
+                ..Default::default()
+            },
+        }
+    }
+
+    /// Delete triples in one transaction.
+    ///
+    /// A key without a live triple, or one already deleted earlier in the
+    /// request, gets a `NotFound` result; the others are deleted and broadcast
+    /// as DELETE changes on commit.
+    fn delete(&self, request: &DeleteRequest) -> proto::ServerResponse {
+        // Get the database - should always be Some since we checked is_connected()
+        let Some(db_arc) = &self.database else {
+            return status_response(
+                proto::google::rpc::Code::Internal,
+                "Connection not established".to_owned(),
+            );
+        };
+        let Ok(mut db) = self.write_database(db_arc) else {
+            return status_response(
+                proto::google::rpc::Code::Internal,
+                "Database lock poisoned".to_owned(),
+            );
+        };
         let mut txn = match db.begin(self.connection_id) {
             Ok(txn) => txn,
+            Err(e) => {
+                return status_response(
+                    database_error_code(&e),
+                    format!("Failed to begin transaction: {e}"),
+                );
+            }
+        };
+
+        let mut deleted = HashSet::new();
+        let mut delete_results = Vec::with_capacity(request.keys.len());
+        for (entity_id, attribute_id) in &request.keys {
+            let result = if deleted.contains(&(*entity_id, *attribute_id)) {
+                Err(DatabaseError::NotFound)
+            } else {
+                txn.delete(entity_id, attribute_id)
+            };
+            let status = match result {
+                Ok(()) => {
+                    deleted.insert((*entity_id, *attribute_id));
+                    proto::google::rpc::Status {
+                        code: proto::google::rpc::Code::Ok.into(),
+                        ..Default::default()
+                    }
+                }
+                Err(DatabaseError::NotFound) => proto::google::rpc::Status {
+                    code: proto::google::rpc::Code::NotFound.into(),
+                    message: "Triple not found".to_owned(),
+                    ..Default::default()
+                },
+                Err(e) => {
+                    txn.abort();
+                    return status_response(
+                        database_error_code(&e),
+                        format!("Failed to delete triple: {e}"),
+                    );
+                }
+            };
+            delete_results.push(proto::DeleteResult {
+                entity_id: entity_id.0.to_vec(),
+                attribute_id: attribute_id.0.to_vec(),
+                status: Some(status),
+            });
+        }
+
+        // Commit the transaction (broadcasting happens automatically in the database)
+        if let Err(e) = txn.commit() {
+            return status_response(
+                database_error_code(&e),
+                format!("Failed to commit transaction: {e}"),
+            );
+        }
+        drop(db);
+
+        proto::ServerResponse {
+            status: Some(proto::google::rpc::Status {
+                code: proto::google::rpc::Code::Ok.into(),
+                ..Default::default()
+            }),
+            delete_results,
+            ..Default::default()
+        }
+    }
+
+    /// Buffer a bulk load chunk, and write the whole load as one transaction
+    /// once its last chunk arrives.
+    ///
+    /// The response to the last chunk counts the triples the load changed.
+    /// Fails with `FailedPrecondition` inside an explicit transaction, and
+    /// with `ResourceExhausted` for a load past `MAX_BULK_LOAD_TRIPLES`.
+    ///
+    /// # Post-conditions
+    /// - After the last chunk or a chunk past the limit, successful or not,
+    ///   nothing is buffered
+    fn bulk_load(&mut self, request: BulkLoadRequest) -> proto::ServerResponse {
+        if !matches!(self.transaction, TransactionState::Idle) {
+            return status_response(
+                proto::google::rpc::Code::FailedPrecondition,
+                "Bulk loads cannot run inside a transaction".to_owned(),
+            );
+        }
+        if self.bulk_load.len() + request.triples.len() > MAX_BULK_LOAD_TRIPLES {
+            self.bulk_load = Vec::new();
+            return status_response(
+                proto::google::rpc::Code::ResourceExhausted,
+                format!("Bulk load exceeds {MAX_BULK_LOAD_TRIPLES} triples"),
+            );
+        }
+        self.bulk_load.extend(request.triples);
+        if !request.finish {
+            return status_response(proto::google::rpc::Code::Ok, String::new());
+        }
+        let triples = std::mem::take(&mut self.bulk_load);
+
+        // Get the database - should always be Some since we checked is_connected()
+        let Some(db_arc) = &self.database else {
+            return status_response(
+                proto::google::rpc::Code::Internal,
+                "Connection not established".to_owned(),
+            );
+        };
+        let Ok(mut db) = self.write_database(db_arc) else {
+            return status_response(
+                proto::google::rpc::Code::Internal,
+                "Database lock poisoned".to_owned(),
+            );
+        };
+        if let Err(response) = receive_hlcs(&mut db, triples.iter().map(|t| t.hlc)) {
+            return *response;
+        }
+
+        // Commit the load (broadcasting happens automatically in the database)
+        match db.bulk_load(self.connection_id, triples) {
+            Ok(outcome) => proto::ServerResponse {
+                triples_loaded: Some(u64::try_from(outcome.changes_applied).unwrap_or(u64::MAX)),
+                ..status_response(proto::google::rpc::Code::Ok, String::new())
+            },
+            Err(e) => commit_error_response(&e),
+        }
+    }
+
+    /// Write an update request's triples: on its own, or buffered in the
+    /// open transaction.
+    ///
+    /// A rejected request aborts the open transaction, since some of its
+    /// triples may already be buffered. While a transaction the server
+    /// aborted is outstanding, updates fail with `Aborted`.
+    fn write_triples(&mut self, request: &TripleUpdateRequest) -> proto::ServerResponse {
+        let mut open = match std::mem::replace(&mut self.transaction, TransactionState::Idle) {
+            TransactionState::Idle => return self.update(request),
+            TransactionState::Aborted(reason) => {
+                let response = status_response(
+                    proto::google::rpc::Code::Aborted,
+                    reason.as_str().to_owned(),
+                );
+                self.transaction = TransactionState::Aborted(reason);
+                return response;
+            }
+            TransactionState::Open(open) => open,
+        };
+
+        let buffered = match self.database.as_deref().map(|db| self.read_database(db)) {
+            Some(Ok(db)) => buffer_writes(&db, &mut open.txn, request),
+            _ => Err(Box::new(status_response(
+                proto::google::rpc::Code::Internal,
+                "Database lock poisoned".to_owned(),
+            ))),
+        };
+        match buffered {
+            Ok(()) => {
+                open.writes.extend(
+                    request
+                        .triples
+                        .iter()
+                        .map(|t| (t.entity_id, t.attribute_id, t.hlc)),
+                );
+                self.transaction = TransactionState::Open(open);
+                status_response(proto::google::rpc::Code::Ok, String::new())
+            }
+            Err(response) => {
+                let message = response
+                    .status
+                    .as_ref()
+                    .map_or("", |status| status.message.as_str());
+                self.transaction = TransactionState::Aborted(format!(
+                    "Transaction aborted after a failed write: {message}"
+                ));
+                self.discard_transaction(*open);
+                *response
+            }
+        }
+    }
+
+    /// Begin an explicit transaction that buffers this connection's updates.
+    ///
+    /// Replaces a transaction the server aborted. Fails with
+    /// `FailedPrecondition` if one is already open.
+    ///
+    /// # Post-conditions
+    /// - On success, the transaction is `Open` with a snapshot pinned until
+    ///   it is committed, aborted or times out
+    fn begin_txn(&mut self, request: proto::BeginTxnRequest) -> proto::ServerResponse {
+        if matches!(self.transaction, TransactionState::Open(_)) {
+            return status_response(
+                proto::google::rpc::Code::FailedPrecondition,
+                "A transaction is already open".to_owned(),
+            );
+        }
+        let Some(db_arc) = &self.database else {
+            return status_response(
+                proto::google::rpc::Code::Internal,
+                "Connection not established".to_owned(),
+            );
+        };
+        let Ok(db) = self.read_database(db_arc) else {
+            return status_response(
+                proto::google::rpc::Code::Internal,
+                "Database lock poisoned".to_owned(),
+            );
+        };
+        let txn = match db.begin_optimistic(self.connection_id) {
+            Ok(txn) => txn,
+            Err(e) => {
+                return status_response(
+                    database_error_code(&e),
+                    format!("Failed to begin transaction: {e}"),
+                );
+            }
+        };
+        drop(db);
+
+        let idle_timeout = match request.timeout_ms {
+            0 => DEFAULT_TRANSACTION_IDLE_TIMEOUT,
+            timeout_ms => Duration::from_millis(u64::from(timeout_ms)),
+        };
+        let now = Instant::now();
+        self.transaction = TransactionState::Open(Box::new(OpenTransaction {
+            txn,
+            writes: Vec::new(),
+            began_at: now,
+            active_at: now,
+            idle_timeout: idle_timeout.min(self.max_transaction_duration),
+        }));
+        status_response(proto::google::rpc::Code::Ok, String::new())
+    }
+
+    /// Commit the open transaction's buffered writes atomically.
+    ///
+    /// The response reads back every buffered write, as an update's does.
+    /// Fails with `Aborted`, writing nothing, if the server already aborted
+    /// the transaction or another transaction wrote one of its triples after
+    /// it began. Either way, the connection is left with no transaction.
+    fn commit_txn(&mut self) -> proto::ServerResponse {
+        let open = match std::mem::replace(&mut self.transaction, TransactionState::Idle) {
+            TransactionState::Idle => {
+                return status_response(
+                    proto::google::rpc::Code::FailedPrecondition,
+                    "No transaction is open".to_owned(),
+                );
+            }
+            TransactionState::Aborted(reason) => {
+                return status_response(proto::google::rpc::Code::Aborted, reason);
+            }
+            TransactionState::Open(open) => open,
+        };
+        let OpenTransaction { txn, writes, .. } = *open;
+
+        // Get the database - should always be Some since we checked is_connected()
+        let Some(db_arc) = &self.database else {
+            return status_response(
+                proto::google::rpc::Code::Internal,
+                "Connection not established".to_owned(),
+            );
+        };
+        let Ok(mut db) = self.write_database(db_arc) else {
+            return status_response(
+                proto::google::rpc::Code::Internal,
+                "Database lock poisoned".to_owned(),
+            );
+        };
+        if let Err(response) = receive_hlcs(&mut db, writes.iter().map(|&(_, _, hlc)| hlc)) {
+            if let Err(abort_error) = db.abort_optimistic(txn) {
+                tracing::warn!("failed to abort transaction: {abort_error}");
+            }
+            return *response;
+        }
+
+        // Commit the transaction (broadcasting happens automatically in the database)
+        match db.commit_optimistic(txn) {
+            Ok(outcome) => written_triples_response(&db, &outcome, &writes),
+            Err(e) => commit_error_response(&e),
+        }
+    }
+
+    /// Discard the open transaction, or acknowledge one the server aborted.
+    fn abort_txn(&mut self) -> proto::ServerResponse {
+        match std::mem::replace(&mut self.transaction, TransactionState::Idle) {
+            TransactionState::Idle => {
+                return status_response(
+                    proto::google::rpc::Code::FailedPrecondition,
+                    "No transaction is open".to_owned(),
+                );
+            }
+            TransactionState::Aborted(_) => {}
+            TransactionState::Open(open) => self.discard_transaction(*open),
+        }
+        status_response(proto::google::rpc::Code::Ok, String::new())
+    }
+
+    /// Get when the open transaction times out, if one is open: after its
+    /// idle timeout without a message, or the maximum duration after it
+    /// began, whichever is sooner.
+    #[must_use]
+    pub fn transaction_deadline(&self) -> Option<Instant> {
+        match &self.transaction {
+            TransactionState::Open(open) => Some(
+                (open.active_at + open.idle_timeout)
+                    .min(open.began_at + self.max_transaction_duration),
+            ),
+            TransactionState::Idle | TransactionState::Aborted(_) => None,
+        }
+    }
+
+    /// Abort the open transaction if it has timed out.
+    ///
+    /// Runs before each message is handled. The transport also calls it once
+    /// `transaction_deadline` passes, so an abandoned transaction does not pin
+    /// its snapshot until the client's next message.
+    ///
+    /// # Post-conditions
+    /// - Returns `true` if a transaction was aborted; it is then `Aborted`
+    ///   until the client commits or aborts it
+    pub fn abort_expired_transaction(&mut self) -> bool {
+        let now = Instant::now();
+        let reason = match &self.transaction {
+            TransactionState::Open(open)
+                if now >= open.began_at + self.max_transaction_duration =>
+            {
+                format!(
+                    "Transaction aborted after being open for over {} ms",
+                    self.max_transaction_duration.as_millis()
+                )
+            }
+            TransactionState::Open(open) if now >= open.active_at + open.idle_timeout => {
+                format!(
+                    "Transaction aborted after being idle for over {} ms",
+                    open.idle_timeout.as_millis()
+                )
+            }
+            _ => return false,
+        };
+        tracing::debug!("{reason}");
+        let previous = std::mem::replace(&mut self.transaction, TransactionState::Aborted(reason));
+        if let TransactionState::Open(open) = previous {
+            self.discard_transaction(*open);
+        }
+        true
+    }
+
+    /// Abort an explicit transaction's optimistic transaction, unpinning its
+    /// snapshot.
+    fn discard_transaction(&self, open: OpenTransaction) {
+        let Some(db_arc) = &self.database else {
+            return;
+        };
+        let Ok(db) = self.read_database(db_arc) else {
+            tracing::warn!("database lock poisoned, transaction not aborted");
+            return;
+        };
+        if let Err(e) = db.abort_optimistic(open.txn) {
+            tracing::warn!("failed to abort transaction: {e}");
+        }
+    }
+
+    fn query(&self, request: &proto::QueryRequest) -> proto::ServerResponse {
+        // Get the database - should always be Some since we checked is_connected()
+        let Some(db_arc) = &self.database else {
+            return proto::ServerResponse {
+                status: Some(proto::google::rpc::Status {
+                    code: proto::google::rpc::Code::Internal.into(),
+                    message: "Connection not established".to_owned(),
+                    ..Default::default()
+                }),
+                ..Default::default()
+            };
+        };
+
+        // Acquire read lock (concurrent reads are allowed)
+        let Ok(db) = self.read_database(db_arc) else {
+            return proto::ServerResponse {
+                status: Some(proto::google::rpc::Status {
+                    code: proto::google::rpc::Code::Internal.into(),
+                    message: "Database lock poisoned".to_owned(),
+                    ..Default::default()
+                }),
+                ..Default::default()
+            };
+        };
+
+        // Convert proto request to internal query using the trait
+        let query = match Query::from_proto(request) {
+            Ok(q) => q,
             Err(e) => {
                 return proto::ServerResponse {
                     status: Some(proto::google::rpc::Status {
-                        code: proto::google::rpc::Code::Internal.into(),
-                        message: format!("Failed to begin transaction: {e}"),
+                        code: proto::google::rpc::Code::InvalidArgument.into(),
+                        message: e,
                         ..Default::default()
                     }),
                     ..Default::default()
@@ -497,176 +1759,800 @@ impl ClientConnection {
             }
         };
 
-        // Track the keys for reading back current values
-        let keys: Vec<(EntityId, AttributeId)> = triples
+        // Begin a read-only snapshot
+        let snapshot = match db.begin_readonly() {
+            Ok(snapshot) => snapshot,
+            Err(e) => {
+                return status_response(
+                    proto::google::rpc::Code::Internal,
+                    format!("Failed to begin snapshot: {e}"),
+                );
+            }
+        };
+
+        // Execute the query
+        let result = {
+            let engine = QueryEngine::new(&snapshot);
+            engine.execute(&query)
+        };
+
+        // Close the snapshot and release it
+        let txn_id = snapshot.close();
+        if let Err(e) = db.release_snapshot(txn_id) {
+            return status_response(
+                proto::google::rpc::Code::Internal,
+                format!("Failed to release snapshot: {e}"),
+            );
+        }
+
+        query_response(request, result)
+    }
+
+    /// Run a batch of queries against one snapshot.
+    ///
+    /// Every query reads the same snapshot, taken before the first query and
+    /// released after the last. The database read lock is held for the whole
+    /// batch, so writes wait until it ends: storage keeps one version per
+    /// triple, and a triple overwritten mid-batch would otherwise vanish from
+    /// the snapshot.
+    ///
+    /// # Post-conditions
+    ///
+    /// - On success, `batch_results` holds one result per query, in request
+    ///   order, and the snapshot has been released.
+    /// - If any query is malformed, no query runs and the whole request fails
+    ///   with `InvalidArgument`.
+    fn read_batch(&self, request: &proto::ReadBatchRequest) -> proto::ServerResponse {
+        // Get the database - should always be Some since we checked is_connected()
+        let Some(db_arc) = &self.database else {
+            return status_response(
+                proto::google::rpc::Code::Internal,
+                "Connection not established".to_owned(),
+            );
+        };
+
+        let mut queries = Vec::with_capacity(request.queries.len());
+        for (index, query_request) in request.queries.iter().enumerate() {
+            match Query::from_proto(query_request) {
+                Ok(query) => queries.push(query),
+                Err(e) => {
+                    return status_response(
+                        proto::google::rpc::Code::InvalidArgument,
+                        format!("Query {index}: {e}"),
+                    );
+                }
+            }
+        }
+
+        let Ok(db) = self.read_database(db_arc) else {
+            return status_response(
+                proto::google::rpc::Code::Internal,
+                "Database lock poisoned".to_owned(),
+            );
+        };
+        let snapshot = match db.begin_readonly() {
+            Ok(snapshot) => snapshot,
+            Err(e) => {
+                return status_response(
+                    proto::google::rpc::Code::Internal,
+                    format!("Failed to begin snapshot: {e}"),
+                );
+            }
+        };
+        let engine = QueryEngine::new(&snapshot);
+        let batch_results = request
+            .queries
             .iter()
-            .map(|t| (t.entity_id, t.attribute_id))
+            .zip(&queries)
+            .map(|(query_request, query)| {
+                let response = query_response(query_request, engine.execute(query));
+                proto::BatchQueryResult {
+                    status: response.status,
+                    columns: response.columns,
+                    rows: response.rows,
+                    arrow_ipc: response.arrow_ipc,
+                }
+            })
             .collect();
+        let txn_id = snapshot.close();
+        if let Err(e) = db.release_snapshot(txn_id) {
+            return status_response(
+                proto::google::rpc::Code::Internal,
+                format!("Failed to release snapshot: {e}"),
+            );
+        }
+        drop(db);
+
+        proto::ServerResponse {
+            batch_results,
+            ..status_response(proto::google::rpc::Code::Ok, String::new())
+        }
+    }
+
+    /// Describe the database's attributes from a sample of their triples.
+    ///
+    /// # Post-conditions
+    ///
+    /// - On success, `attributes` holds at most `MAX_SCHEMA_ATTRIBUTES`
+    ///   attributes, all within the app's attribute scope, and
+    ///   `schema_truncated` says whether any in scope were left out.
+    fn describe_schema(&self, request: proto::DescribeSchemaRequest) -> proto::ServerResponse {
+        // Get the database - should always be Some since we checked is_connected()
+        let Some(db_arc) = &self.database else {
+            return status_response(
+                proto::google::rpc::Code::Internal,
+                "Connection not established".to_owned(),
+            );
+        };
+        let max_attributes = match usize::try_from(request.max_attributes) {
+            Ok(0) | Err(_) => MAX_SCHEMA_ATTRIBUTES,
+            Ok(max_attributes) => max_attributes.min(MAX_SCHEMA_ATTRIBUTES),
+        };
+
+        let Ok(db) = self.read_database(db_arc) else {
+            return status_response(
+                proto::google::rpc::Code::Internal,
+                "Database lock poisoned".to_owned(),
+            );
+        };
+        let allowed_prefixes = self
+            .app_config
+            .as_ref()
+            .and_then(|config| config.allowed_attribute_prefixes());
+        let sample = match db.describe_schema(max_attributes, allowed_prefixes) {
+            Ok(sample) => sample,
+            Err(e) => {
+                return status_response(
+                    database_error_code(&e),
+                    format!("Failed to describe schema: {e}"),
+                );
+            }
+        };
+        drop(db);
+
+        proto::ServerResponse {
+            attributes: sample
+                .attributes
+                .into_iter()
+                .map(|attribute| proto::AttributeSchema {
+                    attribute_id: attribute.attribute_id.0.to_vec(),
+                    attribute_name: attribute.name,
+                    value_types: attribute
+                        .value_types
+                        .into_iter()
+                        .map(ProtoSerializable::to_proto)
+                        .collect(),
+                })
+                .collect(),
+            schema_truncated: Some(sample.truncated),
+            ..status_response(proto::google::rpc::Code::Ok, String::new())
+        }
+    }
 
-        // Insert or update triples where client HLC is newer
-        for (triple, should_update, is_insert) in updates_to_apply {
-            if should_update {
-                // Triple now uses storage::TripleValue directly
-                let value = triple.value.clone_value();
+    /// Open a cursor over a query's results, pinning a fresh snapshot.
+    fn open_cursor(&self, request: &proto::QueryRequest) -> proto::ServerResponse {
+        // Both should always be Some since we checked is_connected()
+        let (Some(db_arc), Some(cursors)) = (&self.database, &self.cursors) else {
+            return status_response(
+                proto::google::rpc::Code::Internal,
+                "Connection not established".to_owned(),
+            );
+        };
+        let Ok(db) = self.read_database(db_arc) else {
+            return status_response(
+                proto::google::rpc::Code::Internal,
+                "Database lock poisoned".to_owned(),
+            );
+        };
+
+        let query = match Query::from_proto(request) {
+            Ok(q) => q,
+            Err(e) => return status_response(proto::google::rpc::Code::InvalidArgument, e),
+        };
+        if let Err(e) = CursorTable::check_query(&query) {
+            return cursor_error_response(&e);
+        }
+
+        // The cursor takes its own pin, so this snapshot is released as usual
+        let snapshot = match db.begin_readonly() {
+            Ok(snapshot) => snapshot,
+            Err(e) => {
+                return status_response(
+                    proto::google::rpc::Code::Internal,
+                    format!("Failed to begin snapshot: {e}"),
+                );
+            }
+        };
+        let result = QueryEngine::new(&snapshot).open_cursor(cursors, query);
+        let txn_id = snapshot.close();
+        if let Err(e) = db.release_snapshot(txn_id) {
+            return status_response(
+                proto::google::rpc::Code::Internal,
+                format!("Failed to release snapshot: {e}"),
+            );
+        }
+
+        match result {
+            Ok(cursor_id) => proto::ServerResponse {
+                cursor_id: Some(cursor_id.0),
+                ..status_response(proto::google::rpc::Code::Ok, String::new())
+            },
+            Err(e) => cursor_error_response(&e),
+        }
+    }
+
+    /// Fetch the next batch of rows from a cursor.
+    fn fetch_cursor(&self, request: &proto::FetchCursorRequest) -> proto::ServerResponse {
+        if request.max_rows == 0 {
+            return status_response(
+                proto::google::rpc::Code::InvalidArgument,
+                "max_rows must be positive".to_owned(),
+            );
+        }
+        let max_rows = usize::try_from(request.max_rows)
+            .unwrap_or(usize::MAX)
+            .min(MAX_CURSOR_FETCH_ROWS);
+
+        // Both should always be Some since we checked is_connected()
+        let (Some(db_arc), Some(cursors)) = (&self.database, &self.cursors) else {
+            return status_response(
+                proto::google::rpc::Code::Internal,
+                "Connection not established".to_owned(),
+            );
+        };
+        let Ok(db) = self.read_database(db_arc) else {
+            return status_response(
+                proto::google::rpc::Code::Internal,
+                "Database lock poisoned".to_owned(),
+            );
+        };
+
+        let result = cursors.fetch(&db, CursorId(request.cursor_id), max_rows);
+        drop(db);
+
+        match result {
+            Ok(batch) => {
+                let response = batch.result.to_proto();
+                proto::ServerResponse {
+                    columns: response.columns,
+                    rows: response.rows,
+                    cursor_exhausted: Some(batch.exhausted),
+                    ..status_response(proto::google::rpc::Code::Ok, String::new())
+                }
+            }
+            Err(e) => cursor_error_response(&e),
+        }
+    }
+
+    /// Close a cursor and release its snapshot.
+    fn close_cursor(&self, request: proto::CloseCursorRequest) -> proto::ServerResponse {
+        // Both should always be Some since we checked is_connected()
+        let (Some(db_arc), Some(cursors)) = (&self.database, &self.cursors) else {
+            return status_response(
+                proto::google::rpc::Code::Internal,
+                "Connection not established".to_owned(),
+            );
+        };
+        let Ok(db) = self.read_database(db_arc) else {
+            return status_response(
+                proto::google::rpc::Code::Internal,
+                "Database lock poisoned".to_owned(),
+            );
+        };
+
+        let result = cursors.close(&db, CursorId(request.cursor_id));
+        drop(db);
+
+        match result {
+            Ok(()) => status_response(proto::google::rpc::Code::Ok, String::new()),
+            Err(e) => cursor_error_response(&e),
+        }
+    }
+}
+
+impl Drop for ClientConnection {
+    /// Abort an open transaction, so its snapshot does not stay pinned.
+    fn drop(&mut self) {
+        let transaction = std::mem::replace(&mut self.transaction, TransactionState::Idle);
+        if let TransactionState::Open(open) = transaction {
+            self.discard_transaction(*open);
+        }
+    }
+}
+
+/// Wrap a handler's response, tagged with the request's ID, as the messages
+/// to send.
+fn response_messages(
+    request_id: Option<u32>,
+    response: proto::ServerResponse,
+) -> Vec<proto::ServerMessage> {
+    vec![proto::ServerMessage {
+        payload: Some(proto::server_message::Payload::Response(
+            proto::ServerResponse {
+                request_id,
+                ..response
+            },
+        )),
+    }]
+}
+
+/// Build the response to a query from its result.
+///
+/// Rows are returned as an Arrow IPC stream instead when the request asks for
+/// it and the result can be encoded.
+fn query_response(
+    request: &proto::QueryRequest,
+    result: Result<QueryResult, QueryError>,
+) -> proto::ServerResponse {
+    match result {
+        Ok(query_result) => {
+            let arrow_ipc = if request.arrow_ipc {
+                encode_arrow_response(&query_result)
+            } else {
+                None
+            };
+            let (columns, rows) = if arrow_ipc.is_some() {
+                (query_result.columns, Vec::new())
+            } else {
+                let response = query_result.to_proto();
+                (response.columns, response.rows)
+            };
+            proto::ServerResponse {
+                status: Some(proto::google::rpc::Status {
+                    code: proto::google::rpc::Code::Ok.into(),
+                    ..Default::default()
+                }),
+                columns,
+                rows,
+                arrow_ipc,
+                ..Default::default()
+            }
+        }
+        Err(e) => proto::ServerResponse {
+            status: Some(proto::google::rpc::Status {
+                code: query_error_code(&e).into(),
+                message: format!("Query failed: {e}"),
+                ..Default::default()
+            }),
+            ..Default::default()
+        },
+    }
+}
+
+/// A written triple's entity, attribute and client HLC.
+type TripleKey = (EntityId, AttributeId, HlcTimestamp);
+
+/// Buffer an update request's triples in `txn`, per its write mode.
+///
+/// # Errors
+///
+/// Returns the response rejecting the request if a `CreateOnly` triple the
+/// client sent is present, an `UpdateOnly` triple is absent, a
+/// `CommitIfVersion` entity is at another version (see
+/// `check_entity_versions`), or a triple cannot be read. Some of the
+/// request's triples may already be buffered.
+fn buffer_writes(
+    db: &Database,
+    txn: &mut OptimisticTransaction,
+    request: &TripleUpdateRequest,
+) -> Result<(), Box<proto::ServerResponse>> {
+    if request.mode == WriteMode::CommitIfVersion {
+        check_entity_versions(db, txn, request)?;
+    }
+    // Commit drops writes whose client HLC is not newer than the stored value
+    for (index, triple) in request.triples.iter().enumerate() {
+        let value = triple.value.clone_value();
+        let rejection = match request.mode {
+            WriteMode::Upsert | WriteMode::CommitIfVersion => {
+                // Missing value or error reading - treat as an insert
+                let is_insert = !matches!(
+                    txn.contains(db, &triple.entity_id, &triple.attribute_id),
+                    Ok(true)
+                );
                 if is_insert {
                     txn.insert_with_hlc(triple.entity_id, triple.attribute_id, value, triple.hlc);
                 } else {
                     txn.update_with_hlc(triple.entity_id, triple.attribute_id, value, triple.hlc);
                 }
+                None
             }
+            WriteMode::CreateOnly => match txn.insert_if_absent_with_hlc(
+                db,
+                triple.entity_id,
+                triple.attribute_id,
+                value,
+                triple.hlc,
+            ) {
+                // An attached default never overwrites an existing triple
+                Ok(inserted) if inserted || index >= request.sent_count => None,
+                Ok(_) => Some((
+                    proto::google::rpc::Code::AlreadyExists,
+                    format!("Triple #{index} already exists"),
+                )),
+                Err(e) => Some((
+                    database_error_code(&e),
+                    format!("Failed to read triple #{index}: {e}"),
+                )),
+            },
+            WriteMode::UpdateOnly => {
+                match txn.contains(db, &triple.entity_id, &triple.attribute_id) {
+                    Ok(true) => {
+                        txn.update_with_hlc(
+                            triple.entity_id,
+                            triple.attribute_id,
+                            value,
+                            triple.hlc,
+                        );
+                        None
+                    }
+                    Ok(false) => Some((
+                        proto::google::rpc::Code::NotFound,
+                        format!("Triple #{index} not found"),
+                    )),
+                    Err(e) => Some((
+                        database_error_code(&e),
+                        format!("Failed to read triple #{index}: {e}"),
+                    )),
+                }
+            }
+        };
+        if let Some((code, message)) = rejection {
+            return Err(Box::new(status_response(code, message)));
         }
+    }
+    Ok(())
+}
 
-        // Commit the transaction (broadcasting happens automatically in the database)
-        if let Err(e) = txn.commit() {
-            return proto::ServerResponse {
-                status: Some(proto::google::rpc::Status {
-                    code: proto::google::rpc::Code::Internal.into(),
-                    message: format!("Failed to commit transaction: {e}"),
-                    ..Default::default()
+/// Check that every entity a `CommitIfVersion` request writes is at its
+/// expected version.
+///
+/// Each version is read in `txn`, so if another commit changes the entity
+/// before `txn` commits, the commit fails with a write conflict.
+///
+/// # Errors
+///
+/// Returns a `FailedPrecondition` response if the database does not keep
+/// entity versions, an `Aborted` response with a `VersionMismatch` reason for
+/// the first entity, in request order, at another version, or an error
+/// response if a version cannot be read.
+fn check_entity_versions(
+    db: &Database,
+    txn: &mut OptimisticTransaction,
+    request: &TripleUpdateRequest,
+) -> Result<(), Box<proto::ServerResponse>> {
+    if !db.entity_versions_enabled() {
+        return Err(Box::new(status_response(
+            proto::google::rpc::Code::FailedPrecondition,
+            "Entity versions are not enabled on this server".to_owned(),
+        )));
+    }
+    let mut checked = HashSet::new();
+    for (index, triple) in request.triples[..request.sent_count].iter().enumerate() {
+        if !checked.insert(triple.entity_id) {
+            continue;
+        }
+        // Every written entity has one, per the request's invariants
+        let expected = request
+            .expected_versions
+            .get(&triple.entity_id)
+            .copied()
+            .unwrap_or_default();
+        let actual = txn.entity_version(db, &triple.entity_id).map_err(|e| {
+            Box::new(status_response(
+                database_error_code(&e),
+                format!("Failed to read the version of triple #{index}'s entity: {e}"),
+            ))
+        })?;
+        if actual != expected {
+            let mut response = status_response(
+                proto::google::rpc::Code::Aborted,
+                format!(
+                    "The entity of triple #{index} is at version {actual}, expected {expected}"
+                ),
+            );
+            add_error_reason(
+                &mut response,
+                proto::error_detail::Reason::VersionMismatch(proto::VersionMismatch {
+                    entity_id: triple.entity_id.0.to_vec(),
+                    expected,
+                    actual,
                 }),
-                ..Default::default()
-            };
+            );
+            return Err(Box::new(response));
         }
+    }
+    Ok(())
+}
 
-        // Read back the current values and return them in the response
-        let mut response_triples = Vec::with_capacity(keys.len());
-
-        // Begin a read-only snapshot to get current values
-        let snapshot = db.begin_readonly();
-
-        for (entity_id, attribute_id) in keys {
-            if let Ok(Some(record)) = snapshot.get(&entity_id, &attribute_id) {
-                // Convert storage::TripleValue directly to proto
-                let proto_value = match record.value {
-                    TripleValue::Null => None, // Proto doesn't have null
-                    TripleValue::String(s) => Some(proto::triple_value::Value::String(s)),
-                    TripleValue::Number(n) => Some(proto::triple_value::Value::Number(n)),
-                    TripleValue::Boolean(b) => Some(proto::triple_value::Value::Boolean(b)),
-                    TripleValue::Ref(id) => {
-                        // Serialize Ref as string (matching to_proto impl)
-                        let s = std::str::from_utf8(&id.0).map_or_else(
-                            |_| {
-                                use std::fmt::Write;
-                                id.0.iter().fold(String::with_capacity(32), |mut acc, b| {
-                                    let _ = write!(acc, "{b:02x}");
-                                    acc
-                                })
-                            },
-                            |s| s.trim_end_matches('\0').to_owned(),
-                        );
-                        Some(proto::triple_value::Value::String(s))
-                    }
-                };
-                response_triples.push(proto::Triple {
-                    entity_id: Some(entity_id.0.to_vec()),
-                    attribute_id: Some(attribute_id.0.to_vec()),
-                    value: Some(proto::TripleValue { value: proto_value }),
-                    hlc: Some(proto::HlcTimestamp {
-                        physical_time_ms: record.created_hlc.physical_time,
-                        logical_counter: record.created_hlc.logical_counter,
-                        node_id: record.created_hlc.node_id,
-                    }),
-                });
-            }
+/// Validate the `entity_id` and `attribute_id` a `SubscribeRequest` filters
+/// its changes by.
+///
+/// # Errors
+///
+/// Returns an error if an ID is not exactly 16 bytes, or if either is
+/// combined with a `query`.
+#[allow(clippy::disallowed_methods)] // The ID bytes are validated into IDs
+fn subscription_filter_ids(
+    req: &proto::SubscribeRequest,
+) -> Result<(Option<EntityId>, Option<AttributeId>), String> {
+    if req.query.is_some() && (req.entity_id.is_some() || req.attribute_id.is_some()) {
+        return Err("entity_id and attribute_id cannot be combined with query".to_owned());
+    }
+    let entity_id = req
+        .entity_id
+        .clone()
+        .map(|id| validate_proto_id(Some(id), "SubscribeRequest", "entity_id"))
+        .transpose()?
+        .map(EntityId);
+    let attribute_id = req
+        .attribute_id
+        .clone()
+        .map(|id| validate_proto_id(Some(id), "SubscribeRequest", "attribute_id"))
+        .transpose()?
+        .map(AttributeId);
+    Ok((entity_id, attribute_id))
+}
+
+/// Merge each client HLC into the server clock so later server-issued
+/// timestamps order after them.
+///
+/// # Errors
+///
+/// Returns an `InvalidArgument` response if a timestamp is too far ahead.
+fn receive_hlcs(
+    db: &mut Database,
+    hlcs: impl Iterator<Item = HlcTimestamp>,
+) -> Result<(), Box<proto::ServerResponse>> {
+    for (index, hlc) in hlcs.enumerate() {
+        if let Err(e) = db.receive_hlc(hlc) {
+            return Err(Box::new(status_response(
+                proto::google::rpc::Code::InvalidArgument,
+                format!("Invalid triple #{index}: {e}"),
+            )));
         }
+    }
+    Ok(())
+}
 
-        let txn_id = snapshot.close();
-        db.release_snapshot(txn_id);
+/// The response to committed writes: the current value of each written
+/// triple, and whether the write changed it.
+fn written_triples_response(
+    db: &Database,
+    outcome: &CommitOutcome,
+    writes: &[TripleKey],
+) -> proto::ServerResponse {
+    // Writes that changed stored state, to tell each triple's outcome
+    let applied: HashMap<TripleKey, ChangeType> = outcome
+        .applied
+        .iter()
+        .map(|change| {
+            (
+                (change.entity_id, change.attribute_id, change.hlc),
+                change.change_type,
+            )
+        })
+        .collect();
+
+    // Read back the current values and return them in the response
+    let mut response_triples = Vec::with_capacity(writes.len());
+    let mut write_results = Vec::with_capacity(writes.len());
+
+    // Begin a read-only snapshot to get current values
+    let snapshot = match db.begin_readonly() {
+        Ok(snapshot) => snapshot,
+        Err(e) => {
+            return status_response(
+                database_error_code(&e),
+                format!("Failed to read back written triples: {e}"),
+            );
+        }
+    };
+
+    for &(entity_id, attribute_id, hlc) in writes {
+        let current = snapshot.get(&entity_id, &attribute_id).ok().flatten();
+        let change_type = applied.get(&(entity_id, attribute_id, hlc));
+        write_results.push(proto::WriteResult {
+            entity_id: entity_id.0.to_vec(),
+            attribute_id: attribute_id.0.to_vec(),
+            change_type: change_type.map_or_else(
+                || proto::ChangeType::Unspecified.into(),
+                |change| change.to_proto(),
+            ),
+            hlc: current.as_ref().map(|record| record.created_hlc.to_proto()),
+        });
+        if let Some(record) = current {
+            // Convert storage::TripleValue directly to proto
+            let proto_value = match record.value {
+                TripleValue::Null => None, // Proto doesn't have null
+                TripleValue::String(s) => Some(proto::triple_value::Value::String(s)),
+                TripleValue::Number(n) => Some(proto::triple_value::Value::Number(n)),
+                TripleValue::Boolean(b) => Some(proto::triple_value::Value::Boolean(b)),
+                TripleValue::Bytes(bytes) => Some(proto::triple_value::Value::Bytes(bytes)),
+                TripleValue::Ref(id) => {
+                    // Serialize Ref as string (matching to_proto impl)
+                    let s = std::str::from_utf8(&id.0).map_or_else(
+                        |_| {
+                            use std::fmt::Write;
+                            id.0.iter().fold(String::with_capacity(32), |mut acc, b| {
+                                let _ = write!(acc, "{b:02x}");
+                                acc
+                            })
+                        },
+                        |s| s.trim_end_matches('\0').to_owned(),
+                    );
+                    Some(proto::triple_value::Value::String(s))
+                }
+            };
+            response_triples.push(proto::Triple {
+                entity_id: Some(entity_id.0.to_vec()),
+                attribute_id: Some(attribute_id.0.to_vec()),
+                value: Some(proto::TripleValue { value: proto_value }),
+                hlc: Some(proto::HlcTimestamp {
+                    physical_time_ms: record.created_hlc.physical_time,
+                    logical_counter: record.created_hlc.logical_counter,
+                    node_id: record.created_hlc.node_id,
+                }),
+                attribute_name: None,
+            });
+        }
+    }
 
-        proto::ServerResponse {
-            status: Some(proto::google::rpc::Status {
-                code: proto::google::rpc::Code::Ok.into(),
-                ..Default::default()
-            }),
-            triples: response_triples,
+    let txn_id = snapshot.close();
+    if let Err(e) = db.release_snapshot(txn_id) {
+        return status_response(
+            proto::google::rpc::Code::Internal,
+            format!("Failed to release snapshot: {e}"),
+        );
+    }
+
+    proto::ServerResponse {
+        status: Some(proto::google::rpc::Status {
+            code: proto::google::rpc::Code::Ok.into(),
             ..Default::default()
-        }
+        }),
+        triples: response_triples,
+        write_results,
+        ..Default::default()
     }
+}
 
-    fn query(&self, request: &proto::QueryRequest) -> proto::ServerResponse {
-        // Get the database - should always be Some since we checked is_connected()
-        let Some(db_arc) = &self.database else {
-            return proto::ServerResponse {
-                status: Some(proto::google::rpc::Status {
-                    code: proto::google::rpc::Code::Internal.into(),
-                    message: "Connection not established".to_owned(),
-                    ..Default::default()
-                }),
-                ..Default::default()
-            };
-        };
+/// Build a response carrying only a status.
+fn status_response(code: proto::google::rpc::Code, message: String) -> proto::ServerResponse {
+    proto::ServerResponse {
+        status: Some(proto::google::rpc::Status {
+            code: code.into(),
+            message,
+            ..Default::default()
+        }),
+        ..Default::default()
+    }
+}
 
-        // Acquire read lock (concurrent reads are allowed)
-        let Ok(db) = db_arc.read() else {
-            return proto::ServerResponse {
-                status: Some(proto::google::rpc::Status {
-                    code: proto::google::rpc::Code::Internal.into(),
-                    message: "Database lock poisoned".to_owned(),
-                    ..Default::default()
-                }),
-                ..Default::default()
-            };
-        };
+/// Encode a query result as an Arrow IPC stream, or `None` if it must be
+/// returned as rows because a column mixes value types.
+fn encode_arrow_response(result: &QueryResult) -> Option<Vec<u8>> {
+    encode_arrow_ipc(result)
+        .inspect_err(|e| tracing::debug!("returning rows instead of Arrow: {e}"))
+        .ok()
+}
 
-        // Convert proto request to internal query using the trait
-        let query = match Query::from_proto(request) {
-            Ok(q) => q,
-            Err(e) => {
-                return proto::ServerResponse {
-                    status: Some(proto::google::rpc::Status {
-                        code: proto::google::rpc::Code::InvalidArgument.into(),
-                        message: e,
-                        ..Default::default()
-                    }),
-                    ..Default::default()
-                };
-            }
-        };
+/// The status code for a failed database operation.
+///
+/// An exhausted buffer pool is `ResourceExhausted`, telling the client to
+/// retry later. A write too large for the WAL is also `ResourceExhausted`,
+/// though retrying it fails the same way; other failures are `Internal`.
+fn database_error_code(error: &DatabaseError) -> proto::google::rpc::Code {
+    if error.is_resource_exhausted()
+        || matches!(
+            error,
+            DatabaseError::ValueTooLarge { .. } | DatabaseError::TransactionTooLarge { .. }
+        )
+    {
+        proto::google::rpc::Code::ResourceExhausted
+    } else {
+        proto::google::rpc::Code::Internal
+    }
+}
 
-        // Begin a read-only snapshot
-        let snapshot = db.begin_readonly();
+/// The status code for a failed query: `InvalidArgument` if the query cannot
+/// be answered from the data it matched, as for database errors otherwise.
+fn query_error_code(error: &QueryError) -> proto::google::rpc::Code {
+    match error {
+        QueryError::NonNumericAggregate { .. } => proto::google::rpc::Code::InvalidArgument,
+        QueryError::Database(e) => database_error_code(e),
+        QueryError::Transaction(_) => proto::google::rpc::Code::Internal,
+    }
+}
 
-        // Execute the query
-        let result = {
-            let engine = QueryEngine::new(&snapshot);
-            engine.execute(&query)
-        };
+/// Build the response to a failed commit.
+///
+/// A write conflict is `Aborted`, telling the client a retry may succeed, and
+/// names the conflicting triple in a `WriteConflict` reason.
+fn commit_error_response(error: &DatabaseError) -> proto::ServerResponse {
+    let message = format!("Failed to commit transaction: {error}");
+    let DatabaseError::WriteConflict {
+        entity_id,
+        attribute_id,
+    } = error
+    else {
+        return status_response(database_error_code(error), message);
+    };
+    let mut response = status_response(proto::google::rpc::Code::Aborted, message);
+    add_error_reason(
+        &mut response,
+        proto::error_detail::Reason::WriteConflict(proto::WriteConflict {
+            entity_id: entity_id.0.to_vec(),
+            attribute_id: attribute_id.0.to_vec(),
+        }),
+    );
+    response
+}
 
-        // Close the snapshot and release it
-        let txn_id = snapshot.close();
-        db.release_snapshot(txn_id);
+/// Map a cursor error to a response with the matching status code.
+fn cursor_error_response(error: &CursorError) -> proto::ServerResponse {
+    let code = match error {
+        CursorError::NotFound(_) => proto::google::rpc::Code::NotFound,
+        CursorError::MissingWherePattern
+        | CursorError::AggregateQuery
+        | CursorError::UnsupportedClause(_) => proto::google::rpc::Code::InvalidArgument,
+        CursorError::Database(e) => database_error_code(e),
+        CursorError::LockPoisoned => proto::google::rpc::Code::Internal,
+    };
+    status_response(code, error.to_string())
+}
 
-        // Handle the result
-        match result {
-            Ok(query_result) => {
-                let response = query_result.to_proto();
-                proto::ServerResponse {
-                    status: Some(proto::google::rpc::Status {
-                        code: proto::google::rpc::Code::Ok.into(),
-                        ..Default::default()
-                    }),
-                    columns: response.columns,
-                    rows: response.rows,
-                    ..Default::default()
-                }
-            }
-            Err(e) => proto::ServerResponse {
-                status: Some(proto::google::rpc::Status {
-                    code: proto::google::rpc::Code::Internal.into(),
-                    message: format!("Query failed: {e}"),
-                    ..Default::default()
-                }),
-                ..Default::default()
-            },
-        }
+/// Name of a client message's payload type, for tracing.
+const fn message_type(message: &proto::ClientMessage) -> &'static str {
+    use proto::client_message::Payload;
+    match &message.payload {
+        Some(Payload::Connect(_)) => "Connect",
+        Some(Payload::TripleUpdateRequest(_)) => "TripleUpdate",
+        Some(Payload::Query(_)) => "Query",
+        Some(Payload::Contains(_)) => "Contains",
+        Some(Payload::Subscribe(_)) => "Subscribe",
+        Some(Payload::SubscribeQuery(_)) => "SubscribeQuery",
+        Some(Payload::BulkLoad(_)) => "BulkLoad",
+        Some(Payload::Unsubscribe(_)) => "Unsubscribe",
+        Some(Payload::OpenCursor(_)) => "OpenCursor",
+        Some(Payload::FetchCursor(_)) => "FetchCursor",
+        Some(Payload::CloseCursor(_)) => "CloseCursor",
+        Some(Payload::SetDefaults(_)) => "SetDefaults",
+        Some(Payload::Delete(_)) => "Delete",
+        Some(Payload::ReadBatch(_)) => "ReadBatch",
+        Some(Payload::DescribeSchema(_)) => "DescribeSchema",
+        Some(Payload::BeginTxn(_)) => "BeginTxn",
+        Some(Payload::CommitTxn(_)) => "CommitTxn",
+        Some(Payload::AbortTxn(_)) => "AbortTxn",
+        None => "Empty",
     }
 }
 
+/// Status code name of the response among a request's messages, for tracing.
+fn response_status(messages: &[proto::ServerMessage]) -> &'static str {
+    messages
+        .iter()
+        .rev()
+        .find_map(|message| match &message.payload {
+            Some(proto::server_message::Payload::Response(response)) => response.status.as_ref(),
+            _ => None,
+        })
+        .and_then(|status| proto::google::rpc::Code::try_from(status.code).ok())
+        .map_or("UNKNOWN", |code| code.as_str_name())
+}
+
+/// Microseconds since `start`, saturating at `u64::MAX`.
+fn elapsed_us(start: Instant) -> u64 {
+    u64::try_from(start.elapsed().as_micros()).unwrap_or(u64::MAX)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use crate::proto;
+    use crate::storage::buffer_pool::{BufferPool, MIN_POOL_CAPACITY};
+    use crate::storage::{HlcClock, SystemTimeSource};
     use crate::testing::new_test_database;
+    use std::cmp::Ordering;
 
     /// Create a test `ClientConnection`.
     fn new_test_client() -> ClientConnection {
@@ -709,10 +2595,13 @@ mod tests {
                 logical_counter: 0,
                 node_id: 1,
             }),
+            attribute_name: None,
         };
 
         let update_request = proto::TripleUpdateRequest {
             triples: vec![triple],
+            mode: proto::WriteMode::Upsert.into(),
+            expected_versions: vec![],
         };
 
         let client_message = proto::ClientMessage {
@@ -766,10 +2655,13 @@ mod tests {
                 logical_counter: 0,
                 node_id: 1,
             }),
+            attribute_name: None,
         };
 
         let update_request = proto::TripleUpdateRequest {
             triples: vec![triple],
+            mode: proto::WriteMode::Upsert.into(),
+            expected_versions: vec![],
         };
 
         let client_message = proto::ClientMessage {
@@ -806,10 +2698,13 @@ mod tests {
                 logical_counter: 0,
                 node_id: 1,
             }),
+            attribute_name: None,
         };
 
         let update_request = proto::TripleUpdateRequest {
             triples: vec![triple],
+            mode: proto::WriteMode::Upsert.into(),
+            expected_versions: vec![],
         };
 
         let client_message = proto::ClientMessage {
@@ -832,7 +2727,11 @@ mod tests {
     fn test_handle_message_empty_triples() {
         let mut client_conn = new_test_client();
 
-        let update_request = proto::TripleUpdateRequest { triples: vec![] };
+        let update_request = proto::TripleUpdateRequest {
+            triples: vec![],
+            mode: proto::WriteMode::Upsert.into(),
+            expected_versions: vec![],
+        };
 
         let client_message = proto::ClientMessage {
             request_id: Some(126),
@@ -869,10 +2768,13 @@ mod tests {
                 logical_counter: 0,
                 node_id: 1,
             }),
+            attribute_name: None,
         };
 
         let update_request = proto::TripleUpdateRequest {
             triples: vec![triple],
+            mode: proto::WriteMode::Upsert.into(),
+            expected_versions: vec![],
         };
 
         let insert_message = proto::ClientMessage {
@@ -906,6 +2808,13 @@ mod tests {
             }],
             optional: vec![],
             where_not: vec![],
+            arrow_ipc: false,
+            limit: None,
+            offset: None,
+            order_by: vec![],
+            aggregates: vec![],
+            group_by: vec![],
+            distinct: false,
         };
 
         let query_message = proto::ClientMessage {
@@ -961,10 +2870,15 @@ mod tests {
                     logical_counter: 0,
                     node_id: 1,
                 }),
+                attribute_name: None,
             });
         }
 
-        let update_request = proto::TripleUpdateRequest { triples };
+        let update_request = proto::TripleUpdateRequest {
+            triples,
+            mode: proto::WriteMode::Upsert.into(),
+            expected_versions: vec![],
+        };
 
         let insert_message = proto::ClientMessage {
             request_id: Some(300),
@@ -1004,6 +2918,13 @@ mod tests {
             }],
             optional: vec![],
             where_not: vec![],
+            arrow_ipc: false,
+            limit: None,
+            offset: None,
+            order_by: vec![],
+            aggregates: vec![],
+            group_by: vec![],
+            distinct: false,
         };
 
         let query_message = proto::ClientMessage {
@@ -1044,6 +2965,109 @@ mod tests {
         );
     }
 
+    /// Fields of a span, formatted as strings.
+    #[derive(Default)]
+    struct SpanFields(HashMap<String, String>);
+
+    impl tracing::field::Visit for SpanFields {
+        fn record_str(&mut self, field: &tracing::field::Field, value: &str) {
+            self.0.insert(field.name().to_string(), value.to_string());
+        }
+
+        fn record_debug(&mut self, field: &tracing::field::Field, value: &dyn std::fmt::Debug) {
+            self.0
+                .insert(field.name().to_string(), format!("{value:?}"));
+        }
+    }
+
+    /// Layer that collects the fields of each closed `request` span.
+    #[derive(Default)]
+    struct RequestSpans(Arc<std::sync::Mutex<Vec<HashMap<String, String>>>>);
+
+    impl<S> tracing_subscriber::Layer<S> for RequestSpans
+    where
+        S: tracing::Subscriber + for<'a> tracing_subscriber::registry::LookupSpan<'a>,
+    {
+        fn on_new_span(
+            &self,
+            attrs: &tracing::span::Attributes<'_>,
+            id: &tracing::span::Id,
+            ctx: tracing_subscriber::layer::Context<'_, S>,
+        ) {
+            if attrs.metadata().name() != "request" {
+                return;
+            }
+            let mut fields = SpanFields::default();
+            attrs.record(&mut fields);
+            ctx.span(id)
+                .expect("span exists")
+                .extensions_mut()
+                .insert(fields);
+        }
+
+        fn on_record(
+            &self,
+            id: &tracing::span::Id,
+            values: &tracing::span::Record<'_>,
+            ctx: tracing_subscriber::layer::Context<'_, S>,
+        ) {
+            let span = ctx.span(id).expect("span exists");
+            if let Some(fields) = span.extensions_mut().get_mut::<SpanFields>() {
+                values.record(fields);
+            }
+        }
+
+        fn on_close(&self, id: tracing::span::Id, ctx: tracing_subscriber::layer::Context<'_, S>) {
+            let span = ctx.span(&id).expect("span exists");
+            let fields = span.extensions_mut().remove::<SpanFields>();
+            if let Some(fields) = fields {
+                self.0.lock().expect("lock spans").push(fields.0);
+            }
+        }
+    }
+
+    #[test]
+    fn test_handle_message_records_request_span() {
+        use tracing_subscriber::layer::SubscriberExt;
+
+        let spans = RequestSpans::default();
+        let collected = Arc::clone(&spans.0);
+        let subscriber = tracing_subscriber::registry().with(spans);
+        let mut client_conn = new_test_client();
+        let connection_id = client_conn.connection_id();
+
+        tracing::subscriber::with_default(subscriber, || {
+            client_conn.handle_message(proto::ClientMessage {
+                request_id: Some(7),
+                payload: Some(proto::client_message::Payload::Contains(
+                    proto::ContainsRequest {
+                        entity_id: Some(vec![1u8; 16]),
+                        attribute_id: Some(vec![2u8; 16]),
+                    },
+                )),
+            });
+            client_conn.handle_message(proto::ClientMessage {
+                request_id: None,
+                payload: None,
+            });
+        });
+
+        let spans = std::mem::take(&mut *collected.lock().expect("lock spans"));
+        assert_eq!(spans.len(), 2);
+        let contains = &spans[0];
+        assert_eq!(contains["request_id"], "7");
+        assert_eq!(contains["message_type"], "Contains");
+        assert_eq!(contains["connection_id"], connection_id.to_string());
+        assert_eq!(contains["status"], "OK");
+        assert!(contains["latency_us"].parse::<u64>().is_ok());
+        assert!(contains["lock_wait_us"].parse::<u64>().is_ok());
+
+        let empty = &spans[1];
+        assert!(!empty.contains_key("request_id"));
+        assert_eq!(empty["message_type"], "Empty");
+        assert_eq!(empty["status"], "INVALID_ARGUMENT");
+    }
+
     #[test]
     fn test_handle_message_invalid_entity_id_length() {
         let mut client_conn = new_test_client();
@@ -1060,10 +3084,13 @@ mod tests {
                 logical_counter: 0,
                 node_id: 1,
             }),
+            attribute_name: None,
         };
 
         let update_request = proto::TripleUpdateRequest {
             triples: vec![triple],
+            mode: proto::WriteMode::Upsert.into(),
+            expected_versions: vec![],
         };
 
         let client_message = proto::ClientMessage {
@@ -1099,10 +3126,13 @@ mod tests {
                 logical_counter: 0,
                 node_id: 1,
             }),
+            attribute_name: None,
         };
 
         let update_request = proto::TripleUpdateRequest {
             triples: vec![triple],
+            mode: proto::WriteMode::Upsert.into(),
+            expected_versions: vec![],
         };
 
         let client_message = proto::ClientMessage {
@@ -1138,10 +3168,13 @@ mod tests {
                 logical_counter: 0,
                 node_id: 1,
             }),
+            attribute_name: None,
         };
 
         let update_request = proto::TripleUpdateRequest {
             triples: vec![triple],
+            mode: proto::WriteMode::Upsert.into(),
+            expected_versions: vec![],
         };
 
         let client_message = proto::ClientMessage {
@@ -1177,10 +3210,13 @@ mod tests {
                 logical_counter: 0,
                 node_id: 1,
             }),
+            attribute_name: None,
         };
 
         let update_request = proto::TripleUpdateRequest {
             triples: vec![triple],
+            mode: proto::WriteMode::Upsert.into(),
+            expected_versions: vec![],
         };
 
         let client_message = proto::ClientMessage {
@@ -1199,4 +3235,363 @@ mod tests {
             proto::google::rpc::Code::InvalidArgument as i32
         );
     }
+
+    #[test]
+    fn test_client_hlc_advances_server_clock() {
+        let mut client_conn = new_test_client();
+        let server_hlc = client_conn
+            .database
+            .as_ref()
+            .unwrap()
+            .read()
+            .unwrap()
+            .current_hlc();
+
+        // A client clock slightly ahead of the server, within the allowed drift
+        let client_hlc = HlcTimestamp {
+            physical_time: server_hlc.physical_time + 10_000,
+            logical_counter: 5,
+            node_id: 9,
+        };
+        let response = extract_response(client_conn.handle_message(proto::ClientMessage {
+            request_id: Some(1),
+            payload: Some(proto::client_message::Payload::TripleUpdateRequest(
+                proto::TripleUpdateRequest {
+                    triples: vec![proto::Triple {
+                        entity_id: Some(vec![1u8; 16]),
+                        attribute_id: Some(vec![2u8; 16]),
+                        value: Some(proto::TripleValue {
+                            value: Some(proto::triple_value::Value::Boolean(true)),
+                        }),
+                        hlc: Some(client_hlc.to_proto()),
+                        attribute_name: None,
+                    }],
+                    mode: proto::WriteMode::Upsert.into(),
+                    expected_versions: vec![],
+                },
+            )),
+        }));
+        assert_eq!(
+            response.status.unwrap().code,
+            proto::google::rpc::Code::Ok as i32
+        );
+
+        // The stored triple keeps the client HLC
+        assert_eq!(response.triples[0].hlc, Some(client_hlc.to_proto()));
+
+        // Timestamps the server issues afterwards order after the client's
+        let current_hlc = client_conn
+            .database
+            .as_ref()
+            .unwrap()
+            .read()
+            .unwrap()
+            .current_hlc();
+        assert_eq!(
+            HlcClock::<SystemTimeSource>::compare(current_hlc, client_hlc),
+            Ordering::Greater
+        );
+    }
+
+    #[test]
+    fn test_exhausted_buffer_pool_returns_resource_exhausted() {
+        let dir = tempfile::tempdir().expect("create temp dir");
+        let pool = BufferPool::new(MIN_POOL_CAPACITY);
+        #[allow(clippy::disallowed_methods)] // Kept to lease buffers directly
+        let database =
+            Database::create(&dir.path().join("test.db"), Arc::clone(&pool)).expect("create db");
+        let mut client_conn = ClientConnection::new(database);
+        let insert = |request_id| proto::ClientMessage {
+            request_id: Some(request_id),
+            payload: Some(proto::client_message::Payload::TripleUpdateRequest(
+                proto::TripleUpdateRequest {
+                    triples: vec![proto::Triple {
+                        entity_id: Some(vec![1u8; 16]),
+                        attribute_id: Some(vec![2u8; 16]),
+                        value: Some(proto::TripleValue {
+                            value: Some(proto::triple_value::Value::Number(1.0)),
+                        }),
+                        hlc: Some(proto::HlcTimestamp {
+                            physical_time_ms: 1000,
+                            logical_counter: 0,
+                            node_id: 1,
+                        }),
+                        attribute_name: None,
+                    }],
+                    mode: proto::WriteMode::Upsert.into(),
+                    expected_versions: vec![],
+                },
+            )),
+        };
+
+        // A burst of operations holds every buffer
+        let held: Vec<_> = std::iter::from_fn(|| pool.lease_page()).collect();
+        let response = extract_response(client_conn.handle_message(insert(1)));
+        assert_eq!(
+            response.status.expect("status").code,
+            proto::google::rpc::Code::ResourceExhausted as i32
+        );
+
+        // Once the burst is over, the retried write succeeds
+        drop(held);
+        let response = extract_response(client_conn.handle_message(insert(2)));
+        assert_eq!(
+            response.status.expect("status").code,
+            proto::google::rpc::Code::Ok as i32
+        );
+    }
+
+    #[test]
+    fn test_poisoned_database_lock_returns_internal_error() {
+        let mut client_conn = new_test_client();
+        let database = client_conn.shared_database().expect("connected");
+        assert!(!client_conn.is_database_poisoned());
+
+        // A handler panicking while holding the write lock poisons it
+        let handle = std::thread::spawn(move || {
+            let _db = database.write().expect("lock");
+            panic!("handler panicked");
+        });
+        assert!(handle.join().is_err());
+        assert!(client_conn.is_database_poisoned());
+
+        let response = extract_response(client_conn.handle_message(proto::ClientMessage {
+            request_id: Some(7),
+            payload: Some(proto::client_message::Payload::Contains(
+                proto::ContainsRequest {
+                    entity_id: Some(vec![1u8; 16]),
+                    attribute_id: Some(vec![2u8; 16]),
+                },
+            )),
+        }));
+        assert_eq!(response.request_id, Some(7));
+        assert_eq!(
+            response.status.expect("status").code,
+            proto::google::rpc::Code::Internal as i32
+        );
+    }
+
+    #[test]
+    fn test_database_error_code_for_writes_too_large_for_wal() {
+        let too_large = [
+            DatabaseError::ValueTooLarge {
+                size: 2_000_000,
+                max: 1_000_000,
+            },
+            DatabaseError::TransactionTooLarge {
+                size: 2_000_000,
+                capacity: 1_048_576,
+            },
+        ];
+        for error in &too_large {
+            assert_eq!(
+                database_error_code(error),
+                proto::google::rpc::Code::ResourceExhausted
+            );
+        }
+        assert_eq!(
+            database_error_code(&DatabaseError::ReadOnly),
+            proto::google::rpc::Code::Internal
+        );
+    }
+
+    /// Query for the value of one triple.
+    fn point_query(entity_id: [u8; 16], attribute_id: [u8; 16]) -> proto::QueryRequest {
+        proto::QueryRequest {
+            find: vec![proto::QueryPatternVariable {
+                label: Some("value".to_owned()),
+            }],
+            r#where: vec![proto::QueryPattern {
+                entity: Some(proto::query_pattern::Entity::EntityId(entity_id.to_vec())),
+                attribute: Some(proto::query_pattern::Attribute::AttributeId(
+                    attribute_id.to_vec(),
+                )),
+                value_group: Some(proto::query_pattern::ValueGroup::ValueVariable(
+                    proto::QueryPatternVariable {
+                        label: Some("value".to_owned()),
+                    },
+                )),
+            }],
+            optional: vec![],
+            where_not: vec![],
+            arrow_ipc: false,
+            limit: None,
+            offset: None,
+            order_by: vec![],
+            aggregates: vec![],
+            group_by: vec![],
+            distinct: false,
+        }
+    }
+
+    /// Overwrite a number triple in its own transaction.
+    #[allow(clippy::significant_drop_tightening)]
+    fn write_number(database: &RwLock<Database>, entity_id: [u8; 16], value: f64) {
+        let mut db = database.write().expect("lock");
+        let mut txn = db.begin(0).expect("begin");
+        txn.insert(
+            EntityId(entity_id),
+            AttributeId([2u8; 16]),
+            TripleValue::Number(value),
+        )
+        .expect("insert");
+        txn.commit().expect("commit");
+    }
+
+    /// The number in the first cell of a query result.
+    fn first_number(rows: &[proto::QueryResultRow]) -> Option<f64> {
+        match &rows.first()?.values.first()?.value {
+            Some(proto::query_result_value::Value::TripleValue(proto::TripleValue {
+                value: Some(proto::triple_value::Value::Number(n)),
+            })) => Some(*n),
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn test_read_batch_does_not_observe_concurrent_writes() {
+        let mut client_conn = new_test_client();
+        let database = client_conn.shared_database().expect("connected");
+        let (counter, marker) = ([1u8; 16], [3u8; 16]);
+        write_number(&database, counter, 0.0);
+
+        // A writer keeps bumping the counter, then writing a new marker
+        // triple, while batches read both in between
+        let done = Arc::new(std::sync::atomic::AtomicBool::new(false));
+        #[allow(clippy::disallowed_methods)]
+        let writer = {
+            let database = Arc::clone(&database);
+            let done = Arc::clone(&done);
+            std::thread::spawn(move || {
+                let mut value = 0.0;
+                while !done.load(AtomicOrdering::Relaxed) {
+                    value += 1.0;
+                    write_number(&database, counter, value);
+                    write_number(&database, marker, value);
+                }
+            })
+        };
+
+        for request_id in 0..50 {
+            let queries = (0..10)
+                .flat_map(|_| {
+                    [
+                        point_query(counter, [2u8; 16]),
+                        point_query(marker, [2u8; 16]),
+                    ]
+                })
+                .collect();
+            let response = extract_response(client_conn.handle_message(proto::ClientMessage {
+                request_id: Some(request_id),
+                payload: Some(proto::client_message::Payload::ReadBatch(
+                    proto::ReadBatchRequest { queries },
+                )),
+            }));
+            let values: Vec<Option<f64>> = response
+                .batch_results
+                .iter()
+                .map(|result| first_number(&result.rows))
+                .collect();
+            assert_eq!(values.len(), 20);
+
+            // Every read of a triple sees the same state, and the marker
+            // never runs ahead of the counter it was written after
+            let (counters, markers): (Vec<_>, Vec<_>) =
+                values.chunks(2).map(|pair| (pair[0], pair[1])).unzip();
+            assert!(counters.iter().all(|value| *value == counters[0]));
+            assert!(markers.iter().all(|value| *value == markers[0]));
+            assert!(counters[0].is_some());
+            assert!(markers[0].unwrap_or(0.0) <= counters[0].unwrap_or(0.0));
+        }
+        done.store(true, AtomicOrdering::Relaxed);
+        writer.join().expect("writer");
+
+        // The batches' snapshots were all released
+        assert_eq!(
+            database
+                .read()
+                .expect("lock")
+                .active_snapshot_count()
+                .expect("count"),
+            0
+        );
+    }
+
+    #[test]
+    fn test_transaction_aborted_after_max_duration_unpins_snapshot() {
+        let mut client_conn =
+            new_test_client().with_max_transaction_duration(Duration::from_millis(20));
+        let database = client_conn.shared_database().expect("connected");
+        let active_snapshots = || {
+            database
+                .read()
+                .expect("lock")
+                .active_snapshot_count()
+                .expect("count")
+        };
+        let begin = proto::ClientMessage {
+            request_id: Some(1),
+            payload: Some(proto::client_message::Payload::BeginTxn(
+                proto::BeginTxnRequest { timeout_ms: 0 },
+            )),
+        };
+
+        let response = extract_response(client_conn.handle_message(begin));
+        assert_eq!(
+            response.status.expect("status").code,
+            proto::google::rpc::Code::Ok as i32
+        );
+        assert_eq!(active_snapshots(), 1);
+        // The default idle timeout is capped to the maximum duration
+        let deadline = client_conn.transaction_deadline().expect("open");
+        assert!(deadline <= Instant::now() + Duration::from_millis(20));
+
+        std::thread::sleep(Duration::from_millis(40));
+        assert!(client_conn.abort_expired_transaction());
+        assert_eq!(active_snapshots(), 0);
+        assert_eq!(client_conn.transaction_deadline(), None);
+
+        let commit = proto::ClientMessage {
+            request_id: Some(2),
+            payload: Some(proto::client_message::Payload::CommitTxn(
+                proto::CommitTxnRequest {},
+            )),
+        };
+        let status = extract_response(client_conn.handle_message(commit))
+            .status
+            .expect("status");
+        assert_eq!(status.code, proto::google::rpc::Code::Aborted as i32);
+        assert!(status.message.contains("open for over 20 ms"));
+    }
+
+    #[test]
+    fn test_dropped_connection_aborts_open_transaction() {
+        let mut client_conn = new_test_client();
+        let database = client_conn.shared_database().expect("connected");
+        client_conn.handle_message(proto::ClientMessage {
+            request_id: Some(1),
+            payload: Some(proto::client_message::Payload::BeginTxn(
+                proto::BeginTxnRequest { timeout_ms: 0 },
+            )),
+        });
+        assert_eq!(
+            database
+                .read()
+                .expect("lock")
+                .active_snapshot_count()
+                .expect("count"),
+            1
+        );
+
+        drop(client_conn);
+
+        assert_eq!(
+            database
+                .read()
+                .expect("lock")
+                .active_snapshot_count()
+                .expect("count"),
+            0
+        );
+    }
 }
@@ -7,6 +7,7 @@
 //! - `ServerConfig` contains valid configuration values.
 //! - `listen_port` defaults to 3000 if not specified.
 //! - `database_directory` defaults to "./data" if not specified.
+//! - `node_id` is set and non-zero.
 //!
 //! # Invariants
 //! - `admin_app_api_key` is always a non-empty string.
@@ -14,12 +15,34 @@
 
 use std::path::PathBuf;
 
+use crate::constants::{DEFAULT_MAX_MESSAGE_SIZE, DEFAULT_MAX_SUBSCRIPTIONS_PER_CONNECTION};
+use crate::storage::UNASSIGNED_NODE_ID;
+use crate::storage::buffer_pool::{DEFAULT_POOL_CAPACITY, MIN_POOL_CAPACITY};
+use crate::storage::wal::MIN_WAL_CAPACITY;
+use crate::types::ValueLimits;
+
 /// Server configuration loaded from environment variables.
 ///
 /// # Environment Variables
 /// - `ENSO_ADMIN_APP_API_KEY`: Required. The API key for admin app access.
+/// - `ENSO_NODE_ID`: Required. This server's node ID, between 1 and 4294967295.
+///   Must be unique across servers, since it breaks ties between HLC
+///   timestamps; every database the server opens is stamped with it.
 /// - `ENSO_DATABASE_DIRECTORY`: Optional. Path to the database directory. Defaults to "./data".
 /// - `ENSO_LISTEN_PORT`: Optional. Port to listen on. Defaults to 3000.
+/// - `ENSO_MAX_STRING_VALUE_LENGTH`: Optional. Maximum string value length in bytes.
+///   Defaults to 1024; may be raised up to 65535.
+/// - `ENSO_BUFFER_POOL_PAGES`: Optional. Number of 8KB pages in the buffer pool
+///   shared by all databases. Defaults to 262144 (2GB); must be at least 64.
+/// - `ENSO_MAX_SUBSCRIPTIONS_PER_CONNECTION`: Optional. Maximum number of
+///   active subscriptions on one connection. Defaults to 256; must be at least 1.
+/// - `ENSO_MAX_MESSAGE_SIZE`: Optional. Maximum size in bytes of a message
+///   from a client. Defaults to 16777216 (16MB); must be at least 1.
+/// - `ENSO_ENTITY_VERSIONS`: Optional. `true` to keep a version number for
+///   every entity, needed by `COMMIT_IF_VERSION` writes. Defaults to `false`.
+/// - `ENSO_MAX_WAL_CAPACITY`: Optional. Size in bytes a database's WAL may
+///   grow to when a transaction does not fit in it. Must be at least 1048576
+///   (1MB). Unset, the WAL keeps its capacity and such transactions fail.
 #[derive(Debug)]
 pub struct ServerConfig {
     /// API key for admin app access.
@@ -28,6 +51,20 @@ pub struct ServerConfig {
     pub database_directory: PathBuf,
     /// Port the server listens on.
     pub listen_port: u16,
+    /// Limits on values accepted from clients.
+    pub value_limits: ValueLimits,
+    /// Number of pages in the shared buffer pool.
+    pub buffer_pool_pages: usize,
+    /// This server's node ID in the cluster.
+    pub node_id: u32,
+    /// Maximum number of active subscriptions on one connection.
+    pub max_subscriptions_per_connection: usize,
+    /// Maximum size in bytes of a message from a client.
+    pub max_message_size: usize,
+    /// Whether databases keep a version number for every entity.
+    pub entity_versions: bool,
+    /// Size in bytes a database's WAL may grow to, or `None` to keep it fixed.
+    pub max_wal_capacity: Option<u64>,
 }
 
 /// Error returned when configuration loading fails.
@@ -77,8 +114,16 @@ impl ServerConfig {
     /// Load configuration from environment variables.
     ///
     /// # Errors
-    /// Returns `ConfigError::MissingEnvVar` if `ENSO_ADMIN_APP_API_KEY` is not set.
-    /// Returns `ConfigError::InvalidValue` if `ENSO_LISTEN_PORT` is not a valid u16.
+    /// Returns `ConfigError::MissingEnvVar` if `ENSO_ADMIN_APP_API_KEY` or
+    /// `ENSO_NODE_ID` is not set.
+    /// Returns `ConfigError::InvalidValue` if `ENSO_LISTEN_PORT` is not a valid u16,
+    /// if `ENSO_MAX_STRING_VALUE_LENGTH` is not between 1 and 65535, or if
+    /// `ENSO_BUFFER_POOL_PAGES` is not a number of at least 64, if
+    /// `ENSO_MAX_SUBSCRIPTIONS_PER_CONNECTION` or `ENSO_MAX_MESSAGE_SIZE` is
+    /// not a number of at least 1, if `ENSO_ENTITY_VERSIONS` is not `true` or
+    /// `false`, if `ENSO_MAX_WAL_CAPACITY` is not a number of at least
+    /// 1048576, or if `ENSO_NODE_ID` is not a number between 1 and 4294967295.
+    #[allow(clippy::too_many_lines)] // One block per variable
     pub fn from_env() -> Result<Self, ConfigError> {
         let admin_app_api_key = std::env::var("ENSO_ADMIN_APP_API_KEY")
             .map_err(|_| ConfigError::MissingEnvVar("ENSO_ADMIN_APP_API_KEY"))?;
@@ -107,10 +152,111 @@ impl ServerConfig {
             Err(_) => Self::DEFAULT_PORT,
         };
 
+        let value_limits = match std::env::var("ENSO_MAX_STRING_VALUE_LENGTH") {
+            Ok(length_str) => length_str
+                .parse::<usize>()
+                .ok()
+                .and_then(|length| ValueLimits::new(length).ok())
+                .ok_or(ConfigError::InvalidValue {
+                    name: "ENSO_MAX_STRING_VALUE_LENGTH",
+                    value: length_str,
+                    reason: "must be a number between 1 and 65535",
+                })?,
+            Err(_) => ValueLimits::default(),
+        };
+
+        let buffer_pool_pages = match std::env::var("ENSO_BUFFER_POOL_PAGES") {
+            Ok(pages_str) => pages_str
+                .parse::<usize>()
+                .ok()
+                .filter(|pages| *pages >= MIN_POOL_CAPACITY)
+                .ok_or(ConfigError::InvalidValue {
+                    name: "ENSO_BUFFER_POOL_PAGES",
+                    value: pages_str,
+                    reason: "must be a number of at least 64",
+                })?,
+            Err(_) => DEFAULT_POOL_CAPACITY,
+        };
+
+        let max_subscriptions_per_connection =
+            match std::env::var("ENSO_MAX_SUBSCRIPTIONS_PER_CONNECTION") {
+                Ok(max_str) => max_str
+                    .parse::<usize>()
+                    .ok()
+                    .filter(|max| *max >= 1)
+                    .ok_or(ConfigError::InvalidValue {
+                        name: "ENSO_MAX_SUBSCRIPTIONS_PER_CONNECTION",
+                        value: max_str,
+                        reason: "must be a number of at least 1",
+                    })?,
+                Err(_) => DEFAULT_MAX_SUBSCRIPTIONS_PER_CONNECTION,
+            };
+
+        let max_message_size = match std::env::var("ENSO_MAX_MESSAGE_SIZE") {
+            Ok(size_str) => size_str
+                .parse::<usize>()
+                .ok()
+                .filter(|size| *size >= 1)
+                .ok_or(ConfigError::InvalidValue {
+                    name: "ENSO_MAX_MESSAGE_SIZE",
+                    value: size_str,
+                    reason: "must be a number of at least 1",
+                })?,
+            Err(_) => DEFAULT_MAX_MESSAGE_SIZE,
+        };
+
+        let entity_versions = match std::env::var("ENSO_ENTITY_VERSIONS") {
+            Ok(enabled_str) => {
+                enabled_str
+                    .parse::<bool>()
+                    .map_err(|_| ConfigError::InvalidValue {
+                        name: "ENSO_ENTITY_VERSIONS",
+                        value: enabled_str,
+                        reason: "must be true or false",
+                    })?
+            }
+            Err(_) => false,
+        };
+
+        let max_wal_capacity = match std::env::var("ENSO_MAX_WAL_CAPACITY") {
+            Ok(capacity_str) => Some(
+                capacity_str
+                    .parse::<u64>()
+                    .ok()
+                    .filter(|capacity| *capacity >= MIN_WAL_CAPACITY)
+                    .ok_or(ConfigError::InvalidValue {
+                        name: "ENSO_MAX_WAL_CAPACITY",
+                        value: capacity_str,
+                        reason: "must be a number of at least 1048576",
+                    })?,
+            ),
+            Err(_) => None,
+        };
+
+        let node_id_str = std::env::var("ENSO_NODE_ID")
+            .map_err(|_| ConfigError::MissingEnvVar("ENSO_NODE_ID"))?;
+        // Node 0 is reserved for files no node has claimed
+        let node_id = node_id_str
+            .parse::<u32>()
+            .ok()
+            .filter(|node_id| *node_id != UNASSIGNED_NODE_ID)
+            .ok_or(ConfigError::InvalidValue {
+                name: "ENSO_NODE_ID",
+                value: node_id_str,
+                reason: "must be a number between 1 and 4294967295",
+            })?;
+
         Ok(Self {
             admin_app_api_key,
             database_directory,
             listen_port,
+            value_limits,
+            buffer_pool_pages,
+            node_id,
+            max_subscriptions_per_connection,
+            max_message_size,
+            entity_versions,
+            max_wal_capacity,
         })
     }
 }
@@ -1 +1,49 @@
-pub const MAX_TRIPLE_STRING_VALUE_LENGTH: usize = 1024;
+use std::time::Duration;
+
+/// Hard upper bound on string value length in bytes, set by the `u16` length
+/// prefix in the storage encoding. Configured limits cannot exceed it.
+pub const MAX_TRIPLE_STRING_VALUE_LENGTH: usize = u16::MAX as usize;
+
+/// Hard upper bound on bytes value length. Values this large span many
+/// overflow pages, and a whole value is read into memory on every access.
+pub const MAX_TRIPLE_BYTES_VALUE_LENGTH: usize = 1024 * 1024;
+
+/// Default limit on string value length in bytes when none is configured.
+pub const DEFAULT_MAX_TRIPLE_STRING_VALUE_LENGTH: usize = 1024;
+
+/// Upper bound on rows returned by a single `FetchCursorRequest`. Larger
+/// requests are capped to it.
+pub const MAX_CURSOR_FETCH_ROWS: usize = 10_000;
+
+/// Upper bound on attributes described by a single `DescribeSchemaRequest`.
+/// Requests for more, or for 0, are capped to it.
+pub const MAX_SCHEMA_ATTRIBUTES: usize = 1000;
+
+/// Times a triple update is rebuilt after a concurrent update to the same
+/// triple commits first. Once exhausted, the update fails with `Aborted`.
+pub const MAX_UPDATE_CONFLICT_RETRIES: usize = 8;
+
+/// Default time an explicit transaction may go without a message from its
+/// connection before the server aborts it.
+pub const DEFAULT_TRANSACTION_IDLE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Longest an explicit transaction may stay open, however active. Its pinned
+/// snapshot holds back garbage collection for as long as it is open.
+pub const MAX_TRANSACTION_DURATION: Duration = Duration::from_mins(1);
+
+/// Upper bound on triples in one bulk load. The load is buffered in memory
+/// until its last chunk arrives.
+pub const MAX_BULK_LOAD_TRIPLES: usize = 1_000_000;
+
+/// Frames a connection may have queued for its client. A client that falls
+/// this far behind is disconnected and must resync.
+pub const OUTBOUND_QUEUE_CAPACITY: usize = 1024;
+
+/// Default limit on active subscriptions per connection. Every change is
+/// matched against each subscription, so the limit caps the fan-out work a
+/// single client can cause.
+pub const DEFAULT_MAX_SUBSCRIPTIONS_PER_CONNECTION: usize = 256;
+
+/// Default limit on the size in bytes of a message from a client. Larger
+/// frames are rejected before they are buffered or decoded.
+pub const DEFAULT_MAX_MESSAGE_SIZE: usize = 16 * 1024 * 1024;
@@ -2,7 +2,8 @@
 //!
 //! This module provides a thread-safe registry that manages database instances.
 //! Multiple connections with the same `app_api_key` share a single `Database` instance,
-//! enabling subscription broadcasting across connections.
+//! enabling subscription broadcasting across connections. Each database owns its own
+//! change broadcast channel, so subscriptions never cross tenants.
 //!
 //! # Thread Safety
 //!
@@ -10,23 +11,104 @@
 //! - Multiple threads can read from the same database simultaneously
 //! - Write operations acquire exclusive access
 //!
+//! # Lifecycle
+//!
+//! Databases are opened lazily on first use. A database is *idle* when the registry
+//! holds the only strong reference to it (no connection is using it) and it has no
+//! open query cursors. `close_idle_databases` first expires idle cursors, then
+//! closes idle databases once they exceed the idle timeout, and the
+//! least recently used idle database is closed early when opening another would
+//! exceed `max_open_databases`.
+//!
 //! # Invariants
 //!
-//! - Each `app_api_key` maps to exactly one `Database` instance
-//! - Database instances are never removed once created (for the lifetime of the registry)
+//! - Each `app_api_key` maps to at most one open `Database` instance
+//! - A database is only closed while no connection holds a reference to it
+//!   and no query cursor is open on it
+//! - The number of open databases never exceeds `max_open_databases`
 //! - All `app_api_key` values are validated before use
 
 use std::collections::HashMap;
 use std::path::PathBuf;
-use std::sync::{Arc, RwLock};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, RwLock, Weak};
+use std::time::{Duration, Instant};
 
+use crate::query::CursorTable;
 use crate::storage::buffer_pool::{BufferPool, DEFAULT_POOL_CAPACITY};
+use crate::storage::checkpoint_task::spawn_checkpoint_task;
 use crate::storage::gc::{GcConfig, spawn_gc_task};
 use crate::storage::{Database, DatabaseError};
 
 /// Maximum length for an `app_api_key`.
 const MAX_API_KEY_LENGTH: usize = 256;
 
+/// Default maximum number of simultaneously open databases.
+pub const DEFAULT_MAX_OPEN_DATABASES: usize = 1024;
+
+/// Default time a database may sit unused before it is closed.
+pub const DEFAULT_IDLE_TIMEOUT: Duration = Duration::from_mins(10);
+
+/// Default time between idle sweeps in `spawn_idle_sweep_task`.
+pub const DEFAULT_IDLE_SWEEP_INTERVAL: Duration = Duration::from_mins(1);
+
+/// Lifecycle limits for a `DatabaseRegistry`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RegistryLimits {
+    /// Maximum number of databases open at once.
+    pub max_open_databases: usize,
+    /// How long an unused database stays open before `close_idle_databases` closes it.
+    pub idle_timeout: Duration,
+}
+
+impl Default for RegistryLimits {
+    fn default() -> Self {
+        Self {
+            max_open_databases: DEFAULT_MAX_OPEN_DATABASES,
+            idle_timeout: DEFAULT_IDLE_TIMEOUT,
+        }
+    }
+}
+
+/// An open database and the time it was last handed out.
+struct RegistryEntry {
+    /// The shared database instance.
+    database: Arc<RwLock<Database>>,
+    /// Milliseconds since the registry's epoch at the last `get_or_create`.
+    last_accessed_ms: AtomicU64,
+    /// Query cursors open on the database, shared by its connections.
+    cursors: Arc<CursorTable>,
+}
+
+impl RegistryEntry {
+    /// Whether any caller other than the registry holds the database, or a
+    /// cursor is open on it.
+    fn is_in_use(&self) -> bool {
+        Arc::strong_count(&self.database) > 1
+            || self
+                .cursors
+                .open_cursor_count()
+                .map_or(true, |count| count > 0)
+    }
+
+    /// Close cursors that exceeded their idle timeout.
+    ///
+    /// Skips the database if it is locked for writing; a busy database is
+    /// swept on a later pass or by its next cursor operation.
+    fn expire_idle_cursors(&self, app_api_key: &str) {
+        let Ok(database) = self.database.try_read() else {
+            return;
+        };
+        match self.cursors.expire_idle(&database) {
+            Ok(0) => {}
+            Ok(expired) => {
+                tracing::info!("Expired {} idle cursors for app '{}'", expired, app_api_key);
+            }
+            Err(e) => tracing::error!("Cursor sweep for app '{}' failed: {e}", app_api_key),
+        }
+    }
+}
+
 /// Registry of open databases, keyed by `app_api_key`.
 ///
 /// Enables multiple connections with the same `app_api_key` to share one Database instance.
@@ -34,11 +116,21 @@ const MAX_API_KEY_LENGTH: usize = 256;
 pub struct DatabaseRegistry {
     /// Map from `app_api_key` to shared database instance.
     /// Uses `RwLock` to allow concurrent reads of the map.
-    databases: RwLock<HashMap<String, Arc<RwLock<Database>>>>,
+    databases: RwLock<HashMap<String, RegistryEntry>>,
     /// Base directory where database files are stored.
     base_directory: PathBuf,
     /// Shared buffer pool for all databases.
     buffer_pool: Arc<BufferPool>,
+    /// Open-count and idle limits.
+    limits: RegistryLimits,
+    /// Node ID databases are opened as, or `None` to use each file's own.
+    node_id: Option<u32>,
+    /// Whether databases are opened keeping entity versions.
+    entity_versions: bool,
+    /// Capacity databases' WALs may grow to, or `None` to keep them fixed.
+    max_wal_capacity: Option<u64>,
+    /// Reference point for `RegistryEntry::last_accessed_ms`.
+    epoch: Instant,
 }
 
 impl DatabaseRegistry {
@@ -52,11 +144,7 @@ impl DatabaseRegistry {
     /// Initializes a shared 2GB buffer pool for all databases.
     #[must_use]
     pub fn new(base_directory: PathBuf) -> Self {
-        Self {
-            databases: RwLock::new(HashMap::new()),
-            base_directory,
-            buffer_pool: BufferPool::new(DEFAULT_POOL_CAPACITY),
-        }
+        Self::with_pool_capacity(base_directory, DEFAULT_POOL_CAPACITY)
     }
 
     /// Create a new database registry with a custom buffer pool capacity.
@@ -71,13 +159,76 @@ impl DatabaseRegistry {
             databases: RwLock::new(HashMap::new()),
             base_directory,
             buffer_pool: BufferPool::new(pool_capacity),
+            limits: RegistryLimits::default(),
+            node_id: None,
+            entity_versions: false,
+            max_wal_capacity: None,
+            epoch: Instant::now(),
         }
     }
 
+    /// Replace the registry's lifecycle limits.
+    ///
+    /// # Pre-conditions
+    /// - `limits.max_open_databases` is at least 1
+    #[must_use]
+    pub const fn with_limits(mut self, limits: RegistryLimits) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    /// Open every database as node `node_id`.
+    ///
+    /// New databases store the ID, and opening one that stores another ID
+    /// fails with `DatabaseError::NodeIdMismatch` (see
+    /// `Database::open_with_options`).
+    #[must_use]
+    pub const fn with_node_id(mut self, node_id: u32) -> Self {
+        self.node_id = Some(node_id);
+        self
+    }
+
+    /// Keep entity versions in every database the registry opens, if
+    /// `enabled` (see `Database::enable_entity_versions`).
+    #[must_use]
+    pub const fn with_entity_versions(mut self, enabled: bool) -> Self {
+        self.entity_versions = enabled;
+        self
+    }
+
+    /// Let every database the registry opens grow its WAL up to
+    /// `max_capacity` bytes, if set (see `Database::enable_wal_growth`).
+    #[must_use]
+    pub const fn with_max_wal_capacity(mut self, max_capacity: Option<u64>) -> Self {
+        self.max_wal_capacity = max_capacity;
+        self
+    }
+
+    /// The registry's lifecycle limits.
+    #[must_use]
+    pub const fn limits(&self) -> RegistryLimits {
+        self.limits
+    }
+
+    /// Number of databases currently open.
+    ///
+    /// # Errors
+    ///
+    /// Returns `DatabaseError::LockPoisoned` if the registry lock is poisoned.
+    pub fn open_database_count(&self) -> Result<usize, DatabaseError> {
+        let databases = self
+            .databases
+            .read()
+            .map_err(|_| DatabaseError::LockPoisoned)?;
+        Ok(databases.len())
+    }
+
     /// Get or create a database for the given `app_api_key`.
     ///
     /// If a database for this key already exists, returns a reference to it.
     /// Otherwise, opens or creates a new database file and stores it in the registry.
+    /// If the registry is at `max_open_databases`, the least recently used idle
+    /// database is closed first.
     ///
     /// # Pre-conditions
     ///
@@ -88,18 +239,22 @@ impl DatabaseRegistry {
     ///
     /// Returns an error if:
     /// - The registry lock is poisoned
+    /// - The registry is at its open limit and every open database is in use
     /// - The database cannot be opened or created
     #[allow(clippy::disallowed_methods)] // Arc::clone is safe and expected
     #[allow(clippy::significant_drop_tightening)] // False positive - we need the lock held during insert
     pub fn get_or_create(&self, app_api_key: &str) -> Result<Arc<RwLock<Database>>, DatabaseError> {
+        let now_ms = self.elapsed_ms();
+
         // Fast path: check if database already exists (read lock only)
         {
             let databases = self
                 .databases
                 .read()
                 .map_err(|_| DatabaseError::LockPoisoned)?;
-            if let Some(db) = databases.get(app_api_key) {
-                return Ok(Arc::clone(db));
+            if let Some(entry) = databases.get(app_api_key) {
+                entry.last_accessed_ms.store(now_ms, Ordering::Relaxed);
+                return Ok(Arc::clone(&entry.database));
             }
         }
 
@@ -110,14 +265,34 @@ impl DatabaseRegistry {
             .map_err(|_| DatabaseError::LockPoisoned)?;
 
         // Double-check: another thread may have created it while we waited for the write lock
-        if let Some(db) = databases.get(app_api_key) {
-            return Ok(Arc::clone(db));
+        if let Some(entry) = databases.get(app_api_key) {
+            entry.last_accessed_ms.store(now_ms, Ordering::Relaxed);
+            return Ok(Arc::clone(&entry.database));
+        }
+
+        if databases.len() >= self.limits.max_open_databases {
+            let least_recently_used = databases
+                .iter()
+                .filter(|(_, entry)| !entry.is_in_use())
+                .min_by_key(|(_, entry)| entry.last_accessed_ms.load(Ordering::Relaxed))
+                .map(|(key, _)| key.clone());
+            let Some(key) = least_recently_used else {
+                return Err(DatabaseError::TooManyOpenDatabases(
+                    self.limits.max_open_databases,
+                ));
+            };
+            if let Some(entry) = databases.remove(&key) {
+                close_entry(&key, entry)?;
+            }
         }
 
         // Create the database
         let db_path = self.base_directory.join(format!("{app_api_key}.db"));
-        let (database, recovery_result) =
-            Database::open_or_create(&db_path, Arc::clone(&self.buffer_pool))?;
+        let pool = Arc::clone(&self.buffer_pool);
+        let (mut database, recovery_result) = match self.node_id {
+            Some(node_id) => Database::open_or_create_with_node_id(&db_path, pool, node_id)?,
+            None => Database::open_or_create(&db_path, pool)?,
+        };
 
         if let Some(result) = recovery_result {
             tracing::info!(
@@ -129,24 +304,198 @@ impl DatabaseRegistry {
             );
         }
 
-        // Get the GC notify handle before wrapping in RwLock
+        if self.entity_versions {
+            database.enable_entity_versions();
+        }
+        if let Some(max_capacity) = self.max_wal_capacity {
+            database.enable_wal_growth(max_capacity);
+        }
+
+        // Get the notify handles before wrapping in RwLock
         let gc_notify = database.gc_notify();
+        let checkpoint_notify = database.checkpoint_notify();
 
         let db_arc = Arc::new(RwLock::new(database));
-        databases.insert(app_api_key.to_string(), Arc::clone(&db_arc));
+        databases.insert(
+            app_api_key.to_string(),
+            RegistryEntry {
+                database: Arc::clone(&db_arc),
+                last_accessed_ms: AtomicU64::new(now_ms),
+                cursors: Arc::new(CursorTable::default()),
+            },
+        );
 
-        // Spawn background GC task with weak reference to prevent cycles
-        // The task will exit cleanly when the database is dropped
+        // Spawn background GC and checkpoint tasks with weak references to prevent cycles
+        // The tasks will exit cleanly when the database is dropped
         // Only spawn if we're inside a tokio runtime (may not be in some test contexts)
         if tokio::runtime::Handle::try_current().is_ok() {
             let weak_db = Arc::downgrade(&db_arc);
             let _gc_handle = spawn_gc_task(weak_db, gc_notify, GcConfig::default());
+            let weak_db = Arc::downgrade(&db_arc);
+            let _checkpoint_handle = spawn_checkpoint_task(weak_db, checkpoint_notify);
         }
 
         tracing::info!("Opened database for app '{}'", app_api_key);
 
         Ok(db_arc)
     }
+
+    /// Get the database for `app_api_key` if one exists, without creating it.
+    ///
+    /// An existing database that is closed is opened as by `get_or_create`.
+    ///
+    /// # Pre-conditions
+    ///
+    /// - `app_api_key` must be valid; see `validate_api_key`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the registry lock is poisoned or the database
+    /// cannot be opened.
+    pub fn get_existing(
+        &self,
+        app_api_key: &str,
+    ) -> Result<Option<Arc<RwLock<Database>>>, DatabaseError> {
+        let is_open = self
+            .databases
+            .read()
+            .map_err(|_| DatabaseError::LockPoisoned)?
+            .contains_key(app_api_key);
+        if !is_open
+            && !self
+                .base_directory
+                .join(format!("{app_api_key}.db"))
+                .exists()
+        {
+            return Ok(None);
+        }
+        self.get_or_create(app_api_key).map(Some)
+    }
+
+    /// Get the cursor table of an open database.
+    ///
+    /// Returns `None` if no database is open for `app_api_key`. Call after
+    /// `get_or_create`; the database stays open while the caller holds it.
+    ///
+    /// # Errors
+    ///
+    /// Returns `DatabaseError::LockPoisoned` if the registry lock is poisoned.
+    #[allow(clippy::disallowed_methods)] // Arc::clone is safe and expected
+    pub fn cursors(&self, app_api_key: &str) -> Result<Option<Arc<CursorTable>>, DatabaseError> {
+        let databases = self
+            .databases
+            .read()
+            .map_err(|_| DatabaseError::LockPoisoned)?;
+        Ok(databases
+            .get(app_api_key)
+            .map(|entry| Arc::clone(&entry.cursors)))
+    }
+
+    /// Close every database that is not in use and has not been requested for
+    /// at least the idle timeout.
+    ///
+    /// Idle cursors are expired first, so a database kept open only by
+    /// abandoned cursors is closed too.
+    ///
+    /// # Returns
+    /// The number of databases closed.
+    ///
+    /// # Post-conditions
+    /// - No closed database was referenced outside the registry
+    /// - Closed databases are checkpointed and reopen on the next `get_or_create`
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the registry lock is poisoned or a database fails to
+    /// close. Databases closed before the failure stay closed.
+    #[allow(clippy::disallowed_methods)] // Keys are cloned to release the map borrow before removal
+    pub fn close_idle_databases(&self) -> Result<usize, DatabaseError> {
+        let now_ms = self.elapsed_ms();
+        let idle_timeout_ms =
+            u64::try_from(self.limits.idle_timeout.as_millis()).unwrap_or(u64::MAX);
+
+        let mut databases = self
+            .databases
+            .write()
+            .map_err(|_| DatabaseError::LockPoisoned)?;
+        for (key, entry) in databases.iter() {
+            entry.expire_idle_cursors(key);
+        }
+        let idle_keys: Vec<String> = databases
+            .iter()
+            .filter(|(_, entry)| {
+                let last_accessed_ms = entry.last_accessed_ms.load(Ordering::Relaxed);
+                !entry.is_in_use() && now_ms.saturating_sub(last_accessed_ms) >= idle_timeout_ms
+            })
+            .map(|(key, _)| key.clone())
+            .collect();
+
+        let mut closed = 0;
+        for key in idle_keys {
+            if let Some(entry) = databases.remove(&key) {
+                close_entry(&key, entry)?;
+                closed += 1;
+            }
+        }
+        drop(databases);
+        Ok(closed)
+    }
+
+    /// Milliseconds since the registry was created.
+    fn elapsed_ms(&self) -> u64 {
+        u64::try_from(self.epoch.elapsed().as_millis()).unwrap_or(u64::MAX)
+    }
+}
+
+/// Close a database removed from the registry.
+///
+/// If a background task briefly holds a strong reference, the database cannot be
+/// closed explicitly; it is dropped when that reference goes away instead, which
+/// is safe because every commit is already durable in the WAL.
+fn close_entry(app_api_key: &str, entry: RegistryEntry) -> Result<(), DatabaseError> {
+    let Ok(lock) = Arc::try_unwrap(entry.database) else {
+        tracing::info!("Released database for app '{}'", app_api_key);
+        return Ok(());
+    };
+    let database = lock.into_inner().map_err(|_| DatabaseError::LockPoisoned)?;
+
+    // Wake the background tasks so they observe the dropped database and exit
+    let gc_notify = database.gc_notify();
+    let checkpoint_notify = database.checkpoint_notify();
+    database.close()?;
+    gc_notify.notify_one();
+    checkpoint_notify.notify_one();
+
+    tracing::info!("Closed database for app '{}'", app_api_key);
+    Ok(())
+}
+
+/// Spawn a background task that periodically closes idle databases.
+///
+/// # Arguments
+/// * `registry` - Weak reference to the registry to sweep
+/// * `interval` - Time between sweeps
+///
+/// # Invariants
+/// - Exits cleanly when the registry is dropped
+#[must_use]
+pub fn spawn_idle_sweep_task(
+    registry: Weak<DatabaseRegistry>,
+    interval: Duration,
+) -> tokio::task::JoinHandle<()> {
+    tokio::spawn(async move {
+        loop {
+            tokio::time::sleep(interval).await;
+            let Some(registry) = registry.upgrade() else {
+                break;
+            };
+            match registry.close_idle_databases() {
+                Ok(0) => {}
+                Ok(closed) => tracing::info!("Closed {} idle databases", closed),
+                Err(e) => tracing::error!("Idle database sweep failed: {e}"),
+            }
+        }
+    })
 }
 
 /// Error returned when validating an `app_api_key`.
@@ -217,6 +566,232 @@ pub fn validate_api_key(key: &str) -> Result<(), ApiKeyValidationError> {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::query::{Pattern, PatternElement, Query, QueryEngine};
+    use crate::types::{AttributeId, EntityId, TripleValue};
+    use tempfile::tempdir;
+
+    fn create_test_registry(limits: RegistryLimits) -> (tempfile::TempDir, DatabaseRegistry) {
+        let dir = tempdir().expect("create temp dir");
+        let registry =
+            DatabaseRegistry::with_pool_capacity(dir.path().to_path_buf(), 100).with_limits(limits);
+        (dir, registry)
+    }
+
+    fn insert_number(database: &Arc<RwLock<Database>>, value: f64) {
+        let mut db = database.write().expect("lock should not be poisoned");
+        let mut txn = db.begin(1).expect("begin");
+        txn.insert(
+            EntityId([1u8; 16]),
+            AttributeId([2u8; 16]),
+            TripleValue::Number(value),
+        )
+        .expect("insert");
+        txn.commit().expect("commit");
+        drop(db);
+    }
+
+    #[test]
+    fn test_get_or_create_returns_shared_instance() {
+        let (_dir, registry) = create_test_registry(RegistryLimits::default());
+
+        let first = registry.get_or_create("app").expect("open app");
+        let second = registry.get_or_create("app").expect("open app again");
+
+        assert!(Arc::ptr_eq(&first, &second));
+        assert_eq!(registry.open_database_count().expect("count"), 1);
+    }
+
+    #[test]
+    fn test_close_idle_databases_skips_databases_in_use() {
+        let (_dir, registry) = create_test_registry(RegistryLimits {
+            max_open_databases: DEFAULT_MAX_OPEN_DATABASES,
+            idle_timeout: Duration::ZERO,
+        });
+
+        let in_use = registry.get_or_create("in-use").expect("open in-use");
+        drop(registry.get_or_create("idle").expect("open idle"));
+
+        let closed = registry.close_idle_databases().expect("sweep");
+        assert_eq!(closed, 1);
+        assert_eq!(registry.open_database_count().expect("count"), 1);
+
+        let reopened = registry.get_or_create("in-use").expect("reopen in-use");
+        assert!(Arc::ptr_eq(&in_use, &reopened));
+    }
+
+    #[test]
+    fn test_close_idle_databases_keeps_databases_with_open_cursors() {
+        let (_dir, registry) = create_test_registry(RegistryLimits {
+            max_open_databases: DEFAULT_MAX_OPEN_DATABASES,
+            idle_timeout: Duration::ZERO,
+        });
+
+        let database = registry.get_or_create("app").expect("open app");
+        insert_number(&database, 1.0);
+        let cursors = registry
+            .cursors("app")
+            .expect("cursors")
+            .expect("app is open");
+        let cursor_id = {
+            let db = database.read().expect("lock should not be poisoned");
+            let snapshot = db.begin_readonly().expect("begin readonly");
+            let query = Query::new().find("e").where_pattern(Pattern::new(
+                PatternElement::var("e"),
+                PatternElement::var("a"),
+                PatternElement::var("v"),
+            ));
+            let cursor_id = QueryEngine::new(&snapshot)
+                .open_cursor(&cursors, query)
+                .expect("open cursor");
+            let txn_id = snapshot.close();
+            db.release_snapshot(txn_id).expect("release snapshot");
+            cursor_id
+        };
+        drop(database);
+
+        assert_eq!(registry.close_idle_databases().expect("sweep"), 0);
+
+        let database = registry.get_or_create("app").expect("reopen app");
+        cursors
+            .close(
+                &database.read().expect("lock should not be poisoned"),
+                cursor_id,
+            )
+            .expect("close cursor");
+        drop(database);
+
+        assert_eq!(registry.close_idle_databases().expect("sweep"), 1);
+        assert_eq!(registry.cursors("app").expect("cursors").map(|_| ()), None);
+    }
+
+    #[test]
+    fn test_close_idle_databases_respects_idle_timeout() {
+        let (_dir, registry) = create_test_registry(RegistryLimits::default());
+
+        drop(registry.get_or_create("app").expect("open app"));
+
+        let closed = registry.close_idle_databases().expect("sweep");
+        assert_eq!(closed, 0);
+        assert_eq!(registry.open_database_count().expect("count"), 1);
+    }
+
+    #[test]
+    fn test_closed_database_reopens_with_data() {
+        let (_dir, registry) = create_test_registry(RegistryLimits {
+            max_open_databases: DEFAULT_MAX_OPEN_DATABASES,
+            idle_timeout: Duration::ZERO,
+        });
+
+        let database = registry.get_or_create("app").expect("open app");
+        insert_number(&database, 42.0);
+        drop(database);
+        assert_eq!(registry.close_idle_databases().expect("sweep"), 1);
+
+        let database = registry.get_or_create("app").expect("reopen app");
+        let db = database.read().expect("lock should not be poisoned");
+        let record = db
+            .begin_readonly()
+            .expect("begin readonly")
+            .get(&EntityId([1u8; 16]), &AttributeId([2u8; 16]))
+            .expect("get");
+        drop(db);
+        assert_eq!(
+            record.map(|record| record.value),
+            Some(TripleValue::Number(42.0))
+        );
+    }
+
+    #[test]
+    fn test_get_existing_does_not_create_databases() {
+        let (_dir, registry) = create_test_registry(RegistryLimits {
+            max_open_databases: DEFAULT_MAX_OPEN_DATABASES,
+            idle_timeout: Duration::ZERO,
+        });
+        assert!(registry.get_existing("app").expect("lookup").is_none());
+        assert_eq!(registry.open_database_count().expect("count"), 0);
+
+        // Closed databases are reopened
+        drop(registry.get_or_create("app").expect("create app"));
+        assert_eq!(registry.close_idle_databases().expect("sweep"), 1);
+        assert!(registry.get_existing("app").expect("lookup").is_some());
+        assert_eq!(registry.open_database_count().expect("count"), 1);
+    }
+
+    #[test]
+    fn test_registry_rejects_database_of_another_node() {
+        let dir = tempdir().expect("create temp dir");
+        let registry =
+            DatabaseRegistry::with_pool_capacity(dir.path().to_path_buf(), 100).with_node_id(1);
+        let database = registry.get_or_create("app").expect("open app");
+        assert_eq!(database.read().expect("lock").node_id(), 1);
+        drop(database);
+        drop(registry);
+
+        let registry =
+            DatabaseRegistry::with_pool_capacity(dir.path().to_path_buf(), 100).with_node_id(2);
+        assert!(matches!(
+            registry.get_or_create("app"),
+            Err(DatabaseError::NodeIdMismatch {
+                configured: 2,
+                stored: 1,
+            })
+        ));
+    }
+
+    #[test]
+    fn test_open_limit_evicts_least_recently_used_idle_database() {
+        let (_dir, registry) = create_test_registry(RegistryLimits {
+            max_open_databases: 2,
+            idle_timeout: DEFAULT_IDLE_TIMEOUT,
+        });
+
+        let first = registry.get_or_create("first").expect("open first");
+        drop(registry.get_or_create("second").expect("open second"));
+        let third = registry.get_or_create("third").expect("open third");
+
+        assert_eq!(registry.open_database_count().expect("count"), 2);
+        let first_again = registry.get_or_create("first").expect("get first");
+        assert!(Arc::ptr_eq(&first, &first_again));
+        let third_again = registry.get_or_create("third").expect("get third");
+        assert!(Arc::ptr_eq(&third, &third_again));
+    }
+
+    #[test]
+    fn test_open_limit_fails_when_all_databases_in_use() {
+        let (_dir, registry) = create_test_registry(RegistryLimits {
+            max_open_databases: 1,
+            idle_timeout: DEFAULT_IDLE_TIMEOUT,
+        });
+
+        let _first = registry.get_or_create("first").expect("open first");
+        let result = registry.get_or_create("second");
+
+        assert!(matches!(
+            result,
+            Err(DatabaseError::TooManyOpenDatabases(1))
+        ));
+    }
+
+    #[test]
+    fn test_change_notifications_do_not_cross_databases() {
+        let (_dir, registry) = create_test_registry(RegistryLimits::default());
+
+        let tenant_a = registry.get_or_create("tenant-a").expect("open tenant-a");
+        let tenant_b = registry.get_or_create("tenant-b").expect("open tenant-b");
+        let mut receiver_a = tenant_a
+            .read()
+            .expect("lock should not be poisoned")
+            .subscribe_to_changes(99);
+        let mut receiver_b = tenant_b
+            .read()
+            .expect("lock should not be poisoned")
+            .subscribe_to_changes(99);
+
+        insert_number(&tenant_b, 1.0);
+
+        assert!(receiver_a.try_recv().is_err());
+        assert!(receiver_b.try_recv().is_ok());
+    }
 
     #[test]
     fn test_validate_api_key_valid() {
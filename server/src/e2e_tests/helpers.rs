@@ -1,21 +1,22 @@
 //! Common helpers for end-to-end tests.
 
 use std::path::PathBuf;
-use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::{Arc, RwLock};
 
+use crate::DatabaseRegistry;
+use crate::auth::ConfigRegistry;
 use crate::client_connection::ClientConnection;
+use crate::config::ServerConfig;
+use crate::constants::{DEFAULT_MAX_MESSAGE_SIZE, DEFAULT_MAX_SUBSCRIPTIONS_PER_CONNECTION};
 use crate::proto;
 use crate::storage::buffer_pool::BufferPool;
 use crate::storage::{Database, FilteredChangeReceiver};
+use crate::types::ValueLimits;
+use crate::websocket::{AppState, router};
 
-/// Counter for generating unique test database IDs.
-static TEST_DB_COUNTER: AtomicU64 = AtomicU64::new(0);
-
-/// RAII guard that cleans up the database file on drop.
+/// A connected client on its own in-memory database.
 pub struct TestClient {
     pub client: ClientConnection,
-    db_path: PathBuf,
     /// Shared database reference for creating sibling clients.
     shared_db: Arc<RwLock<Database>>,
 }
@@ -27,32 +28,35 @@ impl TestClient {
     /// This is for tests that don't need to test the connection handshake.
     #[must_use]
     pub fn new() -> Self {
-        let temp_dir = std::env::temp_dir();
-        let instance_id = TEST_DB_COUNTER.fetch_add(1, Ordering::Relaxed);
-        let db_path = temp_dir.join(format!("e2e_test_{instance_id}.db"));
-
-        // Remove if exists
-        let _ = std::fs::remove_file(&db_path);
+        Self::with_value_limits(ValueLimits::default())
+    }
 
-        // Create buffer pool for the test database
-        let pool = BufferPool::new(100);
+    /// Create a new test client with a fresh database and the given value limits.
+    #[must_use]
+    pub fn with_value_limits(value_limits: ValueLimits) -> Self {
+        Self::with_database(new_test_database(), value_limits)
+    }
 
-        #[allow(clippy::expect_used)]
-        let database = Database::create(&db_path, pool).expect("Failed to create test database");
+    /// Create a new test client with a fresh database that keeps entity
+    /// versions.
+    #[must_use]
+    pub fn with_entity_versions() -> Self {
+        let mut database = new_test_database();
+        database.enable_entity_versions();
+        Self::with_database(database, ValueLimits::default())
+    }
 
+    /// Create a test client connected to `database`.
+    fn with_database(database: Database, value_limits: ValueLimits) -> Self {
         // Database now handles broadcast channel internally
         // ClientConnection::new() puts the connection in Connected state
-        let client = ClientConnection::new(database);
+        let client = ClientConnection::new(database).with_value_limits(value_limits);
         #[allow(clippy::expect_used)]
         let shared_db = client
             .shared_database()
             .expect("Client should be connected");
 
-        Self {
-            client,
-            db_path,
-            shared_db,
-        }
+        Self { client, shared_db }
     }
 
     /// Create a sibling client that shares the same database.
@@ -120,16 +124,18 @@ impl TestClient {
     }
 }
 
-impl Drop for TestClient {
-    fn drop(&mut self) {
-        let _ = std::fs::remove_file(&self.db_path);
-    }
+/// Create an in-memory database for a test client.
+fn new_test_database() -> Database {
+    // Create buffer pool for the test database
+    let pool = BufferPool::new(100);
+
+    #[allow(clippy::expect_used)]
+    Database::create_in_memory(pool).expect("Failed to create test database")
 }
 
 /// A sibling client that shares the same database as its parent `TestClient`.
 ///
 /// This represents a separate WebSocket connection to the same server.
-/// It does not own the database file and does not clean it up on drop.
 pub struct SiblingClient {
     pub client: ClientConnection,
 }
@@ -243,6 +249,15 @@ pub fn status_code(response: &proto::ServerResponse) -> i32 {
     response.status.as_ref().map_or(-1, |s| s.code)
 }
 
+/// Get the status message from a response, or `""` if it has no status.
+#[must_use]
+pub fn status_message(response: &proto::ServerResponse) -> &str {
+    response
+        .status
+        .as_ref()
+        .map_or("", |status| status.message.as_str())
+}
+
 /// Extract the inner value from a query result row's first column.
 #[must_use]
 pub fn extract_value(response: &proto::ServerResponse, row: usize) -> Option<&proto::TripleValue> {
@@ -324,6 +339,23 @@ pub fn is_undefined_at(response: &proto::ServerResponse, row: usize, col: usize)
         .is_some_and(|v| v.is_undefined)
 }
 
+/// Collect the string values in column `col` of every row.
+///
+/// # Panics
+///
+/// Panics if a row has no string value in that column.
+#[must_use]
+pub fn get_strings_at(response: &proto::ServerResponse, col: usize) -> Vec<String> {
+    (0..response.rows.len())
+        .map(|row| {
+            #[allow(clippy::expect_used)]
+            get_string_at(response, row, col)
+                .expect("row should have a string value")
+                .to_owned()
+        })
+        .collect()
+}
+
 // =============================================================================
 // HLC Helpers
 // =============================================================================
@@ -339,3 +371,247 @@ pub fn new_hlc(seed: u64) -> proto::HlcTimestamp {
         node_id: 1,
     }
 }
+
+// =============================================================================
+// Request Helpers
+// =============================================================================
+
+/// The attribute `name_triple` writes.
+pub const NAME: u8 = 1;
+
+/// A query pattern variable.
+#[must_use]
+pub fn variable(label: &str) -> proto::QueryPatternVariable {
+    proto::QueryPatternVariable {
+        label: Some(label.to_string()),
+    }
+}
+
+/// The pattern `[?e attribute ?value_variable]`.
+#[must_use]
+pub fn pattern(attribute_id: [u8; 16], value_variable: &str) -> proto::QueryPattern {
+    proto::QueryPattern {
+        entity: Some(proto::query_pattern::Entity::EntityVariable(variable("e"))),
+        attribute: Some(proto::query_pattern::Attribute::AttributeId(
+            attribute_id.to_vec(),
+        )),
+        value_group: Some(proto::query_pattern::ValueGroup::ValueVariable(variable(
+            value_variable,
+        ))),
+    }
+}
+
+/// A string triple with the HLC of `new_hlc(hlc_seed)`.
+#[must_use]
+pub fn string_triple(
+    entity_id: [u8; 16],
+    attribute_id: [u8; 16],
+    value: &str,
+    hlc_seed: u64,
+) -> proto::Triple {
+    proto::Triple {
+        entity_id: Some(entity_id.to_vec()),
+        attribute_id: Some(attribute_id.to_vec()),
+        value: Some(proto::TripleValue {
+            value: Some(proto::triple_value::Value::String(value.to_string())),
+        }),
+        hlc: Some(new_hlc(hlc_seed)),
+        attribute_name: None,
+    }
+}
+
+/// A triple naming `entity` with the `NAME` attribute.
+#[must_use]
+pub fn name_triple(entity: u8, name: &str) -> proto::Triple {
+    string_triple(new_entity_id(entity), new_attribute_id(NAME), name, 1)
+}
+
+/// Send a message with the given payload.
+pub fn send(
+    client: &mut TestClient,
+    payload: proto::client_message::Payload,
+) -> proto::ServerResponse {
+    client.handle_message(proto::ClientMessage {
+        request_id: Some(1),
+        payload: Some(payload),
+    })
+}
+
+/// Upsert one string triple with the HLC of `new_hlc(1)`.
+///
+/// # Panics
+///
+/// Panics if the server rejects the write.
+pub fn insert(client: &mut TestClient, entity_id: [u8; 16], attribute_id: [u8; 16], value: &str) {
+    upsert(client, string_triple(entity_id, attribute_id, value, 1));
+}
+
+/// Upsert the string triple `"v{seed}"` with the HLC of `new_hlc(seed)`.
+///
+/// # Panics
+///
+/// Panics if the server rejects the write.
+pub fn insert_seeded(
+    client: &mut TestClient,
+    entity_id: [u8; 16],
+    attribute_id: [u8; 16],
+    seed: u64,
+) {
+    upsert(
+        client,
+        string_triple(entity_id, attribute_id, &format!("v{seed}"), seed),
+    );
+}
+
+/// Upsert one triple of any value type with the HLC of `new_hlc(1)`.
+///
+/// # Panics
+///
+/// Panics if the server rejects the write.
+pub fn insert_value(
+    client: &mut TestClient,
+    entity_id: [u8; 16],
+    attribute_id: [u8; 16],
+    value: proto::triple_value::Value,
+) {
+    upsert(
+        client,
+        proto::Triple {
+            entity_id: Some(entity_id.to_vec()),
+            attribute_id: Some(attribute_id.to_vec()),
+            value: Some(proto::TripleValue { value: Some(value) }),
+            hlc: Some(new_hlc(1)),
+            attribute_name: None,
+        },
+    );
+}
+
+/// Upsert one triple by attribute name with the HLC of `new_hlc(1)`.
+///
+/// # Panics
+///
+/// Panics if the server rejects the write.
+pub fn insert_named(
+    client: &mut TestClient,
+    entity_id: [u8; 16],
+    attribute_name: &str,
+    value: proto::triple_value::Value,
+) {
+    upsert(
+        client,
+        proto::Triple {
+            entity_id: Some(entity_id.to_vec()),
+            attribute_id: None,
+            value: Some(proto::TripleValue { value: Some(value) }),
+            hlc: Some(new_hlc(1)),
+            attribute_name: Some(attribute_name.to_string()),
+        },
+    );
+}
+
+/// Upsert one triple and assert the server accepted it.
+fn upsert(client: &mut TestClient, triple: proto::Triple) {
+    let response = client.handle_message(proto::ClientMessage {
+        request_id: Some(100),
+        payload: Some(proto::client_message::Payload::TripleUpdateRequest(
+            proto::TripleUpdateRequest {
+                triples: vec![triple],
+                mode: proto::WriteMode::Upsert.into(),
+                expected_versions: vec![],
+            },
+        )),
+    });
+    assert!(is_ok(&response));
+}
+
+/// The number of entities with a committed value for `attribute_id`.
+///
+/// # Panics
+///
+/// Panics if the query fails.
+pub fn entity_count(client: &mut TestClient, attribute_id: [u8; 16]) -> usize {
+    let response = client.handle_message(proto::ClientMessage {
+        request_id: Some(100),
+        payload: Some(proto::client_message::Payload::Query(proto::QueryRequest {
+            find: vec![variable("e")],
+            r#where: vec![pattern(attribute_id, "value")],
+            optional: vec![],
+            where_not: vec![],
+            arrow_ipc: false,
+            limit: None,
+            offset: None,
+            order_by: vec![],
+            aggregates: vec![],
+            group_by: vec![],
+            distinct: false,
+        })),
+    });
+    assert!(is_ok(&response));
+    response.rows.len()
+}
+
+/// Deliver every pending notification to the sibling's subscriptions.
+///
+/// Like the WebSocket handler, this turns each broadcast notification into
+/// per-subscription updates with `ClientConnection::updates_for`.
+pub fn drain_updates(
+    sibling: &SiblingClient,
+    change_rx: &mut FilteredChangeReceiver,
+) -> Vec<proto::SubscriptionUpdate> {
+    let mut updates = Vec::new();
+    while let Ok(notification) = change_rx.try_recv() {
+        updates.extend(sibling.client.updates_for(&notification));
+    }
+    updates
+}
+
+/// Serve the sync protocol on an ephemeral port and return its WebSocket URL.
+///
+/// The returned `TempDir` holds the databases and must outlive the server.
+pub async fn start_server() -> (tempfile::TempDir, String) {
+    start_server_with_max_message_size(DEFAULT_MAX_MESSAGE_SIZE).await
+}
+
+/// Like `start_server`, with a limit on the size of client messages.
+pub async fn start_server_with_max_message_size(
+    max_message_size: usize,
+) -> (tempfile::TempDir, String) {
+    serve(max_message_size, ConfigRegistry::new()).await
+}
+
+/// Like `start_server`, authenticating apps with the configs in `configs`.
+pub async fn start_server_with_configs(configs: ConfigRegistry) -> (tempfile::TempDir, String) {
+    serve(DEFAULT_MAX_MESSAGE_SIZE, configs).await
+}
+
+/// Serve the sync protocol on an ephemeral port with the given settings.
+#[allow(clippy::expect_used)]
+async fn serve(max_message_size: usize, configs: ConfigRegistry) -> (tempfile::TempDir, String) {
+    let dir = tempfile::tempdir().expect("create temp dir");
+    let state = AppState {
+        registry: Arc::new(
+            DatabaseRegistry::with_pool_capacity(dir.path().to_path_buf(), 100).with_node_id(1),
+        ),
+        config: Arc::new(ServerConfig {
+            admin_app_api_key: "admin".to_string(),
+            database_directory: PathBuf::new(),
+            listen_port: 0,
+            value_limits: ValueLimits::default(),
+            buffer_pool_pages: 100,
+            node_id: 1,
+            max_subscriptions_per_connection: DEFAULT_MAX_SUBSCRIPTIONS_PER_CONNECTION,
+            max_message_size,
+            entity_versions: false,
+            max_wal_capacity: None,
+        }),
+        configs: Arc::new(configs),
+    };
+
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0")
+        .await
+        .expect("bind listener");
+    let addr = listener.local_addr().expect("local addr");
+    tokio::spawn(async move { axum::serve(listener, router(state)).await });
+
+    (dir, format!("ws://{addr}/ws"))
+}
@@ -7,10 +7,23 @@
 
 mod helpers;
 
+mod test_attribute_names;
+mod test_attribute_scope;
+mod test_batch_atomicity;
+mod test_bulk_load;
+mod test_client;
 mod test_columns;
+mod test_concurrent_updates;
 mod test_connect_request;
+mod test_connection_defaults;
+mod test_contains;
+mod test_cursor;
+mod test_delete;
+mod test_describe_schema;
 mod test_determinism;
 mod test_empty_triples;
+mod test_entity_subscription;
+mod test_entity_versions;
 mod test_hlc_conflict_resolution;
 mod test_insert_boolean;
 mod test_insert_multiple_entities;
@@ -19,18 +32,36 @@ mod test_insert_number;
 mod test_insert_string;
 mod test_invalid_attribute_id;
 mod test_invalid_entity_id;
+mod test_json_protocol;
+mod test_live_query_subscription;
 mod test_many_inserts;
+mod test_message_size_limit;
 mod test_missing_fields;
+mod test_query_after_update;
+mod test_query_aggregates;
+mod test_query_arrow;
 mod test_query_combined;
+mod test_query_distinct;
 mod test_query_empty_database;
+mod test_query_entity_set;
+mod test_query_limit;
 mod test_query_nonexistent;
 mod test_query_optional;
+mod test_query_order_by;
+mod test_query_presence;
+mod test_query_subscription;
 mod test_query_where_not;
+mod test_read_batch;
 mod test_request_id;
+mod test_rest_gateway;
 mod test_sequence;
 mod test_string_limits;
 mod test_subscription_basic;
+mod test_subscription_filter;
+mod test_subscription_history;
 mod test_subscription_multi_connection;
+mod test_transactions;
 mod test_update_changes_type;
 mod test_update_overwrites;
 mod test_update_response_format;
+mod test_write_modes;
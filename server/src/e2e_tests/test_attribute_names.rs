@@ -0,0 +1,132 @@
+//! Test referencing attributes by human-readable name.
+
+use crate::e2e_tests::helpers::{
+    TestClient, get_string_value, is_ok, new_entity_id, new_hlc, status_code,
+};
+use crate::proto;
+
+fn insert_by_name(
+    client: &mut TestClient,
+    request_id: u32,
+    entity_id: [u8; 16],
+    attribute_name: &str,
+    value: &str,
+    hlc_seed: u64,
+) -> proto::ServerResponse {
+    client.handle_message(proto::ClientMessage {
+        request_id: Some(request_id),
+        payload: Some(proto::client_message::Payload::TripleUpdateRequest(
+            proto::TripleUpdateRequest {
+                triples: vec![proto::Triple {
+                    entity_id: Some(entity_id.to_vec()),
+                    attribute_id: None,
+                    value: Some(proto::TripleValue {
+                        value: Some(proto::triple_value::Value::String(value.to_string())),
+                    }),
+                    hlc: Some(new_hlc(hlc_seed)),
+                    attribute_name: Some(attribute_name.to_string()),
+                }],
+                mode: proto::WriteMode::Upsert.into(),
+                expected_versions: vec![],
+            },
+        )),
+    })
+}
+
+fn query_by_name(
+    client: &mut TestClient,
+    request_id: u32,
+    entity_id: [u8; 16],
+    attribute_name: &str,
+) -> proto::ServerResponse {
+    client.handle_message(proto::ClientMessage {
+        request_id: Some(request_id),
+        payload: Some(proto::client_message::Payload::Query(proto::QueryRequest {
+            find: vec![proto::QueryPatternVariable {
+                label: Some("value".to_string()),
+            }],
+            r#where: vec![proto::QueryPattern {
+                entity: Some(proto::query_pattern::Entity::EntityId(entity_id.to_vec())),
+                attribute: Some(proto::query_pattern::Attribute::AttributeName(
+                    attribute_name.to_string(),
+                )),
+                value_group: Some(proto::query_pattern::ValueGroup::ValueVariable(
+                    proto::QueryPatternVariable {
+                        label: Some("value".to_string()),
+                    },
+                )),
+            }],
+            optional: vec![],
+            where_not: vec![],
+            arrow_ipc: false,
+            limit: None,
+            offset: None,
+            order_by: vec![],
+            aggregates: vec![],
+            group_by: vec![],
+            distinct: false,
+        })),
+    })
+}
+
+#[test]
+fn test_insert_and_query_by_attribute_name() {
+    let mut client = TestClient::new();
+    let entity_id = new_entity_id(1);
+
+    let insert_response = insert_by_name(&mut client, 1, entity_id, "email", "a@example.com", 1);
+    assert!(is_ok(&insert_response));
+
+    let query_response = query_by_name(&mut client, 2, entity_id, "email");
+    assert!(is_ok(&query_response));
+    assert_eq!(query_response.rows.len(), 1);
+    assert_eq!(get_string_value(&query_response, 0), Some("a@example.com"));
+}
+
+#[test]
+fn test_attribute_name_resolves_to_same_id() {
+    let mut client = TestClient::new();
+    let entity_id = new_entity_id(1);
+
+    let first = insert_by_name(&mut client, 1, entity_id, "email", "old@example.com", 1);
+    let second = insert_by_name(&mut client, 2, entity_id, "email", "new@example.com", 2);
+    assert!(is_ok(&first));
+    assert!(is_ok(&second));
+    assert_eq!(
+        first.triples[0].attribute_id,
+        second.triples[0].attribute_id
+    );
+
+    // The second insert overwrote the first rather than adding a new attribute
+    let query_response = query_by_name(&mut client, 3, entity_id, "email");
+    assert_eq!(query_response.rows.len(), 1);
+    assert_eq!(
+        get_string_value(&query_response, 0),
+        Some("new@example.com")
+    );
+}
+
+#[test]
+fn test_query_by_unregistered_attribute_name() {
+    let mut client = TestClient::new();
+    let entity_id = new_entity_id(1);
+
+    let insert_response = insert_by_name(&mut client, 1, entity_id, "email", "a@example.com", 1);
+    assert!(is_ok(&insert_response));
+
+    let query_response = query_by_name(&mut client, 2, entity_id, "phone");
+    assert!(is_ok(&query_response));
+    assert!(query_response.rows.is_empty());
+}
+
+#[test]
+fn test_insert_with_empty_attribute_name() {
+    let mut client = TestClient::new();
+
+    let response = insert_by_name(&mut client, 1, new_entity_id(1), "", "value", 1);
+
+    assert_eq!(
+        status_code(&response),
+        proto::google::rpc::Code::InvalidArgument as i32
+    );
+}
@@ -0,0 +1,339 @@
+//! End-to-end tests for per-app attribute scoping.
+//!
+//! An app whose `AppConfig` allows only attribute IDs starting with `0x01` may
+//! read and write those attributes, while every request touching an attribute
+//! starting with `0x02` fails with `PermissionDenied`. The config reaches the
+//! connection through `ConfigRegistry` when the app connects with a JWT.
+
+use std::sync::Arc;
+
+use jsonwebtoken::{Algorithm, EncodingKey, Header, encode};
+
+use crate::auth::{AppConfig, ConfigRegistry, JwtConfig};
+use crate::client_connection::ClientConnection;
+use crate::database_registry::DatabaseRegistry;
+use crate::e2e_tests::helpers::{
+    SiblingClient, TestClient, get_string_value, is_ok, new_entity_id, new_hlc, status_code,
+};
+use crate::proto;
+
+const APP_API_KEY: &str = "scoped_app";
+const SECRET: &[u8] = b"scoped-app-secret-that-is-long-enough";
+
+/// An attribute ID starting with `prefix`.
+fn attribute_id(prefix: u8, seed: u8) -> [u8; 16] {
+    let mut id = [seed; 16];
+    id[0] = prefix;
+    id
+}
+
+/// The scoped app's configuration: HS256 tokens, attributes `0x01..` only.
+fn scoped_config() -> AppConfig {
+    let jwt_config = JwtConfig::new_hs256(SECRET.to_vec()).expect("valid secret");
+    AppConfig::new(APP_API_KEY.to_string(), Some(jwt_config))
+        .with_allowed_attribute_prefixes(vec![vec![0x01]])
+}
+
+/// A token for the scoped app, signed with `secret`.
+fn token(secret: &[u8]) -> String {
+    let claims = serde_json::json!({ "sub": "user-1" });
+    encode(
+        &Header::new(Algorithm::HS256),
+        &claims,
+        &EncodingKey::from_secret(secret),
+    )
+    .expect("failed to create test token")
+}
+
+/// A connection scoped to the app, sharing the unrestricted client's database.
+fn scoped_sibling(client: &TestClient) -> SiblingClient {
+    let database = client.client.shared_database().expect("connected");
+    SiblingClient {
+        client: ClientConnection::new_shared(database).with_app_config(Arc::new(scoped_config())),
+    }
+}
+
+fn is_permission_denied(response: &proto::ServerResponse) -> bool {
+    status_code(response) == proto::google::rpc::Code::PermissionDenied as i32
+}
+
+fn update_request(triples: &[([u8; 16], [u8; 16], &str)]) -> proto::ClientMessage {
+    proto::ClientMessage {
+        request_id: Some(1),
+        payload: Some(proto::client_message::Payload::TripleUpdateRequest(
+            proto::TripleUpdateRequest {
+                triples: triples
+                    .iter()
+                    .enumerate()
+                    .map(|(seed, (entity_id, attribute_id, value))| proto::Triple {
+                        entity_id: Some(entity_id.to_vec()),
+                        attribute_id: Some(attribute_id.to_vec()),
+                        value: Some(proto::TripleValue {
+                            value: Some(proto::triple_value::Value::String((*value).to_string())),
+                        }),
+                        hlc: Some(new_hlc(seed as u64 + 1)),
+                        attribute_name: None,
+                    })
+                    .collect(),
+                mode: proto::WriteMode::Upsert.into(),
+                expected_versions: vec![],
+            },
+        )),
+    }
+}
+
+/// Query `[entity_id attribute ?value]`.
+fn query_request(
+    entity_id: [u8; 16],
+    attribute: proto::query_pattern::Attribute,
+) -> proto::ClientMessage {
+    proto::ClientMessage {
+        request_id: Some(2),
+        payload: Some(proto::client_message::Payload::Query(proto::QueryRequest {
+            find: vec![proto::QueryPatternVariable {
+                label: Some("value".to_string()),
+            }],
+            r#where: vec![proto::QueryPattern {
+                entity: Some(proto::query_pattern::Entity::EntityId(entity_id.to_vec())),
+                attribute: Some(attribute),
+                value_group: Some(proto::query_pattern::ValueGroup::ValueVariable(
+                    proto::QueryPatternVariable {
+                        label: Some("value".to_string()),
+                    },
+                )),
+            }],
+            ..Default::default()
+        })),
+    }
+}
+
+fn subscribe_request(attribute_id: Option<[u8; 16]>) -> proto::ClientMessage {
+    proto::ClientMessage {
+        request_id: Some(3),
+        payload: Some(proto::client_message::Payload::Subscribe(
+            proto::SubscribeRequest {
+                subscription_id: 1,
+                since_hlc: None,
+                query: None,
+                watch_entity_id: None,
+                until_hlc: None,
+                include_old_values: false,
+                entity_id: None,
+                attribute_id: attribute_id.map(|id| id.to_vec()),
+            },
+        )),
+    }
+}
+
+fn connect_request(auth_token: Option<String>) -> proto::ClientMessage {
+    proto::ClientMessage {
+        request_id: Some(1),
+        payload: Some(proto::client_message::Payload::Connect(
+            proto::ConnectRequest {
+                app_api_key: APP_API_KEY.to_string(),
+                auth_token,
+            },
+        )),
+    }
+}
+
+#[test]
+fn test_scoped_app_writes_only_allowed_attributes() {
+    let client = TestClient::new();
+    let mut scoped = scoped_sibling(&client);
+    let entity_id = new_entity_id(1);
+
+    let allowed = scoped.handle_message(update_request(&[(
+        entity_id,
+        attribute_id(0x01, 1),
+        "allowed",
+    )]));
+    assert!(is_ok(&allowed));
+
+    let denied = scoped.handle_message(update_request(&[(
+        entity_id,
+        attribute_id(0x02, 1),
+        "denied",
+    )]));
+    assert!(is_permission_denied(&denied));
+
+    // One out-of-scope triple rejects the whole batch
+    let mixed = scoped.handle_message(update_request(&[
+        (entity_id, attribute_id(0x01, 2), "allowed"),
+        (entity_id, attribute_id(0x02, 2), "denied"),
+    ]));
+    assert!(is_permission_denied(&mixed));
+    let response = scoped.handle_message(query_request(
+        entity_id,
+        proto::query_pattern::Attribute::AttributeId(attribute_id(0x01, 2).to_vec()),
+    ));
+    assert!(is_ok(&response));
+    assert!(
+        response.rows.is_empty(),
+        "nothing from the batch is written"
+    );
+}
+
+#[test]
+fn test_scoped_app_queries_only_allowed_attributes() {
+    let mut client = TestClient::new();
+    let mut scoped = scoped_sibling(&client);
+    let entity_id = new_entity_id(2);
+    let response = client.handle_message(update_request(&[
+        (entity_id, attribute_id(0x01, 3), "visible"),
+        (entity_id, attribute_id(0x02, 3), "hidden"),
+    ]));
+    assert!(is_ok(&response));
+
+    let allowed = scoped.handle_message(query_request(
+        entity_id,
+        proto::query_pattern::Attribute::AttributeId(attribute_id(0x01, 3).to_vec()),
+    ));
+    assert!(is_ok(&allowed));
+    assert_eq!(get_string_value(&allowed, 0), Some("visible"));
+
+    let denied = scoped.handle_message(query_request(
+        entity_id,
+        proto::query_pattern::Attribute::AttributeId(attribute_id(0x02, 3).to_vec()),
+    ));
+    assert!(is_permission_denied(&denied));
+    assert!(denied.rows.is_empty());
+
+    // A variable attribute could match an out-of-scope attribute
+    let variable = scoped.handle_message(query_request(
+        entity_id,
+        proto::query_pattern::Attribute::AttributeVariable(proto::QueryPatternVariable {
+            label: Some("attribute".to_string()),
+        }),
+    ));
+    assert!(is_permission_denied(&variable));
+}
+
+#[test]
+fn test_scoped_app_subscribes_only_to_allowed_attributes() {
+    let client = TestClient::new();
+    let mut scoped = scoped_sibling(&client);
+
+    assert!(is_permission_denied(
+        &scoped.handle_message(subscribe_request(None))
+    ));
+    assert!(is_permission_denied(
+        &scoped.handle_message(subscribe_request(Some(attribute_id(0x02, 4))))
+    ));
+    assert_eq!(scoped.client.subscriptions().count(), 0);
+
+    assert!(is_ok(
+        &scoped.handle_message(subscribe_request(Some(attribute_id(0x01, 4))))
+    ));
+}
+
+#[test]
+fn test_scoped_app_schema_lists_only_allowed_attributes() {
+    let mut client = TestClient::new();
+    let mut scoped = scoped_sibling(&client);
+    let entity_id = new_entity_id(5);
+    let response = client.handle_message(update_request(&[
+        (entity_id, attribute_id(0x01, 5), "visible"),
+        (entity_id, attribute_id(0x02, 5), "hidden"),
+    ]));
+    assert!(is_ok(&response));
+
+    let schema = scoped.handle_message(proto::ClientMessage {
+        request_id: Some(4),
+        payload: Some(proto::client_message::Payload::DescribeSchema(
+            proto::DescribeSchemaRequest { max_attributes: 0 },
+        )),
+    });
+    assert!(is_ok(&schema));
+    let ids: Vec<_> = schema
+        .attributes
+        .iter()
+        .map(|attribute| attribute.attribute_id.as_slice())
+        .collect();
+    assert_eq!(ids, vec![attribute_id(0x01, 5).as_slice()]);
+}
+
+#[test]
+fn test_connect_with_token_binds_app_config() {
+    let dir = tempfile::tempdir().expect("create temp dir");
+    let registry = Arc::new(DatabaseRegistry::new(dir.path().to_path_buf()));
+    let mut configs = ConfigRegistry::new();
+    configs.register(scoped_config());
+    let configs = Arc::new(configs);
+    let connect = |auth_token| {
+        let mut conn = ClientConnection::new_awaiting_connect(Arc::clone(&registry))
+            .with_config_registry(Arc::clone(&configs));
+        let responses = conn.handle_message(connect_request(auth_token));
+        let Some(proto::server_message::Payload::Response(response)) = &responses[0].payload else {
+            panic!("expected a response, got {:?}", responses[0].payload);
+        };
+        (conn, status_code(response))
+    };
+
+    let (conn, code) = connect(None);
+    assert_eq!(code, proto::google::rpc::Code::Unauthenticated as i32);
+    assert!(!conn.is_connected());
+    let (conn, code) = connect(Some(token(b"some-other-secret-that-is-long-enough")));
+    assert_eq!(code, proto::google::rpc::Code::Unauthenticated as i32);
+    assert!(!conn.is_connected());
+
+    let (conn, code) = connect(Some(token(SECRET)));
+    assert_eq!(code, proto::google::rpc::Code::Ok as i32);
+    let mut scoped = SiblingClient { client: conn };
+    let entity_id = new_entity_id(6);
+    assert!(is_ok(&scoped.handle_message(update_request(&[(
+        entity_id,
+        attribute_id(0x01, 6),
+        "allowed",
+    )]))));
+    assert!(is_permission_denied(&scoped.handle_message(
+        update_request(&[(entity_id, attribute_id(0x02, 6), "denied")])
+    )));
+}
+
+#[test]
+fn test_scoped_app_schema_limit_counts_only_allowed_attributes() {
+    let mut client = TestClient::new();
+    let mut scoped = scoped_sibling(&client);
+    let entity_id = new_entity_id(7);
+    // The out-of-scope `0x00..` attributes sort before the allowed ones
+    let response = client.handle_message(update_request(&[
+        (entity_id, attribute_id(0x00, 7), "hidden"),
+        (entity_id, attribute_id(0x00, 8), "hidden"),
+        (entity_id, attribute_id(0x00, 9), "hidden"),
+        (entity_id, attribute_id(0x01, 7), "visible"),
+        (entity_id, attribute_id(0x01, 8), "visible"),
+    ]));
+    assert!(is_ok(&response));
+    let describe_schema = |scoped: &mut SiblingClient, max_attributes| {
+        scoped.handle_message(proto::ClientMessage {
+            request_id: Some(4),
+            payload: Some(proto::client_message::Payload::DescribeSchema(
+                proto::DescribeSchemaRequest { max_attributes },
+            )),
+        })
+    };
+
+    let schema = describe_schema(&mut scoped, 2);
+
+    assert!(is_ok(&schema));
+    let ids: Vec<_> = schema
+        .attributes
+        .iter()
+        .map(|attribute| attribute.attribute_id.as_slice())
+        .collect();
+    assert_eq!(
+        ids,
+        vec![
+            attribute_id(0x01, 7).as_slice(),
+            attribute_id(0x01, 8).as_slice()
+        ]
+    );
+    assert_eq!(schema.schema_truncated, Some(false));
+
+    let schema = describe_schema(&mut scoped, 1);
+
+    assert!(is_ok(&schema));
+    assert_eq!(schema.attributes.len(), 1);
+    assert_eq!(schema.schema_truncated, Some(true));
+}
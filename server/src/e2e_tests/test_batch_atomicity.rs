@@ -0,0 +1,116 @@
+//! Test that an update request with one invalid triple writes none of its
+//! triples.
+
+use crate::e2e_tests::helpers::{
+    NAME, TestClient, entity_count, is_ok, name_triple, new_attribute_id, send, status_code,
+    status_message,
+};
+use crate::proto;
+use crate::types::ValueLimits;
+
+/// Upsert a batch of triples.
+fn write(client: &mut TestClient, triples: Vec<proto::Triple>) -> proto::ServerResponse {
+    send(
+        client,
+        proto::client_message::Payload::TripleUpdateRequest(proto::TripleUpdateRequest {
+            triples,
+            mode: proto::WriteMode::Upsert.into(),
+            expected_versions: vec![],
+        }),
+    )
+}
+
+#[test]
+fn test_batch_with_bad_entity_id_writes_nothing() {
+    let mut client = TestClient::new();
+
+    // Wherever the bad triple is, the valid ones around it are not written
+    for bad_index in 0..3 {
+        let mut triples = vec![name_triple(1, "a"), name_triple(2, "b")];
+        let mut bad = name_triple(3, "c");
+        bad.entity_id = Some(vec![3; 15]);
+        triples.insert(bad_index, bad);
+
+        let response = write(&mut client, triples);
+
+        assert_eq!(
+            status_code(&response),
+            proto::google::rpc::Code::InvalidArgument as i32
+        );
+        assert!(
+            status_message(&response).contains(&format!("triple #{bad_index}")),
+            "{}",
+            status_message(&response)
+        );
+        assert!(response.write_results.is_empty());
+        assert_eq!(
+            entity_count(&mut client, new_attribute_id(NAME)),
+            0,
+            "bad triple #{bad_index}"
+        );
+    }
+
+    // The same batch without the bad triple is written in full
+    let response = write(&mut client, vec![name_triple(1, "a"), name_triple(2, "b")]);
+    assert!(is_ok(&response));
+    assert_eq!(entity_count(&mut client, new_attribute_id(NAME)), 2);
+}
+
+#[test]
+fn test_batch_with_missing_hlc_writes_nothing() {
+    let mut client = TestClient::new();
+    let mut bad = name_triple(2, "b");
+    bad.hlc = None;
+
+    let response = write(&mut client, vec![name_triple(1, "a"), bad]);
+
+    assert_eq!(
+        status_code(&response),
+        proto::google::rpc::Code::InvalidArgument as i32
+    );
+    assert_eq!(entity_count(&mut client, new_attribute_id(NAME)), 0);
+}
+
+#[test]
+fn test_batch_over_value_limit_writes_nothing() {
+    let limits = ValueLimits::new(16).expect("valid limit");
+    let mut client = TestClient::with_value_limits(limits);
+
+    let response = write(
+        &mut client,
+        vec![name_triple(1, "a"), name_triple(2, &"b".repeat(17))],
+    );
+
+    assert_eq!(
+        status_code(&response),
+        proto::google::rpc::Code::InvalidArgument as i32
+    );
+    assert_eq!(entity_count(&mut client, new_attribute_id(NAME)), 0);
+}
+
+#[test]
+fn test_invalid_batch_leaves_transaction_open() {
+    let mut client = TestClient::new();
+    let begin = send(
+        &mut client,
+        proto::client_message::Payload::BeginTxn(proto::BeginTxnRequest { timeout_ms: 0 }),
+    );
+    assert!(is_ok(&begin));
+    assert!(is_ok(&write(&mut client, vec![name_triple(1, "a")])));
+
+    let mut bad = name_triple(3, "c");
+    bad.attribute_id = Some(vec![1; 17]);
+    let response = write(&mut client, vec![name_triple(2, "b"), bad]);
+    assert_eq!(
+        status_code(&response),
+        proto::google::rpc::Code::InvalidArgument as i32
+    );
+
+    // The rejected batch was never buffered; the earlier update commits
+    let commit = send(
+        &mut client,
+        proto::client_message::Payload::CommitTxn(proto::CommitTxnRequest {}),
+    );
+    assert!(is_ok(&commit));
+    assert_eq!(entity_count(&mut client, new_attribute_id(NAME)), 1);
+}
@@ -0,0 +1,100 @@
+//! Test `BulkLoadRequest`: chunked loads written as one transaction.
+
+use crate::e2e_tests::helpers::{
+    NAME, TestClient, entity_count, is_ok, name_triple, new_attribute_id, send, status_code,
+};
+use crate::proto;
+
+/// Send one chunk of a bulk load.
+fn load(
+    client: &mut TestClient,
+    triples: Vec<proto::Triple>,
+    finish: bool,
+) -> proto::ServerResponse {
+    send(
+        client,
+        proto::client_message::Payload::BulkLoad(proto::BulkLoadRequest { triples, finish }),
+    )
+}
+
+#[test]
+fn test_bulk_load_writes_every_chunk_on_finish() {
+    let mut client = TestClient::new();
+
+    let response = load(
+        &mut client,
+        vec![name_triple(1, "Ada"), name_triple(2, "Grace")],
+        false,
+    );
+    assert!(is_ok(&response));
+    assert_eq!(response.triples_loaded, None);
+    // Buffered chunks are not visible until the last one arrives
+    assert_eq!(entity_count(&mut client, new_attribute_id(NAME)), 0);
+
+    let response = load(&mut client, vec![name_triple(3, "Edsger")], true);
+
+    assert!(is_ok(&response));
+    assert_eq!(response.triples_loaded, Some(3));
+    assert_eq!(entity_count(&mut client, new_attribute_id(NAME)), 3);
+}
+
+#[test]
+fn test_bulk_load_rejects_invalid_chunk_without_buffering_it() {
+    let mut client = TestClient::new();
+    assert!(is_ok(&load(
+        &mut client,
+        vec![name_triple(1, "Ada")],
+        false
+    )));
+
+    let mut invalid = name_triple(2, "Grace");
+    invalid.entity_id = Some(vec![2; 4]);
+    let response = load(&mut client, vec![invalid], false);
+    assert_eq!(
+        status_code(&response),
+        proto::google::rpc::Code::InvalidArgument as i32
+    );
+
+    // The earlier chunk is still buffered
+    let response = load(&mut client, vec![name_triple(2, "Grace")], true);
+    assert!(is_ok(&response));
+    assert_eq!(response.triples_loaded, Some(2));
+    assert_eq!(entity_count(&mut client, new_attribute_id(NAME)), 2);
+}
+
+#[test]
+fn test_bulk_load_counts_only_changed_triples() {
+    let mut client = TestClient::new();
+    assert!(is_ok(&load(&mut client, vec![name_triple(1, "Ada")], true)));
+
+    // Rewriting the same value at the same HLC changes nothing
+    let response = load(
+        &mut client,
+        vec![name_triple(1, "Ada"), name_triple(2, "Grace")],
+        true,
+    );
+
+    assert!(is_ok(&response));
+    assert_eq!(response.triples_loaded, Some(1));
+}
+
+#[test]
+fn test_bulk_load_fails_inside_transaction() {
+    let mut client = TestClient::new();
+    assert!(is_ok(&send(
+        &mut client,
+        proto::client_message::Payload::BeginTxn(proto::BeginTxnRequest { timeout_ms: 0 }),
+    )));
+
+    let response = load(&mut client, vec![name_triple(1, "Ada")], true);
+
+    assert_eq!(
+        status_code(&response),
+        proto::google::rpc::Code::FailedPrecondition as i32
+    );
+    assert!(is_ok(&send(
+        &mut client,
+        proto::client_message::Payload::AbortTxn(proto::AbortTxnRequest {}),
+    )));
+    assert_eq!(entity_count(&mut client, new_attribute_id(NAME)), 0);
+}
@@ -0,0 +1,363 @@
+//! End-to-end tests for the typed `Client` against an in-process server.
+//!
+//! Each test binds the WebSocket router to an ephemeral port and talks to it
+//! over a real socket, covering the full client/server round-trip.
+
+use std::collections::HashMap;
+use std::time::Duration;
+
+use jsonwebtoken::{Algorithm, EncodingKey, Header, encode};
+
+use crate::auth::{AppConfig, ConfigRegistry, JwtConfig};
+use crate::client::{Client, ClientError};
+use crate::e2e_tests::helpers::{
+    new_attribute_id, new_entity_id, start_server, start_server_with_configs,
+};
+use crate::proto;
+use crate::query::{Filter, Pattern, PatternElement, Query, QueryBuildError, Variable};
+use crate::types::{AttributeId, ChangeType, EntityId, TripleValue};
+
+/// Query for the value of one entity/attribute pair.
+fn value_query(entity_id: [u8; 16], attribute_id: [u8; 16]) -> Query {
+    Query::new().find("value").where_pattern(Pattern::new(
+        PatternElement::Entity(EntityId(entity_id)),
+        PatternElement::Field(AttributeId(attribute_id)),
+        PatternElement::var("value"),
+    ))
+}
+
+#[tokio::test]
+async fn test_client_insert_then_query_roundtrip() {
+    let (_dir, url) = start_server().await;
+    let client = Client::connect(&url, "client-app", None)
+        .await
+        .expect("connect");
+
+    let entity_id = new_entity_id(1);
+    let attribute_id = new_attribute_id(1);
+    client
+        .insert(
+            EntityId(entity_id),
+            AttributeId(attribute_id),
+            TripleValue::Number(42.5),
+        )
+        .await
+        .expect("insert");
+
+    let response = client
+        .query(&value_query(entity_id, attribute_id))
+        .await
+        .expect("query");
+
+    assert_eq!(response.columns, vec!["value".to_string()]);
+    assert_eq!(response.rows.len(), 1);
+    let value = response.rows[0].values[0].value.as_ref();
+    assert_eq!(
+        value,
+        Some(&proto::query_result_value::Value::TripleValue(
+            proto::TripleValue {
+                value: Some(proto::triple_value::Value::Number(42.5)),
+            }
+        ))
+    );
+}
+
+#[tokio::test]
+async fn test_client_contains() {
+    let (_dir, url) = start_server().await;
+    let client = Client::connect(&url, "client-app", None)
+        .await
+        .expect("connect");
+
+    let entity_id = EntityId(new_entity_id(1));
+    let attribute_id = AttributeId(new_attribute_id(1));
+    assert!(
+        !client
+            .contains(entity_id, attribute_id)
+            .await
+            .expect("contains")
+    );
+
+    client
+        .insert(entity_id, attribute_id, TripleValue::Boolean(true))
+        .await
+        .expect("insert");
+    assert!(
+        client
+            .contains(entity_id, attribute_id)
+            .await
+            .expect("contains")
+    );
+}
+
+#[tokio::test]
+async fn test_client_delete() {
+    let (_dir, url) = start_server().await;
+    let client = Client::connect(&url, "client-app", None)
+        .await
+        .expect("connect");
+
+    let entity_id = EntityId(new_entity_id(1));
+    let attribute_id = AttributeId(new_attribute_id(1));
+    client
+        .insert(entity_id, attribute_id, TripleValue::Number(1.0))
+        .await
+        .expect("insert");
+
+    assert!(
+        client
+            .delete(entity_id, attribute_id)
+            .await
+            .expect("delete")
+    );
+    assert!(
+        !client
+            .contains(entity_id, attribute_id)
+            .await
+            .expect("contains")
+    );
+    // Nothing left to delete
+    assert!(
+        !client
+            .delete(entity_id, attribute_id)
+            .await
+            .expect("delete")
+    );
+}
+
+#[tokio::test]
+async fn test_client_cursor_pages_through_results() {
+    let (_dir, url) = start_server().await;
+    let client = Client::connect(&url, "client-app", None)
+        .await
+        .expect("connect");
+
+    let attribute_id = AttributeId(new_attribute_id(1));
+    for seed in 0..3 {
+        client
+            .insert(
+                EntityId(new_entity_id(seed)),
+                attribute_id,
+                TripleValue::Number(f64::from(seed)),
+            )
+            .await
+            .expect("insert");
+    }
+
+    let query = Query::new().find("value").where_pattern(Pattern::new(
+        PatternElement::var("e"),
+        PatternElement::Field(attribute_id),
+        PatternElement::var("value"),
+    ));
+    let cursor_id = client.open_cursor(&query).await.expect("open cursor");
+
+    let first = client.fetch_cursor(cursor_id, 2).await.expect("fetch");
+    assert_eq!(first.result.rows.len(), 2);
+    assert!(!first.exhausted);
+    let second = client.fetch_cursor(cursor_id, 2).await.expect("fetch");
+    assert_eq!(second.result.rows.len(), 1);
+    assert!(second.exhausted);
+
+    let closed = client.close_cursor(cursor_id).await;
+    assert!(matches!(
+        closed,
+        Err(ClientError::Status { code, .. }) if code == proto::google::rpc::Code::NotFound as i32
+    ));
+}
+
+#[tokio::test]
+async fn test_client_subscription_receives_changes_from_other_client() {
+    let (_dir, url) = start_server().await;
+    let subscriber = Client::connect(&url, "shared-app", None)
+        .await
+        .expect("connect subscriber");
+    let writer = Client::connect(&url, "shared-app", None)
+        .await
+        .expect("connect writer");
+
+    let mut subscription = subscriber.subscribe(None).await.expect("subscribe");
+
+    writer
+        .insert(
+            EntityId(new_entity_id(2)),
+            AttributeId(new_attribute_id(2)),
+            TripleValue::String("hello".to_string()),
+        )
+        .await
+        .expect("insert");
+
+    let change = tokio::time::timeout(Duration::from_secs(5), subscription.next())
+        .await
+        .expect("change should arrive")
+        .expect("subscription should be open");
+    assert_eq!(change.change_type, ChangeType::Insert);
+    assert_eq!(change.entity_id, EntityId(new_entity_id(2)));
+    assert_eq!(change.value, Some(TripleValue::String("hello".to_string())));
+}
+
+#[tokio::test]
+async fn test_client_watch_entity_backfills_then_filters() {
+    let (_dir, url) = start_server().await;
+    let subscriber = Client::connect(&url, "watch-app", None)
+        .await
+        .expect("connect subscriber");
+    let writer = Client::connect(&url, "watch-app", None)
+        .await
+        .expect("connect writer");
+    let watched = EntityId(new_entity_id(4));
+
+    writer
+        .insert(
+            watched,
+            AttributeId(new_attribute_id(4)),
+            TripleValue::Number(1.0),
+        )
+        .await
+        .expect("insert");
+    let mut subscription = subscriber.watch_entity(watched).await.expect("watch");
+
+    let backfill = tokio::time::timeout(Duration::from_secs(5), subscription.next())
+        .await
+        .expect("backfill should arrive")
+        .expect("subscription should be open");
+    assert_eq!(backfill.change_type, ChangeType::Insert);
+    assert_eq!(backfill.value, Some(TripleValue::Number(1.0)));
+
+    writer
+        .insert(
+            EntityId(new_entity_id(5)),
+            AttributeId(new_attribute_id(4)),
+            TripleValue::Number(2.0),
+        )
+        .await
+        .expect("insert other entity");
+    writer
+        .insert(
+            watched,
+            AttributeId(new_attribute_id(5)),
+            TripleValue::Number(3.0),
+        )
+        .await
+        .expect("insert watched entity");
+
+    let change = tokio::time::timeout(Duration::from_secs(5), subscription.next())
+        .await
+        .expect("change should arrive")
+        .expect("subscription should be open");
+    assert_eq!(change.entity_id, watched);
+    assert_eq!(change.value, Some(TripleValue::Number(3.0)));
+}
+
+#[tokio::test]
+async fn test_client_subscriptions_do_not_cross_databases() {
+    let (_dir, url) = start_server().await;
+    let subscriber = Client::connect(&url, "tenant-a", None)
+        .await
+        .expect("connect tenant-a");
+    let writer = Client::connect(&url, "tenant-b", None)
+        .await
+        .expect("connect tenant-b");
+
+    let mut subscription = subscriber.subscribe(None).await.expect("subscribe");
+    writer
+        .insert(
+            EntityId(new_entity_id(3)),
+            AttributeId(new_attribute_id(3)),
+            TripleValue::Boolean(true),
+        )
+        .await
+        .expect("insert");
+
+    let change = tokio::time::timeout(Duration::from_millis(200), subscription.next()).await;
+    assert!(change.is_err(), "tenant-a must not see tenant-b's writes");
+}
+
+#[tokio::test]
+async fn test_client_connect_rejects_invalid_api_key() {
+    let (_dir, url) = start_server().await;
+
+    let result = Client::connect(&url, "../evil", None).await;
+
+    assert!(matches!(result, Err(ClientError::Status { .. })));
+}
+
+#[tokio::test]
+async fn test_client_insert_rejects_invalid_value() {
+    let (_dir, url) = start_server().await;
+    let client = Client::connect(&url, "client-app", None)
+        .await
+        .expect("connect");
+
+    let result = client
+        .insert(
+            EntityId(new_entity_id(4)),
+            AttributeId(new_attribute_id(4)),
+            TripleValue::String(String::new()),
+        )
+        .await;
+
+    assert!(matches!(result, Err(ClientError::Status { .. })));
+}
+
+#[tokio::test]
+async fn test_client_connects_to_token_protected_app() {
+    const SECRET: &[u8] = b"client-app-secret-that-is-long-enough";
+    let mut configs = ConfigRegistry::new();
+    configs.register(AppConfig::new(
+        "protected-app".to_string(),
+        Some(JwtConfig::new_hs256(SECRET.to_vec()).expect("valid secret")),
+    ));
+    let (_dir, url) = start_server_with_configs(configs).await;
+    let token = encode(
+        &Header::new(Algorithm::HS256),
+        &HashMap::from([("sub", "user-1")]),
+        &EncodingKey::from_secret(SECRET),
+    )
+    .expect("create token");
+
+    let rejected = Client::connect(&url, "protected-app", None).await;
+    assert!(matches!(
+        rejected,
+        Err(ClientError::Status { code, .. })
+            if code == proto::google::rpc::Code::Unauthenticated as i32
+    ));
+
+    let client = Client::connect(&url, "protected-app", Some(&token))
+        .await
+        .expect("connect with token");
+    let entity_id = new_entity_id(5);
+    let attribute_id = new_attribute_id(5);
+    client
+        .insert(
+            EntityId(entity_id),
+            AttributeId(attribute_id),
+            TripleValue::Boolean(true),
+        )
+        .await
+        .expect("insert");
+    let response = client
+        .query(&value_query(entity_id, attribute_id))
+        .await
+        .expect("query");
+    assert_eq!(response.rows.len(), 1);
+}
+
+#[tokio::test]
+async fn test_client_query_rejects_unsendable_query() {
+    let (_dir, url) = start_server().await;
+    let client = Client::connect(&url, "client-app", None)
+        .await
+        .expect("connect");
+
+    // Filters run in the server process and have no wire form
+    let query = value_query(new_entity_id(6), new_attribute_id(6))
+        .filter(Filter::new(Variable::new("value"), |_| true));
+    let result = client.query(&query).await;
+
+    assert!(matches!(
+        result,
+        Err(ClientError::InvalidQuery(
+            QueryBuildError::FilterNotSerializable(_)
+        ))
+    ));
+}
@@ -22,7 +22,10 @@ fn test_query_returns_correct_columns() {
                         value: Some(proto::triple_value::Value::String("test".to_string())),
                     }),
                     hlc: Some(new_hlc(1)),
+                    attribute_name: None,
                 }],
+                mode: proto::WriteMode::Upsert.into(),
+                expected_versions: vec![],
             },
         )),
     });
@@ -48,6 +51,13 @@ fn test_query_returns_correct_columns() {
             }],
             optional: vec![],
             where_not: vec![],
+            arrow_ipc: false,
+            limit: None,
+            offset: None,
+            order_by: vec![],
+            aggregates: vec![],
+            group_by: vec![],
+            distinct: false,
         })),
     });
     assert!(is_ok(&point_response));
@@ -80,6 +90,13 @@ fn test_query_returns_correct_columns() {
             }],
             optional: vec![],
             where_not: vec![],
+            arrow_ipc: false,
+            limit: None,
+            offset: None,
+            order_by: vec![],
+            aggregates: vec![],
+            group_by: vec![],
+            distinct: false,
         })),
     });
     assert!(is_ok(&scan_response));
@@ -0,0 +1,107 @@
+//! Test triple updates from several connections running at the same time.
+//!
+//! Updates are built under a shared lock and validated at commit, so
+//! connections writing different triples do not wait on each other, and
+//! connections writing the same triple retry instead of failing.
+
+use std::thread;
+
+use crate::e2e_tests::helpers::{TestClient, is_ok, new_attribute_id, new_entity_id, new_hlc};
+use crate::proto;
+
+/// Updates sent by each connection.
+const UPDATES_PER_CONNECTION: u8 = 40;
+
+/// Build an update writing `value` to a triple.
+fn update(request_id: u32, entity_seed: u8, value: f64, hlc_seed: u64) -> proto::ClientMessage {
+    proto::ClientMessage {
+        request_id: Some(request_id),
+        payload: Some(proto::client_message::Payload::TripleUpdateRequest(
+            proto::TripleUpdateRequest {
+                triples: vec![proto::Triple {
+                    entity_id: Some(new_entity_id(entity_seed).to_vec()),
+                    attribute_id: Some(new_attribute_id(1).to_vec()),
+                    value: Some(proto::TripleValue {
+                        value: Some(proto::triple_value::Value::Number(value)),
+                    }),
+                    hlc: Some(new_hlc(hlc_seed)),
+                    attribute_name: None,
+                }],
+                mode: proto::WriteMode::Upsert.into(),
+                expected_versions: vec![],
+            },
+        )),
+    }
+}
+
+/// Build a `ContainsRequest` for a triple.
+fn contains(request_id: u32, entity_seed: u8) -> proto::ClientMessage {
+    proto::ClientMessage {
+        request_id: Some(request_id),
+        payload: Some(proto::client_message::Payload::Contains(
+            proto::ContainsRequest {
+                entity_id: Some(new_entity_id(entity_seed).to_vec()),
+                attribute_id: Some(new_attribute_id(1).to_vec()),
+            },
+        )),
+    }
+}
+
+/// Test that concurrent connections writing disjoint triples and one shared
+/// triple all succeed, with last-writer-wins deciding the shared value.
+#[test]
+fn test_concurrent_updates_from_sibling_connections() {
+    let mut client = TestClient::new();
+    let mut sibling = client.create_sibling();
+
+    thread::scope(|scope| {
+        let first = scope.spawn(|| {
+            for i in 0..UPDATES_PER_CONNECTION {
+                let request_id = u32::from(i);
+                let hlc_seed = 2 * u64::from(i) + 1;
+                assert!(is_ok(&client.handle_message(update(
+                    request_id,
+                    i,
+                    f64::from(i),
+                    hlc_seed
+                ))));
+                assert!(is_ok(
+                    &client.handle_message(update(request_id, 255, 1.0, hlc_seed))
+                ));
+            }
+        });
+        let second = scope.spawn(|| {
+            for i in 0..UPDATES_PER_CONNECTION {
+                let request_id = u32::from(i);
+                let hlc_seed = 2 * u64::from(i) + 2;
+                assert!(is_ok(&sibling.handle_message(update(
+                    request_id,
+                    UPDATES_PER_CONNECTION + i,
+                    f64::from(i),
+                    hlc_seed
+                ))));
+                assert!(is_ok(
+                    &sibling.handle_message(update(request_id, 255, 2.0, hlc_seed))
+                ));
+            }
+        });
+        first.join().expect("first connection panicked");
+        second.join().expect("second connection panicked");
+    });
+
+    for seed in 0..2 * UPDATES_PER_CONNECTION {
+        let response = client.handle_message(contains(1, seed));
+        assert_eq!(response.exists, Some(true), "triple {seed} missing");
+    }
+
+    // The sibling wrote the shared triple with the highest HLC
+    let response = client.handle_message(update(1, 255, 3.0, 1));
+    assert!(is_ok(&response));
+    assert_eq!(response.triples.len(), 1);
+    assert_eq!(
+        response.triples[0].value,
+        Some(proto::TripleValue {
+            value: Some(proto::triple_value::Value::Number(2.0)),
+        })
+    );
+}
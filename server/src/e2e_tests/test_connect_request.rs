@@ -33,6 +33,7 @@ fn test_connect_request_succeeds() {
         payload: Some(proto::client_message::Payload::Connect(
             proto::ConnectRequest {
                 app_api_key: "test_app".to_string(),
+                auth_token: None,
             },
         )),
     });
@@ -65,6 +66,13 @@ fn test_operation_before_connect_fails() {
             r#where: vec![],
             optional: vec![],
             where_not: vec![],
+            arrow_ipc: false,
+            limit: None,
+            offset: None,
+            order_by: vec![],
+            aggregates: vec![],
+            group_by: vec![],
+            distinct: false,
         })),
     });
 
@@ -110,7 +118,10 @@ fn test_update_before_connect_fails() {
                         value: Some(proto::triple_value::Value::String("test".to_string())),
                     }),
                     hlc: Some(new_hlc(1)),
+                    attribute_name: None,
                 }],
+                mode: proto::WriteMode::Upsert.into(),
+                expected_versions: vec![],
             },
         )),
     });
@@ -139,6 +150,7 @@ fn test_double_connect_fails() {
         payload: Some(proto::client_message::Payload::Connect(
             proto::ConnectRequest {
                 app_api_key: "test_app".to_string(),
+                auth_token: None,
             },
         )),
     });
@@ -151,6 +163,7 @@ fn test_double_connect_fails() {
         payload: Some(proto::client_message::Payload::Connect(
             proto::ConnectRequest {
                 app_api_key: "other_app".to_string(),
+                auth_token: None,
             },
         )),
     });
@@ -184,6 +197,7 @@ fn test_empty_api_key_fails() {
         payload: Some(proto::client_message::Payload::Connect(
             proto::ConnectRequest {
                 app_api_key: String::new(),
+                auth_token: None,
             },
         )),
     });
@@ -215,6 +229,7 @@ fn test_invalid_api_key_characters_fail() {
         payload: Some(proto::client_message::Payload::Connect(
             proto::ConnectRequest {
                 app_api_key: "../evil/path".to_string(),
+                auth_token: None,
             },
         )),
     });
@@ -251,6 +266,7 @@ fn test_operations_after_connect_work() {
         payload: Some(proto::client_message::Payload::Connect(
             proto::ConnectRequest {
                 app_api_key: "test_app".to_string(),
+                auth_token: None,
             },
         )),
     });
@@ -272,7 +288,10 @@ fn test_operations_after_connect_work() {
                         value: Some(proto::triple_value::Value::String("test".to_string())),
                     }),
                     hlc: Some(new_hlc(1)),
+                    attribute_name: None,
                 }],
+                mode: proto::WriteMode::Upsert.into(),
+                expected_versions: vec![],
             },
         )),
     });
@@ -306,6 +325,7 @@ fn test_different_api_keys_create_different_databases() {
             payload: Some(proto::client_message::Payload::Connect(
                 proto::ConnectRequest {
                     app_api_key: "app1".to_string(),
+                    auth_token: None,
                 },
             )),
         });
@@ -324,7 +344,10 @@ fn test_different_api_keys_create_different_databases() {
                             )),
                         }),
                         hlc: Some(new_hlc(1)),
+                        attribute_name: None,
                     }],
+                    mode: proto::WriteMode::Upsert.into(),
+                    expected_versions: vec![],
                 },
             )),
         });
@@ -338,6 +361,7 @@ fn test_different_api_keys_create_different_databases() {
             payload: Some(proto::client_message::Payload::Connect(
                 proto::ConnectRequest {
                     app_api_key: "app2".to_string(),
+                    auth_token: None,
                 },
             )),
         });
@@ -362,6 +386,13 @@ fn test_different_api_keys_create_different_databases() {
                 }],
                 optional: vec![],
                 where_not: vec![],
+                arrow_ipc: false,
+                limit: None,
+                offset: None,
+                order_by: vec![],
+                aggregates: vec![],
+                group_by: vec![],
+                distinct: false,
             })),
         });
 
@@ -396,6 +427,7 @@ fn test_same_api_key_shares_database() {
             payload: Some(proto::client_message::Payload::Connect(
                 proto::ConnectRequest {
                     app_api_key: "shared_app".to_string(),
+                    auth_token: None,
                 },
             )),
         });
@@ -413,7 +445,10 @@ fn test_same_api_key_shares_database() {
                             )),
                         }),
                         hlc: Some(new_hlc(1)),
+                        attribute_name: None,
                     }],
+                    mode: proto::WriteMode::Upsert.into(),
+                    expected_versions: vec![],
                 },
             )),
         });
@@ -427,6 +462,7 @@ fn test_same_api_key_shares_database() {
             payload: Some(proto::client_message::Payload::Connect(
                 proto::ConnectRequest {
                     app_api_key: "shared_app".to_string(),
+                    auth_token: None,
                 },
             )),
         });
@@ -451,6 +487,13 @@ fn test_same_api_key_shares_database() {
                 }],
                 optional: vec![],
                 where_not: vec![],
+                arrow_ipc: false,
+                limit: None,
+                offset: None,
+                order_by: vec![],
+                aggregates: vec![],
+                group_by: vec![],
+                distinct: false,
             })),
         });
 
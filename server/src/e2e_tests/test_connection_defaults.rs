@@ -0,0 +1,287 @@
+//! Test connection-scoped default triples set with `SetDefaultsRequest`.
+
+use crate::e2e_tests::helpers::{
+    TestClient, is_ok, new_attribute_id, new_entity_id, new_hlc, status_code,
+};
+use crate::proto;
+
+fn string_value(value: &str) -> proto::TripleValue {
+    proto::TripleValue {
+        value: Some(proto::triple_value::Value::String(value.to_string())),
+    }
+}
+
+fn set_defaults(
+    client: &mut TestClient,
+    request_id: u32,
+    defaults: Vec<proto::DefaultTriple>,
+) -> proto::ServerResponse {
+    client.handle_message(proto::ClientMessage {
+        request_id: Some(request_id),
+        payload: Some(proto::client_message::Payload::SetDefaults(
+            proto::SetDefaultsRequest { defaults },
+        )),
+    })
+}
+
+fn write_string(
+    entity_id: &[u8; 16],
+    attribute_id: &[u8; 16],
+    value: &str,
+    hlc_seed: u64,
+) -> proto::Triple {
+    proto::Triple {
+        entity_id: Some(entity_id.to_vec()),
+        attribute_id: Some(attribute_id.to_vec()),
+        value: Some(string_value(value)),
+        hlc: Some(new_hlc(hlc_seed)),
+        attribute_name: None,
+    }
+}
+
+fn update(
+    client: &mut TestClient,
+    request_id: u32,
+    triples: Vec<proto::Triple>,
+) -> proto::ServerResponse {
+    client.handle_message(proto::ClientMessage {
+        request_id: Some(request_id),
+        payload: Some(proto::client_message::Payload::TripleUpdateRequest(
+            proto::TripleUpdateRequest {
+                triples,
+                mode: proto::WriteMode::Upsert.into(),
+                expected_versions: vec![],
+            },
+        )),
+    })
+}
+
+/// Collect the (entity, attribute, value) triples returned by an update.
+fn returned_triples(response: &proto::ServerResponse) -> Vec<(Vec<u8>, Vec<u8>, Option<String>)> {
+    response
+        .triples
+        .iter()
+        .map(|triple| {
+            let value = match triple.value.as_ref().and_then(|value| value.value.as_ref()) {
+                Some(proto::triple_value::Value::String(value)) => Some(value.as_str().to_owned()),
+                _ => None,
+            };
+            (
+                triple.entity_id.as_deref().expect("entity_id").to_vec(),
+                triple
+                    .attribute_id
+                    .as_deref()
+                    .expect("attribute_id")
+                    .to_vec(),
+                value,
+            )
+        })
+        .collect()
+}
+
+/// Test that defaults are written for every entity, once each, and that a
+/// request writing a default attribute keeps its own value.
+#[test]
+fn test_defaults_attached_to_written_entities() {
+    let mut client = TestClient::new();
+
+    let author_attr = new_attribute_id(1);
+    let source_attr = new_attribute_id(2);
+    let name_attr = new_attribute_id(3);
+    let entity1 = new_entity_id(1);
+    let entity2 = new_entity_id(2);
+
+    let response = set_defaults(
+        &mut client,
+        1,
+        vec![
+            proto::DefaultTriple {
+                attribute_id: Some(author_attr.to_vec()),
+                value: Some(string_value("alice")),
+            },
+            proto::DefaultTriple {
+                attribute_id: Some(source_attr.to_vec()),
+                value: Some(string_value("import")),
+            },
+        ],
+    );
+    assert!(is_ok(&response));
+    assert_eq!(response.request_id, Some(1));
+
+    let response = update(
+        &mut client,
+        2,
+        vec![
+            write_string(&entity1, &name_attr, "first", 1),
+            write_string(&entity2, &name_attr, "second", 2),
+            write_string(&entity2, &author_attr, "bob", 3),
+            write_string(&entity1, &name_attr, "first again", 4),
+        ],
+    );
+    assert!(is_ok(&response));
+
+    let triples = returned_triples(&response);
+    assert_eq!(triples.len(), 7);
+    assert!(triples.contains(&(
+        entity1.to_vec(),
+        author_attr.to_vec(),
+        Some("alice".to_string())
+    )));
+    assert!(triples.contains(&(
+        entity1.to_vec(),
+        source_attr.to_vec(),
+        Some("import".to_string())
+    )));
+    assert!(triples.contains(&(
+        entity2.to_vec(),
+        author_attr.to_vec(),
+        Some("bob".to_string())
+    )));
+    assert!(triples.contains(&(
+        entity2.to_vec(),
+        source_attr.to_vec(),
+        Some("import".to_string())
+    )));
+    assert!(!triples.contains(&(
+        entity2.to_vec(),
+        author_attr.to_vec(),
+        Some("alice".to_string())
+    )));
+    assert_eq!(response.write_results.len(), triples.len());
+}
+
+/// Test that an empty `SetDefaultsRequest` clears the defaults.
+#[test]
+fn test_empty_defaults_clear_previous_defaults() {
+    let mut client = TestClient::new();
+
+    let author_attr = new_attribute_id(10);
+    let name_attr = new_attribute_id(11);
+    let entity = new_entity_id(10);
+
+    let response = set_defaults(
+        &mut client,
+        1,
+        vec![proto::DefaultTriple {
+            attribute_id: Some(author_attr.to_vec()),
+            value: Some(string_value("alice")),
+        }],
+    );
+    assert!(is_ok(&response));
+    assert!(is_ok(&set_defaults(&mut client, 2, vec![])));
+
+    let response = update(
+        &mut client,
+        3,
+        vec![write_string(&entity, &name_attr, "plain", 1)],
+    );
+    assert!(is_ok(&response));
+    assert_eq!(
+        returned_triples(&response),
+        vec![(
+            entity.to_vec(),
+            name_attr.to_vec(),
+            Some("plain".to_string())
+        )]
+    );
+}
+
+/// Test that defaults belong to one connection and are not shared with
+/// siblings.
+#[test]
+fn test_defaults_are_scoped_to_the_connection() {
+    let mut client = TestClient::new();
+    let mut sibling = client.create_sibling();
+
+    let author_attr = new_attribute_id(20);
+    let name_attr = new_attribute_id(21);
+    let entity = new_entity_id(20);
+
+    let response = set_defaults(
+        &mut client,
+        1,
+        vec![proto::DefaultTriple {
+            attribute_id: Some(author_attr.to_vec()),
+            value: Some(string_value("alice")),
+        }],
+    );
+    assert!(is_ok(&response));
+
+    let response = sibling.handle_message(proto::ClientMessage {
+        request_id: Some(1),
+        payload: Some(proto::client_message::Payload::TripleUpdateRequest(
+            proto::TripleUpdateRequest {
+                triples: vec![write_string(&entity, &name_attr, "from sibling", 1)],
+                mode: proto::WriteMode::Upsert.into(),
+                expected_versions: vec![],
+            },
+        )),
+    });
+    assert!(is_ok(&response));
+    assert_eq!(response.triples.len(), 1);
+}
+
+/// Test that invalid defaults are rejected and leave the previous defaults
+/// in place.
+#[test]
+fn test_invalid_defaults_rejected() {
+    let mut client = TestClient::new();
+
+    let author_attr = new_attribute_id(30);
+    let name_attr = new_attribute_id(31);
+    let entity = new_entity_id(30);
+
+    let response = set_defaults(
+        &mut client,
+        1,
+        vec![proto::DefaultTriple {
+            attribute_id: Some(author_attr.to_vec()),
+            value: Some(string_value("alice")),
+        }],
+    );
+    assert!(is_ok(&response));
+
+    let duplicate = set_defaults(
+        &mut client,
+        2,
+        vec![
+            proto::DefaultTriple {
+                attribute_id: Some(name_attr.to_vec()),
+                value: Some(string_value("a")),
+            },
+            proto::DefaultTriple {
+                attribute_id: Some(name_attr.to_vec()),
+                value: Some(string_value("b")),
+            },
+        ],
+    );
+    assert_eq!(
+        status_code(&duplicate),
+        proto::google::rpc::Code::InvalidArgument as i32
+    );
+
+    let short_id = set_defaults(
+        &mut client,
+        3,
+        vec![proto::DefaultTriple {
+            attribute_id: Some(vec![1, 2, 3]),
+            value: Some(string_value("a")),
+        }],
+    );
+    assert_eq!(
+        status_code(&short_id),
+        proto::google::rpc::Code::InvalidArgument as i32
+    );
+
+    let response = update(
+        &mut client,
+        4,
+        vec![write_string(&entity, &name_attr, "plain", 1)],
+    );
+    assert!(is_ok(&response));
+    assert!(returned_triples(&response).contains(&(
+        entity.to_vec(),
+        author_attr.to_vec(),
+        Some("alice".to_string())
+    )));
+}
@@ -0,0 +1,79 @@
+//! Test `ContainsRequest` existence checks.
+
+use crate::e2e_tests::helpers::{
+    TestClient, is_ok, new_attribute_id, new_entity_id, new_hlc, status_code,
+};
+use crate::proto;
+
+/// Send a `ContainsRequest` for one entity/attribute pair.
+fn contains(
+    client: &mut TestClient,
+    request_id: u32,
+    entity_id: Vec<u8>,
+    attribute_id: Vec<u8>,
+) -> proto::ServerResponse {
+    client.handle_message(proto::ClientMessage {
+        request_id: Some(request_id),
+        payload: Some(proto::client_message::Payload::Contains(
+            proto::ContainsRequest {
+                entity_id: Some(entity_id),
+                attribute_id: Some(attribute_id),
+            },
+        )),
+    })
+}
+
+/// Test that a written triple exists and an unwritten one does not.
+#[test]
+fn test_contains_reports_existence() {
+    let mut client = TestClient::new();
+
+    let entity = new_entity_id(1);
+    let name_attr = new_attribute_id(10);
+    let age_attr = new_attribute_id(11);
+
+    let insert_response = client.handle_message(proto::ClientMessage {
+        request_id: Some(1),
+        payload: Some(proto::client_message::Payload::TripleUpdateRequest(
+            proto::TripleUpdateRequest {
+                triples: vec![proto::Triple {
+                    entity_id: Some(entity.to_vec()),
+                    attribute_id: Some(name_attr.to_vec()),
+                    value: Some(proto::TripleValue {
+                        value: Some(proto::triple_value::Value::String("Alice".to_string())),
+                    }),
+                    hlc: Some(new_hlc(1)),
+                    attribute_name: None,
+                }],
+                mode: proto::WriteMode::Upsert.into(),
+                expected_versions: vec![],
+            },
+        )),
+    });
+    assert!(is_ok(&insert_response));
+
+    let response = contains(&mut client, 2, entity.to_vec(), name_attr.to_vec());
+    assert!(is_ok(&response));
+    assert_eq!(response.request_id, Some(2));
+    assert_eq!(response.exists, Some(true));
+    // The value itself is never returned
+    assert!(response.triples.is_empty());
+
+    let response = contains(&mut client, 3, entity.to_vec(), age_attr.to_vec());
+    assert!(is_ok(&response));
+    assert_eq!(response.exists, Some(false));
+}
+
+/// Test that a malformed ID is rejected.
+#[test]
+fn test_contains_rejects_invalid_entity_id() {
+    let mut client = TestClient::new();
+
+    let response = contains(&mut client, 1, vec![1, 2, 3], new_attribute_id(10).to_vec());
+
+    assert_eq!(
+        status_code(&response),
+        proto::google::rpc::Code::InvalidArgument as i32
+    );
+    assert_eq!(response.exists, None);
+}
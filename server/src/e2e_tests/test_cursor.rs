@@ -0,0 +1,330 @@
+//! Test server-side cursors: `OpenCursorRequest`, `FetchCursorRequest` and
+//! `CloseCursorRequest`.
+
+use std::sync::Arc;
+
+use crate::client_connection::ClientConnection;
+use crate::database_registry::DatabaseRegistry;
+use crate::e2e_tests::helpers::{
+    TestClient, get_strings_at, is_ok, new_attribute_id, new_entity_id, new_hlc, status_code,
+};
+use crate::proto;
+
+/// Attribute holding each entity's name.
+const NAME_ATTR_SEED: u8 = 10;
+
+/// Build a `ClientMessage` with the given payload.
+fn message(request_id: u32, payload: proto::client_message::Payload) -> proto::ClientMessage {
+    proto::ClientMessage {
+        request_id: Some(request_id),
+        payload: Some(payload),
+    }
+}
+
+/// Build an update naming `count` entities `user0`, `user1`, ...
+fn insert_names(count: u8) -> proto::client_message::Payload {
+    let triples = (0..count)
+        .map(|i| proto::Triple {
+            entity_id: Some(new_entity_id(i).to_vec()),
+            attribute_id: Some(new_attribute_id(NAME_ATTR_SEED).to_vec()),
+            value: Some(proto::TripleValue {
+                value: Some(proto::triple_value::Value::String(format!("user{i}"))),
+            }),
+            hlc: Some(new_hlc(u64::from(i) + 1)),
+            attribute_name: None,
+        })
+        .collect();
+    proto::client_message::Payload::TripleUpdateRequest(proto::TripleUpdateRequest {
+        triples,
+        mode: proto::WriteMode::Upsert.into(),
+        expected_versions: vec![],
+    })
+}
+
+/// Build an `OpenCursorRequest` for `[:find ?name :where [?id name ?name]]`.
+fn open_names_cursor() -> proto::client_message::Payload {
+    proto::client_message::Payload::OpenCursor(proto::OpenCursorRequest {
+        query: Some(proto::QueryRequest {
+            find: vec![proto::QueryPatternVariable {
+                label: Some("name".to_string()),
+            }],
+            r#where: vec![proto::QueryPattern {
+                entity: Some(proto::query_pattern::Entity::EntityVariable(
+                    proto::QueryPatternVariable {
+                        label: Some("id".to_string()),
+                    },
+                )),
+                attribute: Some(proto::query_pattern::Attribute::AttributeId(
+                    new_attribute_id(NAME_ATTR_SEED).to_vec(),
+                )),
+                value_group: Some(proto::query_pattern::ValueGroup::ValueVariable(
+                    proto::QueryPatternVariable {
+                        label: Some("name".to_string()),
+                    },
+                )),
+            }],
+            optional: vec![],
+            where_not: vec![],
+            arrow_ipc: false,
+            limit: None,
+            offset: None,
+            order_by: vec![],
+            aggregates: vec![],
+            group_by: vec![],
+            distinct: false,
+        }),
+    })
+}
+
+/// Build a `FetchCursorRequest`.
+const fn fetch(cursor_id: u64, max_rows: u32) -> proto::client_message::Payload {
+    proto::client_message::Payload::FetchCursor(proto::FetchCursorRequest {
+        cursor_id,
+        max_rows,
+    })
+}
+
+/// Test that fetching in batches returns every row exactly once, then
+/// reports exhaustion and closes the cursor.
+#[test]
+fn test_cursor_fetch_to_exhaustion() {
+    let mut client = TestClient::new();
+    assert!(is_ok(&client.handle_message(message(1, insert_names(5)))));
+
+    let response = client.handle_message(message(2, open_names_cursor()));
+    assert!(is_ok(&response));
+    let cursor_id = response.cursor_id.expect("cursor id");
+
+    let mut fetched = Vec::new();
+    let mut request_id = 3;
+    loop {
+        let response = client.handle_message(message(request_id, fetch(cursor_id, 2)));
+        assert!(is_ok(&response));
+        assert_eq!(response.columns, vec!["name".to_string()]);
+        assert!(response.rows.len() <= 2);
+        fetched.extend(get_strings_at(&response, 0));
+        request_id += 1;
+        if response.cursor_exhausted == Some(true) {
+            break;
+        }
+    }
+
+    fetched.sort();
+    let expected: Vec<String> = (0..5).map(|i| format!("user{i}")).collect();
+    assert_eq!(fetched, expected);
+
+    // An exhausted cursor is closed
+    let response = client.handle_message(message(request_id, fetch(cursor_id, 2)));
+    assert_eq!(
+        status_code(&response),
+        proto::google::rpc::Code::NotFound as i32
+    );
+}
+
+/// Test that a cursor reads the snapshot taken when it opened.
+#[test]
+fn test_cursor_ignores_later_writes() {
+    let mut client = TestClient::new();
+    assert!(is_ok(&client.handle_message(message(1, insert_names(2)))));
+
+    let response = client.handle_message(message(2, open_names_cursor()));
+    let cursor_id = response.cursor_id.expect("cursor id");
+    assert!(is_ok(&client.handle_message(message(3, insert_names(4)))));
+
+    let response = client.handle_message(message(4, fetch(cursor_id, 100)));
+    assert!(is_ok(&response));
+    assert_eq!(get_strings_at(&response, 0).len(), 2);
+    assert_eq!(response.cursor_exhausted, Some(true));
+}
+
+/// Test that a closed cursor can no longer be fetched or closed.
+#[test]
+fn test_cursor_close() {
+    let mut client = TestClient::new();
+    assert!(is_ok(&client.handle_message(message(1, insert_names(3)))));
+
+    let response = client.handle_message(message(2, open_names_cursor()));
+    let cursor_id = response.cursor_id.expect("cursor id");
+
+    let close =
+        || proto::client_message::Payload::CloseCursor(proto::CloseCursorRequest { cursor_id });
+    assert!(is_ok(&client.handle_message(message(3, close()))));
+
+    let response = client.handle_message(message(4, fetch(cursor_id, 1)));
+    assert_eq!(
+        status_code(&response),
+        proto::google::rpc::Code::NotFound as i32
+    );
+    let response = client.handle_message(message(5, close()));
+    assert_eq!(
+        status_code(&response),
+        proto::google::rpc::Code::NotFound as i32
+    );
+}
+
+/// Test that invalid cursor requests are rejected.
+#[test]
+fn test_cursor_rejects_invalid_requests() {
+    let mut client = TestClient::new();
+    let response = client.handle_message(message(1, open_names_cursor()));
+    let cursor_id = response.cursor_id.expect("cursor id");
+
+    let response = client.handle_message(message(2, fetch(cursor_id, 0)));
+    assert_eq!(
+        status_code(&response),
+        proto::google::rpc::Code::InvalidArgument as i32
+    );
+
+    let response = client.handle_message(message(
+        3,
+        proto::client_message::Payload::OpenCursor(proto::OpenCursorRequest { query: None }),
+    ));
+    assert_eq!(
+        status_code(&response),
+        proto::google::rpc::Code::InvalidArgument as i32
+    );
+}
+
+/// Test that a cursor over an aggregate or grouped query is rejected rather
+/// than returning headers that do not match its rows.
+#[test]
+fn test_cursor_rejects_aggregate_queries() {
+    let mut client = TestClient::new();
+    let variable = |label: &str| proto::QueryPatternVariable {
+        label: Some(label.to_owned()),
+    };
+    let query = || {
+        let proto::client_message::Payload::OpenCursor(request) = open_names_cursor() else {
+            unreachable!("open_names_cursor builds an OpenCursorRequest");
+        };
+        proto::QueryRequest {
+            find: vec![],
+            ..request.query.expect("query")
+        }
+    };
+    let aggregate = proto::QueryRequest {
+        aggregates: vec![proto::QueryAggregate {
+            function: proto::AggregateFunction::Count.into(),
+            variable: Some(variable("id")),
+        }],
+        ..query()
+    };
+    let group = proto::QueryRequest {
+        group_by: vec![variable("name")],
+        ..query()
+    };
+
+    for (request_id, query) in [(1, aggregate), (2, group)] {
+        let response = client.handle_message(message(
+            request_id,
+            proto::client_message::Payload::OpenCursor(proto::OpenCursorRequest {
+                query: Some(query),
+            }),
+        ));
+
+        assert_eq!(
+            status_code(&response),
+            proto::google::rpc::Code::InvalidArgument as i32
+        );
+        assert!(response.cursor_id.is_none());
+    }
+}
+
+/// Test that a cursor over a query it would have to sort, skip, limit or
+/// deduplicate is rejected rather than returning wrong rows.
+#[test]
+fn test_cursor_rejects_clauses_it_cannot_apply() {
+    let mut client = TestClient::new();
+    let query = || {
+        let proto::client_message::Payload::OpenCursor(request) = open_names_cursor() else {
+            unreachable!("open_names_cursor builds an OpenCursorRequest");
+        };
+        request.query.expect("query")
+    };
+    let queries = [
+        proto::QueryRequest {
+            order_by: vec![proto::QueryOrderBy {
+                variable: Some(proto::QueryPatternVariable {
+                    label: Some("name".to_owned()),
+                }),
+                ..Default::default()
+            }],
+            ..query()
+        },
+        proto::QueryRequest {
+            limit: Some(2),
+            ..query()
+        },
+        proto::QueryRequest {
+            offset: Some(1),
+            ..query()
+        },
+        proto::QueryRequest {
+            distinct: true,
+            ..query()
+        },
+    ];
+
+    for (request_id, query) in (1..).zip(queries) {
+        let response = client.handle_message(message(
+            request_id,
+            proto::client_message::Payload::OpenCursor(proto::OpenCursorRequest {
+                query: Some(query),
+            }),
+        ));
+
+        assert_eq!(
+            status_code(&response),
+            proto::google::rpc::Code::InvalidArgument as i32
+        );
+        assert!(response.cursor_id.is_none());
+    }
+}
+
+/// Test that a cursor outlives the connection that opened it, so a client
+/// can reconnect and resume.
+#[test]
+fn test_cursor_resumes_after_reconnect() {
+    let dir = tempfile::tempdir().expect("create temp dir");
+    let registry = Arc::new(DatabaseRegistry::new(dir.path().to_path_buf()));
+    let connect = || {
+        proto::client_message::Payload::Connect(proto::ConnectRequest {
+            app_api_key: "cursor_app".to_string(),
+            auth_token: None,
+        })
+    };
+    let response_of = |messages: Vec<proto::ServerMessage>| match messages
+        .into_iter()
+        .next_back()
+        .and_then(|message| message.payload)
+    {
+        Some(proto::server_message::Payload::Response(response)) => response,
+        _ => panic!("Expected Response"),
+    };
+
+    let mut first = ClientConnection::new_awaiting_connect(Arc::clone(&registry));
+    assert!(is_ok(&response_of(
+        first.handle_message(message(1, connect()))
+    )));
+    assert!(is_ok(&response_of(
+        first.handle_message(message(2, insert_names(3)))
+    )));
+    let response = response_of(first.handle_message(message(3, open_names_cursor())));
+    let cursor_id = response.cursor_id.expect("cursor id");
+    let response = response_of(first.handle_message(message(4, fetch(cursor_id, 2))));
+    let mut fetched = get_strings_at(&response, 0);
+    assert_eq!(fetched.len(), 2);
+    drop(first);
+
+    let mut second = ClientConnection::new_awaiting_connect(registry);
+    assert!(is_ok(&response_of(
+        second.handle_message(message(1, connect()))
+    )));
+    let response = response_of(second.handle_message(message(2, fetch(cursor_id, 2))));
+    assert!(is_ok(&response));
+    assert_eq!(response.cursor_exhausted, Some(true));
+    fetched.extend(get_strings_at(&response, 0));
+
+    fetched.sort();
+    assert_eq!(fetched, vec!["user0", "user1", "user2"]);
+}
@@ -0,0 +1,200 @@
+//! Test deleting triples with a `DeleteRequest`.
+
+use crate::e2e_tests::helpers::{
+    TestClient, insert, is_ok, new_attribute_id, new_entity_id, status_code,
+};
+use crate::proto;
+use crate::types::{AttributeId, ChangeType, EntityId};
+
+/// Send a `DeleteRequest` for the given keys.
+fn delete(client: &mut TestClient, keys: Vec<(Vec<u8>, Vec<u8>)>) -> proto::ServerResponse {
+    client.handle_message(proto::ClientMessage {
+        request_id: Some(2),
+        payload: Some(proto::client_message::Payload::Delete(
+            proto::DeleteRequest {
+                keys: keys
+                    .into_iter()
+                    .map(|(entity_id, attribute_id)| proto::TripleKey {
+                        entity_id: Some(entity_id),
+                        attribute_id: Some(attribute_id),
+                    })
+                    .collect(),
+            },
+        )),
+    })
+}
+
+/// Whether a triple exists, per a `ContainsRequest`.
+fn exists(client: &mut TestClient, entity_id: [u8; 16], attribute_id: [u8; 16]) -> bool {
+    let response = client.handle_message(proto::ClientMessage {
+        request_id: Some(3),
+        payload: Some(proto::client_message::Payload::Contains(
+            proto::ContainsRequest {
+                entity_id: Some(entity_id.to_vec()),
+                attribute_id: Some(attribute_id.to_vec()),
+            },
+        )),
+    });
+    assert!(is_ok(&response));
+    response.exists == Some(true)
+}
+
+/// The status code of each delete result.
+fn result_codes(response: &proto::ServerResponse) -> Vec<i32> {
+    response
+        .delete_results
+        .iter()
+        .map(|result| result.status.as_ref().map_or(-1, |status| status.code))
+        .collect()
+}
+
+#[test]
+fn test_delete_triple() {
+    let mut client = TestClient::new();
+    let entity_id = new_entity_id(1);
+    let attribute_id = new_attribute_id(1);
+    insert(&mut client, entity_id, attribute_id, "hello");
+
+    let response = delete(
+        &mut client,
+        vec![(entity_id.to_vec(), attribute_id.to_vec())],
+    );
+
+    assert!(is_ok(&response));
+    assert_eq!(response.request_id, Some(2));
+    assert_eq!(response.delete_results.len(), 1);
+    assert_eq!(response.delete_results[0].entity_id, entity_id.to_vec());
+    assert_eq!(
+        response.delete_results[0].attribute_id,
+        attribute_id.to_vec()
+    );
+    assert_eq!(
+        result_codes(&response),
+        vec![proto::google::rpc::Code::Ok as i32]
+    );
+    assert!(!exists(&mut client, entity_id, attribute_id));
+}
+
+#[test]
+fn test_delete_reports_missing_triples() {
+    let mut client = TestClient::new();
+    let entity_id = new_entity_id(1);
+    let name_attr = new_attribute_id(1);
+    let age_attr = new_attribute_id(2);
+    insert(&mut client, entity_id, name_attr, "Alice");
+
+    // The second delete of name_attr finds it already deleted
+    let response = delete(
+        &mut client,
+        vec![
+            (entity_id.to_vec(), age_attr.to_vec()),
+            (entity_id.to_vec(), name_attr.to_vec()),
+            (entity_id.to_vec(), name_attr.to_vec()),
+        ],
+    );
+
+    assert!(is_ok(&response));
+    assert_eq!(
+        result_codes(&response),
+        vec![
+            proto::google::rpc::Code::NotFound as i32,
+            proto::google::rpc::Code::Ok as i32,
+            proto::google::rpc::Code::NotFound as i32,
+        ]
+    );
+    assert!(!exists(&mut client, entity_id, name_attr));
+}
+
+#[test]
+fn test_delete_multiple_entities_in_one_request() {
+    let mut client = TestClient::new();
+    let attribute_id = new_attribute_id(1);
+    let entities = [new_entity_id(1), new_entity_id(2), new_entity_id(3)];
+    for entity_id in entities {
+        insert(&mut client, entity_id, attribute_id, "value");
+    }
+
+    let response = delete(
+        &mut client,
+        vec![
+            (entities[0].to_vec(), attribute_id.to_vec()),
+            (entities[2].to_vec(), attribute_id.to_vec()),
+        ],
+    );
+
+    assert!(is_ok(&response));
+    assert!(!exists(&mut client, entities[0], attribute_id));
+    assert!(exists(&mut client, entities[1], attribute_id));
+    assert!(!exists(&mut client, entities[2], attribute_id));
+}
+
+#[test]
+fn test_delete_broadcasts_change_notifications() {
+    let mut client = TestClient::new();
+    let sibling = client.create_sibling();
+    let entity_id = new_entity_id(1);
+    let attribute_id = new_attribute_id(1);
+    let missing_attr = new_attribute_id(2);
+    insert(&mut client, entity_id, attribute_id, "hello");
+
+    let mut change_rx = sibling.subscribe_to_changes();
+    let response = delete(
+        &mut client,
+        vec![
+            (entity_id.to_vec(), attribute_id.to_vec()),
+            (entity_id.to_vec(), missing_attr.to_vec()),
+        ],
+    );
+    assert!(is_ok(&response));
+
+    // One notification for the transaction, holding only the real delete
+    let notification = change_rx
+        .try_recv()
+        .expect("sibling should receive notification");
+    assert_eq!(notification.changes.len(), 1);
+    let change = &notification.changes[0];
+    assert_eq!(change.change_type, ChangeType::Delete);
+    assert_eq!(change.entity_id, EntityId(entity_id));
+    assert_eq!(change.attribute_id, AttributeId(attribute_id));
+    assert_eq!(change.value, None);
+    assert!(change_rx.try_recv().is_err());
+}
+
+#[test]
+fn test_delete_only_missing_triples_does_not_broadcast() {
+    let mut client = TestClient::new();
+    let sibling = client.create_sibling();
+    let mut change_rx = sibling.subscribe_to_changes();
+
+    let response = delete(
+        &mut client,
+        vec![(new_entity_id(1).to_vec(), new_attribute_id(1).to_vec())],
+    );
+
+    assert!(is_ok(&response));
+    assert!(change_rx.try_recv().is_err());
+}
+
+#[test]
+fn test_delete_rejects_invalid_ids() {
+    let mut client = TestClient::new();
+    let entity_id = new_entity_id(1);
+    let attribute_id = new_attribute_id(1);
+    insert(&mut client, entity_id, attribute_id, "hello");
+
+    // A bad key rejects the whole request, deleting nothing
+    let response = delete(
+        &mut client,
+        vec![
+            (entity_id.to_vec(), attribute_id.to_vec()),
+            (vec![1, 2, 3], attribute_id.to_vec()),
+        ],
+    );
+
+    assert_eq!(
+        status_code(&response),
+        proto::google::rpc::Code::InvalidArgument as i32
+    );
+    assert!(response.delete_results.is_empty());
+    assert!(exists(&mut client, entity_id, attribute_id));
+}
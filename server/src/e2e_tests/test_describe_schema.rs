@@ -0,0 +1,115 @@
+//! Test describing the database's attributes with a `DescribeSchemaRequest`.
+
+use crate::e2e_tests::helpers::{
+    TestClient, insert_named, insert_value, is_ok, new_attribute_id, new_entity_id,
+};
+use crate::proto;
+
+/// Send a `DescribeSchemaRequest`.
+fn describe_schema(client: &mut TestClient, max_attributes: u32) -> proto::ServerResponse {
+    client.handle_message(proto::ClientMessage {
+        request_id: Some(2),
+        payload: Some(proto::client_message::Payload::DescribeSchema(
+            proto::DescribeSchemaRequest { max_attributes },
+        )),
+    })
+}
+
+/// The sampled value types of the attribute with the given ID.
+fn value_types(response: &proto::ServerResponse, attribute_id: &[u8]) -> Vec<proto::ValueType> {
+    response
+        .attributes
+        .iter()
+        .find(|attribute| attribute.attribute_id == attribute_id)
+        .map(|attribute| attribute.value_types().collect())
+        .unwrap_or_default()
+}
+
+#[test]
+fn test_describe_schema_reports_mixed_types() {
+    let mut client = TestClient::new();
+    let age = new_attribute_id(1);
+    let status = new_attribute_id(2);
+    insert_value(
+        &mut client,
+        new_entity_id(1),
+        age,
+        proto::triple_value::Value::Number(30.0),
+    );
+    insert_value(
+        &mut client,
+        new_entity_id(1),
+        status,
+        proto::triple_value::Value::Boolean(true),
+    );
+    insert_value(
+        &mut client,
+        new_entity_id(2),
+        status,
+        proto::triple_value::Value::String("away".to_string()),
+    );
+    insert_named(
+        &mut client,
+        new_entity_id(1),
+        "email",
+        proto::triple_value::Value::String("a@example.com".to_string()),
+    );
+
+    let response = describe_schema(&mut client, 0);
+
+    assert!(is_ok(&response));
+    assert_eq!(response.schema_truncated, Some(false));
+    assert_eq!(response.attributes.len(), 3);
+    assert_eq!(value_types(&response, &age), vec![proto::ValueType::Number]);
+    assert_eq!(
+        value_types(&response, &status),
+        vec![proto::ValueType::Boolean, proto::ValueType::String]
+    );
+    let email = response
+        .attributes
+        .iter()
+        .find(|attribute| attribute.attribute_name.as_deref() == Some("email"))
+        .expect("named attribute is described");
+    assert_eq!(
+        email.value_types().collect::<Vec<_>>(),
+        vec![proto::ValueType::String]
+    );
+}
+
+#[test]
+fn test_describe_schema_truncates_at_max_attributes() {
+    let mut client = TestClient::new();
+    for seed in 1..=3 {
+        insert_value(
+            &mut client,
+            new_entity_id(1),
+            new_attribute_id(seed),
+            proto::triple_value::Value::Number(f64::from(seed)),
+        );
+    }
+
+    let response = describe_schema(&mut client, 2);
+
+    assert!(is_ok(&response));
+    assert_eq!(response.schema_truncated, Some(true));
+    let ids: Vec<_> = response
+        .attributes
+        .iter()
+        .map(|attribute| attribute.attribute_id.as_slice())
+        .collect();
+    assert_eq!(
+        ids,
+        vec![&new_attribute_id(1)[..], &new_attribute_id(2)[..]]
+    );
+}
+
+#[test]
+fn test_describe_empty_schema() {
+    let mut client = TestClient::new();
+
+    let response = describe_schema(&mut client, 10);
+
+    assert!(is_ok(&response));
+    assert!(response.attributes.is_empty());
+    assert_eq!(response.schema_truncated, Some(false));
+}
@@ -26,7 +26,10 @@ fn run_sequence() -> Vec<proto::ServerResponse> {
                         value: Some(proto::triple_value::Value::String("first".to_string())),
                     }),
                     hlc: Some(new_hlc(1)),
+                    attribute_name: None,
                 }],
+                mode: proto::WriteMode::Upsert.into(),
+                expected_versions: vec![],
             },
         )),
     }));
@@ -42,7 +45,10 @@ fn run_sequence() -> Vec<proto::ServerResponse> {
                         value: Some(proto::triple_value::Value::Number(42.0)),
                     }),
                     hlc: Some(new_hlc(2)),
+                    attribute_name: None,
                 }],
+                mode: proto::WriteMode::Upsert.into(),
+                expected_versions: vec![],
             },
         )),
     }));
@@ -74,6 +80,13 @@ fn run_sequence() -> Vec<proto::ServerResponse> {
             }],
             optional: vec![],
             where_not: vec![],
+            arrow_ipc: false,
+            limit: None,
+            offset: None,
+            order_by: vec![],
+            aggregates: vec![],
+            group_by: vec![],
+            distinct: false,
         })),
     }));
 
@@ -89,7 +102,10 @@ fn run_sequence() -> Vec<proto::ServerResponse> {
                         value: Some(proto::triple_value::Value::String("updated".to_string())),
                     }),
                     hlc: Some(new_hlc(3)),
+                    attribute_name: None,
                 }],
+                mode: proto::WriteMode::Upsert.into(),
+                expected_versions: vec![],
             },
         )),
     }));
@@ -121,6 +137,13 @@ fn run_sequence() -> Vec<proto::ServerResponse> {
             }],
             optional: vec![],
             where_not: vec![],
+            arrow_ipc: false,
+            limit: None,
+            offset: None,
+            order_by: vec![],
+            aggregates: vec![],
+            group_by: vec![],
+            distinct: false,
         })),
     }));
 
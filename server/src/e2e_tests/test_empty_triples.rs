@@ -10,7 +10,11 @@ fn test_empty_triples_request() {
     let request = proto::ClientMessage {
         request_id: Some(1),
         payload: Some(proto::client_message::Payload::TripleUpdateRequest(
-            proto::TripleUpdateRequest { triples: vec![] },
+            proto::TripleUpdateRequest {
+                triples: vec![],
+                mode: proto::WriteMode::Upsert.into(),
+                expected_versions: vec![],
+            },
         )),
     };
 
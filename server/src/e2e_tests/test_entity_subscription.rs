@@ -0,0 +1,235 @@
+//! End-to-end tests for entity subscriptions.
+//!
+//! A subscription with `watch_entity_id` is backfilled with the entity's
+//! current triples, then receives every later change to that entity. Like the
+//! WebSocket handler, these tests turn each broadcast notification into
+//! per-subscription updates with `ClientConnection::updates_for`.
+
+use crate::e2e_tests::helpers::{
+    SiblingClient, TestClient, drain_updates, insert_seeded, is_ok, new_attribute_id,
+    new_entity_id, new_hlc,
+};
+use crate::proto;
+use crate::types::{AttributeId, EntityId};
+
+/// Subscribe to one entity and return every message the server sent back.
+fn watch_entity(
+    sibling: &mut SiblingClient,
+    subscription_id: u32,
+    entity_id: &[u8],
+    include_old_values: bool,
+) -> Vec<proto::ServerMessage> {
+    sibling.client.handle_message(proto::ClientMessage {
+        request_id: Some(subscription_id),
+        payload: Some(proto::client_message::Payload::Subscribe(
+            proto::SubscribeRequest {
+                subscription_id,
+                since_hlc: None,
+                query: None,
+                watch_entity_id: Some(entity_id.to_vec()),
+                until_hlc: None,
+                include_old_values,
+                entity_id: None,
+                attribute_id: None,
+            },
+        )),
+    })
+}
+
+/// Delete a triple directly in the shared database.
+fn delete(client: &TestClient, entity_id: [u8; 16], attribute_id: [u8; 16]) {
+    let database = client
+        .client
+        .shared_database()
+        .expect("client should be connected");
+    let mut db = database.write().expect("lock database");
+    let mut txn = db.begin(0).expect("begin");
+    txn.delete(&EntityId(entity_id), &AttributeId(attribute_id))
+        .expect("delete");
+    txn.commit().expect("commit");
+    drop(db);
+}
+
+/// The (entity, attribute) IDs and change type of each change in an update.
+fn change_keys(update: &proto::SubscriptionUpdate) -> Vec<(Vec<u8>, Vec<u8>, i32)> {
+    update
+        .changes
+        .iter()
+        .map(|change| {
+            let triple = change
+                .triple
+                .as_ref()
+                .expect("change should carry a triple");
+            (
+                triple.entity_id.as_deref().unwrap_or_default().to_owned(),
+                triple
+                    .attribute_id
+                    .as_deref()
+                    .unwrap_or_default()
+                    .to_owned(),
+                change.change_type,
+            )
+        })
+        .collect()
+}
+
+#[test]
+fn test_entity_subscription_backfills_current_triples() {
+    let mut client = TestClient::new();
+    let mut sibling = client.create_sibling();
+    let watched = new_entity_id(1);
+    let other = new_entity_id(2);
+
+    insert_seeded(&mut client, watched, new_attribute_id(1), 1);
+    insert_seeded(&mut client, watched, new_attribute_id(2), 2);
+    insert_seeded(&mut client, other, new_attribute_id(1), 3);
+
+    let messages = watch_entity(&mut sibling, 1, &watched, false);
+    assert_eq!(messages.len(), 2, "backfill, then OK");
+    let Some(proto::server_message::Payload::SubscriptionUpdate(update)) = &messages[0].payload
+    else {
+        panic!("expected SubscriptionUpdate, got {:?}", messages[0].payload);
+    };
+    assert_eq!(update.subscription_id, 1);
+    assert!(!update.query_invalidated);
+    let insert_type = proto::ChangeType::Insert as i32;
+    assert_eq!(
+        change_keys(update),
+        vec![
+            (watched.to_vec(), new_attribute_id(1).to_vec(), insert_type),
+            (watched.to_vec(), new_attribute_id(2).to_vec(), insert_type),
+        ]
+    );
+    let first = update.changes[0].triple.as_ref().expect("triple");
+    assert_eq!(first.hlc, Some(new_hlc(1)));
+    assert_eq!(
+        first.value.as_ref().and_then(|value| value.value.as_ref()),
+        Some(&proto::triple_value::Value::String("v1".to_string()))
+    );
+}
+
+#[test]
+fn test_entity_subscription_without_triples_sends_only_ok() {
+    let client = TestClient::new();
+    let mut sibling = client.create_sibling();
+
+    let messages = watch_entity(&mut sibling, 1, &new_entity_id(3), false);
+    assert_eq!(messages.len(), 1, "nothing to backfill");
+    assert!(matches!(
+        messages[0].payload,
+        Some(proto::server_message::Payload::Response(_))
+    ));
+    assert_eq!(sibling.client.subscriptions().count(), 1);
+}
+
+#[test]
+fn test_entity_subscription_streams_changes_to_its_entity() {
+    let mut client = TestClient::new();
+    let mut sibling = client.create_sibling();
+    let mut change_rx = sibling.subscribe_to_changes();
+    let watched = new_entity_id(4);
+    let attribute_id = new_attribute_id(4);
+
+    watch_entity(&mut sibling, 1, &watched, false);
+
+    insert_seeded(&mut client, new_entity_id(5), attribute_id, 1);
+    assert!(
+        drain_updates(&sibling, &mut change_rx).is_empty(),
+        "changes to other entities are filtered out"
+    );
+
+    insert_seeded(&mut client, watched, attribute_id, 2);
+    insert_seeded(&mut client, watched, attribute_id, 3);
+    delete(&client, watched, attribute_id);
+
+    let updates = drain_updates(&sibling, &mut change_rx);
+    let keys: Vec<_> = updates.iter().flat_map(change_keys).collect();
+    assert_eq!(
+        keys,
+        vec![
+            (
+                watched.to_vec(),
+                attribute_id.to_vec(),
+                proto::ChangeType::Insert as i32
+            ),
+            (
+                watched.to_vec(),
+                attribute_id.to_vec(),
+                proto::ChangeType::Update as i32
+            ),
+            (
+                watched.to_vec(),
+                attribute_id.to_vec(),
+                proto::ChangeType::Delete as i32
+            ),
+        ]
+    );
+}
+
+#[test]
+fn test_entity_subscription_with_old_values() {
+    let mut client = TestClient::new();
+    let mut sibling = client.create_sibling();
+    let mut change_rx = sibling.subscribe_to_changes();
+    let watched = new_entity_id(6);
+    let attribute_id = new_attribute_id(6);
+
+    watch_entity(&mut sibling, 1, &watched, true);
+
+    insert_seeded(&mut client, watched, attribute_id, 1);
+    insert_seeded(&mut client, watched, attribute_id, 2);
+    delete(&client, watched, attribute_id);
+
+    let string = |s: &str| proto::TripleValue {
+        value: Some(proto::triple_value::Value::String(s.to_string())),
+    };
+    let updates = drain_updates(&sibling, &mut change_rx);
+    let old_values: Vec<_> = updates
+        .iter()
+        .flat_map(|update| &update.changes)
+        .map(|change| (change.change_type, change.old_value.clone()))
+        .collect();
+    assert_eq!(
+        old_values,
+        vec![
+            (proto::ChangeType::Insert as i32, None),
+            (proto::ChangeType::Update as i32, Some(string("v1"))),
+            (proto::ChangeType::Delete as i32, Some(string("v2"))),
+        ]
+    );
+}
+
+#[test]
+fn test_entity_subscription_rejects_invalid_requests() {
+    let client = TestClient::new();
+    let mut sibling = client.create_sibling();
+    let entity_id = new_entity_id(6);
+
+    let short_id = watch_entity(&mut sibling, 1, &[1, 2, 3], false);
+    assert_eq!(short_id.len(), 1);
+
+    let combined = sibling.client.handle_message(proto::ClientMessage {
+        request_id: Some(2),
+        payload: Some(proto::client_message::Payload::Subscribe(
+            proto::SubscribeRequest {
+                subscription_id: 2,
+                since_hlc: Some(new_hlc(1)),
+                query: None,
+                watch_entity_id: Some(entity_id.to_vec()),
+                until_hlc: None,
+                include_old_values: false,
+                entity_id: None,
+                attribute_id: None,
+            },
+        )),
+    });
+    assert_eq!(combined.len(), 1);
+
+    for message in short_id.iter().chain(&combined) {
+        let Some(proto::server_message::Payload::Response(response)) = &message.payload else {
+            panic!("expected a response, got {:?}", message.payload);
+        };
+        assert!(!is_ok(response));
+    }
+    assert_eq!(sibling.client.subscriptions().count(), 0);
+}
@@ -0,0 +1,273 @@
+//! Test per-entity versions and `WRITE_MODE_COMMIT_IF_VERSION` writes.
+
+use crate::e2e_tests::helpers::{
+    TestClient, get_number_value, get_string_value, is_ok, new_attribute_id, new_entity_id,
+    new_hlc, status_code,
+};
+use crate::proto;
+use crate::storage::entity_versions::ENTITY_VERSION_ATTRIBUTE_ID;
+use crate::types::error_detail::error_reason;
+use crate::types::{AttributeId, EntityId, TripleValue};
+
+/// The entity every test writes.
+const ENTITY: u8 = 1;
+
+/// Attributes written to the entity.
+const NAME: u8 = 1;
+const EMAIL: u8 = 2;
+
+/// Send a triple update.
+fn send_update(
+    client: &mut TestClient,
+    request: proto::TripleUpdateRequest,
+) -> proto::ServerResponse {
+    client.handle_message(proto::ClientMessage {
+        request_id: Some(1),
+        payload: Some(proto::client_message::Payload::TripleUpdateRequest(request)),
+    })
+}
+
+/// A string triple on the test entity.
+fn triple(attribute: u8, value: &str, hlc_seed: u64) -> proto::Triple {
+    proto::Triple {
+        entity_id: Some(new_entity_id(ENTITY).to_vec()),
+        attribute_id: Some(new_attribute_id(attribute).to_vec()),
+        value: Some(proto::TripleValue {
+            value: Some(proto::triple_value::Value::String(value.to_string())),
+        }),
+        hlc: Some(new_hlc(hlc_seed)),
+        attribute_name: None,
+    }
+}
+
+/// Upsert one attribute of the test entity.
+fn upsert(
+    client: &mut TestClient,
+    attribute: u8,
+    value: &str,
+    hlc_seed: u64,
+) -> proto::ServerResponse {
+    send_update(
+        client,
+        proto::TripleUpdateRequest {
+            triples: vec![triple(attribute, value, hlc_seed)],
+            mode: proto::WriteMode::Upsert.into(),
+            expected_versions: vec![],
+        },
+    )
+}
+
+/// Write one attribute of the test entity if it is at `version`.
+fn commit_if_version(
+    client: &mut TestClient,
+    attribute: u8,
+    value: &str,
+    hlc_seed: u64,
+    version: u64,
+) -> proto::ServerResponse {
+    send_update(
+        client,
+        proto::TripleUpdateRequest {
+            triples: vec![triple(attribute, value, hlc_seed)],
+            mode: proto::WriteMode::CommitIfVersion.into(),
+            expected_versions: vec![proto::EntityVersion {
+                entity_id: new_entity_id(ENTITY).to_vec(),
+                version,
+            }],
+        },
+    )
+}
+
+/// Query one attribute of the test entity.
+fn query_attribute(client: &mut TestClient, attribute_id: Vec<u8>) -> proto::ServerResponse {
+    let response = client.handle_message(proto::ClientMessage {
+        request_id: Some(2),
+        payload: Some(proto::client_message::Payload::Query(proto::QueryRequest {
+            find: vec![proto::QueryPatternVariable {
+                label: Some("value".to_string()),
+            }],
+            r#where: vec![proto::QueryPattern {
+                entity: Some(proto::query_pattern::Entity::EntityId(
+                    new_entity_id(ENTITY).to_vec(),
+                )),
+                attribute: Some(proto::query_pattern::Attribute::AttributeId(attribute_id)),
+                value_group: Some(proto::query_pattern::ValueGroup::ValueVariable(
+                    proto::QueryPatternVariable {
+                        label: Some("value".to_string()),
+                    },
+                )),
+            }],
+            optional: vec![],
+            where_not: vec![],
+            arrow_ipc: false,
+            limit: None,
+            offset: None,
+            order_by: vec![],
+            aggregates: vec![],
+            group_by: vec![],
+            distinct: false,
+        })),
+    });
+    assert!(is_ok(&response));
+    response
+}
+
+/// The test entity's version, as a query reads it.
+fn version(client: &mut TestClient) -> Option<f64> {
+    let response = query_attribute(client, ENTITY_VERSION_ATTRIBUTE_ID.0.to_vec());
+    get_number_value(&response, 0)
+}
+
+#[test]
+fn test_version_counts_changing_commits() {
+    let mut client = TestClient::with_entity_versions();
+    assert_eq!(version(&mut client), None);
+
+    assert!(is_ok(&upsert(&mut client, NAME, "Ada", 1)));
+    assert!(is_ok(&upsert(&mut client, EMAIL, "ada@example.com", 2)));
+    assert_eq!(version(&mut client), Some(2.0));
+
+    // Rewriting the same value changes nothing
+    assert!(is_ok(&upsert(&mut client, NAME, "Ada", 3)));
+    assert_eq!(version(&mut client), Some(2.0));
+}
+
+#[test]
+fn test_change_notifications_carry_the_version() {
+    let mut client = TestClient::with_entity_versions();
+    let sibling = client.create_sibling();
+    let mut change_rx = sibling.subscribe_to_changes();
+
+    assert!(is_ok(&upsert(&mut client, NAME, "Ada", 1)));
+
+    let notification = change_rx
+        .try_recv()
+        .expect("sibling should receive notification");
+    let changes: Vec<_> = notification
+        .changes
+        .iter()
+        .map(|change| (change.entity_id, change.attribute_id, &change.value))
+        .collect();
+    assert_eq!(
+        changes,
+        vec![
+            (
+                EntityId(new_entity_id(ENTITY)),
+                AttributeId(new_attribute_id(NAME)),
+                &Some(TripleValue::String("Ada".to_string())),
+            ),
+            (
+                EntityId(new_entity_id(ENTITY)),
+                ENTITY_VERSION_ATTRIBUTE_ID,
+                &Some(TripleValue::Number(1.0)),
+            ),
+        ]
+    );
+}
+
+#[test]
+fn test_commit_if_version_writes_at_expected_version() {
+    let mut client = TestClient::with_entity_versions();
+    assert!(is_ok(&commit_if_version(&mut client, NAME, "Ada", 1, 0)));
+    assert!(is_ok(&commit_if_version(&mut client, NAME, "Grace", 2, 1)));
+
+    let response = query_attribute(&mut client, new_attribute_id(NAME).to_vec());
+    assert_eq!(get_string_value(&response, 0), Some("Grace"));
+    assert_eq!(version(&mut client), Some(2.0));
+}
+
+#[test]
+fn test_stale_version_write_is_rejected() {
+    let mut client = TestClient::with_entity_versions();
+    let mut other = client.create_sibling();
+    assert!(is_ok(&upsert(&mut client, NAME, "Ada", 1)));
+    assert_eq!(version(&mut client), Some(1.0));
+
+    // Another connection changes a different attribute of the entity
+    let response = other.handle_message(proto::ClientMessage {
+        request_id: Some(1),
+        payload: Some(proto::client_message::Payload::TripleUpdateRequest(
+            proto::TripleUpdateRequest {
+                triples: vec![triple(EMAIL, "ada@example.com", 2)],
+                mode: proto::WriteMode::Upsert.into(),
+                expected_versions: vec![],
+            },
+        )),
+    });
+    assert!(is_ok(&response));
+
+    let response = commit_if_version(&mut client, NAME, "Grace", 3, 1);
+
+    assert_eq!(
+        status_code(&response),
+        proto::google::rpc::Code::Aborted as i32
+    );
+    assert_eq!(
+        response.status.as_ref().and_then(error_reason),
+        Some(proto::error_detail::Reason::VersionMismatch(
+            proto::VersionMismatch {
+                entity_id: new_entity_id(ENTITY).to_vec(),
+                expected: 1,
+                actual: 2,
+            }
+        ))
+    );
+    // Nothing was written
+    let response = query_attribute(&mut client, new_attribute_id(NAME).to_vec());
+    assert_eq!(get_string_value(&response, 0), Some("Ada"));
+    assert_eq!(version(&mut client), Some(2.0));
+}
+
+#[test]
+fn test_commit_if_version_needs_every_entity_listed() {
+    let mut client = TestClient::with_entity_versions();
+
+    let response = send_update(
+        &mut client,
+        proto::TripleUpdateRequest {
+            triples: vec![triple(NAME, "Ada", 1)],
+            mode: proto::WriteMode::CommitIfVersion.into(),
+            expected_versions: vec![],
+        },
+    );
+
+    assert_eq!(
+        status_code(&response),
+        proto::google::rpc::Code::InvalidArgument as i32
+    );
+}
+
+#[test]
+fn test_expected_versions_need_commit_if_version() {
+    let mut client = TestClient::with_entity_versions();
+
+    let response = send_update(
+        &mut client,
+        proto::TripleUpdateRequest {
+            triples: vec![triple(NAME, "Ada", 1)],
+            mode: proto::WriteMode::Upsert.into(),
+            expected_versions: vec![proto::EntityVersion {
+                entity_id: new_entity_id(ENTITY).to_vec(),
+                version: 0,
+            }],
+        },
+    );
+
+    assert_eq!(
+        status_code(&response),
+        proto::google::rpc::Code::InvalidArgument as i32
+    );
+}
+
+#[test]
+fn test_commit_if_version_needs_versions_enabled() {
+    let mut client = TestClient::new();
+
+    let response = commit_if_version(&mut client, NAME, "Ada", 1, 0);
+
+    assert_eq!(
+        status_code(&response),
+        proto::google::rpc::Code::FailedPrecondition as i32
+    );
+    assert_eq!(version(&mut client), None);
+}
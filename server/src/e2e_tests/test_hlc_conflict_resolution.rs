@@ -6,7 +6,9 @@
 //! - Responses include the current HLC for each triple
 //! - Queries return the correct version after updates
 
-use crate::e2e_tests::helpers::{TestClient, is_ok, new_attribute_id, new_entity_id};
+use crate::e2e_tests::helpers::{
+    TestClient, get_string_value, is_ok, new_attribute_id, new_entity_id,
+};
 use crate::proto;
 
 /// Helper to create an HLC timestamp.
@@ -32,6 +34,7 @@ fn make_triple(
             value: Some(proto::triple_value::Value::String(value.to_string())),
         }),
         hlc: Some(hlc),
+        attribute_name: None,
     }
 }
 
@@ -61,6 +64,13 @@ fn query_triple(
             }],
             optional: vec![],
             where_not: vec![],
+            arrow_ipc: false,
+            limit: None,
+            offset: None,
+            order_by: vec![],
+            aggregates: vec![],
+            group_by: vec![],
+            distinct: false,
         })),
     })
 }
@@ -91,6 +101,8 @@ fn test_insert_new_triple_succeeds() {
         payload: Some(proto::client_message::Payload::TripleUpdateRequest(
             proto::TripleUpdateRequest {
                 triples: vec![make_triple(entity_id, attribute_id, "initial", hlc)],
+                mode: proto::WriteMode::Upsert.into(),
+                expected_versions: vec![],
             },
         )),
     });
@@ -127,6 +139,8 @@ fn test_newer_hlc_wins() {
         payload: Some(proto::client_message::Payload::TripleUpdateRequest(
             proto::TripleUpdateRequest {
                 triples: vec![make_triple(entity_id, attribute_id, "initial", initial_hlc)],
+                mode: proto::WriteMode::Upsert.into(),
+                expected_versions: vec![],
             },
         )),
     });
@@ -139,6 +153,8 @@ fn test_newer_hlc_wins() {
         payload: Some(proto::client_message::Payload::TripleUpdateRequest(
             proto::TripleUpdateRequest {
                 triples: vec![make_triple(entity_id, attribute_id, "updated", newer_hlc)],
+                mode: proto::WriteMode::Upsert.into(),
+                expected_versions: vec![],
             },
         )),
     });
@@ -174,6 +190,8 @@ fn test_older_hlc_loses() {
         payload: Some(proto::client_message::Payload::TripleUpdateRequest(
             proto::TripleUpdateRequest {
                 triples: vec![make_triple(entity_id, attribute_id, "initial", initial_hlc)],
+                mode: proto::WriteMode::Upsert.into(),
+                expected_versions: vec![],
             },
         )),
     });
@@ -186,6 +204,8 @@ fn test_older_hlc_loses() {
         payload: Some(proto::client_message::Payload::TripleUpdateRequest(
             proto::TripleUpdateRequest {
                 triples: vec![make_triple(entity_id, attribute_id, "rejected", older_hlc)],
+                mode: proto::WriteMode::Upsert.into(),
+                expected_versions: vec![],
             },
         )),
     });
@@ -221,6 +241,8 @@ fn test_equal_hlc_rejected() {
         payload: Some(proto::client_message::Payload::TripleUpdateRequest(
             proto::TripleUpdateRequest {
                 triples: vec![make_triple(entity_id, attribute_id, "initial", hlc)],
+                mode: proto::WriteMode::Upsert.into(),
+                expected_versions: vec![],
             },
         )),
     });
@@ -233,6 +255,8 @@ fn test_equal_hlc_rejected() {
         payload: Some(proto::client_message::Payload::TripleUpdateRequest(
             proto::TripleUpdateRequest {
                 triples: vec![make_triple(entity_id, attribute_id, "rejected", same_hlc)],
+                mode: proto::WriteMode::Upsert.into(),
+                expected_versions: vec![],
             },
         )),
     });
@@ -268,6 +292,8 @@ fn test_logical_counter_newer_wins() {
         payload: Some(proto::client_message::Payload::TripleUpdateRequest(
             proto::TripleUpdateRequest {
                 triples: vec![make_triple(entity_id, attribute_id, "initial", initial_hlc)],
+                mode: proto::WriteMode::Upsert.into(),
+                expected_versions: vec![],
             },
         )),
     });
@@ -280,6 +306,8 @@ fn test_logical_counter_newer_wins() {
         payload: Some(proto::client_message::Payload::TripleUpdateRequest(
             proto::TripleUpdateRequest {
                 triples: vec![make_triple(entity_id, attribute_id, "updated", newer_hlc)],
+                mode: proto::WriteMode::Upsert.into(),
+                expected_versions: vec![],
             },
         )),
     });
@@ -313,6 +341,8 @@ fn test_logical_counter_older_loses() {
         payload: Some(proto::client_message::Payload::TripleUpdateRequest(
             proto::TripleUpdateRequest {
                 triples: vec![make_triple(entity_id, attribute_id, "initial", initial_hlc)],
+                mode: proto::WriteMode::Upsert.into(),
+                expected_versions: vec![],
             },
         )),
     });
@@ -325,6 +355,8 @@ fn test_logical_counter_older_loses() {
         payload: Some(proto::client_message::Payload::TripleUpdateRequest(
             proto::TripleUpdateRequest {
                 triples: vec![make_triple(entity_id, attribute_id, "rejected", older_hlc)],
+                mode: proto::WriteMode::Upsert.into(),
+                expected_versions: vec![],
             },
         )),
     });
@@ -360,6 +392,8 @@ fn test_node_id_newer_wins() {
         payload: Some(proto::client_message::Payload::TripleUpdateRequest(
             proto::TripleUpdateRequest {
                 triples: vec![make_triple(entity_id, attribute_id, "initial", initial_hlc)],
+                mode: proto::WriteMode::Upsert.into(),
+                expected_versions: vec![],
             },
         )),
     });
@@ -372,6 +406,8 @@ fn test_node_id_newer_wins() {
         payload: Some(proto::client_message::Payload::TripleUpdateRequest(
             proto::TripleUpdateRequest {
                 triples: vec![make_triple(entity_id, attribute_id, "updated", newer_hlc)],
+                mode: proto::WriteMode::Upsert.into(),
+                expected_versions: vec![],
             },
         )),
     });
@@ -406,6 +442,8 @@ fn test_node_id_older_loses() {
         payload: Some(proto::client_message::Payload::TripleUpdateRequest(
             proto::TripleUpdateRequest {
                 triples: vec![make_triple(entity_id, attribute_id, "initial", initial_hlc)],
+                mode: proto::WriteMode::Upsert.into(),
+                expected_versions: vec![],
             },
         )),
     });
@@ -418,6 +456,8 @@ fn test_node_id_older_loses() {
         payload: Some(proto::client_message::Payload::TripleUpdateRequest(
             proto::TripleUpdateRequest {
                 triples: vec![make_triple(entity_id, attribute_id, "rejected", older_hlc)],
+                mode: proto::WriteMode::Upsert.into(),
+                expected_versions: vec![],
             },
         )),
     });
@@ -457,6 +497,8 @@ fn test_mixed_batch_some_update_some_reject() {
                     make_triple(entity1, attribute_id_1, "value1", make_hlc(1000, 0, 1)),
                     make_triple(entity2, attribute_id_2, "value2", make_hlc(2000, 0, 1)),
                 ],
+                mode: proto::WriteMode::Upsert.into(),
+                expected_versions: vec![],
             },
         )),
     });
@@ -473,6 +515,8 @@ fn test_mixed_batch_some_update_some_reject() {
                     make_triple(entity1, attribute_id_1, "updated1", make_hlc(3000, 0, 1)), // Newer
                     make_triple(entity2, attribute_id_2, "rejected2", make_hlc(1500, 0, 1)), // Older
                 ],
+                mode: proto::WriteMode::Upsert.into(),
+                expected_versions: vec![],
             },
         )),
     });
@@ -521,6 +565,8 @@ fn test_response_includes_hlc() {
         payload: Some(proto::client_message::Payload::TripleUpdateRequest(
             proto::TripleUpdateRequest {
                 triples: vec![make_triple(entity_id, attribute_id, "test", hlc)],
+                mode: proto::WriteMode::Upsert.into(),
+                expected_versions: vec![],
             },
         )),
     });
@@ -557,7 +603,10 @@ fn test_missing_hlc_rejected() {
                         value: Some(proto::triple_value::Value::String("test".to_string())),
                     }),
                     hlc: None, // Missing HLC
+                    attribute_name: None,
                 }],
+                mode: proto::WriteMode::Upsert.into(),
+                expected_versions: vec![],
             },
         )),
     });
@@ -571,3 +620,85 @@ fn test_missing_hlc_rejected() {
     );
     assert!(status.message.contains("hlc"));
 }
+
+/// Current wall-clock time in milliseconds.
+fn now_ms() -> u64 {
+    let elapsed = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .expect("system time after epoch");
+    u64::try_from(elapsed.as_millis()).expect("time fits in u64")
+}
+
+#[test]
+fn test_far_future_hlc_rejected() {
+    let mut client = TestClient::new();
+
+    let entity_id = new_entity_id(109);
+    let attribute_id = new_attribute_id(109);
+
+    // An hour ahead is well past the allowed clock drift
+    let future_hlc = make_hlc(now_ms() + 60 * 60 * 1000, 0, 1);
+    let response = client.handle_message(proto::ClientMessage {
+        request_id: Some(1),
+        payload: Some(proto::client_message::Payload::TripleUpdateRequest(
+            proto::TripleUpdateRequest {
+                triples: vec![make_triple(entity_id, attribute_id, "future", future_hlc)],
+                mode: proto::WriteMode::Upsert.into(),
+                expected_versions: vec![],
+            },
+        )),
+    });
+
+    let status = response.status.as_ref().expect("status");
+    assert_eq!(
+        status.code,
+        proto::google::rpc::Code::InvalidArgument as i32
+    );
+    assert!(status.message.contains("drift"));
+
+    // Nothing was written
+    let query_response = query_triple(&mut client, entity_id, attribute_id, 2);
+    assert!(query_response.rows.is_empty());
+}
+
+#[test]
+fn test_offline_hlc_preserved_and_stale_write_loses() {
+    let mut client = TestClient::new();
+
+    let entity_id = new_entity_id(110);
+    let attribute_id = new_attribute_id(110);
+    let now = now_ms();
+
+    // A change made online, then one made offline earlier and synced later
+    let online_hlc = make_hlc(now, 0, 1);
+    let offline_hlc = make_hlc(now - 10 * 60 * 1000, 0, 2);
+
+    let online = client.handle_message(proto::ClientMessage {
+        request_id: Some(1),
+        payload: Some(proto::client_message::Payload::TripleUpdateRequest(
+            proto::TripleUpdateRequest {
+                triples: vec![make_triple(entity_id, attribute_id, "online", online_hlc)],
+                mode: proto::WriteMode::Upsert.into(),
+                expected_versions: vec![],
+            },
+        )),
+    });
+    assert!(is_ok(&online));
+
+    let offline = client.handle_message(proto::ClientMessage {
+        request_id: Some(2),
+        payload: Some(proto::client_message::Payload::TripleUpdateRequest(
+            proto::TripleUpdateRequest {
+                triples: vec![make_triple(entity_id, attribute_id, "offline", offline_hlc)],
+                mode: proto::WriteMode::Upsert.into(),
+                expected_versions: vec![],
+            },
+        )),
+    });
+    assert!(is_ok(&offline));
+
+    // The stored triple keeps the newer client HLC, not a server timestamp
+    assert_eq!(offline.triples[0].hlc, Some(online_hlc));
+    let query_response = query_triple(&mut client, entity_id, attribute_id, 3);
+    assert_eq!(get_string_value(&query_response, 0), Some("online"));
+}
@@ -5,6 +5,43 @@ use crate::e2e_tests::helpers::{
 };
 use crate::proto;
 
+/// Query the value of `attribute_id` on `entity_id`.
+fn query_value(
+    client: &mut TestClient,
+    request_id: u32,
+    entity_id: [u8; 16],
+    attribute_id: [u8; 16],
+) -> proto::ServerResponse {
+    client.handle_message(proto::ClientMessage {
+        request_id: Some(request_id),
+        payload: Some(proto::client_message::Payload::Query(proto::QueryRequest {
+            find: vec![proto::QueryPatternVariable {
+                label: Some("value".to_string()),
+            }],
+            r#where: vec![proto::QueryPattern {
+                entity: Some(proto::query_pattern::Entity::EntityId(entity_id.to_vec())),
+                attribute: Some(proto::query_pattern::Attribute::AttributeId(
+                    attribute_id.to_vec(),
+                )),
+                value_group: Some(proto::query_pattern::ValueGroup::ValueVariable(
+                    proto::QueryPatternVariable {
+                        label: Some("value".to_string()),
+                    },
+                )),
+            }],
+            optional: vec![],
+            where_not: vec![],
+            arrow_ipc: false,
+            limit: None,
+            offset: None,
+            order_by: vec![],
+            aggregates: vec![],
+            group_by: vec![],
+            distinct: false,
+        })),
+    })
+}
+
 #[test]
 fn test_insert_multiple_entities() {
     let mut client = TestClient::new();
@@ -25,7 +62,10 @@ fn test_insert_multiple_entities() {
                         value: Some(proto::triple_value::Value::String("entity one".to_string())),
                     }),
                     hlc: Some(new_hlc(1)),
+                    attribute_name: None,
                 }],
+                mode: proto::WriteMode::Upsert.into(),
+                expected_versions: vec![],
             },
         )),
     });
@@ -43,60 +83,23 @@ fn test_insert_multiple_entities() {
                         value: Some(proto::triple_value::Value::String("entity two".to_string())),
                     }),
                     hlc: Some(new_hlc(2)),
+                    attribute_name: None,
                 }],
+                mode: proto::WriteMode::Upsert.into(),
+                expected_versions: vec![],
             },
         )),
     });
     assert!(is_ok(&response2));
 
     // Query entity 1
-    let query1 = client.handle_message(proto::ClientMessage {
-        request_id: Some(3),
-        payload: Some(proto::client_message::Payload::Query(proto::QueryRequest {
-            find: vec![proto::QueryPatternVariable {
-                label: Some("value".to_string()),
-            }],
-            r#where: vec![proto::QueryPattern {
-                entity: Some(proto::query_pattern::Entity::EntityId(entity_id_1.to_vec())),
-                attribute: Some(proto::query_pattern::Attribute::AttributeId(
-                    attribute_id.to_vec(),
-                )),
-                value_group: Some(proto::query_pattern::ValueGroup::ValueVariable(
-                    proto::QueryPatternVariable {
-                        label: Some("value".to_string()),
-                    },
-                )),
-            }],
-            optional: vec![],
-            where_not: vec![],
-        })),
-    });
+    let query1 = query_value(&mut client, 3, entity_id_1, attribute_id);
     assert!(is_ok(&query1));
     assert_eq!(query1.rows.len(), 1);
     assert_eq!(get_string_value(&query1, 0), Some("entity one"));
 
     // Query entity 2
-    let query2 = client.handle_message(proto::ClientMessage {
-        request_id: Some(4),
-        payload: Some(proto::client_message::Payload::Query(proto::QueryRequest {
-            find: vec![proto::QueryPatternVariable {
-                label: Some("value".to_string()),
-            }],
-            r#where: vec![proto::QueryPattern {
-                entity: Some(proto::query_pattern::Entity::EntityId(entity_id_2.to_vec())),
-                attribute: Some(proto::query_pattern::Attribute::AttributeId(
-                    attribute_id.to_vec(),
-                )),
-                value_group: Some(proto::query_pattern::ValueGroup::ValueVariable(
-                    proto::QueryPatternVariable {
-                        label: Some("value".to_string()),
-                    },
-                )),
-            }],
-            optional: vec![],
-            where_not: vec![],
-        })),
-    });
+    let query2 = query_value(&mut client, 4, entity_id_2, attribute_id);
     assert!(is_ok(&query2));
     assert_eq!(query2.rows.len(), 1);
     assert_eq!(get_string_value(&query2, 0), Some("entity two"));
@@ -25,6 +25,7 @@ fn test_insert_multiple_triples_single_request() {
                             value: Some(proto::triple_value::Value::String("name".to_string())),
                         }),
                         hlc: Some(new_hlc(1)),
+                        attribute_name: None,
                     },
                     proto::Triple {
                         entity_id: Some(entity_id.to_vec()),
@@ -33,6 +34,7 @@ fn test_insert_multiple_triples_single_request() {
                             value: Some(proto::triple_value::Value::Number(25.0)),
                         }),
                         hlc: Some(new_hlc(2)),
+                        attribute_name: None,
                     },
                     proto::Triple {
                         entity_id: Some(entity_id.to_vec()),
@@ -41,8 +43,11 @@ fn test_insert_multiple_triples_single_request() {
                             value: Some(proto::triple_value::Value::Boolean(false)),
                         }),
                         hlc: Some(new_hlc(3)),
+                        attribute_name: None,
                     },
                 ],
+                mode: proto::WriteMode::Upsert.into(),
+                expected_versions: vec![],
             },
         )),
     });
@@ -76,6 +81,13 @@ fn test_insert_multiple_triples_single_request() {
             }],
             optional: vec![],
             where_not: vec![],
+            arrow_ipc: false,
+            limit: None,
+            offset: None,
+            order_by: vec![],
+            aggregates: vec![],
+            group_by: vec![],
+            distinct: false,
         })),
     });
 
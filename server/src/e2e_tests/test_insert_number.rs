@@ -24,7 +24,10 @@ fn test_insert_number_then_query() {
                         value: Some(proto::triple_value::Value::Number(42.5)),
                     }),
                     hlc: Some(new_hlc(1)),
+                    attribute_name: None,
                 }],
+                mode: proto::WriteMode::Upsert.into(),
+                expected_versions: vec![],
             },
         )),
     });
@@ -51,6 +54,13 @@ fn test_insert_number_then_query() {
             }],
             optional: vec![],
             where_not: vec![],
+            arrow_ipc: false,
+            limit: None,
+            offset: None,
+            order_by: vec![],
+            aggregates: vec![],
+            group_by: vec![],
+            distinct: false,
         })),
     });
 
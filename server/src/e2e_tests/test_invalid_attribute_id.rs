@@ -19,7 +19,10 @@ fn test_invalid_attribute_id_length() {
                         value: Some(proto::triple_value::Value::String("test".to_string())),
                     }),
                     hlc: Some(new_hlc(1)),
+                    attribute_name: None,
                 }],
+                mode: proto::WriteMode::Upsert.into(),
+                expected_versions: vec![],
             },
         )),
     };
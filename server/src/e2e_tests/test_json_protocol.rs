@@ -0,0 +1,167 @@
+//! Test the JSON encoding of the protocol over a real WebSocket.
+//!
+//! Text frames carry JSON and are answered with JSON text frames; binary
+//! frames on the same connection still use protobuf.
+
+use futures::{SinkExt, StreamExt};
+use prost::Message as ProstMessage;
+use serde_json::{Value, json};
+use tokio_tungstenite::tungstenite::Message;
+
+use crate::e2e_tests::helpers::start_server;
+use crate::proto;
+
+type Socket =
+    tokio_tungstenite::WebSocketStream<tokio_tungstenite::MaybeTlsStream<tokio::net::TcpStream>>;
+
+/// Send a JSON request and return the parsed JSON response.
+async fn request_json(socket: &mut Socket, request: &Value) -> Value {
+    socket
+        .send(Message::Text(request.to_string().into()))
+        .await
+        .expect("send");
+    match socket.next().await.expect("response").expect("frame") {
+        Message::Text(text) => serde_json::from_str(&text).expect("response is JSON"),
+        other => panic!("expected a text frame, got {other:?}"),
+    }
+}
+
+#[tokio::test]
+async fn test_json_query_gets_json_response() {
+    let (_dir, url) = start_server().await;
+    let (mut socket, _) = tokio_tungstenite::connect_async(url.as_str())
+        .await
+        .expect("connect");
+
+    let connected = request_json(
+        &mut socket,
+        &json!({"requestId": 1, "connect": {"appApiKey": "json-app"}}),
+    )
+    .await;
+    assert_eq!(connected["response"]["requestId"], 1);
+    assert_eq!(connected["response"]["status"]["code"], 0);
+
+    // 16 bytes of 0x01 and 0x02, base64-encoded
+    let entity_id = "AQEBAQEBAQEBAQEBAQEBAQ==";
+    let attribute_id = "AgICAgICAgICAgICAgICAg==";
+
+    let inserted = request_json(
+        &mut socket,
+        &json!({
+            "requestId": 2,
+            "tripleUpdateRequest": {"triples": [{
+                "entityId": entity_id,
+                "attributeId": attribute_id,
+                "value": {"string": "Alice"},
+                "hlc": {"physicalTimeMs": "1700000000000", "logicalCounter": 0, "nodeId": 1}
+            }]}
+        }),
+    )
+    .await;
+    assert_eq!(inserted["response"]["status"]["code"], 0);
+    assert_eq!(inserted["response"]["triples"][0]["entityId"], entity_id);
+
+    let queried = request_json(
+        &mut socket,
+        &json!({
+            "requestId": 3,
+            "query": {
+                "find": [{"label": "name"}],
+                "where": [{
+                    "entityId": entity_id,
+                    "attributeId": attribute_id,
+                    "valueVariable": {"label": "name"}
+                }]
+            }
+        }),
+    )
+    .await;
+    assert_eq!(queried["response"]["requestId"], 3);
+    assert_eq!(queried["response"]["status"]["code"], 0);
+    assert_eq!(queried["response"]["columns"], json!(["name"]));
+    assert_eq!(
+        queried["response"]["rows"][0]["values"][0]["tripleValue"]["string"],
+        "Alice"
+    );
+}
+
+#[tokio::test]
+async fn test_invalid_json_gets_json_error() {
+    let (_dir, url) = start_server().await;
+    let (mut socket, _) = tokio_tungstenite::connect_async(url.as_str())
+        .await
+        .expect("connect");
+
+    socket
+        .send(Message::Text("{not json".into()))
+        .await
+        .expect("send");
+    let Message::Text(text) = socket.next().await.expect("response").expect("frame") else {
+        panic!("expected a text frame");
+    };
+    let response: Value = serde_json::from_str(&text).expect("response is JSON");
+
+    assert_eq!(
+        response["response"]["status"]["code"],
+        proto::google::rpc::Code::InvalidArgument as i32
+    );
+}
+
+#[tokio::test]
+async fn test_binary_frames_still_use_protobuf() {
+    let (_dir, url) = start_server().await;
+    let (mut socket, _) = tokio_tungstenite::connect_async(url.as_str())
+        .await
+        .expect("connect");
+
+    // A JSON request first, then a binary one on the same connection
+    request_json(
+        &mut socket,
+        &json!({"requestId": 1, "connect": {"appApiKey": "mixed-app"}}),
+    )
+    .await;
+    let query = proto::ClientMessage {
+        request_id: Some(2),
+        payload: Some(proto::client_message::Payload::Query(proto::QueryRequest {
+            find: vec![proto::QueryPatternVariable {
+                label: Some("value".to_string()),
+            }],
+            r#where: vec![proto::QueryPattern {
+                entity: Some(proto::query_pattern::Entity::EntityVariable(
+                    proto::QueryPatternVariable {
+                        label: Some("e".to_string()),
+                    },
+                )),
+                attribute: Some(proto::query_pattern::Attribute::AttributeId(vec![2u8; 16])),
+                value_group: Some(proto::query_pattern::ValueGroup::ValueVariable(
+                    proto::QueryPatternVariable {
+                        label: Some("value".to_string()),
+                    },
+                )),
+            }],
+            optional: vec![],
+            where_not: vec![],
+            arrow_ipc: false,
+            limit: None,
+            offset: None,
+            order_by: vec![],
+            aggregates: vec![],
+            group_by: vec![],
+            distinct: false,
+        })),
+    };
+    socket
+        .send(Message::Binary(query.encode_to_vec().into()))
+        .await
+        .expect("send");
+
+    let Message::Binary(bytes) = socket.next().await.expect("response").expect("frame") else {
+        panic!("expected a binary frame");
+    };
+    let message = proto::ServerMessage::decode(bytes.as_ref()).expect("response is protobuf");
+    let Some(proto::server_message::Payload::Response(response)) = message.payload else {
+        panic!("expected a response");
+    };
+    assert_eq!(response.request_id, Some(2));
+    assert!(response.rows.is_empty());
+}
@@ -0,0 +1,171 @@
+//! End-to-end tests for live query subscriptions.
+//!
+//! A `SubscribeQueryRequest` receives its query's result when subscribing,
+//! then a fresh result whenever a committed change can affect the query. Like
+//! the WebSocket handler, these tests turn each broadcast notification into
+//! per-subscription updates with `ClientConnection::updates_for`.
+
+use crate::e2e_tests::helpers::{
+    SiblingClient, TestClient, drain_updates, insert_seeded, is_ok, new_attribute_id, new_entity_id,
+};
+use crate::proto;
+
+/// Build the query `[?e attribute_id ?value]`, finding `?value`.
+fn values_query(attribute_id: [u8; 16]) -> proto::QueryRequest {
+    proto::QueryRequest {
+        find: vec![proto::QueryPatternVariable {
+            label: Some("value".to_string()),
+        }],
+        r#where: vec![proto::QueryPattern {
+            entity: Some(proto::query_pattern::Entity::EntityVariable(
+                proto::QueryPatternVariable {
+                    label: Some("e".to_string()),
+                },
+            )),
+            attribute: Some(proto::query_pattern::Attribute::AttributeId(
+                attribute_id.to_vec(),
+            )),
+            value_group: Some(proto::query_pattern::ValueGroup::ValueVariable(
+                proto::QueryPatternVariable {
+                    label: Some("value".to_string()),
+                },
+            )),
+        }],
+        ..Default::default()
+    }
+}
+
+/// Subscribe to a live query and return every message the server sent back.
+fn subscribe_live(
+    sibling: &mut SiblingClient,
+    subscription_id: u32,
+    query: Option<proto::QueryRequest>,
+) -> Vec<proto::ServerMessage> {
+    sibling.client.handle_message(proto::ClientMessage {
+        request_id: Some(subscription_id),
+        payload: Some(proto::client_message::Payload::SubscribeQuery(
+            proto::SubscribeQueryRequest {
+                subscription_id,
+                query,
+            },
+        )),
+    })
+}
+
+/// The sorted string values in the first column of a live query result.
+fn result_strings(update: &proto::SubscriptionUpdate) -> Vec<String> {
+    let result = update
+        .query_result
+        .as_ref()
+        .expect("update should carry a query result");
+    assert_eq!(
+        result.status.as_ref().map(|status| status.code),
+        Some(proto::google::rpc::Code::Ok as i32)
+    );
+    let mut values: Vec<String> = result
+        .rows
+        .iter()
+        .filter_map(|row| match &row.values.first()?.value {
+            Some(proto::query_result_value::Value::TripleValue(proto::TripleValue {
+                value: Some(proto::triple_value::Value::String(s)),
+            })) => Some(s.clone()),
+            _ => None,
+        })
+        .collect();
+    values.sort();
+    values
+}
+
+#[test]
+fn test_live_query_sends_initial_result() {
+    let mut client = TestClient::new();
+    let mut sibling = client.create_sibling();
+    let attribute_id = new_attribute_id(1);
+    insert_seeded(&mut client, new_entity_id(1), attribute_id, 1);
+
+    let messages = subscribe_live(&mut sibling, 1, Some(values_query(attribute_id)));
+    assert_eq!(messages.len(), 2, "initial result, then OK");
+    let Some(proto::server_message::Payload::SubscriptionUpdate(update)) = &messages[0].payload
+    else {
+        panic!("expected SubscriptionUpdate, got {:?}", messages[0].payload);
+    };
+    assert_eq!(update.subscription_id, 1);
+    assert!(update.changes.is_empty());
+    assert!(!update.query_invalidated);
+    assert_eq!(result_strings(update), vec!["v1".to_string()]);
+    let Some(proto::server_message::Payload::Response(response)) = &messages[1].payload else {
+        panic!("expected a response, got {:?}", messages[1].payload);
+    };
+    assert!(is_ok(response));
+}
+
+#[test]
+fn test_live_query_reruns_only_on_matching_change() {
+    let mut client = TestClient::new();
+    let mut sibling = client.create_sibling();
+    let mut change_rx = sibling.subscribe_to_changes();
+    let watched = new_attribute_id(2);
+    let other = new_attribute_id(3);
+
+    subscribe_live(&mut sibling, 1, Some(values_query(watched)));
+
+    insert_seeded(&mut client, new_entity_id(2), other, 1);
+    assert!(
+        drain_updates(&sibling, &mut change_rx).is_empty(),
+        "a change to another attribute does not re-run the query"
+    );
+
+    insert_seeded(&mut client, new_entity_id(2), watched, 2);
+    let updates = drain_updates(&sibling, &mut change_rx);
+    assert_eq!(updates.len(), 1);
+    assert_eq!(updates[0].subscription_id, 1);
+    assert!(updates[0].commit_hlc.is_some());
+    assert_eq!(result_strings(&updates[0]), vec!["v2".to_string()]);
+
+    insert_seeded(&mut client, new_entity_id(3), watched, 3);
+    let updates = drain_updates(&sibling, &mut change_rx);
+    assert_eq!(updates.len(), 1);
+    assert_eq!(
+        result_strings(&updates[0]),
+        vec!["v2".to_string(), "v3".to_string()]
+    );
+}
+
+#[test]
+fn test_live_query_stops_after_unsubscribe() {
+    let mut client = TestClient::new();
+    let mut sibling = client.create_sibling();
+    let mut change_rx = sibling.subscribe_to_changes();
+    let attribute_id = new_attribute_id(4);
+
+    subscribe_live(&mut sibling, 1, Some(values_query(attribute_id)));
+    let response = sibling.handle_message(proto::ClientMessage {
+        request_id: Some(2),
+        payload: Some(proto::client_message::Payload::Unsubscribe(
+            proto::UnsubscribeRequest { subscription_id: 1 },
+        )),
+    });
+    assert!(is_ok(&response));
+
+    insert_seeded(&mut client, new_entity_id(4), attribute_id, 1);
+    assert!(drain_updates(&sibling, &mut change_rx).is_empty());
+}
+
+#[test]
+fn test_live_query_rejects_invalid_requests() {
+    let client = TestClient::new();
+    let mut sibling = client.create_sibling();
+
+    let missing = subscribe_live(&mut sibling, 1, None);
+    let mut query = values_query(new_attribute_id(5));
+    query.r#where[0].entity = None;
+    let invalid = subscribe_live(&mut sibling, 2, Some(query));
+
+    for message in missing.iter().chain(&invalid) {
+        let Some(proto::server_message::Payload::Response(response)) = &message.payload else {
+            panic!("expected a response, got {:?}", message.payload);
+        };
+        assert!(!is_ok(response));
+    }
+    assert_eq!(sibling.client.subscriptions().count(), 0);
+}
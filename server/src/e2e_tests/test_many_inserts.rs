@@ -24,7 +24,10 @@ fn test_many_sequential_inserts() {
                             value: Some(proto::triple_value::Value::Number(f64::from(i))),
                         }),
                         hlc: Some(new_hlc(u64::from(i) + 1)),
+                        attribute_name: None,
                     }],
+                    mode: proto::WriteMode::Upsert.into(),
+                    expected_versions: vec![],
                 },
             )),
         });
@@ -58,6 +61,13 @@ fn test_many_sequential_inserts() {
             }],
             optional: vec![],
             where_not: vec![],
+            arrow_ipc: false,
+            limit: None,
+            offset: None,
+            order_by: vec![],
+            aggregates: vec![],
+            group_by: vec![],
+            distinct: false,
         })),
     });
     assert!(is_ok(&query_response));
@@ -0,0 +1,133 @@
+//! Test that messages over the server's size limit are rejected before they
+//! are decoded.
+
+use futures::{SinkExt, StreamExt};
+use prost::Message as ProstMessage;
+use tokio_tungstenite::tungstenite::Message;
+use tokio_tungstenite::tungstenite::protocol::frame::coding::CloseCode;
+
+use crate::e2e_tests::helpers::start_server_with_max_message_size;
+use crate::proto;
+use crate::types::error_detail::error_reason;
+
+/// Message size limit of the test server.
+const MAX_MESSAGE_SIZE: usize = 1024;
+
+type Socket =
+    tokio_tungstenite::WebSocketStream<tokio_tungstenite::MaybeTlsStream<tokio::net::TcpStream>>;
+
+/// Connect a raw WebSocket to a server with a `MAX_MESSAGE_SIZE` limit.
+async fn connect() -> (tempfile::TempDir, Socket) {
+    let (dir, url) = start_server_with_max_message_size(MAX_MESSAGE_SIZE).await;
+    let (socket, _) = tokio_tungstenite::connect_async(url.as_str())
+        .await
+        .expect("connect");
+    (dir, socket)
+}
+
+/// Read the next frame, which must be a binary `ServerResponse`.
+async fn next_response(socket: &mut Socket) -> proto::ServerResponse {
+    let Some(Ok(Message::Binary(data))) = socket.next().await else {
+        panic!("expected a binary frame");
+    };
+    match proto::ServerMessage::decode(data.as_ref())
+        .expect("decode")
+        .payload
+    {
+        Some(proto::server_message::Payload::Response(response)) => response,
+        other => panic!("expected a response, got {other:?}"),
+    }
+}
+
+/// The status code of a response.
+fn code(response: &proto::ServerResponse) -> i32 {
+    response.status.as_ref().map_or(-1, |status| status.code)
+}
+
+#[tokio::test]
+async fn test_frame_over_limit_is_rejected_without_decoding() {
+    let (_dir, mut socket) = connect().await;
+
+    // Not a valid ClientMessage: decoding it would fail with InvalidArgument
+    socket
+        .send(Message::Binary(vec![0xFF; MAX_MESSAGE_SIZE + 1].into()))
+        .await
+        .expect("send");
+
+    let response = next_response(&mut socket).await;
+    assert_eq!(
+        code(&response),
+        proto::google::rpc::Code::ResourceExhausted as i32
+    );
+    assert!(
+        response
+            .status
+            .as_ref()
+            .is_some_and(|status| status.message.contains("1024 bytes")),
+        "{response:?}"
+    );
+    assert_eq!(
+        response.status.as_ref().and_then(error_reason),
+        Some(proto::error_detail::Reason::MessageTooLarge(
+            proto::MessageTooLarge {
+                limit: MAX_MESSAGE_SIZE as u64,
+            }
+        ))
+    );
+
+    // Then the server closes the connection as "message too big"
+    match socket.next().await {
+        Some(Ok(Message::Close(Some(frame)))) => assert_eq!(frame.code, CloseCode::Size),
+        other => panic!("expected a close frame, got {other:?}"),
+    }
+}
+
+#[tokio::test]
+async fn test_text_frame_over_limit_is_rejected() {
+    let (_dir, mut socket) = connect().await;
+
+    socket
+        .send(Message::Text("x".repeat(MAX_MESSAGE_SIZE + 1).into()))
+        .await
+        .expect("send");
+
+    let response = next_response(&mut socket).await;
+    assert_eq!(
+        code(&response),
+        proto::google::rpc::Code::ResourceExhausted as i32
+    );
+}
+
+#[tokio::test]
+async fn test_frame_at_limit_is_decoded() {
+    let (_dir, mut socket) = connect().await;
+
+    // At the limit the frame is accepted and decoded, failing as garbage
+    socket
+        .send(Message::Binary(vec![0xFF; MAX_MESSAGE_SIZE].into()))
+        .await
+        .expect("send");
+    let response = next_response(&mut socket).await;
+    assert_eq!(
+        code(&response),
+        proto::google::rpc::Code::InvalidArgument as i32
+    );
+
+    // The connection stays open for well-formed requests
+    let connect = proto::ClientMessage {
+        request_id: Some(7),
+        payload: Some(proto::client_message::Payload::Connect(
+            proto::ConnectRequest {
+                app_api_key: "size-limit-app".to_string(),
+                auth_token: None,
+            },
+        )),
+    };
+    socket
+        .send(Message::Binary(connect.encode_to_vec().into()))
+        .await
+        .expect("send");
+    let response = next_response(&mut socket).await;
+    assert_eq!(response.request_id, Some(7));
+    assert_eq!(code(&response), proto::google::rpc::Code::Ok as i32);
+}
@@ -18,7 +18,10 @@ fn test_missing_entity_id() {
                         value: Some(proto::triple_value::Value::String("test".to_string())),
                     }),
                     hlc: Some(new_hlc(1)),
+                    attribute_name: None,
                 }],
+                mode: proto::WriteMode::Upsert.into(),
+                expected_versions: vec![],
             },
         )),
     };
@@ -45,7 +48,10 @@ fn test_missing_attribute_id() {
                         value: Some(proto::triple_value::Value::String("test".to_string())),
                     }),
                     hlc: Some(new_hlc(1)),
+                    attribute_name: None,
                 }],
+                mode: proto::WriteMode::Upsert.into(),
+                expected_versions: vec![],
             },
         )),
     };
@@ -70,7 +76,10 @@ fn test_missing_value() {
                     attribute_id: Some(vec![0u8; 16]),
                     value: None,
                     hlc: Some(new_hlc(1)),
+                    attribute_name: None,
                 }],
+                mode: proto::WriteMode::Upsert.into(),
+                expected_versions: vec![],
             },
         )),
     };
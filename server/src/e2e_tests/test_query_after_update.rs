@@ -0,0 +1,168 @@
+//! Test that OPTIONAL and WHERE-NOT queries see later updates.
+//!
+//! Queries run over a fresh snapshot per request, so each one must reflect
+//! every write committed before it.
+
+use crate::e2e_tests::helpers::{
+    TestClient, get_number_at, get_string_at, is_ok, is_undefined_at, new_attribute_id,
+    new_entity_id, new_hlc, string_triple,
+};
+use crate::proto;
+
+/// Build a triple with a number value.
+fn number_triple(
+    entity_id: [u8; 16],
+    attribute_id: [u8; 16],
+    value: f64,
+    hlc: u64,
+) -> proto::Triple {
+    proto::Triple {
+        entity_id: Some(entity_id.to_vec()),
+        attribute_id: Some(attribute_id.to_vec()),
+        value: Some(proto::TripleValue {
+            value: Some(proto::triple_value::Value::Number(value)),
+        }),
+        hlc: Some(new_hlc(hlc)),
+        attribute_name: None,
+    }
+}
+
+/// Send a triple update request and assert it succeeded.
+fn write(client: &mut TestClient, request_id: u32, triples: Vec<proto::Triple>) {
+    let response = client.handle_message(proto::ClientMessage {
+        request_id: Some(request_id),
+        payload: Some(proto::client_message::Payload::TripleUpdateRequest(
+            proto::TripleUpdateRequest {
+                triples,
+                mode: proto::WriteMode::Upsert.into(),
+                expected_versions: vec![],
+            },
+        )),
+    });
+    assert!(is_ok(&response));
+}
+
+/// Build a pattern `[?id <attribute_id> ?value_label]`.
+fn variable_pattern(attribute_id: [u8; 16], value_label: &str) -> proto::QueryPattern {
+    proto::QueryPattern {
+        entity: Some(proto::query_pattern::Entity::EntityVariable(
+            proto::QueryPatternVariable {
+                label: Some("id".to_string()),
+            },
+        )),
+        attribute: Some(proto::query_pattern::Attribute::AttributeId(
+            attribute_id.to_vec(),
+        )),
+        value_group: Some(proto::query_pattern::ValueGroup::ValueVariable(
+            proto::QueryPatternVariable {
+                label: Some(value_label.to_string()),
+            },
+        )),
+    }
+}
+
+/// Find names with an optional age, excluding entities with a `banned` value.
+fn query(
+    client: &mut TestClient,
+    request_id: u32,
+    name_attr: [u8; 16],
+    age_attr: [u8; 16],
+    banned_attr: [u8; 16],
+) -> proto::ServerResponse {
+    let response = client.handle_message(proto::ClientMessage {
+        request_id: Some(request_id),
+        payload: Some(proto::client_message::Payload::Query(proto::QueryRequest {
+            find: vec![
+                proto::QueryPatternVariable {
+                    label: Some("name".to_string()),
+                },
+                proto::QueryPatternVariable {
+                    label: Some("age".to_string()),
+                },
+            ],
+            r#where: vec![variable_pattern(name_attr, "name")],
+            optional: vec![variable_pattern(age_attr, "age")],
+            where_not: vec![variable_pattern(banned_attr, "banned")],
+            arrow_ipc: false,
+            limit: None,
+            offset: None,
+            order_by: vec![],
+            aggregates: vec![],
+            group_by: vec![],
+            distinct: false,
+        })),
+    });
+    assert!(is_ok(&response));
+    response
+}
+
+/// Test that an OPTIONAL value appears once written and tracks later updates.
+#[test]
+fn test_query_optional_sees_later_writes() {
+    let mut client = TestClient::new();
+
+    let entity = new_entity_id(1);
+    let name_attr = new_attribute_id(10);
+    let age_attr = new_attribute_id(11);
+    let banned_attr = new_attribute_id(12);
+
+    write(
+        &mut client,
+        1,
+        vec![string_triple(entity, name_attr, "Alice", 1)],
+    );
+    let response = query(&mut client, 2, name_attr, age_attr, banned_attr);
+    assert_eq!(response.rows.len(), 1);
+    assert!(is_undefined_at(&response, 0, 1));
+
+    write(
+        &mut client,
+        3,
+        vec![number_triple(entity, age_attr, 30.0, 2)],
+    );
+    let response = query(&mut client, 4, name_attr, age_attr, banned_attr);
+    assert_eq!(response.rows.len(), 1);
+    assert_eq!(get_number_at(&response, 0, 1), Some(30.0));
+
+    write(
+        &mut client,
+        5,
+        vec![number_triple(entity, age_attr, 31.0, 3)],
+    );
+    let response = query(&mut client, 6, name_attr, age_attr, banned_attr);
+    assert_eq!(response.rows.len(), 1);
+    assert_eq!(get_number_at(&response, 0, 1), Some(31.0));
+}
+
+/// Test that a WHERE-NOT exclusion takes effect as soon as the excluded
+/// attribute is written.
+#[test]
+fn test_query_where_not_sees_later_writes() {
+    let mut client = TestClient::new();
+
+    let alice = new_entity_id(1);
+    let bob = new_entity_id(2);
+    let name_attr = new_attribute_id(10);
+    let age_attr = new_attribute_id(11);
+    let banned_attr = new_attribute_id(12);
+
+    write(
+        &mut client,
+        1,
+        vec![
+            string_triple(alice, name_attr, "Alice", 1),
+            string_triple(bob, name_attr, "Bob", 2),
+        ],
+    );
+    let response = query(&mut client, 2, name_attr, age_attr, banned_attr);
+    assert_eq!(response.rows.len(), 2);
+
+    write(
+        &mut client,
+        3,
+        vec![string_triple(bob, banned_attr, "spam", 3)],
+    );
+    let response = query(&mut client, 4, name_attr, age_attr, banned_attr);
+    assert_eq!(response.rows.len(), 1);
+    assert_eq!(get_string_at(&response, 0, 0), Some("Alice"));
+}
@@ -0,0 +1,194 @@
+//! Test aggregate queries.
+
+use crate::e2e_tests::helpers::{
+    TestClient, get_number_at, get_value_at, insert_value, is_ok, is_undefined_at,
+    new_attribute_id, new_entity_id, pattern, status_code, variable,
+};
+use crate::proto;
+
+/// Aggregate `label` with each of `functions` over the rows matching
+/// `[?e attribute ?label]`.
+fn query_aggregates(
+    client: &mut TestClient,
+    attribute_id: [u8; 16],
+    label: &str,
+    functions: &[proto::AggregateFunction],
+) -> proto::ServerResponse {
+    client.handle_message(proto::ClientMessage {
+        request_id: Some(2),
+        payload: Some(proto::client_message::Payload::Query(proto::QueryRequest {
+            find: vec![],
+            r#where: vec![pattern(attribute_id, label)],
+            optional: vec![],
+            where_not: vec![],
+            arrow_ipc: false,
+            limit: None,
+            offset: None,
+            order_by: vec![],
+            aggregates: functions
+                .iter()
+                .map(|function| proto::QueryAggregate {
+                    function: (*function).into(),
+                    variable: Some(variable(label)),
+                })
+                .collect(),
+            group_by: vec![],
+            distinct: false,
+        })),
+    })
+}
+
+const ALL: [proto::AggregateFunction; 5] = [
+    proto::AggregateFunction::Count,
+    proto::AggregateFunction::Sum,
+    proto::AggregateFunction::Min,
+    proto::AggregateFunction::Max,
+    proto::AggregateFunction::Avg,
+];
+
+#[test]
+fn test_query_aggregates() {
+    let mut client = TestClient::new();
+    let score_attr = new_attribute_id(1);
+    for (n, score) in (1..).zip([4.0, 10.0, 1.0]) {
+        insert_value(
+            &mut client,
+            new_entity_id(n),
+            score_attr,
+            proto::triple_value::Value::Number(score),
+        );
+    }
+
+    let response = query_aggregates(&mut client, score_attr, "score", &ALL);
+
+    assert!(is_ok(&response));
+    assert_eq!(
+        response.columns,
+        vec![
+            "count(score)",
+            "sum(score)",
+            "min(score)",
+            "max(score)",
+            "avg(score)"
+        ]
+    );
+    assert_eq!(response.rows.len(), 1);
+    let values: Vec<Option<f64>> = (0..ALL.len())
+        .map(|col| get_number_at(&response, 0, col))
+        .collect();
+    assert_eq!(
+        values,
+        vec![Some(3.0), Some(15.0), Some(1.0), Some(10.0), Some(5.0)]
+    );
+}
+
+#[test]
+fn test_query_aggregates_over_no_rows() {
+    let mut client = TestClient::new();
+
+    let response = query_aggregates(&mut client, new_attribute_id(1), "score", &ALL);
+
+    assert!(is_ok(&response));
+    assert_eq!(response.rows.len(), 1);
+    assert_eq!(get_number_at(&response, 0, 0), Some(0.0));
+    assert_eq!(get_number_at(&response, 0, 1), Some(0.0));
+    for col in 2..ALL.len() {
+        assert!(is_undefined_at(&response, 0, col), "column {col}");
+    }
+}
+
+#[test]
+fn test_query_sum_of_strings_is_rejected() {
+    let mut client = TestClient::new();
+    let name_attr = new_attribute_id(1);
+    insert_value(
+        &mut client,
+        new_entity_id(1),
+        name_attr,
+        proto::triple_value::Value::String("alice".to_string()),
+    );
+
+    let response = query_aggregates(
+        &mut client,
+        name_attr,
+        "name",
+        &[proto::AggregateFunction::Sum],
+    );
+    assert_eq!(
+        status_code(&response),
+        proto::google::rpc::Code::InvalidArgument as i32
+    );
+
+    // Any value can be counted
+    let response = query_aggregates(
+        &mut client,
+        name_attr,
+        "name",
+        &[proto::AggregateFunction::Count],
+    );
+    assert!(is_ok(&response));
+    assert_eq!(get_number_at(&response, 0, 0), Some(1.0));
+}
+
+#[test]
+fn test_query_group_by_boolean() {
+    let mut client = TestClient::new();
+    let active_attr = new_attribute_id(1);
+    let banned_attr = new_attribute_id(2);
+    for (n, active) in (1..).zip([true, false, true, true, false, true]) {
+        insert_value(
+            &mut client,
+            new_entity_id(n),
+            active_attr,
+            proto::triple_value::Value::Boolean(active),
+        );
+    }
+    // Excluded by the WHERE-NOT pattern, so not counted in its group
+    insert_value(
+        &mut client,
+        new_entity_id(6),
+        banned_attr,
+        proto::triple_value::Value::Boolean(true),
+    );
+
+    let response = client.handle_message(proto::ClientMessage {
+        request_id: Some(2),
+        payload: Some(proto::client_message::Payload::Query(proto::QueryRequest {
+            find: vec![],
+            r#where: vec![pattern(active_attr, "active")],
+            optional: vec![],
+            where_not: vec![pattern(banned_attr, "banned")],
+            arrow_ipc: false,
+            limit: None,
+            offset: None,
+            order_by: vec![],
+            aggregates: vec![proto::QueryAggregate {
+                function: proto::AggregateFunction::Count.into(),
+                variable: Some(variable("e")),
+            }],
+            group_by: vec![variable("active")],
+            distinct: false,
+        })),
+    });
+
+    assert!(is_ok(&response));
+    assert_eq!(response.columns, vec!["active", "count(e)"]);
+    let groups: Vec<(Option<&proto::TripleValue>, Option<f64>)> = (0..response.rows.len())
+        .map(|row| {
+            (
+                get_value_at(&response, row, 0),
+                get_number_at(&response, row, 1),
+            )
+        })
+        .collect();
+    let boolean = |b| proto::TripleValue {
+        value: Some(proto::triple_value::Value::Boolean(b)),
+    };
+    assert_eq!(
+        groups,
+        vec![
+            (Some(&boolean(true)), Some(3.0)),
+            (Some(&boolean(false)), Some(2.0)),
+        ]
+    );
+}
@@ -0,0 +1,139 @@
+//! Test query results encoded as Arrow IPC streams.
+
+use crate::e2e_tests::helpers::{
+    TestClient, get_string_at, is_ok, new_attribute_id, new_entity_id, new_hlc,
+};
+use crate::proto;
+
+/// Insert one triple per (entity, value) pair under `attribute_id`.
+fn insert_values(
+    client: &mut TestClient,
+    attribute_id: [u8; 16],
+    values: Vec<([u8; 16], proto::triple_value::Value)>,
+) {
+    let triples = values
+        .into_iter()
+        .enumerate()
+        .map(|(index, (entity_id, value))| proto::Triple {
+            entity_id: Some(entity_id.to_vec()),
+            attribute_id: Some(attribute_id.to_vec()),
+            value: Some(proto::TripleValue { value: Some(value) }),
+            hlc: Some(new_hlc(index as u64 + 1)),
+            attribute_name: None,
+        })
+        .collect();
+    let response = client.handle_message(proto::ClientMessage {
+        request_id: Some(1),
+        payload: Some(proto::client_message::Payload::TripleUpdateRequest(
+            proto::TripleUpdateRequest {
+                triples,
+                mode: proto::WriteMode::Upsert.into(),
+                expected_versions: vec![],
+            },
+        )),
+    });
+    assert!(is_ok(&response));
+}
+
+/// Query `[:find ?e ?value :where [?e attribute_id ?value]]`.
+fn query_values(
+    client: &mut TestClient,
+    attribute_id: [u8; 16],
+    arrow_ipc: bool,
+) -> proto::ServerResponse {
+    let variable = |label: &str| proto::QueryPatternVariable {
+        label: Some(label.to_string()),
+    };
+    client.handle_message(proto::ClientMessage {
+        request_id: Some(2),
+        payload: Some(proto::client_message::Payload::Query(proto::QueryRequest {
+            find: vec![variable("e"), variable("value")],
+            r#where: vec![proto::QueryPattern {
+                entity: Some(proto::query_pattern::Entity::EntityVariable(variable("e"))),
+                attribute: Some(proto::query_pattern::Attribute::AttributeId(
+                    attribute_id.to_vec(),
+                )),
+                value_group: Some(proto::query_pattern::ValueGroup::ValueVariable(variable(
+                    "value",
+                ))),
+            }],
+            optional: vec![],
+            where_not: vec![],
+            arrow_ipc,
+            limit: None,
+            offset: None,
+            order_by: vec![],
+            aggregates: vec![],
+            group_by: vec![],
+            distinct: false,
+        })),
+    })
+}
+
+#[test]
+fn test_query_returns_arrow_ipc_stream() {
+    let mut client = TestClient::new();
+    let age_attr = new_attribute_id(1);
+    insert_values(
+        &mut client,
+        age_attr,
+        vec![
+            (new_entity_id(1), proto::triple_value::Value::Number(30.0)),
+            (new_entity_id(2), proto::triple_value::Value::Number(41.5)),
+        ],
+    );
+
+    let response = query_values(&mut client, age_attr, true);
+    assert!(is_ok(&response));
+    assert_eq!(response.columns, vec!["e", "value"]);
+    assert!(response.rows.is_empty());
+    let stream = response.arrow_ipc.expect("Arrow stream");
+    assert_eq!(&stream[..4], [0xFF; 4]);
+    assert_eq!(
+        &stream[stream.len() - 8..],
+        [0xFF, 0xFF, 0xFF, 0xFF, 0, 0, 0, 0]
+    );
+    // The record batch body holds both numbers
+    let tail = &stream[..stream.len() - 8];
+    assert!(
+        tail.windows(8)
+            .any(|window| window == 30.0f64.to_le_bytes())
+    );
+    assert!(
+        tail.windows(8)
+            .any(|window| window == 41.5f64.to_le_bytes())
+    );
+
+    // Without the flag the same query returns rows
+    let response = query_values(&mut client, age_attr, false);
+    assert!(is_ok(&response));
+    assert_eq!(response.rows.len(), 2);
+    assert!(response.arrow_ipc.is_none());
+}
+
+#[test]
+fn test_query_arrow_ipc_falls_back_to_rows_for_mixed_types() {
+    let mut client = TestClient::new();
+    let label_attr = new_attribute_id(2);
+    insert_values(
+        &mut client,
+        label_attr,
+        vec![
+            (
+                new_entity_id(1),
+                proto::triple_value::Value::String("one".to_string()),
+            ),
+            (new_entity_id(2), proto::triple_value::Value::Number(2.0)),
+        ],
+    );
+
+    let response = query_values(&mut client, label_attr, true);
+    assert!(is_ok(&response));
+    assert!(response.arrow_ipc.is_none());
+    assert_eq!(response.columns, vec!["e", "value"]);
+    assert_eq!(response.rows.len(), 2);
+    let strings: Vec<_> = (0..2)
+        .filter_map(|row| get_string_at(&response, row, 1))
+        .collect();
+    assert_eq!(strings, vec!["one"]);
+}
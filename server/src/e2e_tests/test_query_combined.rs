@@ -42,6 +42,7 @@ fn test_query_combined_features() {
                             value: Some(proto::triple_value::Value::String("Alice".to_string())),
                         }),
                         hlc: Some(new_hlc(1)),
+                        attribute_name: None,
                     },
                     proto::Triple {
                         entity_id: Some(entity1.to_vec()),
@@ -52,6 +53,7 @@ fn test_query_combined_features() {
                             )),
                         }),
                         hlc: Some(new_hlc(2)),
+                        attribute_name: None,
                     },
                     // User 2: Bob without dept
                     proto::Triple {
@@ -61,6 +63,7 @@ fn test_query_combined_features() {
                             value: Some(proto::triple_value::Value::String("Bob".to_string())),
                         }),
                         hlc: Some(new_hlc(3)),
+                        attribute_name: None,
                     },
                     // User 3: Charlie without dept
                     proto::Triple {
@@ -70,6 +73,7 @@ fn test_query_combined_features() {
                             value: Some(proto::triple_value::Value::String("Charlie".to_string())),
                         }),
                         hlc: Some(new_hlc(4)),
+                        attribute_name: None,
                     },
                     // User 4: Dave with dept and inactive
                     proto::Triple {
@@ -79,6 +83,7 @@ fn test_query_combined_features() {
                             value: Some(proto::triple_value::Value::String("Dave".to_string())),
                         }),
                         hlc: Some(new_hlc(5)),
+                        attribute_name: None,
                     },
                     proto::Triple {
                         entity_id: Some(entity4.to_vec()),
@@ -87,6 +92,7 @@ fn test_query_combined_features() {
                             value: Some(proto::triple_value::Value::String("HR".to_string())),
                         }),
                         hlc: Some(new_hlc(6)),
+                        attribute_name: None,
                     },
                     proto::Triple {
                         entity_id: Some(entity4.to_vec()),
@@ -95,8 +101,11 @@ fn test_query_combined_features() {
                             value: Some(proto::triple_value::Value::Boolean(true)),
                         }),
                         hlc: Some(new_hlc(7)),
+                        attribute_name: None,
                     },
                 ],
+                mode: proto::WriteMode::Upsert.into(),
+                expected_versions: vec![],
             },
         )),
     });
@@ -159,6 +168,13 @@ fn test_query_combined_features() {
                     },
                 )),
             }],
+            arrow_ipc: false,
+            limit: None,
+            offset: None,
+            order_by: vec![],
+            aggregates: vec![],
+            group_by: vec![],
+            distinct: false,
         })),
     });
 
@@ -220,6 +236,7 @@ fn test_query_combined_multiple_where_not() {
                             value: Some(proto::triple_value::Value::String("Alice".to_string())),
                         }),
                         hlc: Some(new_hlc(1)),
+                        attribute_name: None,
                     },
                     proto::Triple {
                         entity_id: Some(entity1.to_vec()),
@@ -228,6 +245,7 @@ fn test_query_combined_multiple_where_not() {
                             value: Some(proto::triple_value::Value::Boolean(true)),
                         }),
                         hlc: Some(new_hlc(2)),
+                        attribute_name: None,
                     },
                     proto::Triple {
                         entity_id: Some(entity2.to_vec()),
@@ -236,6 +254,7 @@ fn test_query_combined_multiple_where_not() {
                             value: Some(proto::triple_value::Value::String("Bob".to_string())),
                         }),
                         hlc: Some(new_hlc(3)),
+                        attribute_name: None,
                     },
                     proto::Triple {
                         entity_id: Some(entity2.to_vec()),
@@ -244,6 +263,7 @@ fn test_query_combined_multiple_where_not() {
                             value: Some(proto::triple_value::Value::Boolean(true)),
                         }),
                         hlc: Some(new_hlc(4)),
+                        attribute_name: None,
                     },
                     proto::Triple {
                         entity_id: Some(entity3.to_vec()),
@@ -252,8 +272,11 @@ fn test_query_combined_multiple_where_not() {
                             value: Some(proto::triple_value::Value::String("Charlie".to_string())),
                         }),
                         hlc: Some(new_hlc(5)),
+                        attribute_name: None,
                     },
                 ],
+                mode: proto::WriteMode::Upsert.into(),
+                expected_versions: vec![],
             },
         )),
     });
@@ -314,6 +337,13 @@ fn test_query_combined_multiple_where_not() {
                     )),
                 },
             ],
+            arrow_ipc: false,
+            limit: None,
+            offset: None,
+            order_by: vec![],
+            aggregates: vec![],
+            group_by: vec![],
+            distinct: false,
         })),
     });
 
@@ -352,6 +382,7 @@ fn test_query_combined_multiple_where() {
                             value: Some(proto::triple_value::Value::String("Alice".to_string())),
                         }),
                         hlc: Some(new_hlc(1)),
+                        attribute_name: None,
                     },
                     proto::Triple {
                         entity_id: Some(entity1.to_vec()),
@@ -360,6 +391,7 @@ fn test_query_combined_multiple_where() {
                             value: Some(proto::triple_value::Value::String("admin".to_string())),
                         }),
                         hlc: Some(new_hlc(2)),
+                        attribute_name: None,
                     },
                     proto::Triple {
                         entity_id: Some(entity1.to_vec()),
@@ -370,6 +402,7 @@ fn test_query_combined_multiple_where() {
                             )),
                         }),
                         hlc: Some(new_hlc(3)),
+                        attribute_name: None,
                     },
                     proto::Triple {
                         entity_id: Some(entity2.to_vec()),
@@ -378,6 +411,7 @@ fn test_query_combined_multiple_where() {
                             value: Some(proto::triple_value::Value::String("Bob".to_string())),
                         }),
                         hlc: Some(new_hlc(4)),
+                        attribute_name: None,
                     },
                     proto::Triple {
                         entity_id: Some(entity2.to_vec()),
@@ -386,6 +420,7 @@ fn test_query_combined_multiple_where() {
                             value: Some(proto::triple_value::Value::String("user".to_string())),
                         }),
                         hlc: Some(new_hlc(5)),
+                        attribute_name: None,
                     },
                     proto::Triple {
                         entity_id: Some(entity3.to_vec()),
@@ -394,6 +429,7 @@ fn test_query_combined_multiple_where() {
                             value: Some(proto::triple_value::Value::String("Charlie".to_string())),
                         }),
                         hlc: Some(new_hlc(6)),
+                        attribute_name: None,
                     },
                     proto::Triple {
                         entity_id: Some(entity3.to_vec()),
@@ -402,6 +438,7 @@ fn test_query_combined_multiple_where() {
                             value: Some(proto::triple_value::Value::String("admin".to_string())),
                         }),
                         hlc: Some(new_hlc(7)),
+                        attribute_name: None,
                     },
                     proto::Triple {
                         entity_id: Some(entity3.to_vec()),
@@ -410,8 +447,11 @@ fn test_query_combined_multiple_where() {
                             value: Some(proto::triple_value::Value::Boolean(true)),
                         }),
                         hlc: Some(new_hlc(8)),
+                        attribute_name: None,
                     },
                 ],
+                mode: proto::WriteMode::Upsert.into(),
+                expected_versions: vec![],
             },
         )),
     });
@@ -491,6 +531,13 @@ fn test_query_combined_multiple_where() {
                     },
                 )),
             }],
+            arrow_ipc: false,
+            limit: None,
+            offset: None,
+            order_by: vec![],
+            aggregates: vec![],
+            group_by: vec![],
+            distinct: false,
         })),
     });
 
@@ -0,0 +1,78 @@
+//! Test dropping duplicate query rows.
+
+use crate::e2e_tests::helpers::{
+    TestClient, get_string_at, insert, is_ok, new_attribute_id, new_entity_id, variable,
+};
+use crate::proto;
+
+/// Query the cities of every entity with one, and return them.
+fn query_cities(
+    client: &mut TestClient,
+    city_attr: [u8; 16],
+    distinct: bool,
+    order_by: Vec<proto::QueryOrderBy>,
+    limit: Option<u32>,
+) -> Vec<String> {
+    let response = client.handle_message(proto::ClientMessage {
+        request_id: Some(2),
+        payload: Some(proto::client_message::Payload::Query(proto::QueryRequest {
+            find: vec![variable("city")],
+            r#where: vec![proto::QueryPattern {
+                entity: Some(proto::query_pattern::Entity::EntityVariable(variable("e"))),
+                attribute: Some(proto::query_pattern::Attribute::AttributeId(
+                    city_attr.to_vec(),
+                )),
+                value_group: Some(proto::query_pattern::ValueGroup::ValueVariable(variable(
+                    "city",
+                ))),
+            }],
+            optional: vec![],
+            where_not: vec![],
+            arrow_ipc: false,
+            limit,
+            offset: None,
+            order_by,
+            aggregates: vec![],
+            group_by: vec![],
+            distinct,
+        })),
+    });
+    assert!(is_ok(&response));
+    (0..response.rows.len())
+        .map(|row| get_string_at(&response, row, 0).expect("city").to_owned())
+        .collect()
+}
+
+#[test]
+fn test_query_distinct_collapses_duplicates() {
+    let mut client = TestClient::new();
+    let city_attr = new_attribute_id(1);
+    for (n, city) in (1..).zip(["paris", "rome", "paris", "oslo", "rome"]) {
+        insert(&mut client, new_entity_id(n), city_attr, city);
+    }
+
+    assert_eq!(
+        query_cities(&mut client, city_attr, false, vec![], None).len(),
+        5
+    );
+    // Each city once, where it first appears
+    assert_eq!(
+        query_cities(&mut client, city_attr, true, vec![], None),
+        vec!["paris", "rome", "oslo"]
+    );
+    // The limit counts distinct rows
+    assert_eq!(
+        query_cities(&mut client, city_attr, true, vec![], Some(2)),
+        vec!["paris", "rome"]
+    );
+
+    let by_city = proto::QueryOrderBy {
+        variable: Some(variable("city")),
+        direction: proto::SortDirection::Descending.into(),
+        nulls: proto::NullsOrder::First.into(),
+    };
+    assert_eq!(
+        query_cities(&mut client, city_attr, true, vec![by_city], None),
+        vec!["rome", "paris", "oslo"]
+    );
+}
@@ -0,0 +1,122 @@
+//! Test querying a set of entity IDs in one pattern (an IN clause).
+
+use crate::e2e_tests::helpers::{
+    TestClient, get_string_at, insert, is_ok, new_attribute_id, new_entity_id, variable,
+};
+use crate::proto;
+use crate::types::query::id_to_string;
+
+/// Query `[?e in {entity_ids} attribute ?value]`, returning `?e` and `?value`.
+fn query_entity_set(
+    client: &mut TestClient,
+    entity_ids: &[[u8; 16]],
+    attribute: proto::query_pattern::Attribute,
+) -> proto::ServerResponse {
+    client.handle_message(proto::ClientMessage {
+        request_id: Some(2),
+        payload: Some(proto::client_message::Payload::Query(proto::QueryRequest {
+            find: vec![variable("e"), variable("value")],
+            r#where: vec![proto::QueryPattern {
+                entity: Some(proto::query_pattern::Entity::EntityIds(
+                    proto::EntityIdSet {
+                        entity_ids: entity_ids.iter().map(|id| id.to_vec()).collect(),
+                        variable: Some(variable("e")),
+                    },
+                )),
+                attribute: Some(attribute),
+                value_group: Some(proto::query_pattern::ValueGroup::ValueVariable(variable(
+                    "value",
+                ))),
+            }],
+            optional: vec![],
+            where_not: vec![],
+            arrow_ipc: false,
+            limit: None,
+            offset: None,
+            order_by: vec![],
+            aggregates: vec![],
+            group_by: vec![],
+            distinct: false,
+        })),
+    })
+}
+
+/// The entity ID in column 0 of a row, as the server renders IDs.
+fn entity_at(response: &proto::ServerResponse, row: usize) -> Option<&str> {
+    match &response.rows.get(row)?.values.first()?.value {
+        Some(proto::query_result_value::Value::Id(id)) => Some(id.as_str()),
+        _ => None,
+    }
+}
+
+#[test]
+fn test_query_entity_set_returns_only_present_entities() {
+    let mut client = TestClient::new();
+    let name_attr = new_attribute_id(1);
+    insert(&mut client, new_entity_id(1), name_attr, "one");
+    insert(&mut client, new_entity_id(2), name_attr, "two");
+    insert(&mut client, new_entity_id(3), name_attr, "three");
+
+    // Entities 40 and 50 were never written; entity 3 is listed twice
+    let response = query_entity_set(
+        &mut client,
+        &[
+            new_entity_id(3),
+            new_entity_id(40),
+            new_entity_id(1),
+            new_entity_id(50),
+            new_entity_id(3),
+        ],
+        proto::query_pattern::Attribute::AttributeId(name_attr.to_vec()),
+    );
+
+    assert!(is_ok(&response));
+    assert_eq!(response.columns, vec!["e", "value"]);
+    assert_eq!(response.rows.len(), 2);
+    // Rows come in entity order, one per present entity
+    assert_eq!(
+        entity_at(&response, 0),
+        Some(id_to_string(&new_entity_id(1)).as_str())
+    );
+    assert_eq!(get_string_at(&response, 0, 1), Some("one"));
+    assert_eq!(
+        entity_at(&response, 1),
+        Some(id_to_string(&new_entity_id(3)).as_str())
+    );
+    assert_eq!(get_string_at(&response, 1, 1), Some("three"));
+}
+
+#[test]
+fn test_query_entity_set_with_attribute_variable() {
+    let mut client = TestClient::new();
+    insert(&mut client, new_entity_id(1), new_attribute_id(1), "name");
+    insert(&mut client, new_entity_id(1), new_attribute_id(2), "email");
+    insert(&mut client, new_entity_id(2), new_attribute_id(1), "other");
+
+    // Every triple of each listed entity
+    let response = query_entity_set(
+        &mut client,
+        &[new_entity_id(1), new_entity_id(9)],
+        proto::query_pattern::Attribute::AttributeVariable(variable("a")),
+    );
+
+    assert!(is_ok(&response));
+    assert_eq!(response.rows.len(), 2);
+    assert_eq!(get_string_at(&response, 0, 1), Some("name"));
+    assert_eq!(get_string_at(&response, 1, 1), Some("email"));
+}
+
+#[test]
+fn test_query_empty_entity_set_matches_nothing() {
+    let mut client = TestClient::new();
+    insert(&mut client, new_entity_id(1), new_attribute_id(1), "one");
+
+    let response = query_entity_set(
+        &mut client,
+        &[],
+        proto::query_pattern::Attribute::AttributeId(new_attribute_id(1).to_vec()),
+    );
+
+    assert!(is_ok(&response));
+    assert!(response.rows.is_empty());
+}
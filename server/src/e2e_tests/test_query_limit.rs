@@ -0,0 +1,82 @@
+//! Test limiting and offsetting query results.
+
+use crate::e2e_tests::helpers::{
+    TestClient, get_strings_at, insert, is_ok, new_attribute_id, new_entity_id, pattern, variable,
+};
+use crate::proto;
+
+/// Query the names, skipping `offset` rows and returning at most `limit`,
+/// leaving out entities with a nickname.
+fn query_names(
+    client: &mut TestClient,
+    name_attr: [u8; 16],
+    nickname_attr: [u8; 16],
+    limit: Option<u32>,
+    offset: Option<u32>,
+) -> proto::ServerResponse {
+    client.handle_message(proto::ClientMessage {
+        request_id: Some(2),
+        payload: Some(proto::client_message::Payload::Query(proto::QueryRequest {
+            find: vec![variable("e"), variable("name")],
+            r#where: vec![pattern(name_attr, "name")],
+            optional: vec![],
+            where_not: vec![pattern(nickname_attr, "nickname")],
+            arrow_ipc: false,
+            limit,
+            offset,
+            order_by: vec![],
+            aggregates: vec![],
+            group_by: vec![],
+            distinct: false,
+        })),
+    })
+}
+
+#[test]
+fn test_query_limit_and_offset() {
+    let mut client = TestClient::new();
+    let name_attr = new_attribute_id(1);
+    let nickname_attr = new_attribute_id(2);
+    for (n, name) in (1..).zip(["a", "b", "c", "d", "e"]) {
+        insert(&mut client, new_entity_id(n), name_attr, name);
+    }
+    // Excluded by the WHERE-NOT pattern, whether or not the limit is reached
+    insert(&mut client, new_entity_id(1), nickname_attr, "ay");
+    insert(&mut client, new_entity_id(3), nickname_attr, "cee");
+
+    let response = query_names(&mut client, name_attr, nickname_attr, None, None);
+    assert!(is_ok(&response));
+    assert_eq!(get_strings_at(&response, 1), vec!["b", "d", "e"]);
+
+    let response = query_names(&mut client, name_attr, nickname_attr, Some(2), None);
+    assert!(is_ok(&response));
+    assert_eq!(response.columns, vec!["e", "name"]);
+    assert_eq!(get_strings_at(&response, 1), vec!["b", "d"]);
+
+    let response = query_names(&mut client, name_attr, nickname_attr, Some(1), Some(1));
+    assert!(is_ok(&response));
+    assert_eq!(get_strings_at(&response, 1), vec!["d"]);
+
+    let response = query_names(&mut client, name_attr, nickname_attr, None, Some(2));
+    assert!(is_ok(&response));
+    assert_eq!(get_strings_at(&response, 1), vec!["e"]);
+}
+
+#[test]
+fn test_query_limit_zero_keeps_columns() {
+    let mut client = TestClient::new();
+    let name_attr = new_attribute_id(1);
+    insert(&mut client, new_entity_id(1), name_attr, "a");
+
+    let response = query_names(
+        &mut client,
+        name_attr,
+        new_attribute_id(2),
+        Some(0),
+        Some(10),
+    );
+
+    assert!(is_ok(&response));
+    assert_eq!(response.columns, vec!["e", "name"]);
+    assert!(response.rows.is_empty());
+}
@@ -40,6 +40,7 @@ fn test_query_optional_some_missing() {
                             value: Some(proto::triple_value::Value::String("Alice".to_string())),
                         }),
                         hlc: Some(new_hlc(1)),
+                        attribute_name: None,
                     },
                     proto::Triple {
                         entity_id: Some(entity1.to_vec()),
@@ -48,6 +49,7 @@ fn test_query_optional_some_missing() {
                             value: Some(proto::triple_value::Value::Number(30.0)),
                         }),
                         hlc: Some(new_hlc(2)),
+                        attribute_name: None,
                     },
                     // Entity 2: Bob without age
                     proto::Triple {
@@ -57,6 +59,7 @@ fn test_query_optional_some_missing() {
                             value: Some(proto::triple_value::Value::String("Bob".to_string())),
                         }),
                         hlc: Some(new_hlc(3)),
+                        attribute_name: None,
                     },
                     // Entity 3: Charlie with age
                     proto::Triple {
@@ -66,6 +69,7 @@ fn test_query_optional_some_missing() {
                             value: Some(proto::triple_value::Value::String("Charlie".to_string())),
                         }),
                         hlc: Some(new_hlc(4)),
+                        attribute_name: None,
                     },
                     proto::Triple {
                         entity_id: Some(entity3.to_vec()),
@@ -74,8 +78,11 @@ fn test_query_optional_some_missing() {
                             value: Some(proto::triple_value::Value::Number(25.0)),
                         }),
                         hlc: Some(new_hlc(5)),
+                        attribute_name: None,
                     },
                 ],
+                mode: proto::WriteMode::Upsert.into(),
+                expected_versions: vec![],
             },
         )),
     });
@@ -124,6 +131,13 @@ fn test_query_optional_some_missing() {
                 )),
             }],
             where_not: vec![],
+            arrow_ipc: false,
+            limit: None,
+            offset: None,
+            order_by: vec![],
+            aggregates: vec![],
+            group_by: vec![],
+            distinct: false,
         })),
     });
 
@@ -214,6 +228,7 @@ fn test_query_optional_no_base_results() {
                             value: Some(proto::triple_value::Value::String("Alice".to_string())),
                         }),
                         hlc: Some(new_hlc(1)),
+                        attribute_name: None,
                     },
                     proto::Triple {
                         entity_id: Some(entity1.to_vec()),
@@ -222,8 +237,11 @@ fn test_query_optional_no_base_results() {
                             value: Some(proto::triple_value::Value::Number(30.0)),
                         }),
                         hlc: Some(new_hlc(2)),
+                        attribute_name: None,
                     },
                 ],
+                mode: proto::WriteMode::Upsert.into(),
+                expected_versions: vec![],
             },
         )),
     });
@@ -272,6 +290,13 @@ fn test_query_optional_no_base_results() {
                 )),
             }],
             where_not: vec![],
+            arrow_ipc: false,
+            limit: None,
+            offset: None,
+            order_by: vec![],
+            aggregates: vec![],
+            group_by: vec![],
+            distinct: false,
         })),
     });
 
@@ -306,6 +331,7 @@ fn test_query_multiple_optional_patterns() {
                             value: Some(proto::triple_value::Value::String("Alice".to_string())),
                         }),
                         hlc: Some(new_hlc(1)),
+                        attribute_name: None,
                     },
                     proto::Triple {
                         entity_id: Some(entity1.to_vec()),
@@ -314,6 +340,7 @@ fn test_query_multiple_optional_patterns() {
                             value: Some(proto::triple_value::Value::Number(30.0)),
                         }),
                         hlc: Some(new_hlc(2)),
+                        attribute_name: None,
                     },
                     proto::Triple {
                         entity_id: Some(entity1.to_vec()),
@@ -324,6 +351,7 @@ fn test_query_multiple_optional_patterns() {
                             )),
                         }),
                         hlc: Some(new_hlc(3)),
+                        attribute_name: None,
                     },
                     proto::Triple {
                         entity_id: Some(entity2.to_vec()),
@@ -332,6 +360,7 @@ fn test_query_multiple_optional_patterns() {
                             value: Some(proto::triple_value::Value::String("Bob".to_string())),
                         }),
                         hlc: Some(new_hlc(4)),
+                        attribute_name: None,
                     },
                     proto::Triple {
                         entity_id: Some(entity2.to_vec()),
@@ -340,8 +369,11 @@ fn test_query_multiple_optional_patterns() {
                             value: Some(proto::triple_value::Value::Number(25.0)),
                         }),
                         hlc: Some(new_hlc(5)),
+                        attribute_name: None,
                     },
                 ],
+                mode: proto::WriteMode::Upsert.into(),
+                expected_versions: vec![],
             },
         )),
     });
@@ -410,6 +442,13 @@ fn test_query_multiple_optional_patterns() {
                 },
             ],
             where_not: vec![],
+            arrow_ipc: false,
+            limit: None,
+            offset: None,
+            order_by: vec![],
+            aggregates: vec![],
+            group_by: vec![],
+            distinct: false,
         })),
     });
 
@@ -0,0 +1,180 @@
+//! Test ordering query results by the values bound to variables.
+
+use crate::e2e_tests::helpers::{
+    TestClient, get_strings_at, insert_value, is_ok, new_attribute_id, new_entity_id, pattern,
+    variable,
+};
+use crate::proto;
+
+/// A sort key on `label`.
+fn order(
+    label: &str,
+    direction: proto::SortDirection,
+    nulls: proto::NullsOrder,
+) -> proto::QueryOrderBy {
+    proto::QueryOrderBy {
+        variable: Some(variable(label)),
+        direction: direction.into(),
+        nulls: nulls.into(),
+    }
+}
+
+/// Give entities 1 to 6 a name and a score of mixed types, and entity 7 a
+/// name only. Returns the name and score attributes.
+fn insert_scores(client: &mut TestClient) -> ([u8; 16], [u8; 16]) {
+    use proto::triple_value::Value;
+
+    let name_attr = new_attribute_id(1);
+    let score_attr = new_attribute_id(2);
+    let scores = [
+        Value::Number(10.0),
+        Value::String("b".to_string()),
+        Value::Number(2.0),
+        Value::Boolean(true),
+        Value::String("a".to_string()),
+        // Ties with entity 3
+        Value::Number(2.0),
+    ];
+    for (n, score) in (1..).zip(scores) {
+        insert_value(
+            client,
+            new_entity_id(n),
+            name_attr,
+            Value::String(format!("n{n}")),
+        );
+        insert_value(client, new_entity_id(n), score_attr, score);
+    }
+    insert_value(
+        client,
+        new_entity_id(7),
+        name_attr,
+        Value::String("n7".to_string()),
+    );
+    (name_attr, score_attr)
+}
+
+/// Query names and scores, the score optional if `optional_score` is set.
+fn query_scores(
+    client: &mut TestClient,
+    name_attr: [u8; 16],
+    score_attr: [u8; 16],
+    optional_score: bool,
+    order_by: Vec<proto::QueryOrderBy>,
+    limit: Option<u32>,
+) -> proto::ServerResponse {
+    let score = pattern(score_attr, "score");
+    let (mut r#where, optional) = if optional_score {
+        (vec![], vec![score])
+    } else {
+        (vec![score], vec![])
+    };
+    r#where.insert(0, pattern(name_attr, "name"));
+    let response = client.handle_message(proto::ClientMessage {
+        request_id: Some(2),
+        payload: Some(proto::client_message::Payload::Query(proto::QueryRequest {
+            find: vec![variable("name"), variable("score")],
+            r#where,
+            optional,
+            where_not: vec![],
+            arrow_ipc: false,
+            limit,
+            offset: None,
+            order_by,
+            aggregates: vec![],
+            group_by: vec![],
+            distinct: false,
+        })),
+    });
+    assert!(is_ok(&response));
+    response
+}
+
+#[test]
+fn test_query_order_by_mixed_value_types() {
+    let mut client = TestClient::new();
+    let (name_attr, score_attr) = insert_scores(&mut client);
+    let ascending = || {
+        order(
+            "score",
+            proto::SortDirection::Ascending,
+            proto::NullsOrder::First,
+        )
+    };
+
+    // Booleans, then numbers numerically, then strings; ties keep entity order
+    let response = query_scores(
+        &mut client,
+        name_attr,
+        score_attr,
+        false,
+        vec![ascending()],
+        None,
+    );
+    assert_eq!(
+        get_strings_at(&response, 0),
+        vec!["n4", "n3", "n6", "n1", "n5", "n2"]
+    );
+
+    // Sorting happens before the limit
+    let descending = order(
+        "score",
+        proto::SortDirection::Descending,
+        proto::NullsOrder::First,
+    );
+    let response = query_scores(
+        &mut client,
+        name_attr,
+        score_attr,
+        false,
+        vec![descending],
+        Some(2),
+    );
+    assert_eq!(get_strings_at(&response, 0), vec!["n2", "n5"]);
+
+    // A second key breaks the ties of the first
+    let by_name = order(
+        "name",
+        proto::SortDirection::Descending,
+        proto::NullsOrder::First,
+    );
+    let response = query_scores(
+        &mut client,
+        name_attr,
+        score_attr,
+        false,
+        vec![ascending(), by_name],
+        None,
+    );
+    assert_eq!(
+        get_strings_at(&response, 0),
+        vec!["n4", "n6", "n3", "n1", "n5", "n2"]
+    );
+}
+
+#[test]
+fn test_query_order_by_places_unbound_values() {
+    let mut client = TestClient::new();
+    let (name_attr, score_attr) = insert_scores(&mut client);
+
+    for (nulls, expected) in [
+        (
+            proto::NullsOrder::First,
+            vec!["n7", "n4", "n3", "n6", "n1", "n5", "n2"],
+        ),
+        (
+            proto::NullsOrder::Last,
+            vec!["n4", "n3", "n6", "n1", "n5", "n2", "n7"],
+        ),
+    ] {
+        let by_score = order("score", proto::SortDirection::Ascending, nulls);
+        let response = query_scores(
+            &mut client,
+            name_attr,
+            score_attr,
+            true,
+            vec![by_score],
+            None,
+        );
+        assert_eq!(get_strings_at(&response, 0), expected, "{nulls:?}");
+    }
+}
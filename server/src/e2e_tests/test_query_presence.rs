@@ -0,0 +1,124 @@
+//! Test presence patterns, which match any value with `any_value`.
+//!
+//! In `where`, a presence pattern keeps entities that have the attribute; in
+//! `where_not`, it keeps those that lack it.
+
+use crate::e2e_tests::helpers::{TestClient, insert_value, is_ok, new_attribute_id, new_entity_id};
+use crate::proto;
+
+/// The `email` attribute.
+const EMAIL: u8 = 1;
+/// The `verified` attribute.
+const VERIFIED: u8 = 2;
+/// The `name` attribute.
+const NAME: u8 = 3;
+
+/// A pattern matching entity `?e` with any value of `attribute`.
+fn presence(attribute: u8) -> proto::QueryPattern {
+    proto::QueryPattern {
+        entity: Some(proto::query_pattern::Entity::EntityVariable(
+            proto::QueryPatternVariable {
+                label: Some("e".to_string()),
+            },
+        )),
+        attribute: Some(proto::query_pattern::Attribute::AttributeId(
+            new_attribute_id(attribute).to_vec(),
+        )),
+        value_group: Some(proto::query_pattern::ValueGroup::AnyValue(
+            proto::AnyValue {},
+        )),
+    }
+}
+
+/// Find `?e` with the given `where` and `where_not` patterns.
+fn query_entities(
+    client: &mut TestClient,
+    r#where: Vec<proto::QueryPattern>,
+    where_not: Vec<proto::QueryPattern>,
+) -> proto::ServerResponse {
+    client.handle_message(proto::ClientMessage {
+        request_id: Some(2),
+        payload: Some(proto::client_message::Payload::Query(proto::QueryRequest {
+            find: vec![proto::QueryPatternVariable {
+                label: Some("e".to_string()),
+            }],
+            r#where,
+            optional: vec![],
+            where_not,
+            arrow_ipc: false,
+            limit: None,
+            offset: None,
+            order_by: vec![],
+            aggregates: vec![],
+            group_by: vec![],
+            distinct: false,
+        })),
+    })
+}
+
+/// The number of result rows.
+const fn row_count(response: &proto::ServerResponse) -> usize {
+    response.rows.len()
+}
+
+/// Set up four users:
+/// - 1: email, verified
+/// - 2: email
+/// - 3: verified
+/// - 4: name only
+fn setup() -> TestClient {
+    let mut client = TestClient::new();
+    let string = |s: &str| proto::triple_value::Value::String(s.to_string());
+    for (entity, attribute, value) in [
+        (1, EMAIL, string("a@example.com")),
+        (1, VERIFIED, proto::triple_value::Value::Boolean(true)),
+        (2, EMAIL, string("b@example.com")),
+        (3, VERIFIED, proto::triple_value::Value::Boolean(false)),
+        (4, NAME, string("Dana")),
+    ] {
+        insert_value(
+            &mut client,
+            new_entity_id(entity),
+            new_attribute_id(attribute),
+            value,
+        );
+    }
+    client
+}
+
+#[test]
+fn test_presence_pattern_matches_any_value() {
+    let mut client = setup();
+
+    let response = query_entities(&mut client, vec![presence(VERIFIED)], vec![]);
+
+    assert!(is_ok(&response));
+    // Both true and false count as present
+    assert_eq!(row_count(&response), 2);
+}
+
+#[test]
+fn test_absence_pattern_excludes_entities_with_attribute() {
+    let mut client = setup();
+
+    let response = query_entities(&mut client, vec![presence(NAME)], vec![presence(EMAIL)]);
+
+    assert!(is_ok(&response));
+    assert_eq!(row_count(&response), 1);
+}
+
+#[test]
+fn test_presence_and_absence_combined() {
+    let mut client = setup();
+
+    // Entities that have an email but are not verified
+    let response = query_entities(&mut client, vec![presence(EMAIL)], vec![presence(VERIFIED)]);
+
+    assert!(is_ok(&response));
+    assert_eq!(row_count(&response), 1);
+    let entity = response.rows[0].values[0].value.as_ref();
+    let Some(proto::query_result_value::Value::Id(id)) = entity else {
+        panic!("expected an entity ID, got {entity:?}");
+    };
+    assert_eq!(id, &crate::types::query::id_to_string(&new_entity_id(2)));
+}
@@ -0,0 +1,221 @@
+//! End-to-end tests for query subscriptions.
+//!
+//! A query subscription is notified that its query was invalidated instead of
+//! receiving raw changes. Like the WebSocket handler, these tests turn each
+//! broadcast notification into per-subscription updates with
+//! `Subscription::update_for`.
+
+use crate::e2e_tests::helpers::{
+    SiblingClient, TestClient, drain_updates, insert_seeded, is_ok, new_attribute_id,
+    new_entity_id, new_hlc,
+};
+use crate::proto;
+
+/// Build a one-pattern query `[entity attribute_id ?value]`.
+///
+/// `entity_id` of `None` makes the entity a variable.
+fn pattern_query(entity_id: Option<[u8; 16]>, attribute_id: [u8; 16]) -> proto::QueryRequest {
+    let entity = entity_id.map_or_else(
+        || {
+            proto::query_pattern::Entity::EntityVariable(proto::QueryPatternVariable {
+                label: Some("e".to_string()),
+            })
+        },
+        |id| proto::query_pattern::Entity::EntityId(id.to_vec()),
+    );
+    proto::QueryRequest {
+        find: vec![proto::QueryPatternVariable {
+            label: Some("value".to_string()),
+        }],
+        r#where: vec![proto::QueryPattern {
+            entity: Some(entity),
+            attribute: Some(proto::query_pattern::Attribute::AttributeId(
+                attribute_id.to_vec(),
+            )),
+            value_group: Some(proto::query_pattern::ValueGroup::ValueVariable(
+                proto::QueryPatternVariable {
+                    label: Some("value".to_string()),
+                },
+            )),
+        }],
+        optional: vec![],
+        where_not: vec![],
+        arrow_ipc: false,
+        limit: None,
+        offset: None,
+        order_by: vec![],
+        aggregates: vec![],
+        group_by: vec![],
+        distinct: false,
+    }
+}
+
+/// Subscribe to a query and return every message the server sent back.
+fn subscribe_query(
+    sibling: &mut SiblingClient,
+    subscription_id: u32,
+    since_hlc: Option<proto::HlcTimestamp>,
+    query: proto::QueryRequest,
+) -> Vec<proto::ServerMessage> {
+    sibling.client.handle_message(proto::ClientMessage {
+        request_id: Some(subscription_id),
+        payload: Some(proto::client_message::Payload::Subscribe(
+            proto::SubscribeRequest {
+                subscription_id,
+                since_hlc,
+                query: Some(query),
+                watch_entity_id: None,
+                until_hlc: None,
+                include_old_values: false,
+                entity_id: None,
+                attribute_id: None,
+            },
+        )),
+    })
+}
+
+#[test]
+fn test_query_subscription_invalidated_by_watched_attribute() {
+    let mut client = TestClient::new();
+    let mut sibling = client.create_sibling();
+    let mut change_rx = sibling.subscribe_to_changes();
+    let watched = new_attribute_id(1);
+    let other = new_attribute_id(2);
+
+    let messages = subscribe_query(&mut sibling, 1, None, pattern_query(None, watched));
+    assert_eq!(messages.len(), 1, "no backfill without since_hlc");
+
+    insert_seeded(&mut client, new_entity_id(1), other, 1);
+    assert!(
+        drain_updates(&sibling, &mut change_rx).is_empty(),
+        "unrelated attribute should not invalidate the query"
+    );
+
+    insert_seeded(&mut client, new_entity_id(1), watched, 2);
+    let updates = drain_updates(&sibling, &mut change_rx);
+    assert_eq!(updates.len(), 1);
+    assert_eq!(updates[0].subscription_id, 1);
+    assert!(updates[0].query_invalidated);
+    assert!(updates[0].changes.is_empty());
+}
+
+#[test]
+fn test_query_subscription_respects_fixed_entity() {
+    let mut client = TestClient::new();
+    let mut sibling = client.create_sibling();
+    let mut change_rx = sibling.subscribe_to_changes();
+    let attribute_id = new_attribute_id(3);
+    let watched_entity = new_entity_id(3);
+
+    subscribe_query(
+        &mut sibling,
+        1,
+        None,
+        pattern_query(Some(watched_entity), attribute_id),
+    );
+
+    insert_seeded(&mut client, new_entity_id(4), attribute_id, 1);
+    assert!(drain_updates(&sibling, &mut change_rx).is_empty());
+
+    insert_seeded(&mut client, watched_entity, attribute_id, 2);
+    let updates = drain_updates(&sibling, &mut change_rx);
+    assert_eq!(updates.len(), 1);
+    assert!(updates[0].query_invalidated);
+}
+
+#[test]
+fn test_query_and_raw_subscriptions_coexist() {
+    let mut client = TestClient::new();
+    let mut sibling = client.create_sibling();
+    let mut change_rx = sibling.subscribe_to_changes();
+    let watched = new_attribute_id(5);
+
+    subscribe_query(&mut sibling, 1, None, pattern_query(None, watched));
+    let response = sibling.handle_message(proto::ClientMessage {
+        request_id: Some(2),
+        payload: Some(proto::client_message::Payload::Subscribe(
+            proto::SubscribeRequest {
+                subscription_id: 2,
+                since_hlc: None,
+                query: None,
+                watch_entity_id: None,
+                until_hlc: None,
+                include_old_values: false,
+                entity_id: None,
+                attribute_id: None,
+            },
+        )),
+    });
+    assert!(is_ok(&response));
+
+    insert_seeded(&mut client, new_entity_id(5), new_attribute_id(6), 1);
+    let updates = drain_updates(&sibling, &mut change_rx);
+    assert_eq!(
+        updates.len(),
+        1,
+        "only the raw subscription sees the change"
+    );
+    assert_eq!(updates[0].subscription_id, 2);
+    assert_eq!(updates[0].changes.len(), 1);
+    assert!(!updates[0].query_invalidated);
+}
+
+#[test]
+fn test_query_subscription_backfill_invalidates() {
+    let mut client = TestClient::new();
+    let mut sibling = client.create_sibling();
+    let watched = new_attribute_id(7);
+
+    insert_seeded(&mut client, new_entity_id(7), watched, 5);
+
+    let messages = subscribe_query(
+        &mut sibling,
+        1,
+        Some(new_hlc(1)),
+        pattern_query(None, watched),
+    );
+    assert_eq!(messages.len(), 2, "backfill invalidation, then OK");
+    match &messages[0].payload {
+        Some(proto::server_message::Payload::SubscriptionUpdate(update)) => {
+            assert!(update.query_invalidated);
+            assert!(update.changes.is_empty());
+        }
+        other => panic!("expected SubscriptionUpdate, got {other:?}"),
+    }
+
+    // A backfill with no matching change sends no update
+    let messages = subscribe_query(
+        &mut sibling,
+        2,
+        Some(new_hlc(1)),
+        pattern_query(None, new_attribute_id(8)),
+    );
+    assert_eq!(messages.len(), 1);
+}
+
+#[test]
+fn test_query_subscription_rejects_invalid_query() {
+    let client = TestClient::new();
+    let mut sibling = client.create_sibling();
+    let mut query = pattern_query(None, new_attribute_id(9));
+    query.r#where[0].entity = None;
+
+    let response = sibling.handle_message(proto::ClientMessage {
+        request_id: Some(1),
+        payload: Some(proto::client_message::Payload::Subscribe(
+            proto::SubscribeRequest {
+                subscription_id: 1,
+                since_hlc: None,
+                query: Some(query),
+                watch_entity_id: None,
+                until_hlc: None,
+                include_old_values: false,
+                entity_id: None,
+                attribute_id: None,
+            },
+        )),
+    });
+
+    assert!(!is_ok(&response));
+    assert_eq!(sibling.client.subscriptions().count(), 0);
+}
@@ -15,6 +15,7 @@ use crate::proto;
 /// Query: find entities WITHOUT the active attribute
 /// Expected: 1 row (Charlie)
 #[test]
+#[allow(clippy::too_many_lines)]
 fn test_query_where_not_excludes_attribute() {
     let mut client = TestClient::new();
 
@@ -38,6 +39,7 @@ fn test_query_where_not_excludes_attribute() {
                             value: Some(proto::triple_value::Value::String("Alice".to_string())),
                         }),
                         hlc: Some(new_hlc(1)),
+                        attribute_name: None,
                     },
                     proto::Triple {
                         entity_id: Some(entity1.to_vec()),
@@ -46,6 +48,7 @@ fn test_query_where_not_excludes_attribute() {
                             value: Some(proto::triple_value::Value::Boolean(true)),
                         }),
                         hlc: Some(new_hlc(2)),
+                        attribute_name: None,
                     },
                     // Entity 2: Bob with active=false
                     proto::Triple {
@@ -55,6 +58,7 @@ fn test_query_where_not_excludes_attribute() {
                             value: Some(proto::triple_value::Value::String("Bob".to_string())),
                         }),
                         hlc: Some(new_hlc(3)),
+                        attribute_name: None,
                     },
                     proto::Triple {
                         entity_id: Some(entity2.to_vec()),
@@ -63,6 +67,7 @@ fn test_query_where_not_excludes_attribute() {
                             value: Some(proto::triple_value::Value::Boolean(false)),
                         }),
                         hlc: Some(new_hlc(4)),
+                        attribute_name: None,
                     },
                     // Entity 3: Charlie with no active field
                     proto::Triple {
@@ -72,8 +77,11 @@ fn test_query_where_not_excludes_attribute() {
                             value: Some(proto::triple_value::Value::String("Charlie".to_string())),
                         }),
                         hlc: Some(new_hlc(5)),
+                        attribute_name: None,
                     },
                 ],
+                mode: proto::WriteMode::Upsert.into(),
+                expected_versions: vec![],
             },
         )),
     });
@@ -117,6 +125,13 @@ fn test_query_where_not_excludes_attribute() {
                     },
                 )),
             }],
+            arrow_ipc: false,
+            limit: None,
+            offset: None,
+            order_by: vec![],
+            aggregates: vec![],
+            group_by: vec![],
+            distinct: false,
         })),
     });
 
@@ -158,6 +173,7 @@ fn test_query_where_not_excludes_value() {
                             value: Some(proto::triple_value::Value::String("Alice".to_string())),
                         }),
                         hlc: Some(new_hlc(1)),
+                        attribute_name: None,
                     },
                     proto::Triple {
                         entity_id: Some(entity1.to_vec()),
@@ -166,6 +182,7 @@ fn test_query_where_not_excludes_value() {
                             value: Some(proto::triple_value::Value::Boolean(true)),
                         }),
                         hlc: Some(new_hlc(2)),
+                        attribute_name: None,
                     },
                     proto::Triple {
                         entity_id: Some(entity2.to_vec()),
@@ -174,6 +191,7 @@ fn test_query_where_not_excludes_value() {
                             value: Some(proto::triple_value::Value::String("Bob".to_string())),
                         }),
                         hlc: Some(new_hlc(3)),
+                        attribute_name: None,
                     },
                     proto::Triple {
                         entity_id: Some(entity2.to_vec()),
@@ -182,6 +200,7 @@ fn test_query_where_not_excludes_value() {
                             value: Some(proto::triple_value::Value::Boolean(false)),
                         }),
                         hlc: Some(new_hlc(4)),
+                        attribute_name: None,
                     },
                     proto::Triple {
                         entity_id: Some(entity3.to_vec()),
@@ -190,8 +209,11 @@ fn test_query_where_not_excludes_value() {
                             value: Some(proto::triple_value::Value::String("Charlie".to_string())),
                         }),
                         hlc: Some(new_hlc(5)),
+                        attribute_name: None,
                     },
                 ],
+                mode: proto::WriteMode::Upsert.into(),
+                expected_versions: vec![],
             },
         )),
     });
@@ -235,6 +257,13 @@ fn test_query_where_not_excludes_value() {
                     },
                 )),
             }],
+            arrow_ipc: false,
+            limit: None,
+            offset: None,
+            order_by: vec![],
+            aggregates: vec![],
+            group_by: vec![],
+            distinct: false,
         })),
     });
 
@@ -273,6 +302,7 @@ fn test_query_where_not_no_exclusions() {
                             value: Some(proto::triple_value::Value::String("Alice".to_string())),
                         }),
                         hlc: Some(new_hlc(1)),
+                        attribute_name: None,
                     },
                     proto::Triple {
                         entity_id: Some(entity2.to_vec()),
@@ -281,8 +311,11 @@ fn test_query_where_not_no_exclusions() {
                             value: Some(proto::triple_value::Value::String("Bob".to_string())),
                         }),
                         hlc: Some(new_hlc(2)),
+                        attribute_name: None,
                     },
                 ],
+                mode: proto::WriteMode::Upsert.into(),
+                expected_versions: vec![],
             },
         )),
     });
@@ -326,6 +359,13 @@ fn test_query_where_not_no_exclusions() {
                     },
                 )),
             }],
+            arrow_ipc: false,
+            limit: None,
+            offset: None,
+            order_by: vec![],
+            aggregates: vec![],
+            group_by: vec![],
+            distinct: false,
         })),
     });
 
@@ -355,6 +395,7 @@ fn test_query_where_not_excludes_all() {
                             value: Some(proto::triple_value::Value::String("Alice".to_string())),
                         }),
                         hlc: Some(new_hlc(1)),
+                        attribute_name: None,
                     },
                     proto::Triple {
                         entity_id: Some(entity2.to_vec()),
@@ -363,8 +404,11 @@ fn test_query_where_not_excludes_all() {
                             value: Some(proto::triple_value::Value::String("Bob".to_string())),
                         }),
                         hlc: Some(new_hlc(2)),
+                        attribute_name: None,
                     },
                 ],
+                mode: proto::WriteMode::Upsert.into(),
+                expected_versions: vec![],
             },
         )),
     });
@@ -408,6 +452,13 @@ fn test_query_where_not_excludes_all() {
                     },
                 )),
             }],
+            arrow_ipc: false,
+            limit: None,
+            offset: None,
+            order_by: vec![],
+            aggregates: vec![],
+            group_by: vec![],
+            distinct: false,
         })),
     });
 
@@ -0,0 +1,164 @@
+//! Test running several queries against one snapshot with a `ReadBatchRequest`.
+
+use crate::e2e_tests::helpers::{
+    TestClient, insert, insert_named, is_ok, new_attribute_id, new_entity_id, status_code,
+};
+use crate::proto;
+
+/// Query for the value of one triple.
+fn point_query(
+    entity_id: [u8; 16],
+    attribute: proto::query_pattern::Attribute,
+) -> proto::QueryRequest {
+    proto::QueryRequest {
+        find: vec![proto::QueryPatternVariable {
+            label: Some("value".to_string()),
+        }],
+        r#where: vec![proto::QueryPattern {
+            entity: Some(proto::query_pattern::Entity::EntityId(entity_id.to_vec())),
+            attribute: Some(attribute),
+            value_group: Some(proto::query_pattern::ValueGroup::ValueVariable(
+                proto::QueryPatternVariable {
+                    label: Some("value".to_string()),
+                },
+            )),
+        }],
+        optional: vec![],
+        where_not: vec![],
+        arrow_ipc: false,
+        limit: None,
+        offset: None,
+        order_by: vec![],
+        aggregates: vec![],
+        group_by: vec![],
+        distinct: false,
+    }
+}
+
+/// Send a `ReadBatchRequest` with the given queries.
+fn read_batch(client: &mut TestClient, queries: Vec<proto::QueryRequest>) -> proto::ServerResponse {
+    client.handle_message(proto::ClientMessage {
+        request_id: Some(2),
+        payload: Some(proto::client_message::Payload::ReadBatch(
+            proto::ReadBatchRequest { queries },
+        )),
+    })
+}
+
+/// The string values in a batch result's rows.
+fn strings(result: &proto::BatchQueryResult) -> Vec<&str> {
+    result
+        .rows
+        .iter()
+        .filter_map(|row| match &row.values.first()?.value {
+            Some(proto::query_result_value::Value::TripleValue(proto::TripleValue {
+                value: Some(proto::triple_value::Value::String(s)),
+            })) => Some(s.as_str()),
+            _ => None,
+        })
+        .collect()
+}
+
+#[test]
+fn test_read_batch_returns_result_per_query() {
+    let mut client = TestClient::new();
+    let attribute_id = new_attribute_id(1);
+    let alice = new_entity_id(1);
+    let bob = new_entity_id(2);
+    insert(&mut client, alice, attribute_id, "Alice");
+    insert(&mut client, bob, attribute_id, "Bob");
+
+    let attribute = || proto::query_pattern::Attribute::AttributeId(attribute_id.to_vec());
+    let response = read_batch(
+        &mut client,
+        vec![
+            point_query(bob, attribute()),
+            point_query(new_entity_id(3), attribute()),
+            point_query(alice, attribute()),
+        ],
+    );
+
+    assert!(is_ok(&response));
+    assert_eq!(response.request_id, Some(2));
+    assert!(response.rows.is_empty());
+    assert_eq!(response.batch_results.len(), 3);
+    for result in &response.batch_results {
+        assert_eq!(
+            result.status.as_ref().map(|status| status.code),
+            Some(proto::google::rpc::Code::Ok as i32)
+        );
+        assert_eq!(result.columns, vec!["value"]);
+    }
+    assert_eq!(strings(&response.batch_results[0]), vec!["Bob"]);
+    assert!(strings(&response.batch_results[1]).is_empty());
+    assert_eq!(strings(&response.batch_results[2]), vec!["Alice"]);
+}
+
+#[test]
+fn test_read_batch_resolves_attribute_names() {
+    let mut client = TestClient::new();
+    let entity_id = new_entity_id(1);
+    insert_named(
+        &mut client,
+        entity_id,
+        "name",
+        proto::triple_value::Value::String("Alice".to_string()),
+    );
+
+    let response = read_batch(
+        &mut client,
+        vec![
+            point_query(
+                entity_id,
+                proto::query_pattern::Attribute::AttributeName("name".to_string()),
+            ),
+            point_query(
+                entity_id,
+                proto::query_pattern::Attribute::AttributeName("missing".to_string()),
+            ),
+        ],
+    );
+
+    assert!(is_ok(&response));
+    assert_eq!(strings(&response.batch_results[0]), vec!["Alice"]);
+    assert!(strings(&response.batch_results[1]).is_empty());
+}
+
+#[test]
+fn test_read_batch_rejects_malformed_query() {
+    let mut client = TestClient::new();
+    let attribute_id = new_attribute_id(1);
+    let mut malformed = point_query(
+        new_entity_id(1),
+        proto::query_pattern::Attribute::AttributeId(attribute_id.to_vec()),
+    );
+    malformed.r#where[0].entity = None;
+
+    let response = read_batch(
+        &mut client,
+        vec![
+            point_query(
+                new_entity_id(1),
+                proto::query_pattern::Attribute::AttributeId(attribute_id.to_vec()),
+            ),
+            malformed,
+        ],
+    );
+
+    // No query runs when any of them is malformed
+    assert_eq!(
+        status_code(&response),
+        proto::google::rpc::Code::InvalidArgument as i32
+    );
+    assert!(response.batch_results.is_empty());
+}
+
+#[test]
+fn test_empty_read_batch() {
+    let mut client = TestClient::new();
+
+    let response = read_batch(&mut client, vec![]);
+
+    assert!(is_ok(&response));
+    assert!(response.batch_results.is_empty());
+}
@@ -11,7 +11,11 @@ fn test_request_id_preserved() {
         let request = proto::ClientMessage {
             request_id: Some(request_id),
             payload: Some(proto::client_message::Payload::TripleUpdateRequest(
-                proto::TripleUpdateRequest { triples: vec![] },
+                proto::TripleUpdateRequest {
+                    triples: vec![],
+                    mode: proto::WriteMode::Upsert.into(),
+                    expected_versions: vec![],
+                },
             )),
         };
 
@@ -27,7 +31,11 @@ fn test_request_id_none() {
     let request = proto::ClientMessage {
         request_id: None,
         payload: Some(proto::client_message::Payload::TripleUpdateRequest(
-            proto::TripleUpdateRequest { triples: vec![] },
+            proto::TripleUpdateRequest {
+                triples: vec![],
+                mode: proto::WriteMode::Upsert.into(),
+                expected_versions: vec![],
+            },
         )),
     };
 
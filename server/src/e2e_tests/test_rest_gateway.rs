@@ -0,0 +1,275 @@
+//! End-to-end tests for the REST/JSON gateway over a real HTTP server.
+//!
+//! Requests are sent with a minimal HTTP/1.1 client on a raw TCP stream, one
+//! request per connection.
+
+use serde_json::{Value, json};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+use crate::e2e_tests::helpers::{TestClient, new_hlc, start_server};
+use crate::proto;
+
+const APP_API_KEY: &str = "rest_app";
+
+/// Start a server and return its `host:port`.
+async fn start() -> (tempfile::TempDir, String) {
+    let (dir, url) = start_server().await;
+    let addr = url
+        .strip_prefix("ws://")
+        .and_then(|rest| rest.strip_suffix("/ws"))
+        .expect("server URL")
+        .to_string();
+    (dir, addr)
+}
+
+/// Send one HTTP request and return the status code and JSON body.
+async fn request(
+    addr: &str,
+    method: &str,
+    path: &str,
+    app_api_key: Option<&str>,
+    body: Option<&Value>,
+) -> (u16, Value) {
+    let body = body.map(Value::to_string).unwrap_or_default();
+    let key_header = app_api_key
+        .map(|key| format!("x-app-api-key: {key}\r\n"))
+        .unwrap_or_default();
+    let head = format!(
+        "{method} {path} HTTP/1.1\r\nHost: {addr}\r\nConnection: close\r\nContent-Type: application/json\r\nContent-Length: {}\r\n{key_header}\r\n",
+        body.len()
+    );
+
+    let mut stream = tokio::net::TcpStream::connect(addr).await.expect("connect");
+    stream
+        .write_all(format!("{head}{body}").as_bytes())
+        .await
+        .expect("send request");
+    let mut response = String::new();
+    stream
+        .read_to_string(&mut response)
+        .await
+        .expect("read response");
+
+    let (head, body) = response.split_once("\r\n\r\n").expect("response head");
+    let status = head
+        .split_whitespace()
+        .nth(1)
+        .and_then(|code| code.parse().ok())
+        .expect("status code");
+    (status, serde_json::from_str(body).expect("JSON body"))
+}
+
+async fn post(addr: &str, path: &str, body: &Value) -> (u16, Value) {
+    request(addr, "POST", path, Some(APP_API_KEY), Some(body)).await
+}
+
+fn hex_id(byte: u8) -> String {
+    format!("{byte:02x}").repeat(16)
+}
+
+#[tokio::test]
+async fn test_write_then_read_entity() {
+    let (_dir, addr) = start().await;
+    let entity = hex_id(0x11);
+
+    let (status, body) = post(
+        &addr,
+        "/v1/triples",
+        &json!({"triples": [
+            {"entity_id": entity, "attribute_id": hex_id(0x21), "value": "Alice"},
+            {"entity_id": entity, "attribute_id": hex_id(0x22), "value": 42.5},
+            {"entity_id": entity, "attribute_id": hex_id(0x23), "value": true},
+            {"entity_id": entity, "attribute_id": hex_id(0x24), "value": {"bytes": "00ff"}},
+        ]}),
+    )
+    .await;
+    assert_eq!(status, 200, "{body}");
+    let results = body["results"].as_array().expect("results");
+    assert_eq!(results.len(), 4);
+    assert_eq!(results[0]["entity_id"], entity);
+    assert_eq!(results[0]["attribute_id"], hex_id(0x21));
+    assert!(results.iter().all(|result| result["change"] == "insert"));
+
+    let (status, body) = post(
+        &addr,
+        "/v1/triples",
+        &json!({"triples": [{"entity_id": entity, "attribute_id": hex_id(0x21), "value": "Bob"}]}),
+    )
+    .await;
+    assert_eq!(status, 200, "{body}");
+    assert_eq!(body["results"][0]["change"], "update");
+
+    let (status, body) = request(
+        &addr,
+        "GET",
+        &format!("/v1/entity/{entity}"),
+        Some(APP_API_KEY),
+        None,
+    )
+    .await;
+    assert_eq!(status, 200, "{body}");
+    assert_eq!(
+        body,
+        json!({
+            "entity_id": entity,
+            "attributes": {
+                hex_id(0x21): "Bob",
+                hex_id(0x22): 42.5,
+                hex_id(0x23): true,
+                hex_id(0x24): {"bytes": "00ff"},
+            }
+        })
+    );
+
+    let (status, body) = request(
+        &addr,
+        "GET",
+        &format!("/v1/entity/{}", hex_id(0x12)),
+        Some(APP_API_KEY),
+        None,
+    )
+    .await;
+    assert_eq!(status, 404, "{body}");
+}
+
+#[tokio::test]
+async fn test_query() {
+    let (_dir, addr) = start().await;
+    let name = hex_id(0x31);
+    let (status, body) = post(
+        &addr,
+        "/v1/triples",
+        &json!({"triples": [
+            {"entity_id": hex_id(0x01), "attribute_id": name, "value": "Alice"},
+            {"entity_id": hex_id(0x02), "attribute_id": name, "value": "Bob"},
+        ]}),
+    )
+    .await;
+    assert_eq!(status, 200, "{body}");
+
+    let (status, body) = post(
+        &addr,
+        "/v1/query",
+        &json!({
+            "find": ["e", "name"],
+            "where": [{"entity": {"var": "e"}, "attribute": name, "value": {"var": "name"}}],
+        }),
+    )
+    .await;
+    assert_eq!(status, 200, "{body}");
+    assert_eq!(body["columns"], json!(["e", "name"]));
+    let mut rows = body["rows"].as_array().expect("rows").to_owned();
+    rows.sort_by_key(|row| row[1].as_str().map(str::to_owned));
+    assert_eq!(
+        rows,
+        vec![json!([hex_id(0x01), "Alice"]), json!([hex_id(0x02), "Bob"])]
+    );
+
+    let (status, body) = post(
+        &addr,
+        "/v1/query",
+        &json!({
+            "find": ["e"],
+            "where": [{"entity": {"var": "e"}, "attribute": name, "value": "Bob"}],
+        }),
+    )
+    .await;
+    assert_eq!(status, 200, "{body}");
+    assert_eq!(body["rows"], json!([[hex_id(0x02)]]));
+}
+
+#[tokio::test]
+async fn test_too_long_string_is_bad_request_with_protocol_message() {
+    let (_dir, addr) = start().await;
+    let long_value = "x".repeat(2000);
+
+    // The WebSocket path's message for the same triple
+    let mut client = TestClient::new();
+    let expected = client.handle_message(proto::ClientMessage {
+        request_id: Some(1),
+        payload: Some(proto::client_message::Payload::TripleUpdateRequest(
+            proto::TripleUpdateRequest {
+                triples: vec![proto::Triple {
+                    entity_id: Some(vec![0x41; 16]),
+                    attribute_id: Some(vec![0x42; 16]),
+                    value: Some(proto::TripleValue {
+                        value: Some(proto::triple_value::Value::String(long_value.clone())),
+                    }),
+                    hlc: Some(new_hlc(1)),
+                    attribute_name: None,
+                }],
+                mode: proto::WriteMode::Upsert.into(),
+                expected_versions: vec![],
+            },
+        )),
+    });
+    let expected = expected.status.expect("status");
+    assert_eq!(
+        expected.code,
+        proto::google::rpc::Code::InvalidArgument as i32
+    );
+
+    let (status, body) = post(
+        &addr,
+        "/v1/triples",
+        &json!({"triples": [
+            {"entity_id": hex_id(0x41), "attribute_id": hex_id(0x42), "value": long_value},
+        ]}),
+    )
+    .await;
+    assert_eq!(status, 400);
+    assert_eq!(body["error"], expected.message);
+}
+
+#[tokio::test]
+async fn test_malformed_requests_are_bad_requests() {
+    let (_dir, addr) = start().await;
+
+    let (status, body) = request(&addr, "GET", "/v1/entity/not-hex", Some(APP_API_KEY), None).await;
+    assert_eq!(status, 400);
+    assert!(
+        body["error"]
+            .as_str()
+            .is_some_and(|error| error.starts_with("invalid entity ID"))
+    );
+
+    let (status, _) = post(
+        &addr,
+        "/v1/triples",
+        &json!({"triples": [
+            {"entity_id": hex_id(0x01), "attribute_id": "abcd", "value": "x"},
+        ]}),
+    )
+    .await;
+    assert_eq!(status, 400);
+
+    let (status, _) = post(
+        &addr,
+        "/v1/triples",
+        &json!({"triples": [
+            {"entity_id": hex_id(0x01), "attribute_id": hex_id(0x02), "value": null},
+        ]}),
+    )
+    .await;
+    assert_eq!(status, 400);
+
+    let (status, body) = post(&addr, "/v1/query", &json!({"where": []})).await;
+    assert_eq!(status, 400);
+    assert!(body["error"].is_string());
+}
+
+#[tokio::test]
+async fn test_missing_app_key_is_unauthorized() {
+    let (_dir, addr) = start().await;
+
+    let (status, body) = request(
+        &addr,
+        "POST",
+        "/v1/query",
+        None,
+        Some(&json!({"find": ["e"], "where": []})),
+    )
+    .await;
+    assert_eq!(status, 401);
+    assert_eq!(body["error"], "x-app-api-key header is required");
+}
@@ -5,6 +5,43 @@ use crate::e2e_tests::helpers::{
 };
 use crate::proto;
 
+/// Query the value of `attribute_id` on `entity_id`.
+fn query_value(
+    client: &mut TestClient,
+    request_id: u32,
+    entity_id: [u8; 16],
+    attribute_id: [u8; 16],
+) -> proto::ServerResponse {
+    client.handle_message(proto::ClientMessage {
+        request_id: Some(request_id),
+        payload: Some(proto::client_message::Payload::Query(proto::QueryRequest {
+            find: vec![proto::QueryPatternVariable {
+                label: Some("value".to_string()),
+            }],
+            r#where: vec![proto::QueryPattern {
+                entity: Some(proto::query_pattern::Entity::EntityId(entity_id.to_vec())),
+                attribute: Some(proto::query_pattern::Attribute::AttributeId(
+                    attribute_id.to_vec(),
+                )),
+                value_group: Some(proto::query_pattern::ValueGroup::ValueVariable(
+                    proto::QueryPatternVariable {
+                        label: Some("value".to_string()),
+                    },
+                )),
+            }],
+            optional: vec![],
+            where_not: vec![],
+            arrow_ipc: false,
+            limit: None,
+            offset: None,
+            order_by: vec![],
+            aggregates: vec![],
+            group_by: vec![],
+            distinct: false,
+        })),
+    })
+}
+
 #[test]
 fn test_sequence_insert_query_update_query() {
     let mut client = TestClient::new();
@@ -24,34 +61,17 @@ fn test_sequence_insert_query_update_query() {
                         value: Some(proto::triple_value::Value::Number(1.0)),
                     }),
                     hlc: Some(new_hlc(1)),
+                    attribute_name: None,
                 }],
+                mode: proto::WriteMode::Upsert.into(),
+                expected_versions: vec![],
             },
         )),
     });
     assert!(is_ok(&response1));
 
     // Step 2: Query (should see 1.0)
-    let response2 = client.handle_message(proto::ClientMessage {
-        request_id: Some(2),
-        payload: Some(proto::client_message::Payload::Query(proto::QueryRequest {
-            find: vec![proto::QueryPatternVariable {
-                label: Some("value".to_string()),
-            }],
-            r#where: vec![proto::QueryPattern {
-                entity: Some(proto::query_pattern::Entity::EntityId(entity_id.to_vec())),
-                attribute: Some(proto::query_pattern::Attribute::AttributeId(
-                    attribute_id.to_vec(),
-                )),
-                value_group: Some(proto::query_pattern::ValueGroup::ValueVariable(
-                    proto::QueryPatternVariable {
-                        label: Some("value".to_string()),
-                    },
-                )),
-            }],
-            optional: vec![],
-            where_not: vec![],
-        })),
-    });
+    let response2 = query_value(&mut client, 2, entity_id, attribute_id);
     assert!(is_ok(&response2));
     assert_eq!(response2.rows.len(), 1);
     assert_eq!(get_number_value(&response2, 0), Some(1.0));
@@ -68,34 +88,17 @@ fn test_sequence_insert_query_update_query() {
                         value: Some(proto::triple_value::Value::Number(2.0)),
                     }),
                     hlc: Some(new_hlc(2)),
+                    attribute_name: None,
                 }],
+                mode: proto::WriteMode::Upsert.into(),
+                expected_versions: vec![],
             },
         )),
     });
     assert!(is_ok(&response3));
 
     // Step 4: Query (should see 2.0)
-    let response4 = client.handle_message(proto::ClientMessage {
-        request_id: Some(4),
-        payload: Some(proto::client_message::Payload::Query(proto::QueryRequest {
-            find: vec![proto::QueryPatternVariable {
-                label: Some("value".to_string()),
-            }],
-            r#where: vec![proto::QueryPattern {
-                entity: Some(proto::query_pattern::Entity::EntityId(entity_id.to_vec())),
-                attribute: Some(proto::query_pattern::Attribute::AttributeId(
-                    attribute_id.to_vec(),
-                )),
-                value_group: Some(proto::query_pattern::ValueGroup::ValueVariable(
-                    proto::QueryPatternVariable {
-                        label: Some("value".to_string()),
-                    },
-                )),
-            }],
-            optional: vec![],
-            where_not: vec![],
-        })),
-    });
+    let response4 = query_value(&mut client, 4, entity_id, attribute_id);
     assert!(is_ok(&response4));
     assert_eq!(response4.rows.len(), 1);
     assert_eq!(get_number_value(&response4, 0), Some(2.0));
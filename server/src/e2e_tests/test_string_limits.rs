@@ -2,8 +2,11 @@
 
 use crate::e2e_tests::helpers::{
     TestClient, get_string_value, is_ok, new_attribute_id, new_entity_id, new_hlc, status_code,
+    status_message,
 };
 use crate::proto;
+use crate::types::ValueLimits;
+use crate::types::error_detail::error_reason;
 
 #[test]
 fn test_max_length_string_value() {
@@ -26,7 +29,10 @@ fn test_max_length_string_value() {
                         value: Some(proto::triple_value::Value::String(max_string.clone())),
                     }),
                     hlc: Some(new_hlc(1)),
+                    attribute_name: None,
                 }],
+                mode: proto::WriteMode::Upsert.into(),
+                expected_versions: vec![],
             },
         )),
     });
@@ -52,6 +58,13 @@ fn test_max_length_string_value() {
             }],
             optional: vec![],
             where_not: vec![],
+            arrow_ipc: false,
+            limit: None,
+            offset: None,
+            order_by: vec![],
+            aggregates: vec![],
+            group_by: vec![],
+            distinct: false,
         })),
     });
     assert!(is_ok(&query_response));
@@ -83,7 +96,10 @@ fn test_string_too_long_rejected() {
                         value: Some(proto::triple_value::Value::String(too_long_string)),
                     }),
                     hlc: Some(new_hlc(1)),
+                    attribute_name: None,
                 }],
+                mode: proto::WriteMode::Upsert.into(),
+                expected_versions: vec![],
             },
         )),
     });
@@ -93,3 +109,170 @@ fn test_string_too_long_rejected() {
         proto::google::rpc::Code::InvalidArgument as i32
     );
 }
+
+fn insert_string(client: &mut TestClient, seed: u8, value: String) -> proto::ServerResponse {
+    client.handle_message(proto::ClientMessage {
+        request_id: Some(1),
+        payload: Some(proto::client_message::Payload::TripleUpdateRequest(
+            proto::TripleUpdateRequest {
+                triples: vec![proto::Triple {
+                    entity_id: Some(new_entity_id(seed).to_vec()),
+                    attribute_id: Some(new_attribute_id(seed).to_vec()),
+                    value: Some(proto::TripleValue {
+                        value: Some(proto::triple_value::Value::String(value)),
+                    }),
+                    hlc: Some(new_hlc(u64::from(seed))),
+                    attribute_name: None,
+                }],
+                mode: proto::WriteMode::Upsert.into(),
+                expected_versions: vec![],
+            },
+        )),
+    })
+}
+
+#[test]
+fn test_lowered_string_limit_boundaries() {
+    let limits = ValueLimits::new(16).expect("valid limit");
+    let mut client = TestClient::with_value_limits(limits);
+
+    assert!(is_ok(&insert_string(&mut client, 72, "a".repeat(15))));
+    assert!(is_ok(&insert_string(&mut client, 73, "a".repeat(16))));
+
+    let response = insert_string(&mut client, 74, "a".repeat(17));
+    assert_eq!(
+        status_code(&response),
+        proto::google::rpc::Code::InvalidArgument as i32
+    );
+    assert!(status_message(&response).contains("Max: 16, got: 17"));
+    assert_eq!(
+        response.status.as_ref().and_then(error_reason),
+        Some(proto::error_detail::Reason::StringTooLong(
+            proto::StringTooLong {
+                location: "triple #0".to_string(),
+                limit: 16,
+                length: 17,
+            }
+        ))
+    );
+}
+
+#[test]
+fn test_raised_string_limit_boundaries() {
+    // Larger than a page, so accepted values are stored in overflow pages
+    let limits = ValueLimits::new(8192).expect("valid limit");
+    let mut client = TestClient::with_value_limits(limits);
+
+    assert!(is_ok(&insert_string(&mut client, 75, "b".repeat(8191))));
+    assert!(is_ok(&insert_string(&mut client, 76, "b".repeat(8192))));
+
+    let response = insert_string(&mut client, 77, "b".repeat(8193));
+    assert_eq!(
+        status_code(&response),
+        proto::google::rpc::Code::InvalidArgument as i32
+    );
+    assert!(status_message(&response).contains("Max: 8192, got: 8193"));
+
+    // The value at the limit reads back intact
+    let query_response = client.handle_message(proto::ClientMessage {
+        request_id: Some(2),
+        payload: Some(proto::client_message::Payload::Query(proto::QueryRequest {
+            find: vec![proto::QueryPatternVariable {
+                label: Some("value".to_string()),
+            }],
+            r#where: vec![proto::QueryPattern {
+                entity: Some(proto::query_pattern::Entity::EntityId(
+                    new_entity_id(76).to_vec(),
+                )),
+                attribute: Some(proto::query_pattern::Attribute::AttributeId(
+                    new_attribute_id(76).to_vec(),
+                )),
+                value_group: Some(proto::query_pattern::ValueGroup::ValueVariable(
+                    proto::QueryPatternVariable {
+                        label: Some("value".to_string()),
+                    },
+                )),
+            }],
+            optional: vec![],
+            where_not: vec![],
+            arrow_ipc: false,
+            limit: None,
+            offset: None,
+            order_by: vec![],
+            aggregates: vec![],
+            group_by: vec![],
+            distinct: false,
+        })),
+    });
+    assert!(is_ok(&query_response));
+    assert_eq!(
+        get_string_value(&query_response, 0).map(str::len),
+        Some(8192)
+    );
+}
+
+fn query_string(client: &mut TestClient, value: String) -> proto::ServerResponse {
+    client.handle_message(proto::ClientMessage {
+        request_id: Some(3),
+        payload: Some(proto::client_message::Payload::Query(proto::QueryRequest {
+            find: vec![proto::QueryPatternVariable {
+                label: Some("e".to_string()),
+            }],
+            r#where: vec![proto::QueryPattern {
+                entity: Some(proto::query_pattern::Entity::EntityVariable(
+                    proto::QueryPatternVariable {
+                        label: Some("e".to_string()),
+                    },
+                )),
+                attribute: Some(proto::query_pattern::Attribute::AttributeId(
+                    new_attribute_id(78).to_vec(),
+                )),
+                value_group: Some(proto::query_pattern::ValueGroup::Value(
+                    proto::TripleValue {
+                        value: Some(proto::triple_value::Value::String(value)),
+                    },
+                )),
+            }],
+            optional: vec![],
+            where_not: vec![],
+            arrow_ipc: false,
+            limit: None,
+            offset: None,
+            order_by: vec![],
+            aggregates: vec![],
+            group_by: vec![],
+            distinct: false,
+        })),
+    })
+}
+
+#[test]
+fn test_query_string_limit_boundaries() {
+    let limits = ValueLimits::new(16).expect("valid limit");
+    let mut client = TestClient::with_value_limits(limits);
+    assert!(is_ok(&insert_string(&mut client, 78, "c".repeat(16))));
+
+    let response = query_string(&mut client, "c".repeat(16));
+    assert!(is_ok(&response));
+    assert_eq!(response.rows.len(), 1);
+
+    let response = query_string(&mut client, "c".repeat(17));
+    assert_eq!(
+        status_code(&response),
+        proto::google::rpc::Code::InvalidArgument as i32
+    );
+    assert_eq!(
+        status_message(&response),
+        "Invalid where pattern #0: Triple string value too long. Max: 16, got: 17"
+    );
+
+    let response = query_string(&mut client, String::new());
+    assert_eq!(
+        status_code(&response),
+        proto::google::rpc::Code::InvalidArgument as i32
+    );
+    assert_eq!(
+        status_message(&response),
+        "Invalid where pattern #0: Triple string value was empty"
+    );
+}
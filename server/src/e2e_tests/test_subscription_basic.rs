@@ -37,7 +37,10 @@ fn test_insert_broadcasts_change_notification() {
                         value: Some(proto::triple_value::Value::String("test".to_string())),
                     }),
                     hlc: Some(new_hlc(1)),
+                    attribute_name: None,
                 }],
+                mode: proto::WriteMode::Upsert.into(),
+                expected_versions: vec![],
             },
         )),
     });
@@ -84,7 +87,10 @@ fn test_update_broadcasts_change_notification() {
                         value: Some(proto::triple_value::Value::String("initial".to_string())),
                     }),
                     hlc: Some(new_hlc(1)),
+                    attribute_name: None,
                 }],
+                mode: proto::WriteMode::Upsert.into(),
+                expected_versions: vec![],
             },
         )),
     });
@@ -105,7 +111,10 @@ fn test_update_broadcasts_change_notification() {
                         value: Some(proto::triple_value::Value::String("updated".to_string())),
                     }),
                     hlc: Some(new_hlc(2)),
+                    attribute_name: None,
                 }],
+                mode: proto::WriteMode::Upsert.into(),
+                expected_versions: vec![],
             },
         )),
     });
@@ -158,6 +167,7 @@ fn test_batch_insert_broadcasts_multiple_changes() {
                             value: Some(proto::triple_value::Value::String("first".to_string())),
                         }),
                         hlc: Some(new_hlc(1)),
+                        attribute_name: None,
                     },
                     proto::Triple {
                         entity_id: Some(entity_id_2.to_vec()),
@@ -166,8 +176,11 @@ fn test_batch_insert_broadcasts_multiple_changes() {
                             value: Some(proto::triple_value::Value::Number(42.0)),
                         }),
                         hlc: Some(new_hlc(1)),
+                        attribute_name: None,
                     },
                 ],
+                mode: proto::WriteMode::Upsert.into(),
+                expected_versions: vec![],
             },
         )),
     });
@@ -224,7 +237,10 @@ fn test_get_changes_since_returns_changes() {
                         )),
                     }),
                     hlc: Some(new_hlc(5)),
+                    attribute_name: None,
                 }],
+                mode: proto::WriteMode::Upsert.into(),
+                expected_versions: vec![],
             },
         )),
     });
@@ -258,7 +274,10 @@ fn test_failed_operation_does_not_broadcast() {
                         value: Some(proto::triple_value::Value::String("invalid".to_string())),
                     }),
                     hlc: Some(new_hlc(1)),
+                    attribute_name: None,
                 }],
+                mode: proto::WriteMode::Upsert.into(),
+                expected_versions: vec![],
             },
         )),
     });
@@ -293,7 +312,10 @@ fn test_older_hlc_update_does_not_broadcast() {
                         value: Some(proto::triple_value::Value::String("newer".to_string())),
                     }),
                     hlc: Some(new_hlc(10)),
+                    attribute_name: None,
                 }],
+                mode: proto::WriteMode::Upsert.into(),
+                expected_versions: vec![],
             },
         )),
     });
@@ -314,7 +336,10 @@ fn test_older_hlc_update_does_not_broadcast() {
                         value: Some(proto::triple_value::Value::String("older".to_string())),
                     }),
                     hlc: Some(new_hlc(5)), // Older than 10
+                    attribute_name: None,
                 }],
+                mode: proto::WriteMode::Upsert.into(),
+                expected_versions: vec![],
             },
         )),
     });
@@ -355,7 +380,10 @@ fn test_insert_boolean_broadcasts_correctly() {
                         value: Some(proto::triple_value::Value::Boolean(true)),
                     }),
                     hlc: Some(new_hlc(1)),
+                    attribute_name: None,
                 }],
+                mode: proto::WriteMode::Upsert.into(),
+                expected_versions: vec![],
             },
         )),
     });
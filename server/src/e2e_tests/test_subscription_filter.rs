@@ -0,0 +1,169 @@
+//! End-to-end tests for subscriptions filtered by `entity_id` and/or
+//! `attribute_id`.
+//!
+//! A filtered subscription receives only the changes to matching triples,
+//! both live and in its `since_hlc` backfill. Like the WebSocket handler,
+//! these tests turn each broadcast notification into per-subscription updates
+//! with `ClientConnection::updates_for`.
+
+use crate::e2e_tests::helpers::{
+    SiblingClient, TestClient, drain_updates, insert_seeded, is_ok, new_attribute_id,
+    new_entity_id, new_hlc,
+};
+use crate::proto;
+
+/// Subscribe with the given filter and return every message sent back.
+fn subscribe_filtered(
+    sibling: &mut SiblingClient,
+    subscription_id: u32,
+    since_hlc: Option<proto::HlcTimestamp>,
+    entity_id: Option<&[u8]>,
+    attribute_id: Option<&[u8]>,
+) -> Vec<proto::ServerMessage> {
+    sibling.client.handle_message(proto::ClientMessage {
+        request_id: Some(subscription_id),
+        payload: Some(proto::client_message::Payload::Subscribe(
+            proto::SubscribeRequest {
+                subscription_id,
+                since_hlc,
+                query: None,
+                watch_entity_id: None,
+                until_hlc: None,
+                include_old_values: false,
+                entity_id: entity_id.map(<[u8]>::to_vec),
+                attribute_id: attribute_id.map(<[u8]>::to_vec),
+            },
+        )),
+    })
+}
+
+/// The (entity, attribute) IDs of each change in an update.
+fn change_ids(update: &proto::SubscriptionUpdate) -> Vec<(Vec<u8>, Vec<u8>)> {
+    update
+        .changes
+        .iter()
+        .map(|change| {
+            let triple = change
+                .triple
+                .as_ref()
+                .expect("change should carry a triple");
+            (
+                triple.entity_id.clone().unwrap_or_default(),
+                triple.attribute_id.clone().unwrap_or_default(),
+            )
+        })
+        .collect()
+}
+
+#[test]
+fn test_entity_filter_receives_only_matching_changes() {
+    let mut client = TestClient::new();
+    let mut sibling = client.create_sibling();
+    let mut change_rx = sibling.subscribe_to_changes();
+    let watched = new_entity_id(1);
+    let other = new_entity_id(2);
+    let attribute_id = new_attribute_id(1);
+
+    let messages = subscribe_filtered(&mut sibling, 1, None, Some(&watched), None);
+    assert_eq!(messages.len(), 1, "nothing to backfill without since_hlc");
+
+    insert_seeded(&mut client, watched, attribute_id, 1);
+    insert_seeded(&mut client, other, attribute_id, 2);
+    insert_seeded(&mut client, watched, new_attribute_id(2), 3);
+    insert_seeded(&mut client, other, new_attribute_id(2), 4);
+
+    let updates = drain_updates(&sibling, &mut change_rx);
+    assert!(updates.iter().all(|update| update.subscription_id == 1));
+    let ids: Vec<_> = updates.iter().flat_map(change_ids).collect();
+    assert_eq!(
+        ids,
+        vec![
+            (watched.to_vec(), attribute_id.to_vec()),
+            (watched.to_vec(), new_attribute_id(2).to_vec()),
+        ]
+    );
+}
+
+#[test]
+fn test_entity_and_attribute_filter_must_both_match() {
+    let mut client = TestClient::new();
+    let mut sibling = client.create_sibling();
+    let mut change_rx = sibling.subscribe_to_changes();
+    let entity_id = new_entity_id(3);
+    let attribute_id = new_attribute_id(3);
+
+    subscribe_filtered(&mut sibling, 1, None, Some(&entity_id), Some(&attribute_id));
+
+    insert_seeded(&mut client, entity_id, new_attribute_id(4), 1);
+    insert_seeded(&mut client, new_entity_id(4), attribute_id, 2);
+    assert!(drain_updates(&sibling, &mut change_rx).is_empty());
+
+    insert_seeded(&mut client, entity_id, attribute_id, 3);
+    let updates = drain_updates(&sibling, &mut change_rx);
+    assert_eq!(updates.len(), 1);
+    assert_eq!(
+        change_ids(&updates[0]),
+        vec![(entity_id.to_vec(), attribute_id.to_vec())]
+    );
+}
+
+#[test]
+fn test_attribute_filter_applies_to_backfill() {
+    let mut client = TestClient::new();
+    let mut sibling = client.create_sibling();
+    let attribute_id = new_attribute_id(5);
+
+    insert_seeded(&mut client, new_entity_id(5), attribute_id, 1);
+    insert_seeded(&mut client, new_entity_id(5), new_attribute_id(6), 2);
+    insert_seeded(&mut client, new_entity_id(6), attribute_id, 3);
+
+    let messages = subscribe_filtered(&mut sibling, 1, Some(new_hlc(0)), None, Some(&attribute_id));
+    assert_eq!(messages.len(), 2, "backfill, then OK");
+    let Some(proto::server_message::Payload::SubscriptionUpdate(update)) = &messages[0].payload
+    else {
+        panic!("expected SubscriptionUpdate, got {:?}", messages[0].payload);
+    };
+    assert_eq!(
+        change_ids(update),
+        vec![
+            (new_entity_id(5).to_vec(), attribute_id.to_vec()),
+            (new_entity_id(6).to_vec(), attribute_id.to_vec()),
+        ]
+    );
+}
+
+#[test]
+fn test_filtered_subscription_rejects_invalid_requests() {
+    let client = TestClient::new();
+    let mut sibling = client.create_sibling();
+
+    let short_entity = subscribe_filtered(&mut sibling, 1, None, Some(&[1, 2, 3]), None);
+    let short_attribute = subscribe_filtered(&mut sibling, 2, None, None, Some(&[1, 2, 3]));
+    let with_query = sibling.client.handle_message(proto::ClientMessage {
+        request_id: Some(3),
+        payload: Some(proto::client_message::Payload::Subscribe(
+            proto::SubscribeRequest {
+                subscription_id: 3,
+                since_hlc: None,
+                query: Some(proto::QueryRequest::default()),
+                watch_entity_id: None,
+                until_hlc: None,
+                include_old_values: false,
+                entity_id: Some(new_entity_id(7).to_vec()),
+                attribute_id: None,
+            },
+        )),
+    });
+
+    for message in short_entity
+        .iter()
+        .chain(&short_attribute)
+        .chain(&with_query)
+    {
+        let Some(proto::server_message::Payload::Response(response)) = &message.payload else {
+            panic!("expected a response, got {:?}", message.payload);
+        };
+        assert!(!is_ok(response));
+    }
+    assert_eq!(sibling.client.subscriptions().count(), 0);
+}
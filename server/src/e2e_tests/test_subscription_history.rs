@@ -0,0 +1,127 @@
+//! End-to-end tests for subscriptions backfilled with a bounded slice of
+//! history, using `since_hlc` together with `until_hlc`.
+
+use crate::e2e_tests::helpers::{TestClient, is_ok, new_attribute_id, new_entity_id, new_hlc};
+use crate::proto;
+
+/// Insert one string triple per seed, each in its own transaction at `new_hlc(seed)`.
+fn insert_history(client: &mut TestClient, seeds: std::ops::RangeInclusive<u8>) {
+    for seed in seeds {
+        let response = client.handle_message(proto::ClientMessage {
+            request_id: Some(1),
+            payload: Some(proto::client_message::Payload::TripleUpdateRequest(
+                proto::TripleUpdateRequest {
+                    triples: vec![proto::Triple {
+                        entity_id: Some(new_entity_id(seed).to_vec()),
+                        attribute_id: Some(new_attribute_id(1).to_vec()),
+                        value: Some(proto::TripleValue {
+                            value: Some(proto::triple_value::Value::String(format!("v{seed}"))),
+                        }),
+                        hlc: Some(new_hlc(u64::from(seed))),
+                        attribute_name: None,
+                    }],
+                    mode: proto::WriteMode::Upsert.into(),
+                    expected_versions: vec![],
+                },
+            )),
+        });
+        assert!(is_ok(&response));
+    }
+}
+
+/// Subscribe with the given HLC bounds and return every message sent back.
+fn subscribe(
+    client: &mut TestClient,
+    subscription_id: u32,
+    since_hlc: Option<proto::HlcTimestamp>,
+    until_hlc: Option<proto::HlcTimestamp>,
+) -> Vec<proto::ServerMessage> {
+    client.client.handle_message(proto::ClientMessage {
+        request_id: Some(subscription_id),
+        payload: Some(proto::client_message::Payload::Subscribe(
+            proto::SubscribeRequest {
+                subscription_id,
+                since_hlc,
+                query: None,
+                watch_entity_id: None,
+                until_hlc,
+                include_old_values: false,
+                entity_id: None,
+                attribute_id: None,
+            },
+        )),
+    })
+}
+
+/// The physical times of the changes in a subscription update message.
+fn change_times(message: &proto::ServerMessage) -> Vec<u64> {
+    let Some(proto::server_message::Payload::SubscriptionUpdate(update)) = &message.payload else {
+        panic!("expected a subscription update, got {message:?}");
+    };
+    update
+        .changes
+        .iter()
+        .filter_map(|change| change.triple.as_ref()?.hlc.as_ref())
+        .map(|hlc| hlc.physical_time_ms)
+        .collect()
+}
+
+fn is_ok_message(message: &proto::ServerMessage) -> bool {
+    matches!(
+        &message.payload,
+        Some(proto::server_message::Payload::Response(response)) if is_ok(response)
+    )
+}
+
+#[test]
+fn test_subscribe_backfills_bounded_history() {
+    let mut client = TestClient::new();
+    insert_history(&mut client, 1..=4);
+
+    let messages = subscribe(&mut client, 1, Some(new_hlc(2)), Some(new_hlc(3)));
+
+    assert_eq!(messages.len(), 2);
+    assert_eq!(change_times(&messages[0]), vec![2000, 3000]);
+    assert!(is_ok_message(&messages[1]));
+}
+
+#[test]
+fn test_subscribe_with_empty_history_window_sends_no_backfill() {
+    let mut client = TestClient::new();
+    insert_history(&mut client, 1..=2);
+
+    // Between the two writes
+    let messages = subscribe(
+        &mut client,
+        1,
+        Some(proto::HlcTimestamp {
+            physical_time_ms: 1001,
+            logical_counter: 0,
+            node_id: 1,
+        }),
+        Some(proto::HlcTimestamp {
+            physical_time_ms: 1999,
+            logical_counter: 0,
+            node_id: 1,
+        }),
+    );
+    assert_eq!(messages.len(), 1);
+    assert!(is_ok_message(&messages[0]));
+
+    // Ending before it starts
+    let messages = subscribe(&mut client, 2, Some(new_hlc(2)), Some(new_hlc(1)));
+    assert_eq!(messages.len(), 1);
+    assert!(is_ok_message(&messages[0]));
+}
+
+#[test]
+fn test_subscribe_rejects_until_hlc_without_since_hlc() {
+    let mut client = TestClient::new();
+
+    let messages = subscribe(&mut client, 1, None, Some(new_hlc(1)));
+
+    assert_eq!(messages.len(), 1);
+    assert!(!is_ok_message(&messages[0]));
+    // The subscription was not registered
+    assert_eq!(client.client.subscriptions().count(), 0);
+}
@@ -41,7 +41,10 @@ fn test_sibling_connection_receives_notification() {
                         )),
                     }),
                     hlc: Some(new_hlc(1)),
+                    attribute_name: None,
                 }],
+                mode: proto::WriteMode::Upsert.into(),
+                expected_versions: vec![],
             },
         )),
     });
@@ -90,7 +93,10 @@ fn test_bidirectional_notifications() {
                         )),
                     }),
                     hlc: Some(new_hlc(1)),
+                    attribute_name: None,
                 }],
+                mode: proto::WriteMode::Upsert.into(),
+                expected_versions: vec![],
             },
         )),
     });
@@ -123,7 +129,10 @@ fn test_bidirectional_notifications() {
                         )),
                     }),
                     hlc: Some(new_hlc(2)),
+                    attribute_name: None,
                 }],
+                mode: proto::WriteMode::Upsert.into(),
+                expected_versions: vec![],
             },
         )),
     });
@@ -174,7 +183,10 @@ fn test_multiple_siblings_all_receive_notifications() {
                         )),
                     }),
                     hlc: Some(new_hlc(1)),
+                    attribute_name: None,
                 }],
+                mode: proto::WriteMode::Upsert.into(),
+                expected_versions: vec![],
             },
         )),
     });
@@ -232,7 +244,10 @@ fn test_late_sibling_subscriber() {
                         value: Some(proto::triple_value::Value::String("before".to_string())),
                     }),
                     hlc: Some(new_hlc(1)),
+                    attribute_name: None,
                 }],
+                mode: proto::WriteMode::Upsert.into(),
+                expected_versions: vec![],
             },
         )),
     });
@@ -266,7 +281,10 @@ fn test_late_sibling_subscriber() {
                         value: Some(proto::triple_value::Value::String("after".to_string())),
                     }),
                     hlc: Some(new_hlc(2)),
+                    attribute_name: None,
                 }],
+                mode: proto::WriteMode::Upsert.into(),
+                expected_versions: vec![],
             },
         )),
     });
@@ -321,7 +339,10 @@ fn test_notification_includes_source_connection_id() {
                         value: Some(proto::triple_value::Value::String("test".to_string())),
                     }),
                     hlc: Some(new_hlc(1)),
+                    attribute_name: None,
                 }],
+                mode: proto::WriteMode::Upsert.into(),
+                expected_versions: vec![],
             },
         )),
     });
@@ -372,7 +393,10 @@ fn test_filter_own_writes() {
                         )),
                     }),
                     hlc: Some(new_hlc(1)),
+                    attribute_name: None,
                 }],
+                mode: proto::WriteMode::Upsert.into(),
+                expected_versions: vec![],
             },
         )),
     });
@@ -393,7 +417,10 @@ fn test_filter_own_writes() {
                         )),
                     }),
                     hlc: Some(new_hlc(2)),
+                    attribute_name: None,
                 }],
+                mode: proto::WriteMode::Upsert.into(),
+                expected_versions: vec![],
             },
         )),
     });
@@ -437,3 +464,78 @@ fn test_filter_own_writes() {
         Some(TripleValue::String("from client1".to_string()))
     );
 }
+
+/// Test that updates from several writers arrive in commit order.
+///
+/// The writers' HLCs run backwards, so only each update's `commit_hlc`
+/// orders the commits. It increases strictly, giving the subscriber a single
+/// watermark.
+#[test]
+fn test_updates_carry_commit_hlc_in_commit_order() {
+    let mut writer1 = TestClient::new();
+    let mut writer2 = writer1.create_sibling();
+    let mut subscriber = writer1.create_sibling();
+    let mut rx = subscriber.subscribe_to_changes();
+    let response = subscriber.handle_message(proto::ClientMessage {
+        request_id: Some(1),
+        payload: Some(proto::client_message::Payload::Subscribe(
+            proto::SubscribeRequest {
+                subscription_id: 1,
+                since_hlc: None,
+                query: None,
+                watch_entity_id: None,
+                until_hlc: None,
+                include_old_values: false,
+                entity_id: None,
+                attribute_id: None,
+            },
+        )),
+    });
+    assert!(is_ok(&response));
+
+    let attribute_id = new_attribute_id(252);
+    for seed in 0..10u8 {
+        let message = proto::ClientMessage {
+            request_id: Some(u32::from(seed) + 2),
+            payload: Some(proto::client_message::Payload::TripleUpdateRequest(
+                proto::TripleUpdateRequest {
+                    triples: vec![proto::Triple {
+                        entity_id: Some(new_entity_id(seed).to_vec()),
+                        attribute_id: Some(attribute_id.to_vec()),
+                        value: Some(proto::TripleValue {
+                            value: Some(proto::triple_value::Value::Number(f64::from(seed))),
+                        }),
+                        hlc: Some(new_hlc(100 - u64::from(seed))),
+                        attribute_name: None,
+                    }],
+                    mode: proto::WriteMode::Upsert.into(),
+                    expected_versions: vec![],
+                },
+            )),
+        };
+        let response = if seed % 2 == 0 {
+            writer1.handle_message(message)
+        } else {
+            writer2.handle_message(message)
+        };
+        assert!(is_ok(&response));
+    }
+
+    let mut updates = Vec::new();
+    while let Ok(notification) = rx.try_recv() {
+        updates.extend(subscriber.client.updates_for(&notification));
+    }
+    assert_eq!(updates.len(), 10);
+    let mut last_commit_hlc = None;
+    for (seed, update) in (0..10u8).zip(&updates) {
+        let entity_id = update.changes[0]
+            .triple
+            .as_ref()
+            .and_then(|triple| triple.entity_id.as_deref());
+        assert_eq!(entity_id, Some(&new_entity_id(seed)[..]));
+        let commit_hlc = update.commit_hlc.expect("live update has a commit HLC");
+        let commit_hlc = (commit_hlc.physical_time_ms, commit_hlc.logical_counter);
+        assert!(last_commit_hlc < Some(commit_hlc), "commit HLCs increase");
+        last_commit_hlc = Some(commit_hlc);
+    }
+}
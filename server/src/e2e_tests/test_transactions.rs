@@ -0,0 +1,184 @@
+//! Test explicit transactions: `BeginTxnRequest`, `CommitTxnRequest` and
+//! `AbortTxnRequest` spanning several update messages.
+
+use std::thread::sleep;
+use std::time::Duration;
+
+use crate::e2e_tests::helpers::{
+    NAME, TestClient, entity_count, is_ok, name_triple, new_attribute_id, new_entity_id, new_hlc,
+    send,
+};
+use crate::proto;
+use crate::types::error_detail::error_reason;
+
+/// Begin a transaction with the given idle timeout.
+fn begin(client: &mut TestClient, timeout_ms: u32) -> proto::ServerResponse {
+    send(
+        client,
+        proto::client_message::Payload::BeginTxn(proto::BeginTxnRequest { timeout_ms }),
+    )
+}
+
+/// Commit the open transaction.
+fn commit(client: &mut TestClient) -> proto::ServerResponse {
+    send(
+        client,
+        proto::client_message::Payload::CommitTxn(proto::CommitTxnRequest {}),
+    )
+}
+
+/// Abort the open transaction.
+fn abort(client: &mut TestClient) -> proto::ServerResponse {
+    send(
+        client,
+        proto::client_message::Payload::AbortTxn(proto::AbortTxnRequest {}),
+    )
+}
+
+/// Write an entity's name.
+fn write_name(client: &mut TestClient, entity: u8, name: &str) -> proto::ServerResponse {
+    send(
+        client,
+        proto::client_message::Payload::TripleUpdateRequest(proto::TripleUpdateRequest {
+            triples: vec![name_triple(entity, name)],
+            mode: proto::WriteMode::Upsert.into(),
+            expected_versions: vec![],
+        }),
+    )
+}
+
+/// The status code of a response.
+fn code(response: &proto::ServerResponse) -> Option<proto::google::rpc::Code> {
+    response
+        .status
+        .as_ref()
+        .and_then(|status| proto::google::rpc::Code::try_from(status.code).ok())
+}
+
+#[test]
+fn test_commit_writes_every_buffered_update() {
+    let mut client = TestClient::new();
+    assert!(is_ok(&begin(&mut client, 0)));
+
+    assert!(is_ok(&write_name(&mut client, 1, "Ada")));
+    assert!(is_ok(&write_name(&mut client, 2, "Grace")));
+    // Buffered writes are not visible until the commit
+    assert_eq!(entity_count(&mut client, new_attribute_id(NAME)), 0);
+
+    let response = commit(&mut client);
+
+    assert!(is_ok(&response));
+    assert_eq!(response.write_results.len(), 2);
+    assert!(
+        response
+            .write_results
+            .iter()
+            .all(|result| result.change_type == i32::from(proto::ChangeType::Insert))
+    );
+    assert_eq!(entity_count(&mut client, new_attribute_id(NAME)), 2);
+}
+
+#[test]
+fn test_abort_discards_buffered_updates() {
+    let mut client = TestClient::new();
+    assert!(is_ok(&begin(&mut client, 0)));
+    assert!(is_ok(&write_name(&mut client, 1, "Ada")));
+
+    assert!(is_ok(&abort(&mut client)));
+
+    assert_eq!(entity_count(&mut client, new_attribute_id(NAME)), 0);
+    assert_eq!(
+        code(&commit(&mut client)),
+        Some(proto::google::rpc::Code::FailedPrecondition)
+    );
+    // Later updates commit on their own again
+    assert!(is_ok(&write_name(&mut client, 1, "Ada")));
+    assert_eq!(entity_count(&mut client, new_attribute_id(NAME)), 1);
+}
+
+#[test]
+fn test_idle_transaction_is_aborted() {
+    let mut client = TestClient::new();
+    assert!(is_ok(&begin(&mut client, 20)));
+    assert!(is_ok(&write_name(&mut client, 1, "Ada")));
+
+    sleep(Duration::from_millis(50));
+
+    // The timed-out transaction rejects writes until it is ended
+    assert_eq!(
+        code(&write_name(&mut client, 2, "Grace")),
+        Some(proto::google::rpc::Code::Aborted)
+    );
+    assert_eq!(
+        code(&commit(&mut client)),
+        Some(proto::google::rpc::Code::Aborted)
+    );
+    assert_eq!(entity_count(&mut client, new_attribute_id(NAME)), 0);
+}
+
+#[test]
+fn test_conflicting_commit_is_aborted() {
+    let mut client = TestClient::new();
+    let mut other = client.create_sibling();
+    assert!(is_ok(&begin(&mut client, 0)));
+    assert!(is_ok(&write_name(&mut client, 1, "Ada")));
+
+    // Another connection writes the same triple before the commit
+    let response = other.handle_message(proto::ClientMessage {
+        request_id: Some(1),
+        payload: Some(proto::client_message::Payload::TripleUpdateRequest(
+            proto::TripleUpdateRequest {
+                triples: vec![proto::Triple {
+                    entity_id: Some(new_entity_id(1).to_vec()),
+                    attribute_id: Some(new_attribute_id(NAME).to_vec()),
+                    value: Some(proto::TripleValue {
+                        value: Some(proto::triple_value::Value::String("Grace".to_string())),
+                    }),
+                    hlc: Some(new_hlc(2)),
+                    attribute_name: None,
+                }],
+                mode: proto::WriteMode::Upsert.into(),
+                expected_versions: vec![],
+            },
+        )),
+    });
+    assert!(is_ok(&response));
+
+    let response = commit(&mut client);
+
+    assert_eq!(code(&response), Some(proto::google::rpc::Code::Aborted));
+    assert_eq!(
+        response.status.as_ref().and_then(error_reason),
+        Some(proto::error_detail::Reason::WriteConflict(
+            proto::WriteConflict {
+                entity_id: new_entity_id(1).to_vec(),
+                attribute_id: new_attribute_id(NAME).to_vec(),
+            }
+        ))
+    );
+}
+
+#[test]
+fn test_transaction_rejects_nested_begin_and_deletes() {
+    let mut client = TestClient::new();
+    assert!(is_ok(&begin(&mut client, 0)));
+
+    assert_eq!(
+        code(&begin(&mut client, 0)),
+        Some(proto::google::rpc::Code::FailedPrecondition)
+    );
+    let response = send(
+        &mut client,
+        proto::client_message::Payload::Delete(proto::DeleteRequest {
+            keys: vec![proto::TripleKey {
+                entity_id: Some(new_entity_id(1).to_vec()),
+                attribute_id: Some(new_attribute_id(NAME).to_vec()),
+            }],
+        }),
+    );
+    assert_eq!(
+        code(&response),
+        Some(proto::google::rpc::Code::FailedPrecondition)
+    );
+    assert!(is_ok(&abort(&mut client)));
+}
@@ -24,7 +24,10 @@ fn test_update_changes_value_type() {
                         value: Some(proto::triple_value::Value::String("text".to_string())),
                     }),
                     hlc: Some(new_hlc(1)),
+                    attribute_name: None,
                 }],
+                mode: proto::WriteMode::Upsert.into(),
+                expected_versions: vec![],
             },
         )),
     });
@@ -42,7 +45,10 @@ fn test_update_changes_value_type() {
                         value: Some(proto::triple_value::Value::Number(123.0)),
                     }),
                     hlc: Some(new_hlc(2)),
+                    attribute_name: None,
                 }],
+                mode: proto::WriteMode::Upsert.into(),
+                expected_versions: vec![],
             },
         )),
     });
@@ -68,6 +74,13 @@ fn test_update_changes_value_type() {
             }],
             optional: vec![],
             where_not: vec![],
+            arrow_ipc: false,
+            limit: None,
+            offset: None,
+            order_by: vec![],
+            aggregates: vec![],
+            group_by: vec![],
+            distinct: false,
         })),
     });
     assert!(is_ok(&query_response));
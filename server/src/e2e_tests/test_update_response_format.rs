@@ -25,7 +25,10 @@ fn test_insert_response_returns_written_triples() {
                         value: Some(proto::triple_value::Value::String("hello".to_string())),
                     }),
                     hlc: Some(new_hlc(1)),
+                    attribute_name: None,
                 }],
+                mode: proto::WriteMode::Upsert.into(),
+                expected_versions: vec![],
             },
         )),
     });
@@ -72,7 +75,10 @@ fn test_update_response_returns_current_value() {
                         value: Some(proto::triple_value::Value::String("original".to_string())),
                     }),
                     hlc: Some(new_hlc(1)),
+                    attribute_name: None,
                 }],
+                mode: proto::WriteMode::Upsert.into(),
+                expected_versions: vec![],
             },
         )),
     });
@@ -91,7 +97,10 @@ fn test_update_response_returns_current_value() {
                         value: Some(proto::triple_value::Value::String("updated".to_string())),
                     }),
                     hlc: Some(new_hlc(2)),
+                    attribute_name: None,
                 }],
+                mode: proto::WriteMode::Upsert.into(),
+                expected_versions: vec![],
             },
         )),
     });
@@ -136,6 +145,7 @@ fn test_multi_triple_update_returns_all_values() {
                             value: Some(proto::triple_value::Value::String("value1".to_string())),
                         }),
                         hlc: Some(new_hlc(1)),
+                        attribute_name: None,
                     },
                     proto::Triple {
                         entity_id: Some(new_entity_id(83).to_vec()),
@@ -144,6 +154,7 @@ fn test_multi_triple_update_returns_all_values() {
                             value: Some(proto::triple_value::Value::Number(42.0)),
                         }),
                         hlc: Some(new_hlc(2)),
+                        attribute_name: None,
                     },
                     proto::Triple {
                         entity_id: Some(new_entity_id(84).to_vec()),
@@ -152,8 +163,11 @@ fn test_multi_triple_update_returns_all_values() {
                             value: Some(proto::triple_value::Value::Boolean(true)),
                         }),
                         hlc: Some(new_hlc(3)),
+                        attribute_name: None,
                     },
                 ],
+                mode: proto::WriteMode::Upsert.into(),
+                expected_versions: vec![],
             },
         )),
     });
@@ -199,7 +213,11 @@ fn test_empty_update_returns_no_triples() {
     let response = client.handle_message(proto::ClientMessage {
         request_id: Some(1),
         payload: Some(proto::client_message::Payload::TripleUpdateRequest(
-            proto::TripleUpdateRequest { triples: vec![] },
+            proto::TripleUpdateRequest {
+                triples: vec![],
+                mode: proto::WriteMode::Upsert.into(),
+                expected_versions: vec![],
+            },
         )),
     });
 
@@ -212,3 +230,122 @@ fn test_empty_update_returns_no_triples() {
         "Empty update response should contain no triples"
     );
 }
+
+/// Write one string triple and return the response.
+fn write_string(
+    client: &mut TestClient,
+    entity_id: [u8; 16],
+    attribute_id: [u8; 16],
+    value: &str,
+    seed: u64,
+) -> proto::ServerResponse {
+    client.handle_message(proto::ClientMessage {
+        request_id: Some(1),
+        payload: Some(proto::client_message::Payload::TripleUpdateRequest(
+            proto::TripleUpdateRequest {
+                triples: vec![proto::Triple {
+                    entity_id: Some(entity_id.to_vec()),
+                    attribute_id: Some(attribute_id.to_vec()),
+                    value: Some(proto::TripleValue {
+                        value: Some(proto::triple_value::Value::String(value.to_string())),
+                    }),
+                    hlc: Some(new_hlc(seed)),
+                    attribute_name: None,
+                }],
+                mode: proto::WriteMode::Upsert.into(),
+                expected_versions: vec![],
+            },
+        )),
+    })
+}
+
+#[test]
+fn test_write_results_report_insert_and_update() {
+    let mut client = TestClient::new();
+    let entity_id = new_entity_id(84);
+    let attribute_id = new_attribute_id(84);
+
+    let response = write_string(&mut client, entity_id, attribute_id, "first", 1);
+    assert!(is_ok(&response));
+    assert_eq!(response.write_results.len(), 1);
+    let result = &response.write_results[0];
+    assert_eq!(result.entity_id, entity_id.to_vec());
+    assert_eq!(result.attribute_id, attribute_id.to_vec());
+    assert_eq!(result.change_type, proto::ChangeType::Insert as i32);
+    assert_eq!(result.hlc, Some(new_hlc(1)));
+
+    let response = write_string(&mut client, entity_id, attribute_id, "second", 2);
+    let result = &response.write_results[0];
+    assert_eq!(result.change_type, proto::ChangeType::Update as i32);
+    assert_eq!(result.hlc, Some(new_hlc(2)));
+}
+
+#[test]
+fn test_write_results_report_stale_and_unchanged_writes() {
+    let mut client = TestClient::new();
+    let entity_id = new_entity_id(85);
+    let attribute_id = new_attribute_id(85);
+    write_string(&mut client, entity_id, attribute_id, "current", 5);
+
+    // An older HLC loses; the result carries the stored HLC
+    let response = write_string(&mut client, entity_id, attribute_id, "stale", 3);
+    assert!(is_ok(&response));
+    let result = &response.write_results[0];
+    assert_eq!(result.change_type, proto::ChangeType::Unspecified as i32);
+    assert_eq!(result.hlc, Some(new_hlc(5)));
+
+    // Rewriting the same value advances the HLC without changing the triple
+    let response = write_string(&mut client, entity_id, attribute_id, "current", 6);
+    let result = &response.write_results[0];
+    assert_eq!(result.change_type, proto::ChangeType::Unspecified as i32);
+    assert_eq!(result.hlc, Some(new_hlc(6)));
+}
+
+#[test]
+fn test_write_results_follow_request_order() {
+    let mut client = TestClient::new();
+    let attribute_id = new_attribute_id(86);
+    let existing = new_entity_id(86);
+    let fresh = new_entity_id(87);
+    write_string(&mut client, existing, attribute_id, "old", 1);
+
+    let triple = |entity_id: [u8; 16], seed: u64| proto::Triple {
+        entity_id: Some(entity_id.to_vec()),
+        attribute_id: Some(attribute_id.to_vec()),
+        value: Some(proto::TripleValue {
+            value: Some(proto::triple_value::Value::Number(1.0)),
+        }),
+        hlc: Some(new_hlc(seed)),
+        attribute_name: None,
+    };
+    let response = client.handle_message(proto::ClientMessage {
+        request_id: Some(2),
+        payload: Some(proto::client_message::Payload::TripleUpdateRequest(
+            proto::TripleUpdateRequest {
+                triples: vec![triple(fresh, 2), triple(existing, 3)],
+                mode: proto::WriteMode::Upsert.into(),
+                expected_versions: vec![],
+            },
+        )),
+    });
+
+    assert!(is_ok(&response));
+    let entities: Vec<&[u8]> = response
+        .write_results
+        .iter()
+        .map(|result| result.entity_id.as_slice())
+        .collect();
+    assert_eq!(entities, vec![&fresh[..], &existing[..]]);
+    let change_types: Vec<i32> = response
+        .write_results
+        .iter()
+        .map(|result| result.change_type)
+        .collect();
+    assert_eq!(
+        change_types,
+        vec![
+            proto::ChangeType::Insert as i32,
+            proto::ChangeType::Update as i32
+        ]
+    );
+}
@@ -0,0 +1,317 @@
+//! Test the create-only and update-only write modes of `TripleUpdateRequest`.
+
+use crate::e2e_tests::helpers::{
+    TestClient, get_string_value, is_ok, new_attribute_id, new_entity_id, status_code,
+    status_message, string_triple,
+};
+use crate::proto;
+
+fn write(
+    client: &mut TestClient,
+    mode: proto::WriteMode,
+    triples: Vec<proto::Triple>,
+) -> proto::ServerResponse {
+    client.handle_message(proto::ClientMessage {
+        request_id: Some(1),
+        payload: Some(proto::client_message::Payload::TripleUpdateRequest(
+            proto::TripleUpdateRequest {
+                triples,
+                mode: mode.into(),
+                expected_versions: vec![],
+            },
+        )),
+    })
+}
+
+/// The stored string value of a triple, if any.
+fn stored_value(
+    client: &mut TestClient,
+    entity_id: [u8; 16],
+    attribute_id: [u8; 16],
+) -> Option<String> {
+    let response = client.handle_message(proto::ClientMessage {
+        request_id: Some(2),
+        payload: Some(proto::client_message::Payload::Query(proto::QueryRequest {
+            find: vec![proto::QueryPatternVariable {
+                label: Some("value".to_string()),
+            }],
+            r#where: vec![proto::QueryPattern {
+                entity: Some(proto::query_pattern::Entity::EntityId(entity_id.to_vec())),
+                attribute: Some(proto::query_pattern::Attribute::AttributeId(
+                    attribute_id.to_vec(),
+                )),
+                value_group: Some(proto::query_pattern::ValueGroup::ValueVariable(
+                    proto::QueryPatternVariable {
+                        label: Some("value".to_string()),
+                    },
+                )),
+            }],
+            optional: vec![],
+            where_not: vec![],
+            arrow_ipc: false,
+            limit: None,
+            offset: None,
+            order_by: vec![],
+            aggregates: vec![],
+            group_by: vec![],
+            distinct: false,
+        })),
+    });
+    assert!(is_ok(&response));
+    get_string_value(&response, 0).map(str::to_owned)
+}
+
+#[test]
+fn test_upsert_inserts_and_overwrites() {
+    let mut client = TestClient::new();
+    let (entity_id, attribute_id) = (new_entity_id(1), new_attribute_id(1));
+
+    let response = write(
+        &mut client,
+        proto::WriteMode::Upsert,
+        vec![string_triple(entity_id, attribute_id, "first", 1)],
+    );
+    assert!(is_ok(&response));
+    assert_eq!(
+        response.write_results[0].change_type,
+        proto::ChangeType::Insert as i32
+    );
+
+    let response = write(
+        &mut client,
+        proto::WriteMode::Upsert,
+        vec![string_triple(entity_id, attribute_id, "second", 2)],
+    );
+    assert!(is_ok(&response));
+    assert_eq!(
+        response.write_results[0].change_type,
+        proto::ChangeType::Update as i32
+    );
+    assert_eq!(
+        stored_value(&mut client, entity_id, attribute_id).as_deref(),
+        Some("second")
+    );
+}
+
+#[test]
+fn test_create_only_inserts_absent_triple() {
+    let mut client = TestClient::new();
+    let (entity_id, attribute_id) = (new_entity_id(1), new_attribute_id(1));
+
+    let response = write(
+        &mut client,
+        proto::WriteMode::CreateOnly,
+        vec![string_triple(entity_id, attribute_id, "created", 1)],
+    );
+
+    assert!(is_ok(&response));
+    assert_eq!(
+        response.write_results[0].change_type,
+        proto::ChangeType::Insert as i32
+    );
+    assert_eq!(
+        stored_value(&mut client, entity_id, attribute_id).as_deref(),
+        Some("created")
+    );
+}
+
+#[test]
+fn test_create_only_rejects_present_triple() {
+    let mut client = TestClient::new();
+    let entity_id = new_entity_id(1);
+    let (name_attr, email_attr) = (new_attribute_id(1), new_attribute_id(2));
+    assert!(is_ok(&write(
+        &mut client,
+        proto::WriteMode::Upsert,
+        vec![string_triple(entity_id, name_attr, "original", 1)],
+    )));
+
+    // The absent email triple is not written either
+    let response = write(
+        &mut client,
+        proto::WriteMode::CreateOnly,
+        vec![
+            string_triple(entity_id, email_attr, "a@example.com", 2),
+            string_triple(entity_id, name_attr, "clobbered", 2),
+        ],
+    );
+
+    assert_eq!(
+        status_code(&response),
+        proto::google::rpc::Code::AlreadyExists as i32
+    );
+    assert_eq!(status_message(&response), "Triple #1 already exists");
+    assert_eq!(
+        stored_value(&mut client, entity_id, name_attr).as_deref(),
+        Some("original")
+    );
+    assert_eq!(stored_value(&mut client, entity_id, email_attr), None);
+}
+
+#[test]
+fn test_create_only_rejects_duplicate_triples() {
+    let mut client = TestClient::new();
+    let (entity_id, attribute_id) = (new_entity_id(1), new_attribute_id(1));
+
+    let response = write(
+        &mut client,
+        proto::WriteMode::CreateOnly,
+        vec![
+            string_triple(entity_id, attribute_id, "first", 1),
+            string_triple(entity_id, attribute_id, "second", 2),
+        ],
+    );
+
+    assert_eq!(
+        status_code(&response),
+        proto::google::rpc::Code::AlreadyExists as i32
+    );
+    assert_eq!(stored_value(&mut client, entity_id, attribute_id), None);
+}
+
+#[test]
+fn test_update_only_overwrites_present_triple() {
+    let mut client = TestClient::new();
+    let (entity_id, attribute_id) = (new_entity_id(1), new_attribute_id(1));
+    assert!(is_ok(&write(
+        &mut client,
+        proto::WriteMode::Upsert,
+        vec![string_triple(entity_id, attribute_id, "original", 1)],
+    )));
+
+    let response = write(
+        &mut client,
+        proto::WriteMode::UpdateOnly,
+        vec![string_triple(entity_id, attribute_id, "updated", 2)],
+    );
+
+    assert!(is_ok(&response));
+    assert_eq!(
+        response.write_results[0].change_type,
+        proto::ChangeType::Update as i32
+    );
+    assert_eq!(
+        stored_value(&mut client, entity_id, attribute_id).as_deref(),
+        Some("updated")
+    );
+}
+
+#[test]
+fn test_update_only_rejects_absent_triple() {
+    let mut client = TestClient::new();
+    let entity_id = new_entity_id(1);
+    let (name_attr, email_attr) = (new_attribute_id(1), new_attribute_id(2));
+    assert!(is_ok(&write(
+        &mut client,
+        proto::WriteMode::Upsert,
+        vec![string_triple(entity_id, name_attr, "original", 1)],
+    )));
+
+    // The present name triple is not updated either
+    let response = write(
+        &mut client,
+        proto::WriteMode::UpdateOnly,
+        vec![
+            string_triple(entity_id, name_attr, "updated", 2),
+            string_triple(entity_id, email_attr, "a@example.com", 2),
+        ],
+    );
+
+    assert_eq!(
+        status_code(&response),
+        proto::google::rpc::Code::NotFound as i32
+    );
+    assert_eq!(status_message(&response), "Triple #1 not found");
+    assert_eq!(
+        stored_value(&mut client, entity_id, name_attr).as_deref(),
+        Some("original")
+    );
+    assert_eq!(stored_value(&mut client, entity_id, email_attr), None);
+}
+
+#[test]
+fn test_create_only_defaults_fill_only_absent_triples() {
+    let mut client = TestClient::new();
+    let entity_id = new_entity_id(1);
+    let (name_attr, status_attr, role_attr) = (
+        new_attribute_id(1),
+        new_attribute_id(2),
+        new_attribute_id(3),
+    );
+    assert!(is_ok(&write(
+        &mut client,
+        proto::WriteMode::Upsert,
+        vec![string_triple(entity_id, status_attr, "active", 1)],
+    )));
+    let default = |attribute_id: [u8; 16], value: &str| proto::DefaultTriple {
+        attribute_id: Some(attribute_id.to_vec()),
+        value: Some(proto::TripleValue {
+            value: Some(proto::triple_value::Value::String(value.to_string())),
+        }),
+    };
+    let response = client.handle_message(proto::ClientMessage {
+        request_id: Some(3),
+        payload: Some(proto::client_message::Payload::SetDefaults(
+            proto::SetDefaultsRequest {
+                defaults: vec![
+                    default(status_attr, "pending"),
+                    default(role_attr, "member"),
+                ],
+            },
+        )),
+    });
+    assert!(is_ok(&response));
+
+    let response = write(
+        &mut client,
+        proto::WriteMode::CreateOnly,
+        vec![string_triple(entity_id, name_attr, "Alice", 2)],
+    );
+
+    assert!(is_ok(&response));
+    assert_eq!(
+        stored_value(&mut client, entity_id, status_attr).as_deref(),
+        Some("active")
+    );
+    assert_eq!(
+        stored_value(&mut client, entity_id, role_attr).as_deref(),
+        Some("member")
+    );
+
+    // Update-only writes attach no defaults
+    let other = new_entity_id(2);
+    assert!(is_ok(&write(
+        &mut client,
+        proto::WriteMode::Upsert,
+        vec![string_triple(other, name_attr, "Bob", 3)],
+    )));
+    let response = write(
+        &mut client,
+        proto::WriteMode::UpdateOnly,
+        vec![string_triple(other, name_attr, "Robert", 4)],
+    );
+    assert!(is_ok(&response));
+    assert_eq!(response.write_results.len(), 1);
+}
+
+#[test]
+fn test_unknown_write_mode_rejected() {
+    let mut client = TestClient::new();
+
+    let response = client.handle_message(proto::ClientMessage {
+        request_id: Some(1),
+        payload: Some(proto::client_message::Payload::TripleUpdateRequest(
+            proto::TripleUpdateRequest {
+                triples: vec![string_triple(new_entity_id(1), new_attribute_id(1), "x", 1)],
+                mode: 7,
+                expected_versions: vec![],
+            },
+        )),
+    });
+
+    assert_eq!(
+        status_code(&response),
+        proto::google::rpc::Code::InvalidArgument as i32
+    );
+    assert_eq!(status_message(&response), "Unknown write mode: 7");
+}
@@ -3,6 +3,7 @@
 // Test code is allowed to use unwrap() for convenience.
 #![cfg_attr(not(test), deny(clippy::unwrap_used))]
 
+pub mod admin;
 pub mod auth;
 
 // Life of a request:
@@ -22,19 +23,22 @@ pub mod auth;
 //  - Datalog to SQL query engine
 //  - Pub-sub component
 
+pub mod client;
 mod client_connection;
 pub mod config;
 mod constants;
 pub mod database_registry;
 mod e2e_tests;
 pub mod proto;
-mod query;
+pub mod query;
+pub mod rest;
 pub mod simulation;
 pub mod storage;
 pub mod subscription;
 #[cfg(test)]
 mod testing;
 pub mod types;
+pub mod websocket;
 
 pub use client_connection::{ClientConnection, ConnectionState};
 pub use database_registry::DatabaseRegistry;
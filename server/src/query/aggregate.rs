@@ -0,0 +1,286 @@
+//! Aggregate query execution.
+//!
+//! A query with aggregates (see `Query::find_count` and friends) returns a
+//! row per group instead of a row per match. The rows are partitioned into
+//! groups by the values bound to the `Query::group_by` variables; without
+//! any, every row is in one group.
+//!
+//! # Column layout
+//!
+//! A result row holds the group's value of each `group_by` variable, in
+//! order, then the value of each aggregate, in order. Group columns are
+//! named after their variable and aggregate columns like `count(e)`:
+//!
+//! ```text
+//! group_by(status), find_count(e), find_avg(age)
+//!     => status | count(e) | avg(age)
+//! ```
+//!
+//! Groups come in the order their first row is matched, so in canonical
+//! order. A query without `group_by` always returns exactly one row, even if
+//! nothing matched; a grouped query returns no rows then.
+//!
+//! # Design
+//!
+//! The engine evaluates the query one seed at a time, as for an unordered
+//! `execute`, and feeds each matched row to `Groups`, which looks its group
+//! up in a `HashMap` keyed by the serialized group values and adds the row to
+//! that group's `Accumulator`s. Rows are not held, so the aggregates are
+//! computed in a single pass in memory proportional to the number of groups.
+//! Each accumulator keeps only its running state: a count, a sum, or the
+//! extreme value so far.
+//!
+//! Null and unbound group values are grouped together, and yield a null
+//! group column. Null and unbound aggregated values are skipped. Over no
+//! values, `count` and `sum` are 0, and `min`, `max` and `avg` are null.
+
+use std::collections::HashMap;
+
+use super::context::QueryContext;
+use super::engine::row_key;
+use super::executor::QueryError;
+use super::types::{Aggregate, AggregateFunction, Datom, Query, QueryRow, Value, Variable};
+
+/// The groups of an aggregate query's rows, and each group's aggregates.
+pub(super) struct Groups<'a> {
+    query: &'a Query,
+    /// Index into `entries` of each group, by its serialized group values.
+    index: HashMap<Vec<u8>, usize>,
+    /// Each group's values of the `group_by` variables and accumulators, in
+    /// the order the groups were first matched.
+    entries: Vec<(QueryRow, Vec<Accumulator<'a>>)>,
+}
+
+impl<'a> Groups<'a> {
+    /// Create the groups of `query`, before any row has been added.
+    pub(super) fn new(query: &'a Query) -> Self {
+        Self {
+            query,
+            index: HashMap::new(),
+            entries: Vec::new(),
+        }
+    }
+
+    /// Add a matched row to its group.
+    ///
+    /// # Errors
+    ///
+    /// Returns `QueryError::NonNumericAggregate` if a `sum` or `avg` is given
+    /// a value that is not a number.
+    pub(super) fn add(&mut self, ctx: &QueryContext) -> Result<(), QueryError> {
+        let values: Vec<Option<&Datom>> = self
+            .query
+            .group_by
+            .iter()
+            .map(|variable| ctx.get(variable))
+            .collect();
+        let key = row_key(values.iter().copied());
+        let index = if let Some(index) = self.index.get(&key) {
+            *index
+        } else {
+            let group = values
+                .iter()
+                .map(|value| value.map(Datom::clone_value))
+                .collect();
+            self.entries.push((group, self.accumulators()));
+            self.index.insert(key, self.entries.len() - 1);
+            self.entries.len() - 1
+        };
+        for accumulator in &mut self.entries[index].1 {
+            accumulator.add(ctx.get(accumulator.variable()))?;
+        }
+        Ok(())
+    }
+
+    /// The result rows, one per group: its group values, then its
+    /// aggregates.
+    pub(super) fn finish(mut self) -> Vec<QueryRow> {
+        // Without grouping, the aggregates are over all rows, even none
+        if self.query.group_by.is_empty() && self.entries.is_empty() {
+            self.entries.push((Vec::new(), self.accumulators()));
+        }
+        self.entries
+            .into_iter()
+            .map(|(mut row, accumulators)| {
+                row.extend(accumulators.into_iter().map(Accumulator::finish));
+                row
+            })
+            .collect()
+    }
+
+    /// A fresh accumulator for each of the query's aggregates.
+    fn accumulators(&self) -> Vec<Accumulator<'a>> {
+        self.query.aggregates.iter().map(Accumulator::new).collect()
+    }
+}
+
+/// Running state of one aggregate.
+pub(super) struct Accumulator<'a> {
+    aggregate: &'a Aggregate,
+    /// Number of values seen.
+    count: usize,
+    /// Sum of the values seen, for `sum` and `avg`.
+    sum: f64,
+    /// Extreme value seen, for `min` and `max`.
+    extreme: Option<Datom>,
+}
+
+impl<'a> Accumulator<'a> {
+    /// Create an accumulator for `aggregate` that has seen no values.
+    pub(super) const fn new(aggregate: &'a Aggregate) -> Self {
+        Self {
+            aggregate,
+            count: 0,
+            sum: 0.0,
+            extreme: None,
+        }
+    }
+
+    /// The variable whose values are aggregated.
+    pub(super) const fn variable(&self) -> &'a Variable {
+        &self.aggregate.variable
+    }
+
+    /// Add the value a row binds to the aggregated variable.
+    ///
+    /// # Errors
+    ///
+    /// Returns `QueryError::NonNumericAggregate` if a `sum` or `avg` is given
+    /// a value that is not a number.
+    pub(super) fn add(&mut self, datom: Option<&Datom>) -> Result<(), QueryError> {
+        let Some(datom) = datom.filter(|datom| !matches!(datom, Datom::Value(Value::Null))) else {
+            return Ok(());
+        };
+        self.count += 1;
+        match self.aggregate.function {
+            AggregateFunction::Count => {}
+            AggregateFunction::Sum | AggregateFunction::Avg => match datom {
+                Datom::Value(Value::Number(n)) => self.sum += n,
+                _ => {
+                    return Err(QueryError::NonNumericAggregate {
+                        aggregate: self.aggregate.column(),
+                        value: datom.to_string(),
+                    });
+                }
+            },
+            AggregateFunction::Min | AggregateFunction::Max => {
+                let wanted = if self.aggregate.function == AggregateFunction::Min {
+                    std::cmp::Ordering::Less
+                } else {
+                    std::cmp::Ordering::Greater
+                };
+                if self
+                    .extreme
+                    .as_ref()
+                    .is_none_or(|extreme| datom.cmp_total(extreme) == wanted)
+                {
+                    self.extreme = Some(datom.clone_value());
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// The aggregate of the values added, or `None` for null.
+    #[allow(clippy::cast_precision_loss)] // Exact up to 2^53 rows
+    pub(super) fn finish(self) -> Option<Datom> {
+        match self.aggregate.function {
+            AggregateFunction::Count => Some(Datom::number(self.count as f64)),
+            AggregateFunction::Sum => Some(Datom::number(self.sum)),
+            AggregateFunction::Min | AggregateFunction::Max => self.extreme,
+            AggregateFunction::Avg => {
+                (self.count > 0).then(|| Datom::number(self.sum / self.count as f64))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn aggregate(function: AggregateFunction) -> Aggregate {
+        Aggregate {
+            function,
+            variable: Variable::new("v"),
+        }
+    }
+
+    /// Aggregate `values` with `function`.
+    fn run(function: AggregateFunction, values: &[Option<Datom>]) -> Option<Datom> {
+        let aggregate = aggregate(function);
+        let mut accumulator = Accumulator::new(&aggregate);
+        for value in values {
+            accumulator.add(value.as_ref()).expect("add");
+        }
+        accumulator.finish()
+    }
+
+    #[test]
+    fn test_accumulators_skip_nulls() {
+        let values = [
+            Some(Datom::number(3)),
+            None,
+            Some(Datom::Value(Value::Null)),
+            Some(Datom::number(1)),
+            Some(Datom::number(5)),
+        ];
+
+        assert_eq!(
+            run(AggregateFunction::Count, &values),
+            Some(Datom::number(3))
+        );
+        assert_eq!(run(AggregateFunction::Sum, &values), Some(Datom::number(9)));
+        assert_eq!(run(AggregateFunction::Min, &values), Some(Datom::number(1)));
+        assert_eq!(run(AggregateFunction::Max, &values), Some(Datom::number(5)));
+        assert_eq!(run(AggregateFunction::Avg, &values), Some(Datom::number(3)));
+    }
+
+    #[test]
+    fn test_accumulators_over_no_values() {
+        assert_eq!(run(AggregateFunction::Count, &[]), Some(Datom::number(0)));
+        assert_eq!(run(AggregateFunction::Sum, &[]), Some(Datom::number(0)));
+        assert_eq!(run(AggregateFunction::Min, &[]), None);
+        assert_eq!(run(AggregateFunction::Max, &[]), None);
+        assert_eq!(run(AggregateFunction::Avg, &[None]), None);
+    }
+
+    #[test]
+    fn test_min_max_span_value_types() {
+        let values = [
+            Some(Datom::string("b")),
+            Some(Datom::number(10)),
+            Some(Datom::boolean(true)),
+        ];
+
+        assert_eq!(
+            run(AggregateFunction::Min, &values),
+            Some(Datom::boolean(true))
+        );
+        assert_eq!(
+            run(AggregateFunction::Max, &values),
+            Some(Datom::string("b"))
+        );
+        assert_eq!(
+            run(AggregateFunction::Count, &values),
+            Some(Datom::number(3))
+        );
+    }
+
+    #[test]
+    fn test_sum_rejects_non_numeric_values() {
+        for function in [AggregateFunction::Sum, AggregateFunction::Avg] {
+            let aggregate = aggregate(function);
+            let mut accumulator = Accumulator::new(&aggregate);
+            accumulator.add(Some(&Datom::number(1))).expect("add");
+
+            let error = accumulator
+                .add(Some(&Datom::string("x")))
+                .expect_err("non-numeric value");
+            assert!(
+                matches!(&error, QueryError::NonNumericAggregate { aggregate, .. } if *aggregate == format!("{}(v)", function.name())),
+                "{error}"
+            );
+        }
+    }
+}
@@ -0,0 +1,651 @@
+//! Arrow IPC encoding of query results.
+//!
+//! Analytics clients can ask for a query's results as an Arrow IPC stream
+//! (see `QueryRequest.arrow_ipc`), which dataframe libraries read column by
+//! column instead of decoding protobuf rows. Each find variable becomes one
+//! column, typed from the values it holds:
+//!
+//! | Values                         | Arrow type |
+//! |--------------------------------|------------|
+//! | Entity or attribute IDs        | `Utf8`     |
+//! | Strings and entity references  | `Utf8`     |
+//! | Numbers                        | `Float64`  |
+//! | Booleans                       | `Bool`     |
+//! | Bytes                          | `Binary`   |
+//! | Only undefined or null values  | `Null`     |
+//!
+//! Undefined values (from OPTIONAL patterns) and null values are Arrow nulls.
+//! IDs are rendered as in the row encoding. A column mixing two of the types
+//! above, including IDs with strings, cannot be encoded; the caller then
+//! falls back to rows.
+//!
+//! The stream is written by hand rather than with the `arrow` crate, which
+//! would pull in a large dependency tree for one encoder. It holds a schema
+//! message, one record batch and the end-of-stream marker, in little-endian
+//! byte order with metadata version V5.
+
+use std::borrow::Cow;
+use std::fmt;
+
+use super::{Datom, QueryResult, Value};
+use crate::types::query::id_to_string;
+
+/// Arrow metadata version V5.
+const METADATA_VERSION_V5: i16 = 4;
+
+/// Marker that starts every encapsulated IPC message.
+const CONTINUATION_MARKER: u32 = 0xFFFF_FFFF;
+
+/// `MessageHeader` union tags.
+const HEADER_SCHEMA: u8 = 1;
+const HEADER_RECORD_BATCH: u8 = 3;
+
+/// `FloatingPoint.precision` for 64-bit floats.
+const PRECISION_DOUBLE: i16 = 2;
+
+/// Errors that prevent encoding a result as Arrow.
+#[derive(Debug, PartialEq, Eq)]
+pub enum ArrowEncodeError {
+    /// A column holds values of more than one Arrow type.
+    MixedColumnTypes {
+        /// The column's find variable.
+        column: String,
+    },
+    /// A string or bytes column's data exceeds the 2 GiB that `Utf8` and
+    /// `Binary` offsets address.
+    ColumnTooLarge {
+        /// The column's find variable.
+        column: String,
+    },
+}
+
+impl fmt::Display for ArrowEncodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::MixedColumnTypes { column } => {
+                write!(f, "column ?{column} holds values of more than one type")
+            }
+            Self::ColumnTooLarge { column } => {
+                write!(f, "column ?{column} holds more than 2 GiB of data")
+            }
+        }
+    }
+}
+
+impl std::error::Error for ArrowEncodeError {}
+
+/// Encode a query result as an Arrow IPC stream.
+///
+/// # Post-conditions
+/// - The stream's schema has one nullable field per column, named after its
+///   find variable, followed by one record batch holding every row
+///
+/// # Errors
+///
+/// Returns `ArrowEncodeError::MixedColumnTypes` if a column holds values of
+/// more than one type, or `ArrowEncodeError::ColumnTooLarge` if a string or
+/// bytes column is too large for its offsets.
+pub fn encode_arrow_ipc(result: &QueryResult) -> Result<Vec<u8>, ArrowEncodeError> {
+    let mut fields = Vec::with_capacity(result.columns.len());
+    let mut body = Body::default();
+    for (index, name) in result.columns.iter().enumerate() {
+        let values: Vec<Option<Cell<'_>>> = result
+            .rows
+            .iter()
+            .map(|row| {
+                row.get(index)
+                    .and_then(Option::as_ref)
+                    .and_then(Cell::from_datom)
+            })
+            .collect();
+        let column_type =
+            ColumnType::infer(&values).ok_or_else(|| ArrowEncodeError::MixedColumnTypes {
+                column: name.to_owned(),
+            })?;
+        body.push_column(column_type, &values)
+            .map_err(|()| ArrowEncodeError::ColumnTooLarge {
+                column: name.to_owned(),
+            })?;
+        fields.push(column_type.field(name));
+    }
+
+    let row_count = i64::try_from(result.rows.len()).unwrap_or(i64::MAX);
+    let schema = message(
+        HEADER_SCHEMA,
+        Object::Table(vec![(1, Slot::Offset(Object::Tables(fields)))]),
+        0,
+    );
+    let body_length = i64::try_from(body.data.len()).unwrap_or(i64::MAX);
+    let record_batch = message(
+        HEADER_RECORD_BATCH,
+        Object::Table(vec![
+            (0, Slot::I64(row_count)),
+            (1, Slot::Offset(Object::Structs(body.nodes))),
+            (2, Slot::Offset(Object::Structs(body.buffers))),
+        ]),
+        body_length,
+    );
+
+    let mut stream = Vec::new();
+    write_message(&mut stream, &schema, &[]);
+    write_message(&mut stream, &record_batch, &body.data);
+    stream.extend_from_slice(&CONTINUATION_MARKER.to_le_bytes());
+    stream.extend_from_slice(&0u32.to_le_bytes());
+    Ok(stream)
+}
+
+/// A non-null value in a column.
+enum Cell<'a> {
+    Id(String),
+    String(Cow<'a, str>),
+    Number(f64),
+    Boolean(bool),
+    Bytes(&'a [u8]),
+}
+
+impl<'a> Cell<'a> {
+    /// The value a datom holds, or `None` for a null value.
+    fn from_datom(datom: &'a Datom) -> Option<Self> {
+        match datom {
+            Datom::Entity(id) => Some(Self::Id(id_to_string(&id.0))),
+            Datom::Field(id) => Some(Self::Id(id_to_string(&id.0))),
+            Datom::Value(Value::Null) => None,
+            Datom::Value(Value::Boolean(b)) => Some(Self::Boolean(*b)),
+            Datom::Value(Value::Number(n)) => Some(Self::Number(*n)),
+            Datom::Value(Value::String(s)) => Some(Self::String(s.into())),
+            Datom::Value(Value::Bytes(bytes)) => Some(Self::Bytes(bytes)),
+            // References are strings in the row encoding too
+            Datom::Value(Value::Ref(id)) => Some(Self::String(id_to_string(&id.0).into())),
+        }
+    }
+
+    /// The type of column that holds this value.
+    const fn column_type(&self) -> ColumnType {
+        match self {
+            Self::Id(_) => ColumnType::Id,
+            Self::String(_) => ColumnType::String,
+            Self::Number(_) => ColumnType::Float64,
+            Self::Boolean(_) => ColumnType::Bool,
+            Self::Bytes(_) => ColumnType::Binary,
+        }
+    }
+
+    /// The value's variable-length data, if it is a string, ID or bytes.
+    fn as_bytes(&self) -> Option<&[u8]> {
+        match self {
+            Self::Id(id) => Some(id.as_bytes()),
+            Self::String(s) => Some(s.as_bytes()),
+            Self::Bytes(bytes) => Some(bytes),
+            Self::Number(_) | Self::Boolean(_) => None,
+        }
+    }
+}
+
+/// The type inferred for a column.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ColumnType {
+    /// Every value is null.
+    Null,
+    /// Entity or attribute IDs.
+    Id,
+    /// Strings and entity references.
+    String,
+    Float64,
+    Bool,
+    Binary,
+}
+
+impl ColumnType {
+    /// The single type of a column's non-null values, or `None` if they
+    /// have more than one.
+    fn infer(values: &[Option<Cell<'_>>]) -> Option<Self> {
+        let mut column_type = Self::Null;
+        for value in values.iter().flatten() {
+            match (column_type, value.column_type()) {
+                (Self::Null, found) => column_type = found,
+                (expected, found) if expected == found => {}
+                _ => return None,
+            }
+        }
+        Some(column_type)
+    }
+
+    /// The schema `Field` for a nullable column of this type.
+    fn field(self, name: &str) -> Object {
+        let (type_tag, type_table) = match self {
+            Self::Null => (1, Vec::new()),
+            Self::Float64 => (3, vec![(0, Slot::I16(PRECISION_DOUBLE))]),
+            Self::Binary => (4, Vec::new()),
+            Self::Id | Self::String => (5, Vec::new()),
+            Self::Bool => (6, Vec::new()),
+        };
+        Object::Table(vec![
+            (0, Slot::Offset(Object::String(name.to_owned()))),
+            (1, Slot::U8(1)),
+            (2, Slot::U8(type_tag)),
+            (3, Slot::Offset(Object::Table(type_table))),
+            (5, Slot::Offset(Object::Tables(Vec::new()))),
+        ])
+    }
+}
+
+/// A record batch body under construction.
+#[derive(Default)]
+struct Body {
+    /// `FieldNode` structs: length and null count of each column.
+    nodes: Vec<[i64; 2]>,
+    /// `Buffer` structs: offset and length of each buffer in `data`.
+    buffers: Vec<[i64; 2]>,
+    /// The buffers, each padded to 8 bytes.
+    data: Vec<u8>,
+}
+
+impl Body {
+    /// Append a column's node and buffers.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Err` if a string or bytes column's data is too large for its
+    /// offsets.
+    fn push_column(
+        &mut self,
+        column_type: ColumnType,
+        values: &[Option<Cell<'_>>],
+    ) -> Result<(), ()> {
+        let length = values.len();
+        let null_count = values.iter().filter(|value| value.is_none()).count();
+        self.nodes.push([to_i64(length), to_i64(null_count)]);
+        if column_type == ColumnType::Null {
+            return Ok(());
+        }
+
+        // Validity bitmap, omitted when nothing is null
+        if null_count == 0 {
+            self.push_buffer(&[]);
+        } else {
+            self.push_buffer(&bitmap(values.iter().map(Option::is_some)));
+        }
+
+        match column_type {
+            ColumnType::Null => {}
+            ColumnType::Float64 => {
+                let data: Vec<u8> = values
+                    .iter()
+                    .flat_map(|value| match value {
+                        Some(Cell::Number(n)) => n.to_le_bytes(),
+                        _ => [0; 8],
+                    })
+                    .collect();
+                self.push_buffer(&data);
+            }
+            ColumnType::Bool => {
+                let data = bitmap(
+                    values
+                        .iter()
+                        .map(|value| matches!(value, Some(Cell::Boolean(true)))),
+                );
+                self.push_buffer(&data);
+            }
+            ColumnType::Id | ColumnType::String | ColumnType::Binary => {
+                let mut offsets = Vec::with_capacity((length + 1) * 4);
+                let mut data = Vec::new();
+                offsets.extend_from_slice(&0i32.to_le_bytes());
+                for value in values {
+                    data.extend_from_slice(value.as_ref().and_then(Cell::as_bytes).unwrap_or(&[]));
+                    let end = i32::try_from(data.len()).map_err(|_| ())?;
+                    offsets.extend_from_slice(&end.to_le_bytes());
+                }
+                self.push_buffer(&offsets);
+                self.push_buffer(&data);
+            }
+        }
+        Ok(())
+    }
+
+    /// Append a buffer, padded to 8 bytes.
+    fn push_buffer(&mut self, buffer: &[u8]) {
+        self.buffers
+            .push([to_i64(self.data.len()), to_i64(buffer.len())]);
+        self.data.extend_from_slice(buffer);
+        pad(&mut self.data, 8);
+    }
+}
+
+/// Pack booleans into an LSB-first bitmap.
+fn bitmap(bits: impl Iterator<Item = bool>) -> Vec<u8> {
+    let mut bytes = Vec::new();
+    for (index, bit) in bits.enumerate() {
+        if index % 8 == 0 {
+            bytes.push(0);
+        }
+        if bit && let Some(byte) = bytes.last_mut() {
+            *byte |= 1 << (index % 8);
+        }
+    }
+    bytes
+}
+
+/// Convert a length to an Arrow `long`.
+fn to_i64(value: usize) -> i64 {
+    i64::try_from(value).unwrap_or(i64::MAX)
+}
+
+/// Pad `bytes` with zeros to a multiple of `align`.
+fn pad(bytes: &mut Vec<u8>, align: usize) {
+    bytes.resize(bytes.len().next_multiple_of(align), 0);
+}
+
+/// Build a `Message` flatbuffer with the given header.
+fn message(header_type: u8, header: Object, body_length: i64) -> Vec<u8> {
+    flatbuffer(&Object::Table(vec![
+        (0, Slot::I16(METADATA_VERSION_V5)),
+        (1, Slot::U8(header_type)),
+        (2, Slot::Offset(header)),
+        (3, Slot::I64(body_length)),
+    ]))
+}
+
+/// Write an encapsulated message: the continuation marker, the metadata
+/// length, the metadata padded so the body starts 8-byte aligned, then the
+/// body.
+fn write_message(stream: &mut Vec<u8>, metadata: &[u8], body: &[u8]) {
+    let padded_length = metadata.len().next_multiple_of(8);
+    stream.extend_from_slice(&CONTINUATION_MARKER.to_le_bytes());
+    stream.extend_from_slice(
+        &u32::try_from(padded_length)
+            .unwrap_or(u32::MAX)
+            .to_le_bytes(),
+    );
+    stream.extend_from_slice(metadata);
+    pad(stream, 8);
+    stream.extend_from_slice(body);
+}
+
+/// A flatbuffer object.
+enum Object {
+    /// A table of field values by slot.
+    Table(Vec<(u16, Slot)>),
+    /// A string.
+    String(String),
+    /// A vector of tables.
+    Tables(Vec<Self>),
+    /// A vector of structs of two longs (`FieldNode` or `Buffer`).
+    Structs(Vec<[i64; 2]>),
+}
+
+/// A table field value.
+enum Slot {
+    U8(u8),
+    I16(i16),
+    I64(i64),
+    /// A reference to an object stored after the table.
+    Offset(Object),
+}
+
+impl Slot {
+    /// Size and alignment of the value inline in the table.
+    const fn size(&self) -> usize {
+        match self {
+            Self::U8(_) => 1,
+            Self::I16(_) => 2,
+            Self::I64(_) => 8,
+            Self::Offset(_) => 4,
+        }
+    }
+}
+
+/// Serialize a flatbuffer with `root` as its root table.
+///
+/// Objects are written front to back: every object follows the table or
+/// vector that refers to it, so offsets are always forward as flatbuffers
+/// require, and each vtable directly precedes its table. Positions are
+/// aligned relative to the start of the buffer.
+fn flatbuffer(root: &Object) -> Vec<u8> {
+    let mut buf = vec![0; 4];
+    let root_position = write_object(&mut buf, root);
+    patch_offset(&mut buf, 0, root_position);
+    pad(&mut buf, 8);
+    buf
+}
+
+/// Write `object` at the end of `buf` and return its position.
+fn write_object(buf: &mut Vec<u8>, object: &Object) -> usize {
+    match object {
+        Object::Table(slots) => write_table(buf, slots),
+        Object::String(s) => {
+            pad(buf, 4);
+            let position = buf.len();
+            buf.extend_from_slice(&to_u32(s.len()).to_le_bytes());
+            buf.extend_from_slice(s.as_bytes());
+            buf.push(0);
+            position
+        }
+        Object::Tables(tables) => {
+            pad(buf, 4);
+            let position = buf.len();
+            buf.extend_from_slice(&to_u32(tables.len()).to_le_bytes());
+            let first = buf.len();
+            buf.resize(first + 4 * tables.len(), 0);
+            for (index, table) in tables.iter().enumerate() {
+                let table_position = write_object(buf, table);
+                patch_offset(buf, first + 4 * index, table_position);
+            }
+            position
+        }
+        Object::Structs(structs) => {
+            // The elements after the length must be 8-byte aligned
+            pad(buf, 8);
+            buf.resize(buf.len() + 4, 0);
+            let position = buf.len();
+            buf.extend_from_slice(&to_u32(structs.len()).to_le_bytes());
+            for [first, second] in structs {
+                buf.extend_from_slice(&first.to_le_bytes());
+                buf.extend_from_slice(&second.to_le_bytes());
+            }
+            position
+        }
+    }
+}
+
+/// Write a vtable and its table, then the objects the table refers to.
+fn write_table(buf: &mut Vec<u8>, slots: &[(u16, Slot)]) -> usize {
+    pad(buf, 2);
+    let vtable_position = buf.len();
+    let slot_count = slots
+        .iter()
+        .map(|(slot, _)| usize::from(*slot) + 1)
+        .max()
+        .unwrap_or(0);
+    let table_position = (vtable_position + 4 + 2 * slot_count).next_multiple_of(8);
+
+    // Lay out fields largest first after the vtable offset
+    let mut order: Vec<usize> = (0..slots.len()).collect();
+    order.sort_by_key(|&index| std::cmp::Reverse(slots[index].1.size()));
+    let mut field_offsets = vec![0u16; slot_count];
+    let mut cursor = table_position + 4;
+    let mut placed = Vec::with_capacity(slots.len());
+    for index in order {
+        let (slot, value) = &slots[index];
+        cursor = cursor.next_multiple_of(value.size());
+        field_offsets[usize::from(*slot)] = to_u16(cursor - table_position);
+        placed.push((cursor, value));
+        cursor += value.size();
+    }
+
+    buf.extend_from_slice(&to_u16(4 + 2 * slot_count).to_le_bytes());
+    buf.extend_from_slice(&to_u16(cursor - table_position).to_le_bytes());
+    for offset in field_offsets {
+        buf.extend_from_slice(&offset.to_le_bytes());
+    }
+    buf.resize(table_position, 0);
+    let vtable_distance = i32::try_from(table_position - vtable_position).unwrap_or(i32::MAX);
+    buf.extend_from_slice(&vtable_distance.to_le_bytes());
+    buf.resize(cursor, 0);
+
+    for (position, value) in &placed {
+        let bytes = match value {
+            Slot::U8(v) => v.to_le_bytes().to_vec(),
+            Slot::I16(v) => v.to_le_bytes().to_vec(),
+            Slot::I64(v) => v.to_le_bytes().to_vec(),
+            Slot::Offset(_) => continue,
+        };
+        buf[*position..*position + bytes.len()].copy_from_slice(&bytes);
+    }
+    for (position, value) in placed {
+        if let Slot::Offset(object) = value {
+            let object_position = write_object(buf, object);
+            patch_offset(buf, position, object_position);
+        }
+    }
+    table_position
+}
+
+/// Point the offset at `position` to `target`, which follows it.
+fn patch_offset(buf: &mut [u8], position: usize, target: usize) {
+    buf[position..position + 4].copy_from_slice(&to_u32(target - position).to_le_bytes());
+}
+
+/// Convert a flatbuffer length or offset to `u32`.
+fn to_u32(value: usize) -> u32 {
+    u32::try_from(value).unwrap_or(u32::MAX)
+}
+
+/// Convert a vtable entry to `u16`.
+fn to_u16(value: usize) -> u16 {
+    u16::try_from(value).unwrap_or(u16::MAX)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::query::EntityId;
+
+    fn string(s: &str) -> Datom {
+        Datom::Value(Value::String(s.to_owned()))
+    }
+
+    fn number(n: f64) -> Datom {
+        Datom::Value(Value::Number(n))
+    }
+
+    /// Split a stream into its schema metadata and record batch body.
+    fn split_stream(stream: &[u8]) -> (&[u8], &[u8]) {
+        let read_u32 =
+            |at: usize| u32::from_le_bytes(stream[at..at + 4].try_into().expect("4 bytes"));
+        assert_eq!(read_u32(0), CONTINUATION_MARKER);
+        let schema_end = 8 + read_u32(4) as usize;
+        assert_eq!(read_u32(schema_end), CONTINUATION_MARKER);
+        let batch_end = schema_end + 8 + read_u32(schema_end + 4) as usize;
+        assert_eq!(batch_end % 8, 0, "the body must be 8-byte aligned");
+        assert_eq!(
+            &stream[stream.len() - 8..],
+            [0xFF, 0xFF, 0xFF, 0xFF, 0, 0, 0, 0]
+        );
+        (&stream[8..schema_end], &stream[batch_end..stream.len() - 8])
+    }
+
+    #[test]
+    fn test_encode_arrow_ipc_body_buffers() {
+        let result = QueryResult {
+            columns: vec!["name".to_owned(), "age".to_owned()],
+            rows: vec![
+                vec![Some(string("Al")), Some(number(1.5))],
+                vec![None, Some(number(2.0))],
+            ],
+        };
+        let stream = encode_arrow_ipc(&result).expect("encode");
+        let (_, body) = split_stream(&stream);
+
+        let mut expected = Vec::new();
+        // name: validity, offsets and data
+        expected.extend_from_slice(&[0b01, 0, 0, 0, 0, 0, 0, 0]);
+        for offset in [0i32, 2, 2] {
+            expected.extend_from_slice(&offset.to_le_bytes());
+        }
+        expected.extend_from_slice(&[0; 4]);
+        expected.extend_from_slice(b"Al\0\0\0\0\0\0");
+        // age: empty validity, then values
+        expected.extend_from_slice(&1.5f64.to_le_bytes());
+        expected.extend_from_slice(&2.0f64.to_le_bytes());
+        assert_eq!(body, expected);
+    }
+
+    #[test]
+    fn test_encode_arrow_ipc_schema_names_columns() {
+        let result = QueryResult {
+            columns: vec!["first_name".to_owned()],
+            rows: vec![],
+        };
+        let stream = encode_arrow_ipc(&result).expect("encode");
+        let (schema, body) = split_stream(&stream);
+        assert!(schema.windows(10).any(|window| window == b"first_name"));
+        assert!(body.is_empty());
+    }
+
+    #[test]
+    fn test_encode_arrow_ipc_rejects_mixed_columns() {
+        let result = QueryResult {
+            columns: vec!["v".to_owned()],
+            rows: vec![vec![Some(string("1"))], vec![Some(number(1.0))]],
+        };
+        assert_eq!(
+            encode_arrow_ipc(&result),
+            Err(ArrowEncodeError::MixedColumnTypes {
+                column: "v".to_owned(),
+            })
+        );
+
+        // IDs and strings are both Utf8 but still count as mixed
+        let result = QueryResult {
+            columns: vec!["v".to_owned()],
+            rows: vec![
+                vec![Some(Datom::Entity(EntityId([1; 16])))],
+                vec![Some(string("1"))],
+            ],
+        };
+        assert!(encode_arrow_ipc(&result).is_err());
+    }
+
+    #[test]
+    fn test_column_type_inference() {
+        let infer = |datoms: &[Option<Datom>]| {
+            let values: Vec<_> = datoms
+                .iter()
+                .map(|datom| datom.as_ref().and_then(Cell::from_datom))
+                .collect();
+            ColumnType::infer(&values)
+        };
+        assert_eq!(infer(&[]), Some(ColumnType::Null));
+        assert_eq!(
+            infer(&[None, Some(Datom::Value(Value::Null))]),
+            Some(ColumnType::Null)
+        );
+        assert_eq!(infer(&[None, Some(number(1.0))]), Some(ColumnType::Float64));
+        assert_eq!(
+            infer(&[
+                Some(string("a")),
+                Some(Datom::Value(Value::Ref(EntityId([1; 16]))))
+            ]),
+            Some(ColumnType::String)
+        );
+        assert_eq!(
+            infer(&[Some(Datom::Value(Value::Boolean(false)))]),
+            Some(ColumnType::Bool)
+        );
+        assert_eq!(
+            infer(&[Some(Datom::Value(Value::Bytes(vec![0xFF])))]),
+            Some(ColumnType::Binary)
+        );
+        assert_eq!(
+            infer(&[Some(Datom::Value(Value::Bytes(vec![]))), Some(string("a"))]),
+            None
+        );
+        assert_eq!(infer(&[Some(string("a")), Some(number(1.0))]), None);
+    }
+
+    #[test]
+    fn test_bitmap_is_lsb_first() {
+        let bits = [true, false, false, true, false, false, false, false, true];
+        assert_eq!(bitmap(bits.into_iter()), vec![0b0000_1001, 0b0000_0001]);
+    }
+}
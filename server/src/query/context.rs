@@ -108,10 +108,10 @@ impl QueryContext {
     #[must_use]
     pub fn is_consistent_with(&self, other: &Self) -> bool {
         for (name, value) in &self.bindings {
-            if let Some(other_value) = other.bindings.get(name) {
-                if value != other_value {
-                    return false;
-                }
+            if let Some(other_value) = other.bindings.get(name)
+                && value != other_value
+            {
+                return false;
             }
         }
         true
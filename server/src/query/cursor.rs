@@ -0,0 +1,637 @@
+//! Server-side query cursors.
+//!
+//! A cursor lets a client page through a large query result and resume after
+//! a dropped connection. Each cursor owns the query, a pinned `Snapshot` and
+//! the scan position, so every batch reads the same consistent view.
+//!
+//! # Design
+//!
+//! Cursors live in a `CursorTable` shared by every connection to a database,
+//! so a reconnecting client can keep fetching from the same cursor. The scan
+//! position is the primary index key of the triple that seeds the next row
+//! (see `QueryEngine::fetch_page`), plus the number of that seed's rows
+//! already returned.
+//!
+//! A pinned snapshot blocks garbage collection of the records it can see, so
+//! cursors that go unused for the idle timeout are closed and their snapshots
+//! released. Expiry runs lazily on every cursor operation and from the
+//! registry's idle sweep.
+//!
+//! # Invariants
+//!
+//! - Every cursor in the table holds exactly one snapshot pin
+//! - A cursor is removed, and its pin released, when it is closed, expires,
+//!   or is fetched to exhaustion
+
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+
+use super::engine::QueryEngine;
+use super::types::{Query, QueryResult};
+use crate::storage::{Database, DatabaseError, PinnedSnapshot, Snapshot};
+use crate::types::{AttributeId, EntityId};
+
+/// Default time a cursor may go unused before it expires.
+pub const DEFAULT_CURSOR_IDLE_TIMEOUT: Duration = Duration::from_mins(5);
+
+/// Identifier of an open cursor, unique within its `CursorTable`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct CursorId(pub u64);
+
+impl std::fmt::Display for CursorId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+/// Where a cursor resumes scanning.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CursorPosition {
+    /// Entity of the seed triple to resume at.
+    pub entity_id: EntityId,
+    /// Attribute of the seed triple to resume at.
+    pub attribute_id: AttributeId,
+    /// Value hash of the seed triple to resume at: 0 for a cardinality-one
+    /// attribute.
+    pub value_hash: u64,
+    /// Rows of that seed already returned.
+    pub rows_fetched: usize,
+}
+
+/// One batch of rows fetched from a cursor.
+#[derive(Debug)]
+pub struct CursorBatch {
+    /// The rows in this batch, at most the requested `max_rows`.
+    pub result: QueryResult,
+    /// Whether the cursor has no more rows. An exhausted cursor is closed.
+    pub exhausted: bool,
+}
+
+/// An open cursor.
+struct QueryCursor {
+    /// The query being paged through.
+    query: Query,
+    /// The snapshot every batch reads from.
+    pinned: PinnedSnapshot,
+    /// Where the next batch starts. `None` before the first batch.
+    position: Option<CursorPosition>,
+    /// When the cursor was opened or last fetched from.
+    last_used: Instant,
+}
+
+/// The open cursors of one database.
+pub struct CursorTable {
+    /// Open cursors by ID.
+    cursors: Mutex<HashMap<CursorId, QueryCursor>>,
+    /// Next cursor ID to hand out. IDs start at 1.
+    next_id: AtomicU64,
+    /// How long a cursor may go unused before it expires.
+    idle_timeout: Duration,
+}
+
+impl Default for CursorTable {
+    fn default() -> Self {
+        Self::new(DEFAULT_CURSOR_IDLE_TIMEOUT)
+    }
+}
+
+impl CursorTable {
+    /// Create an empty cursor table.
+    #[must_use]
+    pub fn new(idle_timeout: Duration) -> Self {
+        Self {
+            cursors: Mutex::new(HashMap::new()),
+            next_id: AtomicU64::new(1),
+            idle_timeout,
+        }
+    }
+
+    /// Open a cursor over `query`, pinning `snapshot`.
+    ///
+    /// Prefer `QueryEngine::open_cursor`.
+    ///
+    /// # Post-conditions
+    /// - On success, `snapshot` is pinned until the cursor is removed
+    ///
+    /// # Errors
+    ///
+    /// Returns any error from `check_query`, or `CursorError::LockPoisoned`
+    /// if the table lock is poisoned.
+    pub fn open(&self, snapshot: &Snapshot<'_>, query: Query) -> Result<CursorId, CursorError> {
+        Self::check_query(&query)?;
+
+        let pinned = snapshot.pin()?;
+        let mut cursors = self.cursors.lock().map_err(|_| CursorError::LockPoisoned)?;
+        let cursor_id = CursorId(self.next_id.fetch_add(1, Ordering::Relaxed));
+        cursors.insert(
+            cursor_id,
+            QueryCursor {
+                query,
+                pinned,
+                position: None,
+                last_used: Instant::now(),
+            },
+        );
+        drop(cursors);
+
+        Ok(cursor_id)
+    }
+
+    /// Check that a cursor can page through `query`.
+    ///
+    /// A cursor returns the rows of each seed as it scans, so it cannot
+    /// compute aggregates, sort, skip, limit or deduplicate rows, which need
+    /// every row first.
+    ///
+    /// # Errors
+    ///
+    /// Returns `CursorError::MissingWherePattern` if the query has no WHERE
+    /// pattern, `CursorError::AggregateQuery` if it has aggregates or
+    /// group-by variables, or `CursorError::UnsupportedClause` naming the
+    /// first of an ordering, limit, offset or distinct flag it sets.
+    pub const fn check_query(query: &Query) -> Result<(), CursorError> {
+        if query.where_patterns.is_empty() {
+            return Err(CursorError::MissingWherePattern);
+        }
+        if query.is_aggregate() {
+            return Err(CursorError::AggregateQuery);
+        }
+        let clause = if !query.order_by.is_empty() {
+            "order_by"
+        } else if query.limit.is_some() {
+            "limit"
+        } else if query.offset != 0 {
+            "offset"
+        } else if query.distinct {
+            "distinct"
+        } else {
+            return Ok(());
+        };
+        Err(CursorError::UnsupportedClause(clause))
+    }
+
+    /// Fetch the next batch of up to `max_rows` rows and advance the cursor.
+    ///
+    /// # Pre-conditions
+    /// - `database` is the database the cursor's snapshot was taken from
+    ///
+    /// # Post-conditions
+    /// - If the batch is exhausted, the cursor is closed and its pin released
+    ///
+    /// # Errors
+    ///
+    /// Returns `CursorError::NotFound` if the cursor was closed or expired,
+    /// `CursorError::Database` if reading the snapshot fails (the cursor stays
+    /// open at its previous position), or `CursorError::LockPoisoned` if the
+    /// table lock is poisoned.
+    pub fn fetch(
+        &self,
+        database: &Database,
+        cursor_id: CursorId,
+        max_rows: usize,
+    ) -> Result<CursorBatch, CursorError> {
+        let mut cursors = self.cursors.lock().map_err(|_| CursorError::LockPoisoned)?;
+        let now = Instant::now();
+        self.expire(&mut cursors, database, now);
+
+        let cursor = cursors
+            .get_mut(&cursor_id)
+            .ok_or(CursorError::NotFound(cursor_id))?;
+        let snapshot = database.resume_snapshot(cursor.pinned);
+        let (result, position) = QueryEngine::new(&snapshot).fetch_page(
+            &cursor.query,
+            cursor.position.as_ref(),
+            max_rows.max(1),
+        )?;
+        cursor.last_used = now;
+
+        let exhausted = position.is_none();
+        if exhausted {
+            if let Some(cursor) = cursors.remove(&cursor_id) {
+                database.unpin_snapshot(cursor.pinned)?;
+            }
+        } else {
+            cursor.position = position;
+        }
+        drop(cursors);
+
+        Ok(CursorBatch { result, exhausted })
+    }
+
+    /// Close a cursor and release its snapshot.
+    ///
+    /// # Errors
+    ///
+    /// Returns `CursorError::NotFound` if the cursor was already closed or
+    /// expired, or `CursorError::LockPoisoned` if the table lock is poisoned.
+    pub fn close(&self, database: &Database, cursor_id: CursorId) -> Result<(), CursorError> {
+        let mut cursors = self.cursors.lock().map_err(|_| CursorError::LockPoisoned)?;
+        self.expire(&mut cursors, database, Instant::now());
+
+        let cursor = cursors
+            .remove(&cursor_id)
+            .ok_or(CursorError::NotFound(cursor_id))?;
+        drop(cursors);
+        database.unpin_snapshot(cursor.pinned)?;
+        Ok(())
+    }
+
+    /// Close every cursor unused for at least the idle timeout.
+    ///
+    /// # Returns
+    /// The number of cursors closed.
+    ///
+    /// # Errors
+    ///
+    /// Returns `CursorError::LockPoisoned` if the table lock is poisoned.
+    pub fn expire_idle(&self, database: &Database) -> Result<usize, CursorError> {
+        let mut cursors = self.cursors.lock().map_err(|_| CursorError::LockPoisoned)?;
+        let expired = self.expire(&mut cursors, database, Instant::now());
+        drop(cursors);
+        Ok(expired)
+    }
+
+    /// Number of open cursors, including idle ones not yet expired.
+    ///
+    /// # Errors
+    ///
+    /// Returns `CursorError::LockPoisoned` if the table lock is poisoned.
+    pub fn open_cursor_count(&self) -> Result<usize, CursorError> {
+        let cursors = self.cursors.lock().map_err(|_| CursorError::LockPoisoned)?;
+        Ok(cursors.len())
+    }
+
+    /// Remove cursors idle since before `now - idle_timeout`, releasing their pins.
+    fn expire(
+        &self,
+        cursors: &mut HashMap<CursorId, QueryCursor>,
+        database: &Database,
+        now: Instant,
+    ) -> usize {
+        let before = cursors.len();
+        cursors.retain(|cursor_id, cursor| {
+            let idle = now.saturating_duration_since(cursor.last_used) >= self.idle_timeout;
+            if idle {
+                tracing::debug!("Cursor {} expired after being idle", cursor_id);
+                if let Err(e) = database.unpin_snapshot(cursor.pinned) {
+                    tracing::warn!("Failed to release expired cursor {}: {}", cursor_id, e);
+                }
+            }
+            !idle
+        });
+        before - cursors.len()
+    }
+}
+
+/// Errors from cursor operations.
+#[derive(Debug)]
+pub enum CursorError {
+    /// The cursor does not exist: it was closed, expired, or never opened.
+    NotFound(CursorId),
+    /// The query has no WHERE pattern to scan.
+    MissingWherePattern,
+    /// The query has aggregates or group-by variables, which a cursor cannot
+    /// compute a page at a time.
+    AggregateQuery,
+    /// The query sets a clause a cursor cannot apply a page at a time.
+    UnsupportedClause(&'static str),
+    /// The cursor table lock was poisoned.
+    LockPoisoned,
+    /// Reading the cursor's snapshot failed.
+    Database(DatabaseError),
+}
+
+impl std::fmt::Display for CursorError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::NotFound(cursor_id) => {
+                write!(f, "Cursor {cursor_id} not found; it may have expired")
+            }
+            Self::MissingWherePattern => {
+                write!(f, "Cursor queries must have at least one WHERE pattern")
+            }
+            Self::AggregateQuery => {
+                write!(f, "Cursor queries cannot have aggregates or group_by")
+            }
+            Self::UnsupportedClause(clause) => write!(f, "Cursor queries cannot have {clause}"),
+            Self::LockPoisoned => write!(f, "Cursor table lock poisoned"),
+            Self::Database(e) => write!(f, "Database error: {e}"),
+        }
+    }
+}
+
+impl std::error::Error for CursorError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Database(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<DatabaseError> for CursorError {
+    fn from(e: DatabaseError) -> Self {
+        Self::Database(e)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::query::{Direction, Pattern, PatternElement};
+    use crate::storage::buffer_pool::BufferPool;
+    use crate::types::{Cardinality, TripleValue};
+    use tempfile::tempdir;
+
+    /// Create a database with `count` entities that each have a name.
+    fn create_test_db(count: u8) -> (tempfile::TempDir, Database) {
+        let dir = tempdir().expect("create temp dir");
+        let mut db =
+            Database::create(&dir.path().join("test.db"), BufferPool::new(100)).expect("create db");
+
+        let mut txn = db.begin(0).expect("begin");
+        for i in 0..count {
+            txn.insert(
+                EntityId([i; 16]),
+                AttributeId::from_string("name"),
+                TripleValue::String(format!("user{i}")),
+            )
+            .expect("insert");
+        }
+        txn.commit().expect("commit");
+
+        (dir, db)
+    }
+
+    fn name_query() -> Query {
+        Query::new().find("name").where_pattern(Pattern::new(
+            PatternElement::var("e"),
+            PatternElement::field("name"),
+            PatternElement::var("name"),
+        ))
+    }
+
+    fn open_cursor(db: &Database, cursors: &CursorTable, query: Query) -> CursorId {
+        let snapshot = db.begin_readonly().expect("begin readonly");
+        let cursor_id = QueryEngine::new(&snapshot)
+            .open_cursor(cursors, query)
+            .expect("open cursor");
+        let txn_id = snapshot.close();
+        db.release_snapshot(txn_id).expect("release snapshot");
+        cursor_id
+    }
+
+    #[test]
+    fn test_cursor_fetch_to_exhaustion() {
+        let (_dir, mut db) = create_test_db(5);
+        let cursors = CursorTable::default();
+        let cursor_id = open_cursor(&db, &cursors, name_query());
+        assert_eq!(
+            db.active_snapshot_count().expect("active snapshot count"),
+            1
+        );
+
+        // Writes after opening are not visible to the cursor
+        let mut txn = db.begin(0).expect("begin");
+        txn.insert(
+            EntityId([9u8; 16]),
+            AttributeId::from_string("name"),
+            TripleValue::String("late".to_owned()),
+        )
+        .expect("insert");
+        txn.commit().expect("commit");
+
+        let mut names = Vec::new();
+        let mut batches = 0;
+        loop {
+            let batch = cursors.fetch(&db, cursor_id, 2).expect("fetch");
+            assert!(batch.result.len() <= 2);
+            names.extend(batch.result.rows.iter().map(|row| format!("{:?}", row[0])));
+            batches += 1;
+            if batch.exhausted {
+                break;
+            }
+        }
+
+        assert_eq!(names.len(), 5);
+        assert_eq!(batches, 3);
+        assert!(names.iter().all(|name| !name.contains("late")));
+        assert_eq!(cursors.open_cursor_count().expect("count"), 0);
+        assert_eq!(
+            db.active_snapshot_count().expect("active snapshot count"),
+            0
+        );
+        assert!(matches!(
+            cursors.fetch(&db, cursor_id, 2),
+            Err(CursorError::NotFound(_))
+        ));
+    }
+
+    #[test]
+    fn test_cursor_resumes_within_a_seed() {
+        let (_dir, mut db) = create_test_db(1);
+        let mut txn = db.begin(0).expect("begin");
+        for i in 0..3u8 {
+            txn.insert(
+                EntityId([i + 10; 16]),
+                AttributeId::from_string("owner"),
+                TripleValue::Ref(EntityId([0u8; 16])),
+            )
+            .expect("insert");
+        }
+        txn.commit().expect("commit");
+
+        // One name seeds three rows, which must span batches without repeats
+        let query = Query::new()
+            .find("pet")
+            .where_pattern(Pattern::new(
+                PatternElement::var("e"),
+                PatternElement::field("name"),
+                PatternElement::var("name"),
+            ))
+            .where_pattern(Pattern::new(
+                PatternElement::var("pet"),
+                PatternElement::field("owner"),
+                PatternElement::var("e"),
+            ));
+        let cursors = CursorTable::default();
+        let cursor_id = open_cursor(&db, &cursors, query);
+
+        let first = cursors.fetch(&db, cursor_id, 2).expect("fetch first");
+        assert_eq!(first.result.len(), 2);
+        assert!(!first.exhausted);
+        let second = cursors.fetch(&db, cursor_id, 2).expect("fetch second");
+        assert_eq!(second.result.len(), 1);
+        assert!(second.exhausted);
+
+        let mut pets: Vec<String> = first
+            .result
+            .rows
+            .iter()
+            .chain(second.result.rows.iter())
+            .map(|row| format!("{:?}", row[0]))
+            .collect();
+        pets.sort();
+        pets.dedup();
+        assert_eq!(pets.len(), 3);
+    }
+
+    #[test]
+    fn test_cursor_pages_through_the_values_of_one_attribute() {
+        let (_dir, mut db) = create_test_db(0);
+        let tag = AttributeId::from_string("tag");
+        db.set_attribute_cardinality(tag, Cardinality::Many)
+            .expect("declare");
+        let mut txn = db.begin(0).expect("begin");
+        for i in 0..5 {
+            txn.insert(
+                EntityId([1u8; 16]),
+                tag,
+                TripleValue::String(format!("tag{i}")),
+            )
+            .expect("insert");
+        }
+        txn.commit().expect("commit");
+
+        // Each value seeds a row, and batches end between values
+        let query = Query::new().find("tag").where_pattern(Pattern::new(
+            PatternElement::var("e"),
+            PatternElement::field("tag"),
+            PatternElement::var("tag"),
+        ));
+        let cursors = CursorTable::default();
+        let cursor_id = open_cursor(&db, &cursors, query);
+
+        let mut tags = Vec::new();
+        loop {
+            let batch = cursors.fetch(&db, cursor_id, 2).expect("fetch");
+            tags.extend(batch.result.rows.iter().map(|row| format!("{:?}", row[0])));
+            if batch.exhausted {
+                break;
+            }
+        }
+        tags.sort();
+        tags.dedup();
+        assert_eq!(tags.len(), 5);
+    }
+
+    #[test]
+    fn test_cursor_idle_expiry_releases_snapshot() {
+        let (_dir, db) = create_test_db(3);
+        let cursors = CursorTable::new(Duration::ZERO);
+        let cursor_id = open_cursor(&db, &cursors, name_query());
+        assert_eq!(
+            db.active_snapshot_count().expect("active snapshot count"),
+            1
+        );
+
+        assert_eq!(cursors.expire_idle(&db).expect("expire"), 1);
+        assert_eq!(
+            db.active_snapshot_count().expect("active snapshot count"),
+            0
+        );
+        assert!(matches!(
+            cursors.fetch(&db, cursor_id, 10),
+            Err(CursorError::NotFound(_))
+        ));
+    }
+
+    #[test]
+    fn test_cursor_close_releases_snapshot() {
+        let (_dir, db) = create_test_db(3);
+        let cursors = CursorTable::default();
+        let cursor_id = open_cursor(&db, &cursors, name_query());
+
+        cursors.close(&db, cursor_id).expect("close");
+        assert_eq!(
+            db.active_snapshot_count().expect("active snapshot count"),
+            0
+        );
+        assert!(matches!(
+            cursors.close(&db, cursor_id),
+            Err(CursorError::NotFound(_))
+        ));
+    }
+
+    #[test]
+    fn test_cursor_requires_where_pattern() {
+        let (_dir, db) = create_test_db(1);
+        let cursors = CursorTable::default();
+
+        let snapshot = db.begin_readonly().expect("begin readonly");
+        let result = QueryEngine::new(&snapshot).open_cursor(&cursors, Query::new().find("e"));
+        let txn_id = snapshot.close();
+        db.release_snapshot(txn_id).expect("release snapshot");
+
+        assert!(matches!(result, Err(CursorError::MissingWherePattern)));
+        assert_eq!(
+            db.active_snapshot_count().expect("active snapshot count"),
+            0
+        );
+    }
+
+    #[test]
+    fn test_cursor_rejects_aggregate_queries() {
+        let (_dir, db) = create_test_db(3);
+        let cursors = CursorTable::default();
+        let pattern = || {
+            Pattern::new(
+                PatternElement::var("e"),
+                PatternElement::field("name"),
+                PatternElement::var("name"),
+            )
+        };
+        let queries = [
+            Query::new().find_count("e").where_pattern(pattern()),
+            Query::new().group_by("name").where_pattern(pattern()),
+        ];
+
+        let snapshot = db.begin_readonly().expect("begin readonly");
+        for query in queries {
+            let result = QueryEngine::new(&snapshot).open_cursor(&cursors, query);
+            assert!(matches!(result, Err(CursorError::AggregateQuery)));
+        }
+        let txn_id = snapshot.close();
+        db.release_snapshot(txn_id).expect("release snapshot");
+
+        assert_eq!(cursors.open_cursor_count().expect("count"), 0);
+        assert_eq!(
+            db.active_snapshot_count().expect("active snapshot count"),
+            0
+        );
+    }
+
+    #[test]
+    fn test_cursor_rejects_clauses_it_cannot_apply() {
+        let (_dir, db) = create_test_db(3);
+        let cursors = CursorTable::default();
+        let queries = [
+            (
+                name_query().order_by("name", Direction::Ascending),
+                "order_by",
+            ),
+            (name_query().limit(2), "limit"),
+            (name_query().offset(1), "offset"),
+            (name_query().distinct(), "distinct"),
+        ];
+
+        let snapshot = db.begin_readonly().expect("begin readonly");
+        for (query, expected) in queries {
+            let result = QueryEngine::new(&snapshot).open_cursor(&cursors, query);
+            assert!(
+                matches!(result, Err(CursorError::UnsupportedClause(clause)) if clause == expected),
+                "{expected}: {result:?}"
+            );
+        }
+        // A zero offset skips nothing, so it is the same as none
+        QueryEngine::new(&snapshot)
+            .open_cursor(&cursors, name_query().offset(0))
+            .expect("open cursor");
+        let txn_id = snapshot.close();
+        db.release_snapshot(txn_id).expect("release snapshot");
+
+        assert_eq!(cursors.open_cursor_count().expect("count"), 1);
+    }
+}
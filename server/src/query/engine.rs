@@ -5,19 +5,35 @@
 //! - WHERE patterns (required matches)
 //! - OPTIONAL patterns (left join)
 //! - WHERE-NOT patterns (anti-join / negation)
-//! - Filters (predicate functions)
+//! - Filters (predicate functions); a comparison filter on the value of a
+//!   WHERE pattern narrows the attribute index read to its range
+//! - Entity sets (IN clauses), read with one primary index lookup per entity
+//! - Presence patterns (`[?e :email _]`), which check that an entity has an
+//!   attribute without reading its value
+//! - Aggregates (count, sum, min, max, avg), optionally grouped, computed in
+//!   one pass over the matched rows (see `aggregate`)
 
 // Allow some clippy lints that trigger on valid query engine patterns
 #![allow(clippy::option_if_let_else)] // if-let is clearer for mutable pattern matching
 #![allow(clippy::unused_self)] // Methods take &self for API consistency
 #![allow(clippy::match_wildcard_for_single_variants)] // Wildcards are intentional for extensibility
 
+use std::cmp::Ordering;
+use std::collections::HashSet;
+
+use super::aggregate::Groups;
 use super::context::QueryContext;
+use super::cursor::{CursorError, CursorId, CursorPosition, CursorTable};
+use super::executor::QueryError;
+use super::plan::QueryPlan;
+use super::stream::RowStream;
 use super::types::{
-    Datom, EntityId, FieldId, Pattern, PatternElement, Query, QueryResult, QueryRow, Triple, Value,
+    Aggregate, Datom, EntityId, FieldId, Pattern, PatternElement, Query, QueryResult, QueryRow,
+    Triple, Value, ValueRange,
 };
-use crate::storage::{DatabaseError, Snapshot};
-use crate::types::{AttributeId, TripleRecord};
+use crate::storage::attribute_cardinality::is_reserved_entity;
+use crate::storage::{AttributeEntityIterator, DatabaseError, Snapshot, TripleIterator};
+use crate::types::TripleRecord;
 
 /// The query engine evaluates queries against a database snapshot.
 pub struct QueryEngine<'a, 'b> {
@@ -26,26 +42,351 @@ pub struct QueryEngine<'a, 'b> {
 
 impl<'a, 'b> QueryEngine<'a, 'b> {
     /// Create a new query engine for a database snapshot.
+    #[must_use]
     pub const fn new(snapshot: &'a Snapshot<'b>) -> Self {
         Self { snapshot }
     }
 
     /// Execute a query and return results.
-    pub fn execute(&self, query: &Query) -> Result<QueryResult, DatabaseError> {
-        // Start with a single empty context
-        let mut contexts = vec![QueryContext::new()];
+    ///
+    /// # Post-conditions
+    /// - Rows are in canonical order: sorted by the primary key (entity, then
+    ///   attribute) of the triple matched by the first WHERE pattern, then by
+    ///   that of the second, and so on through the OPTIONAL patterns. The order
+    ///   depends only on the data, never on how GC or node splits have laid out
+    ///   the index pages.
+    /// - With `query.order_by` keys, rows are instead sorted by them, and rows
+    ///   tied on every key stay in canonical order
+    /// - With `query.distinct`, a row equal to an earlier one, in canonical
+    ///   order, is dropped before sorting. Each distinct row keeps the
+    ///   bindings of its first match, which an ordered query sorts by
+    /// - The first `query.offset` rows are skipped, then at most `query.limit`
+    ///   are returned, counting distinct rows only. Unless the query is
+    ///   ordered, evaluation stops once the limit is reached, and matches of
+    ///   the first WHERE pattern past that point are never read; OPTIONAL and
+    ///   WHERE-NOT patterns and filters still apply to every returned row
+    /// - With `query.aggregates` or `query.group_by`, a row holds each group's
+    ///   values and aggregates (see `aggregate`), computed over every matched
+    ///   row, and ordering is ignored
+    /// - `columns` do not depend on the limit or offset
+    ///
+    /// # Errors
+    ///
+    /// Returns `QueryError::NonNumericAggregate` if a sum or average meets a
+    /// value that is not a number, or an error if reading the snapshot fails.
+    pub fn execute(&self, query: &Query) -> Result<QueryResult, QueryError> {
+        let mut result = QueryResult::with_columns(result_columns(query));
+        if query.limit == Some(0) {
+            return Ok(result);
+        }
+        if query.is_aggregate() {
+            let limit = query.limit.unwrap_or(usize::MAX);
+            for row in self
+                .aggregate_rows(query)?
+                .into_iter()
+                .skip(query.offset)
+                .take(limit)
+            {
+                result.push(row);
+            }
+            return Ok(result);
+        }
+        if !query.order_by.is_empty() {
+            let limit = query.limit.unwrap_or(usize::MAX);
+            for ctx in self
+                .sorted_contexts(query)?
+                .iter()
+                .skip(query.offset)
+                .take(limit)
+            {
+                result.push(project(query, ctx));
+            }
+            return Ok(result);
+        }
+
+        // Each match of the first WHERE pattern seeds its rows, in order, so
+        // the rest of the query runs for no more seeds than `limit` needs
+        let (mut seeds, rest) = self.seeds(query)?;
+        let mut seen = HashSet::new();
+        let mut to_skip = query.offset;
+        while let Some(seed) = seeds.next_seed(self)? {
+            for ctx in self.evaluate(query, rest, vec![seed])? {
+                let row = project(query, &ctx);
+                if query.distinct && !seen.insert(row_key(row.iter().map(Option::as_ref))) {
+                    continue;
+                }
+                if to_skip > 0 {
+                    to_skip -= 1;
+                    continue;
+                }
+                result.push(row);
+                if query.limit.is_some_and(|limit| result.len() >= limit) {
+                    return Ok(result);
+                }
+            }
+        }
+
+        Ok(result)
+    }
+
+    /// Execute a query, yielding its rows one at a time.
+    ///
+    /// Unlike `execute`, rows are produced as the stream is advanced, and only
+    /// the rows seeded by one triple are held at once, so memory stays bounded
+    /// whatever the size of the result. Rows, their order, and the effect of
+    /// the limit and offset are the same as `execute`'s. Like cursors, the
+    /// stream seeds from a full primary index scan, whatever the plan says
+    /// for the first pattern.
+    ///
+    /// The stream borrows the snapshot and the query for `'a`, so the
+    /// snapshot cannot be closed until the stream is dropped (see `stream`).
+    /// A query without WHERE patterns is evaluated up front, and so are
+    /// ordered and aggregate queries, since their first row is only known
+    /// once every row is.
+    ///
+    /// # Errors
+    ///
+    /// Returns `QueryError::NonNumericAggregate` if a sum or average meets a
+    /// value that is not a number, or an error if reading the snapshot fails.
+    pub fn execute_streaming(&self, query: &'a Query) -> Result<RowStream<'a, 'b>, QueryError> {
+        let rows = if query.is_aggregate() {
+            self.aggregate_rows(query)?
+        } else if !query.order_by.is_empty() {
+            let contexts = self.sorted_contexts(query)?;
+            contexts.iter().map(|ctx| project(query, ctx)).collect()
+        } else if query.where_patterns.is_empty() {
+            let contexts = self.evaluate(query, &[], vec![QueryContext::new()])?;
+            contexts.iter().map(|ctx| project(query, ctx)).collect()
+        } else {
+            let seeds = self.snapshot.iter_all()?;
+            return Ok(RowStream::new(
+                self.snapshot,
+                query,
+                Some(seeds),
+                Vec::new(),
+            ));
+        };
+        Ok(RowStream::new(self.snapshot, query, None, rows))
+    }
+
+    /// Describe which index `execute` reads for each pattern of `query`.
+    ///
+    /// Does not read the database. Cursors (`open_cursor`) always seed from a
+    /// full primary index scan, whatever the plan says for the first pattern.
+    #[must_use]
+    pub fn explain(&self, query: &Query) -> QueryPlan {
+        QueryPlan::new(query)
+    }
+
+    /// Open a server-side cursor over `query`, pinning this engine's snapshot.
+    ///
+    /// Rows are fetched in batches with `CursorTable::fetch`, which resumes
+    /// from the stored scan position over the same pinned snapshot.
+    ///
+    /// # Errors
+    ///
+    /// Returns any error from `CursorTable::check_query`, or
+    /// `CursorError::LockPoisoned` if the table's lock is poisoned.
+    pub fn open_cursor(
+        &self,
+        cursors: &CursorTable,
+        query: Query,
+    ) -> Result<CursorId, CursorError> {
+        cursors.open(self.snapshot, query)
+    }
+
+    /// Produce up to `max_rows` result rows, resuming from `start`.
+    ///
+    /// Scans the primary index in key order and treats each triple matching the
+    /// first WHERE pattern as a seed for the rest of the query, so the scan can
+    /// stop and resume at any seed. Returns the rows and the position to resume
+    /// from, or `None` once every seed has been processed.
+    ///
+    /// # Pre-conditions
+    /// - `query.where_patterns` is non-empty
+    /// - `max_rows > 0`
+    pub(super) fn fetch_page(
+        &self,
+        query: &Query,
+        start: Option<&CursorPosition>,
+        max_rows: usize,
+    ) -> Result<(QueryResult, Option<CursorPosition>), DatabaseError> {
+        let mut result = QueryResult::with_columns(result_columns(query));
+        let (start_entity, start_field) = start.map_or_else(
+            || (EntityId::default(), FieldId::default()),
+            |position| (position.entity_id, position.attribute_id),
+        );
+        let mut scan = self.snapshot.scan_from(&start_entity, &start_field)?;
+
+        while let Some(record) = scan.next_record()? {
+            let value_hash = record.value_hash();
+            let triple = record_to_triple(record);
+            // The scan starts at the first value of the resumed attribute
+            let resumed_attribute = start.filter(|position| {
+                position.entity_id == triple.entity && position.attribute_id == triple.field
+            });
+            if resumed_attribute.is_some_and(|position| value_hash < position.value_hash) {
+                continue;
+            }
+            let contexts = self.seed_contexts(query, &triple)?;
+
+            // Rows of a partly fetched seed that were already returned
+            let already_fetched = resumed_attribute
+                .filter(|position| position.value_hash == value_hash)
+                .map_or(0, |position| position.rows_fetched);
+
+            for (index, ctx) in contexts.iter().enumerate().skip(already_fetched) {
+                if result.len() == max_rows {
+                    let position = CursorPosition {
+                        entity_id: triple.entity,
+                        attribute_id: triple.field,
+                        value_hash,
+                        rows_fetched: index,
+                    };
+                    return Ok((result, Some(position)));
+                }
+                result.push(project(query, ctx));
+            }
+        }
+
+        Ok((result, None))
+    }
+
+    /// Run `query` with `triple` as the match of its first WHERE pattern.
+    ///
+    /// Returns the contexts of the rows `triple` seeds, in result order: none
+    /// if it does not match the first pattern, belongs to a reserved entity,
+    /// or if `query` has no WHERE patterns.
+    pub(super) fn seed_contexts(
+        &self,
+        query: &Query,
+        triple: &Triple,
+    ) -> Result<Vec<QueryContext>, DatabaseError> {
+        let Some((seed_pattern, rest)) = query.where_patterns.split_first() else {
+            return Ok(Vec::new());
+        };
+        if is_reserved_entity(&triple.entity) {
+            return Ok(Vec::new());
+        }
+        match self.try_match_triple(seed_pattern, triple, &QueryContext::new()) {
+            Some(seed) => self.evaluate(query, rest, vec![seed]),
+            None => Ok(Vec::new()),
+        }
+    }
+
+    /// Match the first WHERE pattern of `query`, returning its matches as
+    /// seeds, in primary key order, and the patterns left to evaluate.
+    ///
+    /// A pattern with only its attribute bound reads the attribute index one
+    /// entity at a time, and one with neither its entity nor its attribute
+    /// bound scans the primary index, as the seeds are taken. Other patterns
+    /// are matched up front: a bound entity or entity set reads only those
+    /// entities, and a value range's index entries are in value order, so
+    /// they are sorted into primary key order first. A query without WHERE
+    /// patterns has a single, empty seed.
+    fn seeds<'q>(&self, query: &'q Query) -> Result<(Seeds<'q, 'b>, &'q [Pattern]), DatabaseError> {
+        let Some((first, rest)) = query.where_patterns.split_first() else {
+            return Ok((Seeds::matched(None, vec![QueryContext::new()]), &[]));
+        };
+        let range = query.value_range(first);
+        let ctx = QueryContext::new();
+        let entity_bound = matches!(first.entity, PatternElement::EntitySet { .. })
+            || self.resolve_entity(&first.entity, &ctx).is_some();
+        let is_presence = matches!(first.value, PatternElement::Any);
+        let source = match self.resolve_field(&first.field, &ctx) {
+            _ if entity_bound => None,
+            Some(field_id) if range.is_none() || is_presence => Some(SeedSource::Entities(
+                self.snapshot.iter_entities_with_attribute(&field_id)?,
+                field_id,
+            )),
+            Some(_) => None,
+            None => Some(SeedSource::Triples(self.snapshot.iter_all()?)),
+        };
+        let seeds = match source {
+            Some(source) => Seeds {
+                pattern: Some(first),
+                source,
+                pending: Vec::new().into_iter(),
+            },
+            None => Seeds::matched(Some(first), self.match_pattern(first, range, &ctx)?),
+        };
+        Ok((seeds, rest))
+    }
+
+    /// Match `pattern`, with only its attribute `field_id` bound, against
+    /// one entity, in primary key order.
+    fn entity_seeds(
+        &self,
+        pattern: &Pattern,
+        entity_id: &EntityId,
+        field_id: &FieldId,
+    ) -> Result<Vec<QueryContext>, DatabaseError> {
+        let ctx = QueryContext::new();
+        if matches!(pattern.value, PatternElement::Any) {
+            let seed = self.presence_match(pattern, entity_id, field_id, &ctx)?;
+            return Ok(seed.into_iter().collect());
+        }
+        let records = self.snapshot.get_values(entity_id, field_id)?;
+        Ok(in_primary_key_order(records)
+            .iter()
+            .filter_map(|triple| self.try_match_triple(pattern, triple, &ctx))
+            .collect())
+    }
+
+    /// Compute the groups and aggregates of `query` over every row it
+    /// matches.
+    ///
+    /// Rows are evaluated one seed at a time and folded into their group as
+    /// they are produced, so beyond the seeds `seeds` matches up front, only
+    /// one seed's rows are held at once. Returns a row per group (see
+    /// `aggregate`).
+    fn aggregate_rows(&self, query: &Query) -> Result<Vec<QueryRow>, QueryError> {
+        let mut groups = Groups::new(query);
+        let (mut seeds, rest) = self.seeds(query)?;
+        while let Some(seed) = seeds.next_seed(self)? {
+            for ctx in self.evaluate(query, rest, vec![seed])? {
+                groups.add(&ctx)?;
+            }
+        }
+        Ok(groups.finish())
+    }
+
+    /// Evaluate every row of `query`, sorted by its `order_by` keys.
+    ///
+    /// The sort is stable, so rows tied on every key stay in canonical order.
+    /// A distinct query drops duplicate rows before sorting.
+    fn sorted_contexts(&self, query: &Query) -> Result<Vec<QueryContext>, DatabaseError> {
+        let mut contexts =
+            self.evaluate(query, &query.where_patterns, vec![QueryContext::new()])?;
+        if query.distinct {
+            let mut seen = HashSet::new();
+            contexts.retain(|ctx| seen.insert(row_key(query.find.iter().map(|var| ctx.get(var)))));
+        }
+        contexts.sort_by(|a, b| {
+            query
+                .order_by
+                .iter()
+                .fold(Ordering::Equal, |ordering, order| {
+                    ordering
+                        .then_with(|| order.compare(a.get(&order.variable), b.get(&order.variable)))
+                })
+        });
+        Ok(contexts)
+    }
 
+    /// Run `where_patterns` and the remaining clauses of `query` over `contexts`.
+    fn evaluate(
+        &self,
+        query: &Query,
+        where_patterns: &[Pattern],
+        mut contexts: Vec<QueryContext>,
+    ) -> Result<Vec<QueryContext>, DatabaseError> {
         // Process WHERE patterns (required)
-        for pattern in &query.where_patterns {
-            contexts = self.match_pattern_all(pattern, contexts)?;
+        for pattern in where_patterns {
+            let range = query.value_range(pattern);
+            contexts = self.match_pattern_all(pattern, range, contexts)?;
             if contexts.is_empty() {
-                return Ok(QueryResult::with_columns(
-                    query
-                        .find
-                        .iter()
-                        .map(|v| v.name.as_str().to_owned())
-                        .collect(),
-                ));
+                return Ok(contexts);
             }
         }
 
@@ -67,36 +408,22 @@ impl<'a, 'b> QueryEngine<'a, 'b> {
             });
         }
 
-        // Build result
-        let columns: Vec<String> = query
-            .find
-            .iter()
-            .map(|v| v.name.as_str().to_owned())
-            .collect();
-        let mut result = QueryResult::with_columns(columns);
-
-        for ctx in contexts {
-            let row: QueryRow = query
-                .find
-                .iter()
-                .map(|var| ctx.get(var).map(Datom::clone_value))
-                .collect();
-            result.push(row);
-        }
-
-        Ok(result)
+        Ok(contexts)
     }
 
     /// Match a pattern against all triples, extending each context.
+    ///
+    /// If `range` is set, triples whose value is outside it may be skipped.
     fn match_pattern_all(
         &self,
         pattern: &Pattern,
+        range: Option<&ValueRange>,
         contexts: Vec<QueryContext>,
     ) -> Result<Vec<QueryContext>, DatabaseError> {
         let mut new_contexts = Vec::new();
 
         for ctx in contexts {
-            let matches = self.match_pattern(pattern, &ctx)?;
+            let matches = self.match_pattern(pattern, range, &ctx)?;
             new_contexts.extend(matches);
         }
 
@@ -104,12 +431,21 @@ impl<'a, 'b> QueryEngine<'a, 'b> {
     }
 
     /// Match a pattern against all triples with the given context.
+    ///
+    /// If `range` is set, triples whose value is outside it may be skipped.
     fn match_pattern(
         &self,
         pattern: &Pattern,
+        range: Option<&ValueRange>,
         ctx: &QueryContext,
     ) -> Result<Vec<QueryContext>, DatabaseError> {
-        let triples = self.get_candidate_triples(pattern, ctx)?;
+        if matches!(pattern.value, PatternElement::Any)
+            && let Some(field_id) = self.resolve_field(&pattern.field, ctx)
+        {
+            return self.match_presence(pattern, &field_id, ctx);
+        }
+
+        let triples = self.get_candidate_triples(pattern, range, ctx)?;
         let mut results = Vec::new();
 
         for triple in triples {
@@ -121,42 +457,140 @@ impl<'a, 'b> QueryEngine<'a, 'b> {
         Ok(results)
     }
 
+    /// Match a pattern whose value is `Any` and whose attribute is known.
+    ///
+    /// Only checks which entities have the attribute, without reading values:
+    /// the candidates are the pattern's entity, its entity set, or, if the
+    /// entity is not bound, the entities the attribute index lists. Each is
+    /// confirmed with a header-only primary index lookup, which also skips
+    /// expired triples.
+    ///
+    /// # Post-conditions
+    /// - The contexts are in entity order, as `match_pattern` would return them
+    fn match_presence(
+        &self,
+        pattern: &Pattern,
+        field_id: &FieldId,
+        ctx: &QueryContext,
+    ) -> Result<Vec<QueryContext>, DatabaseError> {
+        let candidates: Vec<EntityId> = match &pattern.entity {
+            PatternElement::EntitySet { ids, .. } => ids.iter().copied().collect(),
+            element => match self.resolve_entity(element, ctx) {
+                Some(entity_id) => vec![entity_id],
+                None => self.snapshot.get_entities_with_attribute(field_id)?,
+            },
+        };
+
+        let mut results = Vec::new();
+        for entity_id in candidates {
+            results.extend(self.presence_match(pattern, &entity_id, field_id, ctx)?);
+        }
+        Ok(results)
+    }
+
+    /// Match a presence pattern against one entity, binding the entity and
+    /// attribute if the entity has the attribute.
+    fn presence_match(
+        &self,
+        pattern: &Pattern,
+        entity_id: &EntityId,
+        field_id: &FieldId,
+        ctx: &QueryContext,
+    ) -> Result<Option<QueryContext>, DatabaseError> {
+        let mut new_ctx = ctx.clone_value();
+        let matched = self.match_entity_element(&pattern.entity, entity_id, &mut new_ctx)
+            && self.match_field_element(&pattern.field, field_id, &mut new_ctx)
+            && self.snapshot.contains(entity_id, field_id)?;
+        Ok(matched.then_some(new_ctx))
+    }
+
     /// Get candidate triples based on pattern constraints.
+    ///
+    /// # Post-conditions
+    /// - The triples are in primary key order, whichever index produced them
+    /// - The index read is `AccessPath::for_bindings` of whether the entity
+    ///   and attribute resolve, or `AccessPath::AttributeRangeScan` if only
+    ///   the attribute does and `range` is set, as `explain` reports
     fn get_candidate_triples(
         &self,
         pattern: &Pattern,
+        range: Option<&ValueRange>,
         ctx: &QueryContext,
     ) -> Result<Vec<Triple>, DatabaseError> {
-        // Try to use entity index if we have a concrete entity
-        if let Some(entity_id) = self.resolve_entity(&pattern.entity, ctx) {
-            if let Some(field_id) = self.resolve_field(&pattern.field, ctx) {
-                // Most specific: entity + field lookup
-                if let Some(record) = self.snapshot.get(&entity_id, &field_id)? {
-                    return Ok(vec![record_to_triple(record)]);
-                }
-                return Ok(Vec::new());
+        let field_id = self.resolve_field(&pattern.field, ctx);
+        if let PatternElement::EntitySet { ids, variable } = &pattern.entity {
+            // A variable bound by an earlier pattern narrows the set to its entity
+            let bound = variable.as_ref().and_then(|var| match ctx.get(var) {
+                Some(Datom::Entity(id)) => Some(*id),
+                _ => None,
+            });
+            let ids: Vec<EntityId> = match bound {
+                Some(id) => ids.iter().copied().filter(|entity| *entity == id).collect(),
+                None => ids.iter().copied().collect(),
+            };
+            return self.get_entity_set_triples(&ids, field_id.as_ref());
+        }
+        let entity_id = self.resolve_entity(&pattern.entity, ctx);
+        match (entity_id, field_id) {
+            // Most specific: entity + field lookup, one triple per value
+            (Some(entity_id), Some(field_id)) => {
+                let records = self.snapshot.get_values(&entity_id, &field_id)?;
+                Ok(in_primary_key_order(records))
             }
             // Entity-only scan
-            let records = self.snapshot.scan_entity(&entity_id)?;
-            return Ok(records.into_iter().map(record_to_triple).collect());
-        }
-
-        // Try attribute index if we have a concrete field but no entity
-        if let Some(field_id) = self.resolve_field(&pattern.field, ctx) {
-            // Use attribute index to get all entities with this attribute
-            let entity_ids = self.snapshot.get_entities_with_attribute(&field_id)?;
-            let mut triples = Vec::new();
-            for entity_id in entity_ids {
-                if let Some(record) = self.snapshot.get(&entity_id, &field_id)? {
-                    triples.push(record_to_triple(record));
+            (Some(entity_id), None) => {
+                let records = self.snapshot.scan_entity(&entity_id)?;
+                Ok(in_primary_key_order(records))
+            }
+            // Attribute-only with a range: the attribute index's value entries
+            // list the entities with a value in it
+            (None, Some(field_id)) if let Some(range) = range => {
+                let records = self.snapshot.scan_attribute_range(&field_id, range)?;
+                Ok(in_primary_key_order(records))
+            }
+            // Attribute-only: the attribute index lists the entities with the
+            // attribute, then a point lookup fetches each one's values
+            (None, Some(field_id)) => {
+                let entity_ids = self.snapshot.get_entities_with_attribute(&field_id)?;
+                let mut records = Vec::new();
+                for entity_id in entity_ids {
+                    records.extend(self.snapshot.get_values(&entity_id, &field_id)?);
                 }
+                Ok(in_primary_key_order(records))
+            }
+            // Fall back to scanning all triples
+            (None, None) => {
+                let records = self.snapshot.collect_all()?;
+                Ok(in_primary_key_order(records))
             }
-            return Ok(triples);
         }
+    }
 
-        // Fall back to scanning all triples
-        let records = self.snapshot.collect_all()?;
-        Ok(records.into_iter().map(record_to_triple).collect())
+    /// Get the triples of each entity in a set, for an entity-set pattern.
+    ///
+    /// Reads each entity with its own primary index lookup: a point lookup
+    /// if the field is bound, otherwise a scan of the entity. Entities
+    /// without a matching triple contribute nothing.
+    ///
+    /// # Pre-conditions
+    /// - `entity_ids` is sorted and free of duplicates
+    ///
+    /// # Post-conditions
+    /// - The triples are in primary key order
+    fn get_entity_set_triples(
+        &self,
+        entity_ids: &[EntityId],
+        field_id: Option<&FieldId>,
+    ) -> Result<Vec<Triple>, DatabaseError> {
+        let mut triples = Vec::new();
+        for entity_id in entity_ids {
+            let records = match field_id {
+                Some(field_id) => self.snapshot.get_values(entity_id, field_id)?,
+                None => self.snapshot.scan_entity(entity_id)?,
+            };
+            triples.extend(in_primary_key_order(records));
+        }
+        Ok(triples)
     }
 
     /// Try to resolve a pattern element to an entity ID.
@@ -220,6 +654,22 @@ impl<'a, 'b> QueryEngine<'a, 'b> {
     ) -> bool {
         match element {
             PatternElement::Entity(id) => id == entity,
+            PatternElement::EntitySet { ids, variable } => {
+                if !ids.contains(entity) {
+                    return false;
+                }
+                let Some(var) = variable else {
+                    return true;
+                };
+                match ctx.get(var) {
+                    Some(Datom::Entity(id)) => id == entity,
+                    Some(_) => false,
+                    None => {
+                        ctx.set(var, Datom::Entity(*entity));
+                        true
+                    }
+                }
+            }
             PatternElement::Variable(var) => {
                 if let Some(bound) = ctx.get(var) {
                     // Variable already bound - check consistency
@@ -269,6 +719,7 @@ impl<'a, 'b> QueryEngine<'a, 'b> {
         ctx: &mut QueryContext,
     ) -> bool {
         match element {
+            PatternElement::Any => true,
             PatternElement::Value(v) => values_equal(v, value),
             PatternElement::Variable(var) => {
                 if let Some(bound) = ctx.get(var) {
@@ -302,7 +753,7 @@ impl<'a, 'b> QueryEngine<'a, 'b> {
         let mut results = Vec::new();
 
         for ctx in contexts {
-            let matches = self.match_pattern(pattern, &ctx)?;
+            let matches = self.match_pattern(pattern, None, &ctx)?;
             if matches.is_empty() {
                 // No matches - keep original context (left join behavior)
                 results.push(ctx);
@@ -324,7 +775,7 @@ impl<'a, 'b> QueryEngine<'a, 'b> {
         let mut results = Vec::new();
 
         for ctx in contexts {
-            let matches = self.match_pattern(pattern, &ctx)?;
+            let matches = self.match_pattern(pattern, None, &ctx)?;
             if matches.is_empty() {
                 // Keep only contexts with no matches
                 results.push(ctx);
@@ -335,11 +786,59 @@ impl<'a, 'b> QueryEngine<'a, 'b> {
     }
 }
 
+/// Column names of a query's result: the `find` variables, then the
+/// `group_by` variables, then the aggregates, in order.
+pub(super) fn result_columns(query: &Query) -> Vec<String> {
+    query
+        .find
+        .iter()
+        .map(|v| v.name.as_str().to_owned())
+        .chain(query.group_by.iter().map(|v| v.name.as_str().to_owned()))
+        .chain(query.aggregates.iter().map(Aggregate::column))
+        .collect()
+}
+
+/// Build a result row from the `find` variables bound in `ctx`.
+pub(super) fn project(query: &Query, ctx: &QueryContext) -> QueryRow {
+    query
+        .find
+        .iter()
+        .map(|var| ctx.get(var).map(Datom::clone_value))
+        .collect()
+}
+
+/// Serialize a row's values into a key that is equal for two rows exactly
+/// when their values are, counting null and unbound values as equal.
+///
+/// Each value is tagged with its kind, and serialized values are
+/// self-delimiting, so the concatenation is unambiguous.
+pub(super) fn row_key<'d>(values: impl IntoIterator<Item = Option<&'d Datom>>) -> Vec<u8> {
+    let mut key = Vec::new();
+    for value in values {
+        match value {
+            None | Some(Datom::Value(Value::Null)) => key.push(0),
+            Some(Datom::Value(value)) => {
+                key.push(1);
+                key.extend(value.to_bytes());
+            }
+            Some(Datom::Entity(id)) => {
+                key.push(2);
+                key.extend_from_slice(&id.0);
+            }
+            Some(Datom::Field(id)) => {
+                key.push(3);
+                key.extend_from_slice(&id.0);
+            }
+        }
+    }
+    key
+}
+
 /// Convert a storage `TripleRecord` to a query `Triple`.
 ///
 /// Since query types are now unified with storage types, this is a simple
 /// field extraction.
-fn record_to_triple(record: TripleRecord) -> Triple {
+pub(super) fn record_to_triple(record: TripleRecord) -> Triple {
     Triple {
         entity: record.entity_id,
         field: record.attribute_id,
@@ -354,18 +853,119 @@ fn values_equal(a: &Value, b: &Value) -> bool {
         (Value::Boolean(x), Value::Boolean(y)) => x == y,
         (Value::Number(x), Value::Number(y)) => (x - y).abs() < f64::EPSILON,
         (Value::String(x), Value::String(y)) => x == y,
+        (Value::Bytes(x), Value::Bytes(y)) => x == y,
         (Value::Ref(x), Value::Ref(y)) => x == y,
         _ => false,
     }
 }
 
+/// Sort `records` by primary key and convert them to triples, so that result
+/// order never depends on the index that produced them or on its physical
+/// layout.
+///
+/// Drops the triples of reserved entities, which describe attributes rather
+/// than user data. Index scans already yield key order, so this is a linear
+/// pass in practice.
+fn in_primary_key_order(mut records: Vec<TripleRecord>) -> Vec<Triple> {
+    records.retain(|record| !is_reserved_entity(&record.entity_id));
+    records.sort_by_key(|record| {
+        (
+            record.entity_id.0,
+            record.attribute_id.0,
+            record.value_hash(),
+        )
+    });
+    records.into_iter().map(record_to_triple).collect()
+}
+
+/// Matches of a query's first WHERE pattern, in primary key order, read as
+/// they are taken.
+///
+/// Created by `QueryEngine::seeds`.
+struct Seeds<'q, 'b> {
+    /// The pattern matched, or `None` for a query without WHERE patterns.
+    pattern: Option<&'q Pattern>,
+    /// Where candidates are read from once `pending` runs out.
+    source: SeedSource<'b>,
+    /// Matches read but not yet taken.
+    pending: std::vec::IntoIter<QueryContext>,
+}
+
+/// The index `Seeds` reads candidate triples from.
+enum SeedSource<'b> {
+    /// Every match is already pending.
+    Matched,
+    /// The entities with an attribute, from the attribute index; each
+    /// entity's values of it are read with a point lookup.
+    Entities(AttributeEntityIterator<'b>, FieldId),
+    /// Every triple, from the primary index.
+    Triples(TripleIterator<'b>),
+}
+
+impl<'q, 'b> Seeds<'q, 'b> {
+    /// Seeds that were all matched up front.
+    fn matched(pattern: Option<&'q Pattern>, matches: Vec<QueryContext>) -> Self {
+        Self {
+            pattern,
+            source: SeedSource::Matched,
+            pending: matches.into_iter(),
+        }
+    }
+
+    /// Take the next seed, reading candidates from the source until one
+    /// matches, or `None` when every candidate has been read.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if reading the snapshot fails.
+    fn next_seed(
+        &mut self,
+        engine: &QueryEngine<'_, 'b>,
+    ) -> Result<Option<QueryContext>, DatabaseError> {
+        loop {
+            if let Some(seed) = self.pending.next() {
+                return Ok(Some(seed));
+            }
+            let Some(pattern) = self.pattern else {
+                return Ok(None);
+            };
+            let matches = match &mut self.source {
+                SeedSource::Matched => return Ok(None),
+                SeedSource::Entities(entities, field_id) => {
+                    let Some(entity_id) = entities.next_entity()? else {
+                        self.source = SeedSource::Matched;
+                        return Ok(None);
+                    };
+                    engine.entity_seeds(pattern, &entity_id, field_id)?
+                }
+                SeedSource::Triples(triples) => {
+                    let Some(record) = triples.next_record()? else {
+                        self.source = SeedSource::Matched;
+                        return Ok(None);
+                    };
+                    if is_reserved_entity(&record.entity_id) {
+                        continue;
+                    }
+                    let triple = record_to_triple(record);
+                    engine
+                        .try_match_triple(pattern, &triple, &QueryContext::new())
+                        .into_iter()
+                        .collect()
+                }
+            };
+            self.pending = matches.into_iter();
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::query::types::Variable;
+    use crate::query::plan::AccessPath;
+    use crate::query::types::{Direction, Variable};
     use crate::storage::Database;
     use crate::storage::buffer_pool::BufferPool;
-    use crate::types::{AttributeId, EntityId, TripleValue as StorageTripleValue};
+    use crate::types::{AttributeId, Cardinality, EntityId, TripleValue as StorageTripleValue};
     use std::sync::Arc;
     use tempfile::tempdir;
 
@@ -395,9 +995,12 @@ mod tests {
                 user1,
                 name_field,
                 StorageTripleValue::String("Alice".to_string()),
-            );
-            txn.insert(user1, age_field, StorageTripleValue::Number(30.0));
-            txn.insert(user1, active_field, StorageTripleValue::Boolean(true));
+            )
+            .expect("insert");
+            txn.insert(user1, age_field, StorageTripleValue::Number(30.0))
+                .expect("insert");
+            txn.insert(user1, active_field, StorageTripleValue::Boolean(true))
+                .expect("insert");
 
             // User 2: Bob
             let user2 = EntityId::from_string("user2");
@@ -405,9 +1008,12 @@ mod tests {
                 user2,
                 name_field,
                 StorageTripleValue::String("Bob".to_string()),
-            );
-            txn.insert(user2, age_field, StorageTripleValue::Number(25.0));
-            txn.insert(user2, active_field, StorageTripleValue::Boolean(false));
+            )
+            .expect("insert");
+            txn.insert(user2, age_field, StorageTripleValue::Number(25.0))
+                .expect("insert");
+            txn.insert(user2, active_field, StorageTripleValue::Boolean(false))
+                .expect("insert");
 
             // User 3: Charlie (no age)
             let user3 = EntityId::from_string("user3");
@@ -415,8 +1021,10 @@ mod tests {
                 user3,
                 name_field,
                 StorageTripleValue::String("Charlie".to_string()),
-            );
-            txn.insert(user3, active_field, StorageTripleValue::Boolean(true));
+            )
+            .expect("insert");
+            txn.insert(user3, active_field, StorageTripleValue::Boolean(true))
+                .expect("insert");
 
             txn.commit().expect("commit");
         }
@@ -431,7 +1039,7 @@ mod tests {
         let (db, _) = Database::open(&path, pool).expect("open db");
 
         let txn_id = {
-            let snapshot = db.begin_readonly();
+            let snapshot = db.begin_readonly().expect("begin readonly");
             let engine = QueryEngine::new(&snapshot);
 
             // Find all entity names
@@ -448,7 +1056,7 @@ mod tests {
             assert_eq!(result.len(), 3);
             snapshot.close()
         };
-        db.release_snapshot(txn_id);
+        db.release_snapshot(txn_id).expect("release snapshot");
     }
 
     #[test]
@@ -457,7 +1065,7 @@ mod tests {
         let (db, _) = Database::open(&path, pool).expect("open db");
 
         let txn_id = {
-            let snapshot = db.begin_readonly();
+            let snapshot = db.begin_readonly().expect("begin readonly");
             let engine = QueryEngine::new(&snapshot);
 
             // Find name for user1
@@ -474,7 +1082,120 @@ mod tests {
             assert!(matches!(name, Datom::Value(Value::String(s)) if s == "Alice"));
             snapshot.close()
         };
-        db.release_snapshot(txn_id);
+        db.release_snapshot(txn_id).expect("release snapshot");
+    }
+
+    #[test]
+    fn test_presence_and_absence_patterns() {
+        let (_dir, path, pool) = create_test_db_with_data();
+        let (db, _) = Database::open(&path, pool).expect("open db");
+        let entities = |result: &QueryResult| -> Vec<Datom> {
+            result
+                .rows
+                .iter()
+                .filter_map(|row| row[0].as_ref().map(Datom::clone_value))
+                .collect()
+        };
+
+        let txn_id = {
+            let snapshot = db.begin_readonly().expect("begin readonly");
+            let engine = QueryEngine::new(&snapshot);
+
+            // Presence: every entity with an age, whatever its value
+            let query = Query::new()
+                .find("e")
+                .has(PatternElement::var("e"), PatternElement::field("age"));
+            let result = engine.execute(&query).expect("execute");
+            assert_eq!(
+                entities(&result),
+                vec![Datom::entity("user1"), Datom::entity("user2")]
+            );
+
+            // Absence, joined to a pattern that binds the entity
+            let query = Query::new()
+                .find("e")
+                .find("name")
+                .where_pattern(Pattern::new(
+                    PatternElement::var("e"),
+                    PatternElement::field("name"),
+                    PatternElement::var("name"),
+                ))
+                .lacks(PatternElement::var("e"), PatternElement::field("age"));
+            let result = engine.execute(&query).expect("execute");
+            assert_eq!(entities(&result), vec![Datom::entity("user3")]);
+            assert_eq!(result.rows[0][1], Some(Datom::string("Charlie")));
+
+            // Both in one query: active entities without an age
+            let query = Query::new()
+                .find("e")
+                .has(PatternElement::var("e"), PatternElement::field("active"))
+                .lacks(PatternElement::var("e"), PatternElement::field("age"));
+            let result = engine.execute(&query).expect("execute");
+            assert_eq!(entities(&result), vec![Datom::entity("user3")]);
+
+            // A concrete entity checks one triple; a missing one matches nothing
+            let query = Query::new()
+                .find("e")
+                .has(PatternElement::var("e"), PatternElement::field("name"))
+                .has(
+                    PatternElement::entity("user3"),
+                    PatternElement::field("age"),
+                );
+            assert!(engine.execute(&query).expect("execute").is_empty());
+            snapshot.close()
+        };
+        db.release_snapshot(txn_id).expect("release snapshot");
+    }
+
+    #[test]
+    fn test_query_with_entity_set() {
+        let (_dir, path, pool) = create_test_db_with_data();
+        let (db, _) = Database::open(&path, pool).expect("open db");
+
+        let txn_id = {
+            let snapshot = db.begin_readonly().expect("begin readonly");
+            let engine = QueryEngine::new(&snapshot);
+
+            // user4 does not exist, and user3 has no age
+            let query = Query::new()
+                .find("e")
+                .find("age")
+                .where_pattern(Pattern::new(
+                    PatternElement::entities("e", &["user4", "user3", "user2", "user1"]),
+                    PatternElement::field("age"),
+                    PatternElement::var("age"),
+                ));
+            let result = engine.execute(&query).expect("execute");
+            assert_eq!(result.len(), 2);
+            assert_eq!(
+                result.rows[0][0],
+                Some(Datom::Entity(EntityId::from_string("user1")))
+            );
+            assert_eq!(result.rows[1][1], Some(Datom::number(25.0)));
+            assert_eq!(
+                engine.explain(&query).patterns[0].access_path,
+                AccessPath::PrimaryLookup
+            );
+
+            // A variable bound by an earlier pattern narrows the set
+            let query = Query::new()
+                .find("age")
+                .where_pattern(Pattern::new(
+                    PatternElement::var("e"),
+                    PatternElement::field("name"),
+                    PatternElement::string("Bob"),
+                ))
+                .where_pattern(Pattern::new(
+                    PatternElement::entities("e", &["user1", "user2"]),
+                    PatternElement::field("age"),
+                    PatternElement::var("age"),
+                ));
+            let result = engine.execute(&query).expect("execute");
+            assert_eq!(result.len(), 1);
+            assert_eq!(result.rows[0][0], Some(Datom::number(25.0)));
+            snapshot.close()
+        };
+        db.release_snapshot(txn_id).expect("release snapshot");
     }
 
     #[test]
@@ -483,7 +1204,7 @@ mod tests {
         let (db, _) = Database::open(&path, pool).expect("open db");
 
         let txn_id = {
-            let snapshot = db.begin_readonly();
+            let snapshot = db.begin_readonly().expect("begin readonly");
             let engine = QueryEngine::new(&snapshot);
 
             // Find entities with both name and age
@@ -506,7 +1227,55 @@ mod tests {
             assert_eq!(result.len(), 2); // Only user1 and user2 have age
             snapshot.close()
         };
-        db.release_snapshot(txn_id);
+        db.release_snapshot(txn_id).expect("release snapshot");
+    }
+
+    #[test]
+    fn test_explain_attribute_only_query_uses_attribute_index() {
+        let (_dir, path, pool) = create_test_db_with_data();
+        let (db, _) = Database::open(&path, pool).expect("open db");
+
+        let txn_id = {
+            let snapshot = db.begin_readonly().expect("begin readonly");
+            let engine = QueryEngine::new(&snapshot);
+
+            // Find every entity with an age, whatever the age
+            let query = Query::new()
+                .find("e")
+                .find("age")
+                .where_pattern(Pattern::new(
+                    PatternElement::var("e"),
+                    PatternElement::field("age"),
+                    PatternElement::var("age"),
+                ));
+
+            let plan = engine.explain(&query);
+            assert_eq!(plan.patterns.len(), 1);
+            assert_eq!(plan.patterns[0].access_path, AccessPath::AttributeIndexScan);
+            assert!(!plan.uses(AccessPath::FullScan));
+
+            let result = engine.execute(&query).expect("execute");
+            let ages: Vec<_> = result
+                .rows
+                .iter()
+                .map(|row| (row[0].as_ref(), row[1].as_ref()))
+                .collect();
+            assert_eq!(
+                ages,
+                vec![
+                    (
+                        Some(&Datom::Entity(EntityId::from_string("user1"))),
+                        Some(&Datom::Value(Value::Number(30.0)))
+                    ),
+                    (
+                        Some(&Datom::Entity(EntityId::from_string("user2"))),
+                        Some(&Datom::Value(Value::Number(25.0)))
+                    ),
+                ]
+            );
+            snapshot.close()
+        };
+        db.release_snapshot(txn_id).expect("release snapshot");
     }
 
     #[test]
@@ -515,7 +1284,7 @@ mod tests {
         let (db, _) = Database::open(&path, pool).expect("open db");
 
         let txn_id = {
-            let snapshot = db.begin_readonly();
+            let snapshot = db.begin_readonly().expect("begin readonly");
             let engine = QueryEngine::new(&snapshot);
 
             // Find all entities with name, optionally with age
@@ -546,7 +1315,7 @@ mod tests {
             assert!(charlie[2].is_none()); // No age
             snapshot.close()
         };
-        db.release_snapshot(txn_id);
+        db.release_snapshot(txn_id).expect("release snapshot");
     }
 
     #[test]
@@ -555,7 +1324,7 @@ mod tests {
         let (db, _) = Database::open(&path, pool).expect("open db");
 
         let txn_id = {
-            let snapshot = db.begin_readonly();
+            let snapshot = db.begin_readonly().expect("begin readonly");
             let engine = QueryEngine::new(&snapshot);
 
             // Find entities that don't have an age
@@ -580,7 +1349,305 @@ mod tests {
             assert!(matches!(name, Datom::Value(Value::String(s)) if s == "Charlie"));
             snapshot.close()
         };
-        db.release_snapshot(txn_id);
+        db.release_snapshot(txn_id).expect("release snapshot");
+    }
+
+    #[test]
+    fn test_limit_and_offset() {
+        let (_dir, path, pool) = create_test_db_with_data();
+        let (db, _) = Database::open(&path, pool).expect("open db");
+
+        let txn_id = {
+            let snapshot = db.begin_readonly().expect("begin readonly");
+            let engine = QueryEngine::new(&snapshot);
+            let names = |query: &Query| -> Vec<String> {
+                let result = engine.execute(query).expect("execute");
+                assert_eq!(result.columns, result_columns(query));
+                result
+                    .rows
+                    .iter()
+                    .map(|row| match &row[1] {
+                        Some(Datom::Value(Value::String(s))) => s.clone(),
+                        other => panic!("expected a name, got {other:?}"),
+                    })
+                    .collect()
+            };
+            let name_query = || {
+                Query::new()
+                    .find("e")
+                    .find("name")
+                    .where_pattern(Pattern::new(
+                        PatternElement::var("e"),
+                        PatternElement::field("name"),
+                        PatternElement::var("name"),
+                    ))
+            };
+
+            assert_eq!(names(&name_query().limit(2)), vec!["Alice", "Bob"]);
+            assert_eq!(names(&name_query().offset(1).limit(1)), vec!["Bob"]);
+            assert!(names(&name_query().limit(0)).is_empty());
+            assert!(names(&name_query().offset(5)).is_empty());
+
+            // Rows skipped or cut off by the limit are still matched against
+            // the OPTIONAL and WHERE-NOT patterns first
+            let query = name_query()
+                .find("age")
+                .optional(Pattern::new(
+                    PatternElement::var("e"),
+                    PatternElement::field("age"),
+                    PatternElement::var("age"),
+                ))
+                .offset(2);
+            let result = engine.execute(&query).expect("execute");
+            assert_eq!(result.len(), 1);
+            assert!(result.rows[0][2].is_none());
+
+            let query = name_query()
+                .where_not(Pattern::new(
+                    PatternElement::var("e"),
+                    PatternElement::field("age"),
+                    PatternElement::var("_age"),
+                ))
+                .limit(1);
+            assert_eq!(names(&query), vec!["Charlie"]);
+            snapshot.close()
+        };
+        db.release_snapshot(txn_id).expect("release snapshot");
+    }
+
+    #[test]
+    fn test_seeds_are_read_as_taken() {
+        let (_dir, path, pool) = create_test_db_with_data();
+        let (db, _) = Database::open(&path, pool).expect("open db");
+
+        let txn_id = {
+            let snapshot = db.begin_readonly().expect("begin readonly");
+            let engine = QueryEngine::new(&snapshot);
+            // The attribute index, then the primary index, seeds these
+            for field in [PatternElement::field("name"), PatternElement::var("field")] {
+                let query = Query::new().find("e").where_pattern(Pattern::new(
+                    PatternElement::var("e"),
+                    field,
+                    PatternElement::var("value"),
+                ));
+                let (mut seeds, rest) = engine.seeds(&query).expect("seeds");
+                assert!(rest.is_empty());
+                assert!(seeds.next_seed(&engine).expect("next seed").is_some());
+                // Only the first candidate's single match has been read
+                assert_eq!(seeds.pending.len(), 0);
+                assert!(!matches!(seeds.source, SeedSource::Matched));
+            }
+            snapshot.close()
+        };
+        db.release_snapshot(txn_id).expect("release snapshot");
+    }
+
+    #[test]
+    fn test_aggregates() {
+        let (_dir, path, pool) = create_test_db_with_data();
+        let (db, _) = Database::open(&path, pool).expect("open db");
+
+        let txn_id = {
+            let snapshot = db.begin_readonly().expect("begin readonly");
+            let engine = QueryEngine::new(&snapshot);
+            let pattern = |field: &str, value: &str| {
+                Pattern::new(
+                    PatternElement::var("e"),
+                    PatternElement::field(field),
+                    PatternElement::var(value),
+                )
+            };
+
+            // Charlie has no age, so is counted but not summed
+            let query = Query::new()
+                .find_count("e")
+                .find_sum("age")
+                .find_min("age")
+                .find_max("age")
+                .find_avg("age")
+                .where_pattern(pattern("name", "name"))
+                .optional(pattern("age", "age"));
+            let result = engine.execute(&query).expect("execute");
+            assert_eq!(
+                result.columns,
+                vec!["count(e)", "sum(age)", "min(age)", "max(age)", "avg(age)"]
+            );
+            assert_eq!(
+                result.rows,
+                vec![vec![
+                    Some(Datom::number(3)),
+                    Some(Datom::number(55)),
+                    Some(Datom::number(25)),
+                    Some(Datom::number(30)),
+                    Some(Datom::number(27.5)),
+                ]]
+            );
+            assert!(
+                engine
+                    .execute(&query.offset(1))
+                    .expect("execute")
+                    .is_empty()
+            );
+
+            // Rows excluded by WHERE-NOT patterns are not aggregated
+            let query = Query::new()
+                .find_count("e")
+                .where_pattern(pattern("name", "name"))
+                .where_not(pattern("age", "_age"));
+            let result = engine.execute(&query).expect("execute");
+            assert_eq!(result.rows, vec![vec![Some(Datom::number(1))]]);
+
+            let query = Query::new()
+                .find_count("v")
+                .find_sum("v")
+                .find_min("v")
+                .find_max("v")
+                .find_avg("v")
+                .where_pattern(pattern("missing", "v"));
+            let result = engine.execute(&query).expect("execute");
+            assert_eq!(
+                result.rows,
+                vec![vec![
+                    Some(Datom::number(0)),
+                    Some(Datom::number(0)),
+                    None,
+                    None,
+                    None
+                ]]
+            );
+
+            let query = Query::new()
+                .find_sum("name")
+                .where_pattern(pattern("name", "name"));
+            let error = engine.execute(&query).expect_err("names are not numbers");
+            assert!(
+                matches!(&error, QueryError::NonNumericAggregate { aggregate, .. } if aggregate == "sum(name)"),
+                "{error}"
+            );
+            snapshot.close()
+        };
+        db.release_snapshot(txn_id).expect("release snapshot");
+    }
+
+    #[test]
+    fn test_grouped_aggregates() {
+        let (_dir, path, pool) = create_test_db_with_data();
+        let (db, _) = Database::open(&path, pool).expect("open db");
+
+        let txn_id = {
+            let snapshot = db.begin_readonly().expect("begin readonly");
+            let engine = QueryEngine::new(&snapshot);
+            let pattern = |field: &str, value: &str| {
+                Pattern::new(
+                    PatternElement::var("e"),
+                    PatternElement::field(field),
+                    PatternElement::var(value),
+                )
+            };
+            let grouped = || {
+                Query::new()
+                    .group_by("active")
+                    .find_count("e")
+                    .find_sum("age")
+                    .where_pattern(pattern("active", "active"))
+                    .optional(pattern("age", "age"))
+            };
+
+            // Alice and Charlie are active, Bob is not; groups come in the
+            // order of their first row
+            let result = engine.execute(&grouped()).expect("execute");
+            assert_eq!(result.columns, vec!["active", "count(e)", "sum(age)"]);
+            assert_eq!(
+                result.rows,
+                vec![
+                    vec![
+                        Some(Datom::boolean(true)),
+                        Some(Datom::number(2)),
+                        Some(Datom::number(30)),
+                    ],
+                    vec![
+                        Some(Datom::boolean(false)),
+                        Some(Datom::number(1)),
+                        Some(Datom::number(25)),
+                    ],
+                ]
+            );
+
+            // Excluded rows leave no group behind
+            let query = grouped().where_not(pattern("age", "_age"));
+            let result = engine.execute(&query).expect("execute");
+            assert_eq!(
+                result.rows,
+                vec![vec![
+                    Some(Datom::boolean(true)),
+                    Some(Datom::number(1)),
+                    Some(Datom::number(0)),
+                ]]
+            );
+
+            // Nothing matched, so there are no groups
+            let query = Query::new()
+                .group_by("v")
+                .find_count("e")
+                .where_pattern(pattern("missing", "v"));
+            assert!(engine.execute(&query).expect("execute").is_empty());
+            snapshot.close()
+        };
+        db.release_snapshot(txn_id).expect("release snapshot");
+    }
+
+    #[test]
+    fn test_distinct() {
+        let (_dir, path, pool) = create_test_db_with_data();
+        let (db, _) = Database::open(&path, pool).expect("open db");
+
+        let txn_id = {
+            let snapshot = db.begin_readonly().expect("begin readonly");
+            let engine = QueryEngine::new(&snapshot);
+            let active = |query: &Query| -> Vec<Option<Datom>> {
+                let result = engine.execute(query).expect("execute");
+                result
+                    .rows
+                    .into_iter()
+                    .map(|mut row| row.swap_remove(0))
+                    .collect()
+            };
+            // Alice and Charlie are active, Bob is not
+            let query = || {
+                Query::new().find("active").where_pattern(Pattern::new(
+                    PatternElement::var("e"),
+                    PatternElement::field("active"),
+                    PatternElement::var("active"),
+                ))
+            };
+            let yes = || Some(Datom::boolean(true));
+            let no = || Some(Datom::boolean(false));
+
+            assert_eq!(active(&query()).len(), 3);
+            assert_eq!(active(&query().distinct()), vec![yes(), no()]);
+            // The offset and limit count distinct rows
+            assert_eq!(active(&query().distinct().offset(1).limit(1)), vec![no()]);
+            assert_eq!(
+                active(&query().distinct().order_by("active", Direction::Ascending)),
+                vec![no(), yes()]
+            );
+            snapshot.close()
+        };
+        db.release_snapshot(txn_id).expect("release snapshot");
+    }
+
+    #[test]
+    fn test_row_key_separates_kinds() {
+        let string = Datom::string("a");
+        let entity = Datom::entity("a");
+        let null = Datom::Value(Value::Null);
+
+        assert_ne!(row_key([Some(&string)]), row_key([Some(&entity)]));
+        assert_eq!(row_key([None]), row_key([Some(&null)]));
+        assert_ne!(
+            row_key([Some(&string), None]),
+            row_key([None, Some(&string)])
+        );
     }
 
     #[test]
@@ -589,7 +1656,7 @@ mod tests {
         let (db, _) = Database::open(&path, pool).expect("open db");
 
         let txn_id = {
-            let snapshot = db.begin_readonly();
+            let snapshot = db.begin_readonly().expect("begin readonly");
             let engine = QueryEngine::new(&snapshot);
 
             // Find entities with age > 26
@@ -613,7 +1680,88 @@ mod tests {
             assert_eq!(result.len(), 1); // Only Alice (age 30)
             snapshot.close()
         };
-        db.release_snapshot(txn_id);
+        db.release_snapshot(txn_id).expect("release snapshot");
+    }
+
+    #[test]
+    fn test_range_filter_reads_only_values_in_range() {
+        let (_dir, path, pool) = create_test_db_with_data();
+        let (mut db, _) = Database::open(&path, pool).expect("open db");
+        // Move Bob's age across the range's lower bound
+        let mut txn = db.begin(0).expect("begin");
+        txn.update(
+            EntityId::from_string("user2"),
+            AttributeId::from_string("age"),
+            StorageTripleValue::Number(41.0),
+        )
+        .expect("update");
+        txn.commit().expect("commit");
+
+        let txn_id = {
+            let snapshot = db.begin_readonly().expect("begin readonly");
+            let engine = QueryEngine::new(&snapshot);
+
+            // Find entities with 26 < age <= 41
+            let query = Query::new()
+                .find("e")
+                .find("age")
+                .where_pattern(Pattern::new(
+                    PatternElement::var("e"),
+                    PatternElement::field("age"),
+                    PatternElement::var("age"),
+                ))
+                .filter(super::super::types::Filter::range(
+                    Variable::new("age"),
+                    ValueRange::new(
+                        std::ops::Bound::Excluded(Value::Number(26.0)),
+                        std::ops::Bound::Included(Value::Number(41.0)),
+                    ),
+                ));
+
+            assert_eq!(
+                engine.explain(&query).patterns[0].access_path,
+                AccessPath::AttributeRangeScan
+            );
+            let result = engine.execute(&query).expect("execute");
+            let rows: Vec<_> = result
+                .rows
+                .iter()
+                .map(|row| (row[0].as_ref(), row[1].as_ref()))
+                .collect();
+            assert_eq!(
+                rows,
+                vec![
+                    (
+                        Some(&Datom::Entity(EntityId::from_string("user1"))),
+                        Some(&Datom::Value(Value::Number(30.0)))
+                    ),
+                    (
+                        Some(&Datom::Entity(EntityId::from_string("user2"))),
+                        Some(&Datom::Value(Value::Number(41.0)))
+                    ),
+                ]
+            );
+
+            // Names before "Bob": only Alice
+            let query = Query::new()
+                .find("e")
+                .where_pattern(Pattern::new(
+                    PatternElement::var("e"),
+                    PatternElement::field("name"),
+                    PatternElement::var("name"),
+                ))
+                .filter(super::super::types::Filter::range(
+                    Variable::new("name"),
+                    ValueRange::less_than(Value::String("Bob".to_string())),
+                ));
+            let result = engine.execute(&query).expect("execute");
+            assert_eq!(
+                result.rows,
+                vec![vec![Some(Datom::Entity(EntityId::from_string("user1")))]]
+            );
+            snapshot.close()
+        };
+        db.release_snapshot(txn_id).expect("release snapshot");
     }
 
     #[test]
@@ -622,7 +1770,7 @@ mod tests {
         let (db, _) = Database::open(&path, pool).expect("open db");
 
         let txn_id = {
-            let snapshot = db.begin_readonly();
+            let snapshot = db.begin_readonly().expect("begin readonly");
             let engine = QueryEngine::new(&snapshot);
 
             // Find entity with name "Bob"
@@ -639,7 +1787,86 @@ mod tests {
             assert!(matches!(entity, Datom::Entity(id) if id.0[..4] == *b"user"));
             snapshot.close()
         };
-        db.release_snapshot(txn_id);
+        db.release_snapshot(txn_id).expect("release snapshot");
+    }
+
+    #[test]
+    fn test_bytes_value_match() {
+        let mut db = Database::create_in_memory(test_pool()).expect("create db");
+        let avatar = AttributeId::from_string("avatar");
+        let mut txn = db.begin(0).expect("begin");
+        for (n, bytes) in [(1u8, vec![1, 2, 3]), (2, vec![1, 2])] {
+            txn.insert(EntityId([n; 16]), avatar, StorageTripleValue::Bytes(bytes))
+                .expect("insert");
+        }
+        txn.commit().expect("commit");
+
+        let txn_id = {
+            let snapshot = db.begin_readonly().expect("begin readonly");
+            let query = Query::new().find("e").where_pattern(Pattern::new(
+                PatternElement::var("e"),
+                PatternElement::field("avatar"),
+                PatternElement::Value(Value::Bytes(vec![1, 2, 3])),
+            ));
+            let result = QueryEngine::new(&snapshot)
+                .execute(&query)
+                .expect("execute");
+            assert_eq!(
+                result.rows,
+                vec![vec![Some(Datom::Entity(EntityId([1; 16])))]]
+            );
+            snapshot.close()
+        };
+        db.release_snapshot(txn_id).expect("release snapshot");
+    }
+
+    #[test]
+    fn test_query_yields_each_value_of_a_cardinality_many_attribute() {
+        let mut db = Database::create_in_memory(test_pool()).expect("create db");
+        let tag = AttributeId::from_string("tag");
+        db.set_attribute_cardinality(tag, Cardinality::Many)
+            .expect("declare");
+        let mut txn = db.begin(0).expect("begin");
+        for (n, name) in [(1u8, "red"), (1, "blue"), (2, "red")] {
+            txn.insert(
+                EntityId([n; 16]),
+                tag,
+                StorageTripleValue::String(name.to_owned()),
+            )
+            .expect("insert");
+        }
+        txn.commit().expect("commit");
+
+        let txn_id = {
+            let snapshot = db.begin_readonly().expect("begin readonly");
+            let engine = QueryEngine::new(&snapshot);
+
+            let all_tags = Query::new()
+                .find("e")
+                .find("tag")
+                .where_pattern(Pattern::new(
+                    PatternElement::var("e"),
+                    PatternElement::field("tag"),
+                    PatternElement::var("tag"),
+                ));
+            assert_eq!(engine.execute(&all_tags).expect("execute").len(), 3);
+
+            let entity_tags = Query::new().find("tag").where_pattern(Pattern::new(
+                PatternElement::Entity(EntityId([1; 16])),
+                PatternElement::field("tag"),
+                PatternElement::var("tag"),
+            ));
+            assert_eq!(engine.execute(&entity_tags).expect("execute").len(), 2);
+
+            let tagged_red = Query::new().find("e").where_pattern(Pattern::new(
+                PatternElement::var("e"),
+                PatternElement::field("tag"),
+                PatternElement::string("red"),
+            ));
+            assert_eq!(engine.execute(&tagged_red).expect("execute").len(), 2);
+            snapshot.close()
+        };
+        db.release_snapshot(txn_id).expect("release snapshot");
     }
 
     #[test]
@@ -648,7 +1875,7 @@ mod tests {
         let (db, _) = Database::open(&path, pool).expect("open db");
 
         let txn_id = {
-            let snapshot = db.begin_readonly();
+            let snapshot = db.begin_readonly().expect("begin readonly");
             let engine = QueryEngine::new(&snapshot);
 
             // Find entity with name "Nobody"
@@ -662,7 +1889,7 @@ mod tests {
             assert!(result.is_empty());
             snapshot.close()
         };
-        db.release_snapshot(txn_id);
+        db.release_snapshot(txn_id).expect("release snapshot");
     }
 
     #[test]
@@ -674,7 +1901,7 @@ mod tests {
         let db = Database::create(&path, pool).expect("create db");
 
         let txn_id = {
-            let snapshot = db.begin_readonly();
+            let snapshot = db.begin_readonly().expect("begin readonly");
             let engine = QueryEngine::new(&snapshot);
 
             // Query should return empty results
@@ -691,7 +1918,81 @@ mod tests {
             assert!(result.is_empty());
             snapshot.close()
         };
-        db.release_snapshot(txn_id);
+        db.release_snapshot(txn_id).expect("release snapshot");
+    }
+
+    /// Entities bound to `?e` by a `[?e :name ?name]` query, in row order.
+    fn queried_entities(db: &Database) -> Vec<EntityId> {
+        let snapshot = db.begin_readonly().expect("begin readonly");
+        let query = Query::new().find("e").where_pattern(Pattern::new(
+            PatternElement::var("e"),
+            PatternElement::field("name"),
+            PatternElement::var("name"),
+        ));
+        let result = QueryEngine::new(&snapshot)
+            .execute(&query)
+            .expect("execute");
+        let entities = result
+            .rows
+            .iter()
+            .map(|row| match &row[0] {
+                Some(Datom::Entity(id)) => *id,
+                other => panic!("expected entity, got {other:?}"),
+            })
+            .collect();
+        db.release_snapshot(snapshot.close())
+            .expect("release snapshot");
+        entities
+    }
+
+    #[test]
+    fn test_result_order_survives_restructuring() {
+        let dir = tempdir().expect("create temp dir");
+        let path = dir.path().join("test.db");
+        let mut db = Database::create(&path, test_pool()).expect("create db");
+        let name_field = AttributeId::from_string("name");
+        let entity = |i: u32| {
+            let mut id = [0u8; 16];
+            id[..4].copy_from_slice(&i.to_be_bytes());
+            EntityId(id)
+        };
+
+        // Insert in reverse key order, enough to split index nodes
+        {
+            let mut txn = db.begin(0).expect("begin");
+            for i in (0..1000).rev() {
+                txn.insert(
+                    entity(i),
+                    name_field,
+                    StorageTripleValue::String(format!("user{i}")),
+                )
+                .expect("insert");
+            }
+            txn.commit().expect("commit");
+        }
+        let before = queried_entities(&db);
+        assert_eq!(before, (0..1000).map(entity).collect::<Vec<_>>());
+
+        // Delete a contiguous range and every third survivor, then GC to
+        // remove the records and merge the emptied nodes
+        {
+            let mut txn = db.begin(0).expect("begin");
+            for i in (0..1000).filter(|i| (200..600).contains(i) || i % 3 == 0) {
+                txn.delete(&entity(i), &name_field).expect("delete");
+            }
+            txn.commit().expect("commit");
+        }
+        db.force_gc().expect("gc");
+
+        let after = queried_entities(&db);
+        let expected: Vec<EntityId> = before
+            .into_iter()
+            .filter(|id| {
+                let i = u32::from_be_bytes([id.0[0], id.0[1], id.0[2], id.0[3]]);
+                !(200..600).contains(&i) && i % 3 != 0
+            })
+            .collect();
+        assert_eq!(after, expected);
     }
 
     #[test]
@@ -713,12 +2014,13 @@ mod tests {
                 user1,
                 name_field,
                 StorageTripleValue::String("Alice".to_string()),
-            );
+            )
+            .expect("insert");
             txn.commit().expect("commit");
         }
 
         // Create a snapshot at txn_id = 1
-        let snapshot = db.begin_readonly();
+        let snapshot = db.begin_readonly().expect("begin readonly");
 
         // Verify the initial data is visible
         {
@@ -733,6 +2035,6 @@ mod tests {
         }
 
         let txn_id = snapshot.close();
-        db.release_snapshot(txn_id);
+        db.release_snapshot(txn_id).expect("release snapshot");
     }
 }
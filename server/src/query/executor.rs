@@ -1,13 +1,21 @@
-//! Query execution engine.
+//! Legacy query executor.
 //!
-//! Executes queries against the storage engine and returns storage types directly.
+//! Executes lookups against a storage transaction and returns storage types
+//! directly. Superseded by `QueryEngine`, which runs full queries (including
+//! OPTIONAL, WHERE-NOT, and filters) over a read-only `Snapshot` and is what
+//! the connection handler uses for `QueryRequest`s.
+//!
+//! `QueryError` is shared with `QueryEngine::execute`, which returns it for
+//! queries that fail at run time.
 
-#![allow(dead_code)] // Query executor will be used when QueryRequest is implemented
+// The executor's own implementation necessarily refers to the deprecated type
+#![allow(deprecated)]
 
-use crate::storage::{Transaction, TransactionError};
+use crate::storage::{DatabaseError, Transaction, TransactionError};
 use crate::types::{AttributeId, EntityId, TripleRecord};
 
 /// A query executor that operates within a transaction.
+#[deprecated(note = "use `QueryEngine` over a `Snapshot` instead")]
 pub struct QueryExecutor<'a, 'b> {
     txn: &'a mut Transaction<'b>,
 }
@@ -64,12 +72,25 @@ impl<'a, 'b> QueryExecutor<'a, 'b> {
 pub enum QueryError {
     /// Transaction error.
     Transaction(TransactionError),
+    /// Reading the snapshot failed.
+    Database(DatabaseError),
+    /// A sum or average met a value that is not a number.
+    NonNumericAggregate {
+        /// The aggregate, e.g. `sum(v)`.
+        aggregate: String,
+        /// The rendered value.
+        value: String,
+    },
 }
 
 impl std::fmt::Display for QueryError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             Self::Transaction(e) => write!(f, "transaction error: {e}"),
+            Self::Database(e) => write!(f, "database error: {e}"),
+            Self::NonNumericAggregate { aggregate, value } => {
+                write!(f, "{aggregate} requires numbers, got {value}")
+            }
         }
     }
 }
@@ -78,10 +99,18 @@ impl std::error::Error for QueryError {
     fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
         match self {
             Self::Transaction(e) => Some(e),
+            Self::Database(e) => Some(e),
+            Self::NonNumericAggregate { .. } => None,
         }
     }
 }
 
+impl From<DatabaseError> for QueryError {
+    fn from(e: DatabaseError) -> Self {
+        Self::Database(e)
+    }
+}
+
 impl From<TransactionError> for QueryError {
     fn from(e: TransactionError) -> Self {
         Self::Transaction(e)
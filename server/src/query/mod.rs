@@ -6,46 +6,67 @@
 //! - WHERE clauses (conjunction of patterns)
 //! - OPTIONAL clauses (left join semantics)
 //! - WHERE-NOT clauses (anti-join / negation)
-//! - Filters (predicate functions)
+//! - Presence patterns, matching any value of an attribute
+//! - Filters (predicate functions), with comparison filters read from the
+//!   attribute index's value entries (see `Filter::range`)
+//! - Ordering by the values bound to variables (see `Query::order_by`)
+//! - Aggregates such as counts and sums, over all rows or per group (see
+//!   `aggregate`)
+//! - Server-side cursors for paging through large results (see `cursor`)
+//! - Streaming execution, yielding rows without collecting them (see `stream`)
+//! - Arrow IPC encoding of results for analytics clients (see `arrow`)
+//! - Query plans showing which index each pattern reads (see `plan`)
+//!
+//! The connection handler executes every `QueryRequest` with `QueryEngine` over
+//! a read-only `Snapshot`, so all clause types above except filters (which are
+//! Rust closures and cannot be sent over the wire) are available to clients.
 //!
 //! # Datalog-style Query Example
 //!
 //! ```
-//! // Build a query that finds all entities with a "name" attribute
-//! // using the pattern matching API:
-//! //
-//! // let query = Query::new()
-//! //     .find("e")
-//! //     .find("name")
-//! //     .where_pattern(Pattern::new(
-//! //         PatternElement::var("e"),
-//! //         PatternElement::field("name"),
-//! //         PatternElement::var("name"),
-//! //     ));
-//! //
-//! // The query can be executed against a database snapshot:
-//! // let mut engine = QueryEngine::new(&mut snapshot);
-//! // let result = engine.execute(&query)?;
+//! use server::proto;
+//! use server::query::{Pattern, PatternElement, Query};
+//!
+//! // Find all entities with a "name" attribute
+//! let query = Query::new()
+//!     .find("e")
+//!     .find("name")
+//!     .where_pattern(Pattern::new(
+//!         PatternElement::var("e"),
+//!         PatternElement::field("name"),
+//!         PatternElement::var("name"),
+//!     ));
+//! assert_eq!(query.to_string(), "[:find ?e ?name :where [?e :name ?name]]");
+//!
+//! // Send it to a server, or execute it locally with `QueryEngine`
+//! let request = proto::QueryRequest::try_from(&query).expect("query is well-formed");
+//! assert_eq!(request.find.len(), 2);
 //! ```
 
-// Allow dead code - this module exports a public API that isn't yet integrated
-// with the HTTP handlers. The code is tested via integration tests.
-#![allow(dead_code)]
-#![allow(unused_imports)]
-
+pub mod aggregate;
+pub mod arrow;
 pub mod context;
+pub mod cursor;
 pub mod engine;
 mod executor;
+pub mod plan;
+pub mod stream;
 pub mod types;
 
 // Datalog-style query engine
+pub use arrow::{ArrowEncodeError, encode_arrow_ipc};
 pub use context::QueryContext;
+pub use cursor::{CursorBatch, CursorError, CursorId, CursorTable};
 pub use engine::QueryEngine;
+pub use plan::{AccessPath, Clause, PatternPlan, QueryPlan};
+pub use stream::RowStream;
 pub use types::{
-    Datom, EntityId, FieldId, Filter, Pattern, PatternElement, Query, QueryResult, QueryRow,
-    Triple, Value, Variable,
+    Aggregate, AggregateFunction, Datom, Direction, EntityId, FieldId, Filter, Nulls, OrderBy,
+    Pattern, PatternElement, Query, QueryBuildError, QueryResult, QueryRow, Triple, Value,
+    ValueRange, Variable,
 };
 
-// Legacy query executor (operates on storage transactions)
-#[allow(unused_imports)]
+// Legacy query executor (operates on storage transactions), deprecated in
+// favor of `QueryEngine`
+#[allow(deprecated)]
 pub use executor::{QueryError, QueryExecutor};
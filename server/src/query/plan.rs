@@ -0,0 +1,291 @@
+//! Query plans: which index each pattern of a query is read from.
+//!
+//! `QueryEngine::explain` describes how `QueryEngine::execute` will fetch the
+//! candidate triples for each pattern, without touching the database. The
+//! access path depends only on which of the pattern's entity and attribute are
+//! known when the pattern runs, either as constants or as variables bound by
+//! an earlier pattern, and on whether a range filter bounds its value.
+
+use std::collections::HashSet;
+use std::fmt;
+
+use super::types::{Pattern, PatternElement, Query};
+
+/// How the candidate triples for a pattern are fetched.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AccessPath {
+    /// One point lookup in the primary index (entity and attribute bound),
+    /// or one per entity of an entity set.
+    PrimaryLookup,
+    /// A scan of one entity's triples in the primary index (entity bound),
+    /// or of each entity's in an entity set.
+    EntityScan,
+    /// A scan of the attribute index for the entities with the attribute,
+    /// then one primary index point lookup per entity (attribute bound).
+    AttributeIndexScan,
+    /// A scan of the attribute index's value entries between a range
+    /// filter's bounds, then one primary index point lookup per entity
+    /// (attribute bound, value range-filtered).
+    AttributeRangeScan,
+    /// A scan of every triple in the primary index (neither bound).
+    FullScan,
+}
+
+impl AccessPath {
+    /// The access path for a pattern with the given parts bound.
+    #[must_use]
+    pub const fn for_bindings(entity_bound: bool, attribute_bound: bool) -> Self {
+        match (entity_bound, attribute_bound) {
+            (true, true) => Self::PrimaryLookup,
+            (true, false) => Self::EntityScan,
+            (false, true) => Self::AttributeIndexScan,
+            (false, false) => Self::FullScan,
+        }
+    }
+}
+
+impl fmt::Display for AccessPath {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Self::PrimaryLookup => "primary lookup",
+            Self::EntityScan => "entity scan",
+            Self::AttributeIndexScan => "attribute index scan",
+            Self::AttributeRangeScan => "attribute range scan",
+            Self::FullScan => "full scan",
+        };
+        write!(f, "{name}")
+    }
+}
+
+/// The clause a pattern belongs to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Clause {
+    Where,
+    Optional,
+    WhereNot,
+}
+
+impl fmt::Display for Clause {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let keyword = match self {
+            Self::Where => ":where",
+            Self::Optional => ":optional",
+            Self::WhereNot => ":where-not",
+        };
+        write!(f, "{keyword}")
+    }
+}
+
+/// The plan for one pattern.
+#[derive(Debug, PartialEq, Eq)]
+pub struct PatternPlan {
+    /// The clause the pattern belongs to.
+    pub clause: Clause,
+    /// The rendered pattern, e.g. `[?e :name ?name]`.
+    pub pattern: String,
+    /// How its candidate triples are fetched.
+    pub access_path: AccessPath,
+}
+
+/// The plan for a query: one entry per pattern, in evaluation order.
+#[derive(Debug, PartialEq, Eq)]
+pub struct QueryPlan {
+    /// The WHERE, then OPTIONAL, then WHERE-NOT patterns.
+    pub patterns: Vec<PatternPlan>,
+}
+
+impl QueryPlan {
+    /// Plan `query` as `QueryEngine::execute` evaluates it.
+    ///
+    /// Variables bound by an earlier OPTIONAL pattern count as bound, though
+    /// rows where that pattern did not match read a wider range.
+    #[must_use]
+    pub fn new(query: &Query) -> Self {
+        let mut bindings = Bindings::default();
+        let clauses = [
+            (Clause::Where, &query.where_patterns),
+            (Clause::Optional, &query.optional_patterns),
+            (Clause::WhereNot, &query.where_not_patterns),
+        ];
+        let mut patterns = Vec::new();
+        for (clause, clause_patterns) in clauses {
+            for pattern in clause_patterns {
+                let mut access_path = bindings.access_path(pattern);
+                // Only WHERE patterns are narrowed by filters
+                if clause == Clause::Where
+                    && access_path == AccessPath::AttributeIndexScan
+                    && query.value_range(pattern).is_some()
+                {
+                    access_path = AccessPath::AttributeRangeScan;
+                }
+                patterns.push(PatternPlan {
+                    clause,
+                    pattern: pattern.to_string(),
+                    access_path,
+                });
+                // WHERE-NOT patterns bind nothing in the rows they keep
+                if clause != Clause::WhereNot {
+                    bindings.bind(pattern);
+                }
+            }
+        }
+        Self { patterns }
+    }
+
+    /// Whether any pattern is read with `access_path`.
+    #[must_use]
+    pub fn uses(&self, access_path: AccessPath) -> bool {
+        self.patterns
+            .iter()
+            .any(|pattern| pattern.access_path == access_path)
+    }
+}
+
+impl fmt::Display for QueryPlan {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let lines: Vec<String> = self
+            .patterns
+            .iter()
+            .map(|plan| format!("{} {}: {}", plan.clause, plan.pattern, plan.access_path))
+            .collect();
+        write!(f, "{}", lines.join("\n"))
+    }
+}
+
+/// Variables bound so far, by the position that bound them.
+///
+/// A variable only resolves to an entity if an entity position bound it, and
+/// to an attribute if an attribute position did, matching how the engine
+/// resolves bindings.
+#[derive(Default)]
+struct Bindings<'a> {
+    entities: HashSet<&'a str>,
+    attributes: HashSet<&'a str>,
+}
+
+impl<'a> Bindings<'a> {
+    fn access_path(&self, pattern: &Pattern) -> AccessPath {
+        let entity_bound = match &pattern.entity {
+            PatternElement::Entity(_) | PatternElement::EntitySet { .. } => true,
+            PatternElement::Variable(var) => self.entities.contains(var.name.as_str()),
+            _ => false,
+        };
+        let attribute_bound = match &pattern.field {
+            PatternElement::Field(_) => true,
+            PatternElement::Variable(var) => self.attributes.contains(var.name.as_str()),
+            _ => false,
+        };
+        AccessPath::for_bindings(entity_bound, attribute_bound)
+    }
+
+    fn bind(&mut self, pattern: &'a Pattern) {
+        if let Some(var) = pattern.entity.as_variable() {
+            self.entities.insert(&var.name);
+        }
+        if let PatternElement::Variable(var) = &pattern.field {
+            self.attributes.insert(&var.name);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::query::types::{Filter, TripleValue, ValueRange, Variable};
+
+    #[test]
+    fn test_plan_follows_bindings_of_earlier_patterns() {
+        let query = Query::new()
+            .find("e")
+            .find("friend")
+            .where_pattern(Pattern::new(
+                PatternElement::var("e"),
+                PatternElement::field("name"),
+                PatternElement::string("Alice"),
+            ))
+            .where_pattern(Pattern::new(
+                PatternElement::var("e"),
+                PatternElement::field("friend"),
+                PatternElement::var("friend"),
+            ))
+            // ?friend is bound as a value, so it does not resolve an entity
+            .where_pattern(Pattern::new(
+                PatternElement::var("friend"),
+                PatternElement::var("a"),
+                PatternElement::var("v"),
+            ))
+            .optional(Pattern::new(
+                PatternElement::var("e"),
+                PatternElement::var("a"),
+                PatternElement::var("w"),
+            ))
+            .where_not(Pattern::new(
+                PatternElement::var("e"),
+                PatternElement::var("b"),
+                PatternElement::var("x"),
+            ));
+
+        let paths: Vec<_> = QueryPlan::new(&query)
+            .patterns
+            .iter()
+            .map(|plan| (plan.clause, plan.access_path))
+            .collect();
+        assert_eq!(
+            paths,
+            vec![
+                (Clause::Where, AccessPath::AttributeIndexScan),
+                (Clause::Where, AccessPath::PrimaryLookup),
+                (Clause::Where, AccessPath::FullScan),
+                (Clause::Optional, AccessPath::PrimaryLookup),
+                (Clause::WhereNot, AccessPath::EntityScan),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_plan_uses_range_scan_for_range_filtered_value() {
+        let age = Pattern::new(
+            PatternElement::var("e"),
+            PatternElement::field("age"),
+            PatternElement::var("age"),
+        );
+        let query = Query::new()
+            .find("e")
+            .where_pattern(age)
+            .optional(Pattern::new(
+                PatternElement::var("f"),
+                PatternElement::field("age"),
+                PatternElement::var("age"),
+            ))
+            .filter(Filter::range(
+                Variable::new("age"),
+                ValueRange::at_least(TripleValue::Number(21.0)),
+            ));
+
+        let paths: Vec<_> = QueryPlan::new(&query)
+            .patterns
+            .iter()
+            .map(|plan| plan.access_path)
+            .collect();
+        assert_eq!(
+            paths,
+            vec![
+                AccessPath::AttributeRangeScan,
+                AccessPath::AttributeIndexScan
+            ]
+        );
+    }
+
+    #[test]
+    fn test_plan_display() {
+        let query = Query::new().find("e").where_pattern(Pattern::new(
+            PatternElement::var("e"),
+            PatternElement::field("age"),
+            PatternElement::var("age"),
+        ));
+        assert_eq!(
+            QueryPlan::new(&query).to_string(),
+            ":where [?e :age ?age]: attribute index scan"
+        );
+    }
+}
@@ -0,0 +1,289 @@
+//! Streaming query execution.
+//!
+//! A `RowStream` yields the rows of a query one at a time instead of
+//! collecting them into a `QueryResult`, for analytics queries whose results
+//! are too large to hold in memory.
+//!
+//! # Design
+//!
+//! Like a cursor (see `QueryEngine::fetch_page`), the stream scans the primary
+//! index in key order and treats each triple matching the first WHERE pattern
+//! as a seed for the rest of the query. Only the rows of the current seed are
+//! buffered, so memory is bounded by the most rows one triple seeds, not by
+//! the size of the result. Seeds come in primary key order, so rows come in
+//! the same canonical order as from `QueryEngine::execute`. A distinct query
+//! also keeps a key per distinct row yielded, to drop later duplicates.
+//!
+//! # Lifetimes
+//!
+//! `RowStream<'a, 'b>` borrows a `Snapshot<'b>` for `'a`, exactly as the
+//! `QueryEngine<'a, 'b>` that created it does, and the query for `'a` too.
+//! The snapshot stays open for as long as the stream lives:
+//! `Snapshot::close` takes the snapshot by value, so the borrow checker
+//! rejects closing it while a stream still borrows it. Drop the stream, then
+//! close the snapshot and release its transaction ID:
+//!
+//! ```ignore
+//! let snapshot = db.begin_readonly()?;
+//! {
+//!     let engine = QueryEngine::new(&snapshot);
+//!     let mut rows = engine.execute_streaming(&query)?;
+//!     while let Some(row) = rows.next_row()? {
+//!         // ...
+//!     }
+//! } // `rows` and `engine` are dropped here
+//! let txn_id = snapshot.close();
+//! db.release_snapshot(txn_id)?;
+//! ```
+
+use std::collections::HashSet;
+
+use super::engine::{QueryEngine, project, record_to_triple, result_columns, row_key};
+use super::types::{Query, QueryRow};
+use crate::storage::{DatabaseError, Snapshot, TripleIterator};
+
+/// Rows of a query, produced as the stream is advanced.
+///
+/// Created by `QueryEngine::execute_streaming`. Besides `next_row`, it
+/// implements `Iterator`, yielding `Result`s so a read error ends the stream
+/// without panicking.
+pub struct RowStream<'a, 'b> {
+    engine: QueryEngine<'a, 'b>,
+    query: &'a Query,
+    /// Scan over the seed candidates. `None` for a query without WHERE
+    /// patterns, whose rows are all pending from the start.
+    seeds: Option<TripleIterator<'b>>,
+    /// Rows of the current seed not yet yielded.
+    pending: std::vec::IntoIter<QueryRow>,
+    /// Rows still to skip for the query's offset.
+    to_skip: usize,
+    /// Rows still to yield for the query's limit, if it has one.
+    remaining: Option<usize>,
+    /// Keys of the rows yielded or skipped so far, for a distinct query.
+    seen: Option<HashSet<Vec<u8>>>,
+}
+
+impl<'a, 'b> RowStream<'a, 'b> {
+    /// Create a stream over `query`, seeding from `seeds` after `pending`.
+    pub(super) fn new(
+        snapshot: &'a Snapshot<'b>,
+        query: &'a Query,
+        seeds: Option<TripleIterator<'b>>,
+        pending: Vec<QueryRow>,
+    ) -> Self {
+        Self {
+            engine: QueryEngine::new(snapshot),
+            query,
+            seeds,
+            pending: pending.into_iter(),
+            to_skip: query.offset,
+            remaining: query.limit,
+            seen: query.distinct.then(HashSet::new),
+        }
+    }
+
+    /// Column names of the rows, in `find` order.
+    #[must_use]
+    pub fn columns(&self) -> Vec<String> {
+        result_columns(self.query)
+    }
+
+    /// Get the next row, or `None` when the stream is exhausted.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if reading the snapshot fails.
+    pub fn next_row(&mut self) -> Result<Option<QueryRow>, DatabaseError> {
+        while self.remaining != Some(0) {
+            if let Some(row) = self.pending.next() {
+                if let Some(seen) = &mut self.seen
+                    && !seen.insert(row_key(row.iter().map(Option::as_ref)))
+                {
+                    continue;
+                }
+                if self.to_skip > 0 {
+                    self.to_skip -= 1;
+                    continue;
+                }
+                if let Some(remaining) = &mut self.remaining {
+                    *remaining -= 1;
+                }
+                return Ok(Some(row));
+            }
+
+            let Some(seeds) = &mut self.seeds else {
+                return Ok(None);
+            };
+            let Some(record) = seeds.next_record()? else {
+                self.seeds = None;
+                return Ok(None);
+            };
+            let contexts = self
+                .engine
+                .seed_contexts(self.query, &record_to_triple(record))?;
+            self.pending = contexts
+                .iter()
+                .map(|ctx| project(self.query, ctx))
+                .collect::<Vec<_>>()
+                .into_iter();
+        }
+        Ok(None)
+    }
+}
+
+impl Iterator for RowStream<'_, '_> {
+    type Item = Result<QueryRow, DatabaseError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_row().transpose()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::query::{Datom, Filter, Pattern, PatternElement, Variable};
+    use crate::storage::Database;
+    use crate::storage::buffer_pool::BufferPool;
+    use crate::types::{AttributeId, EntityId, TripleValue};
+
+    /// Create a database where each of `count` entities has a name, every
+    /// third a nickname, and every other a score.
+    fn create_test_db(count: u8) -> Database {
+        let mut db = Database::create_in_memory(BufferPool::new(100)).expect("create db");
+        let mut txn = db.begin(0).expect("begin");
+        for i in 0..count {
+            let entity = EntityId([i; 16]);
+            txn.insert(
+                entity,
+                AttributeId::from_string("name"),
+                TripleValue::String(format!("user{i}")),
+            )
+            .expect("insert");
+            if i % 3 == 0 {
+                txn.insert(
+                    entity,
+                    AttributeId::from_string("nickname"),
+                    TripleValue::String(format!("u{i}")),
+                )
+                .expect("insert");
+            }
+            if i % 2 == 0 {
+                txn.insert(
+                    entity,
+                    AttributeId::from_string("score"),
+                    TripleValue::Number(f64::from(i)),
+                )
+                .expect("insert");
+            }
+        }
+        txn.commit().expect("commit");
+        db
+    }
+
+    fn pattern(field: &str, value: &str) -> Pattern {
+        Pattern::new(
+            PatternElement::var("e"),
+            PatternElement::field(field),
+            PatternElement::var(value),
+        )
+    }
+
+    /// Names with an optional score, leaving out entities with a nickname and
+    /// names of `user1` and `user2`.
+    fn query() -> Query {
+        Query::new()
+            .find("name")
+            .find("score")
+            .where_pattern(pattern("name", "name"))
+            .optional(pattern("score", "score"))
+            .where_not(pattern("nickname", "nickname"))
+            .filter(Filter::new(Variable::new("name"), |datom| {
+                !matches!(
+                    datom,
+                    Some(Datom::Value(TripleValue::String(name))) if name == "user1" || name == "user2"
+                )
+            }))
+    }
+
+    /// Collect a stream's rows with a `next_row` loop.
+    fn drain(stream: &mut RowStream<'_, '_>) -> Vec<QueryRow> {
+        let mut rows = Vec::new();
+        while let Some(row) = stream.next_row().expect("next row") {
+            rows.push(row);
+        }
+        rows
+    }
+
+    #[test]
+    fn test_stream_yields_the_rows_of_execute() {
+        let db = create_test_db(20);
+        let txn_id = {
+            let snapshot = db.begin_readonly().expect("begin readonly");
+            let engine = QueryEngine::new(&snapshot);
+            let query = query();
+
+            let expected = engine.execute(&query).expect("execute");
+            let mut stream = engine.execute_streaming(&query).expect("stream");
+            assert_eq!(stream.columns(), expected.columns);
+            let rows = drain(&mut stream);
+            assert_eq!(rows, expected.rows);
+            // 20 names, less 7 with a nickname and 2 filtered out
+            assert_eq!(rows.len(), 11);
+            assert!(stream.next_row().expect("next row").is_none());
+            drop(stream);
+            snapshot.close()
+        };
+        db.release_snapshot(txn_id).expect("release snapshot");
+    }
+
+    #[test]
+    fn test_stream_respects_limit_and_offset() {
+        let db = create_test_db(20);
+        let txn_id = {
+            let snapshot = db.begin_readonly().expect("begin readonly");
+            let engine = QueryEngine::new(&snapshot);
+
+            for (offset, limit) in [(0, 3), (4, 5), (10, 10), (0, 0), (30, 1)] {
+                let query = query().offset(offset).limit(limit);
+                let expected = engine.execute(&query).expect("execute");
+                let rows: Vec<QueryRow> = engine
+                    .execute_streaming(&query)
+                    .expect("stream")
+                    .collect::<Result<_, _>>()
+                    .expect("rows");
+                assert_eq!(rows, expected.rows, "offset {offset}, limit {limit}");
+            }
+            snapshot.close()
+        };
+        db.release_snapshot(txn_id).expect("release snapshot");
+    }
+
+    #[test]
+    fn test_stream_drops_duplicate_rows() {
+        let db = create_test_db(20);
+        let txn_id = {
+            let snapshot = db.begin_readonly().expect("begin readonly");
+            let engine = QueryEngine::new(&snapshot);
+            // Every entity without a score projects to the same empty row
+            let scores = || {
+                Query::new()
+                    .find("score")
+                    .where_pattern(pattern("name", "name"))
+                    .optional(pattern("score", "score"))
+                    .distinct()
+            };
+
+            for (offset, limit) in [(0, 20), (1, 3), (10, 5)] {
+                let query = scores().offset(offset).limit(limit);
+                let expected = engine.execute(&query).expect("execute");
+                let rows = drain(&mut engine.execute_streaming(&query).expect("stream"));
+                assert_eq!(rows, expected.rows, "offset {offset}, limit {limit}");
+            }
+            // 10 scores, and one row for the 10 entities without one
+            assert_eq!(engine.execute(&scores()).expect("execute").len(), 11);
+            snapshot.close()
+        };
+        db.release_snapshot(txn_id).expect("release snapshot");
+    }
+}
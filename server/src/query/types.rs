@@ -6,14 +6,18 @@
 //! - `Variable` - A placeholder in query patterns
 //! - `Pattern` - A query pattern with variables or concrete values
 //! - `Query` - A complete query with where, optional, filters, and whereNot
+//! - `OrderBy` - A sort key for a query's results
+//! - `Aggregate` - An aggregate find spec, such as a count or sum
 
 #![allow(clippy::type_complexity)] // Complex boxed trait objects are necessary for filters
 
+use std::cmp::Ordering;
+use std::collections::BTreeSet;
 use std::fmt;
 
 // Re-export storage types for use in queries.
 // This unifies the type system so queries use the same types as storage.
-pub use crate::types::{AttributeId, EntityId, TripleValue};
+pub use crate::types::{AttributeId, EntityId, TripleValue, ValueRange};
 
 /// Type alias for backwards compatibility.
 /// In queries, we refer to attributes as "fields".
@@ -76,6 +80,29 @@ impl Datom {
             Self::Value(v) => Self::Value(v.clone_value()),
         }
     }
+
+    /// Compare two datoms in a total order.
+    ///
+    /// Values come first, ordered by `TripleValue::cmp_total`, then entity
+    /// IDs, then field IDs, each ordered by their bytes.
+    #[must_use]
+    pub fn cmp_total(&self, other: &Self) -> Ordering {
+        match (self, other) {
+            (Self::Value(a), Self::Value(b)) => a.cmp_total(b),
+            (Self::Entity(a), Self::Entity(b)) => a.0.cmp(&b.0),
+            (Self::Field(a), Self::Field(b)) => a.0.cmp(&b.0),
+            _ => self.kind_rank().cmp(&other.kind_rank()),
+        }
+    }
+
+    /// Rank of this datom's kind in the order of `cmp_total`.
+    const fn kind_rank(&self) -> u8 {
+        match self {
+            Self::Value(_) => 0,
+            Self::Entity(_) => 1,
+            Self::Field(_) => 2,
+        }
+    }
 }
 
 impl fmt::Display for Datom {
@@ -159,12 +186,24 @@ impl fmt::Display for Variable {
 pub enum PatternElement {
     /// A concrete entity ID.
     Entity(EntityId),
+    /// A set of concrete entity IDs, matching any of them (an IN clause),
+    /// optionally binding a variable to the matched entity. Only valid in
+    /// the entity position.
+    EntitySet {
+        /// The entities to match.
+        ids: BTreeSet<EntityId>,
+        /// Variable bound to the matched entity.
+        variable: Option<Variable>,
+    },
     /// A concrete field ID.
     Field(FieldId),
     /// A concrete value.
     Value(Value),
     /// A variable to be bound.
     Variable(Variable),
+    /// Any value, bound to nothing, so the pattern only checks that the
+    /// entity has the attribute. Only valid in the value position.
+    Any,
 }
 
 impl PatternElement {
@@ -180,6 +219,16 @@ impl PatternElement {
         Self::Entity(EntityId::from_string(s))
     }
 
+    /// Create an entity set pattern element from entity strings, binding
+    /// the matched entity to `var`.
+    #[must_use]
+    pub fn entities(var: impl Into<String>, ids: &[&str]) -> Self {
+        Self::EntitySet {
+            ids: ids.iter().map(|s| EntityId::from_string(s)).collect(),
+            variable: Some(Variable::new(var)),
+        }
+    }
+
     /// Create a field pattern element.
     #[must_use]
     pub fn field(s: &str) -> Self {
@@ -204,16 +253,39 @@ impl PatternElement {
         matches!(self, Self::Variable(_))
     }
 
-    /// Get the variable if this is one.
+    /// Get the variable this element binds, if any: the variable itself, or
+    /// the variable of an entity set.
     #[must_use]
     pub const fn as_variable(&self) -> Option<&Variable> {
         match self {
-            Self::Variable(v) => Some(v),
+            Self::Variable(v)
+            | Self::EntitySet {
+                variable: Some(v), ..
+            } => Some(v),
             _ => None,
         }
     }
 }
 
+impl fmt::Display for PatternElement {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Entity(id) => write!(f, "#{id}"),
+            Self::EntitySet { ids, variable } => {
+                if let Some(variable) = variable {
+                    write!(f, "{variable} in ")?;
+                }
+                let ids: Vec<String> = ids.iter().map(|id| format!("#{id}")).collect();
+                write!(f, "{{{}}}", ids.join(" "))
+            }
+            Self::Field(id) => write!(f, ":{id}"),
+            Self::Value(v) => write!(f, "{v}"),
+            Self::Variable(v) => write!(f, "{v}"),
+            Self::Any => write!(f, "_"),
+        }
+    }
+}
+
 /// A query pattern - a triple where any element can be a variable.
 #[derive(Debug, PartialEq)]
 pub struct Pattern {
@@ -251,12 +323,24 @@ impl Pattern {
     }
 }
 
+impl fmt::Display for Pattern {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "[{} {} {}]", self.entity, self.field, self.value)
+    }
+}
+
 /// A filter that can be applied to query results.
 pub struct Filter {
     /// The variable to filter on.
     pub selector: Variable,
     /// The filter predicate.
     pub predicate: Box<dyn Fn(Option<&Datom>) -> bool + Send + Sync>,
+    /// The range of values the predicate keeps, if it is a comparison.
+    ///
+    /// The engine reads the attribute index's value entries between the
+    /// range's bounds for a WHERE pattern whose value is the selector, rather
+    /// than every entity with the attribute.
+    pub range: Option<ValueRange>,
 }
 
 impl Filter {
@@ -268,6 +352,21 @@ impl Filter {
         Self {
             selector,
             predicate: Box::new(predicate),
+            range: None,
+        }
+    }
+
+    /// Create a filter keeping the rows whose `selector` is a value in
+    /// `range`, such as `?age > 21` with `ValueRange::greater_than`.
+    #[must_use]
+    pub fn range(selector: Variable, range: ValueRange) -> Self {
+        let kept = range.clone_range();
+        Self {
+            selector,
+            predicate: Box::new(
+                move |datom| matches!(datom, Some(Datom::Value(value)) if kept.contains(value)),
+            ),
+            range: Some(range),
         }
     }
 
@@ -283,10 +382,142 @@ impl fmt::Debug for Filter {
         f.debug_struct("Filter")
             .field("selector", &self.selector)
             .field("predicate", &"<fn>")
+            .field("range", &self.range)
             .finish()
     }
 }
 
+/// Direction of a sort key.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum Direction {
+    /// Smallest first.
+    #[default]
+    Ascending,
+    /// Largest first.
+    Descending,
+}
+
+/// Where rows whose sort variable is null or unbound go, whatever the
+/// direction.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum Nulls {
+    /// Before every other row.
+    #[default]
+    First,
+    /// After every other row.
+    Last,
+}
+
+/// A sort key for a query's results: the value bound to a variable.
+#[derive(Debug, PartialEq, Eq)]
+pub struct OrderBy {
+    /// The variable whose bound value rows are sorted by.
+    pub variable: Variable,
+    /// Whether the smallest or the largest value comes first.
+    pub direction: Direction,
+    /// Where rows with a null value or no binding go.
+    pub nulls: Nulls,
+}
+
+impl OrderBy {
+    /// Compare two rows' datoms for this key.
+    ///
+    /// A missing datom and `Value::Null` are both null. Other datoms compare
+    /// by `Datom::cmp_total`, reversed for a descending key.
+    #[must_use]
+    pub fn compare(&self, a: Option<&Datom>, b: Option<&Datom>) -> Ordering {
+        let is_null =
+            |datom: Option<&Datom>| matches!(datom, None | Some(Datom::Value(Value::Null)));
+        match (a, b) {
+            _ if is_null(a) && is_null(b) => Ordering::Equal,
+            _ if is_null(a) => self.nulls_before(),
+            _ if is_null(b) => self.nulls_before().reverse(),
+            (Some(a), Some(b)) => match self.direction {
+                Direction::Ascending => a.cmp_total(b),
+                Direction::Descending => b.cmp_total(a),
+            },
+            _ => Ordering::Equal,
+        }
+    }
+
+    /// How a null compares to a non-null datom.
+    const fn nulls_before(&self) -> Ordering {
+        match self.nulls {
+            Nulls::First => Ordering::Less,
+            Nulls::Last => Ordering::Greater,
+        }
+    }
+}
+
+impl fmt::Display for OrderBy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let direction = match self.direction {
+            Direction::Ascending => "asc",
+            Direction::Descending => "desc",
+        };
+        write!(f, "({} {direction}", self.variable)?;
+        if self.nulls == Nulls::Last {
+            write!(f, " nulls-last")?;
+        }
+        write!(f, ")")
+    }
+}
+
+/// A function folding the values bound to a variable into one.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AggregateFunction {
+    /// Number of bound values.
+    Count,
+    /// Sum of the values, which must be numbers; 0 if there are none.
+    Sum,
+    /// Smallest value by `Datom::cmp_total`.
+    Min,
+    /// Largest value by `Datom::cmp_total`.
+    Max,
+    /// Mean of the values, which must be numbers.
+    Avg,
+}
+
+impl AggregateFunction {
+    /// The function's name, as used in column names.
+    #[must_use]
+    pub const fn name(self) -> &'static str {
+        match self {
+            Self::Count => "count",
+            Self::Sum => "sum",
+            Self::Min => "min",
+            Self::Max => "max",
+            Self::Avg => "avg",
+        }
+    }
+}
+
+/// An aggregate find spec: a function over the values bound to a variable.
+///
+/// Null and unbound values are skipped, so `count` counts the rows that bind
+/// the variable, and `min`, `max` and `avg` are null when none do.
+#[derive(Debug, PartialEq, Eq)]
+pub struct Aggregate {
+    /// The function to apply.
+    pub function: AggregateFunction,
+    /// The variable whose values are aggregated.
+    pub variable: Variable,
+}
+
+impl Aggregate {
+    /// The name of the aggregate's result column, e.g. `count(e)`.
+    #[must_use]
+    pub fn column(&self) -> String {
+        format!("{}({})", self.function.name(), self.variable.name.as_str())
+    }
+}
+
+impl fmt::Display for Aggregate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "({} {})", self.function.name(), self.variable)
+    }
+}
+
 /// A complete query.
 #[derive(Debug, Default)]
 pub struct Query {
@@ -300,6 +531,22 @@ pub struct Query {
     pub where_not_patterns: Vec<Pattern>,
     /// Filters to apply.
     pub filters: Vec<Filter>,
+    /// Maximum number of rows to return, counted after `offset`.
+    pub limit: Option<usize>,
+    /// Number of rows to skip before returning any.
+    pub offset: usize,
+    /// Sort keys, compared in order; rows tied on every key keep their
+    /// canonical order.
+    pub order_by: Vec<OrderBy>,
+    /// Aggregates to return instead of `find` variables. A query with
+    /// aggregates returns a row per group (see `group_by`), with a column per
+    /// group variable and then per aggregate.
+    pub aggregates: Vec<Aggregate>,
+    /// Variables whose bound values partition the matched rows into groups.
+    /// Without any, every row is in a single group.
+    pub group_by: Vec<Variable>,
+    /// Whether duplicate rows are dropped, keeping the first of each.
+    pub distinct: bool,
 }
 
 impl Query {
@@ -310,36 +557,341 @@ impl Query {
     }
 
     /// Add a variable to the find clause.
+    #[must_use]
     pub fn find(mut self, var: impl Into<String>) -> Self {
         self.find.push(Variable::new(var));
         self
     }
 
+    /// Add an aggregate of the values bound to `var` to the find clause.
+    #[must_use]
+    pub fn find_aggregate(mut self, function: AggregateFunction, var: impl Into<String>) -> Self {
+        self.aggregates.push(Aggregate {
+            function,
+            variable: Variable::new(var),
+        });
+        self
+    }
+
+    /// Find the number of rows binding `var`.
+    #[must_use]
+    pub fn find_count(self, var: impl Into<String>) -> Self {
+        self.find_aggregate(AggregateFunction::Count, var)
+    }
+
+    /// Find the sum of the numbers bound to `var`.
+    #[must_use]
+    pub fn find_sum(self, var: impl Into<String>) -> Self {
+        self.find_aggregate(AggregateFunction::Sum, var)
+    }
+
+    /// Find the smallest value bound to `var`.
+    #[must_use]
+    pub fn find_min(self, var: impl Into<String>) -> Self {
+        self.find_aggregate(AggregateFunction::Min, var)
+    }
+
+    /// Find the largest value bound to `var`.
+    #[must_use]
+    pub fn find_max(self, var: impl Into<String>) -> Self {
+        self.find_aggregate(AggregateFunction::Max, var)
+    }
+
+    /// Find the mean of the numbers bound to `var`.
+    #[must_use]
+    pub fn find_avg(self, var: impl Into<String>) -> Self {
+        self.find_aggregate(AggregateFunction::Avg, var)
+    }
+
+    /// Group the rows by the value bound to `var`, computing the aggregates
+    /// once per group.
+    ///
+    /// Each call adds a variable to the group key, and a column before the
+    /// aggregates' columns.
+    #[must_use]
+    pub fn group_by(mut self, var: impl Into<String>) -> Self {
+        self.group_by.push(Variable::new(var));
+        self
+    }
+
+    /// Whether the query returns aggregates or groups instead of a row per
+    /// match.
+    #[must_use]
+    pub const fn is_aggregate(&self) -> bool {
+        !self.aggregates.is_empty() || !self.group_by.is_empty()
+    }
+
     /// Add a where pattern.
+    #[must_use]
     pub fn where_pattern(mut self, pattern: Pattern) -> Self {
         self.where_patterns.push(pattern);
         self
     }
 
     /// Add an optional pattern.
+    #[must_use]
     pub fn optional(mut self, pattern: Pattern) -> Self {
         self.optional_patterns.push(pattern);
         self
     }
 
     /// Add a where-not pattern.
+    #[must_use]
     pub fn where_not(mut self, pattern: Pattern) -> Self {
         self.where_not_patterns.push(pattern);
         self
     }
 
+    /// Require the entity to have the attribute, with any value.
+    ///
+    /// Shorthand for a where pattern whose value is `PatternElement::Any`.
+    #[must_use]
+    pub fn has(self, entity: PatternElement, field: PatternElement) -> Self {
+        self.where_pattern(Pattern::new(entity, field, PatternElement::Any))
+    }
+
+    /// Require the entity to lack the attribute.
+    ///
+    /// Shorthand for a where-not pattern whose value is `PatternElement::Any`.
+    #[must_use]
+    pub fn lacks(self, entity: PatternElement, field: PatternElement) -> Self {
+        self.where_not(Pattern::new(entity, field, PatternElement::Any))
+    }
+
     /// Add a filter.
+    #[must_use]
     pub fn filter(mut self, filter: Filter) -> Self {
         self.filters.push(filter);
         self
     }
+
+    /// Return at most `n` rows.
+    #[must_use]
+    pub const fn limit(mut self, n: usize) -> Self {
+        self.limit = Some(n);
+        self
+    }
+
+    /// Skip the first `n` rows, in result order.
+    #[must_use]
+    pub const fn offset(mut self, n: usize) -> Self {
+        self.offset = n;
+        self
+    }
+
+    /// Drop duplicate rows, keeping the first of each.
+    ///
+    /// Rows are compared after projection onto the `find` variables, so
+    /// matches differing only in variables that are not returned collapse
+    /// into one row. The limit and offset count distinct rows.
+    #[must_use]
+    pub const fn distinct(mut self) -> Self {
+        self.distinct = true;
+        self
+    }
+
+    /// Sort the rows by the value bound to `var`, with nulls first.
+    ///
+    /// Each call adds a key that breaks the ties of the keys before it.
+    #[must_use]
+    pub fn order_by(self, var: impl Into<String>, direction: Direction) -> Self {
+        self.order_by_with_nulls(var, direction, Nulls::First)
+    }
+
+    /// Sort the rows by the value bound to `var`, placing nulls as `nulls`
+    /// says.
+    #[must_use]
+    pub fn order_by_with_nulls(
+        mut self,
+        var: impl Into<String>,
+        direction: Direction,
+        nulls: Nulls,
+    ) -> Self {
+        self.order_by.push(OrderBy {
+            variable: Variable::new(var),
+            direction,
+            nulls,
+        });
+        self
+    }
+
+    /// Get the range of the first range filter on `pattern`'s value
+    /// variable, if any.
+    ///
+    /// Every row the query returns has that variable in the range, so a WHERE
+    /// pattern may skip the triples whose value is outside it.
+    #[must_use]
+    pub fn value_range(&self, pattern: &Pattern) -> Option<&ValueRange> {
+        let PatternElement::Variable(var) = &pattern.value else {
+            return None;
+        };
+        self.filters
+            .iter()
+            .filter(|filter| filter.selector == *var)
+            .find_map(|filter| filter.range.as_ref())
+    }
+}
+
+impl Query {
+    /// Check that the query is well-formed.
+    ///
+    /// # Post-conditions
+    /// - At least one variable, group, or aggregate is returned
+    /// - No `find` variable is returned alongside groups or aggregates
+    /// - Every `find` variable, aggregated or grouped variable, filter
+    ///   selector, and `order_by` variable is bound by a where or optional
+    ///   pattern
+    ///
+    /// # Errors
+    ///
+    /// Returns the first problem found.
+    pub fn validate(&self) -> Result<(), QueryBuildError> {
+        if self.find.is_empty() && !self.is_aggregate() {
+            return Err(QueryBuildError::NoFindVariables);
+        }
+        if let Some(variable) = self.find.first().filter(|_| self.is_aggregate()) {
+            return Err(QueryBuildError::UnaggregatedVariable(
+                variable.name.as_str().to_owned(),
+            ));
+        }
+
+        let is_bound = |variable: &Variable| {
+            self.where_patterns
+                .iter()
+                .chain(&self.optional_patterns)
+                .flat_map(|pattern| [&pattern.entity, &pattern.field, &pattern.value])
+                .any(|element| element.as_variable() == Some(variable))
+        };
+
+        let selectors = self.filters.iter().map(|filter| &filter.selector);
+        let sort_keys = self.order_by.iter().map(|order| &order.variable);
+        let aggregated = self.aggregates.iter().map(|aggregate| &aggregate.variable);
+        for variable in self
+            .find
+            .iter()
+            .chain(&self.group_by)
+            .chain(aggregated)
+            .chain(selectors)
+            .chain(sort_keys)
+        {
+            if !is_bound(variable) {
+                return Err(QueryBuildError::UnboundVariable(
+                    variable.name.as_str().to_owned(),
+                ));
+            }
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for Query {
+    /// Render the query in a datalog-like form for logging, e.g.
+    /// `[:find ?e ?name :where [?e :name ?name]]`.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "[:find")?;
+        for variable in &self.find {
+            write!(f, " {variable}")?;
+        }
+        for aggregate in &self.aggregates {
+            write!(f, " {aggregate}")?;
+        }
+
+        let clauses = [
+            (":where", &self.where_patterns),
+            (":optional", &self.optional_patterns),
+            (":where-not", &self.where_not_patterns),
+        ];
+        for (keyword, patterns) in clauses {
+            if !patterns.is_empty() {
+                write!(f, " {keyword}")?;
+                for pattern in patterns {
+                    write!(f, " {pattern}")?;
+                }
+            }
+        }
+
+        if !self.filters.is_empty() {
+            write!(f, " :filter")?;
+            for filter in &self.filters {
+                write!(f, " (fn {})", filter.selector)?;
+            }
+        }
+        if !self.group_by.is_empty() {
+            write!(f, " :group-by")?;
+            for variable in &self.group_by {
+                write!(f, " {variable}")?;
+            }
+        }
+        if !self.order_by.is_empty() {
+            write!(f, " :order-by")?;
+            for order in &self.order_by {
+                write!(f, " {order}")?;
+            }
+        }
+        if self.distinct {
+            write!(f, " :distinct")?;
+        }
+        if let Some(limit) = self.limit {
+            write!(f, " :limit {limit}")?;
+        }
+        if self.offset > 0 {
+            write!(f, " :offset {}", self.offset)?;
+        }
+        write!(f, "]")
+    }
 }
 
+/// Error returned when a query cannot be sent to the server.
+#[derive(Debug, PartialEq, Eq)]
+pub enum QueryBuildError {
+    /// The query has no `find` variables, groups, or aggregates.
+    NoFindVariables,
+    /// A `find` variable is returned alongside groups or aggregates.
+    UnaggregatedVariable(String),
+    /// A `find` variable, filter selector, or `order_by` variable is not
+    /// bound by any where or optional pattern.
+    UnboundVariable(String),
+    /// Filters are Rust closures and cannot be serialized.
+    FilterNotSerializable(String),
+    /// A pattern element is not valid in its position (entity, attribute, or value).
+    InvalidPatternElement {
+        /// The position within the pattern.
+        position: &'static str,
+        /// The rendered element.
+        element: String,
+    },
+}
+
+impl fmt::Display for QueryBuildError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::NoFindVariables => {
+                write!(f, "query must find at least one variable or aggregate")
+            }
+            Self::UnaggregatedVariable(name) => {
+                write!(
+                    f,
+                    "variable ?{name} cannot be returned alongside aggregates; group by it instead"
+                )
+            }
+            Self::UnboundVariable(name) => {
+                write!(
+                    f,
+                    "variable ?{name} is not bound by any where or optional pattern"
+                )
+            }
+            Self::FilterNotSerializable(name) => {
+                write!(f, "filter on ?{name} cannot be sent to the server")
+            }
+            Self::InvalidPatternElement { position, element } => {
+                write!(f, "{element} is not valid in the {position} position")
+            }
+        }
+    }
+}
+
+impl std::error::Error for QueryBuildError {}
+
 /// A row of query results.
 pub type QueryRow = Vec<Option<Datom>>;
 
@@ -404,6 +956,197 @@ mod tests {
         assert_eq!(v1, v2);
     }
 
+    fn name_query() -> Query {
+        Query::new()
+            .find("e")
+            .find("name")
+            .where_pattern(Pattern::new(
+                PatternElement::var("e"),
+                PatternElement::field("name"),
+                PatternElement::var("name"),
+            ))
+    }
+
+    #[test]
+    fn test_query_display() {
+        let query = name_query().where_not(Pattern::new(
+            PatternElement::var("e"),
+            PatternElement::field("deleted"),
+            PatternElement::Value(Value::boolean(true)),
+        ));
+
+        assert_eq!(
+            query.to_string(),
+            "[:find ?e ?name :where [?e :name ?name] :where-not [?e :deleted true]]"
+        );
+    }
+
+    #[test]
+    fn test_query_display_limit_and_offset() {
+        assert_eq!(
+            name_query().limit(10).offset(20).to_string(),
+            "[:find ?e ?name :where [?e :name ?name] :limit 10 :offset 20]"
+        );
+    }
+
+    #[test]
+    fn test_query_display_order_by() {
+        let query = name_query()
+            .order_by("name", Direction::Descending)
+            .order_by_with_nulls("e", Direction::Ascending, Nulls::Last);
+        assert_eq!(
+            query.to_string(),
+            "[:find ?e ?name :where [?e :name ?name] :order-by (?name desc) (?e asc nulls-last)]"
+        );
+    }
+
+    #[test]
+    fn test_order_by_compare() {
+        let ascending = OrderBy {
+            variable: Variable::new("x"),
+            direction: Direction::Ascending,
+            nulls: Nulls::First,
+        };
+        let descending = OrderBy {
+            variable: Variable::new("x"),
+            direction: Direction::Descending,
+            nulls: Nulls::Last,
+        };
+        let one = Datom::number(1);
+        let two = Datom::number(2);
+        let null = Datom::Value(Value::Null);
+
+        assert_eq!(ascending.compare(Some(&one), Some(&two)), Ordering::Less);
+        assert_eq!(
+            descending.compare(Some(&one), Some(&two)),
+            Ordering::Greater
+        );
+        // Unbound and null values are both null, placed the same either way
+        assert_eq!(ascending.compare(None, Some(&null)), Ordering::Equal);
+        assert_eq!(ascending.compare(None, Some(&two)), Ordering::Less);
+        assert_eq!(
+            descending.compare(Some(&null), Some(&two)),
+            Ordering::Greater
+        );
+        // Values sort before entities
+        assert_eq!(
+            ascending.compare(Some(&Datom::entity("a")), Some(&Datom::string("z"))),
+            Ordering::Greater
+        );
+    }
+
+    #[test]
+    fn test_query_display_presence_patterns() {
+        let query = Query::new()
+            .find("e")
+            .has(PatternElement::var("e"), PatternElement::field("email"))
+            .lacks(PatternElement::var("e"), PatternElement::field("verified"));
+
+        assert_eq!(
+            query.to_string(),
+            "[:find ?e :where [?e :email _] :where-not [?e :verified _]]"
+        );
+    }
+
+    #[test]
+    fn test_query_validate_accepts_bound_variables() {
+        assert_eq!(name_query().validate(), Ok(()));
+    }
+
+    #[test]
+    fn test_query_validate_rejects_empty_find() {
+        let query = Query::new().where_pattern(Pattern::new(
+            PatternElement::var("e"),
+            PatternElement::field("name"),
+            PatternElement::var("name"),
+        ));
+
+        assert_eq!(query.validate(), Err(QueryBuildError::NoFindVariables));
+    }
+
+    #[test]
+    fn test_query_validate_rejects_unbound_find_variable() {
+        let query = name_query().find("age");
+
+        assert_eq!(
+            query.validate(),
+            Err(QueryBuildError::UnboundVariable("age".to_owned()))
+        );
+    }
+
+    #[test]
+    fn test_query_validate_rejects_unbound_filter() {
+        let query = name_query().filter(Filter::new(Variable::new("age"), |_| true));
+
+        assert_eq!(
+            query.validate(),
+            Err(QueryBuildError::UnboundVariable("age".to_owned()))
+        );
+    }
+
+    #[test]
+    fn test_query_validate_rejects_unbound_order_by() {
+        let query = name_query().order_by("age", Direction::Ascending);
+
+        assert_eq!(
+            query.validate(),
+            Err(QueryBuildError::UnboundVariable("age".to_owned()))
+        );
+    }
+
+    #[test]
+    fn test_query_display_distinct() {
+        let query = name_query().distinct().limit(2);
+        assert_eq!(
+            query.to_string(),
+            "[:find ?e ?name :where [?e :name ?name] :distinct :limit 2]"
+        );
+    }
+
+    #[test]
+    fn test_query_aggregates() {
+        let query = Query::new()
+            .find_count("e")
+            .find_avg("name")
+            .where_pattern(Pattern::new(
+                PatternElement::var("e"),
+                PatternElement::field("name"),
+                PatternElement::var("name"),
+            ));
+
+        assert_eq!(query.validate(), Ok(()));
+        assert_eq!(
+            query.to_string(),
+            "[:find (count ?e) (avg ?name) :where [?e :name ?name]]"
+        );
+        assert_eq!(query.aggregates[1].column(), "avg(name)");
+        assert_eq!(
+            query.group_by("name").to_string(),
+            "[:find (count ?e) (avg ?name) :where [?e :name ?name] :group-by ?name]"
+        );
+        assert_eq!(
+            name_query().find_count("e").validate(),
+            Err(QueryBuildError::UnaggregatedVariable("e".to_owned()))
+        );
+        assert_eq!(
+            Query::new().group_by("status").validate(),
+            Err(QueryBuildError::UnboundVariable("status".to_owned()))
+        );
+    }
+
+    #[test]
+    fn test_range_filter_keeps_values_in_range() {
+        let filter = Filter::range(
+            Variable::new("age"),
+            ValueRange::greater_than(Value::Number(21.0)),
+        );
+
+        assert!(filter.apply(Some(&Datom::number(30.0))));
+        assert!(!filter.apply(Some(&Datom::number(21.0))));
+        assert!(!filter.apply(Some(&Datom::string("30"))));
+        assert!(!filter.apply(None));
+    }
+
     #[test]
     fn test_datom_clone() {
         let d1 = Datom::string("world");
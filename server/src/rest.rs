@@ -0,0 +1,675 @@
+//! REST/JSON gateway to the sync protocol.
+//!
+//! Each route translates its JSON body into a `ClientMessage` and runs it
+//! through a fresh `ClientConnection`, exactly as a WebSocket client's
+//! request would, so validation, authentication and attribute scoping are
+//! shared with the WebSocket endpoint.
+//!
+//! - `POST /v1/triples` writes triples (`TripleUpdateRequest`)
+//! - `POST /v1/query` runs a query (`QueryRequest`)
+//! - `GET /v1/entity/{id}` reads every attribute of one entity
+//!
+//! Requests name their app in the `x-app-api-key` header, and carry the app's
+//! JWT, if it requires one, as a bearer token in `Authorization`. Entity and
+//! attribute IDs are 32 hex digits. Values are JSON strings, numbers and
+//! booleans; bytes values are written `{"bytes": "<hex>"}`. Writes are
+//! stamped with an HLC from the database clock, one per request.
+//!
+//! # Responses
+//!
+//! - `200 OK` with a JSON body
+//! - `400 Bad Request` for malformed JSON, IDs or values, and for every
+//!   request the protocol rejects with `InvalidArgument`, with the same
+//!   message
+//! - `401 Unauthorized` if `x-app-api-key` is missing or authentication fails
+//! - `403 Forbidden` if the app may not access an attribute
+//! - `404 Not Found` if the entity has no attributes, or for `NotFound` from
+//!   an `update_only` write
+//! - `409 Conflict` if a `create_only` write finds an existing triple
+//! - `500 Internal Server Error` for anything else
+//!
+//! Every error body is `{"error": "<message>"}`.
+
+use std::collections::BTreeMap;
+use std::sync::Arc;
+
+use axum::{
+    Json,
+    extract::{Path, State, rejection::JsonRejection},
+    http::{HeaderMap, StatusCode, header},
+    response::{IntoResponse, Response},
+};
+use serde::{Deserialize, Serialize};
+use serde_json::Value as JsonValue;
+
+use crate::proto::google::rpc::Code;
+use crate::types::{AttributeId, EntityId, HlcTimestamp, ProtoSerializable};
+use crate::websocket::AppState;
+use crate::{ClientConnection, proto};
+
+/// Header naming the app a request is for.
+pub const APP_API_KEY_HEADER: &str = "x-app-api-key";
+
+/// Body of `POST /v1/triples`.
+#[derive(Debug, Deserialize)]
+pub struct WriteTriplesRequest {
+    /// The triples to write, all in one transaction.
+    pub triples: Vec<JsonTriple>,
+    /// How triples that already exist are treated.
+    #[serde(default)]
+    pub mode: JsonWriteMode,
+}
+
+/// A triple to write.
+#[derive(Debug, Deserialize)]
+pub struct JsonTriple {
+    /// Entity ID as 32 hex digits.
+    pub entity_id: String,
+    /// Attribute ID as 32 hex digits.
+    pub attribute_id: String,
+    /// The value: a string, number, boolean, or `{"bytes": "<hex>"}`.
+    pub value: JsonValue,
+}
+
+/// Write mode of `POST /v1/triples`; see `proto::WriteMode`.
+#[derive(Debug, Default, Clone, Copy, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum JsonWriteMode {
+    /// Insert absent triples and overwrite present ones.
+    #[default]
+    Upsert,
+    /// Only insert.
+    CreateOnly,
+    /// Only overwrite.
+    UpdateOnly,
+}
+
+impl From<JsonWriteMode> for proto::WriteMode {
+    fn from(mode: JsonWriteMode) -> Self {
+        match mode {
+            JsonWriteMode::Upsert => Self::Upsert,
+            JsonWriteMode::CreateOnly => Self::CreateOnly,
+            JsonWriteMode::UpdateOnly => Self::UpdateOnly,
+        }
+    }
+}
+
+/// Response of `POST /v1/triples`.
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub struct WriteTriplesResponse {
+    /// One result per written triple, in request order.
+    pub results: Vec<JsonWriteResult>,
+}
+
+/// Outcome of one written triple.
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub struct JsonWriteResult {
+    /// Entity ID as 32 hex digits.
+    pub entity_id: String,
+    /// Attribute ID as 32 hex digits.
+    pub attribute_id: String,
+    /// `insert`, `update`, or `unchanged` if the write did not change the
+    /// stored triple.
+    pub change: String,
+}
+
+/// Body of `POST /v1/query`.
+///
+/// Pattern terms are IDs as 32 hex digits, values as in `JsonTriple`, or
+/// variables written `{"var": "<name>"}`.
+#[derive(Debug, Deserialize)]
+pub struct QueryBody {
+    /// The variables to return, in column order.
+    pub find: Vec<String>,
+    /// The patterns to match.
+    #[serde(default, rename = "where")]
+    pub where_: Vec<JsonPattern>,
+    /// Optional patterns (left join semantics).
+    #[serde(default)]
+    pub optional: Vec<JsonPattern>,
+    /// Negated patterns.
+    #[serde(default)]
+    pub where_not: Vec<JsonPattern>,
+    /// Maximum number of rows to return.
+    pub limit: Option<u32>,
+    /// Number of rows to skip.
+    pub offset: Option<u32>,
+}
+
+/// A `[entity attribute value]` query pattern.
+#[derive(Debug, Deserialize)]
+pub struct JsonPattern {
+    /// Entity ID or variable.
+    pub entity: JsonValue,
+    /// Attribute ID or variable.
+    pub attribute: JsonValue,
+    /// Value or variable.
+    pub value: JsonValue,
+}
+
+/// Response of `POST /v1/query`.
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub struct QueryResponseBody {
+    /// The find variables, in column order.
+    pub columns: Vec<String>,
+    /// One array per row, one cell per column. Undefined cells are `null`
+    /// and IDs are 32 hex digits.
+    pub rows: Vec<Vec<JsonValue>>,
+}
+
+/// Response of `GET /v1/entity/{id}`.
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub struct EntityResponse {
+    /// Entity ID as 32 hex digits.
+    pub entity_id: String,
+    /// The entity's values, keyed by attribute ID as 32 hex digits.
+    pub attributes: BTreeMap<String, JsonValue>,
+}
+
+/// Error returned by a REST route, sent as `{"error": message}`.
+#[derive(Debug)]
+pub struct RestError {
+    /// HTTP status of the response.
+    pub status: StatusCode,
+    /// Human-readable message.
+    pub message: String,
+}
+
+impl RestError {
+    /// A `400 Bad Request` error.
+    #[must_use]
+    pub fn bad_request(message: impl Into<String>) -> Self {
+        Self {
+            status: StatusCode::BAD_REQUEST,
+            message: message.into(),
+        }
+    }
+
+    /// A `500 Internal Server Error`.
+    #[must_use]
+    pub fn internal(message: impl Into<String>) -> Self {
+        Self {
+            status: StatusCode::INTERNAL_SERVER_ERROR,
+            message: message.into(),
+        }
+    }
+
+    /// The error for a non-OK status returned by `ClientConnection`.
+    #[must_use]
+    pub fn from_status(status: proto::google::rpc::Status) -> Self {
+        Self {
+            status: http_status(status.code),
+            message: status.message,
+        }
+    }
+}
+
+impl std::fmt::Display for RestError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {}", self.status, self.message)
+    }
+}
+
+impl std::error::Error for RestError {}
+
+impl From<JsonRejection> for RestError {
+    fn from(rejection: JsonRejection) -> Self {
+        Self::bad_request(rejection.body_text())
+    }
+}
+
+impl IntoResponse for RestError {
+    fn into_response(self) -> Response {
+        (
+            self.status,
+            Json(serde_json::json!({ "error": self.message })),
+        )
+            .into_response()
+    }
+}
+
+/// HTTP status for an rpc status code.
+#[must_use]
+pub fn http_status(code: i32) -> StatusCode {
+    match Code::try_from(code) {
+        Ok(Code::Ok) => StatusCode::OK,
+        Ok(Code::InvalidArgument | Code::OutOfRange) => StatusCode::BAD_REQUEST,
+        Ok(Code::Unauthenticated) => StatusCode::UNAUTHORIZED,
+        Ok(Code::PermissionDenied) => StatusCode::FORBIDDEN,
+        Ok(Code::NotFound) => StatusCode::NOT_FOUND,
+        Ok(Code::AlreadyExists | Code::Aborted | Code::FailedPrecondition) => StatusCode::CONFLICT,
+        Ok(Code::ResourceExhausted) => StatusCode::TOO_MANY_REQUESTS,
+        Ok(Code::Unavailable) => StatusCode::SERVICE_UNAVAILABLE,
+        _ => StatusCode::INTERNAL_SERVER_ERROR,
+    }
+}
+
+/// Handle `POST /v1/triples`.
+pub async fn write_triples_handler(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    body: Result<Json<WriteTriplesRequest>, JsonRejection>,
+) -> Result<Json<WriteTriplesResponse>, RestError> {
+    let Json(body) = body?;
+    let mut triples = body
+        .triples
+        .iter()
+        .map(|triple| {
+            Ok(proto::Triple {
+                entity_id: Some(parse_entity_id(&triple.entity_id)?.0.to_vec()),
+                attribute_id: Some(parse_attribute_id(&triple.attribute_id)?.0.to_vec()),
+                value: Some(json_to_triple_value(&triple.value)?),
+                hlc: None,
+                attribute_name: None,
+            })
+        })
+        .collect::<Result<Vec<_>, RestError>>()?;
+
+    let mut conn = connect(&state, &headers)?;
+    let hlc = next_hlc(&conn)?;
+    for triple in &mut triples {
+        triple.hlc = Some(hlc.to_proto());
+    }
+    let response = send(
+        &mut conn,
+        proto::client_message::Payload::TripleUpdateRequest(proto::TripleUpdateRequest {
+            triples,
+            mode: proto::WriteMode::from(body.mode).into(),
+            expected_versions: vec![],
+        }),
+    )?;
+
+    let results = response
+        .write_results
+        .iter()
+        .map(|result| JsonWriteResult {
+            entity_id: encode_hex(&result.entity_id),
+            attribute_id: encode_hex(&result.attribute_id),
+            change: match proto::ChangeType::try_from(result.change_type) {
+                Ok(proto::ChangeType::Insert) => "insert",
+                Ok(proto::ChangeType::Update) => "update",
+                _ => "unchanged",
+            }
+            .to_owned(),
+        })
+        .collect();
+    Ok(Json(WriteTriplesResponse { results }))
+}
+
+/// Handle `POST /v1/query`.
+pub async fn query_handler(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    body: Result<Json<QueryBody>, JsonRejection>,
+) -> Result<Json<QueryResponseBody>, RestError> {
+    let Json(body) = body?;
+    let request = proto::QueryRequest {
+        find: body.find.into_iter().map(variable).collect(),
+        r#where: json_to_patterns(&body.where_)?,
+        optional: json_to_patterns(&body.optional)?,
+        where_not: json_to_patterns(&body.where_not)?,
+        limit: body.limit,
+        offset: body.offset,
+        ..Default::default()
+    };
+
+    let mut conn = connect(&state, &headers)?;
+    let response = send(&mut conn, proto::client_message::Payload::Query(request))?;
+    Ok(Json(QueryResponseBody {
+        rows: rows_to_json(&response.rows),
+        columns: response.columns,
+    }))
+}
+
+/// Handle `GET /v1/entity/{id}`.
+pub async fn entity_handler(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    headers: HeaderMap,
+) -> Result<Json<EntityResponse>, RestError> {
+    let entity_id = parse_entity_id(&id)?;
+    let request = proto::QueryRequest {
+        find: vec![
+            variable("attribute".to_owned()),
+            variable("value".to_owned()),
+        ],
+        r#where: vec![proto::QueryPattern {
+            entity: Some(proto::query_pattern::Entity::EntityId(entity_id.0.to_vec())),
+            attribute: Some(proto::query_pattern::Attribute::AttributeVariable(
+                variable("attribute".to_owned()),
+            )),
+            value_group: Some(proto::query_pattern::ValueGroup::ValueVariable(variable(
+                "value".to_owned(),
+            ))),
+        }],
+        ..Default::default()
+    };
+
+    let mut conn = connect(&state, &headers)?;
+    let response = send(&mut conn, proto::client_message::Payload::Query(request))?;
+    let attributes: BTreeMap<String, JsonValue> = rows_to_json(&response.rows)
+        .into_iter()
+        .filter_map(|row| {
+            let mut cells = row.into_iter();
+            match (cells.next(), cells.next()) {
+                (Some(JsonValue::String(attribute_id)), Some(value)) => Some((attribute_id, value)),
+                _ => None,
+            }
+        })
+        .collect();
+    if attributes.is_empty() {
+        return Err(RestError {
+            status: StatusCode::NOT_FOUND,
+            message: format!("entity {} not found", entity_id.to_hex()),
+        });
+    }
+    Ok(Json(EntityResponse {
+        entity_id: entity_id.to_hex(),
+        attributes,
+    }))
+}
+
+/// Open a connection for the app named in `headers` and authenticate it.
+///
+/// # Errors
+///
+/// Returns `401 Unauthorized` if `x-app-api-key` is missing, or the error
+/// the `ConnectRequest` fails with.
+#[allow(clippy::disallowed_methods)] // Arc::clone is cheap
+fn connect(state: &AppState, headers: &HeaderMap) -> Result<ClientConnection, RestError> {
+    let app_api_key = headers
+        .get(APP_API_KEY_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .ok_or_else(|| RestError {
+            status: StatusCode::UNAUTHORIZED,
+            message: format!("{APP_API_KEY_HEADER} header is required"),
+        })?;
+    let auth_token = headers
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .map(str::to_owned);
+
+    let mut conn = ClientConnection::new_awaiting_connect(Arc::clone(&state.registry))
+        .with_value_limits(state.config.value_limits)
+        .with_config_registry(Arc::clone(&state.configs));
+    send(
+        &mut conn,
+        proto::client_message::Payload::Connect(proto::ConnectRequest {
+            app_api_key: app_api_key.to_owned(),
+            auth_token,
+        }),
+    )?;
+    Ok(conn)
+}
+
+/// Issue the HLC for a write from the connected database's clock.
+fn next_hlc(conn: &ClientConnection) -> Result<HlcTimestamp, RestError> {
+    let database = conn
+        .shared_database()
+        .ok_or_else(|| RestError::internal("connection has no database"))?;
+    let mut db = database
+        .write()
+        .map_err(|_| RestError::internal("database lock poisoned"))?;
+    Ok(db.next_hlc())
+}
+
+/// Send one request through `conn` and return its OK response.
+///
+/// # Errors
+///
+/// Returns the response's non-OK status as a `RestError`.
+fn send(
+    conn: &mut ClientConnection,
+    payload: proto::client_message::Payload,
+) -> Result<proto::ServerResponse, RestError> {
+    let messages = conn.handle_message(proto::ClientMessage {
+        request_id: Some(1),
+        payload: Some(payload),
+    });
+    let response = messages
+        .into_iter()
+        .find_map(|message| match message.payload {
+            Some(proto::server_message::Payload::Response(response)) => Some(response),
+            _ => None,
+        })
+        .ok_or_else(|| RestError::internal("no response to request"))?;
+    match response.status {
+        Some(ref status) if status.code != Code::Ok as i32 => {
+            Err(RestError::from_status(status.to_owned()))
+        }
+        _ => Ok(response),
+    }
+}
+
+const fn variable(label: String) -> proto::QueryPatternVariable {
+    proto::QueryPatternVariable { label: Some(label) }
+}
+
+/// The variable name of a `{"var": "<name>"}` term, if `term` is one.
+fn as_variable(term: &JsonValue) -> Option<String> {
+    match term.as_object()?.get("var")? {
+        JsonValue::String(name) => Some(name.to_owned()),
+        _ => None,
+    }
+}
+
+fn json_to_patterns(patterns: &[JsonPattern]) -> Result<Vec<proto::QueryPattern>, RestError> {
+    patterns.iter().map(json_to_pattern).collect()
+}
+
+fn json_to_pattern(pattern: &JsonPattern) -> Result<proto::QueryPattern, RestError> {
+    use proto::query_pattern::{Attribute, Entity, ValueGroup};
+
+    let entity = match as_variable(&pattern.entity) {
+        Some(name) => Entity::EntityVariable(variable(name)),
+        None => Entity::EntityId(parse_entity_id(id_term(&pattern.entity)?)?.0.to_vec()),
+    };
+    let attribute = match as_variable(&pattern.attribute) {
+        Some(name) => Attribute::AttributeVariable(variable(name)),
+        None => {
+            Attribute::AttributeId(parse_attribute_id(id_term(&pattern.attribute)?)?.0.to_vec())
+        }
+    };
+    let value_group = match as_variable(&pattern.value) {
+        Some(name) => ValueGroup::ValueVariable(variable(name)),
+        None => ValueGroup::Value(json_to_triple_value(&pattern.value)?),
+    };
+    Ok(proto::QueryPattern {
+        entity: Some(entity),
+        attribute: Some(attribute),
+        value_group: Some(value_group),
+    })
+}
+
+/// The ID string of a pattern term that is not a variable.
+fn id_term(term: &JsonValue) -> Result<&str, RestError> {
+    term.as_str().ok_or_else(|| {
+        RestError::bad_request(format!(
+            "pattern term must be an ID or {{\"var\": name}}, got {term}"
+        ))
+    })
+}
+
+fn parse_entity_id(hex: &str) -> Result<EntityId, RestError> {
+    EntityId::from_hex(hex).map_err(|e| RestError::bad_request(format!("invalid entity ID: {e}")))
+}
+
+fn parse_attribute_id(hex: &str) -> Result<AttributeId, RestError> {
+    AttributeId::from_hex(hex)
+        .map_err(|e| RestError::bad_request(format!("invalid attribute ID: {e}")))
+}
+
+/// Convert a JSON scalar, or `{"bytes": "<hex>"}`, to a triple value.
+fn json_to_triple_value(value: &JsonValue) -> Result<proto::TripleValue, RestError> {
+    use proto::triple_value::Value;
+
+    let value = match value {
+        JsonValue::String(s) => Value::String(s.to_owned()),
+        JsonValue::Number(n) => Value::Number(
+            n.as_f64()
+                .ok_or_else(|| RestError::bad_request(format!("unsupported number {n}")))?,
+        ),
+        JsonValue::Bool(b) => Value::Boolean(*b),
+        JsonValue::Object(object) => match object.get("bytes") {
+            Some(JsonValue::String(hex)) if object.len() == 1 => {
+                Value::Bytes(decode_hex(hex).ok_or_else(|| {
+                    RestError::bad_request(format!("bytes value must be hex, got {hex:?}"))
+                })?)
+            }
+            _ => return Err(unsupported_value(value)),
+        },
+        JsonValue::Null | JsonValue::Array(_) => return Err(unsupported_value(value)),
+    };
+    Ok(proto::TripleValue { value: Some(value) })
+}
+
+fn unsupported_value(value: &JsonValue) -> RestError {
+    RestError::bad_request(format!(
+        "value must be a string, number, boolean or {{\"bytes\": hex}}, got {value}"
+    ))
+}
+
+/// Convert a triple value to JSON. Numbers JSON cannot hold become `null`.
+fn triple_value_to_json(value: &proto::TripleValue) -> JsonValue {
+    use proto::triple_value::Value;
+
+    match &value.value {
+        Some(Value::String(s)) => JsonValue::String(s.to_owned()),
+        Some(Value::Number(n)) => {
+            serde_json::Number::from_f64(*n).map_or(JsonValue::Null, JsonValue::Number)
+        }
+        Some(Value::Boolean(b)) => JsonValue::Bool(*b),
+        Some(Value::Bytes(bytes)) => serde_json::json!({ "bytes": encode_hex(bytes) }),
+        None => JsonValue::Null,
+    }
+}
+
+fn rows_to_json(rows: &[proto::QueryResultRow]) -> Vec<Vec<JsonValue>> {
+    rows.iter()
+        .map(|row| row.values.iter().map(cell_to_json).collect())
+        .collect()
+}
+
+fn cell_to_json(cell: &proto::QueryResultValue) -> JsonValue {
+    match &cell.value {
+        _ if cell.is_undefined => JsonValue::Null,
+        Some(proto::query_result_value::Value::Id(id)) => JsonValue::String(id_to_hex(id)),
+        Some(proto::query_result_value::Value::TripleValue(value)) => triple_value_to_json(value),
+        None => JsonValue::Null,
+    }
+}
+
+/// Convert an ID from a query result back to 32 hex digits.
+///
+/// Query results carry IDs as their UTF-8 text with trailing NUL bytes
+/// trimmed, or as hex if the bytes are not UTF-8, so anything shorter than
+/// 32 characters is padded back to 16 bytes.
+fn id_to_hex(id: &str) -> String {
+    if id.len() == 32 {
+        return id.to_owned();
+    }
+    let mut bytes = [0u8; 16];
+    for (byte, value) in bytes.iter_mut().zip(id.as_bytes()) {
+        *byte = *value;
+    }
+    EntityId(bytes).to_hex()
+}
+
+fn encode_hex(bytes: &[u8]) -> String {
+    use std::fmt::Write;
+    bytes
+        .iter()
+        .fold(String::with_capacity(bytes.len() * 2), |mut acc, b| {
+            let _ = write!(acc, "{b:02x}");
+            acc
+        })
+}
+
+fn decode_hex(hex: &str) -> Option<Vec<u8>> {
+    if !hex.len().is_multiple_of(2) {
+        return None;
+    }
+    hex.as_bytes()
+        .chunks(2)
+        .map(|pair| {
+            let high = char::from(pair[0]).to_digit(16)?;
+            let low = char::from(pair[1]).to_digit(16)?;
+            u8::try_from(high << 4 | low).ok()
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hex_round_trip() {
+        let bytes = vec![0x00, 0x7f, 0xab, 0xff];
+        assert_eq!(encode_hex(&bytes), "007fabff");
+        assert_eq!(decode_hex("007fabff"), Some(bytes));
+        assert_eq!(decode_hex("abc"), None);
+        assert_eq!(decode_hex("zz"), None);
+        assert_eq!(decode_hex("+f"), None);
+    }
+
+    #[test]
+    fn test_json_values_round_trip() {
+        for value in [
+            serde_json::json!("text"),
+            serde_json::json!(1.5),
+            serde_json::json!(true),
+            serde_json::json!({ "bytes": "0102" }),
+        ] {
+            let triple_value = json_to_triple_value(&value).expect("valid value");
+            assert_eq!(triple_value_to_json(&triple_value), value);
+        }
+    }
+
+    #[test]
+    fn test_unsupported_values_are_rejected() {
+        for value in [
+            JsonValue::Null,
+            serde_json::json!([1]),
+            serde_json::json!({ "other": "x" }),
+            serde_json::json!({ "bytes": "xyz" }),
+        ] {
+            let error = json_to_triple_value(&value).expect_err("unsupported value");
+            assert_eq!(error.status, StatusCode::BAD_REQUEST);
+        }
+    }
+
+    #[test]
+    fn test_id_to_hex_restores_trimmed_ids() {
+        let hex = "0102030405060708090a0b0c0d0e0f10";
+        assert_eq!(id_to_hex(hex), hex);
+        // "ab" followed by 14 NUL bytes, as `id_to_string` renders it
+        assert_eq!(id_to_hex("ab"), "61620000000000000000000000000000");
+    }
+
+    #[test]
+    fn test_http_status_mapping() {
+        assert_eq!(
+            http_status(Code::InvalidArgument as i32),
+            StatusCode::BAD_REQUEST
+        );
+        assert_eq!(
+            http_status(Code::Unauthenticated as i32),
+            StatusCode::UNAUTHORIZED
+        );
+        assert_eq!(
+            http_status(Code::PermissionDenied as i32),
+            StatusCode::FORBIDDEN
+        );
+        assert_eq!(
+            http_status(Code::AlreadyExists as i32),
+            StatusCode::CONFLICT
+        );
+        assert_eq!(
+            http_status(Code::Internal as i32),
+            StatusCode::INTERNAL_SERVER_ERROR
+        );
+        assert_eq!(http_status(-1), StatusCode::INTERNAL_SERVER_ERROR);
+    }
+}
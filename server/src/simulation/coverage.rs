@@ -0,0 +1,169 @@
+//! Coverage tracking for deterministic simulation testing.
+//!
+//! Records which malformations the message generator produced and which
+//! error codes the server answered with, so a run shows whether malformed
+//! inputs actually reach the validation branches in `ClientConnection`.
+
+// Simulation code legitimately needs cloning for test data
+#![allow(clippy::disallowed_methods)]
+
+use std::collections::BTreeMap;
+use std::fmt;
+
+use super::message_gen::MalformationType;
+use crate::proto::google::rpc::Code;
+
+/// How often one malformation type was generated and rejected.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct MalformationCount {
+    /// Number of messages generated with this malformation.
+    pub generated: u64,
+    /// Number of those messages that got an error response.
+    pub rejected: u64,
+}
+
+/// Malformation and error code counts for a simulation run.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SimulationCoverage {
+    /// Counts per malformation type, including types never generated.
+    pub malformations: BTreeMap<MalformationType, MalformationCount>,
+    /// Number of responses per non-OK status code.
+    pub error_codes: BTreeMap<i32, u64>,
+}
+
+impl Default for SimulationCoverage {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SimulationCoverage {
+    /// Create empty coverage with every malformation type at zero.
+    #[must_use]
+    pub fn new() -> Self {
+        Self {
+            malformations: MalformationType::ALL
+                .into_iter()
+                .map(|malformation| (malformation, MalformationCount::default()))
+                .collect(),
+            error_codes: BTreeMap::new(),
+        }
+    }
+
+    /// Record the response to one message.
+    ///
+    /// `malformation` is the malformation applied to the message, if any, and
+    /// `code` is the response's status code, or `None` if it had no status.
+    pub fn record(&mut self, malformation: Option<MalformationType>, code: Option<i32>) {
+        let rejected = code.is_some_and(|code| code != Code::Ok as i32);
+        if let Some(malformation) = malformation {
+            let count = self.malformations.entry(malformation).or_default();
+            count.generated += 1;
+            if rejected {
+                count.rejected += 1;
+            }
+        }
+        if let Some(code) = code.filter(|_| rejected) {
+            *self.error_codes.entry(code).or_default() += 1;
+        }
+    }
+
+    /// Total number of malformed messages generated.
+    #[must_use]
+    pub fn malformed_messages(&self) -> u64 {
+        self.malformations
+            .values()
+            .map(|count| count.generated)
+            .sum()
+    }
+
+    /// Malformation types that were generated but never got an error
+    /// response, which points at a gap in the server's validation.
+    pub fn unrejected_malformations(
+        &self,
+    ) -> impl Iterator<Item = (MalformationType, MalformationCount)> + '_ {
+        self.malformations
+            .iter()
+            .filter(|(_, count)| count.generated > 0 && count.rejected == 0)
+            .map(|(malformation, count)| (*malformation, *count))
+    }
+}
+
+impl fmt::Display for SimulationCoverage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut lines = Vec::new();
+        if self.malformed_messages() > 0 {
+            lines.push("malformations:".to_string());
+            for (malformation, count) in &self.malformations {
+                lines.push(format!(
+                    "  {malformation:?}: {} generated, {} rejected",
+                    count.generated, count.rejected
+                ));
+            }
+        }
+        if !self.error_codes.is_empty() {
+            lines.push("error codes:".to_string());
+            for (code, responses) in &self.error_codes {
+                let name = Code::try_from(*code)
+                    .map_or_else(|_| code.to_string(), |code| code.as_str_name().to_string());
+                lines.push(format!("  {name}: {responses}"));
+            }
+        }
+        write!(f, "{}", lines.join("\n"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_coverage_counts_malformations_and_error_codes() {
+        let mut coverage = SimulationCoverage::new();
+        let invalid = Code::InvalidArgument as i32;
+        coverage.record(Some(MalformationType::MissingValue), Some(invalid));
+        coverage.record(Some(MalformationType::MissingValue), Some(invalid));
+        coverage.record(
+            Some(MalformationType::EmptyStringValue),
+            Some(Code::Ok as i32),
+        );
+        coverage.record(None, Some(Code::Ok as i32));
+        coverage.record(None, Some(Code::Aborted as i32));
+
+        assert_eq!(coverage.malformed_messages(), 3);
+        assert_eq!(
+            coverage.malformations[&MalformationType::MissingValue],
+            MalformationCount {
+                generated: 2,
+                rejected: 2,
+            }
+        );
+        assert_eq!(
+            coverage.malformations[&MalformationType::MissingEntityId],
+            MalformationCount::default()
+        );
+        assert_eq!(
+            coverage.error_codes,
+            BTreeMap::from([(invalid, 2), (Code::Aborted as i32, 1)])
+        );
+        assert_eq!(
+            coverage.unrejected_malformations().collect::<Vec<_>>(),
+            vec![(
+                MalformationType::EmptyStringValue,
+                MalformationCount {
+                    generated: 1,
+                    rejected: 0,
+                },
+            )]
+        );
+    }
+
+    #[test]
+    fn test_coverage_display() {
+        let mut coverage = SimulationCoverage::new();
+        assert_eq!(coverage.to_string(), "");
+
+        coverage.record(None, Some(Code::NotFound as i32));
+        assert_eq!(coverage.to_string(), "error codes:\n  NOT_FOUND: 1");
+    }
+}
@@ -7,12 +7,14 @@
 #![allow(clippy::disallowed_methods)]
 
 use std::collections::HashMap;
+use std::fmt;
 
 use crate::proto;
+use crate::storage::Database;
 
 /// A recorded operation in the simulation.
 #[allow(dead_code)] // Fields used for debugging and future invariant checks
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub enum Operation {
     /// An update request.
     Update {
@@ -37,7 +39,7 @@ pub enum Operation {
 }
 
 /// Tracks the history of operations for invariant checking.
-#[derive(Debug, Default)]
+#[derive(Debug, Default, Clone)]
 pub struct OperationHistory {
     /// All operations in order.
     operations: Vec<Operation>,
@@ -93,19 +95,18 @@ impl OperationHistory {
             for triple in &request.triples {
                 if let (Some(entity_id), Some(attribute_id), Some(value)) =
                     (&triple.entity_id, &triple.attribute_id, &triple.value)
-                {
-                    if let (Ok(e), Ok(a)) = (
+                    && let (Ok(e), Ok(a)) = (
                         <[u8; 16]>::try_from(entity_id.as_slice()),
                         <[u8; 16]>::try_from(attribute_id.as_slice()),
-                    ) {
-                        self.expected_state.insert(
-                            (e, a),
-                            ExpectedValue {
-                                value: value.clone(),
-                                written_at: self.operations.len(),
-                            },
-                        );
-                    }
+                    )
+                {
+                    self.expected_state.insert(
+                        (e, a),
+                        ExpectedValue {
+                            value: value.clone(),
+                            written_at: self.operations.len(),
+                        },
+                    );
                 }
             }
         } else {
@@ -148,6 +149,12 @@ impl OperationHistory {
         });
     }
 
+    /// Get all operations in order.
+    #[must_use]
+    pub fn operations(&self) -> &[Operation] {
+        &self.operations
+    }
+
     /// Get the number of operations.
     #[must_use]
     pub const fn len(&self) -> usize {
@@ -181,7 +188,7 @@ impl OperationHistory {
 }
 
 /// Statistics about the operation history.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct HistoryStats {
     /// Total number of operations.
     pub total_operations: usize,
@@ -208,7 +215,22 @@ pub struct InvariantViolation {
     pub context: String,
 }
 
+impl fmt::Display for InvariantViolation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "operation #{}: {}",
+            self.operation_index, self.description
+        )?;
+        if !self.context.is_empty() {
+            write!(f, " ({})", self.context)?;
+        }
+        Ok(())
+    }
+}
+
 /// Checker for database invariants.
+#[derive(Clone)]
 pub struct InvariantChecker {
     /// Detected violations.
     violations: Vec<InvariantViolation>,
@@ -251,6 +273,29 @@ impl InvariantChecker {
         self.violations.push(violation);
     }
 
+    /// Check that the primary, attribute and entity-attribute indexes agree.
+    ///
+    /// Run after each commit. Adds one violation per triple that is live in
+    /// some indexes but not others, naming the triple's key.
+    pub fn check_index_consistency(&mut self, database: &Database, operation_index: usize) {
+        match database.check_index_consistency() {
+            Ok(mismatches) => {
+                for mismatch in mismatches {
+                    self.violations.push(InvariantViolation {
+                        description: "Indexes disagree".to_string(),
+                        operation_index,
+                        context: mismatch.to_string(),
+                    });
+                }
+            }
+            Err(e) => self.violations.push(InvariantViolation {
+                description: "Index consistency check failed".to_string(),
+                operation_index,
+                context: e.to_string(),
+            }),
+        }
+    }
+
     /// Check that a response is valid (has proper structure).
     pub fn check_response_valid(
         &mut self,
@@ -353,7 +398,10 @@ mod tests {
                     logical_counter: 0,
                     node_id: 1,
                 }),
+                attribute_name: None,
             }],
+            mode: proto::WriteMode::Upsert.into(),
+            expected_versions: vec![],
         };
 
         let response = proto::ServerResponse {
@@ -376,7 +424,11 @@ mod tests {
     fn test_operation_history_record_failed_update() {
         let mut history = OperationHistory::new();
 
-        let request = proto::TripleUpdateRequest { triples: vec![] };
+        let request = proto::TripleUpdateRequest {
+            triples: vec![],
+            mode: proto::WriteMode::Upsert.into(),
+            expected_versions: vec![],
+        };
 
         let response = proto::ServerResponse {
             request_id: Some(1),
@@ -453,4 +505,73 @@ mod tests {
         checker.check_query_result_structure(&response, 1);
         assert!(checker.has_violations());
     }
+
+    #[test]
+    fn test_invariant_checker_index_consistency_catches_buggy_apply() {
+        use crate::storage::buffer_pool::BufferPool;
+        use crate::storage::{DatabaseFile, PrimaryIndex};
+        use crate::types::{AttributeId, EntityId, HlcTimestamp, TripleRecord, TripleValue};
+
+        let dir = tempfile::tempdir().expect("create temp dir");
+        let path = dir.path().join("test.db");
+        let attribute_id = AttributeId([1u8; 16]);
+        let mut checker = InvariantChecker::new();
+        {
+            let mut db = Database::create(&path, BufferPool::new(100)).expect("create db");
+            let mut txn = db.begin(0).expect("begin");
+            txn.insert(EntityId([1u8; 16]), attribute_id, TripleValue::Number(1.0))
+                .expect("insert");
+            txn.commit().expect("commit");
+            checker.check_index_consistency(&db, 0);
+            assert!(!checker.has_violations(), "{:?}", checker.violations());
+        }
+
+        // A buggy apply path that writes the primary index but skips both
+        // secondary indexes
+        let skipped = EntityId([2u8; 16]);
+        {
+            let mut file = DatabaseFile::open(&path, BufferPool::new(100)).expect("open file");
+            let root_page = file.superblock().primary_index_root;
+            let mut index = PrimaryIndex::new(&mut file, root_page).expect("open index");
+            let record = TripleRecord::new(
+                skipped,
+                attribute_id,
+                1,
+                HlcTimestamp {
+                    physical_time: 1,
+                    logical_counter: 0,
+                    node_id: 0,
+                },
+                TripleValue::Number(2.0),
+            );
+            index.insert(&record).expect("insert");
+            let root_page = index.root_page();
+            file.superblock_mut().primary_index_root = root_page;
+            file.write_superblock().expect("write superblock");
+            file.sync().expect("sync");
+        }
+
+        let (db, _) = Database::open(&path, BufferPool::new(100)).expect("open db");
+        checker.check_index_consistency(&db, 1);
+        let contexts: Vec<&str> = checker
+            .violations()
+            .iter()
+            .map(|violation| {
+                assert_eq!(violation.operation_index, 1);
+                assert_eq!(violation.description, "Indexes disagree");
+                violation.context.as_str()
+            })
+            .collect();
+        assert_eq!(
+            contexts,
+            vec![
+                format!(
+                    "({skipped}, {attribute_id}) is live in the Primary index but not in the Attribute index"
+                ),
+                format!(
+                    "({skipped}, {attribute_id}) is live in the Primary index but not in the EntityAttribute index"
+                ),
+            ]
+        );
+    }
 }
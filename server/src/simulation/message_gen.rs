@@ -11,6 +11,9 @@ use rand::{Rng, SeedableRng};
 
 use crate::proto;
 
+/// Upper bound for generated HLC physical times (2023-11-14), always in the past.
+const MAX_RANDOM_PHYSICAL_TIME_MS: u64 = 1_700_000_000_000;
+
 /// Configuration for message generation.
 #[derive(Debug, Clone)]
 pub struct MessageGenConfig {
@@ -39,7 +42,7 @@ impl Default for MessageGenConfig {
 }
 
 /// Types of malformations that can be generated.
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub enum MalformationType {
     /// Entity ID with wrong length (not 16 bytes).
     WrongLengthEntityId,
@@ -82,7 +85,9 @@ impl MalformationType {
 /// Generator for random `ClientMessage` instances.
 ///
 /// This generator produces deterministic sequences of messages
-/// given the same seed, enabling reproducible testing.
+/// given the same seed, enabling reproducible testing. Cloning a generator
+/// copies its RNG state, so the clone continues the same sequence.
+#[derive(Clone)]
 pub struct MessageGenerator {
     rng: StdRng,
     config: MessageGenConfig,
@@ -153,12 +158,26 @@ impl MessageGenerator {
     /// This may generate a well-formed or malformed message depending
     /// on the configuration.
     pub fn next_message(&mut self) -> proto::ClientMessage {
+        self.next_message_with_malformation().0
+    }
+
+    /// Generate the next message, along with the malformation applied to it.
+    ///
+    /// Consumes the same randomness as `next_message`, so the two can be
+    /// interchanged without changing the sequence.
+    ///
+    /// # Post-conditions
+    /// - The malformation is `None` iff the message is well-formed
+    pub fn next_message_with_malformation(
+        &mut self,
+    ) -> (proto::ClientMessage, Option<MalformationType>) {
         let should_malform = self.rng.random::<f64>() < self.config.malformed_rate;
 
         if should_malform {
-            self.generate_malformed_message()
+            let (message, malformation) = self.generate_malformed_message_with_type();
+            (message, Some(malformation))
         } else {
-            self.generate_wellformed_message()
+            (self.generate_wellformed_message(), None)
         }
     }
 
@@ -183,6 +202,12 @@ impl MessageGenerator {
 
     /// Generate a malformed message.
     pub fn generate_malformed_message(&mut self) -> proto::ClientMessage {
+        self.generate_malformed_message_with_type().0
+    }
+
+    /// Generate a malformed message with a random malformation, returning
+    /// both.
+    fn generate_malformed_message_with_type(&mut self) -> (proto::ClientMessage, MalformationType) {
         let request_id = self.next_request_id;
         self.next_request_id += 1;
 
@@ -190,7 +215,10 @@ impl MessageGenerator {
         let malformation_idx = self.rng.random_range(0..MalformationType::ALL.len());
         let malformation = MalformationType::ALL[malformation_idx];
 
-        self.generate_message_with_malformation(request_id, malformation)
+        (
+            self.generate_message_with_malformation(request_id, malformation),
+            malformation,
+        )
     }
 
     /// Generate a message with a specific malformation.
@@ -208,12 +236,15 @@ impl MessageGenerator {
                     attribute_id: Some(self.random_attribute_id().to_vec()),
                     value: Some(self.random_value()),
                     hlc,
+                    attribute_name: None,
                 };
                 proto::ClientMessage {
                     request_id: Some(request_id),
                     payload: Some(proto::client_message::Payload::TripleUpdateRequest(
                         proto::TripleUpdateRequest {
                             triples: vec![triple],
+                            mode: proto::WriteMode::Upsert.into(),
+                            expected_versions: vec![],
                         },
                     )),
                 }
@@ -225,12 +256,15 @@ impl MessageGenerator {
                     attribute_id: Some(vec![1, 2, 3, 4, 5]), // Wrong length (5 instead of 16)
                     value: Some(self.random_value()),
                     hlc,
+                    attribute_name: None,
                 };
                 proto::ClientMessage {
                     request_id: Some(request_id),
                     payload: Some(proto::client_message::Payload::TripleUpdateRequest(
                         proto::TripleUpdateRequest {
                             triples: vec![triple],
+                            mode: proto::WriteMode::Upsert.into(),
+                            expected_versions: vec![],
                         },
                     )),
                 }
@@ -242,12 +276,15 @@ impl MessageGenerator {
                     attribute_id: Some(self.random_attribute_id().to_vec()),
                     value: Some(self.random_value()),
                     hlc,
+                    attribute_name: None,
                 };
                 proto::ClientMessage {
                     request_id: Some(request_id),
                     payload: Some(proto::client_message::Payload::TripleUpdateRequest(
                         proto::TripleUpdateRequest {
                             triples: vec![triple],
+                            mode: proto::WriteMode::Upsert.into(),
+                            expected_versions: vec![],
                         },
                     )),
                 }
@@ -259,12 +296,15 @@ impl MessageGenerator {
                     attribute_id: None, // Missing
                     value: Some(self.random_value()),
                     hlc,
+                    attribute_name: None,
                 };
                 proto::ClientMessage {
                     request_id: Some(request_id),
                     payload: Some(proto::client_message::Payload::TripleUpdateRequest(
                         proto::TripleUpdateRequest {
                             triples: vec![triple],
+                            mode: proto::WriteMode::Upsert.into(),
+                            expected_versions: vec![],
                         },
                     )),
                 }
@@ -276,12 +316,15 @@ impl MessageGenerator {
                     attribute_id: Some(self.random_attribute_id().to_vec()),
                     value: None, // Missing
                     hlc,
+                    attribute_name: None,
                 };
                 proto::ClientMessage {
                     request_id: Some(request_id),
                     payload: Some(proto::client_message::Payload::TripleUpdateRequest(
                         proto::TripleUpdateRequest {
                             triples: vec![triple],
+                            mode: proto::WriteMode::Upsert.into(),
+                            expected_versions: vec![],
                         },
                     )),
                 }
@@ -289,7 +332,11 @@ impl MessageGenerator {
             MalformationType::EmptyTriples => proto::ClientMessage {
                 request_id: Some(request_id),
                 payload: Some(proto::client_message::Payload::TripleUpdateRequest(
-                    proto::TripleUpdateRequest { triples: vec![] },
+                    proto::TripleUpdateRequest {
+                        triples: vec![],
+                        mode: proto::WriteMode::Upsert.into(),
+                        expected_versions: vec![],
+                    },
                 )),
             },
             MalformationType::OverflowStringValue => {
@@ -303,12 +350,15 @@ impl MessageGenerator {
                         value: Some(proto::triple_value::Value::String(long_string)),
                     }),
                     hlc,
+                    attribute_name: None,
                 };
                 proto::ClientMessage {
                     request_id: Some(request_id),
                     payload: Some(proto::client_message::Payload::TripleUpdateRequest(
                         proto::TripleUpdateRequest {
                             triples: vec![triple],
+                            mode: proto::WriteMode::Upsert.into(),
+                            expected_versions: vec![],
                         },
                     )),
                 }
@@ -322,12 +372,15 @@ impl MessageGenerator {
                         value: Some(proto::triple_value::Value::Number(f64::NAN)),
                     }),
                     hlc,
+                    attribute_name: None,
                 };
                 proto::ClientMessage {
                     request_id: Some(request_id),
                     payload: Some(proto::client_message::Payload::TripleUpdateRequest(
                         proto::TripleUpdateRequest {
                             triples: vec![triple],
+                            mode: proto::WriteMode::Upsert.into(),
+                            expected_versions: vec![],
                         },
                     )),
                 }
@@ -341,12 +394,15 @@ impl MessageGenerator {
                         value: Some(proto::triple_value::Value::Number(f64::INFINITY)),
                     }),
                     hlc,
+                    attribute_name: None,
                 };
                 proto::ClientMessage {
                     request_id: Some(request_id),
                     payload: Some(proto::client_message::Payload::TripleUpdateRequest(
                         proto::TripleUpdateRequest {
                             triples: vec![triple],
+                            mode: proto::WriteMode::Upsert.into(),
+                            expected_versions: vec![],
                         },
                     )),
                 }
@@ -360,12 +416,15 @@ impl MessageGenerator {
                         value: Some(proto::triple_value::Value::String(String::new())),
                     }),
                     hlc,
+                    attribute_name: None,
                 };
                 proto::ClientMessage {
                     request_id: Some(request_id),
                     payload: Some(proto::client_message::Payload::TripleUpdateRequest(
                         proto::TripleUpdateRequest {
                             triples: vec![triple],
+                            mode: proto::WriteMode::Upsert.into(),
+                            expected_versions: vec![],
                         },
                     )),
                 }
@@ -396,6 +455,13 @@ impl MessageGenerator {
             }],
             optional: vec![],
             where_not: vec![],
+            arrow_ipc: false,
+            limit: None,
+            offset: None,
+            order_by: vec![],
+            aggregates: vec![],
+            group_by: vec![],
+            distinct: false,
         }
     }
 
@@ -408,7 +474,11 @@ impl MessageGenerator {
         let triples: Vec<proto::Triple> =
             (0..num_triples).map(|_| self.generate_triple()).collect();
 
-        proto::TripleUpdateRequest { triples }
+        proto::TripleUpdateRequest {
+            triples,
+            mode: proto::WriteMode::Upsert.into(),
+            expected_versions: vec![],
+        }
     }
 
     /// Generate a random well-formed triple.
@@ -418,6 +488,7 @@ impl MessageGenerator {
             attribute_id: Some(self.random_attribute_id().to_vec()),
             value: Some(self.random_value()),
             hlc: Some(self.random_hlc()),
+            attribute_name: None,
         }
     }
 
@@ -434,9 +505,12 @@ impl MessageGenerator {
     }
 
     /// Generate a random HLC timestamp.
+    ///
+    /// Physical times stay in the past so the server's clock drift check
+    /// accepts them regardless of when the simulation runs.
     fn random_hlc(&mut self) -> proto::HlcTimestamp {
         proto::HlcTimestamp {
-            physical_time_ms: self.rng.random(),
+            physical_time_ms: self.rng.random_range(0..=MAX_RANDOM_PHYSICAL_TIME_MS),
             logical_counter: self.rng.random(),
             node_id: self.rng.random(),
         }
@@ -528,6 +602,35 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_message_generator_reports_malformation() {
+        use prost::Message as _;
+
+        let config = MessageGenConfig {
+            malformed_rate: 0.5,
+            ..Default::default()
+        };
+        let mut generator = MessageGenerator::with_config(12345, config.clone());
+        let mut replay = MessageGenerator::with_config(12345, config);
+
+        let mut malformed = 0;
+        for _ in 0..100 {
+            let (message, malformation) = generator.next_message_with_malformation();
+            // Compare encodings, since NaN values never compare equal
+            assert_eq!(
+                message.encode_to_vec(),
+                replay.next_message().encode_to_vec()
+            );
+            if malformation.is_some() {
+                malformed += 1;
+            }
+        }
+        assert!(malformed > 0 && malformed < 100);
+
+        let mut wellformed = MessageGenerator::new(12345);
+        assert_eq!(wellformed.next_message_with_malformation().1, None);
+    }
+
     #[test]
     fn test_message_generator_request_ids_increment() {
         let mut generator = MessageGenerator::new(12345);
@@ -6,19 +6,23 @@
 // Simulation code legitimately needs cloning for test data
 #![allow(clippy::disallowed_methods)]
 
+use std::fmt;
+use std::fmt::Write as _;
+use std::path::Path;
 use std::sync::atomic::{AtomicU64, Ordering};
 
 use crate::client_connection::ClientConnection;
 use crate::proto;
-use crate::storage::Database;
 use crate::storage::buffer_pool::BufferPool;
+use crate::storage::{Database, MIN_PAGE_SIZE, PageId, StorageError, Superblock};
 
 /// Counter for generating unique simulator instance IDs.
 static SIMULATOR_COUNTER: AtomicU64 = AtomicU64::new(0);
 
+use super::coverage::SimulationCoverage;
 use super::invariants::{InvariantChecker, InvariantViolation, OperationHistory};
 use super::message_gen::{MessageGenConfig, MessageGenerator};
-use super::storage::{FaultConfig, SimulatedStorage};
+use super::storage::{FaultConfig, SimulatedStorage, SimulatedStorageSnapshot};
 use super::time::SimulatedTimeSource;
 
 /// Configuration for the simulator.
@@ -34,6 +38,9 @@ pub struct SimulatorConfig {
     pub advance_time: bool,
     /// Milliseconds to advance time per operation.
     pub time_advance_ms: u64,
+    /// Whether a malformation type that never got an error response is an
+    /// invariant violation.
+    pub require_malformation_coverage: bool,
 }
 
 impl SimulatorConfig {
@@ -46,6 +53,7 @@ impl SimulatorConfig {
             message_config: MessageGenConfig::default(),
             advance_time: true,
             time_advance_ms: 1,
+            require_malformation_coverage: false,
         }
     }
 
@@ -76,6 +84,48 @@ impl SimulatorConfig {
         self.advance_time = false;
         self
     }
+
+    /// Fail the run if a malformation type was generated but the server
+    /// accepted every message carrying it, which means validation missed it.
+    #[must_use]
+    pub const fn with_required_malformation_coverage(mut self) -> Self {
+        self.require_malformation_coverage = true;
+        self
+    }
+
+    /// The `simulate` command that replays a run of `message_count` messages
+    /// with this config.
+    ///
+    /// The command sets the seed, malformed rate, fault rates and time
+    /// advancement. Other message generation settings are not included, so the
+    /// replay matches only if they are at their defaults.
+    #[must_use]
+    pub fn reproducer(&self, message_count: usize) -> String {
+        let mut command = format!(
+            "cargo run --bin simulate -- --seed {} --messages {message_count} --malformed-rate {}",
+            self.seed, self.message_config.malformed_rate
+        );
+        let faults = &self.fault_config;
+        for (flag, rate) in [
+            ("--read-error-rate", faults.read_error_rate),
+            ("--write-error-rate", faults.write_error_rate),
+            ("--sync-error-rate", faults.sync_error_rate),
+            ("--corruption-rate", faults.corruption_rate),
+            ("--partial-write-rate", faults.partial_write_rate),
+            ("--torn-write-rate", faults.torn_write_rate),
+        ] {
+            if rate > 0.0 {
+                let _ = write!(command, " {flag} {rate}");
+            }
+        }
+        if !self.advance_time {
+            command.push_str(" --no-time-advance");
+        }
+        if self.require_malformation_coverage {
+            command.push_str(" --require-malformation-coverage");
+        }
+        command
+    }
 }
 
 /// Results from a simulation run.
@@ -95,6 +145,10 @@ pub struct SimulationResult {
     pub completed_successfully: bool,
     /// Error message if simulation failed.
     pub error: Option<String>,
+    /// Malformations generated and error codes returned.
+    pub coverage: SimulationCoverage,
+    /// Command that replays this simulation.
+    pub reproducer: String,
 }
 
 impl SimulationResult {
@@ -105,6 +159,35 @@ impl SimulationResult {
     }
 }
 
+impl fmt::Display for SimulationResult {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "seed {}: {} messages, {} successful, {} failed",
+            self.seed, self.messages_processed, self.successful_operations, self.failed_operations
+        )?;
+        if let Some(error) = &self.error {
+            writeln!(f, "error: {error}")?;
+        }
+        let coverage = self.coverage.to_string();
+        if !coverage.is_empty() {
+            writeln!(f, "{coverage}")?;
+        }
+        if !self.invariant_violations.is_empty() {
+            writeln!(
+                f,
+                "{} invariant violations:",
+                self.invariant_violations.len()
+            )?;
+            for violation in &self.invariant_violations {
+                writeln!(f, "  {violation}")?;
+            }
+        }
+        writeln!(f, "{}", if self.passed() { "PASSED" } else { "FAILED" })?;
+        write!(f, "reproduce with: {}", self.reproducer)
+    }
+}
+
 /// The main simulator harness.
 ///
 /// This ties together all simulation components:
@@ -122,6 +205,53 @@ pub struct Simulator {
     messages_processed: u64,
     successful_operations: u64,
     failed_operations: u64,
+    coverage: SimulationCoverage,
+    /// Simulated storage with fault injection.
+    storage: SimulatedStorage,
+    /// Database file contents to start the next run from, if restored from
+    /// a snapshot.
+    restored_database: Option<Vec<u8>>,
+}
+
+/// The complete state of a simulator between two messages.
+///
+/// Taken by `Simulator::run_with_snapshot` and resumed with
+/// `Simulator::from_snapshot`, so a long failing run can be continued from
+/// near the failure instead of replayed from the first message. Holds the
+/// message generator's RNG, the simulated time and storage, the history and
+/// checker, and a copy of the database file. Per-connection state such as
+/// subscriptions is not captured; the generator sends only updates and
+/// queries, which leave none.
+#[derive(Clone)]
+pub struct SimulatorSnapshot {
+    config: SimulatorConfig,
+    message_generator: MessageGenerator,
+    history: OperationHistory,
+    checker: InvariantChecker,
+    time_source: SimulatedTimeSource,
+    storage: SimulatedStorageSnapshot,
+    messages_processed: u64,
+    successful_operations: u64,
+    failed_operations: u64,
+    coverage: SimulationCoverage,
+    /// Contents of the database file.
+    database: Vec<u8>,
+}
+
+/// A crash planned for the commit of the next update.
+struct PlannedCrash {
+    /// Leading bytes of the torn page that reach the disk.
+    kept: usize,
+    /// Contents of the database file before the update.
+    before: Vec<u8>,
+}
+
+impl SimulatorSnapshot {
+    /// Number of messages processed when the snapshot was taken.
+    #[must_use]
+    pub const fn messages_processed(&self) -> u64 {
+        self.messages_processed
+    }
 }
 
 impl Simulator {
@@ -131,6 +261,8 @@ impl Simulator {
         let message_generator =
             MessageGenerator::with_config(config.seed, config.message_config.clone());
         let time_source = SimulatedTimeSource::default_start();
+        // Not used by the database yet - full DST would make Database generic
+        let storage = SimulatedStorage::with_config(config.seed, config.fault_config.clone());
 
         Self {
             config,
@@ -141,17 +273,81 @@ impl Simulator {
             messages_processed: 0,
             successful_operations: 0,
             failed_operations: 0,
+            coverage: SimulationCoverage::new(),
+            storage,
+            restored_database: None,
         }
     }
 
     /// Run the simulation for a given number of messages.
     ///
     /// This creates a fresh database, sends the specified number of messages,
-    /// and checks invariants after each operation.
+    /// and checks invariants after each operation. The seed and a command that
+    /// replays the run are logged before it starts.
+    ///
+    /// A simulator created by `from_snapshot` starts from the snapshot's
+    /// database instead, and sends `message_count` more messages.
     pub fn run(&mut self, message_count: usize) -> SimulationResult {
-        // Create simulated storage (not used yet - full DST would make Database generic)
-        let _storage =
-            SimulatedStorage::with_config(self.config.seed, self.config.fault_config.clone());
+        self.run_inner(message_count, None).0
+    }
+
+    /// Run like `run`, also capturing the simulator's state once
+    /// `snapshot_at` messages have been processed in total.
+    ///
+    /// The snapshot is taken between messages, so resuming it with
+    /// `from_snapshot` and running the remaining messages behaves exactly like
+    /// the rest of this run.
+    ///
+    /// # Post-conditions
+    /// - The snapshot is `None` if the run never reached `snapshot_at`
+    ///   messages, or if the database file could not be read
+    pub fn run_with_snapshot(
+        &mut self,
+        message_count: usize,
+        snapshot_at: u64,
+    ) -> (SimulationResult, Option<SimulatorSnapshot>) {
+        self.run_inner(message_count, Some(snapshot_at))
+    }
+
+    /// Create a simulator that continues from a snapshot.
+    ///
+    /// The next `run` restores the snapshot's database and continues with the
+    /// message the original run would have sent next.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the snapshot's simulated storage cannot be
+    /// restored.
+    pub fn from_snapshot(snapshot: &SimulatorSnapshot) -> Result<Self, StorageError> {
+        Ok(Self {
+            config: snapshot.config.clone(),
+            message_generator: snapshot.message_generator.clone(),
+            history: snapshot.history.clone(),
+            checker: snapshot.checker.clone(),
+            time_source: snapshot.time_source.clone(),
+            storage: SimulatedStorage::restore(&snapshot.storage)?,
+            messages_processed: snapshot.messages_processed,
+            successful_operations: snapshot.successful_operations,
+            failed_operations: snapshot.failed_operations,
+            coverage: snapshot.coverage.clone(),
+            restored_database: Some(snapshot.database.clone()),
+        })
+    }
+
+    /// Run the simulation, taking a snapshot at `snapshot_at` if given.
+    fn run_inner(
+        &mut self,
+        message_count: usize,
+        snapshot_at: Option<u64>,
+    ) -> (SimulationResult, Option<SimulatorSnapshot>) {
+        // A restored simulator replays from the seed with the earlier messages
+        let total_messages =
+            usize::try_from(self.messages_processed).unwrap_or(usize::MAX) + message_count;
+        let reproducer = self.config.reproducer(total_messages);
+        tracing::info!(
+            "Simulating {message_count} messages with seed {}; reproduce with: {reproducer}",
+            self.config.seed
+        );
 
         // Create database with simulated storage
         // NOTE: For now we use the real Database which uses DatabaseFile internally.
@@ -167,13 +363,10 @@ impl Simulator {
         // Remove if exists
         let _ = std::fs::remove_file(&db_path);
 
-        // Create a buffer pool for the simulator database
-        let pool = BufferPool::new(100);
-
-        let database = match Database::create(&db_path, pool) {
+        let database = match self.open_database(&db_path) {
             Ok(db) => db,
             Err(e) => {
-                return SimulationResult {
+                let result = SimulationResult {
                     seed: self.config.seed,
                     messages_processed: 0,
                     successful_operations: 0,
@@ -181,7 +374,10 @@ impl Simulator {
                     invariant_violations: vec![],
                     completed_successfully: false,
                     error: Some(format!("Failed to create database: {e}")),
+                    coverage: SimulationCoverage::new(),
+                    reproducer,
                 };
+                return (result, None);
             }
         };
 
@@ -189,24 +385,84 @@ impl Simulator {
         let mut client_connection = ClientConnection::new(database);
 
         // Run the simulation
-        let result = self.run_with_connection(&mut client_connection, message_count);
+        let mut snapshot = None;
+        let result = self.run_with_connection(
+            &mut client_connection,
+            &db_path,
+            message_count,
+            reproducer,
+            |simulator, connection| {
+                if snapshot.is_none() && Some(simulator.messages_processed) == snapshot_at {
+                    snapshot = simulator.snapshot(connection, &db_path);
+                }
+            },
+        );
 
         // Cleanup
+        drop(client_connection);
         let _ = std::fs::remove_file(&db_path);
 
-        result
+        (result, snapshot)
+    }
+
+    /// Create the run's database at `path`, or restore a snapshot's there.
+    fn open_database(&mut self, path: &Path) -> Result<Database, String> {
+        // Create a buffer pool for the simulator database
+        let pool = BufferPool::new(100);
+
+        match self.restored_database.take() {
+            Some(bytes) => {
+                std::fs::write(path, bytes).map_err(|e| e.to_string())?;
+                Database::open(path, pool)
+                    .map(|(db, _)| db)
+                    .map_err(|e| e.to_string())
+            }
+            None => Database::create(path, pool).map_err(|e| e.to_string()),
+        }
+    }
+
+    /// Capture the simulator's state and a copy of its database file.
+    fn snapshot(
+        &self,
+        client_connection: &ClientConnection,
+        db_path: &Path,
+    ) -> Option<SimulatorSnapshot> {
+        let bytes = read_database_file(client_connection, db_path)?;
+
+        Some(SimulatorSnapshot {
+            config: self.config.clone(),
+            message_generator: self.message_generator.clone(),
+            history: self.history.clone(),
+            checker: self.checker.clone(),
+            time_source: self.time_source.clone(),
+            storage: self.storage.snapshot(),
+            messages_processed: self.messages_processed,
+            successful_operations: self.successful_operations,
+            failed_operations: self.failed_operations,
+            coverage: self.coverage.clone(),
+            database: bytes,
+        })
     }
 
     /// Run simulation with an existing client connection.
+    ///
+    /// Calls `before_message` before each message and once after the last.
+    #[allow(clippy::too_many_lines)]
     fn run_with_connection(
         &mut self,
         client_connection: &mut ClientConnection,
+        db_path: &Path,
         message_count: usize,
+        reproducer: String,
+        mut before_message: impl FnMut(&Self, &ClientConnection),
     ) -> SimulationResult {
         for _ in 0..message_count {
+            before_message(self, client_connection);
+
             // Generate next message
-            let message = self.message_generator.next_message();
+            let (message, malformation) = self.message_generator.next_message_with_malformation();
             self.messages_processed += 1;
+            let crash = self.plan_crash(&message, client_connection, db_path);
 
             // Process the message (handle_message is now sync and returns Vec)
             let responses = client_connection.handle_message(message.clone());
@@ -214,6 +470,7 @@ impl Simulator {
             // For simulation purposes, we expect exactly one response for most messages
             // (Subscribe may return multiple, but we only check the last one which is the status)
             let Some(response) = responses.last() else {
+                self.coverage.record(malformation, None);
                 self.checker.add_violation(InvariantViolation {
                     description: "No response returned".to_string(),
                     operation_index: self.history.len(),
@@ -225,6 +482,7 @@ impl Simulator {
             // Extract the server response
             let Some(proto::server_message::Payload::Response(server_response)) = &response.payload
             else {
+                self.coverage.record(malformation, None);
                 self.checker.add_violation(InvariantViolation {
                     description: "No response returned".to_string(),
                     operation_index: self.history.len(),
@@ -232,19 +490,29 @@ impl Simulator {
                 });
                 continue;
             };
+            self.coverage.record(
+                malformation,
+                server_response.status.as_ref().map(|status| status.code),
+            );
 
             // Check invariants and record operation
             match &message.payload {
                 Some(proto::client_message::Payload::TripleUpdateRequest(req)) => {
                     self.checker
                         .check_update_response(req, server_response, self.history.len());
-                    self.history.record_update(req.clone(), server_response);
-
-                    if server_response
+                    let committed = server_response
                         .status
                         .as_ref()
-                        .is_some_and(|s| s.code == proto::google::rpc::Code::Ok as i32)
-                    {
+                        .is_some_and(|s| s.code == proto::google::rpc::Code::Ok as i32);
+                    if committed {
+                        if let Some(crash) = crash {
+                            self.crash_and_recover(client_connection, db_path, &crash);
+                        }
+                        self.check_index_consistency(client_connection);
+                    }
+                    self.history.record_update(req.clone(), server_response);
+
+                    if committed {
                         self.successful_operations += 1;
                     } else {
                         self.failed_operations += 1;
@@ -267,14 +535,26 @@ impl Simulator {
                 }
                 Some(
                     proto::client_message::Payload::Subscribe(_)
+                    | proto::client_message::Payload::SubscribeQuery(_)
                     | proto::client_message::Payload::Unsubscribe(_)
-                    | proto::client_message::Payload::Connect(_),
-                ) => {
-                    // Subscriptions and Connect not supported in simulation yet
-                    self.failed_operations += 1;
-                }
-                None => {
-                    // Message with no payload - this is an error
+                    | proto::client_message::Payload::Connect(_)
+                    | proto::client_message::Payload::Contains(_)
+                    | proto::client_message::Payload::OpenCursor(_)
+                    | proto::client_message::Payload::FetchCursor(_)
+                    | proto::client_message::Payload::CloseCursor(_)
+                    | proto::client_message::Payload::SetDefaults(_)
+                    | proto::client_message::Payload::Delete(_)
+                    | proto::client_message::Payload::ReadBatch(_)
+                    | proto::client_message::Payload::DescribeSchema(_)
+                    | proto::client_message::Payload::BeginTxn(_)
+                    | proto::client_message::Payload::CommitTxn(_)
+                    | proto::client_message::Payload::AbortTxn(_)
+                    | proto::client_message::Payload::BulkLoad(_),
+                )
+                | None => {
+                    // Subscriptions, Connect, Contains, cursors, deletes, read batches,
+                    // schema descriptions, explicit transactions and bulk loads are not
+                    // supported in simulation yet; a message with no payload is an error
                     self.failed_operations += 1;
                 }
             }
@@ -285,6 +565,12 @@ impl Simulator {
             }
         }
 
+        before_message(self, client_connection);
+
+        if self.config.require_malformation_coverage {
+            self.check_malformation_coverage();
+        }
+
         SimulationResult {
             seed: self.config.seed,
             messages_processed: self.messages_processed,
@@ -293,9 +579,138 @@ impl Simulator {
             invariant_violations: self.checker.violations().to_vec(),
             completed_successfully: true,
             error: None,
+            coverage: self.coverage.clone(),
+            reproducer,
+        }
+    }
+
+    /// Report each malformation type the server never rejected.
+    fn check_malformation_coverage(&mut self) {
+        for (malformation, count) in self.coverage.unrejected_malformations() {
+            self.checker.add_violation(InvariantViolation {
+                description: "Malformation never rejected".to_string(),
+                operation_index: self.history.len(),
+                context: format!(
+                    "{malformation:?}: all {} messages were accepted",
+                    count.generated
+                ),
+            });
+        }
+    }
+
+    /// Decide whether a crash tears the commit of `message`, using the fault
+    /// config's `torn_write_rate`, and if so copy the database file first.
+    fn plan_crash(
+        &mut self,
+        message: &proto::ClientMessage,
+        client_connection: &ClientConnection,
+        db_path: &Path,
+    ) -> Option<PlannedCrash> {
+        if !matches!(
+            message.payload,
+            Some(proto::client_message::Payload::TripleUpdateRequest(_))
+        ) {
+            return None;
+        }
+        let kept = self.storage.next_torn_write()?;
+        let before = read_database_file(client_connection, db_path)?;
+        Some(PlannedCrash { kept, before })
+    }
+
+    /// Crash after a commit, tearing one page it wrote, then reopen the
+    /// database through recovery.
+    ///
+    /// The commit synced its WAL record before writing index pages, so
+    /// recovery must restore it however those pages tear. WAL pages are never
+    /// torn, and the commit's other pages reach the disk intact. If recovery
+    /// fails, the violation is recorded and the run continues on the database
+    /// from before the crash.
+    fn crash_and_recover(
+        &mut self,
+        client_connection: &mut ClientConnection,
+        db_path: &Path,
+        crash: &PlannedCrash,
+    ) {
+        let operation_index = self.history.len();
+        let Some(after) = read_database_file(client_connection, db_path) else {
+            return;
+        };
+        let Some((torn, page_id)) = self.tear_page(crash, &after) else {
+            return;
+        };
+        let context = format!("page {page_id} torn after {} bytes", crash.kept);
+        tracing::debug!("Simulating a crash: {context}");
+
+        if let Err(e) = std::fs::write(db_path, torn) {
+            self.checker.add_violation(InvariantViolation {
+                description: "Failed to simulate a crash".to_string(),
+                operation_index,
+                context: format!("{context}: {e}"),
+            });
+            return;
+        }
+        match Database::open(db_path, BufferPool::new(100)) {
+            Ok((database, _)) => *client_connection = ClientConnection::new(database),
+            Err(e) => {
+                self.checker.add_violation(InvariantViolation {
+                    description: "Recovery failed after a torn write".to_string(),
+                    operation_index,
+                    context: format!("{context}: {e}"),
+                });
+                let _ = std::fs::write(db_path, after);
+            }
         }
     }
 
+    /// Tear a random non-WAL page that changed between `crash.before` and
+    /// `after`, keeping `crash.kept` bytes of its new contents.
+    ///
+    /// Returns the torn file contents and the torn page, or `None` if no such
+    /// page changed.
+    fn tear_page(&mut self, crash: &PlannedCrash, after: &[u8]) -> Option<(Vec<u8>, PageId)> {
+        let superblock = read_superblock(after)?;
+        let page_size = superblock.page_size as usize;
+        let wal_start = superblock.txn_log_start;
+        let wal_pages = wal_start..wal_start + superblock.txn_log_capacity;
+        let changed: Vec<PageId> = after
+            .chunks(page_size)
+            .enumerate()
+            .filter(|(index, page)| {
+                let start = index * page_size;
+                crash.before.get(start..start + page.len()) != Some(*page)
+            })
+            .map(|(index, _)| index as PageId)
+            .filter(|page_id| !wal_pages.contains(&(page_id * u64::from(superblock.page_size))))
+            .collect();
+        let page_id = self.storage.pick_torn_page(&changed)?;
+
+        let mut torn = after.to_vec();
+        let start = usize::try_from(page_id).ok()? * page_size;
+        let end = (start + page_size).min(torn.len());
+        let tear = start + crash.kept.min(page_size);
+        for (offset, byte) in torn.iter_mut().enumerate().take(end).skip(tear) {
+            *byte = crash.before.get(offset).copied().unwrap_or(0);
+        }
+        Some((torn, page_id))
+    }
+
+    /// Check that the connection's database indexes agree after a commit.
+    fn check_index_consistency(&mut self, client_connection: &ClientConnection) {
+        let operation_index = self.history.len();
+        let Some(database) = client_connection.shared_database() else {
+            return;
+        };
+        let Ok(db) = database.read() else {
+            self.checker.add_violation(InvariantViolation {
+                description: "Database lock poisoned".to_string(),
+                operation_index,
+                context: String::new(),
+            });
+            return;
+        };
+        self.checker.check_index_consistency(&db, operation_index);
+    }
+
     /// Get the operation history.
     #[must_use]
     pub const fn history(&self) -> &OperationHistory {
@@ -320,6 +735,32 @@ impl Simulator {
     }
 }
 
+/// Copy the connection's database file, holding the lock so no commit lands
+/// while it is read.
+fn read_database_file(client_connection: &ClientConnection, db_path: &Path) -> Option<Vec<u8>> {
+    let database = client_connection.shared_database()?;
+    let bytes = database
+        .read()
+        .ok()
+        .map(|_db| std::fs::read(db_path))
+        .transpose();
+    match bytes {
+        Ok(bytes) => bytes,
+        Err(e) => {
+            tracing::warn!("Failed to copy the simulation database: {e}");
+            None
+        }
+    }
+}
+
+/// Parse the superblock at the start of a database file, which fits in the
+/// first `MIN_PAGE_SIZE` bytes whatever its page size.
+fn read_superblock(file: &[u8]) -> Option<Superblock> {
+    let mut page = BufferPool::new(1).lease_page_zeroed()?;
+    page.as_bytes_mut()[..MIN_PAGE_SIZE].copy_from_slice(file.get(..MIN_PAGE_SIZE)?);
+    Superblock::from_page(&page).ok()
+}
+
 /// Statistics about the simulation.
 #[derive(Debug, Clone)]
 pub struct SimulatorStats {
@@ -337,6 +778,9 @@ pub struct SimulatorStats {
 mod tests {
     use super::*;
 
+    use crate::simulation::MalformationType;
+    use crate::simulation::invariants::Operation;
+
     #[test]
     fn test_simulator_basic() {
         let config = SimulatorConfig::new(12345);
@@ -386,15 +830,11 @@ mod tests {
 
         let result = simulator.run(200);
 
-        assert!(
-            result.passed(),
-            "Simulation should pass: {:?}",
-            result.invariant_violations
-        );
+        assert!(result.passed(), "Simulation should pass: {result}");
     }
 
     #[test]
-    #[ignore] // Long running test
+    #[ignore = "long running test"]
     fn test_simulator_stress() {
         let config = SimulatorConfig::new(99999)
             .with_malformed_rate(0.1)
@@ -404,6 +844,188 @@ mod tests {
         let result = simulator.run(10_000);
 
         assert!(result.completed_successfully);
-        assert!(result.passed());
+        assert!(result.passed(), "{result}");
+    }
+
+    #[test]
+    fn test_simulator_tracks_malformation_coverage() {
+        let config = SimulatorConfig::new(2024)
+            .with_malformed_rate(1.0)
+            .with_required_malformation_coverage();
+        let mut simulator = Simulator::new(config);
+
+        let result = simulator.run(300);
+
+        let coverage = &result.coverage;
+        assert_eq!(coverage.malformed_messages(), 300);
+        for malformation in [
+            MalformationType::WrongLengthEntityId,
+            MalformationType::WrongLengthAttributeId,
+            MalformationType::MissingEntityId,
+            MalformationType::MissingAttributeId,
+            MalformationType::MissingValue,
+            MalformationType::OverflowStringValue,
+        ] {
+            let count = coverage.malformations[&malformation];
+            assert!(count.generated > 0, "{malformation:?} never generated");
+            assert_eq!(count.rejected, count.generated, "{malformation:?}");
+        }
+        let invalid_argument = proto::google::rpc::Code::InvalidArgument as i32;
+        assert!(coverage.error_codes[&invalid_argument] > 0);
+
+        // Every malformation the server accepted fails the run
+        let unrejected: Vec<String> = coverage
+            .unrejected_malformations()
+            .map(|(malformation, _)| format!("{malformation:?}"))
+            .collect();
+        let flagged: Vec<String> = result
+            .invariant_violations
+            .iter()
+            .map(|violation| {
+                assert_eq!(violation.description, "Malformation never rejected");
+                violation
+                    .context
+                    .split(':')
+                    .next()
+                    .unwrap_or_default()
+                    .to_string()
+            })
+            .collect();
+        assert_eq!(flagged, unrejected);
+        assert_eq!(result.passed(), unrejected.is_empty());
+    }
+
+    /// A config that crashes and tears a page after every committed update.
+    fn torn_write_config(seed: u64) -> SimulatorConfig {
+        SimulatorConfig::new(seed).with_fault_config(FaultConfig {
+            torn_write_rate: 1.0,
+            ..FaultConfig::default()
+        })
+    }
+
+    #[test]
+    fn test_simulator_crashes_on_torn_writes() {
+        let mut simulator = Simulator::new(torn_write_config(31337));
+
+        let result = simulator.run(50);
+
+        // Every crash reopens the database and the run carries on
+        assert!(result.completed_successfully, "{result}");
+        assert_eq!(result.messages_processed, 50);
+        assert!(result.successful_operations > 0);
+        assert!(simulator.storage.stats().torn_writes > 0);
+        assert!(result.passed(), "{result}");
+    }
+
+    #[test]
+    fn test_simulator_recovers_from_torn_writes() {
+        let mut simulator = Simulator::new(torn_write_config(31337));
+
+        let result = simulator.run(200);
+
+        assert!(result.passed(), "{result}");
+    }
+
+    /// The number of rows each query in the history returned.
+    fn query_row_counts(history: &OperationHistory) -> Vec<usize> {
+        history
+            .operations()
+            .iter()
+            .filter_map(|operation| match operation {
+                Operation::Query { row_count, .. } => Some(*row_count),
+                Operation::Update { .. } => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_simulator_snapshot_resumes_identically() {
+        let config = SimulatorConfig::new(777).with_malformed_rate(0.2);
+        let mut uninterrupted = Simulator::new(config);
+        let (expected, snapshot) = uninterrupted.run_with_snapshot(200, 120);
+        let snapshot = snapshot.expect("run should reach the snapshot");
+        assert_eq!(snapshot.messages_processed(), 120);
+
+        // Resume twice to check the snapshot is not consumed
+        for _ in 0..2 {
+            let mut resumed = Simulator::from_snapshot(&snapshot).expect("restore snapshot");
+            let result = resumed.run(80);
+
+            assert_eq!(result.messages_processed, expected.messages_processed);
+            assert_eq!(result.successful_operations, expected.successful_operations);
+            assert_eq!(result.failed_operations, expected.failed_operations);
+            assert_eq!(result.coverage, expected.coverage);
+            assert_eq!(result.reproducer, expected.reproducer);
+            assert_eq!(
+                result.invariant_violations.len(),
+                expected.invariant_violations.len()
+            );
+            assert_eq!(resumed.history().stats(), uninterrupted.history().stats());
+            // Queries see the same rows, so the database was restored too
+            assert_eq!(
+                query_row_counts(resumed.history()),
+                query_row_counts(uninterrupted.history())
+            );
+            assert!(result.passed(), "{result}");
+        }
+    }
+
+    #[test]
+    fn test_simulator_snapshot_not_reached() {
+        let mut simulator = Simulator::new(SimulatorConfig::new(1));
+        let (result, snapshot) = simulator.run_with_snapshot(10, 11);
+
+        assert_eq!(result.messages_processed, 10);
+        assert!(snapshot.is_none());
+    }
+
+    #[test]
+    fn test_simulator_reproducer() {
+        let config = SimulatorConfig::new(42)
+            .with_malformed_rate(0.25)
+            .with_fault_config(FaultConfig {
+                write_error_rate: 0.5,
+                torn_write_rate: 0.1,
+                ..FaultConfig::default()
+            })
+            .without_time_advance();
+
+        assert_eq!(
+            config.reproducer(10),
+            "cargo run --bin simulate -- --seed 42 --messages 10 --malformed-rate 0.25 \
+             --write-error-rate 0.5 --torn-write-rate 0.1 --no-time-advance"
+        );
+    }
+
+    #[test]
+    fn test_simulation_result_display() {
+        let mut coverage = SimulationCoverage::new();
+        coverage.record(None, Some(proto::google::rpc::Code::InvalidArgument as i32));
+        let result = SimulationResult {
+            seed: 7,
+            messages_processed: 3,
+            successful_operations: 2,
+            failed_operations: 1,
+            invariant_violations: vec![InvariantViolation {
+                description: "Value mismatch".to_string(),
+                operation_index: 2,
+                context: "entity 01".to_string(),
+            }],
+            completed_successfully: true,
+            error: None,
+            coverage,
+            reproducer: SimulatorConfig::new(7).reproducer(3),
+        };
+
+        assert_eq!(
+            result.to_string(),
+            "seed 7: 3 messages, 2 successful, 1 failed\n\
+             error codes:\n\
+             \x20 INVALID_ARGUMENT: 1\n\
+             1 invariant violations:\n\
+             \x20 operation #2: Value mismatch (entity 01)\n\
+             FAILED\n\
+             reproduce with: cargo run --bin simulate -- --seed 7 --messages 3 --malformed-rate 0"
+        );
     }
 }
@@ -10,7 +10,7 @@
 // Simulation code legitimately needs cloning for test data
 #![allow(clippy::disallowed_methods)]
 
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::sync::Arc;
 
 use rand::rngs::StdRng;
@@ -35,6 +35,9 @@ pub struct FaultConfig {
     pub corruption_rate: f64,
     /// Probability of partial write (0.0 - 1.0).
     pub partial_write_rate: f64,
+    /// Probability that a crash tears a write, so only a prefix of the new
+    /// page reaches the disk and the rest keeps its old contents (0.0 - 1.0).
+    pub torn_write_rate: f64,
 }
 
 impl Default for FaultConfig {
@@ -45,6 +48,7 @@ impl Default for FaultConfig {
             sync_error_rate: 0.0,
             corruption_rate: 0.0,
             partial_write_rate: 0.0,
+            torn_write_rate: 0.0,
         }
     }
 }
@@ -65,6 +69,7 @@ impl FaultConfig {
             sync_error_rate: 0.001,
             corruption_rate: 0.001,
             partial_write_rate: 0.001,
+            torn_write_rate: 0.001,
         }
     }
 
@@ -77,6 +82,20 @@ impl FaultConfig {
             sync_error_rate: 0.05,
             corruption_rate: 0.05,
             partial_write_rate: 0.05,
+            torn_write_rate: 0.05,
+        }
+    }
+
+    /// Create a fault config that injects every kind of fault at `rate`.
+    #[must_use]
+    pub const fn uniform(rate: f64) -> Self {
+        Self {
+            read_error_rate: rate,
+            write_error_rate: rate,
+            sync_error_rate: rate,
+            corruption_rate: rate,
+            partial_write_rate: rate,
+            torn_write_rate: rate,
         }
     }
 }
@@ -129,6 +148,27 @@ pub struct SimulatedStorage {
     stats: SimulatedStorageStats,
 }
 
+/// A copy of a `SimulatedStorage`'s pages, WAL, fault state and RNG.
+///
+/// Restoring it with `SimulatedStorage::restore` yields storage that behaves
+/// identically to the original from the point the snapshot was taken,
+/// including which faults it injects.
+#[derive(Debug, Clone)]
+pub struct SimulatedStorageSnapshot {
+    /// Page contents, by page ID.
+    pages: BTreeMap<PageId, Vec<u8>>,
+    total_pages: u64,
+    superblock: Superblock,
+    pool_capacity: usize,
+    wal_records: Vec<SimulatedWalRecord>,
+    next_lsn: Lsn,
+    wal_capacity: u64,
+    wal_initialized: bool,
+    fault_config: FaultConfig,
+    rng: StdRng,
+    stats: SimulatedStorageStats,
+}
+
 /// Statistics about simulated storage operations.
 #[derive(Debug, Default, Clone)]
 pub struct SimulatedStorageStats {
@@ -148,6 +188,8 @@ pub struct SimulatedStorageStats {
     pub corrupted_reads: u64,
     /// Number of partial writes.
     pub partial_writes: u64,
+    /// Number of torn writes.
+    pub torn_writes: u64,
 }
 
 impl SimulatedStorage {
@@ -207,6 +249,61 @@ impl SimulatedStorage {
         self.stats = SimulatedStorageStats::default();
     }
 
+    /// Capture the storage's complete state.
+    #[must_use]
+    pub fn snapshot(&self) -> SimulatedStorageSnapshot {
+        SimulatedStorageSnapshot {
+            pages: self
+                .pages
+                .iter()
+                .map(|(page_id, page)| (*page_id, page.as_bytes().to_vec()))
+                .collect(),
+            total_pages: self.total_pages,
+            superblock: self.superblock,
+            pool_capacity: self.buffer_pool.capacity(),
+            wal_records: self.wal_records.clone(),
+            next_lsn: self.next_lsn,
+            wal_capacity: self.wal_capacity,
+            wal_initialized: self.wal_initialized,
+            fault_config: self.fault_config.clone(),
+            rng: self.rng.clone(),
+            stats: self.stats.clone(),
+        }
+    }
+
+    /// Rebuild storage from a snapshot, with a new buffer pool of the same
+    /// capacity as the original's.
+    ///
+    /// # Errors
+    ///
+    /// Returns `StorageError::BufferPoolExhausted` if the pool cannot hold
+    /// every page.
+    pub fn restore(snapshot: &SimulatedStorageSnapshot) -> Result<Self, StorageError> {
+        let buffer_pool = BufferPool::new(snapshot.pool_capacity);
+        let mut pages = HashMap::with_capacity(snapshot.pages.len());
+        for (page_id, bytes) in &snapshot.pages {
+            let mut page = buffer_pool
+                .lease_page()
+                .ok_or(StorageError::BufferPoolExhausted)?;
+            page.as_bytes_mut().copy_from_slice(bytes);
+            pages.insert(*page_id, page);
+        }
+
+        Ok(Self {
+            pages,
+            total_pages: snapshot.total_pages,
+            superblock: snapshot.superblock,
+            buffer_pool,
+            wal_records: snapshot.wal_records.clone(),
+            next_lsn: snapshot.next_lsn,
+            wal_capacity: snapshot.wal_capacity,
+            wal_initialized: snapshot.wal_initialized,
+            fault_config: snapshot.fault_config.clone(),
+            rng: snapshot.rng.clone(),
+            stats: snapshot.stats.clone(),
+        })
+    }
+
     /// Update the fault configuration.
     pub const fn set_fault_config(&mut self, config: FaultConfig) {
         self.fault_config = config;
@@ -220,6 +317,26 @@ impl SimulatedStorage {
         self.rng.random::<f64>() < rate
     }
 
+    /// Decide whether the next page write is torn, using `torn_write_rate`.
+    ///
+    /// Returns how many leading bytes of the page reach the disk before the
+    /// tear, between 1 and `PAGE_SIZE - 1`, and counts the torn write.
+    pub fn next_torn_write(&mut self) -> Option<usize> {
+        if !self.should_inject_fault(self.fault_config.torn_write_rate) {
+            return None;
+        }
+        self.stats.torn_writes += 1;
+        Some(self.rng.random_range(1..PAGE_SIZE))
+    }
+
+    /// Pick which of the pages a crash interrupted is torn.
+    pub fn pick_torn_page(&mut self, candidates: &[PageId]) -> Option<PageId> {
+        if candidates.is_empty() {
+            return None;
+        }
+        Some(candidates[self.rng.random_range(0..candidates.len())])
+    }
+
     /// Corrupt a page by flipping random bits.
     fn corrupt_page(&mut self, page: &mut Page) {
         // Flip 1-8 random bits
@@ -310,6 +427,15 @@ impl Storage for SimulatedStorage {
             self.make_partial_write(&mut page_to_write);
         }
 
+        // Check for a torn write, which keeps the old page past the tear
+        if let Some(kept) = self.next_torn_write() {
+            let torn = &mut page_to_write.as_bytes_mut()[kept..];
+            match self.pages.get(&page_id) {
+                Some(old) => torn.copy_from_slice(&old.as_bytes()[kept..]),
+                None => torn.fill(0),
+            }
+        }
+
         self.pages.insert(page_id, page_to_write);
         Ok(())
     }
@@ -558,6 +684,30 @@ mod tests {
         assert_eq!(storage.stats().injected_read_errors, 1);
     }
 
+    #[test]
+    fn test_simulated_storage_torn_write() {
+        let mut storage = SimulatedStorage::new(12345);
+        storage.allocate_pages(1).unwrap();
+
+        let mut page = storage.buffer_pool().lease_page_zeroed().unwrap();
+        page.as_bytes_mut().fill(0xAA);
+        storage.write_page(1, &page).unwrap();
+
+        storage.set_fault_config(FaultConfig {
+            torn_write_rate: 1.0,
+            ..Default::default()
+        });
+        page.as_bytes_mut().fill(0xBB);
+        storage.write_page(1, &page).unwrap();
+
+        // The second write keeps a prefix of 0xBB over the first write's 0xAA
+        let stored = storage.pages[&1].as_bytes().to_vec();
+        let kept = stored.iter().take_while(|byte| **byte == 0xBB).count();
+        assert!(kept > 0 && kept < PAGE_SIZE);
+        assert!(stored[kept..].iter().all(|byte| *byte == 0xAA));
+        assert_eq!(storage.stats().torn_writes, 1);
+    }
+
     #[test]
     fn test_simulated_storage_deterministic() {
         // Same seed should produce same behavior
@@ -586,6 +736,50 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_simulated_storage_snapshot_restore() {
+        let config = FaultConfig {
+            read_error_rate: 0.5,
+            ..Default::default()
+        };
+        let mut storage = SimulatedStorage::with_config(12345, config);
+        storage.allocate_pages(10).unwrap();
+        let mut page = storage
+            .buffer_pool()
+            .lease_page_zeroed()
+            .expect("should lease");
+        page.write_bytes(0, b"snapshot");
+        storage.write_page(3, &page).unwrap();
+        for i in 1..6 {
+            let _ = storage.read_page(i);
+        }
+
+        let snapshot = storage.snapshot();
+        let mut restored = SimulatedStorage::restore(&snapshot).unwrap();
+
+        let original: Vec<Option<Vec<u8>>> = (1..11)
+            .map(|i| {
+                storage
+                    .read_page(i)
+                    .ok()
+                    .map(|page| page.read_bytes(0, 8).to_vec())
+            })
+            .collect();
+        let replayed: Vec<Option<Vec<u8>>> = (1..11)
+            .map(|i| {
+                restored
+                    .read_page(i)
+                    .ok()
+                    .map(|page| page.read_bytes(0, 8).to_vec())
+            })
+            .collect();
+        assert_eq!(original, replayed);
+        assert_eq!(
+            storage.stats().injected_read_errors,
+            restored.stats().injected_read_errors
+        );
+    }
+
     #[test]
     fn test_simulated_storage_page_out_of_bounds() {
         let mut storage = SimulatedStorage::new(12345);
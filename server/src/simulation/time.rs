@@ -80,7 +80,7 @@ impl SimulatedTimeSource {
 
     /// Get the current simulated time without advancing it.
     #[must_use]
-    pub fn current(&self) -> u64 {
+    pub const fn current(&self) -> u64 {
         self.current_time_ms.get()
     }
 }
@@ -0,0 +1,462 @@
+//! Deterministic interleaving of concurrent optimistic transactions.
+//!
+//! Each simulated transaction increments a counter on a few random triples:
+//! it reads every counter from its snapshot, then writes each value plus one.
+//! Transactions are started, advanced one read at a time and committed in a
+//! seeded random order, so many of them overlap.
+//!
+//! Optimistic validation must make the outcome serializable in commit order.
+//! The simulator checks that directly: each committed transaction must have
+//! read exactly the counters left by the transactions committed before it,
+//! and the final counters must equal the number of committed increments.
+//! A lost update or a stale read shows up as an invariant violation.
+//!
+//! The checks hold at both isolation levels: every counter a transaction
+//! reads it also writes, and writes are always validated, so a read-committed
+//! transaction that read a newer counter than its snapshot fails to commit.
+//!
+//! The `TransactionWorkload::BoundedMembership` workload checks predicate
+//! reads instead. Each transaction scans the members of a set and adds a new
+//! member only if the set is below its bound, so two concurrent transactions
+//! that each saw room would overfill it unless the insert the other made,
+//! which neither read as a triple, fails validation. The simulator checks
+//! that each committed transaction scanned exactly the members left by the
+//! transactions committed before it, and that the set never exceeds its
+//! bound.
+
+use std::collections::{BTreeSet, HashMap};
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use rand::rngs::StdRng;
+use rand::seq::IndexedRandom;
+use rand::{Rng, SeedableRng};
+
+use super::invariants::InvariantViolation;
+use crate::storage::buffer_pool::BufferPool;
+use crate::storage::{Database, DatabaseError, IsolationLevel, OptimisticTransaction};
+use crate::types::{AttributeId, EntityId, TripleValue};
+
+/// Counter for generating unique simulator instance IDs.
+static TRANSACTION_SIMULATOR_COUNTER: AtomicU64 = AtomicU64::new(0);
+
+/// Number of distinct counters. Kept small so transactions often collide.
+const COUNTER_KEYS: u8 = 6;
+
+/// Most counters a single transaction touches.
+const MAX_KEYS_PER_TRANSACTION: usize = 3;
+
+/// Most transactions in flight at once.
+const MAX_IN_FLIGHT: usize = 4;
+
+/// Attribute holding every counter.
+const COUNTER_ATTRIBUTE: AttributeId = AttributeId([0xC0; 16]);
+
+/// Attribute marking each member of the bounded set.
+const MEMBER_ATTRIBUTE: AttributeId = AttributeId([0xE0; 16]);
+
+/// Most members the bounded set may hold.
+const MAX_MEMBERS: usize = 3;
+
+/// Shape of the simulated transactions.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TransactionWorkload {
+    /// Each transaction reads a few counters and increments them.
+    #[default]
+    Counters,
+    /// Each transaction scans a set's members, then adds a member if there
+    /// are fewer than `MAX_MEMBERS`, or removes the first one otherwise.
+    BoundedMembership,
+}
+
+/// Results from a transaction simulation run.
+#[derive(Debug)]
+pub struct TransactionSimulationResult {
+    /// The seed used for this simulation.
+    pub seed: u64,
+    /// Number of transactions that committed.
+    pub committed: u64,
+    /// Number of transactions rejected with a write conflict.
+    pub conflicts: u64,
+    /// Serializability violations detected.
+    pub invariant_violations: Vec<InvariantViolation>,
+    /// Error message if the simulation could not run.
+    pub error: Option<String>,
+}
+
+impl TransactionSimulationResult {
+    /// Check if the simulation passed (ran to completion with no violations).
+    #[must_use]
+    pub const fn passed(&self) -> bool {
+        self.error.is_none() && self.invariant_violations.is_empty()
+    }
+}
+
+/// A simulated transaction in flight.
+struct InFlight {
+    txn: OptimisticTransaction,
+    /// Counters this transaction increments, in read order.
+    keys: Vec<EntityId>,
+    /// Counter values read so far, parallel to the first entries of `keys`.
+    read_values: Vec<u64>,
+}
+
+/// A simulated membership transaction in flight.
+struct MembershipInFlight {
+    txn: OptimisticTransaction,
+    /// Entity this transaction adds if the set has room.
+    candidate: EntityId,
+    /// Members scanned, once the scan has run.
+    scanned: Option<Vec<EntityId>>,
+}
+
+/// Simulator for concurrent optimistic transactions.
+pub struct TransactionSimulator {
+    seed: u64,
+    rng: StdRng,
+    /// Isolation level every transaction runs at.
+    isolation: IsolationLevel,
+    /// Shape of every transaction.
+    workload: TransactionWorkload,
+}
+
+impl TransactionSimulator {
+    /// Create a new transaction simulator with the given seed.
+    #[must_use]
+    pub fn new(seed: u64) -> Self {
+        Self {
+            seed,
+            rng: StdRng::seed_from_u64(seed),
+            isolation: IsolationLevel::default(),
+            workload: TransactionWorkload::default(),
+        }
+    }
+
+    /// Run every transaction at `isolation` instead of the default.
+    #[must_use]
+    pub const fn with_isolation(mut self, isolation: IsolationLevel) -> Self {
+        self.isolation = isolation;
+        self
+    }
+
+    /// Run `workload` instead of the default counters.
+    #[must_use]
+    pub const fn with_workload(mut self, workload: TransactionWorkload) -> Self {
+        self.workload = workload;
+        self
+    }
+
+    /// Run `transaction_count` transactions against a fresh database.
+    pub fn run(&mut self, transaction_count: usize) -> TransactionSimulationResult {
+        let instance_id = TRANSACTION_SIMULATOR_COUNTER.fetch_add(1, Ordering::Relaxed);
+        let db_path =
+            std::env::temp_dir().join(format!("dst_txn_sim_{}_{}.db", self.seed, instance_id));
+        let _ = std::fs::remove_file(&db_path);
+
+        let mut result = TransactionSimulationResult {
+            seed: self.seed,
+            committed: 0,
+            conflicts: 0,
+            invariant_violations: vec![],
+            error: None,
+        };
+        match Database::create(&db_path, BufferPool::new(100)) {
+            Ok(mut db) => {
+                let run = match self.workload {
+                    TransactionWorkload::Counters => {
+                        self.run_with_database(&mut db, transaction_count, &mut result)
+                    }
+                    TransactionWorkload::BoundedMembership => {
+                        self.run_membership(&mut db, transaction_count, &mut result)
+                    }
+                };
+                if let Err(e) = run {
+                    result.error = Some(format!("Database error: {e}"));
+                }
+            }
+            Err(e) => result.error = Some(format!("Failed to create database: {e}")),
+        }
+
+        let _ = std::fs::remove_file(&db_path);
+        result
+    }
+
+    /// Interleave the transactions and check the outcome.
+    fn run_with_database(
+        &mut self,
+        db: &mut Database,
+        transaction_count: usize,
+        result: &mut TransactionSimulationResult,
+    ) -> Result<(), DatabaseError> {
+        let all_keys: Vec<EntityId> = (0..COUNTER_KEYS).map(|i| EntityId([i; 16])).collect();
+        // Counter values in a serial execution of the committed transactions
+        let mut expected: HashMap<EntityId, u64> = HashMap::new();
+        let mut in_flight: Vec<InFlight> = Vec::new();
+        let mut started = 0;
+
+        while started < transaction_count || !in_flight.is_empty() {
+            let can_start = started < transaction_count && in_flight.len() < MAX_IN_FLIGHT;
+            if can_start && (in_flight.is_empty() || self.rng.random_bool(0.3)) {
+                let key_count = self.rng.random_range(1..=MAX_KEYS_PER_TRANSACTION);
+                let keys = all_keys
+                    .choose_multiple(&mut self.rng, key_count)
+                    .copied()
+                    .collect();
+                in_flight.push(InFlight {
+                    txn: db.begin_optimistic(0)?.with_isolation(self.isolation),
+                    keys,
+                    read_values: Vec::new(),
+                });
+                started += 1;
+                continue;
+            }
+
+            let index = self.rng.random_range(0..in_flight.len());
+            let current = &mut in_flight[index];
+            if current.read_values.len() < current.keys.len() {
+                let key = current.keys[current.read_values.len()];
+                let value = match current.txn.get(db, &key, &COUNTER_ATTRIBUTE)? {
+                    Some(record) => counter_value(&record.value),
+                    None => 0,
+                };
+                current.read_values.push(value);
+                continue;
+            }
+
+            let InFlight {
+                mut txn,
+                keys,
+                read_values,
+            } = in_flight.swap_remove(index);
+            for (key, value) in keys.iter().zip(&read_values) {
+                #[allow(clippy::cast_precision_loss)] // Counters stay far below 2^52
+                let next = TripleValue::Number((value + 1) as f64);
+                if *value == 0 {
+                    txn.insert(*key, COUNTER_ATTRIBUTE, next);
+                } else {
+                    txn.update(*key, COUNTER_ATTRIBUTE, next);
+                }
+            }
+            match db.commit_optimistic(txn) {
+                Ok(_) => {
+                    for (key, value) in keys.iter().zip(&read_values) {
+                        let serial = expected.entry(*key).or_insert(0);
+                        if *value != *serial {
+                            result.invariant_violations.push(InvariantViolation {
+                                description: "Committed transaction read a stale counter"
+                                    .to_owned(),
+                                operation_index: usize::try_from(result.committed)
+                                    .unwrap_or(usize::MAX),
+                                context: format!(
+                                    "counter {}: read {value}, serial value {serial}",
+                                    key.0[0]
+                                ),
+                            });
+                        }
+                        *serial += 1;
+                    }
+                    result.committed += 1;
+                }
+                Err(DatabaseError::WriteConflict { .. }) => result.conflicts += 1,
+                Err(e) => return Err(e),
+            }
+        }
+
+        Self::check_final_state(db, &all_keys, &expected, result)
+    }
+
+    /// Interleave membership transactions and check the outcome.
+    fn run_membership(
+        &mut self,
+        db: &mut Database,
+        transaction_count: usize,
+        result: &mut TransactionSimulationResult,
+    ) -> Result<(), DatabaseError> {
+        // Members in a serial execution of the committed transactions
+        let mut expected: BTreeSet<EntityId> = BTreeSet::new();
+        let mut in_flight: Vec<MembershipInFlight> = Vec::new();
+        let mut started: usize = 0;
+
+        while started < transaction_count || !in_flight.is_empty() {
+            let can_start = started < transaction_count && in_flight.len() < MAX_IN_FLIGHT;
+            if can_start && (in_flight.is_empty() || self.rng.random_bool(0.3)) {
+                let mut candidate = [0u8; 16];
+                candidate[..8].copy_from_slice(&(started as u64).to_be_bytes());
+                in_flight.push(MembershipInFlight {
+                    txn: db.begin_optimistic(0)?.with_isolation(self.isolation),
+                    candidate: EntityId(candidate),
+                    scanned: None,
+                });
+                started += 1;
+                continue;
+            }
+
+            let index = self.rng.random_range(0..in_flight.len());
+            let current = &mut in_flight[index];
+            if current.scanned.is_none() {
+                let members = current
+                    .txn
+                    .get_entities_with_attribute(db, &MEMBER_ATTRIBUTE)?;
+                current.scanned = Some(members);
+                continue;
+            }
+
+            let MembershipInFlight {
+                mut txn,
+                candidate,
+                scanned,
+            } = in_flight.swap_remove(index);
+            let scanned = scanned.unwrap_or_default();
+            let removed = (scanned.len() >= MAX_MEMBERS).then(|| scanned[0]);
+            match removed {
+                Some(member) => txn.delete(member, MEMBER_ATTRIBUTE),
+                None => txn.insert(candidate, MEMBER_ATTRIBUTE, TripleValue::Boolean(true)),
+            }
+            match db.commit_optimistic(txn) {
+                Ok(_) => {
+                    let serial: Vec<EntityId> = expected.iter().copied().collect();
+                    if scanned != serial {
+                        result.invariant_violations.push(InvariantViolation {
+                            description: "Committed transaction scanned stale members".to_owned(),
+                            operation_index: usize::try_from(result.committed)
+                                .unwrap_or(usize::MAX),
+                            context: format!(
+                                "scanned {} members, serial set has {}",
+                                scanned.len(),
+                                serial.len()
+                            ),
+                        });
+                    }
+                    match removed {
+                        Some(member) => expected.remove(&member),
+                        None => expected.insert(candidate),
+                    };
+                    result.committed += 1;
+                }
+                Err(DatabaseError::WriteConflict { .. }) => result.conflicts += 1,
+                Err(e) => return Err(e),
+            }
+        }
+
+        let snapshot = db.begin_readonly()?;
+        let stored = snapshot.get_entities_with_attribute(&MEMBER_ATTRIBUTE)?;
+        let txn_id = snapshot.close();
+        db.release_snapshot(txn_id)?;
+        if stored.len() > MAX_MEMBERS {
+            result.invariant_violations.push(InvariantViolation {
+                description: "Phantom: the set exceeds its bound".to_owned(),
+                operation_index: usize::try_from(result.committed).unwrap_or(usize::MAX),
+                context: format!("{} members, bound {MAX_MEMBERS}", stored.len()),
+            });
+        }
+        if stored != expected.into_iter().collect::<Vec<_>>() {
+            result.invariant_violations.push(InvariantViolation {
+                description: "Stored members differ from serial execution".to_owned(),
+                operation_index: usize::try_from(result.committed).unwrap_or(usize::MAX),
+                context: format!("{} members stored", stored.len()),
+            });
+        }
+        Ok(())
+    }
+
+    /// Check that the stored counters match the serial execution.
+    fn check_final_state(
+        db: &Database,
+        all_keys: &[EntityId],
+        expected: &HashMap<EntityId, u64>,
+        result: &mut TransactionSimulationResult,
+    ) -> Result<(), DatabaseError> {
+        let snapshot = db.begin_readonly()?;
+        for key in all_keys {
+            let stored = snapshot
+                .get(key, &COUNTER_ATTRIBUTE)?
+                .map_or(0, |record| counter_value(&record.value));
+            let serial = expected.get(key).copied().unwrap_or(0);
+            if stored != serial {
+                result.invariant_violations.push(InvariantViolation {
+                    description: "Lost update: stored counter differs from serial execution"
+                        .to_owned(),
+                    operation_index: usize::try_from(result.committed).unwrap_or(usize::MAX),
+                    context: format!(
+                        "counter {}: stored {stored}, serial value {serial}",
+                        key.0[0]
+                    ),
+                });
+            }
+        }
+        let txn_id = snapshot.close();
+        db.release_snapshot(txn_id)?;
+        Ok(())
+    }
+}
+
+/// Read a counter stored as a number.
+#[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)] // Counters are small naturals
+const fn counter_value(value: &TripleValue) -> u64 {
+    match value {
+        TripleValue::Number(n) => *n as u64,
+        _ => 0,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_optimistic_transactions_are_serializable() {
+        for seed in [1, 42, 12345, 54321, 99999] {
+            let result = TransactionSimulator::new(seed).run(200);
+            assert!(
+                result.passed(),
+                "seed {seed} failed: {:?} {:?}",
+                result.error,
+                result.invariant_violations
+            );
+            assert!(result.committed > 0, "seed {seed} committed nothing");
+            // Six counters and four transactions in flight guarantee overlap
+            assert!(result.conflicts > 0, "seed {seed} never conflicted");
+        }
+    }
+
+    #[test]
+    fn test_read_committed_transactions_prevent_lost_updates() {
+        for seed in [3, 77, 2024] {
+            let result = TransactionSimulator::new(seed)
+                .with_isolation(IsolationLevel::ReadCommitted)
+                .run(200);
+            assert!(
+                result.passed(),
+                "seed {seed} failed: {:?} {:?}",
+                result.error,
+                result.invariant_violations
+            );
+            assert!(result.committed > 0, "seed {seed} committed nothing");
+        }
+    }
+
+    #[test]
+    fn test_attribute_scans_are_serializable() {
+        for seed in [5, 321, 8080] {
+            let result = TransactionSimulator::new(seed)
+                .with_workload(TransactionWorkload::BoundedMembership)
+                .run(200);
+            assert!(
+                result.passed(),
+                "seed {seed} failed: {:?} {:?}",
+                result.error,
+                result.invariant_violations
+            );
+            assert!(result.committed > 0, "seed {seed} committed nothing");
+            // Every transaction scans the same attribute, so overlaps conflict
+            assert!(result.conflicts > 0, "seed {seed} never conflicted");
+        }
+    }
+
+    #[test]
+    fn test_transaction_simulator_deterministic() {
+        let first = TransactionSimulator::new(7).run(100);
+        let second = TransactionSimulator::new(7).run(100);
+        assert_eq!(first.committed, second.committed);
+        assert_eq!(first.conflicts, second.conflicts);
+    }
+}
@@ -0,0 +1,550 @@
+//! Declared attribute cardinality.
+//!
+//! Every attribute is cardinality-one unless declared otherwise: each entity
+//! holds at most one value of it, and a write replaces that value. An
+//! attribute declared cardinality-many, such as a `tag`, holds any number of
+//! distinct values per entity instead (see `Cardinality`).
+//!
+//! # Design
+//!
+//! Each declaration is stored as an ordinary triple on a reserved entity:
+//!
+//! ```text
+//! (ATTRIBUTE_CARDINALITY_ENTITY_ID, <attribute>, String("many"))
+//! ```
+//!
+//! Like attribute names, declarations are written through the WAL, recovered
+//! after a crash, and broadcast and replicated like any other change. A
+//! committing transaction reads the declaration of each attribute it writes
+//! once, and stamps the cardinality on every record it stores, so a record's
+//! primary key never depends on a later declaration.
+//!
+//! With a cardinality-many attribute:
+//!
+//! - An insert or update adds its value, leaving the entity's other values
+//! - `WalTransaction::delete_value` removes one value; `delete` removes all
+//! - `Snapshot::get_values` reads every value, and queries yield one match per
+//!   value
+//!
+//! # Change notifications
+//!
+//! Each value is a triple of its own, so adding a value is broadcast as an
+//! `Insert` carrying it and removing one as a `Delete` whose `old_value` is the
+//! removed value. Writing a value the entity already holds changes nothing and
+//! is not broadcast. Changes to different values of one attribute in the same
+//! transaction are sent separately rather than coalesced.
+//!
+//! # Invariants
+//!
+//! - An attribute's cardinality only changes while it has no live triples
+//! - Writes to the reserved names and cardinality entities are always
+//!   cardinality-one
+
+use crate::storage::attribute_names::ATTRIBUTE_NAMES_ENTITY_ID;
+use crate::storage::database::SYSTEM_CONNECTION_ID;
+use crate::storage::{Database, DatabaseError};
+use crate::types::{AttributeId, Cardinality, EntityId, PendingTriple, TripleRecord, TripleValue};
+
+/// Reserved entity holding the cardinality declarations.
+///
+/// The leading NUL byte keeps it disjoint from IDs built with
+/// `EntityId::from_string`, which start with the string's bytes.
+pub const ATTRIBUTE_CARDINALITY_ENTITY_ID: EntityId = EntityId(*b"\0enso:attr-cards");
+
+impl Database {
+    /// Declare the cardinality of `attribute_id`.
+    ///
+    /// Declaring the cardinality an attribute already has does nothing.
+    ///
+    /// # Post-conditions
+    /// - Writes committed afterwards store `attribute_id` with `cardinality`,
+    ///   including after reopening
+    ///
+    /// # Errors
+    ///
+    /// Returns `DatabaseError::AttributeInUse` if the attribute has live
+    /// triples, whose keys depend on the old cardinality, or an error if
+    /// reading the index or writing the declaration fails.
+    pub fn set_attribute_cardinality(
+        &mut self,
+        attribute_id: AttributeId,
+        cardinality: Cardinality,
+    ) -> Result<(), DatabaseError> {
+        if self.attribute_cardinality(&attribute_id)? == cardinality {
+            return Ok(());
+        }
+
+        // The name and declaration of the attribute are not uses of it
+        let snapshot = self.begin_readonly()?;
+        let sample = snapshot.sample_entities_with_attribute(&attribute_id, 3);
+        let txn_id = snapshot.close();
+        self.release_snapshot(txn_id)?;
+        if sample?
+            .iter()
+            .any(|entity_id| !is_reserved_entity(entity_id))
+        {
+            return Err(DatabaseError::AttributeInUse(attribute_id));
+        }
+
+        let mut txn = self.begin(SYSTEM_CONNECTION_ID)?;
+        txn.insert(
+            ATTRIBUTE_CARDINALITY_ENTITY_ID,
+            attribute_id,
+            TripleValue::String(cardinality.as_str().to_owned()),
+        )?;
+        txn.commit()?;
+
+        tracing::debug!(
+            "Declared attribute {:?} cardinality-{}",
+            attribute_id,
+            cardinality.as_str()
+        );
+        Ok(())
+    }
+
+    /// Get the declared cardinality of `attribute_id`, cardinality-one if it
+    /// was never declared.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if reading the declarations fails.
+    pub fn attribute_cardinality(
+        &self,
+        attribute_id: &AttributeId,
+    ) -> Result<Cardinality, DatabaseError> {
+        let snapshot = self.begin_readonly()?;
+        let record = snapshot.get(&ATTRIBUTE_CARDINALITY_ENTITY_ID, attribute_id);
+        let txn_id = snapshot.close();
+        self.release_snapshot(txn_id)?;
+        Ok(declared_cardinality(record?.as_ref()))
+    }
+}
+
+/// The cardinality a declaration record declares, cardinality-one if there
+/// is none or it names no cardinality.
+pub(crate) fn declared_cardinality(record: Option<&TripleRecord>) -> Cardinality {
+    match record.map(|record| &record.value) {
+        Some(TripleValue::String(name)) => Cardinality::parse(name).unwrap_or_default(),
+        _ => Cardinality::One,
+    }
+}
+
+/// The attribute whose declaration `op` writes, with the cardinality it
+/// declares, or `None` if `op` writes no declaration. Deleting a declaration
+/// declares the default, `Cardinality::One`.
+pub(crate) fn declaration_written(op: &PendingTriple) -> Option<(AttributeId, Cardinality)> {
+    match op {
+        PendingTriple::Insert(record) | PendingTriple::Update(record)
+            if record.entity_id == ATTRIBUTE_CARDINALITY_ENTITY_ID =>
+        {
+            Some((record.attribute_id, declared_cardinality(Some(record))))
+        }
+        PendingTriple::Delete {
+            entity_id,
+            attribute_id,
+            ..
+        } if *entity_id == ATTRIBUTE_CARDINALITY_ENTITY_ID => {
+            Some((*attribute_id, Cardinality::One))
+        }
+        _ => None,
+    }
+}
+
+/// Whether `entity_id` is a reserved entity, whose triples describe
+/// attributes rather than user data.
+///
+/// Writes to a reserved entity ignore declared cardinalities, and queries
+/// skip its triples.
+pub(crate) fn is_reserved_entity(entity_id: &EntityId) -> bool {
+    *entity_id == ATTRIBUTE_NAMES_ENTITY_ID || *entity_id == ATTRIBUTE_CARDINALITY_ENTITY_ID
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::buffer_pool::BufferPool;
+    use crate::types::cardinality::with_forced_value_hash;
+    use tempfile::tempdir;
+
+    const TAG: AttributeId = AttributeId([7u8; 16]);
+    const ENTITY: EntityId = EntityId([1u8; 16]);
+    /// Payloads of two `TripleValue::Bytes` with the same value hash, found
+    /// with Pollard's rho.
+    const COLLIDING_FIRST: [u8; 8] = 0x2782_4E2C_1695_2CE6_u64.to_le_bytes();
+    const COLLIDING_SECOND: [u8; 8] = 0xB709_FD3E_883F_D36B_u64.to_le_bytes();
+
+    fn tag(name: &str) -> TripleValue {
+        TripleValue::String(name.to_owned())
+    }
+
+    #[test]
+    fn test_attribute_cardinality_defaults_to_one() {
+        let db = Database::create_in_memory(BufferPool::new(100)).expect("create db");
+
+        assert_eq!(
+            db.attribute_cardinality(&TAG).expect("cardinality"),
+            Cardinality::One
+        );
+    }
+
+    #[test]
+    fn test_attribute_cardinality_survives_reopen() {
+        let dir = tempdir().expect("create temp dir");
+        let path = dir.path().join("test.db");
+
+        let mut db = Database::create(&path, BufferPool::new(100)).expect("create db");
+        db.set_attribute_cardinality(TAG, Cardinality::Many)
+            .expect("declare");
+        db.close().expect("close");
+
+        let (mut db, _) = Database::open_or_create(&path, BufferPool::new(100)).expect("reopen");
+        assert_eq!(
+            db.attribute_cardinality(&TAG).expect("cardinality"),
+            Cardinality::Many
+        );
+
+        let mut txn = db.begin(0).expect("begin");
+        txn.insert(ENTITY, TAG, tag("red")).expect("insert");
+        txn.insert(ENTITY, TAG, tag("blue")).expect("insert");
+        txn.commit().expect("commit");
+
+        let snapshot = db.begin_readonly().expect("snapshot");
+        assert_eq!(snapshot.get_values(&ENTITY, &TAG).expect("values").len(), 2);
+        db.release_snapshot(snapshot.close()).expect("release");
+    }
+
+    #[test]
+    fn test_cardinality_many_values() {
+        let mut db = Database::create_in_memory(BufferPool::new(100)).expect("create db");
+        db.set_attribute_cardinality(TAG, Cardinality::Many)
+            .expect("declare");
+        let mut rx = db.subscribe_to_changes(1);
+
+        let mut txn = db.begin(0).expect("begin");
+        txn.insert(ENTITY, TAG, tag("red")).expect("insert");
+        txn.insert(ENTITY, TAG, tag("blue")).expect("insert");
+        txn.commit().expect("commit");
+        let notification = rx.try_recv().expect("insert notification");
+        assert_eq!(notification.changes.len(), 2);
+
+        // Writing a value the entity already holds changes nothing
+        let mut txn = db.begin(0).expect("begin");
+        txn.update(ENTITY, TAG, tag("red")).expect("update");
+        let outcome = txn.commit().expect("commit");
+        assert_eq!(outcome.changes_applied, 0);
+        assert!(rx.try_recv().is_err());
+
+        let mut txn = db.begin(0).expect("begin");
+        txn.delete_value(&ENTITY, &TAG, &tag("red"))
+            .expect("delete value");
+        assert!(matches!(
+            txn.delete_value(&ENTITY, &TAG, &tag("green")),
+            Err(DatabaseError::NotFound)
+        ));
+        txn.commit().expect("commit");
+        let notification = rx.try_recv().expect("delete notification");
+        assert_eq!(notification.changes.len(), 1);
+        assert_eq!(notification.changes[0].old_value, Some(tag("red")));
+
+        let snapshot = db.begin_readonly().expect("snapshot");
+        let values = snapshot.get_values(&ENTITY, &TAG).expect("values");
+        db.release_snapshot(snapshot.close()).expect("release");
+        assert_eq!(values.len(), 1);
+        assert_eq!(values[0].value, tag("blue"));
+
+        let mut txn = db.begin(0).expect("begin");
+        txn.insert(ENTITY, TAG, tag("green")).expect("insert");
+        txn.commit().expect("commit");
+        let mut txn = db.begin(0).expect("begin");
+        txn.delete(&ENTITY, &TAG).expect("delete");
+        txn.commit().expect("commit");
+
+        let snapshot = db.begin_readonly().expect("snapshot");
+        assert!(
+            snapshot
+                .get_values(&ENTITY, &TAG)
+                .expect("values")
+                .is_empty()
+        );
+        db.release_snapshot(snapshot.close()).expect("release");
+    }
+
+    #[test]
+    fn test_cardinality_many_values_are_recovered() {
+        let dir = tempdir().expect("create temp dir");
+        let path = dir.path().join("test.db");
+
+        {
+            let mut db = Database::create(&path, BufferPool::new(100)).expect("create db");
+            db.set_attribute_cardinality(TAG, Cardinality::Many)
+                .expect("declare");
+            let mut txn = db.begin(0).expect("begin");
+            txn.insert(ENTITY, TAG, tag("red")).expect("insert");
+            txn.insert(ENTITY, TAG, tag("blue")).expect("insert");
+            txn.commit().expect("commit");
+            let mut txn = db.begin(0).expect("begin");
+            txn.delete_value(&ENTITY, &TAG, &tag("red"))
+                .expect("delete value");
+            txn.commit().expect("commit");
+            // Dropped without close(), so reopening replays the WAL
+        }
+
+        let (db, _recovery) = Database::open(&path, BufferPool::new(100)).expect("open db");
+        let snapshot = db.begin_readonly().expect("snapshot");
+        let values = snapshot.get_values(&ENTITY, &TAG).expect("values");
+        db.release_snapshot(snapshot.close()).expect("release");
+        assert_eq!(values.len(), 1);
+        assert_eq!(values[0].value, tag("blue"));
+    }
+
+    /// Two distinct values with the same FNV-1a value hash.
+    fn colliding_values() -> (TripleValue, TripleValue) {
+        let first = TripleValue::Bytes(COLLIDING_FIRST.to_vec());
+        let second = TripleValue::Bytes(COLLIDING_SECOND.to_vec());
+        assert_ne!(first, second);
+        assert_eq!(
+            Cardinality::Many.value_hash(&first),
+            Cardinality::Many.value_hash(&second)
+        );
+        (first, second)
+    }
+
+    /// Every value of the entity's tag, in key order.
+    fn tag_values(db: &Database) -> Vec<TripleValue> {
+        let snapshot = db.begin_readonly().expect("snapshot");
+        let values = snapshot.get_values(&ENTITY, &TAG).expect("values");
+        db.release_snapshot(snapshot.close()).expect("release");
+        values.into_iter().map(|record| record.value).collect()
+    }
+
+    #[test]
+    fn test_cardinality_many_colliding_values_coexist() {
+        let dir = tempdir().expect("create temp dir");
+        let path = dir.path().join("test.db");
+        let (first, second) = colliding_values();
+        let home = Cardinality::Many.value_hash(&first);
+
+        {
+            let mut db = Database::create(&path, BufferPool::new(100)).expect("create db");
+            db.set_attribute_cardinality(TAG, Cardinality::Many)
+                .expect("declare");
+            let mut txn = db.begin(0).expect("begin");
+            txn.insert(ENTITY, TAG, first.clone_value())
+                .expect("insert");
+            txn.commit().expect("commit");
+            let mut txn = db.begin(0).expect("begin");
+            txn.insert(ENTITY, TAG, second.clone_value())
+                .expect("insert");
+            let outcome = txn.commit().expect("commit");
+            assert_eq!(outcome.changes_applied, 1);
+            assert_eq!(
+                tag_values(&db),
+                vec![first.clone_value(), second.clone_value()]
+            );
+
+            // Each value is found again past the collision
+            let mut txn = db.begin(0).expect("begin");
+            txn.insert(ENTITY, TAG, second.clone_value())
+                .expect("insert");
+            txn.insert(ENTITY, TAG, first.clone_value())
+                .expect("insert");
+            let outcome = txn.commit().expect("commit");
+            assert_eq!(outcome.changes_applied, 0);
+            // Dropped without close(), so reopening replays the WAL
+        }
+
+        let (mut db, _recovery) = Database::open(&path, BufferPool::new(100)).expect("open db");
+        let snapshot = db.begin_readonly().expect("snapshot");
+        let records = snapshot.get_values(&ENTITY, &TAG).expect("values");
+        db.release_snapshot(snapshot.close()).expect("release");
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[0].value_hash(), home);
+        assert_eq!(records[1].value_hash(), Cardinality::next_value_hash(home));
+
+        let mut txn = db.begin(0).expect("begin");
+        txn.delete_value(&ENTITY, &TAG, &first)
+            .expect("delete value");
+        txn.commit().expect("commit");
+        assert_eq!(tag_values(&db), vec![second]);
+    }
+
+    #[test]
+    fn test_cardinality_many_colliding_values_in_one_transaction() {
+        let mut db = Database::create_in_memory(BufferPool::new(100)).expect("create db");
+        db.set_attribute_cardinality(TAG, Cardinality::Many)
+            .expect("declare");
+        let (first, second) = colliding_values();
+
+        let mut txn = db.begin(0).expect("begin");
+        txn.insert(ENTITY, TAG, first.clone_value())
+            .expect("insert");
+        txn.insert(ENTITY, TAG, second.clone_value())
+            .expect("insert");
+        txn.insert(ENTITY, TAG, first.clone_value())
+            .expect("insert");
+        txn.commit().expect("commit");
+        // The repeated value lands on its earlier key, not past the second
+        assert_eq!(tag_values(&db), vec![first, second]);
+    }
+
+    #[test]
+    fn test_cardinality_many_probe_survives_gc() {
+        let mut db = Database::create_in_memory(BufferPool::new(100)).expect("create db");
+        db.set_attribute_cardinality(TAG, Cardinality::Many)
+            .expect("declare");
+        let (first, second) = colliding_values();
+
+        let mut txn = db.begin(0).expect("begin");
+        txn.insert(ENTITY, TAG, first.clone_value())
+            .expect("insert");
+        txn.insert(ENTITY, TAG, second.clone_value())
+            .expect("insert");
+        txn.commit().expect("commit");
+        let mut txn = db.begin(0).expect("begin");
+        txn.delete_value(&ENTITY, &TAG, &first)
+            .expect("delete value");
+        txn.commit().expect("commit");
+        db.force_gc().expect("gc");
+
+        // The probe still passes the collected value's key to reach the
+        // displaced one, so writing it again stores nothing new
+        let mut txn = db.begin(0).expect("begin");
+        txn.insert(ENTITY, TAG, second.clone_value())
+            .expect("insert");
+        let outcome = txn.commit().expect("commit");
+        assert_eq!(outcome.changes_applied, 0);
+        assert_eq!(tag_values(&db), vec![second.clone_value()]);
+
+        let mut txn = db.begin(0).expect("begin");
+        txn.insert(ENTITY, TAG, first.clone_value())
+            .expect("insert");
+        txn.commit().expect("commit");
+        assert_eq!(tag_values(&db), vec![first, second]);
+    }
+
+    /// Every value of the entity's tag, sorted by value.
+    fn sorted_tag_values(db: &Database) -> Vec<String> {
+        let mut values: Vec<String> = tag_values(db)
+            .into_iter()
+            .map(|value| match value {
+                TripleValue::String(name) => name,
+                other => panic!("unexpected tag {other:?}"),
+            })
+            .collect();
+        values.sort();
+        values
+    }
+
+    /// Commit each of `names` as a tag in its own transaction, returning the
+    /// number of changes each applied.
+    fn insert_tags(db: &mut Database, names: &[&str]) -> Vec<usize> {
+        names
+            .iter()
+            .map(|name| {
+                let mut txn = db.begin(0).expect("begin");
+                txn.insert(ENTITY, TAG, tag(name)).expect("insert");
+                txn.commit().expect("commit").changes_applied
+            })
+            .collect()
+    }
+
+    /// Commit a delete of each of `names` in one transaction.
+    fn delete_tags(db: &mut Database, names: &[&str]) {
+        let mut txn = db.begin(0).expect("begin");
+        for name in names {
+            txn.delete_value(&ENTITY, &TAG, &tag(name))
+                .expect("delete value");
+        }
+        txn.commit().expect("commit");
+    }
+
+    #[test]
+    fn test_forced_collisions_probe_a_chain_past_deletes() {
+        // Wraps from u64::MAX to 1, skipping the cardinality-one key
+        with_forced_value_hash(u64::MAX - 1, || {
+            let mut db = Database::create_in_memory(BufferPool::new(100)).expect("create db");
+            db.set_attribute_cardinality(TAG, Cardinality::Many)
+                .expect("declare");
+            assert_eq!(insert_tags(&mut db, &["a", "b", "c", "d"]), [1, 1, 1, 1]);
+
+            let snapshot = db.begin_readonly().expect("snapshot");
+            let mut hashes: Vec<(u64, TripleValue)> = snapshot
+                .get_values(&ENTITY, &TAG)
+                .expect("values")
+                .into_iter()
+                .map(|record| (record.value_hash(), record.value))
+                .collect();
+            db.release_snapshot(snapshot.close()).expect("release");
+            hashes.sort_by_key(|(value_hash, _)| *value_hash);
+            assert_eq!(
+                hashes,
+                vec![
+                    (1, tag("c")),
+                    (2, tag("d")),
+                    (u64::MAX - 1, tag("a")),
+                    (u64::MAX, tag("b")),
+                ]
+            );
+
+            // Lookups probe past the deleted values' keys
+            delete_tags(&mut db, &["a", "b"]);
+            assert_eq!(sorted_tag_values(&db), ["c", "d"]);
+            assert_eq!(insert_tags(&mut db, &["c", "d"]), [0, 0]);
+            delete_tags(&mut db, &["c"]);
+            assert_eq!(sorted_tag_values(&db), ["d"]);
+            assert_eq!(insert_tags(&mut db, &["d"]), [0]);
+
+            // Deleted values are written again without displacing "d"
+            assert_eq!(insert_tags(&mut db, &["b", "a", "c"]), [1, 1, 1]);
+            assert_eq!(sorted_tag_values(&db), ["a", "b", "c", "d"]);
+            assert!(db.check_index_consistency().expect("check").is_empty());
+        });
+    }
+
+    #[test]
+    fn test_forced_collisions_probe_past_collected_deletes() {
+        with_forced_value_hash(5, || {
+            let mut db = Database::create_in_memory(BufferPool::new(100)).expect("create db");
+            db.set_attribute_cardinality(TAG, Cardinality::Many)
+                .expect("declare");
+            insert_tags(&mut db, &["a", "b", "c"]);
+            delete_tags(&mut db, &["a", "b"]);
+            db.force_gc().expect("gc");
+
+            // "c" is still found past the keys of the collected values
+            assert_eq!(insert_tags(&mut db, &["c"]), [0]);
+            assert_eq!(sorted_tag_values(&db), ["c"]);
+            delete_tags(&mut db, &["c"]);
+            assert!(sorted_tag_values(&db).is_empty());
+            db.force_gc().expect("gc");
+
+            assert_eq!(insert_tags(&mut db, &["c", "b", "a"]), [1, 1, 1]);
+            assert_eq!(sorted_tag_values(&db), ["a", "b", "c"]);
+            delete_tags(&mut db, &["b"]);
+            db.force_gc().expect("gc");
+            assert_eq!(insert_tags(&mut db, &["a"]), [0]);
+            assert_eq!(sorted_tag_values(&db), ["a", "c"]);
+            assert!(db.check_index_consistency().expect("check").is_empty());
+        });
+    }
+
+    #[test]
+    fn test_set_attribute_cardinality_rejects_attribute_in_use() {
+        let mut db = Database::create_in_memory(BufferPool::new(100)).expect("create db");
+        let mut txn = db.begin(0).expect("begin");
+        txn.insert(ENTITY, TAG, tag("red")).expect("insert");
+        txn.commit().expect("commit");
+
+        let result = db.set_attribute_cardinality(TAG, Cardinality::Many);
+        assert!(matches!(result, Err(DatabaseError::AttributeInUse(id)) if id == TAG));
+        // Redeclaring the current cardinality is not a change
+        db.set_attribute_cardinality(TAG, Cardinality::One)
+            .expect("redeclare");
+
+        let mut txn = db.begin(0).expect("begin");
+        txn.delete(&ENTITY, &TAG).expect("delete");
+        txn.commit().expect("commit");
+        db.set_attribute_cardinality(TAG, Cardinality::Many)
+            .expect("declare once unused");
+    }
+}
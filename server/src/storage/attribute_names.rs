@@ -0,0 +1,213 @@
+//! Human-readable attribute names.
+//!
+//! Maps string names such as `"email"` to stable `AttributeId`s so clients do
+//! not have to manage raw 16-byte IDs themselves. The facility is opt-in: the
+//! raw-ID API is unchanged, and a database that never registers a name stores
+//! nothing extra.
+//!
+//! # Design
+//!
+//! Each registered name is stored as an ordinary triple on a reserved entity:
+//!
+//! ```text
+//! (ATTRIBUTE_NAMES_ENTITY_ID, <assigned AttributeId>, String(<name>))
+//! ```
+//!
+//! Storing the mapping as triples means it is written through the WAL,
+//! recovered after a crash, and broadcast to subscribers like any other change.
+//! Lookups scan the reserved entity, which holds one triple per name.
+//!
+//! # Invariants
+//!
+//! - Once assigned, a name's `AttributeId` never changes
+//! - Each name maps to at most one `AttributeId` per database
+
+use crate::storage::database::SYSTEM_CONNECTION_ID;
+use crate::storage::{Database, DatabaseError};
+use crate::types::{AttributeId, EntityId, TripleRecord, TripleValue};
+
+/// Reserved entity holding the name-to-ID mapping.
+///
+/// The leading NUL byte keeps it disjoint from IDs built with
+/// `EntityId::from_string`, which start with the string's bytes.
+pub const ATTRIBUTE_NAMES_ENTITY_ID: EntityId = EntityId(*b"\0enso:attr-names");
+
+/// Maximum length of an attribute name in bytes.
+///
+/// Short enough that the name is always stored inline, never in overflow pages.
+pub const MAX_ATTRIBUTE_NAME_LENGTH: usize = 256;
+
+impl Database {
+    /// Get the `AttributeId` for `name`, assigning a new one if the name is unknown.
+    ///
+    /// # Post-conditions
+    /// - The same name returns the same ID on every call, including after reopening
+    ///
+    /// # Errors
+    ///
+    /// Returns `DatabaseError::InvalidAttributeName` if the name is empty or longer
+    /// than `MAX_ATTRIBUTE_NAME_LENGTH`, or an error if registering the name fails.
+    pub fn attribute_id_for_name(&mut self, name: &str) -> Result<AttributeId, DatabaseError> {
+        if let Some(attribute_id) = self.attribute_id_by_name(name)? {
+            return Ok(attribute_id);
+        }
+        validate_attribute_name(name)?;
+
+        let attribute_id = AttributeId::new_v4();
+        let mut txn = self.begin(SYSTEM_CONNECTION_ID)?;
+        txn.insert(
+            ATTRIBUTE_NAMES_ENTITY_ID,
+            attribute_id,
+            TripleValue::String(name.to_owned()),
+        )?;
+        txn.commit()?;
+
+        tracing::debug!("Registered attribute name '{}'", name);
+        Ok(attribute_id)
+    }
+
+    /// Look up the `AttributeId` registered for `name`, without registering it.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if reading the name registry fails.
+    pub fn attribute_id_by_name(&self, name: &str) -> Result<Option<AttributeId>, DatabaseError> {
+        let records = self.attribute_name_records()?;
+        Ok(records
+            .into_iter()
+            .find(|record| matches!(&record.value, TripleValue::String(s) if s == name))
+            .map(|record| record.attribute_id))
+    }
+
+    /// Look up the name registered for `attribute_id`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if reading the name registry fails.
+    pub fn attribute_name(
+        &self,
+        attribute_id: &AttributeId,
+    ) -> Result<Option<String>, DatabaseError> {
+        let records = self.attribute_name_records()?;
+        Ok(records
+            .into_iter()
+            .find(|record| record.attribute_id == *attribute_id)
+            .and_then(|record| match record.value {
+                TripleValue::String(name) => Some(name),
+                _ => None,
+            }))
+    }
+
+    /// Read every triple on the reserved names entity.
+    fn attribute_name_records(&self) -> Result<Vec<TripleRecord>, DatabaseError> {
+        let snapshot = self.begin_readonly()?;
+        let records = snapshot.scan_entity(&ATTRIBUTE_NAMES_ENTITY_ID);
+        let txn_id = snapshot.close();
+        self.release_snapshot(txn_id)?;
+        records
+    }
+}
+
+/// Check that `name` can be registered.
+fn validate_attribute_name(name: &str) -> Result<(), DatabaseError> {
+    if name.is_empty() || name.len() > MAX_ATTRIBUTE_NAME_LENGTH {
+        return Err(DatabaseError::InvalidAttributeName(name.to_owned()));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::buffer_pool::BufferPool;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_attribute_id_for_name_is_stable() {
+        let dir = tempdir().expect("create temp dir");
+        let mut db =
+            Database::create(&dir.path().join("test.db"), BufferPool::new(100)).expect("create db");
+
+        let first = db.attribute_id_for_name("email").expect("register");
+        let second = db.attribute_id_for_name("email").expect("fetch");
+
+        assert_eq!(first, second);
+    }
+
+    #[test]
+    fn test_attribute_id_for_name_assigns_distinct_ids() {
+        let dir = tempdir().expect("create temp dir");
+        let mut db =
+            Database::create(&dir.path().join("test.db"), BufferPool::new(100)).expect("create db");
+
+        let email = db.attribute_id_for_name("email").expect("register email");
+        let status = db.attribute_id_for_name("status").expect("register status");
+
+        assert_ne!(email, status);
+    }
+
+    #[test]
+    fn test_attribute_names_survive_reopen() {
+        let dir = tempdir().expect("create temp dir");
+        let path = dir.path().join("test.db");
+
+        let mut db = Database::create(&path, BufferPool::new(100)).expect("create db");
+        let email = db.attribute_id_for_name("email").expect("register");
+        db.close().expect("close");
+
+        let (mut db, _) = Database::open_or_create(&path, BufferPool::new(100)).expect("reopen");
+        assert_eq!(
+            db.attribute_id_by_name("email").expect("lookup"),
+            Some(email)
+        );
+        assert_eq!(db.attribute_id_for_name("email").expect("fetch"), email);
+    }
+
+    #[test]
+    fn test_attribute_id_by_name_does_not_register() {
+        let dir = tempdir().expect("create temp dir");
+        let db =
+            Database::create(&dir.path().join("test.db"), BufferPool::new(100)).expect("create db");
+
+        assert_eq!(db.attribute_id_by_name("email").expect("lookup"), None);
+        assert_eq!(
+            db.attribute_id_by_name("email").expect("lookup again"),
+            None
+        );
+    }
+
+    #[test]
+    fn test_attribute_name_reverse_lookup() {
+        let dir = tempdir().expect("create temp dir");
+        let mut db =
+            Database::create(&dir.path().join("test.db"), BufferPool::new(100)).expect("create db");
+
+        let email = db.attribute_id_for_name("email").expect("register");
+
+        assert_eq!(
+            db.attribute_name(&email).expect("reverse lookup"),
+            Some("email".to_owned())
+        );
+        assert_eq!(
+            db.attribute_name(&AttributeId([9u8; 16]))
+                .expect("reverse lookup"),
+            None
+        );
+    }
+
+    #[test]
+    fn test_attribute_id_for_name_rejects_invalid_names() {
+        let dir = tempdir().expect("create temp dir");
+        let mut db =
+            Database::create(&dir.path().join("test.db"), BufferPool::new(100)).expect("create db");
+
+        let empty = db.attribute_id_for_name("");
+        assert!(matches!(empty, Err(DatabaseError::InvalidAttributeName(_))));
+
+        let too_long = db.attribute_id_for_name(&"a".repeat(MAX_ATTRIBUTE_NAME_LENGTH + 1));
+        assert!(matches!(
+            too_long,
+            Err(DatabaseError::InvalidAttributeName(_))
+        ));
+    }
+}
@@ -0,0 +1,382 @@
+//! Portable snapshot backups.
+//!
+//! A backup holds the live triples of one snapshot, independent of page size,
+//! checksum algorithm and transaction IDs, so it can be restored into any
+//! fresh database. Written by `Database::export_snapshot` and read by
+//! `Database::import_snapshot`.
+//!
+//! # File Format
+//!
+//! ```text
+//! Offset   Size   Field
+//! 0-7      8      Magic bytes (b"ENSOBKUP")
+//! 8-11     4      Format version (u32, little-endian)
+//! 12-27    16     HLC of the exported snapshot
+//! 28+      var    Records, each a u32 length followed by a serialized
+//!                 `TripleRecord`
+//! ...      4      End marker: a zero length
+//! ...      8      Number of records (u64)
+//! ```
+//!
+//! The trailer makes truncation detectable: a backup cut off between two
+//! records would otherwise read as a complete, smaller one.
+
+use std::io::{self, Read, Write};
+
+use crate::types::{HlcTimestamp, TripleError, TripleRecord};
+
+/// Magic bytes at the start of every backup.
+pub const BACKUP_MAGIC: [u8; 8] = *b"ENSOBKUP";
+
+/// Version of the backup format written by this build.
+pub const BACKUP_FORMAT_VERSION: u32 = 1;
+
+/// Length of the backup header in bytes.
+pub const BACKUP_HEADER_SIZE: usize = 8 + 4 + HlcTimestamp::SIZE;
+
+/// The header at the start of a backup.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BackupHeader {
+    /// Format version the backup was written with.
+    pub version: u32,
+    /// HLC of the exported snapshot. Every record's HLC is at or before it.
+    pub hlc: HlcTimestamp,
+}
+
+impl BackupHeader {
+    /// Header for a backup of the snapshot at `hlc`, in the current format.
+    #[must_use]
+    pub const fn new(hlc: HlcTimestamp) -> Self {
+        Self {
+            version: BACKUP_FORMAT_VERSION,
+            hlc,
+        }
+    }
+
+    /// Serialize the header.
+    #[must_use]
+    pub fn to_bytes(&self) -> [u8; BACKUP_HEADER_SIZE] {
+        let mut bytes = [0u8; BACKUP_HEADER_SIZE];
+        bytes[0..8].copy_from_slice(&BACKUP_MAGIC);
+        bytes[8..12].copy_from_slice(&self.version.to_le_bytes());
+        bytes[12..].copy_from_slice(&self.hlc.to_bytes());
+        bytes
+    }
+
+    /// Deserialize a header.
+    ///
+    /// # Errors
+    ///
+    /// Returns `BackupError::InvalidMagic` if `bytes` is not a backup header,
+    /// or `BackupError::UnsupportedVersion` if it was written in a format
+    /// this build cannot read.
+    pub fn from_bytes(bytes: &[u8; BACKUP_HEADER_SIZE]) -> Result<Self, BackupError> {
+        if bytes[0..8] != BACKUP_MAGIC {
+            return Err(BackupError::InvalidMagic);
+        }
+        let version = u32::from_le_bytes([bytes[8], bytes[9], bytes[10], bytes[11]]);
+        if version != BACKUP_FORMAT_VERSION {
+            return Err(BackupError::UnsupportedVersion(version));
+        }
+        let mut hlc = [0u8; HlcTimestamp::SIZE];
+        hlc.copy_from_slice(&bytes[12..]);
+        Ok(Self {
+            version,
+            hlc: HlcTimestamp::from_bytes(&hlc),
+        })
+    }
+}
+
+/// Writes a backup: the header, then records, then the trailer.
+pub struct BackupWriter<W: Write> {
+    writer: W,
+    records_written: u64,
+}
+
+impl<W: Write> BackupWriter<W> {
+    /// Start a backup by writing its header.
+    ///
+    /// # Errors
+    ///
+    /// Returns `BackupError::Io` if the header cannot be written.
+    pub fn new(mut writer: W, header: &BackupHeader) -> Result<Self, BackupError> {
+        writer.write_all(&header.to_bytes())?;
+        Ok(Self {
+            writer,
+            records_written: 0,
+        })
+    }
+
+    /// Append a record.
+    ///
+    /// # Errors
+    ///
+    /// Returns `BackupError::Io` if the record cannot be written, or
+    /// `BackupError::RecordTooLarge` if it does not fit a u32 length.
+    pub fn write_record(&mut self, record: &TripleRecord) -> Result<(), BackupError> {
+        let bytes = record.to_bytes();
+        let length = u32::try_from(bytes.len()).map_err(|_| BackupError::RecordTooLarge)?;
+        self.writer.write_all(&length.to_le_bytes())?;
+        self.writer.write_all(&bytes)?;
+        self.records_written += 1;
+        Ok(())
+    }
+
+    /// Write the trailer and flush, returning the number of records written.
+    ///
+    /// # Errors
+    ///
+    /// Returns `BackupError::Io` if the trailer cannot be written or flushed.
+    pub fn finish(mut self) -> Result<u64, BackupError> {
+        self.writer.write_all(&0u32.to_le_bytes())?;
+        self.writer.write_all(&self.records_written.to_le_bytes())?;
+        self.writer.flush()?;
+        Ok(self.records_written)
+    }
+}
+
+/// Reads a backup's header, then its records in order.
+pub struct BackupReader<R: Read> {
+    reader: R,
+    header: BackupHeader,
+    records_read: u64,
+    finished: bool,
+}
+
+impl<R: Read> BackupReader<R> {
+    /// Start reading a backup by reading and checking its header.
+    ///
+    /// # Errors
+    ///
+    /// Returns `BackupError::Truncated` if the input ends within the header,
+    /// or an error from `BackupHeader::from_bytes`.
+    pub fn new(mut reader: R) -> Result<Self, BackupError> {
+        let mut bytes = [0u8; BACKUP_HEADER_SIZE];
+        read_exact(&mut reader, &mut bytes)?;
+        let header = BackupHeader::from_bytes(&bytes)?;
+        Ok(Self {
+            reader,
+            header,
+            records_read: 0,
+            finished: false,
+        })
+    }
+
+    /// The backup's header.
+    #[must_use]
+    pub const fn header(&self) -> &BackupHeader {
+        &self.header
+    }
+
+    /// Read the next record, or `None` after the last one.
+    ///
+    /// # Errors
+    ///
+    /// - `BackupError::Truncated` if the input ends before the trailer
+    /// - `BackupError::CountMismatch` if the trailer's count differs from the
+    ///   number of records read
+    /// - `BackupError::Triple` if a record cannot be deserialized
+    pub fn next_record(&mut self) -> Result<Option<TripleRecord>, BackupError> {
+        if self.finished {
+            return Ok(None);
+        }
+        let mut length = [0u8; 4];
+        read_exact(&mut self.reader, &mut length)?;
+        let length = u32::from_le_bytes(length) as usize;
+        if length == 0 {
+            let mut count = [0u8; 8];
+            read_exact(&mut self.reader, &mut count)?;
+            let expected = u64::from_le_bytes(count);
+            if expected != self.records_read {
+                return Err(BackupError::CountMismatch {
+                    expected,
+                    actual: self.records_read,
+                });
+            }
+            self.finished = true;
+            return Ok(None);
+        }
+        let mut bytes = vec![0u8; length];
+        read_exact(&mut self.reader, &mut bytes)?;
+        let record = TripleRecord::from_bytes(&bytes)?;
+        self.records_read += 1;
+        Ok(Some(record))
+    }
+}
+
+/// Fill `buf`, reporting an early end of input as `BackupError::Truncated`.
+fn read_exact(reader: &mut impl Read, buf: &mut [u8]) -> Result<(), BackupError> {
+    reader.read_exact(buf).map_err(|e| {
+        if e.kind() == io::ErrorKind::UnexpectedEof {
+            BackupError::Truncated
+        } else {
+            BackupError::Io(e)
+        }
+    })
+}
+
+/// Errors that can occur reading or writing a backup.
+#[derive(Debug)]
+pub enum BackupError {
+    /// I/O error on the backup stream.
+    Io(io::Error),
+    /// The input does not start with `BACKUP_MAGIC`.
+    InvalidMagic,
+    /// The backup was written in a format version this build cannot read.
+    UnsupportedVersion(u32),
+    /// The input ended before the backup's trailer.
+    Truncated,
+    /// A record is too large for its length prefix.
+    RecordTooLarge,
+    /// A record could not be deserialized.
+    Triple(TripleError),
+    /// The trailer's record count differs from the records read.
+    CountMismatch {
+        /// Record count stored in the trailer.
+        expected: u64,
+        /// Number of records read.
+        actual: u64,
+    },
+}
+
+impl std::fmt::Display for BackupError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Io(e) => write!(f, "I/O error: {e}"),
+            Self::InvalidMagic => write!(f, "not a backup file"),
+            Self::UnsupportedVersion(version) => {
+                write!(f, "unsupported backup format version {version}")
+            }
+            Self::Truncated => write!(f, "backup is truncated"),
+            Self::RecordTooLarge => write!(f, "record too large for a backup"),
+            Self::Triple(e) => write!(f, "invalid record: {e}"),
+            Self::CountMismatch { expected, actual } => write!(
+                f,
+                "backup trailer counts {expected} records, but {actual} were read"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for BackupError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Io(e) => Some(e),
+            Self::Triple(e) => Some(e),
+            Self::InvalidMagic
+            | Self::UnsupportedVersion(_)
+            | Self::Truncated
+            | Self::RecordTooLarge
+            | Self::CountMismatch { .. } => None,
+        }
+    }
+}
+
+impl From<io::Error> for BackupError {
+    fn from(e: io::Error) -> Self {
+        Self::Io(e)
+    }
+}
+
+impl From<TripleError> for BackupError {
+    fn from(e: TripleError) -> Self {
+        Self::Triple(e)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{AttributeId, EntityId, TripleValue};
+
+    fn record(index: u8) -> TripleRecord {
+        TripleRecord::new(
+            EntityId([index; 16]),
+            AttributeId([2u8; 16]),
+            7,
+            HlcTimestamp::new(1_000, u32::from(index)),
+            TripleValue::Number(f64::from(index)),
+        )
+    }
+
+    fn backup(records: &[TripleRecord]) -> Vec<u8> {
+        let header = BackupHeader::new(HlcTimestamp::new(2_000, 3));
+        let mut bytes = Vec::new();
+        let mut writer = BackupWriter::new(&mut bytes, &header).expect("header");
+        for record in records {
+            writer.write_record(record).expect("record");
+        }
+        let count = writer.finish().expect("finish");
+        assert_eq!(count, u64::try_from(records.len()).unwrap_or(u64::MAX));
+        bytes
+    }
+
+    #[test]
+    fn test_backup_round_trip() {
+        let records = vec![record(1), record(2), record(3)];
+        let bytes = backup(&records);
+
+        let mut reader = BackupReader::new(bytes.as_slice()).expect("header");
+        assert_eq!(reader.header().version, BACKUP_FORMAT_VERSION);
+        assert_eq!(reader.header().hlc, HlcTimestamp::new(2_000, 3));
+        let mut read = Vec::new();
+        while let Some(record) = reader.next_record().expect("record") {
+            read.push(record);
+        }
+        assert_eq!(
+            read.iter().map(TripleRecord::to_bytes).collect::<Vec<_>>(),
+            records
+                .iter()
+                .map(TripleRecord::to_bytes)
+                .collect::<Vec<_>>()
+        );
+        assert!(reader.next_record().expect("after end").is_none());
+    }
+
+    #[test]
+    fn test_backup_rejects_other_files() {
+        let mut bytes = backup(&[record(1)]);
+        bytes[0] = b'X';
+        assert!(matches!(
+            BackupReader::new(bytes.as_slice()),
+            Err(BackupError::InvalidMagic)
+        ));
+
+        let mut bytes = backup(&[record(1)]);
+        bytes[8..12].copy_from_slice(&99u32.to_le_bytes());
+        assert!(matches!(
+            BackupReader::new(bytes.as_slice()),
+            Err(BackupError::UnsupportedVersion(99))
+        ));
+    }
+
+    #[test]
+    fn test_backup_detects_truncation() {
+        let records = vec![record(1), record(2)];
+        let bytes = backup(&records);
+        // Cut off at the boundary between the two records
+        let record_len = 4 + records[0].to_bytes().len();
+        let truncated = &bytes[..BACKUP_HEADER_SIZE + record_len];
+
+        let mut reader = BackupReader::new(truncated).expect("header");
+        assert!(reader.next_record().expect("first record").is_some());
+        assert!(matches!(reader.next_record(), Err(BackupError::Truncated)));
+    }
+
+    #[test]
+    fn test_backup_detects_count_mismatch() {
+        let mut bytes = backup(&[record(1)]);
+        let count_offset = bytes.len() - 8;
+        bytes[count_offset..].copy_from_slice(&2u64.to_le_bytes());
+
+        let mut reader = BackupReader::new(bytes.as_slice()).expect("header");
+        assert!(reader.next_record().expect("record").is_some());
+        assert!(matches!(
+            reader.next_record(),
+            Err(BackupError::CountMismatch {
+                expected: 2,
+                actual: 1
+            })
+        ));
+    }
+}
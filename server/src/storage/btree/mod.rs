@@ -8,9 +8,16 @@
 //! - Internal nodes: store keys and child page pointers
 //! - Leaf nodes: store key-value pairs, doubly-linked for efficient range scans
 //!
+//! Removing keys keeps non-root nodes at least `MIN_FILL_PERCENT` full by
+//! merging an underfull node with a sibling or borrowing entries from it. The
+//! root collapses into its only child, shrinking the tree's height.
+//!
 //! # Key Format
 //!
-//! Keys are 32 bytes: `(entity_id: [u8; 16], attribute_id: [u8; 16])`
+//! Keys are 40 bytes: `(entity_id: [u8; 16], attribute_id: [u8; 16], value_hash: u64)`,
+//! the hash big-endian so an attribute's values are adjacent. A
+//! cardinality-one triple's value hash is 0; each value of a cardinality-many
+//! attribute has its own nonzero hash.
 //!
 //! # Usage
 //!
@@ -18,23 +25,24 @@
 //! use server::storage::btree::{make_key, KEY_SIZE};
 //! use server::types::{EntityId, AttributeId};
 //!
-//! // Keys are 32 bytes: entity_id (16) + attribute_id (16)
+//! // Keys are 40 bytes: entity_id (16) + attribute_id (16) + value_hash (8)
 //! let entity_id = EntityId([1u8; 16]);
 //! let attribute_id = AttributeId([2u8; 16]);
 //! let key = make_key(&entity_id, &attribute_id);
 //!
 //! assert_eq!(key.len(), KEY_SIZE);
 //! assert_eq!(&key[..16], &entity_id.0);
-//! assert_eq!(&key[16..], &attribute_id.0);
+//! assert_eq!(&key[16..32], &attribute_id.0);
+//! assert_eq!(&key[32..], &[0u8; 8]);
 //! ```
 
 mod node;
 mod tree;
 
 pub use node::{
-    InternalNode, KEY_SIZE, Key, LeafEntry, LeafNode, MAX_INLINE_VALUE_SIZE, NodeError, NodeHeader,
-    NodeType, compare_keys, make_key, split_key,
+    InternalNode, KEY_SIZE, Key, LeafEntry, LeafNode, MAX_INLINE_VALUE_SIZE, MIN_FILL_PERCENT,
+    NodeError, NodeHeader, NodeType, compare_keys, key_value_hash, make_key, make_value_key,
+    split_key,
 };
 pub use tree::{BTree, BTreeError, BTreeIterator};
-#[cfg(unix)]
 pub use tree::{BTreeReader, BTreeReaderIterator};
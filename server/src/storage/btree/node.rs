@@ -1,18 +1,18 @@
 //! B-tree node types and serialization.
 //!
-//! The B-tree uses 8KB pages with the following node types:
+//! The B-tree uses the database file's pages (8KB by default) with the following node types:
 //! - Internal nodes: store keys and child page pointers
 //! - Leaf nodes: store key-value pairs, doubly-linked for range scans
 
 #![allow(clippy::cast_possible_truncation)]
 
-use crate::storage::page::{PAGE_SIZE, Page, PageHeader, PageId, PageType};
+use crate::storage::page::{Page, PageHeader, PageId, PageType};
 use crate::types::{AttributeId, EntityId};
 
-/// Size of a key in bytes (`entity_id` + `attribute_id` = 16 + 16).
-pub const KEY_SIZE: usize = 32;
+/// Size of a key in bytes (`entity_id` + `attribute_id` + `value_hash` = 16 + 16 + 8).
+pub const KEY_SIZE: usize = 40;
 
-/// A 32-byte key for the primary index (`entity_id`, `attribute_id`).
+/// A 40-byte key for the primary index (`entity_id`, `attribute_id`, `value_hash`).
 pub type Key = [u8; KEY_SIZE];
 
 /// Node header layout (after page header):
@@ -28,30 +28,36 @@ const NODE_HEADER_SIZE: usize = 27;
 /// Offset where node data starts (after page header + node header).
 const DATA_OFFSET: usize = PageHeader::SIZE + NODE_HEADER_SIZE;
 
-/// Available space for node data.
-const DATA_SPACE: usize = PAGE_SIZE - DATA_OFFSET;
+/// Available space for node data in a page of `page_size` bytes.
+const fn data_space(page_size: usize) -> usize {
+    page_size - DATA_OFFSET
+}
 
-/// Internal node entry size: key (32 bytes) + child pointer (8 bytes).
+/// Internal node entry size: key (40 bytes) + child pointer (8 bytes).
 const INTERNAL_ENTRY_SIZE: usize = KEY_SIZE + 8;
 
-/// Maximum number of keys in an internal node.
+/// Maximum number of keys in an internal node stored in a page of `page_size` bytes.
 /// We need space for N keys and N+1 child pointers.
-/// `DATA_SPACE` = N * `KEY_SIZE` + (N+1) * 8
-/// `DATA_SPACE` = N * 32 + N * 8 + 8
-/// `DATA_SPACE` - 8 = N * 40
-/// N = (`DATA_SPACE` - 8) / 40
-pub const MAX_INTERNAL_KEYS: usize = (DATA_SPACE - 8) / INTERNAL_ENTRY_SIZE;
+/// `data_space` = N * `KEY_SIZE` + (N+1) * 8
+/// `data_space` = N * 40 + N * 8 + 8
+/// `data_space` - 8 = N * 48
+/// N = (`data_space` - 8) / 48
+#[must_use]
+pub const fn max_internal_keys(page_size: usize) -> usize {
+    (data_space(page_size) - 8) / INTERNAL_ENTRY_SIZE
+}
 
-/// Leaf entry overhead: key (32 bytes) + `value_len` (2 bytes).
+/// Leaf entry overhead: key (40 bytes) + `value_len` (2 bytes).
 const LEAF_ENTRY_OVERHEAD: usize = KEY_SIZE + 2;
 
 /// Maximum value size that can be stored inline in a leaf.
 /// For larger values, we'd use overflow pages (not implemented in Phase 1).
 pub const MAX_INLINE_VALUE_SIZE: usize = 1024;
 
-/// Minimum number of entries in a leaf node (except root).
-/// This is approximate since leaf entries are variable-sized.
-pub const MIN_LEAF_ENTRIES: usize = 2;
+/// Fill, in percent of a page's capacity, below which a non-root node is
+/// merged with a sibling or refilled from one. A leaf's fill is the bytes its
+/// entries take; an internal node's is its key count.
+pub const MIN_FILL_PERCENT: usize = 25;
 
 /// Node type discriminant.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -175,6 +181,10 @@ impl InternalNode {
         }
 
         let key_count = header.key_count as usize;
+        // Bounded by the buffer, so a torn count cannot read past it
+        if key_count > max_internal_keys(page.as_bytes().len()) {
+            return Err(NodeError::Truncated);
+        }
         let mut keys = Vec::with_capacity(key_count);
         let mut children = Vec::with_capacity(key_count + 1);
 
@@ -247,11 +257,57 @@ impl InternalNode {
         }
     }
 
-    /// Check if the node is full.
+    /// Check if the node is full when stored in a page of `page_size` bytes.
+    #[must_use]
+    #[allow(clippy::missing_const_for_fn)] // Vec::len() is not const-stable
+    pub fn is_full(&self, page_size: usize) -> bool {
+        self.keys.len() >= max_internal_keys(page_size)
+    }
+
+    /// Check if the node is below `MIN_FILL_PERCENT` when stored in a page of
+    /// `page_size` bytes.
+    #[must_use]
+    #[allow(clippy::missing_const_for_fn)] // Vec::len() is not const-stable
+    pub fn is_underfull(&self, page_size: usize) -> bool {
+        self.keys.len() * 100 < max_internal_keys(page_size) * MIN_FILL_PERCENT
+    }
+
+    /// Check if this node and its right sibling fit in one page of
+    /// `page_size` bytes, with the key separating them.
     #[must_use]
     #[allow(clippy::missing_const_for_fn)] // Vec::len() is not const-stable
-    pub fn is_full(&self) -> bool {
-        self.keys.len() >= MAX_INTERNAL_KEYS
+    pub fn can_merge(&self, right: &Self, page_size: usize) -> bool {
+        self.keys.len() + 1 + right.keys.len() <= max_internal_keys(page_size)
+    }
+
+    /// Append the right sibling's keys and children, pulling down the key
+    /// that separated the two in their parent.
+    pub fn merge(&mut self, separator: Key, right: Self) {
+        self.keys.push(separator);
+        self.keys.extend(right.keys);
+        self.children.extend(right.children);
+    }
+
+    /// Even out the key counts of this node and its right sibling, rotating
+    /// keys through their parent's `separator`.
+    ///
+    /// Returns the new separator.
+    #[must_use]
+    pub fn redistribute(&mut self, separator: Key, right: &mut Self) -> Key {
+        let mut keys = std::mem::take(&mut self.keys);
+        keys.push(separator);
+        keys.append(&mut right.keys);
+        let mut children = std::mem::take(&mut self.children);
+        children.append(&mut right.children);
+
+        let mid = keys.len() / 2;
+        right.keys = keys.split_off(mid + 1);
+        let separator = keys[mid];
+        keys.truncate(mid);
+        self.keys = keys;
+        right.children = children.split_off(mid + 1);
+        self.children = children;
+        separator
     }
 
     /// Insert a key and right child at the appropriate position.
@@ -332,18 +388,23 @@ impl LeafNode {
             .sum()
     }
 
-    /// Check if a new entry would fit in this node.
+    /// Check if a new entry would fit in this node when stored in a page of `page_size` bytes.
     #[must_use]
-    pub fn can_fit(&self, value_len: usize) -> bool {
-        self.entries_size() + LEAF_ENTRY_OVERHEAD + value_len <= DATA_SPACE
+    pub fn can_fit(&self, value_len: usize, page_size: usize) -> bool {
+        self.entries_size() + LEAF_ENTRY_OVERHEAD + value_len <= data_space(page_size)
     }
 
-    /// Check if an updated value would fit in this node.
+    /// Check if an updated value would fit in this node when stored in a page of `page_size` bytes.
     ///
     /// When updating an existing entry, the new value replaces the old one,
     /// so we need to account for the size difference rather than the full size.
     #[must_use]
-    pub fn can_fit_update(&self, old_value_len: usize, new_value_len: usize) -> bool {
+    pub fn can_fit_update(
+        &self,
+        old_value_len: usize,
+        new_value_len: usize,
+        page_size: usize,
+    ) -> bool {
         // New size = current size - old value + new value
         let current = self.entries_size();
         if new_value_len <= old_value_len {
@@ -352,15 +413,62 @@ impl LeafNode {
         } else {
             // Growing: check if the additional space fits
             let additional = new_value_len - old_value_len;
-            current + additional <= DATA_SPACE
+            current + additional <= data_space(page_size)
         }
     }
 
-    /// Check if the node is at minimum capacity.
+    /// Check if the node is below `MIN_FILL_PERCENT` when stored in a page of
+    /// `page_size` bytes.
     #[must_use]
-    #[allow(clippy::missing_const_for_fn)] // Vec::len() is not const-stable
-    pub fn is_underfull(&self) -> bool {
-        self.entries.len() < MIN_LEAF_ENTRIES
+    pub fn is_underfull(&self, page_size: usize) -> bool {
+        self.entries_size() * 100 < data_space(page_size) * MIN_FILL_PERCENT
+    }
+
+    /// Check if this node and its right sibling fit in one page of
+    /// `page_size` bytes.
+    #[must_use]
+    pub fn can_merge(&self, right: &Self, page_size: usize) -> bool {
+        self.entries_size() + right.entries_size() <= data_space(page_size)
+    }
+
+    /// Append the right sibling's entries, taking over its link to the next
+    /// leaf.
+    pub fn merge(&mut self, right: Self) {
+        self.entries.extend(right.entries);
+        self.header.next_leaf = right.header.next_leaf;
+    }
+
+    /// Even out the bytes taken by this node and its right sibling, moving
+    /// entries across.
+    ///
+    /// Each side ends up with at most half their combined size plus one
+    /// entry. Returns the first key of the right sibling, the new separator.
+    ///
+    /// # Pre-conditions
+    /// - The two nodes hold at least two entries between them
+    #[must_use]
+    pub fn redistribute(&mut self, right: &mut Self) -> Key {
+        let mut entries = std::mem::take(&mut self.entries);
+        entries.append(&mut right.entries);
+        let total: usize = entries
+            .iter()
+            .map(|e| LEAF_ENTRY_OVERHEAD + e.value.len())
+            .sum();
+
+        let mut left_size = 0;
+        let mut split = entries.len() - 1;
+        for (i, entry) in entries.iter().enumerate() {
+            left_size += LEAF_ENTRY_OVERHEAD + entry.value.len();
+            if left_size * 2 >= total {
+                split = i + 1;
+                break;
+            }
+        }
+        let split = split.clamp(1, entries.len() - 1);
+
+        right.entries = entries.split_off(split);
+        self.entries = entries;
+        right.entries[0].key
     }
 
     /// Read a leaf node from a page.
@@ -372,10 +480,16 @@ impl LeafNode {
 
         let entry_count = header.key_count as usize;
         let mut entries = Vec::with_capacity(entry_count);
+        // Bounded by the buffer, so a torn count cannot read past it
+        let buffer_size = page.as_bytes().len();
 
         let mut offset = DATA_OFFSET;
 
         for _ in 0..entry_count {
+            if offset + LEAF_ENTRY_OVERHEAD > buffer_size {
+                return Err(NodeError::Truncated);
+            }
+
             // Read key
             let mut key = [0u8; KEY_SIZE];
             key.copy_from_slice(page.read_bytes(offset, KEY_SIZE));
@@ -385,6 +499,9 @@ impl LeafNode {
             let value_len =
                 u16::from_le_bytes([page.read_u8(offset), page.read_u8(offset + 1)]) as usize;
             offset += 2;
+            if offset + value_len > buffer_size {
+                return Err(NodeError::Truncated);
+            }
 
             // Read value
             let value = page.read_bytes(offset, value_len).to_vec();
@@ -504,6 +621,8 @@ pub enum NodeError {
     ValueTooLarge(usize),
     /// Node is full.
     NodeFull,
+    /// Node entries run past the end of the page, e.g. after a torn write.
+    Truncated,
 }
 
 impl std::fmt::Display for NodeError {
@@ -518,6 +637,7 @@ impl std::fmt::Display for NodeError {
                 )
             }
             Self::NodeFull => write!(f, "node is full"),
+            Self::Truncated => write!(f, "node entries run past the end of the page"),
         }
     }
 }
@@ -531,11 +651,23 @@ pub fn compare_keys(a: &Key, b: &Key) -> std::cmp::Ordering {
 }
 
 /// Create a key from `entity_id` and `attribute_id`.
+///
+/// The value hash is 0, the key of a cardinality-one attribute's triple.
 #[must_use]
 pub fn make_key(entity_id: &EntityId, attribute_id: &AttributeId) -> Key {
+    make_value_key(entity_id, attribute_id, 0)
+}
+
+/// Create a key from `entity_id`, `attribute_id` and `value_hash`.
+///
+/// The keys of one entity and attribute are adjacent and ordered by value
+/// hash, so a scan from `make_key` finds every value of the attribute.
+#[must_use]
+pub fn make_value_key(entity_id: &EntityId, attribute_id: &AttributeId, value_hash: u64) -> Key {
     let mut key = [0u8; KEY_SIZE];
     key[..16].copy_from_slice(&entity_id.0);
-    key[16..].copy_from_slice(&attribute_id.0);
+    key[16..32].copy_from_slice(&attribute_id.0);
+    key[32..].copy_from_slice(&value_hash.to_be_bytes());
     key
 }
 
@@ -545,14 +677,23 @@ pub fn split_key(key: &Key) -> (EntityId, AttributeId) {
     let mut entity_bytes = [0u8; 16];
     let mut attribute_bytes = [0u8; 16];
     entity_bytes.copy_from_slice(&key[..16]);
-    attribute_bytes.copy_from_slice(&key[16..]);
+    attribute_bytes.copy_from_slice(&key[16..32]);
     (EntityId(entity_bytes), AttributeId(attribute_bytes))
 }
 
+/// Extract the value hash from a key.
+#[must_use]
+pub fn key_value_hash(key: &Key) -> u64 {
+    let mut hash = [0u8; 8];
+    hash.copy_from_slice(&key[32..]);
+    u64::from_be_bytes(hash)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use crate::storage::buffer_pool::BufferPool;
+    use crate::storage::page::PAGE_SIZE;
 
     #[test]
     fn test_key_operations() {
@@ -564,6 +705,21 @@ mod tests {
 
         assert_eq!(e, entity_id);
         assert_eq!(a, attribute_id);
+        assert_eq!(key_value_hash(&key), 0);
+    }
+
+    #[test]
+    fn test_value_keys_follow_their_attribute() {
+        let entity_id = EntityId([1u8; 16]);
+        let attribute_id = AttributeId([2u8; 16]);
+        let next_attribute = AttributeId([3u8; 16]);
+
+        let key = make_value_key(&entity_id, &attribute_id, u64::MAX);
+        assert_eq!(split_key(&key), (entity_id, attribute_id));
+        assert_eq!(key_value_hash(&key), u64::MAX);
+
+        assert!(make_key(&entity_id, &attribute_id) < make_value_key(&entity_id, &attribute_id, 1));
+        assert!(key < make_key(&entity_id, &next_attribute));
     }
 
     #[test]
@@ -602,6 +758,34 @@ mod tests {
         assert_eq!(restored.get(&[3u8; KEY_SIZE]), Some(b"value3".as_slice()));
     }
 
+    #[test]
+    fn test_torn_node_pages_are_rejected() {
+        let pool = BufferPool::new(10);
+        let mut leaf = LeafNode::new(0);
+        leaf.insert([1u8; KEY_SIZE], vec![7u8; 100]);
+        let mut page = pool.lease_page_zeroed().expect("should lease");
+        leaf.write_to_page(&mut page);
+
+        // A torn write can leave a new entry count over old entries
+        let mut header = NodeHeader::from_page(&page).expect("should parse");
+        header.key_count = u16::MAX;
+        header.write_to_page(&mut page);
+        assert!(matches!(
+            LeafNode::from_page(&page),
+            Err(NodeError::Truncated)
+        ));
+
+        let mut page = pool.lease_page_zeroed().expect("should lease");
+        InternalNode::new(0).write_to_page(&mut page);
+        let mut header = NodeHeader::from_page(&page).expect("should parse");
+        header.key_count = u16::MAX;
+        header.write_to_page(&mut page);
+        assert!(matches!(
+            InternalNode::from_page(&page),
+            Err(NodeError::Truncated)
+        ));
+    }
+
     #[test]
     fn test_leaf_node_insert_update() {
         let mut node = LeafNode::new(0);
@@ -668,6 +852,73 @@ mod tests {
         assert_eq!(split_key, right.entries[0].key);
     }
 
+    fn leaf_with(keys: std::ops::Range<u8>, value_len: usize) -> LeafNode {
+        let mut node = LeafNode::new(0);
+        for i in keys {
+            let mut key = [0u8; KEY_SIZE];
+            key[0] = i;
+            node.insert(key, vec![i; value_len]);
+        }
+        node
+    }
+
+    #[test]
+    fn test_leaf_node_merge() {
+        let mut left = leaf_with(0..3, 10);
+        let mut right = leaf_with(3..5, 10);
+        right.header.next_leaf = 99;
+        assert!(left.is_underfull(PAGE_SIZE));
+        assert!(left.can_merge(&right, PAGE_SIZE));
+
+        left.merge(right);
+
+        assert_eq!(left.entries.len(), 5);
+        assert!(left.entries.windows(2).all(|w| w[0].key < w[1].key));
+        assert_eq!(left.header.next_leaf, 99);
+    }
+
+    #[test]
+    fn test_leaf_node_redistribute() {
+        // A nearly empty left node and a full right one
+        let mut left = leaf_with(0..1, 500);
+        let mut right = leaf_with(1..16, 500);
+        assert!(left.is_underfull(PAGE_SIZE));
+        assert!(!left.can_merge(&right, PAGE_SIZE));
+
+        let separator = left.redistribute(&mut right);
+
+        assert_eq!(left.entries.len(), 8);
+        assert_eq!(right.entries.len(), 8);
+        assert_eq!(separator, right.entries[0].key);
+        assert!(left.entries.last().expect("left").key < separator);
+        assert!(!left.is_underfull(PAGE_SIZE));
+    }
+
+    #[test]
+    fn test_internal_node_merge_and_redistribute() {
+        let key = |i: u8| [i; KEY_SIZE];
+        let mut left = InternalNode::with_children(0, 1, key(10), 2);
+        let right = InternalNode::with_children(0, 3, key(30), 4);
+        assert!(left.is_underfull(PAGE_SIZE));
+        assert!(left.can_merge(&right, PAGE_SIZE));
+
+        left.merge(key(20), right);
+        assert_eq!(left.keys, vec![key(10), key(20), key(30)]);
+        assert_eq!(left.children, vec![1, 2, 3, 4]);
+
+        let mut right = InternalNode::new(0);
+        right.keys = (41..=45).map(key).collect();
+        right.children = (5..=10).collect();
+        let separator = left.redistribute(key(40), &mut right);
+
+        // Keys 10..=45 in order, split evenly around the new separator
+        assert_eq!(left.keys, vec![key(10), key(20), key(30), key(40)]);
+        assert_eq!(separator, key(41));
+        assert_eq!(right.keys, vec![key(42), key(43), key(44), key(45)]);
+        assert_eq!(left.children, vec![1, 2, 3, 4, 5]);
+        assert_eq!(right.children, vec![6, 7, 8, 9, 10]);
+    }
+
     #[test]
     fn test_internal_node_split() {
         let mut node = InternalNode::new(0);
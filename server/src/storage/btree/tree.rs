@@ -14,13 +14,26 @@ use crate::storage::btree::node::{
     InternalNode, Key, LeafEntry, LeafNode, MAX_INLINE_VALUE_SIZE, NodeError, NodeHeader, NodeType,
 };
 use crate::storage::file::{DatabaseFile, FileError};
-#[cfg(unix)]
-use crate::storage::overflow::read_overflow_at;
 use crate::storage::overflow::{
     OverflowError, OverflowRef, free_overflow, read_overflow, write_overflow,
 };
+use crate::storage::overflow::{read_overflow_at, read_overflow_prefix_at};
 use crate::storage::page::PageId;
 
+/// The keys that belong in a leaf: from `lower`, inclusive, to `upper`,
+/// exclusive. A missing bound is unbounded.
+#[derive(Default)]
+struct KeyRange {
+    lower: Option<Key>,
+    upper: Option<Key>,
+}
+
+impl KeyRange {
+    fn contains(&self, key: &Key) -> bool {
+        self.lower.is_none_or(|lower| *key >= lower) && self.upper.is_none_or(|upper| *key < upper)
+    }
+}
+
 /// A B-tree backed by a database file.
 pub struct BTree<'a> {
     file: &'a mut DatabaseFile,
@@ -73,19 +86,44 @@ impl<'a> BTree<'a> {
         let page = self.file.read_page(leaf_page_id)?;
         let leaf = LeafNode::from_page(&page)?;
 
-        match leaf.get(key) {
-            Some(stored_value) => {
-                // Check if this is an overflow reference
-                if let Some(overflow_ref) = OverflowRef::from_bytes(stored_value) {
-                    // Read from overflow pages
-                    let value = read_overflow(self.file, &overflow_ref)?;
-                    Ok(Some(value))
-                } else {
-                    // Inline value
-                    Ok(Some(stored_value.to_vec()))
+        leaf.get(key)
+            .map(|stored_value| self.load_value(stored_value))
+            .transpose()
+    }
+
+    /// Look up many keys, in order, as `get` would one at a time.
+    ///
+    /// As with `insert_many`, a key in the leaf the previous key was found
+    /// in reuses that leaf, so keys sorted by key read each leaf once.
+    pub fn get_many(&mut self, keys: &[Key]) -> Result<Vec<Option<Vec<u8>>>, BTreeError> {
+        let mut values = Vec::with_capacity(keys.len());
+        // The leaf last read, with the keys it covers
+        let mut current: Option<(LeafNode, KeyRange)> = None;
+
+        for key in keys {
+            let (leaf, range) = match current.take() {
+                Some(current) if current.1.contains(key) => current,
+                _ => {
+                    let (page_id, range) = self.find_leaf_with_range(key)?;
+                    (self.read_leaf(page_id)?, range)
                 }
-            }
-            None => Ok(None),
+            };
+            values.push(
+                leaf.get(key)
+                    .map(|stored_value| self.load_value(stored_value))
+                    .transpose()?,
+            );
+            current = Some((leaf, range));
+        }
+        Ok(values)
+    }
+
+    /// The value a leaf stores as `stored_value`, read from overflow pages
+    /// if it is a reference to them.
+    fn load_value(&mut self, stored_value: &[u8]) -> Result<Vec<u8>, BTreeError> {
+        match OverflowRef::from_bytes(stored_value) {
+            Some(overflow_ref) => Ok(read_overflow(self.file, &overflow_ref)?),
+            None => Ok(stored_value.to_vec()),
         }
     }
 
@@ -94,13 +132,7 @@ impl<'a> BTree<'a> {
     /// Values larger than `MAX_INLINE_VALUE_SIZE` are stored in overflow pages.
     /// Returns the old value if updating, None if inserting.
     pub fn insert(&mut self, key: Key, value: Vec<u8>) -> Result<Option<Vec<u8>>, BTreeError> {
-        // For large values, write to overflow pages and store a reference
-        let stored_value = if value.len() > MAX_INLINE_VALUE_SIZE {
-            let overflow_ref = write_overflow(self.file, &value)?;
-            overflow_ref.to_bytes().to_vec()
-        } else {
-            value
-        };
+        let stored_value = self.store_value(value)?;
 
         // Find the leaf node
         let leaf_page_id = self.find_leaf(&key)?;
@@ -109,9 +141,10 @@ impl<'a> BTree<'a> {
 
         // Check if we need to split
         // Update case: check if larger value fits; Insert case: check if new entry fits
+        let page_size = self.file.page_size();
         let needs_split = leaf.get(&key).map_or_else(
-            || !leaf.can_fit(stored_value.len()),
-            |old_value| !leaf.can_fit_update(old_value.len(), stored_value.len()),
+            || !leaf.can_fit(stored_value.len(), page_size),
+            |old_value| !leaf.can_fit_update(old_value.len(), stored_value.len(), page_size),
         );
 
         if needs_split {
@@ -148,9 +181,77 @@ impl<'a> BTree<'a> {
         Ok(None)
     }
 
+    /// Insert or update many key-value pairs, in order.
+    ///
+    /// Leaves the tree as calling `insert` for each pair would. A pair whose
+    /// key belongs in the leaf the previous pair went to is added to that
+    /// leaf in memory, and the leaf is written once the pairs move on, so
+    /// pairs sorted by key cost about one leaf read and write per leaf
+    /// rather than per pair.
+    pub fn insert_many(
+        &mut self,
+        entries: impl IntoIterator<Item = (Key, Vec<u8>)>,
+    ) -> Result<(), BTreeError> {
+        let page_size = self.file.page_size();
+        // The leaf being filled, not yet written, with the keys it covers
+        let mut current: Option<(PageId, LeafNode, KeyRange)> = None;
+
+        for (key, value) in entries {
+            let stored_value = self.store_value(value)?;
+            let (leaf_page_id, mut leaf, range) = match current.take() {
+                Some(current) if current.2.contains(&key) => current,
+                previous => {
+                    if let Some((page_id, leaf, _)) = previous {
+                        self.write_leaf(page_id, &leaf)?;
+                    }
+                    let (page_id, range) = self.find_leaf_with_range(&key)?;
+                    (page_id, self.read_leaf(page_id)?, range)
+                }
+            };
+
+            let needs_split = leaf.get(&key).map_or_else(
+                || !leaf.can_fit(stored_value.len(), page_size),
+                |old_value| !leaf.can_fit_update(old_value.len(), stored_value.len(), page_size),
+            );
+            if needs_split {
+                // Splitting writes the leaf and moves the keys it covers
+                self.insert_with_split_internal(leaf_page_id, leaf, key, stored_value)?;
+                continue;
+            }
+
+            if let Some(old_stored) = leaf.insert(key, stored_value)
+                && let Some(overflow_ref) = OverflowRef::from_bytes(&old_stored)
+            {
+                free_overflow(self.file, &overflow_ref)?;
+            }
+            current = Some((leaf_page_id, leaf, range));
+        }
+
+        if let Some((page_id, leaf, _)) = current {
+            self.write_leaf(page_id, &leaf)?;
+        }
+        Ok(())
+    }
+
+    /// The bytes a leaf stores for `value`: the value itself, or for values
+    /// larger than `MAX_INLINE_VALUE_SIZE` a reference to the overflow pages
+    /// it is written to.
+    fn store_value(&mut self, value: Vec<u8>) -> Result<Vec<u8>, BTreeError> {
+        if value.len() > MAX_INLINE_VALUE_SIZE {
+            let overflow_ref = write_overflow(self.file, &value)?;
+            Ok(overflow_ref.to_bytes().to_vec())
+        } else {
+            Ok(value)
+        }
+    }
+
     /// Remove a key-value pair.
     ///
-    /// If the value was stored in overflow pages, those pages are freed.
+    /// If the value was stored in overflow pages, those pages are freed. A
+    /// non-root leaf left below `MIN_FILL_PERCENT` is merged with a sibling or
+    /// refilled from one (see `rebalance_leaf`), which may shrink the tree's
+    /// height and change `root_page`. Pages leaving the tree return to the
+    /// file's free list.
     /// Returns the removed value if found.
     pub fn remove(&mut self, key: &Key) -> Result<Option<Vec<u8>>, BTreeError> {
         let leaf_page_id = self.find_leaf(key)?;
@@ -160,17 +261,11 @@ impl<'a> BTree<'a> {
         let old_stored = leaf.remove(key);
 
         if let Some(stored_bytes) = old_stored {
-            // Write back the modified leaf
-            let mut page = self
-                .file
-                .buffer_pool()
-                .lease_page_zeroed()
-                .ok_or(FileError::BufferPoolExhausted)?;
-            leaf.write_to_page(&mut page);
-            self.file.write_page(leaf_page_id, &page)?;
-
-            // Note: We don't handle underflow/merging in Phase 1
-            // This is acceptable for append-heavy workloads
+            if leaf.header.parent_page != 0 && leaf.is_underfull(self.file.page_size()) {
+                self.rebalance_leaf(leaf_page_id, leaf)?;
+            } else {
+                self.write_leaf(leaf_page_id, &leaf)?;
+            }
 
             // If the removed value was an overflow reference, free those pages
             // and return the actual value
@@ -186,9 +281,168 @@ impl<'a> BTree<'a> {
         Ok(None)
     }
 
+    /// Restore an underfull non-root leaf.
+    ///
+    /// The leaf is paired with its right sibling under the same parent, or its
+    /// left one if it is the last child. If the pair fits in one page, the
+    /// right node is merged into the left and freed, and the parent, which
+    /// loses their separator key, is rebalanced in turn. Otherwise entries
+    /// move across until the two are even, and the separator is updated.
+    fn rebalance_leaf(&mut self, page_id: PageId, leaf: LeafNode) -> Result<(), BTreeError> {
+        let parent_id = leaf.header.parent_page;
+        let mut parent = self.read_internal(parent_id)?;
+        let Some(separator) = sibling_separator(&parent, page_id) else {
+            return self.write_leaf(page_id, &leaf);
+        };
+        let (left_id, right_id) = (parent.children[separator], parent.children[separator + 1]);
+        let (mut left, mut right) = if left_id == page_id {
+            (leaf, self.read_leaf(right_id)?)
+        } else {
+            (self.read_leaf(left_id)?, leaf)
+        };
+
+        if left.can_merge(&right, self.file.page_size()) {
+            let next_id = right.header.next_leaf;
+            left.merge(right);
+            self.write_leaf(left_id, &left)?;
+            if next_id != 0 {
+                let mut next = self.read_leaf(next_id)?;
+                next.header.prev_leaf = left_id;
+                self.write_leaf(next_id, &next)?;
+            }
+            self.free_page(right_id)?;
+
+            parent.keys.remove(separator);
+            parent.children.remove(separator + 1);
+            self.rebalance_internal(parent_id, parent)
+        } else {
+            parent.keys[separator] = left.redistribute(&mut right);
+            self.write_leaf(left_id, &left)?;
+            self.write_leaf(right_id, &right)?;
+            self.write_internal(parent_id, &parent)
+        }
+    }
+
+    /// Write back an internal node that lost a key, restoring it if needed.
+    ///
+    /// A root left with a single child is freed and the child becomes the
+    /// root, shrinking the tree by one level. A non-root node below
+    /// `MIN_FILL_PERCENT` is merged with or refilled from a sibling as in
+    /// `rebalance_leaf`, with the separator moving through the parent, and
+    /// children that change nodes get their parent pointers updated.
+    fn rebalance_internal(
+        &mut self,
+        page_id: PageId,
+        node: InternalNode,
+    ) -> Result<(), BTreeError> {
+        let parent_id = node.header.parent_page;
+        if parent_id == 0 {
+            if node.keys.is_empty() {
+                let child = node.children[0];
+                self.update_parent_pointer(child, 0)?;
+                self.free_page(page_id)?;
+                self.root_page = child;
+                return Ok(());
+            }
+            return self.write_internal(page_id, &node);
+        }
+        let page_size = self.file.page_size();
+        if !node.is_underfull(page_size) {
+            return self.write_internal(page_id, &node);
+        }
+
+        let mut parent = self.read_internal(parent_id)?;
+        let Some(separator) = sibling_separator(&parent, page_id) else {
+            return self.write_internal(page_id, &node);
+        };
+        let (left_id, right_id) = (parent.children[separator], parent.children[separator + 1]);
+        let (mut left, mut right) = if left_id == page_id {
+            (node, self.read_internal(right_id)?)
+        } else {
+            (self.read_internal(left_id)?, node)
+        };
+        let (left_len, right_len) = (left.children.len(), right.children.len());
+
+        if left.can_merge(&right, page_size) {
+            left.merge(parent.keys[separator], right);
+            for &child in &left.children[left_len..] {
+                self.update_parent_pointer(child, left_id)?;
+            }
+            self.write_internal(left_id, &left)?;
+            self.free_page(right_id)?;
+
+            parent.keys.remove(separator);
+            parent.children.remove(separator + 1);
+            self.rebalance_internal(parent_id, parent)
+        } else {
+            parent.keys[separator] = left.redistribute(parent.keys[separator], &mut right);
+            if left.children.len() > left_len {
+                for &child in &left.children[left_len..] {
+                    self.update_parent_pointer(child, left_id)?;
+                }
+            } else {
+                for &child in &right.children[..right.children.len() - right_len] {
+                    self.update_parent_pointer(child, right_id)?;
+                }
+            }
+            self.write_internal(left_id, &left)?;
+            self.write_internal(right_id, &right)?;
+            self.write_internal(parent_id, &parent)
+        }
+    }
+
+    /// Read a leaf node.
+    fn read_leaf(&mut self, page_id: PageId) -> Result<LeafNode, BTreeError> {
+        let page = self.file.read_page(page_id)?;
+        Ok(LeafNode::from_page(&page)?)
+    }
+
+    /// Read an internal node.
+    fn read_internal(&mut self, page_id: PageId) -> Result<InternalNode, BTreeError> {
+        let page = self.file.read_page(page_id)?;
+        Ok(InternalNode::from_page(&page)?)
+    }
+
+    /// Write a leaf node to its page.
+    fn write_leaf(&mut self, page_id: PageId, leaf: &LeafNode) -> Result<(), BTreeError> {
+        let mut page = self
+            .file
+            .buffer_pool()
+            .lease_page_zeroed()
+            .ok_or(FileError::BufferPoolExhausted)?;
+        leaf.write_to_page(&mut page);
+        self.file.write_page(page_id, &page)?;
+        Ok(())
+    }
+
+    /// Write an internal node to its page.
+    fn write_internal(&mut self, page_id: PageId, node: &InternalNode) -> Result<(), BTreeError> {
+        let mut page = self
+            .file
+            .buffer_pool()
+            .lease_page_zeroed()
+            .ok_or(FileError::BufferPoolExhausted)?;
+        node.write_to_page(&mut page);
+        self.file.write_page(page_id, &page)?;
+        Ok(())
+    }
+
+    /// Return a page that left the tree to the file's free list.
+    fn free_page(&mut self, page_id: PageId) -> Result<(), BTreeError> {
+        self.file.free_pages(page_id, 1)?;
+        Ok(())
+    }
+
     /// Find the leaf page that should contain the given key.
     fn find_leaf(&mut self, key: &Key) -> Result<PageId, BTreeError> {
+        Ok(self.find_leaf_with_range(key)?.0)
+    }
+
+    /// Find the leaf page that should contain the given key, with the range
+    /// of keys that belong in it.
+    fn find_leaf_with_range(&mut self, key: &Key) -> Result<(PageId, KeyRange), BTreeError> {
         let mut current_page_id = self.root_page;
+        let mut range = KeyRange::default();
 
         loop {
             let page = self.file.read_page(current_page_id)?;
@@ -196,10 +450,18 @@ impl<'a> BTree<'a> {
                 NodeHeader::from_page(&page).ok_or(BTreeError::Node(NodeError::InvalidHeader))?;
 
             match header.node_type {
-                NodeType::Leaf => return Ok(current_page_id),
+                NodeType::Leaf => return Ok((current_page_id, range)),
                 NodeType::Internal => {
                     let node = InternalNode::from_page(&page)?;
                     let child_idx = node.find_child_index(key);
+                    // A child's separators bound it at least as tightly as
+                    // its parent's
+                    if let Some(lower) = child_idx.checked_sub(1) {
+                        range.lower = Some(node.keys[lower]);
+                    }
+                    if let Some(upper) = node.keys.get(child_idx) {
+                        range.upper = Some(*upper);
+                    }
                     current_page_id = node.children[child_idx];
                 }
             }
@@ -215,7 +477,7 @@ impl<'a> BTree<'a> {
         stored_value: Vec<u8>,
     ) -> Result<Option<Vec<u8>>, BTreeError> {
         // Insert into the leaf first (it may overflow temporarily)
-        let old_value = leaf.insert(key, stored_value);
+        let old_stored = leaf.insert(key, stored_value);
 
         // Split the leaf
         let (split_key, mut right_leaf) = leaf.split();
@@ -268,7 +530,18 @@ impl<'a> BTree<'a> {
             leaf.header.parent_page,
         )?;
 
-        Ok(old_value)
+        // As in the non-split path, free a replaced overflow value and
+        // return the actual old value rather than its reference
+        if let Some(old_bytes) = old_stored {
+            if let Some(overflow_ref) = OverflowRef::from_bytes(&old_bytes) {
+                let old_value = read_overflow(self.file, &overflow_ref)?;
+                free_overflow(self.file, &overflow_ref)?;
+                return Ok(Some(old_value));
+            }
+            return Ok(Some(old_bytes));
+        }
+
+        Ok(None)
     }
 
     /// Insert a new key into a parent node after a child split.
@@ -287,7 +560,7 @@ impl<'a> BTree<'a> {
         let page = self.file.read_page(parent_page_id)?;
         let mut parent = InternalNode::from_page(&page)?;
 
-        if parent.is_full() {
+        if parent.is_full(self.file.page_size()) {
             // Need to split the internal node
             parent.insert(key, right_child);
             let (median_key, right_parent) = parent.split();
@@ -452,6 +725,22 @@ impl<'a> BTree<'a> {
         })
     }
 
+    /// Create an iterator starting from a given key, consuming the tree so the
+    /// iterator borrows the file for the tree's whole lifetime.
+    pub fn into_iter_from(mut self, start_key: &Key) -> Result<BTreeIterator<'a>, BTreeError> {
+        let leaf_page_id = self.find_leaf(start_key)?;
+        let page = self.file.read_page(leaf_page_id)?;
+        let leaf = LeafNode::from_page(&page)?;
+        let start_index = leaf.find_index(start_key).unwrap_or_else(|i| i);
+
+        Ok(BTreeIterator {
+            file: self.file,
+            current_page_id: leaf_page_id,
+            current_index: start_index,
+            current_entries: Some(leaf.entries),
+        })
+    }
+
     /// Count the total number of entries in the tree.
     pub fn count(&mut self) -> Result<usize, BTreeError> {
         let mut count = 0;
@@ -488,28 +777,53 @@ impl<'a> BTree<'a> {
     }
 }
 
+/// Index in `parent.keys` of the key separating `child` from the sibling it
+/// is paired with for rebalancing: its right sibling, or its left one if it
+/// is the last child.
+///
+/// Returns `None` if `child` has no sibling or is not a child of `parent`.
+fn sibling_separator(parent: &InternalNode, child: PageId) -> Option<usize> {
+    let index = parent.children.iter().position(|&c| c == child)?;
+    if parent.children.len() < 2 {
+        None
+    } else if index + 1 < parent.children.len() {
+        Some(index)
+    } else {
+        Some(index - 1)
+    }
+}
+
 /// Read-only B-tree accessor for concurrent snapshot reads.
 ///
 /// This struct provides read-only access to the B-tree using position-independent
 /// reads (`read_page_at`), which allows concurrent reads from multiple threads
 /// without requiring mutable access to the database file.
-#[cfg(unix)]
 pub struct BTreeReader<'a> {
     file: &'a DatabaseFile,
     root_page: PageId,
 }
 
-#[cfg(unix)]
 impl<'a> BTreeReader<'a> {
     /// Create a new read-only B-tree accessor.
     ///
     /// # Pre-conditions
-    /// - `root_page` must be a valid B-tree root (not 0)
+    /// - `root_page` must be a valid B-tree root, or 0 for a tree that has not
+    ///   been created yet (which reads as empty)
     #[must_use]
     pub const fn new(file: &'a DatabaseFile, root_page: PageId) -> Self {
         Self { file, root_page }
     }
 
+    /// An iterator that yields no entries, for a tree that has not been created.
+    const fn empty_iterator(&self) -> BTreeReaderIterator<'a> {
+        BTreeReaderIterator {
+            file: self.file,
+            current_page_id: 0,
+            current_index: 0,
+            current_entries: None,
+        }
+    }
+
     /// Get the root page ID.
     #[must_use]
     pub const fn root_page(&self) -> PageId {
@@ -520,6 +834,10 @@ impl<'a> BTreeReader<'a> {
     ///
     /// If the value is stored in overflow pages, it will be read and returned.
     pub fn get(&self, key: &Key) -> Result<Option<Vec<u8>>, BTreeError> {
+        if self.root_page == 0 {
+            return Ok(None);
+        }
+
         let leaf_page_id = self.find_leaf(key)?;
         let page = self.file.read_page_at(leaf_page_id)?;
         let leaf = LeafNode::from_page(&page)?;
@@ -540,6 +858,33 @@ impl<'a> BTreeReader<'a> {
         }
     }
 
+    /// Look up the first `len` bytes of a value by key.
+    ///
+    /// Unlike `get`, reads only the overflow pages holding the prefix, so
+    /// callers that need a value's header do not pay for the whole value.
+    pub fn get_prefix(&self, key: &Key, len: usize) -> Result<Option<Vec<u8>>, BTreeError> {
+        if self.root_page == 0 {
+            return Ok(None);
+        }
+
+        let leaf_page_id = self.find_leaf(key)?;
+        let page = self.file.read_page_at(leaf_page_id)?;
+        let leaf = LeafNode::from_page(&page)?;
+
+        match leaf.get(key) {
+            Some(stored_value) => {
+                if let Some(overflow_ref) = OverflowRef::from_bytes(stored_value) {
+                    let prefix = read_overflow_prefix_at(self.file, &overflow_ref, len)?;
+                    Ok(Some(prefix))
+                } else {
+                    let end = len.min(stored_value.len());
+                    Ok(Some(stored_value[..end].to_vec()))
+                }
+            }
+            None => Ok(None),
+        }
+    }
+
     /// Find the leaf page that should contain the given key.
     fn find_leaf(&self, key: &Key) -> Result<PageId, BTreeError> {
         let mut current_page_id = self.root_page;
@@ -561,7 +906,11 @@ impl<'a> BTreeReader<'a> {
     }
 
     /// Create a cursor over all entries in key order.
-    pub fn cursor(&self) -> Result<BTreeReaderIterator<'_>, BTreeError> {
+    pub fn cursor(&self) -> Result<BTreeReaderIterator<'a>, BTreeError> {
+        if self.root_page == 0 {
+            return Ok(self.empty_iterator());
+        }
+
         // Find the leftmost leaf
         let mut current_page_id = self.root_page;
 
@@ -588,7 +937,11 @@ impl<'a> BTreeReader<'a> {
     }
 
     /// Create an iterator starting from a given key.
-    pub fn iter_from(&self, start_key: &Key) -> Result<BTreeReaderIterator<'_>, BTreeError> {
+    pub fn iter_from(&self, start_key: &Key) -> Result<BTreeReaderIterator<'a>, BTreeError> {
+        if self.root_page == 0 {
+            return Ok(self.empty_iterator());
+        }
+
         let leaf_page_id = self.find_leaf(start_key)?;
         let page = self.file.read_page_at(leaf_page_id)?;
         let leaf = LeafNode::from_page(&page)?;
@@ -606,6 +959,10 @@ impl<'a> BTreeReader<'a> {
 
     /// Count the total number of entries in the tree.
     pub fn count(&self) -> Result<usize, BTreeError> {
+        if self.root_page == 0 {
+            return Ok(0);
+        }
+
         let mut count = 0;
         let mut current_page_id = self.root_page;
 
@@ -643,7 +1000,6 @@ impl<'a> BTreeReader<'a> {
 /// Read-only iterator over B-tree entries.
 ///
 /// Uses position-independent reads for concurrent access.
-#[cfg(unix)]
 pub struct BTreeReaderIterator<'a> {
     file: &'a DatabaseFile,
     current_page_id: PageId,
@@ -651,7 +1007,6 @@ pub struct BTreeReaderIterator<'a> {
     current_entries: Option<Vec<LeafEntry>>,
 }
 
-#[cfg(unix)]
 impl BTreeReaderIterator<'_> {
     /// Get the next entry.
     pub fn next_entry(&mut self) -> Result<Option<(Key, Vec<u8>)>, BTreeError> {
@@ -691,6 +1046,18 @@ impl BTreeReaderIterator<'_> {
             self.current_entries = None;
         }
     }
+
+    /// Get the next entry, reading its value from overflow pages if it is
+    /// stored there. `next_entry` yields the overflow reference as stored.
+    pub fn next_resolved_entry(&mut self) -> Result<Option<(Key, Vec<u8>)>, BTreeError> {
+        let Some((key, value)) = self.next_entry()? else {
+            return Ok(None);
+        };
+        match OverflowRef::from_bytes(&value) {
+            Some(overflow_ref) => Ok(Some((key, read_overflow_at(self.file, &overflow_ref)?))),
+            None => Ok(Some((key, value))),
+        }
+    }
 }
 
 /// Iterator over B-tree entries.
@@ -740,6 +1107,18 @@ impl BTreeIterator<'_> {
             self.current_entries = None;
         }
     }
+
+    /// Get the next entry, reading its value from overflow pages if it is
+    /// stored there. `next_entry` yields the overflow reference as stored.
+    pub fn next_resolved_entry(&mut self) -> Result<Option<(Key, Vec<u8>)>, BTreeError> {
+        let Some((key, value)) = self.next_entry()? else {
+            return Ok(None);
+        };
+        match OverflowRef::from_bytes(&value) {
+            Some(overflow_ref) => Ok(Some((key, read_overflow(self.file, &overflow_ref)?))),
+            None => Ok(Some((key, value))),
+        }
+    }
 }
 
 /// Errors that can occur during B-tree operations.
@@ -797,6 +1176,7 @@ mod tests {
     use crate::storage::btree::node::make_key;
     use crate::storage::buffer_pool::BufferPool;
     use crate::storage::file::DatabaseFile;
+    use crate::storage::page::PageType;
     use crate::types::{AttributeId, EntityId};
     use std::sync::Arc;
     use tempfile::tempdir;
@@ -942,6 +1322,63 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_btree_insert_many_matches_inserts() {
+        let (_dir, path) = create_test_db();
+        let pool = test_pool();
+        let mut file = DatabaseFile::create(&path, pool).expect("create db");
+        let key = |i: u16| {
+            let mut entity_bytes = [0u8; 16];
+            entity_bytes[0..2].copy_from_slice(&i.to_be_bytes());
+            make_key(&EntityId(entity_bytes), &AttributeId::default())
+        };
+        let value = |i: u16, round: u16| -> Vec<u8> {
+            if i.is_multiple_of(50) {
+                vec![u8::try_from(round).expect("round"); 2 * MAX_INLINE_VALUE_SIZE]
+            } else {
+                format!("value_{i}_{round}").into_bytes()
+            }
+        };
+
+        let mut tree = BTree::new(&mut file, 0).expect("create tree");
+        // Sorted runs that split leaves, then an unsorted batch that
+        // overwrites every key, including the overflow values
+        let n = 800u16;
+        tree.insert_many((0..n).map(|i| (key(i), value(i, 0))))
+            .expect("insert sorted");
+        tree.insert_many(
+            (0..n)
+                .rev()
+                .step_by(2)
+                .chain((0..n).step_by(2))
+                .map(|i| (key(i), value(i, 1))),
+        )
+        .expect("insert unsorted");
+        // A repeated key keeps its last value
+        tree.insert_many([(key(3), value(3, 2)), (key(3), value(3, 3))])
+            .expect("insert repeated");
+
+        let keys: Vec<Key> = (0..n + 10).map(key).collect();
+        let values = tree.get_many(&keys).expect("get many");
+        for (i, actual) in (0..n + 10).zip(values) {
+            let expected = tree.get(&key(i)).expect("get");
+            assert_eq!(actual, expected, "get_many mismatch at {i}");
+        }
+
+        assert_eq!(tree.count().expect("count"), usize::from(n));
+        for i in 0..n {
+            let round = if i == 3 { 3 } else { 1 };
+            let actual = tree.get(&key(i)).expect("get");
+            assert_eq!(actual, Some(value(i, round)), "mismatch at {i}");
+        }
+        let mut cursor = tree.cursor().expect("cursor");
+        let mut previous = None;
+        while let Some((key, _)) = cursor.next_entry().expect("next") {
+            assert!(previous < Some(key), "keys out of order");
+            previous = Some(key);
+        }
+    }
+
     #[test]
     fn test_btree_iter_from() {
         let (_dir, path) = create_test_db();
@@ -1102,4 +1539,246 @@ mod tests {
         let retrieved = tree.get(&key).expect("get very large");
         assert_eq!(retrieved, Some(very_large_value));
     }
+
+    #[test]
+    fn test_btree_update_with_split_frees_overflow() {
+        let (_dir, path) = create_test_db();
+        let pool = test_pool();
+        let mut file = DatabaseFile::create(&path, pool).expect("create db");
+
+        let mut tree = BTree::new(&mut file, 0).expect("create tree");
+        let root = tree.root_page();
+
+        // An overflow value followed by inline values that nearly fill the leaf
+        let key = make_key(&EntityId([1u8; 16]), &AttributeId([1u8; 16]));
+        let large_value = vec![7u8; 2 * MAX_INLINE_VALUE_SIZE];
+        tree.insert(key, large_value.clone()).expect("insert large");
+        for i in 2..9u8 {
+            let filler = make_key(&EntityId([i; 16]), &AttributeId([i; 16]));
+            tree.insert(filler, vec![i; 1000]).expect("insert filler");
+        }
+        assert_eq!(tree.root_page(), root, "leaf should not have split yet");
+
+        // Replacing the 12-byte overflow reference with an inline value splits the leaf
+        let old_value = tree
+            .insert(key, vec![8u8; MAX_INLINE_VALUE_SIZE])
+            .expect("update");
+        assert_ne!(tree.root_page(), root, "update should split the leaf");
+        assert_eq!(old_value, Some(large_value));
+
+        let file = tree.file_mut();
+        let mut overflow_pages = 0;
+        let mut free_pages = 0;
+        for page_id in 1..file.total_pages() {
+            let page = file.read_page(page_id).expect("read page");
+            match PageType::try_from(page.read_u8(0)) {
+                Ok(PageType::Overflow) => overflow_pages += 1,
+                Ok(PageType::Free) => free_pages += 1,
+                _ => {}
+            }
+        }
+        assert_eq!(overflow_pages, 0);
+        assert!(free_pages > 0);
+    }
+
+    /// Key for entry `i`, in the same order as `i`.
+    fn numbered_key(i: u16) -> Key {
+        let mut entity_bytes = [0u8; 16];
+        entity_bytes[0..2].copy_from_slice(&i.to_be_bytes());
+        make_key(&EntityId(entity_bytes), &AttributeId::default())
+    }
+
+    /// Count the B-tree node pages in the file.
+    fn count_node_pages(file: &mut DatabaseFile) -> usize {
+        let mut node_pages = 0;
+        for page_id in 1..file.total_pages() {
+            let page = file.read_page(page_id).expect("read page");
+            if matches!(
+                PageType::try_from(page.read_u8(0)),
+                Ok(PageType::BTreeLeaf | PageType::BTreeInternal)
+            ) {
+                node_pages += 1;
+            }
+        }
+        node_pages
+    }
+
+    /// Check the parent pointers and key counts of the subtree at `page_id`,
+    /// collecting its leaves in key order.
+    fn check_subtree(
+        file: &mut DatabaseFile,
+        page_id: PageId,
+        parent: PageId,
+        leaves: &mut Vec<PageId>,
+    ) {
+        let page = file.read_page(page_id).expect("read page");
+        if PageType::try_from(page.read_u8(0)) == Ok(PageType::BTreeLeaf) {
+            let leaf = LeafNode::from_page(&page).expect("leaf");
+            assert_eq!(leaf.header.parent_page, parent, "parent of leaf {page_id}");
+            leaves.push(page_id);
+            return;
+        }
+        let node = InternalNode::from_page(&page).expect("internal node");
+        drop(page);
+        assert_eq!(node.header.parent_page, parent, "parent of node {page_id}");
+        assert_eq!(node.children.len(), node.keys.len() + 1);
+        assert!(node.keys.is_sorted(), "keys of node {page_id}");
+        for &child in &node.children {
+            check_subtree(file, child, page_id, leaves);
+        }
+    }
+
+    /// Check the tree's structure, including that the leaf chain links the
+    /// leaves in key order in both directions.
+    fn check_tree(tree: &mut BTree<'_>) {
+        let root = tree.root_page();
+        let file = tree.file_mut();
+        let mut leaves = Vec::new();
+        check_subtree(file, root, 0, &mut leaves);
+        let links: Vec<(PageId, PageId)> = leaves
+            .iter()
+            .map(|&page_id| {
+                let page = file.read_page(page_id).expect("read page");
+                let leaf = LeafNode::from_page(&page).expect("leaf");
+                (leaf.header.prev_leaf, leaf.header.next_leaf)
+            })
+            .collect();
+        for (i, &(prev, next)) in links.iter().enumerate() {
+            let expected_prev = if i == 0 { 0 } else { leaves[i - 1] };
+            let expected_next = leaves.get(i + 1).copied().unwrap_or(0);
+            assert_eq!(prev, expected_prev, "prev of leaf {}", leaves[i]);
+            assert_eq!(next, expected_next, "next of leaf {}", leaves[i]);
+        }
+    }
+
+    #[test]
+    fn test_btree_remove_merges_nodes() {
+        let (_dir, path) = create_test_db();
+        let pool = test_pool();
+        let mut file = DatabaseFile::create(&path, pool).expect("create db");
+
+        let mut tree = BTree::new(&mut file, 0).expect("create tree");
+        let n: u16 = 10_000;
+        for i in 0..n {
+            tree.insert(numbered_key(i), vec![(i % 200) as u8; 200])
+                .expect("insert");
+        }
+        let before = count_node_pages(tree.file_mut());
+
+        for i in (0..n).filter(|i| i % 10 != 0) {
+            let removed = tree.remove(&numbered_key(i)).expect("remove");
+            assert_eq!(removed, Some(vec![(i % 200) as u8; 200]), "remove {i}");
+        }
+        check_tree(&mut tree);
+        let after = count_node_pages(tree.file_mut());
+        assert!(
+            after * 5 < before,
+            "node pages should drop substantially, from {before} to {after}"
+        );
+        // Every page that left the tree is on the free list
+        assert_eq!(tree.file_mut().free_page_count(), (before - after) as u64);
+
+        let mut cursor = tree.cursor().expect("cursor");
+        for i in (0..n).step_by(10) {
+            let (key, value) = cursor.next_entry().expect("next").expect("entry");
+            assert_eq!(key, numbered_key(i));
+            assert_eq!(value, vec![(i % 200) as u8; 200]);
+        }
+        assert!(cursor.next_entry().expect("next").is_none());
+    }
+
+    #[test]
+    fn test_btree_remove_all_collapses_root() {
+        let (_dir, path) = create_test_db();
+        let pool = test_pool();
+        let mut file = DatabaseFile::create(&path, pool).expect("create db");
+
+        let mut tree = BTree::new(&mut file, 0).expect("create tree");
+        let n: u16 = 10_000;
+        for i in 0..n {
+            tree.insert(numbered_key(i), vec![1u8; 200])
+                .expect("insert");
+        }
+        for i in 0..n {
+            assert!(tree.remove(&numbered_key(i)).expect("remove").is_some());
+        }
+
+        check_tree(&mut tree);
+        assert_eq!(count_node_pages(tree.file_mut()), 1);
+        assert_eq!(tree.count().expect("count"), 0);
+
+        // The collapsed tree still accepts inserts
+        tree.insert(numbered_key(7), b"again".to_vec())
+            .expect("insert");
+        assert_eq!(
+            tree.get(&numbered_key(7)).expect("get"),
+            Some(b"again".to_vec())
+        );
+    }
+
+    #[test]
+    fn test_btree_remove_in_scrambled_order() {
+        let (_dir, path) = create_test_db();
+        let pool = test_pool();
+        let mut file = DatabaseFile::create(&path, pool).expect("create db");
+
+        let mut tree = BTree::new(&mut file, 0).expect("create tree");
+        // 7919 is coprime with 3000, so these visit every key once
+        let n: u16 = 3000;
+        let scrambled = |step: u32| (0..u32::from(n)).map(move |i| (i * step % 3000) as u16);
+        for i in scrambled(7919) {
+            tree.insert(numbered_key(i), i.to_be_bytes().repeat(60))
+                .expect("insert");
+        }
+
+        // Mixed fill levels make some rebalances redistribute rather than merge
+        let mut remaining: std::collections::BTreeSet<u16> = (0..n).collect();
+        for (removed, i) in scrambled(1237).enumerate() {
+            assert_eq!(
+                tree.remove(&numbered_key(i)).expect("remove"),
+                Some(i.to_be_bytes().repeat(60))
+            );
+            remaining.remove(&i);
+            if removed % 500 == 0 {
+                check_tree(&mut tree);
+                let mut cursor = tree.cursor().expect("cursor");
+                for &j in &remaining {
+                    let (key, _) = cursor.next_entry().expect("next").expect("entry");
+                    assert_eq!(key, numbered_key(j));
+                }
+                assert!(cursor.next_entry().expect("next").is_none());
+            }
+        }
+        check_tree(&mut tree);
+        assert_eq!(tree.count().expect("count"), 0);
+    }
+
+    #[test]
+    fn test_btree_reader_uncreated_tree_is_empty() {
+        let (_dir, path) = create_test_db();
+        let pool = test_pool();
+        let file = DatabaseFile::create(&path, pool).expect("create db");
+
+        let reader = BTreeReader::new(&file, 0);
+        let key = make_key(&EntityId([1u8; 16]), &AttributeId([1u8; 16]));
+
+        assert_eq!(reader.get(&key).expect("get"), None);
+        assert_eq!(reader.count().expect("count"), 0);
+        assert!(
+            reader
+                .cursor()
+                .expect("cursor")
+                .next_entry()
+                .expect("next")
+                .is_none()
+        );
+        assert!(
+            reader
+                .iter_from(&key)
+                .expect("iter_from")
+                .next_entry()
+                .expect("next")
+                .is_none()
+        );
+    }
 }
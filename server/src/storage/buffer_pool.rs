@@ -1,7 +1,11 @@
 //! Buffer pool for pre-allocated page buffers.
 //!
 //! The buffer pool reduces memory allocation overhead by maintaining a fixed
-//! pool of 8KB page buffers that are leased out and returned automatically.
+//! pool of page buffers that are leased out and returned automatically. The
+//! buffers are 8KB unless the pool is created with `with_page_size`, and a
+//! pool serves files of any page size up to that of its buffers.
+//! Buffers that are not leased double as a cache of clean page images, so
+//! rereading a recently used page does not touch the disk.
 //!
 //! # Design
 //!
@@ -9,21 +13,167 @@
 //! - Uses a free list (Vec) for O(1) lease/return
 //! - Returns buffers automatically via RAII (Drop trait on Page)
 //! - Thread-safe: uses Mutex for internal synchronization
+//! - Caches page images by file and page ID. When the free list is empty, a
+//!   lease evicts the least recently used cached page and reuses its buffer.
+//!
+//! # Eviction
+//!
+//! Only cached images are ever evicted, and a cached image always matches the
+//! page on disk: files add images after reading or writing a page, never for
+//! pages modified in memory. A page being modified is a leased `Page`, which
+//! is never evicted, so eviction never loses a write.
+//!
+//! # Exhaustion
+//!
+//! When every buffer is leased at once, usually by a burst of concurrent
+//! operations, a lease waits for one to be returned, retrying a bounded
+//! number of times before failing. Callers surface the failure as
+//! `BufferPoolExhausted`, which is transient: the operation may be retried
+//! once other operations finish.
 //!
 //! # Invariants
 //!
 //! - Pool capacity is fixed after construction
 //! - All returned buffers must have come from this pool (enforced by type system)
-//! - Free list size + leased count == capacity
+//! - Free list size + cached pages + leased count == capacity
+//! - Every buffer is `page_size` bytes
 
-use std::sync::{Arc, Mutex};
+use std::collections::{BTreeMap, HashMap};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Condvar, Mutex};
+use std::time::Duration;
 
-use crate::storage::page::{PAGE_SIZE, Page};
+use crate::storage::page::{PAGE_SIZE, Page, PageId, is_supported_page_size};
 
 /// Default buffer pool capacity in pages (262,144 pages = 2GB).
 /// This is sized for a shared pool across all open databases.
 pub const DEFAULT_POOL_CAPACITY: usize = 262_144;
 
+/// Smallest buffer pool capacity the server accepts.
+///
+/// Writes hold the pages on a B-tree path, and their splits, leased at once,
+/// so a pool much smaller than this fails writes to deep trees.
+pub const MIN_POOL_CAPACITY: usize = 64;
+
+/// Times a lease waits for a buffer to be returned before giving up.
+pub const LEASE_RETRY_ATTEMPTS: u32 = 5;
+
+/// How long each lease retry waits for a buffer to be returned.
+pub const LEASE_RETRY_WAIT: Duration = Duration::from_millis(10);
+
+/// Identifies a page image in the cache.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct CacheKey {
+    /// The file the page belongs to, from `BufferPool::register_file`.
+    pub file_id: u64,
+    /// The page within the file.
+    pub page_id: PageId,
+}
+
+/// A cached page image.
+struct CachedPage {
+    buffer: Box<[u8]>,
+    /// Position in `PageCache::lru`.
+    last_used: u64,
+}
+
+/// Clean page images held in buffers that are not leased.
+///
+/// # Invariants
+/// - `lru` has exactly one entry per entry of `pages`, keyed by its `last_used`
+#[derive(Default)]
+struct PageCache {
+    pages: HashMap<CacheKey, CachedPage>,
+    /// Keys by last use, least recent first.
+    lru: BTreeMap<u64, CacheKey>,
+    /// Source of `last_used` values.
+    clock: u64,
+}
+
+impl PageCache {
+    const fn tick(&mut self) -> u64 {
+        self.clock += 1;
+        self.clock
+    }
+
+    /// Copy a cached image into `dest` and mark it most recently used.
+    fn read(&mut self, key: CacheKey, dest: &mut [u8]) -> bool {
+        let now = self.tick();
+        let Some(page) = self.pages.get_mut(&key) else {
+            return false;
+        };
+        dest.copy_from_slice(&page.buffer[..dest.len()]);
+        self.lru.remove(&page.last_used);
+        page.last_used = now;
+        self.lru.insert(now, key);
+        true
+    }
+
+    /// Remove an image, returning its buffer.
+    fn remove(&mut self, key: CacheKey) -> Option<Box<[u8]>> {
+        let page = self.pages.remove(&key)?;
+        self.lru.remove(&page.last_used);
+        Some(page.buffer)
+    }
+
+    /// Remove the least recently used image, returning its buffer.
+    fn evict(&mut self) -> Option<Box<[u8]>> {
+        let (_, key) = self.lru.pop_first()?;
+        self.pages.remove(&key).map(|page| page.buffer)
+    }
+
+    /// Remove the images matching `predicate`, returning their buffers.
+    fn remove_where(&mut self, predicate: impl Fn(&CacheKey) -> bool) -> Vec<Box<[u8]>> {
+        let lru = &mut self.lru;
+        self.pages
+            .extract_if(|key, _| predicate(key))
+            .map(|(_, page)| {
+                lru.remove(&page.last_used);
+                page.buffer
+            })
+            .collect()
+    }
+}
+
+/// Buffers not currently leased.
+struct PoolState {
+    /// Free buffers available for leasing.
+    free_list: Vec<Box<[u8]>>,
+    /// Buffers holding cached page images, also available for leasing.
+    cache: PageCache,
+}
+
+impl PoolState {
+    /// Number of buffers not leased.
+    fn idle(&self) -> usize {
+        self.free_list.len() + self.cache.pages.len()
+    }
+
+    /// Take a free buffer, or else evict the least recently used cached page.
+    fn take_idle(&mut self) -> Option<Box<[u8]>> {
+        self.free_list.pop().or_else(|| self.cache.evict())
+    }
+
+    /// Store a page image in the buffer holding its earlier image, a free
+    /// buffer, or the least recently used cached buffer, in that order.
+    fn cache_page(&mut self, key: CacheKey, bytes: &[u8]) {
+        let buffer = self
+            .cache
+            .remove(key)
+            .or_else(|| self.free_list.pop())
+            .or_else(|| self.cache.evict());
+        let Some(mut buffer) = buffer else {
+            return;
+        };
+        buffer[..bytes.len()].copy_from_slice(bytes);
+        let last_used = self.cache.tick();
+        self.cache.lru.insert(last_used, key);
+        self.cache
+            .pages
+            .insert(key, CachedPage { buffer, last_used });
+    }
+}
+
 /// A buffer pool that pre-allocates page buffers.
 ///
 /// # Pre-conditions
@@ -34,17 +184,22 @@ pub const DEFAULT_POOL_CAPACITY: usize = 262_144;
 /// - Free list contains `capacity` buffers
 ///
 /// # Invariants
-/// - `free_list.len() + leased_count == capacity`
+/// - `free_list.len() + cached pages + leased_count == capacity`
 pub struct BufferPool {
-    /// Free buffers available for leasing.
-    /// Invariant: all buffers are `PAGE_SIZE` bytes.
-    free_list: Mutex<Vec<Box<[u8; PAGE_SIZE]>>>,
+    state: Mutex<PoolState>,
+    /// Signaled when a buffer is returned, waking waiting leases.
+    returned: Condvar,
     /// Total capacity (for assertions).
     capacity: usize,
+    /// Size in bytes of every buffer.
+    page_size: usize,
+    /// Source of `register_file` IDs.
+    next_file_id: AtomicU64,
 }
 
 impl BufferPool {
-    /// Create a new buffer pool with the given capacity.
+    /// Create a new buffer pool with the given capacity, of buffers of the
+    /// default `PAGE_SIZE`.
     ///
     /// # Pre-conditions
     /// - `capacity` > 0
@@ -57,43 +212,87 @@ impl BufferPool {
     /// Panics if capacity is 0.
     #[must_use]
     pub fn new(capacity: usize) -> Arc<Self> {
+        Self::with_page_size(capacity, PAGE_SIZE)
+    }
+
+    /// Create a new buffer pool with the given capacity, of buffers of
+    /// `page_size` bytes.
+    ///
+    /// The pool serves files whose page size is at most `page_size`.
+    ///
+    /// # Pre-conditions
+    /// - `capacity` > 0
+    /// - `page_size` is one of `SUPPORTED_PAGE_SIZES`
+    ///
+    /// # Post-conditions
+    /// - Pool contains `capacity` zeroed buffers of `page_size` bytes
+    ///
+    /// # Panics
+    /// Panics if capacity is 0 or the page size is not supported.
+    #[must_use]
+    pub fn with_page_size(capacity: usize, page_size: usize) -> Arc<Self> {
         assert!(capacity > 0, "Buffer pool capacity must be positive");
+        assert!(
+            is_supported_page_size(page_size),
+            "Buffer pool page size {page_size} is not supported"
+        );
 
         let mut free_list = Vec::with_capacity(capacity);
         for _ in 0..capacity {
-            free_list.push(Box::new([0u8; PAGE_SIZE]));
+            free_list.push(vec![0u8; page_size].into_boxed_slice());
         }
 
         Arc::new(Self {
-            free_list: Mutex::new(free_list),
+            state: Mutex::new(PoolState {
+                free_list,
+                cache: PageCache::default(),
+            }),
+            returned: Condvar::new(),
             capacity,
+            page_size,
+            next_file_id: AtomicU64::new(0),
         })
     }
 
     /// Lease a buffer from the pool.
     ///
+    /// Takes a free buffer if there is one, otherwise evicts the least
+    /// recently used cached page. If every buffer is leased, waits up to
+    /// `LEASE_RETRY_ATTEMPTS` times `LEASE_RETRY_WAIT` for one to be returned.
+    ///
     /// # Returns
-    /// - `Some(Box<[u8; PAGE_SIZE]>)` if a buffer is available
-    /// - `None` if the pool is exhausted
+    /// - `Some(Box<[u8]>)` of `page_size` bytes if a buffer is available
+    /// - `None` if every buffer stayed leased
     ///
     /// # Post-conditions
-    /// - If Some, `free_list.len()` decreased by 1
+    /// - If Some, the idle buffer count decreased by 1
     /// - Buffer contents are undefined (may contain stale data)
     #[allow(clippy::expect_used)] // Mutex poisoning indicates unrecoverable state
-    pub fn lease(&self) -> Option<Box<[u8; PAGE_SIZE]>> {
-        self.free_list.lock().expect("lock poisoned").pop()
+    pub fn lease(&self) -> Option<Box<[u8]>> {
+        let mut state = self.state.lock().expect("lock poisoned");
+        for _ in 0..LEASE_RETRY_ATTEMPTS {
+            if let Some(buffer) = state.take_idle() {
+                return Some(buffer);
+            }
+            state = self
+                .returned
+                .wait_timeout(state, LEASE_RETRY_WAIT)
+                .expect("lock poisoned")
+                .0;
+        }
+        state.take_idle()
     }
 
     /// Lease a zeroed buffer from the pool.
     ///
     /// # Returns
-    /// - `Some(Box<[u8; PAGE_SIZE]>)` if a buffer is available (zeroed)
-    /// - `None` if the pool is exhausted
+    /// - `Some(Box<[u8]>)` of `page_size` bytes if a buffer is available (zeroed)
+    /// - `None` if every buffer is leased
     ///
     /// # Post-conditions
-    /// - If Some, `free_list.len()` decreased by 1
+    /// - If Some, the idle buffer count decreased by 1
     /// - Buffer contents are all zeros
-    pub fn lease_zeroed(&self) -> Option<Box<[u8; PAGE_SIZE]>> {
+    pub fn lease_zeroed(&self) -> Option<Box<[u8]>> {
         let mut buffer = self.lease()?;
         buffer.fill(0);
         Some(buffer)
@@ -103,10 +302,10 @@ impl BufferPool {
     ///
     /// # Returns
     /// - `Some(Page)` if a buffer is available
-    /// - `None` if the pool is exhausted
+    /// - `None` if every buffer is leased
     ///
     /// # Post-conditions
-    /// - If Some, `free_list.len()` decreased by 1
+    /// - If Some, the idle buffer count decreased by 1
     /// - Page contents are undefined (may contain stale data)
     #[allow(clippy::disallowed_methods)] // Arc::clone is required for shared ownership
     pub fn lease_page(self: &Arc<Self>) -> Option<Page> {
@@ -118,10 +317,10 @@ impl BufferPool {
     ///
     /// # Returns
     /// - `Some(Page)` if a buffer is available (zeroed)
-    /// - `None` if the pool is exhausted
+    /// - `None` if every buffer is leased
     ///
     /// # Post-conditions
-    /// - If Some, `free_list.len()` decreased by 1
+    /// - If Some, the idle buffer count decreased by 1
     /// - Page contents are all zeros
     #[allow(clippy::disallowed_methods)] // Arc::clone is required for shared ownership
     pub fn lease_page_zeroed(self: &Arc<Self>) -> Option<Page> {
@@ -140,21 +339,96 @@ impl BufferPool {
     /// # Panics
     /// Panics if returning would exceed capacity (indicates a bug).
     #[allow(clippy::expect_used)] // Mutex poisoning indicates unrecoverable state
-    pub fn return_buffer(&self, buffer: Box<[u8; PAGE_SIZE]>) {
-        let mut free_list = self.free_list.lock().expect("lock poisoned");
+    pub fn return_buffer(&self, buffer: Box<[u8]>) {
+        let mut state = self.state.lock().expect("lock poisoned");
         // Invariant check: we should never exceed capacity
         assert!(
-            free_list.len() < self.capacity,
+            state.idle() < self.capacity,
             "Buffer pool overflow: returning buffer to full pool"
         );
-        free_list.push(buffer);
+        assert_eq!(
+            buffer.len(),
+            self.page_size,
+            "Returned buffer is not the pool's page size"
+        );
+        state.free_list.push(buffer);
+        drop(state);
+        self.returned.notify_one();
     }
 
-    /// Get the number of available buffers.
+    /// Assign a file the ID that keys its pages in the cache.
+    ///
+    /// Each call returns a new ID, so files never see each other's pages.
+    pub fn register_file(&self) -> u64 {
+        self.next_file_id.fetch_add(1, Ordering::Relaxed)
+    }
+
+    /// Copy a cached page image into `dest`.
+    ///
+    /// # Pre-conditions
+    /// - `dest.len()` is at most `page_size`
+    ///
+    /// # Returns
+    /// Whether the page was cached; if not, `dest` is unchanged.
+    #[allow(clippy::expect_used)] // Mutex poisoning indicates unrecoverable state
+    pub fn read_cached(&self, key: CacheKey, dest: &mut [u8]) -> bool {
+        self.state
+            .lock()
+            .expect("lock poisoned")
+            .cache
+            .read(key, dest)
+    }
+
+    /// Cache the image of a page as it is on disk.
+    ///
+    /// Replaces any earlier image of the page. The image is dropped instead
+    /// if every buffer is leased.
+    ///
+    /// # Pre-conditions
+    /// - `bytes` matches the page's contents on disk
+    /// - `bytes.len()` is at most `page_size`
+    #[allow(clippy::expect_used)] // Mutex poisoning indicates unrecoverable state
+    pub fn cache_page(&self, key: CacheKey, bytes: &[u8]) {
+        self.state
+            .lock()
+            .expect("lock poisoned")
+            .cache_page(key, bytes);
+    }
+
+    /// Drop the cached image of a page, if any.
+    #[allow(clippy::expect_used)] // Mutex poisoning indicates unrecoverable state
+    pub fn forget_page(&self, key: CacheKey) {
+        let mut state = self.state.lock().expect("lock poisoned");
+        if let Some(buffer) = state.cache.remove(key) {
+            state.free_list.push(buffer);
+        }
+    }
+
+    /// Drop a file's cached images of pages at or after `first_page`.
+    ///
+    /// Files call this when pages leave the file, so a page allocated later
+    /// at the same ID is never served from a stale image.
+    #[allow(clippy::expect_used)] // Mutex poisoning indicates unrecoverable state
+    pub fn forget_pages(&self, file_id: u64, first_page: PageId) {
+        let mut state = self.state.lock().expect("lock poisoned");
+        let buffers = state
+            .cache
+            .remove_where(|key| key.file_id == file_id && key.page_id >= first_page);
+        state.free_list.extend(buffers);
+    }
+
+    /// Get the number of buffers that can be leased, free or cached.
     #[must_use]
     #[allow(clippy::expect_used)] // Mutex poisoning indicates unrecoverable state
     pub fn available(&self) -> usize {
-        self.free_list.lock().expect("lock poisoned").len()
+        self.state.lock().expect("lock poisoned").idle()
+    }
+
+    /// Get the number of cached page images.
+    #[must_use]
+    #[allow(clippy::expect_used)] // Mutex poisoning indicates unrecoverable state
+    pub fn cached(&self) -> usize {
+        self.state.lock().expect("lock poisoned").cache.pages.len()
     }
 
     /// Get the total capacity.
@@ -162,6 +436,13 @@ impl BufferPool {
     pub const fn capacity(&self) -> usize {
         self.capacity
     }
+
+    /// Get the size in bytes of every buffer, the largest page size of the
+    /// files the pool can serve.
+    #[must_use]
+    pub const fn page_size(&self) -> usize {
+        self.page_size
+    }
 }
 
 #[cfg(test)]
@@ -253,6 +534,110 @@ mod tests {
         assert_eq!(page.read_u8(100), 0);
     }
 
+    fn key(page_id: PageId) -> CacheKey {
+        CacheKey {
+            file_id: 0,
+            page_id,
+        }
+    }
+
+    #[test]
+    fn test_cached_page_is_read_back() {
+        let pool = BufferPool::new(2);
+        pool.cache_page(key(1), &[7u8; PAGE_SIZE]);
+        assert_eq!(pool.cached(), 1);
+        assert_eq!(pool.available(), 2, "cached buffers can still be leased");
+
+        let mut dest = [0u8; PAGE_SIZE];
+        assert!(pool.read_cached(key(1), &mut dest));
+        assert_eq!(dest, [7u8; PAGE_SIZE]);
+        assert!(!pool.read_cached(key(2), &mut dest));
+        let other_file = CacheKey {
+            file_id: 1,
+            page_id: 1,
+        };
+        assert!(!pool.read_cached(other_file, &mut dest));
+    }
+
+    #[test]
+    fn test_lease_evicts_least_recently_used_page() {
+        let pool = BufferPool::new(2);
+        pool.cache_page(key(1), &[1u8; PAGE_SIZE]);
+        pool.cache_page(key(2), &[2u8; PAGE_SIZE]);
+        let mut dest = [0u8; PAGE_SIZE];
+        assert!(pool.read_cached(key(1), &mut dest));
+
+        // Page 2 is least recently used, so it goes first
+        let _leased = pool.lease_page().expect("should evict a cached page");
+        assert!(!pool.read_cached(key(2), &mut dest));
+        assert!(pool.read_cached(key(1), &mut dest));
+
+        // Caching another page evicts page 1 to reuse its buffer
+        pool.cache_page(key(3), &[3u8; PAGE_SIZE]);
+        assert!(!pool.read_cached(key(1), &mut dest));
+        assert!(pool.read_cached(key(3), &mut dest));
+
+        let _leased_too = pool.lease_page().expect("should evict page 3");
+        assert!(pool.lease_page().is_none(), "every buffer is leased");
+        pool.cache_page(key(4), &[4u8; PAGE_SIZE]);
+        assert_eq!(pool.cached(), 0, "nothing to cache into");
+    }
+
+    #[test]
+    fn test_lease_waits_for_returned_buffer() {
+        let pool = BufferPool::new(1);
+        let page = pool.lease_page().expect("should lease");
+
+        let waiter = {
+            #[allow(clippy::disallowed_methods)] // The waiting thread shares the pool
+            let pool = Arc::clone(&pool);
+            std::thread::spawn(move || pool.lease().is_some())
+        };
+        drop(page);
+        assert!(
+            waiter.join().expect("waiter should not panic"),
+            "a lease waits for a buffer returned by another operation"
+        );
+    }
+
+    #[test]
+    fn test_forget_pages() {
+        let pool = BufferPool::new(4);
+        for page_id in 1..=3 {
+            pool.cache_page(key(page_id), &[0u8; PAGE_SIZE]);
+        }
+
+        pool.forget_page(key(1));
+        pool.forget_pages(0, 3);
+        let mut dest = [0u8; PAGE_SIZE];
+        assert!(!pool.read_cached(key(1), &mut dest));
+        assert!(pool.read_cached(key(2), &mut dest));
+        assert!(!pool.read_cached(key(3), &mut dest));
+        assert_eq!(pool.cached(), 1);
+        assert_eq!(pool.available(), 4);
+    }
+
+    #[test]
+    fn test_pool_with_page_size() {
+        let pool = BufferPool::with_page_size(2, 65536);
+        assert_eq!(pool.page_size(), 65536);
+        let page = pool.lease_page_zeroed().expect("should lease");
+        assert_eq!(page.as_bytes().len(), 65536);
+        drop(page);
+
+        // A smaller page is cached in the first bytes of a buffer
+        pool.cache_page(key(1), &[7u8; PAGE_SIZE]);
+        let mut dest = [0u8; PAGE_SIZE];
+        assert!(pool.read_cached(key(1), &mut dest));
+        assert_eq!(dest, [7u8; PAGE_SIZE]);
+    }
+
+    #[test]
+    #[should_panic(expected = "is not supported")]
+    fn test_unsupported_page_size_panics() {
+        let _ = BufferPool::with_page_size(1, 5000);
+    }
+
     #[test]
     #[should_panic(expected = "capacity must be positive")]
     fn test_zero_capacity_panics() {
@@ -264,7 +649,7 @@ mod tests {
     fn test_return_to_full_pool_panics() {
         let pool = BufferPool::new(1);
         // Don't lease anything, just try to return an extra buffer
-        let extra_buffer = Box::new([0u8; PAGE_SIZE]);
+        let extra_buffer = vec![0u8; PAGE_SIZE].into_boxed_slice();
         pool.return_buffer(extra_buffer);
     }
 }
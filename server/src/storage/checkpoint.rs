@@ -11,6 +11,10 @@
 //! - Manual trigger via API
 //! - Clean shutdown
 //!
+//! Threshold-triggered checkpoints run on a background task (see
+//! `checkpoint_task`); commits only signal it. A commit checkpoints inline
+//! only when the WAL is under pressure (see `wal_pressure_exceeded`).
+//!
 //! # Checkpoint Process
 //!
 //! 1. Flush all dirty pages to disk
@@ -18,11 +22,29 @@
 //! 3. Update superblock with checkpoint LSN and HLC
 //! 4. fsync to ensure durability
 //!
+//! # Page Durability
+//!
+//! With a WAL, a commit is durable once its WAL records and the WAL head are
+//! synced; the index pages it then writes are replayed from the WAL after a
+//! crash. By default commits therefore do not sync pages a second time, and a
+//! checkpoint syncs every page written since the last one before recording
+//! itself. Set `CheckpointConfig::sync_pages_on_commit` to sync pages on every
+//! commit instead.
+//!
+//! # Progress and Cancellation
+//!
+//! `perform_checkpoint_with` reports progress through a `CheckpointControl`
+//! callback and can be cancelled through its flag. Cancellation is only
+//! honored before the checkpoint record is written, so a cancelled checkpoint
+//! leaves the WAL, superblock and checkpoint state as they were; the pages it
+//! flushed stay counted as dirty for the next attempt.
+//!
 //! # Recovery
 //!
 //! On startup, recovery only needs to replay WAL records after the last checkpoint.
 
 use std::collections::HashSet;
+use std::sync::atomic::{AtomicBool, Ordering};
 
 use crate::storage::file::{DatabaseFile, FileError};
 use crate::storage::page::PageId;
@@ -45,6 +67,12 @@ pub struct CheckpointConfig {
     /// Number of bytes written to WAL between automatic checkpoints.
     /// Set to 0 to disable byte-based checkpoints.
     pub bytes_threshold: u64,
+
+    /// Sync index pages on every commit, not only at checkpoints.
+    ///
+    /// Off by default: the WAL already makes each commit durable. Has no
+    /// effect without a WAL, where every commit syncs its pages.
+    pub sync_pages_on_commit: bool,
 }
 
 impl Default for CheckpointConfig {
@@ -52,6 +80,7 @@ impl Default for CheckpointConfig {
         Self {
             txn_threshold: DEFAULT_TXN_THRESHOLD,
             bytes_threshold: DEFAULT_BYTES_THRESHOLD,
+            sync_pages_on_commit: false,
         }
     }
 }
@@ -63,6 +92,7 @@ impl CheckpointConfig {
         Self {
             txn_threshold,
             bytes_threshold,
+            sync_pages_on_commit: false,
         }
     }
 
@@ -72,8 +102,16 @@ impl CheckpointConfig {
         Self {
             txn_threshold: 0,
             bytes_threshold: 0,
+            sync_pages_on_commit: false,
         }
     }
+
+    /// Set whether commits sync index pages (see `sync_pages_on_commit`).
+    #[must_use]
+    pub const fn with_page_sync_on_commit(mut self, sync_pages_on_commit: bool) -> Self {
+        self.sync_pages_on_commit = sync_pages_on_commit;
+        self
+    }
 }
 
 /// Tracks state needed for checkpoint decisions.
@@ -137,6 +175,13 @@ impl CheckpointState {
         self.txns_since_checkpoint
     }
 
+    /// Whether commits sync index pages rather than leaving them to the
+    /// next checkpoint.
+    #[must_use]
+    pub const fn sync_pages_on_commit(&self) -> bool {
+        self.config.sync_pages_on_commit
+    }
+
     /// Get the bytes written since last checkpoint.
     #[must_use]
     pub const fn bytes_since_checkpoint(&self) -> u64 {
@@ -190,6 +235,16 @@ impl CheckpointState {
         false
     }
 
+    /// Check if un-checkpointed WAL bytes have reached half of `wal_capacity`.
+    ///
+    /// Checkpoints normally run on the background task. Past this point the
+    /// task has fallen far enough behind that the WAL risks wrapping onto
+    /// records a crash would still need, so the caller should checkpoint inline.
+    #[must_use]
+    pub const fn wal_pressure_exceeded(&self, wal_capacity: u64) -> bool {
+        self.bytes_since_checkpoint >= wal_capacity / 2
+    }
+
     /// Get the set of dirty pages that need to be flushed.
     #[must_use]
     #[allow(clippy::missing_const_for_fn)] // HashSet reference not const-stable
@@ -225,6 +280,65 @@ pub struct CheckpointResult {
     pub pages_flushed: usize,
 }
 
+/// Progress of a running checkpoint, passed to `CheckpointControl` callbacks.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CheckpointProgress {
+    /// Dirty pages flushed to disk so far.
+    pub pages_flushed: usize,
+    /// Dirty pages this checkpoint flushes in total.
+    pub pages_total: usize,
+}
+
+/// Optional progress reporting and cancellation for a checkpoint.
+///
+/// The default reports nothing and never cancels.
+#[derive(Default)]
+pub struct CheckpointControl<'a> {
+    /// Called as dirty pages are flushed.
+    progress: Option<&'a mut dyn FnMut(CheckpointProgress)>,
+    /// Once set, the checkpoint is abandoned at its next cancellation point.
+    cancel: Option<&'a AtomicBool>,
+}
+
+impl<'a> CheckpointControl<'a> {
+    /// Call `progress` as the checkpoint flushes dirty pages.
+    #[must_use]
+    pub fn with_progress(mut self, progress: &'a mut dyn FnMut(CheckpointProgress)) -> Self {
+        self.progress = Some(progress);
+        self
+    }
+
+    /// Abandon the checkpoint once `cancel` is set.
+    ///
+    /// The flag may be set from another thread, or from the progress callback.
+    #[must_use]
+    pub const fn with_cancel(mut self, cancel: &'a AtomicBool) -> Self {
+        self.cancel = Some(cancel);
+        self
+    }
+
+    /// Report progress to the callback, if any.
+    fn report(&mut self, pages_flushed: usize, pages_total: usize) {
+        if let Some(progress) = self.progress.as_mut() {
+            progress(CheckpointProgress {
+                pages_flushed,
+                pages_total,
+            });
+        }
+    }
+
+    /// Fail with `CheckpointError::Cancelled` if cancellation was requested.
+    fn check_cancelled(&self) -> Result<(), CheckpointError> {
+        if self
+            .cancel
+            .is_some_and(|cancel| cancel.load(Ordering::Acquire))
+        {
+            return Err(CheckpointError::Cancelled);
+        }
+        Ok(())
+    }
+}
+
 /// Perform a checkpoint on the database.
 ///
 /// This function:
@@ -245,10 +359,39 @@ pub fn perform_checkpoint(
     state: &mut CheckpointState,
     hlc: HlcTimestamp,
 ) -> Result<CheckpointResult, CheckpointError> {
-    // Step 1: Count dirty pages (they will be flushed implicitly by write operations)
-    // In a real implementation, we'd track and flush dirty pages from a page cache.
-    // For now, we just record the count.
+    perform_checkpoint_with(file, state, hlc, CheckpointControl::default())
+}
+
+/// Perform a checkpoint, reporting progress and honoring cancellation
+/// through `control`.
+///
+/// # Errors
+///
+/// Returns `CheckpointError::Cancelled` if cancellation was requested before
+/// the checkpoint record was written.
+///
+/// # Post-conditions
+/// - On `Cancelled`, the WAL, superblock and `state` are unchanged, except
+///   that pages written since the last checkpoint are now tracked as dirty
+pub fn perform_checkpoint_with(
+    file: &mut DatabaseFile,
+    state: &mut CheckpointState,
+    hlc: HlcTimestamp,
+    mut control: CheckpointControl<'_>,
+) -> Result<CheckpointResult, CheckpointError> {
+    // Step 1: Flush dirty pages. Page writes go straight to the file, but
+    // commits may leave them unsynced; they must be durable before the
+    // checkpoint record tells recovery to skip the WAL records behind them.
+    state.mark_dirty_batch(&file.take_written_pages());
     let pages_flushed = state.dirty_page_count();
+    control.report(0, pages_flushed);
+    control.check_cancelled()?;
+    file.sync()?;
+    control.report(pages_flushed, pages_flushed);
+
+    // Last chance to cancel: once the record is written, the checkpoint is
+    // applied. Flushed pages stay dirty, so a retry counts them again.
+    control.check_cancelled()?;
 
     // Step 2: Read values needed for checkpoint record BEFORE borrowing for WAL
     let min_active_txn = file.superblock().next_txn_id;
@@ -256,7 +399,10 @@ pub fn perform_checkpoint(
 
     // Step 3: Write checkpoint record to WAL
     let (checkpoint_lsn, wal_head, last_lsn) = {
-        let mut wal = file.wal()?;
+        // Every committed page was synced above, so once this record is durable
+        // nothing earlier in the WAL is needed. The record may therefore reuse
+        // space the overwrite guard would otherwise protect.
+        let mut wal = file.wal()?.with_checkpoint_offset(None);
 
         let payload = LogRecordPayload::checkpoint(min_active_txn, active_txn_count);
 
@@ -278,6 +424,7 @@ pub fn perform_checkpoint(
         let sb = file.superblock_mut();
         sb.last_checkpoint_lsn = checkpoint_lsn;
         sb.last_checkpoint_hlc = hlc;
+        sb.txn_log_checkpoint_offset = wal_head;
     }
 
     // Write superblock to disk
@@ -333,6 +480,8 @@ pub enum CheckpointError {
     File(FileError),
     /// WAL error.
     Wal(WalError),
+    /// The checkpoint was cancelled before it was applied.
+    Cancelled,
 }
 
 impl std::fmt::Display for CheckpointError {
@@ -340,6 +489,7 @@ impl std::fmt::Display for CheckpointError {
         match self {
             Self::File(e) => write!(f, "checkpoint file error: {e}"),
             Self::Wal(e) => write!(f, "checkpoint WAL error: {e}"),
+            Self::Cancelled => write!(f, "checkpoint cancelled"),
         }
     }
 }
@@ -349,6 +499,7 @@ impl std::error::Error for CheckpointError {
         match self {
             Self::File(e) => Some(e),
             Self::Wal(e) => Some(e),
+            Self::Cancelled => None,
         }
     }
 }
@@ -513,6 +664,18 @@ mod tests {
         assert_eq!(file.superblock().last_checkpoint_hlc, hlc);
     }
 
+    #[test]
+    fn test_wal_pressure_exceeded() {
+        let config = CheckpointConfig::new(0, 1024);
+        let mut state = CheckpointState::new(config, 0, HlcTimestamp::new(0, 0));
+
+        state.record_wal_write(4095);
+        assert!(!state.wal_pressure_exceeded(8192));
+
+        state.record_wal_write(1);
+        assert!(state.wal_pressure_exceeded(8192));
+    }
+
     #[test]
     fn test_maybe_checkpoint() {
         let (_dir, path) = create_test_db();
@@ -0,0 +1,159 @@
+//! Background checkpointing for the storage engine.
+//!
+//! Commits that cross a checkpoint threshold signal this task instead of
+//! checkpointing inline, so no single commit pays the checkpoint latency.
+//!
+//! # Design
+//!
+//! Mirrors the GC task: the task holds a `Weak<RwLock<Database>>` and waits on
+//! the database's `checkpoint_notify` signal. The checkpoint runs under the
+//! database write lock, so it never interleaves with a commit. When the
+//! database is dropped, `Weak::upgrade()` returns `None` and the task exits.
+
+use std::sync::{Arc, RwLock, Weak};
+
+use tokio::sync::Notify;
+
+use crate::storage::Database;
+
+/// Spawn a background checkpoint task for a database.
+///
+/// The task waits for signals on `notify` and checkpoints if the database's
+/// thresholds are still exceeded when it wakes.
+///
+/// # Arguments
+/// * `database` - Weak reference to the database to checkpoint
+/// * `notify` - Notification channel signaled when a checkpoint is due
+///
+/// # Returns
+/// A `JoinHandle` that can be used to await the task or cancel it on shutdown.
+///
+/// # Invariants
+/// - Uses `Weak` reference to prevent reference cycles
+/// - Exits cleanly when database is dropped
+pub fn spawn_checkpoint_task(
+    database: Weak<RwLock<Database>>,
+    notify: Arc<Notify>,
+) -> tokio::task::JoinHandle<()> {
+    tokio::spawn(async move {
+        checkpoint_loop(database, notify).await;
+    })
+}
+
+/// The main checkpoint loop.
+///
+/// Runs until the database is dropped or the task is cancelled.
+async fn checkpoint_loop(database: Weak<RwLock<Database>>, notify: Arc<Notify>) {
+    loop {
+        // Wait for notification that a checkpoint is due
+        notify.notified().await;
+
+        let Some(db_arc) = database.upgrade() else {
+            // Database was dropped, exit the task
+            break;
+        };
+
+        let result = {
+            let Ok(mut db) = db_arc.write() else {
+                // Lock was poisoned, exit the task
+                eprintln!("Checkpoint error: database lock poisoned");
+                break;
+            };
+            // A commit may have checkpointed inline while we waited for the lock
+            db.checkpoint_if_due()
+        };
+
+        if let Err(e) = result {
+            // Log the error but continue - the next signal retries
+            eprintln!("Checkpoint error: {e}");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::CheckpointConfig;
+    use crate::storage::buffer_pool::BufferPool;
+    use crate::storage::file::FileFormat;
+    use crate::storage::wal::DEFAULT_WAL_CAPACITY;
+    use crate::types::{AttributeId, EntityId, TripleValue};
+    use std::time::Duration;
+    use tempfile::tempdir;
+
+    fn test_pool() -> Arc<BufferPool> {
+        BufferPool::new(100)
+    }
+
+    #[tokio::test]
+    async fn test_checkpoint_task_checkpoints_after_signal() {
+        let dir = tempdir().expect("create temp dir");
+        let path = dir.path().join("test.db");
+
+        let mut db = Database::create_with_options(
+            &path,
+            test_pool(),
+            FileFormat::default(),
+            DEFAULT_WAL_CAPACITY,
+            CheckpointConfig::new(1, 0),
+            0,
+        )
+        .expect("create db");
+
+        // The commit signals before the task exists; the stored permit wakes it
+        let mut txn = db.begin(0).expect("begin");
+        txn.insert(
+            EntityId([1u8; 16]),
+            AttributeId([2u8; 16]),
+            TripleValue::Number(1.0),
+        )
+        .expect("insert");
+        txn.commit().expect("commit");
+        assert_eq!(db.checkpoint_state().txns_since_checkpoint(), 1);
+
+        let notify = db.checkpoint_notify();
+        let db_arc = Arc::new(RwLock::new(db));
+        let handle = spawn_checkpoint_task(Arc::downgrade(&db_arc), notify);
+
+        let checkpointed = tokio::time::timeout(Duration::from_secs(5), async {
+            loop {
+                let txns = db_arc
+                    .read()
+                    .expect("lock should not be poisoned")
+                    .checkpoint_state()
+                    .txns_since_checkpoint();
+                if txns == 0 {
+                    break;
+                }
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        })
+        .await;
+        assert!(checkpointed.is_ok(), "background checkpoint should run");
+
+        handle.abort();
+    }
+
+    #[tokio::test]
+    async fn test_checkpoint_task_exits_when_database_dropped() {
+        let dir = tempdir().expect("create temp dir");
+        let path = dir.path().join("test.db");
+
+        let db = Database::create(&path, test_pool()).expect("create db");
+        let db_arc = Arc::new(RwLock::new(db));
+        let notify = db_arc
+            .read()
+            .expect("lock should not be poisoned")
+            .checkpoint_notify();
+        let handle = spawn_checkpoint_task(Arc::downgrade(&db_arc), Arc::clone(&notify));
+
+        drop(db_arc);
+        notify.notify_one();
+
+        let result = tokio::time::timeout(Duration::from_secs(1), handle).await;
+        assert!(
+            result.is_ok(),
+            "checkpoint task should exit when database is dropped"
+        );
+    }
+}
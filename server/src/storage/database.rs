@@ -24,42 +24,68 @@
 //! let value = TripleValue::String("hello".to_string());
 //!
 //! let mut txn = db.begin(0).unwrap(); // 0 = connection ID
-//! txn.insert(entity_id, attr_id, value); // Buffers the insert
+//! txn.insert(entity_id, attr_id, value).unwrap(); // Buffers the insert
 //! txn.commit().unwrap();  // Writes to WAL, then applies to index
 //! ```
 
-use std::collections::BTreeMap;
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
+use std::fs::File;
+use std::io::{BufReader, Read, Write};
 use std::path::Path;
+use std::sync::atomic::{AtomicU64, Ordering as AtomicOrdering};
 use std::sync::{Arc, Mutex};
 
 use tokio::sync::broadcast;
 
 use crate::storage::FilteredChangeReceiver;
+use crate::storage::attribute_cardinality::{
+    ATTRIBUTE_CARDINALITY_ENTITY_ID, declaration_written, declared_cardinality, is_reserved_entity,
+};
+use crate::storage::backup::{BackupError, BackupHeader, BackupReader, BackupWriter};
 use crate::storage::buffer_pool::BufferPool;
 use crate::storage::checkpoint::{
-    CheckpointConfig, CheckpointError, CheckpointResult, CheckpointState, force_checkpoint,
-    maybe_checkpoint,
+    CheckpointConfig, CheckpointControl, CheckpointError, CheckpointResult, CheckpointState,
+    force_checkpoint, maybe_checkpoint, perform_checkpoint_with,
 };
-use crate::storage::file::{DatabaseFile, FileError};
+use crate::storage::entity_versions::{self, ENTITY_VERSION_ATTRIBUTE_ID};
+use crate::storage::file::{DatabaseFile, FileError, FileFormat, WalCompaction};
 use crate::storage::hlc::{Clock, ClockError};
-#[cfg(unix)]
-use crate::storage::indexes::attribute::AttributeIndexReader;
 use crate::storage::indexes::attribute::{AttributeIndex, AttributeIndexError};
-#[cfg(unix)]
-use crate::storage::indexes::entity_attribute::EntityAttributeIndexReader;
+use crate::storage::indexes::attribute::{AttributeIndexReader, AttributeScanReaderIterator};
+use crate::storage::indexes::entity_attribute::{
+    DistinctEntityReaderIterator, EntityAttributeIndexReader,
+};
 use crate::storage::indexes::entity_attribute::{EntityAttributeIndex, EntityAttributeIndexError};
-#[cfg(unix)]
-use crate::storage::indexes::primary::PrimaryIndexReader;
 use crate::storage::indexes::primary::{PrimaryIndex, PrimaryIndexError};
-use crate::storage::recovery::{self, RecoveryError, RecoveryResult};
-use crate::storage::time::SystemTimeSource;
+use crate::storage::indexes::primary::{PrimaryIndexReader, PrimaryIndexReaderCursor};
+use crate::storage::io::StorageError;
+use crate::storage::page::{PageId, PageType};
+use crate::storage::recovery::{
+    self, IndexMismatch, RECOVERY_BATCH_RECORDS, RecoveryError, RecoveryResult, VerificationReport,
+};
+use crate::storage::superblock::{Superblock, UNASSIGNED_NODE_ID};
+use crate::storage::time::{SystemTimeSource, TimeSource};
 use crate::storage::tombstone::{Tombstone, TombstoneError, TombstoneList};
-use crate::storage::wal::{DEFAULT_WAL_CAPACITY, LogRecordPayload, Lsn, WalError};
+use crate::storage::triggers::{
+    AttributeTriggers, MAX_TRIGGER_DEPTH, MAX_TRIGGERED_WRITES, TriggerContext, TriggerEvent,
+    TriggerId,
+};
+use crate::storage::upgrade::{LegacyFile, UpgradeError};
+use crate::storage::wal::{
+    DEFAULT_WAL_CAPACITY, LogRecord, LogRecordPayload, Lsn, WalError, WalRecords,
+};
 use crate::types::{
-    AttributeId, ChangeNotification, ChangeRecord, ChangeType, ConnectionId, EntityId,
-    HlcTimestamp, PendingTriple, TripleError, TripleRecord, TripleValue, TxnId,
+    AttributeId, Cardinality, ChangeNotification, ChangeRecord, ChangeType, ConnectionId, EntityId,
+    HlcTimestamp, PendingTriple, PendingTripleData, RecordStorageSize, TripleError, TripleRecord,
+    TripleValue, TxnId, TxnRange, ValueRange,
 };
 
+/// Connection ID used for writes made by the database itself, such as schema
+/// writes and expiry deletes. Client connection IDs start at 1, so no client
+/// filters these out.
+pub const SYSTEM_CONNECTION_ID: ConnectionId = 0;
+
 /// Trait for applying operations to secondary indexes (attribute and entity-attribute).
 ///
 /// This trait abstracts over the different argument orders used by secondary indexes,
@@ -68,18 +94,35 @@ trait SecondaryIndexOps {
     type Error: Into<DatabaseError>;
 
     /// Apply an insert operation to the index.
-    fn apply_insert(
+    fn apply_insert(&mut self, record: &TripleRecord, txn_id: TxnId) -> Result<(), Self::Error>;
+
+    /// Apply a run of insert operations to the index, in order.
+    ///
+    /// By default this applies them one at a time.
+    fn apply_inserts(
         &mut self,
-        entity_id: &EntityId,
-        attribute_id: &AttributeId,
+        records: &[&TripleRecord],
         txn_id: TxnId,
-    ) -> Result<(), Self::Error>;
+    ) -> Result<(), Self::Error> {
+        records
+            .iter()
+            .try_for_each(|record| self.apply_insert(record, txn_id))
+    }
+
+    /// Apply an update operation to the index.
+    ///
+    /// Updates don't change the entity-attribute mapping, so by default this
+    /// does nothing.
+    fn apply_update(&mut self, _record: &TripleRecord) -> Result<(), Self::Error> {
+        Ok(())
+    }
 
     /// Apply a delete operation to the index.
     fn apply_delete(
         &mut self,
         entity_id: &EntityId,
         attribute_id: &AttributeId,
+        value_hash: u64,
         txn_id: TxnId,
     ) -> Result<(), Self::Error>;
 }
@@ -87,23 +130,54 @@ trait SecondaryIndexOps {
 impl SecondaryIndexOps for AttributeIndex<'_> {
     type Error = AttributeIndexError;
 
-    fn apply_insert(
+    fn apply_insert(&mut self, record: &TripleRecord, txn_id: TxnId) -> Result<(), Self::Error> {
+        // AttributeIndex uses (attribute_id, entity_id) order
+        self.insert(
+            &record.attribute_id,
+            &record.entity_id,
+            record.value_hash(),
+            &record.value,
+            txn_id,
+        )
+    }
+
+    fn apply_inserts(
         &mut self,
-        entity_id: &EntityId,
-        attribute_id: &AttributeId,
+        records: &[&TripleRecord],
         txn_id: TxnId,
     ) -> Result<(), Self::Error> {
-        // AttributeIndex uses (attribute_id, entity_id) order
-        self.insert(attribute_id, entity_id, txn_id)
+        self.insert_many(
+            records.iter().map(|record| {
+                (
+                    record.attribute_id,
+                    record.entity_id,
+                    record.value_hash(),
+                    &record.value,
+                )
+            }),
+            txn_id,
+        )
+    }
+
+    fn apply_update(&mut self, record: &TripleRecord) -> Result<(), Self::Error> {
+        // The value entries are ordered by value, so they move with it
+        self.update_value(
+            &record.attribute_id,
+            &record.entity_id,
+            record.value_hash(),
+            &record.value,
+        )?;
+        Ok(())
     }
 
     fn apply_delete(
         &mut self,
         entity_id: &EntityId,
         attribute_id: &AttributeId,
+        value_hash: u64,
         txn_id: TxnId,
     ) -> Result<(), Self::Error> {
-        self.mark_deleted(attribute_id, entity_id, txn_id)?;
+        self.mark_deleted(attribute_id, entity_id, value_hash, txn_id)?;
         Ok(())
     }
 }
@@ -111,58 +185,124 @@ impl SecondaryIndexOps for AttributeIndex<'_> {
 impl SecondaryIndexOps for EntityAttributeIndex<'_> {
     type Error = EntityAttributeIndexError;
 
-    fn apply_insert(
+    fn apply_insert(&mut self, record: &TripleRecord, txn_id: TxnId) -> Result<(), Self::Error> {
+        // EntityAttributeIndex uses (entity_id, attribute_id) order
+        self.insert(
+            &record.entity_id,
+            &record.attribute_id,
+            record.value_hash(),
+            txn_id,
+        )
+    }
+
+    fn apply_inserts(
         &mut self,
-        entity_id: &EntityId,
-        attribute_id: &AttributeId,
+        records: &[&TripleRecord],
         txn_id: TxnId,
     ) -> Result<(), Self::Error> {
-        // EntityAttributeIndex uses (entity_id, attribute_id) order
-        self.insert(entity_id, attribute_id, txn_id)
+        self.insert_many(
+            records
+                .iter()
+                .map(|record| (record.entity_id, record.attribute_id, record.value_hash())),
+            txn_id,
+        )
     }
 
     fn apply_delete(
         &mut self,
         entity_id: &EntityId,
         attribute_id: &AttributeId,
+        value_hash: u64,
         txn_id: TxnId,
     ) -> Result<(), Self::Error> {
-        self.mark_deleted(entity_id, attribute_id, txn_id)?;
+        self.mark_deleted(entity_id, attribute_id, value_hash, txn_id)?;
         Ok(())
     }
 }
 
 /// Apply buffered operations to a secondary index.
 ///
-/// This helper function applies Insert and Delete operations to any index
-/// implementing `SecondaryIndexOps`. Update operations are skipped as they
-/// don't change the entity-attribute mapping in secondary indexes.
+/// This helper function applies Insert, Update and Delete operations to any
+/// index implementing `SecondaryIndexOps`. Consecutive inserts are applied
+/// as one run.
 fn apply_ops_to_secondary_index<I: SecondaryIndexOps>(
     index: &mut I,
     operations: &[PendingTriple],
     txn_id: TxnId,
 ) -> Result<(), DatabaseError> {
+    let mut inserts = Vec::new();
     for op in operations {
+        if let PendingTriple::Insert(record) = op {
+            inserts.push(record);
+            continue;
+        }
+        index
+            .apply_inserts(&std::mem::take(&mut inserts), txn_id)
+            .map_err(Into::into)?;
         match op {
-            PendingTriple::Insert(record) => {
-                index
-                    .apply_insert(&record.entity_id, &record.attribute_id, txn_id)
-                    .map_err(Into::into)?;
-            }
-            PendingTriple::Update(_) => {
-                // Updates don't change the entity-attribute mapping in secondary indexes
+            PendingTriple::Insert(_) => {}
+            PendingTriple::Update(record) => {
+                index.apply_update(record).map_err(Into::into)?;
             }
             PendingTriple::Delete {
                 entity_id,
                 attribute_id,
+                value_hash,
+                ..
             } => {
                 index
-                    .apply_delete(entity_id, attribute_id, txn_id)
+                    .apply_delete(entity_id, attribute_id, *value_hash, txn_id)
                     .map_err(Into::into)?;
             }
         }
     }
-    Ok(())
+    index.apply_inserts(&inserts, txn_id).map_err(Into::into)
+}
+
+/// Primary key of a triple: its entity, attribute and value hash.
+type TripleKey = (EntityId, AttributeId, u64);
+
+/// Collapse a transaction's changes so each key appears once, in its final state.
+///
+/// The merged change takes the place of the key's first change. It is a delete
+/// if the last change was. Otherwise it carries the last value and HLC, and is
+/// an insert if the first change was, since the key then had no value before
+/// the transaction. Each value of a cardinality-many attribute is a key of its
+/// own, so changes to different values are never merged.
+fn coalesce_changes(changes: Vec<(TripleKey, ChangeRecord)>) -> Vec<(TripleKey, ChangeRecord)> {
+    // Position of each key's merged change, and the type of its first change
+    let mut merged: HashMap<TripleKey, (usize, ChangeType)> = HashMap::new();
+    let mut coalesced: Vec<(TripleKey, ChangeRecord)> = Vec::with_capacity(changes.len());
+    for (key, mut change) in changes {
+        if let Some(&(position, first_type)) = merged.get(&key) {
+            if change.change_type != ChangeType::Delete {
+                change.change_type = if first_type == ChangeType::Insert {
+                    ChangeType::Insert
+                } else {
+                    ChangeType::Update
+                };
+            }
+            coalesced[position] = (key, change);
+        } else {
+            merged.insert(key, (coalesced.len(), change.change_type));
+            coalesced.push((key, change));
+        }
+    }
+    coalesced
+}
+
+/// Committed values of the keys a transaction writes, as they were before it.
+type PriorValues = HashMap<TripleKey, TripleValue>;
+
+/// The newest version of a key seen during last-writer-wins conflict resolution.
+#[derive(Debug, Clone, Copy)]
+struct Version {
+    /// HLC of the write or delete that produced this version.
+    hlc: HlcTimestamp,
+    /// Whether the key has a value (false for a tombstone).
+    is_live: bool,
+    /// Whether this transaction produced the version.
+    written_here: bool,
 }
 
 /// Tracks active read-only snapshots for garbage collection.
@@ -186,13 +326,16 @@ impl ActiveSnapshots {
     ///
     /// Multiple snapshots can be registered at the same `txn_id` (concurrent reads).
     ///
-    /// # Panics
-    /// Panics if the mutex is poisoned.
-    fn register(&self, txn_id: TxnId) {
-        let Ok(mut active) = self.active.lock() else {
-            panic!("ActiveSnapshots mutex poisoned");
-        };
-        *active.entry(txn_id).or_insert(0) += 1;
+    /// # Errors
+    /// Returns `DatabaseError::LockPoisoned` if the mutex is poisoned.
+    fn register(&self, txn_id: TxnId) -> Result<(), DatabaseError> {
+        *self
+            .active
+            .lock()
+            .map_err(|_| DatabaseError::LockPoisoned)?
+            .entry(txn_id)
+            .or_insert(0) += 1;
+        Ok(())
     }
 
     /// Unregister a snapshot when it's released.
@@ -201,24 +344,29 @@ impl ActiveSnapshots {
     ///
     /// # Panics
     /// Panics if the `txn_id` was not registered (indicates a programming error).
-    /// Panics if the mutex is poisoned.
-    fn unregister(&self, txn_id: TxnId) {
-        let Ok(mut active) = self.active.lock() else {
-            panic!("ActiveSnapshots mutex poisoned");
-        };
-        match active.get_mut(&txn_id) {
+    ///
+    /// # Errors
+    /// Returns `DatabaseError::LockPoisoned` if the mutex is poisoned.
+    fn unregister(&self, txn_id: TxnId) -> Result<(), DatabaseError> {
+        let mut active = self
+            .active
+            .lock()
+            .map_err(|_| DatabaseError::LockPoisoned)?;
+        let registered = match active.get_mut(&txn_id) {
             Some(count) if *count > 1 => {
                 *count -= 1;
+                true
             }
-            Some(_) => {
-                active.remove(&txn_id);
-            }
-            None => {
-                panic!(
-                    "Snapshot txn_id {txn_id} was not registered - releasing unregistered snapshot"
-                );
-            }
-        }
+            Some(_) => active.remove(&txn_id).is_some(),
+            None => false,
+        };
+        // Release the lock first so the panic does not poison it
+        drop(active);
+        assert!(
+            registered,
+            "Snapshot txn_id {txn_id} was not registered - releasing unregistered snapshot"
+        );
+        Ok(())
     }
 
     /// Get the minimum active snapshot transaction ID.
@@ -226,24 +374,26 @@ impl ActiveSnapshots {
     /// Returns None if there are no active snapshots.
     /// Deleted records with `deleted_txn` < this value can be garbage collected.
     ///
-    /// # Panics
-    /// Panics if the mutex is poisoned.
-    fn min_active(&self) -> Option<TxnId> {
-        let Ok(active) = self.active.lock() else {
-            panic!("ActiveSnapshots mutex poisoned");
-        };
-        active.first_key_value().map(|(&txn_id, _)| txn_id)
+    /// # Errors
+    /// Returns `DatabaseError::LockPoisoned` if the mutex is poisoned.
+    fn min_active(&self) -> Result<Option<TxnId>, DatabaseError> {
+        let active = self
+            .active
+            .lock()
+            .map_err(|_| DatabaseError::LockPoisoned)?;
+        Ok(active.first_key_value().map(|(&txn_id, _)| txn_id))
     }
 
     /// Get the count of active snapshots (total across all `txn_id`s).
     ///
-    /// # Panics
-    /// Panics if the mutex is poisoned.
-    fn count(&self) -> usize {
-        let Ok(active) = self.active.lock() else {
-            panic!("ActiveSnapshots mutex poisoned");
-        };
-        active.values().sum()
+    /// # Errors
+    /// Returns `DatabaseError::LockPoisoned` if the mutex is poisoned.
+    fn count(&self) -> Result<usize, DatabaseError> {
+        let active = self
+            .active
+            .lock()
+            .map_err(|_| DatabaseError::LockPoisoned)?;
+        Ok(active.values().sum())
     }
 }
 
@@ -253,6 +403,9 @@ const DEFAULT_NODE_ID: u32 = 0;
 /// Default capacity for the change notification broadcast channel.
 const DEFAULT_BROADCAST_CAPACITY: usize = 1000;
 
+/// Triples a bulk load reads and sorts at a time.
+const BULK_LOAD_CHUNK_TRIPLES: usize = 4096;
+
 /// A database instance with WAL and crash recovery.
 ///
 /// This is the main entry point for working with the storage engine.
@@ -275,6 +428,12 @@ const DEFAULT_BROADCAST_CAPACITY: usize = 1000;
 /// tombstones incrementally after each commit, removing records that are no longer
 /// visible to any active snapshot. Use `gc_notify()` to get a handle for signaling
 /// the GC task.
+///
+/// # Background Checkpoints
+///
+/// Commits that cross a checkpoint threshold signal `checkpoint_notify()` rather
+/// than checkpointing inline. Run `spawn_checkpoint_task` to service the signal.
+/// A commit still checkpoints inline if the WAL fills up before the task catches up.
 pub struct Database {
     file: DatabaseFile,
     checkpoint_state: CheckpointState,
@@ -288,6 +447,22 @@ pub struct Database {
     tombstone_list: TombstoneList,
     /// Notifier for signaling the background GC task.
     gc_notify: Arc<tokio::sync::Notify>,
+    /// Notifier for signaling the background checkpoint task.
+    checkpoint_notify: Arc<tokio::sync::Notify>,
+    /// Index verification report, if verification was requested at open.
+    verification: Option<VerificationReport>,
+    /// Triggers fired on commit, by watched attribute.
+    triggers: AttributeTriggers,
+    /// Earliest expiry of a live triple, or `None` if no triple expires.
+    ///
+    /// May be earlier than the true earliest expiry, never later; the expiry
+    /// sweep recomputes it when it scans.
+    next_expiry_ms: Option<u64>,
+    /// Whether commits keep each changed entity's version current.
+    entity_versions: bool,
+    /// Capacity in bytes a commit may grow the WAL to, or `None` to keep it
+    /// fixed.
+    max_wal_capacity: Option<u64>,
 }
 
 impl Database {
@@ -299,6 +474,7 @@ impl Database {
         Self::create_with_options(
             path,
             pool,
+            FileFormat::default(),
             DEFAULT_WAL_CAPACITY,
             CheckpointConfig::default(),
             DEFAULT_NODE_ID,
@@ -310,19 +486,65 @@ impl Database {
     /// # Arguments
     /// * `path` - Path for the database file
     /// * `pool` - Shared buffer pool for page allocations
+    /// * `format` - Page size and checksum algorithm, fixed for the lifetime
+    ///   of the file.
     /// * `wal_capacity` - Capacity of the write-ahead log in bytes
     /// * `checkpoint_config` - Configuration for automatic checkpointing
-    /// * `node_id` - Unique identifier for this node (for distributed deployments)
+    /// * `node_id` - Unique identifier for this node (for distributed
+    ///   deployments), stored in the superblock
     pub fn create_with_options(
         path: &Path,
         pool: Arc<BufferPool>,
+        format: FileFormat,
+        wal_capacity: u64,
+        checkpoint_config: CheckpointConfig,
+        node_id: u32,
+    ) -> Result<Self, DatabaseError> {
+        let file = DatabaseFile::create_with_format(path, pool, format)?;
+        Self::create_from_file(file, wal_capacity, checkpoint_config, node_id)
+    }
+
+    /// Create a new database held in memory, e.g. for tests or ephemeral
+    /// workloads.
+    ///
+    /// Uses the same defaults as `create()`. See `create_in_memory_with_options`.
+    pub fn create_in_memory(pool: Arc<BufferPool>) -> Result<Self, DatabaseError> {
+        Self::create_in_memory_with_options(
+            pool,
+            FileFormat::default(),
+            DEFAULT_WAL_CAPACITY,
+            CheckpointConfig::default(),
+            DEFAULT_NODE_ID,
+        )
+    }
+
+    /// Create a new database held in memory with custom options.
+    ///
+    /// The database runs the same page, B-tree, WAL and checkpoint code as a
+    /// file-backed one, but syncs are no-ops and its contents are lost when it
+    /// is dropped. It cannot be reopened, so there is never anything to
+    /// recover. The arguments are as for `create_with_options`.
+    pub fn create_in_memory_with_options(
+        pool: Arc<BufferPool>,
+        format: FileFormat,
+        wal_capacity: u64,
+        checkpoint_config: CheckpointConfig,
+        node_id: u32,
+    ) -> Result<Self, DatabaseError> {
+        let file = DatabaseFile::create_in_memory(pool, format)?;
+        Self::create_from_file(file, wal_capacity, checkpoint_config, node_id)
+    }
+
+    /// Initialize a newly created file and build a database around it.
+    fn create_from_file(
+        mut file: DatabaseFile,
         wal_capacity: u64,
         checkpoint_config: CheckpointConfig,
         node_id: u32,
     ) -> Result<Self, DatabaseError> {
-        let mut file = DatabaseFile::create(path, pool)?;
+        file.superblock_mut().node_id = node_id;
 
-        // Initialize WAL
+        // Initialize WAL (writes the superblock)
         file.init_wal(wal_capacity)?;
 
         let checkpoint_state = CheckpointState::from_database(&file, checkpoint_config);
@@ -339,18 +561,31 @@ impl Database {
             change_tx,
             tombstone_list: TombstoneList::new(),
             gc_notify: Arc::new(tokio::sync::Notify::new()),
+            checkpoint_notify: Arc::new(tokio::sync::Notify::new()),
+            verification: None,
+            triggers: AttributeTriggers::default(),
+            next_expiry_ms: None,
+            entity_versions: false,
+            max_wal_capacity: None,
         })
     }
 
     /// Open an existing database at the given path.
     ///
-    /// Runs crash recovery if needed to restore consistent state.
-    /// Uses node ID 0 for single-node deployments.
+    /// Runs crash recovery if needed to restore consistent state, first
+    /// upgrading a file written in an older format version in place, and
+    /// rebuilding a file whose pages a crash tore (see `repair_if_torn`).
+    /// Uses the node ID stored in the file, so it never fails with
+    /// `DatabaseError::NodeIdMismatch`.
     pub fn open(
         path: &Path,
         pool: Arc<BufferPool>,
     ) -> Result<(Self, Option<RecoveryResult>), DatabaseError> {
-        Self::open_with_options(path, pool, CheckpointConfig::default(), DEFAULT_NODE_ID)
+        Self::upgrade_if_needed(path, &pool)?;
+        Self::repair_if_torn(path, &pool)?;
+        let file = DatabaseFile::open(path, pool)?;
+        let node_id = file.superblock().node_id;
+        Self::open_file(file, CheckpointConfig::default(), node_id, false)
     }
 
     /// Open an existing database with custom options.
@@ -359,22 +594,237 @@ impl Database {
     /// * `path` - Path to the existing database file
     /// * `pool` - Shared buffer pool for page allocations
     /// * `checkpoint_config` - Configuration for automatic checkpointing
-    /// * `node_id` - Unique identifier for this node (for distributed deployments)
+    /// * `node_id` - Unique identifier for this node (for distributed
+    ///   deployments). Must match the ID stored in the file, unless the file
+    ///   has none yet (`UNASSIGNED_NODE_ID`), in which case it is stored.
+    /// * `verify_on_open` - After recovery, compare the indexes against the
+    ///   WAL tail and keep the report (see `verification_report()`). This is a
+    ///   slow diagnostic; leave it off in production.
+    ///
+    /// # Post-conditions
+    /// - `verification_report()` is `Some` iff `verify_on_open` is set
+    /// - The file's stored node ID is `node_id`
+    ///
+    /// # Errors
+    ///
+    /// Returns `DatabaseError::NodeIdMismatch`, before recovery touches the
+    /// file, if another node ID is stored in it. Two nodes sharing a file
+    /// would issue colliding HLC timestamps.
     pub fn open_with_options(
         path: &Path,
         pool: Arc<BufferPool>,
         checkpoint_config: CheckpointConfig,
         node_id: u32,
+        verify_on_open: bool,
     ) -> Result<(Self, Option<RecoveryResult>), DatabaseError> {
+        Self::upgrade_if_needed(path, &pool)?;
+        Self::repair_if_torn(path, &pool)?;
         let mut file = DatabaseFile::open(path, pool)?;
+        let stored = file.superblock().node_id;
+        if stored != node_id && stored != UNASSIGNED_NODE_ID {
+            return Err(DatabaseError::NodeIdMismatch {
+                configured: node_id,
+                stored,
+            });
+        }
+        if stored != node_id {
+            file.superblock_mut().node_id = node_id;
+            file.write_superblock()?;
+            file.sync()?;
+        }
+        Self::open_file(file, checkpoint_config, node_id, verify_on_open)
+    }
+
+    /// Rewrite the file at `path` in the current format if an older format
+    /// version wrote it (see `storage::upgrade`).
+    ///
+    /// The triples, with their tombstones, are imported into a new file
+    /// beside it, followed by the transactions its WAL holds past the last
+    /// checkpoint, and the new file is checkpointed (see `replace_file`).
+    fn upgrade_if_needed(path: &Path, pool: &Arc<BufferPool>) -> Result<(), DatabaseError> {
+        #[allow(clippy::disallowed_methods)] // Arc::clone shares the pool with the new file
+        let mut legacy = LegacyFile::new(DatabaseFile::open_to_copy(path, Arc::clone(pool))?);
+        if !legacy.needs_upgrade() {
+            return Ok(());
+        }
+        let superblock = *legacy.superblock();
+        let transactions = legacy.committed_wal_tail()?;
+
+        Self::replace_file(path, pool, &superblock, ".upgrade", |db| {
+            db.clock.receive(superblock.last_checkpoint_hlc)?;
+            let mut triples = legacy.triples()?;
+            db.import_records(|| Ok(triples.next_record()?))?;
+            for records in &transactions {
+                db.apply_remote_changes(records)?;
+            }
+            db.checkpoint()?;
+            Ok(())
+        })
+    }
+
+    /// Rebuild the file at `path` if a crash tore any of its pages (see
+    /// `recovery::find_torn_pages`), which replaying the WAL cannot repair.
+    ///
+    /// A file never checkpointed still holds every transaction since its
+    /// creation in its WAL, and they are replayed in commit order. Otherwise
+    /// the triples of its primary index are imported first, followed by the
+    /// transactions past the last checkpoint. The new file is not
+    /// checkpointed, so its WAL holds the whole history again (see
+    /// `replace_file`).
+    ///
+    /// # Errors
+    /// Returns `DatabaseError::TornPages` if the superblock of a checkpointed
+    /// file is torn, since its other copy may predate pages it depends on,
+    /// or an error reading the primary index if its own pages are torn.
+    fn repair_if_torn(path: &Path, pool: &Arc<BufferPool>) -> Result<(), DatabaseError> {
+        #[allow(clippy::disallowed_methods)] // Arc::clone shares the pool with the new file
+        let mut file = DatabaseFile::open_to_copy(path, Arc::clone(pool))?;
+        let torn = recovery::find_torn_pages(&mut file)?;
+        if torn.is_empty() {
+            return Ok(());
+        }
+        let superblock = *file.superblock();
+        let checkpointed = superblock.last_checkpoint_lsn != 0;
+        if checkpointed && torn.first() == Some(&0) {
+            return Err(DatabaseError::TornPages(torn));
+        }
+        tracing::warn!(
+            "Rebuilding {} after a crash tore pages {torn:?}",
+            path.display()
+        );
+        let transactions = recovery::committed_transactions(&mut file)?;
+
+        Self::replace_file(path, pool, &superblock, ".repair", |db| {
+            db.clock.receive(superblock.last_checkpoint_hlc)?;
+            if checkpointed {
+                db.import_primary_index(&file, superblock.primary_index_root)?;
+            }
+            for records in &transactions {
+                db.apply_remote_changes(records)?;
+            }
+            Ok(())
+        })
+    }
 
-        // Run recovery if needed
-        let recovery_result = if file.has_wal() && recovery::needs_recovery(&mut file)? {
-            Some(recovery::recover(&mut file)?)
+    /// Replace the file at `path` with a new one, filled by `fill`.
+    ///
+    /// The new file is created beside the old one, its name extended with
+    /// `suffix`, keeping `superblock`'s page size, checksum algorithm, WAL
+    /// capacity and node ID. It never checkpoints on its own. Once filled, it
+    /// is renamed over the old file, so a failure leaves the old file as it
+    /// was.
+    fn replace_file(
+        path: &Path,
+        pool: &Arc<BufferPool>,
+        superblock: &Superblock,
+        suffix: &str,
+        fill: impl FnOnce(&mut Self) -> Result<(), DatabaseError>,
+    ) -> Result<(), DatabaseError> {
+        let mut new_path = path.as_os_str().to_owned();
+        new_path.push(suffix);
+        let new_path = Path::new(&new_path);
+        // Left behind by a replacement that failed
+        if new_path.exists() {
+            std::fs::remove_file(new_path).map_err(FileError::Io)?;
+        }
+        let format = FileFormat {
+            page_size: superblock.page_size as usize,
+            checksum_algorithm: superblock.checksum_algorithm,
+        };
+        let wal_capacity = if superblock.txn_log_capacity == 0 {
+            DEFAULT_WAL_CAPACITY
+        } else {
+            superblock.txn_log_capacity
+        };
+        #[allow(clippy::disallowed_methods)] // Arc::clone shares the pool with the old file
+        let db = Self::create_with_options(
+            new_path,
+            Arc::clone(pool),
+            format,
+            wal_capacity,
+            CheckpointConfig::disabled(),
+            superblock.node_id,
+        );
+        let (db, ()) = Self::fill_created(new_path, db, fill)?;
+        drop(db);
+        std::fs::rename(new_path, path).map_err(FileError::Io)?;
+        Ok(())
+    }
+
+    /// Recover and verify an opened file, then build a database around it.
+    fn open_file(
+        mut file: DatabaseFile,
+        checkpoint_config: CheckpointConfig,
+        node_id: u32,
+        verify_on_open: bool,
+    ) -> Result<(Self, Option<RecoveryResult>), DatabaseError> {
+        let recovery_result = Self::recover_if_needed(&mut file)?;
+
+        let verification = if verify_on_open {
+            let report = recovery::verify(&mut file)?;
+            for divergence in &report.divergences {
+                tracing::error!("Index verification at open: {divergence}");
+            }
+            Some(report)
         } else {
             None
         };
 
+        let db = Self::from_file(file, checkpoint_config, node_id, verification)?;
+        Ok((db, recovery_result))
+    }
+
+    /// Open an existing database for reads only, e.g. to inspect a copy of a
+    /// production file.
+    ///
+    /// The file's bytes are never changed. If the database needs recovery, it
+    /// is recovered in memory: snapshots see every committed transaction, but
+    /// nothing is written back. Uses the stored node ID and never checkpoints.
+    ///
+    /// # Post-conditions
+    /// - `begin_readonly()` snapshots work as usual
+    /// - `begin()`, `begin_optimistic()`, checkpoints, GC and expiry sweeps
+    ///   return `DatabaseError::ReadOnly`
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file cannot be opened, its superblock is
+    /// invalid, or in-memory recovery fails. A file in an older format
+    /// version is rejected with `FileError::NeedsUpgrade`, and one whose
+    /// pages a crash tore with `DatabaseError::TornPages`, since only a
+    /// writable open upgrades or rebuilds it. A torn superblock is reported
+    /// as invalid.
+    pub fn open_readonly(
+        path: &Path,
+        pool: Arc<BufferPool>,
+    ) -> Result<(Self, Option<RecoveryResult>), DatabaseError> {
+        let mut file = DatabaseFile::open_readonly(path, pool)?;
+        let torn = recovery::find_torn_pages(&mut file)?;
+        if !torn.is_empty() {
+            return Err(DatabaseError::TornPages(torn));
+        }
+        let recovery_result = Self::recover_if_needed(&mut file)?;
+        let node_id = file.superblock().node_id;
+        let db = Self::from_file(file, CheckpointConfig::disabled(), node_id, None)?;
+        Ok((db, recovery_result))
+    }
+
+    /// Run crash recovery on a freshly opened file if its WAL needs it.
+    fn recover_if_needed(file: &mut DatabaseFile) -> Result<Option<RecoveryResult>, DatabaseError> {
+        if file.has_wal() && recovery::needs_recovery(file)? {
+            Ok(Some(recovery::recover(file)?))
+        } else {
+            Ok(None)
+        }
+    }
+
+    /// Build a database around an opened and recovered file.
+    fn from_file(
+        mut file: DatabaseFile,
+        checkpoint_config: CheckpointConfig,
+        node_id: u32,
+        verification: Option<VerificationReport>,
+    ) -> Result<Self, DatabaseError> {
         let checkpoint_state = CheckpointState::from_database(&file, checkpoint_config);
 
         // Initialize clock from last checkpoint timestamp
@@ -401,18 +851,45 @@ impl Database {
             tombstone_list.load_head_slot(&mut file)?;
         }
 
-        Ok((
-            Self {
-                file,
-                checkpoint_state,
-                clock,
-                active_snapshots: ActiveSnapshots::default(),
-                change_tx,
-                tombstone_list,
-                gc_notify: Arc::new(tokio::sync::Notify::new()),
-            },
-            recovery_result,
-        ))
+        Ok(Self {
+            file,
+            checkpoint_state,
+            clock,
+            active_snapshots: ActiveSnapshots::default(),
+            change_tx,
+            tombstone_list,
+            gc_notify: Arc::new(tokio::sync::Notify::new()),
+            checkpoint_notify: Arc::new(tokio::sync::Notify::new()),
+            verification,
+            triggers: AttributeTriggers::default(),
+            // Unknown until the first sweep scans the primary index
+            next_expiry_ms: Some(0),
+            entity_versions: false,
+            max_wal_capacity: None,
+        })
+    }
+
+    /// Whether the database was opened with `open_readonly`.
+    #[must_use]
+    pub const fn is_readonly(&self) -> bool {
+        self.file.is_readonly()
+    }
+
+    /// Whether the database was created with `create_in_memory` or
+    /// `create_in_memory_with_options`.
+    #[must_use]
+    pub const fn is_in_memory(&self) -> bool {
+        self.file.is_in_memory()
+    }
+
+    /// Fail with `DatabaseError::ReadOnly` if the database was opened
+    /// read-only.
+    const fn ensure_writable(&self) -> Result<(), DatabaseError> {
+        if self.is_readonly() {
+            Err(DatabaseError::ReadOnly)
+        } else {
+            Ok(())
+        }
     }
 
     /// Open an existing database or create a new one if it doesn't exist.
@@ -428,12 +905,48 @@ impl Database {
         }
     }
 
+    /// Open the database at `path` as node `node_id`, creating it if it does
+    /// not exist.
+    ///
+    /// # Errors
+    ///
+    /// Returns `DatabaseError::NodeIdMismatch` if the file exists and another
+    /// node ID is stored in it (see `open_with_options`).
+    pub fn open_or_create_with_node_id(
+        path: &Path,
+        pool: Arc<BufferPool>,
+        node_id: u32,
+    ) -> Result<(Self, Option<RecoveryResult>), DatabaseError> {
+        if path.exists() {
+            Self::open_with_options(path, pool, CheckpointConfig::default(), node_id, false)
+        } else {
+            let db = Self::create_with_options(
+                path,
+                pool,
+                FileFormat::default(),
+                DEFAULT_WAL_CAPACITY,
+                CheckpointConfig::default(),
+                node_id,
+            )?;
+            Ok((db, None))
+        }
+    }
+
+    /// Iterate over the WAL records of the database file at `path`.
+    ///
+    /// Read-only and independent of any open handle; see
+    /// `DatabaseFile::wal_records`.
+    pub fn wal_records(path: &Path) -> Result<WalRecords<File>, DatabaseError> {
+        Ok(DatabaseFile::wal_records(path)?)
+    }
+
     /// Begin a new write transaction.
     ///
     /// The transaction buffers all operations and writes to WAL on commit.
     /// The transaction is assigned a unique HLC timestamp.
     ///
     /// Only one write transaction can be active at a time (enforced by borrow checker).
+    /// Use `begin_optimistic()` to build transactions concurrently instead.
     ///
     /// # Arguments
     ///
@@ -441,6 +954,10 @@ impl Database {
     ///   This is included in change notifications so subscribers can filter out
     ///   their own writes.
     ///
+    /// # Errors
+    /// Returns `DatabaseError::ReadOnly` if the database was opened with
+    /// `open_readonly`.
+    ///
     /// # Panics
     /// Panics if transaction ID is 0 (indicates uninitialized database state).
     #[allow(clippy::disallowed_methods)] // Clone needed for broadcast sender
@@ -448,6 +965,8 @@ impl Database {
         &mut self,
         connection_id: ConnectionId,
     ) -> Result<WalTransaction<'_>, DatabaseError> {
+        self.ensure_writable()?;
+
         // Get next transaction ID
         let txn_id = self.file.superblock().next_txn_id;
 
@@ -465,7 +984,12 @@ impl Database {
             &mut self.checkpoint_state,
             &mut self.clock,
             &mut self.tombstone_list,
+            &self.triggers,
+            &mut self.next_expiry_ms,
+            self.entity_versions,
+            self.max_wal_capacity,
             Arc::clone(&self.gc_notify),
+            Arc::clone(&self.checkpoint_notify),
             txn_id,
             hlc,
             self.change_tx.clone(),
@@ -495,23 +1019,32 @@ impl Database {
     /// let path = Path::new("/tmp/my_database");
     /// let mut db = Database::create(path, pool).unwrap();
     ///
-    /// let mut snapshot = db.begin_readonly();
+    /// let mut snapshot = db.begin_readonly().unwrap();
     /// let entity = EntityId([1u8; 16]);
     /// let attr = AttributeId([2u8; 16]);
     /// let record = snapshot.get(&entity, &attr);
-    /// let txn_id = snapshot.close(); // Returns the snapshot's txn_id
-    /// db.release_snapshot(txn_id);   // Allow garbage collection
+    /// let txn_id = snapshot.close();       // Returns the snapshot's txn_id
+    /// db.release_snapshot(txn_id).unwrap(); // Allow garbage collection
     /// ```
-    #[cfg(unix)]
-    pub fn begin_readonly(&self) -> Snapshot<'_> {
+    ///
+    /// # Errors
+    /// Returns `DatabaseError::LockPoisoned` if the snapshot registry's lock
+    /// is poisoned.
+    pub fn begin_readonly(&self) -> Result<Snapshot<'_>, DatabaseError> {
         // Snapshot sees all committed transactions (next_txn_id - 1)
         let txn_id = self.file.superblock().next_txn_id.saturating_sub(1);
         let hlc = self.clock.last();
 
         // Register the snapshot for garbage collection tracking
-        self.active_snapshots.register(txn_id);
+        self.active_snapshots.register(txn_id)?;
 
-        Snapshot::new(&self.file, txn_id, hlc)
+        Ok(Snapshot::new(
+            &self.file,
+            &self.active_snapshots,
+            txn_id,
+            hlc,
+            self.expiry_now_ms(),
+        ))
     }
 
     /// Release a snapshot and allow garbage collection.
@@ -519,55 +1052,413 @@ impl Database {
     /// Call this after closing a snapshot to remove it from the active
     /// snapshot list. This allows deleted records that were visible to
     /// this snapshot to be garbage collected.
-    pub fn release_snapshot(&self, txn_id: TxnId) {
-        self.active_snapshots.unregister(txn_id);
-    }
-
-    /// Get the minimum active snapshot transaction ID.
     ///
-    /// Returns None if there are no active snapshots.
-    /// This is used for garbage collection - deleted records with
-    /// `deleted_txn` less than this value can be physically removed.
-    #[must_use]
-    pub fn min_active_snapshot(&self) -> Option<TxnId> {
-        self.active_snapshots.min_active()
+    /// # Errors
+    /// Returns `DatabaseError::LockPoisoned` if the snapshot registry's lock
+    /// is poisoned.
+    pub fn release_snapshot(&self, txn_id: TxnId) -> Result<(), DatabaseError> {
+        self.active_snapshots.unregister(txn_id)
     }
 
-    /// Get the count of active read-only snapshots.
+    /// Resume a snapshot pinned with `Snapshot::pin`.
+    ///
+    /// The returned snapshot sees exactly the view the pinned snapshot saw. It
+    /// shares the pin's garbage collection registration, so close it without
+    /// calling `release_snapshot()`.
+    ///
+    /// # Pre-conditions
+    /// - `pinned` came from a snapshot of this database and has not been unpinned
     #[must_use]
-    pub fn active_snapshot_count(&self) -> usize {
-        self.active_snapshots.count()
+    pub const fn resume_snapshot(&self, pinned: PinnedSnapshot) -> Snapshot<'_> {
+        Snapshot::new(
+            &self.file,
+            &self.active_snapshots,
+            pinned.txn_id,
+            pinned.hlc,
+            pinned.now_ms,
+        )
     }
 
-    /// Get the current checkpoint state.
-    #[must_use]
-    pub const fn checkpoint_state(&self) -> &CheckpointState {
-        &self.checkpoint_state
+    /// Release a pinned snapshot and allow garbage collection.
+    ///
+    /// # Panics
+    /// Panics if `pinned` was already unpinned.
+    ///
+    /// # Errors
+    /// Returns `DatabaseError::LockPoisoned` if the snapshot registry's lock
+    /// is poisoned.
+    pub fn unpin_snapshot(&self, pinned: PinnedSnapshot) -> Result<(), DatabaseError> {
+        self.active_snapshots.unregister(pinned.txn_id)
     }
 
-    /// Force a checkpoint.
-    pub fn checkpoint(&mut self) -> Result<CheckpointResult, DatabaseError> {
-        let hlc = self.clock.tick();
-        Ok(force_checkpoint(
-            &mut self.file,
-            &mut self.checkpoint_state,
-            hlc,
-        )?)
+    /// Begin an optimistic write transaction.
+    ///
+    /// Unlike `begin()`, this only needs shared access, so connections can
+    /// build transactions concurrently under a read lock. The transaction
+    /// reads from a snapshot pinned here and buffers its writes; nothing is
+    /// written until `commit_optimistic()`, which needs exclusive access only
+    /// for validation and the WAL append.
+    ///
+    /// # Post-conditions
+    /// - The transaction's snapshot is registered for garbage collection until
+    ///   it is passed to `commit_optimistic()` or `abort_optimistic()`
+    ///
+    /// # Errors
+    /// Returns `DatabaseError::ReadOnly` if the database was opened with
+    /// `open_readonly`, or `DatabaseError::LockPoisoned` if the snapshot
+    /// registry's lock is poisoned.
+    pub fn begin_optimistic(
+        &self,
+        connection_id: ConnectionId,
+    ) -> Result<OptimisticTransaction, DatabaseError> {
+        self.ensure_writable()?;
+        let snapshot = self.begin_readonly()?;
+        let pinned = snapshot.pin();
+        let txn_id = snapshot.close();
+        self.release_snapshot(txn_id)?;
+        Ok(OptimisticTransaction::new(pinned?, connection_id))
     }
 
-    /// Close the database cleanly.
+    /// Validate and commit an optimistic transaction.
     ///
-    /// Performs a final checkpoint to minimize recovery time on next open.
-    pub fn close(mut self) -> Result<(), DatabaseError> {
-        if self.file.has_wal() {
-            let hlc = self.clock.tick();
-            force_checkpoint(&mut self.file, &mut self.checkpoint_state, hlc)?;
+    /// Validation fails if any triple the transaction read or wrote was
+    /// written by a transaction that committed after it began, or if any
+    /// attribute it scanned gained, changed or lost a triple since. Scans
+    /// are validated as whole attributes, so a concurrent insert that would
+    /// have matched the scan (a phantom) fails validation too. This makes
+    /// optimistic transactions at `IsolationLevel::Snapshot` serializable
+    /// for every read made through the transaction: each commits as if it
+    /// ran alone at its commit point. Reads made on other snapshots, such as
+    /// queries, are not part of the transaction and are not validated.
+    /// Transactions touching disjoint triples and attributes never conflict.
+    /// At `IsolationLevel::ReadCommitted` no reads are recorded, so only
+    /// writes are validated: lost updates are still prevented, but write
+    /// skew and phantoms are not.
+    ///
+    /// # Errors
+    /// Returns `DatabaseError::WriteConflict` naming the first conflicting
+    /// triple; nothing is written and the caller may retry with a fresh
+    /// transaction. Otherwise returns any error from `WalTransaction::commit`.
+    ///
+    /// # Post-conditions
+    /// - The transaction's snapshot is unpinned, whether or not it committed
+    pub fn commit_optimistic(
+        &mut self,
+        mut optimistic: OptimisticTransaction,
+    ) -> Result<CommitOutcome, DatabaseError> {
+        optimistic.finalized = true;
+        self.unpin_snapshot(optimistic.snapshot)?;
+        let snapshot_txn = optimistic.snapshot.snapshot_txn();
+
+        let mut txn = self.begin(optimistic.connection_id)?;
+        let hlc = txn.hlc();
+
+        // Validate every key once; the read set already covers most writes
+        let mut validated = HashSet::new();
+        let write_keys = optimistic.writes.iter().map(OptimisticWrite::key);
+        for (entity_id, attribute_id) in optimistic.reads.iter().copied().chain(write_keys) {
+            if !validated.insert((entity_id, attribute_id)) {
+                continue;
+            }
+            let last_write_txn = txn
+                .get_values_including_deleted(&entity_id, &attribute_id)?
+                .iter()
+                .map(|record| record.created_txn.max(record.deleted_txn))
+                .max();
+            if last_write_txn.is_some_and(|last_write_txn| last_write_txn > snapshot_txn) {
+                txn.abort();
+                return Err(DatabaseError::WriteConflict {
+                    entity_id,
+                    attribute_id,
+                });
+            }
         }
-        self.file.sync()?;
-        Ok(())
-    }
-
-    /// Get the next LSN that will be assigned.
+        for attribute_id in optimistic.scanned_attributes.iter().copied() {
+            if let Some(entity_id) = txn.attribute_written_after(&attribute_id, snapshot_txn)? {
+                txn.abort();
+                return Err(DatabaseError::WriteConflict {
+                    entity_id,
+                    attribute_id,
+                });
+            }
+        }
+
+        for write in std::mem::take(&mut optimistic.writes) {
+            let buffered = match write {
+                OptimisticWrite::Insert {
+                    entity_id,
+                    attribute_id,
+                    value,
+                    hlc: write_hlc,
+                } => txn.insert_with_hlc(entity_id, attribute_id, value, write_hlc.unwrap_or(hlc)),
+                OptimisticWrite::Update {
+                    entity_id,
+                    attribute_id,
+                    value,
+                    hlc: write_hlc,
+                } => txn.update_with_hlc(entity_id, attribute_id, value, write_hlc.unwrap_or(hlc)),
+                OptimisticWrite::Delete {
+                    entity_id,
+                    attribute_id,
+                    hlc: write_hlc,
+                } => txn.delete_with_hlc(entity_id, attribute_id, write_hlc.unwrap_or(hlc)),
+            };
+            if let Err(e) = buffered {
+                txn.abort();
+                return Err(e);
+            }
+        }
+        txn.commit()
+    }
+
+    /// Discard an optimistic transaction and unpin its snapshot.
+    ///
+    /// # Errors
+    /// Returns `DatabaseError::LockPoisoned` if the snapshot registry's lock
+    /// is poisoned.
+    pub fn abort_optimistic(
+        &self,
+        mut optimistic: OptimisticTransaction,
+    ) -> Result<(), DatabaseError> {
+        optimistic.finalized = true;
+        self.unpin_snapshot(optimistic.snapshot)
+    }
+
+    /// Get the minimum active snapshot transaction ID.
+    ///
+    /// Returns None if there are no active snapshots.
+    /// This is used for garbage collection - deleted records with
+    /// `deleted_txn` less than this value can be physically removed.
+    ///
+    /// # Errors
+    /// Returns `DatabaseError::LockPoisoned` if the snapshot registry's lock
+    /// is poisoned.
+    pub fn min_active_snapshot(&self) -> Result<Option<TxnId>, DatabaseError> {
+        self.active_snapshots.min_active()
+    }
+
+    /// Get the count of active read-only snapshots.
+    ///
+    /// # Errors
+    /// Returns `DatabaseError::LockPoisoned` if the snapshot registry's lock
+    /// is poisoned.
+    pub fn active_snapshot_count(&self) -> Result<usize, DatabaseError> {
+        self.active_snapshots.count()
+    }
+
+    /// Keep a version number for every entity from now on.
+    ///
+    /// Each later commit that changes an entity's triples bumps its version,
+    /// stored as a triple under `ENTITY_VERSION_ATTRIBUTE_ID`. See the
+    /// `entity_versions` module. The setting is not stored in the file, so
+    /// enable it every time the database is opened.
+    pub const fn enable_entity_versions(&mut self) {
+        self.entity_versions = true;
+    }
+
+    /// Whether commits keep entity versions; see `enable_entity_versions`.
+    #[must_use]
+    pub const fn entity_versions_enabled(&self) -> bool {
+        self.entity_versions
+    }
+
+    /// Let commits grow the WAL up to `max_capacity` bytes.
+    ///
+    /// A transaction whose records do not fit in the WAL then checkpoints and
+    /// grows it (see `DatabaseFile::grow_wal`), at least doubling its
+    /// capacity, instead of failing with `DatabaseError::TransactionTooLarge`
+    /// or `DatabaseError::ValueTooLarge`. Writes too large for `max_capacity`
+    /// still fail. The grown capacity is stored in the file, but the limit is
+    /// not, so enable growth every time the database is opened.
+    pub const fn enable_wal_growth(&mut self, max_capacity: u64) {
+        self.max_wal_capacity = Some(max_capacity);
+    }
+
+    /// Capacity commits may grow the WAL to, or `None` if it is fixed; see
+    /// `enable_wal_growth`.
+    #[must_use]
+    pub const fn max_wal_capacity(&self) -> Option<u64> {
+        self.max_wal_capacity
+    }
+
+    /// Register a trigger fired when a transaction changes a triple with
+    /// `attribute_id`.
+    ///
+    /// The trigger runs inside `commit()` and can buffer more writes into the
+    /// same transaction. See the `triggers` module for ordering and limits.
+    pub fn register_trigger<F>(&mut self, attribute_id: AttributeId, trigger: F) -> TriggerId
+    where
+        F: Fn(&TriggerEvent<'_>, &mut TriggerContext<'_>) -> Result<(), DatabaseError>
+            + Send
+            + Sync
+            + 'static,
+    {
+        self.triggers.register(attribute_id, Box::new(trigger))
+    }
+
+    /// Remove a trigger. Returns whether it was registered.
+    pub fn unregister_trigger(&mut self, id: TriggerId) -> bool {
+        self.triggers.unregister(id)
+    }
+
+    /// Get the index verification report from open.
+    ///
+    /// `None` unless the database was opened with `verify_on_open`.
+    #[must_use]
+    pub const fn verification_report(&self) -> Option<&VerificationReport> {
+        self.verification.as_ref()
+    }
+
+    /// Check that the secondary indexes agree with the primary index.
+    ///
+    /// Scans all three indexes; see `recovery::check_index_consistency`. This
+    /// is a slow diagnostic meant for tests and simulation.
+    ///
+    /// # Post-conditions
+    /// - Returns an empty list iff every live triple is in all three indexes
+    pub fn check_index_consistency(&self) -> Result<Vec<IndexMismatch>, DatabaseError> {
+        Ok(recovery::check_index_consistency(&self.file)?)
+    }
+
+    /// Get the current checkpoint state.
+    #[must_use]
+    pub const fn checkpoint_state(&self) -> &CheckpointState {
+        &self.checkpoint_state
+    }
+
+    /// Force a checkpoint.
+    pub fn checkpoint(&mut self) -> Result<CheckpointResult, DatabaseError> {
+        self.ensure_writable()?;
+        let hlc = self.clock.tick();
+        Ok(force_checkpoint(
+            &mut self.file,
+            &mut self.checkpoint_state,
+            hlc,
+        )?)
+    }
+
+    /// Force a checkpoint, reporting progress and honoring cancellation
+    /// through `control`.
+    ///
+    /// A cancelled checkpoint is abandoned before it is applied, leaving the
+    /// database as it was; calling this again performs it in full.
+    ///
+    /// # Errors
+    ///
+    /// Returns `DatabaseError::Checkpoint(CheckpointError::Cancelled)` if
+    /// cancelled.
+    pub fn checkpoint_with(
+        &mut self,
+        control: CheckpointControl<'_>,
+    ) -> Result<CheckpointResult, DatabaseError> {
+        self.ensure_writable()?;
+        let hlc = self.clock.tick();
+        Ok(perform_checkpoint_with(
+            &mut self.file,
+            &mut self.checkpoint_state,
+            hlc,
+            control,
+        )?)
+    }
+
+    /// Checkpoint, then shrink the WAL region to `capacity` bytes.
+    ///
+    /// After the checkpoint the only live record is the checkpoint record,
+    /// which is moved to the start of the region. Pages past the new capacity
+    /// are truncated from the file if the region ends it, and otherwise kept
+    /// as free pages. A crash during compaction recovers against either the
+    /// old or the new region. See `DatabaseFile::compact_wal`.
+    ///
+    /// # Post-conditions
+    /// - The WAL capacity is at most its previous value and at least
+    ///   `MIN_WAL_CAPACITY`.
+    /// - WAL history before the checkpoint is gone, so subscriptions can no
+    ///   longer backfill changes older than this call.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the checkpoint or any WAL or file I/O fails.
+    pub fn compact_wal(&mut self, capacity: u64) -> Result<WalCompaction, DatabaseError> {
+        self.checkpoint()?;
+        Ok(self.file.compact_wal(capacity)?)
+    }
+
+    /// Checkpoint, then resize the WAL region to `capacity` bytes.
+    ///
+    /// A larger capacity grows the region (see `DatabaseFile::grow_wal`),
+    /// moving it to the end of the file unless it already ends it. A smaller
+    /// one compacts it as `compact_wal` does. Either way a crash recovers
+    /// against the old or the new region.
+    ///
+    /// # Returns
+    /// The WAL capacity after resizing, rounded to whole pages.
+    ///
+    /// # Post-conditions
+    /// - The WAL capacity is at least `MIN_WAL_CAPACITY`.
+    /// - WAL history before the checkpoint is gone, so subscriptions can no
+    ///   longer backfill changes older than this call.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the checkpoint or any WAL or file I/O fails.
+    pub fn resize_wal(&mut self, capacity: u64) -> Result<u64, DatabaseError> {
+        self.checkpoint()?;
+        if capacity > self.file.wal_capacity() {
+            self.file.grow_wal(capacity)?;
+        } else {
+            self.file.compact_wal(capacity)?;
+        }
+        Ok(self.file.wal_capacity())
+    }
+
+    /// Capacity of the WAL region in bytes, or 0 if the database has no WAL.
+    #[must_use]
+    pub const fn wal_capacity(&self) -> u64 {
+        self.file.superblock().txn_log_capacity
+    }
+
+    /// Checkpoint if the configured thresholds have been crossed.
+    ///
+    /// Called by the background checkpoint task when signaled. Returns `None`
+    /// if no checkpoint was due, e.g. because a commit already checkpointed
+    /// inline under WAL pressure.
+    pub fn checkpoint_if_due(&mut self) -> Result<Option<CheckpointResult>, DatabaseError> {
+        if self.is_readonly() || !self.file.has_wal() || !self.checkpoint_state.should_checkpoint()
+        {
+            return Ok(None);
+        }
+        let hlc = self.clock.tick();
+        Ok(maybe_checkpoint(
+            &mut self.file,
+            &mut self.checkpoint_state,
+            hlc,
+        )?)
+    }
+
+    /// Get a clone of the checkpoint notify handle.
+    ///
+    /// This is used by the background checkpoint task to wait for signals
+    /// that a checkpoint is due.
+    #[must_use]
+    #[allow(clippy::disallowed_methods)] // Arc::clone is needed for async task
+    pub fn checkpoint_notify(&self) -> Arc<tokio::sync::Notify> {
+        Arc::clone(&self.checkpoint_notify)
+    }
+
+    /// Close the database cleanly.
+    ///
+    /// Performs a final checkpoint to minimize recovery time on next open,
+    /// unless the database was opened read-only.
+    pub fn close(mut self) -> Result<(), DatabaseError> {
+        if self.file.has_wal() && !self.is_readonly() {
+            let hlc = self.clock.tick();
+            force_checkpoint(&mut self.file, &mut self.checkpoint_state, hlc)?;
+        }
+        self.file.sync()?;
+        Ok(())
+    }
+
+    /// Get the next LSN that will be assigned.
     pub fn next_lsn(&mut self) -> Result<Lsn, DatabaseError> {
         if !self.file.has_wal() {
             return Ok(0);
@@ -584,6 +1475,15 @@ impl Database {
         self.clock.last()
     }
 
+    /// Issue a new HLC timestamp from the database clock, later than every
+    /// timestamp it issued before.
+    ///
+    /// This stamps writes the server makes on behalf of clients that do not
+    /// keep a clock of their own.
+    pub fn next_hlc(&mut self) -> HlcTimestamp {
+        self.clock.tick()
+    }
+
     /// Get this database's node ID.
     #[must_use]
     pub const fn node_id(&self) -> u32 {
@@ -605,10 +1505,7 @@ impl Database {
     ///
     /// Returns WAL records with HLC >= the given timestamp.
     /// This is useful for subscription queries ("what changed since X").
-    pub fn changes_since(
-        &mut self,
-        since: HlcTimestamp,
-    ) -> Result<Vec<crate::storage::wal::LogRecord>, DatabaseError> {
+    pub fn changes_since(&mut self, since: HlcTimestamp) -> Result<Vec<LogRecord>, DatabaseError> {
         if !self.file.has_wal() {
             return Ok(Vec::new());
         }
@@ -616,105 +1513,735 @@ impl Database {
         Ok(wal.changes_since(since)?)
     }
 
-    /// Subscribe to change notifications.
+    /// Get the committed changes with an HLC from `from` to `to`, inclusive.
     ///
-    /// Returns a receiver that will receive all change notifications broadcast
-    /// after this call. Use this to implement real-time subscriptions.
-    ///
-    /// Multiple subscribers can exist simultaneously - each receives all changes.
-    #[must_use]
-    /// Subscribe to change notifications, filtering out this connection's own writes.
+    /// Returns the INSERT, UPDATE and DELETE records still in the WAL whose
+    /// transaction committed, in log order. This is useful for reading a
+    /// bounded slice of history ("what changed between X and Y").
     ///
-    /// Returns a filtered receiver that only yields notifications from other connections.
-    /// Notifications originating from the specified `connection_id` are automatically skipped.
-    pub fn subscribe_to_changes(&self, connection_id: ConnectionId) -> FilteredChangeReceiver {
-        FilteredChangeReceiver::new(self.change_tx.subscribe(), connection_id)
+    /// # Post-conditions
+    /// - Empty if `from` is after `to`, or if no change in the WAL falls
+    ///   between them
+    pub fn changes_between(
+        &mut self,
+        from: HlcTimestamp,
+        to: HlcTimestamp,
+    ) -> Result<Vec<LogRecord>, DatabaseError> {
+        if !self.file.has_wal() {
+            return Ok(Vec::new());
+        }
+        let mut wal = self.file.wal()?;
+        Ok(wal.changes_between(from, to)?)
     }
 
-    /// Get a clone of the GC notify handle.
+    /// Apply another node's committed change records, as a follower applies
+    /// its leader's `changes_since` stream.
     ///
-    /// This is used by the background GC task to wait for signals that
-    /// there is work to be done.
-    #[must_use]
-    #[allow(clippy::disallowed_methods)] // Arc::clone is needed for async task
-    pub fn gc_notify(&self) -> Arc<tokio::sync::Notify> {
-        Arc::clone(&self.gc_notify)
-    }
+    /// Advances the local clock past the newest remote HLC, then writes every
+    /// INSERT, UPDATE and DELETE record in one local transaction under its
+    /// original HLC. Each write therefore resolves last-writer-wins against
+    /// local state exactly as on its origin node, so nodes that exchange
+    /// changes converge regardless of arrival order. Applied changes are
+    /// broadcast to local subscribers. BEGIN, COMMIT and CHECKPOINT records
+    /// are skipped.
+    ///
+    /// # Errors
+    ///
+    /// Returns `DatabaseError::Clock` if a remote HLC is too far ahead of the
+    /// local clock, in which case nothing is applied.
+    pub fn apply_remote_changes(
+        &mut self,
+        records: &[LogRecord],
+    ) -> Result<CommitOutcome, DatabaseError> {
+        let mut writes = Vec::with_capacity(records.len());
+        for record in records {
+            let write = match &record.payload {
+                LogRecordPayload::Insert(_) | LogRecordPayload::Update(_) => {
+                    let Some(triple) = record.triple_record()? else {
+                        continue;
+                    };
+                    let hlc = triple.created_hlc;
+                    let value = triple.value;
+                    let remote =
+                        TripleRecord::new(triple.entity_id, triple.attribute_id, 0, hlc, value)
+                            .with_expiry(triple.expires_at_ms);
+                    if matches!(record.payload, LogRecordPayload::Insert(_)) {
+                        (hlc, PendingTriple::Insert(remote))
+                    } else {
+                        (hlc, PendingTriple::Update(remote))
+                    }
+                }
+                LogRecordPayload::Delete {
+                    entity_id,
+                    attribute_id,
+                    value_hash,
+                    hlc,
+                } => (
+                    *hlc,
+                    PendingTriple::Delete {
+                        entity_id: *entity_id,
+                        attribute_id: *attribute_id,
+                        value_hash: *value_hash,
+                        hlc: *hlc,
+                    },
+                ),
+                LogRecordPayload::Begin
+                | LogRecordPayload::Commit
+                | LogRecordPayload::Checkpoint { .. } => continue,
+            };
+            writes.push(write);
+        }
 
-    /// Get statistics about pending garbage collection.
-    #[must_use]
-    pub fn gc_stats(&self) -> GcStats {
-        GcStats {
-            pending_tombstones: self.tombstone_list.count(),
-            min_active_snapshot: self.active_snapshots.min_active(),
+        let newest = writes
+            .iter()
+            .map(|(hlc, _)| *hlc)
+            .max_by(|a, b| Clock::<SystemTimeSource>::compare(*a, *b));
+        if let Some(newest) = newest {
+            self.clock.receive(newest)?;
+        }
+
+        let mut txn = self.begin(SYSTEM_CONNECTION_ID)?;
+        for (_, mut write) in writes {
+            if let PendingTriple::Insert(record) | PendingTriple::Update(record) = &mut write {
+                record.created_txn = txn.txn_id;
+            }
+            txn.operations.push(write);
         }
+        txn.commit()
     }
 
-    /// Process a batch of eligible tombstones.
+    /// Load triples in bulk, as a single transaction.
     ///
-    /// This is called by the background GC task to incrementally process
-    /// tombstones. It removes records from all indexes and updates the
-    /// tombstone list.
+    /// Committing triples one at a time syncs the WAL and writes the
+    /// superblock for every commit. A bulk load writes every triple to the
+    /// WAL as one transaction with a single sync, and applies them to the
+    /// indexes with each index page written once, before one final
+    /// superblock write.
+    /// Recovery replays the load only if its COMMIT record reached the WAL, so
+    /// after a crash either every triple is recovered or none is.
     ///
-    /// # Arguments
-    /// * `batch_size` - Maximum number of tombstones to process
+    /// The input is streamed: it is read `BULK_LOAD_CHUNK_TRIPLES` at a time,
+    /// and each chunk is sorted by key before it is buffered in the
+    /// transaction. The transaction must fit in the WAL, so a load too large
+    /// for it fails at the first chunk past the limit, without reading the
+    /// rest of the input.
     ///
-    /// # Returns
-    /// Statistics about the GC operation.
-    pub fn gc_tick(&mut self, batch_size: usize) -> Result<GcTickResult, DatabaseError> {
-        let min_active = self.active_snapshots.min_active();
-
-        // Pop eligible tombstones from the list
-        let tombstones = self
-            .tombstone_list
-            .pop_batch(&mut self.file, min_active, batch_size)?;
-
-        if tombstones.is_empty() {
-            return Ok(GcTickResult {
-                records_removed: 0,
-                tombstones_remaining: self.tombstone_list.count(),
-            });
+    /// Each triple is written like `insert_with_hlc`: it overwrites a stored
+    /// value only if its HLC is newer. Subscribers receive the whole load as
+    /// one change notification from `connection_id`.
+    ///
+    /// # Errors
+    ///
+    /// Returns `DatabaseError::Clock` if an HLC is too far ahead of the local
+    /// clock, or `DatabaseError::TransactionTooLarge` if the load does not fit
+    /// in the WAL. Either way, nothing is written.
+    pub fn bulk_load(
+        &mut self,
+        connection_id: ConnectionId,
+        triples: impl IntoIterator<Item = PendingTripleData>,
+    ) -> Result<CommitOutcome, DatabaseError> {
+        let mut triples = triples.into_iter().peekable();
+        let mut txn = self.begin(connection_id)?;
+        // Sorted inserts land on each index page many times in a row
+        txn.defer_page_writes = true;
+        while triples.peek().is_some() {
+            let mut chunk: Vec<PendingTripleData> =
+                triples.by_ref().take(BULK_LOAD_CHUNK_TRIPLES).collect();
+            // Stable, so several writes to one key keep their order
+            chunk.sort_by_key(|triple| (triple.entity_id, triple.attribute_id.0));
+            if let Err(e) = txn.buffer_bulk_load_chunk(chunk) {
+                txn.abort();
+                return Err(e);
+            }
         }
+        txn.commit()
+    }
 
-        let records_removed = tombstones.len() as u64;
-
-        // Remove from all indexes
-        self.remove_tombstoned_records(&tombstones)?;
+    /// Export the live triples of a consistent snapshot as a portable backup.
+    ///
+    /// The triples are read through a read-only snapshot, so the backup holds
+    /// exactly the state as of its start: commits made while it is written
+    /// are left out. Attribute cardinality declarations come first, so
+    /// `import_snapshot` knows each attribute's cardinality before its
+    /// triples. The format is described in `storage::backup`.
+    ///
+    /// Returns the number of triples exported.
+    ///
+    /// # Errors
+    /// Returns `DatabaseError::Backup` if the backup cannot be written, or an
+    /// error if the snapshot cannot be read.
+    pub fn export_snapshot(&self, writer: impl Write) -> Result<u64, DatabaseError> {
+        let snapshot = self.begin_readonly()?;
+        let exported = export_snapshot_records(&snapshot, writer);
+        let txn_id = snapshot.close();
+        self.release_snapshot(txn_id)?;
+        exported
+    }
 
-        // Persist tombstone list state
-        self.persist_tombstone_metadata()?;
+    /// Create a database at `path` from a backup written by
+    /// `export_snapshot`.
+    ///
+    /// The path must not already exist. Triples are committed in
+    /// transactions as large as the WAL comfortably holds, each applied to
+    /// the primary, attribute and entity-attribute indexes, and keep their
+    /// value, HLC and expiry. The clock is advanced past the backup's HLC, so
+    /// later writes win over every restored triple.
+    ///
+    /// # Errors
+    /// Returns `DatabaseError::Backup` if the backup is malformed, truncated
+    /// or unreadable, `DatabaseError::Clock` if its HLC is too far ahead of
+    /// the local clock, or an error if the database cannot be written. On
+    /// error, the partly imported file is removed.
+    pub fn import_snapshot(
+        path: &Path,
+        pool: Arc<BufferPool>,
+        reader: impl Read,
+    ) -> Result<Self, DatabaseError> {
+        Self::import_snapshot_with_options(
+            path,
+            pool,
+            FileFormat::default(),
+            DEFAULT_WAL_CAPACITY,
+            CheckpointConfig::default(),
+            DEFAULT_NODE_ID,
+            reader,
+        )
+    }
 
-        Ok(GcTickResult {
-            records_removed,
-            tombstones_remaining: self.tombstone_list.count(),
-        })
+    /// Create a database with custom options from a backup written by
+    /// `export_snapshot`.
+    ///
+    /// The backup does not depend on the source's page size or checksum
+    /// algorithm, so `format` may differ from it. The other arguments are as
+    /// for `create_with_options`, and the import as for `import_snapshot`.
+    pub fn import_snapshot_with_options(
+        path: &Path,
+        pool: Arc<BufferPool>,
+        format: FileFormat,
+        wal_capacity: u64,
+        checkpoint_config: CheckpointConfig,
+        node_id: u32,
+        reader: impl Read,
+    ) -> Result<Self, DatabaseError> {
+        let db =
+            Self::create_with_options(path, pool, format, wal_capacity, checkpoint_config, node_id);
+        let (db, ()) = Self::fill_created(path, db, |db| {
+            db.import_snapshot_records(BackupReader::new(reader)?)
+        })?;
+        Ok(db)
     }
 
-    /// Synchronously process all eligible tombstones.
+    /// Restore the database at `source` as of `target_hlc` into a new
+    /// database at `path`, from the backup at `backup` and the source's WAL.
     ///
-    /// Unlike the incremental `gc_tick()`, this processes all eligible
-    /// tombstones in one call. Use sparingly as it may block other operations.
-    pub fn force_gc(&mut self) -> Result<GcStats, DatabaseError> {
-        loop {
-            let result = self.gc_tick(1000)?;
-            if result.records_removed == 0 {
-                break;
-            }
+    /// Index pages are updated in place, so the source keeps no state older
+    /// than its current one to roll back to. The restore instead imports the
+    /// backup, written by `export_snapshot` at or before `target_hlc`, then
+    /// replays the transactions the source's WAL holds that committed after
+    /// the backup and at or before `target_hlc`. Commit HLCs only grow in log
+    /// order, so reading the WAL stops at the first transaction past the
+    /// target. A transaction is replayed whole or not at all, by its commit
+    /// HLC: one that commits after the target is left out even if some of
+    /// its writes carry older HLCs. Each replayed transaction is committed on
+    /// its own under its original HLCs, so the result is the consistent state
+    /// as of the target.
+    ///
+    /// The source is opened with `open_readonly`, so its file is never
+    /// changed and it may be a live database or a copy of one. The WAL is
+    /// read a batch at a time, but the transactions between the backup and
+    /// the target are held in memory while the backup is imported.
+    ///
+    /// # Errors
+    /// - `DatabaseError::Backup` if the backup cannot be opened or read
+    /// - `DatabaseError::RestoreTargetBeforeBackup` if the backup was taken
+    ///   after `target_hlc`
+    /// - An error from `open_readonly` if the source cannot be opened
+    /// - `DatabaseError::WalHistoryUnavailable` if the source's WAL no longer
+    ///   holds every transaction committed since the backup, for example
+    ///   after it wrapped or was compacted; take a newer backup
+    /// - An error from `import_snapshot` if the backup cannot be imported
+    ///
+    /// On error, nothing is left at `path`.
+    pub fn restore_to_hlc(
+        source: &Path,
+        backup: &Path,
+        path: &Path,
+        pool: Arc<BufferPool>,
+        target_hlc: HlcTimestamp,
+    ) -> Result<(Self, RestoreResult), DatabaseError> {
+        let backup = File::open(backup).map_err(BackupError::from)?;
+        let backup = BackupReader::new(BufReader::new(backup))?;
+        let backup_hlc = backup.header().hlc;
+        if Clock::<SystemTimeSource>::compare(backup_hlc, target_hlc) == Ordering::Greater {
+            return Err(DatabaseError::RestoreTargetBeforeBackup {
+                backup_hlc,
+                target_hlc,
+            });
         }
-
-        Ok(GcStats {
-            pending_tombstones: self.tombstone_list.count(),
-            min_active_snapshot: self.active_snapshots.min_active(),
+        #[allow(clippy::disallowed_methods)] // Arc::clone shares the pool with the source
+        let (mut source, _) = Self::open_readonly(source, Arc::clone(&pool))?;
+        let transactions = source.transactions_committed_between(backup_hlc, target_hlc)?;
+        drop(source);
+
+        Self::fill_created(path, Self::create(path, pool), |db| {
+            db.import_snapshot_records(backup)?;
+            for records in &transactions {
+                db.apply_remote_changes(records)?;
+            }
+            Ok(RestoreResult {
+                transactions_replayed: transactions.len(),
+            })
         })
     }
 
-    /// Remove tombstoned records from all three indexes.
-    fn remove_tombstoned_records(&mut self, tombstones: &[Tombstone]) -> Result<(), DatabaseError> {
-        if tombstones.is_empty() {
+    /// Finish creating a database by running `fill` on it, removing the file
+    /// at `path` if creating or filling fails.
+    fn fill_created<T>(
+        path: &Path,
+        created: Result<Self, DatabaseError>,
+        fill: impl FnOnce(&mut Self) -> Result<T, DatabaseError>,
+    ) -> Result<(Self, T), DatabaseError> {
+        let mut db = created?;
+        match fill(&mut db) {
+            Ok(value) => Ok((db, value)),
+            Err(e) => {
+                drop(db);
+                // Best effort: the fill error is what the caller needs
+                let _ = std::fs::remove_file(path);
+                Err(e)
+            }
+        }
+    }
+
+    /// Collect the change records of each transaction in the WAL whose commit
+    /// HLC is after `after` and at or before `until`, in commit order.
+    ///
+    /// The WAL is read `RECOVERY_BATCH_RECORDS` records at a time. BEGIN and
+    /// COMMIT records carry the clock's HLC, which only grows in log order,
+    /// so reading stops at the first one after `until`.
+    ///
+    /// # Pre-conditions
+    /// - `after` is at or before `until`
+    ///
+    /// # Errors
+    /// Returns `DatabaseError::WalHistoryUnavailable` if records of such a
+    /// transaction may have been dropped from the WAL: unless the WAL starts
+    /// at the first LSN, it must hold a BEGIN, COMMIT or CHECKPOINT record at
+    /// or before `after`, ahead of the first such transaction.
+    fn transactions_committed_between(
+        &mut self,
+        after: HlcTimestamp,
+        until: HlcTimestamp,
+    ) -> Result<Vec<Vec<LogRecord>>, DatabaseError> {
+        assert_ne!(
+            Clock::<SystemTimeSource>::compare(after, until),
+            Ordering::Greater,
+            "restore range must not end before it starts"
+        );
+        let mut scan = self.file.wal()?.scan_all();
+        let mut covered = None;
+
+        let mut transactions = Vec::new();
+        let mut open: Option<(TxnId, Vec<LogRecord>)> = None;
+        'scan: while !scan.is_done() {
+            let batch = self
+                .file
+                .wal()?
+                .read_batch(&mut scan, RECOVERY_BATCH_RECORDS)?;
+            for record in batch {
+                let covered = covered.get_or_insert(record.lsn == 1);
+                let is_after =
+                    Clock::<SystemTimeSource>::compare(record.hlc, after) == Ordering::Greater;
+                let is_past_until =
+                    Clock::<SystemTimeSource>::compare(record.hlc, until) == Ordering::Greater;
+                match record.payload {
+                    LogRecordPayload::Begin | LogRecordPayload::Commit if is_past_until => {
+                        break 'scan;
+                    }
+                    LogRecordPayload::Begin => {
+                        *covered |= !is_after;
+                        // A repeated BEGIN restarts the transaction
+                        open = Some((record.txn_id, Vec::new()));
+                    }
+                    LogRecordPayload::Commit => {
+                        *covered |= !is_after;
+                        let Some((txn_id, changes)) = open.take() else {
+                            continue;
+                        };
+                        if txn_id == record.txn_id && is_after {
+                            if !*covered {
+                                return Err(DatabaseError::WalHistoryUnavailable(after));
+                            }
+                            transactions.push(changes);
+                        }
+                    }
+                    LogRecordPayload::Checkpoint { .. } => *covered |= !is_after,
+                    LogRecordPayload::Insert(_)
+                    | LogRecordPayload::Update(_)
+                    | LogRecordPayload::Delete { .. } => {
+                        if let Some((txn_id, changes)) = &mut open
+                            && *txn_id == record.txn_id
+                        {
+                            changes.push(record);
+                        }
+                    }
+                }
+            }
+        }
+        // An empty WAL has dropped nothing
+        if !covered.unwrap_or(true) {
+            return Err(DatabaseError::WalHistoryUnavailable(after));
+        }
+        Ok(transactions)
+    }
+
+    /// Commit every record of a backup, starting a new transaction whenever
+    /// the current one would take more than half the WAL.
+    fn import_snapshot_records<R: Read>(
+        &mut self,
+        mut backup: BackupReader<R>,
+    ) -> Result<(), DatabaseError> {
+        self.clock.receive(backup.header().hlc)?;
+        self.import_records(|| Ok(backup.next_record()?))
+    }
+
+    /// Commit every triple of the primary index rooted at `root_page` in
+    /// `file`, cardinality declarations first, as a backup is imported (see
+    /// `export_snapshot_records`).
+    fn import_primary_index(
+        &mut self,
+        file: &DatabaseFile,
+        root_page: PageId,
+    ) -> Result<(), DatabaseError> {
+        if root_page == 0 {
             return Ok(());
         }
+        let primary = PrimaryIndexReader::new(file, root_page);
+        let mut declarations = primary.scan_entity(&ATTRIBUTE_CARDINALITY_ENTITY_ID)?;
+        self.import_records(|| Ok(declarations.next_record()?))?;
+        let mut cursor = primary.cursor()?;
+        self.import_records(|| {
+            while let Some(record) = cursor.next_record()? {
+                if record.entity_id != ATTRIBUTE_CARDINALITY_ENTITY_ID {
+                    return Ok(Some(record));
+                }
+            }
+            Ok(None)
+        })
+    }
+
+    /// Commit every record `next_record` returns until it returns `None`,
+    /// starting a new transaction whenever the current one would take more
+    /// than half the WAL.
+    ///
+    /// Each record keeps its value, HLC and expiry. A deleted record is
+    /// inserted and then deleted under its delete HLC, leaving a tombstone.
+    fn import_records(
+        &mut self,
+        mut next_record: impl FnMut() -> Result<Option<TripleRecord>, DatabaseError>,
+    ) -> Result<(), DatabaseError> {
+        // A transaction near the full capacity can fail to fit once the WAL
+        // head has moved, so leave room
+        let batch_bytes = self.file.wal_capacity() / 2;
+
+        let mut txn = self.begin(SYSTEM_CONNECTION_ID)?;
+        // Records arrive in key order, mostly landing on the page before
+        txn.defer_page_writes = true;
+        loop {
+            let stored = match next_record() {
+                Ok(Some(stored)) => stored,
+                Ok(None) => break,
+                Err(e) => {
+                    txn.abort();
+                    return Err(e);
+                }
+            };
+            let delete = stored.is_deleted().then_some(PendingTriple::Delete {
+                entity_id: stored.entity_id,
+                attribute_id: stored.attribute_id,
+                value_hash: stored.value_hash(),
+                hlc: stored.deleted_hlc,
+            });
+            let mut write = PendingTriple::Insert(
+                TripleRecord::new(
+                    stored.entity_id,
+                    stored.attribute_id,
+                    0,
+                    stored.created_hlc,
+                    stored.value,
+                )
+                .with_expiry(stored.expires_at_ms),
+            );
+            let write_bytes = wal_record_size(&write) + delete.as_ref().map_or(0, wal_record_size);
+            if !txn.operations.is_empty() && txn.wal_bytes + write_bytes > batch_bytes {
+                txn.commit()?;
+                txn = self.begin(SYSTEM_CONNECTION_ID)?;
+                txn.defer_page_writes = true;
+            }
+            if let PendingTriple::Insert(record) = &mut write {
+                record.created_txn = txn.txn_id;
+            }
+            for operation in std::iter::once(write).chain(delete) {
+                if let Err(e) = txn.buffer_write(operation) {
+                    txn.abort();
+                    return Err(e);
+                }
+            }
+        }
+        txn.commit()?;
+        Ok(())
+    }
+
+    /// Subscribe to change notifications.
+    ///
+    /// Returns a receiver that will receive all change notifications broadcast
+    /// after this call. Use this to implement real-time subscriptions.
+    ///
+    /// Multiple subscribers can exist simultaneously - each receives all changes.
+    #[must_use]
+    /// Subscribe to change notifications, filtering out this connection's own writes.
+    ///
+    /// Returns a filtered receiver that only yields notifications from other connections.
+    /// Notifications originating from the specified `connection_id` are automatically skipped.
+    pub fn subscribe_to_changes(&self, connection_id: ConnectionId) -> FilteredChangeReceiver {
+        FilteredChangeReceiver::new(self.change_tx.subscribe(), connection_id)
+    }
+
+    /// Get a clone of the GC notify handle.
+    ///
+    /// This is used by the background GC task to wait for signals that
+    /// there is work to be done.
+    #[must_use]
+    #[allow(clippy::disallowed_methods)] // Arc::clone is needed for async task
+    pub fn gc_notify(&self) -> Arc<tokio::sync::Notify> {
+        Arc::clone(&self.gc_notify)
+    }
+
+    /// Get statistics about pending garbage collection.
+    ///
+    /// # Errors
+    /// Returns `DatabaseError::LockPoisoned` if the snapshot registry's lock
+    /// is poisoned.
+    pub fn gc_stats(&self) -> Result<GcStats, DatabaseError> {
+        Ok(GcStats {
+            pending_tombstones: self.tombstone_list.count(),
+            min_active_snapshot: self.active_snapshots.min_active()?,
+        })
+    }
+
+    /// Count the pages of the database file by use.
+    ///
+    /// Reads every page outside the WAL region, so the cost grows with the
+    /// file size. Intended for diagnostics and tests, not hot paths.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a page cannot be read.
+    pub fn storage_stats(&mut self) -> Result<StorageStats, DatabaseError> {
+        let total_pages = self.file.total_pages();
+        let superblock = self.file.superblock();
+        let page_size = self.file.page_size() as u64;
+        let wal_start = superblock.txn_log_start / page_size;
+        let wal_end = wal_start + superblock.txn_log_capacity / page_size;
+
+        let mut stats = StorageStats {
+            total_pages,
+            overflow_pages: 0,
+            free_pages: 0,
+        };
+        // Page 0 is the superblock
+        for page_id in 1..total_pages {
+            if (wal_start..wal_end).contains(&page_id) {
+                continue;
+            }
+            let page = self.file.read_page(page_id)?;
+            match PageType::try_from(page.read_u8(0)) {
+                Ok(PageType::Overflow) => stats.overflow_pages += 1,
+                Ok(PageType::Free) => stats.free_pages += 1,
+                _ => {}
+            }
+        }
+
+        Ok(stats)
+    }
+
+    /// Estimate how much space a triple holding `value` takes in the primary
+    /// index, and whether it spills to overflow pages.
+    ///
+    /// Pure: the estimate depends only on the value, not on stored data.
+    #[must_use]
+    pub fn estimated_size_of(value: &TripleValue) -> RecordStorageSize {
+        RecordStorageSize::of_value(value)
+    }
+
+    /// Process a batch of eligible tombstones.
+    ///
+    /// This is called by the background GC task to incrementally process
+    /// tombstones. It removes records from all indexes and updates the
+    /// tombstone list.
+    ///
+    /// # Arguments
+    /// * `batch_size` - Maximum number of tombstones to process
+    ///
+    /// # Returns
+    /// Statistics about the GC operation.
+    pub fn gc_tick(&mut self, batch_size: usize) -> Result<GcTickResult, DatabaseError> {
+        self.ensure_writable()?;
+        let min_active = self.active_snapshots.min_active()?;
+
+        // Pop eligible tombstones from the list
+        let tombstones = self
+            .tombstone_list
+            .pop_batch(&mut self.file, min_active, batch_size)?;
+
+        if tombstones.is_empty() {
+            return Ok(GcTickResult {
+                records_removed: 0,
+                tombstones_processed: 0,
+                tombstones_remaining: self.tombstone_list.count(),
+            });
+        }
+
+        // Remove from all indexes
+        let records_removed = self.remove_tombstoned_records(&tombstones)?;
+
+        // Persist tombstone list state
+        self.persist_tombstone_metadata()?;
+
+        Ok(GcTickResult {
+            records_removed,
+            tombstones_processed: tombstones.len() as u64,
+            tombstones_remaining: self.tombstone_list.count(),
+        })
+    }
+
+    /// Synchronously process all eligible tombstones.
+    ///
+    /// Unlike the incremental `gc_tick()`, this processes all eligible
+    /// tombstones in one call. Use sparingly as it may block other operations.
+    pub fn force_gc(&mut self) -> Result<GcStats, DatabaseError> {
+        loop {
+            let result = self.gc_tick(1000)?;
+            if result.tombstones_processed == 0 {
+                break;
+            }
+        }
+
+        Ok(GcStats {
+            pending_tombstones: self.tombstone_list.count(),
+            min_active_snapshot: self.active_snapshots.min_active()?,
+        })
+    }
+
+    /// Tombstone up to `batch_size` expired triples.
+    ///
+    /// Expired triples already read as absent; the sweep deletes them in an
+    /// ordinary transaction so subscribers receive `Delete` changes and GC can
+    /// reclaim them. Scans the primary index only when the earliest known
+    /// expiry has passed.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the primary index cannot be read or the delete
+    /// transaction fails to commit.
+    ///
+    /// # Post-conditions
+    /// - If `more_expired` is false, every triple expired before the sweep
+    ///   started is tombstoned, except deletes that lose last-writer-wins
+    ///   against a write with a future HLC
+    pub fn expire_triples(
+        &mut self,
+        batch_size: usize,
+    ) -> Result<ExpirySweepResult, DatabaseError> {
+        self.ensure_writable()?;
+        let now_ms = self.expiry_now_ms();
+        if self.next_expiry_ms.is_none_or(|next| next > now_ms) {
+            return Ok(ExpirySweepResult {
+                triples_expired: 0,
+                more_expired: false,
+            });
+        }
+
+        let committed_txn = self.file.superblock().next_txn_id.saturating_sub(1);
+        let root_page = self.file.superblock().primary_index_root;
+        let index = PrimaryIndexReader::new(&self.file, root_page);
+        let mut cursor = index.cursor_visible(committed_txn)?;
+
+        let mut expired = Vec::new();
+        let mut next_expiry_ms: Option<u64> = None;
+        let mut more_expired = false;
+        while let Some(record) = cursor.next_record()? {
+            match record.expires_at_ms {
+                Some(_) if record.is_expired(now_ms) => {
+                    if expired.len() == batch_size {
+                        more_expired = true;
+                        break;
+                    }
+                    expired.push((record.entity_id, record.attribute_id, record.value_hash()));
+                }
+                Some(expires_at_ms) => {
+                    next_expiry_ms =
+                        Some(next_expiry_ms.map_or(expires_at_ms, |next| next.min(expires_at_ms)));
+                }
+                None => {}
+            }
+        }
+        // A sweep that stopped early has not seen every pending expiry
+        self.next_expiry_ms = if more_expired {
+            Some(now_ms)
+        } else {
+            next_expiry_ms
+        };
+
+        if expired.is_empty() {
+            return Ok(ExpirySweepResult {
+                triples_expired: 0,
+                more_expired,
+            });
+        }
+
+        let mut txn = self.begin(SYSTEM_CONNECTION_ID)?;
+        let hlc = txn.hlc();
+        for (entity_id, attribute_id, value_hash) in expired {
+            txn.buffer_delete(entity_id, attribute_id, value_hash, hlc);
+        }
+        let outcome = txn.commit()?;
+
+        Ok(ExpirySweepResult {
+            triples_expired: outcome.changes_applied as u64,
+            more_expired,
+        })
+    }
+
+    /// Physical time expiry is checked against: the wall clock, or the HLC if
+    /// remote timestamps have pushed it ahead.
+    fn expiry_now_ms(&self) -> u64 {
+        self.clock
+            .time_source()
+            .now_ms()
+            .max(self.clock.last().physical_time)
+    }
+
+    /// Remove tombstoned records from all three indexes.
+    ///
+    /// A tombstone is stale if its record was written again after the delete
+    /// (a last-writer-wins resurrection, or a later delete with its own
+    /// tombstone); stale tombstones are skipped so the live record survives.
+    /// A tombstoned cardinality-many record a later displaced record was
+    /// probed past is kept, so probes still reach that record.
+    /// Removing a record frees any overflow pages holding its value.
+    ///
+    /// Returns the number of records removed.
+    fn remove_tombstoned_records(
+        &mut self,
+        tombstones: &[Tombstone],
+    ) -> Result<u64, DatabaseError> {
+        if tombstones.is_empty() {
+            return Ok(0);
+        }
 
-        // Remove from primary index
+        // Remove from primary index, keeping only tombstones that are still current
+        let mut collected = Vec::with_capacity(tombstones.len());
         let primary_root = {
             let root_page = self.file.superblock().primary_index_root;
             if root_page == 0 {
@@ -722,23 +2249,34 @@ impl Database {
             } else {
                 let mut index = PrimaryIndex::new(&mut self.file, root_page)?;
                 for t in tombstones {
-                    index.remove(&t.entity_id, &t.attribute_id)?;
+                    let is_current = index
+                        .get_value(&t.entity_id, &t.attribute_id, t.value_hash)?
+                        .is_some_and(|record| record.deleted_txn == t.deleted_txn);
+                    // The tombstone stays while a probe passes through it
+                    if is_current
+                        && !is_probed_past(&mut index, &t.entity_id, &t.attribute_id, t.value_hash)?
+                    {
+                        index.remove(&t.entity_id, &t.attribute_id, t.value_hash)?;
+                        collected.push(t);
+                    }
                 }
                 index.root_page()
             }
         };
+        let tombstones = collected.as_slice();
 
         // Remove from attribute index
-        let attribute_root = {
+        let (attribute_root, attribute_value_root) = {
             let root_page = self.file.superblock().attribute_index_root;
+            let value_root_page = self.file.superblock().attribute_value_index_root;
             if root_page == 0 {
-                0
+                (0, 0)
             } else {
-                let mut index = AttributeIndex::new(&mut self.file, root_page)?;
+                let mut index = AttributeIndex::new(&mut self.file, root_page, value_root_page)?;
                 for t in tombstones {
-                    index.remove(&t.attribute_id, &t.entity_id)?;
+                    index.remove(&t.attribute_id, &t.entity_id, t.value_hash)?;
                 }
-                index.root_page()
+                (index.root_page(), index.value_root_page())
             }
         };
 
@@ -750,7 +2288,7 @@ impl Database {
             } else {
                 let mut index = EntityAttributeIndex::new(&mut self.file, root_page)?;
                 for t in tombstones {
-                    index.remove(&t.entity_id, &t.attribute_id)?;
+                    index.remove(&t.entity_id, &t.attribute_id, t.value_hash)?;
                 }
                 index.root_page()
             }
@@ -762,15 +2300,20 @@ impl Database {
         }
         if attribute_root != 0 {
             self.file.superblock_mut().attribute_index_root = attribute_root;
+            self.file.superblock_mut().attribute_value_index_root = attribute_value_root;
         }
         if entity_attr_root != 0 {
             self.file.superblock_mut().entity_attribute_index_root = entity_attr_root;
         }
 
         self.file.write_superblock()?;
-        self.file.sync()?;
+        // With the sync deferred, a crash before the next sync can only leave
+        // some of these already-deleted entries behind
+        if !defers_page_sync(&self.file, &self.checkpoint_state) {
+            self.file.sync()?;
+        }
 
-        Ok(())
+        Ok(tombstones.len() as u64)
     }
 
     /// Persist tombstone list metadata to the superblock.
@@ -790,26 +2333,160 @@ impl Database {
     }
 }
 
+/// Whether index page writes may stay unsynced until the next checkpoint.
+///
+/// True with a WAL unless `CheckpointConfig::sync_pages_on_commit` is set;
+/// recovery then rebuilds lost page writes from the WAL.
+const fn defers_page_sync(file: &DatabaseFile, checkpoint_state: &CheckpointState) -> bool {
+    file.has_wal() && !checkpoint_state.sync_pages_on_commit()
+}
+
+/// Whether a displaced cardinality-many record follows `value_hash` in its
+/// run of consecutive keys, so removing the record at `value_hash` would stop
+/// probes short of it.
+///
+/// # Errors
+/// Returns an error if the primary index cannot be read.
+fn is_probed_past(
+    index: &mut PrimaryIndex<'_>,
+    entity_id: &EntityId,
+    attribute_id: &AttributeId,
+    value_hash: u64,
+) -> Result<bool, DatabaseError> {
+    if value_hash == 0 {
+        return Ok(false);
+    }
+    let mut next = Cardinality::next_value_hash(value_hash);
+    while next != value_hash {
+        match index.get_value(entity_id, attribute_id, next)? {
+            None => return Ok(false),
+            Some(record) if record.is_displaced() => return Ok(true),
+            Some(_) => next = Cardinality::next_value_hash(next),
+        }
+    }
+    Ok(false)
+}
+
+/// WAL bytes of a transaction with no operations: its BEGIN and COMMIT.
+const EMPTY_TRANSACTION_WAL_BYTES: u64 = 2 * LogRecord::size_for_payload(0) as u64;
+
+/// Bytes a buffered operation's record takes in the WAL.
+fn wal_record_size(operation: &PendingTriple) -> u64 {
+    let payload_size = match operation {
+        PendingTriple::Insert(record) | PendingTriple::Update(record) => record.serialized_size(),
+        PendingTriple::Delete {
+            entity_id,
+            attribute_id,
+            value_hash,
+            hlc,
+        } => {
+            LogRecordPayload::delete(*entity_id, *attribute_id, *value_hash, *hlc).serialized_size()
+        }
+    };
+    LogRecord::size_for_payload(payload_size) as u64
+}
+
+/// Write a snapshot's live triples to a backup, cardinality declarations
+/// first, returning how many were written.
+fn export_snapshot_records(
+    snapshot: &Snapshot<'_>,
+    writer: impl Write,
+) -> Result<u64, DatabaseError> {
+    let mut backup = BackupWriter::new(writer, &BackupHeader::new(snapshot.hlc()))?;
+    for record in snapshot.scan_entity(&ATTRIBUTE_CARDINALITY_ENTITY_ID)? {
+        backup.write_record(&record)?;
+    }
+    for record in snapshot.iter_all()? {
+        let record = record?;
+        if record.entity_id != ATTRIBUTE_CARDINALITY_ENTITY_ID {
+            backup.write_record(&record)?;
+        }
+    }
+    Ok(backup.finish()?)
+}
+
+/// Which committed state reads inside a write transaction see.
+///
+/// Reads never see the transaction's own buffered writes, at either level.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum IsolationLevel {
+    /// Each read sees the latest committed state at the time of the read, so
+    /// two reads of the same triple may differ if another transaction commits
+    /// in between.
+    ReadCommitted,
+    /// Every read sees the committed state as of `begin`, ignoring later
+    /// commits. Only the latest version of a triple is stored, so a triple
+    /// overwritten since `begin` reads as absent.
+    #[default]
+    Snapshot,
+}
+
+impl IsolationLevel {
+    /// Whether a read at this level sees a committed record with MVCC range
+    /// `range`, for a transaction that began once `snapshot_txn` committed.
+    const fn sees(self, range: TxnRange, snapshot_txn: TxnId) -> bool {
+        match self {
+            Self::ReadCommitted => !range.is_deleted(),
+            Self::Snapshot => range.is_visible_to(snapshot_txn),
+        }
+    }
+}
+
+/// Identifies a savepoint within a `WalTransaction`.
+///
+/// Obtained from `WalTransaction::savepoint()` and only meaningful for the
+/// transaction that created it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SavepointId(u64);
+
+/// Source of savepoint IDs, unique across transactions so a savepoint from
+/// one transaction is never mistaken for another's.
+static NEXT_SAVEPOINT_ID: AtomicU64 = AtomicU64::new(0);
+
 /// A WAL-backed transaction.
 ///
 /// Operations are buffered and written to WAL on commit, then applied to the index.
 /// This ensures crash recovery can replay committed transactions.
+///
+/// Finalize with `commit()` or `abort()`. A transaction dropped without either
+/// is aborted with a warning.
 pub struct WalTransaction<'a> {
     file: &'a mut DatabaseFile,
     checkpoint_state: &'a mut CheckpointState,
     clock: &'a mut Clock<SystemTimeSource>,
     tombstone_list: &'a mut TombstoneList,
+    /// Triggers fired on commit.
+    triggers: &'a AttributeTriggers,
+    /// The database's earliest pending expiry, lowered by committed writes.
+    next_expiry_ms: &'a mut Option<u64>,
+    /// Whether commit bumps the version of each entity it changes.
+    entity_versions: bool,
+    /// Capacity commit may grow the WAL to, or `None` if it is fixed.
+    max_wal_capacity: Option<u64>,
     gc_notify: Arc<tokio::sync::Notify>,
+    checkpoint_notify: Arc<tokio::sync::Notify>,
     txn_id: TxnId,
     hlc: HlcTimestamp,
     /// Buffered operations to be written on commit
     operations: Vec<PendingTriple>,
+    /// WAL bytes the buffered operations will take, with BEGIN and COMMIT
+    wal_bytes: u64,
     /// Whether this transaction has been finalized
     finalized: bool,
+    /// Live savepoints, oldest first, with the operation count at each
+    savepoints: Vec<(SavepointId, usize)>,
     /// Broadcast sender for change notifications.
     change_tx: broadcast::Sender<ChangeNotification>,
     /// The connection that created this transaction.
     connection_id: ConnectionId,
+    /// Which committed state reads see.
+    isolation: IsolationLevel,
+    /// Declared cardinality of each attribute written, read once per
+    /// transaction.
+    cardinalities: HashMap<AttributeId, Cardinality>,
+    /// Whether commit holds index page writes in memory and writes each
+    /// page once, for bulk loads.
+    defer_page_writes: bool,
 }
 
 impl<'a> WalTransaction<'a> {
@@ -820,7 +2497,12 @@ impl<'a> WalTransaction<'a> {
         checkpoint_state: &'a mut CheckpointState,
         clock: &'a mut Clock<SystemTimeSource>,
         tombstone_list: &'a mut TombstoneList,
+        triggers: &'a AttributeTriggers,
+        next_expiry_ms: &'a mut Option<u64>,
+        entity_versions: bool,
+        max_wal_capacity: Option<u64>,
         gc_notify: Arc<tokio::sync::Notify>,
+        checkpoint_notify: Arc<tokio::sync::Notify>,
         txn_id: TxnId,
         hlc: HlcTimestamp,
         change_tx: broadcast::Sender<ChangeNotification>,
@@ -831,16 +2513,50 @@ impl<'a> WalTransaction<'a> {
             checkpoint_state,
             clock,
             tombstone_list,
+            triggers,
+            next_expiry_ms,
+            entity_versions,
+            max_wal_capacity,
             gc_notify,
+            checkpoint_notify,
             txn_id,
             hlc,
             operations: Vec::new(),
+            wal_bytes: EMPTY_TRANSACTION_WAL_BYTES,
             finalized: false,
+            savepoints: Vec::new(),
             change_tx,
             connection_id,
+            isolation: IsolationLevel::default(),
+            cardinalities: HashMap::new(),
+            defer_page_writes: false,
         }
     }
 
+    /// Set which committed state the transaction's reads see.
+    ///
+    /// A write transaction has exclusive access to the database, so no other
+    /// transaction commits while it is open and both levels currently read the
+    /// same state. The level fixes the guarantee `get`, `contains` and
+    /// `scan_entity` make should that change.
+    #[must_use]
+    pub const fn with_isolation(mut self, isolation: IsolationLevel) -> Self {
+        self.isolation = isolation;
+        self
+    }
+
+    /// Get which committed state the transaction's reads see.
+    #[must_use]
+    pub const fn isolation(&self) -> IsolationLevel {
+        self.isolation
+    }
+
+    /// The last transaction committed when this one began, which its
+    /// `IsolationLevel::Snapshot` reads see.
+    const fn begin_snapshot_txn(&self) -> TxnId {
+        self.txn_id - 1
+    }
+
     /// Get the transaction ID.
     #[must_use]
     pub const fn txn_id(&self) -> TxnId {
@@ -853,37 +2569,101 @@ impl<'a> WalTransaction<'a> {
         self.hlc
     }
 
+    /// Check whether a live triple exists for an entity and attribute.
+    ///
+    /// For a cardinality-one triple, reads only the record's MVCC header,
+    /// never its value, so this is cheaper than `get(...).is_some()` for large
+    /// values. A cardinality-many attribute exists if any of its values does.
+    /// A triple that has expired by the transaction's HLC does not exist.
+    ///
+    /// Note: This reads from the committed state, not buffered operations,
+    /// as the transaction's `IsolationLevel` defines it.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the primary index cannot be read.
+    pub fn contains(
+        &self,
+        entity_id: &EntityId,
+        attribute_id: &AttributeId,
+    ) -> Result<bool, DatabaseError> {
+        let root_page = self.file.superblock().primary_index_root;
+        let index = PrimaryIndexReader::new(self.file, root_page);
+
+        let sees = |range: TxnRange| {
+            self.isolation.sees(range, self.begin_snapshot_txn())
+                && !range.is_expired(self.hlc.physical_time)
+        };
+        if index
+            .get_txn_range(entity_id, attribute_id)?
+            .is_some_and(sees)
+        {
+            return Ok(true);
+        }
+        Ok(index
+            .get_values(entity_id, attribute_id)?
+            .iter()
+            .any(|record| sees(record.txn_range())))
+    }
+
     /// Look up a single triple by entity and attribute ID.
     ///
-    /// Note: This reads from the committed state, not buffered operations.
+    /// A triple that has expired by the transaction's HLC is absent. For a
+    /// cardinality-many attribute, returns the value with the lowest value
+    /// hash; use `get_values` for all of them.
+    ///
+    /// Note: This reads from the committed state, not buffered operations,
+    /// as the transaction's `IsolationLevel` defines it.
     pub fn get(
         &mut self,
         entity_id: &EntityId,
         attribute_id: &AttributeId,
     ) -> Result<Option<TripleRecord>, DatabaseError> {
-        let root_page = self.file.superblock().primary_index_root;
-        let mut index = PrimaryIndex::new(self.file, root_page)?;
+        Ok(self.get_values(entity_id, attribute_id)?.into_iter().next())
+    }
 
-        match index.get(entity_id, attribute_id)? {
-            Some(record) if !record.is_deleted() => Ok(Some(record)),
-            _ => Ok(None),
-        }
+    /// Get every value of an entity's attribute, in value hash order.
+    ///
+    /// A cardinality-one attribute has at most one. Values that have expired
+    /// by the transaction's HLC are left out.
+    ///
+    /// Note: This reads from the committed state, not buffered operations,
+    /// as the transaction's `IsolationLevel` defines it.
+    pub fn get_values(
+        &mut self,
+        entity_id: &EntityId,
+        attribute_id: &AttributeId,
+    ) -> Result<Vec<TripleRecord>, DatabaseError> {
+        let (isolation, snapshot_txn) = (self.isolation, self.begin_snapshot_txn());
+        let now_ms = self.hlc.physical_time;
+        Ok(self
+            .get_values_including_deleted(entity_id, attribute_id)?
+            .into_iter()
+            .filter(|record| {
+                isolation.sees(record.txn_range(), snapshot_txn) && !record.is_expired(now_ms)
+            })
+            .collect())
     }
 
     /// Scan all triples for an entity.
     ///
-    /// Returns all triples for the given entity as a vector.
+    /// Returns all live, unexpired triples for the given entity as a vector,
+    /// from the committed state as the transaction's `IsolationLevel` defines
+    /// it.
     pub fn scan_entity(
         &mut self,
         entity_id: &EntityId,
     ) -> Result<Vec<TripleRecord>, DatabaseError> {
+        let (isolation, snapshot_txn) = (self.isolation, self.begin_snapshot_txn());
         let root_page = self.file.superblock().primary_index_root;
         let mut index = PrimaryIndex::new(self.file, root_page)?;
         let mut scan = index.scan_entity(entity_id)?;
 
         let mut results = Vec::new();
         while let Some(record) = scan.next_record()? {
-            if !record.is_deleted() {
+            if isolation.sees(record.txn_range(), snapshot_txn)
+                && !record.is_expired(self.hlc.physical_time)
+            {
                 results.push(record);
             }
         }
@@ -907,7 +2687,8 @@ impl<'a> WalTransaction<'a> {
         attribute_id: &AttributeId,
     ) -> Result<Vec<EntityId>, DatabaseError> {
         let root_page = self.file.superblock().attribute_index_root;
-        let mut index = AttributeIndex::new(self.file, root_page)?;
+        let value_root_page = self.file.superblock().attribute_value_index_root;
+        let mut index = AttributeIndex::new(self.file, root_page, value_root_page)?;
         let mut scan = index.scan_attribute(attribute_id)?;
 
         let mut entities = Vec::new();
@@ -938,28 +2719,160 @@ impl<'a> WalTransaction<'a> {
         Ok(attributes)
     }
 
-    /// Insert a triple.
+    /// Insert a triple, overwriting any existing value with an older HLC.
     ///
     /// The operation is buffered until commit.
     /// Uses the transaction's HLC timestamp.
-    pub fn insert(&mut self, entity_id: EntityId, attribute_id: AttributeId, value: TripleValue) {
+    ///
+    /// # Errors
+    ///
+    /// Returns `DatabaseError::ValueTooLarge` or
+    /// `DatabaseError::TransactionTooLarge` if the write would not fit in the
+    /// WAL; nothing is buffered. See `buffer_write`.
+    pub fn insert(
+        &mut self,
+        entity_id: EntityId,
+        attribute_id: AttributeId,
+        value: TripleValue,
+    ) -> Result<(), DatabaseError> {
         let record = TripleRecord::new(entity_id, attribute_id, self.txn_id, self.hlc, value);
-        self.operations.push(PendingTriple::Insert(record));
+        self.buffer_write(PendingTriple::Insert(record))
+    }
+
+    /// Insert a triple that expires at physical time `expires_at_ms`
+    /// (milliseconds since the Unix epoch).
+    ///
+    /// Behaves like `insert()`. Once the expiry passes, reads treat the triple
+    /// as absent and the expiry sweep tombstones it. Writing the triple again
+    /// without an expiry makes it permanent.
+    ///
+    /// # Pre-conditions
+    /// - `expires_at_ms` is positive
+    ///
+    /// # Errors
+    ///
+    /// As for `insert()`.
+    pub fn insert_with_expiry(
+        &mut self,
+        entity_id: EntityId,
+        attribute_id: AttributeId,
+        value: TripleValue,
+        expires_at_ms: u64,
+    ) -> Result<(), DatabaseError> {
+        debug_assert!(expires_at_ms > 0, "Expiry must be positive");
+        let record = TripleRecord::new(entity_id, attribute_id, self.txn_id, self.hlc, value)
+            .with_expiry(Some(expires_at_ms));
+        self.buffer_write(PendingTriple::Insert(record))
     }
 
     /// Insert a triple with a client-provided HLC timestamp.
     ///
     /// The operation is buffered until commit.
-    /// Uses the provided HLC instead of the transaction's HLC for conflict resolution.
+    /// Uses the provided HLC instead of the transaction's HLC for conflict resolution;
+    /// the write is dropped at commit if the stored value's HLC is not older.
+    ///
+    /// # Errors
+    ///
+    /// As for `insert()`.
     pub fn insert_with_hlc(
         &mut self,
         entity_id: EntityId,
         attribute_id: AttributeId,
         value: TripleValue,
         hlc: HlcTimestamp,
-    ) {
+    ) -> Result<(), DatabaseError> {
         let record = TripleRecord::new(entity_id, attribute_id, self.txn_id, hlc, value);
-        self.operations.push(PendingTriple::Insert(record));
+        self.buffer_write(PendingTriple::Insert(record))
+    }
+
+    /// Buffer one chunk of a bulk load, after advancing the clock past its
+    /// newest HLC.
+    ///
+    /// The transaction's HLC moves to the advanced clock, so the load still
+    /// commits after every HLC it writes.
+    ///
+    /// # Errors
+    ///
+    /// Returns `DatabaseError::Clock` if an HLC is too far ahead of the local
+    /// clock, or as for `insert()`. Triples before the failing one stay
+    /// buffered.
+    fn buffer_bulk_load_chunk(
+        &mut self,
+        chunk: Vec<PendingTripleData>,
+    ) -> Result<(), DatabaseError> {
+        let newest = chunk
+            .iter()
+            .map(|triple| triple.hlc)
+            .max_by(|a, b| Clock::<SystemTimeSource>::compare(*a, *b));
+        if let Some(newest) = newest {
+            self.hlc = self.clock.receive(newest)?;
+        }
+        for triple in chunk {
+            self.insert_with_hlc(
+                triple.entity_id,
+                triple.attribute_id,
+                triple.value,
+                triple.hlc,
+            )?;
+        }
+        Ok(())
+    }
+
+    /// Insert a triple only if no live triple exists for it.
+    ///
+    /// Unlike `insert()`, an existing value is never overwritten. Buffered
+    /// operations count: a triple inserted earlier in this transaction is
+    /// present, and one deleted earlier is absent.
+    ///
+    /// The operation is buffered until commit.
+    /// Uses the transaction's HLC timestamp.
+    ///
+    /// # Post-conditions
+    /// - Returns `true` and buffers an insert if the triple was absent
+    /// - Returns `false` and buffers nothing if it was present
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the primary index cannot be read, or as for
+    /// `insert()`.
+    pub fn insert_if_absent(
+        &mut self,
+        entity_id: EntityId,
+        attribute_id: AttributeId,
+        value: TripleValue,
+    ) -> Result<bool, DatabaseError> {
+        let present = match self.buffered_presence(&entity_id, &attribute_id) {
+            Some(present) => present,
+            None => self.contains(&entity_id, &attribute_id)?,
+        };
+        if present {
+            return Ok(false);
+        }
+        self.insert(entity_id, attribute_id, value)?;
+        Ok(true)
+    }
+
+    /// Whether the last buffered operation on a triple leaves it present, or
+    /// `None` if no operation on it is buffered.
+    fn buffered_presence(&self, entity_id: &EntityId, attribute_id: &AttributeId) -> Option<bool> {
+        self.operations
+            .iter()
+            .rev()
+            .find_map(|operation| match operation {
+                PendingTriple::Insert(record) | PendingTriple::Update(record)
+                    if record.entity_id == *entity_id && record.attribute_id == *attribute_id =>
+                {
+                    Some(true)
+                }
+                PendingTriple::Delete {
+                    entity_id: deleted_entity,
+                    attribute_id: deleted_attribute,
+                    ..
+                } if deleted_entity == entity_id && deleted_attribute == attribute_id => {
+                    Some(false)
+                }
+                _ => None,
+            })
     }
 
     /// Update a triple.
@@ -972,56 +2885,237 @@ impl<'a> WalTransaction<'a> {
         value: TripleValue,
     ) -> Result<(), DatabaseError> {
         // Check that the triple exists
-        if self.get(&entity_id, &attribute_id)?.is_none() {
+        if !self.contains(&entity_id, &attribute_id)? {
             return Err(DatabaseError::NotFound);
         }
 
         let record = TripleRecord::new(entity_id, attribute_id, self.txn_id, self.hlc, value);
-        self.operations.push(PendingTriple::Update(record));
-        Ok(())
+        self.buffer_write(PendingTriple::Update(record))
     }
 
     /// Update a triple with a specific HLC timestamp.
     ///
     /// Similar to `update()` but uses the provided HLC instead of the
     /// transaction's clock. Used for client-provided updates where the
-    /// client's HLC should be preserved.
+    /// client's HLC should be preserved. The write is dropped at commit if
+    /// the stored value's HLC is not older.
     ///
     /// The operation is buffered until commit.
+    ///
+    /// # Errors
+    ///
+    /// As for `insert()`.
     pub fn update_with_hlc(
         &mut self,
         entity_id: EntityId,
         attribute_id: AttributeId,
         value: TripleValue,
         hlc: HlcTimestamp,
-    ) {
+    ) -> Result<(), DatabaseError> {
         let record = TripleRecord::new(entity_id, attribute_id, self.txn_id, hlc, value);
-        self.operations.push(PendingTriple::Update(record));
+        self.buffer_write(PendingTriple::Update(record))
     }
 
     /// Delete a triple.
     ///
+    /// Deletes every value of a cardinality-many attribute; use
+    /// `delete_value` to delete one.
+    ///
     /// The operation is buffered until commit.
+    /// Uses the transaction's HLC timestamp as the delete's HLC.
     pub fn delete(
         &mut self,
         entity_id: &EntityId,
         attribute_id: &AttributeId,
     ) -> Result<(), DatabaseError> {
         // Check that the triple exists
-        if self.get(entity_id, attribute_id)?.is_none() {
+        let records = self.get_values(entity_id, attribute_id)?;
+        if records.is_empty() {
             return Err(DatabaseError::NotFound);
         }
 
-        self.operations.push(PendingTriple::Delete {
-            entity_id: *entity_id,
-            attribute_id: *attribute_id,
-        });
+        for record in records {
+            self.buffer_delete(*entity_id, *attribute_id, record.value_hash(), self.hlc);
+        }
+        Ok(())
+    }
+
+    /// Delete one value of a triple.
+    ///
+    /// Deletes `value` from a cardinality-many attribute, leaving its other
+    /// values, or a cardinality-one triple if its value is `value`.
+    ///
+    /// The operation is buffered until commit.
+    /// Uses the transaction's HLC timestamp as the delete's HLC.
+    ///
+    /// # Errors
+    ///
+    /// Returns `DatabaseError::NotFound` if the entity's attribute does not
+    /// hold `value`, or an error if the primary index cannot be read.
+    pub fn delete_value(
+        &mut self,
+        entity_id: &EntityId,
+        attribute_id: &AttributeId,
+        value: &TripleValue,
+    ) -> Result<(), DatabaseError> {
+        let record = self
+            .get_values(entity_id, attribute_id)?
+            .into_iter()
+            .find(|record| record.value == *value)
+            .ok_or(DatabaseError::NotFound)?;
+
+        self.buffer_delete(*entity_id, *attribute_id, record.value_hash(), self.hlc);
+        Ok(())
+    }
+
+    /// Delete a triple with a specific HLC timestamp.
+    ///
+    /// Similar to `delete()` but uses the provided HLC, which is stored on the
+    /// tombstone. The delete is dropped at commit if there is no live value or
+    /// the live value's HLC is not older.
+    ///
+    /// The operation is buffered until commit.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the primary index cannot be read.
+    pub fn delete_with_hlc(
+        &mut self,
+        entity_id: EntityId,
+        attribute_id: AttributeId,
+        hlc: HlcTimestamp,
+    ) -> Result<(), DatabaseError> {
+        // Every stored value, since commit settles which ones are live
+        let records = self.get_values_including_deleted(&entity_id, &attribute_id)?;
+        if records.is_empty() {
+            self.buffer_delete(entity_id, attribute_id, 0, hlc);
+        }
+        for record in records {
+            self.buffer_delete(entity_id, attribute_id, record.value_hash(), hlc);
+        }
+        Ok(())
+    }
+
+    /// Buffer an insert or update, checking it fits in the WAL.
+    ///
+    /// Every operation is logged whole, even a value stored in overflow
+    /// pages, and a transaction's records must all fit in the WAL at once.
+    /// Checking here rejects an oversized write when it is made, rather than
+    /// failing the commit after the whole transaction was built. Records
+    /// never straddle the end of the WAL, so a transaction just under the
+    /// capacity can still fail at commit.
+    ///
+    /// # Errors
+    ///
+    /// - `DatabaseError::ValueTooLarge` if the write would not fit in the WAL
+    ///   even in a transaction of its own
+    /// - `DatabaseError::TransactionTooLarge` if the transaction's records
+    ///   would
+    ///
+    /// # Post-conditions
+    /// - On error, nothing is buffered
+    fn buffer_write(&mut self, operation: PendingTriple) -> Result<(), DatabaseError> {
+        let record_size = wal_record_size(&operation);
+        if self.file.has_wal() {
+            // Commit grows the WAL to fit, if allowed
+            let capacity = self
+                .file
+                .wal_capacity()
+                .max(self.max_wal_capacity.unwrap_or(0));
+            if EMPTY_TRANSACTION_WAL_BYTES + record_size > capacity
+                && let PendingTriple::Insert(record) | PendingTriple::Update(record) = &operation
+            {
+                // The largest value that fits in a transaction of its own
+                let value_size = record.value.serialized_size() as u64;
+                return Err(DatabaseError::ValueTooLarge {
+                    size: value_size,
+                    max: (capacity + value_size)
+                        .saturating_sub(EMPTY_TRANSACTION_WAL_BYTES + record_size),
+                });
+            }
+            if self.wal_bytes + record_size > capacity {
+                return Err(DatabaseError::TransactionTooLarge {
+                    size: self.wal_bytes + record_size,
+                    capacity,
+                });
+            }
+        }
+        self.wal_bytes += record_size;
+        self.operations.push(operation);
+        Ok(())
+    }
+
+    /// Buffer a delete of the value keyed by `value_hash`. Delete records are
+    /// small and fixed-size, so they are only counted toward the
+    /// transaction's WAL size.
+    fn buffer_delete(
+        &mut self,
+        entity_id: EntityId,
+        attribute_id: AttributeId,
+        value_hash: u64,
+        hlc: HlcTimestamp,
+    ) {
+        let operation = PendingTriple::Delete {
+            entity_id,
+            attribute_id,
+            value_hash,
+            hlc,
+        };
+        self.wal_bytes += wal_record_size(&operation);
+        self.operations.push(operation);
+    }
+
+    /// Record a savepoint at the current end of the buffered operations.
+    ///
+    /// Savepoints nest: later savepoints sit inside earlier ones.
+    pub fn savepoint(&mut self) -> SavepointId {
+        let id = SavepointId(NEXT_SAVEPOINT_ID.fetch_add(1, AtomicOrdering::Relaxed));
+        self.savepoints.push((id, self.operations.len()));
+        id
+    }
+
+    /// Discard every operation buffered after `savepoint`.
+    ///
+    /// Operations buffered before the savepoint are kept. The savepoint stays
+    /// valid so it can be rolled back to again; savepoints created after it
+    /// are released.
+    ///
+    /// # Post-conditions
+    /// - The buffered operations are exactly those present when `savepoint`
+    ///   was created
+    ///
+    /// # Errors
+    ///
+    /// Returns `DatabaseError::UnknownSavepoint` if `savepoint` was not created
+    /// by this transaction or was released by rolling back to an earlier one.
+    pub fn rollback_to(&mut self, savepoint: SavepointId) -> Result<(), DatabaseError> {
+        let position = self
+            .savepoints
+            .iter()
+            .position(|(id, _)| *id == savepoint)
+            .ok_or(DatabaseError::UnknownSavepoint)?;
+        let (_, operation_count) = self.savepoints[position];
+
+        self.operations.truncate(operation_count);
+        self.wal_bytes =
+            EMPTY_TRANSACTION_WAL_BYTES + self.operations.iter().map(wal_record_size).sum::<u64>();
+        self.savepoints.truncate(position + 1);
         Ok(())
     }
 
     /// Commit the transaction.
     ///
-    /// This:
+    /// Operations are first resolved last-writer-wins: an insert, update, or
+    /// delete whose HLC is not newer than the stored value's (or tombstone's)
+    /// is dropped, so an out-of-order write never clobbers a newer one.
+    /// Dropped operations are not logged, applied, or broadcast.
+    ///
+    /// A winning write that stores the value the key already holds is a no-op
+    /// update. It is still logged and applied, so its HLC advances, but it is
+    /// not broadcast and not counted in `CommitOutcome::changes_applied` or
+    /// `CommitOutcome::applied`.
+    ///
+    /// Then this:
     /// 1. Writes BEGIN record to WAL
     /// 2. Writes all buffered operations to WAL
     /// 3. Writes COMMIT record to WAL
@@ -1029,11 +3123,18 @@ impl<'a> WalTransaction<'a> {
     /// 5. Applies operations to the index
     /// 6. Broadcasts change notifications
     /// 7. Updates superblock
-    /// 8. Optionally triggers checkpoint
+    /// 8. Signals the checkpoint task if a checkpoint is due, or checkpoints
+    ///    inline if the WAL is under pressure
+    ///
+    /// # Post-conditions
+    /// - The returned outcome carries this transaction's ID and HLC
+    /// - `changes_applied` is 0 if every operation was dropped or a no-op
+    /// - `applied` lists the broadcast changes, so its length is
+    ///   `changes_applied`
     ///
     /// # Panics
     /// Panics if the transaction was already finalized.
-    pub fn commit(mut self) -> Result<(), DatabaseError> {
+    pub fn commit(mut self) -> Result<CommitOutcome, DatabaseError> {
         // Invariant: transaction must not already be finalized
         assert!(
             !self.finalized,
@@ -1042,43 +3143,91 @@ impl<'a> WalTransaction<'a> {
 
         self.finalized = true;
 
+        let txn_id = self.txn_id;
+        let hlc = self.hlc;
+
+        // Drop writes that lose last-writer-wins against newer values
+        let (mut changed, mut prior) = self.resolve_conflicts()?;
+        // Version writes are bookkeeping, left out of the applied changes
+        let operation_count = self.operations.len();
+        if self.entity_versions {
+            self.bump_entity_versions(&mut changed, &mut prior)?;
+        }
+
         if self.operations.is_empty() {
             // Nothing to commit
-            return Ok(());
+            return Ok(CommitOutcome {
+                txn_id,
+                hlc,
+                changes_applied: 0,
+                applied: Vec::new(),
+            });
         }
 
-        let txn_id = self.txn_id;
-        let hlc = self.hlc;
-
         // Step 1-4: Write to WAL
         let wal_bytes_written = if self.file.has_wal() {
-            self.write_to_wal(txn_id, hlc)?
+            self.grow_wal_to_fit()?;
+            match self.write_to_wal(txn_id, hlc) {
+                Err(DatabaseError::Wal(WalError::WouldOverwriteUncheckpointed)) => {
+                    // WAL is full of un-checkpointed records. Checkpoint to free it,
+                    // then rewrite the whole transaction over the partial attempt.
+                    let checkpoint_hlc = self.clock.tick();
+                    force_checkpoint(self.file, self.checkpoint_state, checkpoint_hlc)?;
+                    self.write_to_wal(txn_id, hlc)?
+                }
+                result => result?,
+            }
         } else {
             0
         };
 
+        // Step 4b: Unless pages are synced below, the WAL is the commit point:
+        // make the records and the head that locates them durable now
+        let defer_page_sync = defers_page_sync(self.file, self.checkpoint_state);
+        if defer_page_sync {
+            self.file.write_superblock()?;
+            self.file.sync()?;
+        }
+
         // Step 5: Apply operations to index
-        self.apply_to_index(txn_id, hlc)?;
+        if self.defer_page_writes {
+            self.file.defer_writes();
+        }
+        let applied = self.apply_to_index(txn_id, hlc);
+        self.file.flush_deferred_writes()?;
+        applied?;
 
         // Step 5b: Add tombstones for delete operations
         let has_deletes = self.add_tombstones_for_deletes(txn_id)?;
 
+        // Step 5c: Make the expiry sweep aware of newly written expiries
+        self.record_expiries();
+
         // Step 6: Broadcast change notifications
-        self.broadcast_changes(hlc);
+        self.broadcast_changes(&changed, prior);
 
         // Step 7: Update superblock
         self.file.superblock_mut().next_txn_id = txn_id + 1;
         self.file.write_superblock()?;
-        self.file.sync()?;
+        if !defer_page_sync {
+            self.file.sync()?;
+        }
 
-        // Step 8: Update checkpoint state and maybe checkpoint
+        // Step 8: Update checkpoint state and hand off checkpoint work
         self.checkpoint_state.record_commit();
         self.checkpoint_state.record_wal_write(wal_bytes_written);
 
-        // Check if we should checkpoint (tick clock for checkpoint timestamp)
-        if self.file.has_wal() {
-            let checkpoint_hlc = self.clock.tick();
-            maybe_checkpoint(self.file, self.checkpoint_state, checkpoint_hlc)?;
+        if self.file.has_wal() && self.checkpoint_state.should_checkpoint() {
+            if self
+                .checkpoint_state
+                .wal_pressure_exceeded(self.file.wal_capacity())
+            {
+                // The background task has fallen behind; don't let the WAL wrap
+                let checkpoint_hlc = self.clock.tick();
+                force_checkpoint(self.file, self.checkpoint_state, checkpoint_hlc)?;
+            } else {
+                self.checkpoint_notify.notify_one();
+            }
         }
 
         // Step 9: Signal GC task if we added tombstones (non-blocking)
@@ -1086,6 +3235,198 @@ impl<'a> WalTransaction<'a> {
             self.gc_notify.notify_one();
         }
 
+        let applied = self.applied_changes(&changed[..operation_count]);
+        Ok(CommitOutcome {
+            txn_id,
+            hlc,
+            changes_applied: applied.len(),
+            applied,
+        })
+    }
+
+    /// Summarize the operations that changed stored state, in commit order.
+    fn applied_changes(&self, changed: &[bool]) -> Vec<AppliedChange> {
+        self.operations
+            .iter()
+            .zip(changed)
+            .filter(|(_, changed)| **changed)
+            .map(|(op, _)| match op {
+                PendingTriple::Insert(record) => AppliedChange {
+                    change_type: ChangeType::Insert,
+                    entity_id: record.entity_id,
+                    attribute_id: record.attribute_id,
+                    hlc: record.created_hlc,
+                },
+                PendingTriple::Update(record) => AppliedChange {
+                    change_type: ChangeType::Update,
+                    entity_id: record.entity_id,
+                    attribute_id: record.attribute_id,
+                    hlc: record.created_hlc,
+                },
+                PendingTriple::Delete {
+                    entity_id,
+                    attribute_id,
+                    hlc,
+                    ..
+                } => AppliedChange {
+                    change_type: ChangeType::Delete,
+                    entity_id: *entity_id,
+                    attribute_id: *attribute_id,
+                    hlc: *hlc,
+                },
+            })
+            .collect()
+    }
+
+    /// Buffer a version bump for every entity a kept operation changes,
+    /// behind the transaction's other operations.
+    ///
+    /// A write to an entity's version triple itself counts as a change, and
+    /// the bump, buffered after it, overwrites it. The reserved attribute
+    /// names entity is never versioned.
+    ///
+    /// # Errors
+    /// Returns an error if a version cannot be read, or `TransactionTooLarge`
+    /// if the bumps do not fit in the WAL.
+    ///
+    /// # Post-conditions
+    /// - Each changed entity's version triple is written with its committed
+    ///   version plus one and the transaction's HLC, flagged in `changed`
+    /// - `prior` holds each bumped entity's old version if it captures old
+    ///   values, i.e. if anyone is subscribed to changes
+    fn bump_entity_versions(
+        &mut self,
+        changed: &mut Vec<bool>,
+        prior: &mut PriorValues,
+    ) -> Result<(), DatabaseError> {
+        let mut seen = HashSet::new();
+        let entities: Vec<EntityId> = self
+            .operations
+            .iter()
+            .zip(changed.iter())
+            .filter(|(_, changed)| **changed)
+            .map(|(op, _)| match op {
+                PendingTriple::Insert(record) | PendingTriple::Update(record) => record.entity_id,
+                PendingTriple::Delete { entity_id, .. } => *entity_id,
+            })
+            .filter(|entity_id| !is_reserved_entity(entity_id) && seen.insert(*entity_id))
+            .collect();
+
+        let capture_prior = self.change_tx.receiver_count() > 0;
+        for entity_id in entities {
+            let key = (entity_id, ENTITY_VERSION_ATTRIBUTE_ID, 0);
+            let committed =
+                self.get_including_deleted(&entity_id, &ENTITY_VERSION_ATTRIBUTE_ID, 0)?;
+            let version = entity_versions::version_of(committed.as_ref());
+            let is_live = committed
+                .as_ref()
+                .is_some_and(|record| !record.is_deleted());
+            if capture_prior
+                && is_live
+                && let Some(committed) = committed
+            {
+                prior.insert(key, committed.value);
+            }
+
+            let record = TripleRecord::new(
+                entity_id,
+                ENTITY_VERSION_ATTRIBUTE_ID,
+                self.txn_id,
+                self.hlc,
+                entity_versions::version_value(version + 1),
+            );
+            self.buffer_write(if is_live {
+                PendingTriple::Update(record)
+            } else {
+                PendingTriple::Insert(record)
+            })?;
+            changed.push(true);
+        }
+        Ok(())
+    }
+
+    /// Lower the database's earliest pending expiry to cover this
+    /// transaction's writes.
+    fn record_expiries(&mut self) {
+        let earliest = self
+            .operations
+            .iter()
+            .filter_map(|op| match op {
+                PendingTriple::Insert(record) | PendingTriple::Update(record) => {
+                    record.expires_at_ms
+                }
+                PendingTriple::Delete { .. } => None,
+            })
+            .min();
+        if let Some(earliest) = earliest {
+            *self.next_expiry_ms = Some(
+                self.next_expiry_ms
+                    .map_or(earliest, |next| next.min(earliest)),
+            );
+        }
+    }
+
+    /// Checkpoint and grow the WAL if growth is enabled and this
+    /// transaction's records would not fit in it after a checkpoint.
+    ///
+    /// The WAL at least doubles, up to `max_wal_capacity`. Growing leaves the
+    /// checkpoint record at the start of the region, so past it the records
+    /// fit without wrapping.
+    ///
+    /// # Pre-conditions
+    /// - The database has a WAL.
+    /// - `wal_bytes` is at most `max_wal_capacity`, as `buffer_write`
+    ///   ensures when growth is enabled.
+    ///
+    /// # Post-conditions
+    /// - Without growth enabled, or if the records already fit, nothing
+    ///   changes.
+    /// - Otherwise every WAL record is checkpointed and the capacity is at
+    ///   least `wal_bytes` plus room for the checkpoint record.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the checkpoint or growing the WAL fails.
+    fn grow_wal_to_fit(&mut self) -> Result<(), DatabaseError> {
+        assert!(self.file.has_wal(), "grow_wal_to_fit requires a WAL");
+        let Some(max_capacity) = self.max_wal_capacity else {
+            return Ok(());
+        };
+        assert!(
+            self.wal_bytes <= max_capacity.max(self.file.wal_capacity()),
+            "transaction WAL bytes ({}) exceed the growth limit ({max_capacity})",
+            self.wal_bytes
+        );
+        // The checkpoint record ahead of the transaction, and the byte the
+        // head must stop short of it
+        let checkpoint_bytes =
+            LogRecord::size_for_payload(LogRecordPayload::checkpoint(0, 0).serialized_size())
+                as u64;
+        let needed = self.wal_bytes + 2 * checkpoint_bytes + 1;
+        let capacity = self.file.wal_capacity();
+        if needed <= capacity {
+            return Ok(());
+        }
+
+        let checkpoint_hlc = self.clock.tick();
+        force_checkpoint(self.file, self.checkpoint_state, checkpoint_hlc)?;
+        let target = capacity.saturating_mul(2).min(max_capacity).max(needed);
+        let growth = self.file.grow_wal(target)?;
+        assert!(
+            growth.capacity_after >= needed,
+            "grown WAL ({}) must fit the transaction ({needed})",
+            growth.capacity_after
+        );
+        tracing::info!(
+            "Grew WAL from {} to {} bytes{}",
+            growth.capacity_before,
+            growth.capacity_after,
+            if growth.relocated {
+                ", relocating it"
+            } else {
+                ""
+            }
+        );
         Ok(())
     }
 
@@ -1114,10 +3455,17 @@ impl<'a> WalTransaction<'a> {
                 PendingTriple::Delete {
                     entity_id,
                     attribute_id,
+                    value_hash,
+                    hlc: delete_hlc,
                 } => {
-                    let payload = LogRecordPayload::delete(*entity_id, *attribute_id);
+                    let payload = LogRecordPayload::delete(
+                        *entity_id,
+                        *attribute_id,
+                        *value_hash,
+                        *delete_hlc,
+                    );
                     total_bytes += payload.serialized_size() as u64;
-                    wal.append(txn_id, hlc, payload)?;
+                    wal.append(txn_id, *delete_hlc, payload)?;
                 }
             }
         }
@@ -1140,37 +3488,334 @@ impl<'a> WalTransaction<'a> {
         Ok(total_bytes)
     }
 
-    /// Apply buffered operations to all indexes.
-    fn apply_to_index(&mut self, txn_id: TxnId, _hlc: HlcTimestamp) -> Result<(), DatabaseError> {
-        // Apply to primary index
-        let primary_root = {
-            let root_page = self.file.superblock().primary_index_root;
-            let mut index = PrimaryIndex::new(self.file, root_page)?;
-
-            for op in &self.operations {
-                match op {
-                    PendingTriple::Insert(record) | PendingTriple::Update(record) => {
-                        index.insert(record)?;
+    /// Drop buffered operations that lose last-writer-wins conflict resolution.
+    ///
+    /// Each key's newest version is its committed record's last write: the
+    /// value's `created_hlc`, or the tombstone's `deleted_hlc` if deleted. An
+    /// insert or update is kept only if its HLC is strictly newer, so a write
+    /// older than a delete stays deleted and a newer one resurrects the key.
+    /// A delete is kept only if the key has a live value and the delete's HLC
+    /// is strictly newer than it.
+    ///
+    /// Within the transaction, a later operation replaces an earlier one
+    /// unless its HLC is older, so repeated plain operations (which share the
+    /// transaction HLC) still apply in order.
+    ///
+    /// Tombstones only take part until garbage collection removes them; a
+    /// write older than a collected delete is applied.
+    ///
+    /// Each kept operation that changes a value fires the triggers watching
+    /// its attribute. Their writes queue behind the remaining operations and
+    /// are resolved the same way.
+    ///
+    /// # Errors
+    /// Returns `TriggerDepthExceeded` or `TooManyTriggeredWrites` if triggers
+    /// exceed their limits, or any error a trigger reports.
+    ///
+    /// # Post-conditions
+    /// - No remaining operation has an HLC older than or equal to the
+    ///   committed version it replaces
+    /// - Returns one flag per remaining operation, `false` for a write that
+    ///   stores the key's current committed value
+    /// - If anyone is subscribed to changes, also returns the live committed
+    ///   value of every key the transaction writes that has one, read from the
+    ///   record conflict resolution looks up anyway
+    fn resolve_conflicts(&mut self) -> Result<(Vec<bool>, PriorValues), DatabaseError> {
+        // Newest version per key, or `None` if the key was never written
+        let mut newest: HashMap<TripleKey, Option<Version>> = HashMap::new();
+        // Each operation with its trigger depth; triggered writes queue at the end
+        let mut queue: VecDeque<(PendingTriple, usize)> = std::mem::take(&mut self.operations)
+            .into_iter()
+            .map(|op| (op, 0))
+            .collect();
+        let mut kept = Vec::with_capacity(queue.len());
+        // Cardinality each kept write declares, by attribute
+        let mut declared: HashMap<AttributeId, Cardinality> = HashMap::new();
+        // Value of each cardinality-many insert so far, by key
+        let mut written_values: HashMap<TripleKey, TripleValue> = HashMap::new();
+        let mut changed = Vec::with_capacity(queue.len());
+        let mut triggered_writes = 0;
+        // Old values only go into change notifications, so skip copying them
+        // when nobody receives those
+        let capture_prior = self.change_tx.receiver_count() > 0;
+        let mut prior = PriorValues::new();
+
+        while let Some((op, depth)) = queue.pop_front() {
+            let op = self.with_declared_cardinality(op, &declared)?;
+            let op = self.with_free_value_hash(op, &mut written_values)?;
+            let key = op.key();
+            let (entity_id, attribute_id, value_hash) = key;
+            let hlc = op.hlc();
+            let is_delete = matches!(op, PendingTriple::Delete { .. });
+            let (current, same_value) = if let Some(current) = newest.get(&key) {
+                (*current, false)
+            } else {
+                let committed =
+                    self.get_including_deleted(&entity_id, &attribute_id, value_hash)?;
+                // Only the first write to a key can match the committed value;
+                // later ones are compared against this transaction's writes
+                let same_value = match (&op, &committed) {
+                    (
+                        PendingTriple::Insert(record) | PendingTriple::Update(record),
+                        Some(committed),
+                    ) => {
+                        !committed.is_deleted()
+                            && !committed.is_expired(self.hlc.physical_time)
+                            && committed.value == record.value
+                            && committed.expires_at_ms == record.expires_at_ms
                     }
-                    PendingTriple::Delete {
-                        entity_id,
-                        attribute_id,
-                    } => {
-                        index.mark_deleted(entity_id, attribute_id, txn_id)?;
+                    _ => false,
+                };
+                if capture_prior
+                    && let Some(committed) = &committed
+                    && !committed.is_deleted()
+                    && !committed.is_expired(self.hlc.physical_time)
+                {
+                    prior.insert(key, committed.value.clone_value());
+                }
+                let current = committed.map(|committed| Version {
+                    hlc: committed.last_write_hlc(),
+                    is_live: !committed.is_deleted(),
+                    written_here: false,
+                });
+                (current, same_value)
+            };
+            let wins = match current {
+                // Deleting a key without a live value is a no-op
+                None => !is_delete,
+                Some(version) if is_delete && !version.is_live => false,
+                Some(version) => match Clock::<SystemTimeSource>::compare(hlc, version.hlc) {
+                    Ordering::Greater => true,
+                    Ordering::Equal => version.written_here,
+                    Ordering::Less => false,
+                },
+            };
+
+            if wins {
+                newest.insert(
+                    key,
+                    Some(Version {
+                        hlc,
+                        is_live: !is_delete,
+                        written_here: true,
+                    }),
+                );
+                if let Some((attribute, cardinality)) = declaration_written(&op) {
+                    declared.insert(attribute, cardinality);
+                }
+                kept.push(op);
+                changed.push(!same_value);
+
+                // Triggers fire only on real changes, so rewriting a value settles
+                if !same_value && self.triggers.watches(&attribute_id) {
+                    let writes = self
+                        .triggers
+                        .fire(self.file, &kept, self.txn_id, self.hlc)?;
+                    if !writes.is_empty() {
+                        if depth >= MAX_TRIGGER_DEPTH {
+                            return Err(DatabaseError::TriggerDepthExceeded(MAX_TRIGGER_DEPTH));
+                        }
+                        triggered_writes += writes.len();
+                        if triggered_writes > MAX_TRIGGERED_WRITES {
+                            return Err(DatabaseError::TooManyTriggeredWrites(
+                                MAX_TRIGGERED_WRITES,
+                            ));
+                        }
+                        queue.extend(writes.into_iter().map(|write| (write, depth + 1)));
                     }
                 }
+            } else {
+                newest.insert(key, current);
             }
+        }
 
-            index.root_page()
-        };
+        self.operations = kept;
+        Ok((changed, prior))
+    }
 
-        // Apply to attribute index (attribute_id -> entity_id)
-        let attribute_root = {
-            let root_page = self.file.superblock().attribute_index_root;
-            let mut index = AttributeIndex::new(self.file, root_page)?;
-            apply_ops_to_secondary_index(&mut index, &self.operations, txn_id)?;
-            index.root_page()
-        };
+    /// Look up a committed triple by its value hash, including tombstoned
+    /// records.
+    fn get_including_deleted(
+        &mut self,
+        entity_id: &EntityId,
+        attribute_id: &AttributeId,
+        value_hash: u64,
+    ) -> Result<Option<TripleRecord>, DatabaseError> {
+        let root_page = self.file.superblock().primary_index_root;
+        let mut index = PrimaryIndex::new(self.file, root_page)?;
+        Ok(index.get_value(entity_id, attribute_id, value_hash)?)
+    }
+
+    /// Get every committed value of an entity's attribute, including
+    /// tombstoned records.
+    fn get_values_including_deleted(
+        &mut self,
+        entity_id: &EntityId,
+        attribute_id: &AttributeId,
+    ) -> Result<Vec<TripleRecord>, DatabaseError> {
+        let root_page = self.file.superblock().primary_index_root;
+        let mut index = PrimaryIndex::new(self.file, root_page)?;
+        Ok(index.get_values(entity_id, attribute_id)?)
+    }
+
+    /// Get the first entity with a triple of `attribute_id` written or
+    /// deleted by a transaction committed after `snapshot_txn`.
+    ///
+    /// # Pre-conditions
+    /// - A snapshot at `snapshot_txn` is pinned, so no tombstone written
+    ///   since has been garbage collected
+    fn attribute_written_after(
+        &self,
+        attribute_id: &AttributeId,
+        snapshot_txn: TxnId,
+    ) -> Result<Option<EntityId>, DatabaseError> {
+        let superblock = self.file.superblock();
+        let index = AttributeIndexReader::new(
+            self.file,
+            superblock.attribute_index_root,
+            superblock.attribute_value_index_root,
+        );
+        Ok(index.first_written_after(attribute_id, snapshot_txn)?)
+    }
+
+    /// Stamp a write with its attribute's declared cardinality.
+    ///
+    /// A declaration among the transaction's kept writes, collected in
+    /// `pending` by attribute, counts, so a replicated batch can declare an
+    /// attribute and write it. A cardinality-many update adds
+    /// its value like an insert, so it becomes one. Deletes pass through:
+    /// they already carry the value hash they target.
+    fn with_declared_cardinality(
+        &mut self,
+        op: PendingTriple,
+        pending: &HashMap<AttributeId, Cardinality>,
+    ) -> Result<PendingTriple, DatabaseError> {
+        let (PendingTriple::Insert(record) | PendingTriple::Update(record)) = &op else {
+            return Ok(op);
+        };
+        let cardinality = if is_reserved_entity(&record.entity_id) {
+            Cardinality::One
+        } else if let Some(cardinality) = pending.get(&record.attribute_id) {
+            *cardinality
+        } else {
+            self.cardinality_of(&record.attribute_id)?
+        };
+        Ok(match op {
+            PendingTriple::Update(record) if cardinality == Cardinality::Many => {
+                PendingTriple::Insert(record.with_cardinality(cardinality))
+            }
+            PendingTriple::Insert(record) => {
+                PendingTriple::Insert(record.with_cardinality(cardinality))
+            }
+            PendingTriple::Update(record) => {
+                PendingTriple::Update(record.with_cardinality(cardinality))
+            }
+            delete @ PendingTriple::Delete { .. } => delete,
+        })
+    }
+
+    /// Key a cardinality-many insert at the first value hash, probing from
+    /// its home one, that holds no different value.
+    ///
+    /// A value hash holds a value if a committed record, live or
+    /// tombstoned, or an earlier insert of this transaction is keyed there.
+    /// Values are compared by their serialized bytes, like the hash. Other
+    /// writes pass through.
+    ///
+    /// # Post-conditions
+    /// - The returned write's key is free, or holds the same value
+    /// - `written_values` maps the returned insert's key to its value
+    ///
+    /// # Errors
+    /// Returns an error if the primary index cannot be read.
+    fn with_free_value_hash(
+        &mut self,
+        op: PendingTriple,
+        written_values: &mut HashMap<TripleKey, TripleValue>,
+    ) -> Result<PendingTriple, DatabaseError> {
+        let record = match op {
+            PendingTriple::Insert(record) if record.cardinality == Cardinality::Many => record,
+            op => return Ok(op),
+        };
+        let value_bytes = record.value.to_bytes();
+        let home = record.value_hash();
+        let mut value_hash = home;
+        loop {
+            let key = (record.entity_id, record.attribute_id, value_hash);
+            let holds_other = if let Some(value) = written_values.get(&key) {
+                value.to_bytes() != value_bytes
+            } else {
+                self.get_including_deleted(&record.entity_id, &record.attribute_id, value_hash)?
+                    .is_some_and(|stored| stored.value.to_bytes() != value_bytes)
+            };
+            if !holds_other {
+                break;
+            }
+            value_hash = Cardinality::next_value_hash(value_hash);
+            assert_ne!(value_hash, home, "every value hash holds a different value");
+        }
+        written_values.insert(
+            (record.entity_id, record.attribute_id, value_hash),
+            record.value.clone_value(),
+        );
+        Ok(PendingTriple::Insert(if value_hash == home {
+            record
+        } else {
+            record.with_value_hash(value_hash)
+        }))
+    }
+
+    /// Get the committed declared cardinality of `attribute_id`.
+    fn cardinality_of(&mut self, attribute_id: &AttributeId) -> Result<Cardinality, DatabaseError> {
+        if let Some(cardinality) = self.cardinalities.get(attribute_id) {
+            return Ok(*cardinality);
+        }
+        let declaration = self
+            .get_including_deleted(&ATTRIBUTE_CARDINALITY_ENTITY_ID, attribute_id, 0)?
+            .filter(|record| !record.is_deleted());
+        let cardinality = declared_cardinality(declaration.as_ref());
+        self.cardinalities.insert(*attribute_id, cardinality);
+        Ok(cardinality)
+    }
+
+    /// Apply buffered operations to all indexes.
+    fn apply_to_index(&mut self, txn_id: TxnId, _hlc: HlcTimestamp) -> Result<(), DatabaseError> {
+        // Apply to primary index
+        let primary_root = {
+            let root_page = self.file.superblock().primary_index_root;
+            let mut index = PrimaryIndex::new(self.file, root_page)?;
+
+            // Consecutive writes are inserted as one run
+            let mut writes = Vec::new();
+            for op in &self.operations {
+                match op {
+                    PendingTriple::Insert(record) | PendingTriple::Update(record) => {
+                        writes.push(record);
+                    }
+                    PendingTriple::Delete {
+                        entity_id,
+                        attribute_id,
+                        value_hash,
+                        hlc,
+                    } => {
+                        index.insert_many(std::mem::take(&mut writes))?;
+                        index.mark_deleted(entity_id, attribute_id, *value_hash, txn_id, *hlc)?;
+                    }
+                }
+            }
+            index.insert_many(writes)?;
+
+            index.root_page()
+        };
+
+        // Apply to attribute index (attribute_id -> entity_id)
+        let (attribute_root, attribute_value_root) = {
+            let root_page = self.file.superblock().attribute_index_root;
+            let value_root_page = self.file.superblock().attribute_value_index_root;
+            let mut index = AttributeIndex::new(self.file, root_page, value_root_page)?;
+            apply_ops_to_secondary_index(&mut index, &self.operations, txn_id)?;
+            (index.root_page(), index.value_root_page())
+        };
 
         // Apply to entity-attribute index (entity_id -> attribute_id)
         let entity_attribute_root = {
@@ -1197,6 +3842,7 @@ impl<'a> WalTransaction<'a> {
         // Update root pages in superblock
         self.file.superblock_mut().primary_index_root = primary_root;
         self.file.superblock_mut().attribute_index_root = attribute_root;
+        self.file.superblock_mut().attribute_value_index_root = attribute_value_root;
         self.file.superblock_mut().entity_attribute_index_root = entity_attribute_root;
 
         Ok(())
@@ -1212,9 +3858,11 @@ impl<'a> WalTransaction<'a> {
             if let PendingTriple::Delete {
                 entity_id,
                 attribute_id,
+                value_hash,
+                ..
             } = op
             {
-                let tombstone = Tombstone::new(*entity_id, *attribute_id, txn_id);
+                let tombstone = Tombstone::new(*entity_id, *attribute_id, *value_hash, txn_id);
                 self.tombstone_list.append(tombstone);
                 has_deletes = true;
             }
@@ -1235,46 +3883,80 @@ impl<'a> WalTransaction<'a> {
     }
 
     /// Broadcast change notifications to all subscribers.
-    fn broadcast_changes(&self, hlc: HlcTimestamp) {
-        if self.operations.is_empty() {
-            return;
-        }
-
-        let changes: Vec<ChangeRecord> = self
+    ///
+    /// Only operations flagged in `applied` are sent; nothing is sent if none are.
+    /// Several operations on one key are sent as a single change carrying the
+    /// key's committed state (see `coalesce_changes`), so subscribers never see
+    /// a value that only existed mid-transaction. Each change's old value is
+    /// the key's value in `prior`, the committed state before the transaction.
+    ///
+    /// The notification is sent while the transaction holds the database
+    /// exclusively, so notifications enter the channel in commit order and
+    /// their commit HLCs increase strictly.
+    fn broadcast_changes(&self, applied: &[bool], mut prior: PriorValues) {
+        let changes: Vec<(TripleKey, ChangeRecord)> = self
             .operations
             .iter()
-            .map(|op| match op {
-                PendingTriple::Insert(record) => ChangeRecord {
-                    change_type: ChangeType::Insert,
-                    entity_id: record.entity_id,
-                    attribute_id: record.attribute_id,
-                    value: Some(record.value.clone_value()),
-                    hlc: record.created_hlc,
-                },
-                PendingTriple::Update(record) => ChangeRecord {
-                    change_type: ChangeType::Update,
-                    entity_id: record.entity_id,
-                    attribute_id: record.attribute_id,
-                    value: Some(record.value.clone_value()),
-                    hlc: record.created_hlc,
-                },
+            .zip(applied)
+            .filter(|(_, applied)| **applied)
+            .map(|(op, _)| match op {
+                PendingTriple::Insert(record) => (
+                    (record.entity_id, record.attribute_id, record.value_hash()),
+                    ChangeRecord {
+                        change_type: ChangeType::Insert,
+                        entity_id: record.entity_id,
+                        attribute_id: record.attribute_id,
+                        value: Some(record.value.clone_value()),
+                        old_value: None,
+                        hlc: record.created_hlc,
+                    },
+                ),
+                PendingTriple::Update(record) => (
+                    (record.entity_id, record.attribute_id, record.value_hash()),
+                    ChangeRecord {
+                        change_type: ChangeType::Update,
+                        entity_id: record.entity_id,
+                        attribute_id: record.attribute_id,
+                        value: Some(record.value.clone_value()),
+                        old_value: None,
+                        hlc: record.created_hlc,
+                    },
+                ),
                 PendingTriple::Delete {
                     entity_id,
                     attribute_id,
-                } => ChangeRecord {
-                    change_type: ChangeType::Delete,
-                    entity_id: *entity_id,
-                    attribute_id: *attribute_id,
-                    value: None,
+                    value_hash,
                     hlc,
-                },
+                } => (
+                    (*entity_id, *attribute_id, *value_hash),
+                    ChangeRecord {
+                        change_type: ChangeType::Delete,
+                        entity_id: *entity_id,
+                        attribute_id: *attribute_id,
+                        value: None,
+                        old_value: None,
+                        hlc: *hlc,
+                    },
+                ),
+            })
+            .collect();
+        let changes: Vec<ChangeRecord> = coalesce_changes(changes)
+            .into_iter()
+            .map(|(key, mut change)| {
+                change.old_value = prior.remove(&key);
+                change
             })
             .collect();
 
+        if changes.is_empty() {
+            return;
+        }
+
         // Ignore send errors - no subscribers is not an error
         let _ = self.change_tx.send(ChangeNotification {
             source_connection_id: self.connection_id,
             changes,
+            commit_hlc: self.hlc,
         });
     }
 
@@ -1297,12 +3979,21 @@ impl<'a> WalTransaction<'a> {
 }
 
 impl Drop for WalTransaction<'_> {
+    /// Abort a transaction that was never finalized.
+    ///
+    /// Dropping without `commit()` or `abort()` (for example on an early `?`
+    /// return) discards the buffered operations rather than panicking, since
+    /// nothing has been written to the WAL yet.
     fn drop(&mut self) {
-        assert!(
-            self.finalized,
-            "WalTransaction dropped without commit() or abort() - this is a programming error. \
-             Transactions must be explicitly finalized to ensure data integrity."
-        );
+        if !self.finalized {
+            tracing::warn!(
+                "WalTransaction {} dropped without commit() or abort(); discarding {} buffered operations",
+                self.txn_id,
+                self.operations.len()
+            );
+            self.finalized = true;
+            self.operations.clear();
+        }
     }
 }
 
@@ -1317,6 +4008,12 @@ impl Drop for WalTransaction<'_> {
 /// - Sees all records with `created_txn <= snapshot_txn`
 /// - Does not see records with `created_txn > snapshot_txn`
 /// - Does not see records with `deleted_txn <= snapshot_txn`
+/// - Does not see records that had expired when the snapshot was created
+///
+/// Secondary index lookups (`get_entities_with_attribute`,
+/// `get_attributes_for_entity` and `iter_entities`) return IDs without reading
+/// records, so they still include expired triples until the expiry sweep
+/// tombstones them.
 ///
 /// # Lifecycle
 ///
@@ -1326,19 +4023,52 @@ impl Drop for WalTransaction<'_> {
 /// Read-only snapshot for concurrent database access.
 ///
 /// Uses position-independent reads to allow concurrent access from multiple threads.
-#[cfg(unix)]
 pub struct Snapshot<'a> {
     file: &'a DatabaseFile,
+    /// Registry this snapshot is tracked in, used to pin it.
+    active_snapshots: &'a ActiveSnapshots,
     /// The transaction ID this snapshot sees.
     txn_id: TxnId,
     /// HLC timestamp when the snapshot was created.
     hlc: HlcTimestamp,
+    /// Physical time expiry is checked against, fixed when the snapshot was
+    /// created.
+    now_ms: u64,
 }
 
-#[cfg(unix)]
 impl<'a> Snapshot<'a> {
-    const fn new(file: &'a DatabaseFile, txn_id: TxnId, hlc: HlcTimestamp) -> Self {
-        Self { file, txn_id, hlc }
+    const fn new(
+        file: &'a DatabaseFile,
+        active_snapshots: &'a ActiveSnapshots,
+        txn_id: TxnId,
+        hlc: HlcTimestamp,
+        now_ms: u64,
+    ) -> Self {
+        Self {
+            file,
+            active_snapshots,
+            txn_id,
+            hlc,
+            now_ms,
+        }
+    }
+
+    /// Pin this snapshot's view so it can be resumed later.
+    ///
+    /// The pin registers its own reference for garbage collection, so it stays
+    /// valid after this snapshot is closed and released. Resume it with
+    /// `db.resume_snapshot(pinned)` and release it with `db.unpin_snapshot(pinned)`.
+    ///
+    /// # Errors
+    /// Returns `DatabaseError::LockPoisoned` if the snapshot registry's lock
+    /// is poisoned.
+    pub fn pin(&self) -> Result<PinnedSnapshot, DatabaseError> {
+        self.active_snapshots.register(self.txn_id)?;
+        Ok(PinnedSnapshot {
+            txn_id: self.txn_id,
+            hlc: self.hlc,
+            now_ms: self.now_ms,
+        })
     }
 
     /// Get the snapshot's transaction ID.
@@ -1357,21 +4087,68 @@ impl<'a> Snapshot<'a> {
 
     /// Look up a single triple by entity and attribute ID.
     ///
-    /// Returns the record only if it's visible at this snapshot.
+    /// Returns the record only if it's visible and unexpired at this snapshot.
+    /// For a cardinality-many attribute, returns the value with the lowest
+    /// value hash; use `get_values` for all of them.
     pub fn get(
         &self,
         entity_id: &EntityId,
         attribute_id: &AttributeId,
     ) -> Result<Option<TripleRecord>, DatabaseError> {
+        Ok(self.get_values(entity_id, attribute_id)?.into_iter().next())
+    }
+
+    /// Get every value of an entity's attribute, in value hash order.
+    ///
+    /// Returns the records visible and unexpired at this snapshot. A
+    /// cardinality-one attribute has at most one.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the primary index cannot be read.
+    pub fn get_values(
+        &self,
+        entity_id: &EntityId,
+        attribute_id: &AttributeId,
+    ) -> Result<Vec<TripleRecord>, DatabaseError> {
+        let root_page = self.file.superblock().primary_index_root;
+        let index = PrimaryIndexReader::new(self.file, root_page);
+
+        Ok(index
+            .get_values(entity_id, attribute_id)?
+            .into_iter()
+            .filter(|record| record.is_visible_to(self.txn_id) && !record.is_expired(self.now_ms))
+            .collect())
+    }
+
+    /// Check whether a triple visible at this snapshot exists.
+    ///
+    /// For a cardinality-one triple, reads only the record's MVCC header,
+    /// never its value, so this is cheaper than `get(...).is_some()` for large
+    /// values. A cardinality-many attribute exists if any of its values does.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the primary index cannot be read.
+    pub fn contains(
+        &self,
+        entity_id: &EntityId,
+        attribute_id: &AttributeId,
+    ) -> Result<bool, DatabaseError> {
         let root_page = self.file.superblock().primary_index_root;
         let index = PrimaryIndexReader::new(self.file, root_page);
 
-        Ok(index.get_visible(entity_id, attribute_id, self.txn_id)?)
+        let visible = index
+            .get_txn_range(entity_id, attribute_id)?
+            .is_some_and(|range| {
+                range.is_visible_to(self.txn_id) && !range.is_expired(self.now_ms)
+            });
+        Ok(visible || !self.get_values(entity_id, attribute_id)?.is_empty())
     }
 
     /// Scan all triples for an entity.
     ///
-    /// Returns only triples visible at this snapshot.
+    /// Returns only triples visible and unexpired at this snapshot.
     pub fn scan_entity(&self, entity_id: &EntityId) -> Result<Vec<TripleRecord>, DatabaseError> {
         let root_page = self.file.superblock().primary_index_root;
         let index = PrimaryIndexReader::new(self.file, root_page);
@@ -1379,7 +4156,9 @@ impl<'a> Snapshot<'a> {
 
         let mut results = Vec::new();
         while let Some(record) = scan.next_record()? {
-            results.push(record);
+            if !record.is_expired(self.now_ms) {
+                results.push(record);
+            }
         }
 
         Ok(results)
@@ -1387,34 +4166,43 @@ impl<'a> Snapshot<'a> {
 
     /// Count all visible triples in the index.
     ///
-    /// Note: This counts records visible at this snapshot.
+    /// Note: This counts records visible and unexpired at this snapshot.
     pub fn count(&self) -> Result<usize, DatabaseError> {
         let root_page = self.file.superblock().primary_index_root;
         let index = PrimaryIndexReader::new(self.file, root_page);
         let mut cursor = index.cursor_visible(self.txn_id)?;
 
         let mut count = 0;
-        while cursor.next_record()?.is_some() {
-            count += 1;
+        while let Some(record) = cursor.next_record()? {
+            if !record.is_expired(self.now_ms) {
+                count += 1;
+            }
         }
 
         Ok(count)
     }
 
-    /// Collect all visible triples in key order.
+    /// Iterate over all visible triples in primary key order.
     ///
-    /// Returns records visible at this snapshot.
-    pub fn collect_all(&self) -> Result<Vec<TripleRecord>, DatabaseError> {
+    /// Unlike `collect_all`, records are read from the index one leaf page at
+    /// a time and filtered for visibility and expiry as the iterator advances,
+    /// so a scan over a large database never buffers it in memory.
+    pub fn iter_all(&self) -> Result<TripleIterator<'a>, DatabaseError> {
         let root_page = self.file.superblock().primary_index_root;
         let index = PrimaryIndexReader::new(self.file, root_page);
-        let mut cursor = index.cursor_visible(self.txn_id)?;
+        let inner = index.cursor_visible(self.txn_id)?;
 
-        let mut results = Vec::new();
-        while let Some(record) = cursor.next_record()? {
-            results.push(record);
-        }
+        Ok(TripleIterator {
+            inner,
+            now_ms: self.now_ms,
+        })
+    }
 
-        Ok(results)
+    /// Collect all visible triples in key order.
+    ///
+    /// Returns records visible and unexpired at this snapshot.
+    pub fn collect_all(&self) -> Result<Vec<TripleRecord>, DatabaseError> {
+        self.iter_all()?.collect()
     }
 
     /// Get all entity IDs that have a given attribute.
@@ -1425,9 +4213,7 @@ impl<'a> Snapshot<'a> {
         &self,
         attribute_id: &AttributeId,
     ) -> Result<Vec<EntityId>, DatabaseError> {
-        let root_page = self.file.superblock().attribute_index_root;
-        let index = AttributeIndexReader::new(self.file, root_page);
-        let mut scan = index.scan_attribute_visible(attribute_id, self.txn_id)?;
+        let mut scan = self.iter_entities_with_attribute(attribute_id)?;
 
         let mut entities = Vec::new();
         while let Some(entity_id) = scan.next_entity()? {
@@ -1437,6 +4223,121 @@ impl<'a> Snapshot<'a> {
         Ok(entities)
     }
 
+    /// Iterate over the entity IDs that have a given attribute, in ascending
+    /// order.
+    ///
+    /// Unlike `get_entities_with_attribute`, entities are read from the
+    /// attribute index as the iterator advances, so stopping early reads no
+    /// further.
+    ///
+    /// # Post-conditions
+    /// - Each entity is yielded once, however many values it has
+    /// - Only entities visible at this snapshot are yielded
+    pub fn iter_entities_with_attribute(
+        &self,
+        attribute_id: &AttributeId,
+    ) -> Result<AttributeEntityIterator<'a>, DatabaseError> {
+        let superblock = self.file.superblock();
+        let index = AttributeIndexReader::new(
+            self.file,
+            superblock.attribute_index_root,
+            superblock.attribute_value_index_root,
+        );
+        let inner = index.scan_attribute_visible(attribute_id, self.txn_id)?;
+
+        Ok(AttributeEntityIterator { inner })
+    }
+
+    /// Get up to `limit` entity IDs that have a given attribute, in ascending
+    /// order.
+    ///
+    /// Like `get_entities_with_attribute`, but stops reading the attribute
+    /// index once `limit` visible entities are found.
+    pub fn sample_entities_with_attribute(
+        &self,
+        attribute_id: &AttributeId,
+        limit: usize,
+    ) -> Result<Vec<EntityId>, DatabaseError> {
+        let mut scan = self.iter_entities_with_attribute(attribute_id)?;
+
+        let mut entities = Vec::new();
+        while entities.len() < limit
+            && let Some(entity_id) = scan.next_entity()?
+        {
+            entities.push(entity_id);
+        }
+
+        Ok(entities)
+    }
+
+    /// Get the triples of an attribute whose values fall in `range`.
+    ///
+    /// Reads only the part of the attribute index's value entries between
+    /// the range's bounds, then fetches each listed triple from the primary
+    /// index and keeps it if its value is in the range, which settles the
+    /// long strings the index compares by prefix alone.
+    ///
+    /// # Post-conditions
+    /// - The records are visible and unexpired at this snapshot
+    /// - Each record's value is in `range`
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the attribute or primary index cannot be read.
+    pub fn scan_attribute_range(
+        &self,
+        attribute_id: &AttributeId,
+        range: &ValueRange,
+    ) -> Result<Vec<TripleRecord>, DatabaseError> {
+        let superblock = self.file.superblock();
+        let index = AttributeIndexReader::new(
+            self.file,
+            superblock.attribute_index_root,
+            superblock.attribute_value_index_root,
+        );
+        let mut scan =
+            index.scan_attribute_range(attribute_id, range.clone_range(), self.txn_id)?;
+
+        let root_page = self.file.superblock().primary_index_root;
+        let primary = PrimaryIndexReader::new(self.file, root_page);
+        let mut records = Vec::new();
+        while let Some((entity_id, value_hash)) = scan.next_triple()? {
+            if let Some(record) = primary.get_value(&entity_id, attribute_id, value_hash)?
+                && record.is_visible_to(self.txn_id)
+                && !record.is_expired(self.now_ms)
+                && range.contains(&record.value)
+            {
+                records.push(record);
+            }
+        }
+
+        Ok(records)
+    }
+
+    /// Get the first attribute ID in the attribute index after `start_after`,
+    /// or the first one if `start_after` is `None`.
+    ///
+    /// Each call is one index seek, so walking the distinct attributes does
+    /// not read every triple. The attribute returned may have no triples
+    /// visible at this snapshot; check with `sample_entities_with_attribute`.
+    pub fn next_attribute(
+        &self,
+        start_after: Option<&AttributeId>,
+    ) -> Result<Option<AttributeId>, DatabaseError> {
+        let root_page = self.file.superblock().attribute_index_root;
+        if root_page == 0 {
+            // No triple has ever been committed, so there is no index to walk
+            return Ok(None);
+        }
+
+        let index = AttributeIndexReader::new(
+            self.file,
+            root_page,
+            self.file.superblock().attribute_value_index_root,
+        );
+        Ok(index.next_attribute(start_after)?)
+    }
+
     /// Get all attribute IDs for a given entity.
     ///
     /// Uses the entity-attribute index for efficient lookup.
@@ -1457,6 +4358,74 @@ impl<'a> Snapshot<'a> {
         Ok(attributes)
     }
 
+    /// Get the attribute IDs of an entity that start with `prefix`.
+    ///
+    /// Useful for namespaced attributes: the entity-attribute index keeps an
+    /// entity's attributes sorted, so the scan seeks to the first match and
+    /// stops as soon as it leaves the prefix range. An empty prefix matches
+    /// every attribute; a prefix longer than an attribute ID matches none.
+    /// Returns only attributes visible at this snapshot, in ascending order.
+    pub fn scan_entity_attributes_prefix(
+        &self,
+        entity_id: &EntityId,
+        prefix: &[u8],
+    ) -> Result<Vec<AttributeId>, DatabaseError> {
+        let root_page = self.file.superblock().entity_attribute_index_root;
+        let index = EntityAttributeIndexReader::new(self.file, root_page);
+        let mut scan = index.scan_entity_prefix_visible(entity_id, prefix, self.txn_id)?;
+
+        let mut attributes = Vec::new();
+        while let Some(attribute_id) = scan.next_attribute()? {
+            attributes.push(attribute_id);
+        }
+
+        Ok(attributes)
+    }
+
+    /// Iterate over all distinct entity IDs visible at this snapshot.
+    ///
+    /// Walks the entity-attribute index, which is keyed entity-first, so each
+    /// entity is yielded once regardless of how many attributes it has.
+    /// Entities are yielded in ascending byte order.
+    ///
+    /// To page through a large database, pass the last entity ID of the
+    /// previous chunk as `start_after`; iteration resumes with the next entity.
+    pub fn iter_entities(
+        &self,
+        start_after: Option<&EntityId>,
+    ) -> Result<EntityIterator<'a>, DatabaseError> {
+        let root_page = self.file.superblock().entity_attribute_index_root;
+        if root_page == 0 {
+            // No triple has ever been committed, so there is no index to walk
+            return Ok(EntityIterator { inner: None });
+        }
+
+        let index = EntityAttributeIndexReader::new(self.file, root_page);
+        let inner = index.scan_entities_visible(start_after, self.txn_id)?;
+
+        Ok(EntityIterator { inner: Some(inner) })
+    }
+
+    /// Iterate over visible triples in primary key order, starting at
+    /// `(entity_id, attribute_id)` (inclusive).
+    ///
+    /// To resume a scan, pass the key of the next triple to read; the key need
+    /// not exist.
+    pub fn scan_from(
+        &self,
+        entity_id: &EntityId,
+        attribute_id: &AttributeId,
+    ) -> Result<TripleIterator<'a>, DatabaseError> {
+        let root_page = self.file.superblock().primary_index_root;
+        let index = PrimaryIndexReader::new(self.file, root_page);
+        let inner = index.cursor_visible_from(entity_id, attribute_id, self.txn_id)?;
+
+        Ok(TripleIterator {
+            inner,
+            now_ms: self.now_ms,
+        })
+    }
+
     /// Close the snapshot and return its transaction ID.
     ///
     /// After closing, call `db.release_snapshot(txn_id)` to allow
@@ -1467,22 +4436,538 @@ impl<'a> Snapshot<'a> {
     }
 }
 
-/// Statistics about pending garbage collection.
-#[derive(Debug)]
-pub struct GcStats {
-    /// Number of tombstones (deleted records) awaiting GC.
-    pub pending_tombstones: u64,
-    /// The minimum active snapshot transaction ID.
-    /// Tombstones with `deleted_txn >= min_active_snapshot` cannot be collected.
-    pub min_active_snapshot: Option<TxnId>,
+/// A snapshot view pinned for later use.
+///
+/// Holds a garbage collection registration until passed to
+/// `Database::unpin_snapshot`, so deleted records it can see are never
+/// collected while it is pinned.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PinnedSnapshot {
+    /// The transaction ID the pinned snapshot sees.
+    txn_id: TxnId,
+    /// HLC timestamp when the pinned snapshot was created.
+    hlc: HlcTimestamp,
+    /// Physical time the pinned snapshot checks expiry against.
+    now_ms: u64,
 }
 
-/// Result of an incremental GC tick.
-#[derive(Debug)]
-pub struct GcTickResult {
-    /// Number of records physically removed in this tick.
-    pub records_removed: u64,
-    /// Number of tombstones remaining after this tick.
+impl PinnedSnapshot {
+    /// Get the pinned snapshot's transaction ID.
+    #[must_use]
+    pub const fn snapshot_txn(&self) -> TxnId {
+        self.txn_id
+    }
+}
+
+/// A write transaction that is validated at commit instead of holding
+/// exclusive access while it runs.
+///
+/// Created by `Database::begin_optimistic`. Writes are buffered, and reads
+/// never see them. At the default `IsolationLevel::Snapshot`, reads see the
+/// snapshot taken at begin and are recorded in a read set, and
+/// `Database::commit_optimistic` rejects the transaction if another
+/// transaction wrote any triple it read or wrote, or any attribute it
+/// scanned, in the meantime. At
+/// `IsolationLevel::ReadCommitted`, reads see the latest committed state and
+/// are not validated; only the triples it wrote are.
+///
+/// # Invariants
+/// - Holds a garbage collection pin until committed or aborted, so tombstones
+///   written after it began stay visible to validation
+#[derive(Debug)]
+pub struct OptimisticTransaction {
+    /// Snapshot the transaction reads from.
+    snapshot: PinnedSnapshot,
+    /// The connection that created this transaction.
+    connection_id: ConnectionId,
+    /// Triples read, validated at commit.
+    reads: HashSet<(EntityId, AttributeId)>,
+    /// Attributes scanned, validated at commit.
+    scanned_attributes: HashSet<AttributeId>,
+    /// Buffered writes, applied in order at commit.
+    writes: Vec<OptimisticWrite>,
+    /// Which committed state reads see.
+    isolation: IsolationLevel,
+    /// Whether this transaction has been committed or aborted.
+    finalized: bool,
+}
+
+/// A write buffered by an `OptimisticTransaction`.
+///
+/// `hlc` is `None` for writes that take the commit's HLC.
+#[derive(Debug)]
+enum OptimisticWrite {
+    Insert {
+        entity_id: EntityId,
+        attribute_id: AttributeId,
+        value: TripleValue,
+        hlc: Option<HlcTimestamp>,
+    },
+    Update {
+        entity_id: EntityId,
+        attribute_id: AttributeId,
+        value: TripleValue,
+        hlc: Option<HlcTimestamp>,
+    },
+    Delete {
+        entity_id: EntityId,
+        attribute_id: AttributeId,
+        hlc: Option<HlcTimestamp>,
+    },
+}
+
+/// What an `OptimisticTransaction` read, to validate at commit.
+#[derive(Debug, Clone, Copy)]
+enum ReadRange {
+    /// One triple, present or not.
+    Triple(EntityId, AttributeId),
+    /// Every triple of an attribute.
+    Attribute(AttributeId),
+}
+
+impl OptimisticWrite {
+    /// The triple this write targets.
+    const fn key(&self) -> (EntityId, AttributeId) {
+        match self {
+            Self::Insert {
+                entity_id,
+                attribute_id,
+                ..
+            }
+            | Self::Update {
+                entity_id,
+                attribute_id,
+                ..
+            }
+            | Self::Delete {
+                entity_id,
+                attribute_id,
+                ..
+            } => (*entity_id, *attribute_id),
+        }
+    }
+}
+
+impl OptimisticTransaction {
+    fn new(snapshot: PinnedSnapshot, connection_id: ConnectionId) -> Self {
+        Self {
+            snapshot,
+            connection_id,
+            reads: HashSet::new(),
+            scanned_attributes: HashSet::new(),
+            writes: Vec::new(),
+            isolation: IsolationLevel::default(),
+            finalized: false,
+        }
+    }
+
+    /// Set which committed state the transaction's reads see.
+    ///
+    /// At `IsolationLevel::ReadCommitted` reads are not validated at commit,
+    /// so a transaction may commit having read a value another transaction
+    /// has since overwritten. Only choose it for transactions whose writes do
+    /// not depend on their reads staying current.
+    ///
+    /// # Pre-conditions
+    /// - No reads have been made yet
+    #[must_use]
+    pub fn with_isolation(mut self, isolation: IsolationLevel) -> Self {
+        debug_assert!(
+            self.reads.is_empty() && self.scanned_attributes.is_empty(),
+            "isolation set after reads"
+        );
+        self.isolation = isolation;
+        self
+    }
+
+    /// Get which committed state the transaction's reads see.
+    #[must_use]
+    pub const fn isolation(&self) -> IsolationLevel {
+        self.isolation
+    }
+
+    /// Get the transaction ID of the snapshot this transaction reads from.
+    ///
+    /// At `IsolationLevel::ReadCommitted` reads see later commits too; this is
+    /// still the point writes are validated from.
+    #[must_use]
+    pub const fn snapshot_txn(&self) -> TxnId {
+        self.snapshot.snapshot_txn()
+    }
+
+    /// Run `read` against the committed state the transaction's isolation
+    /// level sees, recording `range` in the read set for snapshot reads.
+    fn read<T>(
+        &mut self,
+        db: &Database,
+        range: ReadRange,
+        read: impl FnOnce(&Snapshot<'_>) -> Result<T, DatabaseError>,
+    ) -> Result<T, DatabaseError> {
+        match self.isolation {
+            IsolationLevel::Snapshot => {
+                match range {
+                    ReadRange::Triple(entity_id, attribute_id) => {
+                        self.reads.insert((entity_id, attribute_id));
+                    }
+                    ReadRange::Attribute(attribute_id) => {
+                        self.scanned_attributes.insert(attribute_id);
+                    }
+                }
+                read(&db.resume_snapshot(self.snapshot))
+            }
+            IsolationLevel::ReadCommitted => {
+                let snapshot = db.begin_readonly()?;
+                let result = read(&snapshot);
+                db.release_snapshot(snapshot.close())?;
+                result
+            }
+        }
+    }
+
+    /// Get a triple as of the transaction's snapshot, or the latest committed
+    /// state at `IsolationLevel::ReadCommitted`.
+    ///
+    /// # Pre-conditions
+    /// - `db` is the database that began this transaction
+    pub fn get(
+        &mut self,
+        db: &Database,
+        entity_id: &EntityId,
+        attribute_id: &AttributeId,
+    ) -> Result<Option<TripleRecord>, DatabaseError> {
+        self.read(
+            db,
+            ReadRange::Triple(*entity_id, *attribute_id),
+            |snapshot| snapshot.get(entity_id, attribute_id),
+        )
+    }
+
+    /// Check whether a triple exists as of the transaction's snapshot, or in
+    /// the latest committed state at `IsolationLevel::ReadCommitted`.
+    ///
+    /// # Pre-conditions
+    /// - `db` is the database that began this transaction
+    pub fn contains(
+        &mut self,
+        db: &Database,
+        entity_id: &EntityId,
+        attribute_id: &AttributeId,
+    ) -> Result<bool, DatabaseError> {
+        self.read(
+            db,
+            ReadRange::Triple(*entity_id, *attribute_id),
+            |snapshot| snapshot.contains(entity_id, attribute_id),
+        )
+    }
+
+    /// Get the entities that have an attribute as of the transaction's
+    /// snapshot, or in the latest committed state at
+    /// `IsolationLevel::ReadCommitted`.
+    ///
+    /// At `IsolationLevel::Snapshot` the whole attribute is validated at
+    /// commit, so any concurrent write of it, including an insert for an
+    /// entity not returned here, makes the commit fail.
+    ///
+    /// # Pre-conditions
+    /// - `db` is the database that began this transaction
+    pub fn get_entities_with_attribute(
+        &mut self,
+        db: &Database,
+        attribute_id: &AttributeId,
+    ) -> Result<Vec<EntityId>, DatabaseError> {
+        self.read(db, ReadRange::Attribute(*attribute_id), |snapshot| {
+            snapshot.get_entities_with_attribute(attribute_id)
+        })
+    }
+
+    /// Get the triples of an attribute whose values fall in `range`, as
+    /// `get_entities_with_attribute` reads and validates the attribute.
+    ///
+    /// # Pre-conditions
+    /// - `db` is the database that began this transaction
+    pub fn scan_attribute_range(
+        &mut self,
+        db: &Database,
+        attribute_id: &AttributeId,
+        range: &ValueRange,
+    ) -> Result<Vec<TripleRecord>, DatabaseError> {
+        self.read(db, ReadRange::Attribute(*attribute_id), |snapshot| {
+            snapshot.scan_attribute_range(attribute_id, range)
+        })
+    }
+
+    /// Insert a triple with the commit's HLC.
+    pub fn insert(&mut self, entity_id: EntityId, attribute_id: AttributeId, value: TripleValue) {
+        self.writes.push(OptimisticWrite::Insert {
+            entity_id,
+            attribute_id,
+            value,
+            hlc: None,
+        });
+    }
+
+    /// Insert a triple with a client-provided HLC timestamp.
+    ///
+    /// As with `WalTransaction::insert_with_hlc`, the write is dropped at
+    /// commit if the stored value's HLC is not older.
+    pub fn insert_with_hlc(
+        &mut self,
+        entity_id: EntityId,
+        attribute_id: AttributeId,
+        value: TripleValue,
+        hlc: HlcTimestamp,
+    ) {
+        self.writes.push(OptimisticWrite::Insert {
+            entity_id,
+            attribute_id,
+            value,
+            hlc: Some(hlc),
+        });
+    }
+
+    /// Insert a triple with a client-provided HLC timestamp, only if no live
+    /// triple exists for it.
+    ///
+    /// The insert is validated at commit like any write, so a concurrent
+    /// insert of the same triple makes the commit fail with a write conflict
+    /// rather than be overwritten, at either isolation level. Buffered writes count, as in
+    /// `WalTransaction::insert_if_absent`.
+    ///
+    /// # Pre-conditions
+    /// - `db` is the database that began this transaction
+    ///
+    /// # Post-conditions
+    /// - Returns `true` and buffers an insert if the triple was absent
+    /// - Returns `false` and buffers nothing if it was present
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the snapshot cannot be read.
+    pub fn insert_if_absent_with_hlc(
+        &mut self,
+        db: &Database,
+        entity_id: EntityId,
+        attribute_id: AttributeId,
+        value: TripleValue,
+        hlc: HlcTimestamp,
+    ) -> Result<bool, DatabaseError> {
+        let buffered = self
+            .writes
+            .iter()
+            .rev()
+            .find(|write| write.key() == (entity_id, attribute_id))
+            .map(|write| !matches!(write, OptimisticWrite::Delete { .. }));
+        let present = match buffered {
+            Some(present) => present,
+            None => self.contains(db, &entity_id, &attribute_id)?,
+        };
+        if present {
+            return Ok(false);
+        }
+        self.insert_with_hlc(entity_id, attribute_id, value, hlc);
+        Ok(true)
+    }
+
+    /// Update a triple with the commit's HLC.
+    pub fn update(&mut self, entity_id: EntityId, attribute_id: AttributeId, value: TripleValue) {
+        self.writes.push(OptimisticWrite::Update {
+            entity_id,
+            attribute_id,
+            value,
+            hlc: None,
+        });
+    }
+
+    /// Update a triple with a client-provided HLC timestamp.
+    pub fn update_with_hlc(
+        &mut self,
+        entity_id: EntityId,
+        attribute_id: AttributeId,
+        value: TripleValue,
+        hlc: HlcTimestamp,
+    ) {
+        self.writes.push(OptimisticWrite::Update {
+            entity_id,
+            attribute_id,
+            value,
+            hlc: Some(hlc),
+        });
+    }
+
+    /// Delete a triple with the commit's HLC.
+    pub fn delete(&mut self, entity_id: EntityId, attribute_id: AttributeId) {
+        self.writes.push(OptimisticWrite::Delete {
+            entity_id,
+            attribute_id,
+            hlc: None,
+        });
+    }
+
+    /// Delete a triple with a client-provided HLC timestamp.
+    pub fn delete_with_hlc(
+        &mut self,
+        entity_id: EntityId,
+        attribute_id: AttributeId,
+        hlc: HlcTimestamp,
+    ) {
+        self.writes.push(OptimisticWrite::Delete {
+            entity_id,
+            attribute_id,
+            hlc: Some(hlc),
+        });
+    }
+}
+
+impl Drop for OptimisticTransaction {
+    /// Warn about a transaction that was never committed or aborted.
+    ///
+    /// Its snapshot stays pinned, which holds back garbage collection.
+    fn drop(&mut self) {
+        if !self.finalized {
+            tracing::warn!(
+                "OptimisticTransaction at snapshot {} dropped without commit or abort; its snapshot stays pinned",
+                self.snapshot.snapshot_txn()
+            );
+        }
+    }
+}
+
+/// Iterator over visible triples in primary key order.
+///
+/// Created by `Snapshot::iter_all` and `Snapshot::scan_from`. Besides
+/// `next_record`, it implements `Iterator`, yielding `Result`s so a read error
+/// ends the scan without panicking.
+pub struct TripleIterator<'a> {
+    inner: PrimaryIndexReaderCursor<'a>,
+    /// Physical time expiry is checked against.
+    now_ms: u64,
+}
+
+impl TripleIterator<'_> {
+    /// Get the next visible, unexpired triple, or `None` when exhausted.
+    pub fn next_record(&mut self) -> Result<Option<TripleRecord>, DatabaseError> {
+        while let Some(record) = self.inner.next_record()? {
+            if !record.is_expired(self.now_ms) {
+                return Ok(Some(record));
+            }
+        }
+        Ok(None)
+    }
+}
+
+impl Iterator for TripleIterator<'_> {
+    type Item = Result<TripleRecord, DatabaseError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_record().transpose()
+    }
+}
+
+/// Iterator over distinct entity IDs visible to a snapshot.
+///
+/// Created by `Snapshot::iter_entities`.
+pub struct EntityIterator<'a> {
+    /// `None` when the database has no entity-attribute index yet.
+    inner: Option<DistinctEntityReaderIterator<'a>>,
+}
+
+impl EntityIterator<'_> {
+    /// Get the next distinct entity ID, or `None` when exhausted.
+    pub fn next_entity(&mut self) -> Result<Option<EntityId>, DatabaseError> {
+        match &mut self.inner {
+            Some(inner) => Ok(inner.next_entity()?),
+            None => Ok(None),
+        }
+    }
+}
+
+/// Iterator over the entity IDs with an attribute, visible to a snapshot.
+///
+/// Created by `Snapshot::iter_entities_with_attribute`.
+pub struct AttributeEntityIterator<'a> {
+    inner: AttributeScanReaderIterator<'a>,
+}
+
+impl AttributeEntityIterator<'_> {
+    /// Get the next entity ID, or `None` when exhausted.
+    pub fn next_entity(&mut self) -> Result<Option<EntityId>, DatabaseError> {
+        Ok(self.inner.next_entity()?)
+    }
+}
+
+/// Statistics about pending garbage collection.
+#[derive(Debug)]
+pub struct GcStats {
+    /// Number of tombstones (deleted records) awaiting GC.
+    pub pending_tombstones: u64,
+    /// The minimum active snapshot transaction ID.
+    /// Tombstones with `deleted_txn >= min_active_snapshot` cannot be collected.
+    pub min_active_snapshot: Option<TxnId>,
+}
+
+/// Page usage of the database file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StorageStats {
+    /// Total number of pages in the file, including the superblock and WAL.
+    pub total_pages: u64,
+    /// Pages holding overflow values that are still referenced.
+    pub overflow_pages: u64,
+    /// Pages freed and awaiting reuse.
+    pub free_pages: u64,
+}
+
+/// Result of a point-in-time restore.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct RestoreResult {
+    /// Transactions replayed from the WAL on top of the base backup.
+    pub transactions_replayed: usize,
+}
+
+/// Result of committing a transaction.
+#[derive(Debug)]
+pub struct CommitOutcome {
+    /// ID of the committed transaction.
+    pub txn_id: TxnId,
+    /// HLC assigned to the transaction.
+    pub hlc: HlcTimestamp,
+    /// Number of operations that changed stored state and were broadcast.
+    /// Excludes operations dropped by last-writer-wins and no-op updates.
+    pub changes_applied: usize,
+    /// The changes that were broadcast, including writes made by triggers.
+    pub applied: Vec<AppliedChange>,
+}
+
+/// A committed operation that changed stored state.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AppliedChange {
+    /// Whether the operation inserted, updated or deleted the triple.
+    pub change_type: ChangeType,
+    /// Entity of the changed triple.
+    pub entity_id: EntityId,
+    /// Attribute of the changed triple.
+    pub attribute_id: AttributeId,
+    /// HLC the change was written with.
+    pub hlc: HlcTimestamp,
+}
+
+/// Result of an expiry sweep.
+#[derive(Debug)]
+pub struct ExpirySweepResult {
+    /// Number of expired triples tombstoned by this sweep.
+    pub triples_expired: u64,
+    /// Whether expired triples remain because the sweep hit its batch size.
+    pub more_expired: bool,
+}
+
+/// Result of an incremental GC tick.
+#[derive(Debug)]
+pub struct GcTickResult {
+    /// Number of records physically removed in this tick.
+    pub records_removed: u64,
+    /// Number of tombstones processed in this tick, including stale ones whose
+    /// record was rewritten after the delete.
+    pub tombstones_processed: u64,
+    /// Number of tombstones remaining after this tick.
     pub tombstones_remaining: u64,
 }
 
@@ -1509,12 +4994,70 @@ pub enum DatabaseError {
     Clock(ClockError),
     /// Tombstone list error.
     Tombstone(TombstoneError),
+    /// Backup read or write error.
+    Backup(BackupError),
+    /// A file in an older format version could not be read to upgrade it.
+    Upgrade(UpgradeError),
+    /// A point-in-time restore's base backup was taken after its target.
+    RestoreTargetBeforeBackup {
+        /// HLC of the backup's snapshot.
+        backup_hlc: HlcTimestamp,
+        /// HLC the restore was asked for.
+        target_hlc: HlcTimestamp,
+    },
+    /// The WAL no longer holds every transaction committed after this HLC.
+    WalHistoryUnavailable(HlcTimestamp),
+    /// A crash tore these pages of the file, and they could not be rebuilt.
+    TornPages(Vec<PageId>),
     /// Triple not found for update/delete.
     NotFound,
     /// Mutex/RwLock was poisoned.
     LockPoisoned,
     /// Connection not established (`ConnectRequest` not yet received).
     NotConnected,
+    /// The registry is at its open-database limit and every open database is in use.
+    TooManyOpenDatabases(usize),
+    /// An attribute name is empty or too long to register.
+    InvalidAttributeName(String),
+    /// An attribute's cardinality cannot change while it has live triples.
+    AttributeInUse(AttributeId),
+    /// A savepoint does not belong to the transaction or was already released.
+    UnknownSavepoint,
+    /// Triggers nested deeper than the limit in one commit.
+    TriggerDepthExceeded(usize),
+    /// Triggers buffered more writes than the limit in one commit.
+    TooManyTriggeredWrites(usize),
+    /// A write was attempted on a database opened with `open_readonly`.
+    ReadOnly,
+    /// An optimistic transaction touched a triple another transaction wrote
+    /// after it began.
+    WriteConflict {
+        /// Entity of the conflicting triple.
+        entity_id: EntityId,
+        /// Attribute of the conflicting triple.
+        attribute_id: AttributeId,
+    },
+    /// A write's value is too large for its record to fit in the WAL.
+    ValueTooLarge {
+        /// Serialized size of the value in bytes.
+        size: u64,
+        /// Largest value size whose record fits in the WAL.
+        max: u64,
+    },
+    /// A transaction's records would not all fit in the WAL.
+    TransactionTooLarge {
+        /// Total size of the transaction's records in bytes.
+        size: u64,
+        /// WAL capacity in bytes.
+        capacity: u64,
+    },
+    /// The database file belongs to another node.
+    NodeIdMismatch {
+        /// The node ID the database was opened with.
+        configured: u32,
+        /// The node ID stored in the file.
+        stored: u32,
+    },
 }
 
 impl std::fmt::Display for DatabaseError {
@@ -1530,9 +5073,66 @@ impl std::fmt::Display for DatabaseError {
             Self::Checkpoint(e) => write!(f, "checkpoint error: {e}"),
             Self::Clock(e) => write!(f, "clock error: {e}"),
             Self::Tombstone(e) => write!(f, "tombstone error: {e}"),
+            Self::Backup(e) => write!(f, "backup error: {e}"),
+            Self::Upgrade(e) => write!(f, "upgrade error: {e}"),
+            Self::RestoreTargetBeforeBackup {
+                backup_hlc,
+                target_hlc,
+            } => write!(
+                f,
+                "restore target {}.{} is before the backup's snapshot at {}.{}",
+                target_hlc.physical_time,
+                target_hlc.logical_counter,
+                backup_hlc.physical_time,
+                backup_hlc.logical_counter
+            ),
+            Self::WalHistoryUnavailable(hlc) => write!(
+                f,
+                "the WAL no longer holds every transaction committed after {}.{}",
+                hlc.physical_time, hlc.logical_counter
+            ),
+            Self::TornPages(pages) => write!(
+                f,
+                "a crash tore pages {pages:?}, which replaying the WAL cannot repair"
+            ),
             Self::NotFound => write!(f, "triple not found"),
             Self::LockPoisoned => write!(f, "database lock poisoned"),
             Self::NotConnected => write!(f, "connection not established"),
+            Self::TooManyOpenDatabases(limit) => {
+                write!(f, "too many open databases (limit {limit}), all in use")
+            }
+            Self::InvalidAttributeName(name) => write!(f, "invalid attribute name: {name:?}"),
+            Self::AttributeInUse(attribute_id) => write!(
+                f,
+                "cannot change the cardinality of attribute {attribute_id}: it has live triples"
+            ),
+            Self::UnknownSavepoint => write!(f, "unknown or released savepoint"),
+            Self::TriggerDepthExceeded(limit) => {
+                write!(f, "triggers nested more than {limit} deep")
+            }
+            Self::TooManyTriggeredWrites(limit) => {
+                write!(f, "triggers buffered more than {limit} writes")
+            }
+            Self::ReadOnly => write!(f, "database was opened read-only"),
+            Self::WriteConflict {
+                entity_id,
+                attribute_id,
+            } => write!(
+                f,
+                "write conflict on triple ({entity_id}, {attribute_id}), retry the transaction"
+            ),
+            Self::ValueTooLarge { size, max } => write!(
+                f,
+                "value of {size} bytes does not fit in the WAL; values must be at most {max} bytes"
+            ),
+            Self::TransactionTooLarge { size, capacity } => write!(
+                f,
+                "transaction of {size} bytes does not fit in the {capacity} byte WAL; split it into smaller transactions"
+            ),
+            Self::NodeIdMismatch { configured, stored } => write!(
+                f,
+                "database belongs to node {stored}, but this node is configured as {configured}"
+            ),
         }
     }
 }
@@ -1550,8 +5150,47 @@ impl std::error::Error for DatabaseError {
             Self::Checkpoint(e) => Some(e),
             Self::Clock(e) => Some(e),
             Self::Tombstone(e) => Some(e),
-            Self::NotFound | Self::LockPoisoned | Self::NotConnected => None,
+            Self::Backup(e) => Some(e),
+            Self::Upgrade(e) => Some(e),
+            Self::NotFound
+            | Self::LockPoisoned
+            | Self::NotConnected
+            | Self::TooManyOpenDatabases(_)
+            | Self::InvalidAttributeName(_)
+            | Self::AttributeInUse(_)
+            | Self::UnknownSavepoint
+            | Self::TriggerDepthExceeded(_)
+            | Self::TooManyTriggeredWrites(_)
+            | Self::ReadOnly
+            | Self::WriteConflict { .. }
+            | Self::ValueTooLarge { .. }
+            | Self::TransactionTooLarge { .. }
+            | Self::NodeIdMismatch { .. }
+            | Self::RestoreTargetBeforeBackup { .. }
+            | Self::WalHistoryUnavailable(_)
+            | Self::TornPages(_) => None,
+        }
+    }
+}
+
+impl DatabaseError {
+    /// Whether the error comes from the buffer pool running out of buffers.
+    ///
+    /// Exhaustion is transient: buffers are returned as concurrent operations
+    /// finish, so the failed operation may be retried.
+    #[must_use]
+    pub fn is_resource_exhausted(&self) -> bool {
+        let mut error: Option<&(dyn std::error::Error + 'static)> = Some(self);
+        while let Some(e) = error {
+            if matches!(e.downcast_ref(), Some(FileError::BufferPoolExhausted))
+                || matches!(e.downcast_ref(), Some(StorageError::BufferPoolExhausted))
+                || matches!(e.downcast_ref(), Some(TombstoneError::BufferPoolExhausted))
+            {
+                return true;
+            }
+            error = e.source();
         }
+        false
     }
 }
 
@@ -1615,11 +5254,31 @@ impl From<TombstoneError> for DatabaseError {
     }
 }
 
+impl From<BackupError> for DatabaseError {
+    fn from(e: BackupError) -> Self {
+        Self::Backup(e)
+    }
+}
+
+impl From<UpgradeError> for DatabaseError {
+    fn from(e: UpgradeError) -> Self {
+        Self::Upgrade(e)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::storage::buffer_pool::BufferPool;
+    use crate::storage::btree::{BTreeError, InternalNode};
+    use crate::storage::buffer_pool::{BufferPool, MIN_POOL_CAPACITY};
+    use crate::storage::checkpoint::CheckpointProgress;
+    use crate::storage::checksum::ChecksumAlgorithm;
+    use crate::storage::overflow::overflow_data_per_page;
+    use crate::storage::page::{MAX_PAGE_SIZE, PAGE_SIZE, Page};
+    use crate::storage::recovery::{DivergenceKind, IndexDivergence, VerifiedIndex};
+    use crate::storage::wal::MIN_WAL_CAPACITY;
     use crate::types::{AttributeId, EntityId};
+    use std::sync::atomic::AtomicBool;
     use tempfile::tempdir;
 
     fn test_pool() -> Arc<BufferPool> {
@@ -1648,7 +5307,8 @@ mod tests {
                 entity_id,
                 attribute_id,
                 TripleValue::String("hello".to_string()),
-            );
+            )
+            .expect("insert");
             txn.commit().expect("commit");
         }
 
@@ -1698,20 +5358,90 @@ mod tests {
     }
 
     #[test]
-    fn test_database_abort_no_persist() {
+    fn test_open_rejects_mismatched_node_id() {
         let (_dir, path) = create_test_db();
         let pool = test_pool();
+        let db = Database::create_with_options(
+            &path,
+            Arc::clone(&pool),
+            FileFormat::default(),
+            DEFAULT_WAL_CAPACITY,
+            CheckpointConfig::default(),
+            7,
+        )
+        .expect("create db");
+        db.close().expect("close");
 
-        // Create and abort transaction
-        {
-            let mut db = Database::create(&path, Arc::clone(&pool)).expect("create db");
-            let mut txn = db.begin(0).expect("begin txn");
+        let result = Database::open_with_options(
+            &path,
+            Arc::clone(&pool),
+            CheckpointConfig::default(),
+            8,
+            false,
+        );
+        assert!(matches!(
+            result,
+            Err(DatabaseError::NodeIdMismatch {
+                configured: 8,
+                stored: 7,
+            })
+        ));
 
-            txn.insert(
-                EntityId([1u8; 16]),
-                AttributeId([2u8; 16]),
+        // The owning node, and an open that takes the stored ID, both succeed
+        let (db, _) = Database::open_with_options(
+            &path,
+            Arc::clone(&pool),
+            CheckpointConfig::default(),
+            7,
+            false,
+        )
+        .expect("open as owner");
+        assert_eq!(db.node_id(), 7);
+        db.close().expect("close");
+        let (db, _) = Database::open(&path, pool).expect("open");
+        assert_eq!(db.node_id(), 7);
+    }
+
+    #[test]
+    fn test_open_claims_unassigned_node_id() {
+        let (_dir, path) = create_test_db();
+        let pool = test_pool();
+        Database::create(&path, Arc::clone(&pool))
+            .expect("create db")
+            .close()
+            .expect("close");
+
+        let (db, _) = Database::open_or_create_with_node_id(&path, Arc::clone(&pool), 3)
+            .expect("claim the file");
+        assert_eq!(db.node_id(), 3);
+        db.close().expect("close");
+
+        let result = Database::open_or_create_with_node_id(&path, pool, 4);
+        assert!(matches!(
+            result,
+            Err(DatabaseError::NodeIdMismatch {
+                configured: 4,
+                stored: 3,
+            })
+        ));
+    }
+
+    #[test]
+    fn test_database_abort_no_persist() {
+        let (_dir, path) = create_test_db();
+        let pool = test_pool();
+
+        // Create and abort transaction
+        {
+            let mut db = Database::create(&path, Arc::clone(&pool)).expect("create db");
+            let mut txn = db.begin(0).expect("begin txn");
+
+            txn.insert(
+                EntityId([1u8; 16]),
+                AttributeId([2u8; 16]),
                 TripleValue::String("aborted".to_string()),
-            );
+            )
+            .expect("insert");
             txn.abort(); // Don't commit
         }
 
@@ -1728,312 +5458,3847 @@ mod tests {
         }
     }
 
+    /// Read the committed value for `entity` under attribute `[1; 16]`.
+    fn committed_value(db: &mut Database, entity: u8) -> Option<TripleValue> {
+        let mut txn = db.begin(0).expect("begin txn");
+        let record = txn
+            .get(&EntityId([entity; 16]), &AttributeId([1u8; 16]))
+            .expect("get");
+        txn.abort();
+        record.map(|record| record.value)
+    }
+
+    #[test]
+    fn test_savepoint_rollback_discards_later_operations() {
+        let (_dir, path) = create_test_db();
+        let mut db = Database::create(&path, test_pool()).expect("create db");
+        let attribute_id = AttributeId([1u8; 16]);
+
+        let mut txn = db.begin(0).expect("begin txn");
+        txn.insert(EntityId([1u8; 16]), attribute_id, TripleValue::Number(1.0))
+            .expect("insert");
+        let savepoint = txn.savepoint();
+        txn.insert(EntityId([2u8; 16]), attribute_id, TripleValue::Number(2.0))
+            .expect("insert");
+        txn.insert(EntityId([1u8; 16]), attribute_id, TripleValue::Number(3.0))
+            .expect("insert");
+        txn.rollback_to(savepoint).expect("rollback");
+        txn.commit().expect("commit");
+
+        assert_eq!(committed_value(&mut db, 1), Some(TripleValue::Number(1.0)));
+        assert_eq!(committed_value(&mut db, 2), None);
+    }
+
+    #[test]
+    fn test_savepoint_nested_rollback_ordering() {
+        let (_dir, path) = create_test_db();
+        let mut db = Database::create(&path, test_pool()).expect("create db");
+        let attribute_id = AttributeId([1u8; 16]);
+
+        let mut txn = db.begin(0).expect("begin txn");
+        let outer = txn.savepoint();
+        txn.insert(EntityId([1u8; 16]), attribute_id, TripleValue::Number(1.0))
+            .expect("insert");
+        let inner = txn.savepoint();
+        txn.insert(EntityId([2u8; 16]), attribute_id, TripleValue::Number(2.0))
+            .expect("insert");
+
+        // Rolling back to the inner savepoint keeps it and the outer one
+        txn.rollback_to(inner).expect("rollback inner");
+        txn.insert(EntityId([3u8; 16]), attribute_id, TripleValue::Number(3.0))
+            .expect("insert");
+        txn.rollback_to(inner).expect("rollback inner again");
+        txn.insert(EntityId([4u8; 16]), attribute_id, TripleValue::Number(4.0))
+            .expect("insert");
+
+        // Rolling back to the outer savepoint releases the inner one
+        txn.rollback_to(outer).expect("rollback outer");
+        assert!(matches!(
+            txn.rollback_to(inner),
+            Err(DatabaseError::UnknownSavepoint)
+        ));
+        txn.insert(EntityId([5u8; 16]), attribute_id, TripleValue::Number(5.0))
+            .expect("insert");
+        txn.commit().expect("commit");
+
+        for entity in 1..=4 {
+            assert_eq!(committed_value(&mut db, entity), None, "entity {entity}");
+        }
+        assert_eq!(committed_value(&mut db, 5), Some(TripleValue::Number(5.0)));
+    }
+
+    #[test]
+    fn test_savepoint_from_another_transaction_is_rejected() {
+        let (_dir, path) = create_test_db();
+        let mut db = Database::create(&path, test_pool()).expect("create db");
+
+        let mut first = db.begin(0).expect("begin txn");
+        let savepoint = first.savepoint();
+        first.abort();
+
+        let mut second = db.begin(0).expect("begin txn");
+        second.savepoint();
+        assert!(matches!(
+            second.rollback_to(savepoint),
+            Err(DatabaseError::UnknownSavepoint)
+        ));
+        second.abort();
+    }
+
+    #[test]
+    fn test_database_drop_without_finalize_aborts() {
+        let (_dir, path) = create_test_db();
+        let pool = test_pool();
+
+        // Drop an unfinalized transaction, as an early `?` return would
+        {
+            let mut db = Database::create(&path, Arc::clone(&pool)).expect("create db");
+            let mut rx = db.subscribe_to_changes(1);
+            let mut txn = db.begin(0).expect("begin txn");
+            txn.insert(
+                EntityId([1u8; 16]),
+                AttributeId([2u8; 16]),
+                TripleValue::String("dropped".to_string()),
+            )
+            .expect("insert");
+            drop(txn);
+            assert!(rx.try_recv().is_err(), "dropped txn should not broadcast");
+
+            // The database is still usable afterwards
+            let mut txn = db.begin(0).expect("begin txn");
+            let record = txn
+                .get(&EntityId([1u8; 16]), &AttributeId([2u8; 16]))
+                .expect("get");
+            assert!(record.is_none());
+            txn.abort();
+        }
+
+        // Reopen and verify data is NOT there
+        {
+            let (mut db, _) = Database::open(&path, Arc::clone(&pool)).expect("open db");
+            let mut txn = db.begin(0).expect("begin txn");
+
+            let record = txn
+                .get(&EntityId([1u8; 16]), &AttributeId([2u8; 16]))
+                .expect("get");
+            assert!(record.is_none());
+            txn.abort();
+        }
+    }
+
+    #[test]
+    fn test_database_update_and_delete() {
+        let (_dir, path) = create_test_db();
+        let pool = test_pool();
+
+        let mut db = Database::create(&path, pool).expect("create db");
+
+        // Insert
+        {
+            let mut txn = db.begin(0).expect("begin");
+            txn.insert(
+                EntityId([1u8; 16]),
+                AttributeId([1u8; 16]),
+                TripleValue::Number(1.0),
+            )
+            .expect("insert");
+            txn.commit().expect("commit");
+        }
+
+        // Update
+        {
+            let mut txn = db.begin(0).expect("begin");
+            txn.update(
+                EntityId([1u8; 16]),
+                AttributeId([1u8; 16]),
+                TripleValue::Number(2.0),
+            )
+            .expect("update");
+            txn.commit().expect("commit");
+        }
+
+        // Verify update
+        {
+            let mut txn = db.begin(0).expect("begin");
+            let record = txn
+                .get(&EntityId([1u8; 16]), &AttributeId([1u8; 16]))
+                .expect("get");
+            assert_eq!(record.unwrap().value, TripleValue::Number(2.0));
+            txn.abort();
+        }
+
+        // Delete
+        {
+            let mut txn = db.begin(0).expect("begin");
+            txn.delete(&EntityId([1u8; 16]), &AttributeId([1u8; 16]))
+                .expect("delete");
+            txn.commit().expect("commit");
+        }
+
+        // Verify delete
+        {
+            let mut txn = db.begin(0).expect("begin");
+            let record = txn
+                .get(&EntityId([1u8; 16]), &AttributeId([1u8; 16]))
+                .expect("get");
+            assert!(record.is_none());
+            txn.abort();
+        }
+    }
+
+    fn hlc_at(physical_time: u64) -> HlcTimestamp {
+        HlcTimestamp {
+            physical_time,
+            logical_counter: 0,
+            node_id: 1,
+        }
+    }
+
+    #[test]
+    fn test_lww_newest_value_wins_regardless_of_arrival_order() {
+        let (_dir, path) = create_test_db();
+        let mut db = Database::create(&path, test_pool()).expect("create db");
+
+        let orders: [[u64; 3]; 6] = [
+            [1, 2, 3],
+            [1, 3, 2],
+            [2, 1, 3],
+            [2, 3, 1],
+            [3, 1, 2],
+            [3, 2, 1],
+        ];
+        for (index, order) in orders.iter().enumerate() {
+            #[allow(clippy::cast_possible_truncation)]
+            let entity_id = EntityId([index as u8 + 1; 16]);
+            let attribute_id = AttributeId([9u8; 16]);
+
+            for &version in order {
+                let mut txn = db.begin(0).expect("begin");
+                #[allow(clippy::cast_precision_loss)]
+                txn.insert_with_hlc(
+                    entity_id,
+                    attribute_id,
+                    TripleValue::Number(version as f64),
+                    hlc_at(version * 1000),
+                )
+                .expect("insert_with_hlc");
+                txn.commit().expect("commit");
+            }
+
+            let mut txn = db.begin(0).expect("begin");
+            let record = txn
+                .get(&entity_id, &attribute_id)
+                .expect("get")
+                .expect("record exists");
+            txn.abort();
+            assert_eq!(record.value, TripleValue::Number(3.0), "order {order:?}");
+            assert_eq!(record.created_hlc, hlc_at(3000), "order {order:?}");
+        }
+    }
+
+    #[test]
+    fn test_lww_equal_hlc_does_not_overwrite() {
+        let (_dir, path) = create_test_db();
+        let mut db = Database::create(&path, test_pool()).expect("create db");
+        let entity_id = EntityId([1u8; 16]);
+        let attribute_id = AttributeId([2u8; 16]);
+
+        for value in ["first", "second"] {
+            let mut txn = db.begin(0).expect("begin");
+            txn.insert_with_hlc(
+                entity_id,
+                attribute_id,
+                TripleValue::String(value.to_owned()),
+                hlc_at(1000),
+            )
+            .expect("insert_with_hlc");
+            txn.commit().expect("commit");
+        }
+
+        let mut txn = db.begin(0).expect("begin");
+        let record = txn.get(&entity_id, &attribute_id).expect("get");
+        txn.abort();
+        assert_eq!(
+            record.map(|record| record.value),
+            Some(TripleValue::String("first".to_owned()))
+        );
+    }
+
+    #[test]
+    fn test_lww_stale_write_is_not_broadcast_or_persisted() {
+        let (_dir, path) = create_test_db();
+        let entity_id = EntityId([1u8; 16]);
+        let attribute_id = AttributeId([2u8; 16]);
+
+        {
+            let mut db = Database::create(&path, test_pool()).expect("create db");
+            let mut txn = db.begin(1).expect("begin");
+            txn.update_with_hlc(
+                entity_id,
+                attribute_id,
+                TripleValue::Number(2.0),
+                hlc_at(2000),
+            )
+            .expect("update_with_hlc");
+            txn.commit().expect("commit");
+
+            let mut rx = db.subscribe_to_changes(0);
+            let mut txn = db.begin(1).expect("begin");
+            txn.update_with_hlc(
+                entity_id,
+                attribute_id,
+                TripleValue::Number(1.0),
+                hlc_at(1000),
+            )
+            .expect("update_with_hlc");
+            txn.commit().expect("commit");
+            assert!(rx.try_recv().is_err(), "stale write should not broadcast");
+            // Dropped without close so reopening replays the WAL
+        }
+
+        let (mut db, _) = Database::open(&path, test_pool()).expect("reopen");
+        let mut txn = db.begin(0).expect("begin");
+        let record = txn.get(&entity_id, &attribute_id).expect("get");
+        txn.abort();
+        assert_eq!(
+            record.map(|record| record.value),
+            Some(TripleValue::Number(2.0))
+        );
+    }
+
+    #[test]
+    fn test_lww_within_one_transaction() {
+        let (_dir, path) = create_test_db();
+        let mut db = Database::create(&path, test_pool()).expect("create db");
+        let plain_key = (EntityId([1u8; 16]), AttributeId([1u8; 16]));
+        let client_key = (EntityId([2u8; 16]), AttributeId([2u8; 16]));
+
+        let mut txn = db.begin(0).expect("begin");
+        // Plain inserts share the transaction HLC; the later one applies
+        txn.insert(plain_key.0, plain_key.1, TripleValue::Number(1.0))
+            .expect("insert");
+        txn.insert(plain_key.0, plain_key.1, TripleValue::Number(2.0))
+            .expect("insert");
+        // Client writes out of order; the newer HLC applies
+        txn.insert_with_hlc(
+            client_key.0,
+            client_key.1,
+            TripleValue::Number(2.0),
+            hlc_at(2000),
+        )
+        .expect("insert_with_hlc");
+        txn.insert_with_hlc(
+            client_key.0,
+            client_key.1,
+            TripleValue::Number(1.0),
+            hlc_at(1000),
+        )
+        .expect("insert_with_hlc");
+        txn.commit().expect("commit");
+
+        let mut txn = db.begin(0).expect("begin");
+        let plain = txn.get(&plain_key.0, &plain_key.1).expect("get");
+        let client = txn.get(&client_key.0, &client_key.1).expect("get");
+        txn.abort();
+        assert_eq!(
+            plain.map(|record| record.value),
+            Some(TripleValue::Number(2.0))
+        );
+        assert_eq!(
+            client.map(|record| record.value),
+            Some(TripleValue::Number(2.0))
+        );
+    }
+
+    /// Write `value` at `hlc_at(write_time)` after deleting at `hlc_at(delete_time)`,
+    /// returning the resulting live value.
+    fn write_after_delete(
+        db: &mut Database,
+        delete_time: u64,
+        write_time: u64,
+    ) -> Option<TripleValue> {
+        let entity_id = EntityId([1u8; 16]);
+        let attribute_id = AttributeId([2u8; 16]);
+
+        let mut txn = db.begin(0).expect("begin");
+        txn.insert_with_hlc(
+            entity_id,
+            attribute_id,
+            TripleValue::Number(1.0),
+            hlc_at(1000),
+        )
+        .expect("insert_with_hlc");
+        txn.commit().expect("commit");
+
+        let mut txn = db.begin(0).expect("begin");
+        txn.delete_with_hlc(entity_id, attribute_id, hlc_at(delete_time))
+            .expect("delete");
+        txn.commit().expect("commit");
+
+        let mut txn = db.begin(0).expect("begin");
+        txn.insert_with_hlc(
+            entity_id,
+            attribute_id,
+            TripleValue::Number(2.0),
+            hlc_at(write_time),
+        )
+        .expect("insert_with_hlc");
+        txn.commit().expect("commit");
+
+        let mut txn = db.begin(0).expect("begin");
+        let record = txn.get(&entity_id, &attribute_id).expect("get");
+        txn.abort();
+        record.map(|record| record.value)
+    }
+
+    #[test]
+    fn test_lww_insert_older_than_delete_loses() {
+        let (_dir, path) = create_test_db();
+        let mut db = Database::create(&path, test_pool()).expect("create db");
+
+        assert_eq!(write_after_delete(&mut db, 3000, 2000), None);
+    }
+
+    #[test]
+    fn test_lww_insert_newer_than_delete_wins() {
+        let (_dir, path) = create_test_db();
+        let mut db = Database::create(&path, test_pool()).expect("create db");
+
+        assert_eq!(
+            write_after_delete(&mut db, 2000, 3000),
+            Some(TripleValue::Number(2.0))
+        );
+    }
+
+    #[test]
+    fn test_lww_stale_delete_is_ignored() {
+        let (_dir, path) = create_test_db();
+        let mut db = Database::create(&path, test_pool()).expect("create db");
+        let entity_id = EntityId([1u8; 16]);
+        let attribute_id = AttributeId([2u8; 16]);
+
+        let mut txn = db.begin(0).expect("begin");
+        txn.insert_with_hlc(
+            entity_id,
+            attribute_id,
+            TripleValue::Number(1.0),
+            hlc_at(2000),
+        )
+        .expect("insert_with_hlc");
+        txn.commit().expect("commit");
+
+        let mut rx = db.subscribe_to_changes(1);
+        let mut txn = db.begin(0).expect("begin");
+        txn.delete_with_hlc(entity_id, attribute_id, hlc_at(1000))
+            .expect("delete");
+        txn.commit().expect("commit");
+        assert!(rx.try_recv().is_err(), "stale delete should not broadcast");
+
+        let mut txn = db.begin(0).expect("begin");
+        let record = txn.get(&entity_id, &attribute_id).expect("get");
+        txn.abort();
+        assert_eq!(
+            record.map(|record| record.value),
+            Some(TripleValue::Number(1.0))
+        );
+    }
+
+    #[test]
+    fn test_apply_remote_changes_converges_to_higher_hlc() {
+        let dir = tempdir().expect("create temp dir");
+        let open_node = |name: &str, node_id: u32| {
+            Database::create_with_options(
+                &dir.path().join(name),
+                test_pool(),
+                FileFormat::default(),
+                DEFAULT_WAL_CAPACITY,
+                CheckpointConfig::default(),
+                node_id,
+            )
+            .expect("create db")
+        };
+        let mut a = open_node("a.db", 1);
+        let mut b = open_node("b.db", 2);
+        let hlc = |physical_time, node_id| HlcTimestamp {
+            physical_time,
+            logical_counter: 0,
+            node_id,
+        };
+        let attr = AttributeId([9u8; 16]);
+        let tied = EntityId([1u8; 16]);
+        let a_newer = EntityId([2u8; 16]);
+        let deleted = EntityId([3u8; 16]);
+
+        // Both nodes start with `deleted` from a
+        let mut txn = a.begin(0).expect("begin");
+        txn.insert_with_hlc(deleted, attr, TripleValue::Number(0.0), hlc(1000, 1))
+            .expect("insert_with_hlc");
+        txn.commit().expect("commit");
+        let initial = a.changes_since(HlcTimestamp::default()).expect("changes");
+        b.apply_remote_changes(&initial).expect("apply");
+
+        // Concurrent edits on each node
+        let mut txn = a.begin(0).expect("begin");
+        txn.insert_with_hlc(tied, attr, TripleValue::Number(1.0), hlc(5000, 1))
+            .expect("insert_with_hlc");
+        txn.insert_with_hlc(a_newer, attr, TripleValue::Number(1.0), hlc(7000, 1))
+            .expect("insert_with_hlc");
+        txn.delete_with_hlc(deleted, attr, hlc(4000, 1))
+            .expect("delete");
+        txn.commit().expect("commit");
+        let mut txn = b.begin(0).expect("begin");
+        txn.insert_with_hlc(tied, attr, TripleValue::Number(2.0), hlc(5000, 2))
+            .expect("insert_with_hlc");
+        txn.insert_with_hlc(a_newer, attr, TripleValue::Number(2.0), hlc(6000, 2))
+            .expect("insert_with_hlc");
+        txn.update_with_hlc(deleted, attr, TripleValue::Number(2.0), hlc(3000, 2))
+            .expect("update_with_hlc");
+        txn.commit().expect("commit");
+
+        // Exchange change streams
+        let from_a = a.changes_since(HlcTimestamp::default()).expect("changes");
+        let from_b = b.changes_since(HlcTimestamp::default()).expect("changes");
+        let mut rx = b.subscribe_to_changes(1);
+        a.apply_remote_changes(&from_b).expect("apply on a");
+        let outcome = b.apply_remote_changes(&from_a).expect("apply on b");
+
+        // Only a's winning writes changed b, and b's subscribers saw them
+        let notification = rx.try_recv().expect("notification");
+        let mut changed: Vec<(EntityId, ChangeType)> = notification
+            .changes
+            .iter()
+            .map(|change| (change.entity_id, change.change_type))
+            .collect();
+        changed.sort_by_key(|(entity_id, _)| entity_id.0);
+        assert_eq!(
+            changed,
+            vec![(a_newer, ChangeType::Insert), (deleted, ChangeType::Delete)]
+        );
+        assert_eq!(outcome.changes_applied, 2);
+        assert_eq!(
+            Clock::<SystemTimeSource>::compare(b.current_hlc(), hlc(7000, 1)),
+            Ordering::Greater
+        );
+
+        let value_of = |db: &Database, entity_id: &EntityId| {
+            let snapshot = db.begin_readonly().expect("begin readonly");
+            let value = snapshot
+                .get(entity_id, &attr)
+                .expect("get")
+                .map(|record| (record.value, record.created_hlc));
+            db.release_snapshot(snapshot.close())
+                .expect("release snapshot");
+            value
+        };
+        for (entity_id, expected) in [
+            (tied, Some((TripleValue::Number(2.0), hlc(5000, 2)))),
+            (a_newer, Some((TripleValue::Number(1.0), hlc(7000, 1)))),
+            (deleted, None),
+        ] {
+            assert_eq!(value_of(&a, &entity_id), expected, "node a");
+            assert_eq!(value_of(&b, &entity_id), expected, "node b");
+        }
+    }
+
+    #[test]
+    fn test_changes_between() {
+        let (_dir, path) = create_test_db();
+        let mut db = Database::create(&path, test_pool()).expect("create db");
+        let attribute_id = AttributeId([2u8; 16]);
+        for physical_time in [1000, 2000, 3000] {
+            let mut txn = db.begin(0).expect("begin");
+            txn.insert_with_hlc(
+                EntityId([1u8; 16]),
+                attribute_id,
+                TripleValue::Number(0.0),
+                HlcTimestamp::new(physical_time, 0),
+            )
+            .expect("insert_with_hlc");
+            txn.commit().expect("commit");
+        }
+        let times = |records: Vec<LogRecord>| -> Vec<u64> {
+            records
+                .iter()
+                .map(|record| record.hlc.physical_time)
+                .collect()
+        };
+
+        let window = db
+            .changes_between(HlcTimestamp::new(2000, 0), HlcTimestamp::new(3000, 0))
+            .expect("changes");
+        assert_eq!(times(window), vec![2000, 3000]);
+
+        let window = db
+            .changes_between(HlcTimestamp::new(1500, 0), HlcTimestamp::new(1999, 0))
+            .expect("changes");
+        assert!(window.is_empty());
+    }
+
+    /// A triple for entity `index` of a bulk load.
+    fn bulk_triple(index: u32, hlc: HlcTimestamp) -> PendingTripleData {
+        let mut entity = [0u8; 16];
+        entity[..4].copy_from_slice(&index.to_be_bytes());
+        PendingTripleData {
+            entity_id: EntityId(entity),
+            attribute_id: AttributeId([7u8; 16]),
+            value: TripleValue::Number(f64::from(index)),
+            hlc,
+        }
+    }
+
+    #[test]
+    fn test_bulk_load_writes_one_wal_transaction() {
+        const LOADED: u32 = 10_000;
+        let (_dir, path) = create_test_db();
+        let pool = test_pool();
+        let hlc = HlcTimestamp::new(1_000, 0);
+
+        let mut db = Database::create(&path, Arc::clone(&pool)).expect("create db");
+        let outcome = db
+            .bulk_load(0, (0..LOADED).map(|index| bulk_triple(index, hlc)))
+            .expect("bulk load");
+        assert_eq!(outcome.changes_applied, LOADED as usize);
+
+        let records = db.file.wal().expect("wal").read_all().expect("read wal");
+        let begins = records
+            .iter()
+            .filter(|record| matches!(record.payload, LogRecordPayload::Begin))
+            .count();
+        let commits = records
+            .iter()
+            .filter(|record| matches!(record.payload, LogRecordPayload::Commit))
+            .count();
+        let inserts = records
+            .iter()
+            .filter(|record| matches!(record.payload, LogRecordPayload::Insert(_)))
+            .count();
+        assert_eq!(begins, 1);
+        assert_eq!(commits, 1);
+        assert_eq!(inserts, LOADED as usize);
+        let txn_id = records[0].txn_id;
+        assert!(records.iter().all(|record| record.txn_id == txn_id));
+        db.close().expect("close");
+
+        let (db, _) = Database::open(&path, pool).expect("reopen db");
+        let snapshot = db.begin_readonly().expect("begin readonly");
+        assert_eq!(snapshot.count().expect("count"), LOADED as usize);
+        for index in [0, LOADED / 2, LOADED - 1] {
+            let triple = bulk_triple(index, hlc);
+            let record = snapshot
+                .get(&triple.entity_id, &triple.attribute_id)
+                .expect("get")
+                .expect("loaded triple");
+            assert_eq!(record.value, triple.value);
+        }
+        let txn_id = snapshot.close();
+        db.release_snapshot(txn_id).expect("release snapshot");
+    }
+
+    #[test]
+    fn test_bulk_load_larger_than_a_chunk_commits_whole() {
+        let loaded = u32::try_from(2 * BULK_LOAD_CHUNK_TRIPLES + 1).expect("fits in u32");
+        let (_dir, path) = create_test_db();
+        let pool = test_pool();
+        let hlc = HlcTimestamp::new(1_000, 0);
+        let rewrite = || PendingTripleData {
+            value: TripleValue::String("rewritten".to_string()),
+            ..bulk_triple(loaded - 1, hlc)
+        };
+
+        let mut db = Database::create(&path, Arc::clone(&pool)).expect("create db");
+        // Reversed, so every chunk is sorted, and the first triple is
+        // rewritten in the last chunk
+        let outcome = db
+            .bulk_load(
+                0,
+                (0..loaded)
+                    .rev()
+                    .map(|index| bulk_triple(index, hlc))
+                    .chain([rewrite()]),
+            )
+            .expect("bulk load");
+
+        // Both writes to the rewritten triple change it
+        assert_eq!(outcome.changes_applied, loaded as usize + 1);
+        let records = db.file.wal().expect("wal").read_all().expect("read wal");
+        let commits = records
+            .iter()
+            .filter(|record| matches!(record.payload, LogRecordPayload::Commit))
+            .count();
+        assert_eq!(commits, 1);
+        let txn_id = records[0].txn_id;
+        assert!(records.iter().all(|record| record.txn_id == txn_id));
+        db.close().expect("close");
+
+        let (db, _) = Database::open(&path, pool).expect("reopen db");
+        let snapshot = db.begin_readonly().expect("begin readonly");
+        assert_eq!(snapshot.count().expect("count"), loaded as usize);
+        let rewrite = rewrite();
+        let record = snapshot
+            .get(&rewrite.entity_id, &rewrite.attribute_id)
+            .expect("get")
+            .expect("rewritten triple");
+        assert_eq!(record.value, rewrite.value);
+        let first = bulk_triple(0, hlc);
+        let record = snapshot
+            .get(&first.entity_id, &first.attribute_id)
+            .expect("get")
+            .expect("loaded triple");
+        assert_eq!(record.value, first.value);
+        let txn_id = snapshot.close();
+        db.release_snapshot(txn_id).expect("release snapshot");
+    }
+
+    #[test]
+    fn test_bulk_load_recovers_whole_after_crash() {
+        let (_dir, path) = create_test_db();
+        let pool = test_pool();
+        let hlc = HlcTimestamp::new(1_000, 0);
+        {
+            let mut db = Database::create(&path, Arc::clone(&pool)).expect("create db");
+            db.bulk_load(0, (0..500).map(|index| bulk_triple(index, hlc)))
+                .expect("bulk load");
+            // Dropped without close() - simulates a crash
+        }
+
+        let (db, recovery) = Database::open(&path, pool).expect("open db");
+        let recovery = recovery.expect("the load needs recovery");
+        assert_eq!(recovery.transactions_replayed, 1);
+        assert_eq!(recovery.operations_applied, 500);
+        let snapshot = db.begin_readonly().expect("begin readonly");
+        assert_eq!(snapshot.count().expect("count"), 500);
+        let txn_id = snapshot.close();
+        db.release_snapshot(txn_id).expect("release snapshot");
+    }
+
+    #[test]
+    fn test_bulk_load_too_large_for_wal_writes_nothing() {
+        let (_dir, path) = create_test_db();
+        let mut db = Database::create_with_options(
+            &path,
+            test_pool(),
+            FileFormat::default(),
+            MIN_WAL_CAPACITY,
+            CheckpointConfig::default(),
+            DEFAULT_NODE_ID,
+        )
+        .expect("create db");
+        let hlc = HlcTimestamp::new(1_000, 0);
+        let read = std::cell::Cell::new(0usize);
+
+        let result = db.bulk_load(
+            0,
+            (0..50_000).map(|index| {
+                read.set(read.get() + 1);
+                bulk_triple(index, hlc)
+            }),
+        );
+        assert!(
+            matches!(result, Err(DatabaseError::TransactionTooLarge { .. })),
+            "{result:?}"
+        );
+        // The load stops at the chunk that overflows the WAL
+        assert!(read.get() < 50_000, "read {} triples", read.get());
+        assert_eq!(read.get() % BULK_LOAD_CHUNK_TRIPLES, 0);
+        let snapshot = db.begin_readonly().expect("begin readonly");
+        assert_eq!(snapshot.count().expect("count"), 0);
+        let txn_id = snapshot.close();
+        db.release_snapshot(txn_id).expect("release snapshot");
+
+        // Later writes still commit
+        db.bulk_load(0, (0..10).map(|index| bulk_triple(index, hlc)))
+            .expect("bulk load");
+    }
+
+    #[test]
+    fn test_bulk_load_resolves_conflicts_by_hlc() {
+        let (_dir, path) = create_test_db();
+        let mut db = Database::create(&path, test_pool()).expect("create db");
+        let older = HlcTimestamp::new(1_000, 0);
+        let newer = HlcTimestamp::new(2_000, 0);
+        let mut txn = db.begin(0).expect("begin");
+        let stored = bulk_triple(1, newer);
+        txn.insert_with_hlc(
+            stored.entity_id,
+            stored.attribute_id,
+            TripleValue::String("stored".to_string()),
+            newer,
+        )
+        .expect("insert");
+        txn.commit().expect("commit");
+
+        let outcome = db
+            .bulk_load(0, [bulk_triple(1, older), bulk_triple(2, older)])
+            .expect("bulk load");
+        assert_eq!(outcome.changes_applied, 1, "the older write is dropped");
+        let snapshot = db.begin_readonly().expect("begin readonly");
+        let record = snapshot
+            .get(&stored.entity_id, &stored.attribute_id)
+            .expect("get")
+            .expect("stored triple");
+        assert_eq!(record.value, TripleValue::String("stored".to_string()));
+        let txn_id = snapshot.close();
+        db.release_snapshot(txn_id).expect("release snapshot");
+    }
+
+    /// Every live triple of a database, as comparable tuples.
+    fn live_triples(
+        db: &Database,
+    ) -> Vec<(
+        EntityId,
+        AttributeId,
+        TripleValue,
+        HlcTimestamp,
+        Option<u64>,
+        Cardinality,
+    )> {
+        let snapshot = db.begin_readonly().expect("begin readonly");
+        let triples = snapshot
+            .collect_all()
+            .expect("collect all")
+            .into_iter()
+            .map(|record| {
+                (
+                    record.entity_id,
+                    record.attribute_id,
+                    record.value,
+                    record.created_hlc,
+                    record.expires_at_ms,
+                    record.cardinality,
+                )
+            })
+            .collect();
+        let txn_id = snapshot.close();
+        db.release_snapshot(txn_id).expect("release snapshot");
+        triples
+    }
+
+    #[test]
+    fn test_snapshot_export_import_round_trip() {
+        let dir = tempdir().expect("temp dir");
+        let mut db =
+            Database::create(&dir.path().join("source.db"), test_pool()).expect("create db");
+        let name = AttributeId([1u8; 16]);
+        let tag = AttributeId([2u8; 16]);
+        let session = AttributeId([3u8; 16]);
+        db.set_attribute_cardinality(tag, Cardinality::Many)
+            .expect("declare tag");
+
+        let mut txn = db.begin(0).expect("begin");
+        for index in 0..200u8 {
+            let entity = EntityId([index; 16]);
+            txn.insert(entity, name, TripleValue::String(format!("entity {index}")))
+                .expect("insert name");
+            txn.insert(entity, tag, TripleValue::Number(f64::from(index)))
+                .expect("insert tag");
+            txn.insert(entity, tag, TripleValue::Boolean(index % 2 == 0))
+                .expect("insert second tag");
+        }
+        txn.insert_with_expiry(
+            EntityId([1u8; 16]),
+            session,
+            TripleValue::Bytes(vec![0, 1, 2]),
+            4_102_444_800_000,
+        )
+        .expect("insert expiring");
+        txn.commit().expect("commit");
+        // Deleted and overwritten values are not exported
+        let mut txn = db.begin(0).expect("begin");
+        txn.delete(&EntityId([2u8; 16]), &name).expect("delete");
+        txn.update(
+            EntityId([3u8; 16]),
+            name,
+            TripleValue::String("renamed".to_string()),
+        )
+        .expect("update");
+        txn.commit().expect("commit");
+
+        let mut backup = Vec::new();
+        let exported = db.export_snapshot(&mut backup).expect("export");
+        let expected = live_triples(&db);
+        assert_eq!(exported, u64::try_from(expected.len()).expect("count"));
+
+        let imported = Database::import_snapshot(
+            &dir.path().join("restored.db"),
+            test_pool(),
+            backup.as_slice(),
+        )
+        .expect("import");
+
+        assert_eq!(live_triples(&imported), expected);
+        assert!(
+            imported
+                .check_index_consistency()
+                .expect("check indexes")
+                .is_empty()
+        );
+        let snapshot = imported.begin_readonly().expect("begin readonly");
+        assert_eq!(
+            snapshot
+                .get_entities_with_attribute(&name)
+                .expect("by attribute")
+                .len(),
+            199
+        );
+        assert_eq!(
+            snapshot
+                .get_attributes_for_entity(&EntityId([1u8; 16]))
+                .expect("by entity"),
+            vec![name, tag, session]
+        );
+        assert_eq!(
+            snapshot
+                .get_values(&EntityId([4u8; 16]), &tag)
+                .expect("tag values")
+                .len(),
+            2
+        );
+        let txn_id = snapshot.close();
+        imported.release_snapshot(txn_id).expect("release snapshot");
+    }
+
+    #[test]
+    fn test_snapshot_export_excludes_later_commits() {
+        let dir = tempdir().expect("temp dir");
+        let mut db =
+            Database::create(&dir.path().join("source.db"), test_pool()).expect("create db");
+        let hlc = HlcTimestamp::new(1_000, 0);
+        db.bulk_load(0, (0..10).map(|index| bulk_triple(index, hlc)))
+            .expect("bulk load");
+
+        let mut backup = Vec::new();
+        db.export_snapshot(&mut backup).expect("export");
+        db.bulk_load(0, (10..20).map(|index| bulk_triple(index, hlc)))
+            .expect("bulk load");
+
+        let imported = Database::import_snapshot(
+            &dir.path().join("restored.db"),
+            test_pool(),
+            backup.as_slice(),
+        )
+        .expect("import");
+        assert_eq!(live_triples(&imported).len(), 10);
+    }
+
+    #[test]
+    fn test_snapshot_import_spans_several_transactions() {
+        let dir = tempdir().expect("temp dir");
+        let mut db =
+            Database::create(&dir.path().join("source.db"), test_pool()).expect("create db");
+        let hlc = HlcTimestamp::new(1_000, 0);
+        db.bulk_load(0, (0..20_000).map(|index| bulk_triple(index, hlc)))
+            .expect("bulk load");
+        let mut backup = Vec::new();
+        db.export_snapshot(&mut backup).expect("export");
+
+        // The backup is larger than the WAL, so no single transaction holds it
+        let restored = dir.path().join("restored.db");
+        assert!(u64::try_from(backup.len()).expect("size") > MIN_WAL_CAPACITY);
+        let imported = Database::import_snapshot_with_options(
+            &restored,
+            test_pool(),
+            FileFormat::default(),
+            MIN_WAL_CAPACITY,
+            CheckpointConfig::default(),
+            DEFAULT_NODE_ID,
+            backup.as_slice(),
+        )
+        .expect("import");
+        assert_eq!(live_triples(&imported), live_triples(&db));
+
+        // The restored database survives a reopen
+        imported.close().expect("close");
+        let (reopened, _) = Database::open(&restored, test_pool()).expect("reopen");
+        assert_eq!(live_triples(&reopened).len(), 20_000);
+    }
+
+    #[test]
+    fn test_snapshot_import_rejects_truncated_backup() {
+        let dir = tempdir().expect("temp dir");
+        let mut db =
+            Database::create(&dir.path().join("source.db"), test_pool()).expect("create db");
+        let hlc = HlcTimestamp::new(1_000, 0);
+        db.bulk_load(0, (0..10).map(|index| bulk_triple(index, hlc)))
+            .expect("bulk load");
+        let mut backup = Vec::new();
+        db.export_snapshot(&mut backup).expect("export");
+        backup.truncate(backup.len() - 1);
+
+        let restored = dir.path().join("restored.db");
+        let result = Database::import_snapshot(&restored, test_pool(), backup.as_slice());
+
+        assert!(matches!(
+            result,
+            Err(DatabaseError::Backup(BackupError::Truncated))
+        ));
+        assert!(!restored.exists());
+    }
+
+    /// Commit one insert of `name` on `entity`, returning the commit's HLC.
+    fn commit_name(db: &mut Database, entity: u8, name: &str) -> HlcTimestamp {
+        let mut txn = db.begin(0).expect("begin");
+        txn.insert(
+            EntityId([entity; 16]),
+            AttributeId([1u8; 16]),
+            TripleValue::String(name.to_string()),
+        )
+        .expect("insert");
+        txn.commit().expect("commit").hlc
+    }
+
+    /// Export a backup of `db` to the file at `path`.
+    fn export_backup(db: &Database, path: &Path) {
+        let file = File::create(path).expect("create backup");
+        db.export_snapshot(file).expect("export");
+    }
+
+    /// The values of the live triples of `db`, in key order.
+    fn live_values(db: &Database) -> Vec<TripleValue> {
+        live_triples(db)
+            .into_iter()
+            .map(|(_, _, value, ..)| value)
+            .collect()
+    }
+
+    #[test]
+    fn test_restore_to_hlc_stops_after_target() {
+        let dir = tempdir().expect("temp dir");
+        let source = dir.path().join("source.db");
+        let base = dir.path().join("base.backup");
+        let mut db = Database::create(&source, test_pool()).expect("create db");
+        commit_name(&mut db, 1, "Ada");
+        export_backup(&db, &base);
+
+        commit_name(&mut db, 2, "Grace");
+        let middle = commit_name(&mut db, 3, "Edsger");
+        // An accidental bulk delete
+        let mut txn = db.begin(0).expect("begin");
+        for entity in 1..=3 {
+            txn.delete(&EntityId([entity; 16]), &AttributeId([1u8; 16]))
+                .expect("delete");
+        }
+        txn.commit().expect("commit");
+
+        let (restored, result) = Database::restore_to_hlc(
+            &source,
+            &base,
+            &dir.path().join("restored.db"),
+            test_pool(),
+            middle,
+        )
+        .expect("restore");
+
+        assert_eq!(
+            result,
+            RestoreResult {
+                transactions_replayed: 2,
+            }
+        );
+        assert_eq!(
+            live_values(&restored),
+            vec![
+                TripleValue::String("Ada".to_string()),
+                TripleValue::String("Grace".to_string()),
+                TripleValue::String("Edsger".to_string()),
+            ]
+        );
+        assert!(
+            restored
+                .check_index_consistency()
+                .expect("check indexes")
+                .is_empty()
+        );
+        // The source database is unchanged
+        assert!(live_triples(&db).is_empty());
+    }
+
+    #[test]
+    fn test_restore_to_hlc_inside_a_transaction_leaves_it_out() {
+        let dir = tempdir().expect("temp dir");
+        let source = dir.path().join("source.db");
+        let base = dir.path().join("base.backup");
+        let mut db = Database::create(&source, test_pool()).expect("create db");
+        commit_name(&mut db, 1, "Ada");
+        export_backup(&db, &base);
+        let target = commit_name(&mut db, 2, "Grace");
+        // One write at the target's HLC, and one at the commit's, after it
+        let mut txn = db.begin(0).expect("begin");
+        txn.insert_with_hlc(
+            EntityId([3u8; 16]),
+            AttributeId([1u8; 16]),
+            TripleValue::String("Edsger".to_string()),
+            target,
+        )
+        .expect("insert");
+        txn.insert(
+            EntityId([4u8; 16]),
+            AttributeId([1u8; 16]),
+            TripleValue::String("Barbara".to_string()),
+        )
+        .expect("insert");
+        let last = txn.commit().expect("commit").hlc;
+        assert_eq!(
+            Clock::<SystemTimeSource>::compare(last, target),
+            Ordering::Greater
+        );
+
+        let (restored, result) = Database::restore_to_hlc(
+            &source,
+            &base,
+            &dir.path().join("at_target.db"),
+            test_pool(),
+            target,
+        )
+        .expect("restore");
+
+        assert_eq!(result.transactions_replayed, 1);
+        assert_eq!(
+            live_values(&restored),
+            vec![
+                TripleValue::String("Ada".to_string()),
+                TripleValue::String("Grace".to_string()),
+            ]
+        );
+
+        let (restored, result) = Database::restore_to_hlc(
+            &source,
+            &base,
+            &dir.path().join("at_commit.db"),
+            test_pool(),
+            last,
+        )
+        .expect("restore");
+
+        assert_eq!(result.transactions_replayed, 2);
+        assert_eq!(live_values(&restored).len(), 4);
+    }
+
+    #[test]
+    fn test_restore_to_hlc_at_backup_replays_nothing() {
+        let dir = tempdir().expect("temp dir");
+        let source = dir.path().join("source.db");
+        let base = dir.path().join("base.backup");
+        let mut db = Database::create(&source, test_pool()).expect("create db");
+        commit_name(&mut db, 1, "Ada");
+        export_backup(&db, &base);
+        let backup_hlc = BackupReader::new(File::open(&base).expect("open backup"))
+            .expect("backup header")
+            .header()
+            .hlc;
+        commit_name(&mut db, 2, "Grace");
+
+        let (restored, result) = Database::restore_to_hlc(
+            &source,
+            &base,
+            &dir.path().join("restored.db"),
+            test_pool(),
+            backup_hlc,
+        )
+        .expect("restore");
+
+        assert_eq!(result.transactions_replayed, 0);
+        assert_eq!(live_triples(&restored).len(), 1);
+    }
+
+    #[test]
+    fn test_restore_to_hlc_rejects_target_before_backup() {
+        let dir = tempdir().expect("temp dir");
+        let source = dir.path().join("source.db");
+        let base = dir.path().join("base.backup");
+        let mut db = Database::create(&source, test_pool()).expect("create db");
+        let first = commit_name(&mut db, 1, "Ada");
+        commit_name(&mut db, 2, "Grace");
+        export_backup(&db, &base);
+
+        let restored = dir.path().join("restored.db");
+        let result = Database::restore_to_hlc(&source, &base, &restored, test_pool(), first);
+
+        assert!(matches!(
+            result,
+            Err(DatabaseError::RestoreTargetBeforeBackup { .. })
+        ));
+        assert!(!restored.exists());
+    }
+
+    #[test]
+    fn test_restore_to_hlc_without_backup_file_is_an_error() {
+        let dir = tempdir().expect("temp dir");
+        let source = dir.path().join("source.db");
+        let mut db = Database::create(&source, test_pool()).expect("create db");
+        let target = commit_name(&mut db, 1, "Ada");
+
+        let restored = dir.path().join("restored.db");
+        let result = Database::restore_to_hlc(
+            &source,
+            &dir.path().join("missing.backup"),
+            &restored,
+            test_pool(),
+            target,
+        );
+
+        assert!(matches!(
+            result,
+            Err(DatabaseError::Backup(BackupError::Io(_)))
+        ));
+        assert!(!restored.exists());
+    }
+
+    #[test]
+    fn test_restore_to_hlc_needs_wal_history_since_backup() {
+        let dir = tempdir().expect("temp dir");
+        let source = dir.path().join("source.db");
+        let base = dir.path().join("base.backup");
+        let mut db = Database::create(&source, test_pool()).expect("create db");
+        commit_name(&mut db, 1, "Ada");
+        export_backup(&db, &base);
+        let target = commit_name(&mut db, 2, "Grace");
+        // Compaction drops the transaction committed after the backup
+        db.compact_wal(MIN_WAL_CAPACITY).expect("compact WAL");
+
+        let restored = dir.path().join("restored.db");
+        let result = Database::restore_to_hlc(&source, &base, &restored, test_pool(), target);
+
+        assert!(matches!(
+            result,
+            Err(DatabaseError::WalHistoryUnavailable(_))
+        ));
+        assert!(!restored.exists());
+    }
+
+    #[test]
+    fn test_lww_delete_hlc_survives_reopen() {
+        let (_dir, path) = create_test_db();
+        let entity_id = EntityId([1u8; 16]);
+        let attribute_id = AttributeId([2u8; 16]);
+
+        {
+            let mut db = Database::create(&path, test_pool()).expect("create db");
+            let mut txn = db.begin(0).expect("begin");
+            txn.insert_with_hlc(
+                entity_id,
+                attribute_id,
+                TripleValue::Number(1.0),
+                hlc_at(1000),
+            )
+            .expect("insert_with_hlc");
+            txn.commit().expect("commit");
+
+            let mut rx = db.subscribe_to_changes(1);
+            let mut txn = db.begin(0).expect("begin");
+            txn.delete_with_hlc(entity_id, attribute_id, hlc_at(3000))
+                .expect("delete");
+            txn.commit().expect("commit");
+            let notification = rx.try_recv().expect("delete should broadcast");
+            assert_eq!(notification.changes[0].hlc, hlc_at(3000));
+            // Dropped without close so reopening replays the WAL
+        }
+
+        let (mut db, _) = Database::open(&path, test_pool()).expect("reopen");
+        let mut txn = db.begin(0).expect("begin");
+        txn.insert_with_hlc(
+            entity_id,
+            attribute_id,
+            TripleValue::Number(2.0),
+            hlc_at(2000),
+        )
+        .expect("insert_with_hlc");
+        txn.commit().expect("commit");
+
+        let mut txn = db.begin(0).expect("begin");
+        let record = txn.get(&entity_id, &attribute_id).expect("get");
+        txn.abort();
+        assert!(record.is_none(), "write older than the delete should lose");
+    }
+
+    #[test]
+    fn test_database_not_found_errors() {
+        let (_dir, path) = create_test_db();
+        let pool = test_pool();
+        let mut db = Database::create(&path, pool).expect("create db");
+
+        let mut txn = db.begin(0).expect("begin");
+
+        // Update non-existent
+        let result = txn.update(
+            EntityId([1u8; 16]),
+            AttributeId([1u8; 16]),
+            TripleValue::Null,
+        );
+        assert!(matches!(result, Err(DatabaseError::NotFound)));
+
+        // Delete non-existent
+        let result = txn.delete(&EntityId([1u8; 16]), &AttributeId([1u8; 16]));
+        assert!(matches!(result, Err(DatabaseError::NotFound)));
+
+        txn.abort();
+    }
+
+    #[test]
+    fn test_insert_if_absent() {
+        let (_dir, path) = create_test_db();
+        let pool = test_pool();
+        let mut db = Database::create(&path, pool).expect("create db");
+        let entity_id = EntityId([1u8; 16]);
+        let present = AttributeId([1u8; 16]);
+        let absent = AttributeId([2u8; 16]);
+
+        let mut txn = db.begin(0).expect("begin");
+        txn.insert(entity_id, present, TripleValue::Number(1.0))
+            .expect("insert");
+        txn.commit().expect("commit");
+
+        let mut txn = db.begin(0).expect("begin");
+        assert!(
+            !txn.insert_if_absent(entity_id, present, TripleValue::Number(2.0))
+                .expect("insert present")
+        );
+        assert!(
+            txn.insert_if_absent(entity_id, absent, TripleValue::Number(3.0))
+                .expect("insert absent")
+        );
+        // The buffered insert now makes the triple present
+        assert!(
+            !txn.insert_if_absent(entity_id, absent, TripleValue::Number(4.0))
+                .expect("insert buffered")
+        );
+        // A buffered delete makes it absent again
+        txn.delete(&entity_id, &present).expect("delete");
+        assert!(
+            txn.insert_if_absent(entity_id, present, TripleValue::Number(5.0))
+                .expect("insert deleted")
+        );
+        txn.commit().expect("commit");
+
+        let mut txn = db.begin(0).expect("begin");
+        let value = |txn: &mut WalTransaction<'_>, attribute_id| {
+            txn.get(&entity_id, &attribute_id)
+                .expect("get")
+                .map(|record| record.value)
+        };
+        assert_eq!(value(&mut txn, present), Some(TripleValue::Number(5.0)));
+        assert_eq!(value(&mut txn, absent), Some(TripleValue::Number(3.0)));
+        txn.abort();
+    }
+
+    #[test]
+    fn test_database_multiple_transactions() {
+        let (_dir, path) = create_test_db();
+        let pool = test_pool();
+        let mut db = Database::create(&path, pool).expect("create db");
+
+        // Multiple sequential transactions
+        for i in 0..10u8 {
+            let mut txn = db.begin(0).expect("begin");
+            let mut entity = [0u8; 16];
+            entity[0] = i;
+            txn.insert(
+                EntityId(entity),
+                AttributeId([1u8; 16]),
+                TripleValue::Number(f64::from(i)),
+            )
+            .expect("insert");
+            txn.commit().expect("commit");
+        }
+
+        // Verify all data
+        let mut txn = db.begin(0).expect("begin");
+        for i in 0..10u8 {
+            let mut entity = [0u8; 16];
+            entity[0] = i;
+            let record = txn
+                .get(&EntityId(entity), &AttributeId([1u8; 16]))
+                .expect("get");
+            assert!(record.is_some());
+            assert_eq!(record.unwrap().value, TripleValue::Number(f64::from(i)));
+        }
+        txn.abort();
+    }
+
+    #[test]
+    fn test_database_checkpoint() {
+        let (_dir, path) = create_test_db();
+        let pool = test_pool();
+        let mut db = Database::create(&path, pool).expect("create db");
+
+        // Insert some data
+        {
+            let mut txn = db.begin(0).expect("begin");
+            txn.insert(
+                EntityId([1u8; 16]),
+                AttributeId([1u8; 16]),
+                TripleValue::Boolean(true),
+            )
+            .expect("insert");
+            txn.commit().expect("commit");
+        }
+
+        // Force checkpoint
+        let result = db.checkpoint().expect("checkpoint");
+        assert!(result.checkpoint_lsn > 0);
+
+        // Close cleanly
+        db.close().expect("close");
+    }
+
+    #[test]
+    fn test_commit_signals_checkpoint_instead_of_blocking() {
+        use futures::FutureExt;
+
+        let (_dir, path) = create_test_db();
+        let pool = test_pool();
+        let mut db = Database::create_with_options(
+            &path,
+            pool,
+            FileFormat::default(),
+            DEFAULT_WAL_CAPACITY,
+            CheckpointConfig::new(1, 0),
+            DEFAULT_NODE_ID,
+        )
+        .expect("create db");
+        let notify = db.checkpoint_notify();
+
+        let mut txn = db.begin(0).expect("begin");
+        txn.insert(
+            EntityId([1u8; 16]),
+            AttributeId([1u8; 16]),
+            TripleValue::Boolean(true),
+        )
+        .expect("insert");
+        txn.commit().expect("commit");
+
+        // The commit crossed the threshold but left the checkpoint to the task
+        assert_eq!(db.checkpoint_state().txns_since_checkpoint(), 1);
+        assert_eq!(db.checkpoint_state().last_checkpoint_lsn(), 0);
+        assert!(notify.notified().now_or_never().is_some());
+
+        let result = db.checkpoint_if_due().expect("checkpoint");
+        assert!(result.is_some());
+        assert_eq!(db.checkpoint_state().txns_since_checkpoint(), 0);
+
+        // Nothing left to do on a second signal
+        let result = db.checkpoint_if_due().expect("checkpoint");
+        assert!(result.is_none());
+    }
+
+    #[test]
+    fn test_commit_checkpoints_inline_under_wal_pressure() {
+        let (_dir, path) = create_test_db();
+        let pool = test_pool();
+        let mut db = Database::create_with_options(
+            &path,
+            pool,
+            FileFormat::default(),
+            MIN_WAL_CAPACITY,
+            CheckpointConfig::new(0, 1),
+            DEFAULT_NODE_ID,
+        )
+        .expect("create db");
+
+        // No checkpoint task is running, so only WAL pressure can checkpoint
+        let value = "v".repeat(1000);
+        for batch in 0..20u8 {
+            let mut txn = db.begin(0).expect("begin");
+            for index in 0..50u8 {
+                txn.insert(
+                    EntityId([batch; 16]),
+                    AttributeId([index; 16]),
+                    TripleValue::String(value.clone()),
+                )
+                .expect("insert");
+            }
+            txn.commit().expect("commit");
+            assert!(
+                !db.checkpoint_state()
+                    .wal_pressure_exceeded(MIN_WAL_CAPACITY)
+            );
+        }
+
+        assert!(db.checkpoint_state().last_checkpoint_lsn() > 0);
+    }
+
+    #[test]
+    fn test_commit_checkpoints_when_wal_full() {
+        let (_dir, path) = create_test_db();
+        let pool = test_pool();
+        let value = "w".repeat(1000);
+
+        {
+            // Automatic checkpoints disabled, so only a full WAL can force one
+            let mut db = Database::create_with_options(
+                &path,
+                Arc::clone(&pool),
+                FileFormat::default(),
+                MIN_WAL_CAPACITY,
+                CheckpointConfig::disabled(),
+                DEFAULT_NODE_ID,
+            )
+            .expect("create db");
+
+            // About 1.5MB of records through a 1MB WAL
+            for batch in 0..30u8 {
+                let mut txn = db.begin(0).expect("begin");
+                for index in 0..50u8 {
+                    txn.insert(
+                        EntityId([batch; 16]),
+                        AttributeId([index; 16]),
+                        TripleValue::String(value.clone()),
+                    )
+                    .expect("insert");
+                }
+                txn.commit().expect("commit");
+            }
+            assert!(db.checkpoint_state().last_checkpoint_lsn() > 0);
+            // Dropped without close to simulate a crash
+        }
+
+        let (db, _) = Database::open(&path, pool).expect("open db");
+        let snapshot = db.begin_readonly().expect("begin readonly");
+        for batch in 0..30u8 {
+            for index in 0..50u8 {
+                let record = snapshot
+                    .get(&EntityId([batch; 16]), &AttributeId([index; 16]))
+                    .expect("get")
+                    .expect("committed record survives");
+                assert_eq!(record.value, TripleValue::String(value.clone()));
+            }
+        }
+        let txn_id = snapshot.close();
+        db.release_snapshot(txn_id).expect("release snapshot");
+    }
+
+    #[test]
+    fn test_in_memory_database() {
+        let mut db = Database::create_in_memory_with_options(
+            test_pool(),
+            FileFormat::default(),
+            MIN_WAL_CAPACITY,
+            CheckpointConfig::disabled(),
+            DEFAULT_NODE_ID,
+        )
+        .expect("create db");
+        assert!(db.is_in_memory());
+        let attribute_id = AttributeId([1u8; 16]);
+
+        // More than a WAL's worth of writes, so commits must checkpoint
+        let value = "m".repeat(1000);
+        for batch in 0..30u8 {
+            let mut txn = db.begin(0).expect("begin");
+            for index in 0..50u8 {
+                txn.insert(
+                    EntityId([batch, index, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0]),
+                    attribute_id,
+                    TripleValue::String(value.clone()),
+                )
+                .expect("insert");
+            }
+            txn.commit().expect("commit");
+        }
+        db.checkpoint().expect("checkpoint");
+        assert!(db.checkpoint_state().last_checkpoint_lsn() > 0);
+
+        let snapshot = db.begin_readonly().expect("begin readonly");
+        let count = snapshot.iter_all().expect("iter").count();
+        let record = snapshot
+            .get(
+                &EntityId([29, 49, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0]),
+                &attribute_id,
+            )
+            .expect("get")
+            .expect("record");
+        db.release_snapshot(snapshot.close())
+            .expect("release snapshot");
+        assert_eq!(count, 1500);
+        assert_eq!(record.value, TripleValue::String(value));
+    }
+
+    #[test]
+    fn test_insert_rejects_value_too_large_for_wal() {
+        let (_dir, path) = create_test_db();
+        let mut db = Database::create_with_options(
+            &path,
+            test_pool(),
+            FileFormat::default(),
+            MIN_WAL_CAPACITY,
+            CheckpointConfig::disabled(),
+            DEFAULT_NODE_ID,
+        )
+        .expect("create db");
+        let entity_id = EntityId([1u8; 16]);
+        let attribute_id = AttributeId([2u8; 16]);
+        let string_of =
+            |length: u64| TripleValue::String("x".repeat(usize::try_from(length).expect("length")));
+
+        // Rejected when buffered, reporting the largest value that fits
+        let mut txn = db.begin(0).expect("begin");
+        let huge = string_of(MIN_WAL_CAPACITY);
+        let huge_size = huge.serialized_size() as u64;
+        let Err(DatabaseError::ValueTooLarge { size, max }) =
+            txn.insert(entity_id, attribute_id, huge)
+        else {
+            panic!("expected ValueTooLarge");
+        };
+        assert_eq!(size, huge_size);
+        assert!(max < MIN_WAL_CAPACITY);
+
+        // One byte over the limit is rejected, the limit itself is buffered
+        let over = string_of(max - 2);
+        assert_eq!(over.serialized_size() as u64, max + 1);
+        assert!(matches!(
+            txn.insert_with_hlc(entity_id, attribute_id, over, HlcTimestamp::default()),
+            Err(DatabaseError::ValueTooLarge { .. })
+        ));
+        txn.insert(entity_id, attribute_id, string_of(max - 3))
+            .expect("insert");
+        txn.abort();
+
+        let mut txn = db.begin(0).expect("begin");
+        assert!(txn.get(&entity_id, &attribute_id).expect("get").is_none());
+        txn.abort();
+    }
+
+    #[test]
+    fn test_transaction_too_large_for_wal_is_rejected_when_buffered() {
+        let (_dir, path) = create_test_db();
+        let mut db = Database::create_with_options(
+            &path,
+            test_pool(),
+            FileFormat::default(),
+            MIN_WAL_CAPACITY,
+            CheckpointConfig::disabled(),
+            DEFAULT_NODE_ID,
+        )
+        .expect("create db");
+        let attribute_id = AttributeId([1u8; 16]);
+        let value = "v".repeat(60_000);
+
+        // Buffer values until the transaction no longer fits in the WAL
+        let mut txn = db.begin(0).expect("begin");
+        let savepoint = txn.savepoint();
+        let mut buffered = 0u8;
+        let error = loop {
+            let result = txn.insert(
+                EntityId([buffered; 16]),
+                attribute_id,
+                TripleValue::String(value.clone()),
+            );
+            match result {
+                Ok(()) => buffered += 1,
+                Err(e) => break e,
+            }
+        };
+        assert!(buffered > 1);
+        assert!(matches!(
+            error,
+            DatabaseError::TransactionTooLarge { size, capacity }
+                if capacity == MIN_WAL_CAPACITY && size > capacity
+        ));
+
+        // Rolling back frees the space the rolled back writes took
+        txn.rollback_to(savepoint).expect("rollback");
+        for index in 0..buffered {
+            txn.insert(
+                EntityId([index; 16]),
+                attribute_id,
+                TripleValue::String("small".to_string()),
+            )
+            .expect("insert");
+        }
+        txn.commit().expect("commit");
+
+        let txn = db.begin(0).expect("begin");
+        assert!(
+            txn.contains(&EntityId([buffered - 1; 16]), &attribute_id)
+                .expect("contains")
+        );
+        txn.abort();
+    }
+
+    #[test]
+    fn test_poisoned_snapshot_registry_returns_error() {
+        let (_dir, path) = create_test_db();
+        let db = Database::create(&path, test_pool()).expect("create db");
+        let snapshot = db.begin_readonly().expect("begin readonly");
+        let txn_id = snapshot.close();
+
+        // Panicking while holding the registry lock poisons it
+        let poisoned = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            let _active = db.active_snapshots.active.lock().expect("lock");
+            panic!("panicked while holding the registry lock");
+        }));
+        assert!(poisoned.is_err());
+
+        assert!(matches!(
+            db.begin_readonly(),
+            Err(DatabaseError::LockPoisoned)
+        ));
+        assert!(matches!(
+            db.release_snapshot(txn_id),
+            Err(DatabaseError::LockPoisoned)
+        ));
+        assert!(matches!(
+            db.begin_optimistic(0),
+            Err(DatabaseError::LockPoisoned)
+        ));
+        assert!(matches!(db.gc_stats(), Err(DatabaseError::LockPoisoned)));
+    }
+
+    #[test]
+    fn test_compact_wal_then_recover_after_crash() {
+        let (_dir, path) = create_test_db();
+        let pool = test_pool();
+
+        {
+            let mut db = Database::create_with_options(
+                &path,
+                Arc::clone(&pool),
+                FileFormat::default(),
+                4 * MIN_WAL_CAPACITY,
+                CheckpointConfig::disabled(),
+                DEFAULT_NODE_ID,
+            )
+            .expect("create db");
+            for index in 0..20u8 {
+                let mut txn = db.begin(0).expect("begin");
+                txn.insert(
+                    EntityId([index; 16]),
+                    AttributeId([1; 16]),
+                    TripleValue::Number(f64::from(index)),
+                )
+                .expect("insert");
+                txn.commit().expect("commit");
+            }
+            let free_before = db.storage_stats().expect("stats").free_pages;
+
+            let compaction = db.compact_wal(0).expect("compact");
+            assert_eq!(compaction.capacity_before, 4 * MIN_WAL_CAPACITY);
+            assert_eq!(compaction.capacity_after, MIN_WAL_CAPACITY);
+            assert!(!compaction.file_truncated, "indexes follow the WAL region");
+            assert!(
+                db.file.superblock().txn_log_checkpoint_offset < 1024,
+                "only the checkpoint record remains, at the region start"
+            );
+            assert_eq!(
+                db.storage_stats().expect("stats").free_pages,
+                free_before + compaction.pages_released
+            );
+
+            // Commits after compaction land in the relocated region
+            for index in 20..40u8 {
+                let mut txn = db.begin(0).expect("begin");
+                txn.insert(
+                    EntityId([index; 16]),
+                    AttributeId([1; 16]),
+                    TripleValue::Number(f64::from(index)),
+                )
+                .expect("insert");
+                txn.commit().expect("commit");
+            }
+            // Dropped without close to simulate a crash
+        }
+
+        let (db, recovery) = Database::open(&path, pool).expect("open db");
+        let recovery = recovery.expect("commits after compaction need recovery");
+        assert_eq!(recovery.transactions_replayed, 20);
+        assert_eq!(db.file.wal_capacity(), MIN_WAL_CAPACITY);
+
+        let snapshot = db.begin_readonly().expect("begin readonly");
+        for index in 0..40u8 {
+            let record = snapshot
+                .get(&EntityId([index; 16]), &AttributeId([1; 16]))
+                .expect("get")
+                .expect("record survives");
+            assert_eq!(record.value, TripleValue::Number(f64::from(index)));
+        }
+        let txn_id = snapshot.close();
+        db.release_snapshot(txn_id).expect("release snapshot");
+    }
+
+    #[test]
+    fn test_resize_wal_fits_value_larger_than_capacity() {
+        let (_dir, path) = create_test_db();
+        let pool = test_pool();
+        let large = vec![0xAB; 1_500_000];
+
+        {
+            let mut db = Database::create_with_options(
+                &path,
+                Arc::clone(&pool),
+                FileFormat::default(),
+                MIN_WAL_CAPACITY,
+                CheckpointConfig::disabled(),
+                DEFAULT_NODE_ID,
+            )
+            .expect("create db");
+            let mut txn = db.begin(0).expect("begin");
+            txn.insert(
+                EntityId([1; 16]),
+                AttributeId([1; 16]),
+                TripleValue::Number(1.0),
+            )
+            .expect("insert");
+            txn.commit().expect("commit");
+
+            let mut txn = db.begin(0).expect("begin");
+            let result = txn.insert(
+                EntityId([2; 16]),
+                AttributeId([1; 16]),
+                TripleValue::Bytes(large.clone()),
+            );
+            assert!(matches!(result, Err(DatabaseError::ValueTooLarge { .. })));
+            txn.abort();
+
+            // Indexes follow the WAL region, so it moves to the end of the file
+            let capacity = db.resize_wal(4 * MIN_WAL_CAPACITY).expect("resize");
+            assert_eq!(capacity, 4 * MIN_WAL_CAPACITY);
+
+            let mut txn = db.begin(0).expect("begin");
+            txn.insert(
+                EntityId([2; 16]),
+                AttributeId([1; 16]),
+                TripleValue::Bytes(large.clone()),
+            )
+            .expect("insert");
+            txn.commit().expect("commit");
+            // Dropped without close to simulate a crash
+        }
+
+        let (db, recovery) = Database::open(&path, pool).expect("open db");
+        let recovery = recovery.expect("the large commit needs recovery");
+        assert_eq!(recovery.transactions_replayed, 1);
+        assert_eq!(db.wal_capacity(), 4 * MIN_WAL_CAPACITY);
+
+        let snapshot = db.begin_readonly().expect("begin readonly");
+        let record = snapshot
+            .get(&EntityId([1; 16]), &AttributeId([1; 16]))
+            .expect("get")
+            .expect("record before resize survives");
+        assert_eq!(record.value, TripleValue::Number(1.0));
+        let record = snapshot
+            .get(&EntityId([2; 16]), &AttributeId([1; 16]))
+            .expect("get")
+            .expect("large record survives");
+        assert_eq!(record.value, TripleValue::Bytes(large));
+        let txn_id = snapshot.close();
+        db.release_snapshot(txn_id).expect("release snapshot");
+    }
+
+    #[test]
+    fn test_commit_grows_wal_for_value_larger_than_capacity() {
+        let (_dir, path) = create_test_db();
+        let pool = test_pool();
+        let large = vec![0xAB; 1_500_000];
+
+        {
+            let mut db = Database::create_with_options(
+                &path,
+                Arc::clone(&pool),
+                FileFormat::default(),
+                MIN_WAL_CAPACITY,
+                CheckpointConfig::disabled(),
+                DEFAULT_NODE_ID,
+            )
+            .expect("create db");
+            db.enable_wal_growth(4 * MIN_WAL_CAPACITY);
+
+            let mut txn = db.begin(0).expect("begin");
+            txn.insert(
+                EntityId([1; 16]),
+                AttributeId([1; 16]),
+                TripleValue::Bytes(large.clone()),
+            )
+            .expect("insert");
+            txn.commit().expect("commit");
+            // Doubled, which is enough
+            assert_eq!(db.wal_capacity(), 2 * MIN_WAL_CAPACITY);
+
+            // Past the limit, writes still fail
+            let mut txn = db.begin(0).expect("begin");
+            let result = txn.insert(
+                EntityId([2; 16]),
+                AttributeId([1; 16]),
+                TripleValue::Bytes(vec![0xAB; 5_000_000]),
+            );
+            assert!(matches!(result, Err(DatabaseError::ValueTooLarge { .. })));
+            txn.abort();
+            // Dropped without close to simulate a crash
+        }
+
+        let (db, recovery) = Database::open(&path, pool).expect("open db");
+        assert!(recovery.is_some_and(|recovery| recovery.transactions_replayed == 1));
+        assert_eq!(db.wal_capacity(), 2 * MIN_WAL_CAPACITY);
+        assert_eq!(db.max_wal_capacity(), None);
+
+        let snapshot = db.begin_readonly().expect("begin readonly");
+        let record = snapshot
+            .get(&EntityId([1; 16]), &AttributeId([1; 16]))
+            .expect("get")
+            .expect("large record survives");
+        assert_eq!(record.value, TripleValue::Bytes(large));
+        let txn_id = snapshot.close();
+        db.release_snapshot(txn_id).expect("release snapshot");
+    }
+
+    #[test]
+    fn test_open_readonly_recovers_in_memory_without_writing() {
+        let (_dir, path) = create_test_db();
+        let pool = test_pool();
+
+        {
+            let mut db = Database::create_with_options(
+                &path,
+                Arc::clone(&pool),
+                FileFormat::default(),
+                4 * MIN_WAL_CAPACITY,
+                CheckpointConfig::disabled(),
+                DEFAULT_NODE_ID,
+            )
+            .expect("create db");
+            for index in 0..5u8 {
+                let mut txn = db.begin(0).expect("begin");
+                txn.insert(
+                    EntityId([index; 16]),
+                    AttributeId([1; 16]),
+                    TripleValue::Number(f64::from(index)),
+                )
+                .expect("insert");
+                txn.commit().expect("commit");
+            }
+            let mut txn = db.begin(0).expect("begin");
+            txn.delete(&EntityId([0; 16]), &AttributeId([1; 16]))
+                .expect("delete");
+            txn.commit().expect("commit");
+            // Dropped without close to simulate a crash
+        }
+        let before = std::fs::read(&path).expect("read file");
+
+        let (mut db, recovery) =
+            Database::open_readonly(&path, Arc::clone(&pool)).expect("open readonly");
+        assert!(db.is_readonly());
+        assert!(
+            recovery
+                .expect("crash needs recovery")
+                .transactions_replayed
+                > 0
+        );
+
+        let snapshot = db.begin_readonly().expect("begin readonly");
+        assert!(
+            snapshot
+                .get(&EntityId([0; 16]), &AttributeId([1; 16]))
+                .expect("get")
+                .is_none()
+        );
+        for index in 1..5u8 {
+            let record = snapshot
+                .get(&EntityId([index; 16]), &AttributeId([1; 16]))
+                .expect("get")
+                .expect("record is visible");
+            assert_eq!(record.value, TripleValue::Number(f64::from(index)));
+        }
+        let txn_id = snapshot.close();
+        db.release_snapshot(txn_id).expect("release snapshot");
+
+        assert!(matches!(db.begin(0), Err(DatabaseError::ReadOnly)));
+        assert!(matches!(
+            db.begin_optimistic(0),
+            Err(DatabaseError::ReadOnly)
+        ));
+        assert!(matches!(db.checkpoint(), Err(DatabaseError::ReadOnly)));
+        assert!(matches!(db.compact_wal(0), Err(DatabaseError::ReadOnly)));
+        assert!(matches!(db.gc_tick(10), Err(DatabaseError::ReadOnly)));
+        assert!(db.checkpoint_if_due().expect("checkpoint if due").is_none());
+        db.close().expect("close");
+
+        // Recovery ran in memory only, so the file is byte-for-byte unchanged
+        assert_eq!(std::fs::read(&path).expect("read file"), before);
+
+        let (_db, recovery) = Database::open(&path, pool).expect("open db");
+        assert!(recovery.is_some(), "the file still needs recovery");
+    }
+
+    /// Commit a triple for each of entities `first` to 4, each in its own
+    /// transaction.
+    fn commit_numbered_triples(db: &mut Database, first: u8) {
+        for index in first..5u8 {
+            let mut txn = db.begin(0).expect("begin");
+            txn.insert(
+                EntityId([index; 16]),
+                AttributeId([1; 16]),
+                TripleValue::Number(f64::from(index)),
+            )
+            .expect("insert");
+            txn.commit().expect("commit");
+        }
+    }
+
+    /// Commit a delete of entity 0's triple.
+    fn commit_delete_of_first_triple(db: &mut Database) {
+        let mut txn = db.begin(0).expect("begin");
+        txn.delete(&EntityId([0; 16]), &AttributeId([1; 16]))
+            .expect("delete");
+        txn.commit().expect("commit");
+    }
+
+    /// Check that entities 1 to 4 have their triples, entity 0's is deleted,
+    /// and the indexes agree.
+    fn assert_numbered_triples_after_delete(db: &mut Database) {
+        let mut txn = db.begin(0).expect("begin");
+        assert!(
+            txn.get(&EntityId([0; 16]), &AttributeId([1; 16]))
+                .expect("get")
+                .is_none()
+        );
+        for index in 1..5u8 {
+            let record = txn
+                .get(&EntityId([index; 16]), &AttributeId([1; 16]))
+                .expect("get")
+                .expect("record is visible");
+            assert_eq!(record.value, TripleValue::Number(f64::from(index)));
+        }
+        txn.abort();
+        assert!(db.check_index_consistency().expect("check").is_empty());
+    }
+
+    /// Overwrite 16 bytes at `offset` within page `page_id` of the file at
+    /// `path`, as a write a crash tore leaves them.
+    fn tear_page(path: &Path, page_id: PageId, offset: u64) {
+        use std::io::{Seek, SeekFrom};
+
+        let page_size = u64::from(
+            DatabaseFile::open_to_copy(path, test_pool())
+                .expect("open file")
+                .superblock()
+                .page_size,
+        );
+        let mut file = std::fs::OpenOptions::new()
+            .write(true)
+            .open(path)
+            .expect("open file");
+        file.seek(SeekFrom::Start(page_id * page_size + offset))
+            .expect("seek");
+        file.write_all(&[0xEE; 16]).expect("write");
+        file.sync_all().expect("sync");
+    }
+
+    /// The superblock of the file at `path`.
+    fn stored_superblock(path: &Path) -> Superblock {
+        *DatabaseFile::open(path, test_pool())
+            .expect("open file")
+            .superblock()
+    }
+
+    #[test]
+    fn test_open_rebuilds_torn_pages_from_wal_history() {
+        let (dir, path) = create_test_db();
+        let pool = test_pool();
+        {
+            let mut db = Database::create(&path, Arc::clone(&pool)).expect("create db");
+            commit_numbered_triples(&mut db, 0);
+            commit_delete_of_first_triple(&mut db);
+            // Dropped without close to simulate a crash
+        }
+        let torn_page = stored_superblock(&path).attribute_index_root;
+        tear_page(&path, torn_page, 200);
+
+        assert!(matches!(
+            Database::open_readonly(&path, Arc::clone(&pool)),
+            Err(DatabaseError::TornPages(pages)) if pages == vec![torn_page]
+        ));
+        let (mut db, _) = Database::open(&path, Arc::clone(&pool)).expect("open db");
+        assert_numbered_triples_after_delete(&mut db);
+        assert!(!dir.path().join("test.db.repair").exists());
+        drop(db);
+
+        // The rebuilt file was never checkpointed, so it can be rebuilt again
+        let torn_page = stored_superblock(&path).primary_index_root;
+        tear_page(&path, torn_page, 200);
+        let (mut db, _) = Database::open(&path, pool).expect("reopen db");
+        assert_numbered_triples_after_delete(&mut db);
+    }
+
+    #[test]
+    fn test_open_rebuilds_torn_pages_after_checkpoint() {
+        let (_dir, path) = create_test_db();
+        let pool = test_pool();
+        let large_value = "x".repeat(20_000);
+        {
+            let mut db = Database::create(&path, Arc::clone(&pool)).expect("create db");
+            commit_numbered_triples(&mut db, 3);
+            // Imported from the primary index's overflow pages
+            let mut txn = db.begin(0).expect("begin");
+            txn.insert(
+                EntityId([9; 16]),
+                AttributeId([1; 16]),
+                TripleValue::String(large_value.clone()),
+            )
+            .expect("insert");
+            txn.commit().expect("commit");
+            db.checkpoint().expect("checkpoint");
+            commit_numbered_triples(&mut db, 0);
+            commit_delete_of_first_triple(&mut db);
+            // Dropped without close to simulate a crash
+        }
+        let superblock = stored_superblock(&path);
+        assert_ne!(superblock.last_checkpoint_lsn, 0);
+        tear_page(&path, superblock.entity_attribute_index_root, 200);
+
+        let (mut db, _) = Database::open(&path, pool).expect("open db");
+        assert_numbered_triples_after_delete(&mut db);
+        let mut txn = db.begin(0).expect("begin");
+        let record = txn
+            .get(&EntityId([9; 16]), &AttributeId([1; 16]))
+            .expect("get")
+            .expect("record is visible");
+        assert_eq!(record.value, TripleValue::String(large_value));
+        txn.abort();
+    }
+
+    /// The overflow pages of the file at `path`.
+    fn overflow_pages(path: &Path) -> Vec<PageId> {
+        let file = DatabaseFile::open_to_copy(path, test_pool()).expect("open file");
+        (1..file.total_pages())
+            .filter(|&page_id| {
+                let page = file.read_page_at(page_id).expect("read page");
+                PageType::try_from(page.read_u8(0)) == Ok(PageType::Overflow)
+            })
+            .collect()
+    }
+
+    /// Commit entities 10 to 10 + `count`, each with a value too large to
+    /// store inline, in one transaction.
+    fn commit_large_values(db: &mut Database, count: u8) {
+        let mut txn = db.begin(0).expect("begin");
+        for index in 10..10 + count {
+            txn.insert(
+                EntityId([index; 16]),
+                AttributeId([1; 16]),
+                TripleValue::String(large_value(index)),
+            )
+            .expect("insert");
+        }
+        txn.commit().expect("commit");
+    }
+
+    /// The value `commit_large_values` writes for entity `index`.
+    fn large_value(index: u8) -> String {
+        char::from(b'a' + index % 26).to_string().repeat(6000)
+    }
+
+    /// Check that entities 10 to 10 + `count` read back the values
+    /// `commit_large_values` wrote.
+    fn assert_large_values(db: &Database, count: u8) {
+        let snapshot = db.begin_readonly().expect("snapshot");
+        for index in 10..10 + count {
+            let record = snapshot
+                .get(&EntityId([index; 16]), &AttributeId([1; 16]))
+                .expect("get")
+                .expect("record is visible");
+            assert_eq!(record.value, TripleValue::String(large_value(index)));
+        }
+        db.release_snapshot(snapshot.close()).expect("release");
+    }
+
+    #[test]
+    fn test_open_rebuilds_torn_overflow_page() {
+        let (_dir, path) = create_test_db();
+        let pool = test_pool();
+        {
+            let mut db = Database::create(&path, Arc::clone(&pool)).expect("create db");
+            commit_large_values(&mut db, 3);
+            commit_numbered_triples(&mut db, 0);
+            commit_delete_of_first_triple(&mut db);
+            // Dropped without close to simulate a crash
+        }
+        let overflow = overflow_pages(&path);
+        assert!(
+            !overflow.is_empty(),
+            "the large values are in overflow pages"
+        );
+        let torn_page = overflow[overflow.len() / 2];
+        tear_page(&path, torn_page, 200);
+
+        assert!(matches!(
+            Database::open_readonly(&path, Arc::clone(&pool)),
+            Err(DatabaseError::TornPages(pages)) if pages == vec![torn_page]
+        ));
+        let (mut db, _) = Database::open(&path, Arc::clone(&pool)).expect("open db");
+        assert_numbered_triples_after_delete(&mut db);
+        assert_large_values(&db, 3);
+    }
+
+    #[test]
+    fn test_open_rebuilds_torn_attribute_index_leaf_after_checkpoint() {
+        let (_dir, path) = create_test_db();
+        let pool = test_pool();
+        let attribute = AttributeId([2; 16]);
+        {
+            let mut db = Database::create(&path, Arc::clone(&pool)).expect("create db");
+            // Enough entries that the attribute index root is an internal node
+            let mut txn = db.begin(0).expect("begin");
+            for index in 0..400u16 {
+                let mut entity = [0u8; 16];
+                entity[..2].copy_from_slice(&index.to_be_bytes());
+                txn.insert(EntityId(entity), attribute, TripleValue::Boolean(true))
+                    .expect("insert");
+            }
+            txn.commit().expect("commit");
+            // Imported from the primary index's overflow pages
+            commit_large_values(&mut db, 3);
+            db.checkpoint().expect("checkpoint");
+            commit_numbered_triples(&mut db, 0);
+            commit_delete_of_first_triple(&mut db);
+            // Dropped without close to simulate a crash
+        }
+        let superblock = stored_superblock(&path);
+        assert_ne!(superblock.last_checkpoint_lsn, 0);
+        let leaf = {
+            let file = DatabaseFile::open_to_copy(&path, test_pool()).expect("open file");
+            let root = file
+                .read_page_at(superblock.attribute_index_root)
+                .expect("read root");
+            InternalNode::from_page(&root)
+                .expect("root is internal")
+                .children[0]
+        };
+        tear_page(&path, leaf, 200);
+
+        assert!(matches!(
+            Database::open_readonly(&path, Arc::clone(&pool)),
+            Err(DatabaseError::TornPages(pages)) if pages == vec![leaf]
+        ));
+        let (mut db, _) = Database::open(&path, pool).expect("open db");
+        assert_numbered_triples_after_delete(&mut db);
+        assert_large_values(&db, 3);
+        let snapshot = db.begin_readonly().expect("snapshot");
+        let entities = snapshot
+            .get_entities_with_attribute(&attribute)
+            .expect("scan attribute");
+        assert_eq!(entities.len(), 400);
+        db.release_snapshot(snapshot.close()).expect("release");
+    }
+
+    #[test]
+    fn test_open_rebuilds_torn_superblock_from_its_copy() {
+        let (_dir, path) = create_test_db();
+        let pool = test_pool();
+        {
+            let mut db = Database::create(&path, Arc::clone(&pool)).expect("create db");
+            commit_numbered_triples(&mut db, 0);
+            commit_delete_of_first_triple(&mut db);
+            // Dropped without close to simulate a crash
+        }
+        // Tear the superblock's page count
+        tear_page(&path, 0, 24);
+
+        let (mut db, _) = Database::open(&path, Arc::clone(&pool)).expect("open db");
+        assert_numbered_triples_after_delete(&mut db);
+        commit_numbered_triples(&mut db, 0);
+        db.checkpoint().expect("checkpoint");
+        commit_delete_of_first_triple(&mut db);
+        drop(db);
+
+        // Once checkpointed, the WAL no longer holds the whole history
+        tear_page(&path, 0, 24);
+        assert!(matches!(
+            Database::open(&path, pool),
+            Err(DatabaseError::TornPages(pages)) if pages == vec![0]
+        ));
+    }
+
+    #[test]
+    fn test_database_recovery_committed() {
+        let (_dir, path) = create_test_db();
+        let pool = test_pool();
+
+        // Create database and insert without clean close
+        {
+            let mut db = Database::create(&path, Arc::clone(&pool)).expect("create db");
+            let mut txn = db.begin(0).expect("begin");
+            txn.insert(
+                EntityId([1u8; 16]),
+                AttributeId([1u8; 16]),
+                TripleValue::String("recovered".to_string()),
+            )
+            .expect("insert");
+            txn.commit().expect("commit");
+            // Don't call close() - simulates crash after commit
+        }
+
+        // Reopen - recovery should find the committed data
+        {
+            let (mut db, recovery) = Database::open(&path, Arc::clone(&pool)).expect("open db");
+
+            // Might have run recovery
+            if let Some(result) = recovery {
+                // The transaction was committed, so it should be replayed
+                assert!(result.transactions_replayed <= 1);
+            }
+
+            // Verify data is there
+            let mut txn = db.begin(0).expect("begin");
+            let record = txn
+                .get(&EntityId([1u8; 16]), &AttributeId([1u8; 16]))
+                .expect("get");
+            assert!(record.is_some());
+            assert_eq!(
+                record.unwrap().value,
+                TripleValue::String("recovered".to_string())
+            );
+            txn.abort();
+
+            db.close().expect("close");
+        }
+    }
+
+    #[test]
+    fn test_database_recovers_large_bytes_value() {
+        let (_dir, path) = create_test_db();
+        let pool = test_pool();
+        let attr = AttributeId([1u8; 16]);
+        let blob: Vec<u8> = (0..=u8::MAX).cycle().take(64 * 1024).collect();
+
+        {
+            let mut db = Database::create(&path, Arc::clone(&pool)).expect("create db");
+            let mut txn = db.begin(0).expect("begin");
+            txn.insert(EntityId([1u8; 16]), attr, TripleValue::Bytes(blob.clone()))
+                .expect("insert");
+            txn.commit().expect("commit");
+
+            // Crash partway through inserting a second blob
+            let mut txn = db.begin(0).expect("begin");
+            txn.insert(
+                EntityId([2u8; 16]),
+                attr,
+                TripleValue::Bytes(vec![0xAB; 64 * 1024]),
+            )
+            .expect("insert");
+            // Dropped without commit() or close()
+        }
+
+        let (mut db, _recovery) = Database::open(&path, Arc::clone(&pool)).expect("open db");
+        let mut txn = db.begin(0).expect("begin");
+        let record = txn
+            .get(&EntityId([1u8; 16]), &attr)
+            .expect("get")
+            .expect("committed blob is recovered");
+        assert_eq!(record.value, TripleValue::Bytes(blob));
+        assert!(txn.get(&EntityId([2u8; 16]), &attr).expect("get").is_none());
+        txn.abort();
+        db.close().expect("close");
+
+        // The blob is still intact once recovery has been checkpointed
+        let (mut db, recovery) = Database::open(&path, pool).expect("reopen db");
+        assert!(recovery.is_none());
+        let mut txn = db.begin(0).expect("begin");
+        let record = txn
+            .get(&EntityId([1u8; 16]), &attr)
+            .expect("get")
+            .expect("blob survives reopen");
+        assert_eq!(record.value.serialized_size(), 1 + 4 + 64 * 1024);
+        txn.abort();
+    }
+
+    #[test]
+    fn test_deferred_page_sync_recovers_from_wal() {
+        let (_dir, path) = create_test_db();
+        let pool = test_pool();
+        let (a, b) = (EntityId([1u8; 16]), EntityId([2u8; 16]));
+        let attr = AttributeId([1u8; 16]);
+
+        let (checkpointed, wal_region, wal_head, last_wal_lsn) = {
+            let mut db = Database::create(&path, Arc::clone(&pool)).expect("create db");
+            assert!(!db.checkpoint_state().sync_pages_on_commit());
+            let mut txn = db.begin(0).expect("begin");
+            txn.insert(a, attr, TripleValue::String("before".to_string()))
+                .expect("insert");
+            txn.commit().expect("commit");
+            db.checkpoint().expect("checkpoint");
+            let checkpointed = std::fs::read(&path).expect("read file");
+
+            let mut txn = db.begin(0).expect("begin");
+            txn.update(a, attr, TripleValue::String("after".to_string()))
+                .expect("update");
+            txn.insert(b, attr, TripleValue::Number(2.0))
+                .expect("insert");
+            txn.commit().expect("commit");
+
+            let sb = db.file.superblock();
+            #[allow(clippy::cast_possible_truncation)] // Test file is small
+            let wal_region =
+                sb.txn_log_start as usize..(sb.txn_log_start + sb.txn_log_capacity) as usize;
+            let current = std::fs::read(&path).expect("read file");
+            (
+                checkpointed,
+                current[wal_region].to_vec(),
+                sb.txn_log_end,
+                sb.last_wal_lsn,
+            )
+            // Dropped without close() - simulates a crash
+        };
+
+        // Crash image: only the commit point (WAL records and head) reached
+        // disk; the index pages and superblock are as of the checkpoint
+        let mut image = checkpointed;
+        let start = usize::try_from(
+            DatabaseFile::open(&path, Arc::clone(&pool))
+                .expect("open file")
+                .superblock()
+                .txn_log_start,
+        )
+        .expect("offset fits");
+        image[start..start + wal_region.len()].copy_from_slice(&wal_region);
+        std::fs::write(&path, image).expect("write crash image");
+        {
+            let mut file = DatabaseFile::open(&path, Arc::clone(&pool)).expect("open file");
+            file.superblock_mut().txn_log_end = wal_head;
+            file.superblock_mut().last_wal_lsn = last_wal_lsn;
+            file.write_superblock().expect("write superblock");
+            file.sync().expect("sync");
+        }
+
+        let (db, recovery) = Database::open(&path, Arc::clone(&pool)).expect("open db");
+        let recovery = recovery.expect("recovery should run");
+        assert_eq!(recovery.transactions_replayed, 1);
+        let snapshot = db.begin_readonly().expect("begin readonly");
+        assert_eq!(
+            snapshot.get(&a, &attr).expect("get").map(|r| r.value),
+            Some(TripleValue::String("after".to_string()))
+        );
+        assert_eq!(
+            snapshot.get(&b, &attr).expect("get").map(|r| r.value),
+            Some(TripleValue::Number(2.0))
+        );
+        db.release_snapshot(snapshot.close())
+            .expect("release snapshot");
+    }
+
+    #[test]
+    fn test_sync_pages_on_commit_config() {
+        let (_dir, path) = create_test_db();
+        let config = CheckpointConfig::default().with_page_sync_on_commit(true);
+        let mut db = Database::create_with_options(
+            &path,
+            test_pool(),
+            FileFormat::default(),
+            DEFAULT_WAL_CAPACITY,
+            config,
+            DEFAULT_NODE_ID,
+        )
+        .expect("create db");
+        assert!(db.checkpoint_state().sync_pages_on_commit());
+
+        let mut txn = db.begin(0).expect("begin");
+        txn.insert(
+            EntityId([1u8; 16]),
+            AttributeId([1u8; 16]),
+            TripleValue::Boolean(true),
+        )
+        .expect("insert");
+        txn.commit().expect("commit");
+        drop(db);
+
+        let (db, _) = Database::open(&path, test_pool()).expect("open db");
+        let snapshot = db.begin_readonly().expect("begin readonly");
+        assert!(
+            snapshot
+                .contains(&EntityId([1u8; 16]), &AttributeId([1u8; 16]))
+                .expect("contains")
+        );
+        db.release_snapshot(snapshot.close())
+            .expect("release snapshot");
+    }
+
+    #[test]
+    fn test_checkpoint_cancel_then_retry() {
+        let (_dir, path) = create_test_db();
+        let mut db = Database::create(&path, test_pool()).expect("create db");
+        let mut txn = db.begin(0).expect("begin");
+        for i in 0..50u8 {
+            txn.insert(
+                EntityId([i; 16]),
+                AttributeId([1u8; 16]),
+                TripleValue::Number(f64::from(i)),
+            )
+            .expect("insert");
+        }
+        txn.commit().expect("commit");
+        let lsn_before = db.checkpoint_state().last_checkpoint_lsn();
+
+        // Cancel from the progress callback, before any page is flushed
+        let cancel = AtomicBool::new(false);
+        let mut reports = Vec::new();
+        let mut progress = |progress: CheckpointProgress| {
+            reports.push(progress);
+            cancel.store(true, AtomicOrdering::Release);
+        };
+        let control = CheckpointControl::default()
+            .with_progress(&mut progress)
+            .with_cancel(&cancel);
+        let result = db.checkpoint_with(control);
+        assert!(matches!(
+            result,
+            Err(DatabaseError::Checkpoint(CheckpointError::Cancelled))
+        ));
+        assert_eq!(reports.len(), 1);
+        let pages_total = reports[0].pages_total;
+        assert!(pages_total > 0);
+        assert_eq!(reports[0].pages_flushed, 0);
+
+        // Nothing was applied
+        assert_eq!(db.checkpoint_state().last_checkpoint_lsn(), lsn_before);
+        assert_eq!(db.checkpoint_state().txns_since_checkpoint(), 1);
+        assert_eq!(db.file.superblock().last_checkpoint_lsn, lsn_before);
+
+        // A retry flushes the same pages and completes
+        let mut reports = Vec::new();
+        let mut progress = |progress: CheckpointProgress| reports.push(progress);
+        let result = db
+            .checkpoint_with(CheckpointControl::default().with_progress(&mut progress))
+            .expect("checkpoint");
+        assert_eq!(result.pages_flushed, pages_total);
+        assert_eq!(
+            reports,
+            vec![
+                CheckpointProgress {
+                    pages_flushed: 0,
+                    pages_total,
+                },
+                CheckpointProgress {
+                    pages_flushed: pages_total,
+                    pages_total,
+                },
+            ]
+        );
+        assert!(db.checkpoint_state().last_checkpoint_lsn() > lsn_before);
+        assert_eq!(db.checkpoint_state().txns_since_checkpoint(), 0);
+        assert_eq!(db.checkpoint_state().dirty_page_count(), 0);
+        drop(db);
+
+        let (db, _) = Database::open(&path, test_pool()).expect("open db");
+        let snapshot = db.begin_readonly().expect("begin readonly");
+        assert_eq!(snapshot.count().expect("count"), 50);
+        db.release_snapshot(snapshot.close())
+            .expect("release snapshot");
+    }
+
+    #[test]
+    fn test_database_empty_commit() {
+        let (_dir, path) = create_test_db();
+        let pool = test_pool();
+        let mut db = Database::create(&path, pool).expect("create db");
+
+        // Empty transaction should commit successfully
+        let txn = db.begin(0).expect("begin");
+        let txn_id = txn.txn_id();
+        let outcome = txn.commit().expect("commit empty");
+        assert_eq!(outcome.txn_id, txn_id);
+        assert_eq!(outcome.changes_applied, 0);
+
+        db.close().expect("close");
+    }
+
+    #[test]
+    fn test_commit_outcome_counts_real_changes() {
+        let (_dir, path) = create_test_db();
+        let mut db = Database::create(&path, test_pool()).expect("create db");
+        let mut rx = db.subscribe_to_changes(1);
+
+        let mut txn = db.begin(0).expect("begin");
+        let txn_id = txn.txn_id();
+        txn.insert(
+            EntityId([1u8; 16]),
+            AttributeId([2u8; 16]),
+            TripleValue::Number(1.0),
+        )
+        .expect("insert");
+        let outcome = txn.commit().expect("commit");
+
+        assert_eq!(outcome.txn_id, txn_id);
+        assert_eq!(outcome.changes_applied, 1);
+        assert_eq!(
+            outcome.applied,
+            vec![AppliedChange {
+                change_type: ChangeType::Insert,
+                entity_id: EntityId([1u8; 16]),
+                attribute_id: AttributeId([2u8; 16]),
+                hlc: outcome.hlc,
+            }]
+        );
+        let notification = rx.try_recv().expect("change should broadcast");
+        assert_eq!(notification.changes.len(), 1);
+        assert_eq!(notification.changes[0].hlc, outcome.hlc);
+    }
+
+    #[test]
+    fn test_commit_outcome_same_value_update_is_not_broadcast() {
+        let (_dir, path) = create_test_db();
+        let mut db = Database::create(&path, test_pool()).expect("create db");
+        let entity_id = EntityId([1u8; 16]);
+        let attribute_id = AttributeId([2u8; 16]);
+
+        let mut txn = db.begin(0).expect("begin");
+        txn.insert(entity_id, attribute_id, TripleValue::Number(1.0))
+            .expect("insert");
+        txn.commit().expect("commit insert");
+
+        let mut rx = db.subscribe_to_changes(1);
+        let mut txn = db.begin(0).expect("begin");
+        txn.update(entity_id, attribute_id, TripleValue::Number(1.0))
+            .expect("update");
+        let outcome = txn.commit().expect("commit update");
+
+        assert_eq!(outcome.changes_applied, 0);
+        assert!(outcome.applied.is_empty());
+        assert!(
+            rx.try_recv().is_err(),
+            "same-value update should not broadcast"
+        );
+
+        // The no-op update still advances the stored HLC
+        let snapshot = db.begin_readonly().expect("begin readonly");
+        let record = snapshot
+            .get(&entity_id, &attribute_id)
+            .expect("get")
+            .expect("record exists");
+        assert_eq!(record.created_hlc, outcome.hlc);
+        let txn_id = snapshot.close();
+        db.release_snapshot(txn_id).expect("release snapshot");
+    }
+
+    #[test]
+    fn test_insert_then_update_broadcasts_one_change() {
+        let (_dir, path) = create_test_db();
+        let mut db = Database::create(&path, test_pool()).expect("create db");
+        let entity_id = EntityId([1u8; 16]);
+        let attribute_id = AttributeId([2u8; 16]);
+        let other_id = EntityId([3u8; 16]);
+
+        let mut rx = db.subscribe_to_changes(1);
+        let mut txn = db.begin(0).expect("begin");
+        txn.insert_with_hlc(
+            entity_id,
+            attribute_id,
+            TripleValue::Number(1.0),
+            hlc_at(1000),
+        )
+        .expect("insert_with_hlc");
+        txn.insert(other_id, attribute_id, TripleValue::Number(0.0))
+            .expect("insert");
+        txn.update_with_hlc(
+            entity_id,
+            attribute_id,
+            TripleValue::Number(2.0),
+            hlc_at(2000),
+        )
+        .expect("update_with_hlc");
+        let outcome = txn.commit().expect("commit");
+        assert_eq!(outcome.changes_applied, 3);
+
+        let notification = rx.try_recv().expect("notification");
+        let changes: Vec<_> = notification
+            .changes
+            .iter()
+            .map(|change| {
+                (
+                    change.entity_id,
+                    change.change_type,
+                    change.value.as_ref().map(TripleValue::clone_value),
+                    change.hlc,
+                )
+            })
+            .collect();
+        assert_eq!(
+            changes,
+            vec![
+                (
+                    entity_id,
+                    ChangeType::Insert,
+                    Some(TripleValue::Number(2.0)),
+                    hlc_at(2000)
+                ),
+                (
+                    other_id,
+                    ChangeType::Insert,
+                    Some(TripleValue::Number(0.0)),
+                    outcome.hlc
+                ),
+            ]
+        );
+        assert!(rx.try_recv().is_err(), "one notification per commit");
+    }
+
+    #[test]
+    fn test_insert_then_delete_broadcasts_one_delete() {
+        let (_dir, path) = create_test_db();
+        let mut db = Database::create(&path, test_pool()).expect("create db");
+        let entity_id = EntityId([1u8; 16]);
+        let attribute_id = AttributeId([2u8; 16]);
+
+        let mut rx = db.subscribe_to_changes(1);
+        let mut txn = db.begin(0).expect("begin");
+        txn.insert_with_hlc(
+            entity_id,
+            attribute_id,
+            TripleValue::Number(1.0),
+            hlc_at(1000),
+        )
+        .expect("insert_with_hlc");
+        txn.delete_with_hlc(entity_id, attribute_id, hlc_at(2000))
+            .expect("delete");
+        txn.commit().expect("commit");
+
+        let notification = rx.try_recv().expect("notification");
+        assert_eq!(notification.changes.len(), 1);
+        let change = &notification.changes[0];
+        assert_eq!(change.change_type, ChangeType::Delete);
+        assert_eq!(change.value, None);
+        assert_eq!(change.hlc, hlc_at(2000));
+    }
+
+    #[test]
+    fn test_changes_carry_replaced_value() {
+        let (_dir, path) = create_test_db();
+        let mut db = Database::create(&path, test_pool()).expect("create db");
+        let entity_id = EntityId([1u8; 16]);
+        let attribute_id = AttributeId([2u8; 16]);
+
+        let mut rx = db.subscribe_to_changes(1);
+        let mut txn = db.begin(0).expect("begin");
+        txn.insert(entity_id, attribute_id, TripleValue::Number(1.0))
+            .expect("insert");
+        txn.commit().expect("commit");
+        let notification = rx.try_recv().expect("insert notification");
+        assert_eq!(notification.changes[0].old_value, None);
+
+        let mut txn = db.begin(0).expect("begin");
+        txn.update(entity_id, attribute_id, TripleValue::Number(2.0))
+            .expect("update");
+        txn.commit().expect("commit");
+        let notification = rx.try_recv().expect("update notification");
+        let change = &notification.changes[0];
+        assert_eq!(change.change_type, ChangeType::Update);
+        assert_eq!(change.value, Some(TripleValue::Number(2.0)));
+        assert_eq!(change.old_value, Some(TripleValue::Number(1.0)));
+
+        let mut txn = db.begin(0).expect("begin");
+        txn.delete(&entity_id, &attribute_id).expect("delete");
+        txn.commit().expect("commit");
+        let notification = rx.try_recv().expect("delete notification");
+        let change = &notification.changes[0];
+        assert_eq!(change.change_type, ChangeType::Delete);
+        assert_eq!(change.value, None);
+        assert_eq!(change.old_value, Some(TripleValue::Number(2.0)));
+    }
+
+    #[test]
+    fn test_notifications_follow_commit_order() {
+        let (_dir, path) = create_test_db();
+        let mut db = Database::create(&path, test_pool()).expect("create db");
+        let attribute_id = AttributeId([2u8; 16]);
+
+        // Writer HLCs run backwards, so only the commit HLC orders the commits
+        let mut rx = db.subscribe_to_changes(1);
+        let mut commit_hlcs = Vec::new();
+        for index in 0..10u8 {
+            let mut txn = db.begin(0).expect("begin");
+            txn.insert_with_hlc(
+                EntityId([index; 16]),
+                attribute_id,
+                TripleValue::Number(f64::from(index)),
+                hlc_at(10_000 - u64::from(index)),
+            )
+            .expect("insert_with_hlc");
+            commit_hlcs.push(txn.commit().expect("commit").hlc);
+        }
+
+        for (index, commit_hlc) in commit_hlcs.iter().enumerate() {
+            let notification = rx.try_recv().expect("notification");
+            assert_eq!(notification.commit_hlc, *commit_hlc);
+            assert_eq!(notification.changes[0].entity_id.0[0] as usize, index);
+        }
+        assert!(commit_hlcs.is_sorted_by(|earlier, later| {
+            (earlier.physical_time, earlier.logical_counter)
+                < (later.physical_time, later.logical_counter)
+        }));
+        assert!(rx.try_recv().is_err(), "one notification per commit");
+    }
+
+    /// Write `triple_count` triples, a value spanning two overflow pages and
+    /// a delete to a new database of `page_size` bytes, then reopen it and
+    /// read them back.
+    fn assert_page_size_roundtrip(pool: &Arc<BufferPool>, page_size: usize, triple_count: u64) {
+        let (_dir, path) = create_test_db();
+        let large_value = "x".repeat(overflow_data_per_page(page_size) + 10);
+
+        {
+            let mut db = Database::create_with_options(
+                &path,
+                Arc::clone(pool),
+                FileFormat {
+                    page_size,
+                    ..FileFormat::default()
+                },
+                DEFAULT_WAL_CAPACITY,
+                CheckpointConfig::default(),
+                DEFAULT_NODE_ID,
+            )
+            .expect("create db");
+
+            let mut txn = db.begin(0).expect("begin");
+            for index in 0..triple_count {
+                txn.insert(
+                    EntityId::from_u64(index * 2),
+                    AttributeId([1u8; 16]),
+                    TripleValue::Number(f64::from(u32::try_from(index).expect("small index"))),
+                )
+                .expect("insert");
+            }
+            txn.insert(
+                EntityId::from_u64(1),
+                AttributeId([2u8; 16]),
+                TripleValue::String(large_value.clone()),
+            )
+            .expect("insert");
+            txn.commit().expect("commit");
+
+            let mut txn = db.begin(0).expect("begin");
+            txn.delete(&EntityId::from_u64(0), &AttributeId([1u8; 16]))
+                .expect("delete");
+            txn.commit().expect("commit");
+
+            db.close().expect("close");
+        }
+
+        let (db, _) = Database::open(&path, Arc::clone(pool)).expect("open db");
+        assert_eq!(db.file.page_size(), page_size);
+
+        let snapshot = db.begin_readonly().expect("begin readonly");
+        for index in 1..triple_count {
+            let record = snapshot
+                .get(&EntityId::from_u64(index * 2), &AttributeId([1u8; 16]))
+                .expect("get")
+                .expect("record exists");
+            assert_eq!(
+                record.value,
+                TripleValue::Number(f64::from(u32::try_from(index).expect("small index")))
+            );
+        }
+        let record = snapshot
+            .get(&EntityId::from_u64(1), &AttributeId([2u8; 16]))
+            .expect("get")
+            .expect("record exists");
+        assert_eq!(record.value, TripleValue::String(large_value));
+        let deleted = snapshot
+            .get(&EntityId::from_u64(0), &AttributeId([1u8; 16]))
+            .expect("get");
+        assert!(deleted.is_none());
+        let txn_id = snapshot.close();
+        db.release_snapshot(txn_id).expect("release snapshot");
+        assert!(db.check_index_consistency().expect("check").is_empty());
+    }
+
+    #[test]
+    fn test_database_small_page_size_roundtrip() {
+        // Enough triples to split leaves and internal nodes of 4KB pages
+        assert_page_size_roundtrip(&test_pool(), 4096, 500);
+    }
+
+    #[test]
+    fn test_database_large_page_size_roundtrip() {
+        // Enough triples to split leaves of 64KB pages
+        let pool = BufferPool::with_page_size(100, MAX_PAGE_SIZE);
+        assert_page_size_roundtrip(&pool, MAX_PAGE_SIZE, 5000);
+    }
+
+    #[test]
+    fn test_database_page_size_must_fit_pool() {
+        let (_dir, path) = create_test_db();
+        let format = FileFormat {
+            page_size: 16384,
+            ..FileFormat::default()
+        };
+
+        let result = Database::create_with_options(
+            &path,
+            test_pool(),
+            format,
+            DEFAULT_WAL_CAPACITY,
+            CheckpointConfig::default(),
+            DEFAULT_NODE_ID,
+        );
+        assert!(matches!(
+            result,
+            Err(DatabaseError::File(FileError::PageSizeExceedsPool {
+                page_size: 16384,
+                pool_page_size: PAGE_SIZE,
+            }))
+        ));
+
+        let db = Database::create_with_options(
+            &path,
+            BufferPool::with_page_size(100, 16384),
+            format,
+            DEFAULT_WAL_CAPACITY,
+            CheckpointConfig::default(),
+            DEFAULT_NODE_ID,
+        )
+        .expect("create db");
+        db.close().expect("close");
+        let result = Database::open(&path, test_pool());
+        assert!(
+            matches!(
+                result,
+                Err(DatabaseError::File(FileError::PageSizeExceedsPool { .. }))
+            ),
+            "{:?}",
+            result.err()
+        );
+    }
+
+    #[test]
+    fn test_database_checksum_algorithm_recovery() {
+        let (_dir, path) = create_test_db();
+        let pool = test_pool();
+
+        {
+            let mut db = Database::create_with_options(
+                &path,
+                Arc::clone(&pool),
+                FileFormat {
+                    checksum_algorithm: ChecksumAlgorithm::Crc32c,
+                    ..FileFormat::default()
+                },
+                DEFAULT_WAL_CAPACITY,
+                CheckpointConfig::disabled(),
+                DEFAULT_NODE_ID,
+            )
+            .expect("create db");
+
+            let mut txn = db.begin(0).expect("begin");
+            txn.insert(
+                EntityId([1u8; 16]),
+                AttributeId([2u8; 16]),
+                TripleValue::String(String::from("crc32c")),
+            )
+            .expect("insert");
+            txn.commit().expect("commit");
+            // Dropped without checkpoint, so reopening replays the WAL
+        }
+
+        let (db, recovery) = Database::open(&path, pool).expect("open db");
+        assert!(recovery.is_some());
+        assert_eq!(db.file.checksum_algorithm(), ChecksumAlgorithm::Crc32c);
+
+        let snapshot = db.begin_readonly().expect("begin readonly");
+        let record = snapshot
+            .get(&EntityId([1u8; 16]), &AttributeId([2u8; 16]))
+            .expect("get")
+            .expect("record exists");
+        assert_eq!(record.value, TripleValue::String(String::from("crc32c")));
+        let txn_id = snapshot.close();
+        db.release_snapshot(txn_id).expect("release snapshot");
+    }
+
+    #[test]
+    fn test_database_create_unsupported_page_size() {
+        let (_dir, path) = create_test_db();
+        let pool = test_pool();
+
+        let result = Database::create_with_options(
+            &path,
+            pool,
+            FileFormat {
+                page_size: 1000,
+                ..FileFormat::default()
+            },
+            DEFAULT_WAL_CAPACITY,
+            CheckpointConfig::default(),
+            DEFAULT_NODE_ID,
+        );
+        assert!(matches!(
+            result,
+            Err(DatabaseError::File(FileError::UnsupportedPageSize(1000)))
+        ));
+        assert!(!path.exists());
+    }
+
+    #[test]
+    fn test_snapshot_basic_read() {
+        let (_dir, path) = create_test_db();
+        let pool = test_pool();
+        let mut db = Database::create(&path, pool).expect("create db");
+
+        // Insert data
+        {
+            let mut txn = db.begin(0).expect("begin");
+            txn.insert(
+                EntityId([1u8; 16]),
+                AttributeId([1u8; 16]),
+                TripleValue::Number(42.0),
+            )
+            .expect("insert");
+            txn.commit().expect("commit");
+        }
+
+        // Read via snapshot
+        let txn_id = {
+            let snapshot = db.begin_readonly().expect("begin readonly");
+            let record = snapshot
+                .get(&EntityId([1u8; 16]), &AttributeId([1u8; 16]))
+                .expect("get");
+            assert!(record.is_some());
+            assert_eq!(record.unwrap().value, TripleValue::Number(42.0));
+            snapshot.close()
+        };
+        db.release_snapshot(txn_id).expect("release snapshot");
+
+        assert_eq!(
+            db.active_snapshot_count().expect("active snapshot count"),
+            0
+        );
+    }
+
+    #[test]
+    fn test_contains_checks_presence_and_tombstones() {
+        let (_dir, path) = create_test_db();
+        let mut db = Database::create(&path, test_pool()).expect("create db");
+        let small = (EntityId([1u8; 16]), AttributeId([1u8; 16]));
+        let large = (EntityId([2u8; 16]), AttributeId([1u8; 16]));
+        let missing = (EntityId([3u8; 16]), AttributeId([1u8; 16]));
+
+        // An uncreated index contains nothing
+        let snapshot = db.begin_readonly().expect("begin readonly");
+        assert!(!snapshot.contains(&small.0, &small.1).expect("contains"));
+        let txn_id = snapshot.close();
+        db.release_snapshot(txn_id).expect("release snapshot");
+
+        let mut txn = db.begin(0).expect("begin");
+        txn.insert(small.0, small.1, TripleValue::Number(1.0))
+            .expect("insert");
+        // Stored in overflow pages
+        txn.insert(large.0, large.1, TripleValue::String("x".repeat(5000)))
+            .expect("insert");
+        txn.commit().expect("commit");
+
+        let mut txn = db.begin(0).expect("begin");
+        assert!(txn.contains(&small.0, &small.1).expect("contains"));
+        assert!(txn.contains(&large.0, &large.1).expect("contains"));
+        assert!(!txn.contains(&missing.0, &missing.1).expect("contains"));
+        txn.delete(&small.0, &small.1).expect("delete");
+        txn.commit().expect("commit delete");
+
+        let txn = db.begin(0).expect("begin");
+        assert!(!txn.contains(&small.0, &small.1).expect("contains"));
+        txn.abort();
+
+        let snapshot = db.begin_readonly().expect("begin readonly");
+        assert!(!snapshot.contains(&small.0, &small.1).expect("contains"));
+        assert!(snapshot.contains(&large.0, &large.1).expect("contains"));
+        assert!(!snapshot.contains(&missing.0, &missing.1).expect("contains"));
+        let txn_id = snapshot.close();
+        db.release_snapshot(txn_id).expect("release snapshot");
+    }
+
+    #[test]
+    fn test_pinned_snapshot_outlives_its_snapshot() {
+        let (_dir, path) = create_test_db();
+        let mut db = Database::create(&path, test_pool()).expect("create db");
+        let entity = EntityId([1u8; 16]);
+        let attr = AttributeId([1u8; 16]);
+
+        let mut txn = db.begin(0).expect("begin");
+        txn.insert(entity, attr, TripleValue::Number(1.0))
+            .expect("insert");
+        txn.commit().expect("commit");
+
+        let snapshot = db.begin_readonly().expect("begin readonly");
+        let pinned = snapshot.pin().expect("pin snapshot");
+        let txn_id = snapshot.close();
+        db.release_snapshot(txn_id).expect("release snapshot");
+        assert_eq!(
+            db.active_snapshot_count().expect("active snapshot count"),
+            1
+        );
+        assert_eq!(
+            db.min_active_snapshot().expect("min active snapshot"),
+            Some(pinned.snapshot_txn())
+        );
+
+        // A delete after pinning stays invisible to the resumed snapshot
+        let mut txn = db.begin(0).expect("begin");
+        txn.delete(&entity, &attr).expect("delete");
+        txn.commit().expect("commit delete");
+
+        let resumed = db.resume_snapshot(pinned);
+        assert!(resumed.get(&entity, &attr).expect("get").is_some());
+        let _ = resumed.close();
+
+        db.unpin_snapshot(pinned).expect("unpin snapshot");
+        assert_eq!(
+            db.active_snapshot_count().expect("active snapshot count"),
+            0
+        );
+    }
+
+    #[test]
+    fn test_optimistic_transactions_on_disjoint_triples_both_commit() {
+        let (_dir, path) = create_test_db();
+        let mut db = Database::create(&path, test_pool()).expect("create db");
+        let attr = AttributeId([1u8; 16]);
+
+        let mut first = db.begin_optimistic(1).expect("begin optimistic");
+        let mut second = db.begin_optimistic(2).expect("begin optimistic");
+        first.insert(EntityId([1u8; 16]), attr, TripleValue::Number(1.0));
+        second.insert(EntityId([2u8; 16]), attr, TripleValue::Number(2.0));
+
+        db.commit_optimistic(first).expect("commit first");
+        db.commit_optimistic(second).expect("commit second");
+        assert_eq!(
+            db.active_snapshot_count().expect("active snapshot count"),
+            0
+        );
+
+        let snapshot = db.begin_readonly().expect("begin readonly");
+        assert!(
+            snapshot
+                .contains(&EntityId([1u8; 16]), &attr)
+                .expect("contains")
+        );
+        assert!(
+            snapshot
+                .contains(&EntityId([2u8; 16]), &attr)
+                .expect("contains")
+        );
+        let txn_id = snapshot.close();
+        db.release_snapshot(txn_id).expect("release snapshot");
+    }
+
+    #[test]
+    fn test_optimistic_write_write_conflict() {
+        let (_dir, path) = create_test_db();
+        let mut db = Database::create(&path, test_pool()).expect("create db");
+        let entity = EntityId([1u8; 16]);
+        let attr = AttributeId([1u8; 16]);
+
+        let mut first = db.begin_optimistic(1).expect("begin optimistic");
+        let mut second = db.begin_optimistic(2).expect("begin optimistic");
+        first.insert(entity, attr, TripleValue::Number(1.0));
+        second.insert(entity, attr, TripleValue::Number(2.0));
+
+        db.commit_optimistic(first).expect("commit first");
+        let result = db.commit_optimistic(second);
+        assert!(matches!(
+            result,
+            Err(DatabaseError::WriteConflict { entity_id, attribute_id })
+                if entity_id == entity && attribute_id == attr
+        ));
+        assert_eq!(
+            db.active_snapshot_count().expect("active snapshot count"),
+            0
+        );
+
+        // The losing write was not applied
+        let snapshot = db.begin_readonly().expect("begin readonly");
+        let record = snapshot.get(&entity, &attr).expect("get").expect("exists");
+        assert_eq!(record.value, TripleValue::Number(1.0));
+        let txn_id = snapshot.close();
+        db.release_snapshot(txn_id).expect("release snapshot");
+    }
+
+    #[test]
+    fn test_optimistic_insert_if_absent_conflicts_with_concurrent_create() {
+        let (_dir, path) = create_test_db();
+        let mut db = Database::create(&path, test_pool()).expect("create db");
+        let entity = EntityId([1u8; 16]);
+        let attr = AttributeId([1u8; 16]);
+        let hlc = |physical_time| HlcTimestamp {
+            physical_time,
+            logical_counter: 0,
+            node_id: 1,
+        };
+
+        let mut first = db.begin_optimistic(1).expect("begin optimistic");
+        let mut second = db.begin_optimistic(2).expect("begin optimistic");
+        assert!(
+            first
+                .insert_if_absent_with_hlc(&db, entity, attr, TripleValue::Number(1.0), hlc(5000))
+                .expect("first create")
+        );
+        assert!(
+            second
+                .insert_if_absent_with_hlc(&db, entity, attr, TripleValue::Number(2.0), hlc(6000))
+                .expect("second create")
+        );
+
+        db.commit_optimistic(first).expect("commit first");
+        // The second create read the triple as absent, which no longer holds
+        assert!(matches!(
+            db.commit_optimistic(second),
+            Err(DatabaseError::WriteConflict { .. })
+        ));
+
+        let mut retry = db.begin_optimistic(2).expect("begin optimistic");
+        assert!(
+            !retry
+                .insert_if_absent_with_hlc(&db, entity, attr, TripleValue::Number(2.0), hlc(6000))
+                .expect("retried create")
+        );
+        db.abort_optimistic(retry).expect("abort");
+    }
+
+    #[test]
+    fn test_optimistic_attribute_scan_conflicts_with_phantom_insert() {
+        let (_dir, path) = create_test_db();
+        let mut db = Database::create(&path, test_pool()).expect("create db");
+        let member = AttributeId([1u8; 16]);
+        let other = AttributeId([2u8; 16]);
+
+        let mut scanning = db.begin_optimistic(1).expect("begin optimistic");
+        let mut inserting = db.begin_optimistic(2).expect("begin optimistic");
+        let mut unrelated = db.begin_optimistic(3).expect("begin optimistic");
+        assert!(
+            scanning
+                .get_entities_with_attribute(&db, &member)
+                .expect("scan")
+                .is_empty()
+        );
+        scanning.insert(EntityId([1u8; 16]), member, TripleValue::Boolean(true));
+        inserting.insert(EntityId([2u8; 16]), member, TripleValue::Boolean(true));
+        unrelated.insert(EntityId([3u8; 16]), other, TripleValue::Boolean(true));
+
+        db.commit_optimistic(unrelated).expect("commit unrelated");
+        db.commit_optimistic(inserting).expect("commit inserting");
+        // Entity 2 would have matched the scan, though it read no triple of it
+        assert!(matches!(
+            db.commit_optimistic(scanning),
+            Err(DatabaseError::WriteConflict { entity_id, attribute_id })
+                if entity_id == EntityId([2u8; 16]) && attribute_id == member
+        ));
+    }
+
+    #[test]
+    fn test_optimistic_isolation_levels_read_different_states() {
+        let (_dir, path) = create_test_db();
+        let mut db = Database::create(&path, test_pool()).expect("create db");
+        let entity = EntityId([1u8; 16]);
+        let attr = AttributeId([1u8; 16]);
+        let mut txn = db.begin(0).expect("begin");
+        txn.insert(entity, attr, TripleValue::Number(1.0))
+            .expect("insert");
+        txn.commit().expect("commit");
+
+        let mut snapshot = db.begin_optimistic(1).expect("begin optimistic");
+        let mut read_committed = db
+            .begin_optimistic(2)
+            .expect("begin optimistic")
+            .with_isolation(IsolationLevel::ReadCommitted);
+        assert_eq!(snapshot.isolation(), IsolationLevel::Snapshot);
+
+        // Commits after both began are seen only by read committed
+        let later = EntityId([2u8; 16]);
+        let mut txn = db.begin(0).expect("begin");
+        txn.insert(later, attr, TripleValue::Number(2.0))
+            .expect("insert");
+        txn.commit().expect("commit");
+        assert!(snapshot.get(&db, &later, &attr).expect("get").is_none());
+        assert_eq!(
+            read_committed
+                .get(&db, &later, &attr)
+                .expect("get")
+                .map(|record| record.value),
+            Some(TripleValue::Number(2.0))
+        );
+
+        let mut txn = db.begin(0).expect("begin");
+        txn.delete(&entity, &attr).expect("delete");
+        txn.commit().expect("commit");
+        assert!(snapshot.contains(&db, &entity, &attr).expect("contains"));
+        assert!(
+            !read_committed
+                .contains(&db, &entity, &attr)
+                .expect("contains")
+        );
+
+        db.abort_optimistic(snapshot).expect("abort");
+        db.abort_optimistic(read_committed).expect("abort");
+        assert_eq!(db.active_snapshot_count().expect("count"), 0);
+    }
+
+    #[test]
+    fn test_optimistic_write_skew_only_commits_under_read_committed() {
+        let attr = AttributeId([1u8; 16]);
+        let (a, b) = (EntityId([1u8; 16]), EntityId([2u8; 16]));
+
+        for isolation in [IsolationLevel::Snapshot, IsolationLevel::ReadCommitted] {
+            let (_dir, path) = create_test_db();
+            let mut db = Database::create(&path, test_pool()).expect("create db");
+            let mut txn = db.begin(0).expect("begin");
+            txn.insert(a, attr, TripleValue::Boolean(true))
+                .expect("insert");
+            txn.insert(b, attr, TripleValue::Boolean(true))
+                .expect("insert");
+            txn.commit().expect("commit");
+
+            // Each clears one flag after checking the other is still set
+            let mut first = db
+                .begin_optimistic(1)
+                .expect("begin optimistic")
+                .with_isolation(isolation);
+            let mut second = db
+                .begin_optimistic(2)
+                .expect("begin optimistic")
+                .with_isolation(isolation);
+            assert!(first.contains(&db, &b, &attr).expect("contains"));
+            assert!(second.contains(&db, &a, &attr).expect("contains"));
+            first.delete(a, attr);
+            second.delete(b, attr);
+
+            db.commit_optimistic(first).expect("commit first");
+            let result = db.commit_optimistic(second);
+            match isolation {
+                IsolationLevel::Snapshot => assert!(
+                    matches!(result, Err(DatabaseError::WriteConflict { entity_id, .. }) if entity_id == a)
+                ),
+                IsolationLevel::ReadCommitted => assert!(result.is_ok()),
+            }
+        }
+    }
+
+    #[test]
+    fn test_wal_transaction_isolation_levels_agree_under_exclusive_access() {
+        let (_dir, path) = create_test_db();
+        let mut db = Database::create(&path, test_pool()).expect("create db");
+        let entity = EntityId([1u8; 16]);
+        let (kept, deleted) = (AttributeId([1u8; 16]), AttributeId([2u8; 16]));
+        let mut txn = db.begin(0).expect("begin");
+        txn.insert(entity, kept, TripleValue::Number(1.0))
+            .expect("insert");
+        txn.insert(entity, deleted, TripleValue::Number(2.0))
+            .expect("insert");
+        txn.commit().expect("commit");
+        let mut txn = db.begin(0).expect("begin");
+        txn.delete(&entity, &deleted).expect("delete");
+        txn.commit().expect("commit");
+
+        for isolation in [IsolationLevel::Snapshot, IsolationLevel::ReadCommitted] {
+            let mut txn = db.begin(0).expect("begin").with_isolation(isolation);
+            assert_eq!(txn.isolation(), isolation);
+            assert!(txn.contains(&entity, &kept).expect("contains"));
+            assert!(!txn.contains(&entity, &deleted).expect("contains"));
+            assert!(txn.get(&entity, &deleted).expect("get").is_none());
+            let attributes: Vec<AttributeId> = txn
+                .scan_entity(&entity)
+                .expect("scan")
+                .iter()
+                .map(|record| record.attribute_id)
+                .collect();
+            assert_eq!(attributes, vec![kept]);
+            txn.abort();
+        }
+    }
+
     #[test]
-    fn test_database_update_and_delete() {
+    fn test_optimistic_read_conflict_detects_later_delete() {
         let (_dir, path) = create_test_db();
-        let pool = test_pool();
+        let mut db = Database::create(&path, test_pool()).expect("create db");
+        let entity = EntityId([1u8; 16]);
+        let attr = AttributeId([1u8; 16]);
+        let other = EntityId([2u8; 16]);
 
-        let mut db = Database::create(&path, pool).expect("create db");
+        let mut txn = db.begin(0).expect("begin");
+        txn.insert(entity, attr, TripleValue::Number(1.0))
+            .expect("insert");
+        txn.commit().expect("commit");
 
-        // Insert
+        // Read one triple, write another based on it
+        let mut optimistic = db.begin_optimistic(1).expect("begin optimistic");
+        let read = optimistic.get(&db, &entity, &attr).expect("get");
+        assert!(read.is_some());
+        optimistic.insert(other, attr, TripleValue::Number(2.0));
+
+        // A delete committed in between invalidates the read
+        let mut txn = db.begin(0).expect("begin");
+        txn.delete(&entity, &attr).expect("delete");
+        txn.commit().expect("commit delete");
+
+        assert!(matches!(
+            db.commit_optimistic(optimistic),
+            Err(DatabaseError::WriteConflict { .. })
+        ));
+    }
+
+    #[test]
+    fn test_abort_optimistic_unpins_snapshot() {
+        let (_dir, path) = create_test_db();
+        let db = Database::create(&path, test_pool()).expect("create db");
+
+        let mut optimistic = db.begin_optimistic(0).expect("begin optimistic");
+        optimistic.insert(
+            EntityId([1u8; 16]),
+            AttributeId([1u8; 16]),
+            TripleValue::Null,
+        );
+        assert_eq!(
+            db.active_snapshot_count().expect("active snapshot count"),
+            1
+        );
+
+        db.abort_optimistic(optimistic).expect("abort optimistic");
+        assert_eq!(
+            db.active_snapshot_count().expect("active snapshot count"),
+            0
+        );
+    }
+
+    /// Reopen `path` with index verification enabled.
+    fn reopen_verified(path: &Path) -> Database {
+        let (db, _) = Database::open_with_options(
+            path,
+            test_pool(),
+            CheckpointConfig::default(),
+            DEFAULT_NODE_ID,
+            true,
+        )
+        .expect("open db");
+        db
+    }
+
+    #[test]
+    fn test_verify_on_open_reports_consistent_indexes() {
+        let (_dir, path) = create_test_db();
         {
+            let mut db = Database::create(&path, test_pool()).expect("create db");
+            let attr = AttributeId([1u8; 16]);
             let mut txn = db.begin(0).expect("begin");
-            txn.insert(
-                EntityId([1u8; 16]),
-                AttributeId([1u8; 16]),
-                TripleValue::Number(1.0),
-            );
+            txn.insert(EntityId([1u8; 16]), attr, TripleValue::Number(1.0))
+                .expect("insert");
+            txn.insert(EntityId([2u8; 16]), attr, TripleValue::Number(2.0))
+                .expect("insert");
             txn.commit().expect("commit");
-        }
 
-        // Update
-        {
             let mut txn = db.begin(0).expect("begin");
-            txn.update(
-                EntityId([1u8; 16]),
-                AttributeId([1u8; 16]),
-                TripleValue::Number(2.0),
-            )
-            .expect("update");
+            txn.update(EntityId([1u8; 16]), attr, TripleValue::Number(3.0))
+                .expect("update");
+            txn.delete(&EntityId([2u8; 16]), &attr).expect("delete");
             txn.commit().expect("commit");
         }
 
-        // Verify update
-        {
-            let mut txn = db.begin(0).expect("begin");
-            let record = txn
-                .get(&EntityId([1u8; 16]), &AttributeId([1u8; 16]))
-                .expect("get");
-            assert_eq!(record.unwrap().value, TripleValue::Number(2.0));
-            txn.abort();
-        }
+        let db = reopen_verified(&path);
+        let report = db.verification_report().expect("verification requested");
+        assert_eq!(report.transactions_checked, 2);
+        assert_eq!(report.triples_checked, 2);
+        assert!(report.is_consistent(), "{:?}", report.divergences);
 
-        // Delete
+        let (db, _) = Database::open(&path, test_pool()).expect("open db");
+        assert!(db.verification_report().is_none());
+    }
+
+    #[test]
+    fn test_verify_on_open_detects_index_divergence() {
+        let (_dir, path) = create_test_db();
+        let entity = EntityId([1u8; 16]);
+        let attr = AttributeId([1u8; 16]);
         {
+            let mut db = Database::create(&path, test_pool()).expect("create db");
             let mut txn = db.begin(0).expect("begin");
-            txn.delete(&EntityId([1u8; 16]), &AttributeId([1u8; 16]))
-                .expect("delete");
+            txn.insert(entity, attr, TripleValue::Number(1.0))
+                .expect("insert");
             txn.commit().expect("commit");
-        }
 
-        // Verify delete
-        {
-            let mut txn = db.begin(0).expect("begin");
-            let record = txn
-                .get(&EntityId([1u8; 16]), &AttributeId([1u8; 16]))
-                .expect("get");
-            assert!(record.is_none());
-            txn.abort();
+            // Drop the attribute index entry behind the WAL's back
+            let root_page = db.file.superblock().attribute_index_root;
+            let value_root_page = db.file.superblock().attribute_value_index_root;
+            let mut index =
+                AttributeIndex::new(&mut db.file, root_page, value_root_page).expect("open index");
+            index.remove(&attr, &entity, 0).expect("remove");
+            let (root_page, value_root_page) = (index.root_page(), index.value_root_page());
+            db.file.superblock_mut().attribute_index_root = root_page;
+            db.file.superblock_mut().attribute_value_index_root = value_root_page;
+            db.file.write_superblock().expect("write superblock");
+            db.file.sync().expect("sync");
         }
+
+        let db = reopen_verified(&path);
+        let report = db.verification_report().expect("verification requested");
+        assert_eq!(
+            report.divergences,
+            vec![IndexDivergence {
+                index: VerifiedIndex::Attribute,
+                entity_id: entity,
+                attribute_id: attr,
+                kind: DivergenceKind::Missing,
+            }]
+        );
     }
 
     #[test]
-    fn test_database_not_found_errors() {
+    fn test_check_index_consistency() {
         let (_dir, path) = create_test_db();
-        let pool = test_pool();
-        let mut db = Database::create(&path, pool).expect("create db");
+        let mut db = Database::create(&path, test_pool()).expect("create db");
+        let kept = EntityId([1u8; 16]);
+        let deleted = EntityId([2u8; 16]);
+        let attr = AttributeId([1u8; 16]);
 
         let mut txn = db.begin(0).expect("begin");
-
-        // Update non-existent
-        let result = txn.update(
-            EntityId([1u8; 16]),
-            AttributeId([1u8; 16]),
-            TripleValue::Null,
+        txn.insert(kept, attr, TripleValue::Number(1.0))
+            .expect("insert");
+        txn.insert(deleted, attr, TripleValue::Number(2.0))
+            .expect("insert");
+        txn.commit().expect("commit");
+        let mut txn = db.begin(0).expect("begin");
+        txn.update(kept, attr, TripleValue::Number(3.0))
+            .expect("update");
+        txn.delete(&deleted, &attr).expect("delete");
+        txn.commit().expect("commit");
+        assert_eq!(db.check_index_consistency().expect("check"), vec![]);
+
+        // Drop the live triple's attribute index entry
+        let root_page = db.file.superblock().attribute_index_root;
+        let value_root_page = db.file.superblock().attribute_value_index_root;
+        let mut index =
+            AttributeIndex::new(&mut db.file, root_page, value_root_page).expect("open index");
+        index.remove(&attr, &kept, 0).expect("remove");
+        let (root_page, value_root_page) = (index.root_page(), index.value_root_page());
+        db.file.superblock_mut().attribute_index_root = root_page;
+        db.file.superblock_mut().attribute_value_index_root = value_root_page;
+
+        assert_eq!(
+            db.check_index_consistency().expect("check"),
+            vec![IndexMismatch {
+                index: VerifiedIndex::Attribute,
+                entity_id: kept,
+                attribute_id: attr,
+                live_in_primary: true,
+            }]
         );
-        assert!(matches!(result, Err(DatabaseError::NotFound)));
+    }
 
-        // Delete non-existent
-        let result = txn.delete(&EntityId([1u8; 16]), &AttributeId([1u8; 16]));
-        assert!(matches!(result, Err(DatabaseError::NotFound)));
+    #[test]
+    fn test_scan_from_resumes_in_key_order() {
+        let (_dir, path) = create_test_db();
+        let mut db = Database::create(&path, test_pool()).expect("create db");
 
-        txn.abort();
+        let snapshot = db.begin_readonly().expect("begin readonly");
+        let mut scan = snapshot
+            .scan_from(&EntityId([0u8; 16]), &AttributeId([0u8; 16]))
+            .expect("scan empty");
+        assert!(scan.next_record().expect("next").is_none());
+        let txn_id = snapshot.close();
+        db.release_snapshot(txn_id).expect("release snapshot");
+
+        let mut txn = db.begin(0).expect("begin");
+        for entity in [3u8, 1, 2] {
+            for attr in [2u8, 1] {
+                txn.insert(
+                    EntityId([entity; 16]),
+                    AttributeId([attr; 16]),
+                    TripleValue::Number(f64::from(entity)),
+                )
+                .expect("insert");
+            }
+        }
+        txn.commit().expect("commit");
+
+        let snapshot = db.begin_readonly().expect("begin readonly");
+        let mut scan = snapshot
+            .scan_from(&EntityId([2u8; 16]), &AttributeId([2u8; 16]))
+            .expect("scan");
+        let mut keys = Vec::new();
+        while let Some(record) = scan.next_record().expect("next") {
+            keys.push((record.entity_id.0[0], record.attribute_id.0[0]));
+        }
+        assert_eq!(keys, vec![(2, 2), (3, 1), (3, 2)]);
+        let txn_id = snapshot.close();
+        db.release_snapshot(txn_id).expect("release snapshot");
     }
 
     #[test]
-    fn test_database_multiple_transactions() {
+    fn test_iter_all_yields_visible_triples_in_key_order() {
         let (_dir, path) = create_test_db();
-        let pool = test_pool();
-        let mut db = Database::create(&path, pool).expect("create db");
+        let mut db = Database::create(&path, test_pool()).expect("create db");
 
-        // Multiple sequential transactions
-        for i in 0..10u8 {
-            let mut txn = db.begin(0).expect("begin");
-            let mut entity = [0u8; 16];
-            entity[0] = i;
+        let snapshot = db.begin_readonly().expect("begin readonly");
+        assert!(snapshot.iter_all().expect("iter empty").next().is_none());
+        db.release_snapshot(snapshot.close())
+            .expect("release snapshot");
+
+        let mut txn = db.begin(0).expect("begin");
+        for entity in [3u8, 1, 2] {
             txn.insert(
-                EntityId(entity),
+                EntityId([entity; 16]),
                 AttributeId([1u8; 16]),
-                TripleValue::Number(f64::from(i)),
-            );
-            txn.commit().expect("commit");
+                TripleValue::Number(f64::from(entity)),
+            )
+            .expect("insert");
         }
+        txn.commit().expect("commit");
 
-        // Verify all data
+        // An older snapshot keeps seeing the deleted triple, and not the new one
+        let snapshot = db.begin_readonly().expect("begin readonly");
+        let pinned = snapshot.pin().expect("pin snapshot");
+        db.release_snapshot(snapshot.close())
+            .expect("release snapshot");
         let mut txn = db.begin(0).expect("begin");
-        for i in 0..10u8 {
-            let mut entity = [0u8; 16];
-            entity[0] = i;
-            let record = txn
-                .get(&EntityId(entity), &AttributeId([1u8; 16]))
-                .expect("get");
-            assert!(record.is_some());
-            assert_eq!(record.unwrap().value, TripleValue::Number(f64::from(i)));
-        }
-        txn.abort();
+        txn.delete(&EntityId([2u8; 16]), &AttributeId([1u8; 16]))
+            .expect("delete");
+        txn.insert(
+            EntityId([4u8; 16]),
+            AttributeId([1u8; 16]),
+            TripleValue::Number(4.0),
+        )
+        .expect("insert");
+        txn.commit().expect("commit");
+        let old = db.resume_snapshot(pinned);
+        let new = db.begin_readonly().expect("begin readonly");
+
+        let entities = |snapshot: &Snapshot<'_>| -> Vec<u8> {
+            snapshot
+                .iter_all()
+                .expect("iter")
+                .map(|record| record.expect("record").entity_id.0[0])
+                .collect()
+        };
+        assert_eq!(entities(&old), vec![1, 2, 3]);
+        assert_eq!(entities(&new), vec![1, 3, 4]);
+
+        // The iterator is lazy: stopping early reads only what was consumed
+        let first = new
+            .iter_all()
+            .expect("iter")
+            .next()
+            .expect("first")
+            .expect("record");
+        assert_eq!(first.entity_id, EntityId([1u8; 16]));
+
+        let _ = old.close();
+        db.release_snapshot(new.close()).expect("release snapshot");
+        db.unpin_snapshot(pinned).expect("unpin snapshot");
     }
 
     #[test]
-    fn test_database_checkpoint() {
+    fn test_snapshot_isolation() {
         let (_dir, path) = create_test_db();
         let pool = test_pool();
         let mut db = Database::create(&path, pool).expect("create db");
 
-        // Insert some data
+        // Insert initial data (txn_id = 1)
         {
             let mut txn = db.begin(0).expect("begin");
             txn.insert(
                 EntityId([1u8; 16]),
                 AttributeId([1u8; 16]),
-                TripleValue::Boolean(true),
-            );
+                TripleValue::Number(1.0),
+            )
+            .expect("insert");
             txn.commit().expect("commit");
         }
 
-        // Force checkpoint
-        let result = db.checkpoint().expect("checkpoint");
-        assert!(result.checkpoint_lsn > 0);
+        // Create a snapshot that sees txn_id = 1
+        let snapshot = db.begin_readonly().expect("begin readonly");
+        let snapshot_txn = snapshot.snapshot_txn();
+        assert_eq!(snapshot_txn, 1);
 
-        // Close cleanly
-        db.close().expect("close");
-    }
+        // Verify snapshot sees the initial data
+        let record = snapshot
+            .get(&EntityId([1u8; 16]), &AttributeId([1u8; 16]))
+            .expect("get");
+        assert!(record.is_some());
+        assert_eq!(record.unwrap().value, TripleValue::Number(1.0));
 
-    #[test]
-    fn test_database_recovery_committed() {
-        let (_dir, path) = create_test_db();
-        let pool = test_pool();
+        // Close snapshot so we can do a write
+        let txn_id = snapshot.close();
+        db.release_snapshot(txn_id).expect("release snapshot");
 
-        // Create database and insert without clean close
+        // Update the data (txn_id = 2)
         {
-            let mut db = Database::create(&path, Arc::clone(&pool)).expect("create db");
             let mut txn = db.begin(0).expect("begin");
-            txn.insert(
+            txn.update(
                 EntityId([1u8; 16]),
                 AttributeId([1u8; 16]),
-                TripleValue::String("recovered".to_string()),
-            );
+                TripleValue::Number(2.0),
+            )
+            .expect("update");
             txn.commit().expect("commit");
-            // Don't call close() - simulates crash after commit
         }
 
-        // Reopen - recovery should find the committed data
-        {
-            let (mut db, recovery) = Database::open(&path, Arc::clone(&pool)).expect("open db");
-
-            // Might have run recovery
-            if let Some(result) = recovery {
-                // The transaction was committed, so it should be replayed
-                assert!(result.transactions_replayed <= 1);
-            }
-
-            // Verify data is there
-            let mut txn = db.begin(0).expect("begin");
-            let record = txn
+        // New snapshot sees updated data
+        let txn_id = {
+            let snapshot2 = db.begin_readonly().expect("begin readonly");
+            assert_eq!(snapshot2.snapshot_txn(), 2);
+            let record = snapshot2
                 .get(&EntityId([1u8; 16]), &AttributeId([1u8; 16]))
                 .expect("get");
             assert!(record.is_some());
-            assert_eq!(
-                record.unwrap().value,
-                TripleValue::String("recovered".to_string())
-            );
-            txn.abort();
-
-            db.close().expect("close");
-        }
-    }
-
-    #[test]
-    fn test_database_empty_commit() {
-        let (_dir, path) = create_test_db();
-        let pool = test_pool();
-        let mut db = Database::create(&path, pool).expect("create db");
-
-        // Empty transaction should commit successfully
-        let txn = db.begin(0).expect("begin");
-        txn.commit().expect("commit empty");
-
-        db.close().expect("close");
+            assert_eq!(record.unwrap().value, TripleValue::Number(2.0));
+            snapshot2.close()
+        };
+        db.release_snapshot(txn_id).expect("release snapshot");
     }
 
     #[test]
-    fn test_snapshot_basic_read() {
+    fn test_snapshot_sees_deleted_records() {
         let (_dir, path) = create_test_db();
         let pool = test_pool();
         let mut db = Database::create(&path, pool).expect("create db");
 
-        // Insert data
+        // Insert data (txn_id = 1)
         {
             let mut txn = db.begin(0).expect("begin");
             txn.insert(
                 EntityId([1u8; 16]),
                 AttributeId([1u8; 16]),
-                TripleValue::Number(42.0),
-            );
+                TripleValue::String("hello".to_string()),
+            )
+            .expect("insert");
             txn.commit().expect("commit");
         }
 
-        // Read via snapshot
+        // Delete the data (txn_id = 2)
+        {
+            let mut txn = db.begin(0).expect("begin");
+            txn.delete(&EntityId([1u8; 16]), &AttributeId([1u8; 16]))
+                .expect("delete");
+            txn.commit().expect("commit");
+        }
+
+        // Current snapshot (at txn=2) should not see the deleted record
         let txn_id = {
-            let snapshot = db.begin_readonly();
+            let snapshot = db.begin_readonly().expect("begin readonly");
+            assert_eq!(snapshot.snapshot_txn(), 2);
             let record = snapshot
                 .get(&EntityId([1u8; 16]), &AttributeId([1u8; 16]))
                 .expect("get");
-            assert!(record.is_some());
-            assert_eq!(record.unwrap().value, TripleValue::Number(42.0));
+            assert!(
+                record.is_none(),
+                "snapshot at delete txn should not see record"
+            );
             snapshot.close()
         };
-        db.release_snapshot(txn_id);
+        db.release_snapshot(txn_id).expect("release snapshot");
+    }
+
+    fn collect_entities(snapshot: &Snapshot<'_>, start_after: Option<&EntityId>) -> Vec<EntityId> {
+        let mut iterator = snapshot.iter_entities(start_after).expect("iter entities");
+        let mut entities = Vec::new();
+        while let Some(entity_id) = iterator.next_entity().expect("next entity") {
+            entities.push(entity_id);
+        }
+        entities
+    }
+
+    #[test]
+    fn test_snapshot_iter_entities_yields_each_entity_once() {
+        let (_dir, path) = create_test_db();
+        let pool = test_pool();
+        let mut db = Database::create(&path, pool).expect("create db");
+
+        {
+            let mut txn = db.begin(0).expect("begin");
+            for entity in 1..=3u8 {
+                for attribute in 1..=4u8 {
+                    txn.insert(
+                        EntityId([entity; 16]),
+                        AttributeId([attribute; 16]),
+                        TripleValue::Number(f64::from(attribute)),
+                    )
+                    .expect("insert");
+                }
+            }
+            txn.commit().expect("commit");
+        }
 
-        assert_eq!(db.active_snapshot_count(), 0);
+        let snapshot = db.begin_readonly().expect("begin readonly");
+        let entities = collect_entities(&snapshot, None);
+        assert_eq!(entities.len(), 3);
+        assert_eq!(entities[0], EntityId([1u8; 16]));
+        assert_eq!(entities[1], EntityId([2u8; 16]));
+        assert_eq!(entities[2], EntityId([3u8; 16]));
+        let txn_id = snapshot.close();
+        db.release_snapshot(txn_id).expect("release snapshot");
     }
 
     #[test]
-    fn test_snapshot_isolation() {
+    fn test_snapshot_iter_entities_start_after() {
         let (_dir, path) = create_test_db();
         let pool = test_pool();
         let mut db = Database::create(&path, pool).expect("create db");
 
-        // Insert initial data (txn_id = 1)
         {
             let mut txn = db.begin(0).expect("begin");
-            txn.insert(
-                EntityId([1u8; 16]),
-                AttributeId([1u8; 16]),
-                TripleValue::Number(1.0),
-            );
+            for entity in 1..=5u8 {
+                txn.insert(
+                    EntityId([entity; 16]),
+                    AttributeId([1u8; 16]),
+                    TripleValue::Boolean(true),
+                )
+                .expect("insert");
+                txn.insert(
+                    EntityId([entity; 16]),
+                    AttributeId([0xFF; 16]),
+                    TripleValue::Boolean(false),
+                )
+                .expect("insert");
+            }
             txn.commit().expect("commit");
         }
 
-        // Create a snapshot that sees txn_id = 1
-        let snapshot = db.begin_readonly();
-        let snapshot_txn = snapshot.snapshot_txn();
-        assert_eq!(snapshot_txn, 1);
-
-        // Verify snapshot sees the initial data
-        let record = snapshot
-            .get(&EntityId([1u8; 16]), &AttributeId([1u8; 16]))
-            .expect("get");
-        assert!(record.is_some());
-        assert_eq!(record.unwrap().value, TripleValue::Number(1.0));
+        let snapshot = db.begin_readonly().expect("begin readonly");
 
-        // Close snapshot so we can do a write
-        let txn_id = snapshot.close();
-        db.release_snapshot(txn_id);
+        // Walk in chunks of two, resuming after the last entity of each chunk
+        let mut all_entities = Vec::new();
+        let mut start_after = None;
+        loop {
+            let mut iterator = snapshot
+                .iter_entities(start_after.as_ref())
+                .expect("iter entities");
+            let mut chunk = Vec::new();
+            while chunk.len() < 2 {
+                match iterator.next_entity().expect("next entity") {
+                    Some(entity_id) => chunk.push(entity_id),
+                    None => break,
+                }
+            }
+            let Some(last) = chunk.last().copied() else {
+                break;
+            };
+            all_entities.extend(chunk);
+            start_after = Some(last);
+        }
 
-        // Update the data (txn_id = 2)
-        {
-            let mut txn = db.begin(0).expect("begin");
-            txn.update(
-                EntityId([1u8; 16]),
-                AttributeId([1u8; 16]),
-                TripleValue::Number(2.0),
-            )
-            .expect("update");
-            txn.commit().expect("commit");
+        assert_eq!(all_entities.len(), 5);
+        for (index, entity_id) in all_entities.iter().enumerate() {
+            let expected = u8::try_from(index + 1).expect("index fits in u8");
+            assert_eq!(*entity_id, EntityId([expected; 16]));
         }
 
-        // New snapshot sees updated data
-        let txn_id = {
-            let snapshot2 = db.begin_readonly();
-            assert_eq!(snapshot2.snapshot_txn(), 2);
-            let record = snapshot2
-                .get(&EntityId([1u8; 16]), &AttributeId([1u8; 16]))
-                .expect("get");
-            assert!(record.is_some());
-            assert_eq!(record.unwrap().value, TripleValue::Number(2.0));
-            snapshot2.close()
-        };
-        db.release_snapshot(txn_id);
+        let after_last = collect_entities(&snapshot, Some(&EntityId([5u8; 16])));
+        assert!(after_last.is_empty());
+        let txn_id = snapshot.close();
+        db.release_snapshot(txn_id).expect("release snapshot");
     }
 
     #[test]
-    fn test_snapshot_sees_deleted_records() {
+    fn test_snapshot_iter_entities_skips_deleted() {
         let (_dir, path) = create_test_db();
         let pool = test_pool();
         let mut db = Database::create(&path, pool).expect("create db");
 
-        // Insert data (txn_id = 1)
         {
             let mut txn = db.begin(0).expect("begin");
             txn.insert(
                 EntityId([1u8; 16]),
                 AttributeId([1u8; 16]),
-                TripleValue::String("hello".to_string()),
-            );
+                TripleValue::Number(1.0),
+            )
+            .expect("insert");
+            txn.insert(
+                EntityId([2u8; 16]),
+                AttributeId([1u8; 16]),
+                TripleValue::Number(2.0),
+            )
+            .expect("insert");
             txn.commit().expect("commit");
         }
-
-        // Delete the data (txn_id = 2)
         {
             let mut txn = db.begin(0).expect("begin");
             txn.delete(&EntityId([1u8; 16]), &AttributeId([1u8; 16]))
@@ -2041,20 +9306,25 @@ mod tests {
             txn.commit().expect("commit");
         }
 
-        // Current snapshot (at txn=2) should not see the deleted record
-        let txn_id = {
-            let snapshot = db.begin_readonly();
-            assert_eq!(snapshot.snapshot_txn(), 2);
-            let record = snapshot
-                .get(&EntityId([1u8; 16]), &AttributeId([1u8; 16]))
-                .expect("get");
-            assert!(
-                record.is_none(),
-                "snapshot at delete txn should not see record"
-            );
-            snapshot.close()
-        };
-        db.release_snapshot(txn_id);
+        let snapshot = db.begin_readonly().expect("begin readonly");
+        let entities = collect_entities(&snapshot, None);
+        assert_eq!(entities.len(), 1);
+        assert_eq!(entities[0], EntityId([2u8; 16]));
+        let txn_id = snapshot.close();
+        db.release_snapshot(txn_id).expect("release snapshot");
+    }
+
+    #[test]
+    fn test_snapshot_iter_entities_empty_database() {
+        let (_dir, path) = create_test_db();
+        let pool = test_pool();
+        let db = Database::create(&path, pool).expect("create db");
+
+        let snapshot = db.begin_readonly().expect("begin readonly");
+        let entities = collect_entities(&snapshot, None);
+        assert!(entities.is_empty());
+        let txn_id = snapshot.close();
+        db.release_snapshot(txn_id).expect("release snapshot");
     }
 
     #[test]
@@ -2071,19 +9341,20 @@ mod tests {
             for i in 0..5u8 {
                 let mut attr = [0u8; 16];
                 attr[0] = i;
-                txn.insert(entity, AttributeId(attr), TripleValue::Number(f64::from(i)));
+                txn.insert(entity, AttributeId(attr), TripleValue::Number(f64::from(i)))
+                    .expect("insert");
             }
             txn.commit().expect("commit");
         }
 
         // Scan via snapshot
         let txn_id = {
-            let snapshot = db.begin_readonly();
+            let snapshot = db.begin_readonly().expect("begin readonly");
             let records = snapshot.scan_entity(&entity).expect("scan");
             assert_eq!(records.len(), 5);
             snapshot.close()
         };
-        db.release_snapshot(txn_id);
+        db.release_snapshot(txn_id).expect("release snapshot");
     }
 
     #[test]
@@ -2102,19 +9373,20 @@ mod tests {
                     EntityId(entity),
                     AttributeId([1u8; 16]),
                     TripleValue::Number(f64::from(i)),
-                );
+                )
+                .expect("insert");
             }
             txn.commit().expect("commit");
         }
 
         // Count via snapshot
         let txn_id = {
-            let snapshot = db.begin_readonly();
+            let snapshot = db.begin_readonly().expect("begin readonly");
             let count = snapshot.count().expect("count");
             assert_eq!(count, 10);
             snapshot.close()
         };
-        db.release_snapshot(txn_id);
+        db.release_snapshot(txn_id).expect("release snapshot");
 
         // Delete some records
         {
@@ -2130,12 +9402,12 @@ mod tests {
 
         // New snapshot should see only 5 records
         let txn_id = {
-            let snapshot = db.begin_readonly();
+            let snapshot = db.begin_readonly().expect("begin readonly");
             let count = snapshot.count().expect("count");
             assert_eq!(count, 5);
             snapshot.close()
         };
-        db.release_snapshot(txn_id);
+        db.release_snapshot(txn_id).expect("release snapshot");
     }
 
     #[test]
@@ -2151,27 +9423,48 @@ mod tests {
                 EntityId([1u8; 16]),
                 AttributeId([1u8; 16]),
                 TripleValue::Boolean(true),
-            );
+            )
+            .expect("insert");
             txn.commit().expect("commit");
         }
 
-        assert_eq!(db.active_snapshot_count(), 0);
-        assert!(db.min_active_snapshot().is_none());
+        assert_eq!(
+            db.active_snapshot_count().expect("active snapshot count"),
+            0
+        );
+        assert!(
+            db.min_active_snapshot()
+                .expect("min active snapshot")
+                .is_none()
+        );
 
         // Create and use snapshot
         let txn_id = {
-            let snapshot = db.begin_readonly();
+            let snapshot = db.begin_readonly().expect("begin readonly");
             snapshot.close()
         };
 
         // Still registered until release
-        assert_eq!(db.active_snapshot_count(), 1);
-        assert_eq!(db.min_active_snapshot(), Some(1));
+        assert_eq!(
+            db.active_snapshot_count().expect("active snapshot count"),
+            1
+        );
+        assert_eq!(
+            db.min_active_snapshot().expect("min active snapshot"),
+            Some(1)
+        );
 
         // Release snapshot
-        db.release_snapshot(txn_id);
-        assert_eq!(db.active_snapshot_count(), 0);
-        assert!(db.min_active_snapshot().is_none());
+        db.release_snapshot(txn_id).expect("release snapshot");
+        assert_eq!(
+            db.active_snapshot_count().expect("active snapshot count"),
+            0
+        );
+        assert!(
+            db.min_active_snapshot()
+                .expect("min active snapshot")
+                .is_none()
+        );
     }
 
     #[test]
@@ -2190,19 +9483,20 @@ mod tests {
                     EntityId(entity),
                     AttributeId([1u8; 16]),
                     TripleValue::Number(f64::from(i)),
-                );
+                )
+                .expect("insert");
             }
             txn.commit().expect("commit");
         }
 
         // Collect all via snapshot
         let txn_id = {
-            let snapshot = db.begin_readonly();
+            let snapshot = db.begin_readonly().expect("begin readonly");
             let records = snapshot.collect_all().expect("collect_all");
             assert_eq!(records.len(), 5);
             snapshot.close()
         };
-        db.release_snapshot(txn_id);
+        db.release_snapshot(txn_id).expect("release snapshot");
     }
 
     #[test]
@@ -2221,7 +9515,8 @@ mod tests {
                     EntityId(entity),
                     AttributeId([1u8; 16]),
                     TripleValue::Number(f64::from(i)),
-                );
+                )
+                .expect("insert");
             }
             txn.commit().expect("commit");
         }
@@ -2246,12 +9541,368 @@ mod tests {
 
         // Verify only 5 records remain
         let txn_id = {
-            let snapshot = db.begin_readonly();
+            let snapshot = db.begin_readonly().expect("begin readonly");
             let count = snapshot.count().expect("count");
             assert_eq!(count, 5);
             snapshot.close()
         };
-        db.release_snapshot(txn_id);
+        db.release_snapshot(txn_id).expect("release snapshot");
+    }
+
+    #[test]
+    fn test_expired_triple_reads_as_absent() {
+        let (_dir, path) = create_test_db();
+        let mut db = Database::create(&path, test_pool()).expect("create db");
+        let entity_id = EntityId([1u8; 16]);
+        let permanent_id = EntityId([3u8; 16]);
+        let attribute_id = AttributeId([2u8; 16]);
+
+        let expires_at_ms = db.expiry_now_ms() + 200;
+        let mut txn = db.begin(0).expect("begin");
+        txn.insert_with_expiry(
+            entity_id,
+            attribute_id,
+            TripleValue::Number(1.0),
+            expires_at_ms,
+        )
+        .expect("insert_with_expiry");
+        txn.insert(permanent_id, attribute_id, TripleValue::Number(2.0))
+            .expect("insert");
+        txn.commit().expect("commit");
+
+        let before = db.begin_readonly().expect("begin readonly");
+        let record = before.get(&entity_id, &attribute_id).expect("get");
+        assert_eq!(
+            record.expect("unexpired triple").expires_at_ms,
+            Some(expires_at_ms)
+        );
+
+        std::thread::sleep(std::time::Duration::from_millis(250));
+
+        let after = db.begin_readonly().expect("begin readonly");
+        assert!(after.get(&entity_id, &attribute_id).expect("get").is_none());
+        assert!(!after.contains(&entity_id, &attribute_id).expect("contains"));
+        assert!(after.scan_entity(&entity_id).expect("scan").is_empty());
+        assert_eq!(after.count().expect("count"), 1);
+        // A snapshot keeps the view it was created with
+        assert!(
+            before
+                .get(&entity_id, &attribute_id)
+                .expect("get")
+                .is_some()
+        );
+        db.release_snapshot(before.close())
+            .expect("release snapshot");
+        db.release_snapshot(after.close())
+            .expect("release snapshot");
+
+        let mut txn = db.begin(0).expect("begin");
+        assert!(txn.get(&entity_id, &attribute_id).expect("get").is_none());
+        assert!(!txn.contains(&entity_id, &attribute_id).expect("contains"));
+        txn.abort();
+    }
+
+    #[test]
+    fn test_expire_triples_tombstones_and_gc_reclaims() {
+        let (_dir, path) = create_test_db();
+        let mut db = Database::create(&path, test_pool()).expect("create db");
+        let entity_id = EntityId([1u8; 16]);
+        let attribute_id = AttributeId([2u8; 16]);
+
+        let mut txn = db.begin(0).expect("begin");
+        txn.insert_with_expiry(entity_id, attribute_id, TripleValue::Number(1.0), 1)
+            .expect("insert_with_expiry");
+        txn.insert(EntityId([3u8; 16]), attribute_id, TripleValue::Number(2.0))
+            .expect("insert");
+        txn.commit().expect("commit");
+
+        let mut rx = db.subscribe_to_changes(1);
+        let sweep = db.expire_triples(100).expect("sweep");
+        assert_eq!(sweep.triples_expired, 1);
+        assert!(!sweep.more_expired);
+
+        let notification = rx.try_recv().expect("expiry should broadcast");
+        assert_eq!(notification.changes.len(), 1);
+        assert_eq!(notification.changes[0].change_type, ChangeType::Delete);
+        assert_eq!(notification.changes[0].entity_id, entity_id);
+
+        let result = db.gc_tick(100).expect("gc");
+        assert_eq!(result.records_removed, 1);
+        let mut txn = db.begin(0).expect("begin");
+        assert_eq!(
+            txn.count().expect("count"),
+            1,
+            "only the permanent triple remains"
+        );
+        txn.abort();
+
+        let sweep = db.expire_triples(100).expect("sweep");
+        assert_eq!(sweep.triples_expired, 0);
+    }
+
+    #[test]
+    fn test_expire_triples_after_reopen_and_in_batches() {
+        let (_dir, path) = create_test_db();
+        let attribute_id = AttributeId([2u8; 16]);
+
+        {
+            let mut db = Database::create(&path, test_pool()).expect("create db");
+            let mut txn = db.begin(0).expect("begin");
+            for i in 0..3u8 {
+                txn.insert_with_expiry(EntityId([i; 16]), attribute_id, TripleValue::Null, 1)
+                    .expect("insert_with_expiry");
+            }
+            txn.commit().expect("commit");
+            db.close().expect("close");
+        }
+
+        // The earliest expiry is not persisted, so the first sweep must scan
+        let (mut db, _) = Database::open(&path, test_pool()).expect("reopen");
+        let sweep = db.expire_triples(2).expect("sweep");
+        assert_eq!(sweep.triples_expired, 2);
+        assert!(sweep.more_expired);
+
+        let sweep = db.expire_triples(2).expect("sweep");
+        assert_eq!(sweep.triples_expired, 1);
+        assert!(!sweep.more_expired);
+    }
+
+    #[test]
+    fn test_estimated_size_of_matches_overflow_storage() {
+        let (_dir, path) = create_test_db();
+        let mut db = Database::create(&path, test_pool()).expect("create db");
+
+        // The largest string whose record still fits inline
+        let mut length = 0;
+        while !Database::estimated_size_of(&TripleValue::String("x".repeat(length + 1))).overflow {
+            length += 1;
+        }
+
+        let inline = TripleValue::String("x".repeat(length));
+        let mut txn = db.begin(0).expect("begin");
+        txn.insert(EntityId([1; 16]), AttributeId([1; 16]), inline)
+            .expect("insert");
+        txn.commit().expect("commit");
+        assert_eq!(db.storage_stats().expect("stats").overflow_pages, 0);
+
+        let spilled = TripleValue::String("x".repeat(length + 1));
+        let mut txn = db.begin(0).expect("begin");
+        txn.insert(EntityId([2; 16]), AttributeId([1; 16]), spilled)
+            .expect("insert");
+        txn.commit().expect("commit");
+        assert!(db.storage_stats().expect("stats").overflow_pages > 0);
+    }
+
+    #[test]
+    fn test_working_set_larger_than_buffer_pool() {
+        let (_dir, path) = create_test_db();
+        let pool = BufferPool::new(MIN_POOL_CAPACITY);
+        #[allow(clippy::disallowed_methods)] // Kept to inspect the pool afterwards
+        let mut db = Database::create(&path, Arc::clone(&pool)).expect("create db");
+        let attribute_id = AttributeId([1u8; 16]);
+        let value = TripleValue::String("x".repeat(500));
+        let entity = |id: u64| {
+            let mut bytes = [1u8; 16];
+            bytes[8..].copy_from_slice(&id.to_be_bytes());
+            EntityId(bytes)
+        };
+
+        for batch in 0..20u64 {
+            let mut txn = db.begin(0).expect("begin");
+            for i in 0..100 {
+                txn.insert(entity(batch * 100 + i), attribute_id, value.clone_value())
+                    .expect("insert");
+            }
+            txn.commit().expect("commit");
+        }
+        // Every triple is read back below, touching each data page
+        let wal_pages = db.file.wal_capacity() / db.file.page_size() as u64;
+        let data_pages = db.storage_stats().expect("stats").total_pages - wal_pages;
+        assert!(data_pages > 2 * MIN_POOL_CAPACITY as u64);
+
+        let snapshot = db.begin_readonly().expect("snapshot");
+        for id in 0..2000 {
+            let record = snapshot.get(&entity(id), &attribute_id).expect("get");
+            assert_eq!(record.map(|record| record.value), Some(value.clone_value()));
+        }
+        let txn_id = snapshot.close();
+        db.release_snapshot(txn_id).expect("release");
+
+        assert!(pool.cached() > 0, "idle buffers should hold recent pages");
+        assert_eq!(pool.available(), MIN_POOL_CAPACITY, "no page is leaked");
+    }
+
+    #[test]
+    fn test_commit_near_pool_exhaustion_evicts_cached_pages() {
+        let (_dir, path) = create_test_db();
+        let pool = BufferPool::new(MIN_POOL_CAPACITY);
+        #[allow(clippy::disallowed_methods)] // Kept to lease buffers directly
+        let mut db = Database::create(&path, Arc::clone(&pool)).expect("create db");
+        let attribute_id = AttributeId([1u8; 16]);
+
+        for batch in 0..10u8 {
+            let mut txn = db.begin(0).expect("begin");
+            for i in 0..100u8 {
+                let mut entity = [batch + 1; 16];
+                entity[15] = i;
+                txn.insert(
+                    EntityId(entity),
+                    attribute_id,
+                    TripleValue::String("x".repeat(500)),
+                )
+                .expect("insert");
+            }
+            txn.commit().expect("commit");
+        }
+        assert!(
+            pool.cached() > MIN_POOL_CAPACITY / 2,
+            "most idle buffers hold cached pages"
+        );
+
+        // Concurrent operations hold all but a handful of buffers, leaving
+        // only cached pages to lease from
+        let held: Vec<Page> = (0..pool.available() - 8)
+            .map(|_| pool.lease_page().expect("lease"))
+            .collect();
+        assert_eq!(pool.cached(), 8);
+        let mut txn = db.begin(0).expect("begin");
+        txn.insert(
+            EntityId([20u8; 16]),
+            attribute_id,
+            TripleValue::String("x".repeat(500)),
+        )
+        .expect("insert");
+        txn.commit().expect("commit should evict cached pages");
+        drop(held);
+        assert_eq!(pool.available(), MIN_POOL_CAPACITY);
+    }
+
+    #[test]
+    fn test_is_resource_exhausted() {
+        let exhausted = DatabaseError::Index(PrimaryIndexError::BTree(BTreeError::File(
+            FileError::BufferPoolExhausted,
+        )));
+        assert!(exhausted.is_resource_exhausted());
+        assert!(DatabaseError::File(FileError::BufferPoolExhausted).is_resource_exhausted());
+        assert!(!DatabaseError::NotFound.is_resource_exhausted());
+        assert!(!DatabaseError::File(FileError::UnsupportedPageSize(3)).is_resource_exhausted());
+    }
+
+    #[test]
+    fn test_gc_frees_overflow_pages() {
+        let (_dir, path) = create_test_db();
+        let mut db = Database::create(&path, test_pool()).expect("create db");
+        let attribute_id = AttributeId([1u8; 16]);
+
+        {
+            let mut txn = db.begin(0).expect("begin");
+            for i in 1..=5u8 {
+                txn.insert(
+                    EntityId([i; 16]),
+                    attribute_id,
+                    TripleValue::String("x".repeat(4000)),
+                )
+                .expect("insert");
+            }
+            txn.commit().expect("commit");
+        }
+        let before = db.storage_stats().expect("stats");
+        assert!(before.overflow_pages >= 5, "large values should overflow");
+
+        {
+            let mut txn = db.begin(0).expect("begin");
+            for i in 1..=5u8 {
+                txn.delete(&EntityId([i; 16]), &attribute_id)
+                    .expect("delete");
+            }
+            txn.commit().expect("commit");
+        }
+        let result = db.force_gc().expect("gc");
+        assert_eq!(result.pending_tombstones, 0);
+
+        let after = db.storage_stats().expect("stats");
+        assert_eq!(
+            after.overflow_pages, 0,
+            "GC should free every overflow page"
+        );
+        assert!(after.free_pages >= before.overflow_pages);
+    }
+
+    #[test]
+    fn test_gc_freed_pages_are_reused() {
+        let (_dir, path) = create_test_db();
+        let mut db = Database::create(&path, test_pool()).expect("create db");
+        let attribute_id = AttributeId([1u8; 16]);
+        let insert_large_values = |db: &mut Database| {
+            let mut txn = db.begin(0).expect("begin");
+            for i in 1..=5u8 {
+                txn.insert(
+                    EntityId([i; 16]),
+                    attribute_id,
+                    TripleValue::String("x".repeat(4000)),
+                )
+                .expect("insert");
+            }
+            txn.commit().expect("commit");
+        };
+
+        insert_large_values(&mut db);
+        {
+            let mut txn = db.begin(0).expect("begin");
+            for i in 1..=5u8 {
+                txn.delete(&EntityId([i; 16]), &attribute_id)
+                    .expect("delete");
+            }
+            txn.commit().expect("commit");
+        }
+        db.force_gc().expect("gc");
+        let after_gc = db.storage_stats().expect("stats");
+        assert!(after_gc.free_pages >= 5);
+
+        // The new overflow values fit in the freed pages
+        insert_large_values(&mut db);
+        let after_reinsert = db.storage_stats().expect("stats");
+        assert_eq!(after_reinsert.total_pages, after_gc.total_pages);
+        assert!(after_reinsert.overflow_pages >= 5);
+    }
+
+    #[test]
+    fn test_gc_skips_record_rewritten_after_delete() {
+        let (_dir, path) = create_test_db();
+        let mut db = Database::create(&path, test_pool()).expect("create db");
+        let entity_id = EntityId([1u8; 16]);
+        let attribute_id = AttributeId([2u8; 16]);
+
+        let mut txn = db.begin(0).expect("begin");
+        txn.insert(entity_id, attribute_id, TripleValue::Number(1.0))
+            .expect("insert");
+        txn.commit().expect("commit");
+
+        let mut txn = db.begin(0).expect("begin");
+        txn.delete(&entity_id, &attribute_id).expect("delete");
+        txn.commit().expect("commit");
+
+        // A newer write resurrects the key before GC runs
+        let mut txn = db.begin(0).expect("begin");
+        txn.insert(entity_id, attribute_id, TripleValue::Number(2.0))
+            .expect("insert");
+        txn.commit().expect("commit");
+
+        let result = db.gc_tick(10).expect("gc");
+        assert_eq!(result.tombstones_processed, 1);
+        assert_eq!(result.records_removed, 0);
+
+        let mut txn = db.begin(0).expect("begin");
+        let record = txn.get(&entity_id, &attribute_id).expect("get");
+        let attributes = txn
+            .get_attributes_for_entity(&entity_id)
+            .expect("attributes");
+        txn.abort();
+        assert_eq!(
+            record.map(|record| record.value),
+            Some(TripleValue::Number(2.0))
+        );
+        assert_eq!(attributes, vec![attribute_id]);
     }
 
     #[test]
@@ -2267,13 +9918,14 @@ mod tests {
                 EntityId([1u8; 16]),
                 AttributeId([1u8; 16]),
                 TripleValue::Number(1.0),
-            );
+            )
+            .expect("insert");
             txn.commit().expect("commit");
         }
 
         // Create a snapshot at txn_id = 1
         let snapshot_txn = {
-            let snapshot = db.begin_readonly();
+            let snapshot = db.begin_readonly().expect("begin readonly");
             snapshot.close()
         };
         // Don't release - keep it active
@@ -2293,7 +9945,7 @@ mod tests {
         assert_eq!(result.min_active_snapshot, Some(1));
 
         // Release the snapshot
-        db.release_snapshot(snapshot_txn);
+        db.release_snapshot(snapshot_txn).expect("release snapshot");
 
         // Run GC again - now it should be removed
         let result = db.force_gc().expect("gc");
@@ -2317,7 +9969,8 @@ mod tests {
                     EntityId(entity),
                     AttributeId([1u8; 16]),
                     TripleValue::Number(f64::from(i)),
-                );
+                )
+                .expect("insert");
             }
             txn.commit().expect("commit");
         }
@@ -2353,18 +10006,20 @@ mod tests {
                 EntityId([1u8; 16]),
                 AttributeId([1u8; 16]),
                 TripleValue::Number(1.0),
-            );
+            )
+            .expect("insert");
             txn.insert(
                 EntityId([2u8; 16]),
                 AttributeId([1u8; 16]),
                 TripleValue::Number(2.0),
-            );
+            )
+            .expect("insert");
             txn.commit().expect("commit");
         }
 
         // Snapshot 1 at txn_id = 1
         let snapshot1_txn = {
-            let snapshot = db.begin_readonly();
+            let snapshot = db.begin_readonly().expect("begin readonly");
             snapshot.close()
         };
 
@@ -2378,7 +10033,7 @@ mod tests {
 
         // Snapshot 2 at txn_id = 2
         let snapshot2_txn = {
-            let snapshot = db.begin_readonly();
+            let snapshot = db.begin_readonly().expect("begin readonly");
             snapshot.close()
         };
 
@@ -2397,7 +10052,8 @@ mod tests {
         assert_eq!(result.min_active_snapshot, Some(1));
 
         // Release snapshot 1
-        db.release_snapshot(snapshot1_txn);
+        db.release_snapshot(snapshot1_txn)
+            .expect("release snapshot");
 
         // GC: min_active = 2, can remove first record (deleted at txn=2, which is not < 2)
         // Wait - deleted_txn=2 is NOT < min_active=2, so it shouldn't be GC'd
@@ -2406,7 +10062,8 @@ mod tests {
         assert_eq!(result.min_active_snapshot, Some(2));
 
         // Release snapshot 2
-        db.release_snapshot(snapshot2_txn);
+        db.release_snapshot(snapshot2_txn)
+            .expect("release snapshot");
 
         // GC: no active snapshots, both records should be removed
         let result = db.force_gc().expect("gc");
@@ -2464,12 +10121,16 @@ mod tests {
         {
             let mut txn = db.begin(0).expect("begin");
             // Entity 1 has attr1 and attr2
-            txn.insert(EntityId([1u8; 16]), attr1, TripleValue::Number(1.0));
-            txn.insert(EntityId([1u8; 16]), attr2, TripleValue::Number(2.0));
+            txn.insert(EntityId([1u8; 16]), attr1, TripleValue::Number(1.0))
+                .expect("insert");
+            txn.insert(EntityId([1u8; 16]), attr2, TripleValue::Number(2.0))
+                .expect("insert");
             // Entity 2 has only attr1
-            txn.insert(EntityId([2u8; 16]), attr1, TripleValue::Number(3.0));
+            txn.insert(EntityId([2u8; 16]), attr1, TripleValue::Number(3.0))
+                .expect("insert");
             // Entity 3 has only attr2
-            txn.insert(EntityId([3u8; 16]), attr2, TripleValue::Number(4.0));
+            txn.insert(EntityId([3u8; 16]), attr2, TripleValue::Number(4.0))
+                .expect("insert");
             txn.commit().expect("commit");
         }
 
@@ -2485,14 +10146,14 @@ mod tests {
 
         // Query entities with attr2 via snapshot
         let txn_id = {
-            let snapshot = db.begin_readonly();
+            let snapshot = db.begin_readonly().expect("begin readonly");
             let entities = snapshot.get_entities_with_attribute(&attr2).expect("query");
             assert_eq!(entities.len(), 2);
             assert!(entities.contains(&EntityId([1u8; 16])));
             assert!(entities.contains(&EntityId([3u8; 16])));
             snapshot.close()
         };
-        db.release_snapshot(txn_id);
+        db.release_snapshot(txn_id).expect("release snapshot");
     }
 
     #[test]
@@ -2511,11 +10172,15 @@ mod tests {
         {
             let mut txn = db.begin(0).expect("begin");
             // Entity 1 has 3 attributes
-            txn.insert(entity1, attr1, TripleValue::Number(1.0));
-            txn.insert(entity1, attr2, TripleValue::Number(2.0));
-            txn.insert(entity1, attr3, TripleValue::Number(3.0));
+            txn.insert(entity1, attr1, TripleValue::Number(1.0))
+                .expect("insert");
+            txn.insert(entity1, attr2, TripleValue::Number(2.0))
+                .expect("insert");
+            txn.insert(entity1, attr3, TripleValue::Number(3.0))
+                .expect("insert");
             // Entity 2 has 1 attribute
-            txn.insert(entity2, attr1, TripleValue::Number(4.0));
+            txn.insert(entity2, attr1, TripleValue::Number(4.0))
+                .expect("insert");
             txn.commit().expect("commit");
         }
 
@@ -2532,13 +10197,13 @@ mod tests {
 
         // Query attributes for entity2 via snapshot
         let txn_id = {
-            let snapshot = db.begin_readonly();
+            let snapshot = db.begin_readonly().expect("begin readonly");
             let attributes = snapshot.get_attributes_for_entity(&entity2).expect("query");
             assert_eq!(attributes.len(), 1);
             assert!(attributes.contains(&attr1));
             snapshot.close()
         };
-        db.release_snapshot(txn_id);
+        db.release_snapshot(txn_id).expect("release snapshot");
     }
 
     #[test]
@@ -2554,20 +10219,22 @@ mod tests {
         // Insert first attribute (txn_id = 1)
         {
             let mut txn = db.begin(0).expect("begin");
-            txn.insert(entity, attr1, TripleValue::Number(1.0));
+            txn.insert(entity, attr1, TripleValue::Number(1.0))
+                .expect("insert");
             txn.commit().expect("commit");
         }
 
         // Create snapshot at txn_id = 1
         let snapshot1_txn = {
-            let snapshot = db.begin_readonly();
+            let snapshot = db.begin_readonly().expect("begin readonly");
             snapshot.close()
         };
 
         // Insert second attribute (txn_id = 2)
         {
             let mut txn = db.begin(0).expect("begin");
-            txn.insert(entity, attr2, TripleValue::Number(2.0));
+            txn.insert(entity, attr2, TripleValue::Number(2.0))
+                .expect("insert");
             txn.commit().expect("commit");
         }
 
@@ -2580,16 +10247,71 @@ mod tests {
 
         // Current snapshot should see only attr2
         let current_txn = {
-            let snapshot = db.begin_readonly();
+            let snapshot = db.begin_readonly().expect("begin readonly");
             let attributes = snapshot.get_attributes_for_entity(&entity).expect("query");
             assert_eq!(attributes.len(), 1);
             assert!(attributes.contains(&attr2));
             snapshot.close()
         };
-        db.release_snapshot(current_txn);
+        db.release_snapshot(current_txn).expect("release snapshot");
 
         // Release snapshot1
-        db.release_snapshot(snapshot1_txn);
+        db.release_snapshot(snapshot1_txn)
+            .expect("release snapshot");
+    }
+
+    #[test]
+    fn test_scan_entity_attributes_prefix() {
+        let (_dir, path) = create_test_db();
+        let mut db = Database::create(&path, test_pool()).expect("create db");
+        let entity = EntityId::from_string("user");
+        let profile_name = AttributeId::from_string("profile.name");
+        let profile_age = AttributeId::from_string("profile.age");
+        let profile_gone = AttributeId::from_string("profile.gone");
+        let settings = AttributeId::from_string("settings.theme");
+
+        let mut txn = db.begin(0).expect("begin");
+        for attribute in [profile_name, profile_age, profile_gone, settings] {
+            txn.insert(entity, attribute, TripleValue::Boolean(true))
+                .expect("insert");
+        }
+        // A neighbouring entity sharing the prefix must not leak into the scan
+        txn.insert(
+            EntityId::from_string("user2"),
+            profile_name,
+            TripleValue::Boolean(true),
+        )
+        .expect("insert");
+        txn.commit().expect("commit");
+        let mut txn = db.begin(0).expect("begin");
+        txn.delete(&entity, &profile_gone).expect("delete");
+        txn.commit().expect("commit");
+
+        let snapshot = db.begin_readonly().expect("begin readonly");
+        let scan = |prefix: &[u8]| {
+            snapshot
+                .scan_entity_attributes_prefix(&entity, prefix)
+                .expect("scan")
+        };
+        assert_eq!(scan(b"profile."), vec![profile_age, profile_name]);
+        assert_eq!(scan(b"settings"), vec![settings]);
+        assert_eq!(scan(b"missing").len(), 0);
+        assert_eq!(scan(b"profile.zzz").len(), 0);
+        assert_eq!(scan(b""), vec![profile_age, profile_name, settings]);
+        assert_eq!(
+            scan(b"profile.name\0\0\0\0"),
+            vec![profile_name],
+            "a full-length prefix is an exact match"
+        );
+        assert!(scan(&[0u8; 17]).is_empty());
+        assert_eq!(
+            snapshot
+                .scan_entity_attributes_prefix(&EntityId::from_string("nobody"), b"profile.")
+                .expect("scan"),
+            vec![]
+        );
+        db.release_snapshot(snapshot.close())
+            .expect("release snapshot");
     }
 
     #[test]
@@ -2604,7 +10326,8 @@ mod tests {
         // Insert
         {
             let mut txn = db.begin(0).expect("begin");
-            txn.insert(entity, attr, TripleValue::Number(1.0));
+            txn.insert(entity, attr, TripleValue::Number(1.0))
+                .expect("insert");
             txn.commit().expect("commit");
         }
 
@@ -2649,7 +10372,8 @@ mod tests {
                     EntityId(entity),
                     AttributeId([1u8; 16]),
                     TripleValue::Number(f64::from(i)),
-                );
+                )
+                .expect("insert");
             }
             txn.commit().expect("commit");
         }
@@ -2668,7 +10392,7 @@ mod tests {
                     };
 
                     // Create snapshot and read data
-                    let snapshot = db_guard.begin_readonly();
+                    let snapshot = db_guard.begin_readonly().expect("begin readonly");
 
                     // Verify can read all data
                     for i in 0..100u8 {
@@ -2685,7 +10409,7 @@ mod tests {
                     assert_eq!(count, 100);
 
                     let txn_id = snapshot.close();
-                    db_guard.release_snapshot(txn_id);
+                    db_guard.release_snapshot(txn_id).expect("release snapshot");
 
                     thread_id
                 })
@@ -2702,6 +10426,11 @@ mod tests {
         let Ok(db_guard) = db.read() else {
             panic!("Failed to acquire read lock");
         };
-        assert_eq!(db_guard.active_snapshot_count(), 0);
+        assert_eq!(
+            db_guard
+                .active_snapshot_count()
+                .expect("active snapshot count"),
+            0
+        );
     }
 }
@@ -0,0 +1,200 @@
+//! Per-entity version numbers for optimistic concurrency.
+//!
+//! Clients that edit several attributes of an entity together need to know
+//! whether anyone changed the entity since they read it. When enabled with
+//! `Database::enable_entity_versions`, every commit that changes an entity's
+//! triples bumps the entity's version by one. A client reads the version with
+//! the rest of the entity, then writes only if it is unchanged.
+//!
+//! # Design
+//!
+//! Each entity's version is stored as an ordinary triple under a reserved
+//! attribute:
+//!
+//! ```text
+//! (<entity>, ENTITY_VERSION_ATTRIBUTE_ID, Number(<version>))
+//! ```
+//!
+//! Storing the version as a triple means it is written through the WAL in the
+//! same transaction as the change it counts, recovered after a crash, matched
+//! by queries, and broadcast to subscribers like any other change. An entity
+//! no commit has changed since versions were enabled has no version triple,
+//! and is at version 0.
+//!
+//! Because every change to an entity writes its version triple, reading the
+//! version inside an `OptimisticTransaction` makes the transaction conflict
+//! with any concurrent change to the entity, not just to the triples it
+//! writes.
+//!
+//! # Invariants
+//!
+//! - An entity's version only increases, by one per commit that changes it
+//! - The reserved attribute names entity has no version
+
+use crate::storage::{Database, DatabaseError, OptimisticTransaction, Snapshot};
+use crate::types::{AttributeId, EntityId, TripleRecord, TripleValue};
+
+/// Reserved attribute holding each entity's version.
+///
+/// The leading NUL byte keeps it disjoint from IDs built with
+/// `AttributeId::from_string`, which start with the string's bytes.
+pub const ENTITY_VERSION_ATTRIBUTE_ID: AttributeId = AttributeId(*b"\0enso:entity-ver");
+
+/// The version stored in an entity's version triple, or 0 if it has none.
+///
+/// A tombstoned record still holds the last version, so a deleted version
+/// triple never sends the count back to 0 before garbage collection.
+#[must_use]
+pub fn version_of(record: Option<&TripleRecord>) -> u64 {
+    match record.map(|record| &record.value) {
+        #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+        // Versions are whole numbers far below 2^53, stored exactly
+        Some(TripleValue::Number(version)) => *version as u64,
+        _ => 0,
+    }
+}
+
+/// The value stored for an entity at `version`.
+#[must_use]
+#[allow(clippy::cast_precision_loss)] // Exact up to 2^53 commits per entity
+pub const fn version_value(version: u64) -> TripleValue {
+    TripleValue::Number(version as f64)
+}
+
+impl Snapshot<'_> {
+    /// Get an entity's version as of the snapshot.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the primary index cannot be read.
+    pub fn entity_version(&self, entity_id: &EntityId) -> Result<u64, DatabaseError> {
+        Ok(version_of(
+            self.get(entity_id, &ENTITY_VERSION_ATTRIBUTE_ID)?.as_ref(),
+        ))
+    }
+}
+
+impl OptimisticTransaction {
+    /// Get an entity's version, as `get` reads triples.
+    ///
+    /// At `IsolationLevel::Snapshot` the read is validated at commit, so the
+    /// transaction fails with `DatabaseError::WriteConflict` if any other
+    /// commit changed the entity after it began.
+    ///
+    /// # Pre-conditions
+    /// - `db` is the database that began this transaction
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the primary index cannot be read.
+    pub fn entity_version(
+        &mut self,
+        db: &Database,
+        entity_id: &EntityId,
+    ) -> Result<u64, DatabaseError> {
+        Ok(version_of(
+            self.get(db, entity_id, &ENTITY_VERSION_ATTRIBUTE_ID)?
+                .as_ref(),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::buffer_pool::BufferPool;
+
+    const ENTITY: EntityId = EntityId([1; 16]);
+    const NAME: AttributeId = AttributeId([2; 16]);
+    const EMAIL: AttributeId = AttributeId([3; 16]);
+
+    fn versioned_database() -> Database {
+        let mut db = Database::create_in_memory(BufferPool::new(100)).expect("create");
+        db.enable_entity_versions();
+        db
+    }
+
+    fn write(db: &mut Database, attribute_id: AttributeId, value: &str) {
+        let mut txn = db.begin(1).expect("begin");
+        txn.insert(ENTITY, attribute_id, TripleValue::String(value.to_string()))
+            .expect("insert");
+        txn.commit().expect("commit");
+    }
+
+    fn version(db: &Database) -> u64 {
+        let snapshot = db.begin_readonly().expect("snapshot");
+        let version = snapshot.entity_version(&ENTITY).expect("version");
+        db.release_snapshot(snapshot.close()).expect("release");
+        version
+    }
+
+    #[test]
+    fn test_each_changing_commit_bumps_the_version_once() {
+        let mut db = versioned_database();
+        assert_eq!(version(&db), 0);
+
+        let mut txn = db.begin(1).expect("begin");
+        txn.insert(ENTITY, NAME, TripleValue::String("Ada".to_string()))
+            .expect("insert");
+        txn.insert(
+            ENTITY,
+            EMAIL,
+            TripleValue::String("ada@example.com".to_string()),
+        )
+        .expect("insert");
+        let outcome = txn.commit().expect("commit");
+        // The version triple is bookkeeping, not one of the commit's changes
+        assert_eq!(outcome.changes_applied, 2);
+        assert_eq!(version(&db), 1);
+
+        write(&mut db, NAME, "Grace");
+        assert_eq!(version(&db), 2);
+
+        let mut txn = db.begin(1).expect("begin");
+        txn.delete(&ENTITY, &EMAIL).expect("delete");
+        txn.commit().expect("commit");
+        assert_eq!(version(&db), 3);
+    }
+
+    #[test]
+    fn test_unchanged_write_keeps_the_version() {
+        let mut db = versioned_database();
+        write(&mut db, NAME, "Ada");
+
+        write(&mut db, NAME, "Ada");
+
+        assert_eq!(version(&db), 1);
+    }
+
+    #[test]
+    fn test_versions_off_by_default() {
+        let mut db = Database::create_in_memory(BufferPool::new(100)).expect("create");
+
+        write(&mut db, NAME, "Ada");
+
+        assert_eq!(version(&db), 0);
+    }
+
+    #[test]
+    fn test_version_read_conflicts_with_any_change_to_the_entity() {
+        let mut db = versioned_database();
+        write(&mut db, NAME, "Ada");
+        let mut txn = db.begin_optimistic(1).expect("begin");
+        assert_eq!(txn.entity_version(&db, &ENTITY).expect("version"), 1);
+        txn.insert(ENTITY, NAME, TripleValue::String("Grace".to_string()));
+
+        // A concurrent change to another attribute of the entity
+        write(&mut db, EMAIL, "ada@example.com");
+
+        let result = db.commit_optimistic(txn);
+        assert!(
+            matches!(
+                result,
+                Err(DatabaseError::WriteConflict { attribute_id, .. })
+                    if attribute_id == ENTITY_VERSION_ATTRIBUTE_ID
+            ),
+            "{result:?}"
+        );
+        assert_eq!(version(&db), 2);
+    }
+}
@@ -1,26 +1,178 @@
 //! Database file I/O operations.
 //!
-//! This module handles reading and writing pages to the database file.
+//! This module handles reading and writing pages to the database file. A
+//! `DatabaseFile` is usually backed by a file on disk, but may instead live in
+//! memory (see `create_in_memory`), sharing every other code path.
 
+use std::collections::{HashMap, HashSet};
 use std::fs::{File, OpenOptions};
 use std::io::{Read, Seek, SeekFrom, Write};
 #[cfg(unix)]
 use std::os::unix::fs::FileExt;
+#[cfg(windows)]
+use std::os::windows::fs::FileExt;
 use std::path::Path;
 use std::sync::Arc;
 
-use crate::storage::buffer_pool::BufferPool;
+use crate::storage::buffer_pool::{BufferPool, CacheKey};
+use crate::storage::checksum::ChecksumAlgorithm;
 use crate::storage::io::{Storage, StorageError};
-use crate::storage::page::{PAGE_SIZE, PAGE_SIZE_U64, Page, PageId};
-use crate::storage::superblock::{Superblock, SuperblockError};
-use crate::storage::wal::{self, LogRecord, LogRecordPayload, Lsn, Wal, WalError};
+use crate::storage::memory_file::MemoryFile;
+use crate::storage::page::{
+    MIN_PAGE_SIZE, PAGE_SIZE, Page, PageError, PageHeader, PageId, PageType,
+    is_supported_page_size, stamp_page_checksum, verify_page_checksum,
+};
+use crate::storage::superblock::{
+    FORMAT_VERSION, PAGE_CHECKSUM_VERSION, Superblock, SuperblockError,
+};
+use crate::storage::wal::{self, LogRecord, LogRecordPayload, Lsn, Wal, WalError, WalRecords};
 use crate::types::HlcTimestamp;
 
+/// Offset in a free page of the ID of the next page on the free list.
+const FREE_PAGE_NEXT_OFFSET: usize = PageHeader::SIZE;
+
+/// On-disk format choices fixed when a database file is created.
+///
+/// Both are recorded in the superblock, so opening a file always uses the
+/// values it was created with.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct FileFormat {
+    /// Page size in bytes, one of `SUPPORTED_PAGE_SIZES`.
+    pub page_size: usize,
+    /// Checksum algorithm for WAL records and pages.
+    pub checksum_algorithm: ChecksumAlgorithm,
+}
+
+impl Default for FileFormat {
+    fn default() -> Self {
+        Self {
+            page_size: PAGE_SIZE,
+            checksum_algorithm: ChecksumAlgorithm::Crc32,
+        }
+    }
+}
+
+/// Result of `DatabaseFile::compact_wal`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WalCompaction {
+    /// WAL capacity in bytes before compaction.
+    pub capacity_before: u64,
+    /// WAL capacity in bytes after compaction.
+    pub capacity_after: u64,
+    /// Pages that left the WAL region.
+    pub pages_released: u64,
+    /// Whether the released pages were truncated from the end of the file.
+    /// Otherwise they remain in the file as free pages.
+    pub file_truncated: bool,
+}
+
+/// Result of `DatabaseFile::grow_wal`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WalGrowth {
+    /// WAL capacity in bytes before growing.
+    pub capacity_before: u64,
+    /// WAL capacity in bytes after growing.
+    pub capacity_after: u64,
+    /// Whether the region moved to the end of the file. Otherwise it was
+    /// extended in place.
+    pub relocated: bool,
+}
+
+/// Where a `DatabaseFile`'s bytes live.
+#[derive(Debug)]
+pub enum FileBacking {
+    /// A file on disk.
+    Disk(File),
+    /// Memory, lost when the `DatabaseFile` is dropped.
+    Memory(MemoryFile),
+}
+
+impl FileBacking {
+    /// Truncate or extend the backing to `len` bytes.
+    fn set_len(&mut self, len: u64) -> std::io::Result<()> {
+        match self {
+            Self::Disk(file) => file.set_len(len),
+            Self::Memory(memory) => {
+                memory.set_len(len);
+                Ok(())
+            }
+        }
+    }
+
+    /// Make all writes durable. A no-op in memory.
+    fn sync_all(&self) -> std::io::Result<()> {
+        match self {
+            Self::Disk(file) => file.sync_all(),
+            Self::Memory(_) => Ok(()),
+        }
+    }
+
+    /// Fill `buffer` from byte `offset` without requiring `&mut`.
+    fn read_exact_at(&self, buffer: &mut [u8], offset: u64) -> std::io::Result<()> {
+        match self {
+            Self::Disk(file) => read_exact_at(file, buffer, offset),
+            Self::Memory(memory) => memory.read_exact_at(buffer, offset),
+        }
+    }
+}
+
+impl Read for FileBacking {
+    fn read(&mut self, buffer: &mut [u8]) -> std::io::Result<usize> {
+        match self {
+            Self::Disk(file) => file.read(buffer),
+            Self::Memory(memory) => memory.read(buffer),
+        }
+    }
+}
+
+impl Write for FileBacking {
+    fn write(&mut self, bytes: &[u8]) -> std::io::Result<usize> {
+        match self {
+            Self::Disk(file) => file.write(bytes),
+            Self::Memory(memory) => memory.write(bytes),
+        }
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        match self {
+            Self::Disk(file) => file.flush(),
+            Self::Memory(memory) => memory.flush(),
+        }
+    }
+}
+
+impl Seek for FileBacking {
+    fn seek(&mut self, position: SeekFrom) -> std::io::Result<u64> {
+        match self {
+            Self::Disk(file) => file.seek(position),
+            Self::Memory(memory) => memory.seek(position),
+        }
+    }
+}
+
 /// A database file handle with low-level page I/O operations.
 pub struct DatabaseFile {
-    file: File,
+    file: FileBacking,
     superblock: Superblock,
     buffer_pool: Arc<BufferPool>,
+    /// Pages written since the last `take_written_pages`, for checkpoints.
+    written_pages: HashSet<PageId>,
+    /// Where page writes go for a file opened with `open_readonly`. `None`
+    /// for writable files.
+    overlay: Option<PageOverlay>,
+    /// Page writes held in memory by `defer_writes`, by page ID. `None`
+    /// unless writes are deferred.
+    deferred: Option<HashMap<PageId, Vec<u8>>>,
+    /// Keys this file's pages in the buffer pool's page cache.
+    cache_id: u64,
+}
+
+/// Pages written in memory instead of to disk, for a read-only file.
+struct PageOverlay {
+    /// Page contents, by page ID.
+    pages: HashMap<PageId, Vec<u8>>,
+    /// Pages in the file on disk. Pages allocated past it exist only here.
+    disk_pages: u64,
 }
 
 impl DatabaseFile {
@@ -28,37 +180,95 @@ impl DatabaseFile {
     ///
     /// Returns an error if the file already exists.
     /// The provided buffer pool is shared across all databases.
+    /// Uses the default `FileFormat` (8KB pages, CRC32 checksums).
     pub fn create(path: &Path, buffer_pool: Arc<BufferPool>) -> Result<Self, FileError> {
+        Self::create_with_format(path, buffer_pool, FileFormat::default())
+    }
+
+    /// Create a new database file with the given on-disk format.
+    ///
+    /// The format is recorded in the superblock and used for every page
+    /// access and WAL record when the file is later opened.
+    ///
+    /// # Pre-conditions
+    /// - `format.page_size` is one of `SUPPORTED_PAGE_SIZES`, otherwise
+    ///   `FileError::UnsupportedPageSize` is returned
+    /// - `format.page_size` is at most the buffer pool's, otherwise
+    ///   `FileError::PageSizeExceedsPool` is returned
+    pub fn create_with_format(
+        path: &Path,
+        buffer_pool: Arc<BufferPool>,
+        format: FileFormat,
+    ) -> Result<Self, FileError> {
+        check_page_size(format.page_size, &buffer_pool)?;
         if path.exists() {
             return Err(FileError::AlreadyExists(path.to_path_buf()));
         }
 
-        let mut file = OpenOptions::new()
+        let file = OpenOptions::new()
             .read(true)
             .write(true)
             .create_new(true)
             .open(path)
             .map_err(FileError::Io)?;
+        Self::create_on(FileBacking::Disk(file), buffer_pool, format)
+    }
+
+    /// Create a new database file held in memory.
+    ///
+    /// The file behaves like one created on disk, except that syncs are
+    /// no-ops and its contents are lost when it is dropped. It can never be
+    /// reopened, so it never needs recovery.
+    ///
+    /// # Pre-conditions
+    /// - `format.page_size` is one of `SUPPORTED_PAGE_SIZES`, otherwise
+    ///   `FileError::UnsupportedPageSize` is returned
+    /// - `format.page_size` is at most the buffer pool's, otherwise
+    ///   `FileError::PageSizeExceedsPool` is returned
+    pub fn create_in_memory(
+        buffer_pool: Arc<BufferPool>,
+        format: FileFormat,
+    ) -> Result<Self, FileError> {
+        check_page_size(format.page_size, &buffer_pool)?;
+        Self::create_on(FileBacking::Memory(MemoryFile::new()), buffer_pool, format)
+    }
 
-        // Initialize with a fresh superblock
-        let superblock = Superblock::new();
+    /// Write a fresh superblock to an empty backing.
+    fn create_on(
+        mut file: FileBacking,
+        buffer_pool: Arc<BufferPool>,
+        format: FileFormat,
+    ) -> Result<Self, FileError> {
+        let page_size = format.page_size;
+        #[allow(clippy::cast_possible_truncation)] // Supported page sizes fit in u32
+        let mut superblock = Superblock::with_page_size(page_size as u32);
+        superblock.checksum_algorithm = format.checksum_algorithm;
         let page = superblock
             .to_page(&buffer_pool)
             .ok_or(FileError::BufferPoolExhausted)?;
 
-        file.write_all(page.as_bytes()).map_err(FileError::Io)?;
+        file.write_all(&page.as_bytes()[..page_size])
+            .map_err(FileError::Io)?;
         file.sync_all().map_err(FileError::Io)?;
+        let cache_id = buffer_pool.register_file();
 
         Ok(Self {
             file,
             superblock,
             buffer_pool,
+            written_pages: HashSet::new(),
+            overlay: None,
+            deferred: None,
+            cache_id,
         })
     }
 
     /// Open an existing database file.
     ///
     /// The provided buffer pool is shared across all databases.
+    /// Pages are read using the page size recorded in the superblock; files
+    /// whose recorded page size is not supported, or is larger than the
+    /// buffer pool's, are rejected.
     pub fn open(path: &Path, buffer_pool: Arc<BufferPool>) -> Result<Self, FileError> {
         let mut file = OpenOptions::new()
             .read(true)
@@ -66,27 +276,113 @@ impl DatabaseFile {
             .open(path)
             .map_err(FileError::Io)?;
 
-        // Read and validate the superblock
-        let mut buf = [0u8; PAGE_SIZE];
-        file.read_exact(&mut buf).map_err(FileError::Io)?;
-
-        // Create a page from the buffer to parse the superblock
-        let page = buffer_pool
-            .lease_page()
-            .ok_or(FileError::BufferPoolExhausted)?;
-        // Copy the read bytes into the page buffer
-        let mut page = page;
-        page.as_bytes_mut().copy_from_slice(&buf);
-
-        let superblock = Superblock::from_page(&page).map_err(FileError::Superblock)?;
+        let superblock = read_superblock(&mut file, &buffer_pool)?;
+        check_page_size(superblock.page_size as usize, &buffer_pool)?;
+        let cache_id = buffer_pool.register_file();
 
         Ok(Self {
-            file,
+            file: FileBacking::Disk(file),
             superblock,
             buffer_pool,
+            written_pages: HashSet::new(),
+            overlay: None,
+            deferred: None,
+            cache_id,
         })
     }
 
+    /// Open an existing database file without ever writing to it.
+    ///
+    /// The file is opened read-only. Page writes, allocations and superblock
+    /// updates, such as those made by crash recovery, are kept in memory, so
+    /// reads see them but the file's bytes are never changed. Syncing is a
+    /// no-op.
+    pub fn open_readonly(path: &Path, buffer_pool: Arc<BufferPool>) -> Result<Self, FileError> {
+        let mut file = File::open(path).map_err(FileError::Io)?;
+        let superblock = read_superblock(&mut file, &buffer_pool)?;
+        check_page_size(superblock.page_size as usize, &buffer_pool)?;
+        Ok(Self::with_overlay(file, superblock, buffer_pool))
+    }
+
+    /// Open an existing database file of any readable format version, without
+    /// ever writing to it, to copy its contents into a new file: to upgrade it
+    /// to the current format, or to rebuild it after a crash tore its pages.
+    ///
+    /// Like `open_readonly`, except that files older than `FORMAT_VERSION` are
+    /// accepted, and a torn superblock is read from its second copy (see
+    /// `Superblock::from_torn_page`). Pages of older files are in an older
+    /// layout, so only the upgrade in `storage::upgrade` may interpret them.
+    pub(crate) fn open_to_copy(
+        path: &Path,
+        buffer_pool: Arc<BufferPool>,
+    ) -> Result<Self, FileError> {
+        let mut file = File::open(path).map_err(FileError::Io)?;
+        let superblock = read_torn_superblock(&mut file, &buffer_pool)?;
+        check_page_size(superblock.page_size as usize, &buffer_pool)?;
+        Ok(Self::with_overlay(file, superblock, buffer_pool))
+    }
+
+    /// Wrap a file opened read-only, so page writes go to an in-memory overlay.
+    fn with_overlay(file: File, superblock: Superblock, buffer_pool: Arc<BufferPool>) -> Self {
+        let cache_id = buffer_pool.register_file();
+        let disk_pages = superblock.total_page_count;
+
+        Self {
+            file: FileBacking::Disk(file),
+            superblock,
+            buffer_pool,
+            written_pages: HashSet::new(),
+            overlay: Some(PageOverlay {
+                pages: HashMap::new(),
+                disk_pages,
+            }),
+            deferred: None,
+            cache_id,
+        }
+    }
+
+    /// Whether the file was opened with `open_readonly`.
+    #[must_use]
+    pub const fn is_readonly(&self) -> bool {
+        self.overlay.is_some()
+    }
+
+    /// Whether the file was created with `create_in_memory`.
+    #[must_use]
+    pub const fn is_in_memory(&self) -> bool {
+        matches!(self.file, FileBacking::Memory(_))
+    }
+
+    /// Iterate over the WAL records of the database file at `path`.
+    ///
+    /// Opens the file read-only and never writes to it, so it is safe to run
+    /// against a database another process has open, e.g. for a `wal-dump`
+    /// diagnostic. Records are read from the tail to the head recorded in the
+    /// superblock; a torn final record ends the scan (see `WalRecords`).
+    ///
+    /// # Errors
+    ///
+    /// Returns `WalError::File` if the file cannot be opened or its superblock
+    /// is invalid, or `WalError::NotInitialized` if it has no WAL.
+    pub fn wal_records(path: &Path) -> Result<WalRecords<File>, WalError> {
+        let mut file = File::open(path).map_err(FileError::Io)?;
+        let superblock = read_superblock(&mut file, &BufferPool::new(1))?;
+        if superblock.txn_log_capacity == 0 {
+            return Err(WalError::NotInitialized);
+        }
+
+        // As in `wal()`, the tail is the start of the region
+        let head = superblock.txn_log_end - superblock.txn_log_start;
+        Ok(WalRecords::new(
+            file,
+            superblock.txn_log_start,
+            superblock.txn_log_capacity,
+            0,
+            head,
+            superblock.checksum_algorithm,
+        ))
+    }
+
     /// Get a reference to the superblock.
     #[must_use]
     pub const fn superblock(&self) -> &Superblock {
@@ -98,6 +394,23 @@ impl DatabaseFile {
         &mut self.superblock
     }
 
+    /// Get the page size of this file in bytes.
+    #[must_use]
+    pub const fn page_size(&self) -> usize {
+        self.superblock.page_size as usize
+    }
+
+    /// Get the checksum algorithm this file was created with.
+    #[must_use]
+    pub const fn checksum_algorithm(&self) -> ChecksumAlgorithm {
+        self.superblock.checksum_algorithm
+    }
+
+    /// Get the page size of this file as u64 for offset calculations.
+    const fn page_size_u64(&self) -> u64 {
+        self.superblock.page_size as u64
+    }
+
     /// Read a page from the file.
     ///
     /// Returns an error if page is out of bounds or buffer pool is exhausted.
@@ -115,26 +428,36 @@ impl DatabaseFile {
             .lease_page()
             .ok_or(FileError::BufferPoolExhausted)?;
 
-        let offset = page_id * PAGE_SIZE_U64;
+        let page_size = self.page_size();
+        if self.read_overlay(page_id, &mut page.as_bytes_mut()[..page_size])
+            || self.buffer_pool.read_cached(
+                self.cache_key(page_id),
+                &mut page.as_bytes_mut()[..page_size],
+            )
+        {
+            return Ok(page);
+        }
+        let offset = page_id * self.page_size_u64();
         self.file
             .seek(SeekFrom::Start(offset))
             .map_err(FileError::Io)?;
 
         self.file
-            .read_exact(page.as_bytes_mut())
+            .read_exact(&mut page.as_bytes_mut()[..page_size])
             .map_err(FileError::Io)?;
+        self.verify_page(page_id, &page.as_bytes()[..page_size])?;
+        self.buffer_pool
+            .cache_page(self.cache_key(page_id), &page.as_bytes()[..page_size]);
 
         Ok(page)
     }
 
-    /// Read a page from the file using pread (position-independent).
+    /// Read a page from the file using a position-independent read.
     ///
-    /// This method uses `pread` via `FileExt::read_exact_at()` which does not
-    /// modify the file cursor position. This allows concurrent reads from
-    /// multiple threads without requiring mutable access.
+    /// Each read names its own offset (see `read_exact_at`), so concurrent
+    /// reads from multiple threads do not need mutable access.
     ///
     /// Returns an error if page is out of bounds or buffer pool is exhausted.
-    #[cfg(unix)]
     pub fn read_page_at(&self, page_id: PageId) -> Result<Page, FileError> {
         if page_id >= self.superblock.total_page_count {
             return Err(FileError::PageOutOfBounds {
@@ -149,15 +472,124 @@ impl DatabaseFile {
             .lease_page()
             .ok_or(FileError::BufferPoolExhausted)?;
 
-        let offset = page_id * PAGE_SIZE_U64;
+        let page_size = self.page_size();
+        if self.read_overlay(page_id, &mut page.as_bytes_mut()[..page_size])
+            || self.buffer_pool.read_cached(
+                self.cache_key(page_id),
+                &mut page.as_bytes_mut()[..page_size],
+            )
+        {
+            return Ok(page);
+        }
+        let offset = page_id * self.page_size_u64();
         self.file
-            .read_exact_at(page.as_bytes_mut(), offset)
+            .read_exact_at(&mut page.as_bytes_mut()[..page_size], offset)
             .map_err(FileError::Io)?;
+        self.verify_page(page_id, &page.as_bytes()[..page_size])?;
+        self.buffer_pool
+            .cache_page(self.cache_key(page_id), &page.as_bytes()[..page_size]);
 
         Ok(page)
     }
 
+    /// Check a page read from disk against the checksum in its header.
+    ///
+    /// Page 0 and WAL records carry checksums of their own, and files older
+    /// than `PAGE_CHECKSUM_VERSION` have none.
+    fn verify_page(&self, page_id: PageId, bytes: &[u8]) -> Result<(), FileError> {
+        if page_id == 0
+            || self.superblock.format_version < PAGE_CHECKSUM_VERSION
+            || self.in_wal_region(page_id)
+        {
+            return Ok(());
+        }
+        verify_page_checksum(bytes, self.checksum_algorithm())
+            .map_err(|error| FileError::CorruptPage { page_id, error })
+    }
+
+    /// Find the pages on disk that fail their checksum, as pages whose write
+    /// a crash tore do.
+    ///
+    /// Page 0 is listed if its superblock is torn (see `superblock_is_torn`).
+    /// WAL pages are skipped. Every other page is read from disk, bypassing
+    /// the page cache.
+    ///
+    /// # Errors
+    /// Returns an error if a page cannot be read.
+    pub fn torn_pages(&self) -> Result<Vec<PageId>, FileError> {
+        if self.superblock.format_version < PAGE_CHECKSUM_VERSION {
+            return Ok(Vec::new());
+        }
+        let mut torn = Vec::new();
+        if self.superblock_is_torn()? {
+            torn.push(0);
+        }
+        let page_size = self.page_size();
+        let mut bytes = vec![0u8; page_size];
+        for page_id in 1..self.total_pages() {
+            if self.in_wal_region(page_id) {
+                continue;
+            }
+            self.file
+                .read_exact_at(&mut bytes, page_id * self.page_size_u64())
+                .map_err(FileError::Io)?;
+            if verify_page_checksum(&bytes, self.checksum_algorithm()).is_err() {
+                torn.push(page_id);
+            }
+        }
+        Ok(torn)
+    }
+
+    /// Check whether the superblock on disk is torn, so that only its second
+    /// copy is valid (see `Superblock::from_torn_page`).
+    ///
+    /// # Errors
+    /// Returns an error if page 0 cannot be read.
+    pub fn superblock_is_torn(&self) -> Result<bool, FileError> {
+        let mut page = self
+            .buffer_pool
+            .lease_page_zeroed()
+            .ok_or(FileError::BufferPoolExhausted)?;
+        self.file
+            .read_exact_at(&mut page.as_bytes_mut()[..MIN_PAGE_SIZE], 0)
+            .map_err(FileError::Io)?;
+        Ok(Superblock::from_page(&page).is_err())
+    }
+
+    /// Fill `buffer` from the read-only overlay or the deferred writes if
+    /// either holds `page_id`.
+    ///
+    /// Pages allocated in the overlay but never written read as zeros.
+    ///
+    /// # Returns
+    /// Whether the overlay supplied the page; if not, read it from disk.
+    fn read_overlay(&self, page_id: PageId, buffer: &mut [u8]) -> bool {
+        if let Some(bytes) = self
+            .deferred
+            .as_ref()
+            .and_then(|deferred| deferred.get(&page_id))
+        {
+            buffer.copy_from_slice(bytes);
+            return true;
+        }
+        let Some(overlay) = &self.overlay else {
+            return false;
+        };
+        if let Some(bytes) = overlay.pages.get(&page_id) {
+            buffer.copy_from_slice(bytes);
+            true
+        } else if page_id >= overlay.disk_pages {
+            buffer.fill(0);
+            true
+        } else {
+            false
+        }
+    }
+
     /// Write a page to the file.
+    ///
+    /// The page is written with its checksum in the page header, which every
+    /// read from disk checks.
     pub fn write_page(&mut self, page_id: PageId, page: &Page) -> Result<(), FileError> {
         if page_id >= self.superblock.total_page_count {
             return Err(FileError::PageOutOfBounds {
@@ -166,28 +598,100 @@ impl DatabaseFile {
             });
         }
 
-        let offset = page_id * PAGE_SIZE_U64;
-        self.file
+        let mut bytes = page.as_bytes()[..self.page_size()].to_vec();
+        stamp_page_checksum(&mut bytes, self.checksum_algorithm());
+        if let Some(overlay) = &mut self.overlay {
+            overlay.pages.insert(page_id, bytes);
+            self.written_pages.insert(page_id);
+            return Ok(());
+        }
+        if let Some(deferred) = &mut self.deferred {
+            deferred.insert(page_id, bytes);
+            return Ok(());
+        }
+        self.write_page_bytes(page_id, &bytes)
+    }
+
+    /// Write a page image to disk and cache it.
+    fn write_page_bytes(&mut self, page_id: PageId, bytes: &[u8]) -> Result<(), FileError> {
+        let key = self.cache_key(page_id);
+        let offset = page_id * self.page_size_u64();
+        let written = self
+            .file
             .seek(SeekFrom::Start(offset))
-            .map_err(FileError::Io)?;
+            .and_then(|_| self.file.write_all(bytes));
+        if let Err(e) = written {
+            // The page on disk may now hold part of either version
+            self.buffer_pool.forget_page(key);
+            return Err(FileError::Io(e));
+        }
+        self.written_pages.insert(page_id);
+        self.buffer_pool.cache_page(key, bytes);
 
-        self.file
-            .write_all(page.as_bytes())
-            .map_err(FileError::Io)?;
+        Ok(())
+    }
+
+    /// Hold page writes in memory until `flush_deferred_writes`.
+    ///
+    /// Reads see the held pages. A page written many times, like a B-tree
+    /// leaf taking a run of sorted inserts, then reaches the file once.
+    /// Writes to a read-only file still go to its overlay.
+    pub fn defer_writes(&mut self) {
+        self.deferred.get_or_insert_with(HashMap::new);
+    }
 
+    /// Write the pages held since `defer_writes`, in page order, and stop
+    /// deferring writes.
+    ///
+    /// # Errors
+    /// Returns an error if a page cannot be written. Pages not yet written
+    /// are dropped, as if their writes had failed.
+    pub fn flush_deferred_writes(&mut self) -> Result<(), FileError> {
+        let Some(deferred) = self.deferred.take() else {
+            return Ok(());
+        };
+        let mut pages: Vec<(PageId, Vec<u8>)> = deferred.into_iter().collect();
+        pages.sort_unstable_by_key(|(page_id, _)| *page_id);
+        for (page_id, bytes) in pages {
+            self.write_page_bytes(page_id, &bytes)?;
+        }
         Ok(())
     }
 
+    /// The key of one of this file's pages in the buffer pool's page cache.
+    const fn cache_key(&self, page_id: PageId) -> CacheKey {
+        CacheKey {
+            file_id: self.cache_id,
+            page_id,
+        }
+    }
+
+    /// Take the IDs of the pages written since the last call.
+    ///
+    /// Checkpoints use this to account for the pages they make durable.
+    pub fn take_written_pages(&mut self) -> Vec<PageId> {
+        std::mem::take(&mut self.written_pages)
+            .into_iter()
+            .collect()
+    }
+
     /// Write the superblock to page 0.
+    ///
+    /// For a read-only file the superblock stays in memory only.
     pub fn write_superblock(&mut self) -> Result<(), FileError> {
+        if self.is_readonly() {
+            return Ok(());
+        }
         let page = self
             .superblock
             .to_page(&self.buffer_pool)
             .ok_or(FileError::BufferPoolExhausted)?;
 
+        let page_size = self.page_size();
+        self.buffer_pool.forget_page(self.cache_key(0));
         self.file.seek(SeekFrom::Start(0)).map_err(FileError::Io)?;
         self.file
-            .write_all(page.as_bytes())
+            .write_all(&page.as_bytes()[..page_size])
             .map_err(FileError::Io)?;
 
         Ok(())
@@ -199,17 +703,37 @@ impl DatabaseFile {
         &self.buffer_pool
     }
 
-    /// Allocate new pages at the end of the file.
+    /// Allocate pages, reusing a freed page if one is available.
+    ///
+    /// A single page is taken from the free list (see `free_pages`) unless it
+    /// is empty. Otherwise, and always for several pages, which must be
+    /// contiguous, the file is extended.
     ///
     /// Returns the page ID of the first allocated page.
+    ///
+    /// # Errors
+    /// Returns an error if extending the file fails, or `CorruptFreeList` if
+    /// the head of the free list is not a free page.
     pub fn allocate_pages(&mut self, count: u64) -> Result<PageId, FileError> {
+        if count == 1 && self.superblock.free_list_head != 0 {
+            return self.pop_free_page();
+        }
+        self.extend_file(count)
+    }
+
+    /// Append `count` pages to the end of the file, returning the first.
+    ///
+    /// The superblock is updated in memory only.
+    fn extend_file(&mut self, count: u64) -> Result<PageId, FileError> {
         let first_new_page = self.superblock.total_page_count;
 
         // Extend the file
         let new_total = first_new_page + count;
-        let new_size = new_total * PAGE_SIZE_U64;
+        let new_size = new_total * self.page_size_u64();
 
-        self.file.set_len(new_size).map_err(FileError::Io)?;
+        if !self.is_readonly() {
+            self.file.set_len(new_size).map_err(FileError::Io)?;
+        }
 
         // Update superblock
         self.superblock.total_page_count = new_total;
@@ -218,8 +742,126 @@ impl DatabaseFile {
         Ok(first_new_page)
     }
 
-    /// Sync all pending writes to disk.
+    /// Take the page at the head of the free list.
+    fn pop_free_page(&mut self) -> Result<PageId, FileError> {
+        let page_id = self.superblock.free_list_head;
+        let page = self.read_page(page_id)?;
+        if PageType::try_from(page.read_u8(0)) != Ok(PageType::Free) {
+            return Err(FileError::CorruptFreeList(page_id));
+        }
+        self.superblock.free_list_head = page.read_u64(FREE_PAGE_NEXT_OFFSET);
+        self.superblock.free_page_count = self.superblock.free_page_count.saturating_sub(1);
+        Ok(page_id)
+    }
+
+    /// Return `count` pages starting at `page_id` to the free list, so that
+    /// `allocate_pages` hands them out again before extending the file.
+    ///
+    /// Each page is overwritten with a `PageType::Free` header followed by the
+    /// ID of the next page on the list. The list's head and length live in
+    /// the superblock, which is updated in memory and persisted by the next
+    /// `write_superblock`.
+    ///
+    /// # Pre-conditions
+    /// - The pages are allocated, not yet free, and no longer referenced.
+    ///
+    /// # Errors
+    /// Returns an error if a page is out of bounds or writing it fails.
+    pub fn free_pages(&mut self, page_id: PageId, count: u64) -> Result<(), FileError> {
+        for page_id in page_id..page_id + count {
+            let mut page = self
+                .buffer_pool
+                .lease_page_zeroed()
+                .ok_or(FileError::BufferPoolExhausted)?;
+            let header = PageHeader {
+                page_type: PageType::Free,
+                flags: 0,
+                checksum: 0,
+            };
+            page.write_bytes(0, &header.to_bytes());
+            page.write_u64(FREE_PAGE_NEXT_OFFSET, self.superblock.free_list_head);
+            Self::write_page(self, page_id, &page)?;
+            self.superblock.free_list_head = page_id;
+            self.superblock.free_page_count += 1;
+        }
+        Ok(())
+    }
+
+    /// Get the number of pages on the free list.
+    #[must_use]
+    pub const fn free_page_count(&self) -> u64 {
+        self.superblock.free_page_count
+    }
+
+    /// Check the free list against the pages it links, rebuilding it from a
+    /// scan of the file if they disagree.
+    ///
+    /// The superblock and the pages are written separately, so after a crash
+    /// the superblock's list may start at a page that was reused since. The
+    /// list is kept if following it from the head reaches the end after
+    /// exactly `free_page_count` distinct `PageType::Free` pages outside the
+    /// WAL region. Otherwise every such page in the file is relinked into a
+    /// new list, lowest page first. Either way, no page on the list is in use.
+    ///
+    /// The superblock is updated in memory only.
+    ///
+    /// # Returns
+    /// Whether the list was rebuilt.
+    ///
+    /// # Errors
+    /// Returns an error if reading or writing a page fails.
+    pub fn repair_free_list(&mut self) -> Result<bool, FileError> {
+        if self.free_list_is_valid()? {
+            return Ok(false);
+        }
+        self.superblock.free_list_head = 0;
+        self.superblock.free_page_count = 0;
+        for page_id in (1..self.total_pages()).rev() {
+            if self.in_wal_region(page_id) {
+                continue;
+            }
+            let page = self.read_page(page_id)?;
+            if PageType::try_from(page.read_u8(0)) == Ok(PageType::Free) {
+                drop(page);
+                self.free_pages(page_id, 1)?;
+            }
+        }
+        Ok(true)
+    }
+
+    /// Check that the free list links exactly `free_page_count` distinct free
+    /// pages outside the WAL region.
+    fn free_list_is_valid(&mut self) -> Result<bool, FileError> {
+        let mut visited = HashSet::new();
+        let mut page_id = self.superblock.free_list_head;
+        while page_id != 0 {
+            if page_id >= self.total_pages()
+                || self.in_wal_region(page_id)
+                || !visited.insert(page_id)
+            {
+                return Ok(false);
+            }
+            let page = self.read_page(page_id)?;
+            if PageType::try_from(page.read_u8(0)) != Ok(PageType::Free) {
+                return Ok(false);
+            }
+            page_id = page.read_u64(FREE_PAGE_NEXT_OFFSET);
+        }
+        Ok(visited.len() as u64 == self.superblock.free_page_count)
+    }
+
+    /// Check whether a page belongs to the WAL region.
+    const fn in_wal_region(&self, page_id: PageId) -> bool {
+        let offset = page_id * self.page_size_u64();
+        offset >= self.superblock.txn_log_start
+            && offset < self.superblock.txn_log_start + self.superblock.txn_log_capacity
+    }
+
+    /// Sync all pending writes to disk. A no-op for a read-only file.
     pub fn sync(&self) -> Result<(), FileError> {
+        if self.is_readonly() {
+            return Ok(());
+        }
         self.file.sync_all().map_err(FileError::Io)
     }
 
@@ -235,21 +877,23 @@ impl DatabaseFile {
     /// Should only be called once when creating a new database.
     ///
     /// # Arguments
-    /// - `capacity`: The desired WAL capacity in bytes (will be rounded up to page size)
+    /// - `capacity`: The desired WAL capacity in bytes (will be rounded up to the file's page size)
     pub fn init_wal(&mut self, capacity: u64) -> Result<(), FileError> {
         let capacity = capacity.max(wal::MIN_WAL_CAPACITY);
-        let wal_pages = wal::pages_for_capacity(capacity);
-        let actual_capacity = wal_pages * PAGE_SIZE_U64;
+        let page_size = self.page_size_u64();
+        let wal_pages = wal::pages_for_capacity(capacity, page_size);
+        let actual_capacity = wal_pages * page_size;
 
         // Allocate pages for the WAL
         let first_wal_page = self.allocate_pages(wal_pages)?;
 
         // Calculate the byte offset where WAL region starts
-        let wal_start_offset = first_wal_page * PAGE_SIZE_U64;
+        let wal_start_offset = first_wal_page * page_size;
 
         // Update superblock with WAL information
         self.superblock.txn_log_start = wal_start_offset;
         self.superblock.txn_log_end = wal_start_offset; // head = start initially
+        self.superblock.txn_log_checkpoint_offset = 0;
         self.superblock.txn_log_capacity = actual_capacity;
         self.superblock.last_checkpoint_lsn = 0;
 
@@ -276,7 +920,7 @@ impl DatabaseFile {
     ///
     /// Returns an error if the WAL has not been initialized.
     #[allow(clippy::missing_const_for_fn)] // Cannot be const due to early return with ?
-    pub fn wal(&mut self) -> Result<Wal<'_, File>, WalError> {
+    pub fn wal(&mut self) -> Result<Wal<'_, FileBacking>, WalError> {
         if !self.has_wal() {
             return Err(WalError::NotInitialized);
         }
@@ -298,14 +942,11 @@ impl DatabaseFile {
             1
         };
 
-        Ok(Wal::new(
-            &mut self.file,
-            region_start,
-            capacity,
-            head,
-            tail,
-            next_lsn,
-        ))
+        Ok(
+            Wal::new(&mut self.file, region_start, capacity, head, tail, next_lsn)
+                .with_checksum_algorithm(self.superblock.checksum_algorithm)
+                .with_checkpoint_offset(Some(self.superblock.txn_log_checkpoint_offset)),
+        )
     }
 
     /// Update the WAL head position in the superblock.
@@ -317,14 +958,350 @@ impl DatabaseFile {
         self.superblock.last_wal_lsn = last_lsn;
     }
 
+    /// Shrink the WAL region to `capacity` bytes once every record is
+    /// checkpointed.
+    ///
+    /// The checkpoint record is copied to the start of the region and the
+    /// superblock is updated to the new head, checkpoint offset and capacity.
+    /// The pages past the new capacity leave the region: if they end the
+    /// file, the file is truncated; otherwise they join the free list.
+    ///
+    /// The copy is synced before the superblock is written, and recovery
+    /// starts from the superblock's checkpoint offset, so a crash at any point
+    /// recovers against either the old or the new region.
+    ///
+    /// # Arguments
+    /// - `capacity`: The desired WAL capacity in bytes (rounded up to the
+    ///   file's page size, and to at least `MIN_WAL_CAPACITY`)
+    ///
+    /// # Pre-conditions
+    /// - The WAL has been initialized.
+    /// - No record follows the last checkpoint (the checkpoint offset is the
+    ///   head).
+    ///
+    /// # Post-conditions
+    /// - The WAL capacity never grows: a larger `capacity` leaves the region
+    ///   at its current size.
+    /// - Records before the checkpoint record are discarded, so
+    ///   `changes_since` no longer returns them.
+    ///
+    /// # Errors
+    /// Returns an error if reading the checkpoint record or any write fails.
+    pub fn compact_wal(&mut self, capacity: u64) -> Result<WalCompaction, WalError> {
+        assert!(self.has_wal(), "compact_wal requires an initialized WAL");
+        self.assert_wal_checkpointed("compact_wal");
+
+        let page_size = self.page_size_u64();
+        let region_start = self.superblock.txn_log_start;
+        let capacity_before = self.superblock.txn_log_capacity;
+
+        let live_len = self.move_checkpoint_record_to_start()?;
+
+        // Keep room past the live bytes so the head never wraps onto them
+        let capacity = capacity.max(wal::MIN_WAL_CAPACITY).max(live_len + 1);
+        let pages_before = capacity_before / page_size;
+        let pages_after = wal::pages_for_capacity(capacity, page_size).min(pages_before);
+        let capacity_after = pages_after * page_size;
+        let first_released = region_start / page_size + pages_after;
+        let pages_released = pages_before - pages_after;
+        let truncates = pages_released > 0 && first_released + pages_released == self.total_pages();
+
+        self.superblock.txn_log_capacity = capacity_after;
+        self.superblock.txn_log_end = region_start + live_len;
+        self.superblock.txn_log_checkpoint_offset = live_len;
+        if truncates {
+            self.superblock.total_page_count = first_released;
+            self.superblock.file_size = first_released * page_size;
+        }
+        Self::write_superblock(self)?;
+        Self::sync(self)?;
+
+        if truncates {
+            self.buffer_pool.forget_pages(self.cache_id, first_released);
+            self.file
+                .set_len(first_released * page_size)
+                .map_err(WalError::Io)?;
+        } else if pages_released > 0 {
+            Self::free_pages(self, first_released, pages_released)?;
+            Self::write_superblock(self)?;
+        }
+        Self::sync(self)?;
+
+        Ok(WalCompaction {
+            capacity_before,
+            capacity_after,
+            pages_released,
+            file_truncated: truncates,
+        })
+    }
+
+    /// Grow the WAL region to `capacity` bytes once every record is
+    /// checkpointed.
+    ///
+    /// If the region ends the file, the file is extended and the region grows
+    /// in place, with the checkpoint record moved to its start as in
+    /// `compact_wal`. Otherwise a new region is allocated at the end of the
+    /// file, the checkpoint record is copied to its start, and the old
+    /// region's pages join the free list.
+    ///
+    /// The new region is synced before the superblock is written, and
+    /// recovery starts from the superblock's region and checkpoint offset, so
+    /// a crash at any point recovers against either the old or the new
+    /// region.
+    ///
+    /// # Arguments
+    /// - `capacity`: The desired WAL capacity in bytes (rounded up to the
+    ///   file's page size)
+    ///
+    /// # Pre-conditions
+    /// - The WAL has been initialized.
+    /// - No record follows the last checkpoint (the checkpoint offset is the
+    ///   head).
+    ///
+    /// # Post-conditions
+    /// - The WAL capacity never shrinks: a smaller `capacity` leaves the
+    ///   region at its current size.
+    /// - Records before the checkpoint record are discarded, so
+    ///   `changes_since` no longer returns them.
+    ///
+    /// # Errors
+    /// Returns an error if reading the checkpoint record or any write fails.
+    pub fn grow_wal(&mut self, capacity: u64) -> Result<WalGrowth, WalError> {
+        assert!(self.has_wal(), "grow_wal requires an initialized WAL");
+        self.assert_wal_checkpointed("grow_wal");
+
+        let page_size = self.page_size_u64();
+        let region_start = self.superblock.txn_log_start;
+        let capacity_before = self.superblock.txn_log_capacity;
+        let pages_before = capacity_before / page_size;
+        let pages_after = wal::pages_for_capacity(capacity, page_size);
+        if pages_after <= pages_before {
+            return Ok(WalGrowth {
+                capacity_before,
+                capacity_after: capacity_before,
+                relocated: false,
+            });
+        }
+        let capacity_after = pages_after * page_size;
+        let first_page = region_start / page_size;
+        let relocated = first_page + pages_before != self.total_pages();
+
+        let (new_start, live_len) = if relocated {
+            let head = self.superblock.txn_log_end - region_start;
+            let record = match self.checkpoint_record_start()? {
+                Some(start) => {
+                    #[allow(clippy::cast_possible_truncation)] // A record length is a u32
+                    let mut record = vec![0u8; (head - start) as usize];
+                    self.file
+                        .read_exact_at(&mut record, region_start + start)
+                        .map_err(WalError::Io)?;
+                    record
+                }
+                None => Vec::new(),
+            };
+            let new_start = self.extend_file(pages_after)? * page_size;
+            self.file
+                .seek(SeekFrom::Start(new_start))
+                .map_err(WalError::Io)?;
+            self.file.write_all(&record).map_err(WalError::Io)?;
+            (new_start, record.len() as u64)
+        } else {
+            let live_len = self.move_checkpoint_record_to_start()?;
+            self.extend_file(pages_after - pages_before)?;
+            (region_start, live_len)
+        };
+        Self::sync(self)?;
+
+        self.superblock.txn_log_start = new_start;
+        self.superblock.txn_log_capacity = capacity_after;
+        self.superblock.txn_log_end = new_start + live_len;
+        self.superblock.txn_log_checkpoint_offset = live_len;
+        Self::write_superblock(self)?;
+        Self::sync(self)?;
+
+        if relocated {
+            Self::free_pages(self, first_page, pages_before)?;
+            Self::write_superblock(self)?;
+            Self::sync(self)?;
+        }
+
+        Ok(WalGrowth {
+            capacity_before,
+            capacity_after,
+            relocated,
+        })
+    }
+
+    /// Panic unless every WAL record is checkpointed, naming `operation`.
+    fn assert_wal_checkpointed(&self, operation: &str) {
+        let checkpoint_offset = self.superblock.txn_log_checkpoint_offset;
+        let head = self.superblock.txn_log_end - self.superblock.txn_log_start;
+        assert_eq!(
+            checkpoint_offset, head,
+            "{operation} requires every WAL record to be checkpointed"
+        );
+    }
+
+    /// Locate the checkpoint record, the only live record once every record
+    /// is checkpointed, relative to the region start.
+    ///
+    /// It is the last record before the head, so the scan from offset 0
+    /// always reaches it unless the head wrapped to 0. Returns `None` if
+    /// nothing before the head is live.
+    fn checkpoint_record_start(&mut self) -> Result<Option<u64>, WalError> {
+        let checkpoint_lsn = self.superblock.last_checkpoint_lsn;
+        let head = self.superblock.txn_log_end - self.superblock.txn_log_start;
+        if checkpoint_lsn > 0 && head > 0 {
+            self.wal()?.find_lsn(checkpoint_lsn)
+        } else {
+            Ok(None)
+        }
+    }
+
+    /// Copy the checkpoint record to the start of the region, unless the copy
+    /// would overwrite it, and return the length of the region's live bytes.
+    ///
+    /// The superblock is not updated; the caller points it at the returned
+    /// length.
+    fn move_checkpoint_record_to_start(&mut self) -> Result<u64, WalError> {
+        let region_start = self.superblock.txn_log_start;
+        let head = self.superblock.txn_log_end - region_start;
+        Ok(match self.checkpoint_record_start()? {
+            // Already at the start of the region
+            Some(0) => head,
+            // Copy it to the start unless the copy would overwrite it
+            Some(start) if head - start <= start => {
+                #[allow(clippy::cast_possible_truncation)] // A record length is a u32
+                let mut record = vec![0u8; (head - start) as usize];
+                self.file
+                    .read_exact_at(&mut record, region_start + start)
+                    .map_err(WalError::Io)?;
+                self.file
+                    .seek(SeekFrom::Start(region_start))
+                    .map_err(WalError::Io)?;
+                self.file.write_all(&record).map_err(WalError::Io)?;
+                Self::sync(self)?;
+                head - start
+            }
+            Some(_) => head,
+            // Nothing before the head is live
+            None => 0,
+        })
+    }
+
     /// Get mutable access to the underlying file handle.
     ///
     /// This is needed for WAL operations that need direct file access.
-    pub const fn file_mut(&mut self) -> &mut File {
+    pub const fn file_mut(&mut self) -> &mut FileBacking {
         &mut self.file
     }
 }
 
+/// Read and validate the superblock at the start of `file`, rejecting files
+/// in an older format with `FileError::NeedsUpgrade`.
+fn read_superblock(
+    file: &mut File,
+    buffer_pool: &Arc<BufferPool>,
+) -> Result<Superblock, FileError> {
+    let superblock = read_any_superblock(file, buffer_pool)?;
+    if superblock.format_version < FORMAT_VERSION {
+        return Err(FileError::NeedsUpgrade(superblock.format_version));
+    }
+    Ok(superblock)
+}
+
+/// Check that files of `page_size` bytes are supported and fit in the
+/// buffers of `buffer_pool`.
+fn check_page_size(page_size: usize, buffer_pool: &BufferPool) -> Result<(), FileError> {
+    if !is_supported_page_size(page_size) {
+        return Err(FileError::UnsupportedPageSize(page_size));
+    }
+    if page_size > buffer_pool.page_size() {
+        return Err(FileError::PageSizeExceedsPool {
+            page_size,
+            pool_page_size: buffer_pool.page_size(),
+        });
+    }
+    Ok(())
+}
+
+/// Read and validate the superblock at the start of `file`, accepting any
+/// format version from `OLDEST_FORMAT_VERSION` on.
+fn read_any_superblock(
+    file: &mut File,
+    buffer_pool: &Arc<BufferPool>,
+) -> Result<Superblock, FileError> {
+    let page = read_superblock_page(file, buffer_pool)?;
+    Superblock::from_page(&page).map_err(FileError::Superblock)
+}
+
+/// Like `read_any_superblock`, but reads a torn superblock from its second
+/// copy (see `Superblock::from_torn_page`).
+fn read_torn_superblock(
+    file: &mut File,
+    buffer_pool: &Arc<BufferPool>,
+) -> Result<Superblock, FileError> {
+    let page = read_superblock_page(file, buffer_pool)?;
+    Superblock::from_torn_page(&page).map_err(FileError::Superblock)
+}
+
+/// Read the start of page 0 of `file`.
+///
+/// Both copies of the superblock live in the first `MIN_PAGE_SIZE` bytes, so
+/// this works before the page size is known.
+fn read_superblock_page(file: &mut File, buffer_pool: &Arc<BufferPool>) -> Result<Page, FileError> {
+    let mut page = buffer_pool
+        .lease_page_zeroed()
+        .ok_or(FileError::BufferPoolExhausted)?;
+    file.read_exact(&mut page.as_bytes_mut()[..MIN_PAGE_SIZE])
+        .map_err(FileError::Io)?;
+    Ok(page)
+}
+
+/// Fill `buffer` from `file` starting at byte `offset`, without requiring `&mut`.
+///
+/// Uses `pread` on Unix, which leaves the file cursor untouched.
+#[cfg(unix)]
+fn read_exact_at(file: &File, buffer: &mut [u8], offset: u64) -> std::io::Result<()> {
+    file.read_exact_at(buffer, offset)
+}
+
+/// Fill `buffer` from `file` starting at byte `offset`, without requiring `&mut`.
+///
+/// Uses `seek_read`, which moves the file cursor; every cursor-based access in
+/// this module seeks first, so that is harmless.
+#[cfg(windows)]
+fn read_exact_at(file: &File, mut buffer: &mut [u8], mut offset: u64) -> std::io::Result<()> {
+    while !buffer.is_empty() {
+        match file.seek_read(buffer, offset) {
+            Ok(0) => return Err(std::io::ErrorKind::UnexpectedEof.into()),
+            Ok(read) => {
+                buffer = &mut buffer[read..];
+                offset += read as u64;
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::Interrupted => {}
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(())
+}
+
+/// Fill `buffer` from `file` starting at byte `offset`, without requiring `&mut`.
+///
+/// Platforms without positional reads seek and read under a short-lived lock
+/// so concurrent readers cannot interleave their seeks.
+#[cfg(not(any(unix, windows)))]
+fn read_exact_at(file: &File, buffer: &mut [u8], offset: u64) -> std::io::Result<()> {
+    static READ_LOCK: std::sync::Mutex<()> = std::sync::Mutex::new(());
+
+    let _guard = READ_LOCK
+        .lock()
+        .unwrap_or_else(std::sync::PoisonError::into_inner);
+    let mut reader = file;
+    reader.seek(SeekFrom::Start(offset))?;
+    reader.read_exact(buffer)
+}
+
 /// Errors that can occur during file operations.
 #[derive(Debug)]
 pub enum FileError {
@@ -338,6 +1315,21 @@ pub enum FileError {
     PageOutOfBounds { page_id: PageId, total_pages: u64 },
     /// Buffer pool exhausted.
     BufferPoolExhausted,
+    /// Requested page size is not one of `SUPPORTED_PAGE_SIZES`.
+    UnsupportedPageSize(usize),
+    /// The file's page size is larger than the buffer pool's buffers.
+    PageSizeExceedsPool {
+        page_size: usize,
+        pool_page_size: usize,
+    },
+    /// The free list names a page that is not free.
+    CorruptFreeList(PageId),
+    /// The file is in an older format version, which `Database::open`
+    /// upgrades in place.
+    NeedsUpgrade(u32),
+    /// A page read from disk does not match its checksum, e.g. because a
+    /// crash tore its write.
+    CorruptPage { page_id: PageId, error: PageError },
 }
 
 impl std::fmt::Display for FileError {
@@ -356,6 +1348,22 @@ impl std::fmt::Display for FileError {
                 )
             }
             Self::BufferPoolExhausted => write!(f, "buffer pool exhausted"),
+            Self::UnsupportedPageSize(size) => write!(f, "unsupported page size: {size}"),
+            Self::PageSizeExceedsPool {
+                page_size,
+                pool_page_size,
+            } => write!(
+                f,
+                "page size {page_size} is larger than the buffer pool's {pool_page_size}"
+            ),
+            Self::CorruptFreeList(page_id) => {
+                write!(f, "free list names page {page_id}, which is not free")
+            }
+            Self::NeedsUpgrade(version) => write!(
+                f,
+                "file is format version {version}; open it writable to upgrade it to version {FORMAT_VERSION}"
+            ),
+            Self::CorruptPage { page_id, error } => write!(f, "page {page_id} is corrupt: {error}"),
         }
     }
 }
@@ -365,9 +1373,14 @@ impl std::error::Error for FileError {
         match self {
             Self::Io(e) => Some(e),
             Self::Superblock(e) => Some(e),
-            Self::AlreadyExists(_) | Self::PageOutOfBounds { .. } | Self::BufferPoolExhausted => {
-                None
-            }
+            Self::CorruptPage { error, .. } => Some(error),
+            Self::AlreadyExists(_)
+            | Self::PageOutOfBounds { .. }
+            | Self::BufferPoolExhausted
+            | Self::UnsupportedPageSize(_)
+            | Self::PageSizeExceedsPool { .. }
+            | Self::CorruptFreeList(_)
+            | Self::NeedsUpgrade(_) => None,
         }
     }
 }
@@ -389,10 +1402,26 @@ impl From<FileError> for StorageError {
             )),
             FileError::Superblock(e) => Self::Superblock(e.to_string()),
             FileError::BufferPoolExhausted => Self::BufferPoolExhausted,
+            FileError::UnsupportedPageSize(size) => {
+                Self::Superblock(format!("unsupported page size: {size}"))
+            }
+            FileError::PageSizeExceedsPool { .. } | FileError::NeedsUpgrade(_) => {
+                Self::Superblock(e.to_string())
+            }
+            FileError::CorruptFreeList(_) | FileError::CorruptPage { .. } => {
+                Self::Corruption(e.to_string())
+            }
         }
     }
 }
 
+impl Drop for DatabaseFile {
+    fn drop(&mut self) {
+        // Return the buffers holding this file's pages to the shared pool
+        self.buffer_pool.forget_pages(self.cache_id, 0);
+    }
+}
+
 impl Storage for DatabaseFile {
     fn buffer_pool(&self) -> &Arc<BufferPool> {
         Self::buffer_pool(self)
@@ -514,6 +1543,43 @@ mod tests {
         BufferPool::new(100)
     }
 
+    #[test]
+    fn test_read_page_at_from_concurrent_threads() {
+        let dir = tempdir().expect("create temp dir");
+        let path = dir.path().join("test.db");
+        let mut db = DatabaseFile::create(&path, test_pool()).expect("create db");
+
+        let first_page = db.allocate_pages(8).expect("allocate");
+        for page_id in first_page..first_page + 8 {
+            let mut page = db.buffer_pool().lease_page_zeroed().expect("lease page");
+            #[allow(clippy::cast_possible_truncation)]
+            page.write_bytes(PageHeader::SIZE, &[page_id as u8; 64]);
+            db.write_page(page_id, &page).expect("write page");
+        }
+
+        // Each thread reads every page in a different order
+        std::thread::scope(|scope| {
+            for thread_index in 0..4u64 {
+                let db = &db;
+                scope.spawn(move || {
+                    for step in 0..8 {
+                        let page_id = first_page + (step + thread_index * 3) % 8;
+                        let page = db.read_page_at(page_id).expect("read page");
+                        #[allow(clippy::cast_possible_truncation)]
+                        let expected = [page_id as u8; 64];
+                        assert_eq!(page.read_bytes(PageHeader::SIZE, 64), &expected[..]);
+                    }
+                });
+            }
+        });
+
+        // Cursor-based reads still see the right page afterwards
+        let page = db.read_page(first_page).expect("read page");
+        #[allow(clippy::cast_possible_truncation)]
+        let expected = [first_page as u8; 64];
+        assert_eq!(page.read_bytes(PageHeader::SIZE, 64), &expected[..]);
+    }
+
     #[test]
     fn test_create_and_open() {
         let dir = tempdir().expect("create temp dir");
@@ -535,6 +1601,285 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_create_and_open_with_small_page_size() {
+        let dir = tempdir().expect("create temp dir");
+        let path = dir.path().join("test.db");
+        let pool = test_pool();
+
+        {
+            let format = FileFormat {
+                page_size: 4096,
+                ..FileFormat::default()
+            };
+            let mut db = DatabaseFile::create_with_format(&path, Arc::clone(&pool), format)
+                .expect("create db");
+            assert_eq!(db.page_size(), 4096);
+            db.allocate_pages(2).expect("allocate");
+
+            let mut page = db.buffer_pool().lease_page_zeroed().expect("lease page");
+            page.write_u64(4000, 0xDEAD_BEEF);
+            db.write_page(2, &page).expect("write");
+            db.write_superblock().expect("write superblock");
+            db.sync().expect("sync");
+        }
+
+        // Three 4KB pages on disk
+        let file_length = fs::metadata(&path).expect("metadata").len();
+        assert_eq!(file_length, 3 * 4096);
+
+        let mut db = DatabaseFile::open(&path, pool).expect("open db");
+        assert_eq!(db.page_size(), 4096);
+        let page = db.read_page(2).expect("read");
+        assert_eq!(page.read_u64(4000), 0xDEAD_BEEF);
+    }
+
+    #[test]
+    fn test_checksum_algorithm_persists_and_applies_to_wal() {
+        let dir = tempdir().expect("create temp dir");
+        let path = dir.path().join("test.db");
+        let pool = test_pool();
+
+        {
+            let format = FileFormat {
+                checksum_algorithm: ChecksumAlgorithm::Crc32c,
+                ..FileFormat::default()
+            };
+            let mut db = DatabaseFile::create_with_format(&path, Arc::clone(&pool), format)
+                .expect("create db");
+            db.init_wal(wal::MIN_WAL_CAPACITY).expect("init wal");
+            db.wal_append(1, HlcTimestamp::new(1000, 0), LogRecordPayload::Begin)
+                .expect("append");
+            db.write_superblock().expect("write superblock");
+            db.sync().expect("sync");
+        }
+
+        let mut db = DatabaseFile::open(&path, pool).expect("open db");
+        assert_eq!(db.checksum_algorithm(), ChecksumAlgorithm::Crc32c);
+
+        let records = db.wal_read_all().expect("read wal");
+        assert_eq!(records.len(), 1);
+        assert_eq!(records[0].txn_id, 1);
+    }
+
+    #[test]
+    fn test_create_in_memory() {
+        let pool = test_pool();
+        let mut db = DatabaseFile::create_in_memory(Arc::clone(&pool), FileFormat::default())
+            .expect("create");
+        assert!(db.is_in_memory());
+        assert!(!db.is_readonly());
+
+        let first_page = db.allocate_pages(2).expect("allocate");
+        let mut page = pool.lease_page_zeroed().expect("lease page");
+        page.write_bytes(PageHeader::SIZE, b"hello world");
+        db.write_page(first_page + 1, &page).expect("write page");
+        db.sync().expect("sync");
+        let read_page = db.read_page_at(first_page + 1).expect("read page");
+        assert_eq!(read_page.read_bytes(PageHeader::SIZE, 11), b"hello world");
+
+        // WAL records are written to and read back from memory
+        db.init_wal(wal::MIN_WAL_CAPACITY).expect("init wal");
+        db.wal_append(7, HlcTimestamp::new(1000, 0), LogRecordPayload::Begin)
+            .expect("append");
+        let records = db.wal_read_all().expect("read wal");
+        assert_eq!(records.len(), 1);
+        assert_eq!(records[0].txn_id, 7);
+    }
+
+    #[test]
+    fn test_create_in_memory_rejects_unsupported_page_size() {
+        let format = FileFormat {
+            page_size: 1000,
+            ..FileFormat::default()
+        };
+        assert!(matches!(
+            DatabaseFile::create_in_memory(test_pool(), format),
+            Err(FileError::UnsupportedPageSize(1000))
+        ));
+    }
+
+    #[test]
+    fn test_wal_records_stops_at_torn_final_record() {
+        let dir = tempdir().expect("create temp dir");
+        let path = dir.path().join("test.db");
+
+        let wal_end = {
+            let mut db = DatabaseFile::create(&path, test_pool()).expect("create db");
+            assert!(matches!(
+                DatabaseFile::wal_records(&path),
+                Err(WalError::NotInitialized)
+            ));
+
+            db.init_wal(wal::MIN_WAL_CAPACITY).expect("init wal");
+            assert_eq!(
+                DatabaseFile::wal_records(&path)
+                    .expect("wal records")
+                    .count(),
+                0
+            );
+
+            let hlc = HlcTimestamp::new(1000, 0);
+            db.wal_append(1, hlc, LogRecordPayload::Begin)
+                .expect("append");
+            db.wal_append(1, hlc, LogRecordPayload::Insert(vec![7u8; 48]))
+                .expect("append");
+            db.wal_append(1, hlc, LogRecordPayload::Commit)
+                .expect("append");
+            db.write_superblock().expect("write superblock");
+            db.sync().expect("sync");
+            db.superblock().txn_log_end
+        };
+
+        let mut records = DatabaseFile::wal_records(&path).expect("wal records");
+        let types: Vec<(Lsn, wal::LogRecordType)> = records
+            .by_ref()
+            .map(|record| {
+                let record = record.expect("record");
+                (record.lsn, record.payload.record_type())
+            })
+            .collect();
+        assert_eq!(
+            types,
+            vec![
+                (1, wal::LogRecordType::Begin),
+                (2, wal::LogRecordType::Insert),
+                (3, wal::LogRecordType::Commit),
+            ]
+        );
+        assert!(records.stopped_by().is_none());
+
+        // Tear the COMMIT record: its last bytes never reached the disk
+        let mut file = OpenOptions::new()
+            .write(true)
+            .open(&path)
+            .expect("open file");
+        file.seek(SeekFrom::Start(wal_end - 6)).expect("seek");
+        file.write_all(&[0u8; 6]).expect("write");
+        file.sync_all().expect("sync");
+
+        let mut records = DatabaseFile::wal_records(&path).expect("wal records");
+        let lsns: Vec<Lsn> = records
+            .by_ref()
+            .map(|record| record.expect("record").lsn)
+            .collect();
+        assert_eq!(lsns, vec![1, 2]);
+        assert!(matches!(
+            records.stopped_by(),
+            Some(WalError::ChecksumMismatch { .. })
+        ));
+    }
+
+    #[test]
+    fn test_compact_wal_truncates_trailing_region() {
+        let dir = tempdir().expect("create temp dir");
+        let path = dir.path().join("test.db");
+
+        {
+            let mut db = DatabaseFile::create(&path, test_pool()).expect("create db");
+            db.init_wal(4 * wal::MIN_WAL_CAPACITY).expect("init wal");
+            let size_before = fs::metadata(&path).expect("metadata").len();
+
+            let compaction = db.compact_wal(0).expect("compact");
+            assert!(compaction.file_truncated);
+            assert_eq!(compaction.capacity_after, wal::MIN_WAL_CAPACITY);
+            assert_eq!(
+                fs::metadata(&path).expect("metadata").len(),
+                size_before - 3 * wal::MIN_WAL_CAPACITY
+            );
+        }
+
+        let mut db = DatabaseFile::open(&path, test_pool()).expect("open db");
+        assert_eq!(db.wal_capacity(), wal::MIN_WAL_CAPACITY);
+        assert_eq!(
+            db.superblock().file_size,
+            fs::metadata(&path).expect("metadata").len()
+        );
+        assert!(db.wal().expect("wal").is_empty());
+    }
+
+    #[test]
+    fn test_grow_wal_extends_trailing_region_in_place() {
+        let dir = tempdir().expect("create temp dir");
+        let path = dir.path().join("test.db");
+
+        {
+            let mut db = DatabaseFile::create(&path, test_pool()).expect("create db");
+            db.init_wal(wal::MIN_WAL_CAPACITY).expect("init wal");
+            let region_start = db.superblock().txn_log_start;
+            let size_before = fs::metadata(&path).expect("metadata").len();
+
+            let growth = db.grow_wal(3 * wal::MIN_WAL_CAPACITY).expect("grow");
+            assert!(!growth.relocated);
+            assert_eq!(growth.capacity_before, wal::MIN_WAL_CAPACITY);
+            assert_eq!(growth.capacity_after, 3 * wal::MIN_WAL_CAPACITY);
+            assert_eq!(db.superblock().txn_log_start, region_start);
+            assert_eq!(
+                fs::metadata(&path).expect("metadata").len(),
+                size_before + 2 * wal::MIN_WAL_CAPACITY
+            );
+
+            // Never shrinks
+            let growth = db.grow_wal(0).expect("grow");
+            assert_eq!(growth.capacity_after, 3 * wal::MIN_WAL_CAPACITY);
+        }
+
+        let mut db = DatabaseFile::open(&path, test_pool()).expect("open db");
+        assert_eq!(db.wal_capacity(), 3 * wal::MIN_WAL_CAPACITY);
+        assert!(db.wal().expect("wal").is_empty());
+    }
+
+    #[test]
+    fn test_grow_wal_relocates_region() {
+        let dir = tempdir().expect("create temp dir");
+        let path = dir.path().join("test.db");
+        let mut db = DatabaseFile::create(&path, test_pool()).expect("create db");
+        db.init_wal(wal::MIN_WAL_CAPACITY).expect("init wal");
+        let old_start = db.superblock().txn_log_start;
+        // A page after the region keeps it from growing in place
+        let last_page = db.allocate_pages(1).expect("allocate");
+
+        let growth = db.grow_wal(2 * wal::MIN_WAL_CAPACITY).expect("grow");
+        assert!(growth.relocated);
+        assert_eq!(growth.capacity_after, 2 * wal::MIN_WAL_CAPACITY);
+        assert_eq!(
+            db.superblock().txn_log_start,
+            (last_page + 1) * db.page_size_u64()
+        );
+
+        // The old region's pages are free
+        assert_eq!(
+            db.free_page_count(),
+            wal::MIN_WAL_CAPACITY / db.page_size_u64()
+        );
+        let page_id = db.allocate_pages(1).expect("allocate");
+        assert!(page_id * db.page_size_u64() >= old_start);
+        assert!(page_id < last_page);
+        assert!(!db.in_wal_region(page_id));
+    }
+
+    #[test]
+    fn test_open_rejects_unsupported_page_size() {
+        let dir = tempdir().expect("create temp dir");
+        let path = dir.path().join("test.db");
+        let pool = test_pool();
+
+        DatabaseFile::create(&path, Arc::clone(&pool)).expect("create db");
+
+        // Overwrite the stored page size (superblock offset 12) with 2048
+        let mut bytes = fs::read(&path).expect("read file");
+        bytes[12..16].copy_from_slice(&2048u32.to_le_bytes());
+        fs::write(&path, &bytes).expect("write file");
+
+        let result = DatabaseFile::open(&path, pool);
+        assert!(matches!(
+            result,
+            Err(FileError::Superblock(SuperblockError::InvalidPageSize(
+                2048
+            )))
+        ));
+    }
+
     #[test]
     fn test_create_already_exists() {
         let dir = tempdir().expect("create temp dir");
@@ -564,12 +1909,145 @@ mod tests {
 
         // Write to a page
         let mut page = db.buffer_pool().lease_page_zeroed().expect("lease page");
-        page.write_bytes(0, b"hello world");
+        page.write_bytes(PageHeader::SIZE, b"hello world");
         db.write_page(3, &page).expect("write page");
 
         // Read it back
         let read_page = db.read_page(3).expect("read page");
-        assert_eq!(read_page.read_bytes(0, 11), b"hello world");
+        assert_eq!(read_page.read_bytes(PageHeader::SIZE, 11), b"hello world");
+    }
+
+    #[test]
+    fn test_allocate_reuses_freed_pages() {
+        let dir = tempdir().expect("create temp dir");
+        let path = dir.path().join("test.db");
+
+        {
+            let mut db = DatabaseFile::create(&path, test_pool()).expect("create db");
+            let first_page = db.allocate_pages(5).expect("allocate");
+            db.free_pages(first_page + 1, 2).expect("free pages");
+            assert_eq!(db.free_page_count(), 2);
+
+            // Single pages come from the free list, most recently freed first
+            assert_eq!(db.allocate_pages(1).expect("allocate"), first_page + 2);
+            assert_eq!(db.free_page_count(), 1);
+
+            // Runs of pages extend the file
+            assert_eq!(db.allocate_pages(2).expect("allocate"), 6);
+            assert_eq!(db.total_pages(), 8);
+            db.write_superblock().expect("write superblock");
+        }
+
+        // The free list persists with the superblock
+        let mut db = DatabaseFile::open(&path, test_pool()).expect("open db");
+        assert_eq!(db.free_page_count(), 1);
+        assert!(!db.repair_free_list().expect("repair"));
+        assert_eq!(db.allocate_pages(1).expect("allocate"), 2);
+        assert_eq!(db.free_page_count(), 0);
+        assert_eq!(db.allocate_pages(1).expect("allocate"), 8);
+    }
+
+    #[test]
+    fn test_repair_free_list_drops_reused_pages() {
+        let dir = tempdir().expect("create temp dir");
+        let path = dir.path().join("test.db");
+        let mut db = DatabaseFile::create(&path, test_pool()).expect("create db");
+        db.allocate_pages(6).expect("allocate");
+        db.free_pages(2, 3).expect("free pages");
+
+        // As after a crash: page 4 was reused, but the superblock still lists it
+        let mut page = db.buffer_pool().lease_page_zeroed().expect("lease page");
+        let header = PageHeader {
+            page_type: PageType::BTreeLeaf,
+            flags: 0,
+            checksum: 0,
+        };
+        page.write_bytes(0, &header.to_bytes());
+        db.write_page(4, &page).expect("write page");
+        drop(page);
+
+        assert!(db.repair_free_list().expect("repair"));
+        assert_eq!(db.free_page_count(), 2);
+        assert_eq!(db.allocate_pages(1).expect("allocate"), 2);
+        assert_eq!(db.allocate_pages(1).expect("allocate"), 3);
+        assert_eq!(db.allocate_pages(1).expect("allocate"), 7);
+        assert!(!db.repair_free_list().expect("repair"));
+    }
+
+    #[test]
+    fn test_compact_wal_frees_released_pages() {
+        let dir = tempdir().expect("create temp dir");
+        let path = dir.path().join("test.db");
+        let mut db = DatabaseFile::create(&path, test_pool()).expect("create db");
+        db.init_wal(4 * wal::MIN_WAL_CAPACITY).expect("init wal");
+        // A page after the region keeps compaction from truncating the file
+        let last_page = db.allocate_pages(1).expect("allocate");
+
+        let compaction = db.compact_wal(0).expect("compact");
+        assert!(!compaction.file_truncated);
+        assert_eq!(db.free_page_count(), compaction.pages_released);
+
+        let page_id = db.allocate_pages(1).expect("allocate");
+        assert!(page_id < last_page);
+        assert!(!db.in_wal_region(page_id));
+        assert_eq!(db.total_pages(), last_page + 1);
+    }
+
+    #[test]
+    fn test_open_readonly_keeps_writes_in_memory() {
+        let dir = tempdir().expect("create temp dir");
+        let path = dir.path().join("test.db");
+        let pool = test_pool();
+
+        {
+            let mut db = DatabaseFile::create(&path, Arc::clone(&pool)).expect("create db");
+            db.allocate_pages(2).expect("allocate");
+            let mut page = db.buffer_pool().lease_page_zeroed().expect("lease page");
+            page.write_bytes(PageHeader::SIZE, b"on disk");
+            db.write_page(1, &page).expect("write page");
+            db.write_superblock().expect("write superblock");
+            db.sync().expect("sync");
+        }
+        let before = fs::read(&path).expect("read file");
+
+        let mut db = DatabaseFile::open_readonly(&path, pool).expect("open readonly");
+        assert!(db.is_readonly());
+        assert_eq!(
+            db.read_page(1)
+                .expect("read")
+                .read_bytes(PageHeader::SIZE, 7),
+            b"on disk"
+        );
+
+        let mut page = db.buffer_pool().lease_page_zeroed().expect("lease page");
+        page.write_bytes(PageHeader::SIZE, b"in memory");
+        db.write_page(1, &page).expect("write page");
+        let new_page = db.allocate_pages(1).expect("allocate");
+        db.superblock_mut().next_txn_id = 42;
+        db.write_superblock().expect("write superblock");
+        db.sync().expect("sync");
+
+        assert_eq!(
+            db.read_page(1)
+                .expect("read")
+                .read_bytes(PageHeader::SIZE, 9),
+            b"in memory"
+        );
+        assert_eq!(
+            db.read_page_at(1)
+                .expect("read")
+                .read_bytes(PageHeader::SIZE, 9),
+            b"in memory"
+        );
+        // Allocated in memory only, so it reads as zeros
+        assert!(
+            db.read_page(new_page)
+                .expect("read")
+                .read_bytes(0, 16)
+                .iter()
+                .all(|&byte| byte == 0)
+        );
+        assert_eq!(fs::read(&path).expect("read file"), before);
     }
 
     #[test]
@@ -633,4 +2111,75 @@ mod tests {
             assert_eq!(page.read_u64(100), 0xDEAD_BEEF_CAFE_BABE);
         }
     }
+
+    /// Overwrite `bytes` at `offset` of the file at `path`, as a torn write
+    /// that reached the disk only in part leaves it.
+    fn tear(path: &Path, offset: u64, bytes: &[u8]) {
+        let mut file = OpenOptions::new()
+            .write(true)
+            .open(path)
+            .expect("open file");
+        file.seek(SeekFrom::Start(offset)).expect("seek");
+        file.write_all(bytes).expect("write");
+        file.sync_all().expect("sync");
+    }
+
+    #[test]
+    fn test_read_page_rejects_torn_page() {
+        let dir = tempdir().expect("create temp dir");
+        let path = dir.path().join("test.db");
+
+        let first_page = {
+            let mut db = DatabaseFile::create(&path, test_pool()).expect("create db");
+            let first_page = db.allocate_pages(2).expect("allocate");
+            for page_id in first_page..first_page + 2 {
+                let mut page = db.buffer_pool().lease_page_zeroed().expect("lease page");
+                page.write_bytes(PageHeader::SIZE, b"whole");
+                db.write_page(page_id, &page).expect("write page");
+            }
+            db.write_superblock().expect("write superblock");
+            db.sync().expect("sync");
+            first_page
+        };
+        tear(&path, first_page * PAGE_SIZE as u64 + 100, b"torn");
+
+        let db = DatabaseFile::open(&path, test_pool()).expect("open db");
+        assert_eq!(db.torn_pages().expect("scan"), vec![first_page]);
+        assert!(!db.superblock_is_torn().expect("check superblock"));
+        assert!(matches!(
+            db.read_page_at(first_page),
+            Err(FileError::CorruptPage {
+                page_id,
+                error: PageError::ChecksumMismatch { .. },
+            }) if page_id == first_page
+        ));
+        let intact = db.read_page_at(first_page + 1).expect("read page");
+        assert_eq!(intact.read_bytes(PageHeader::SIZE, 5), b"whole");
+    }
+
+    #[test]
+    fn test_torn_superblock_is_read_from_its_copy() {
+        let dir = tempdir().expect("create temp dir");
+        let path = dir.path().join("test.db");
+        {
+            let mut db = DatabaseFile::create(&path, test_pool()).expect("create db");
+            db.superblock_mut().next_txn_id = 42;
+            db.write_superblock().expect("write superblock");
+            db.sync().expect("sync");
+        }
+        // Only the first bytes of a later superblock write reached the disk
+        tear(&path, 24, &7u64.to_le_bytes());
+
+        assert!(matches!(
+            DatabaseFile::open(&path, test_pool()),
+            Err(FileError::Superblock(
+                SuperblockError::ChecksumMismatch { .. }
+            ))
+        ));
+        let db = DatabaseFile::open_to_copy(&path, test_pool()).expect("open to copy");
+        assert!(db.superblock_is_torn().expect("check superblock"));
+        assert_eq!(db.torn_pages().expect("scan"), vec![0]);
+        assert_eq!(db.superblock().next_txn_id, 42);
+        assert_eq!(db.total_pages(), 1);
+    }
 }
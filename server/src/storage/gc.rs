@@ -14,8 +14,15 @@
 //!
 //! The GC task is spawned by the database registry when a database is opened.
 //! It processes tombstones after each commit that contains deletes.
+//!
+//! # Expiry
+//!
+//! The task also wakes every `expiry_interval` to sweep expired triples. The
+//! sweep tombstones them in a regular transaction, which broadcasts `Delete`
+//! changes and signals the task again to reclaim the new tombstones.
 
 use std::sync::{Arc, RwLock, Weak};
+use std::time::Duration;
 
 use tokio::sync::Notify;
 
@@ -24,19 +31,26 @@ use crate::storage::Database;
 /// Configuration for the garbage collector.
 #[derive(Debug, Clone, Copy)]
 pub struct GcConfig {
-    /// Maximum number of tombstones to process per tick.
+    /// Maximum number of tombstones to process per tick, and of expired
+    /// triples to tombstone per sweep.
     pub batch_size: usize,
+    /// How often to sweep for expired triples when no GC work is signaled.
+    pub expiry_interval: Duration,
 }
 
 impl Default for GcConfig {
     fn default() -> Self {
-        Self { batch_size: 100 }
+        Self {
+            batch_size: 100,
+            expiry_interval: Duration::from_secs(1),
+        }
     }
 }
 
 /// Spawn a background GC task for a database.
 ///
 /// The task waits for signals on `notify` and processes tombstones in batches.
+/// It also wakes every `config.expiry_interval` to sweep expired triples.
 /// It exits cleanly when the database is dropped (weak reference becomes invalid).
 ///
 /// # Arguments
@@ -50,6 +64,7 @@ impl Default for GcConfig {
 /// # Invariants
 /// - Uses `Weak` reference to prevent reference cycles
 /// - Processes at most `config.batch_size` tombstones per tick
+/// - Tombstones at most `config.batch_size` expired triples per sweep
 /// - Exits cleanly when database is dropped
 pub fn spawn_gc_task(
     database: Weak<RwLock<Database>>,
@@ -66,8 +81,9 @@ pub fn spawn_gc_task(
 /// Runs until the database is dropped or the task is cancelled.
 async fn gc_loop(database: Weak<RwLock<Database>>, notify: Arc<Notify>, config: GcConfig) {
     loop {
-        // Wait for notification that there's work to do
-        notify.notified().await;
+        // Wait for notification that there's work to do, or for the next
+        // expiry sweep
+        let _ = tokio::time::timeout(config.expiry_interval, notify.notified()).await;
 
         // Try to upgrade the weak reference
         let Some(db_arc) = database.upgrade() else {
@@ -75,21 +91,32 @@ async fn gc_loop(database: Weak<RwLock<Database>>, notify: Arc<Notify>, config:
             break;
         };
 
-        // Process one batch of tombstones
+        // Sweep one batch of expired triples, then process one batch of tombstones
         // We acquire the write lock, process, then release it to allow other operations
-        let result = {
+        let (expiry, result) = {
             let Ok(mut db) = db_arc.write() else {
                 // Lock was poisoned, exit the task
                 eprintln!("GC error: database lock poisoned");
                 break;
             };
-            db.gc_tick(config.batch_size)
+            (
+                db.expire_triples(config.batch_size),
+                db.gc_tick(config.batch_size),
+            )
         };
 
+        match expiry {
+            // The sweep's commit signals us to reclaim its tombstones; only a
+            // sweep cut short by the batch size needs another wakeup
+            Ok(sweep) if sweep.more_expired => notify.notify_one(),
+            Ok(_) => {}
+            Err(e) => eprintln!("GC expiry error: {e}"),
+        }
+
         match result {
             Ok(tick_result) => {
                 // If there are more tombstones remaining, signal ourselves to continue
-                if tick_result.tombstones_remaining > 0 && tick_result.records_removed > 0 {
+                if tick_result.tombstones_remaining > 0 && tick_result.tombstones_processed > 0 {
                     notify.notify_one();
                 }
             }
@@ -107,7 +134,7 @@ mod tests {
     use super::*;
     use crate::storage::Database;
     use crate::storage::buffer_pool::BufferPool;
-    use std::time::Duration;
+    use crate::types::{AttributeId, EntityId, TripleValue};
     use tempfile::tempdir;
 
     fn test_pool() -> Arc<BufferPool> {
@@ -148,9 +175,56 @@ mod tests {
         );
     }
 
+    #[tokio::test]
+    async fn test_gc_task_sweeps_expired_triples() {
+        let dir = tempdir().expect("create temp dir");
+        let path = dir.path().join("test.db");
+        let mut db = Database::create(&path, test_pool()).expect("create db");
+        let entity_id = EntityId([1u8; 16]);
+        let attribute_id = AttributeId([2u8; 16]);
+        let mut txn = db.begin(0).expect("begin");
+        txn.insert_with_expiry(entity_id, attribute_id, TripleValue::Null, 1)
+            .expect("insert_with_expiry");
+        txn.commit().expect("commit");
+
+        let notify = db.gc_notify();
+        let db_arc = Arc::new(RwLock::new(db));
+        let config = GcConfig {
+            expiry_interval: Duration::from_millis(10),
+            ..GcConfig::default()
+        };
+        let handle = spawn_gc_task(Arc::downgrade(&db_arc), notify, config);
+
+        // No commit signals the task; the expiry interval wakes it
+        let reclaimed = tokio::time::timeout(Duration::from_secs(5), async {
+            loop {
+                let remaining = {
+                    let mut db = db_arc.write().expect("lock should not be poisoned");
+                    let mut txn = db.begin(0).expect("begin");
+                    let count = txn.count().expect("count");
+                    txn.abort();
+                    drop(db);
+                    count
+                };
+                if remaining == 0 {
+                    break;
+                }
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        })
+        .await;
+
+        assert!(
+            reclaimed.is_ok(),
+            "GC task should sweep and reclaim the expired triple"
+        );
+        handle.abort();
+    }
+
     #[tokio::test]
     async fn test_gc_config_default() {
         let config = GcConfig::default();
         assert_eq!(config.batch_size, 100);
+        assert_eq!(config.expiry_interval, Duration::from_secs(1));
     }
 }
@@ -1,45 +1,99 @@
 //! Attribute index implementation.
 //!
 //! The attribute index maps `attribute_id` -> list of `entity_id`s.
-//! This enables efficient queries like "find all entities with attribute X".
+//! This enables efficient queries like "find all entities with attribute X",
+//! and, through its value entries, "find all entities whose X is in a range".
 //!
 //! # Key Format
 //!
-//! Keys are 32 bytes: `(attribute_id: [u8; 16], entity_id: [u8; 16])`
+//! Keys are 40 bytes: `(attribute_id: [u8; 16], entity_id: [u8; 16], value_hash: u64)`
+//!
+//! The value hash is the triple's key in the primary index: 0 for a
+//! cardinality-one attribute, so each value of a cardinality-many attribute
+//! has its own entry. Scans yield each entity once however many values it
+//! has.
 //!
 //! This format allows:
-//! - Point lookup: check if (attribute, entity) pair exists
+//! - Point lookup: check if (attribute, entity, value hash) exists
 //! - Attribute scan: iterate all entities with a given attribute
 //!
 //! # Value Format
 //!
-//! Values are minimal (just a marker byte) since the key contains all information.
-//! The value stores the `created_txn` for MVCC visibility.
+//! Each entry stores the `created_txn` and `deleted_txn` for MVCC visibility,
+//! then the sort key of the triple's value. The transaction IDs are big-endian,
+//! so an entry never starts with the B-tree's overflow marker byte.
+//!
+//! # Value Entries
+//!
+//! A second B-tree holds the same pairs ordered by value, so a range scan
+//! seeks to its lower bound and stops once it passes its upper bound. Its keys
+//! are `(attribute_id: [u8; 16], sort_key: [u8; 9], entity_hash: [u8; 8])`,
+//! zero-padded to the key size:
+//!
+//! - The sort key is the value's type byte, then 8 bytes that order like the
+//!   value: the order-preserving bits of a Number, the first 8 bytes of a
+//!   String, Bytes or Ref (zero-padded), or the Boolean byte
+//! - The entity hash is a hash of the entity ID and value hash, so entities
+//!   with the same value get distinct keys however alike their IDs are
+//!
+//! Triples whose keys collide share one entry, which lists each entity and
+//! value hash with its MVCC metadata and the start of its value (up to
+//! `MAX_VALUE_PREFIX_SIZE` bytes of a String or Bytes value, or a Ref's
+//! entity ID) for comparisons the sort key cannot settle.
+
+use std::collections::{BTreeMap, HashMap};
+use std::ops::Bound;
 
 use crate::storage::btree::{BTree, BTreeError, KEY_SIZE, Key};
-#[cfg(unix)]
-use crate::storage::btree::{BTreeReader, BTreeReaderIterator};
+use crate::storage::btree::{BTreeIterator, BTreeReader, BTreeReaderIterator};
 use crate::storage::file::DatabaseFile;
 use crate::storage::page::PageId;
-use crate::types::{AttributeId, EntityId, TxnId};
+use crate::types::{AttributeId, EntityId, TripleValue, TxnId, ValueRange, ValueType};
 
-/// Marker value size: just the `created_txn` (8 bytes) and `deleted_txn` (8 bytes).
-const ENTRY_VALUE_SIZE: usize = 16;
+/// Size of a value's sort key: a type byte and 8 ordered bytes.
+const SORT_KEY_SIZE: usize = 9;
+
+/// Entry value size: `created_txn` (8 bytes), `deleted_txn` (8 bytes), and the
+/// value's sort key.
+const ENTRY_VALUE_SIZE: usize = 16 + SORT_KEY_SIZE;
+
+/// Bytes of the entity hash after the sort key of a value entry key.
+const ENTITY_HASH_SIZE: usize = 8;
+
+/// Most bytes of a String a value entry keeps for comparisons.
+pub const MAX_VALUE_PREFIX_SIZE: usize = 64;
+
+/// Most entities one value entry can list. Keeps the count byte below the
+/// overflow marker.
+const MAX_ENTITIES_PER_VALUE_ENTRY: usize = 254;
+
+/// A value's sort key.
+type SortKey = [u8; SORT_KEY_SIZE];
 
 /// Attribute index for efficient attribute-based queries.
 ///
-/// Maps `(attribute_id, entity_id)` -> MVCC metadata.
+/// Maps `(attribute_id, entity_id, value_hash)` -> MVCC metadata, with value entries
+/// ordered by `(attribute_id, value)` for range scans.
 pub struct AttributeIndex<'a> {
     tree: BTree<'a>,
+    value_root_page: PageId,
 }
 
 impl<'a> AttributeIndex<'a> {
     /// Create or open an attribute index.
     ///
-    /// If `root_page` is 0, creates a new empty index.
-    pub fn new(file: &'a mut DatabaseFile, root_page: PageId) -> Result<Self, AttributeIndexError> {
+    /// If `root_page` is 0, creates a new empty index. If `value_root_page` is
+    /// 0, the value entries are created on the first write.
+    pub fn new(
+        file: &'a mut DatabaseFile,
+        root_page: PageId,
+        value_root_page: PageId,
+    ) -> Result<Self, AttributeIndexError> {
         let tree = BTree::new(file, root_page)?;
-        Ok(Self { tree })
+        Ok(Self {
+            tree,
+            value_root_page,
+        })
     }
 
     /// Get the root page ID.
@@ -48,43 +102,40 @@ impl<'a> AttributeIndex<'a> {
         self.tree.root_page()
     }
 
+    /// Get the root page ID of the value entries, or 0 if none were written.
+    #[must_use]
+    pub const fn value_root_page(&self) -> PageId {
+        self.value_root_page
+    }
+
     /// Get mutable access to the underlying database file.
     pub fn file_mut(&mut self) -> &mut DatabaseFile {
         self.tree.file_mut()
     }
 
-    /// Check if an (attribute, entity) pair exists in the index.
+    /// Check if an (attribute, entity, value hash) entry exists in the index.
     pub fn contains(
         &mut self,
         attribute_id: &AttributeId,
         entity_id: &EntityId,
+        value_hash: u64,
     ) -> Result<bool, AttributeIndexError> {
-        let key = make_attribute_key(attribute_id, entity_id);
+        let key = make_attribute_key(attribute_id, entity_id, value_hash);
         Ok(self.tree.get(&key)?.is_some())
     }
 
-    /// Get the MVCC metadata for an (attribute, entity) pair.
+    /// Get the MVCC metadata for an (attribute, entity, value hash) entry.
     ///
     /// Returns `(created_txn, deleted_txn)` if the entry exists.
     pub fn get(
         &mut self,
         attribute_id: &AttributeId,
         entity_id: &EntityId,
+        value_hash: u64,
     ) -> Result<Option<(TxnId, TxnId)>, AttributeIndexError> {
-        let key = make_attribute_key(attribute_id, entity_id);
-        match self.tree.get(&key)? {
-            Some(value) if value.len() >= ENTRY_VALUE_SIZE => {
-                let created_txn = u64::from_le_bytes([
-                    value[0], value[1], value[2], value[3], value[4], value[5], value[6], value[7],
-                ]);
-                let deleted_txn = u64::from_le_bytes([
-                    value[8], value[9], value[10], value[11], value[12], value[13], value[14],
-                    value[15],
-                ]);
-                Ok(Some((created_txn, deleted_txn)))
-            }
-            _ => Ok(None),
-        }
+        Ok(self
+            .get_entry(attribute_id, entity_id, value_hash)?
+            .map(|entry| (entry.created_txn, entry.deleted_txn)))
     }
 
     /// Check if an entry is visible to a given transaction.
@@ -92,62 +143,214 @@ impl<'a> AttributeIndex<'a> {
         &mut self,
         attribute_id: &AttributeId,
         entity_id: &EntityId,
+        value_hash: u64,
         snapshot_txn: TxnId,
     ) -> Result<bool, AttributeIndexError> {
-        match self.get(attribute_id, entity_id)? {
-            Some((created_txn, deleted_txn)) => {
-                let visible =
-                    created_txn <= snapshot_txn && (deleted_txn == 0 || deleted_txn > snapshot_txn);
-                Ok(visible)
-            }
-            None => Ok(false),
-        }
+        Ok(self
+            .get_entry(attribute_id, entity_id, value_hash)?
+            .is_some_and(|entry| entry.is_visible(snapshot_txn)))
     }
 
-    /// Insert an (attribute, entity) pair into the index.
+    /// Insert an (attribute, entity, value hash) entry with its value into
+    /// the index.
+    ///
+    /// Replaces any earlier entry with the same key, such as a deleted one.
     pub fn insert(
         &mut self,
         attribute_id: &AttributeId,
         entity_id: &EntityId,
+        value_hash: u64,
+        value: &TripleValue,
+        created_txn: TxnId,
+    ) -> Result<(), AttributeIndexError> {
+        if let Some(old) = self.get_entry(attribute_id, entity_id, value_hash)? {
+            self.remove_value_entry(attribute_id, &old.sort_key, entity_id, value_hash)?;
+        }
+        let entry = Entry {
+            created_txn,
+            deleted_txn: 0,
+            sort_key: sort_key(value),
+        };
+        self.tree.insert(
+            make_attribute_key(attribute_id, entity_id, value_hash),
+            entry.to_bytes(),
+        )?;
+        self.put_value_entry(
+            attribute_id,
+            &entry.sort_key,
+            ValueEntry::new(*entity_id, value_hash, created_txn, 0, value),
+        )
+    }
+
+    /// Insert many (attribute, entity, value hash) entries with their values,
+    /// in order, as `insert` would one at a time.
+    ///
+    /// Both trees are read and written in runs (see `BTree::insert_many`),
+    /// so entries sorted by attribute and entity are cheapest.
+    pub fn insert_many<'v>(
+        &mut self,
+        entries: impl IntoIterator<Item = (AttributeId, EntityId, u64, &'v TripleValue)>,
         created_txn: TxnId,
     ) -> Result<(), AttributeIndexError> {
-        let key = make_attribute_key(attribute_id, entity_id);
-        let value = make_entry_value(created_txn, 0);
-        self.tree.insert(key, value)?;
+        let entries: Vec<_> = entries.into_iter().collect();
+        let keys: Vec<Key> = entries
+            .iter()
+            .map(|(attribute_id, entity_id, value_hash, _)| {
+                make_attribute_key(attribute_id, entity_id, *value_hash)
+            })
+            .collect();
+        let stored = self.tree.get_many(&keys)?;
+
+        // Sort keys this run writes, so a repeated entry replaces its own
+        // earlier listing rather than the stored one
+        let mut written: HashMap<Key, SortKey> = HashMap::new();
+        let mut writes = Vec::with_capacity(entries.len());
+        // Listings to drop (`None`) or add, by value entry key
+        let mut listings: BTreeMap<Key, Vec<(EntityId, u64, Option<ValueEntry>)>> = BTreeMap::new();
+        for ((attribute_id, entity_id, value_hash, value), (key, stored)) in
+            entries.into_iter().zip(keys.into_iter().zip(stored))
+        {
+            let old_sort_key = written.get(&key).copied().or_else(|| {
+                stored
+                    .as_deref()
+                    .and_then(Entry::from_bytes)
+                    .map(|old| old.sort_key)
+            });
+            if let Some(old_sort_key) = old_sort_key {
+                listings
+                    .entry(make_value_key(
+                        &attribute_id,
+                        &old_sort_key,
+                        &entity_id,
+                        value_hash,
+                    ))
+                    .or_default()
+                    .push((entity_id, value_hash, None));
+            }
+            let entry = Entry {
+                created_txn,
+                deleted_txn: 0,
+                sort_key: sort_key(value),
+            };
+            written.insert(key, entry.sort_key);
+            writes.push((key, entry.to_bytes()));
+            listings
+                .entry(make_value_key(
+                    &attribute_id,
+                    &entry.sort_key,
+                    &entity_id,
+                    value_hash,
+                ))
+                .or_default()
+                .push((
+                    entity_id,
+                    value_hash,
+                    Some(ValueEntry::new(
+                        entity_id,
+                        value_hash,
+                        created_txn,
+                        0,
+                        value,
+                    )),
+                ));
+        }
+        self.tree.insert_many(writes)?;
+
+        let value_keys: Vec<Key> = listings.keys().copied().collect();
+        let mut values = BTree::new(self.tree.file_mut(), self.value_root_page)?;
+        let stored = values.get_many(&value_keys)?;
+        let mut puts = Vec::new();
+        let mut removes = Vec::new();
+        for ((key, changes), stored) in listings.into_iter().zip(stored) {
+            let existed = stored.is_some();
+            let mut value_entries = match stored {
+                Some(bytes) => decode_value_entries(&bytes)?,
+                None => Vec::new(),
+            };
+            for (entity_id, value_hash, listing) in changes {
+                value_entries.retain(|entry| !entry.lists(&entity_id, value_hash));
+                value_entries.extend(listing);
+            }
+            if !value_entries.is_empty() {
+                puts.push((key, encode_value_entries(&value_entries)?));
+            } else if existed {
+                removes.push(key);
+            }
+        }
+        values.insert_many(puts)?;
+        for key in removes {
+            values.remove(&key)?;
+        }
+        self.value_root_page = values.root_page();
         Ok(())
     }
 
-    /// Mark an (attribute, entity) pair as deleted.
+    /// Change the value of an (attribute, entity, value hash) entry, keeping
+    /// its MVCC metadata.
+    ///
+    /// Returns `false` if the entry is not in the index.
+    pub fn update_value(
+        &mut self,
+        attribute_id: &AttributeId,
+        entity_id: &EntityId,
+        value_hash: u64,
+        value: &TripleValue,
+    ) -> Result<bool, AttributeIndexError> {
+        let Some(old) = self.get_entry(attribute_id, entity_id, value_hash)? else {
+            return Ok(false);
+        };
+        self.remove_value_entry(attribute_id, &old.sort_key, entity_id, value_hash)?;
+
+        let entry = Entry {
+            sort_key: sort_key(value),
+            ..old
+        };
+        self.tree.insert(
+            make_attribute_key(attribute_id, entity_id, value_hash),
+            entry.to_bytes(),
+        )?;
+        self.put_value_entry(
+            attribute_id,
+            &entry.sort_key,
+            ValueEntry::new(
+                *entity_id,
+                value_hash,
+                entry.created_txn,
+                entry.deleted_txn,
+                value,
+            ),
+        )?;
+        Ok(true)
+    }
+
+    /// Mark an (attribute, entity, value hash) entry as deleted.
     pub fn mark_deleted(
         &mut self,
         attribute_id: &AttributeId,
         entity_id: &EntityId,
+        value_hash: u64,
         deleted_txn: TxnId,
     ) -> Result<bool, AttributeIndexError> {
-        let key = make_attribute_key(attribute_id, entity_id);
-
         // Get existing entry to preserve created_txn
-        let Some(existing) = self.tree.get(&key)? else {
+        let Some(existing) = self.get_entry(attribute_id, entity_id, value_hash)? else {
             return Ok(false);
         };
 
-        if existing.len() < ENTRY_VALUE_SIZE {
-            return Ok(false);
-        }
-
-        let created_txn = u64::from_le_bytes([
-            existing[0],
-            existing[1],
-            existing[2],
-            existing[3],
-            existing[4],
-            existing[5],
-            existing[6],
-            existing[7],
-        ]);
-
-        let value = make_entry_value(created_txn, deleted_txn);
-        self.tree.insert(key, value)?;
+        let entry = Entry {
+            deleted_txn,
+            ..existing
+        };
+        self.tree.insert(
+            make_attribute_key(attribute_id, entity_id, value_hash),
+            entry.to_bytes(),
+        )?;
+        self.mark_value_entry_deleted(
+            attribute_id,
+            &entry.sort_key,
+            entity_id,
+            value_hash,
+            deleted_txn,
+        )?;
         Ok(true)
     }
 
@@ -156,8 +359,13 @@ impl<'a> AttributeIndex<'a> {
         &mut self,
         attribute_id: &AttributeId,
         entity_id: &EntityId,
+        value_hash: u64,
     ) -> Result<bool, AttributeIndexError> {
-        let key = make_attribute_key(attribute_id, entity_id);
+        let Some(existing) = self.get_entry(attribute_id, entity_id, value_hash)? else {
+            return Ok(false);
+        };
+        self.remove_value_entry(attribute_id, &existing.sort_key, entity_id, value_hash)?;
+        let key = make_attribute_key(attribute_id, entity_id, value_hash);
         Ok(self.tree.remove(&key)?.is_some())
     }
 
@@ -169,13 +377,14 @@ impl<'a> AttributeIndex<'a> {
         attribute_id: &AttributeId,
     ) -> Result<AttributeScanIterator<'_>, AttributeIndexError> {
         // Start key: (attribute_id, 0x00...)
-        let start_key = make_attribute_key(attribute_id, &EntityId::default());
+        let start_key = make_attribute_key(attribute_id, &EntityId::default(), 0);
         let cursor = self.tree.iter_from(&start_key)?;
 
         Ok(AttributeScanIterator {
             cursor,
             attribute_id: *attribute_id,
             snapshot_txn: None,
+            last_entity_id: None,
             done: false,
         })
     }
@@ -186,58 +395,172 @@ impl<'a> AttributeIndex<'a> {
         attribute_id: &AttributeId,
         snapshot_txn: TxnId,
     ) -> Result<AttributeScanIterator<'_>, AttributeIndexError> {
-        let start_key = make_attribute_key(attribute_id, &EntityId::default());
+        let start_key = make_attribute_key(attribute_id, &EntityId::default(), 0);
         let cursor = self.tree.iter_from(&start_key)?;
 
         Ok(AttributeScanIterator {
             cursor,
             attribute_id: *attribute_id,
             snapshot_txn: Some(snapshot_txn),
+            last_entity_id: None,
             done: false,
         })
     }
 
+    /// Scan the visible entities whose value of an attribute is in `range`.
+    ///
+    /// Seeks to the range's lower bound in the value entries and stops once
+    /// past its upper bound, rather than reading every entity with the
+    /// attribute. See `AttributeRangeIterator` for the order and exactness of
+    /// the entities it yields.
+    pub fn scan_attribute_range(
+        &mut self,
+        attribute_id: &AttributeId,
+        range: ValueRange,
+        snapshot_txn: TxnId,
+    ) -> Result<AttributeRangeIterator<'_>, AttributeIndexError> {
+        let scan = RangeScan::new(*attribute_id, range, snapshot_txn);
+        let cursor = if self.value_root_page == 0 {
+            None
+        } else {
+            let values = BTree::new(self.tree.file_mut(), self.value_root_page)?;
+            Some(values.into_iter_from(&scan.start_key())?)
+        };
+        Ok(AttributeRangeIterator { cursor, scan })
+    }
+
     /// Count all entries in the index.
     pub fn count(&mut self) -> Result<usize, AttributeIndexError> {
         Ok(self.tree.count()?)
     }
+
+    /// Get the entry for an (attribute, entity, value hash) key.
+    fn get_entry(
+        &mut self,
+        attribute_id: &AttributeId,
+        entity_id: &EntityId,
+        value_hash: u64,
+    ) -> Result<Option<Entry>, AttributeIndexError> {
+        let key = make_attribute_key(attribute_id, entity_id, value_hash);
+        Ok(self.tree.get(&key)?.as_deref().and_then(Entry::from_bytes))
+    }
+
+    /// Add a triple to the value entry for `sort_key`, replacing any earlier
+    /// listing of it.
+    fn put_value_entry(
+        &mut self,
+        attribute_id: &AttributeId,
+        sort_key: &SortKey,
+        value_entry: ValueEntry,
+    ) -> Result<(), AttributeIndexError> {
+        let key = make_value_key(
+            attribute_id,
+            sort_key,
+            &value_entry.entity_id,
+            value_entry.value_hash,
+        );
+        let mut values = BTree::new(self.tree.file_mut(), self.value_root_page)?;
+        let mut entries = match values.get(&key)? {
+            Some(bytes) => decode_value_entries(&bytes)?,
+            None => Vec::new(),
+        };
+        entries.retain(|entry| !entry.lists(&value_entry.entity_id, value_entry.value_hash));
+        entries.push(value_entry);
+        values.insert(key, encode_value_entries(&entries)?)?;
+        self.value_root_page = values.root_page();
+        Ok(())
+    }
+
+    /// Mark a triple deleted in the value entry for `sort_key`.
+    fn mark_value_entry_deleted(
+        &mut self,
+        attribute_id: &AttributeId,
+        sort_key: &SortKey,
+        entity_id: &EntityId,
+        value_hash: u64,
+        deleted_txn: TxnId,
+    ) -> Result<(), AttributeIndexError> {
+        if self.value_root_page == 0 {
+            return Ok(());
+        }
+        let key = make_value_key(attribute_id, sort_key, entity_id, value_hash);
+        let mut values = BTree::new(self.tree.file_mut(), self.value_root_page)?;
+        let Some(bytes) = values.get(&key)? else {
+            return Ok(());
+        };
+        let mut entries = decode_value_entries(&bytes)?;
+        for entry in &mut entries {
+            if entry.lists(entity_id, value_hash) {
+                entry.deleted_txn = deleted_txn;
+            }
+        }
+        values.insert(key, encode_value_entries(&entries)?)?;
+        self.value_root_page = values.root_page();
+        Ok(())
+    }
+
+    /// Remove a triple from the value entry for `sort_key`, and the entry
+    /// itself once it lists no triples.
+    fn remove_value_entry(
+        &mut self,
+        attribute_id: &AttributeId,
+        sort_key: &SortKey,
+        entity_id: &EntityId,
+        value_hash: u64,
+    ) -> Result<(), AttributeIndexError> {
+        if self.value_root_page == 0 {
+            return Ok(());
+        }
+        let key = make_value_key(attribute_id, sort_key, entity_id, value_hash);
+        let mut values = BTree::new(self.tree.file_mut(), self.value_root_page)?;
+        let Some(bytes) = values.get(&key)? else {
+            return Ok(());
+        };
+        let mut entries = decode_value_entries(&bytes)?;
+        entries.retain(|entry| !entry.lists(entity_id, value_hash));
+        if entries.is_empty() {
+            values.remove(&key)?;
+        } else {
+            values.insert(key, encode_value_entries(&entries)?)?;
+        }
+        self.value_root_page = values.root_page();
+        Ok(())
+    }
 }
 
 /// Read-only attribute index accessor for concurrent snapshot reads.
-#[cfg(unix)]
 pub struct AttributeIndexReader<'a> {
     tree: BTreeReader<'a>,
+    file: &'a DatabaseFile,
+    value_root_page: PageId,
 }
 
-#[cfg(unix)]
 impl<'a> AttributeIndexReader<'a> {
     /// Create a new read-only attribute index accessor.
     #[must_use]
-    pub const fn new(file: &'a DatabaseFile, root_page: PageId) -> Self {
+    pub const fn new(file: &'a DatabaseFile, root_page: PageId, value_root_page: PageId) -> Self {
         let tree = BTreeReader::new(file, root_page);
-        Self { tree }
+        Self {
+            tree,
+            file,
+            value_root_page,
+        }
     }
 
-    /// Get the MVCC metadata for an (attribute, entity) pair.
+    /// Get the MVCC metadata for an (attribute, entity, value hash) entry.
     pub fn get(
         &self,
         attribute_id: &AttributeId,
         entity_id: &EntityId,
+        value_hash: u64,
     ) -> Result<Option<(TxnId, TxnId)>, AttributeIndexError> {
-        let key = make_attribute_key(attribute_id, entity_id);
-        match self.tree.get(&key)? {
-            Some(value) if value.len() >= ENTRY_VALUE_SIZE => {
-                let created_txn = u64::from_le_bytes([
-                    value[0], value[1], value[2], value[3], value[4], value[5], value[6], value[7],
-                ]);
-                let deleted_txn = u64::from_le_bytes([
-                    value[8], value[9], value[10], value[11], value[12], value[13], value[14],
-                    value[15],
-                ]);
-                Ok(Some((created_txn, deleted_txn)))
-            }
-            _ => Ok(None),
-        }
+        let key = make_attribute_key(attribute_id, entity_id, value_hash);
+        Ok(self
+            .tree
+            .get(&key)?
+            .as_deref()
+            .and_then(Entry::from_bytes)
+            .map(|entry| (entry.created_txn, entry.deleted_txn)))
     }
 
     /// Check if an entry is visible to a given transaction.
@@ -245,9 +568,10 @@ impl<'a> AttributeIndexReader<'a> {
         &self,
         attribute_id: &AttributeId,
         entity_id: &EntityId,
+        value_hash: u64,
         snapshot_txn: TxnId,
     ) -> Result<bool, AttributeIndexError> {
-        match self.get(attribute_id, entity_id)? {
+        match self.get(attribute_id, entity_id, value_hash)? {
             Some((created_txn, deleted_txn)) => {
                 let visible =
                     created_txn <= snapshot_txn && (deleted_txn == 0 || deleted_txn > snapshot_txn);
@@ -262,34 +586,139 @@ impl<'a> AttributeIndexReader<'a> {
         &self,
         attribute_id: &AttributeId,
         snapshot_txn: TxnId,
-    ) -> Result<AttributeScanReaderIterator<'_>, AttributeIndexError> {
-        let start_key = make_attribute_key(attribute_id, &EntityId::default());
+    ) -> Result<AttributeScanReaderIterator<'a>, AttributeIndexError> {
+        let start_key = make_attribute_key(attribute_id, &EntityId::default(), 0);
         let cursor = self.tree.iter_from(&start_key)?;
 
         Ok(AttributeScanReaderIterator {
             cursor,
             attribute_id: *attribute_id,
             snapshot_txn: Some(snapshot_txn),
+            last_entity_id: None,
             done: false,
         })
     }
 
+    /// Scan the visible entities whose value of an attribute is in `range`,
+    /// as `AttributeIndex::scan_attribute_range` does.
+    pub fn scan_attribute_range(
+        &self,
+        attribute_id: &AttributeId,
+        range: ValueRange,
+        snapshot_txn: TxnId,
+    ) -> Result<AttributeRangeReaderIterator<'a>, AttributeIndexError> {
+        let scan = RangeScan::new(*attribute_id, range, snapshot_txn);
+        let cursor = if self.value_root_page == 0 {
+            None
+        } else {
+            let values = BTreeReader::new(self.file, self.value_root_page);
+            Some(values.iter_from(&scan.start_key())?)
+        };
+        Ok(AttributeRangeReaderIterator { cursor, scan })
+    }
+
+    /// Get the smallest attribute ID in the index after `start_after`, or the
+    /// smallest overall if `start_after` is `None`.
+    ///
+    /// Seeks past the entries of `start_after` rather than reading them. The
+    /// attribute returned may have no entries visible to a given snapshot.
+    pub fn next_attribute(
+        &self,
+        start_after: Option<&AttributeId>,
+    ) -> Result<Option<AttributeId>, AttributeIndexError> {
+        // (start_after, 0xFF...) sorts after every other entity of `start_after`
+        let start_key = start_after.map_or_else(
+            || make_attribute_key(&AttributeId::default(), &EntityId::default(), 0),
+            |attribute_id| make_attribute_key(attribute_id, &EntityId([0xFF; 16]), u64::MAX),
+        );
+        let mut cursor = self.tree.iter_from(&start_key)?;
+        while let Some((key, _value)) = cursor.next_entry()? {
+            let (attribute_id, _entity_id) = split_attribute_key(&key);
+            if start_after != Some(&attribute_id) {
+                return Ok(Some(attribute_id));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Get the first entity whose entry for an attribute was created or
+    /// deleted by a transaction after `snapshot_txn`, or `None` if none was.
+    ///
+    /// An entry deleted since is still found unless garbage collection has
+    /// removed it, which a snapshot pinned at `snapshot_txn` prevents.
+    pub fn first_written_after(
+        &self,
+        attribute_id: &AttributeId,
+        snapshot_txn: TxnId,
+    ) -> Result<Option<EntityId>, AttributeIndexError> {
+        let start_key = make_attribute_key(attribute_id, &EntityId::default(), 0);
+        let mut cursor = self.tree.iter_from(&start_key)?;
+        while let Some((key, value)) = cursor.next_entry()? {
+            let (entry_attribute_id, entity_id) = split_attribute_key(&key);
+            if entry_attribute_id != *attribute_id {
+                break;
+            }
+            if Entry::from_bytes(&value).is_some_and(|entry| {
+                entry.created_txn > snapshot_txn || entry.deleted_txn > snapshot_txn
+            }) {
+                return Ok(Some(entity_id));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Scan every (attribute, entity) pair that is not marked deleted, in key
+    /// order.
+    pub fn scan_live(&self) -> Result<LiveScanReaderIterator<'a>, AttributeIndexError> {
+        let cursor = self.tree.cursor()?;
+        Ok(LiveScanReaderIterator {
+            cursor,
+            last_pair: None,
+        })
+    }
+
     /// Count all entries in the index.
     pub fn count(&self) -> Result<usize, AttributeIndexError> {
         Ok(self.tree.count()?)
     }
 }
 
+/// Read-only iterator over every live (attribute, entity) pair.
+pub struct LiveScanReaderIterator<'a> {
+    cursor: BTreeReaderIterator<'a>,
+    /// The most recently yielded pair, used to skip its remaining values.
+    last_pair: Option<(AttributeId, EntityId)>,
+}
+
+impl LiveScanReaderIterator<'_> {
+    /// Get the next pair with an entry whose `deleted_txn` is 0.
+    ///
+    /// # Post-conditions
+    /// - Each pair is returned once, however many live values it has
+    pub fn next_pair(&mut self) -> Result<Option<(AttributeId, EntityId)>, AttributeIndexError> {
+        while let Some((key, value)) = self.cursor.next_entry()? {
+            let pair = split_attribute_key(&key);
+            if self.last_pair != Some(pair)
+                && Entry::from_bytes(&value).is_some_and(|entry| entry.deleted_txn == 0)
+            {
+                self.last_pair = Some(pair);
+                return Ok(Some(pair));
+            }
+        }
+        Ok(None)
+    }
+}
+
 /// Read-only iterator over entities with a specific attribute.
-#[cfg(unix)]
 pub struct AttributeScanReaderIterator<'a> {
     cursor: BTreeReaderIterator<'a>,
     attribute_id: AttributeId,
     snapshot_txn: Option<TxnId>,
+    /// The most recently yielded entity, used to skip its remaining values.
+    last_entity_id: Option<EntityId>,
     done: bool,
 }
 
-#[cfg(unix)]
 impl AttributeScanReaderIterator<'_> {
     /// Get the next entity ID with this attribute.
     pub fn next_entity(&mut self) -> Result<Option<EntityId>, AttributeIndexError> {
@@ -309,27 +738,18 @@ impl AttributeScanReaderIterator<'_> {
                 self.done = true;
                 return Ok(None);
             }
+            if self.last_entity_id == Some(entity_id) {
+                continue;
+            }
 
-            if let Some(snapshot_txn) = self.snapshot_txn {
-                if value.len() >= ENTRY_VALUE_SIZE {
-                    let created_txn = u64::from_le_bytes([
-                        value[0], value[1], value[2], value[3], value[4], value[5], value[6],
-                        value[7],
-                    ]);
-                    let deleted_txn = u64::from_le_bytes([
-                        value[8], value[9], value[10], value[11], value[12], value[13], value[14],
-                        value[15],
-                    ]);
-
-                    let visible = created_txn <= snapshot_txn
-                        && (deleted_txn == 0 || deleted_txn > snapshot_txn);
-
-                    if !visible {
-                        continue;
-                    }
-                }
+            if let Some(snapshot_txn) = self.snapshot_txn
+                && let Some(entry) = Entry::from_bytes(&value)
+                && !entry.is_visible(snapshot_txn)
+            {
+                continue;
             }
 
+            self.last_entity_id = Some(entity_id);
             return Ok(Some(entity_id));
         }
     }
@@ -337,9 +757,11 @@ impl AttributeScanReaderIterator<'_> {
 
 /// Iterator over entities with a specific attribute.
 pub struct AttributeScanIterator<'a> {
-    cursor: crate::storage::btree::BTreeIterator<'a>,
+    cursor: BTreeIterator<'a>,
     attribute_id: AttributeId,
     snapshot_txn: Option<TxnId>,
+    /// The most recently yielded entity, used to skip its remaining values.
+    last_entity_id: Option<EntityId>,
     done: bool,
 }
 
@@ -363,56 +785,512 @@ impl AttributeScanIterator<'_> {
                 self.done = true;
                 return Ok(None);
             }
+            if self.last_entity_id == Some(entity_id) {
+                continue;
+            }
 
             // Apply visibility filter if set
-            if let Some(snapshot_txn) = self.snapshot_txn {
-                if value.len() >= ENTRY_VALUE_SIZE {
-                    let created_txn = u64::from_le_bytes([
-                        value[0], value[1], value[2], value[3], value[4], value[5], value[6],
-                        value[7],
-                    ]);
-                    let deleted_txn = u64::from_le_bytes([
-                        value[8], value[9], value[10], value[11], value[12], value[13], value[14],
-                        value[15],
-                    ]);
-
-                    let visible = created_txn <= snapshot_txn
-                        && (deleted_txn == 0 || deleted_txn > snapshot_txn);
-
-                    if !visible {
-                        continue; // Skip non-visible entries
-                    }
-                }
+            if let Some(snapshot_txn) = self.snapshot_txn
+                && let Some(entry) = Entry::from_bytes(&value)
+                && !entry.is_visible(snapshot_txn)
+            {
+                continue; // Skip non-visible entries
             }
 
+            self.last_entity_id = Some(entity_id);
             return Ok(Some(entity_id));
         }
     }
 }
 
+/// Iterator over the triples whose value of an attribute is in a range.
+///
+/// Yields each triple's entity and value hash, in ascending value order, ties
+/// broken by entity ID. An entity with several values of a cardinality-many
+/// attribute in range is yielded once for each.
+/// Strings and Bytes values that share their first `MAX_VALUE_PREFIX_SIZE` bytes order by
+/// entity ID instead, and are yielded whenever the scan reaches them, even if
+/// the rest of the value falls outside the range: check their values if
+/// exact bounds matter.
+pub struct AttributeRangeIterator<'a> {
+    cursor: Option<BTreeIterator<'a>>,
+    scan: RangeScan,
+}
+
+impl AttributeRangeIterator<'_> {
+    /// Get the entity ID and value hash of the next triple whose value is in
+    /// the range.
+    pub fn next_triple(&mut self) -> Result<Option<(EntityId, u64)>, AttributeIndexError> {
+        let cursor = &mut self.cursor;
+        self.scan
+            .next_triple(|| cursor.as_mut().map_or(Ok(None), BTreeIterator::next_entry))
+    }
+}
+
+/// Read-only iterator over the triples whose value of an attribute is in a
+/// range, as `AttributeRangeIterator`.
+pub struct AttributeRangeReaderIterator<'a> {
+    cursor: Option<BTreeReaderIterator<'a>>,
+    scan: RangeScan,
+}
+
+impl AttributeRangeReaderIterator<'_> {
+    /// Get the entity ID and value hash of the next triple whose value is in
+    /// the range.
+    pub fn next_triple(&mut self) -> Result<Option<(EntityId, u64)>, AttributeIndexError> {
+        let cursor = &mut self.cursor;
+        self.scan.next_triple(|| {
+            cursor
+                .as_mut()
+                .map_or(Ok(None), BTreeReaderIterator::next_entry)
+        })
+    }
+}
+
+/// State of a range scan over value entries, shared by both iterators.
+struct RangeScan {
+    attribute_id: AttributeId,
+    range: ValueRange,
+    snapshot_txn: TxnId,
+    /// Sort keys past this one are outside the range.
+    stop_key: Option<SortKey>,
+    /// The first value entry of the next sort key group, read ahead.
+    lookahead: Option<(Key, Vec<u8>)>,
+    /// Matches of the current group, in descending order to pop from.
+    ready: Vec<(Vec<u8>, EntityId, u64)>,
+    done: bool,
+}
+
+impl RangeScan {
+    fn new(attribute_id: AttributeId, range: ValueRange, snapshot_txn: TxnId) -> Self {
+        let stop_key = match (&range.upper, &range.lower) {
+            (Bound::Included(upper) | Bound::Excluded(upper), _) => Some(sort_key(upper)),
+            // Without an upper bound, the range ends with the lower bound's type
+            (Bound::Unbounded, Bound::Included(lower) | Bound::Excluded(lower)) => {
+                let mut key = [0xFF; SORT_KEY_SIZE];
+                key[0] = lower.value_type() as u8;
+                Some(key)
+            }
+            (Bound::Unbounded, Bound::Unbounded) => None,
+        };
+        Self {
+            attribute_id,
+            range,
+            snapshot_txn,
+            stop_key,
+            lookahead: None,
+            ready: Vec::new(),
+            done: false,
+        }
+    }
+
+    /// The value entry key to start scanning from.
+    fn start_key(&self) -> Key {
+        let sort_key = match (&self.range.lower, &self.range.upper) {
+            (Bound::Included(lower) | Bound::Excluded(lower), _) => sort_key(lower),
+            // Without a lower bound, the range starts with the upper bound's type
+            (Bound::Unbounded, Bound::Included(upper) | Bound::Excluded(upper)) => {
+                let mut key = [0; SORT_KEY_SIZE];
+                key[0] = upper.value_type() as u8;
+                key
+            }
+            (Bound::Unbounded, Bound::Unbounded) => [0; SORT_KEY_SIZE],
+        };
+        let mut key = [0u8; KEY_SIZE];
+        key[..16].copy_from_slice(&self.attribute_id.0);
+        key[16..16 + SORT_KEY_SIZE].copy_from_slice(&sort_key);
+        key
+    }
+
+    /// Get the next matching triple, reading value entries with `next_entry`.
+    ///
+    /// Reads a whole group of entries with the same sort key before yielding
+    /// any of them, so the group can be put in value order.
+    fn next_triple(
+        &mut self,
+        mut next_entry: impl FnMut() -> Result<Option<(Key, Vec<u8>)>, BTreeError>,
+    ) -> Result<Option<(EntityId, u64)>, AttributeIndexError> {
+        loop {
+            if let Some((_prefix, entity_id, value_hash)) = self.ready.pop() {
+                return Ok(Some((entity_id, value_hash)));
+            }
+            if self.done {
+                return Ok(None);
+            }
+
+            let first = match self.lookahead.take() {
+                Some(entry) => Some(entry),
+                None => next_entry()?,
+            };
+            let Some((group_key, bytes)) = first else {
+                self.done = true;
+                continue;
+            };
+            let (attribute_id, group_sort_key) = split_value_key(&group_key);
+            if attribute_id != self.attribute_id
+                || self
+                    .stop_key
+                    .is_some_and(|stop_key| group_sort_key > stop_key)
+            {
+                self.done = true;
+                continue;
+            }
+
+            self.collect_matches(&group_sort_key, &bytes)?;
+            loop {
+                let Some((key, bytes)) = next_entry()? else {
+                    self.done = true;
+                    break;
+                };
+                if key[..16 + SORT_KEY_SIZE] != group_key[..16 + SORT_KEY_SIZE] {
+                    self.lookahead = Some((key, bytes));
+                    break;
+                }
+                self.collect_matches(&group_sort_key, &bytes)?;
+            }
+            self.ready.sort_by(|a, b| b.cmp(a));
+        }
+    }
+
+    /// Add the visible triples of a value entry whose values are in range.
+    fn collect_matches(
+        &mut self,
+        sort_key: &SortKey,
+        bytes: &[u8],
+    ) -> Result<(), AttributeIndexError> {
+        for entry in decode_value_entries(bytes)? {
+            let visible = entry.created_txn <= self.snapshot_txn
+                && (entry.deleted_txn == 0 || entry.deleted_txn > self.snapshot_txn);
+            if visible && self.in_range(sort_key, &entry.prefix, entry.truncated) {
+                self.ready
+                    .push((entry.prefix, entry.entity_id, entry.value_hash));
+            }
+        }
+        Ok(())
+    }
+
+    /// Whether a value with this sort key and prefix is in range.
+    ///
+    /// A truncated value counts as in range if its type matches, as only its
+    /// prefix is known.
+    fn in_range(&self, sort_key: &SortKey, prefix: &[u8], truncated: bool) -> bool {
+        if truncated {
+            return [&self.range.lower, &self.range.upper]
+                .into_iter()
+                .all(|bound| match bound {
+                    Bound::Included(value) | Bound::Excluded(value) => {
+                        value.value_type() as u8 == sort_key[0]
+                    }
+                    Bound::Unbounded => true,
+                });
+        }
+        value_from_sort_key(sort_key, prefix).is_some_and(|value| self.range.contains(&value))
+    }
+}
+
+/// MVCC metadata of an (attribute, entity) pair, and its value's sort key.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+struct Entry {
+    created_txn: TxnId,
+    deleted_txn: TxnId,
+    sort_key: SortKey,
+}
+
+impl Entry {
+    /// Check if the entry is visible to a given transaction.
+    const fn is_visible(&self, snapshot_txn: TxnId) -> bool {
+        self.created_txn <= snapshot_txn
+            && (self.deleted_txn == 0 || self.deleted_txn > snapshot_txn)
+    }
+
+    /// Serialize the entry.
+    fn to_bytes(self) -> Vec<u8> {
+        let mut value = Vec::with_capacity(ENTRY_VALUE_SIZE);
+        value.extend_from_slice(&self.created_txn.to_be_bytes());
+        value.extend_from_slice(&self.deleted_txn.to_be_bytes());
+        value.extend_from_slice(&self.sort_key);
+        value
+    }
+
+    /// Deserialize an entry, or `None` if it is too short.
+    fn from_bytes(bytes: &[u8]) -> Option<Self> {
+        if bytes.len() < ENTRY_VALUE_SIZE {
+            return None;
+        }
+        let mut sort_key = [0u8; SORT_KEY_SIZE];
+        sort_key.copy_from_slice(&bytes[16..ENTRY_VALUE_SIZE]);
+        Some(Self {
+            created_txn: read_txn(&bytes[..8]),
+            deleted_txn: read_txn(&bytes[8..16]),
+            sort_key,
+        })
+    }
+}
+
+/// One triple listed in a value entry.
+struct ValueEntry {
+    entity_id: EntityId,
+    /// The triple's value hash (see `Cardinality::value_hash`).
+    value_hash: u64,
+    created_txn: TxnId,
+    deleted_txn: TxnId,
+    /// The start of the value, for comparisons the sort key cannot settle.
+    prefix: Vec<u8>,
+    /// Whether `prefix` is only the start of a longer String or Bytes value.
+    truncated: bool,
+}
+
+impl ValueEntry {
+    /// List a triple with its value's prefix.
+    fn new(
+        entity_id: EntityId,
+        value_hash: u64,
+        created_txn: TxnId,
+        deleted_txn: TxnId,
+        value: &TripleValue,
+    ) -> Self {
+        let (prefix, truncated) = value_prefix(value);
+        Self {
+            entity_id,
+            value_hash,
+            created_txn,
+            deleted_txn,
+            prefix,
+            truncated,
+        }
+    }
+
+    /// Whether this lists the triple with `entity_id` and `value_hash`.
+    fn lists(&self, entity_id: &EntityId, value_hash: u64) -> bool {
+        self.entity_id == *entity_id && self.value_hash == value_hash
+    }
+}
+
+/// Bit of a value entry's prefix length byte set for a truncated prefix.
+const TRUNCATED_FLAG: u8 = 0x80;
+
+/// Serialize the triples of a value entry: a count byte, then for each its
+/// entity ID, value hash, `created_txn`, `deleted_txn`, prefix length byte
+/// (with `TRUNCATED_FLAG`) and prefix.
+fn encode_value_entries(entries: &[ValueEntry]) -> Result<Vec<u8>, AttributeIndexError> {
+    let count = u8::try_from(entries.len())
+        .ok()
+        .filter(|count| usize::from(*count) <= MAX_ENTITIES_PER_VALUE_ENTRY)
+        .ok_or(AttributeIndexError::ValueEntryFull)?;
+    let mut bytes = vec![count];
+    for entry in entries {
+        bytes.extend_from_slice(&entry.entity_id.0);
+        bytes.extend_from_slice(&entry.value_hash.to_be_bytes());
+        bytes.extend_from_slice(&entry.created_txn.to_be_bytes());
+        bytes.extend_from_slice(&entry.deleted_txn.to_be_bytes());
+        // Prefixes are at most MAX_VALUE_PREFIX_SIZE bytes, below the flag
+        #[allow(clippy::cast_possible_truncation)]
+        let length = entry.prefix.len() as u8;
+        bytes.push(if entry.truncated {
+            length | TRUNCATED_FLAG
+        } else {
+            length
+        });
+        bytes.extend_from_slice(&entry.prefix);
+    }
+    Ok(bytes)
+}
+
+/// Bytes of a listed triple before its prefix: entity ID, value hash,
+/// `created_txn`, `deleted_txn` and the prefix length byte.
+const VALUE_ENTRY_HEADER_SIZE: usize = 16 + 8 + 8 + 8 + 1;
+
+/// Deserialize the triples of a value entry.
+fn decode_value_entries(bytes: &[u8]) -> Result<Vec<ValueEntry>, AttributeIndexError> {
+    let (&count, mut rest) = bytes
+        .split_first()
+        .ok_or(AttributeIndexError::InvalidValueEntry)?;
+    let mut entries = Vec::with_capacity(usize::from(count));
+    for _ in 0..count {
+        if rest.len() < VALUE_ENTRY_HEADER_SIZE {
+            return Err(AttributeIndexError::InvalidValueEntry);
+        }
+        let mut entity_id = [0u8; 16];
+        entity_id.copy_from_slice(&rest[..16]);
+        let length = rest[VALUE_ENTRY_HEADER_SIZE - 1];
+        let prefix_len = usize::from(length & !TRUNCATED_FLAG);
+        let Some(prefix) = rest.get(VALUE_ENTRY_HEADER_SIZE..VALUE_ENTRY_HEADER_SIZE + prefix_len)
+        else {
+            return Err(AttributeIndexError::InvalidValueEntry);
+        };
+        entries.push(ValueEntry {
+            entity_id: EntityId(entity_id),
+            value_hash: read_txn(&rest[16..24]),
+            created_txn: read_txn(&rest[24..32]),
+            deleted_txn: read_txn(&rest[32..40]),
+            prefix: prefix.to_vec(),
+            truncated: length & TRUNCATED_FLAG != 0,
+        });
+        rest = &rest[VALUE_ENTRY_HEADER_SIZE + prefix_len..];
+    }
+    Ok(entries)
+}
+
+/// Read a big-endian transaction ID or value hash.
+///
+/// # Pre-conditions
+/// - `bytes` is 8 bytes long
+const fn read_txn(bytes: &[u8]) -> TxnId {
+    let mut txn = [0u8; 8];
+    txn.copy_from_slice(bytes);
+    TxnId::from_be_bytes(txn)
+}
+
+/// The sort key of a value: its type byte, then 8 bytes that order like it.
+///
+/// Numbers map `-0.0` to `0.0` and every NaN to one NaN, which sorts above
+/// infinity, matching `TripleValue::cmp_total`.
+fn sort_key(value: &TripleValue) -> SortKey {
+    let mut key = [0u8; SORT_KEY_SIZE];
+    key[0] = value.value_type() as u8;
+    match value {
+        TripleValue::Null => {}
+        TripleValue::Boolean(b) => key[1] = u8::from(*b),
+        TripleValue::Number(n) => {
+            let canonical = if n.is_nan() { f64::NAN } else { n + 0.0 };
+            key[1..].copy_from_slice(&ordered_bits(canonical).to_be_bytes());
+        }
+        TripleValue::String(s) => {
+            let len = s.len().min(SORT_KEY_SIZE - 1);
+            key[1..=len].copy_from_slice(&s.as_bytes()[..len]);
+        }
+        TripleValue::Bytes(bytes) => {
+            let len = bytes.len().min(SORT_KEY_SIZE - 1);
+            key[1..=len].copy_from_slice(&bytes[..len]);
+        }
+        TripleValue::Ref(id) => key[1..].copy_from_slice(&id.0[..SORT_KEY_SIZE - 1]),
+    }
+    key
+}
+
+/// Map a number's bits so that unsigned order matches numeric order.
+const fn ordered_bits(n: f64) -> u64 {
+    let bits = n.to_bits();
+    if bits >> 63 == 1 {
+        !bits
+    } else {
+        bits | (1 << 63)
+    }
+}
+
+/// The part of a value its sort key leaves out: up to
+/// `MAX_VALUE_PREFIX_SIZE` bytes of a String, cut at a character boundary,
+/// or of Bytes, or a Ref's entity ID. Also returns whether the prefix is
+/// shorter than the value.
+fn value_prefix(value: &TripleValue) -> (Vec<u8>, bool) {
+    match value {
+        TripleValue::String(s) => {
+            let mut len = s.len().min(MAX_VALUE_PREFIX_SIZE);
+            while !s.is_char_boundary(len) {
+                len -= 1;
+            }
+            (s.as_bytes()[..len].to_vec(), len < s.len())
+        }
+        TripleValue::Bytes(bytes) => {
+            let len = bytes.len().min(MAX_VALUE_PREFIX_SIZE);
+            (bytes[..len].to_vec(), len < bytes.len())
+        }
+        TripleValue::Ref(id) => (id.0.to_vec(), false),
+        TripleValue::Null | TripleValue::Boolean(_) | TripleValue::Number(_) => (Vec::new(), false),
+    }
+}
+
+/// Rebuild a value from its sort key and prefix. A String or Bytes value is
+/// only its prefix. Returns `None` for an unknown type byte.
+fn value_from_sort_key(sort_key: &SortKey, prefix: &[u8]) -> Option<TripleValue> {
+    let value_type = ValueType::try_from(sort_key[0]).ok()?;
+    match value_type {
+        ValueType::Null => Some(TripleValue::Null),
+        ValueType::Boolean => Some(TripleValue::Boolean(sort_key[1] != 0)),
+        ValueType::Number => {
+            let mut bits = [0u8; 8];
+            bits.copy_from_slice(&sort_key[1..]);
+            let ordered = u64::from_be_bytes(bits);
+            let bits = if ordered >> 63 == 1 {
+                ordered & !(1 << 63)
+            } else {
+                !ordered
+            };
+            Some(TripleValue::Number(f64::from_bits(bits)))
+        }
+        ValueType::StringInline => std::str::from_utf8(prefix)
+            .ok()
+            .map(|s| TripleValue::String(s.to_owned())),
+        ValueType::Blob => Some(TripleValue::Bytes(prefix.to_vec())),
+        ValueType::Ref => {
+            let id: [u8; 16] = prefix.try_into().ok()?;
+            Some(TripleValue::Ref(EntityId(id)))
+        }
+        ValueType::StringOverflow | ValueType::Date => None,
+    }
+}
+
 /// Create a key for the attribute index.
-fn make_attribute_key(attribute_id: &AttributeId, entity_id: &EntityId) -> Key {
+fn make_attribute_key(attribute_id: &AttributeId, entity_id: &EntityId, value_hash: u64) -> Key {
     let mut key = [0u8; KEY_SIZE];
     key[..16].copy_from_slice(&attribute_id.0);
-    key[16..].copy_from_slice(&entity_id.0);
+    key[16..32].copy_from_slice(&entity_id.0);
+    key[32..].copy_from_slice(&value_hash.to_be_bytes());
     key
 }
 
-/// Split an attribute index key into its components.
+/// Split an attribute index key into its attribute and entity.
 fn split_attribute_key(key: &Key) -> (AttributeId, EntityId) {
     let mut attribute_id = [0u8; 16];
     let mut entity_id = [0u8; 16];
     attribute_id.copy_from_slice(&key[..16]);
-    entity_id.copy_from_slice(&key[16..]);
+    entity_id.copy_from_slice(&key[16..32]);
     (AttributeId(attribute_id), EntityId(entity_id))
 }
 
-/// Create the value for an attribute index entry.
-fn make_entry_value(created_txn: TxnId, deleted_txn: TxnId) -> Vec<u8> {
-    let mut value = Vec::with_capacity(ENTRY_VALUE_SIZE);
-    value.extend_from_slice(&created_txn.to_le_bytes());
-    value.extend_from_slice(&deleted_txn.to_le_bytes());
-    value
+/// Create a key for a value entry.
+fn make_value_key(
+    attribute_id: &AttributeId,
+    sort_key: &SortKey,
+    entity_id: &EntityId,
+    value_hash: u64,
+) -> Key {
+    const HASH_OFFSET: usize = 16 + SORT_KEY_SIZE;
+    let mut key = [0u8; KEY_SIZE];
+    key[..16].copy_from_slice(&attribute_id.0);
+    key[16..HASH_OFFSET].copy_from_slice(sort_key);
+    key[HASH_OFFSET..HASH_OFFSET + ENTITY_HASH_SIZE]
+        .copy_from_slice(&entity_hash(entity_id, value_hash));
+    key
+}
+
+/// Hash an entity ID and value hash for a value entry key.
+///
+/// Mixes both halves of the ID, then the value hash, with the `SplitMix64`
+/// finalizer, so triples that differ in any byte almost never share the key's
+/// hash bytes.
+fn entity_hash(entity_id: &EntityId, value_hash: u64) -> [u8; ENTITY_HASH_SIZE] {
+    const fn mix(mut x: u64) -> u64 {
+        x ^= x >> 30;
+        x = x.wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        x ^= x >> 27;
+        x = x.wrapping_mul(0x94D0_49BB_1331_11EB);
+        x ^ (x >> 31)
+    }
+    let mut low = [0u8; 8];
+    let mut high = [0u8; 8];
+    low.copy_from_slice(&entity_id.0[..8]);
+    high.copy_from_slice(&entity_id.0[8..]);
+    mix(mix(mix(u64::from_be_bytes(low)) ^ u64::from_be_bytes(high)) ^ value_hash).to_be_bytes()
+}
+
+/// Split a value entry key into its attribute and sort key.
+fn split_value_key(key: &Key) -> (AttributeId, SortKey) {
+    let mut attribute_id = [0u8; 16];
+    let mut sort_key = [0u8; SORT_KEY_SIZE];
+    attribute_id.copy_from_slice(&key[..16]);
+    sort_key.copy_from_slice(&key[16..16 + SORT_KEY_SIZE]);
+    (AttributeId(attribute_id), sort_key)
 }
 
 /// Errors that can occur during attribute index operations.
@@ -420,12 +1298,21 @@ fn make_entry_value(created_txn: TxnId, deleted_txn: TxnId) -> Vec<u8> {
 pub enum AttributeIndexError {
     /// B-tree operation failed.
     BTree(BTreeError),
+    /// A value entry could not be decoded.
+    InvalidValueEntry,
+    /// Too many entities collide on one value entry key.
+    ValueEntryFull,
 }
 
 impl std::fmt::Display for AttributeIndexError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             Self::BTree(e) => write!(f, "B-tree error: {e}"),
+            Self::InvalidValueEntry => write!(f, "invalid attribute value entry"),
+            Self::ValueEntryFull => write!(
+                f,
+                "more than {MAX_ENTITIES_PER_VALUE_ENTRY} entities share an attribute value entry"
+            ),
         }
     }
 }
@@ -434,6 +1321,7 @@ impl std::error::Error for AttributeIndexError {
     fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
         match self {
             Self::BTree(e) => Some(e),
+            Self::InvalidValueEntry | Self::ValueEntryFull => None,
         }
     }
 }
@@ -469,27 +1357,34 @@ mod tests {
         let pool = test_pool();
         let mut file = DatabaseFile::create(&path, pool).expect("create db");
 
-        let mut index = AttributeIndex::new(&mut file, 0).expect("create index");
+        let mut index = AttributeIndex::new(&mut file, 0, 0).expect("create index");
 
         let attr1 = AttributeId([1u8; 16]);
         let entity1 = EntityId([10u8; 16]);
         let entity2 = EntityId([20u8; 16]);
 
         // Insert
-        index.insert(&attr1, &entity1, 1).expect("insert");
-        index.insert(&attr1, &entity2, 1).expect("insert");
+        index
+            .insert(&attr1, &entity1, 0, &TripleValue::Null, 1)
+            .expect("insert");
+        index
+            .insert(&attr1, &entity2, 0, &TripleValue::Null, 1)
+            .expect("insert");
 
         // Check contains
-        assert!(index.contains(&attr1, &entity1).expect("contains"));
-        assert!(index.contains(&attr1, &entity2).expect("contains"));
+        assert!(index.contains(&attr1, &entity1, 0).expect("contains"));
+        assert!(index.contains(&attr1, &entity2, 0).expect("contains"));
         assert!(
             !index
-                .contains(&attr1, &EntityId([30u8; 16]))
+                .contains(&attr1, &EntityId([30u8; 16]), 0)
                 .expect("contains")
         );
 
         // Get
-        let (created, deleted) = index.get(&attr1, &entity1).expect("get").expect("exists");
+        let (created, deleted) = index
+            .get(&attr1, &entity1, 0)
+            .expect("get")
+            .expect("exists");
         assert_eq!(created, 1);
         assert_eq!(deleted, 0);
     }
@@ -500,7 +1395,7 @@ mod tests {
         let pool = test_pool();
         let mut file = DatabaseFile::create(&path, pool).expect("create db");
 
-        let mut index = AttributeIndex::new(&mut file, 0).expect("create index");
+        let mut index = AttributeIndex::new(&mut file, 0, 0).expect("create index");
 
         let attr1 = AttributeId([1u8; 16]);
         let attr2 = AttributeId([2u8; 16]);
@@ -509,14 +1404,18 @@ mod tests {
         for i in 0..5u8 {
             let mut entity = [0u8; 16];
             entity[0] = i;
-            index.insert(&attr1, &EntityId(entity), 1).expect("insert");
+            index
+                .insert(&attr1, &EntityId(entity), 0, &TripleValue::Null, 1)
+                .expect("insert");
         }
 
         // Insert entities for attr2
         for i in 0..3u8 {
             let mut entity = [0u8; 16];
             entity[0] = i + 100;
-            index.insert(&attr2, &EntityId(entity), 1).expect("insert");
+            index
+                .insert(&attr2, &EntityId(entity), 0, &TripleValue::Null, 1)
+                .expect("insert");
         }
 
         // Scan attr1
@@ -542,27 +1441,29 @@ mod tests {
         let pool = test_pool();
         let mut file = DatabaseFile::create(&path, pool).expect("create db");
 
-        let mut index = AttributeIndex::new(&mut file, 0).expect("create index");
+        let mut index = AttributeIndex::new(&mut file, 0, 0).expect("create index");
 
         let attr = AttributeId([1u8; 16]);
         let entity = EntityId([10u8; 16]);
 
         // Insert at txn 10
-        index.insert(&attr, &entity, 10).expect("insert");
+        index
+            .insert(&attr, &entity, 0, &TripleValue::Null, 10)
+            .expect("insert");
 
         // Visible to txn >= 10
-        assert!(!index.is_visible(&attr, &entity, 9).expect("vis"));
-        assert!(index.is_visible(&attr, &entity, 10).expect("vis"));
-        assert!(index.is_visible(&attr, &entity, 100).expect("vis"));
+        assert!(!index.is_visible(&attr, &entity, 0, 9).expect("vis"));
+        assert!(index.is_visible(&attr, &entity, 0, 10).expect("vis"));
+        assert!(index.is_visible(&attr, &entity, 0, 100).expect("vis"));
 
         // Mark deleted at txn 50
-        index.mark_deleted(&attr, &entity, 50).expect("delete");
+        index.mark_deleted(&attr, &entity, 0, 50).expect("delete");
 
         // Now visible only in [10, 50)
-        assert!(!index.is_visible(&attr, &entity, 9).expect("vis"));
-        assert!(index.is_visible(&attr, &entity, 10).expect("vis"));
-        assert!(index.is_visible(&attr, &entity, 49).expect("vis"));
-        assert!(!index.is_visible(&attr, &entity, 50).expect("vis"));
+        assert!(!index.is_visible(&attr, &entity, 0, 9).expect("vis"));
+        assert!(index.is_visible(&attr, &entity, 0, 10).expect("vis"));
+        assert!(index.is_visible(&attr, &entity, 0, 49).expect("vis"));
+        assert!(!index.is_visible(&attr, &entity, 0, 50).expect("vis"));
     }
 
     #[test]
@@ -571,16 +1472,18 @@ mod tests {
         let pool = test_pool();
         let mut file = DatabaseFile::create(&path, pool).expect("create db");
 
-        let mut index = AttributeIndex::new(&mut file, 0).expect("create index");
+        let mut index = AttributeIndex::new(&mut file, 0, 0).expect("create index");
 
         let attr = AttributeId([1u8; 16]);
         let entity = EntityId([10u8; 16]);
 
-        index.insert(&attr, &entity, 1).expect("insert");
-        assert!(index.contains(&attr, &entity).expect("contains"));
+        index
+            .insert(&attr, &entity, 0, &TripleValue::Null, 1)
+            .expect("insert");
+        assert!(index.contains(&attr, &entity, 0).expect("contains"));
 
-        index.remove(&attr, &entity).expect("remove");
-        assert!(!index.contains(&attr, &entity).expect("contains"));
+        index.remove(&attr, &entity, 0).expect("remove");
+        assert!(!index.contains(&attr, &entity, 0).expect("contains"));
     }
 
     #[test]
@@ -589,7 +1492,7 @@ mod tests {
         let pool = test_pool();
         let mut file = DatabaseFile::create(&path, pool).expect("create db");
 
-        let mut index = AttributeIndex::new(&mut file, 0).expect("create index");
+        let mut index = AttributeIndex::new(&mut file, 0, 0).expect("create index");
 
         let attr = AttributeId([1u8; 16]);
 
@@ -598,9 +1501,15 @@ mod tests {
         let entity2 = EntityId([2u8; 16]); // created at 20
         let entity3 = EntityId([3u8; 16]); // created at 30
 
-        index.insert(&attr, &entity1, 10).expect("insert");
-        index.insert(&attr, &entity2, 20).expect("insert");
-        index.insert(&attr, &entity3, 30).expect("insert");
+        index
+            .insert(&attr, &entity1, 0, &TripleValue::Null, 10)
+            .expect("insert");
+        index
+            .insert(&attr, &entity2, 0, &TripleValue::Null, 20)
+            .expect("insert");
+        index
+            .insert(&attr, &entity3, 0, &TripleValue::Null, 30)
+            .expect("insert");
 
         // Scan at snapshot 15 - should only see entity1
         let mut scan = index.scan_attribute_visible(&attr, 15).expect("scan");
@@ -619,4 +1528,373 @@ mod tests {
         }
         assert_eq!(entities.len(), 2);
     }
+
+    fn entity(n: u8) -> EntityId {
+        EntityId([n; 16])
+    }
+
+    fn collect_range(
+        index: &mut AttributeIndex<'_>,
+        range: ValueRange,
+        txn: TxnId,
+    ) -> Vec<EntityId> {
+        let mut iter = index
+            .scan_attribute_range(&AttributeId([1u8; 16]), range, txn)
+            .expect("scan");
+        let mut entities = Vec::new();
+        while let Some((e, _)) = iter.next_triple().expect("next") {
+            entities.push(e);
+        }
+        entities
+    }
+
+    #[test]
+    fn test_range_scan_orders_numbers() {
+        let (_dir, path) = create_test_db();
+        let mut file = DatabaseFile::create(&path, test_pool()).expect("create db");
+        let mut index = AttributeIndex::new(&mut file, 0, 0).expect("create index");
+        let attr = AttributeId([1u8; 16]);
+
+        let values = [
+            (1, 3.5),
+            (2, -10.0),
+            (3, f64::NAN),
+            (4, -0.0),
+            (5, 0.25),
+            (6, f64::NEG_INFINITY),
+            (7, 100.0),
+        ];
+        for (n, value) in values {
+            index
+                .insert(&attr, &entity(n), 0, &TripleValue::Number(value), 1)
+                .expect("insert");
+        }
+        // Another attribute's values stay out of the scan
+        index
+            .insert(
+                &AttributeId([2u8; 16]),
+                &entity(8),
+                0,
+                &TripleValue::Number(1.0),
+                1,
+            )
+            .expect("insert");
+
+        let all = ValueRange::new(Bound::Unbounded, Bound::Unbounded);
+        assert_eq!(
+            collect_range(&mut index, all, 1),
+            vec![
+                entity(6),
+                entity(2),
+                entity(4),
+                entity(5),
+                entity(1),
+                entity(7)
+            ]
+        );
+
+        let range = ValueRange::new(
+            Bound::Excluded(TripleValue::Number(-10.0)),
+            Bound::Included(TripleValue::Number(3.5)),
+        );
+        assert_eq!(
+            collect_range(&mut index, range, 1),
+            vec![entity(4), entity(5), entity(1)]
+        );
+
+        let range = ValueRange::at_least(TripleValue::Number(0.0));
+        assert_eq!(
+            collect_range(&mut index, range, 1),
+            vec![entity(4), entity(5), entity(1), entity(7)]
+        );
+    }
+
+    #[test]
+    fn test_range_scan_orders_strings() {
+        let (_dir, path) = create_test_db();
+        let mut file = DatabaseFile::create(&path, test_pool()).expect("create db");
+        let mut index = AttributeIndex::new(&mut file, 0, 0).expect("create index");
+        let attr = AttributeId([1u8; 16]);
+
+        // "applesauce1" and "applesauce2" share their first 8 bytes
+        let values = [
+            "banana",
+            "applesauce2",
+            "cherry",
+            "apple",
+            "applesauce1",
+            "b",
+        ];
+        for (n, value) in (1..).zip(values) {
+            index
+                .insert(
+                    &attr,
+                    &entity(n),
+                    0,
+                    &TripleValue::String(value.to_string()),
+                    1,
+                )
+                .expect("insert");
+        }
+        index
+            .insert(&attr, &entity(9), 0, &TripleValue::Number(1.0), 1)
+            .expect("insert");
+
+        let range = ValueRange::new(
+            Bound::Included(TripleValue::String("applesauce1".to_string())),
+            Bound::Excluded(TripleValue::String("banana".to_string())),
+        );
+        assert_eq!(
+            collect_range(&mut index, range, 1),
+            vec![entity(5), entity(2), entity(6)]
+        );
+
+        let range = ValueRange::less_than(TripleValue::String("applesauce2".to_string()));
+        assert_eq!(
+            collect_range(&mut index, range, 1),
+            vec![entity(4), entity(5)]
+        );
+    }
+
+    #[test]
+    fn test_range_scan_orders_bytes() {
+        let (_dir, path) = create_test_db();
+        let mut file = DatabaseFile::create(&path, test_pool()).expect("create db");
+        let mut index = AttributeIndex::new(&mut file, 0, 0).expect("create index");
+        let attr = AttributeId([1u8; 16]);
+
+        let values: [&[u8]; 4] = [&[2], &[0, 0xFF], &[1; 12], &[1; 10]];
+        for (n, value) in (1..).zip(values) {
+            index
+                .insert(&attr, &entity(n), 0, &TripleValue::bytes(value.to_vec()), 1)
+                .expect("insert");
+        }
+        index
+            .insert(
+                &attr,
+                &entity(9),
+                0,
+                &TripleValue::String("x".to_string()),
+                1,
+            )
+            .expect("insert");
+
+        let range = ValueRange::new(
+            Bound::Included(TripleValue::Bytes(vec![0])),
+            Bound::Unbounded,
+        );
+        assert_eq!(
+            collect_range(&mut index, range, 1),
+            vec![entity(2), entity(4), entity(3), entity(1)]
+        );
+
+        let range = ValueRange::greater_than(TripleValue::Bytes(vec![1; 10]));
+        assert_eq!(
+            collect_range(&mut index, range, 1),
+            vec![entity(3), entity(1)]
+        );
+    }
+
+    #[test]
+    fn test_range_scan_includes_truncated_values() {
+        let (_dir, path) = create_test_db();
+        let mut file = DatabaseFile::create(&path, test_pool()).expect("create db");
+        let mut index = AttributeIndex::new(&mut file, 0, 0).expect("create index");
+        let attr = AttributeId([1u8; 16]);
+
+        // Cut at a character boundary, the prefix is shorter than
+        // MAX_VALUE_PREFIX_SIZE yet still not the whole string
+        let long_string = format!("a{}", "é".repeat(40));
+        let (prefix, truncated) = value_prefix(&TripleValue::String(long_string.clone()));
+        assert!(prefix.len() < MAX_VALUE_PREFIX_SIZE);
+        assert!(truncated);
+        index
+            .insert(&attr, &entity(1), 0, &TripleValue::String(long_string), 1)
+            .expect("insert");
+        index
+            .insert(&attr, &entity(2), 0, &TripleValue::bytes(vec![7; 100]), 1)
+            .expect("insert");
+
+        let stored_prefix = String::from_utf8(prefix).expect("utf8");
+        let range = ValueRange::greater_than(TripleValue::String(stored_prefix));
+        assert_eq!(collect_range(&mut index, range, 1), vec![entity(1)]);
+
+        let range = ValueRange::greater_than(TripleValue::Bytes(vec![7; MAX_VALUE_PREFIX_SIZE]));
+        assert_eq!(collect_range(&mut index, range, 1), vec![entity(2)]);
+    }
+
+    #[test]
+    fn test_range_scan_respects_visibility_and_updates() {
+        let (_dir, path) = create_test_db();
+        let mut file = DatabaseFile::create(&path, test_pool()).expect("create db");
+        let mut index = AttributeIndex::new(&mut file, 0, 0).expect("create index");
+        let attr = AttributeId([1u8; 16]);
+
+        index
+            .insert(&attr, &entity(1), 0, &TripleValue::Number(1.0), 10)
+            .expect("insert");
+        index
+            .insert(&attr, &entity(2), 0, &TripleValue::Number(2.0), 20)
+            .expect("insert");
+        index
+            .insert(&attr, &entity(3), 0, &TripleValue::Number(3.0), 10)
+            .expect("insert");
+        index
+            .mark_deleted(&attr, &entity(3), 0, 30)
+            .expect("delete");
+        assert!(
+            index
+                .update_value(&attr, &entity(1), 0, &TripleValue::Number(5.0))
+                .expect("update")
+        );
+
+        let range = || ValueRange::at_most(TripleValue::Number(4.0));
+        assert_eq!(collect_range(&mut index, range(), 15), vec![entity(3)]);
+        assert_eq!(
+            collect_range(&mut index, range(), 25),
+            vec![entity(2), entity(3)]
+        );
+        assert_eq!(collect_range(&mut index, range(), 30), vec![entity(2)]);
+
+        index.remove(&attr, &entity(2), 0).expect("remove");
+        assert!(collect_range(&mut index, range(), 30).is_empty());
+        assert_eq!(
+            collect_range(
+                &mut index,
+                ValueRange::at_least(TripleValue::Number(5.0)),
+                30
+            ),
+            vec![entity(1)]
+        );
+    }
+
+    #[test]
+    fn test_range_scan_many_entities_with_same_value() {
+        let (_dir, path) = create_test_db();
+        let mut file = DatabaseFile::create(&path, test_pool()).expect("create db");
+        let mut index = AttributeIndex::new(&mut file, 0, 0).expect("create index");
+        let attr = AttributeId([1u8; 16]);
+
+        // IDs that differ only in their first bytes, as zero-padded names do
+        let entities: Vec<EntityId> = (0..600u16)
+            .map(|n| {
+                let mut id = [0u8; 16];
+                id[..2].copy_from_slice(&n.to_be_bytes());
+                EntityId(id)
+            })
+            .collect();
+        for entity_id in &entities {
+            index
+                .insert(&attr, entity_id, 0, &TripleValue::Boolean(true), 1)
+                .expect("insert");
+        }
+
+        let range = ValueRange::at_least(TripleValue::Boolean(true));
+        assert_eq!(collect_range(&mut index, range, 1), entities);
+    }
+
+    #[test]
+    fn test_entry_never_looks_like_overflow_reference() {
+        let (_dir, path) = create_test_db();
+        let mut file = DatabaseFile::create(&path, test_pool()).expect("create db");
+        let mut index = AttributeIndex::new(&mut file, 0, 0).expect("create index");
+        let attr = AttributeId([1u8; 16]);
+
+        index
+            .insert(&attr, &entity(1), 0, &TripleValue::Number(1.0), 255)
+            .expect("insert");
+
+        assert_eq!(
+            index.get(&attr, &entity(1), 0).expect("get"),
+            Some((255, 0))
+        );
+        assert!(
+            index
+                .mark_deleted(&attr, &entity(1), 0, 511)
+                .expect("delete")
+        );
+        assert_eq!(
+            index.get(&attr, &entity(1), 0).expect("get"),
+            Some((255, 511))
+        );
+    }
+
+    #[test]
+    fn test_reader_range_scan_matches_index() {
+        let (_dir, path) = create_test_db();
+        let mut file = DatabaseFile::create(&path, test_pool()).expect("create db");
+        let attr = AttributeId([1u8; 16]);
+        let (root, value_root) = {
+            let mut index = AttributeIndex::new(&mut file, 0, 0).expect("create index");
+            for n in 1..=20u8 {
+                index
+                    .insert(&attr, &entity(n), 0, &TripleValue::Number(f64::from(n)), 1)
+                    .expect("insert");
+            }
+            (index.root_page(), index.value_root_page())
+        };
+
+        let reader = AttributeIndexReader::new(&file, root, value_root);
+        let range = ValueRange::new(
+            Bound::Excluded(TripleValue::Number(5.0)),
+            Bound::Included(TripleValue::Number(8.0)),
+        );
+        let mut iter = reader.scan_attribute_range(&attr, range, 1).expect("scan");
+        let mut entities = Vec::new();
+        while let Some((e, _)) = iter.next_triple().expect("next") {
+            entities.push(e);
+        }
+        assert_eq!(entities, vec![entity(6), entity(7), entity(8)]);
+    }
+
+    #[test]
+    fn test_many_values_of_one_entity() {
+        let (_dir, path) = create_test_db();
+        let mut file = DatabaseFile::create(&path, test_pool()).expect("create db");
+        let mut index = AttributeIndex::new(&mut file, 0, 0).expect("create index");
+        let attr = AttributeId([1u8; 16]);
+
+        // Two scores of entity 1 share a value entry key prefix with entity 2's
+        index
+            .insert(&attr, &entity(1), 10, &TripleValue::Number(1.0), 1)
+            .expect("insert");
+        index
+            .insert(&attr, &entity(1), 20, &TripleValue::Number(2.0), 1)
+            .expect("insert");
+        index
+            .insert(&attr, &entity(2), 0, &TripleValue::Number(1.0), 1)
+            .expect("insert");
+
+        let mut scan = index.scan_attribute_visible(&attr, 1).expect("scan");
+        let mut entities = Vec::new();
+        while let Some(e) = scan.next_entity().expect("next") {
+            entities.push(e);
+        }
+        assert_eq!(entities, vec![entity(1), entity(2)]);
+
+        let range = || ValueRange::at_most(TripleValue::Number(5.0));
+        let mut iter = index.scan_attribute_range(&attr, range(), 1).expect("scan");
+        let mut triples = Vec::new();
+        while let Some(triple) = iter.next_triple().expect("next") {
+            triples.push(triple);
+        }
+        drop(iter);
+        assert_eq!(triples.len(), 3);
+        assert!(triples.contains(&(entity(1), 10)));
+        assert!(triples.contains(&(entity(1), 20)));
+        assert_eq!(triples[2], (entity(1), 20));
+
+        // Deleting one value leaves the entity's other value
+        assert!(
+            index
+                .mark_deleted(&attr, &entity(1), 10, 2)
+                .expect("delete")
+        );
+        assert_eq!(
+            collect_range(&mut index, range(), 2),
+            vec![entity(2), entity(1)]
+        );
+        assert!(index.is_visible(&attr, &entity(1), 20, 2).expect("vis"));
+        assert!(!index.is_visible(&attr, &entity(1), 10, 2).expect("vis"));
+    }
 }
@@ -5,10 +5,15 @@
 //!
 //! # Key Format
 //!
-//! Keys are 32 bytes: `(entity_id: [u8; 16], attribute_id: [u8; 16])`
+//! Keys are 40 bytes: `(entity_id: [u8; 16], attribute_id: [u8; 16], value_hash: u64)`
+//!
+//! The value hash is the triple's key in the primary index: 0 for a
+//! cardinality-one attribute, so each value of a cardinality-many attribute
+//! has its own entry. Scans yield each attribute once however many values it
+//! has.
 //!
 //! This format allows:
-//! - Point lookup: check if (entity, attribute) pair exists
+//! - Point lookup: check if (entity, attribute, value hash) exists
 //! - Entity scan: iterate all attributes for a given entity
 //!
 //! # Value Format
@@ -16,7 +21,6 @@
 //! Values store MVCC metadata: `created_txn` (8 bytes) and `deleted_txn` (8 bytes).
 
 use crate::storage::btree::{BTree, BTreeError, KEY_SIZE, Key};
-#[cfg(unix)]
 use crate::storage::btree::{BTreeReader, BTreeReaderIterator};
 use crate::storage::file::DatabaseFile;
 use crate::storage::page::PageId;
@@ -55,25 +59,27 @@ impl<'a> EntityAttributeIndex<'a> {
         self.tree.file_mut()
     }
 
-    /// Check if an (entity, attribute) pair exists in the index.
+    /// Check if an (entity, attribute, value hash) entry exists in the index.
     pub fn contains(
         &mut self,
         entity_id: &EntityId,
         attribute_id: &AttributeId,
+        value_hash: u64,
     ) -> Result<bool, EntityAttributeIndexError> {
-        let key = make_entity_attribute_key(entity_id, attribute_id);
+        let key = make_entity_attribute_key(entity_id, attribute_id, value_hash);
         Ok(self.tree.get(&key)?.is_some())
     }
 
-    /// Get the MVCC metadata for an (entity, attribute) pair.
+    /// Get the MVCC metadata for an (entity, attribute, value hash) entry.
     ///
     /// Returns `(created_txn, deleted_txn)` if the entry exists.
     pub fn get(
         &mut self,
         entity_id: &EntityId,
         attribute_id: &AttributeId,
+        value_hash: u64,
     ) -> Result<Option<(TxnId, TxnId)>, EntityAttributeIndexError> {
-        let key = make_entity_attribute_key(entity_id, attribute_id);
+        let key = make_entity_attribute_key(entity_id, attribute_id, value_hash);
         match self.tree.get(&key)? {
             Some(value) if value.len() >= ENTRY_VALUE_SIZE => {
                 let created_txn = u64::from_le_bytes([
@@ -94,9 +100,10 @@ impl<'a> EntityAttributeIndex<'a> {
         &mut self,
         entity_id: &EntityId,
         attribute_id: &AttributeId,
+        value_hash: u64,
         snapshot_txn: TxnId,
     ) -> Result<bool, EntityAttributeIndexError> {
-        match self.get(entity_id, attribute_id)? {
+        match self.get(entity_id, attribute_id, value_hash)? {
             Some((created_txn, deleted_txn)) => {
                 let visible =
                     created_txn <= snapshot_txn && (deleted_txn == 0 || deleted_txn > snapshot_txn);
@@ -106,27 +113,49 @@ impl<'a> EntityAttributeIndex<'a> {
         }
     }
 
-    /// Insert an (entity, attribute) pair into the index.
+    /// Insert an (entity, attribute, value hash) entry into the index.
     pub fn insert(
         &mut self,
         entity_id: &EntityId,
         attribute_id: &AttributeId,
+        value_hash: u64,
         created_txn: TxnId,
     ) -> Result<(), EntityAttributeIndexError> {
-        let key = make_entity_attribute_key(entity_id, attribute_id);
+        let key = make_entity_attribute_key(entity_id, attribute_id, value_hash);
         let value = make_entry_value(created_txn, 0);
         self.tree.insert(key, value)?;
         Ok(())
     }
 
-    /// Mark an (entity, attribute) pair as deleted.
+    /// Insert many (entity, attribute, value hash) entries, in order, as
+    /// `insert` would one at a time.
+    ///
+    /// Entries sorted by key are cheapest (see `BTree::insert_many`).
+    pub fn insert_many(
+        &mut self,
+        entries: impl IntoIterator<Item = (EntityId, AttributeId, u64)>,
+        created_txn: TxnId,
+    ) -> Result<(), EntityAttributeIndexError> {
+        self.tree.insert_many(entries.into_iter().map(
+            |(entity_id, attribute_id, value_hash)| {
+                (
+                    make_entity_attribute_key(&entity_id, &attribute_id, value_hash),
+                    make_entry_value(created_txn, 0),
+                )
+            },
+        ))?;
+        Ok(())
+    }
+
+    /// Mark an (entity, attribute, value hash) entry as deleted.
     pub fn mark_deleted(
         &mut self,
         entity_id: &EntityId,
         attribute_id: &AttributeId,
+        value_hash: u64,
         deleted_txn: TxnId,
     ) -> Result<bool, EntityAttributeIndexError> {
-        let key = make_entity_attribute_key(entity_id, attribute_id);
+        let key = make_entity_attribute_key(entity_id, attribute_id, value_hash);
 
         // Get existing entry to preserve created_txn
         let Some(existing) = self.tree.get(&key)? else {
@@ -158,8 +187,9 @@ impl<'a> EntityAttributeIndex<'a> {
         &mut self,
         entity_id: &EntityId,
         attribute_id: &AttributeId,
+        value_hash: u64,
     ) -> Result<bool, EntityAttributeIndexError> {
-        let key = make_entity_attribute_key(entity_id, attribute_id);
+        let key = make_entity_attribute_key(entity_id, attribute_id, value_hash);
         Ok(self.tree.remove(&key)?.is_some())
     }
 
@@ -171,13 +201,14 @@ impl<'a> EntityAttributeIndex<'a> {
         entity_id: &EntityId,
     ) -> Result<EntityScanIterator<'_>, EntityAttributeIndexError> {
         // Start key: (entity_id, 0x00...)
-        let start_key = make_entity_attribute_key(entity_id, &AttributeId::default());
+        let start_key = make_entity_attribute_key(entity_id, &AttributeId::default(), 0);
         let cursor = self.tree.iter_from(&start_key)?;
 
         Ok(EntityScanIterator {
             cursor,
             entity_id: *entity_id,
             snapshot_txn: None,
+            last_attribute_id: None,
             done: false,
         })
     }
@@ -188,13 +219,14 @@ impl<'a> EntityAttributeIndex<'a> {
         entity_id: &EntityId,
         snapshot_txn: TxnId,
     ) -> Result<EntityScanIterator<'_>, EntityAttributeIndexError> {
-        let start_key = make_entity_attribute_key(entity_id, &AttributeId::default());
+        let start_key = make_entity_attribute_key(entity_id, &AttributeId::default(), 0);
         let cursor = self.tree.iter_from(&start_key)?;
 
         Ok(EntityScanIterator {
             cursor,
             entity_id: *entity_id,
             snapshot_txn: Some(snapshot_txn),
+            last_attribute_id: None,
             done: false,
         })
     }
@@ -206,12 +238,10 @@ impl<'a> EntityAttributeIndex<'a> {
 }
 
 /// Read-only entity-attribute index accessor for concurrent snapshot reads.
-#[cfg(unix)]
 pub struct EntityAttributeIndexReader<'a> {
     tree: BTreeReader<'a>,
 }
 
-#[cfg(unix)]
 impl<'a> EntityAttributeIndexReader<'a> {
     /// Create a new read-only entity-attribute index accessor.
     #[must_use]
@@ -220,13 +250,14 @@ impl<'a> EntityAttributeIndexReader<'a> {
         Self { tree }
     }
 
-    /// Get the MVCC metadata for an (entity, attribute) pair.
+    /// Get the MVCC metadata for an (entity, attribute, value hash) entry.
     pub fn get(
         &self,
         entity_id: &EntityId,
         attribute_id: &AttributeId,
+        value_hash: u64,
     ) -> Result<Option<(TxnId, TxnId)>, EntityAttributeIndexError> {
-        let key = make_entity_attribute_key(entity_id, attribute_id);
+        let key = make_entity_attribute_key(entity_id, attribute_id, value_hash);
         match self.tree.get(&key)? {
             Some(value) if value.len() >= ENTRY_VALUE_SIZE => {
                 let created_txn = u64::from_le_bytes([
@@ -248,14 +279,73 @@ impl<'a> EntityAttributeIndexReader<'a> {
         entity_id: &EntityId,
         snapshot_txn: TxnId,
     ) -> Result<EntityScanReaderIterator<'_>, EntityAttributeIndexError> {
-        let start_key = make_entity_attribute_key(entity_id, &AttributeId::default());
+        self.scan_entity_prefix_visible(entity_id, &[], snapshot_txn)
+    }
+
+    /// Scan the visible attributes of an entity whose IDs start with `prefix`.
+    ///
+    /// Matching attributes are adjacent in key order, so the scan seeks to the
+    /// first one and stops at the first attribute past the prefix range.
+    ///
+    /// # Post-conditions
+    /// - Yields nothing if `prefix` is longer than an attribute ID
+    pub fn scan_entity_prefix_visible(
+        &self,
+        entity_id: &EntityId,
+        prefix: &[u8],
+        snapshot_txn: TxnId,
+    ) -> Result<EntityScanReaderIterator<'_>, EntityAttributeIndexError> {
+        let mut start_attribute = AttributeId::default();
+        let prefix_len = prefix.len().min(start_attribute.0.len());
+        start_attribute.0[..prefix_len].copy_from_slice(&prefix[..prefix_len]);
+        let start_key = make_entity_attribute_key(entity_id, &start_attribute, 0);
         let cursor = self.tree.iter_from(&start_key)?;
 
         Ok(EntityScanReaderIterator {
             cursor,
             entity_id: *entity_id,
+            prefix: start_attribute,
+            prefix_len,
             snapshot_txn: Some(snapshot_txn),
-            done: false,
+            last_attribute_id: None,
+            done: prefix.len() > prefix_len,
+        })
+    }
+
+    /// Scan distinct entity IDs that have at least one visible attribute at a snapshot.
+    ///
+    /// Because keys are ordered entity-first, all attributes of an entity are
+    /// adjacent, so each entity is yielded exactly once, in key order.
+    ///
+    /// # Pre-conditions
+    /// - If `start_after` is `Some`, only entities strictly greater than it are yielded
+    pub fn scan_entities_visible(
+        &self,
+        start_after: Option<&EntityId>,
+        snapshot_txn: TxnId,
+    ) -> Result<DistinctEntityReaderIterator<'a>, EntityAttributeIndexError> {
+        // (start_after, 0xFF...) sorts after every attribute of `start_after`
+        let start_key = start_after.map_or_else(
+            || make_entity_attribute_key(&EntityId::default(), &AttributeId::default(), 0),
+            |entity_id| make_entity_attribute_key(entity_id, &AttributeId([0xFF; 16]), u64::MAX),
+        );
+        let cursor = self.tree.iter_from(&start_key)?;
+
+        Ok(DistinctEntityReaderIterator {
+            cursor,
+            start_after: start_after.copied(),
+            last_entity_id: None,
+            snapshot_txn,
+        })
+    }
+
+    /// Scan every (entity, attribute) pair that is not marked deleted, in key
+    /// order.
+    pub fn scan_live(&self) -> Result<LiveScanReaderIterator<'a>, EntityAttributeIndexError> {
+        let cursor = self.tree.cursor()?;
+        Ok(LiveScanReaderIterator {
+            cursor,
+            last_pair: None,
         })
     }
 
@@ -265,16 +355,90 @@ impl<'a> EntityAttributeIndexReader<'a> {
     }
 }
 
+/// Read-only iterator over every live (entity, attribute) pair.
+pub struct LiveScanReaderIterator<'a> {
+    cursor: BTreeReaderIterator<'a>,
+    /// The most recently yielded pair, used to skip its remaining values.
+    last_pair: Option<(EntityId, AttributeId)>,
+}
+
+impl LiveScanReaderIterator<'_> {
+    /// Get the next pair with an entry whose `deleted_txn` is 0.
+    ///
+    /// # Post-conditions
+    /// - Each pair is returned once, however many live values it has
+    pub fn next_pair(
+        &mut self,
+    ) -> Result<Option<(EntityId, AttributeId)>, EntityAttributeIndexError> {
+        while let Some((key, value)) = self.cursor.next_entry()? {
+            let pair = split_entity_attribute_key(&key);
+            if self.last_pair != Some(pair)
+                && value.len() >= ENTRY_VALUE_SIZE
+                && value[8..ENTRY_VALUE_SIZE] == [0u8; 8]
+            {
+                self.last_pair = Some(pair);
+                return Ok(Some(pair));
+            }
+        }
+        Ok(None)
+    }
+}
+
+/// Read-only iterator over distinct entity IDs in the index.
+pub struct DistinctEntityReaderIterator<'a> {
+    cursor: BTreeReaderIterator<'a>,
+    /// Entities less than or equal to this are skipped.
+    start_after: Option<EntityId>,
+    /// The most recently yielded entity, used to skip its remaining attributes.
+    last_entity_id: Option<EntityId>,
+    snapshot_txn: TxnId,
+}
+
+impl DistinctEntityReaderIterator<'_> {
+    /// Get the next distinct entity ID.
+    ///
+    /// # Post-conditions
+    /// - Each entity ID is returned at most once
+    /// - Entity IDs are returned in ascending byte order
+    pub fn next_entity(&mut self) -> Result<Option<EntityId>, EntityAttributeIndexError> {
+        loop {
+            let Some((key, value)) = self.cursor.next_entry()? else {
+                return Ok(None);
+            };
+
+            let (entity_id, _attribute_id) = split_entity_attribute_key(&key);
+
+            if self.last_entity_id == Some(entity_id) {
+                continue;
+            }
+            if let Some(start_after) = self.start_after
+                && entity_id.0 <= start_after.0
+            {
+                continue;
+            }
+            if !is_entry_visible(&value, self.snapshot_txn) {
+                continue;
+            }
+
+            self.last_entity_id = Some(entity_id);
+            return Ok(Some(entity_id));
+        }
+    }
+}
+
 /// Read-only iterator over attributes for a specific entity.
-#[cfg(unix)]
 pub struct EntityScanReaderIterator<'a> {
     cursor: BTreeReaderIterator<'a>,
     entity_id: EntityId,
+    /// Attributes must start with the first `prefix_len` bytes of this.
+    prefix: AttributeId,
+    prefix_len: usize,
     snapshot_txn: Option<TxnId>,
+    /// The most recently yielded attribute, used to skip its remaining values.
+    last_attribute_id: Option<AttributeId>,
     done: bool,
 }
 
-#[cfg(unix)]
 impl EntityScanReaderIterator<'_> {
     /// Get the next attribute ID for this entity.
     pub fn next_attribute(&mut self) -> Result<Option<AttributeId>, EntityAttributeIndexError> {
@@ -290,31 +454,36 @@ impl EntityScanReaderIterator<'_> {
 
             let (ent_id, attribute_id) = split_entity_attribute_key(&key);
 
-            if ent_id != self.entity_id {
+            if ent_id != self.entity_id
+                || attribute_id.0[..self.prefix_len] != self.prefix.0[..self.prefix_len]
+            {
                 self.done = true;
                 return Ok(None);
             }
+            if self.last_attribute_id == Some(attribute_id) {
+                continue;
+            }
+
+            if let Some(snapshot_txn) = self.snapshot_txn
+                && value.len() >= ENTRY_VALUE_SIZE
+            {
+                let created_txn = u64::from_le_bytes([
+                    value[0], value[1], value[2], value[3], value[4], value[5], value[6], value[7],
+                ]);
+                let deleted_txn = u64::from_le_bytes([
+                    value[8], value[9], value[10], value[11], value[12], value[13], value[14],
+                    value[15],
+                ]);
 
-            if let Some(snapshot_txn) = self.snapshot_txn {
-                if value.len() >= ENTRY_VALUE_SIZE {
-                    let created_txn = u64::from_le_bytes([
-                        value[0], value[1], value[2], value[3], value[4], value[5], value[6],
-                        value[7],
-                    ]);
-                    let deleted_txn = u64::from_le_bytes([
-                        value[8], value[9], value[10], value[11], value[12], value[13], value[14],
-                        value[15],
-                    ]);
-
-                    let visible = created_txn <= snapshot_txn
-                        && (deleted_txn == 0 || deleted_txn > snapshot_txn);
-
-                    if !visible {
-                        continue;
-                    }
+                let visible =
+                    created_txn <= snapshot_txn && (deleted_txn == 0 || deleted_txn > snapshot_txn);
+
+                if !visible {
+                    continue;
                 }
             }
 
+            self.last_attribute_id = Some(attribute_id);
             return Ok(Some(attribute_id));
         }
     }
@@ -325,6 +494,8 @@ pub struct EntityScanIterator<'a> {
     cursor: crate::storage::btree::BTreeIterator<'a>,
     entity_id: EntityId,
     snapshot_txn: Option<TxnId>,
+    /// The most recently yielded attribute, used to skip its remaining values.
+    last_attribute_id: Option<AttributeId>,
     done: bool,
 }
 
@@ -348,50 +519,74 @@ impl EntityScanIterator<'_> {
                 self.done = true;
                 return Ok(None);
             }
+            if self.last_attribute_id == Some(attribute_id) {
+                continue;
+            }
 
             // Apply visibility filter if set
-            if let Some(snapshot_txn) = self.snapshot_txn {
-                if value.len() >= ENTRY_VALUE_SIZE {
-                    let created_txn = u64::from_le_bytes([
-                        value[0], value[1], value[2], value[3], value[4], value[5], value[6],
-                        value[7],
-                    ]);
-                    let deleted_txn = u64::from_le_bytes([
-                        value[8], value[9], value[10], value[11], value[12], value[13], value[14],
-                        value[15],
-                    ]);
-
-                    let visible = created_txn <= snapshot_txn
-                        && (deleted_txn == 0 || deleted_txn > snapshot_txn);
-
-                    if !visible {
-                        continue; // Skip non-visible entries
-                    }
+            if let Some(snapshot_txn) = self.snapshot_txn
+                && value.len() >= ENTRY_VALUE_SIZE
+            {
+                let created_txn = u64::from_le_bytes([
+                    value[0], value[1], value[2], value[3], value[4], value[5], value[6], value[7],
+                ]);
+                let deleted_txn = u64::from_le_bytes([
+                    value[8], value[9], value[10], value[11], value[12], value[13], value[14],
+                    value[15],
+                ]);
+
+                let visible =
+                    created_txn <= snapshot_txn && (deleted_txn == 0 || deleted_txn > snapshot_txn);
+
+                if !visible {
+                    continue; // Skip non-visible entries
                 }
             }
 
+            self.last_attribute_id = Some(attribute_id);
             return Ok(Some(attribute_id));
         }
     }
 }
 
 /// Create a key for the entity-attribute index.
-fn make_entity_attribute_key(entity_id: &EntityId, attribute_id: &AttributeId) -> Key {
+fn make_entity_attribute_key(
+    entity_id: &EntityId,
+    attribute_id: &AttributeId,
+    value_hash: u64,
+) -> Key {
     let mut key = [0u8; KEY_SIZE];
     key[..16].copy_from_slice(&entity_id.0);
-    key[16..].copy_from_slice(&attribute_id.0);
+    key[16..32].copy_from_slice(&attribute_id.0);
+    key[32..].copy_from_slice(&value_hash.to_be_bytes());
     key
 }
 
-/// Split an entity-attribute index key into its components.
+/// Split an entity-attribute index key into its entity and attribute.
 fn split_entity_attribute_key(key: &Key) -> (EntityId, AttributeId) {
     let mut entity_id = [0u8; 16];
     let mut attribute_id = [0u8; 16];
     entity_id.copy_from_slice(&key[..16]);
-    attribute_id.copy_from_slice(&key[16..]);
+    attribute_id.copy_from_slice(&key[16..32]);
     (EntityId(entity_id), AttributeId(attribute_id))
 }
 
+/// Check whether an entry's MVCC metadata is visible to a snapshot.
+///
+/// Entries with truncated metadata are treated as visible, matching the scan iterators.
+fn is_entry_visible(value: &[u8], snapshot_txn: TxnId) -> bool {
+    if value.len() < ENTRY_VALUE_SIZE {
+        return true;
+    }
+    let created_txn = u64::from_le_bytes([
+        value[0], value[1], value[2], value[3], value[4], value[5], value[6], value[7],
+    ]);
+    let deleted_txn = u64::from_le_bytes([
+        value[8], value[9], value[10], value[11], value[12], value[13], value[14], value[15],
+    ]);
+    created_txn <= snapshot_txn && (deleted_txn == 0 || deleted_txn > snapshot_txn)
+}
+
 /// Create the value for an entity-attribute index entry.
 fn make_entry_value(created_txn: TxnId, deleted_txn: TxnId) -> Vec<u8> {
     let mut value = Vec::with_capacity(ENTRY_VALUE_SIZE);
@@ -461,20 +656,23 @@ mod tests {
         let attr2 = AttributeId([20u8; 16]);
 
         // Insert
-        index.insert(&entity1, &attr1, 1).expect("insert");
-        index.insert(&entity1, &attr2, 1).expect("insert");
+        index.insert(&entity1, &attr1, 0, 1).expect("insert");
+        index.insert(&entity1, &attr2, 0, 1).expect("insert");
 
         // Check contains
-        assert!(index.contains(&entity1, &attr1).expect("contains"));
-        assert!(index.contains(&entity1, &attr2).expect("contains"));
+        assert!(index.contains(&entity1, &attr1, 0).expect("contains"));
+        assert!(index.contains(&entity1, &attr2, 0).expect("contains"));
         assert!(
             !index
-                .contains(&entity1, &AttributeId([30u8; 16]))
+                .contains(&entity1, &AttributeId([30u8; 16]), 0)
                 .expect("contains")
         );
 
         // Get
-        let (created, deleted) = index.get(&entity1, &attr1).expect("get").expect("exists");
+        let (created, deleted) = index
+            .get(&entity1, &attr1, 0)
+            .expect("get")
+            .expect("exists");
         assert_eq!(created, 1);
         assert_eq!(deleted, 0);
     }
@@ -495,7 +693,7 @@ mod tests {
             let mut attr = [0u8; 16];
             attr[0] = i;
             index
-                .insert(&entity1, &AttributeId(attr), 1)
+                .insert(&entity1, &AttributeId(attr), 0, 1)
                 .expect("insert");
         }
 
@@ -504,7 +702,7 @@ mod tests {
             let mut attr = [0u8; 16];
             attr[0] = i + 100;
             index
-                .insert(&entity2, &AttributeId(attr), 1)
+                .insert(&entity2, &AttributeId(attr), 0, 1)
                 .expect("insert");
         }
 
@@ -537,21 +735,21 @@ mod tests {
         let attr = AttributeId([10u8; 16]);
 
         // Insert at txn 10
-        index.insert(&entity, &attr, 10).expect("insert");
+        index.insert(&entity, &attr, 0, 10).expect("insert");
 
         // Visible to txn >= 10
-        assert!(!index.is_visible(&entity, &attr, 9).expect("vis"));
-        assert!(index.is_visible(&entity, &attr, 10).expect("vis"));
-        assert!(index.is_visible(&entity, &attr, 100).expect("vis"));
+        assert!(!index.is_visible(&entity, &attr, 0, 9).expect("vis"));
+        assert!(index.is_visible(&entity, &attr, 0, 10).expect("vis"));
+        assert!(index.is_visible(&entity, &attr, 0, 100).expect("vis"));
 
         // Mark deleted at txn 50
-        index.mark_deleted(&entity, &attr, 50).expect("delete");
+        index.mark_deleted(&entity, &attr, 0, 50).expect("delete");
 
         // Now visible only in [10, 50)
-        assert!(!index.is_visible(&entity, &attr, 9).expect("vis"));
-        assert!(index.is_visible(&entity, &attr, 10).expect("vis"));
-        assert!(index.is_visible(&entity, &attr, 49).expect("vis"));
-        assert!(!index.is_visible(&entity, &attr, 50).expect("vis"));
+        assert!(!index.is_visible(&entity, &attr, 0, 9).expect("vis"));
+        assert!(index.is_visible(&entity, &attr, 0, 10).expect("vis"));
+        assert!(index.is_visible(&entity, &attr, 0, 49).expect("vis"));
+        assert!(!index.is_visible(&entity, &attr, 0, 50).expect("vis"));
     }
 
     #[test]
@@ -565,11 +763,11 @@ mod tests {
         let entity = EntityId([1u8; 16]);
         let attr = AttributeId([10u8; 16]);
 
-        index.insert(&entity, &attr, 1).expect("insert");
-        assert!(index.contains(&entity, &attr).expect("contains"));
+        index.insert(&entity, &attr, 0, 1).expect("insert");
+        assert!(index.contains(&entity, &attr, 0).expect("contains"));
 
-        index.remove(&entity, &attr).expect("remove");
-        assert!(!index.contains(&entity, &attr).expect("contains"));
+        index.remove(&entity, &attr, 0).expect("remove");
+        assert!(!index.contains(&entity, &attr, 0).expect("contains"));
     }
 
     #[test]
@@ -587,9 +785,9 @@ mod tests {
         let attr2 = AttributeId([2u8; 16]); // created at 20
         let attr3 = AttributeId([3u8; 16]); // created at 30
 
-        index.insert(&entity, &attr1, 10).expect("insert");
-        index.insert(&entity, &attr2, 20).expect("insert");
-        index.insert(&entity, &attr3, 30).expect("insert");
+        index.insert(&entity, &attr1, 0, 10).expect("insert");
+        index.insert(&entity, &attr2, 0, 20).expect("insert");
+        index.insert(&entity, &attr3, 0, 30).expect("insert");
 
         // Scan at snapshot 15 - should only see attr1
         let mut scan = index.scan_entity_visible(&entity, 15).expect("scan");
@@ -608,4 +806,46 @@ mod tests {
         }
         assert_eq!(attributes.len(), 2);
     }
+
+    #[test]
+    fn test_scans_yield_each_attribute_once() {
+        let (_dir, path) = create_test_db();
+        let mut file = DatabaseFile::create(&path, test_pool()).expect("create db");
+        let entity = EntityId([1u8; 16]);
+        let tags = AttributeId([1u8; 16]);
+        let name = AttributeId([2u8; 16]);
+
+        let root = {
+            let mut index = EntityAttributeIndex::new(&mut file, 0).expect("create index");
+            // Three values of a cardinality-many attribute, the first deleted
+            index.insert(&entity, &tags, 7, 10).expect("insert");
+            index.insert(&entity, &tags, 8, 10).expect("insert");
+            index.insert(&entity, &tags, 9, 20).expect("insert");
+            index.mark_deleted(&entity, &tags, 7, 30).expect("delete");
+            index.insert(&entity, &name, 0, 10).expect("insert");
+
+            let mut scan = index.scan_entity_visible(&entity, 40).expect("scan");
+            let mut attributes = Vec::new();
+            while let Some(a) = scan.next_attribute().expect("next") {
+                attributes.push(a);
+            }
+            assert_eq!(attributes, vec![tags, name]);
+            index.root_page()
+        };
+
+        let reader = EntityAttributeIndexReader::new(&file, root);
+        let mut scan = reader.scan_entity_visible(&entity, 40).expect("scan");
+        let mut attributes = Vec::new();
+        while let Some(a) = scan.next_attribute().expect("next") {
+            attributes.push(a);
+        }
+        assert_eq!(attributes, vec![tags, name]);
+
+        let mut live = reader.scan_live().expect("scan live");
+        let mut pairs = Vec::new();
+        while let Some(pair) = live.next_pair().expect("next") {
+            pairs.push(pair);
+        }
+        assert_eq!(pairs, vec![(entity, tags), (entity, name)]);
+    }
 }
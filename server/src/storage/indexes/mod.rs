@@ -2,8 +2,9 @@
 //!
 //! Indexes provide efficient access patterns for triple data:
 //! - Primary index: (`entity_id`, `attribute_id`) -> full triple record
-//! - Attribute index: `attribute_id` -> [(`entity_id`, pointer)] (Phase 4)
-//! - Value indexes: (`attribute_id`, value) -> [`entity_id`] (Phase 4)
+//! - Attribute index: `attribute_id` -> [`entity_id`], with value entries
+//!   (`attribute_id`, value) -> [`entity_id`] for range scans
+//! - Entity-attribute index: `entity_id` -> [`attribute_id`]
 
 pub mod attribute;
 pub mod entity_attribute;
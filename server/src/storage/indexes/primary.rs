@@ -1,18 +1,27 @@
 //! Primary index implementation.
 //!
-//! The primary index maps (`entity_id`, `attribute_id`) to the full triple record.
-//! It is backed by a B-tree and provides efficient point lookups and entity scans.
+//! The primary index maps (`entity_id`, `attribute_id`, `value_hash`) to the
+//! full triple record. It is backed by a B-tree and provides efficient point
+//! lookups and entity scans.
+//!
+//! A cardinality-one triple's value hash is 0, so `get` finds it by entity
+//! and attribute alone. Each value of a cardinality-many attribute is keyed by
+//! its own hash (see `Cardinality::value_hash`); `get_values` finds them all.
 
-use crate::storage::btree::{BTree, BTreeError, make_key, split_key};
-#[cfg(unix)]
+use crate::storage::btree::{
+    BTree, BTreeError, key_value_hash, make_key, make_value_key, split_key,
+};
 use crate::storage::btree::{BTreeReader, BTreeReaderIterator};
 use crate::storage::file::DatabaseFile;
 use crate::storage::page::PageId;
-use crate::types::{AttributeId, EntityId, TripleError, TripleRecord, TxnId};
+use crate::types::{
+    AttributeId, EntityId, HlcTimestamp, TXN_RANGE_PREFIX_SIZE, TripleError, TripleRecord, TxnId,
+    TxnRange,
+};
 
 /// Primary index for triple storage.
 ///
-/// Maps (`entity_id`, `attribute_id`) -> `TripleRecord`.
+/// Maps (`entity_id`, `attribute_id`, `value_hash`) -> `TripleRecord`.
 pub struct PrimaryIndex<'a> {
     tree: BTree<'a>,
 }
@@ -38,12 +47,25 @@ impl<'a> PrimaryIndex<'a> {
     }
 
     /// Look up a single triple by entity and attribute ID.
+    ///
+    /// Finds the triple of a cardinality-one attribute; use `get_value` or
+    /// `get_values` for a cardinality-many attribute.
     pub fn get(
         &mut self,
         entity_id: &EntityId,
         attribute_id: &AttributeId,
     ) -> Result<Option<TripleRecord>, PrimaryIndexError> {
-        let key = make_key(entity_id, attribute_id);
+        self.get_value(entity_id, attribute_id, 0)
+    }
+
+    /// Look up a single triple by entity ID, attribute ID and value hash.
+    pub fn get_value(
+        &mut self,
+        entity_id: &EntityId,
+        attribute_id: &AttributeId,
+        value_hash: u64,
+    ) -> Result<Option<TripleRecord>, PrimaryIndexError> {
+        let key = make_value_key(entity_id, attribute_id, value_hash);
         let value = self.tree.get(&key)?;
 
         match value {
@@ -55,6 +77,34 @@ impl<'a> PrimaryIndex<'a> {
         }
     }
 
+    /// Get every triple of an entity and attribute, in value hash order.
+    ///
+    /// Returns all versions including deleted records.
+    pub fn get_values(
+        &mut self,
+        entity_id: &EntityId,
+        attribute_id: &AttributeId,
+    ) -> Result<Vec<TripleRecord>, PrimaryIndexError> {
+        // The cursor yields overflow references as stored, so find the keys
+        // first and read each record with a point lookup
+        let mut cursor = self.tree.iter_from(&make_key(entity_id, attribute_id))?;
+        let mut value_hashes = Vec::new();
+        while let Some((key, _)) = cursor.next_entry()? {
+            if split_key(&key) != (*entity_id, *attribute_id) {
+                break;
+            }
+            value_hashes.push(key_value_hash(&key));
+        }
+
+        let mut records = Vec::with_capacity(value_hashes.len());
+        for value_hash in value_hashes {
+            if let Some(record) = self.get_value(entity_id, attribute_id, value_hash)? {
+                records.push(record);
+            }
+        }
+        Ok(records)
+    }
+
     /// Look up a single triple, checking visibility against a snapshot.
     pub fn get_visible(
         &mut self,
@@ -70,14 +120,14 @@ impl<'a> PrimaryIndex<'a> {
         }
     }
 
-    /// Insert a new triple record.
+    /// Insert a new triple record, keyed by its value hash.
     ///
     /// Returns the old record if updating an existing triple.
     pub fn insert(
         &mut self,
         record: &TripleRecord,
     ) -> Result<Option<TripleRecord>, PrimaryIndexError> {
-        let key = make_key(&record.entity_id, &record.attribute_id);
+        let key = make_value_key(&record.entity_id, &record.attribute_id, record.value_hash());
         let value = record.to_bytes();
 
         let old_value = self.tree.insert(key, value)?;
@@ -91,20 +141,38 @@ impl<'a> PrimaryIndex<'a> {
         }
     }
 
-    /// Mark a triple as deleted by setting its `deleted_txn`.
+    /// Insert many triple records, in order, as `insert` would one at a
+    /// time.
+    ///
+    /// Records sorted by key are cheapest (see `BTree::insert_many`).
+    pub fn insert_many<'r>(
+        &mut self,
+        records: impl IntoIterator<Item = &'r TripleRecord>,
+    ) -> Result<(), PrimaryIndexError> {
+        self.tree.insert_many(records.into_iter().map(|record| {
+            let key = make_value_key(&record.entity_id, &record.attribute_id, record.value_hash());
+            (key, record.to_bytes())
+        }))?;
+        Ok(())
+    }
+
+    /// Mark a triple as deleted by setting its `deleted_txn` and `deleted_hlc`.
     ///
     /// Returns the updated record, or None if not found.
     pub fn mark_deleted(
         &mut self,
         entity_id: &EntityId,
         attribute_id: &AttributeId,
+        value_hash: u64,
         deleted_txn: TxnId,
+        deleted_hlc: HlcTimestamp,
     ) -> Result<Option<TripleRecord>, PrimaryIndexError> {
-        let Some(mut record) = self.get(entity_id, attribute_id)? else {
+        let Some(mut record) = self.get_value(entity_id, attribute_id, value_hash)? else {
             return Ok(None);
         };
 
         record.deleted_txn = deleted_txn;
+        record.deleted_hlc = deleted_hlc;
         self.insert(&record)
     }
 
@@ -115,8 +183,9 @@ impl<'a> PrimaryIndex<'a> {
         &mut self,
         entity_id: &EntityId,
         attribute_id: &AttributeId,
+        value_hash: u64,
     ) -> Result<Option<TripleRecord>, PrimaryIndexError> {
-        let key = make_key(entity_id, attribute_id);
+        let key = make_value_key(entity_id, attribute_id, value_hash);
         let old_value = self.tree.remove(&key)?;
 
         match old_value {
@@ -202,12 +271,10 @@ impl<'a> PrimaryIndex<'a> {
 /// Read-only primary index accessor for concurrent snapshot reads.
 ///
 /// Uses position-independent reads to allow concurrent access from multiple threads.
-#[cfg(unix)]
 pub struct PrimaryIndexReader<'a> {
     tree: BTreeReader<'a>,
 }
 
-#[cfg(unix)]
 impl<'a> PrimaryIndexReader<'a> {
     /// Create a new read-only primary index accessor.
     ///
@@ -220,12 +287,25 @@ impl<'a> PrimaryIndexReader<'a> {
     }
 
     /// Look up a single triple by entity and attribute ID.
+    ///
+    /// Finds the triple of a cardinality-one attribute; use `get_value` or
+    /// `get_values` for a cardinality-many attribute.
     pub fn get(
         &self,
         entity_id: &EntityId,
         attribute_id: &AttributeId,
     ) -> Result<Option<TripleRecord>, PrimaryIndexError> {
-        let key = make_key(entity_id, attribute_id);
+        self.get_value(entity_id, attribute_id, 0)
+    }
+
+    /// Look up a single triple by entity ID, attribute ID and value hash.
+    pub fn get_value(
+        &self,
+        entity_id: &EntityId,
+        attribute_id: &AttributeId,
+        value_hash: u64,
+    ) -> Result<Option<TripleRecord>, PrimaryIndexError> {
+        let key = make_value_key(entity_id, attribute_id, value_hash);
         let value = self.tree.get(&key)?;
 
         match value {
@@ -237,6 +317,49 @@ impl<'a> PrimaryIndexReader<'a> {
         }
     }
 
+    /// Get every triple of an entity and attribute, in value hash order.
+    ///
+    /// Returns all versions including deleted records.
+    pub fn get_values(
+        &self,
+        entity_id: &EntityId,
+        attribute_id: &AttributeId,
+    ) -> Result<Vec<TripleRecord>, PrimaryIndexError> {
+        // The cursor yields overflow references as stored, so find the keys
+        // first and read each record with a point lookup
+        let mut cursor = self.tree.iter_from(&make_key(entity_id, attribute_id))?;
+        let mut value_hashes = Vec::new();
+        while let Some((key, _)) = cursor.next_entry()? {
+            if split_key(&key) != (*entity_id, *attribute_id) {
+                break;
+            }
+            value_hashes.push(key_value_hash(&key));
+        }
+
+        let mut records = Vec::with_capacity(value_hashes.len());
+        for value_hash in value_hashes {
+            if let Some(record) = self.get_value(entity_id, attribute_id, value_hash)? {
+                records.push(record);
+            }
+        }
+        Ok(records)
+    }
+
+    /// Look up a triple's MVCC transaction range without reading its value.
+    ///
+    /// Returns the range even for deleted records.
+    pub fn get_txn_range(
+        &self,
+        entity_id: &EntityId,
+        attribute_id: &AttributeId,
+    ) -> Result<Option<TxnRange>, PrimaryIndexError> {
+        let key = make_key(entity_id, attribute_id);
+        match self.tree.get_prefix(&key, TXN_RANGE_PREFIX_SIZE)? {
+            Some(bytes) => Ok(Some(TxnRange::from_record_prefix(&bytes)?)),
+            None => Ok(None),
+        }
+    }
+
     /// Look up a single triple, checking visibility against a snapshot.
     pub fn get_visible(
         &self,
@@ -296,7 +419,7 @@ impl<'a> PrimaryIndexReader<'a> {
     }
 
     /// Create a cursor over all triples in key order.
-    pub fn cursor(&self) -> Result<PrimaryIndexReaderCursor<'_>, PrimaryIndexError> {
+    pub fn cursor(&self) -> Result<PrimaryIndexReaderCursor<'a>, PrimaryIndexError> {
         let cursor = self.tree.cursor()?;
         Ok(PrimaryIndexReaderCursor {
             cursor,
@@ -308,28 +431,42 @@ impl<'a> PrimaryIndexReader<'a> {
     pub fn cursor_visible(
         &self,
         snapshot_txn: TxnId,
-    ) -> Result<PrimaryIndexReaderCursor<'_>, PrimaryIndexError> {
+    ) -> Result<PrimaryIndexReaderCursor<'a>, PrimaryIndexError> {
         let cursor = self.tree.cursor()?;
         Ok(PrimaryIndexReaderCursor {
             cursor,
             snapshot_txn: Some(snapshot_txn),
         })
     }
+
+    /// Create a cursor over visible triples at a given snapshot, starting at
+    /// `(entity_id, attribute_id)` (inclusive) and continuing in key order.
+    pub fn cursor_visible_from(
+        &self,
+        entity_id: &EntityId,
+        attribute_id: &AttributeId,
+        snapshot_txn: TxnId,
+    ) -> Result<PrimaryIndexReaderCursor<'a>, PrimaryIndexError> {
+        let start_key = make_key(entity_id, attribute_id);
+        let cursor = self.tree.iter_from(&start_key)?;
+        Ok(PrimaryIndexReaderCursor {
+            cursor,
+            snapshot_txn: Some(snapshot_txn),
+        })
+    }
 }
 
 /// Read-only cursor over all triples in the primary index.
-#[cfg(unix)]
 pub struct PrimaryIndexReaderCursor<'a> {
     cursor: BTreeReaderIterator<'a>,
     snapshot_txn: Option<TxnId>,
 }
 
-#[cfg(unix)]
 impl PrimaryIndexReaderCursor<'_> {
     /// Get the next triple record.
     pub fn next_record(&mut self) -> Result<Option<TripleRecord>, PrimaryIndexError> {
         loop {
-            let Some((_, value)) = self.cursor.next_entry()? else {
+            let Some((_, value)) = self.cursor.next_resolved_entry()? else {
                 return Ok(None);
             };
 
@@ -347,7 +484,6 @@ impl PrimaryIndexReaderCursor<'_> {
 }
 
 /// Read-only iterator over triples for a specific entity.
-#[cfg(unix)]
 pub struct EntityScanReaderIterator<'a> {
     cursor: BTreeReaderIterator<'a>,
     entity_id: EntityId,
@@ -355,7 +491,6 @@ pub struct EntityScanReaderIterator<'a> {
     done: bool,
 }
 
-#[cfg(unix)]
 impl EntityScanReaderIterator<'_> {
     /// Get the next triple for this entity.
     pub fn next_record(&mut self) -> Result<Option<TripleRecord>, PrimaryIndexError> {
@@ -364,7 +499,7 @@ impl EntityScanReaderIterator<'_> {
         }
 
         loop {
-            let Some((key, value)) = self.cursor.next_entry()? else {
+            let Some((key, value)) = self.cursor.next_resolved_entry()? else {
                 self.done = true;
                 return Ok(None);
             };
@@ -404,7 +539,7 @@ impl PrimaryIndexCursor<'_> {
     /// If a snapshot is set, skips records not visible to that snapshot.
     pub fn next_record(&mut self) -> Result<Option<TripleRecord>, PrimaryIndexError> {
         loop {
-            let Some((_, value)) = self.cursor.next_entry()? else {
+            let Some((_, value)) = self.cursor.next_resolved_entry()? else {
                 return Ok(None);
             };
 
@@ -444,7 +579,7 @@ impl EntityScanIterator<'_> {
         }
 
         loop {
-            let Some((key, value)) = self.cursor.next_entry()? else {
+            let Some((key, value)) = self.cursor.next_resolved_entry()? else {
                 self.done = true;
                 return Ok(None);
             };
@@ -516,8 +651,7 @@ mod tests {
     use super::*;
     use crate::storage::buffer_pool::BufferPool;
     use crate::storage::file::DatabaseFile;
-    use crate::types::HlcTimestamp;
-    use crate::types::TripleValue;
+    use crate::types::{Cardinality, TripleValue};
     use std::sync::Arc;
     use tempfile::tempdir;
 
@@ -628,7 +762,7 @@ mod tests {
 
         // Mark deleted at txn 50
         index
-            .mark_deleted(&entity_id, &attribute_id, 50)
+            .mark_deleted(&entity_id, &attribute_id, 0, 50, HlcTimestamp::new(2000, 0))
             .expect("mark deleted");
 
         // Now visible only in range [10, 50)
@@ -716,6 +850,45 @@ mod tests {
         assert_eq!(count, 3);
     }
 
+    #[test]
+    fn test_primary_index_cursors_read_overflow_values() {
+        let (_dir, path) = create_test_db();
+        let pool = test_pool();
+        let mut file = DatabaseFile::create(&path, pool).expect("create db");
+
+        let entity_id = EntityId([1u8; 16]);
+        let large_value = TripleValue::String("x".repeat(20_000));
+        let mut index = PrimaryIndex::new(&mut file, 0).expect("create index");
+        for (attribute, value) in [(1u8, large_value.clone()), (2, TripleValue::Number(2.0))] {
+            let record = TripleRecord::new(
+                entity_id,
+                AttributeId([attribute; 16]),
+                1,
+                HlcTimestamp::new(1000, 0),
+                value,
+            );
+            index.insert(&record).expect("insert");
+        }
+
+        let mut cursor = index.cursor().expect("cursor");
+        let record = cursor.next_record().expect("next").expect("record");
+        assert_eq!(record.value, large_value);
+        let mut scan = index.scan_entity(&entity_id).expect("scan");
+        let record = scan.next_record().expect("next").expect("record");
+        assert_eq!(record.value, large_value);
+
+        let root_page = index.root_page();
+        let reader = PrimaryIndexReader::new(&file, root_page);
+        let mut cursor = reader.cursor().expect("cursor");
+        let record = cursor.next_record().expect("next").expect("record");
+        assert_eq!(record.value, large_value);
+        let mut scan = reader.scan_entity(&entity_id).expect("scan");
+        let record = scan.next_record().expect("next").expect("record");
+        assert_eq!(record.value, large_value);
+        let record = scan.next_record().expect("next").expect("record");
+        assert_eq!(record.value, TripleValue::Number(2.0));
+    }
+
     #[test]
     fn test_primary_index_remove() {
         let (_dir, path) = create_test_db();
@@ -738,7 +911,7 @@ mod tests {
         index.insert(&record).expect("insert");
         assert!(index.get(&entity_id, &attribute_id).expect("get").is_some());
 
-        let removed = index.remove(&entity_id, &attribute_id).expect("remove");
+        let removed = index.remove(&entity_id, &attribute_id, 0).expect("remove");
         assert!(removed.is_some());
         assert_eq!(removed.unwrap().value, TripleValue::Boolean(true));
 
@@ -750,6 +923,74 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_primary_index_many_values() {
+        let (_dir, path) = create_test_db();
+        let mut file = DatabaseFile::create(&path, test_pool()).expect("create db");
+        let mut index = PrimaryIndex::new(&mut file, 0).expect("create index");
+
+        let entity_id = EntityId([1u8; 16]);
+        let tags = AttributeId([2u8; 16]);
+        let mut hashes = Vec::new();
+        for tag in ["red", "green", "blue"] {
+            let record = TripleRecord::new(
+                entity_id,
+                tags,
+                1,
+                HlcTimestamp::new(1000, 0),
+                TripleValue::String(tag.to_string()),
+            )
+            .with_cardinality(Cardinality::Many);
+            hashes.push(record.value_hash());
+            assert!(index.insert(&record).expect("insert").is_none());
+        }
+        // Values of the next attribute stay out of the scan
+        let other = TripleRecord::new(
+            entity_id,
+            AttributeId([3u8; 16]),
+            1,
+            HlcTimestamp::new(1000, 0),
+            TripleValue::Null,
+        );
+        index.insert(&other).expect("insert");
+
+        assert!(index.get(&entity_id, &tags).expect("get").is_none());
+        assert_eq!(
+            index.get_values(&entity_id, &tags).expect("values").len(),
+            3
+        );
+
+        let deleted = index
+            .mark_deleted(&entity_id, &tags, hashes[1], 2, HlcTimestamp::new(2000, 0))
+            .expect("mark deleted")
+            .expect("exists");
+        assert_eq!(deleted.value, TripleValue::String("green".to_string()));
+        let live: Vec<TripleValue> = index
+            .get_values(&entity_id, &tags)
+            .expect("values")
+            .into_iter()
+            .filter(|record| !record.is_deleted())
+            .map(|record| record.value)
+            .collect();
+        assert_eq!(live.len(), 2);
+        assert!(!live.contains(&TripleValue::String("green".to_string())));
+
+        index
+            .remove(&entity_id, &tags, hashes[1])
+            .expect("remove")
+            .expect("exists");
+        assert!(
+            index
+                .get_value(&entity_id, &tags, hashes[1])
+                .expect("get")
+                .is_none()
+        );
+        assert_eq!(
+            index.get_values(&entity_id, &tags).expect("values").len(),
+            2
+        );
+    }
+
     #[test]
     fn test_primary_index_persistence() {
         let (_dir, path) = create_test_db();
@@ -100,7 +100,8 @@ impl From<WalError> for StorageError {
 /// Implementations must ensure:
 /// - `read_page` returns the last written content for a page
 /// - `sync` makes all previous writes durable
-/// - `allocate_pages` extends the storage capacity
+/// - `allocate_pages` returns pages not in use, extending the storage
+///   capacity as needed
 /// - Superblock changes are persisted on `write_superblock` + `sync`
 pub trait Storage {
     // ========== Buffer Pool ==========
@@ -127,7 +128,8 @@ pub trait Storage {
     /// Sync all pending writes to durable storage.
     fn sync(&mut self) -> Result<(), StorageError>;
 
-    /// Allocate new pages at the end of storage.
+    /// Allocate pages not in use, at the end of storage unless the
+    /// implementation reuses freed pages.
     ///
     /// Returns the page ID of the first allocated page.
     fn allocate_pages(&mut self, count: u64) -> Result<PageId, StorageError>;
@@ -0,0 +1,215 @@
+//! In-memory stand-in for a database file.
+//!
+//! `MemoryFile` behaves like a growable file of bytes: it supports `Read`,
+//! `Write` and `Seek` at arbitrary offsets, `set_len`, and positional reads.
+//! `DatabaseFile` uses it to back in-memory databases, so they run the same
+//! page, B-tree and WAL code as file-backed ones.
+//!
+//! Bytes are kept in fixed-size chunks allocated on first write. Like a sparse
+//! file, regions that were never written, such as most of a freshly allocated
+//! WAL, read as zeros without taking memory.
+
+use std::collections::HashMap;
+use std::io::{Read, Seek, SeekFrom, Write};
+
+/// Size of the chunks bytes are stored in.
+const CHUNK_SIZE: u64 = 64 * 1024;
+
+/// A sparse, growable byte buffer with a file-like cursor.
+#[derive(Debug, Default)]
+pub struct MemoryFile {
+    /// Written chunks, by chunk index. Missing chunks read as zeros.
+    chunks: HashMap<u64, Box<[u8]>>,
+    /// Length in bytes.
+    len: u64,
+    /// Cursor position for `Read`, `Write` and `Seek`.
+    position: u64,
+}
+
+impl MemoryFile {
+    /// Create an empty file.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Length in bytes.
+    #[must_use]
+    pub const fn len(&self) -> u64 {
+        self.len
+    }
+
+    /// Whether the file holds no bytes.
+    #[must_use]
+    pub const fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Truncate or extend the file to `len` bytes.
+    ///
+    /// # Post-conditions
+    /// - Bytes past the old length read as zeros
+    /// - The cursor is unchanged, as for `File::set_len`
+    pub fn set_len(&mut self, len: u64) {
+        if len < self.len {
+            // Drop whole chunks past the end and zero the tail of the last one,
+            // so a later extension reads zeros there
+            self.chunks.retain(|&index, _| index * CHUNK_SIZE < len);
+            let (index, offset) = split(len);
+            if let Some(chunk) = self.chunks.get_mut(&index) {
+                chunk[offset..].fill(0);
+            }
+        }
+        self.len = len;
+    }
+
+    /// Fill `buffer` from the bytes starting at `offset`, without moving the
+    /// cursor.
+    ///
+    /// # Errors
+    ///
+    /// Returns `ErrorKind::UnexpectedEof` if the range ends past the end of
+    /// the file.
+    pub fn read_exact_at(&self, buffer: &mut [u8], offset: u64) -> std::io::Result<()> {
+        if offset.saturating_add(buffer.len() as u64) > self.len {
+            return Err(std::io::ErrorKind::UnexpectedEof.into());
+        }
+        let mut done = 0;
+        while done < buffer.len() {
+            let (index, start) = split(offset + done as u64);
+            let count = (chunk_len() - start).min(buffer.len() - done);
+            let target = &mut buffer[done..done + count];
+            match self.chunks.get(&index) {
+                Some(chunk) => target.copy_from_slice(&chunk[start..start + count]),
+                None => target.fill(0),
+            }
+            done += count;
+        }
+        Ok(())
+    }
+
+    /// Copy `bytes` into the file at `offset`, extending it if needed.
+    fn write_at(&mut self, bytes: &[u8], offset: u64) {
+        let mut done = 0;
+        while done < bytes.len() {
+            let (index, start) = split(offset + done as u64);
+            let count = (chunk_len() - start).min(bytes.len() - done);
+            let chunk = self
+                .chunks
+                .entry(index)
+                .or_insert_with(|| vec![0u8; chunk_len()].into_boxed_slice());
+            chunk[start..start + count].copy_from_slice(&bytes[done..done + count]);
+            done += count;
+        }
+        self.len = self.len.max(offset + bytes.len() as u64);
+    }
+}
+
+/// `CHUNK_SIZE` as a `usize`.
+#[allow(clippy::cast_possible_truncation)] // 64KB fits in any usize
+const fn chunk_len() -> usize {
+    CHUNK_SIZE as usize
+}
+
+/// Split a byte offset into its chunk index and the offset within the chunk.
+#[allow(clippy::cast_possible_truncation)] // The remainder is below CHUNK_SIZE
+const fn split(offset: u64) -> (u64, usize) {
+    (offset / CHUNK_SIZE, (offset % CHUNK_SIZE) as usize)
+}
+
+impl Read for MemoryFile {
+    fn read(&mut self, buffer: &mut [u8]) -> std::io::Result<usize> {
+        let available = self.len.saturating_sub(self.position);
+        #[allow(clippy::cast_possible_truncation)] // Bounded by buffer.len()
+        let count = available.min(buffer.len() as u64) as usize;
+        self.read_exact_at(&mut buffer[..count], self.position)?;
+        self.position += count as u64;
+        Ok(count)
+    }
+}
+
+impl Write for MemoryFile {
+    fn write(&mut self, bytes: &[u8]) -> std::io::Result<usize> {
+        self.write_at(bytes, self.position);
+        self.position += bytes.len() as u64;
+        Ok(bytes.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+impl Seek for MemoryFile {
+    fn seek(&mut self, position: SeekFrom) -> std::io::Result<u64> {
+        let target = match position {
+            SeekFrom::Start(offset) => Some(offset),
+            SeekFrom::End(delta) => self.len.checked_add_signed(delta),
+            SeekFrom::Current(delta) => self.position.checked_add_signed(delta),
+        };
+        let Some(target) = target else {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                "seek to a negative or overflowing position",
+            ));
+        };
+        self.position = target;
+        Ok(target)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_write_and_read_across_chunks() {
+        let mut file = MemoryFile::new();
+        let bytes: Vec<u8> = (0..200_000u32).map(|i| (i % 251) as u8).collect();
+
+        file.seek(SeekFrom::Start(1000)).expect("seek");
+        file.write_all(&bytes).expect("write");
+        assert_eq!(file.len(), 201_000);
+
+        let mut read = vec![0u8; bytes.len()];
+        file.seek(SeekFrom::Start(1000)).expect("seek");
+        file.read_exact(&mut read).expect("read");
+        assert_eq!(read, bytes);
+
+        // The gap before the write reads as zeros
+        let mut gap = vec![1u8; 1000];
+        file.read_exact_at(&mut gap, 0).expect("read gap");
+        assert!(gap.iter().all(|&b| b == 0));
+    }
+
+    #[test]
+    fn test_read_past_end_fails() {
+        let mut file = MemoryFile::new();
+        file.write_all(b"hello").expect("write");
+
+        let mut buffer = [0u8; 6];
+        assert!(file.read_exact_at(&mut buffer, 0).is_err());
+        file.seek(SeekFrom::Start(3)).expect("seek");
+        assert_eq!(file.read(&mut buffer).expect("read"), 2);
+        assert_eq!(&buffer[..2], b"lo");
+    }
+
+    #[test]
+    fn test_set_len_truncates_and_zero_extends() {
+        let mut file = MemoryFile::new();
+        file.set_len(3 * CHUNK_SIZE);
+        assert_eq!(file.len(), 3 * CHUNK_SIZE);
+        assert!(file.chunks.is_empty());
+
+        file.seek(SeekFrom::Start(CHUNK_SIZE - 2)).expect("seek");
+        file.write_all(&[7u8; 4]).expect("write");
+        file.set_len(CHUNK_SIZE - 1);
+        assert_eq!(file.chunks.len(), 1);
+        file.set_len(2 * CHUNK_SIZE);
+
+        let mut buffer = [0u8; 4];
+        file.read_exact_at(&mut buffer, CHUNK_SIZE - 2)
+            .expect("read");
+        assert_eq!(buffer, [7, 0, 0, 0]);
+    }
+}
@@ -4,7 +4,8 @@
 //!
 //! # File Format
 //!
-//! The database is stored in a single file with 8KB pages:
+//! The database is stored in a single file of fixed-size pages (8KB by default,
+//! chosen at creation and recorded in the superblock):
 //!
 //! - Page 0: Superblock (metadata about the database)
 //! - Pages 1-N: Allocation bitmap (tracks free/used pages)
@@ -36,43 +37,70 @@
 //! ```
 
 mod allocator;
+pub mod attribute_cardinality;
+pub mod attribute_names;
+pub mod backup;
 pub mod btree;
 pub mod buffer_pool;
 pub mod checkpoint;
+pub mod checkpoint_task;
+pub mod checksum;
 mod database;
+pub mod entity_versions;
 mod file;
 pub mod gc;
 pub mod hlc;
 pub mod indexes;
 pub mod io;
+mod memory_file;
 pub mod overflow;
 mod page;
 pub mod recovery;
+pub mod schema;
 mod superblock;
 pub mod time;
 pub mod tombstone;
 mod transaction;
+pub mod triggers;
+pub mod upgrade;
 pub mod wal;
 
 pub use allocator::PageAllocator;
 pub use buffer_pool::{BufferPool, DEFAULT_POOL_CAPACITY};
 pub use checkpoint::{
-    CheckpointConfig, CheckpointError, CheckpointResult, CheckpointState, force_checkpoint,
-    maybe_checkpoint, perform_checkpoint,
+    CheckpointConfig, CheckpointControl, CheckpointError, CheckpointProgress, CheckpointResult,
+    CheckpointState, force_checkpoint, maybe_checkpoint, perform_checkpoint,
+    perform_checkpoint_with,
 };
-pub use database::{Database, DatabaseError, GcStats, GcTickResult, Snapshot};
-pub use file::{DatabaseFile, FileError};
+pub use checkpoint_task::spawn_checkpoint_task;
+pub use checksum::ChecksumAlgorithm;
+pub use database::{
+    AppliedChange, AttributeEntityIterator, CommitOutcome, Database, DatabaseError, EntityIterator,
+    ExpirySweepResult, GcStats, GcTickResult, IsolationLevel, OptimisticTransaction,
+    PinnedSnapshot, RestoreResult, SavepointId, Snapshot, StorageStats, TripleIterator,
+};
+pub use file::{DatabaseFile, FileBacking, FileError, FileFormat, WalCompaction, WalGrowth};
 pub use gc::{GcConfig, spawn_gc_task};
 pub use hlc::{Clock as HlcClock, ClockError as HlcClockError};
 pub use indexes::primary::{PrimaryIndex, PrimaryIndexError};
 pub use io::{Storage, StorageError};
-pub use page::{PAGE_SIZE, Page, PageError, PageHeader, PageId, PageType};
-pub use recovery::{RecoveryError, RecoveryResult, needs_recovery, recover};
-pub use superblock::{Superblock, SuperblockError};
+pub use memory_file::MemoryFile;
+pub use page::{
+    MAX_PAGE_SIZE, MIN_PAGE_SIZE, PAGE_SIZE, Page, PageError, PageHeader, PageId, PageType,
+    SUPPORTED_PAGE_SIZES, is_supported_page_size,
+};
+pub use recovery::{
+    IndexMismatch, RecoveryError, RecoveryResult, VerificationReport, check_index_consistency,
+    needs_recovery, recover, verify,
+};
+pub use superblock::{Superblock, SuperblockError, UNASSIGNED_NODE_ID};
 pub use time::{SystemTimeSource, TimeSource};
 pub use tombstone::{Tombstone, TombstoneError, TombstoneList};
 pub use transaction::{Transaction, TransactionError};
-pub use wal::{LogRecord, LogRecordPayload, LogRecordType, Lsn, Wal, WalError};
+pub use triggers::{TriggerContext, TriggerEvent, TriggerId};
+pub use wal::{
+    LogRecord, LogRecordPayload, LogRecordType, Lsn, Wal, WalError, WalRecords, WalScan,
+};
 
 use crate::types::{ChangeNotification, ConnectionId};
 
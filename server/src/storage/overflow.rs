@@ -1,7 +1,7 @@
 //! Overflow page management for large values.
 //!
 //! Values larger than `MAX_INLINE_VALUE_SIZE` (1024 bytes) are stored in
-//! overflow pages. Each overflow page can hold up to ~8KB of data, and
+//! overflow pages. Each overflow page holds nearly a full page of data, and
 //! pages are chained together for values larger than a single page.
 //!
 //! # Overflow Page Format
@@ -26,7 +26,7 @@
 //! ```
 
 use crate::storage::file::{DatabaseFile, FileError};
-use crate::storage::page::{PAGE_SIZE, PageHeader, PageId, PageType};
+use crate::storage::page::{PageHeader, PageId, PageType};
 
 /// Size of overflow page header (after page header).
 /// - Next page ID: 8 bytes
@@ -36,8 +36,11 @@ const OVERFLOW_HEADER_SIZE: usize = 12;
 /// Offset where overflow data starts.
 const OVERFLOW_DATA_OFFSET: usize = PageHeader::SIZE + OVERFLOW_HEADER_SIZE;
 
-/// Maximum data per overflow page.
-pub const OVERFLOW_DATA_PER_PAGE: usize = PAGE_SIZE - OVERFLOW_DATA_OFFSET;
+/// Maximum data per overflow page for a page of `page_size` bytes.
+#[must_use]
+pub const fn overflow_data_per_page(page_size: usize) -> usize {
+    page_size - OVERFLOW_DATA_OFFSET
+}
 
 /// Marker byte indicating an overflow reference.
 pub const OVERFLOW_MARKER: u8 = 0xFF;
@@ -114,6 +117,7 @@ pub fn write_overflow(file: &mut DatabaseFile, value: &[u8]) -> Result<OverflowR
     }
 
     let total_length = value.len();
+    let data_per_page = overflow_data_per_page(file.page_size());
     let mut remaining = value;
     let mut first_page = 0;
     let mut prev_page_id = 0;
@@ -123,7 +127,7 @@ pub fn write_overflow(file: &mut DatabaseFile, value: &[u8]) -> Result<OverflowR
         let page_id = file.allocate_pages(1)?;
 
         // Determine how much data goes in this page
-        let chunk_size = remaining.len().min(OVERFLOW_DATA_PER_PAGE);
+        let chunk_size = remaining.len().min(data_per_page);
         let (chunk, rest) = remaining.split_at(chunk_size);
         remaining = rest;
 
@@ -217,7 +221,6 @@ pub fn read_overflow(
 ///
 /// This is a read-only version of `read_overflow` that uses `read_page_at`
 /// to allow concurrent reads without requiring mutable access to the file.
-#[cfg(unix)]
 pub fn read_overflow_at(
     file: &DatabaseFile,
     overflow_ref: &OverflowRef,
@@ -255,11 +258,57 @@ pub fn read_overflow_at(
     Ok(result)
 }
 
+/// Read the first `len` bytes of a large value from overflow pages.
+///
+/// Follows the chain only as far as needed, so a short prefix of a
+/// multi-page value costs a single page read.
+///
+/// # Post-conditions
+/// - Returns `min(len, total_length)` bytes
+pub fn read_overflow_prefix_at(
+    file: &DatabaseFile,
+    overflow_ref: &OverflowRef,
+    len: usize,
+) -> Result<Vec<u8>, OverflowError> {
+    let wanted = len.min(overflow_ref.total_length as usize);
+    let mut result = Vec::with_capacity(wanted);
+    let mut current_page_id = overflow_ref.first_page;
+
+    while result.len() < wanted {
+        if current_page_id == 0 {
+            return Err(OverflowError::LengthMismatch {
+                expected: overflow_ref.total_length as usize,
+                actual: result.len(),
+            });
+        }
+        let page = file.read_page_at(current_page_id)?;
+
+        // Verify page type
+        let page_type = page.read_u8(0);
+        if page_type != PageType::Overflow as u8 {
+            return Err(OverflowError::InvalidPageType(page_type));
+        }
+
+        // Read overflow header
+        let next_page = page.read_u64(PageHeader::SIZE);
+        let data_length = page.read_u32(PageHeader::SIZE + 8) as usize;
+
+        // Read only as much data as still needed
+        let take = data_length.min(wanted - result.len());
+        result.extend_from_slice(page.read_bytes(OVERFLOW_DATA_OFFSET, take));
+
+        current_page_id = next_page;
+    }
+
+    Ok(result)
+}
+
 /// Free overflow pages.
 ///
-/// Follows the overflow page chain and marks pages as free.
-/// Note: Currently this just counts the pages without actually freeing them.
-/// The pages become orphaned and can be reclaimed by a future compaction process.
+/// Follows the overflow page chain and returns each page to the file's free
+/// list, so later allocations reuse it.
+///
+/// Returns the number of pages freed.
 pub fn free_overflow(
     file: &mut DatabaseFile,
     overflow_ref: &OverflowRef,
@@ -269,25 +318,10 @@ pub fn free_overflow(
 
     while current_page_id != 0 {
         let page = file.read_page(current_page_id)?;
-
-        // Read next page
         let next_page = page.read_u64(PageHeader::SIZE);
+        drop(page);
 
-        // Mark the page as free by writing a Free page type
-        // This makes the pages identifiable for future reclamation
-        let mut free_page = file
-            .buffer_pool()
-            .lease_page_zeroed()
-            .ok_or(OverflowError::File(FileError::BufferPoolExhausted))?;
-        let header = PageHeader {
-            page_type: PageType::Free,
-            flags: 0,
-            checksum: 0,
-        };
-        free_page.write_bytes(0, &header.to_bytes());
-        // Preserve the chain pointer so reclamation can follow the chain
-        free_page.write_u64(PageHeader::SIZE, next_page);
-        file.write_page(current_page_id, &free_page)?;
+        file.free_pages(current_page_id, 1)?;
 
         pages_counted += 1;
         current_page_id = next_page;
@@ -411,6 +445,29 @@ mod tests {
         assert_eq!(restored, value);
     }
 
+    #[test]
+    fn test_overflow_prefix() {
+        let (_dir, path) = create_test_db();
+        let pool = test_pool();
+        let mut file = DatabaseFile::create(&path, pool).expect("create db");
+
+        #[allow(clippy::cast_possible_truncation)]
+        let value: Vec<u8> = (0..20000u32).map(|i| i as u8).collect();
+        let overflow_ref = write_overflow(&mut file, &value).expect("write overflow");
+
+        let prefix = read_overflow_prefix_at(&file, &overflow_ref, 48).expect("read prefix");
+        assert_eq!(prefix, &value[..48]);
+
+        // A prefix spanning pages follows the chain
+        let spanning = overflow_data_per_page(file.page_size()) + 10;
+        let prefix = read_overflow_prefix_at(&file, &overflow_ref, spanning).expect("read prefix");
+        assert_eq!(prefix, &value[..spanning]);
+
+        // Asking for more than the value returns the whole value
+        let whole = read_overflow_prefix_at(&file, &overflow_ref, 30000).expect("read prefix");
+        assert_eq!(whole, value);
+    }
+
     #[test]
     fn test_overflow_free() {
         let (_dir, path) = create_test_db();
@@ -433,7 +490,7 @@ mod tests {
         let mut file = DatabaseFile::create(&path, pool).expect("create db");
 
         // Value that exactly fills one page
-        let value = vec![0x11u8; OVERFLOW_DATA_PER_PAGE];
+        let value = vec![0x11u8; overflow_data_per_page(file.page_size())];
         let overflow_ref = write_overflow(&mut file, &value).expect("write overflow");
 
         let restored = read_overflow(&mut file, &overflow_ref).expect("read overflow");
@@ -1,19 +1,44 @@
 //! Page types and constants for the storage engine.
 //!
-//! The storage engine uses 8KB pages as the fundamental unit of I/O.
+//! The storage engine uses fixed-size pages as the fundamental unit of I/O.
+//! The page size is chosen when a database is created (8KB by default) and is
+//! recorded in the superblock so the file is always read with the same size.
 //!
 //! Pages are allocated from a buffer pool to reduce heap allocation overhead.
 //! When a page is dropped, its buffer is automatically returned to the pool.
+//! A pool's buffers all have the same size, chosen when it is created, and
+//! serve files of any page size up to it.
 
 use std::sync::Arc;
 
 use crate::storage::buffer_pool::BufferPool;
+use crate::storage::checksum::ChecksumAlgorithm;
 
-/// Page size in bytes (8KB).
+/// Default page size in bytes (8KB).
+///
+/// This is also the buffer size of a pool created with `BufferPool::new`.
 pub const PAGE_SIZE: usize = 8192;
 
-/// Page size as u64 for offset calculations.
-pub const PAGE_SIZE_U64: u64 = PAGE_SIZE as u64;
+/// Smallest supported page size in bytes (4KB).
+///
+/// A leaf must hold at least two maximum-size inline values so that it can
+/// always be split, which rules out smaller pages.
+pub const MIN_PAGE_SIZE: usize = 4096;
+
+/// Largest supported page size in bytes (64KB).
+pub const MAX_PAGE_SIZE: usize = 65536;
+
+/// Page sizes a database file may be created with.
+///
+/// Every entry is a power of two between `MIN_PAGE_SIZE` and `MAX_PAGE_SIZE`.
+pub const SUPPORTED_PAGE_SIZES: [usize; 5] =
+    [MIN_PAGE_SIZE, PAGE_SIZE, 16384, 32768, MAX_PAGE_SIZE];
+
+/// Check whether a page size is one of `SUPPORTED_PAGE_SIZES`.
+#[must_use]
+pub fn is_supported_page_size(page_size: usize) -> bool {
+    SUPPORTED_PAGE_SIZES.contains(&page_size)
+}
 
 /// A page identifier (0-indexed page number).
 pub type PageId = u64;
@@ -63,7 +88,7 @@ impl TryFrom<u8> for PageType {
 /// Layout:
 /// - `page_type`: 1 byte
 /// - `flags`: 1 byte
-/// - `checksum`: 4 bytes (CRC32)
+/// - `checksum`: 4 bytes (file's `ChecksumAlgorithm`)
 /// - `reserved`: 2 bytes
 ///
 /// Total: 8 bytes
@@ -78,7 +103,10 @@ impl PageHeader {
     /// Size of the page header in bytes.
     pub const SIZE: usize = 8;
 
-    /// Usable space in a page after the header.
+    /// Offset of the checksum within the header.
+    pub const CHECKSUM_OFFSET: usize = 2;
+
+    /// Usable space in a page of the default size after the header.
     pub const USABLE_SPACE: usize = PAGE_SIZE - Self::SIZE;
 
     /// Serialize the header to bytes.
@@ -106,6 +134,54 @@ impl PageHeader {
     }
 }
 
+/// Compute the checksum of a page image, reading the 4-byte checksum field
+/// at `checksum_offset` as zero so the stored checksum is not part of its own
+/// input.
+///
+/// # Pre-conditions
+/// - `checksum_offset + 4 <= bytes.len()`
+#[must_use]
+pub fn page_checksum(bytes: &[u8], checksum_offset: usize, algorithm: ChecksumAlgorithm) -> u32 {
+    let mut hasher = algorithm.hasher();
+    hasher.update(&bytes[..checksum_offset]);
+    hasher.update(&[0u8; 4]);
+    hasher.update(&bytes[checksum_offset + 4..]);
+    hasher.finalize()
+}
+
+/// Store the checksum of a page image in its page header.
+///
+/// `bytes` is the whole page, `page_size` bytes long.
+pub fn stamp_page_checksum(bytes: &mut [u8], algorithm: ChecksumAlgorithm) {
+    let checksum = page_checksum(bytes, PageHeader::CHECKSUM_OFFSET, algorithm);
+    bytes[PageHeader::CHECKSUM_OFFSET..PageHeader::CHECKSUM_OFFSET + 4]
+        .copy_from_slice(&checksum.to_le_bytes());
+}
+
+/// Check a page image against the checksum in its page header.
+///
+/// A page allocated but never written is all zeros, with no checksum to
+/// check, so it always passes.
+///
+/// # Errors
+/// Returns `PageError::ChecksumMismatch` if the page does not match its
+/// checksum, e.g. because a crash tore its write.
+pub fn verify_page_checksum(bytes: &[u8], algorithm: ChecksumAlgorithm) -> Result<(), PageError> {
+    let offset = PageHeader::CHECKSUM_OFFSET;
+    let expected = u32::from_le_bytes([
+        bytes[offset],
+        bytes[offset + 1],
+        bytes[offset + 2],
+        bytes[offset + 3],
+    ]);
+    let actual = page_checksum(bytes, offset, algorithm);
+    if actual == expected || bytes.iter().all(|byte| *byte == 0) {
+        Ok(())
+    } else {
+        Err(PageError::ChecksumMismatch { expected, actual })
+    }
+}
+
 /// A raw page buffer backed by a buffer pool.
 ///
 /// Pages are leased from a `BufferPool` and automatically returned when dropped.
@@ -113,10 +189,10 @@ impl PageHeader {
 ///
 /// # Invariants
 /// - Buffer is always Some until dropped
-/// - Buffer size is always `PAGE_SIZE` bytes
+/// - Buffer size is always the pool's `page_size` bytes
 pub struct Page {
     /// The underlying buffer. Option to allow `take()` in Drop.
-    buffer: Option<Box<[u8; PAGE_SIZE]>>,
+    buffer: Option<Box<[u8]>>,
     /// Reference back to the pool for return on drop.
     pool: Arc<BufferPool>,
 }
@@ -128,24 +204,27 @@ impl Page {
     /// `BufferPool::lease_zeroed()` to create pages.
     ///
     /// # Pre-conditions
-    /// - `buffer` must be a valid `PAGE_SIZE` buffer from `pool`
+    /// - `buffer` must be a buffer from `pool`, of its `page_size` bytes
     ///
     /// # Post-conditions
     /// - Page owns the buffer until dropped
-    pub(crate) const fn from_pool(buffer: Box<[u8; PAGE_SIZE]>, pool: Arc<BufferPool>) -> Self {
+    pub(crate) const fn from_pool(buffer: Box<[u8]>, pool: Arc<BufferPool>) -> Self {
         Self {
             buffer: Some(buffer),
             pool,
         }
     }
 
-    /// Get the raw page data.
+    /// Get the raw page data, the whole of the pool's buffer.
+    ///
+    /// A file whose page size is smaller than the buffer uses only its
+    /// first `page_size` bytes.
     ///
     /// # Panics
     /// Panics if called after the buffer has been taken (should never happen in normal use).
     #[must_use]
     #[allow(clippy::expect_used)] // Buffer being None indicates a bug
-    pub fn as_bytes(&self) -> &[u8; PAGE_SIZE] {
+    pub fn as_bytes(&self) -> &[u8] {
         self.buffer.as_ref().expect("buffer taken before drop")
     }
 
@@ -154,7 +233,7 @@ impl Page {
     /// # Panics
     /// Panics if called after the buffer has been taken (should never happen in normal use).
     #[allow(clippy::expect_used)] // Buffer being None indicates a bug
-    pub fn as_bytes_mut(&mut self) -> &mut [u8; PAGE_SIZE] {
+    pub fn as_bytes_mut(&mut self) -> &mut [u8] {
         self.buffer.as_mut().expect("buffer taken before drop")
     }
 
@@ -218,17 +297,12 @@ impl Page {
         self.as_bytes_mut()[offset..offset + 8].copy_from_slice(&value.to_le_bytes());
     }
 
-    /// Compute CRC32 checksum of the page data (excluding the checksum field itself).
+    /// Compute the checksum of the page data (excluding the checksum field itself)
+    /// with the file's checksum algorithm.
     /// Assumes checksum is stored at bytes 2-5 of the header.
     #[must_use]
-    pub fn compute_checksum(&self) -> u32 {
-        let data = self.as_bytes();
-        // For checksum calculation, we hash everything except the checksum field (bytes 2-5)
-        let mut hasher = crc32fast::Hasher::new();
-        hasher.update(&data[0..2]); // page_type + flags
-        hasher.update(&[0u8; 4]); // zero out checksum field
-        hasher.update(&data[6..]); // rest of page
-        hasher.finalize()
+    pub fn compute_checksum(&self, algorithm: ChecksumAlgorithm) -> u32 {
+        page_checksum(self.as_bytes(), PageHeader::CHECKSUM_OFFSET, algorithm)
     }
 
     /// Get a reference to the pool this page belongs to.
@@ -312,6 +386,17 @@ mod tests {
         BufferPool::new(10)
     }
 
+    #[test]
+    fn test_supported_page_sizes() {
+        assert!(is_supported_page_size(4096));
+        assert!(is_supported_page_size(8192));
+        assert!(is_supported_page_size(16384));
+        assert!(is_supported_page_size(65536));
+        assert!(!is_supported_page_size(2048));
+        assert!(!is_supported_page_size(5000));
+        assert!(!is_supported_page_size(131_072));
+    }
+
     #[test]
     fn test_page_header_roundtrip() {
         let header = PageHeader {
@@ -349,6 +434,46 @@ mod tests {
         assert_eq!(page.read_bytes(500, 5), b"hello");
     }
 
+    #[test]
+    fn test_page_checksum_ignores_checksum_field() {
+        let pool = test_pool();
+        let mut page = pool
+            .lease_zeroed()
+            .map(|buf| Page::from_pool(buf, Arc::clone(&pool)))
+            .expect("should lease");
+        page.write_bytes(500, b"hello");
+
+        for algorithm in [ChecksumAlgorithm::Crc32, ChecksumAlgorithm::Crc32c] {
+            let before = page.compute_checksum(algorithm);
+            page.write_u32(2, before);
+            assert_eq!(page.compute_checksum(algorithm), before);
+        }
+
+        let crc32 = page.compute_checksum(ChecksumAlgorithm::Crc32);
+        let crc32c = page.compute_checksum(ChecksumAlgorithm::Crc32c);
+        assert_ne!(crc32, crc32c);
+    }
+
+    #[test]
+    fn test_verify_page_checksum_detects_torn_page() {
+        let algorithm = ChecksumAlgorithm::Crc32c;
+        let mut bytes = vec![0u8; MIN_PAGE_SIZE];
+        // Allocated but never written
+        assert!(verify_page_checksum(&bytes, algorithm).is_ok());
+
+        bytes[0] = PageType::BTreeLeaf as u8;
+        bytes[100..105].copy_from_slice(b"hello");
+        stamp_page_checksum(&mut bytes, algorithm);
+        assert!(verify_page_checksum(&bytes, algorithm).is_ok());
+
+        // Only a prefix of a later write reached the disk
+        bytes[50..55].copy_from_slice(b"world");
+        assert!(matches!(
+            verify_page_checksum(&bytes, algorithm),
+            Err(PageError::ChecksumMismatch { .. })
+        ));
+    }
+
     #[test]
     fn test_page_type_conversion() {
         assert_eq!(PageType::try_from(0x01), Ok(PageType::Superblock));
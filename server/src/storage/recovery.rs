@@ -6,23 +6,53 @@
 //! # Recovery Process
 //!
 //! 1. Read superblock to get last checkpoint LSN
-//! 2. Scan WAL from checkpoint LSN to head
-//! 3. For each committed transaction:
-//!    - Replay INSERT, UPDATE, DELETE operations
-//!    - Skip uncommitted transactions (no COMMIT record)
+//! 2. First pass: scan WAL from checkpoint LSN to head, noting which
+//!    transactions committed
+//! 3. Second pass: scan again, replaying each committed transaction's
+//!    INSERT, UPDATE and DELETE operations when its COMMIT record is reached.
+//!    Operations of uncommitted transactions (no COMMIT record) are skipped
+//!    without being buffered
 //! 4. Update superblock with recovered state
 //!
+//! Before replaying, recovery checks the free page list and rebuilds it from
+//! a scan of the file if it names a page that is not free (see
+//! `DatabaseFile::repair_free_list`), so no page is allocated twice.
+//!
+//! Both passes read the WAL `RECOVERY_BATCH_RECORDS` records at a time, so
+//! peak memory depends on the largest committed transaction, not on the size
+//! of the WAL. Only the IDs of committed transactions are kept across the
+//! whole scan.
+//!
+//! # Torn Pages
+//!
+//! Replay writes into the pages on disk, so it relies on them being whole. A
+//! crash can tear a page write, leaving the page part new and part old, which
+//! its checksum exposes. `find_torn_pages` looks for such pages before
+//! recovery; replay cannot repair them, so `Database::open` rebuilds the file
+//! instead.
+//!
+//! # Verification
+//!
+//! `verify` replays the same WAL tail into a scratch map and compares it
+//! against the primary and secondary indexes, reporting any divergence. It is
+//! an opt-in diagnostic run at open, not part of recovery itself.
+//!
 //! # Typical Recovery Time
 //!
 //! With aggressive checkpointing, recovery typically replays <1000 records,
 //! completing in <10ms.
 
-use std::collections::{HashMap, HashSet};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
 
 use crate::storage::file::{DatabaseFile, FileError};
-use crate::storage::indexes::primary::{PrimaryIndex, PrimaryIndexError};
+use crate::storage::indexes::attribute::{AttributeIndexError, AttributeIndexReader};
+use crate::storage::indexes::entity_attribute::{
+    EntityAttributeIndexError, EntityAttributeIndexReader,
+};
+use crate::storage::indexes::primary::{PrimaryIndex, PrimaryIndexError, PrimaryIndexReader};
+use crate::storage::page::PageId;
 use crate::storage::tombstone::{Tombstone, TombstoneError, TombstoneList};
-use crate::storage::wal::{LogRecordPayload, Lsn, WalError};
+use crate::storage::wal::{LogRecord, LogRecordPayload, Lsn, WalError, WalScan};
 use crate::types::HlcTimestamp;
 use crate::types::{AttributeId, EntityId, TripleError, TripleRecord, TxnId};
 
@@ -48,15 +78,18 @@ pub struct RecoveryResult {
     pub recovered_lsn: Lsn,
 }
 
+/// Number of WAL records recovery reads into memory at a time.
+pub(crate) const RECOVERY_BATCH_RECORDS: usize = 1024;
+
 /// Pending operations for a transaction being replayed.
 #[derive(Debug, Default)]
 struct PendingTransaction {
-    /// Insert/Update operations: (`entity_id`, `attribute_id`) -> serialized record bytes
-    inserts: HashMap<(EntityId, AttributeId), Vec<u8>>,
-    /// Delete operations: set of (`entity_id`, `attribute_id`)
-    deletes: HashSet<(EntityId, AttributeId)>,
-    /// The commit HLC timestamp (set when COMMIT record is seen)
-    commit_hlc: Option<HlcTimestamp>,
+    /// Insert/Update operations: (`entity_id`, `attribute_id`, `value_hash`)
+    /// -> serialized record bytes
+    inserts: HashMap<(EntityId, AttributeId, u64), Vec<u8>>,
+    /// Delete operations: (`entity_id`, `attribute_id`, `value_hash`) ->
+    /// delete HLC
+    deletes: HashMap<(EntityId, AttributeId, u64), HlcTimestamp>,
 }
 
 impl PendingTransaction {
@@ -64,26 +97,73 @@ impl PendingTransaction {
         Self::default()
     }
 
-    const fn is_committed(&self) -> bool {
-        self.commit_hlc.is_some()
+    /// Add an INSERT, UPDATE or DELETE operation. The last operation on a key
+    /// wins; other payloads are ignored.
+    fn add(&mut self, payload: LogRecordPayload) {
+        match payload {
+            // Updates are treated the same as inserts for replay
+            LogRecordPayload::Insert(bytes) | LogRecordPayload::Update(bytes) => {
+                // The key's value hash depends on the value, so decode the record
+                if let Ok(record) = TripleRecord::from_bytes(&bytes) {
+                    let key = (record.entity_id, record.attribute_id, record.value_hash());
+                    self.inserts.insert(key, bytes);
+                    // Remove from deletes if present (insert after delete)
+                    self.deletes.remove(&key);
+                }
+            }
+            LogRecordPayload::Delete {
+                entity_id,
+                attribute_id,
+                value_hash,
+                hlc,
+            } => {
+                // Remove any pending insert for this key
+                let key = (entity_id, attribute_id, value_hash);
+                self.inserts.remove(&key);
+                self.deletes.insert(key, hlc);
+            }
+            LogRecordPayload::Begin
+            | LogRecordPayload::Commit
+            | LogRecordPayload::Checkpoint { .. } => {}
+        }
     }
 }
 
+/// What the first recovery pass learns about the WAL tail.
+#[derive(Debug, Default)]
+struct WalTailSummary {
+    /// Number of records after the checkpoint.
+    records_scanned: usize,
+    /// Highest LSN seen, at least the checkpoint LSN.
+    highest_lsn: Lsn,
+    /// Transactions with a BEGIN and a later COMMIT record.
+    committed: HashSet<TxnId>,
+    /// Number of transactions with a BEGIN record but no later COMMIT record.
+    uncommitted: usize,
+    /// Highest transaction ID with a BEGIN record.
+    max_txn_id: TxnId,
+}
+
 /// Perform crash recovery on the database.
 ///
 /// This function:
-/// 1. Reads the WAL from the last checkpoint
-/// 2. Groups operations by transaction
-/// 3. Replays only committed transactions
-/// 4. Updates the database state
+/// 1. Scans the WAL from the last checkpoint for committed transactions
+/// 2. Scans it again, replaying only committed transactions
+/// 3. Updates the database state
 ///
 /// # Arguments
 /// * `file` - The database file to recover
 ///
 /// # Returns
 /// A `RecoveryResult` with statistics about the recovery.
-#[allow(clippy::too_many_lines)]
 pub fn recover(file: &mut DatabaseFile) -> Result<RecoveryResult, RecoveryError> {
+    // The superblock's free list may be out of step with the pages after a
+    // crash; fix it before replay allocates from it
+    if file.repair_free_list()? {
+        file.write_superblock()?;
+        file.sync()?;
+    }
+
     // Check if WAL is initialized
     if !file.has_wal() {
         // No WAL, nothing to recover
@@ -98,18 +178,9 @@ pub fn recover(file: &mut DatabaseFile) -> Result<RecoveryResult, RecoveryError>
     }
 
     let checkpoint_lsn = file.superblock().last_checkpoint_lsn;
+    let summary = summarize_wal_tail(file)?;
 
-    // Read all WAL records
-    let records = {
-        let mut wal = file.wal()?;
-        if checkpoint_lsn > 0 {
-            wal.read_from_lsn(checkpoint_lsn)?
-        } else {
-            wal.read_all()?
-        }
-    };
-
-    if records.is_empty() {
+    if summary.records_scanned == 0 {
         return Ok(RecoveryResult {
             records_scanned: 0,
             transactions_replayed: 0,
@@ -120,82 +191,6 @@ pub fn recover(file: &mut DatabaseFile) -> Result<RecoveryResult, RecoveryError>
         });
     }
 
-    // Group records by transaction
-    let mut pending_txns: HashMap<TxnId, PendingTransaction> = HashMap::new();
-    let mut highest_lsn: Lsn = checkpoint_lsn;
-    let records_scanned = records.len();
-
-    for record in records {
-        highest_lsn = highest_lsn.max(record.lsn);
-
-        match record.payload {
-            LogRecordPayload::Begin => {
-                // Start tracking a new transaction
-                pending_txns.insert(record.txn_id, PendingTransaction::new());
-            }
-            LogRecordPayload::Insert(bytes) => {
-                // Store the insert for later replay
-                if let Some(txn) = pending_txns.get_mut(&record.txn_id) {
-                    // Extract entity_id and attribute_id from serialized record
-                    if bytes.len() >= 32 {
-                        let mut entity_bytes = [0u8; 16];
-                        let mut attribute_bytes = [0u8; 16];
-                        entity_bytes.copy_from_slice(&bytes[0..16]);
-                        attribute_bytes.copy_from_slice(&bytes[16..32]);
-                        let entity_id = EntityId(entity_bytes);
-                        let attribute_id = AttributeId(attribute_bytes);
-                        txn.inserts.insert((entity_id, attribute_id), bytes);
-                        // Remove from deletes if present (insert after delete)
-                        txn.deletes.remove(&(entity_id, attribute_id));
-                    }
-                }
-            }
-            LogRecordPayload::Update(bytes) => {
-                // Updates are treated the same as inserts for replay
-                if let Some(txn) = pending_txns.get_mut(&record.txn_id) {
-                    if bytes.len() >= 32 {
-                        let mut entity_bytes = [0u8; 16];
-                        let mut attribute_bytes = [0u8; 16];
-                        entity_bytes.copy_from_slice(&bytes[0..16]);
-                        attribute_bytes.copy_from_slice(&bytes[16..32]);
-                        let entity_id = EntityId(entity_bytes);
-                        let attribute_id = AttributeId(attribute_bytes);
-                        txn.inserts.insert((entity_id, attribute_id), bytes);
-                        txn.deletes.remove(&(entity_id, attribute_id));
-                    }
-                }
-            }
-            LogRecordPayload::Delete {
-                entity_id,
-                attribute_id,
-            } => {
-                if let Some(txn) = pending_txns.get_mut(&record.txn_id) {
-                    // Remove any pending insert for this key
-                    txn.inserts.remove(&(entity_id, attribute_id));
-                    txn.deletes.insert((entity_id, attribute_id));
-                }
-            }
-            LogRecordPayload::Commit => {
-                if let Some(txn) = pending_txns.get_mut(&record.txn_id) {
-                    txn.commit_hlc = Some(record.hlc);
-                }
-            }
-            LogRecordPayload::Checkpoint { .. } => {
-                // Checkpoint records don't affect recovery replay
-            }
-        }
-    }
-
-    // Count committed and uncommitted transactions
-    let transactions_replayed = pending_txns.values().filter(|t| t.is_committed()).count();
-    let transactions_discarded = pending_txns.len() - transactions_replayed;
-
-    // Replay committed transactions
-    let mut operations_applied = 0;
-
-    // Get primary index root
-    let root_page = file.superblock().primary_index_root;
-
     // Load tombstone list from superblock metadata
     let superblock = file.superblock();
     #[allow(clippy::cast_possible_truncation)] // Slot indices always fit in usize
@@ -210,40 +205,20 @@ pub fn recover(file: &mut DatabaseFile) -> Result<RecoveryResult, RecoveryError>
     // Load current head slot position from disk
     tombstone_list.load_head_slot(file)?;
 
-    // Create primary index for applying changes
-    {
+    // Apply committed transactions to the primary index as their COMMIT
+    // records are reached
+    let mut root_page = file.superblock().primary_index_root;
+    let mut operations_applied = 0;
+    replay_committed(file, &summary.committed, |file, txn_id, txn| {
         let mut index = PrimaryIndex::new(file, root_page)?;
-
-        // Apply operations from committed transactions
-        for (txn_id, txn) in &pending_txns {
-            if !txn.is_committed() {
-                continue;
-            }
-
-            // Apply inserts/updates
-            for ((_entity_id, _attribute_id), bytes) in &txn.inserts {
-                let record = TripleRecord::from_bytes(bytes)?;
-                index.insert(&record)?;
-                operations_applied += 1;
-            }
-
-            // Apply deletes and add tombstones
-            for (entity_id, attribute_id) in &txn.deletes {
-                // Mark as deleted with this transaction ID
-                if index.mark_deleted(entity_id, attribute_id, *txn_id).is_ok() {
-                    operations_applied += 1;
-                    // Add tombstone for incremental GC
-                    let tombstone = Tombstone::new(*entity_id, *attribute_id, *txn_id);
-                    tombstone_list.append(tombstone);
-                }
-            }
+        operations_applied += apply_transaction(&mut index, &mut tombstone_list, txn_id, &txn)?;
+        root_page = index.root_page();
+        if tombstone_list.needs_flush() {
+            tombstone_list.flush(file)?;
         }
-
-        // Update superblock with new root page
-        let new_root = index.root_page();
-        let file = index.file_mut();
-        file.superblock_mut().primary_index_root = new_root;
-    }
+        Ok(())
+    })?;
+    file.superblock_mut().primary_index_root = root_page;
 
     // Flush tombstones and update superblock
     tombstone_list.flush(file)?;
@@ -253,9 +228,8 @@ pub fn recover(file: &mut DatabaseFile) -> Result<RecoveryResult, RecoveryError>
     file.superblock_mut().tombstone_count = tombstone_list.count();
 
     // Update next_txn_id to be higher than any recovered transaction
-    let max_txn_id = pending_txns.keys().copied().max().unwrap_or(0);
-    if max_txn_id >= file.superblock().next_txn_id {
-        file.superblock_mut().next_txn_id = max_txn_id + 1;
+    if summary.max_txn_id >= file.superblock().next_txn_id {
+        file.superblock_mut().next_txn_id = summary.max_txn_id + 1;
     }
 
     // Persist superblock
@@ -263,15 +237,512 @@ pub fn recover(file: &mut DatabaseFile) -> Result<RecoveryResult, RecoveryError>
     file.sync()?;
 
     Ok(RecoveryResult {
-        records_scanned,
-        transactions_replayed,
-        transactions_discarded,
+        records_scanned: summary.records_scanned,
+        transactions_replayed: summary.committed.len(),
+        transactions_discarded: summary.uncommitted,
         operations_applied,
         checkpoint_lsn,
-        recovered_lsn: highest_lsn,
+        recovered_lsn: summary.highest_lsn,
+    })
+}
+
+/// Apply one committed transaction's operations to the primary index,
+/// appending a tombstone for each delete.
+///
+/// Returns the number of operations applied. A delete of a triple missing
+/// from the index is skipped.
+fn apply_transaction(
+    index: &mut PrimaryIndex<'_>,
+    tombstone_list: &mut TombstoneList,
+    txn_id: TxnId,
+    txn: &PendingTransaction,
+) -> Result<usize, RecoveryError> {
+    let mut operations_applied = 0;
+
+    // Apply inserts/updates
+    for bytes in txn.inserts.values() {
+        let record = TripleRecord::from_bytes(bytes)?;
+        index.insert(&record)?;
+        operations_applied += 1;
+    }
+
+    // Apply deletes and add tombstones
+    for ((entity_id, attribute_id, value_hash), hlc) in &txn.deletes {
+        // Mark as deleted with this transaction ID and the delete's HLC
+        if index
+            .mark_deleted(entity_id, attribute_id, *value_hash, txn_id, *hlc)
+            .is_ok()
+        {
+            operations_applied += 1;
+            // Add tombstone for incremental GC
+            tombstone_list.append(Tombstone::new(
+                *entity_id,
+                *attribute_id,
+                *value_hash,
+                txn_id,
+            ));
+        }
+    }
+
+    Ok(operations_applied)
+}
+
+/// Feed the WAL records after the last checkpoint to `visit`, reading them
+/// `RECOVERY_BATCH_RECORDS` at a time.
+///
+/// Reading starts at the superblock's checkpoint offset rather than searching
+/// for the checkpoint LSN, so records before it (including a copy left behind
+/// by an interrupted `compact_wal`) are never replayed. `visit` gets the file
+/// back between records, so it may write to it; it must not move the WAL.
+fn for_each_wal_record(
+    file: &mut DatabaseFile,
+    mut visit: impl FnMut(&mut DatabaseFile, LogRecord) -> Result<(), RecoveryError>,
+) -> Result<(), RecoveryError> {
+    let checkpoint_lsn = file.superblock().last_checkpoint_lsn;
+    let checkpoint_offset = file.superblock().txn_log_checkpoint_offset;
+    let mut scan: WalScan = {
+        let wal = file.wal()?;
+        if checkpoint_lsn > 0 {
+            wal.scan_from_offset(checkpoint_offset, checkpoint_lsn + 1)
+        } else {
+            wal.scan_all()
+        }
+    };
+    while !scan.is_done() {
+        let batch = file.wal()?.read_batch(&mut scan, RECOVERY_BATCH_RECORDS)?;
+        for record in batch {
+            visit(file, record)?;
+        }
+    }
+    Ok(())
+}
+
+/// First recovery pass: count the WAL records after the last checkpoint and
+/// find which transactions committed.
+fn summarize_wal_tail(file: &mut DatabaseFile) -> Result<WalTailSummary, RecoveryError> {
+    let mut summary = WalTailSummary {
+        highest_lsn: file.superblock().last_checkpoint_lsn,
+        ..WalTailSummary::default()
+    };
+    // Transactions that began but have not committed yet
+    let mut open: HashSet<TxnId> = HashSet::new();
+
+    for_each_wal_record(file, |_, record| {
+        summary.records_scanned += 1;
+        summary.highest_lsn = summary.highest_lsn.max(record.lsn);
+        match record.payload {
+            LogRecordPayload::Begin => {
+                // A repeated BEGIN restarts the transaction
+                summary.committed.remove(&record.txn_id);
+                open.insert(record.txn_id);
+                summary.max_txn_id = summary.max_txn_id.max(record.txn_id);
+            }
+            LogRecordPayload::Commit if open.remove(&record.txn_id) => {
+                summary.committed.insert(record.txn_id);
+            }
+            _ => {}
+        }
+        Ok(())
+    })?;
+
+    summary.uncommitted = open.len();
+    Ok(summary)
+}
+
+/// Second recovery pass: collect each committed transaction's operations and
+/// pass them to `apply` when its COMMIT record is reached, in commit order.
+///
+/// Operations of transactions not in `committed` are skipped without being
+/// buffered, so only committed transactions still in progress at a point in
+/// the log are held in memory.
+fn replay_committed(
+    file: &mut DatabaseFile,
+    committed: &HashSet<TxnId>,
+    mut apply: impl FnMut(&mut DatabaseFile, TxnId, PendingTransaction) -> Result<(), RecoveryError>,
+) -> Result<(), RecoveryError> {
+    let mut in_progress: HashMap<TxnId, PendingTransaction> = HashMap::new();
+    for_each_wal_record(file, |file, record| {
+        if !committed.contains(&record.txn_id) {
+            return Ok(());
+        }
+        match record.payload {
+            LogRecordPayload::Begin => {
+                in_progress.insert(record.txn_id, PendingTransaction::new());
+            }
+            LogRecordPayload::Commit => {
+                if let Some(txn) = in_progress.remove(&record.txn_id) {
+                    apply(file, record.txn_id, txn)?;
+                }
+            }
+            payload => {
+                if let Some(txn) = in_progress.get_mut(&record.txn_id) {
+                    txn.add(payload);
+                }
+            }
+        }
+        Ok(())
     })
 }
 
+/// Collect the change records of each transaction committed after the last
+/// checkpoint, in commit order, for replaying into another file with
+/// `Database::apply_remote_changes`.
+///
+/// Unlike recovery, which applies each transaction's final writes, this keeps
+/// every record in log order. The records are held in memory, which the WAL
+/// capacity bounds.
+pub(crate) fn committed_transactions(
+    file: &mut DatabaseFile,
+) -> Result<Vec<Vec<LogRecord>>, RecoveryError> {
+    let mut transactions = Vec::new();
+    let mut open: HashMap<TxnId, Vec<LogRecord>> = HashMap::new();
+    for_each_wal_record(file, |_, record| {
+        match record.payload {
+            LogRecordPayload::Begin => {
+                // A repeated BEGIN restarts the transaction
+                open.insert(record.txn_id, Vec::new());
+            }
+            LogRecordPayload::Commit => {
+                if let Some(changes) = open.remove(&record.txn_id) {
+                    transactions.push(changes);
+                }
+            }
+            LogRecordPayload::Checkpoint { .. } => {}
+            LogRecordPayload::Insert(_)
+            | LogRecordPayload::Update(_)
+            | LogRecordPayload::Delete { .. } => {
+                if let Some(changes) = open.get_mut(&record.txn_id) {
+                    changes.push(record);
+                }
+            }
+        }
+        Ok(())
+    })?;
+    Ok(transactions)
+}
+
+/// Find the pages of a file a crash tore (see `DatabaseFile::torn_pages`).
+///
+/// Only a crash during a commit, which leaves WAL records past the last
+/// checkpoint, can tear index pages, so the other pages are read only if the
+/// file needs recovery. The superblock is always checked.
+///
+/// # Post-conditions
+/// - Page IDs are in ascending order
+pub fn find_torn_pages(file: &mut DatabaseFile) -> Result<Vec<PageId>, RecoveryError> {
+    if needs_recovery(file)? {
+        Ok(file.torn_pages()?)
+    } else if file.superblock_is_torn()? {
+        Ok(vec![0])
+    } else {
+        Ok(Vec::new())
+    }
+}
+
+/// Result of verifying the indexes against the WAL tail.
+///
+/// Produced by `verify`, usually through `Database::open_with_options` with
+/// verification enabled.
+#[derive(Debug, Default)]
+pub struct VerificationReport {
+    /// Number of committed transactions replayed from the WAL tail.
+    pub transactions_checked: usize,
+    /// Number of distinct triples the WAL tail writes.
+    pub triples_checked: usize,
+    /// Every disagreement found, ordered by triple then index.
+    pub divergences: Vec<IndexDivergence>,
+}
+
+impl VerificationReport {
+    /// Check whether every index agreed with the WAL.
+    #[must_use]
+    pub const fn is_consistent(&self) -> bool {
+        self.divergences.is_empty()
+    }
+}
+
+/// An index that verification compares against the WAL.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VerifiedIndex {
+    /// The primary `(entity, attribute)` index holding values.
+    Primary,
+    /// The `(attribute, entity)` secondary index.
+    Attribute,
+    /// The `(entity, attribute)` secondary index.
+    EntityAttribute,
+}
+
+/// How an index entry disagrees with the WAL.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DivergenceKind {
+    /// The WAL leaves the triple live but the index has no entry.
+    Missing,
+    /// The WAL leaves the triple live but the index marks it deleted.
+    UnexpectedlyDeleted,
+    /// The WAL deletes the triple but the index still has it live.
+    NotDeleted,
+    /// The index entry does not come from the WAL's last write: a different
+    /// value, HLC or transaction.
+    Stale,
+}
+
+/// A triple whose entry in one index disagrees with the WAL.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct IndexDivergence {
+    /// The index that disagrees.
+    pub index: VerifiedIndex,
+    /// Entity of the triple.
+    pub entity_id: EntityId,
+    /// Attribute of the triple.
+    pub attribute_id: AttributeId,
+    /// How the entry disagrees.
+    pub kind: DivergenceKind,
+}
+
+impl std::fmt::Display for IndexDivergence {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{:?} index entry for ({}, {}) diverges from the WAL: {:?}",
+            self.index, self.entity_id, self.attribute_id, self.kind
+        )
+    }
+}
+
+/// The state the WAL tail leaves a triple in.
+enum ExpectedTriple {
+    /// Written last by a committed insert or update.
+    Live(TripleRecord),
+    /// Deleted last by the given committed transaction.
+    Deleted(TxnId),
+}
+
+/// Compare the indexes against a replay of the WAL tail.
+///
+/// Replays committed transactions after the last checkpoint into a scratch
+/// map without touching the indexes, then checks that every triple they
+/// write is in the state the WAL implies in the primary, attribute and
+/// entity-attribute indexes. This is a diagnostic: it reads the whole WAL
+/// tail and looks up every written triple, so it can be slow.
+///
+/// Deleted triples may be absent from an index, since garbage collection
+/// removes them.
+///
+/// # Pre-conditions
+/// - Recovery has already run, so the indexes should reflect the WAL tail
+///
+/// # Errors
+/// Returns an error if the WAL or an index cannot be read.
+pub fn verify(file: &mut DatabaseFile) -> Result<VerificationReport, RecoveryError> {
+    if !file.has_wal() {
+        return Ok(VerificationReport::default());
+    }
+
+    let summary = summarize_wal_tail(file)?;
+
+    // Replay into a scratch map, keyed by raw bytes for a stable order
+    let mut expected: BTreeMap<([u8; 16], [u8; 16], u64), ExpectedTriple> = BTreeMap::new();
+    let mut transactions_checked = 0;
+    replay_committed(file, &summary.committed, |_, txn_id, txn| {
+        transactions_checked += 1;
+        for ((entity_id, attribute_id, value_hash), bytes) in &txn.inserts {
+            let record = TripleRecord::from_bytes(bytes)?;
+            expected.insert(
+                (entity_id.0, attribute_id.0, *value_hash),
+                ExpectedTriple::Live(record),
+            );
+        }
+        for (entity_id, attribute_id, value_hash) in txn.deletes.keys() {
+            expected.insert(
+                (entity_id.0, attribute_id.0, *value_hash),
+                ExpectedTriple::Deleted(txn_id),
+            );
+        }
+        Ok(())
+    })?;
+
+    let superblock = file.superblock();
+    let primary = PrimaryIndexReader::new(file, superblock.primary_index_root);
+    let attribute = AttributeIndexReader::new(
+        file,
+        superblock.attribute_index_root,
+        superblock.attribute_value_index_root,
+    );
+    let entity_attribute =
+        EntityAttributeIndexReader::new(file, superblock.entity_attribute_index_root);
+
+    let mut divergences = Vec::new();
+    for ((entity_bytes, attribute_bytes, value_hash), expected) in &expected {
+        let value_hash = *value_hash;
+        let entity_id = EntityId(*entity_bytes);
+        let attribute_id = AttributeId(*attribute_bytes);
+        let mut report = |index, kind| {
+            divergences.push(IndexDivergence {
+                index,
+                entity_id,
+                attribute_id,
+                kind,
+            });
+        };
+
+        let primary_kind = match (
+            expected,
+            primary.get_value(&entity_id, &attribute_id, value_hash)?,
+        ) {
+            (ExpectedTriple::Live(_), None) => Some(DivergenceKind::Missing),
+            (ExpectedTriple::Live(_), Some(record)) if record.is_deleted() => {
+                Some(DivergenceKind::UnexpectedlyDeleted)
+            }
+            (ExpectedTriple::Live(want), Some(record)) => (record.created_txn != want.created_txn
+                || record.created_hlc != want.created_hlc
+                || record.value != want.value)
+                .then_some(DivergenceKind::Stale),
+            (ExpectedTriple::Deleted(_), None) => None,
+            (ExpectedTriple::Deleted(_), Some(record)) if !record.is_deleted() => {
+                Some(DivergenceKind::NotDeleted)
+            }
+            (ExpectedTriple::Deleted(txn_id), Some(record)) => {
+                (record.deleted_txn != *txn_id).then_some(DivergenceKind::Stale)
+            }
+        };
+        if let Some(kind) = primary_kind {
+            report(VerifiedIndex::Primary, kind);
+        }
+
+        // Secondary entries hold `(created_txn, deleted_txn)`; updates keep
+        // the original `created_txn`, so only liveness is comparable
+        let secondary = [
+            (
+                VerifiedIndex::Attribute,
+                attribute.get(&attribute_id, &entity_id, value_hash)?,
+            ),
+            (
+                VerifiedIndex::EntityAttribute,
+                entity_attribute.get(&entity_id, &attribute_id, value_hash)?,
+            ),
+        ];
+        for (index, entry) in secondary {
+            let kind = match (expected, entry) {
+                (ExpectedTriple::Live(_), None) => Some(DivergenceKind::Missing),
+                (ExpectedTriple::Live(_), Some((_, deleted_txn))) => {
+                    (deleted_txn != 0).then_some(DivergenceKind::UnexpectedlyDeleted)
+                }
+                (ExpectedTriple::Deleted(_), None) => None,
+                (ExpectedTriple::Deleted(txn_id), Some((_, deleted_txn))) => match deleted_txn {
+                    0 => Some(DivergenceKind::NotDeleted),
+                    deleted_txn => (deleted_txn != *txn_id).then_some(DivergenceKind::Stale),
+                },
+            };
+            if let Some(kind) = kind {
+                report(index, kind);
+            }
+        }
+    }
+
+    Ok(VerificationReport {
+        transactions_checked,
+        triples_checked: expected.len(),
+        divergences,
+    })
+}
+
+/// A triple that a secondary index and the primary index disagree about.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct IndexMismatch {
+    /// The secondary index that disagrees with the primary index.
+    pub index: VerifiedIndex,
+    /// Entity of the triple.
+    pub entity_id: EntityId,
+    /// Attribute of the triple.
+    pub attribute_id: AttributeId,
+    /// Whether the triple is live in the primary index. If so, the secondary
+    /// index has no live entry for it; otherwise the secondary index has a
+    /// live entry the primary index lacks.
+    pub live_in_primary: bool,
+}
+
+impl std::fmt::Display for IndexMismatch {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let (live, missing) = if self.live_in_primary {
+            ("Primary".to_string(), format!("{:?}", self.index))
+        } else {
+            (format!("{:?}", self.index), "Primary".to_string())
+        };
+        write!(
+            f,
+            "({}, {}) is live in the {live} index but not in the {missing} index",
+            self.entity_id, self.attribute_id
+        )
+    }
+}
+
+/// Check that the secondary indexes agree with the primary index.
+///
+/// Every triple live in the primary index must have a live entry in both the
+/// attribute and entity-attribute indexes, and every live secondary entry
+/// must have a live triple in the primary index. Unlike `verify`, this does
+/// not read the WAL, but it scans all three indexes in full, so it is a slow
+/// diagnostic.
+///
+/// # Post-conditions
+/// - Mismatches are ordered by triple, then index
+///
+/// # Errors
+/// Returns an error if an index cannot be read.
+pub fn check_index_consistency(file: &DatabaseFile) -> Result<Vec<IndexMismatch>, RecoveryError> {
+    let superblock = file.superblock();
+
+    let mut primary = BTreeSet::new();
+    let mut cursor = PrimaryIndexReader::new(file, superblock.primary_index_root).cursor()?;
+    while let Some(record) = cursor.next_record()? {
+        if !record.is_deleted() {
+            primary.insert((record.entity_id.0, record.attribute_id.0));
+        }
+    }
+
+    let mut attribute = BTreeSet::new();
+    let mut pairs = AttributeIndexReader::new(
+        file,
+        superblock.attribute_index_root,
+        superblock.attribute_value_index_root,
+    )
+    .scan_live()?;
+    while let Some((attribute_id, entity_id)) = pairs.next_pair()? {
+        attribute.insert((entity_id.0, attribute_id.0));
+    }
+
+    let mut entity_attribute = BTreeSet::new();
+    let mut pairs = EntityAttributeIndexReader::new(file, superblock.entity_attribute_index_root)
+        .scan_live()?;
+    while let Some((entity_id, attribute_id)) = pairs.next_pair()? {
+        entity_attribute.insert((entity_id.0, attribute_id.0));
+    }
+
+    let keys: BTreeSet<&([u8; 16], [u8; 16])> = primary
+        .iter()
+        .chain(&attribute)
+        .chain(&entity_attribute)
+        .collect();
+    let mut mismatches = Vec::new();
+    for key in keys {
+        let live_in_primary = primary.contains(key);
+        for (index, entries) in [
+            (VerifiedIndex::Attribute, &attribute),
+            (VerifiedIndex::EntityAttribute, &entity_attribute),
+        ] {
+            if entries.contains(key) != live_in_primary {
+                mismatches.push(IndexMismatch {
+                    index,
+                    entity_id: EntityId(key.0),
+                    attribute_id: AttributeId(key.1),
+                    live_in_primary,
+                });
+            }
+        }
+    }
+    Ok(mismatches)
+}
+
 /// Check if recovery is needed.
 ///
 /// Recovery is needed if there are WAL records after the last checkpoint
@@ -282,14 +753,15 @@ pub fn needs_recovery(file: &mut DatabaseFile) -> Result<bool, RecoveryError> {
     }
 
     let checkpoint_lsn = file.superblock().last_checkpoint_lsn;
+    let checkpoint_offset = file.superblock().txn_log_checkpoint_offset;
 
     let mut wal = file.wal()?;
 
     // Check if there are any records after the checkpoint
     if checkpoint_lsn > 0 {
-        let records = wal.read_from_lsn(checkpoint_lsn)?;
-        // If there's more than just the checkpoint record itself, we need recovery
-        Ok(records.len() > 1)
+        Ok(!wal
+            .read_from_offset(checkpoint_offset, checkpoint_lsn + 1)?
+            .is_empty())
     } else {
         // No checkpoint, check if WAL is non-empty
         Ok(!wal.is_empty())
@@ -305,6 +777,10 @@ pub enum RecoveryError {
     Wal(WalError),
     /// Index error.
     Index(PrimaryIndexError),
+    /// Attribute index error.
+    AttributeIndex(AttributeIndexError),
+    /// Entity-attribute index error.
+    EntityAttributeIndex(EntityAttributeIndexError),
     /// Triple deserialization error.
     Triple(TripleError),
     /// Tombstone list error.
@@ -319,6 +795,10 @@ impl std::fmt::Display for RecoveryError {
             Self::File(e) => write!(f, "recovery file error: {e}"),
             Self::Wal(e) => write!(f, "recovery WAL error: {e}"),
             Self::Index(e) => write!(f, "recovery index error: {e}"),
+            Self::AttributeIndex(e) => write!(f, "recovery attribute index error: {e}"),
+            Self::EntityAttributeIndex(e) => {
+                write!(f, "recovery entity-attribute index error: {e}")
+            }
             Self::Triple(e) => write!(f, "recovery triple error: {e}"),
             Self::Tombstone(e) => write!(f, "recovery tombstone error: {e}"),
             Self::OrphanCommit(txn_id) => {
@@ -334,6 +814,8 @@ impl std::error::Error for RecoveryError {
             Self::File(e) => Some(e),
             Self::Wal(e) => Some(e),
             Self::Index(e) => Some(e),
+            Self::AttributeIndex(e) => Some(e),
+            Self::EntityAttributeIndex(e) => Some(e),
             Self::Triple(e) => Some(e),
             Self::Tombstone(e) => Some(e),
             Self::OrphanCommit(_) => None,
@@ -359,6 +841,18 @@ impl From<PrimaryIndexError> for RecoveryError {
     }
 }
 
+impl From<AttributeIndexError> for RecoveryError {
+    fn from(e: AttributeIndexError) -> Self {
+        Self::AttributeIndex(e)
+    }
+}
+
+impl From<EntityAttributeIndexError> for RecoveryError {
+    fn from(e: EntityAttributeIndexError) -> Self {
+        Self::EntityAttributeIndex(e)
+    }
+}
+
 impl From<TripleError> for RecoveryError {
     fn from(e: TripleError) -> Self {
         Self::Triple(e)
@@ -534,6 +1028,77 @@ mod tests {
         assert!(record.is_none());
     }
 
+    #[test]
+    fn test_recover_large_bytes_value() {
+        let (_dir, path) = create_test_db();
+        let pool = test_pool();
+        let mut file = DatabaseFile::create(&path, pool).expect("create db");
+        file.init_wal(DEFAULT_WAL_CAPACITY).expect("init wal");
+
+        let hlc = HlcTimestamp::new(1000, 0);
+        let blob: Vec<u8> = (0..=u8::MAX).cycle().take(64 * 1024).collect();
+
+        // A committed 64KB blob, then a crash partway through inserting another
+        {
+            let mut wal = file.wal().expect("get wal");
+
+            wal.append(1, hlc, LogRecordPayload::Begin)
+                .expect("append begin");
+            let triple = TripleRecord::new(
+                EntityId([1u8; 16]),
+                AttributeId([2u8; 16]),
+                1,
+                hlc,
+                TripleValue::Bytes(blob.clone()),
+            );
+            wal.append(1, hlc, LogRecordPayload::insert(&triple))
+                .expect("append insert");
+            wal.append(1, hlc, LogRecordPayload::Commit)
+                .expect("append commit");
+
+            wal.append(2, hlc, LogRecordPayload::Begin)
+                .expect("append begin");
+            let triple = TripleRecord::new(
+                EntityId([3u8; 16]),
+                AttributeId([2u8; 16]),
+                2,
+                hlc,
+                TripleValue::Bytes(vec![0xAB; 64 * 1024]),
+            );
+            wal.append(2, hlc, LogRecordPayload::insert(&triple))
+                .expect("append insert");
+
+            // NO COMMIT for transaction 2
+            wal.sync().expect("sync");
+            let head = wal.head();
+            let last_lsn = wal.last_lsn();
+            #[allow(clippy::drop_non_drop)]
+            drop(wal);
+            file.update_wal_head(head, last_lsn);
+        }
+        file.write_superblock().expect("write superblock");
+
+        let result = recover(&mut file).expect("recover");
+
+        assert_eq!(result.transactions_replayed, 1);
+        assert_eq!(result.transactions_discarded, 1);
+        assert_eq!(result.operations_applied, 1);
+
+        let root_page = file.superblock().primary_index_root;
+        let mut index = PrimaryIndex::new(&mut file, root_page).expect("open index");
+        let record = index
+            .get(&EntityId([1u8; 16]), &AttributeId([2u8; 16]))
+            .expect("get")
+            .expect("committed blob is recovered");
+        assert_eq!(record.value, TripleValue::Bytes(blob));
+        assert!(
+            index
+                .get(&EntityId([3u8; 16]), &AttributeId([2u8; 16]))
+                .expect("get")
+                .is_none()
+        );
+    }
+
     #[test]
     fn test_recover_multiple_transactions() {
         let (_dir, path) = create_test_db();
@@ -667,7 +1232,7 @@ mod tests {
             wal.append(
                 1,
                 hlc,
-                LogRecordPayload::delete(EntityId([1u8; 16]), AttributeId([1u8; 16])),
+                LogRecordPayload::delete(EntityId([1u8; 16]), AttributeId([1u8; 16]), 0, hlc),
             )
             .expect("delete");
             wal.append(1, hlc, LogRecordPayload::Commit)
@@ -695,9 +1260,77 @@ mod tests {
             .get(&EntityId([1u8; 16]), &AttributeId([1u8; 16]))
             .expect("get");
 
-        // Record exists but is deleted
+        // Record exists but is deleted, with the delete's HLC on the tombstone
         assert!(record.is_some());
-        assert!(record.unwrap().is_deleted());
+        let record = record.unwrap();
+        assert!(record.is_deleted());
+        assert_eq!(record.deleted_hlc, hlc);
+    }
+
+    #[test]
+    fn test_verify_reports_primary_index_divergence() {
+        let (_dir, path) = create_test_db();
+        let pool = test_pool();
+        let mut file = DatabaseFile::create(&path, pool).expect("create db");
+        file.init_wal(DEFAULT_WAL_CAPACITY).expect("init wal");
+
+        let hlc = HlcTimestamp::new(1000, 0);
+        let written = EntityId([1u8; 16]);
+        let deleted = EntityId([2u8; 16]);
+        let attr = AttributeId([3u8; 16]);
+
+        // The WAL writes one triple and deletes another
+        {
+            let mut wal = file.wal().expect("get wal");
+            wal.append(1, hlc, LogRecordPayload::Begin).expect("begin");
+            let triple = TripleRecord::new(written, attr, 1, hlc, TripleValue::Number(1.0));
+            wal.append(1, hlc, LogRecordPayload::insert(&triple))
+                .expect("insert");
+            wal.append(1, hlc, LogRecordPayload::delete(deleted, attr, 0, hlc))
+                .expect("delete");
+            wal.append(1, hlc, LogRecordPayload::Commit)
+                .expect("commit");
+            wal.sync().expect("sync");
+            let head = wal.head();
+            let last_lsn = wal.last_lsn();
+            #[allow(clippy::drop_non_drop)]
+            drop(wal);
+            file.update_wal_head(head, last_lsn);
+        }
+
+        // The primary index disagrees on both, and the secondary indexes are empty
+        {
+            let root_page = file.superblock().primary_index_root;
+            let mut index = PrimaryIndex::new(&mut file, root_page).expect("open index");
+            for entity_id in [written, deleted] {
+                let triple = TripleRecord::new(entity_id, attr, 1, hlc, TripleValue::Number(2.0));
+                index.insert(&triple).expect("insert");
+            }
+            let new_root = index.root_page();
+            index.file_mut().superblock_mut().primary_index_root = new_root;
+        }
+
+        let report = verify(&mut file).expect("verify");
+        assert_eq!(report.transactions_checked, 1);
+        assert_eq!(report.triples_checked, 2);
+        let found: Vec<(EntityId, VerifiedIndex, DivergenceKind)> = report
+            .divergences
+            .iter()
+            .map(|d| (d.entity_id, d.index, d.kind))
+            .collect();
+        assert_eq!(
+            found,
+            vec![
+                (written, VerifiedIndex::Primary, DivergenceKind::Stale),
+                (written, VerifiedIndex::Attribute, DivergenceKind::Missing),
+                (
+                    written,
+                    VerifiedIndex::EntityAttribute,
+                    DivergenceKind::Missing
+                ),
+                (deleted, VerifiedIndex::Primary, DivergenceKind::NotDeleted),
+            ]
+        );
     }
 
     #[test]
@@ -863,6 +1496,88 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_recover_many_transactions_across_batches() {
+        let (_dir, path) = create_test_db();
+        let pool = test_pool();
+        let mut file = DatabaseFile::create(&path, pool).expect("create db");
+        file.init_wal(DEFAULT_WAL_CAPACITY).expect("init wal");
+
+        // Far more records than one batch: every transaction inserts a triple
+        // of its own, every seventh never commits, and every tenth deletes
+        // the previous transaction's triple if that one commits
+        let txn_count: u64 = 3000;
+        let entity = |txn_id: u64| {
+            let mut bytes = [0u8; 16];
+            bytes[8..].copy_from_slice(&txn_id.to_be_bytes());
+            EntityId(bytes)
+        };
+        let attribute_id = AttributeId([9u8; 16]);
+        let is_committed = |txn_id: u64| !txn_id.is_multiple_of(7);
+        let deletes_previous = |txn_id: u64| txn_id.is_multiple_of(10) && is_committed(txn_id - 1);
+        let number = |txn_id: u64| TripleValue::Number(f64::from(u32::try_from(txn_id).unwrap()));
+        {
+            let mut wal = file.wal().expect("get wal");
+            for txn_id in 1..=txn_count {
+                let hlc = HlcTimestamp::new(1000 + txn_id, 0);
+                wal.append(txn_id, hlc, LogRecordPayload::Begin)
+                    .expect("begin");
+                let triple =
+                    TripleRecord::new(entity(txn_id), attribute_id, txn_id, hlc, number(txn_id));
+                wal.append(txn_id, hlc, LogRecordPayload::insert(&triple))
+                    .expect("insert");
+                if deletes_previous(txn_id) {
+                    let payload = LogRecordPayload::Delete {
+                        entity_id: entity(txn_id - 1),
+                        attribute_id,
+                        value_hash: 0,
+                        hlc,
+                    };
+                    wal.append(txn_id, hlc, payload).expect("delete");
+                }
+                if is_committed(txn_id) {
+                    wal.append(txn_id, hlc, LogRecordPayload::Commit)
+                        .expect("commit");
+                }
+            }
+            wal.sync().expect("sync");
+            let head = wal.head();
+            let last_lsn = wal.last_lsn();
+            #[allow(clippy::drop_non_drop)]
+            drop(wal);
+            file.update_wal_head(head, last_lsn);
+        }
+        file.write_superblock().expect("write superblock");
+
+        let result = recover(&mut file).expect("recover");
+        let committed: Vec<u64> = (1..=txn_count).filter(|&id| is_committed(id)).collect();
+        let deletes_applied = committed.iter().filter(|&&id| deletes_previous(id)).count();
+        assert!(result.records_scanned > RECOVERY_BATCH_RECORDS * 5);
+        assert_eq!(result.transactions_replayed, committed.len());
+        assert_eq!(
+            result.transactions_discarded,
+            (1..=txn_count).filter(|&id| !is_committed(id)).count()
+        );
+        assert_eq!(result.operations_applied, committed.len() + deletes_applied);
+        assert_eq!(file.superblock().next_txn_id, txn_count + 1);
+        assert_eq!(file.superblock().tombstone_count, deletes_applied as u64);
+
+        let root_page = file.superblock().primary_index_root;
+        let mut index = PrimaryIndex::new(&mut file, root_page).expect("open index");
+        for txn_id in 1..=txn_count {
+            let record = index.get(&entity(txn_id), &attribute_id).expect("get");
+            let deleted = is_committed(txn_id + 1) && deletes_previous(txn_id + 1);
+            match record {
+                Some(record) => {
+                    assert!(is_committed(txn_id), "txn {txn_id} was not committed");
+                    assert_eq!(record.is_deleted(), deleted, "txn {txn_id}");
+                    assert_eq!(record.value, number(txn_id));
+                }
+                None => assert!(!is_committed(txn_id), "txn {txn_id} was committed"),
+            }
+        }
+    }
+
     #[test]
     fn test_recover_insert_then_delete_same_key() {
         // Test recovery when same key is inserted and deleted in same transaction
@@ -894,7 +1609,7 @@ mod tests {
             wal.append(
                 1,
                 hlc,
-                LogRecordPayload::delete(EntityId([1u8; 16]), AttributeId([1u8; 16])),
+                LogRecordPayload::delete(EntityId([1u8; 16]), AttributeId([1u8; 16]), 0, hlc),
             )
             .expect("delete");
 
@@ -0,0 +1,265 @@
+//! Schema introspection.
+//!
+//! Describes which attributes a database holds and what types of value are
+//! stored under them, so clients and tools can discover the data without
+//! scanning it.
+//!
+//! # Design
+//!
+//! The triple store has no declared schema, and reading every triple to infer
+//! one would cost a full scan. Instead the description is sampled:
+//!
+//! - Distinct attributes are found by seeking through the attribute index, one
+//!   seek per attribute, and at most a caller-given number are returned
+//! - Attributes outside the caller's allowed prefixes are skipped before that
+//!   number is counted, so a scoped caller still gets a full page
+//! - Value types are read from the first `SCHEMA_SAMPLE_SIZE` triples of each
+//!   attribute, in entity ID order
+//!
+//! The value types are therefore the types observed, not a guarantee: an
+//! attribute can hold other types in triples outside the sample.
+
+use std::collections::HashMap;
+
+use crate::storage::attribute_cardinality::is_reserved_entity;
+use crate::storage::attribute_names::ATTRIBUTE_NAMES_ENTITY_ID;
+use crate::storage::{Database, DatabaseError, Snapshot};
+use crate::types::{AttributeId, TripleValue, ValueType};
+
+/// Number of triples per attribute whose value types are sampled.
+pub const SCHEMA_SAMPLE_SIZE: usize = 8;
+
+/// One attribute in a schema description.
+#[derive(Debug, PartialEq, Eq)]
+pub struct AttributeSchema {
+    /// The attribute's ID.
+    pub attribute_id: AttributeId,
+    /// The name registered for the attribute, if any.
+    pub name: Option<String>,
+    /// The distinct value types seen in the sampled triples, in type order.
+    pub value_types: Vec<ValueType>,
+}
+
+/// A sampled description of a database's attributes.
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct SchemaSample {
+    /// The attributes with visible triples, in ascending ID order.
+    pub attributes: Vec<AttributeSchema>,
+    /// Whether more attributes exist past the requested maximum.
+    pub truncated: bool,
+}
+
+impl Database {
+    /// Describe up to `max_attributes` attributes and their sampled value types.
+    ///
+    /// Attributes with no visible triples, such as a registered name never
+    /// written to, are left out. The reserved names entity is never sampled.
+    /// With `allowed_prefixes`, only attributes whose ID starts with one of
+    /// them are described or counted toward `max_attributes`.
+    ///
+    /// # Post-conditions
+    /// - At most `max_attributes` attributes are returned
+    /// - Each attribute has at least one value type
+    /// - Each attribute's ID starts with one of `allowed_prefixes`, if given
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a snapshot cannot be taken or an index cannot be read.
+    pub fn describe_schema(
+        &self,
+        max_attributes: usize,
+        allowed_prefixes: Option<&[Vec<u8>]>,
+    ) -> Result<SchemaSample, DatabaseError> {
+        let snapshot = self.begin_readonly()?;
+        let sample = describe(&snapshot, max_attributes, allowed_prefixes);
+        let txn_id = snapshot.close();
+        self.release_snapshot(txn_id)?;
+        sample
+    }
+}
+
+/// Walk the attribute index at `snapshot`, sampling each allowed attribute's
+/// values.
+fn describe(
+    snapshot: &Snapshot<'_>,
+    max_attributes: usize,
+    allowed_prefixes: Option<&[Vec<u8>]>,
+) -> Result<SchemaSample, DatabaseError> {
+    let mut names: HashMap<AttributeId, String> = snapshot
+        .scan_entity(&ATTRIBUTE_NAMES_ENTITY_ID)?
+        .into_iter()
+        .filter_map(|record| match record.value {
+            TripleValue::String(name) => Some((record.attribute_id, name)),
+            _ => None,
+        })
+        .collect();
+
+    let mut sample = SchemaSample::default();
+    let mut last_attribute_id = None;
+    while let Some(attribute_id) = snapshot.next_attribute(last_attribute_id.as_ref())? {
+        last_attribute_id = Some(attribute_id);
+        let is_allowed = allowed_prefixes.is_none_or(|prefixes| {
+            prefixes
+                .iter()
+                .any(|prefix| attribute_id.0.starts_with(prefix))
+        });
+        if !is_allowed {
+            continue;
+        }
+
+        // Two extra, in case the reserved entities are among them
+        let entities =
+            snapshot.sample_entities_with_attribute(&attribute_id, SCHEMA_SAMPLE_SIZE + 2)?;
+        let mut value_types = Vec::new();
+        for entity_id in entities
+            .iter()
+            .filter(|entity_id| !is_reserved_entity(entity_id))
+            .take(SCHEMA_SAMPLE_SIZE)
+        {
+            if let Some(record) = snapshot.get(entity_id, &attribute_id)? {
+                let value_type = record.value.value_type();
+                if !value_types.contains(&value_type) {
+                    value_types.push(value_type);
+                }
+            }
+        }
+        if value_types.is_empty() {
+            continue;
+        }
+
+        if sample.attributes.len() == max_attributes {
+            sample.truncated = true;
+            break;
+        }
+        value_types.sort_by_key(|value_type| *value_type as u8);
+        sample.attributes.push(AttributeSchema {
+            attribute_id,
+            name: names.remove(&attribute_id),
+            value_types,
+        });
+    }
+
+    Ok(sample)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::buffer_pool::BufferPool;
+    use crate::types::EntityId;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_describe_schema_with_mixed_types() {
+        let dir = tempdir().expect("create temp dir");
+        let mut db =
+            Database::create(&dir.path().join("test.db"), BufferPool::new(100)).expect("create db");
+        let name = db.attribute_id_for_name("name").expect("register name");
+        let _unused = db.attribute_id_for_name("unused").expect("register unused");
+        let age = AttributeId([2u8; 16]);
+        let mixed = AttributeId([3u8; 16]);
+
+        let mut txn = db.begin(0).expect("begin");
+        for index in 0..20u8 {
+            let entity_id = EntityId([index + 1; 16]);
+            txn.insert(entity_id, age, TripleValue::Number(f64::from(index)))
+                .expect("insert age");
+            txn.insert(entity_id, name, TripleValue::string(format!("n{index}")))
+                .expect("insert name");
+        }
+        txn.insert(EntityId([1u8; 16]), mixed, TripleValue::Boolean(true))
+            .expect("insert mixed");
+        txn.insert(EntityId([2u8; 16]), mixed, TripleValue::string("yes"))
+            .expect("insert mixed");
+        txn.insert(EntityId([3u8; 16]), mixed, TripleValue::Null)
+            .expect("insert mixed");
+        txn.commit().expect("commit");
+
+        let sample = db.describe_schema(100, None).expect("describe");
+        assert!(!sample.truncated);
+        let mut expected = vec![
+            AttributeSchema {
+                attribute_id: age,
+                name: None,
+                value_types: vec![ValueType::Number],
+            },
+            AttributeSchema {
+                attribute_id: mixed,
+                name: None,
+                value_types: vec![ValueType::Null, ValueType::Boolean, ValueType::StringInline],
+            },
+            AttributeSchema {
+                attribute_id: name,
+                name: Some("name".to_string()),
+                value_types: vec![ValueType::StringInline],
+            },
+        ];
+        expected.sort_by_key(|attribute| attribute.attribute_id.0);
+        assert_eq!(sample.attributes, expected);
+
+        let sample = db.describe_schema(2, None).expect("describe");
+        assert!(sample.truncated);
+        assert_eq!(sample.attributes.len(), 2);
+    }
+
+    #[test]
+    fn test_describe_schema_skips_deleted_attributes() {
+        let dir = tempdir().expect("create temp dir");
+        let mut db =
+            Database::create(&dir.path().join("test.db"), BufferPool::new(100)).expect("create db");
+        assert_eq!(
+            db.describe_schema(10, None).expect("describe"),
+            SchemaSample::default()
+        );
+
+        let entity_id = EntityId([1u8; 16]);
+        let attribute_id = AttributeId([2u8; 16]);
+        let mut txn = db.begin(0).expect("begin");
+        txn.insert(entity_id, attribute_id, TripleValue::Number(1.0))
+            .expect("insert");
+        txn.commit().expect("commit");
+        let mut txn = db.begin(0).expect("begin");
+        txn.delete(&entity_id, &attribute_id).expect("delete");
+        txn.commit().expect("commit");
+
+        assert_eq!(
+            db.describe_schema(10, None).expect("describe"),
+            SchemaSample::default()
+        );
+    }
+
+    #[test]
+    fn test_describe_schema_limit_counts_only_allowed_attributes() {
+        let dir = tempdir().expect("create temp dir");
+        let mut db =
+            Database::create(&dir.path().join("test.db"), BufferPool::new(100)).expect("create db");
+        let mut txn = db.begin(0).expect("begin");
+        // The out-of-scope attributes sort first, so they would use up a
+        // limit applied before the scope
+        for (prefix, seed) in [(0x01, 1), (0x01, 2), (0x01, 3), (0x02, 1), (0x02, 2)] {
+            let mut attribute_id = [seed; 16];
+            attribute_id[0] = prefix;
+            txn.insert(
+                EntityId([1u8; 16]),
+                AttributeId(attribute_id),
+                TripleValue::Number(1.0),
+            )
+            .expect("insert");
+        }
+        txn.commit().expect("commit");
+        let allowed = [vec![0x02]];
+
+        let sample = db.describe_schema(2, Some(&allowed)).expect("describe");
+
+        assert!(!sample.truncated);
+        assert_eq!(sample.attributes.len(), 2);
+        for attribute in &sample.attributes {
+            assert_eq!(attribute.attribute_id.0[0], 0x02);
+        }
+
+        let sample = db.describe_schema(1, Some(&allowed)).expect("describe");
+
+        assert!(sample.truncated);
+        assert_eq!(sample.attributes.len(), 1);
+    }
+}
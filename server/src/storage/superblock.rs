@@ -1,21 +1,43 @@
 //! Superblock structure and serialization.
 //!
 //! The superblock occupies page 0 and contains metadata about the database file.
+//!
+//! From format version 2 on, the superblock carries a checksum, and page 0
+//! holds a second copy of it at `MIRROR_OFFSET`. Page 0 is rewritten in place
+//! after every commit, so a crash can tear it; the copy that survives whole is
+//! read instead (see `Superblock::from_torn_page`).
 
-// PAGE_SIZE is a compile-time constant that fits in u32.
+// Supported page sizes are small compile-time constants that fit in u32.
 #![allow(clippy::cast_possible_truncation)]
 
 use std::sync::Arc;
 
 use crate::storage::buffer_pool::BufferPool;
-use crate::storage::page::{PAGE_SIZE, Page, PageId};
+use crate::storage::checksum::ChecksumAlgorithm;
+use crate::storage::page::{
+    MIN_PAGE_SIZE, PAGE_SIZE, Page, PageId, is_supported_page_size, page_checksum,
+};
 use crate::types::HlcTimestamp;
 
 /// Magic number identifying an Enso database file: "ENSOTRPL"
 pub const MAGIC: [u8; 8] = *b"ENSOTRPL";
 
 /// Current format version.
-pub const FORMAT_VERSION: u32 = 1;
+pub const FORMAT_VERSION: u32 = 2;
+
+/// First format version whose superblock and pages carry a checksum.
+pub const PAGE_CHECKSUM_VERSION: u32 = 2;
+
+/// Oldest format version this build can read. Files from `OLDEST_FORMAT_VERSION`
+/// up to, but not including, `FORMAT_VERSION` are rewritten in the current
+/// format when opened (see `storage::upgrade`).
+pub const OLDEST_FORMAT_VERSION: u32 = 1;
+
+/// Stored node ID of a file no node has claimed yet.
+///
+/// Files created for single-node use, and files from before node IDs were
+/// stored, have this ID; the first node to open one with an ID claims it.
+pub const UNASSIGNED_NODE_ID: u32 = 0;
 
 /// Page size as u32 for storage in superblock.
 const PAGE_SIZE_U32: u32 = PAGE_SIZE as u32;
@@ -45,16 +67,35 @@ mod offsets {
     pub const TOMBSTONE_TAIL_PAGE: usize = 152;
     pub const TOMBSTONE_TAIL_SLOT: usize = 160;
     pub const TOMBSTONE_COUNT: usize = 168;
-    // 176-1023: reserved
-    // 1024-8191: checkpoint metadata
+    // Checksum algorithm (0 = CRC32)
+    pub const CHECKSUM_ALGORITHM: usize = 176;
+    // Node ID (0 = unassigned)
+    pub const NODE_ID: usize = 180;
+    pub const TXN_LOG_CHECKPOINT_OFFSET: usize = 184;
+    pub const ATTRIBUTE_VALUE_INDEX_ROOT: usize = 192;
+    pub const FREE_PAGE_COUNT: usize = 200;
+    // Checksum of bytes 0-211, read as zero here
+    pub const CHECKSUM: usize = 208;
+    // 212-2047: reserved
+    // 2048-2259: copy of bytes 0-211 (see `MIRROR_OFFSET`)
+    // 2260-end of page: reserved
 }
 
+/// Bytes at the start of page 0 holding the superblock and its checksum.
+const SUPERBLOCK_SIZE: usize = 212;
+
+/// Offset in page 0 of the second copy of the superblock.
+///
+/// A torn write of page 0 keeps a prefix of the new page: if the prefix ends
+/// inside the first copy, this one is still the previous superblock, whole.
+pub const MIRROR_OFFSET: usize = MIN_PAGE_SIZE / 2;
+
 /// The superblock contains all metadata about the database file.
 #[derive(Debug, Copy, Clone)]
 pub struct Superblock {
     /// Format version number.
     pub format_version: u32,
-    /// Page size in bytes (should always be `PAGE_SIZE`).
+    /// Page size in bytes, chosen at creation from `SUPPORTED_PAGE_SIZES`.
     pub page_size: u32,
     /// Total file size in bytes.
     pub file_size: u64,
@@ -64,10 +105,14 @@ pub struct Superblock {
     pub primary_index_root: PageId,
     /// Root page of the attribute index.
     pub attribute_index_root: PageId,
+    /// Root page of the attribute index's value-ordered entries.
+    pub attribute_value_index_root: PageId,
     /// Root page of the entity-attribute index.
     pub entity_attribute_index_root: PageId,
-    /// Head of the free page list.
+    /// Head of the free page list, or 0 if it is empty.
     pub free_list_head: PageId,
+    /// Number of pages on the free page list.
+    pub free_page_count: u64,
     /// Log sequence number of the last checkpoint.
     pub last_checkpoint_lsn: u64,
     /// HLC timestamp of the last checkpoint.
@@ -80,6 +125,9 @@ pub struct Superblock {
     pub txn_log_end: u64,
     /// Transaction log capacity in bytes.
     pub txn_log_capacity: u64,
+    /// WAL position just past the last checkpoint record, relative to
+    /// `txn_log_start`. Records from here to the head are not yet checkpointed.
+    pub txn_log_checkpoint_offset: u64,
     /// Number of active transactions.
     pub active_txn_count: u64,
     /// Next transaction ID to assign.
@@ -94,21 +142,36 @@ pub struct Superblock {
     pub tombstone_tail_slot: u64,
     /// Total count of pending tombstones.
     pub tombstone_count: u64,
+    /// Checksum algorithm for WAL records and pages, chosen at creation.
+    pub checksum_algorithm: ChecksumAlgorithm,
+    /// ID of the node that owns the file, or `UNASSIGNED_NODE_ID`.
+    pub node_id: u32,
 }
 
 impl Superblock {
     /// Create a new superblock with default values for a fresh database.
     #[must_use]
     pub const fn new() -> Self {
+        Self::with_page_size(PAGE_SIZE_U32)
+    }
+
+    /// Create a new superblock for a fresh database with the given page size.
+    ///
+    /// # Pre-conditions
+    /// - `page_size` is one of `SUPPORTED_PAGE_SIZES`
+    #[must_use]
+    pub const fn with_page_size(page_size: u32) -> Self {
         Self {
             format_version: FORMAT_VERSION,
-            page_size: PAGE_SIZE_U32,
-            file_size: PAGE_SIZE as u64, // Initially just the superblock
+            page_size,
+            file_size: page_size as u64, // Initially just the superblock
             total_page_count: 1,
             primary_index_root: 0,
             attribute_index_root: 0,
+            attribute_value_index_root: 0,
             entity_attribute_index_root: 0,
             free_list_head: 0,
+            free_page_count: 0,
             last_checkpoint_lsn: 0,
             last_checkpoint_hlc: HlcTimestamp {
                 physical_time: 0,
@@ -119,6 +182,7 @@ impl Superblock {
             txn_log_start: 0,
             txn_log_end: 0,
             txn_log_capacity: 0,
+            txn_log_checkpoint_offset: 0,
             active_txn_count: 0,
             next_txn_id: 1,
             schema_version: 1,
@@ -126,6 +190,8 @@ impl Superblock {
             tombstone_tail_page: 0,
             tombstone_tail_slot: 0,
             tombstone_count: 0,
+            checksum_algorithm: ChecksumAlgorithm::Crc32,
+            node_id: UNASSIGNED_NODE_ID,
         }
     }
 
@@ -142,11 +208,16 @@ impl Superblock {
         page.write_u64(offsets::TOTAL_PAGE_COUNT, self.total_page_count);
         page.write_u64(offsets::PRIMARY_INDEX_ROOT, self.primary_index_root);
         page.write_u64(offsets::ATTRIBUTE_INDEX_ROOT, self.attribute_index_root);
+        page.write_u64(
+            offsets::ATTRIBUTE_VALUE_INDEX_ROOT,
+            self.attribute_value_index_root,
+        );
         page.write_u64(
             offsets::ENTITY_ATTRIBUTE_INDEX_ROOT,
             self.entity_attribute_index_root,
         );
         page.write_u64(offsets::FREE_LIST_HEAD, self.free_list_head);
+        page.write_u64(offsets::FREE_PAGE_COUNT, self.free_page_count);
         page.write_u64(offsets::LAST_CHECKPOINT_LSN, self.last_checkpoint_lsn);
         page.write_bytes(
             offsets::LAST_CHECKPOINT_HLC,
@@ -156,6 +227,10 @@ impl Superblock {
         page.write_u64(offsets::TXN_LOG_START, self.txn_log_start);
         page.write_u64(offsets::TXN_LOG_END, self.txn_log_end);
         page.write_u64(offsets::TXN_LOG_CAPACITY, self.txn_log_capacity);
+        page.write_u64(
+            offsets::TXN_LOG_CHECKPOINT_OFFSET,
+            self.txn_log_checkpoint_offset,
+        );
         page.write_u64(offsets::ACTIVE_TXN_COUNT, self.active_txn_count);
         page.write_u64(offsets::NEXT_TXN_ID, self.next_txn_id);
         page.write_u64(offsets::SCHEMA_VERSION, self.schema_version);
@@ -163,56 +238,108 @@ impl Superblock {
         page.write_u64(offsets::TOMBSTONE_TAIL_PAGE, self.tombstone_tail_page);
         page.write_u64(offsets::TOMBSTONE_TAIL_SLOT, self.tombstone_tail_slot);
         page.write_u64(offsets::TOMBSTONE_COUNT, self.tombstone_count);
+        page.write_u32(offsets::CHECKSUM_ALGORITHM, self.checksum_algorithm as u32);
+        page.write_u32(offsets::NODE_ID, self.node_id);
+        let checksum = page_checksum(
+            &page.as_bytes()[..SUPERBLOCK_SIZE],
+            offsets::CHECKSUM,
+            self.checksum_algorithm,
+        );
+        page.write_u32(offsets::CHECKSUM, checksum);
+        page.as_bytes_mut()
+            .copy_within(..SUPERBLOCK_SIZE, MIRROR_OFFSET);
 
         Some(page)
     }
 
     /// Deserialize a superblock from a page.
+    ///
+    /// From `PAGE_CHECKSUM_VERSION` on, the superblock must match its
+    /// checksum, so a torn superblock is rejected with
+    /// `SuperblockError::ChecksumMismatch`.
     pub fn from_page(page: &Page) -> Result<Self, SuperblockError> {
+        Self::from_copy(page, 0)
+    }
+
+    /// Deserialize a superblock from a page a crash may have torn.
+    ///
+    /// Like `from_page`, except that if the superblock is invalid, its copy
+    /// at `MIRROR_OFFSET` is read instead. After a torn write that copy is
+    /// either the new superblock or the one the write replaced.
+    ///
+    /// # Errors
+    /// Returns the error for the first copy if neither copy is valid.
+    pub fn from_torn_page(page: &Page) -> Result<Self, SuperblockError> {
+        Self::from_page(page).or_else(|e| Self::from_copy(page, MIRROR_OFFSET).map_err(|_| e))
+    }
+
+    /// Deserialize the copy of the superblock starting at byte `base`.
+    fn from_copy(page: &Page, base: usize) -> Result<Self, SuperblockError> {
         // Validate magic number
-        let magic_slice = page.read_bytes(offsets::MAGIC, 8);
+        let magic_slice = page.read_bytes(base + offsets::MAGIC, 8);
         let mut magic = [0u8; 8];
         magic.copy_from_slice(magic_slice);
         if magic != MAGIC {
             return Err(SuperblockError::InvalidMagic(magic));
         }
 
-        let format_version = page.read_u32(offsets::FORMAT_VERSION);
-        if format_version != FORMAT_VERSION {
+        let format_version = page.read_u32(base + offsets::FORMAT_VERSION);
+        if !(OLDEST_FORMAT_VERSION..=FORMAT_VERSION).contains(&format_version) {
             return Err(SuperblockError::UnsupportedVersion(format_version));
         }
 
-        let page_size = page.read_u32(offsets::PAGE_SIZE);
-        if page_size != PAGE_SIZE_U32 {
+        let page_size = page.read_u32(base + offsets::PAGE_SIZE);
+        if !is_supported_page_size(page_size as usize) {
             return Err(SuperblockError::InvalidPageSize(page_size));
         }
 
-        let hlc_slice = page.read_bytes(offsets::LAST_CHECKPOINT_HLC, 16);
+        let checksum_algorithm =
+            ChecksumAlgorithm::try_from(page.read_u32(base + offsets::CHECKSUM_ALGORITHM))
+                .map_err(SuperblockError::UnknownChecksumAlgorithm)?;
+
+        if format_version >= PAGE_CHECKSUM_VERSION {
+            let expected = page.read_u32(base + offsets::CHECKSUM);
+            let actual = page_checksum(
+                &page.as_bytes()[base..base + SUPERBLOCK_SIZE],
+                offsets::CHECKSUM,
+                checksum_algorithm,
+            );
+            if actual != expected {
+                return Err(SuperblockError::ChecksumMismatch { expected, actual });
+            }
+        }
+
+        let hlc_slice = page.read_bytes(base + offsets::LAST_CHECKPOINT_HLC, 16);
         let mut hlc_bytes = [0u8; 16];
         hlc_bytes.copy_from_slice(hlc_slice);
 
         Ok(Self {
             format_version,
             page_size,
-            file_size: page.read_u64(offsets::FILE_SIZE),
-            total_page_count: page.read_u64(offsets::TOTAL_PAGE_COUNT),
-            primary_index_root: page.read_u64(offsets::PRIMARY_INDEX_ROOT),
-            attribute_index_root: page.read_u64(offsets::ATTRIBUTE_INDEX_ROOT),
-            entity_attribute_index_root: page.read_u64(offsets::ENTITY_ATTRIBUTE_INDEX_ROOT),
-            free_list_head: page.read_u64(offsets::FREE_LIST_HEAD),
-            last_checkpoint_lsn: page.read_u64(offsets::LAST_CHECKPOINT_LSN),
+            file_size: page.read_u64(base + offsets::FILE_SIZE),
+            total_page_count: page.read_u64(base + offsets::TOTAL_PAGE_COUNT),
+            primary_index_root: page.read_u64(base + offsets::PRIMARY_INDEX_ROOT),
+            attribute_index_root: page.read_u64(base + offsets::ATTRIBUTE_INDEX_ROOT),
+            attribute_value_index_root: page.read_u64(base + offsets::ATTRIBUTE_VALUE_INDEX_ROOT),
+            entity_attribute_index_root: page.read_u64(base + offsets::ENTITY_ATTRIBUTE_INDEX_ROOT),
+            free_list_head: page.read_u64(base + offsets::FREE_LIST_HEAD),
+            free_page_count: page.read_u64(base + offsets::FREE_PAGE_COUNT),
+            last_checkpoint_lsn: page.read_u64(base + offsets::LAST_CHECKPOINT_LSN),
             last_checkpoint_hlc: HlcTimestamp::from_bytes(&hlc_bytes),
-            last_wal_lsn: page.read_u64(offsets::LAST_WAL_LSN),
-            txn_log_start: page.read_u64(offsets::TXN_LOG_START),
-            txn_log_end: page.read_u64(offsets::TXN_LOG_END),
-            txn_log_capacity: page.read_u64(offsets::TXN_LOG_CAPACITY),
-            active_txn_count: page.read_u64(offsets::ACTIVE_TXN_COUNT),
-            next_txn_id: page.read_u64(offsets::NEXT_TXN_ID),
-            schema_version: page.read_u64(offsets::SCHEMA_VERSION),
-            tombstone_head_page: page.read_u64(offsets::TOMBSTONE_HEAD_PAGE),
-            tombstone_tail_page: page.read_u64(offsets::TOMBSTONE_TAIL_PAGE),
-            tombstone_tail_slot: page.read_u64(offsets::TOMBSTONE_TAIL_SLOT),
-            tombstone_count: page.read_u64(offsets::TOMBSTONE_COUNT),
+            last_wal_lsn: page.read_u64(base + offsets::LAST_WAL_LSN),
+            txn_log_start: page.read_u64(base + offsets::TXN_LOG_START),
+            txn_log_end: page.read_u64(base + offsets::TXN_LOG_END),
+            txn_log_capacity: page.read_u64(base + offsets::TXN_LOG_CAPACITY),
+            txn_log_checkpoint_offset: page.read_u64(base + offsets::TXN_LOG_CHECKPOINT_OFFSET),
+            active_txn_count: page.read_u64(base + offsets::ACTIVE_TXN_COUNT),
+            next_txn_id: page.read_u64(base + offsets::NEXT_TXN_ID),
+            schema_version: page.read_u64(base + offsets::SCHEMA_VERSION),
+            tombstone_head_page: page.read_u64(base + offsets::TOMBSTONE_HEAD_PAGE),
+            tombstone_tail_page: page.read_u64(base + offsets::TOMBSTONE_TAIL_PAGE),
+            tombstone_tail_slot: page.read_u64(base + offsets::TOMBSTONE_TAIL_SLOT),
+            tombstone_count: page.read_u64(base + offsets::TOMBSTONE_COUNT),
+            checksum_algorithm,
+            node_id: page.read_u32(base + offsets::NODE_ID),
         })
     }
 }
@@ -232,6 +359,11 @@ pub enum SuperblockError {
     UnsupportedVersion(u32),
     /// Invalid page size.
     InvalidPageSize(u32),
+    /// Checksum algorithm identifier not known to this version.
+    UnknownChecksumAlgorithm(u32),
+    /// The superblock does not match its checksum, e.g. because a crash tore
+    /// its write.
+    ChecksumMismatch { expected: u32, actual: u32 },
 }
 
 impl std::fmt::Display for SuperblockError {
@@ -245,7 +377,12 @@ impl std::fmt::Display for SuperblockError {
                 )
             }
             Self::UnsupportedVersion(v) => write!(f, "unsupported format version: {v}"),
-            Self::InvalidPageSize(s) => write!(f, "invalid page size: {s}"),
+            Self::InvalidPageSize(s) => write!(f, "unsupported page size: {s}"),
+            Self::UnknownChecksumAlgorithm(id) => write!(f, "unknown checksum algorithm: {id}"),
+            Self::ChecksumMismatch { expected, actual } => write!(
+                f,
+                "superblock checksum mismatch: expected {expected:#010x}, got {actual:#010x}"
+            ),
         }
     }
 }
@@ -269,14 +406,18 @@ mod tests {
         sb.total_page_count = 128;
         sb.primary_index_root = 5;
         sb.attribute_index_root = 10;
+        sb.attribute_value_index_root = 11;
         sb.entity_attribute_index_root = 12;
         sb.free_list_head = 15;
+        sb.free_page_count = 3;
         sb.next_txn_id = 42;
+        sb.txn_log_checkpoint_offset = 4096;
         sb.last_checkpoint_hlc = HlcTimestamp {
             physical_time: 1_234_567_890,
             logical_counter: 100,
             node_id: 1,
         };
+        sb.node_id = 7;
 
         let page = sb.to_page(&pool).expect("should serialize");
         let restored = Superblock::from_page(&page).expect("should parse");
@@ -287,12 +428,16 @@ mod tests {
         assert_eq!(restored.total_page_count, 128);
         assert_eq!(restored.primary_index_root, 5);
         assert_eq!(restored.attribute_index_root, 10);
+        assert_eq!(restored.attribute_value_index_root, 11);
         assert_eq!(restored.entity_attribute_index_root, 12);
         assert_eq!(restored.free_list_head, 15);
+        assert_eq!(restored.free_page_count, 3);
         assert_eq!(restored.next_txn_id, 42);
+        assert_eq!(restored.txn_log_checkpoint_offset, 4096);
         assert_eq!(restored.last_checkpoint_hlc.physical_time, 1_234_567_890);
         assert_eq!(restored.last_checkpoint_hlc.logical_counter, 100);
         assert_eq!(restored.last_checkpoint_hlc.node_id, 1);
+        assert_eq!(restored.node_id, 7);
     }
 
     #[test]
@@ -304,4 +449,125 @@ mod tests {
         let result = Superblock::from_page(&page);
         assert!(matches!(result, Err(SuperblockError::InvalidMagic(_))));
     }
+
+    #[test]
+    fn test_superblock_checksum_algorithm_roundtrip() {
+        let pool = test_pool();
+
+        let mut sb = Superblock::new();
+        assert_eq!(sb.checksum_algorithm, ChecksumAlgorithm::Crc32);
+        sb.checksum_algorithm = ChecksumAlgorithm::Crc32c;
+
+        let page = sb.to_page(&pool).expect("should serialize");
+        let restored = Superblock::from_page(&page).expect("should parse");
+        assert_eq!(restored.checksum_algorithm, ChecksumAlgorithm::Crc32c);
+    }
+
+    #[test]
+    fn test_superblock_unrecorded_checksum_algorithm_is_crc32() {
+        let pool = test_pool();
+
+        // Files written before the field existed have zeroed reserved space there
+        let page = Superblock::new().to_page(&pool).expect("should serialize");
+        assert_eq!(page.read_u32(offsets::CHECKSUM_ALGORITHM), 0);
+
+        let restored = Superblock::from_page(&page).expect("should parse");
+        assert_eq!(restored.checksum_algorithm, ChecksumAlgorithm::Crc32);
+    }
+
+    #[test]
+    fn test_superblock_unknown_checksum_algorithm() {
+        let pool = test_pool();
+        let mut page = Superblock::new().to_page(&pool).expect("should serialize");
+        page.write_u32(offsets::CHECKSUM_ALGORITHM, 99);
+
+        let result = Superblock::from_page(&page);
+        assert!(matches!(
+            result,
+            Err(SuperblockError::UnknownChecksumAlgorithm(99))
+        ));
+    }
+
+    #[test]
+    fn test_superblock_custom_page_size_roundtrip() {
+        let pool = test_pool();
+
+        let sb = Superblock::with_page_size(4096);
+        assert_eq!(sb.file_size, 4096);
+
+        let page = sb.to_page(&pool).expect("should serialize");
+        let restored = Superblock::from_page(&page).expect("should parse");
+        assert_eq!(restored.page_size, 4096);
+    }
+
+    #[test]
+    fn test_superblock_unsupported_page_size() {
+        let pool = test_pool();
+
+        let sb = Superblock::with_page_size(3000);
+        let page = sb.to_page(&pool).expect("should serialize");
+
+        let result = Superblock::from_page(&page);
+        assert!(matches!(
+            result,
+            Err(SuperblockError::InvalidPageSize(3000))
+        ));
+    }
+
+    #[test]
+    fn test_superblock_format_versions() {
+        let pool = test_pool();
+        let mut sb = Superblock::new();
+
+        for version in OLDEST_FORMAT_VERSION..=FORMAT_VERSION {
+            sb.format_version = version;
+            let page = sb.to_page(&pool).expect("should serialize");
+            let restored = Superblock::from_page(&page).expect("should parse");
+            assert_eq!(restored.format_version, version);
+        }
+        for version in [0, FORMAT_VERSION + 1] {
+            sb.format_version = version;
+            let page = sb.to_page(&pool).expect("should serialize");
+            assert!(matches!(
+                Superblock::from_page(&page),
+                Err(SuperblockError::UnsupportedVersion(v)) if v == version
+            ));
+        }
+    }
+
+    #[test]
+    fn test_torn_superblock_falls_back_to_its_copy() {
+        let pool = test_pool();
+        let mut sb = Superblock::new();
+        sb.next_txn_id = 10;
+        let before = sb.to_page(&pool).expect("should serialize");
+        sb.next_txn_id = 11;
+        sb.txn_log_end = 4096;
+        let after = sb.to_page(&pool).expect("should serialize");
+
+        for kept in [
+            offsets::TXN_LOG_END + 2,
+            offsets::NEXT_TXN_ID + 4,
+            SUPERBLOCK_SIZE,
+            MIRROR_OFFSET + 8,
+        ] {
+            // Only `kept` bytes of the new page reached the disk
+            let mut torn = pool.lease_page_zeroed().expect("should lease");
+            torn.as_bytes_mut().copy_from_slice(before.as_bytes());
+            torn.as_bytes_mut()[..kept].copy_from_slice(&after.as_bytes()[..kept]);
+
+            let restored = Superblock::from_torn_page(&torn).expect("should parse");
+            if kept < SUPERBLOCK_SIZE {
+                assert!(matches!(
+                    Superblock::from_page(&torn),
+                    Err(SuperblockError::ChecksumMismatch { .. })
+                ));
+                assert_eq!(restored.next_txn_id, 10, "kept {kept}");
+                assert_eq!(restored.txn_log_end, 0, "kept {kept}");
+            } else {
+                assert_eq!(restored.next_txn_id, 11, "kept {kept}");
+                assert_eq!(restored.txn_log_end, 4096, "kept {kept}");
+            }
+        }
+    }
 }
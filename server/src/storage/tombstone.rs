@@ -20,24 +20,27 @@
 //! ```text
 //! Offset   Size   Field
 //! 0-7      8      Page type marker (PageType::TombstoneList)
-//! 8-15     8      Entry count in this page (0-204)
+//! 8-15     8      Entry count in this page (0-204 with 8KB pages)
 //! 16-23    8      Next page ID (0 = end of list)
 //! 24-31    8      Head slot (only used in head page, for partial consumption)
-//! 32+      var    Tombstone entries (40 bytes each)
+//! 32+      var    Tombstone entries (48 bytes each)
 //! ```
 
 use crate::storage::file::DatabaseFile;
-use crate::storage::page::{PAGE_SIZE, PageHeader, PageId, PageType};
+use crate::storage::page::{PageHeader, PageId, PageType};
 use crate::types::{AttributeId, EntityId, TxnId};
 
 /// Size of a serialized tombstone in bytes.
-pub const TOMBSTONE_SIZE: usize = 40;
+pub const TOMBSTONE_SIZE: usize = 48;
 
 /// Header size in a tombstone page.
 const TOMBSTONE_PAGE_HEADER_SIZE: usize = 32;
 
-/// Maximum number of tombstones per page.
-pub const TOMBSTONES_PER_PAGE: usize = (PAGE_SIZE - TOMBSTONE_PAGE_HEADER_SIZE) / TOMBSTONE_SIZE;
+/// Maximum number of tombstones in a page of `page_size` bytes.
+#[must_use]
+pub const fn tombstones_per_page(page_size: usize) -> usize {
+    (page_size - TOMBSTONE_PAGE_HEADER_SIZE) / TOMBSTONE_SIZE
+}
 
 /// Offset of the entry count field in a tombstone page.
 const OFFSET_ENTRY_COUNT: usize = 8;
@@ -63,6 +66,8 @@ pub struct Tombstone {
     pub entity_id: EntityId,
     /// Attribute ID of the deleted record.
     pub attribute_id: AttributeId,
+    /// Value hash of the deleted record: 0 for a cardinality-one attribute.
+    pub value_hash: u64,
     /// Transaction ID that performed the deletion.
     pub deleted_txn: TxnId,
 }
@@ -73,11 +78,17 @@ impl Tombstone {
     /// # Pre-conditions
     /// - `deleted_txn > 0`
     #[must_use]
-    pub const fn new(entity_id: EntityId, attribute_id: AttributeId, deleted_txn: TxnId) -> Self {
+    pub const fn new(
+        entity_id: EntityId,
+        attribute_id: AttributeId,
+        value_hash: u64,
+        deleted_txn: TxnId,
+    ) -> Self {
         debug_assert!(deleted_txn > 0, "deleted_txn must be positive");
         Self {
             entity_id,
             attribute_id,
+            value_hash,
             deleted_txn,
         }
     }
@@ -101,7 +112,8 @@ impl Tombstone {
         let mut bytes = [0u8; TOMBSTONE_SIZE];
         bytes[0..16].copy_from_slice(&self.entity_id.0);
         bytes[16..32].copy_from_slice(&self.attribute_id.0);
-        bytes[32..40].copy_from_slice(&self.deleted_txn.to_le_bytes());
+        bytes[32..40].copy_from_slice(&self.value_hash.to_le_bytes());
+        bytes[40..48].copy_from_slice(&self.deleted_txn.to_le_bytes());
         bytes
     }
 
@@ -117,13 +129,17 @@ impl Tombstone {
         let mut attribute = [0u8; 16];
         entity.copy_from_slice(&bytes[0..16]);
         attribute.copy_from_slice(&bytes[16..32]);
-        let deleted_txn = u64::from_le_bytes([
+        let value_hash = u64::from_le_bytes([
             bytes[32], bytes[33], bytes[34], bytes[35], bytes[36], bytes[37], bytes[38], bytes[39],
         ]);
+        let deleted_txn = u64::from_le_bytes([
+            bytes[40], bytes[41], bytes[42], bytes[43], bytes[44], bytes[45], bytes[46], bytes[47],
+        ]);
 
         Self {
             entity_id: EntityId(entity),
             attribute_id: AttributeId(attribute),
+            value_hash,
             deleted_txn,
         }
     }
@@ -136,7 +152,7 @@ impl Tombstone {
 /// - `head_page_id == 0` implies empty list (`tail_page_id` also 0)
 /// - `head_page_id != 0` implies `tail_page_id != 0`
 /// - `head_slot` is only meaningful when `head_page_id` != 0
-/// - `tail_slot` is the next available slot in the tail page (0 to `TOMBSTONES_PER_PAGE`)
+/// - `tail_slot` is the next available slot in the tail page (0 to `tombstones_per_page`)
 /// - Write buffer entries are ordered by `deleted_txn` (ascending)
 ///
 /// # Memory Usage
@@ -311,7 +327,7 @@ impl TombstoneList {
         }
 
         // If tail page is full, allocate a new page
-        if self.tail_slot >= TOMBSTONES_PER_PAGE {
+        if self.tail_slot >= tombstones_per_page(file.page_size()) {
             let new_page_id = file.allocate_pages(1)?;
 
             // Link old tail to new page
@@ -523,7 +539,7 @@ mod tests {
 
     #[test]
     fn test_tombstone_serialization() {
-        let tombstone = Tombstone::new(EntityId([1u8; 16]), AttributeId([2u8; 16]), 42);
+        let tombstone = Tombstone::new(EntityId([1u8; 16]), AttributeId([2u8; 16]), 7, 42);
 
         let bytes = tombstone.to_bytes();
         assert_eq!(bytes.len(), TOMBSTONE_SIZE);
@@ -531,12 +547,13 @@ mod tests {
         let restored = Tombstone::from_bytes(&bytes);
         assert_eq!(restored.entity_id, tombstone.entity_id);
         assert_eq!(restored.attribute_id, tombstone.attribute_id);
+        assert_eq!(restored.value_hash, tombstone.value_hash);
         assert_eq!(restored.deleted_txn, tombstone.deleted_txn);
     }
 
     #[test]
     fn test_tombstone_gc_eligibility() {
-        let tombstone = Tombstone::new(EntityId([1u8; 16]), AttributeId([2u8; 16]), 10);
+        let tombstone = Tombstone::new(EntityId([1u8; 16]), AttributeId([2u8; 16]), 0, 10);
 
         // No active snapshots - always eligible
         assert!(tombstone.is_gc_eligible(None));
@@ -563,8 +580,8 @@ mod tests {
     fn test_tombstone_list_append() {
         let mut list = TombstoneList::new();
 
-        let t1 = Tombstone::new(EntityId([1u8; 16]), AttributeId([1u8; 16]), 1);
-        let t2 = Tombstone::new(EntityId([2u8; 16]), AttributeId([2u8; 16]), 2);
+        let t1 = Tombstone::new(EntityId([1u8; 16]), AttributeId([1u8; 16]), 0, 1);
+        let t2 = Tombstone::new(EntityId([2u8; 16]), AttributeId([2u8; 16]), 0, 2);
 
         list.append(t1);
         assert_eq!(list.count(), 1);
@@ -586,6 +603,7 @@ mod tests {
             list.append(Tombstone::new(
                 EntityId([i as u8; 16]),
                 AttributeId([0u8; 16]),
+                0,
                 i as u64 + 1,
             ));
             assert!(!list.needs_flush());
@@ -595,6 +613,7 @@ mod tests {
         list.append(Tombstone::new(
             EntityId([99u8; 16]),
             AttributeId([0u8; 16]),
+            0,
             100,
         ));
         assert!(list.needs_flush());
@@ -615,7 +634,8 @@ mod tests {
     #[test]
     fn test_tombstones_per_page() {
         // Verify the calculation
-        assert_eq!(TOMBSTONES_PER_PAGE, (PAGE_SIZE - 32) / 40);
-        assert_eq!(TOMBSTONES_PER_PAGE, 204);
+        assert_eq!(tombstones_per_page(4096), 84);
+        assert_eq!(tombstones_per_page(8192), 170);
+        assert_eq!(tombstones_per_page(65536), 1364);
     }
 }
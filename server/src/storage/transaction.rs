@@ -136,7 +136,7 @@ impl<'a> Transaction<'a> {
         }
 
         self.index
-            .mark_deleted(entity_id, attribute_id, self.txn_id)?;
+            .mark_deleted(entity_id, attribute_id, 0, self.txn_id, self.hlc)?;
         Ok(())
     }
 
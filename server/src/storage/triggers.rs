@@ -0,0 +1,424 @@
+//! Attribute triggers: callbacks that derive extra writes at commit.
+//!
+//! A trigger watches one attribute. When a transaction changes a triple with
+//! that attribute, `WalTransaction::commit` calls the trigger with the change
+//! and a `TriggerContext`. The trigger can read triples and buffer more writes,
+//! which join the same transaction: they go through the same last-writer-wins
+//! resolution, commit atomically with the change that caused them, and appear
+//! in the same change notification.
+//!
+//! # Example
+//!
+//! Keep `full_name` in sync with `first_name`:
+//!
+//! ```no_run
+//! # use server::storage::Database;
+//! # use server::types::{AttributeId, TripleValue};
+//! # fn example(db: &mut Database) {
+//! let first_name = AttributeId([1u8; 16]);
+//! let full_name = AttributeId([2u8; 16]);
+//! db.register_trigger(first_name, move |event, ctx| {
+//!     let name = match event.value {
+//!         Some(TripleValue::String(first)) => TripleValue::String(format!("{first} Smith")),
+//!         _ => TripleValue::Null,
+//!     };
+//!     ctx.set(event.entity_id, full_name, name)
+//! });
+//! # }
+//! ```
+//!
+//! # Limits
+//!
+//! Triggered writes can fire further triggers. A trigger only fires when a
+//! write actually changes the stored value, so a trigger that rewrites the
+//! same value settles. To stop runaway chains, a commit fails with
+//! `DatabaseError::TriggerDepthExceeded` if triggers nest more than
+//! `MAX_TRIGGER_DEPTH` deep, and with `DatabaseError::TooManyTriggeredWrites`
+//! if they buffer more than `MAX_TRIGGERED_WRITES` writes in total. A failed
+//! commit writes nothing.
+
+use std::collections::HashMap;
+
+use crate::storage::DatabaseError;
+use crate::storage::file::DatabaseFile;
+use crate::storage::indexes::primary::PrimaryIndex;
+use crate::types::{AttributeId, EntityId, HlcTimestamp, PendingTriple, TripleRecord};
+use crate::types::{TripleValue, TxnId};
+
+/// Deepest chain of triggers a single write may set off.
+///
+/// A write from a client is depth 0; a write buffered by a trigger is one
+/// deeper than the write that fired it.
+pub const MAX_TRIGGER_DEPTH: usize = 8;
+
+/// Most writes triggers may buffer in a single transaction.
+pub const MAX_TRIGGERED_WRITES: usize = 1024;
+
+/// Identifies a registered trigger, for `Database::unregister_trigger`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct TriggerId(u64);
+
+/// A change that fired a trigger.
+#[derive(Debug)]
+pub struct TriggerEvent<'e> {
+    /// Entity of the changed triple.
+    pub entity_id: EntityId,
+    /// Attribute of the changed triple (the one the trigger watches).
+    pub attribute_id: AttributeId,
+    /// The new value, or `None` if the triple was deleted.
+    pub value: Option<&'e TripleValue>,
+    /// HLC of the write.
+    pub hlc: HlcTimestamp,
+}
+
+/// A trigger callback.
+///
+/// Errors abort the commit.
+pub type TriggerFn =
+    dyn Fn(&TriggerEvent<'_>, &mut TriggerContext<'_>) -> Result<(), DatabaseError> + Send + Sync;
+
+/// Triggers registered on a database, by watched attribute.
+#[derive(Default)]
+pub(crate) struct AttributeTriggers {
+    next_id: u64,
+    by_attribute: HashMap<AttributeId, Vec<(TriggerId, Box<TriggerFn>)>>,
+}
+
+impl AttributeTriggers {
+    /// Register a trigger on `attribute_id`.
+    pub(crate) fn register(
+        &mut self,
+        attribute_id: AttributeId,
+        trigger: Box<TriggerFn>,
+    ) -> TriggerId {
+        self.next_id += 1;
+        let id = TriggerId(self.next_id);
+        self.by_attribute
+            .entry(attribute_id)
+            .or_default()
+            .push((id, trigger));
+        id
+    }
+
+    /// Remove a trigger. Returns whether it was registered.
+    pub(crate) fn unregister(&mut self, id: TriggerId) -> bool {
+        let mut found = false;
+        self.by_attribute.retain(|_, triggers| {
+            let before = triggers.len();
+            triggers.retain(|(trigger_id, _)| *trigger_id != id);
+            found |= triggers.len() != before;
+            !triggers.is_empty()
+        });
+        found
+    }
+
+    /// Check whether any trigger watches `attribute_id`.
+    pub(crate) fn watches(&self, attribute_id: &AttributeId) -> bool {
+        self.by_attribute.contains_key(attribute_id)
+    }
+
+    /// Run every trigger watching the attribute of `pending`'s last write.
+    ///
+    /// `pending` holds the transaction's writes resolved so far; triggers see
+    /// them through `TriggerContext::get`. Writes are stamped with the
+    /// transaction's ID and HLC.
+    ///
+    /// # Pre-conditions
+    /// - `pending` is not empty
+    ///
+    /// # Errors
+    /// Returns the first error a trigger reports.
+    pub(crate) fn fire(
+        &self,
+        file: &mut DatabaseFile,
+        pending: &[PendingTriple],
+        txn_id: TxnId,
+        hlc: HlcTimestamp,
+    ) -> Result<Vec<PendingTriple>, DatabaseError> {
+        let Some(last) = pending.last() else {
+            return Ok(Vec::new());
+        };
+        let event = match last {
+            PendingTriple::Insert(record) | PendingTriple::Update(record) => TriggerEvent {
+                entity_id: record.entity_id,
+                attribute_id: record.attribute_id,
+                value: Some(&record.value),
+                hlc: record.created_hlc,
+            },
+            PendingTriple::Delete {
+                entity_id,
+                attribute_id,
+                hlc,
+                ..
+            } => TriggerEvent {
+                entity_id: *entity_id,
+                attribute_id: *attribute_id,
+                value: None,
+                hlc: *hlc,
+            },
+        };
+        let Some(triggers) = self.by_attribute.get(&event.attribute_id) else {
+            return Ok(Vec::new());
+        };
+
+        let mut ctx = TriggerContext {
+            file,
+            pending,
+            txn_id,
+            hlc,
+            writes: Vec::new(),
+        };
+        for (_, trigger) in triggers {
+            trigger(&event, &mut ctx)?;
+        }
+        Ok(ctx.writes)
+    }
+}
+
+impl std::fmt::Debug for AttributeTriggers {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("AttributeTriggers")
+            .field(
+                "watched_attributes",
+                &self.by_attribute.keys().collect::<Vec<_>>(),
+            )
+            .finish_non_exhaustive()
+    }
+}
+
+/// Access a trigger gets to the committing transaction.
+///
+/// Reads see committed triples overlaid with the transaction's writes
+/// resolved so far. Writes are buffered and join the transaction once the
+/// trigger returns.
+pub struct TriggerContext<'c> {
+    file: &'c mut DatabaseFile,
+    pending: &'c [PendingTriple],
+    txn_id: TxnId,
+    hlc: HlcTimestamp,
+    writes: Vec<PendingTriple>,
+}
+
+impl TriggerContext<'_> {
+    /// Get a triple's value as this transaction would leave it so far.
+    ///
+    /// Returns `None` if the triple does not exist or is deleted.
+    pub fn get(
+        &mut self,
+        entity_id: &EntityId,
+        attribute_id: &AttributeId,
+    ) -> Result<Option<TripleValue>, DatabaseError> {
+        let buffered = self.writes.iter().rev().chain(self.pending.iter().rev());
+        for op in buffered {
+            match op {
+                PendingTriple::Insert(record) | PendingTriple::Update(record)
+                    if record.entity_id == *entity_id && record.attribute_id == *attribute_id =>
+                {
+                    return Ok(Some(record.value.clone_value()));
+                }
+                PendingTriple::Delete {
+                    entity_id: deleted_entity,
+                    attribute_id: deleted_attribute,
+                    ..
+                } if deleted_entity == entity_id && deleted_attribute == attribute_id => {
+                    return Ok(None);
+                }
+                _ => {}
+            }
+        }
+
+        let root_page = self.file.superblock().primary_index_root;
+        let mut index = PrimaryIndex::new(self.file, root_page)?;
+        Ok(index
+            .get(entity_id, attribute_id)?
+            .filter(|record| !record.is_deleted() && !record.is_expired(self.hlc.physical_time))
+            .map(|record| record.value))
+    }
+
+    /// Buffer a write of `value` to a triple, inserting it if absent.
+    ///
+    /// The write uses the transaction's HLC.
+    pub fn set(
+        &mut self,
+        entity_id: EntityId,
+        attribute_id: AttributeId,
+        value: TripleValue,
+    ) -> Result<(), DatabaseError> {
+        let exists = self.get(&entity_id, &attribute_id)?.is_some();
+        let record = TripleRecord::new(entity_id, attribute_id, self.txn_id, self.hlc, value);
+        self.writes.push(if exists {
+            PendingTriple::Update(record)
+        } else {
+            PendingTriple::Insert(record)
+        });
+        Ok(())
+    }
+
+    /// Buffer a delete of a cardinality-one triple.
+    ///
+    /// The delete uses the transaction's HLC; deleting a missing triple is a
+    /// no-op at commit.
+    pub fn delete(&mut self, entity_id: EntityId, attribute_id: AttributeId) {
+        self.writes.push(PendingTriple::Delete {
+            entity_id,
+            attribute_id,
+            value_hash: 0,
+            hlc: self.hlc,
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::Database;
+    use crate::storage::buffer_pool::BufferPool;
+
+    const FIRST_NAME: AttributeId = AttributeId([1u8; 16]);
+    const LAST_NAME: AttributeId = AttributeId([2u8; 16]);
+    const FULL_NAME: AttributeId = AttributeId([3u8; 16]);
+    const COUNTER: AttributeId = AttributeId([4u8; 16]);
+    const ENTITY: EntityId = EntityId([9u8; 16]);
+
+    fn create_db() -> (tempfile::TempDir, Database) {
+        let dir = tempfile::tempdir().expect("create temp dir");
+        let db =
+            Database::create(&dir.path().join("test.db"), BufferPool::new(100)).expect("create db");
+        (dir, db)
+    }
+
+    /// Recompute `full_name` from `first_name` and `last_name`.
+    fn recompute_full_name(
+        event: &TriggerEvent<'_>,
+        ctx: &mut TriggerContext<'_>,
+    ) -> Result<(), DatabaseError> {
+        let part = |value: Option<TripleValue>| match value {
+            Some(TripleValue::String(s)) => s,
+            _ => String::new(),
+        };
+        let first = part(ctx.get(&event.entity_id, &FIRST_NAME)?);
+        let last = part(ctx.get(&event.entity_id, &LAST_NAME)?);
+        ctx.set(
+            event.entity_id,
+            FULL_NAME,
+            TripleValue::String(format!("{first} {last}").trim().to_owned()),
+        )
+    }
+
+    fn get_value(db: &Database, attribute_id: &AttributeId) -> Option<TripleValue> {
+        let snapshot = db.begin_readonly().expect("begin readonly");
+        let value = snapshot
+            .get(&ENTITY, attribute_id)
+            .expect("get")
+            .map(|record| record.value);
+        let txn_id = snapshot.close();
+        db.release_snapshot(txn_id).expect("release snapshot");
+        value
+    }
+
+    #[test]
+    fn test_trigger_writes_commit_with_the_change() {
+        let (_dir, mut db) = create_db();
+        db.register_trigger(FIRST_NAME, recompute_full_name);
+        db.register_trigger(LAST_NAME, recompute_full_name);
+        let mut changes = db.subscribe_to_changes(1);
+
+        let mut txn = db.begin(0).expect("begin");
+        txn.insert(ENTITY, FIRST_NAME, TripleValue::String("Ada".to_owned()))
+            .expect("insert");
+        txn.insert(ENTITY, LAST_NAME, TripleValue::String("Byron".to_owned()))
+            .expect("insert");
+        txn.commit().expect("commit");
+        assert_eq!(
+            get_value(&db, &FULL_NAME),
+            Some(TripleValue::String("Ada Byron".to_owned()))
+        );
+
+        // The triggered write is in the same notification as its cause
+        let notification = changes.try_recv().expect("notification");
+        let attributes: Vec<AttributeId> = notification
+            .changes
+            .iter()
+            .map(|change| change.attribute_id)
+            .collect();
+        assert!(attributes.contains(&FIRST_NAME));
+        assert!(attributes.contains(&FULL_NAME));
+
+        let mut txn = db.begin(0).expect("begin");
+        txn.update(
+            ENTITY,
+            LAST_NAME,
+            TripleValue::String("Lovelace".to_owned()),
+        )
+        .expect("update");
+        txn.commit().expect("commit");
+        assert_eq!(
+            get_value(&db, &FULL_NAME),
+            Some(TripleValue::String("Ada Lovelace".to_owned()))
+        );
+    }
+
+    #[test]
+    fn test_trigger_does_not_fire_for_unchanged_value() {
+        let (_dir, mut db) = create_db();
+        let mut txn = db.begin(0).expect("begin");
+        txn.insert(ENTITY, FIRST_NAME, TripleValue::String("Ada".to_owned()))
+            .expect("insert");
+        txn.commit().expect("commit");
+
+        db.register_trigger(FIRST_NAME, recompute_full_name);
+        let mut txn = db.begin(0).expect("begin");
+        txn.update(ENTITY, FIRST_NAME, TripleValue::String("Ada".to_owned()))
+            .expect("update");
+        txn.commit().expect("commit");
+        assert_eq!(get_value(&db, &FULL_NAME), None);
+    }
+
+    #[test]
+    fn test_runaway_trigger_hits_depth_limit() {
+        let (_dir, mut db) = create_db();
+        // Each write to the counter writes the counter again, forever
+        db.register_trigger(COUNTER, |event, ctx| {
+            let next = match event.value {
+                Some(TripleValue::Number(n)) => n + 1.0,
+                _ => 0.0,
+            };
+            ctx.set(event.entity_id, COUNTER, TripleValue::Number(next))
+        });
+
+        let mut txn = db.begin(0).expect("begin");
+        txn.insert(ENTITY, COUNTER, TripleValue::Number(0.0))
+            .expect("insert");
+        assert!(matches!(
+            txn.commit(),
+            Err(DatabaseError::TriggerDepthExceeded(MAX_TRIGGER_DEPTH))
+        ));
+        assert_eq!(get_value(&db, &COUNTER), None);
+    }
+
+    #[test]
+    fn test_trigger_error_aborts_commit() {
+        let (_dir, mut db) = create_db();
+        db.register_trigger(FIRST_NAME, |_, _| Err(DatabaseError::NotFound));
+
+        let mut txn = db.begin(0).expect("begin");
+        txn.insert(ENTITY, FIRST_NAME, TripleValue::String("Ada".to_owned()))
+            .expect("insert");
+        assert!(matches!(txn.commit(), Err(DatabaseError::NotFound)));
+        assert_eq!(get_value(&db, &FIRST_NAME), None);
+    }
+
+    #[test]
+    fn test_unregistered_trigger_no_longer_fires() {
+        let (_dir, mut db) = create_db();
+        let id = db.register_trigger(FIRST_NAME, recompute_full_name);
+        assert!(db.unregister_trigger(id));
+        assert!(!db.unregister_trigger(id));
+
+        let mut txn = db.begin(0).expect("begin");
+        txn.insert(ENTITY, FIRST_NAME, TripleValue::String("Ada".to_owned()))
+            .expect("insert");
+        txn.commit().expect("commit");
+        assert_eq!(get_value(&db, &FULL_NAME), None);
+    }
+}
@@ -0,0 +1,577 @@
+//! Upgrades of database files written in format version 1.
+//!
+//! `Database::open` rewrites a version 1 file before opening it. The old file
+//! is read without being written (see `DatabaseFile::open_to_copy`), its
+//! triples are imported into a new file in the current format beside it, and
+//! the new file is renamed over the old one, so an upgrade that fails
+//! part-way leaves the old file as it was.
+//!
+//! # Format Versions
+//!
+//! ```text
+//! Version  Change
+//! 1        Original format
+//! 2        Records carry the delete's HLC, an expiry time, a cardinality
+//!          and a value hash, which primary index keys and WAL deletes
+//!          include. The attribute index gains value-ordered entries. Pages
+//!          and the superblock carry a checksum.
+//! ```
+//!
+//! Only the primary index and the WAL are read: the import rebuilds the other
+//! indexes. Every triple of a version 1 file is cardinality-one and never
+//! expires, and a deleted one recorded no delete HLC. Superblock fields added
+//! in reserved space read as zero: a CRC32 checksum algorithm, an unassigned
+//! node ID, an empty free list, and no recorded WAL checkpoint offset.
+//!
+//! The layouts here are frozen copies of the ones version 1 wrote, so they
+//! must not follow later changes to the current layout.
+
+use std::collections::HashMap;
+
+use crate::storage::btree::{NodeHeader, NodeType};
+use crate::storage::file::{DatabaseFile, FileError};
+use crate::storage::overflow::{OverflowError, OverflowRef, read_overflow_at};
+use crate::storage::page::{Page, PageHeader, PageId};
+use crate::storage::superblock::{FORMAT_VERSION, Superblock};
+use crate::storage::wal::{LogRecord, LogRecordPayload, WalError};
+use crate::types::{
+    AttributeId, EntityId, HlcTimestamp, TripleError, TripleRecord, TripleValue, TxnId,
+};
+
+/// Size of a version 1 primary index key: `entity_id` (16) + `attribute_id`
+/// (16).
+const LEGACY_KEY_SIZE: usize = 32;
+
+/// Offset of a B-tree node's entries: the page header, then a 27-byte node
+/// header.
+const NODE_DATA_OFFSET: usize = PageHeader::SIZE + 27;
+
+/// A database file opened read-only to copy it into the current format.
+pub(crate) struct LegacyFile {
+    file: DatabaseFile,
+}
+
+impl LegacyFile {
+    /// Wrap a file opened with `DatabaseFile::open_to_copy`, whatever its
+    /// format version.
+    pub(crate) const fn new(file: DatabaseFile) -> Self {
+        Self { file }
+    }
+
+    /// The file's superblock, as its format version wrote it.
+    pub(crate) const fn superblock(&self) -> &Superblock {
+        self.file.superblock()
+    }
+
+    /// Whether the file is in an older format than `FORMAT_VERSION`.
+    pub(crate) const fn needs_upgrade(&self) -> bool {
+        self.file.superblock().format_version < FORMAT_VERSION
+    }
+
+    /// The change records of each transaction committed after the last
+    /// checkpoint, in commit order and in the current format.
+    ///
+    /// Their writes may not have reached the index pages, so they are
+    /// replayed after the triples are imported. The records are held in
+    /// memory, which the WAL capacity bounds.
+    pub(crate) fn committed_wal_tail(&mut self) -> Result<Vec<Vec<LogRecord>>, UpgradeError> {
+        if !self.file.has_wal() {
+            return Ok(Vec::new());
+        }
+        let superblock = *self.file.superblock();
+        let checkpoint_lsn = superblock.last_checkpoint_lsn;
+        let mut wal = self.file.wal()?;
+        let records = if checkpoint_lsn == 0 {
+            wal.read_all()?
+        } else if superblock.txn_log_checkpoint_offset == 0 {
+            // Written before the checkpoint offset was recorded
+            wal.read_from_lsn(checkpoint_lsn)?
+        } else {
+            wal.read_from_offset(superblock.txn_log_checkpoint_offset, checkpoint_lsn + 1)?
+        };
+
+        let mut transactions = Vec::new();
+        let mut open: HashMap<TxnId, Vec<LogRecord>> = HashMap::new();
+        for record in records {
+            if record.lsn <= checkpoint_lsn {
+                continue;
+            }
+            let payload = match record.payload {
+                LogRecordPayload::Begin => {
+                    // A repeated BEGIN restarts the transaction
+                    open.insert(record.txn_id, Vec::new());
+                    continue;
+                }
+                LogRecordPayload::Commit => {
+                    if let Some(changes) = open.remove(&record.txn_id) {
+                        transactions.push(changes);
+                    }
+                    continue;
+                }
+                LogRecordPayload::Checkpoint { .. } => continue,
+                LogRecordPayload::Insert(bytes) => {
+                    LogRecordPayload::insert(&decode_record(&bytes)?)
+                }
+                LogRecordPayload::Update(bytes) => {
+                    LogRecordPayload::update(&decode_record(&bytes)?)
+                }
+                // The delete took the HLC of its record header
+                LogRecordPayload::Delete {
+                    entity_id,
+                    attribute_id,
+                    ..
+                } => LogRecordPayload::delete(entity_id, attribute_id, 0, record.hlc),
+            };
+            if let Some(changes) = open.get_mut(&record.txn_id) {
+                changes.push(LogRecord::new(
+                    record.txn_id,
+                    record.lsn,
+                    record.hlc,
+                    payload,
+                ));
+            }
+        }
+        Ok(transactions)
+    }
+
+    /// Read the triples of the primary index, in key order, as of the pages
+    /// on disk.
+    ///
+    /// # Pre-conditions
+    /// - The file needs an upgrade
+    pub(crate) fn triples(&self) -> Result<LegacyTriples<'_>, UpgradeError> {
+        assert!(
+            self.needs_upgrade(),
+            "primary index is in the current layout"
+        );
+        let superblock = self.file.superblock();
+        let mut page_id = superblock.primary_index_root;
+        // Each level is a page, so a deeper descent has met a cycle
+        for _ in 0..superblock.total_page_count {
+            if page_id == 0 {
+                break;
+            }
+            let page = self.file.read_page_at(page_id)?;
+            match node_header(&page, page_id)?.node_type {
+                NodeType::Leaf => break,
+                NodeType::Internal => page_id = page.read_u64(NODE_DATA_OFFSET),
+            }
+        }
+        Ok(LegacyTriples {
+            file: &self.file,
+            next_leaf: page_id,
+            values: Vec::new().into_iter(),
+            leaves_read: 0,
+        })
+    }
+}
+
+/// Reads the triples of a version 1 file's primary index leaf by leaf.
+pub(crate) struct LegacyTriples<'a> {
+    file: &'a DatabaseFile,
+    /// Next leaf to read, or 0 once the last leaf has been read.
+    next_leaf: PageId,
+    /// Stored values of the last leaf read that have not been returned yet.
+    values: std::vec::IntoIter<Vec<u8>>,
+    /// Leaves read so far. More leaves than pages means the chain has a cycle.
+    leaves_read: u64,
+}
+
+impl LegacyTriples<'_> {
+    /// Read the next triple, or `None` once every triple has been read.
+    ///
+    /// Deleted triples are returned too, with their delete's transaction.
+    /// Version 1 recorded no delete HLC, so they carry their creation HLC
+    /// in its place.
+    pub(crate) fn next_record(&mut self) -> Result<Option<TripleRecord>, UpgradeError> {
+        loop {
+            if let Some(stored) = self.values.next() {
+                let bytes = match OverflowRef::from_bytes(&stored) {
+                    Some(overflow_ref) => read_overflow_at(self.file, &overflow_ref)?,
+                    None => stored,
+                };
+                return Ok(Some(decode_record(&bytes)?));
+            }
+            if self.next_leaf == 0 {
+                return Ok(None);
+            }
+            self.read_leaf()?;
+        }
+    }
+
+    /// Read the values of `next_leaf` and move on to its right sibling.
+    fn read_leaf(&mut self) -> Result<(), UpgradeError> {
+        let page_id = self.next_leaf;
+        self.leaves_read += 1;
+        if self.leaves_read > self.file.superblock().total_page_count {
+            return Err(UpgradeError::CorruptIndex(page_id));
+        }
+        let page = self.file.read_page_at(page_id)?;
+        let header = node_header(&page, page_id)?;
+        if header.node_type != NodeType::Leaf {
+            return Err(UpgradeError::CorruptIndex(page_id));
+        }
+
+        // Entries are a key, a u16 value length, then the value
+        let bytes = &page.as_bytes()[..self.file.page_size()];
+        let mut values = Vec::with_capacity(usize::from(header.key_count));
+        let mut offset = NODE_DATA_OFFSET;
+        for _ in 0..header.key_count {
+            let length_offset = offset + LEGACY_KEY_SIZE;
+            let length = bytes
+                .get(length_offset..length_offset + 2)
+                .map(|length| usize::from(u16::from_le_bytes([length[0], length[1]])))
+                .ok_or(UpgradeError::CorruptIndex(page_id))?;
+            let value_offset = length_offset + 2;
+            let value = bytes
+                .get(value_offset..value_offset + length)
+                .ok_or(UpgradeError::CorruptIndex(page_id))?;
+            values.push(value.to_vec());
+            offset = value_offset + length;
+        }
+        self.values = values.into_iter();
+        self.next_leaf = header.next_leaf;
+        Ok(())
+    }
+}
+
+/// Read the node header of B-tree page `page_id`.
+fn node_header(page: &Page, page_id: PageId) -> Result<NodeHeader, UpgradeError> {
+    NodeHeader::from_page(page).ok_or(UpgradeError::CorruptIndex(page_id))
+}
+
+/// Decode a triple record serialized by format version 1.
+///
+/// ```text
+/// entity_id (16), attribute_id (16), created_txn (8), deleted_txn (8),
+/// created_hlc (16), value
+/// ```
+fn decode_record(bytes: &[u8]) -> Result<TripleRecord, TripleError> {
+    let entity_id = EntityId(read_array(bytes, 0)?);
+    let attribute_id = AttributeId(read_array(bytes, 16)?);
+    let created_txn = u64::from_le_bytes(read_array(bytes, 32)?);
+    let deleted_txn = u64::from_le_bytes(read_array(bytes, 40)?);
+    let created_hlc = HlcTimestamp::from_bytes(&read_array(bytes, 48)?);
+    let value_bytes = bytes
+        .get(48 + HlcTimestamp::SIZE..)
+        .filter(|value_bytes| !value_bytes.is_empty())
+        .ok_or(TripleError::InvalidRecord)?;
+    let (value, _) = TripleValue::from_bytes(value_bytes)?;
+
+    let mut record = TripleRecord::new(entity_id, attribute_id, created_txn, created_hlc, value);
+    record.deleted_txn = deleted_txn;
+    record.deleted_hlc = created_hlc;
+    Ok(record)
+}
+
+/// Read the `N` bytes of `bytes` at `offset`.
+fn read_array<const N: usize>(bytes: &[u8], offset: usize) -> Result<[u8; N], TripleError> {
+    bytes
+        .get(offset..offset + N)
+        .and_then(|array| array.try_into().ok())
+        .ok_or(TripleError::InvalidRecord)
+}
+
+/// Errors that can occur reading a file to upgrade it.
+#[derive(Debug)]
+pub enum UpgradeError {
+    /// The file could not be read.
+    File(FileError),
+    /// The WAL could not be read.
+    Wal(WalError),
+    /// A large value could not be read from its overflow pages.
+    Overflow(OverflowError),
+    /// A record could not be deserialized.
+    Triple(TripleError),
+    /// A primary index page is not a well-formed B-tree node, or the leaf
+    /// chain has a cycle.
+    CorruptIndex(PageId),
+}
+
+impl std::fmt::Display for UpgradeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::File(e) => write!(f, "file error: {e}"),
+            Self::Wal(e) => write!(f, "WAL error: {e}"),
+            Self::Overflow(e) => write!(f, "overflow error: {e}"),
+            Self::Triple(e) => write!(f, "invalid record: {e}"),
+            Self::CorruptIndex(page_id) => {
+                write!(f, "primary index page {page_id} is corrupt")
+            }
+        }
+    }
+}
+
+impl std::error::Error for UpgradeError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::File(e) => Some(e),
+            Self::Wal(e) => Some(e),
+            Self::Overflow(e) => Some(e),
+            Self::Triple(e) => Some(e),
+            Self::CorruptIndex(_) => None,
+        }
+    }
+}
+
+impl From<FileError> for UpgradeError {
+    fn from(e: FileError) -> Self {
+        Self::File(e)
+    }
+}
+
+impl From<WalError> for UpgradeError {
+    fn from(e: WalError) -> Self {
+        Self::Wal(e)
+    }
+}
+
+impl From<OverflowError> for UpgradeError {
+    fn from(e: OverflowError) -> Self {
+        Self::Overflow(e)
+    }
+}
+
+impl From<TripleError> for UpgradeError {
+    fn from(e: TripleError) -> Self {
+        Self::Triple(e)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::Database;
+    use crate::storage::buffer_pool::BufferPool;
+    use crate::storage::overflow::write_overflow;
+    use crate::storage::page::PageType;
+    use crate::storage::superblock::OLDEST_FORMAT_VERSION;
+    use crate::storage::wal::DEFAULT_WAL_CAPACITY;
+    use std::path::Path;
+    use tempfile::tempdir;
+
+    fn hlc(physical_time: u64) -> HlcTimestamp {
+        HlcTimestamp::new(physical_time, 0)
+    }
+
+    fn record(index: u8, created_hlc: u64, value: TripleValue) -> TripleRecord {
+        TripleRecord::new(
+            EntityId([index; 16]),
+            AttributeId([0xAA; 16]),
+            1,
+            hlc(created_hlc),
+            value,
+        )
+    }
+
+    fn text(value: &str) -> TripleValue {
+        TripleValue::String(value.to_owned())
+    }
+
+    /// Serialize `record` as format version 1 did.
+    fn encode_record(record: &TripleRecord) -> Vec<u8> {
+        let mut bytes = [record.entity_id.0, record.attribute_id.0].concat();
+        bytes.extend_from_slice(&record.created_txn.to_le_bytes());
+        bytes.extend_from_slice(&record.deleted_txn.to_le_bytes());
+        bytes.extend_from_slice(&record.created_hlc.to_bytes());
+        bytes.extend_from_slice(&record.value.to_bytes());
+        bytes
+    }
+
+    /// The primary index key of `record` in format version 1.
+    fn legacy_key(record: &TripleRecord) -> Vec<u8> {
+        [record.entity_id.0, record.attribute_id.0].concat()
+    }
+
+    /// Leaf entries storing each record as `stored`.
+    fn leaf_data(entries: &[(&TripleRecord, Vec<u8>)]) -> Vec<u8> {
+        let mut data = Vec::new();
+        for (record, stored) in entries {
+            let length = u16::try_from(stored.len()).expect("inline value");
+            data.extend_from_slice(&legacy_key(record));
+            data.extend_from_slice(&length.to_le_bytes());
+            data.extend_from_slice(stored);
+        }
+        data
+    }
+
+    /// Write B-tree page `page_id` with the given node header fields and
+    /// entries.
+    fn write_node(file: &mut DatabaseFile, page_id: PageId, header: NodeHeader, data: &[u8]) {
+        let mut page = file.buffer_pool().lease_page_zeroed().expect("lease page");
+        let page_type = match header.node_type {
+            NodeType::Internal => PageType::BTreeInternal,
+            NodeType::Leaf => PageType::BTreeLeaf,
+        };
+        let page_header = PageHeader {
+            page_type,
+            flags: 0,
+            checksum: 0,
+        };
+        page.write_bytes(0, &page_header.to_bytes());
+        header.write_to_page(&mut page);
+        page.write_bytes(NODE_DATA_OFFSET, data);
+        file.write_page(page_id, &page).expect("write page");
+    }
+
+    const fn node_header(node_type: NodeType, key_count: u16, next_leaf: PageId) -> NodeHeader {
+        NodeHeader {
+            node_type,
+            key_count,
+            parent_page: 0,
+            prev_leaf: 0,
+            next_leaf,
+        }
+    }
+
+    /// Write a file in format version 1, holding:
+    /// - Entities 1 to 4 in a primary index of two leaves under one internal
+    ///   node: 2 deleted, 3 with a value in overflow pages
+    /// - In its WAL, a checkpointed transaction writing entity 7, a committed
+    ///   one writing 5 and deleting 1, and an uncommitted one writing 6
+    fn write_legacy_file(path: &Path) {
+        let mut file = DatabaseFile::create(path, BufferPool::new(100)).expect("create file");
+        file.init_wal(DEFAULT_WAL_CAPACITY).expect("init WAL");
+
+        let alice = record(1, 2000, text("alice"));
+        let mut bob = record(2, 2000, text("bob"));
+        bob.deleted_txn = 3;
+        bob.deleted_hlc = hlc(3000);
+        let carol = record(3, 2000, TripleValue::Bytes(vec![7; 3000]));
+        let dave = record(4, 2000, text("dave"));
+        let carol_overflow =
+            write_overflow(&mut file, &encode_record(&carol)).expect("write overflow");
+
+        let root = file.allocate_pages(3).expect("allocate pages");
+        let (left, right) = (root + 1, root + 2);
+        let mut root_data = left.to_le_bytes().to_vec();
+        root_data.extend_from_slice(&legacy_key(&carol));
+        root_data.extend_from_slice(&right.to_le_bytes());
+        write_node(
+            &mut file,
+            root,
+            node_header(NodeType::Internal, 1, 0),
+            &root_data,
+        );
+        let left_data = leaf_data(&[(&alice, encode_record(&alice)), (&bob, encode_record(&bob))]);
+        write_node(
+            &mut file,
+            left,
+            node_header(NodeType::Leaf, 2, right),
+            &left_data,
+        );
+        let right_data = leaf_data(&[
+            (&carol, carol_overflow.to_bytes().to_vec()),
+            (&dave, encode_record(&dave)),
+        ]);
+        write_node(
+            &mut file,
+            right,
+            node_header(NodeType::Leaf, 2, 0),
+            &right_data,
+        );
+
+        let insert = |index, created_hlc, value| {
+            LogRecordPayload::Insert(encode_record(&record(index, created_hlc, value)))
+        };
+        let (checkpoint_lsn, head, last_lsn) = {
+            let mut wal = file.wal().expect("WAL");
+            wal.append(9, hlc(4000), LogRecordPayload::Begin)
+                .expect("append");
+            wal.append(9, hlc(4000), insert(7, 4000, text("grace")))
+                .expect("append");
+            let checkpoint_lsn = wal
+                .append(9, hlc(4000), LogRecordPayload::Commit)
+                .expect("append");
+            wal.append(10, hlc(5000), LogRecordPayload::Begin)
+                .expect("append");
+            wal.append(10, hlc(5000), insert(5, 5000, text("eve")))
+                .expect("append");
+            let delete =
+                LogRecordPayload::delete(alice.entity_id, alice.attribute_id, 0, hlc(5000));
+            wal.append(10, hlc(5000), delete).expect("append");
+            wal.append(10, hlc(5000), LogRecordPayload::Commit)
+                .expect("append");
+            wal.append(11, hlc(6000), LogRecordPayload::Begin)
+                .expect("append");
+            wal.append(11, hlc(6000), insert(6, 6000, text("frank")))
+                .expect("append");
+            (checkpoint_lsn, wal.head(), wal.last_lsn())
+        };
+        file.update_wal_head(head, last_lsn);
+
+        let superblock = file.superblock_mut();
+        superblock.format_version = OLDEST_FORMAT_VERSION;
+        superblock.primary_index_root = root;
+        superblock.last_checkpoint_lsn = checkpoint_lsn;
+        superblock.last_checkpoint_hlc = hlc(4000);
+        file.write_superblock().expect("write superblock");
+        file.sync().expect("sync");
+    }
+
+    fn get(db: &Database, index: u8) -> Option<TripleRecord> {
+        let snapshot = db.begin_readonly().expect("snapshot");
+        snapshot
+            .get(&EntityId([index; 16]), &AttributeId([0xAA; 16]))
+            .expect("get")
+    }
+
+    #[test]
+    fn test_open_upgrades_version_1() {
+        let dir = tempdir().expect("create temp dir");
+        let path = dir.path().join("test.db");
+        let upgrade_path = dir.path().join("test.db.upgrade");
+        write_legacy_file(&path);
+        // Left behind by an upgrade that failed
+        std::fs::write(&upgrade_path, b"partial").expect("write partial upgrade");
+
+        let (db, _) = Database::open(&path, BufferPool::new(100)).expect("open");
+
+        assert!(!upgrade_path.exists());
+        assert!(get(&db, 1).is_none());
+        assert!(get(&db, 2).is_none());
+        let carol = get(&db, 3).expect("large value");
+        assert_eq!(carol.value, TripleValue::Bytes(vec![7; 3000]));
+        assert_eq!(carol.created_hlc, hlc(2000));
+        let dave = get(&db, 4).expect("value");
+        assert_eq!(dave.expires_at_ms, None);
+        assert_eq!(get(&db, 5).map(|eve| eve.value), Some(text("eve")));
+        assert!(get(&db, 6).is_none());
+        assert!(get(&db, 7).is_none());
+        drop(db);
+
+        let file = DatabaseFile::open(&path, BufferPool::new(100)).expect("open file");
+        assert_eq!(file.superblock().format_version, FORMAT_VERSION);
+        drop(file);
+        let (db, _) = Database::open(&path, BufferPool::new(100)).expect("reopen");
+        assert_eq!(get(&db, 5).map(|eve| eve.value), Some(text("eve")));
+    }
+
+    #[test]
+    fn test_readonly_open_rejects_older_version() {
+        let dir = tempdir().expect("create temp dir");
+        let path = dir.path().join("test.db");
+        write_legacy_file(&path);
+
+        let result = Database::open_readonly(&path, BufferPool::new(100));
+
+        assert!(matches!(
+            result,
+            Err(crate::storage::DatabaseError::File(
+                FileError::NeedsUpgrade(OLDEST_FORMAT_VERSION)
+            ))
+        ));
+    }
+
+    #[test]
+    fn test_decode_record_with_truncated_value_is_an_error() {
+        let bytes = encode_record(&record(1, 2000, text("alice")));
+
+        assert!(decode_record(&bytes).is_ok());
+        assert!(matches!(
+            decode_record(&bytes[..64]),
+            Err(TripleError::InvalidRecord)
+        ));
+        assert!(decode_record(&bytes[..40]).is_err());
+    }
+}
@@ -15,14 +15,15 @@
 //! | 13-20    | lsn (8 bytes) - Log Sequence Number              |
 //! | 21-36    | hlc_timestamp (16 bytes)                         |
 //! | 37-N     | payload (variable, depends on type)              |
-//! | N-N+3    | CRC32 checksum (4 bytes)                         |
+//! | N-N+3    | checksum (4 bytes, file's `ChecksumAlgorithm`)   |
 //! +----------+--------------------------------------------------+
 //! ```
 //!
 //! # Circular Buffer
 //!
 //! The log is a circular buffer with:
-//! - Fixed capacity (configured at database creation, default 64MB)
+//! - Fixed capacity (configured at database creation, default 64MB; grown
+//!   or shrunk only between checkpoints, see `DatabaseFile::grow_wal`)
 //! - Head pointer (next write position)
 //! - Tail pointer (oldest record still needed)
 //! - Records wrap around when reaching the end
@@ -30,10 +31,11 @@
 // record_length fits in u32, capacity checks use u64
 #![allow(clippy::cast_possible_truncation)]
 
+use std::collections::HashSet;
 use std::io::{Read, Seek, SeekFrom, Write};
 
+use crate::storage::checksum::ChecksumAlgorithm;
 use crate::storage::file::FileError;
-use crate::storage::page::PAGE_SIZE;
 use crate::types::HlcTimestamp;
 use crate::types::{AttributeId, EntityId, TripleError, TripleRecord, TxnId};
 
@@ -47,7 +49,7 @@ pub const MIN_WAL_CAPACITY: u64 = 1024 * 1024;
 /// `record_length` (4) + `record_type` (1) + `txn_id` (8) + lsn (8) + hlc (16) = 37 bytes
 const RECORD_HEADER_SIZE: usize = 37;
 
-/// CRC32 checksum size at end of record.
+/// Checksum size at end of record.
 const CHECKSUM_SIZE: usize = 4;
 
 /// Log Sequence Number - monotonically increasing identifier for log records.
@@ -105,6 +107,10 @@ pub enum LogRecordPayload {
         entity_id: EntityId,
         /// Attribute ID of deleted triple.
         attribute_id: AttributeId,
+        /// Value hash of deleted triple: 0 for a cardinality-one attribute.
+        value_hash: u64,
+        /// HLC of the delete, stored on the tombstone for conflict resolution.
+        hlc: HlcTimestamp,
     },
     /// Commit transaction - no additional data.
     Commit,
@@ -145,10 +151,17 @@ impl LogRecordPayload {
 
     /// Create a Delete payload.
     #[must_use]
-    pub const fn delete(entity_id: EntityId, attribute_id: AttributeId) -> Self {
+    pub const fn delete(
+        entity_id: EntityId,
+        attribute_id: AttributeId,
+        value_hash: u64,
+        hlc: HlcTimestamp,
+    ) -> Self {
         Self::Delete {
             entity_id,
             attribute_id,
+            value_hash,
+            hlc,
         }
     }
 
@@ -168,7 +181,8 @@ impl LogRecordPayload {
         match self {
             Self::Begin | Self::Commit => 0,
             Self::Insert(bytes) | Self::Update(bytes) => bytes.len(),
-            Self::Delete { .. } => 32, // entity_id (16) + attribute_id (16)
+            // entity_id (16) + attribute_id (16) + value_hash (8) + hlc (16)
+            Self::Delete { .. } => 56,
             Self::Checkpoint { .. } => 16, // min_active_txn (8) + active_txn_count (8)
         }
     }
@@ -186,10 +200,14 @@ impl LogRecordPayload {
             Self::Delete {
                 entity_id,
                 attribute_id,
+                value_hash,
+                hlc,
             } => {
-                let mut bytes = Vec::with_capacity(32);
+                let mut bytes = Vec::with_capacity(56);
                 bytes.extend_from_slice(&entity_id.0);
                 bytes.extend_from_slice(&attribute_id.0);
+                bytes.extend_from_slice(&value_hash.to_le_bytes());
+                bytes.extend_from_slice(&hlc.to_bytes());
                 bytes
             }
             Self::Checkpoint {
@@ -217,11 +235,22 @@ impl LogRecordPayload {
                 }
                 let mut entity_bytes = [0u8; 16];
                 let mut attribute_bytes = [0u8; 16];
+                let mut hash_bytes = [0u8; 8];
+                let mut hlc_bytes = [0u8; HlcTimestamp::SIZE];
                 entity_bytes.copy_from_slice(&bytes[0..16]);
                 attribute_bytes.copy_from_slice(&bytes[16..32]);
+                // Format version 1 files wrote neither; they are only read to
+                // upgrade them, which keys every triple at 0 and takes the
+                // delete's HLC from its record header
+                if bytes.len() >= 56 {
+                    hash_bytes.copy_from_slice(&bytes[32..40]);
+                    hlc_bytes.copy_from_slice(&bytes[40..56]);
+                }
                 Ok(Self::Delete {
                     entity_id: EntityId(entity_bytes),
                     attribute_id: AttributeId(attribute_bytes),
+                    value_hash: u64::from_le_bytes(hash_bytes),
+                    hlc: HlcTimestamp::from_bytes(&hlc_bytes),
                 })
             }
             LogRecordType::Checkpoint => {
@@ -287,12 +316,24 @@ impl LogRecord {
     /// Calculate the total serialized size of this record.
     #[must_use]
     pub fn serialized_size(&self) -> usize {
-        RECORD_HEADER_SIZE + self.payload.serialized_size() + CHECKSUM_SIZE
+        Self::size_for_payload(self.payload.serialized_size())
     }
 
-    /// Serialize this record to bytes.
+    /// The serialized size of a record whose payload is `payload_size` bytes.
+    #[must_use]
+    pub const fn size_for_payload(payload_size: usize) -> usize {
+        RECORD_HEADER_SIZE + payload_size + CHECKSUM_SIZE
+    }
+
+    /// Serialize this record to bytes with a CRC32 checksum.
     #[must_use]
     pub fn to_bytes(&self) -> Vec<u8> {
+        self.to_bytes_with_checksum(ChecksumAlgorithm::Crc32)
+    }
+
+    /// Serialize this record to bytes, checksummed with `algorithm`.
+    #[must_use]
+    pub fn to_bytes_with_checksum(&self, algorithm: ChecksumAlgorithm) -> Vec<u8> {
         let payload_bytes = self.payload.to_bytes();
         let total_len = RECORD_HEADER_SIZE + payload_bytes.len() + CHECKSUM_SIZE;
 
@@ -316,20 +357,33 @@ impl LogRecord {
         // Payload (variable)
         bytes.extend_from_slice(&payload_bytes);
 
-        // CRC32 checksum (4 bytes) - computed over everything before it
-        let checksum = crc32fast::hash(&bytes);
+        // Checksum (4 bytes) - computed over everything before it
+        let checksum = algorithm.checksum(&bytes);
         bytes.extend_from_slice(&checksum.to_le_bytes());
 
         bytes
     }
 
-    /// Deserialize a record from bytes.
+    /// Deserialize a record from bytes, verifying a CRC32 checksum.
     ///
     /// Returns the record and the number of bytes consumed.
     ///
     /// # Panics
     /// Panics if the input buffer is empty (indicates programming error).
     pub fn from_bytes(bytes: &[u8]) -> Result<(Self, usize), WalError> {
+        Self::from_bytes_with_checksum(bytes, ChecksumAlgorithm::Crc32)
+    }
+
+    /// Deserialize a record from bytes, verifying the checksum with `algorithm`.
+    ///
+    /// Returns the record and the number of bytes consumed.
+    ///
+    /// # Panics
+    /// Panics if the input buffer is empty (indicates programming error).
+    pub fn from_bytes_with_checksum(
+        bytes: &[u8],
+        algorithm: ChecksumAlgorithm,
+    ) -> Result<(Self, usize), WalError> {
         // Pre-condition: input buffer must not be empty
         assert!(
             !bytes.is_empty(),
@@ -354,7 +408,7 @@ impl LogRecord {
             bytes[record_len - 2],
             bytes[record_len - 1],
         ]);
-        let computed_checksum = crc32fast::hash(&bytes[..record_len - 4]);
+        let computed_checksum = algorithm.checksum(&bytes[..record_len - 4]);
         if stored_checksum != computed_checksum {
             return Err(WalError::ChecksumMismatch {
                 expected: stored_checksum,
@@ -416,6 +470,11 @@ pub struct Wal<'a, F: Read + Write + Seek> {
     next_lsn: Lsn,
     /// Whether the buffer has wrapped around.
     wrapped: bool,
+    /// Algorithm used to checksum records.
+    checksum_algorithm: ChecksumAlgorithm,
+    /// Position just past the last checkpoint record (relative to `region_start`).
+    /// When set, appends never overwrite records between it and the head.
+    checkpoint_offset: Option<u64>,
 }
 
 impl<'a, F: Read + Write + Seek> Wal<'a, F> {
@@ -463,9 +522,35 @@ impl<'a, F: Read + Write + Seek> Wal<'a, F> {
             tail,
             next_lsn,
             wrapped: head < tail,
+            checksum_algorithm: ChecksumAlgorithm::Crc32,
+            checkpoint_offset: None,
         }
     }
 
+    /// Use `algorithm` to checksum appended records and verify read records.
+    ///
+    /// Defaults to `ChecksumAlgorithm::Crc32`. Must match the algorithm the
+    /// existing records in the region were written with.
+    #[must_use]
+    pub const fn with_checksum_algorithm(mut self, algorithm: ChecksumAlgorithm) -> Self {
+        self.checksum_algorithm = algorithm;
+        self
+    }
+
+    /// Protect records written after the checkpoint ending at `checkpoint_offset`.
+    ///
+    /// With an offset set, `append` returns `WouldOverwriteUncheckpointed`
+    /// instead of reusing space still needed for recovery. `None` disables the
+    /// check.
+    ///
+    /// # Pre-conditions
+    /// - `checkpoint_offset` is at most the capacity
+    #[must_use]
+    pub const fn with_checkpoint_offset(mut self, checkpoint_offset: Option<u64>) -> Self {
+        self.checkpoint_offset = checkpoint_offset;
+        self
+    }
+
     /// Get the current head position (relative to `region_start`).
     #[must_use]
     pub const fn head(&self) -> u64 {
@@ -532,7 +617,7 @@ impl<'a, F: Read + Write + Seek> Wal<'a, F> {
     ) -> Result<Lsn, WalError> {
         let lsn = self.next_lsn;
         let record = LogRecord::new(txn_id, lsn, hlc, payload);
-        let bytes = record.to_bytes();
+        let bytes = record.to_bytes_with_checksum(self.checksum_algorithm);
         let record_len = bytes.len() as u64;
 
         // Invariant: record length must be reasonable (at least header + checksum)
@@ -552,7 +637,10 @@ impl<'a, F: Read + Write + Seek> Wal<'a, F> {
 
         // Check if we need to wrap or advance tail
         let space_to_end = self.capacity - self.head;
-        if record_len > space_to_end {
+        let wraps = record_len > space_to_end;
+        self.check_uncheckpointed_overwrite(record_len, wraps)?;
+
+        if wraps {
             // Not enough contiguous space at end, wrap to beginning
             // First, advance tail past any records we're overwriting
             self.advance_tail_to(record_len)?;
@@ -597,6 +685,44 @@ impl<'a, F: Read + Write + Seek> Wal<'a, F> {
         Ok(lsn)
     }
 
+    /// Ensure writing `record_len` bytes at the head leaves every record written
+    /// since the last checkpoint intact.
+    ///
+    /// The un-checkpointed records are those from `checkpoint_offset` up to the
+    /// head, wrapping around the end of the region when the head is behind the
+    /// offset. The head may never land exactly on the offset again, since that
+    /// position means "nothing un-checkpointed".
+    const fn check_uncheckpointed_overwrite(
+        &self,
+        record_len: u64,
+        wraps: bool,
+    ) -> Result<(), WalError> {
+        let Some(checkpoint_offset) = self.checkpoint_offset else {
+            return Ok(());
+        };
+        if checkpoint_offset == self.head {
+            // Nothing written since the checkpoint
+            return Ok(());
+        }
+
+        let fits = if checkpoint_offset > self.head {
+            // Un-checkpointed records run from the offset to the end, then 0..head
+            !wraps && self.head + record_len < checkpoint_offset
+        } else if wraps {
+            // Un-checkpointed records are offset..head; the write restarts at 0
+            record_len < checkpoint_offset
+        } else {
+            // Writing up to the end of the region moves the head back to 0
+            (self.head + record_len) % self.capacity != checkpoint_offset
+        };
+
+        if fits {
+            Ok(())
+        } else {
+            Err(WalError::WouldOverwriteUncheckpointed)
+        }
+    }
+
     /// Advance the tail pointer to free up space.
     fn advance_tail_to(&mut self, needed: u64) -> Result<(), WalError> {
         let mut freed = 0u64;
@@ -677,7 +803,8 @@ impl<'a, F: Read + Write + Seek> Wal<'a, F> {
             .read_exact(&mut record_buf)
             .map_err(WalError::Io)?;
 
-        let (record, consumed) = LogRecord::from_bytes(&record_buf)?;
+        let (record, consumed) =
+            LogRecord::from_bytes_with_checksum(&record_buf, self.checksum_algorithm)?;
 
         // Calculate next offset (with wrap-around)
         let next_offset = offset + consumed as u64;
@@ -692,39 +819,11 @@ impl<'a, F: Read + Write + Seek> Wal<'a, F> {
 
     /// Read all log records from tail to head.
     ///
-    /// This collects all records into a vector. For streaming access,
-    /// use `read_at` with manual offset tracking.
+    /// This collects all records into a vector. To read a large log in
+    /// bounded batches, use `scan_all` with `read_batch`.
     pub fn read_all(&mut self) -> Result<Vec<LogRecord>, WalError> {
-        if self.is_empty() {
-            return Ok(Vec::new());
-        }
-
-        let mut records = Vec::new();
-        let mut offset = self.tail;
-
-        loop {
-            let (record, next_offset) = self.read_at(offset)?;
-            records.push(record);
-
-            // Check if we've reached the head
-            if next_offset == self.head {
-                break;
-            }
-            if self.wrapped && offset >= self.head && next_offset <= self.head {
-                break;
-            }
-
-            offset = next_offset;
-
-            // Safety limit to prevent infinite loops
-            if records.len()
-                > (self.capacity / (RECORD_HEADER_SIZE + CHECKSUM_SIZE) as u64) as usize
-            {
-                break;
-            }
-        }
-
-        Ok(records)
+        let mut scan = self.scan_all();
+        self.read_batch(&mut scan, usize::MAX)
     }
 
     /// Find the offset of a record with the given LSN.
@@ -804,35 +903,196 @@ impl<'a, F: Read + Write + Seek> Wal<'a, F> {
         Ok(records)
     }
 
+    /// Read all records from `start_offset` up to the head.
+    ///
+    /// Unlike `read_from_lsn`, this never scans the log from the tail, so
+    /// bytes before `start_offset` are never interpreted. A record that did
+    /// not fit before the end of the region was written at offset 0 instead;
+    /// when the bytes at an offset past the head are not the record with the
+    /// expected LSN, reading continues from offset 0.
+    ///
+    /// To read a large log in bounded batches, use `scan_from_offset` with
+    /// `read_batch`.
+    ///
+    /// # Pre-conditions
+    /// - `start_offset` is a record boundary or the head.
+    /// - `first_lsn` is the LSN of the first record at or after `start_offset`.
+    ///
+    /// # Post-conditions
+    /// - Returns an empty vector if `start_offset` equals the head.
+    /// - Returned records have consecutive LSNs starting at `first_lsn`.
+    pub fn read_from_offset(
+        &mut self,
+        start_offset: u64,
+        first_lsn: Lsn,
+    ) -> Result<Vec<LogRecord>, WalError> {
+        let mut scan = self.scan_from_offset(start_offset, first_lsn);
+        self.read_batch(&mut scan, usize::MAX)
+    }
+
+    /// Start a scan of all records from tail to head, as read by `read_all`.
+    #[must_use]
+    pub const fn scan_all(&self) -> WalScan {
+        WalScan {
+            offset: self.tail,
+            expected_lsn: None,
+            records_read: 0,
+            done: self.is_empty(),
+        }
+    }
+
+    /// Start a scan of the records from `start_offset` up to the head, as
+    /// read by `read_from_offset`.
+    ///
+    /// # Pre-conditions
+    /// - `start_offset` is a record boundary or the head.
+    /// - `first_lsn` is the LSN of the first record at or after `start_offset`.
+    #[must_use]
+    pub const fn scan_from_offset(&self, start_offset: u64, first_lsn: Lsn) -> WalScan {
+        WalScan {
+            offset: start_offset,
+            expected_lsn: Some(first_lsn),
+            records_read: 0,
+            done: self.is_empty() || start_offset == self.head,
+        }
+    }
+
+    /// Read the next records of `scan`, at most `max_records` of them.
+    ///
+    /// The scan holds no borrow of the WAL, so the file can be modified
+    /// between batches, e.g. to apply the records read so far. Each batch
+    /// must be read from a `Wal` with the same region, head and tail as the
+    /// one that started the scan.
+    ///
+    /// # Post-conditions
+    /// - Returns an empty vector once the scan has reached the head.
+    /// - The batches of a scan, concatenated, are the records `read_all` or
+    ///   `read_from_offset` would return.
+    pub fn read_batch(
+        &mut self,
+        scan: &mut WalScan,
+        max_records: usize,
+    ) -> Result<Vec<LogRecord>, WalError> {
+        let mut records = Vec::new();
+        #[allow(clippy::cast_possible_truncation)] // Bounded by the WAL capacity
+        let max_total = (self.capacity / (RECORD_HEADER_SIZE + CHECKSUM_SIZE) as u64) as usize;
+
+        while !scan.done && records.len() < max_records {
+            let offset = scan.offset;
+            let (record, next_offset) = match (scan.expected_lsn, self.read_at(offset)) {
+                (None, read) => read?,
+                (Some(expected_lsn), Ok((record, next_offset))) if record.lsn == expected_lsn => {
+                    (record, next_offset)
+                }
+                // Stale bytes left at the end of the region when the writer
+                // wrapped early
+                (
+                    Some(_),
+                    Ok(_)
+                    | Err(
+                        WalError::CorruptRecord
+                        | WalError::ChecksumMismatch { .. }
+                        | WalError::InvalidRecordType(_),
+                    ),
+                ) if offset > self.head => {
+                    scan.offset = 0;
+                    continue;
+                }
+                (Some(_), Err(WalError::Io(ref io)))
+                    if offset > self.head && io.kind() == std::io::ErrorKind::UnexpectedEof =>
+                {
+                    scan.offset = 0;
+                    continue;
+                }
+                (Some(_), Ok(_)) => return Err(WalError::CorruptRecord),
+                (Some(_), Err(e)) => return Err(e),
+            };
+            records.push(record);
+            scan.records_read += 1;
+            if let Some(expected_lsn) = &mut scan.expected_lsn {
+                *expected_lsn += 1;
+            }
+
+            // Check if we've reached the head
+            let wrapped_past_head = scan.expected_lsn.is_none()
+                && self.wrapped
+                && offset >= self.head
+                && next_offset <= self.head;
+            // Safety limit to prevent infinite loops
+            scan.done =
+                next_offset == self.head || wrapped_past_head || scan.records_read > max_total;
+            scan.offset = next_offset;
+        }
+
+        Ok(records)
+    }
+
     /// Read all change records (INSERT, UPDATE, DELETE) since a given HLC timestamp.
     ///
     /// Returns records where HLC >= the given timestamp.
     pub fn changes_since(&mut self, target_hlc: HlcTimestamp) -> Result<Vec<LogRecord>, WalError> {
+        Ok(self.scan_changes(target_hlc, None)?.0)
+    }
+
+    /// Read the committed change records (INSERT, UPDATE, DELETE) whose HLC
+    /// lies between `from` and `to`, both inclusive.
+    ///
+    /// HLCs are compared by physical time, then logical counter. A change
+    /// belongs to a committed transaction if the transaction's COMMIT record
+    /// is still in the log, whatever its HLC.
+    ///
+    /// # Post-conditions
+    /// - Records are in log order
+    /// - Empty if `from` is after `to`
+    pub fn changes_between(
+        &mut self,
+        from: HlcTimestamp,
+        to: HlcTimestamp,
+    ) -> Result<Vec<LogRecord>, WalError> {
+        let (mut changes, committed) = self.scan_changes(from, Some(to))?;
+        changes.retain(|record| committed.contains(&record.txn_id));
+        Ok(changes)
+    }
+
+    /// Scan from tail to head for the change records with an HLC of at least
+    /// `from` and, if given, at most `to`.
+    ///
+    /// Also returns the IDs of the transactions whose COMMIT record was seen.
+    fn scan_changes(
+        &mut self,
+        from: HlcTimestamp,
+        to: Option<HlcTimestamp>,
+    ) -> Result<(Vec<LogRecord>, HashSet<TxnId>), WalError> {
+        let mut changes = Vec::new();
+        let mut committed = HashSet::new();
         if self.is_empty() {
-            return Ok(Vec::new());
+            return Ok((changes, committed));
         }
 
-        let mut changes = Vec::new();
         let mut offset = self.tail;
+        let mut next_lsn = None;
         let max_iterations = self.capacity / (RECORD_HEADER_SIZE + CHECKSUM_SIZE) as u64;
 
         for _ in 0..max_iterations {
-            let (record, next_offset) = self.read_at(offset)?;
-
-            // Check HLC
-            let hlc_matches = record.hlc.physical_time > target_hlc.physical_time
-                || (record.hlc.physical_time == target_hlc.physical_time
-                    && record.hlc.logical_counter >= target_hlc.logical_counter);
-
-            if hlc_matches {
-                match &record.payload {
-                    LogRecordPayload::Insert(_)
-                    | LogRecordPayload::Update(_)
-                    | LogRecordPayload::Delete { .. } => {
-                        changes.push(record);
-                    }
-                    _ => {} // Skip BEGIN, COMMIT, CHECKPOINT
+            let (record, next_offset) = self.read_in_sequence(&mut offset, next_lsn)?;
+            next_lsn = Some(record.lsn + 1);
+
+            let hlc = (record.hlc.physical_time, record.hlc.logical_counter);
+            let hlc_matches = hlc >= (from.physical_time, from.logical_counter)
+                && to.is_none_or(|to| hlc <= (to.physical_time, to.logical_counter));
+
+            match &record.payload {
+                LogRecordPayload::Insert(_)
+                | LogRecordPayload::Update(_)
+                | LogRecordPayload::Delete { .. }
+                    if hlc_matches =>
+                {
+                    changes.push(record);
                 }
+                LogRecordPayload::Commit => {
+                    committed.insert(record.txn_id);
+                }
+                _ => {} // Skip BEGIN, CHECKPOINT and changes outside the range
             }
 
             // Check if we've reached the head
@@ -846,7 +1106,177 @@ impl<'a, F: Read + Write + Seek> Wal<'a, F> {
             offset = next_offset;
         }
 
-        Ok(changes)
+        Ok((changes, committed))
+    }
+
+    /// Read the record at `offset`, which should carry `expected_lsn`.
+    ///
+    /// A record that did not fit before the end of the region was written at
+    /// the start instead, leaving stale bytes behind the last record of the
+    /// previous pass. If `offset` is past the head and does not hold the
+    /// expected record, the record is read from the start of the region and
+    /// `offset` is moved there.
+    fn read_in_sequence(
+        &mut self,
+        offset: &mut u64,
+        expected_lsn: Option<Lsn>,
+    ) -> Result<(LogRecord, u64), WalError> {
+        let read = self.read_at(*offset);
+        let in_sequence = read
+            .as_ref()
+            .is_ok_and(|(record, _)| expected_lsn.is_none_or(|lsn| record.lsn == lsn));
+        if in_sequence || *offset <= self.head {
+            return read;
+        }
+        *offset = 0;
+        self.read_at(0)
+    }
+}
+
+/// Position of a resumable scan over a WAL's records.
+///
+/// Created by `Wal::scan_all` or `Wal::scan_from_offset` and advanced by
+/// `Wal::read_batch`, so a large log can be read a bounded number of records
+/// at a time.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WalScan {
+    /// Offset of the next record to read (relative to `region_start`).
+    offset: u64,
+    /// LSN the next record must have, or `None` to accept any LSN.
+    expected_lsn: Option<Lsn>,
+    /// Records read so far, checked against the safety limit.
+    records_read: usize,
+    /// Set once the scan has reached the head.
+    done: bool,
+}
+
+impl WalScan {
+    /// Whether the scan has reached the head.
+    #[must_use]
+    pub const fn is_done(&self) -> bool {
+        self.done
+    }
+}
+
+/// Read-only iterator over the records of a WAL region, from tail to head.
+///
+/// Unlike `Wal`, it needs only `Read + Seek`, so it can inspect a database
+/// file opened read-only, e.g. for a `wal-dump` diagnostic. Create one over a
+/// database file with `DatabaseFile::wal_records`.
+///
+/// A record that is truncated or fails its checksum ends the scan instead of
+/// failing it: a crash can tear the last record written, and every record
+/// before it is still valid. `stopped_by` reports what ended the scan early.
+pub struct WalRecords<R: Read + Seek> {
+    /// The underlying reader.
+    reader: R,
+    /// Start offset of the WAL region in the file.
+    region_start: u64,
+    /// Capacity of the WAL region in bytes.
+    capacity: u64,
+    /// Write position to stop at (relative to `region_start`).
+    head: u64,
+    /// Offset of the next record to read (relative to `region_start`).
+    offset: u64,
+    /// Algorithm the records were checksummed with.
+    checksum_algorithm: ChecksumAlgorithm,
+    /// Set once the scan has ended.
+    done: bool,
+    /// The error that ended the scan before reaching the head, if any.
+    stopped_by: Option<WalError>,
+}
+
+impl<R: Read + Seek> WalRecords<R> {
+    /// Create an iterator over the records from `tail` up to `head`.
+    ///
+    /// # Pre-conditions
+    /// - `tail` and `head` are at most `capacity`, relative to `region_start`
+    #[must_use]
+    pub const fn new(
+        reader: R,
+        region_start: u64,
+        capacity: u64,
+        tail: u64,
+        head: u64,
+        checksum_algorithm: ChecksumAlgorithm,
+    ) -> Self {
+        Self {
+            reader,
+            region_start,
+            capacity,
+            head,
+            offset: tail,
+            checksum_algorithm,
+            done: tail == head,
+            stopped_by: None,
+        }
+    }
+
+    /// The torn or corrupt record that ended the scan before the head.
+    ///
+    /// `None` while the scan is running and after it reached the head.
+    #[must_use]
+    pub const fn stopped_by(&self) -> Option<&WalError> {
+        self.stopped_by.as_ref()
+    }
+
+    /// Read the record at the current offset.
+    fn read_next(&mut self) -> Result<LogRecord, WalError> {
+        let start = self.region_start + self.offset;
+        let mut len_bytes = [0u8; 4];
+        self.reader.seek(SeekFrom::Start(start))?;
+        self.reader.read_exact(&mut len_bytes)?;
+
+        let record_len = u64::from(u32::from_le_bytes(len_bytes));
+        if record_len < (RECORD_HEADER_SIZE + CHECKSUM_SIZE) as u64
+            || self.offset + record_len > self.capacity
+        {
+            return Err(WalError::CorruptRecord);
+        }
+
+        let mut record_buf = vec![0u8; record_len as usize];
+        self.reader.seek(SeekFrom::Start(start))?;
+        self.reader.read_exact(&mut record_buf)?;
+
+        let (record, consumed) =
+            LogRecord::from_bytes_with_checksum(&record_buf, self.checksum_algorithm)?;
+        self.offset = (self.offset + consumed as u64) % self.capacity;
+        Ok(record)
+    }
+}
+
+impl<R: Read + Seek> Iterator for WalRecords<R> {
+    type Item = Result<LogRecord, WalError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+
+        match self.read_next() {
+            Ok(record) => {
+                self.done = self.offset == self.head;
+                Some(Ok(record))
+            }
+            Err(e) => {
+                self.done = true;
+                match e {
+                    // A torn write: the file ends mid-record, or the record
+                    // was only partly overwritten
+                    WalError::Io(ref io) if io.kind() == std::io::ErrorKind::UnexpectedEof => {
+                        self.stopped_by = Some(e);
+                        None
+                    }
+                    WalError::CorruptRecord
+                    | WalError::ChecksumMismatch { .. }
+                    | WalError::InvalidRecordType(_) => {
+                        self.stopped_by = Some(e);
+                        None
+                    }
+                    e => Some(Err(e)),
+                }
+            }
+        }
     }
 }
 
@@ -863,6 +1293,8 @@ pub enum WalError {
     ChecksumMismatch { expected: u32, actual: u32 },
     /// Record too large for WAL capacity.
     RecordTooLarge { size: u64, capacity: u64 },
+    /// Appending would overwrite records not yet covered by a checkpoint.
+    WouldOverwriteUncheckpointed,
     /// Invalid offset in WAL.
     InvalidOffset(u64),
     /// LSN not found (may have been overwritten).
@@ -893,6 +1325,12 @@ impl std::fmt::Display for WalError {
                     "WAL record too large: {size} bytes exceeds capacity of {capacity} bytes"
                 )
             }
+            Self::WouldOverwriteUncheckpointed => {
+                write!(
+                    f,
+                    "WAL full: append would overwrite records not yet checkpointed"
+                )
+            }
             Self::InvalidOffset(o) => write!(f, "invalid WAL offset: {o}"),
             Self::LsnNotFound(lsn) => write!(f, "LSN {lsn} not found in WAL (may be overwritten)"),
             Self::NotInitialized => write!(f, "WAL region not initialized"),
@@ -931,10 +1369,10 @@ impl From<TripleError> for WalError {
     }
 }
 
-/// Calculate the number of pages needed for a given WAL capacity.
+/// Calculate the number of pages of `page_size` bytes needed for a given WAL capacity.
 #[must_use]
-pub const fn pages_for_capacity(capacity: u64) -> u64 {
-    capacity.div_ceil(PAGE_SIZE as u64)
+pub const fn pages_for_capacity(capacity: u64, page_size: u64) -> u64 {
+    capacity.div_ceil(page_size)
 }
 
 #[cfg(test)]
@@ -1013,7 +1451,12 @@ mod tests {
             5,
             400,
             HlcTimestamp::new(4000, 2),
-            LogRecordPayload::delete(EntityId([3u8; 16]), AttributeId([4u8; 16])),
+            LogRecordPayload::delete(
+                EntityId([3u8; 16]),
+                AttributeId([4u8; 16]),
+                42,
+                HlcTimestamp::new(4000, 2),
+            ),
         );
 
         let bytes = record.to_bytes();
@@ -1022,15 +1465,43 @@ mod tests {
         if let LogRecordPayload::Delete {
             entity_id,
             attribute_id,
+            value_hash,
+            hlc,
         } = decoded.payload
         {
             assert_eq!(entity_id, EntityId([3u8; 16]));
             assert_eq!(attribute_id, AttributeId([4u8; 16]));
+            assert_eq!(value_hash, 42);
+            assert_eq!(hlc, HlcTimestamp::new(4000, 2));
         } else {
             panic!("expected Delete payload");
         }
     }
 
+    #[test]
+    fn test_delete_payload_from_format_version_1() {
+        let bytes = [[3u8; 16], [4u8; 16]].concat();
+        let legacy = LogRecordPayload::from_bytes(LogRecordType::Delete, &bytes).unwrap();
+
+        let LogRecordPayload::Delete {
+            entity_id,
+            attribute_id,
+            value_hash,
+            hlc,
+        } = legacy
+        else {
+            panic!("expected Delete payload");
+        };
+        assert_eq!(entity_id, EntityId([3u8; 16]));
+        assert_eq!(attribute_id, AttributeId([4u8; 16]));
+        assert_eq!(value_hash, 0);
+        assert_eq!(hlc, HlcTimestamp::new(0, 0));
+        assert!(matches!(
+            LogRecordPayload::from_bytes(LogRecordType::Delete, &bytes[..31]),
+            Err(WalError::CorruptRecord)
+        ));
+    }
+
     #[test]
     fn test_log_record_roundtrip_checkpoint() {
         let record = LogRecord::new(
@@ -1067,6 +1538,87 @@ mod tests {
         assert!(matches!(result, Err(WalError::ChecksumMismatch { .. })));
     }
 
+    #[test]
+    fn test_checksum_algorithm_roundtrip() {
+        let record = LogRecord::new(1, 100, HlcTimestamp::new(1000, 0), LogRecordPayload::Begin);
+
+        let bytes = record.to_bytes_with_checksum(ChecksumAlgorithm::Crc32c);
+        let (decoded, _) =
+            LogRecord::from_bytes_with_checksum(&bytes, ChecksumAlgorithm::Crc32c).unwrap();
+        assert_eq!(decoded.lsn, 100);
+
+        let result = LogRecord::from_bytes_with_checksum(&bytes, ChecksumAlgorithm::Crc32);
+        assert!(matches!(result, Err(WalError::ChecksumMismatch { .. })));
+    }
+
+    #[test]
+    fn test_wal_with_checksum_algorithm() {
+        let mut cursor = create_test_cursor(4096);
+        {
+            let mut wal = Wal::new(&mut cursor, 0, 4096, 0, 0, 1)
+                .with_checksum_algorithm(ChecksumAlgorithm::Crc32c);
+            wal.append(1, HlcTimestamp::new(1000, 0), LogRecordPayload::Begin)
+                .unwrap();
+
+            let (record, _) = wal.read_at(0).unwrap();
+            assert_eq!(record.lsn, 1);
+        }
+
+        // Reading with the wrong algorithm fails verification
+        let mut wal = Wal::new(&mut cursor, 0, 4096, 0, 0, 2);
+        let result = wal.read_at(0);
+        assert!(matches!(result, Err(WalError::ChecksumMismatch { .. })));
+    }
+
+    #[test]
+    fn test_wal_refuses_to_overwrite_uncheckpointed() {
+        let mut cursor = create_test_cursor(1024);
+        let mut wal = Wal::new(&mut cursor, 0, 1024, 0, 0, 1).with_checkpoint_offset(Some(0));
+
+        // Each BEGIN record is 41 bytes; 24 of them fill 984 bytes
+        for _ in 0..24 {
+            wal.append(1, HlcTimestamp::new(1000, 0), LogRecordPayload::Begin)
+                .unwrap();
+        }
+        let head = wal.head();
+
+        // Wrapping to the start would overwrite the first un-checkpointed record
+        let result = wal.append(1, HlcTimestamp::new(1000, 0), LogRecordPayload::Begin);
+        assert!(matches!(
+            result,
+            Err(WalError::WouldOverwriteUncheckpointed)
+        ));
+        assert_eq!(wal.head(), head);
+    }
+
+    #[test]
+    fn test_wal_reuses_checkpointed_space() {
+        let mut cursor = create_test_cursor(1024);
+        let mut wal = Wal::new(&mut cursor, 0, 1024, 0, 0, 1);
+        for _ in 0..24 {
+            wal.append(1, HlcTimestamp::new(1000, 0), LogRecordPayload::Begin)
+                .unwrap();
+        }
+        let head = wal.head();
+
+        // Everything before the head is checkpointed, so wrapping is allowed
+        let mut wal = wal.with_checkpoint_offset(Some(head));
+        wal.append(1, HlcTimestamp::new(1000, 0), LogRecordPayload::Begin)
+            .unwrap();
+        assert_eq!(wal.head(), 41);
+
+        // But the new head may not run into the checkpoint position
+        for _ in 0..22 {
+            wal.append(1, HlcTimestamp::new(1000, 0), LogRecordPayload::Begin)
+                .unwrap();
+        }
+        let result = wal.append(1, HlcTimestamp::new(1000, 0), LogRecordPayload::Begin);
+        assert!(matches!(
+            result,
+            Err(WalError::WouldOverwriteUncheckpointed)
+        ));
+    }
+
     #[test]
     fn test_wal_append_and_read() {
         let mut cursor = create_test_cursor(4096);
@@ -1134,10 +1686,11 @@ mod tests {
 
     #[test]
     fn test_pages_for_capacity() {
-        assert_eq!(pages_for_capacity(8192), 1);
-        assert_eq!(pages_for_capacity(8193), 2);
-        assert_eq!(pages_for_capacity(16384), 2);
-        assert_eq!(pages_for_capacity(DEFAULT_WAL_CAPACITY), 8192);
+        assert_eq!(pages_for_capacity(8192, 8192), 1);
+        assert_eq!(pages_for_capacity(8193, 8192), 2);
+        assert_eq!(pages_for_capacity(16384, 8192), 2);
+        assert_eq!(pages_for_capacity(DEFAULT_WAL_CAPACITY, 8192), 8192);
+        assert_eq!(pages_for_capacity(DEFAULT_WAL_CAPACITY, 4096), 16384);
     }
 
     #[test]
@@ -1152,8 +1705,14 @@ mod tests {
         assert_eq!(LogRecordPayload::Begin.serialized_size(), 0);
         assert_eq!(LogRecordPayload::Commit.serialized_size(), 0);
         assert_eq!(
-            LogRecordPayload::delete(EntityId([0u8; 16]), AttributeId([0u8; 16])).serialized_size(),
-            32
+            LogRecordPayload::delete(
+                EntityId([0u8; 16]),
+                AttributeId([0u8; 16]),
+                0,
+                HlcTimestamp::new(0, 0)
+            )
+            .serialized_size(),
+            56
         );
         assert_eq!(LogRecordPayload::checkpoint(0, 0).serialized_size(), 16);
     }
@@ -1178,6 +1737,40 @@ mod tests {
         assert_eq!(records[2].lsn, 3);
     }
 
+    #[test]
+    fn test_wal_read_batch_resumes_where_it_stopped() {
+        let mut cursor = create_test_cursor(8192);
+        let mut wal = Wal::new(&mut cursor, 0, 8192, 0, 0, 1);
+        for txn_id in 1..=5 {
+            wal.append(txn_id, HlcTimestamp::new(1000, 0), LogRecordPayload::Begin)
+                .unwrap();
+            wal.append(txn_id, HlcTimestamp::new(1000, 0), LogRecordPayload::Commit)
+                .unwrap();
+        }
+        let all: Vec<Lsn> = wal.read_all().unwrap().iter().map(|r| r.lsn).collect();
+
+        let mut scan = wal.scan_all();
+        let mut batched = Vec::new();
+        while !scan.is_done() {
+            let batch = wal.read_batch(&mut scan, 3).unwrap();
+            assert!(!batch.is_empty() && batch.len() <= 3);
+            batched.extend(batch.iter().map(|r| r.lsn));
+        }
+        assert_eq!(batched, all);
+        assert!(wal.read_batch(&mut scan, 3).unwrap().is_empty());
+
+        // A scan from an offset starts at the record there
+        let (_, second_offset) = wal.read_at(0).unwrap();
+        let mut scan = wal.scan_from_offset(second_offset, 2);
+        let lsns: Vec<Lsn> = wal
+            .read_batch(&mut scan, 4)
+            .unwrap()
+            .iter()
+            .map(|r| r.lsn)
+            .collect();
+        assert_eq!(lsns, vec![2, 3, 4, 5]);
+    }
+
     #[test]
     fn test_wal_changes_since() {
         let mut cursor = create_test_cursor(8192);
@@ -1210,4 +1803,117 @@ mod tests {
         assert_eq!(changes.len(), 1);
         assert!(matches!(changes[0].payload, LogRecordPayload::Insert(_)));
     }
+
+    /// Append one committed transaction inserting a triple at `physical_time`.
+    fn append_insert_txn(wal: &mut Wal<'_, Cursor<Vec<u8>>>, txn_id: TxnId, physical_time: u64) {
+        let hlc = HlcTimestamp::new(physical_time, 0);
+        let triple = TripleRecord::new(
+            EntityId([1u8; 16]),
+            AttributeId([2u8; 16]),
+            txn_id,
+            hlc,
+            TripleValue::Number(42.0),
+        );
+        wal.append(txn_id, hlc, LogRecordPayload::Begin).unwrap();
+        wal.append(txn_id, hlc, LogRecordPayload::insert(&triple))
+            .unwrap();
+        wal.append(txn_id, hlc, LogRecordPayload::Commit).unwrap();
+    }
+
+    fn physical_times(records: &[LogRecord]) -> Vec<u64> {
+        records
+            .iter()
+            .map(|record| record.hlc.physical_time)
+            .collect()
+    }
+
+    #[test]
+    fn test_wal_changes_between() {
+        let mut cursor = create_test_cursor(8192);
+        let mut wal = Wal::new(&mut cursor, 0, 8192, 0, 0, 1);
+        for (txn_id, physical_time) in [(1, 1000), (2, 2000), (3, 3000)] {
+            append_insert_txn(&mut wal, txn_id, physical_time);
+        }
+
+        // Both ends are inclusive
+        let changes = wal
+            .changes_between(HlcTimestamp::new(1000, 0), HlcTimestamp::new(2000, 0))
+            .unwrap();
+        assert_eq!(physical_times(&changes), vec![1000, 2000]);
+
+        // Empty windows
+        let changes = wal
+            .changes_between(HlcTimestamp::new(1001, 0), HlcTimestamp::new(1999, 0))
+            .unwrap();
+        assert!(changes.is_empty());
+        let changes = wal
+            .changes_between(HlcTimestamp::new(3000, 0), HlcTimestamp::new(1000, 0))
+            .unwrap();
+        assert!(changes.is_empty());
+    }
+
+    #[test]
+    fn test_wal_changes_between_skips_uncommitted() {
+        let mut cursor = create_test_cursor(8192);
+        let mut wal = Wal::new(&mut cursor, 0, 8192, 0, 0, 1);
+        append_insert_txn(&mut wal, 1, 1000);
+        let triple = TripleRecord::new(
+            EntityId([3u8; 16]),
+            AttributeId([2u8; 16]),
+            2,
+            HlcTimestamp::new(2000, 0),
+            TripleValue::Number(7.0),
+        );
+        wal.append(2, HlcTimestamp::new(2000, 0), LogRecordPayload::Begin)
+            .unwrap();
+        wal.append(
+            2,
+            HlcTimestamp::new(2000, 0),
+            LogRecordPayload::insert(&triple),
+        )
+        .unwrap();
+
+        let changes = wal
+            .changes_between(HlcTimestamp::new(0, 0), HlcTimestamp::new(u64::MAX, 0))
+            .unwrap();
+        assert_eq!(physical_times(&changes), vec![1000]);
+        // changes_since does not wait for the commit
+        assert_eq!(wal.changes_since(HlcTimestamp::new(0, 0)).unwrap().len(), 2);
+    }
+
+    #[test]
+    fn test_wal_changes_between_across_wrap() {
+        let mut cursor = create_test_cursor(1024);
+        let mut wal = Wal::new(&mut cursor, 0, 1024, 0, 0, 1);
+
+        // Append transactions until the head wraps back to the start
+        let mut physical_time = 0;
+        let mut last_head = 0;
+        while wal.head() >= last_head {
+            last_head = wal.head();
+            physical_time += 1000;
+            append_insert_txn(&mut wal, physical_time, physical_time);
+        }
+        let wrapped_at = physical_time;
+        append_insert_txn(&mut wal, wrapped_at + 1000, wrapped_at + 1000);
+
+        // The window spans the last transaction before the wrap and the
+        // transactions after it
+        let changes = wal
+            .changes_between(
+                HlcTimestamp::new(wrapped_at - 1000, 0),
+                HlcTimestamp::new(wrapped_at + 1000, 0),
+            )
+            .unwrap();
+        assert_eq!(
+            physical_times(&changes),
+            vec![wrapped_at - 1000, wrapped_at, wrapped_at + 1000]
+        );
+
+        // Transactions overwritten by the wrap are gone
+        let changes = wal
+            .changes_between(HlcTimestamp::new(0, 0), HlcTimestamp::new(1000, 0))
+            .unwrap();
+        assert!(changes.is_empty());
+    }
 }
@@ -10,21 +10,54 @@
 //! 3. If `since_hlc` provided, server sends historical changes as initial `SubscriptionUpdate`
 //! 4. Server sends ongoing `SubscriptionUpdate` messages as changes occur
 //! 5. Client sends `UnsubscribeRequest` to cancel, or subscription ends on disconnect
+//!
+//! # Query Subscriptions
+//!
+//! A `SubscribeRequest` with a `query` watches that query instead of raw
+//! changes. Its `QueryWatch` decides which changes can affect the query, and
+//! each matching batch of changes sends one update with `query_invalidated`
+//! set, telling the client to re-run the query.
+//!
+//! # Live Query Subscriptions
+//!
+//! A `SubscribeQueryRequest` also watches a query, but the server re-runs it
+//! itself: `Subscription::live_query_for` returns the query when a batch of
+//! changes can affect it, and the connection sends the result from a fresh
+//! snapshot. Results are eventually consistent. A re-run may already see
+//! later commits, so several changes can coalesce into one result, and an
+//! unaffected result is sent again rather than compared with the last one.
+//!
+//! # Entity Subscriptions
+//!
+//! A `SubscribeRequest` with a `watch_entity_id` receives only the changes to
+//! that entity's triples, deletes included. It is backfilled with the entity's
+//! current triples rather than with history since an HLC.
+//!
+//! # Filtered Subscriptions
+//!
+//! A `SubscribeRequest` with an `entity_id` and/or `attribute_id` receives
+//! only the changes to triples matching both, live and in its `since_hlc`
+//! history alike, since both pass through `Subscription::update_for`.
 
 use std::collections::HashMap;
 
+use crate::constants::DEFAULT_MAX_SUBSCRIPTIONS_PER_CONNECTION;
 use crate::proto;
+use crate::query::{PatternElement, Query};
 use crate::storage::{LogRecord, LogRecordPayload};
-use crate::types::{HlcTimestamp, ProtoSerializable, TripleRecord};
+use crate::types::{AttributeId, EntityId, HlcTimestamp, ProtoSerializable, TripleRecord};
 
 /// Per-connection subscription tracking.
 ///
 /// Each WebSocket connection maintains its own set of active subscriptions.
 /// Subscriptions are identified by client-provided IDs that must be unique
-/// within the connection.
+/// within the connection. Every change is checked against every subscription,
+/// so their number is capped to bound the fan-out work per change.
 pub struct ClientSubscriptions {
     /// Map of `subscription_id` -> Subscription metadata.
     subscriptions: HashMap<u32, Subscription>,
+    /// Maximum number of active subscriptions.
+    max_subscriptions: usize,
 }
 
 /// Metadata for a single subscription.
@@ -34,6 +67,242 @@ pub struct Subscription {
     /// Optional HLC timestamp for filtering changes.
     /// Only changes with HLC > `since_hlc` are sent.
     pub since_hlc: Option<HlcTimestamp>,
+    /// Which changes the subscription receives.
+    pub filter: SubscriptionFilter,
+    /// Whether changes keep their `old_value`. Off unless the client asked.
+    pub include_old_values: bool,
+}
+
+/// Which changes a subscription receives.
+#[derive(Debug)]
+pub enum SubscriptionFilter {
+    /// Every change.
+    All,
+    /// Invalidations of a query.
+    Query(QueryWatch),
+    /// Fresh results of a query, re-run by the connection.
+    LiveQuery(LiveQuery),
+    /// Changes to one entity's triples.
+    Entity(EntityId),
+    /// Changes to the triples matching an entity and an attribute filter;
+    /// `None` matches any ID.
+    Triples {
+        entity_id: Option<EntityId>,
+        attribute_id: Option<AttributeId>,
+    },
+}
+
+impl Subscription {
+    /// Build the update to send this subscription for a batch of changes.
+    ///
+    /// A raw subscription receives every change, an entity subscription the
+    /// changes to its entity, and a filtered subscription the changes
+    /// matching its filter. A query subscription receives an
+    /// invalidation if any change can affect its query. A live query
+    /// subscription receives nothing here; see `live_query_for`. Old values
+    /// are stripped unless the subscription asked for them.
+    ///
+    /// # Returns
+    /// The update, or `None` if the subscription should not be notified.
+    #[must_use]
+    pub fn update_for(&self, changes: &[proto::ChangeRecord]) -> Option<proto::SubscriptionUpdate> {
+        let mut update = self.filtered_update(changes)?;
+        if !self.include_old_values {
+            for change in &mut update.changes {
+                change.old_value = None;
+            }
+        }
+        Some(update)
+    }
+
+    /// Build the update for the changes this subscription's filter matches.
+    #[allow(clippy::disallowed_methods)] // Clone needed for proto types
+    fn filtered_update(
+        &self,
+        changes: &[proto::ChangeRecord],
+    ) -> Option<proto::SubscriptionUpdate> {
+        match &self.filter {
+            SubscriptionFilter::All if changes.is_empty() => None,
+            SubscriptionFilter::All => Some(create_subscription_update(self.id, changes)),
+            SubscriptionFilter::Query(watch)
+                if changes.iter().any(|change| watch.is_affected_by(change)) =>
+            {
+                Some(proto::SubscriptionUpdate {
+                    subscription_id: self.id,
+                    changes: Vec::new(),
+                    query_invalidated: true,
+                    commit_hlc: None,
+                    query_result: None,
+                })
+            }
+            SubscriptionFilter::Query(_) | SubscriptionFilter::LiveQuery(_) => None,
+            SubscriptionFilter::Entity(entity_id) => {
+                self.matching_update(changes, Some(entity_id), None)
+            }
+            SubscriptionFilter::Triples {
+                entity_id,
+                attribute_id,
+            } => self.matching_update(changes, entity_id.as_ref(), attribute_id.as_ref()),
+        }
+    }
+
+    /// Get the query a live query subscription should re-run for a batch of
+    /// changes.
+    ///
+    /// # Returns
+    /// The subscribed query, or `None` if this is not a live query
+    /// subscription or no change can affect its query.
+    #[must_use]
+    pub fn live_query_for(&self, changes: &[proto::ChangeRecord]) -> Option<&proto::QueryRequest> {
+        match &self.filter {
+            SubscriptionFilter::LiveQuery(live)
+                if changes
+                    .iter()
+                    .any(|change| live.watch.is_affected_by(change)) =>
+            {
+                Some(&live.request)
+            }
+            _ => None,
+        }
+    }
+
+    /// Build the update for the changes whose triple has the given entity
+    /// and attribute; `None` matches any ID.
+    ///
+    /// A change whose triple is missing a filtered ID never matches.
+    #[allow(clippy::disallowed_methods)] // Clone needed for proto types
+    fn matching_update(
+        &self,
+        changes: &[proto::ChangeRecord],
+        entity_id: Option<&EntityId>,
+        attribute_id: Option<&AttributeId>,
+    ) -> Option<proto::SubscriptionUpdate> {
+        let changes: Vec<proto::ChangeRecord> = changes
+            .iter()
+            .filter(|change| {
+                change.triple.as_ref().is_some_and(|triple| {
+                    entity_id.is_none_or(|id| triple.entity_id.as_deref() == Some(&id.0[..]))
+                        && attribute_id
+                            .is_none_or(|id| triple.attribute_id.as_deref() == Some(&id.0[..]))
+                })
+            })
+            .cloned()
+            .collect();
+        if changes.is_empty() {
+            return None;
+        }
+        Some(proto::SubscriptionUpdate {
+            subscription_id: self.id,
+            changes,
+            query_invalidated: false,
+            commit_hlc: None,
+            query_result: None,
+        })
+    }
+}
+
+/// The triples a query subscription watches.
+///
+/// Holds one entity and attribute filter per pattern of the query (required,
+/// optional and negated alike). A change can affect the query if it matches
+/// any pattern. Values are not compared, so a change may invalidate a query
+/// whose result stays the same, but no change that alters the result is missed.
+///
+/// Patterns are indexed by attribute, so a change is only compared with the
+/// patterns for its own attribute and those with a variable attribute.
+#[derive(Debug)]
+pub struct QueryWatch {
+    /// Entity filters of the patterns with a fixed attribute, by attribute;
+    /// `None` matches any entity.
+    by_attribute: HashMap<AttributeId, Vec<Option<EntityId>>>,
+    /// Entity filters of the patterns with a variable attribute.
+    any_attribute: Vec<Option<EntityId>>,
+}
+
+/// A live query subscription's query and the triples it watches.
+#[derive(Debug)]
+pub struct LiveQuery {
+    /// The query as subscribed, with attribute names resolved.
+    request: proto::QueryRequest,
+    /// Which changes can affect the query.
+    watch: QueryWatch,
+}
+
+impl LiveQuery {
+    /// Build a live query from its request and parsed query.
+    #[must_use]
+    pub fn new(request: proto::QueryRequest, query: &Query) -> Self {
+        Self {
+            request,
+            watch: QueryWatch::new(query),
+        }
+    }
+}
+
+impl QueryWatch {
+    /// Build the watch for a query.
+    #[must_use]
+    pub fn new(query: &Query) -> Self {
+        let mut watch = Self {
+            by_attribute: HashMap::new(),
+            any_attribute: Vec::new(),
+        };
+        let patterns = query
+            .where_patterns
+            .iter()
+            .chain(&query.optional_patterns)
+            .chain(&query.where_not_patterns)
+            .flat_map(|pattern| {
+                let attribute_id = match pattern.field {
+                    PatternElement::Field(id) => Some(id),
+                    _ => None,
+                };
+                // An entity set watches each of its entities
+                let entity_ids: Vec<Option<EntityId>> = match &pattern.entity {
+                    PatternElement::Entity(id) => vec![Some(*id)],
+                    PatternElement::EntitySet { ids, .. } => {
+                        ids.iter().copied().map(Some).collect()
+                    }
+                    _ => vec![None],
+                };
+                entity_ids
+                    .into_iter()
+                    .map(move |entity_id| (entity_id, attribute_id))
+            });
+        for (entity_id, attribute_id) in patterns {
+            match attribute_id {
+                Some(attribute_id) => watch
+                    .by_attribute
+                    .entry(attribute_id)
+                    .or_default()
+                    .push(entity_id),
+                None => watch.any_attribute.push(entity_id),
+            }
+        }
+        watch
+    }
+
+    /// Check whether a change can affect the watched query.
+    ///
+    /// A change whose triple is missing its IDs is assumed to affect it.
+    #[must_use]
+    pub fn is_affected_by(&self, change: &proto::ChangeRecord) -> bool {
+        let Some(triple) = &change.triple else {
+            return true;
+        };
+        let (Some(entity_id), Some(attribute_id)) = (&triple.entity_id, &triple.attribute_id)
+        else {
+            return true;
+        };
+        let for_attribute = <[u8; 16]>::try_from(attribute_id.as_slice())
+            .ok()
+            .and_then(|id| self.by_attribute.get(&AttributeId(id)))
+            .into_iter()
+            .flatten();
+        for_attribute
+            .chain(&self.any_attribute)
+            .any(|entity| entity.is_none_or(|id| id.0[..] == entity_id[..]))
+    }
 }
 
 impl ClientSubscriptions {
@@ -42,25 +311,148 @@ impl ClientSubscriptions {
     pub fn new() -> Self {
         Self {
             subscriptions: HashMap::new(),
+            max_subscriptions: DEFAULT_MAX_SUBSCRIPTIONS_PER_CONNECTION,
         }
     }
 
+    /// Set the maximum number of active subscriptions.
+    ///
+    /// Subscriptions already registered are kept even if they exceed it.
+    #[must_use]
+    pub const fn with_max_subscriptions(mut self, max_subscriptions: usize) -> Self {
+        self.max_subscriptions = max_subscriptions;
+        self
+    }
+
+    /// Get the maximum number of active subscriptions.
+    #[must_use]
+    pub const fn max_subscriptions(&self) -> usize {
+        self.max_subscriptions
+    }
+
     /// Add a new subscription.
     ///
     /// # Errors
     ///
     /// Returns `SubscriptionError::AlreadyExists` if a subscription with the
-    /// given ID already exists.
+    /// given ID already exists, or `SubscriptionError::LimitReached` if the
+    /// maximum number of subscriptions is already active.
     pub fn add(
         &mut self,
         id: u32,
         since_hlc: Option<HlcTimestamp>,
     ) -> Result<(), SubscriptionError> {
-        if self.subscriptions.contains_key(&id) {
-            return Err(SubscriptionError::AlreadyExists(id));
+        self.insert(Subscription {
+            id,
+            since_hlc,
+            filter: SubscriptionFilter::All,
+            include_old_values: false,
+        })
+    }
+
+    /// Add a subscription that watches a query.
+    ///
+    /// # Errors
+    ///
+    /// Returns `SubscriptionError::AlreadyExists` if a subscription with the
+    /// given ID already exists, or `SubscriptionError::LimitReached` if the
+    /// maximum number of subscriptions is already active.
+    pub fn add_query(
+        &mut self,
+        id: u32,
+        since_hlc: Option<HlcTimestamp>,
+        query: QueryWatch,
+    ) -> Result<(), SubscriptionError> {
+        self.insert(Subscription {
+            id,
+            since_hlc,
+            filter: SubscriptionFilter::Query(query),
+            include_old_values: false,
+        })
+    }
+
+    /// Add a subscription whose query the connection re-runs on change.
+    ///
+    /// # Errors
+    ///
+    /// Returns `SubscriptionError::AlreadyExists` if a subscription with the
+    /// given ID already exists, or `SubscriptionError::LimitReached` if the
+    /// maximum number of subscriptions is already active.
+    pub fn add_live_query(&mut self, id: u32, query: LiveQuery) -> Result<(), SubscriptionError> {
+        self.insert(Subscription {
+            id,
+            since_hlc: None,
+            filter: SubscriptionFilter::LiveQuery(query),
+            include_old_values: false,
+        })
+    }
+
+    /// Add a subscription that watches one entity's triples.
+    ///
+    /// # Errors
+    ///
+    /// Returns `SubscriptionError::AlreadyExists` if a subscription with the
+    /// given ID already exists, or `SubscriptionError::LimitReached` if the
+    /// maximum number of subscriptions is already active.
+    pub fn add_entity(&mut self, id: u32, entity_id: EntityId) -> Result<(), SubscriptionError> {
+        self.insert(Subscription {
+            id,
+            since_hlc: None,
+            filter: SubscriptionFilter::Entity(entity_id),
+            include_old_values: false,
+        })
+    }
+
+    /// Add a subscription that receives the changes to triples with the given
+    /// entity and attribute; `None` matches any ID.
+    ///
+    /// # Errors
+    ///
+    /// Returns `SubscriptionError::AlreadyExists` if a subscription with the
+    /// given ID already exists, or `SubscriptionError::LimitReached` if the
+    /// maximum number of subscriptions is already active.
+    pub fn add_filtered(
+        &mut self,
+        id: u32,
+        since_hlc: Option<HlcTimestamp>,
+        entity_id: Option<EntityId>,
+        attribute_id: Option<AttributeId>,
+    ) -> Result<(), SubscriptionError> {
+        self.insert(Subscription {
+            id,
+            since_hlc,
+            filter: SubscriptionFilter::Triples {
+                entity_id,
+                attribute_id,
+            },
+            include_old_values: false,
+        })
+    }
+
+    /// Send a subscription's changes with their old values.
+    ///
+    /// # Errors
+    ///
+    /// Returns `SubscriptionError::NotFound` if no subscription has the given
+    /// ID.
+    pub fn include_old_values(&mut self, id: u32) -> Result<(), SubscriptionError> {
+        let subscription = self
+            .subscriptions
+            .get_mut(&id)
+            .ok_or(SubscriptionError::NotFound(id))?;
+        subscription.include_old_values = true;
+        Ok(())
+    }
+
+    /// Insert a subscription, rejecting a duplicate ID or one past the limit.
+    fn insert(&mut self, subscription: Subscription) -> Result<(), SubscriptionError> {
+        if self.subscriptions.contains_key(&subscription.id) {
+            return Err(SubscriptionError::AlreadyExists(subscription.id));
+        }
+        if self.subscriptions.len() >= self.max_subscriptions {
+            return Err(SubscriptionError::LimitReached(self.max_subscriptions));
         }
-        self.subscriptions
-            .insert(id, Subscription { id, since_hlc });
+        self.subscriptions.insert(subscription.id, subscription);
         Ok(())
     }
 
@@ -114,6 +506,8 @@ pub enum SubscriptionError {
     AlreadyExists(u32),
     /// No subscription with this ID exists.
     NotFound(u32),
+    /// The connection already has the maximum number of subscriptions.
+    LimitReached(usize),
 }
 
 impl std::fmt::Display for SubscriptionError {
@@ -121,6 +515,12 @@ impl std::fmt::Display for SubscriptionError {
         match self {
             Self::AlreadyExists(id) => write!(f, "subscription {id} already exists"),
             Self::NotFound(id) => write!(f, "subscription {id} not found"),
+            Self::LimitReached(max) => {
+                write!(
+                    f,
+                    "subscription limit reached: at most {max} per connection"
+                )
+            }
         }
     }
 }
@@ -209,6 +609,37 @@ pub fn create_internal_error_response(
     }
 }
 
+/// Create the error response for a failed subscribe.
+///
+/// A connection at its subscription limit gets `ResourceExhausted`, so the
+/// client knows to unsubscribe before subscribing again; other failures are
+/// `InvalidArgument`.
+#[must_use]
+pub fn create_subscription_error_response(
+    request_id: Option<u32>,
+    error: &SubscriptionError,
+) -> proto::ServerMessage {
+    let code = match error {
+        SubscriptionError::LimitReached(_) => proto::google::rpc::Code::ResourceExhausted,
+        SubscriptionError::AlreadyExists(_) | SubscriptionError::NotFound(_) => {
+            proto::google::rpc::Code::InvalidArgument
+        }
+    };
+    proto::ServerMessage {
+        payload: Some(proto::server_message::Payload::Response(
+            proto::ServerResponse {
+                request_id,
+                status: Some(proto::google::rpc::Status {
+                    code: code.into(),
+                    message: error.to_string(),
+                    ..Default::default()
+                }),
+                ..Default::default()
+            },
+        )),
+    }
+}
+
 /// Convert a slice of log records to proto change records.
 ///
 /// Filters out non-change records (BEGIN, COMMIT, CHECKPOINT) and logs warnings
@@ -248,7 +679,9 @@ pub fn log_record_to_change_record(
                     attribute_id: Some(triple.attribute_id.0.to_vec()),
                     value: (&triple.value).to_proto(),
                     hlc: Some(record.hlc.to_proto()),
+                    attribute_name: None,
                 }),
+                old_value: None,
             }))
         }
         LogRecordPayload::Update(bytes) => {
@@ -261,20 +694,26 @@ pub fn log_record_to_change_record(
                     attribute_id: Some(triple.attribute_id.0.to_vec()),
                     value: (&triple.value).to_proto(),
                     hlc: Some(record.hlc.to_proto()),
+                    attribute_name: None,
                 }),
+                old_value: None,
             }))
         }
         LogRecordPayload::Delete {
             entity_id,
             attribute_id,
+            hlc,
+            ..
         } => Ok(Some(proto::ChangeRecord {
             change_type: proto::ChangeType::Delete.into(),
             triple: Some(proto::Triple {
                 entity_id: Some(entity_id.0.to_vec()),
                 attribute_id: Some(attribute_id.0.to_vec()),
                 value: None,
-                hlc: Some(record.hlc.to_proto()),
+                hlc: Some(hlc.to_proto()),
+                attribute_name: None,
             }),
+            old_value: None,
         })),
         LogRecordPayload::Begin
         | LogRecordPayload::Commit
@@ -292,6 +731,9 @@ pub fn create_subscription_update(
     proto::SubscriptionUpdate {
         subscription_id,
         changes: changes.to_vec(),
+        query_invalidated: false,
+        commit_hlc: None,
+        query_result: None,
     }
 }
 
@@ -330,6 +772,54 @@ mod tests {
         assert_eq!(subs.remove(1), Err(SubscriptionError::NotFound(1)));
     }
 
+    #[test]
+    fn test_add_past_limit_fails() {
+        let mut subs = ClientSubscriptions::new().with_max_subscriptions(2);
+        subs.add(1, None).expect("add should succeed");
+        subs.add_entity(2, EntityId([1u8; 16]))
+            .expect("add should succeed");
+
+        assert_eq!(subs.add(3, None), Err(SubscriptionError::LimitReached(2)));
+        assert_eq!(
+            subs.add_entity(3, EntityId([1u8; 16])),
+            Err(SubscriptionError::LimitReached(2))
+        );
+        // A duplicate ID is still reported as such
+        assert_eq!(subs.add(1, None), Err(SubscriptionError::AlreadyExists(1)));
+        assert_eq!(subs.len(), 2);
+        assert!(subs.get(3).is_none());
+    }
+
+    #[test]
+    fn test_remove_frees_a_slot_under_limit() {
+        let mut subs = ClientSubscriptions::new().with_max_subscriptions(1);
+        subs.add(1, None).expect("add should succeed");
+        assert_eq!(subs.add(2, None), Err(SubscriptionError::LimitReached(1)));
+
+        subs.remove(1).expect("remove should succeed");
+        assert!(subs.add(2, None).is_ok());
+        assert_eq!(subs.len(), 1);
+    }
+
+    #[test]
+    fn test_default_limit() {
+        let mut subs = ClientSubscriptions::new();
+        assert_eq!(
+            subs.max_subscriptions(),
+            DEFAULT_MAX_SUBSCRIPTIONS_PER_CONNECTION
+        );
+        for id in 0..DEFAULT_MAX_SUBSCRIPTIONS_PER_CONNECTION {
+            let id = u32::try_from(id).expect("id fits in u32");
+            subs.add(id, None).expect("add should succeed");
+        }
+        assert_eq!(
+            subs.add(u32::MAX, None),
+            Err(SubscriptionError::LimitReached(
+                DEFAULT_MAX_SUBSCRIPTIONS_PER_CONNECTION
+            ))
+        );
+    }
+
     #[test]
     fn test_add_with_since_hlc() {
         let mut subs = ClientSubscriptions::new();
@@ -343,6 +833,224 @@ mod tests {
         assert_eq!(sub.since_hlc, Some(hlc));
     }
 
+    fn change_for(entity: &str, attribute: &str) -> proto::ChangeRecord {
+        proto::ChangeRecord {
+            change_type: proto::ChangeType::Insert.into(),
+            triple: Some(proto::Triple {
+                entity_id: Some(EntityId::from_string(entity).0.to_vec()),
+                attribute_id: Some(AttributeId::from_string(attribute).0.to_vec()),
+                value: None,
+                hlc: None,
+                attribute_name: None,
+            }),
+            old_value: None,
+        }
+    }
+
+    #[test]
+    fn test_query_watch_matches_every_pattern_kind() {
+        use crate::query::Pattern;
+
+        let query = Query::new()
+            .find("name")
+            .where_pattern(Pattern::new(
+                PatternElement::var("e"),
+                PatternElement::field("name"),
+                PatternElement::var("name"),
+            ))
+            .optional(Pattern::new(
+                PatternElement::var("e"),
+                PatternElement::field("age"),
+                PatternElement::var("age"),
+            ))
+            .where_not(Pattern::new(
+                PatternElement::entity("banned"),
+                PatternElement::field("member"),
+                PatternElement::var("e"),
+            ));
+        let watch = QueryWatch::new(&query);
+
+        assert!(watch.is_affected_by(&change_for("alice", "name")));
+        assert!(watch.is_affected_by(&change_for("alice", "age")));
+        assert!(watch.is_affected_by(&change_for("banned", "member")));
+        assert!(!watch.is_affected_by(&change_for("alice", "member")));
+        assert!(!watch.is_affected_by(&change_for("alice", "email")));
+    }
+
+    #[test]
+    fn test_query_watch_matches_each_entity_of_a_set() {
+        let query = Query::new()
+            .find("name")
+            .where_pattern(crate::query::Pattern::new(
+                PatternElement::entities("e", &["alice", "bob"]),
+                PatternElement::field("name"),
+                PatternElement::var("name"),
+            ));
+        let watch = QueryWatch::new(&query);
+
+        assert!(watch.is_affected_by(&change_for("alice", "name")));
+        assert!(watch.is_affected_by(&change_for("bob", "name")));
+        assert!(!watch.is_affected_by(&change_for("carol", "name")));
+        assert!(!watch.is_affected_by(&change_for("alice", "age")));
+    }
+
+    #[test]
+    fn test_query_subscription_update_is_invalidation() {
+        let query = Query::new()
+            .find("v")
+            .where_pattern(crate::query::Pattern::new(
+                PatternElement::var("e"),
+                PatternElement::field("name"),
+                PatternElement::var("v"),
+            ));
+        let mut subs = ClientSubscriptions::new();
+        subs.add_query(1, None, QueryWatch::new(&query))
+            .expect("add should succeed");
+        let sub = subs.get(1).expect("subscription should exist");
+
+        assert!(sub.update_for(&[change_for("a", "email")]).is_none());
+        let update = sub
+            .update_for(&[change_for("a", "email"), change_for("a", "name")])
+            .expect("query should be invalidated");
+        assert!(update.query_invalidated);
+        assert!(update.changes.is_empty());
+    }
+
+    #[test]
+    fn test_query_watch_matches_variable_attribute_by_entity() {
+        let query = Query::new()
+            .find("v")
+            .where_pattern(crate::query::Pattern::new(
+                PatternElement::entity("alice"),
+                PatternElement::var("a"),
+                PatternElement::var("v"),
+            ))
+            .where_pattern(crate::query::Pattern::new(
+                PatternElement::var("e"),
+                PatternElement::field("name"),
+                PatternElement::var("v"),
+            ));
+        let watch = QueryWatch::new(&query);
+
+        assert!(watch.is_affected_by(&change_for("alice", "email")));
+        assert!(watch.is_affected_by(&change_for("bob", "name")));
+        assert!(!watch.is_affected_by(&change_for("bob", "email")));
+    }
+
+    #[test]
+    fn test_live_query_rerun_only_when_affected() {
+        let query = Query::new()
+            .find("v")
+            .where_pattern(crate::query::Pattern::new(
+                PatternElement::var("e"),
+                PatternElement::field("name"),
+                PatternElement::var("v"),
+            ));
+        let request = proto::QueryRequest {
+            limit: Some(5),
+            ..Default::default()
+        };
+        let mut subs = ClientSubscriptions::new();
+        subs.add_live_query(1, LiveQuery::new(request, &query))
+            .expect("add should succeed");
+        let sub = subs.get(1).expect("subscription should exist");
+
+        assert!(sub.live_query_for(&[change_for("a", "email")]).is_none());
+        let rerun = sub
+            .live_query_for(&[change_for("a", "email"), change_for("a", "name")])
+            .expect("query should be re-run");
+        assert_eq!(rerun.limit, Some(5));
+        assert!(
+            sub.update_for(&[change_for("a", "name")]).is_none(),
+            "live query results come from the connection"
+        );
+    }
+
+    #[test]
+    fn test_entity_subscription_receives_only_its_entity() {
+        let mut subs = ClientSubscriptions::new();
+        subs.add_entity(1, EntityId::from_string("a"))
+            .expect("add should succeed");
+        let sub = subs.get(1).expect("subscription should exist");
+
+        assert!(sub.update_for(&[change_for("b", "name")]).is_none());
+        let update = sub
+            .update_for(&[
+                change_for("b", "name"),
+                change_for("a", "name"),
+                change_for("a", "email"),
+            ])
+            .expect("entity changes should be delivered");
+        assert!(!update.query_invalidated);
+        assert_eq!(
+            update.changes,
+            vec![change_for("a", "name"), change_for("a", "email")]
+        );
+    }
+
+    #[test]
+    fn test_filtered_subscription_receives_only_matching_changes() {
+        let mut subs = ClientSubscriptions::new();
+        subs.add_filtered(1, None, None, Some(AttributeId::from_string("name")))
+            .expect("add should succeed");
+        subs.add_filtered(
+            2,
+            None,
+            Some(EntityId::from_string("a")),
+            Some(AttributeId::from_string("name")),
+        )
+        .expect("add should succeed");
+        let changes = [
+            change_for("a", "name"),
+            change_for("a", "email"),
+            change_for("b", "name"),
+        ];
+
+        let by_attribute = subs.get(1).expect("subscription should exist");
+        let update = by_attribute
+            .update_for(&changes)
+            .expect("attribute changes should be delivered");
+        assert_eq!(
+            update.changes,
+            vec![change_for("a", "name"), change_for("b", "name")]
+        );
+
+        let by_triple = subs.get(2).expect("subscription should exist");
+        let update = by_triple
+            .update_for(&changes)
+            .expect("triple changes should be delivered");
+        assert_eq!(update.changes, vec![change_for("a", "name")]);
+        assert!(by_triple.update_for(&changes[1..]).is_none());
+    }
+
+    #[test]
+    fn test_old_values_only_sent_when_requested() {
+        let mut change = change_for("a", "name");
+        change.old_value = Some(proto::TripleValue {
+            value: Some(proto::triple_value::Value::Number(1.0)),
+        });
+        let mut subs = ClientSubscriptions::new();
+        subs.add(1, None).expect("add should succeed");
+        subs.add(2, None).expect("add should succeed");
+        subs.include_old_values(2).expect("subscription exists");
+        assert!(matches!(
+            subs.include_old_values(3),
+            Err(SubscriptionError::NotFound(3))
+        ));
+
+        let plain = subs.get(1).expect("subscription should exist");
+        let update = plain
+            .update_for(std::slice::from_ref(&change))
+            .expect("update");
+        assert_eq!(update.changes[0].old_value, None);
+
+        let with_old = subs.get(2).expect("subscription should exist");
+        let update = with_old
+            .update_for(std::slice::from_ref(&change))
+            .expect("update");
+        assert_eq!(update.changes[0].old_value, change.old_value);
+    }
+
     #[test]
     fn test_client_subscribe_returns_ok() {
         let db = crate::testing::new_test_database().expect("create test db");
@@ -354,6 +1062,12 @@ mod tests {
                 proto::SubscribeRequest {
                     subscription_id: 1,
                     since_hlc: None,
+                    query: None,
+                    watch_entity_id: None,
+                    until_hlc: None,
+                    include_old_values: false,
+                    entity_id: None,
+                    attribute_id: None,
                 },
             )),
         };
@@ -389,6 +1103,12 @@ mod tests {
                 proto::SubscribeRequest {
                     subscription_id: 1,
                     since_hlc: None,
+                    query: None,
+                    watch_entity_id: None,
+                    until_hlc: None,
+                    include_old_values: false,
+                    entity_id: None,
+                    attribute_id: None,
                 },
             )),
         };
@@ -401,6 +1121,12 @@ mod tests {
                 proto::SubscribeRequest {
                     subscription_id: 1,
                     since_hlc: None,
+                    query: None,
+                    watch_entity_id: None,
+                    until_hlc: None,
+                    include_old_values: false,
+                    entity_id: None,
+                    attribute_id: None,
                 },
             )),
         };
@@ -427,6 +1153,60 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_client_subscribe_past_limit_returns_resource_exhausted() {
+        let db = crate::testing::new_test_database().expect("create test db");
+        let mut client = ClientConnection::new(db).with_max_subscriptions(1);
+        let subscribe =
+            |request_id: u32, subscription_id: u32, watch_entity_id| proto::ClientMessage {
+                request_id: Some(request_id),
+                payload: Some(proto::client_message::Payload::Subscribe(
+                    proto::SubscribeRequest {
+                        subscription_id,
+                        since_hlc: None,
+                        query: None,
+                        watch_entity_id,
+                        until_hlc: None,
+                        include_old_values: false,
+                        entity_id: None,
+                        attribute_id: None,
+                    },
+                )),
+            };
+        let status_code = |messages: &[proto::ServerMessage]| match &messages[0].payload {
+            Some(proto::server_message::Payload::Response(resp)) => {
+                resp.status.as_ref().unwrap().code
+            }
+            _ => panic!("expected Response payload"),
+        };
+
+        let messages = client.handle_message(subscribe(1, 1, None));
+        assert_eq!(status_code(&messages), proto::google::rpc::Code::Ok as i32);
+        assert_eq!(client.subscription_count(), 1);
+
+        // Both raw and entity subscriptions count against the limit
+        for (request_id, watch_entity_id) in [(2, None), (3, Some(vec![1u8; 16]))] {
+            let messages = client.handle_message(subscribe(request_id, 2, watch_entity_id));
+            assert_eq!(messages.len(), 1);
+            assert_eq!(
+                status_code(&messages),
+                proto::google::rpc::Code::ResourceExhausted as i32
+            );
+        }
+        assert_eq!(client.subscription_count(), 1);
+
+        // Unsubscribing makes room again
+        let _ = client.handle_message(proto::ClientMessage {
+            request_id: Some(4),
+            payload: Some(proto::client_message::Payload::Unsubscribe(
+                proto::UnsubscribeRequest { subscription_id: 1 },
+            )),
+        });
+        let messages = client.handle_message(subscribe(5, 2, None));
+        assert_eq!(status_code(&messages), proto::google::rpc::Code::Ok as i32);
+        assert_eq!(client.subscription_count(), 1);
+    }
+
     #[test]
     fn test_client_unsubscribe_returns_ok() {
         let db = crate::testing::new_test_database().expect("create test db");
@@ -439,6 +1219,12 @@ mod tests {
                 proto::SubscribeRequest {
                     subscription_id: 1,
                     since_hlc: None,
+                    query: None,
+                    watch_entity_id: None,
+                    until_hlc: None,
+                    include_old_values: false,
+                    entity_id: None,
+                    attribute_id: None,
                 },
             )),
         };
@@ -0,0 +1,103 @@
+//! Bulk load requests.
+
+use crate::proto;
+use crate::types::value_limits::ValueLimitViolation;
+use crate::types::{PendingTripleData, ProtoDeserializable, ValueLimits};
+
+/// A validated chunk of a bulk load.
+#[derive(Debug)]
+pub struct BulkLoadRequest {
+    /// The chunk's triples, in request order.
+    pub triples: Vec<PendingTripleData>,
+    /// Whether this is the load's last chunk.
+    pub finish: bool,
+}
+
+impl ProtoDeserializable<proto::BulkLoadRequest> for BulkLoadRequest {
+    /// # Errors
+    ///
+    /// Returns an error naming the first triple that fails to parse.
+    fn from_proto(request: proto::BulkLoadRequest) -> Result<Self, String> {
+        let triples = request
+            .triples
+            .into_iter()
+            .enumerate()
+            .map(|(index, triple)| {
+                PendingTripleData::from_proto(triple)
+                    .map_err(|err| format!("Failed to parse triple #{index}: {err}"))
+            })
+            .collect::<Result<_, _>>()?;
+
+        Ok(Self {
+            triples,
+            finish: request.finish,
+        })
+    }
+}
+
+impl BulkLoadRequest {
+    /// Check every triple's value against the configured limits.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error naming the first triple whose value exceeds `limits`.
+    pub fn check_value_limits(&self, limits: &ValueLimits) -> Result<(), ValueLimitViolation> {
+        for (index, triple) in self.triples.iter().enumerate() {
+            limits
+                .check(&triple.value)
+                .map_err(|error| ValueLimitViolation {
+                    location: format!("triple #{index}"),
+                    error,
+                })?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{AttributeId, EntityId, TripleValue};
+
+    fn triple(entity_id: Vec<u8>) -> proto::Triple {
+        proto::Triple {
+            entity_id: Some(entity_id),
+            attribute_id: Some([2u8; 16].to_vec()),
+            value: Some(proto::TripleValue {
+                value: Some(proto::triple_value::Value::Number(1.0)),
+            }),
+            hlc: Some(proto::HlcTimestamp {
+                physical_time_ms: 1000,
+                logical_counter: 0,
+                node_id: 1,
+            }),
+            attribute_name: None,
+        }
+    }
+
+    #[test]
+    fn test_bulk_load_request_from_proto() {
+        let request = BulkLoadRequest::from_proto(proto::BulkLoadRequest {
+            triples: vec![triple([1u8; 16].to_vec())],
+            finish: true,
+        })
+        .expect("valid request");
+
+        assert!(request.finish);
+        assert_eq!(request.triples.len(), 1);
+        assert_eq!(request.triples[0].entity_id, EntityId([1u8; 16]));
+        assert_eq!(request.triples[0].attribute_id, AttributeId([2u8; 16]));
+        assert_eq!(request.triples[0].value, TripleValue::Number(1.0));
+    }
+
+    #[test]
+    fn test_bulk_load_request_names_invalid_triple() {
+        let err = BulkLoadRequest::from_proto(proto::BulkLoadRequest {
+            triples: vec![triple([1u8; 16].to_vec()), triple(vec![1u8; 4])],
+            finish: false,
+        })
+        .expect_err("short entity ID");
+
+        assert!(err.starts_with("Failed to parse triple #1:"), "{err}");
+    }
+}
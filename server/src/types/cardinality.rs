@@ -0,0 +1,173 @@
+//! Attribute cardinality.
+//!
+//! An attribute holds either one value per entity, replaced by each write, or
+//! many values per entity, added by each write. The primary index keys a
+//! triple by `(entity_id, attribute_id, value_hash)`: a cardinality-one triple
+//! has value hash 0, and each value of a cardinality-many attribute is keyed
+//! by a hash of the value, so the values coexist.
+//!
+//! Distinct values can share a hash. A write whose hash already keys a
+//! different value of the entity's attribute takes the next free hash
+//! instead (linear probing), so colliding values never overwrite each other.
+
+use crate::types::TripleValue;
+
+#[cfg(test)]
+thread_local! {
+    /// Value hash every cardinality-many value takes on this thread while
+    /// set, so tests can make any values collide.
+    static FORCED_VALUE_HASH: std::cell::Cell<Option<u64>> = const { std::cell::Cell::new(None) };
+}
+
+/// Run `run` with every cardinality-many value on this thread hashed to
+/// `value_hash`.
+///
+/// # Pre-conditions
+/// - `value_hash` is nonzero
+#[cfg(test)]
+pub fn with_forced_value_hash<T>(value_hash: u64, run: impl FnOnce() -> T) -> T {
+    assert_ne!(value_hash, 0, "0 is the cardinality-one key");
+    let previous = FORCED_VALUE_HASH.replace(Some(value_hash));
+    let result = run();
+    FORCED_VALUE_HASH.set(previous);
+    result
+}
+
+/// How many values an attribute holds per entity.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Cardinality {
+    /// One value; a write replaces it.
+    #[default]
+    One,
+    /// Any number of distinct values; a write adds one.
+    Many,
+}
+
+impl Cardinality {
+    /// The byte stored for this cardinality in a serialized record.
+    #[must_use]
+    pub const fn to_byte(self) -> u8 {
+        match self {
+            Self::One => 0,
+            Self::Many => 1,
+        }
+    }
+
+    /// Decode a stored cardinality byte, or `None` if it is unknown.
+    #[must_use]
+    pub const fn from_byte(byte: u8) -> Option<Self> {
+        match byte {
+            0 => Some(Self::One),
+            1 => Some(Self::Many),
+            _ => None,
+        }
+    }
+
+    /// The name a cardinality is declared with: `"one"` or `"many"`.
+    #[must_use]
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            Self::One => "one",
+            Self::Many => "many",
+        }
+    }
+
+    /// Parse a declared cardinality name, or `None` if it is neither
+    /// `"one"` nor `"many"`.
+    #[must_use]
+    pub fn parse(name: &str) -> Option<Self> {
+        match name {
+            "one" => Some(Self::One),
+            "many" => Some(Self::Many),
+            _ => None,
+        }
+    }
+
+    /// The home value hash of a triple with `value`: the first key it is
+    /// probed at in the primary index.
+    ///
+    /// 0 for a cardinality-one attribute. For a cardinality-many attribute, a
+    /// nonzero 64-bit FNV-1a hash of the serialized value, so equal values
+    /// share a home key. A different value already keyed there moves the
+    /// write on to `next_value_hash`.
+    #[must_use]
+    pub fn value_hash(self, value: &TripleValue) -> u64 {
+        const OFFSET_BASIS: u64 = 0xCBF2_9CE4_8422_2325;
+        const PRIME: u64 = 0x0000_0100_0000_01B3;
+        match self {
+            Self::One => 0,
+            Self::Many => {
+                #[cfg(test)]
+                if let Some(forced) = FORCED_VALUE_HASH.get() {
+                    return forced;
+                }
+                let hash = value.to_bytes().iter().fold(OFFSET_BASIS, |hash, byte| {
+                    (hash ^ u64::from(*byte)).wrapping_mul(PRIME)
+                });
+                // 0 is the cardinality-one key
+                hash.max(1)
+            }
+        }
+    }
+
+    /// The cardinality-many value hash probed after `value_hash` when it
+    /// keys a different value.
+    ///
+    /// # Pre-conditions
+    /// - `value_hash` is nonzero
+    ///
+    /// # Post-conditions
+    /// - The result is nonzero: it wraps from `u64::MAX` to 1, skipping the
+    ///   cardinality-one key
+    #[must_use]
+    pub const fn next_value_hash(value_hash: u64) -> u64 {
+        assert!(value_hash != 0, "cardinality-one triples are never probed");
+        if value_hash == u64::MAX {
+            1
+        } else {
+            value_hash + 1
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cardinality_byte_roundtrip() {
+        for cardinality in [Cardinality::One, Cardinality::Many] {
+            assert_eq!(
+                Cardinality::from_byte(cardinality.to_byte()),
+                Some(cardinality)
+            );
+            assert_eq!(Cardinality::parse(cardinality.as_str()), Some(cardinality));
+        }
+        assert_eq!(Cardinality::from_byte(2), None);
+        assert_eq!(Cardinality::parse("several"), None);
+    }
+
+    #[test]
+    fn test_value_hash() {
+        let red = TripleValue::String("red".to_string());
+        let blue = TripleValue::String("blue".to_string());
+
+        assert_eq!(Cardinality::One.value_hash(&red), 0);
+        assert_ne!(Cardinality::Many.value_hash(&red), 0);
+        assert_eq!(
+            Cardinality::Many.value_hash(&red),
+            Cardinality::Many.value_hash(&TripleValue::String("red".to_string()))
+        );
+        assert_ne!(
+            Cardinality::Many.value_hash(&red),
+            Cardinality::Many.value_hash(&blue)
+        );
+    }
+
+    #[test]
+    fn test_next_value_hash() {
+        assert_eq!(Cardinality::next_value_hash(1), 2);
+        assert_eq!(Cardinality::next_value_hash(u64::MAX - 1), u64::MAX);
+        assert_eq!(Cardinality::next_value_hash(u64::MAX), 1);
+    }
+}
@@ -4,7 +4,9 @@
 //! triple modifications, plus conversion to proto equivalents.
 
 use crate::proto;
-use crate::types::{AttributeId, EntityId, HlcTimestamp, ProtoSerializable, TripleValue};
+use crate::types::{
+    AttributeId, EntityId, HlcTimestamp, ProtoDeserializable, ProtoSerializable, TripleValue,
+};
 
 // =============================================================================
 // Change Notification Types
@@ -33,6 +35,9 @@ pub struct ChangeRecord {
     pub attribute_id: AttributeId,
     /// The value of the triple. `None` for Delete operations.
     pub value: Option<TripleValue>,
+    /// The committed value the change replaced or deleted. `None` for inserts
+    /// and for changes read back from the WAL, which does not record it.
+    pub old_value: Option<TripleValue>,
     /// The HLC timestamp of the change.
     pub hlc: HlcTimestamp,
 }
@@ -52,6 +57,12 @@ pub struct ChangeNotification {
     pub source_connection_id: ConnectionId,
     /// The changes that occurred in this transaction.
     pub changes: Vec<ChangeRecord>,
+    /// The committing transaction's HLC.
+    ///
+    /// Notifications are sent in commit order, so this increases strictly from
+    /// one notification to the next. The changes' own HLCs come from the
+    /// writers and need not follow it.
+    pub commit_hlc: HlcTimestamp,
 }
 
 impl ProtoSerializable<i32> for ChangeType {
@@ -68,6 +79,7 @@ impl ProtoSerializable<proto::ChangeRecord> for ChangeRecord {
     #[allow(clippy::disallowed_methods)] // Clone needed for String conversion
     fn to_proto(self) -> proto::ChangeRecord {
         let value = self.value.and_then(ProtoSerializable::to_proto);
+        let old_value = self.old_value.and_then(ProtoSerializable::to_proto);
 
         proto::ChangeRecord {
             change_type: self.change_type.to_proto(),
@@ -76,7 +88,9 @@ impl ProtoSerializable<proto::ChangeRecord> for ChangeRecord {
                 attribute_id: Some(self.attribute_id.0.to_vec()),
                 value,
                 hlc: Some(self.hlc.to_proto()),
+                attribute_name: None,
             }),
+            old_value,
         }
     }
 }
@@ -88,6 +102,10 @@ impl ProtoSerializable<proto::ChangeRecord> for &ChangeRecord {
     #[allow(clippy::disallowed_methods)] // Clone needed for String conversion
     fn to_proto(self) -> proto::ChangeRecord {
         let value = self.value.as_ref().and_then(ProtoSerializable::to_proto);
+        let old_value = self
+            .old_value
+            .as_ref()
+            .and_then(ProtoSerializable::to_proto);
 
         proto::ChangeRecord {
             change_type: self.change_type.to_proto(),
@@ -96,11 +114,74 @@ impl ProtoSerializable<proto::ChangeRecord> for &ChangeRecord {
                 attribute_id: Some(self.attribute_id.0.to_vec()),
                 value,
                 hlc: Some(self.hlc.to_proto()),
+                attribute_name: None,
             }),
+            old_value,
         }
     }
 }
 
+impl ProtoDeserializable<i32> for ChangeType {
+    fn from_proto(proto_type: i32) -> Result<Self, String> {
+        match proto::ChangeType::try_from(proto_type) {
+            Ok(proto::ChangeType::Insert) => Ok(Self::Insert),
+            Ok(proto::ChangeType::Update) => Ok(Self::Update),
+            Ok(proto::ChangeType::Delete) => Ok(Self::Delete),
+            Ok(proto::ChangeType::Unspecified) | Err(_) => {
+                Err(format!("Unknown change type: {proto_type}"))
+            }
+        }
+    }
+}
+
+impl ProtoDeserializable<proto::ChangeRecord> for ChangeRecord {
+    /// Deserialize a `ChangeRecord` received in a `SubscriptionUpdate`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the change type is unknown, the triple or its HLC is
+    /// missing, an ID is not exactly 16 bytes, or a non-delete change has no value.
+    fn from_proto(proto_change: proto::ChangeRecord) -> Result<Self, String> {
+        let change_type = ChangeType::from_proto(proto_change.change_type)?;
+        let triple = proto_change
+            .triple
+            .ok_or("ChangeRecord proto did not contain a triple.")?;
+
+        let entity_id = triple
+            .entity_id
+            .and_then(|bytes| <[u8; 16]>::try_from(bytes.as_slice()).ok())
+            .ok_or("ChangeRecord entity_id must be exactly 16 bytes.")?;
+        let attribute_id = triple
+            .attribute_id
+            .and_then(|bytes| <[u8; 16]>::try_from(bytes.as_slice()).ok())
+            .ok_or("ChangeRecord attribute_id must be exactly 16 bytes.")?;
+
+        let value = match (change_type, triple.value) {
+            (ChangeType::Delete, _) => None,
+            (_, Some(proto_value)) => Some(TripleValue::from_proto(proto_value)?),
+            (_, None) => return Err("ChangeRecord proto did not contain a value.".to_owned()),
+        };
+
+        let old_value = proto_change
+            .old_value
+            .map(TripleValue::from_proto)
+            .transpose()?;
+
+        let hlc = triple
+            .hlc
+            .ok_or("ChangeRecord proto did not contain an hlc timestamp.")?;
+
+        Ok(Self {
+            change_type,
+            entity_id: EntityId(entity_id),
+            attribute_id: AttributeId(attribute_id),
+            value,
+            old_value,
+            hlc: HlcTimestamp::from_proto(hlc)?,
+        })
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -131,6 +212,7 @@ mod tests {
             entity_id: EntityId([1u8; 16]),
             attribute_id: AttributeId([2u8; 16]),
             value: Some(TripleValue::String("hello".to_string())),
+            old_value: None,
             hlc: HlcTimestamp {
                 physical_time: 1000,
                 logical_counter: 1,
@@ -158,6 +240,7 @@ mod tests {
             entity_id: EntityId([1u8; 16]),
             attribute_id: AttributeId([2u8; 16]),
             value: None,
+            old_value: None,
             hlc: HlcTimestamp {
                 physical_time: 1000,
                 logical_counter: 1,
@@ -179,6 +262,7 @@ mod tests {
             entity_id: EntityId([3u8; 16]),
             attribute_id: AttributeId([4u8; 16]),
             value: Some(TripleValue::Boolean(true)),
+            old_value: Some(TripleValue::Boolean(false)),
             hlc: HlcTimestamp {
                 physical_time: 2000,
                 logical_counter: 2,
@@ -188,8 +272,55 @@ mod tests {
 
         let proto_change: proto::ChangeRecord = (&change).to_proto();
         assert_eq!(proto_change.change_type, proto::ChangeType::Update as i32);
+        let decoded = ChangeRecord::from_proto(proto_change).expect("decode");
+        assert_eq!(decoded.old_value, Some(TripleValue::Boolean(false)));
 
         // Original still accessible
         assert_eq!(change.change_type, ChangeType::Update);
     }
+
+    #[test]
+    fn test_change_record_proto_roundtrip() {
+        let change = ChangeRecord {
+            change_type: ChangeType::Insert,
+            entity_id: EntityId([5u8; 16]),
+            attribute_id: AttributeId([6u8; 16]),
+            value: Some(TripleValue::Number(1.5)),
+            old_value: None,
+            hlc: HlcTimestamp {
+                physical_time: 3000,
+                logical_counter: 3,
+                node_id: 7,
+            },
+        };
+
+        let decoded = ChangeRecord::from_proto(change.to_proto()).expect("decode");
+        assert_eq!(decoded.change_type, ChangeType::Insert);
+        assert_eq!(decoded.entity_id, EntityId([5u8; 16]));
+        assert_eq!(decoded.attribute_id, AttributeId([6u8; 16]));
+        assert_eq!(decoded.value, Some(TripleValue::Number(1.5)));
+        assert_eq!(decoded.hlc.logical_counter, 3);
+    }
+
+    #[test]
+    fn test_change_record_from_proto_rejects_short_entity_id() {
+        let proto_change = proto::ChangeRecord {
+            change_type: proto::ChangeType::Delete as i32,
+            triple: Some(proto::Triple {
+                entity_id: Some(vec![1u8; 4]),
+                attribute_id: Some(vec![2u8; 16]),
+                value: None,
+                hlc: Some(proto::HlcTimestamp::default()),
+                attribute_name: None,
+            }),
+            old_value: None,
+        };
+
+        assert!(ChangeRecord::from_proto(proto_change).is_err());
+    }
+
+    #[test]
+    fn test_change_type_from_proto_rejects_unspecified() {
+        assert!(ChangeType::from_proto(proto::ChangeType::Unspecified as i32).is_err());
+    }
 }
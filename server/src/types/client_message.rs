@@ -1,6 +1,16 @@
 use crate::{
+    auth::AppConfig,
     proto,
-    types::{ProtoDeserializable, triple_update_request::TripleUpdateRequest},
+    types::{
+        AttributeId, ProtoDeserializable, ValueLimits,
+        bulk_load_request::BulkLoadRequest,
+        contains_request::ContainsRequest,
+        delete_request::DeleteRequest,
+        query::{check_query_attribute_scope, check_query_value_limits},
+        set_defaults_request::SetDefaultsRequest,
+        triple_update_request::TripleUpdateRequest,
+        value_limits::ValueLimitViolation,
+    },
 };
 
 #[derive(Debug)]
@@ -8,8 +18,21 @@ pub enum ClientMessagePayload {
     TripleUpdateRequest(TripleUpdateRequest),
     Query(proto::QueryRequest),
     Subscribe(proto::SubscribeRequest),
+    SubscribeQuery(proto::SubscribeQueryRequest),
     Unsubscribe(proto::UnsubscribeRequest),
     Connect(proto::ConnectRequest),
+    Contains(ContainsRequest),
+    OpenCursor(proto::QueryRequest),
+    FetchCursor(proto::FetchCursorRequest),
+    CloseCursor(proto::CloseCursorRequest),
+    SetDefaults(SetDefaultsRequest),
+    Delete(DeleteRequest),
+    ReadBatch(proto::ReadBatchRequest),
+    DescribeSchema(proto::DescribeSchemaRequest),
+    BeginTxn(proto::BeginTxnRequest),
+    CommitTxn(proto::CommitTxnRequest),
+    AbortTxn(proto::AbortTxnRequest),
+    BulkLoad(BulkLoadRequest),
 }
 
 #[derive(Debug)]
@@ -17,6 +40,136 @@ pub struct ClientMessage {
     pub payload: ClientMessagePayload,
 }
 
+impl ClientMessage {
+    /// Check every string value the message carries against the configured
+    /// limits: written values, connection defaults, and query pattern values.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error naming the first value that is empty or exceeds
+    /// `limits`.
+    pub fn check_value_limits(&self, limits: &ValueLimits) -> Result<(), ValueLimitViolation> {
+        match &self.payload {
+            ClientMessagePayload::TripleUpdateRequest(request) => {
+                request.check_value_limits(limits)
+            }
+            ClientMessagePayload::SetDefaults(request) => request.check_value_limits(limits),
+            ClientMessagePayload::BulkLoad(request) => request.check_value_limits(limits),
+            ClientMessagePayload::Query(request) | ClientMessagePayload::OpenCursor(request) => {
+                check_query_value_limits(request, limits)
+            }
+            ClientMessagePayload::Subscribe(request) => request
+                .query
+                .as_ref()
+                .map_or(Ok(()), |query| check_query_value_limits(query, limits)),
+            ClientMessagePayload::SubscribeQuery(request) => request
+                .query
+                .as_ref()
+                .map_or(Ok(()), |query| check_query_value_limits(query, limits)),
+            ClientMessagePayload::ReadBatch(request) => request
+                .queries
+                .iter()
+                .try_for_each(|query| check_query_value_limits(query, limits)),
+            ClientMessagePayload::Unsubscribe(_)
+            | ClientMessagePayload::Connect(_)
+            | ClientMessagePayload::Contains(_)
+            | ClientMessagePayload::FetchCursor(_)
+            | ClientMessagePayload::CloseCursor(_)
+            | ClientMessagePayload::Delete(_)
+            | ClientMessagePayload::DescribeSchema(_)
+            | ClientMessagePayload::BeginTxn(_)
+            | ClientMessagePayload::CommitTxn(_)
+            | ClientMessagePayload::AbortTxn(_) => Ok(()),
+        }
+    }
+
+    /// Check that the message only touches attributes the app may access:
+    /// written, bulk loaded and deleted triples, connection defaults, and
+    /// query patterns.
+    ///
+    /// Subscriptions must be scoped too: a query subscription by its query,
+    /// and a raw subscription by an in-scope `attribute_id` filter. Entity
+    /// watches stream every attribute of the entity, so a scoped app cannot
+    /// use them.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error naming the first attribute reference outside
+    /// `config`'s scope.
+    pub fn check_attribute_scope(&self, config: &AppConfig) -> Result<(), String> {
+        if config.allowed_attribute_prefixes().is_none() {
+            return Ok(());
+        }
+        let check_id = |attribute_id: &AttributeId| {
+            if config.allows_attribute(&attribute_id.0) {
+                Ok(())
+            } else {
+                Err(format!(
+                    "attribute {attribute_id} is outside the app's scope"
+                ))
+            }
+        };
+        match &self.payload {
+            ClientMessagePayload::TripleUpdateRequest(request) => request
+                .triples
+                .iter()
+                .try_for_each(|triple| check_id(&triple.attribute_id)),
+            ClientMessagePayload::BulkLoad(request) => request
+                .triples
+                .iter()
+                .try_for_each(|triple| check_id(&triple.attribute_id)),
+            ClientMessagePayload::Contains(request) => check_id(&request.attribute_id),
+            ClientMessagePayload::Delete(request) => request
+                .keys
+                .iter()
+                .try_for_each(|(_, attribute_id)| check_id(attribute_id)),
+            ClientMessagePayload::SetDefaults(request) => request
+                .defaults
+                .iter()
+                .try_for_each(|default| check_id(&default.attribute_id)),
+            ClientMessagePayload::Query(request) | ClientMessagePayload::OpenCursor(request) => {
+                check_query_attribute_scope(request, config)
+            }
+            ClientMessagePayload::ReadBatch(request) => request
+                .queries
+                .iter()
+                .try_for_each(|query| check_query_attribute_scope(query, config)),
+            ClientMessagePayload::SubscribeQuery(request) => request
+                .query
+                .as_ref()
+                .map_or(Ok(()), |query| check_query_attribute_scope(query, config)),
+            ClientMessagePayload::Subscribe(request) => {
+                if request.watch_entity_id.is_some() {
+                    return Err(
+                        "watch_entity_id is not allowed for an app scoped to attributes"
+                            .to_string(),
+                    );
+                }
+                match (&request.query, &request.attribute_id) {
+                    (Some(query), _) => check_query_attribute_scope(query, config),
+                    (None, Some(attribute_id)) if config.allows_attribute(attribute_id) => Ok(()),
+                    (None, Some(_)) => {
+                        Err("attribute_id filter is outside the app's scope".to_string())
+                    }
+                    (None, None) => Err(
+                        "subscriptions of an app scoped to attributes need a query or an \
+                         attribute_id filter"
+                            .to_string(),
+                    ),
+                }
+            }
+            ClientMessagePayload::Unsubscribe(_)
+            | ClientMessagePayload::Connect(_)
+            | ClientMessagePayload::FetchCursor(_)
+            | ClientMessagePayload::CloseCursor(_)
+            | ClientMessagePayload::DescribeSchema(_)
+            | ClientMessagePayload::BeginTxn(_)
+            | ClientMessagePayload::CommitTxn(_)
+            | ClientMessagePayload::AbortTxn(_) => Ok(()),
+        }
+    }
+}
+
 impl ProtoDeserializable<proto::ClientMessage> for ClientMessage {
     fn from_proto(proto_message: proto::ClientMessage) -> Result<Self, String> {
         if proto_message.request_id.is_none() {
@@ -32,12 +185,54 @@ impl ProtoDeserializable<proto::ClientMessage> for ClientMessage {
             Some(proto::client_message::Payload::Subscribe(request)) => {
                 ClientMessagePayload::Subscribe(request)
             }
+            Some(proto::client_message::Payload::SubscribeQuery(request)) => {
+                ClientMessagePayload::SubscribeQuery(request)
+            }
             Some(proto::client_message::Payload::Unsubscribe(request)) => {
                 ClientMessagePayload::Unsubscribe(request)
             }
             Some(proto::client_message::Payload::Connect(request)) => {
                 ClientMessagePayload::Connect(request)
             }
+            Some(proto::client_message::Payload::Contains(request)) => {
+                ClientMessagePayload::Contains(ContainsRequest::from_proto(request)?)
+            }
+            Some(proto::client_message::Payload::OpenCursor(request)) => {
+                let Some(query) = request.query else {
+                    return Err("OpenCursorRequest must have a query".to_string());
+                };
+                ClientMessagePayload::OpenCursor(query)
+            }
+            Some(proto::client_message::Payload::FetchCursor(request)) => {
+                ClientMessagePayload::FetchCursor(request)
+            }
+            Some(proto::client_message::Payload::CloseCursor(request)) => {
+                ClientMessagePayload::CloseCursor(request)
+            }
+            Some(proto::client_message::Payload::SetDefaults(request)) => {
+                ClientMessagePayload::SetDefaults(SetDefaultsRequest::from_proto(request)?)
+            }
+            Some(proto::client_message::Payload::Delete(request)) => {
+                ClientMessagePayload::Delete(DeleteRequest::from_proto(request)?)
+            }
+            Some(proto::client_message::Payload::ReadBatch(request)) => {
+                ClientMessagePayload::ReadBatch(request)
+            }
+            Some(proto::client_message::Payload::DescribeSchema(request)) => {
+                ClientMessagePayload::DescribeSchema(request)
+            }
+            Some(proto::client_message::Payload::BeginTxn(request)) => {
+                ClientMessagePayload::BeginTxn(request)
+            }
+            Some(proto::client_message::Payload::CommitTxn(request)) => {
+                ClientMessagePayload::CommitTxn(request)
+            }
+            Some(proto::client_message::Payload::AbortTxn(request)) => {
+                ClientMessagePayload::AbortTxn(request)
+            }
+            Some(proto::client_message::Payload::BulkLoad(request)) => {
+                ClientMessagePayload::BulkLoad(BulkLoadRequest::from_proto(request)?)
+            }
             None => return Err("Client message must have a payload".to_string()),
         };
         Ok(Self { payload })
@@ -0,0 +1,72 @@
+//! Existence check requests.
+
+use crate::proto;
+use crate::types::pending_triple::validate_proto_id;
+use crate::types::{AttributeId, EntityId, ProtoDeserializable};
+
+/// A validated request to check whether a triple exists.
+///
+/// # Invariants
+///
+/// - `entity_id` and `attribute_id` were exactly 16 bytes on the wire
+#[derive(Debug)]
+pub struct ContainsRequest {
+    pub entity_id: EntityId,
+    pub attribute_id: AttributeId,
+}
+
+impl ProtoDeserializable<proto::ContainsRequest> for ContainsRequest {
+    /// # Errors
+    ///
+    /// Returns an error if `entity_id` or `attribute_id` is missing or not
+    /// exactly 16 bytes.
+    fn from_proto(request: proto::ContainsRequest) -> Result<Self, String> {
+        let entity_id = EntityId(validate_proto_id(
+            request.entity_id,
+            "ContainsRequest",
+            "entity_id",
+        )?);
+        let attribute_id = AttributeId(validate_proto_id(
+            request.attribute_id,
+            "ContainsRequest",
+            "attribute_id",
+        )?);
+
+        Ok(Self {
+            entity_id,
+            attribute_id,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_contains_request_from_proto() {
+        let request = ContainsRequest::from_proto(proto::ContainsRequest {
+            entity_id: Some([1u8; 16].to_vec()),
+            attribute_id: Some([2u8; 16].to_vec()),
+        })
+        .expect("valid request");
+
+        assert_eq!(request.entity_id, EntityId([1u8; 16]));
+        assert_eq!(request.attribute_id, AttributeId([2u8; 16]));
+    }
+
+    #[test]
+    fn test_contains_request_rejects_bad_ids() {
+        let missing = ContainsRequest::from_proto(proto::ContainsRequest {
+            entity_id: None,
+            attribute_id: Some([2u8; 16].to_vec()),
+        });
+        assert!(missing.is_err());
+
+        let short = ContainsRequest::from_proto(proto::ContainsRequest {
+            entity_id: Some([1u8; 16].to_vec()),
+            attribute_id: Some(vec![1, 2, 3]),
+        });
+        assert!(short.is_err());
+    }
+}
@@ -0,0 +1,92 @@
+//! Delete requests.
+
+use crate::proto;
+use crate::types::pending_triple::validate_proto_id;
+use crate::types::{AttributeId, EntityId, ProtoDeserializable};
+
+/// A validated request to delete triples.
+///
+/// # Invariants
+///
+/// - Every key's `entity_id` and `attribute_id` were exactly 16 bytes on the
+///   wire
+#[derive(Debug)]
+pub struct DeleteRequest {
+    /// The triples to delete, in request order.
+    pub keys: Vec<(EntityId, AttributeId)>,
+}
+
+impl ProtoDeserializable<proto::DeleteRequest> for DeleteRequest {
+    /// # Errors
+    ///
+    /// Returns an error naming the first key whose `entity_id` or
+    /// `attribute_id` is missing or not exactly 16 bytes.
+    fn from_proto(request: proto::DeleteRequest) -> Result<Self, String> {
+        let keys = request
+            .keys
+            .into_iter()
+            .enumerate()
+            .map(|(index, key)| {
+                let entity_id = validate_proto_id(key.entity_id, "TripleKey", "entity_id");
+                let attribute_id = validate_proto_id(key.attribute_id, "TripleKey", "attribute_id");
+                match (entity_id, attribute_id) {
+                    (Ok(entity_id), Ok(attribute_id)) => {
+                        Ok((EntityId(entity_id), AttributeId(attribute_id)))
+                    }
+                    (Err(err), _) | (_, Err(err)) => {
+                        Err(format!("Failed to parse key #{index}: {err}"))
+                    }
+                }
+            })
+            .collect::<Result<_, _>>()?;
+
+        Ok(Self { keys })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_delete_request_from_proto() {
+        let request = DeleteRequest::from_proto(proto::DeleteRequest {
+            keys: vec![proto::TripleKey {
+                entity_id: Some([1u8; 16].to_vec()),
+                attribute_id: Some([2u8; 16].to_vec()),
+            }],
+        })
+        .expect("valid request");
+
+        assert_eq!(
+            request.keys,
+            vec![(EntityId([1u8; 16]), AttributeId([2u8; 16]))]
+        );
+    }
+
+    #[test]
+    fn test_delete_request_rejects_bad_ids() {
+        let result = DeleteRequest::from_proto(proto::DeleteRequest {
+            keys: vec![
+                proto::TripleKey {
+                    entity_id: Some([1u8; 16].to_vec()),
+                    attribute_id: Some([2u8; 16].to_vec()),
+                },
+                proto::TripleKey {
+                    entity_id: Some([1u8; 16].to_vec()),
+                    attribute_id: Some(vec![1, 2, 3]),
+                },
+            ],
+        });
+        let err = result.expect_err("short attribute ID");
+        assert!(err.contains("key #1"), "{err}");
+
+        let missing = DeleteRequest::from_proto(proto::DeleteRequest {
+            keys: vec![proto::TripleKey {
+                entity_id: None,
+                attribute_id: Some([2u8; 16].to_vec()),
+            }],
+        });
+        assert!(missing.is_err());
+    }
+}
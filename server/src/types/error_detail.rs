@@ -0,0 +1,89 @@
+//! Machine-readable error reasons, sent in `google.rpc.Status.details`.
+//!
+//! An error status carries at most one `ErrorDetail`, packed in a
+//! `google.protobuf.Any`. Clients match on its reason to decide whether to
+//! retry, resync or give up, instead of parsing the status message.
+
+use prost::Message;
+
+use crate::proto;
+
+/// Type URL of an `ErrorDetail` packed in a status's details.
+pub const ERROR_DETAIL_TYPE_URL: &str = "type.googleapis.com/protocol.ErrorDetail";
+
+/// Pack a reason as a status detail.
+#[must_use]
+pub fn pack_error_detail(reason: proto::error_detail::Reason) -> prost_types::Any {
+    prost_types::Any {
+        type_url: ERROR_DETAIL_TYPE_URL.to_owned(),
+        value: proto::ErrorDetail {
+            reason: Some(reason),
+        }
+        .encode_to_vec(),
+    }
+}
+
+/// Get the reason from a status's details, if it carries one.
+///
+/// Details of other types, and an `ErrorDetail` that fails to decode, are
+/// ignored.
+#[must_use]
+pub fn error_reason(status: &proto::google::rpc::Status) -> Option<proto::error_detail::Reason> {
+    status
+        .details
+        .iter()
+        .filter(|detail| detail.type_url == ERROR_DETAIL_TYPE_URL)
+        .find_map(|detail| {
+            proto::ErrorDetail::decode(detail.value.as_slice())
+                .ok()?
+                .reason
+        })
+}
+
+/// Add a reason to a response's status.
+///
+/// # Pre-conditions
+/// - The response has a status
+pub fn add_error_reason(response: &mut proto::ServerResponse, reason: proto::error_detail::Reason) {
+    debug_assert!(response.status.is_some(), "reason added without a status");
+    if let Some(status) = response.status.as_mut() {
+        status.details.push(pack_error_detail(reason));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_error_reason_round_trip() {
+        let reason = || {
+            proto::error_detail::Reason::SubscriptionLagged(proto::SubscriptionLagged {
+                missed: 12,
+            })
+        };
+        let status = proto::google::rpc::Status {
+            code: proto::google::rpc::Code::Unavailable.into(),
+            message: "lagged".to_owned(),
+            details: vec![
+                prost_types::Any {
+                    type_url: "type.googleapis.com/other.Detail".to_owned(),
+                    value: vec![1, 2, 3],
+                },
+                pack_error_detail(reason()),
+            ],
+        };
+
+        assert_eq!(error_reason(&status), Some(reason()));
+    }
+
+    #[test]
+    fn test_error_reason_absent() {
+        let status = proto::google::rpc::Status {
+            code: proto::google::rpc::Code::Internal.into(),
+            ..Default::default()
+        };
+
+        assert_eq!(error_reason(&status), None);
+    }
+}
@@ -12,7 +12,7 @@ use crate::types::{ProtoDeserializable, ProtoSerializable};
 /// - `physical_time`: 8 bytes (nanoseconds since Unix epoch)
 /// - `logical_counter`: 4 bytes
 /// - `node_id`: 4 bytes
-#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq, Hash)]
 pub struct HlcTimestamp {
     /// Physical time in nanoseconds since Unix epoch.
     pub physical_time: u64,
@@ -3,9 +3,166 @@
 //! This module provides newtype wrappers for entity and attribute IDs,
 //! ensuring type safety and providing convenient methods for creation
 //! and display.
+//!
+//! Both IDs are 16 bytes, so they map directly onto UUIDs. Besides the raw
+//! array constructor, each type can be built from a `u128` UUID, generated
+//! randomly as a version 4 UUID, or parsed from 32 hex digits or the hyphenated
+//! UUID form.
 
 use std::fmt;
 
+/// Number of hex digits in a 16-byte ID.
+const HEX_LENGTH: usize = 32;
+
+/// Length of the hyphenated UUID form (`8-4-4-4-12`).
+const UUID_STRING_LENGTH: usize = 36;
+
+/// Byte offsets in the hyphenated UUID form that must hold a hyphen.
+const UUID_HYPHEN_POSITIONS: [usize; 4] = [8, 13, 18, 23];
+
+/// Error returned when parsing an ID from a string.
+#[derive(Debug, PartialEq, Eq)]
+pub enum IdParseError {
+    /// The input is neither 32 hex digits nor a 36-character hyphenated UUID.
+    InvalidLength(usize),
+    /// The input contains a character that is not a hex digit.
+    InvalidCharacter(char),
+}
+
+impl fmt::Display for IdParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::InvalidLength(length) => write!(
+                f,
+                "ID must be {HEX_LENGTH} hex digits or a {UUID_STRING_LENGTH}-character UUID, got {length} characters"
+            ),
+            Self::InvalidCharacter(c) => write!(f, "ID contains non-hex character {c:?}"),
+        }
+    }
+}
+
+impl std::error::Error for IdParseError {}
+
+/// Parse 32 hex digits, optionally in hyphenated UUID form, into 16 bytes.
+fn parse_id(input: &str) -> Result<[u8; 16], IdParseError> {
+    let is_uuid_form = input.len() == UUID_STRING_LENGTH
+        && UUID_HYPHEN_POSITIONS
+            .iter()
+            .all(|&position| input.as_bytes()[position] == b'-');
+    if input.len() != HEX_LENGTH && !is_uuid_form {
+        return Err(IdParseError::InvalidLength(input.chars().count()));
+    }
+
+    let mut digits = input.chars().filter(|&c| !(is_uuid_form && c == '-'));
+    let mut bytes = [0u8; 16];
+    for byte in &mut bytes {
+        let mut value = 0u8;
+        for _ in 0..2 {
+            // The length checks above guarantee 32 digits remain
+            let c = digits.next().unwrap_or('0');
+            let digit = c.to_digit(16).ok_or(IdParseError::InvalidCharacter(c))?;
+            // to_digit(16) is always < 16
+            value = (value << 4) | u8::try_from(digit).unwrap_or(0);
+        }
+        *byte = value;
+    }
+    Ok(bytes)
+}
+
+/// Format 16 bytes as 32 lowercase hex digits.
+fn format_hex(bytes: &[u8; 16]) -> String {
+    use std::fmt::Write;
+    bytes
+        .iter()
+        .fold(String::with_capacity(HEX_LENGTH), |mut acc, b| {
+            let _ = write!(acc, "{b:02x}");
+            acc
+        })
+}
+
+/// Format 16 bytes as a hyphenated UUID string.
+fn format_uuid(bytes: &[u8; 16]) -> String {
+    let hex = format_hex(bytes);
+    format!(
+        "{}-{}-{}-{}-{}",
+        &hex[0..8],
+        &hex[8..12],
+        &hex[12..16],
+        &hex[16..20],
+        &hex[20..32]
+    )
+}
+
+/// Generate random bytes laid out as a version 4, variant 1 UUID.
+fn random_v4_bytes() -> [u8; 16] {
+    let mut bytes: [u8; 16] = rand::random();
+    bytes[6] = (bytes[6] & 0x0F) | 0x40;
+    bytes[8] = (bytes[8] & 0x3F) | 0x80;
+    bytes
+}
+
+/// Implement the UUID and hex constructors shared by both ID types.
+macro_rules! impl_id_encodings {
+    ($id:ident) => {
+        impl $id {
+            /// Create an ID from a UUID's 128-bit value (big-endian, as in RFC 9562).
+            #[must_use]
+            pub const fn from_uuid(uuid: u128) -> Self {
+                Self(uuid.to_be_bytes())
+            }
+
+            /// The ID as a UUID's 128-bit value.
+            #[must_use]
+            pub const fn to_uuid(&self) -> u128 {
+                u128::from_be_bytes(self.0)
+            }
+
+            /// Generate a random ID laid out as a version 4 UUID.
+            #[must_use]
+            pub fn new_v4() -> Self {
+                Self(random_v4_bytes())
+            }
+
+            /// Parse an ID from exactly 32 hex digits.
+            ///
+            /// # Errors
+            ///
+            /// Returns an error if the input is not 32 characters long or
+            /// contains a non-hex character.
+            pub fn from_hex(hex: &str) -> Result<Self, IdParseError> {
+                if hex.len() != HEX_LENGTH {
+                    return Err(IdParseError::InvalidLength(hex.chars().count()));
+                }
+                parse_id(hex).map(Self)
+            }
+
+            /// Format the ID as 32 lowercase hex digits.
+            #[must_use]
+            pub fn to_hex(&self) -> String {
+                format_hex(&self.0)
+            }
+
+            /// Format the ID as a hyphenated UUID string.
+            #[must_use]
+            pub fn to_uuid_string(&self) -> String {
+                format_uuid(&self.0)
+            }
+        }
+
+        impl TryFrom<&str> for $id {
+            type Error = IdParseError;
+
+            /// Parse an ID from 32 hex digits or a hyphenated UUID string.
+            fn try_from(input: &str) -> Result<Self, IdParseError> {
+                parse_id(input).map(Self)
+            }
+        }
+    };
+}
+
+impl_id_encodings!(EntityId);
+impl_id_encodings!(AttributeId);
+
 /// A unique identifier for an entity.
 ///
 /// Wraps a 16-byte array. The inner field is public to allow direct access
@@ -15,7 +172,9 @@ use std::fmt;
 ///
 /// - The ID is exactly 16 bytes.
 /// - The ID may contain any byte values.
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+///
+/// IDs order by their bytes, as entities are ordered in the primary index.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
 pub struct EntityId(pub [u8; 16]);
 
 impl EntityId {
@@ -215,4 +374,81 @@ mod tests {
         assert_eq!(id1, id2);
         assert_ne!(id1, id3);
     }
+
+    #[test]
+    fn test_entity_id_uuid_roundtrip() {
+        let uuid = 0x6717_0f2c_9a4b_4d2e_8c1f_0123_4567_89ab_u128;
+        let id = EntityId::from_uuid(uuid);
+        assert_eq!(id.0[0], 0x67);
+        assert_eq!(id.to_uuid(), uuid);
+    }
+
+    #[test]
+    fn test_entity_id_new_v4_sets_version_and_variant() {
+        let id = EntityId::new_v4();
+        assert_eq!(id.0[6] >> 4, 4);
+        assert_eq!(id.0[8] >> 6, 0b10);
+    }
+
+    #[test]
+    fn test_entity_id_new_v4_is_random() {
+        assert_ne!(EntityId::new_v4(), EntityId::new_v4());
+    }
+
+    #[test]
+    fn test_entity_id_hex_roundtrip() {
+        let id = EntityId::from_hex("67170f2c9a4b4d2e8c1f0123456789ab").expect("parse hex");
+        assert_eq!(id.to_hex(), "67170f2c9a4b4d2e8c1f0123456789ab");
+    }
+
+    #[test]
+    fn test_entity_id_from_hex_accepts_uppercase() {
+        let id = EntityId::from_hex("67170F2C9A4B4D2E8C1F0123456789AB").expect("parse hex");
+        assert_eq!(id.to_hex(), "67170f2c9a4b4d2e8c1f0123456789ab");
+    }
+
+    #[test]
+    fn test_entity_id_from_hex_rejects_uuid_form() {
+        let result = EntityId::from_hex("67170f2c-9a4b-4d2e-8c1f-0123456789ab");
+        assert_eq!(result, Err(IdParseError::InvalidLength(36)));
+    }
+
+    #[test]
+    fn test_entity_id_try_from_uuid_string() {
+        let id = EntityId::try_from("67170f2c-9a4b-4d2e-8c1f-0123456789ab").expect("parse uuid");
+        assert_eq!(id.to_uuid_string(), "67170f2c-9a4b-4d2e-8c1f-0123456789ab");
+        assert_eq!(id.to_uuid(), 0x6717_0f2c_9a4b_4d2e_8c1f_0123_4567_89ab_u128);
+    }
+
+    #[test]
+    fn test_entity_id_try_from_hex_string() {
+        let id = EntityId::try_from("00000000000000000000000000000001").expect("parse hex");
+        assert_eq!(id.to_uuid(), 1);
+    }
+
+    #[test]
+    fn test_entity_id_try_from_rejects_wrong_length() {
+        let result = EntityId::try_from("abcd");
+        assert_eq!(result, Err(IdParseError::InvalidLength(4)));
+    }
+
+    #[test]
+    fn test_entity_id_try_from_rejects_non_hex() {
+        let result = EntityId::try_from("zz000000000000000000000000000000");
+        assert_eq!(result, Err(IdParseError::InvalidCharacter('z')));
+    }
+
+    #[test]
+    fn test_entity_id_try_from_rejects_misplaced_hyphens() {
+        let result = EntityId::try_from("67170f2c9-a4b-4d2e-8c1f-0123456789ab");
+        assert_eq!(result, Err(IdParseError::InvalidLength(36)));
+    }
+
+    #[test]
+    fn test_attribute_id_uuid_and_hex() {
+        let id = AttributeId::from_uuid(0xff);
+        assert_eq!(id.to_hex(), "000000000000000000000000000000ff");
+        assert_eq!(AttributeId::try_from(id.to_uuid_string().as_str()), Ok(id));
+        assert_eq!(AttributeId::from_hex(&id.to_hex()), Ok(id));
+    }
 }
@@ -1,19 +1,33 @@
+pub mod bulk_load_request;
+pub mod cardinality;
 pub mod change_record;
 pub mod client_message;
+pub mod contains_request;
+pub mod delete_request;
+pub mod error_detail;
 pub mod hlc;
 pub mod ids;
 pub mod pending_triple;
+pub mod proto_json;
 pub mod query;
+pub mod set_defaults_request;
 pub mod triple_record;
 pub mod triple_update_request;
 pub mod triple_value;
+pub mod value_limits;
+pub mod value_range;
 
+pub use cardinality::Cardinality;
 pub use change_record::{ChangeNotification, ChangeRecord, ChangeType, ConnectionId};
 pub use hlc::HlcTimestamp;
-pub use ids::{AttributeId, EntityId};
+pub use ids::{AttributeId, EntityId, IdParseError};
 pub use pending_triple::{PendingTriple, PendingTripleData};
-pub use triple_record::{TripleError, TripleRecord, TxnId};
+pub use triple_record::{
+    RecordStorageSize, TXN_RANGE_PREFIX_SIZE, TripleError, TripleRecord, TxnId, TxnRange,
+};
 pub use triple_value::{TripleValue, TripleValueError, ValueType};
+pub use value_limits::ValueLimits;
+pub use value_range::ValueRange;
 
 pub trait ProtoDeserializable<T> {
     fn from_proto(proto_obj: T) -> Result<Self, String>
@@ -25,9 +25,39 @@ pub enum PendingTriple {
     Delete {
         entity_id: EntityId,
         attribute_id: AttributeId,
+        /// Key of the deleted value: 0 for a cardinality-one attribute.
+        value_hash: u64,
+        hlc: HlcTimestamp,
     },
 }
 
+impl PendingTriple {
+    /// The primary key the operation writes: entity, attribute, and value hash.
+    #[must_use]
+    pub const fn key(&self) -> (EntityId, AttributeId, u64) {
+        match self {
+            Self::Insert(record) | Self::Update(record) => {
+                (record.entity_id, record.attribute_id, record.value_hash())
+            }
+            Self::Delete {
+                entity_id,
+                attribute_id,
+                value_hash,
+                ..
+            } => (*entity_id, *attribute_id, *value_hash),
+        }
+    }
+
+    /// The HLC the operation is written with.
+    #[must_use]
+    pub const fn hlc(&self) -> HlcTimestamp {
+        match self {
+            Self::Insert(record) | Self::Update(record) => record.created_hlc,
+            Self::Delete { hlc, .. } => *hlc,
+        }
+    }
+}
+
 /// Raw triple data from proto, before Insert/Update determination.
 ///
 /// This struct holds validated data extracted from a `proto::Triple` message.
@@ -39,7 +69,8 @@ pub enum PendingTriple {
 /// - `entity_id` is exactly 16 bytes
 /// - `attribute_id` is exactly 16 bytes
 /// - `value` is a valid, non-null value
-/// - String values are non-empty and within `MAX_TRIPLE_STRING_VALUE_LENGTH`
+/// - String values are non-empty and within `MAX_TRIPLE_STRING_VALUE_LENGTH`;
+///   the configured `ValueLimits` are checked separately
 #[derive(Debug)]
 pub struct PendingTripleData {
     pub entity_id: EntityId,
@@ -110,7 +141,7 @@ impl ProtoDeserializable<proto::Triple> for PendingTripleData {
 /// Returns an error if:
 /// - The field is missing
 /// - The field is not exactly 16 bytes
-fn validate_proto_id(
+pub(crate) fn validate_proto_id(
     maybe_bytes: Option<Vec<u8>>,
     proto_name: &'static str,
     field_name: &'static str,
@@ -154,6 +185,7 @@ mod tests {
                 logical_counter: 0,
                 node_id: 1,
             }),
+            attribute_name: None,
         }
     }
 
@@ -183,6 +215,7 @@ mod tests {
                 logical_counter: 0,
                 node_id: 1,
             }),
+            attribute_name: None,
         };
         let result = PendingTripleData::from_proto(proto);
         assert!(result.is_err());
@@ -202,6 +235,7 @@ mod tests {
                 logical_counter: 0,
                 node_id: 1,
             }),
+            attribute_name: None,
         };
         let result = PendingTripleData::from_proto(proto);
         assert!(result.is_err());
@@ -219,6 +253,7 @@ mod tests {
                 logical_counter: 0,
                 node_id: 1,
             }),
+            attribute_name: None,
         };
         let result = PendingTripleData::from_proto(proto);
         assert!(result.is_err());
@@ -0,0 +1,424 @@
+//! JSON encoding of the sync protocol messages.
+//!
+//! Browser and debugging clients can send `ClientMessage`s as JSON text frames
+//! instead of binary protobuf. The encoding follows the proto3 JSON mapping
+//! closely enough for `protobuf-es` `toJson`/`fromJson` to interoperate:
+//!
+//! - Field names are lowerCamelCase
+//! - Oneof members appear as fields of the enclosing message
+//! - `bytes` fields are base64 strings
+//! - `uint64` fields are decimal strings on output; numbers are also accepted
+//! - Absent fields take their default value
+//!
+//! The serde derives themselves are added to the generated types in
+//! `build.rs`; this module holds the field codecs they reference.
+//!
+//! `google.rpc.Status.details` is not part of the JSON encoding.
+
+use crate::proto;
+
+/// Decode a `ClientMessage` from JSON text.
+///
+/// # Errors
+///
+/// Returns an error if the text is not a valid JSON `ClientMessage`.
+pub fn decode_client_message(text: &str) -> Result<proto::ClientMessage, serde_json::Error> {
+    serde_json::from_str(text)
+}
+
+/// Encode a `ServerMessage` as JSON text.
+///
+/// # Errors
+///
+/// Returns an error if serialization fails, which does not happen for
+/// messages built from the generated types.
+pub fn encode_server_message(message: &proto::ServerMessage) -> Result<String, serde_json::Error> {
+    serde_json::to_string(message)
+}
+
+const BASE64_ALPHABET: &[u8; 64] =
+    b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+/// Encode bytes as padded standard base64.
+fn encode_base64(bytes: &[u8]) -> String {
+    let mut out = String::with_capacity(bytes.len().div_ceil(3) * 4);
+    for chunk in bytes.chunks(3) {
+        let b0 = chunk[0];
+        let b1 = chunk.get(1).copied().unwrap_or(0);
+        let b2 = chunk.get(2).copied().unwrap_or(0);
+        let indices = [
+            b0 >> 2,
+            ((b0 & 0x03) << 4) | (b1 >> 4),
+            ((b1 & 0x0f) << 2) | (b2 >> 6),
+            b2 & 0x3f,
+        ];
+        for (position, index) in indices.iter().enumerate() {
+            if position <= chunk.len() {
+                out.push(char::from(BASE64_ALPHABET[usize::from(*index)]));
+            } else {
+                out.push('=');
+            }
+        }
+    }
+    out
+}
+
+/// Decode standard or URL-safe base64, with or without padding.
+fn decode_base64(text: &str) -> Result<Vec<u8>, String> {
+    let digits = text.trim_end_matches('=');
+    let mut out = Vec::with_capacity(digits.len() * 3 / 4);
+    let mut buffer: u32 = 0;
+    let mut bits = 0;
+    for character in digits.chars() {
+        let value = match character {
+            'A'..='Z' => u32::from(character) - u32::from('A'),
+            'a'..='z' => u32::from(character) - u32::from('a') + 26,
+            '0'..='9' => u32::from(character) - u32::from('0') + 52,
+            '+' | '-' => 62,
+            '/' | '_' => 63,
+            _ => return Err(format!("invalid base64 character '{character}'")),
+        };
+        buffer = (buffer << 6) | value;
+        bits += 6;
+        if bits >= 8 {
+            bits -= 8;
+            #[allow(clippy::cast_possible_truncation)] // Masked to a single byte
+            out.push((buffer >> bits) as u8);
+            buffer &= (1 << bits) - 1;
+        }
+    }
+    if bits >= 6 {
+        return Err("invalid base64 length".to_owned());
+    }
+    Ok(out)
+}
+
+/// Serde codec for `bytes` fields as base64 strings.
+pub mod base64 {
+    use serde::{Deserialize, Deserializer, Serializer, de::Error};
+
+    pub fn serialize<S: Serializer>(bytes: &[u8], serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&super::encode_base64(bytes))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<u8>, D::Error> {
+        let text = String::deserialize(deserializer)?;
+        super::decode_base64(&text).map_err(D::Error::custom)
+    }
+}
+
+/// Serde codec for `optional bytes` fields as base64 strings.
+pub mod optional_base64 {
+    use serde::{Deserialize, Deserializer, Serializer, de::Error};
+
+    #[allow(clippy::ref_option)] // Signature required by `#[serde(with)]`
+    pub fn serialize<S: Serializer>(
+        bytes: &Option<Vec<u8>>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        match bytes {
+            Some(bytes) => serializer.serialize_some(&super::encode_base64(bytes)),
+            None => serializer.serialize_none(),
+        }
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Option<Vec<u8>>, D::Error> {
+        Option::<String>::deserialize(deserializer)?
+            .map(|text| super::decode_base64(&text).map_err(D::Error::custom))
+            .transpose()
+    }
+}
+
+/// Serde codec for `repeated bytes` fields as arrays of base64 strings.
+pub mod repeated_base64 {
+    use serde::{Deserialize, Deserializer, Serializer, de::Error, ser::SerializeSeq};
+
+    pub fn serialize<S: Serializer>(values: &[Vec<u8>], serializer: S) -> Result<S::Ok, S::Error> {
+        let mut seq = serializer.serialize_seq(Some(values.len()))?;
+        for bytes in values {
+            seq.serialize_element(&super::encode_base64(bytes))?;
+        }
+        seq.end()
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Vec<Vec<u8>>, D::Error> {
+        Vec::<String>::deserialize(deserializer)?
+            .iter()
+            .map(|text| super::decode_base64(text).map_err(D::Error::custom))
+            .collect()
+    }
+}
+
+/// Serde codec for `uint64` fields as decimal strings.
+///
+/// Accepts a JSON number as well, since proto3 JSON parsers must.
+pub mod uint64 {
+    use serde::{Deserialize, Deserializer, Serializer, de::Error};
+
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum NumberOrString {
+        Number(u64),
+        String(String),
+    }
+
+    #[allow(clippy::trivially_copy_pass_by_ref)] // Signature required by `#[serde(with)]`
+    pub fn serialize<S: Serializer>(value: &u64, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&value.to_string())
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<u64, D::Error> {
+        match NumberOrString::deserialize(deserializer)? {
+            NumberOrString::Number(value) => Ok(value),
+            NumberOrString::String(text) => text.parse().map_err(D::Error::custom),
+        }
+    }
+}
+
+/// Serde codec for `optional uint64` fields as decimal strings.
+pub mod optional_uint64 {
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    #[allow(clippy::ref_option)] // Signature required by `#[serde(with)]`
+    pub fn serialize<S: Serializer>(value: &Option<u64>, serializer: S) -> Result<S::Ok, S::Error> {
+        match value {
+            Some(value) => serializer.serialize_some(&value.to_string()),
+            None => serializer.serialize_none(),
+        }
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Option<u64>, D::Error> {
+        #[derive(Deserialize)]
+        struct Wrapper(#[serde(with = "super::uint64")] u64);
+
+        Ok(Option::<Wrapper>::deserialize(deserializer)?.map(|Wrapper(value)| value))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_base64_roundtrip() {
+        for length in 0..20u8 {
+            let bytes: Vec<u8> = (0..length).map(|i| i.wrapping_mul(37)).collect();
+            let encoded = encode_base64(&bytes);
+            assert_eq!(encoded.len() % 4, 0);
+            assert_eq!(decode_base64(&encoded).expect("decode"), bytes);
+        }
+    }
+
+    #[test]
+    fn test_base64_known_values() {
+        assert_eq!(encode_base64(b"foobar"), "Zm9vYmFy");
+        assert_eq!(encode_base64(b"fo"), "Zm8=");
+        assert_eq!(decode_base64("Zm8").expect("unpadded"), b"fo");
+        assert_eq!(decode_base64("-_8=").expect("url-safe"), [0xfb, 0xff]);
+        assert!(decode_base64("Zm9v!").is_err());
+        assert!(decode_base64("Z").is_err());
+    }
+
+    #[test]
+    fn test_decode_client_message_query() {
+        let text = r#"{
+            "requestId": 7,
+            "query": {
+                "find": [{"label": "name"}],
+                "where": [{
+                    "entityVariable": {"label": "e"},
+                    "attributeId": "AgICAgICAgICAgICAgICAg==",
+                    "valueVariable": {"label": "name"}
+                }]
+            }
+        }"#;
+
+        let message = decode_client_message(text).expect("decode");
+
+        assert_eq!(message.request_id, Some(7));
+        let Some(proto::client_message::Payload::Query(query)) = message.payload else {
+            panic!("expected a query payload");
+        };
+        assert_eq!(query.find[0].label.as_deref(), Some("name"));
+        assert_eq!(
+            query.r#where[0].attribute,
+            Some(proto::query_pattern::Attribute::AttributeId(vec![2u8; 16]))
+        );
+        assert!(query.optional.is_empty());
+    }
+
+    #[test]
+    fn test_entity_id_sets_are_base64_strings() {
+        let text = r#"{
+            "requestId": 1,
+            "query": {
+                "find": [{"label": "e"}],
+                "where": [{
+                    "entityIds": {
+                        "entityIds": ["AQEBAQEBAQEBAQEBAQEBAQ==", "AgICAgICAgICAgICAgICAg=="],
+                        "variable": {"label": "e"}
+                    },
+                    "attributeId": "AwMDAwMDAwMDAwMDAwMDAw==",
+                    "valueVariable": {"label": "v"}
+                }]
+            }
+        }"#;
+
+        let message = decode_client_message(text).expect("decode");
+
+        let Some(proto::client_message::Payload::Query(query)) = message.payload else {
+            panic!("expected a query payload");
+        };
+        let Some(proto::query_pattern::Entity::EntityIds(set)) = &query.r#where[0].entity else {
+            panic!("expected an entity ID set");
+        };
+        assert_eq!(set.entity_ids, vec![vec![1u8; 16], vec![2u8; 16]]);
+        let encoded = serde_json::to_string(set).expect("encode");
+        assert!(encoded.starts_with(
+            r#"{"entityIds":["AQEBAQEBAQEBAQEBAQEBAQ==","AgICAgICAgICAgICAgICAg=="]"#
+        ));
+    }
+
+    #[test]
+    fn test_bytes_values_are_base64_strings() {
+        let value = proto::TripleValue {
+            value: Some(proto::triple_value::Value::Bytes(vec![1, 2, 3])),
+        };
+
+        let encoded = serde_json::to_string(&value).expect("encode");
+        assert_eq!(encoded, r#"{"bytes":"AQID"}"#);
+        let decoded: proto::TripleValue = serde_json::from_str(&encoded).expect("decode");
+        assert_eq!(decoded, value);
+    }
+
+    #[test]
+    fn test_decode_client_message_hlc_accepts_number_and_string() {
+        let text = r#"{"tripleUpdateRequest": {"triples": [
+            {"hlc": {"physicalTimeMs": "1700000000000", "logicalCounter": 1, "nodeId": 2}},
+            {"hlc": {"physicalTimeMs": 1700000000001}}
+        ]}}"#;
+
+        let message = decode_client_message(text).expect("decode");
+
+        let Some(proto::client_message::Payload::TripleUpdateRequest(request)) = message.payload
+        else {
+            panic!("expected an update payload");
+        };
+        let times: Vec<u64> = request
+            .triples
+            .iter()
+            .filter_map(|triple| triple.hlc.as_ref().map(|hlc| hlc.physical_time_ms))
+            .collect();
+        assert_eq!(times, vec![1_700_000_000_000, 1_700_000_000_001]);
+    }
+
+    #[test]
+    fn test_decode_client_message_rejects_invalid_json() {
+        assert!(decode_client_message("not json").is_err());
+        assert!(decode_client_message(r#"{"requestId": "seven"}"#).is_err());
+    }
+
+    #[test]
+    fn test_encode_server_message_response() {
+        let message = proto::ServerMessage {
+            payload: Some(proto::server_message::Payload::Response(
+                proto::ServerResponse {
+                    request_id: Some(3),
+                    status: Some(proto::google::rpc::Status {
+                        code: 0,
+                        message: String::new(),
+                        details: vec![],
+                    }),
+                    columns: vec!["name".to_owned()],
+                    rows: vec![proto::QueryResultRow {
+                        values: vec![proto::QueryResultValue {
+                            value: Some(proto::query_result_value::Value::TripleValue(
+                                proto::TripleValue {
+                                    value: Some(proto::triple_value::Value::String(
+                                        "Alice".to_owned(),
+                                    )),
+                                },
+                            )),
+                            is_undefined: false,
+                        }],
+                    }],
+                    triples: vec![],
+                    exists: None,
+                    cursor_id: None,
+                    cursor_exhausted: None,
+                    write_results: vec![],
+                    arrow_ipc: None,
+                    delete_results: vec![],
+                    batch_results: vec![],
+                    attributes: vec![],
+                    schema_truncated: None,
+                    triples_loaded: None,
+                },
+            )),
+        };
+
+        let text = encode_server_message(&message).expect("encode");
+
+        assert_eq!(
+            text,
+            r#"{"response":{"requestId":3,"status":{"code":0,"message":""},"triples":[],"columns":["name"],"rows":[{"values":[{"isUndefined":false,"tripleValue":{"string":"Alice"}}]}],"exists":null,"cursorId":null,"cursorExhausted":null,"writeResults":[],"arrowIpc":null,"deleteResults":[],"batchResults":[],"attributes":[],"schemaTruncated":null,"triplesLoaded":null}}"#
+        );
+    }
+
+    #[test]
+    fn test_cursor_ids_are_decimal_strings() {
+        let message = decode_client_message(
+            r#"{"requestId": 1, "fetchCursor": {"cursorId": "18446744073709551615", "maxRows": 10}}"#,
+        )
+        .expect("decode");
+        let Some(proto::client_message::Payload::FetchCursor(request)) = message.payload else {
+            panic!("expected a fetch cursor payload");
+        };
+        assert_eq!(request.cursor_id, u64::MAX);
+
+        let text = encode_server_message(&proto::ServerMessage {
+            payload: Some(proto::server_message::Payload::Response(
+                proto::ServerResponse {
+                    cursor_id: Some(42),
+                    ..Default::default()
+                },
+            )),
+        })
+        .expect("encode");
+        assert!(text.contains(r#""cursorId":"42""#));
+    }
+
+    #[test]
+    fn test_uint64_values_past_double_precision_are_exact() {
+        // 2^53 + 1, the first integer an f64 cannot hold
+        let message = decode_client_message(
+            r#"{"requestId": 1, "tripleUpdateRequest": {"triples": [{
+                "hlc": {"physicalTimeMs": "9007199254740993", "logicalCounter": 0, "nodeId": 1}
+            }]}}"#,
+        )
+        .expect("decode");
+        let Some(proto::client_message::Payload::TripleUpdateRequest(request)) = message.payload
+        else {
+            panic!("expected a triple update payload");
+        };
+        let hlc = request.triples[0].hlc.as_ref().expect("hlc");
+        assert_eq!(hlc.physical_time_ms, 9_007_199_254_740_993);
+
+        let text = encode_server_message(&proto::ServerMessage {
+            payload: Some(proto::server_message::Payload::Response(
+                proto::ServerResponse {
+                    triples_loaded: Some(u64::MAX),
+                    ..Default::default()
+                },
+            )),
+        })
+        .expect("encode");
+        assert!(text.contains(r#""triplesLoaded":"18446744073709551615""#));
+    }
+}
@@ -3,9 +3,16 @@
 //! This module implements `ProtoDeserializable` and `ProtoSerializable` for query types.
 
 use crate::{
+    auth::AppConfig,
     proto,
-    query::{Datom, EntityId, Pattern, PatternElement, Query, QueryResult, Value, Variable},
-    types::{AttributeId, ProtoDeserializable, ProtoSerializable},
+    query::{
+        Aggregate, AggregateFunction, Datom, Direction, EntityId, Nulls, OrderBy, Pattern,
+        PatternElement, Query, QueryBuildError, QueryResult, Value, Variable,
+    },
+    types::{
+        AttributeId, ProtoDeserializable, ProtoSerializable, ValueLimits,
+        value_limits::ValueLimitViolation,
+    },
 };
 
 /// Query response containing columns and rows for proto serialization.
@@ -41,10 +48,105 @@ impl ProtoDeserializable<&proto::QueryRequest> for Query {
             query = query.where_not(proto_pattern_to_query(pattern)?);
         }
 
+        if let Some(limit) = request.limit {
+            query = query.limit(usize::try_from(limit).unwrap_or(usize::MAX));
+        }
+        if let Some(offset) = request.offset {
+            query = query.offset(usize::try_from(offset).unwrap_or(usize::MAX));
+        }
+        if request.distinct {
+            query = query.distinct();
+        }
+
+        for order in &request.order_by {
+            let variable = order
+                .variable
+                .as_ref()
+                .ok_or("QueryOrderBy proto did not contain a variable")?;
+            let direction = match order.direction() {
+                proto::SortDirection::Ascending => Direction::Ascending,
+                proto::SortDirection::Descending => Direction::Descending,
+            };
+            let nulls = match order.nulls() {
+                proto::NullsOrder::First => Nulls::First,
+                proto::NullsOrder::Last => Nulls::Last,
+            };
+            query =
+                query.order_by_with_nulls(proto_variable_to_query(variable).name, direction, nulls);
+        }
+
+        for aggregate in &request.aggregates {
+            let variable = aggregate
+                .variable
+                .as_ref()
+                .ok_or("QueryAggregate proto did not contain a variable")?;
+            let function = match aggregate.function() {
+                proto::AggregateFunction::Unspecified => {
+                    return Err("QueryAggregate proto did not specify a function".to_owned());
+                }
+                proto::AggregateFunction::Count => AggregateFunction::Count,
+                proto::AggregateFunction::Sum => AggregateFunction::Sum,
+                proto::AggregateFunction::Min => AggregateFunction::Min,
+                proto::AggregateFunction::Max => AggregateFunction::Max,
+                proto::AggregateFunction::Avg => AggregateFunction::Avg,
+            };
+            query = query.find_aggregate(function, proto_variable_to_query(variable).name);
+        }
+        for variable in &request.group_by {
+            query = query.group_by(proto_variable_to_query(variable).name);
+        }
+        if query.is_aggregate() && !query.find.is_empty() {
+            return Err(
+                "find variables cannot be returned alongside groups or aggregates".to_owned(),
+            );
+        }
+
         Ok(query)
     }
 }
 
+impl TryFrom<&Query> for proto::QueryRequest {
+    type Error = QueryBuildError;
+
+    /// Build the wire request for a query.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the query fails `Query::validate`, has filters, or
+    /// places an element where the protocol cannot carry it.
+    fn try_from(query: &Query) -> Result<Self, QueryBuildError> {
+        query.validate()?;
+        if let Some(filter) = query.filters.first() {
+            return Err(QueryBuildError::FilterNotSerializable(
+                filter.selector.name.as_str().to_owned(),
+            ));
+        }
+
+        let patterns_to_proto = |patterns: &[Pattern]| {
+            patterns
+                .iter()
+                .map(query_pattern_to_proto)
+                .collect::<Result<Vec<_>, _>>()
+        };
+
+        Ok(Self {
+            find: query.find.iter().map(query_variable_to_proto).collect(),
+            r#where: patterns_to_proto(&query.where_patterns)?,
+            optional: patterns_to_proto(&query.optional_patterns)?,
+            where_not: patterns_to_proto(&query.where_not_patterns)?,
+            arrow_ipc: false,
+            limit: query
+                .limit
+                .map(|limit| u32::try_from(limit).unwrap_or(u32::MAX)),
+            offset: (query.offset > 0).then(|| u32::try_from(query.offset).unwrap_or(u32::MAX)),
+            order_by: query.order_by.iter().map(order_by_to_proto).collect(),
+            aggregates: query.aggregates.iter().map(aggregate_to_proto).collect(),
+            group_by: query.group_by.iter().map(query_variable_to_proto).collect(),
+            distinct: query.distinct,
+        })
+    }
+}
+
 impl ProtoSerializable<QueryResponse> for QueryResult {
     fn to_proto(self) -> QueryResponse {
         let columns = self.columns.iter().map(ToOwned::to_owned).collect();
@@ -64,11 +166,184 @@ impl ProtoSerializable<QueryResponse> for QueryResult {
     }
 }
 
+/// Check every string value in a query's patterns against the configured
+/// limits.
+///
+/// A string that could never have been written can never match, so it is
+/// rejected rather than silently matching nothing.
+///
+/// # Errors
+///
+/// Returns an error naming the first pattern whose string value is empty or
+/// exceeds `limits`.
+pub fn check_query_value_limits(
+    request: &proto::QueryRequest,
+    limits: &ValueLimits,
+) -> Result<(), ValueLimitViolation> {
+    let clauses = [
+        ("where", &request.r#where),
+        ("optional", &request.optional),
+        ("where_not", &request.where_not),
+    ];
+    for (clause, patterns) in clauses {
+        for (index, pattern) in patterns.iter().enumerate() {
+            if let Some(proto::query_pattern::ValueGroup::Value(proto::TripleValue {
+                value: Some(proto::triple_value::Value::String(s)),
+            })) = &pattern.value_group
+            {
+                limits
+                    .check_string(s)
+                    .map_err(|error| ValueLimitViolation {
+                        location: format!("{clause} pattern #{index}"),
+                        error,
+                    })?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Check that every pattern in a query names an attribute the app may access.
+///
+/// A pattern with a variable attribute can match any attribute, so it is
+/// outside every restricted scope. Patterns with no attribute are left for
+/// query validation to reject.
+///
+/// # Errors
+///
+/// Returns an error naming the first pattern that reaches outside `config`'s
+/// attribute scope.
+pub fn check_query_attribute_scope(
+    request: &proto::QueryRequest,
+    config: &AppConfig,
+) -> Result<(), String> {
+    if config.allowed_attribute_prefixes().is_none() {
+        return Ok(());
+    }
+    let clauses = [
+        ("where", &request.r#where),
+        ("optional", &request.optional),
+        ("where_not", &request.where_not),
+    ];
+    for (clause, patterns) in clauses {
+        for (index, pattern) in patterns.iter().enumerate() {
+            match &pattern.attribute {
+                Some(proto::query_pattern::Attribute::AttributeId(bytes)) => {
+                    let attribute_id = AttributeId(bytes_to_id(bytes));
+                    if !config.allows_attribute(&attribute_id.0) {
+                        return Err(format!(
+                            "{clause} pattern #{index} references attribute {attribute_id} \
+                             outside the app's scope"
+                        ));
+                    }
+                }
+                Some(proto::query_pattern::Attribute::AttributeVariable(_)) => {
+                    return Err(format!(
+                        "{clause} pattern #{index} has a variable attribute, which the app's \
+                         scope does not allow"
+                    ));
+                }
+                Some(proto::query_pattern::Attribute::AttributeName(_)) | None => {}
+            }
+        }
+    }
+    Ok(())
+}
+
 /// Convert a proto `QueryPatternVariable` to an internal `Variable`.
 fn proto_variable_to_query(var: &proto::QueryPatternVariable) -> Variable {
     Variable::new(var.label.as_deref().unwrap_or(""))
 }
 
+/// Convert an internal `Variable` to a proto `QueryPatternVariable`.
+fn query_variable_to_proto(var: &Variable) -> proto::QueryPatternVariable {
+    proto::QueryPatternVariable {
+        label: Some(var.name.as_str().to_owned()),
+    }
+}
+
+/// Convert an internal `Aggregate` to a proto `QueryAggregate`.
+fn aggregate_to_proto(aggregate: &Aggregate) -> proto::QueryAggregate {
+    let function = match aggregate.function {
+        AggregateFunction::Count => proto::AggregateFunction::Count,
+        AggregateFunction::Sum => proto::AggregateFunction::Sum,
+        AggregateFunction::Min => proto::AggregateFunction::Min,
+        AggregateFunction::Max => proto::AggregateFunction::Max,
+        AggregateFunction::Avg => proto::AggregateFunction::Avg,
+    };
+    proto::QueryAggregate {
+        function: function.into(),
+        variable: Some(query_variable_to_proto(&aggregate.variable)),
+    }
+}
+
+/// Convert an internal `OrderBy` to a proto `QueryOrderBy`.
+fn order_by_to_proto(order: &OrderBy) -> proto::QueryOrderBy {
+    let direction = match order.direction {
+        Direction::Ascending => proto::SortDirection::Ascending,
+        Direction::Descending => proto::SortDirection::Descending,
+    };
+    let nulls = match order.nulls {
+        Nulls::First => proto::NullsOrder::First,
+        Nulls::Last => proto::NullsOrder::Last,
+    };
+    proto::QueryOrderBy {
+        variable: Some(query_variable_to_proto(&order.variable)),
+        direction: direction.into(),
+        nulls: nulls.into(),
+    }
+}
+
+/// Convert an internal `Pattern` to a proto `QueryPattern`.
+fn query_pattern_to_proto(pattern: &Pattern) -> Result<proto::QueryPattern, QueryBuildError> {
+    let invalid =
+        |position: &'static str, element: &PatternElement| QueryBuildError::InvalidPatternElement {
+            position,
+            element: element.to_string(),
+        };
+
+    let entity = match &pattern.entity {
+        PatternElement::Entity(id) => proto::query_pattern::Entity::EntityId(id.0.to_vec()),
+        PatternElement::EntitySet { ids, variable } => {
+            proto::query_pattern::Entity::EntityIds(proto::EntityIdSet {
+                entity_ids: ids.iter().map(|id| id.0.to_vec()).collect(),
+                variable: variable.as_ref().map(query_variable_to_proto),
+            })
+        }
+        PatternElement::Variable(var) => {
+            proto::query_pattern::Entity::EntityVariable(query_variable_to_proto(var))
+        }
+        other => return Err(invalid("entity", other)),
+    };
+
+    let attribute = match &pattern.field {
+        PatternElement::Field(id) => proto::query_pattern::Attribute::AttributeId(id.0.to_vec()),
+        PatternElement::Variable(var) => {
+            proto::query_pattern::Attribute::AttributeVariable(query_variable_to_proto(var))
+        }
+        other => return Err(invalid("attribute", other)),
+    };
+
+    let value_group = match &pattern.value {
+        // Refs have no wire representation yet
+        PatternElement::Value(Value::Ref(_)) => return Err(invalid("value", &pattern.value)),
+        PatternElement::Value(v) => {
+            proto::query_pattern::ValueGroup::Value(value_to_proto_triple_value(v))
+        }
+        PatternElement::Variable(var) => {
+            proto::query_pattern::ValueGroup::ValueVariable(query_variable_to_proto(var))
+        }
+        PatternElement::Any => proto::query_pattern::ValueGroup::AnyValue(proto::AnyValue {}),
+        other => return Err(invalid("value", other)),
+    };
+
+    Ok(proto::QueryPattern {
+        entity: Some(entity),
+        attribute: Some(attribute),
+        value_group: Some(value_group),
+    })
+}
+
 /// Convert bytes to a 16-byte array (zero-padded if needed).
 fn bytes_to_id(bytes: &[u8]) -> [u8; 16] {
     let mut arr = [0u8; 16];
@@ -84,6 +359,14 @@ fn proto_pattern_to_query(pattern: &proto::QueryPattern) -> Result<Pattern, Stri
         Some(proto::query_pattern::Entity::EntityId(bytes)) => {
             PatternElement::Entity(EntityId(bytes_to_id(bytes)))
         }
+        Some(proto::query_pattern::Entity::EntityIds(set)) => PatternElement::EntitySet {
+            ids: set
+                .entity_ids
+                .iter()
+                .map(|bytes| EntityId(bytes_to_id(bytes)))
+                .collect(),
+            variable: set.variable.as_ref().map(proto_variable_to_query),
+        },
         Some(proto::query_pattern::Entity::EntityVariable(var)) => {
             PatternElement::Variable(proto_variable_to_query(var))
         }
@@ -98,6 +381,9 @@ fn proto_pattern_to_query(pattern: &proto::QueryPattern) -> Result<Pattern, Stri
         Some(proto::query_pattern::Attribute::AttributeVariable(var)) => {
             PatternElement::Variable(proto_variable_to_query(var))
         }
+        Some(proto::query_pattern::Attribute::AttributeName(name)) => {
+            return Err(format!("Attribute name '{name}' was not resolved to an ID"));
+        }
         None => return Err("Pattern missing attribute".to_owned()),
     };
 
@@ -109,6 +395,7 @@ fn proto_pattern_to_query(pattern: &proto::QueryPattern) -> Result<Pattern, Stri
         Some(proto::query_pattern::ValueGroup::ValueVariable(var)) => {
             PatternElement::Variable(proto_variable_to_query(var))
         }
+        Some(proto::query_pattern::ValueGroup::AnyValue(_)) => PatternElement::Any,
         None => return Err("Pattern missing value".to_owned()),
     };
 
@@ -121,12 +408,13 @@ fn proto_triple_value_to_query(v: &proto::TripleValue) -> Value {
         Some(proto::triple_value::Value::String(s)) => Value::String(s.to_owned()),
         Some(proto::triple_value::Value::Number(n)) => Value::Number(*n),
         Some(proto::triple_value::Value::Boolean(b)) => Value::Boolean(*b),
+        Some(proto::triple_value::Value::Bytes(bytes)) => Value::Bytes(bytes.as_slice().to_vec()),
         None => Value::Null,
     }
 }
 
 /// Convert a 16-byte ID to a string (UTF-8, trimming null bytes).
-fn id_to_string(id: &[u8; 16]) -> String {
+pub(crate) fn id_to_string(id: &[u8; 16]) -> String {
     std::str::from_utf8(id).map_or_else(
         |_| {
             // Fallback: hex encoding (manual)
@@ -177,9 +465,261 @@ fn value_to_proto_triple_value(v: &Value) -> proto::TripleValue {
         Value::String(s) => proto::TripleValue {
             value: Some(proto::triple_value::Value::String(s.to_owned())),
         },
+        Value::Bytes(bytes) => proto::TripleValue {
+            value: Some(proto::triple_value::Value::Bytes(bytes.as_slice().to_vec())),
+        },
         Value::Ref(id) => proto::TripleValue {
             // Store ref as a string ID
             value: Some(proto::triple_value::Value::String(id_to_string(&id.0))),
         },
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::query::Filter;
+
+    fn name_query() -> Query {
+        Query::new()
+            .find("e")
+            .find("name")
+            .where_pattern(Pattern::new(
+                PatternElement::var("e"),
+                PatternElement::field("name"),
+                PatternElement::var("name"),
+            ))
+    }
+
+    #[test]
+    fn test_query_to_proto_roundtrip() {
+        let query = name_query()
+            .optional(Pattern::new(
+                PatternElement::var("e"),
+                PatternElement::field("age"),
+                PatternElement::var("age"),
+            ))
+            .where_not(Pattern::new(
+                PatternElement::var("e"),
+                PatternElement::field("banned"),
+                PatternElement::Value(Value::boolean(true)),
+            ));
+
+        let request = proto::QueryRequest::try_from(&query).expect("convert");
+        assert_eq!(request.find.len(), 2);
+        assert_eq!(request.r#where.len(), 1);
+        assert_eq!(request.optional.len(), 1);
+        assert_eq!(request.where_not.len(), 1);
+
+        let decoded = Query::from_proto(&request).expect("decode");
+        assert_eq!(decoded.to_string(), query.to_string());
+    }
+
+    #[test]
+    fn test_limit_and_offset_roundtrip() {
+        let query = name_query().limit(5).offset(10);
+
+        let request = proto::QueryRequest::try_from(&query).expect("convert");
+        assert_eq!(request.limit, Some(5));
+        assert_eq!(request.offset, Some(10));
+
+        let decoded = Query::from_proto(&request).expect("decode");
+        assert_eq!(decoded.limit, Some(5));
+        assert_eq!(decoded.offset, 10);
+
+        let request = proto::QueryRequest::try_from(&name_query()).expect("convert");
+        assert_eq!(request.limit, None);
+        assert_eq!(request.offset, None);
+    }
+
+    #[test]
+    fn test_distinct_roundtrip() {
+        let request = proto::QueryRequest::try_from(&name_query().distinct()).expect("convert");
+        assert!(request.distinct);
+        assert!(Query::from_proto(&request).expect("decode").distinct);
+
+        let request = proto::QueryRequest::try_from(&name_query()).expect("convert");
+        assert!(!Query::from_proto(&request).expect("decode").distinct);
+    }
+
+    #[test]
+    fn test_order_by_roundtrip() {
+        let query = name_query()
+            .order_by("name", Direction::Descending)
+            .order_by_with_nulls("e", Direction::Ascending, Nulls::Last);
+
+        let request = proto::QueryRequest::try_from(&query).expect("convert");
+        assert_eq!(request.order_by.len(), 2);
+        assert_eq!(
+            request.order_by[0].direction(),
+            proto::SortDirection::Descending
+        );
+        assert_eq!(request.order_by[1].nulls(), proto::NullsOrder::Last);
+
+        let decoded = Query::from_proto(&request).expect("decode");
+        assert_eq!(decoded.order_by, query.order_by);
+    }
+
+    #[test]
+    fn test_aggregate_roundtrip() {
+        let query = Query::new()
+            .group_by("name")
+            .find_count("e")
+            .find_max("name")
+            .where_pattern(Pattern::new(
+                PatternElement::var("e"),
+                PatternElement::field("name"),
+                PatternElement::var("name"),
+            ));
+
+        let mut request = proto::QueryRequest::try_from(&query).expect("convert");
+        assert!(request.find.is_empty());
+        assert_eq!(
+            request.aggregates[1].function(),
+            proto::AggregateFunction::Max
+        );
+
+        let decoded = Query::from_proto(&request).expect("decode");
+        assert_eq!(decoded.aggregates, query.aggregates);
+        assert_eq!(decoded.group_by, query.group_by);
+
+        request.aggregates[0].function = proto::AggregateFunction::Unspecified.into();
+        assert!(Query::from_proto(&request).is_err());
+        request.aggregates[0].function = proto::AggregateFunction::Count.into();
+        request.find.push(proto::QueryPatternVariable {
+            label: Some("e".to_owned()),
+        });
+        assert!(Query::from_proto(&request).is_err());
+    }
+
+    #[test]
+    fn test_presence_pattern_roundtrip() {
+        let query = Query::new()
+            .find("e")
+            .has(PatternElement::var("e"), PatternElement::field("email"))
+            .lacks(PatternElement::var("e"), PatternElement::field("verified"));
+
+        let request = proto::QueryRequest::try_from(&query).expect("convert");
+        assert_eq!(
+            request.where_not[0].value_group,
+            Some(proto::query_pattern::ValueGroup::AnyValue(
+                proto::AnyValue {}
+            ))
+        );
+
+        let decoded = Query::from_proto(&request).expect("decode");
+        assert_eq!(decoded.where_patterns, query.where_patterns);
+        assert_eq!(decoded.where_not_patterns, query.where_not_patterns);
+    }
+
+    #[test]
+    fn test_entity_set_roundtrip() {
+        let query = Query::new()
+            .find("e")
+            .find("name")
+            .where_pattern(Pattern::new(
+                PatternElement::entities("e", &["user2", "user1", "user2"]),
+                PatternElement::field("name"),
+                PatternElement::var("name"),
+            ));
+
+        let request = proto::QueryRequest::try_from(&query).expect("convert");
+        let Some(proto::query_pattern::Entity::EntityIds(set)) = &request.r#where[0].entity else {
+            panic!("expected an entity set");
+        };
+        // Duplicates are dropped and the IDs sorted
+        assert_eq!(
+            set.entity_ids,
+            vec![
+                EntityId::from_string("user1").0.to_vec(),
+                EntityId::from_string("user2").0.to_vec()
+            ]
+        );
+
+        let decoded = Query::from_proto(&request).expect("decode");
+        assert_eq!(decoded.where_patterns, query.where_patterns);
+        assert_eq!(
+            decoded.to_string(),
+            "[:find ?e ?name :where [?e in {#user1 #user2} :name ?name]]"
+        );
+    }
+
+    #[test]
+    fn test_query_to_proto_rejects_filters() {
+        let query = name_query().filter(Filter::new(Variable::new("name"), |_| true));
+
+        let result = proto::QueryRequest::try_from(&query);
+
+        assert_eq!(
+            result,
+            Err(QueryBuildError::FilterNotSerializable("name".to_owned()))
+        );
+    }
+
+    #[test]
+    fn test_query_to_proto_rejects_value_in_entity_position() {
+        let query = Query::new().find("x").where_pattern(Pattern::new(
+            PatternElement::number(1.0),
+            PatternElement::field("name"),
+            PatternElement::var("x"),
+        ));
+
+        let result = proto::QueryRequest::try_from(&query);
+
+        assert!(matches!(
+            result,
+            Err(QueryBuildError::InvalidPatternElement {
+                position: "entity",
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn test_query_to_proto_rejects_unbound_find() {
+        let query = name_query().find("missing");
+
+        let result = proto::QueryRequest::try_from(&query);
+
+        assert_eq!(
+            result,
+            Err(QueryBuildError::UnboundVariable("missing".to_owned()))
+        );
+    }
+
+    #[test]
+    fn test_check_query_value_limits() {
+        let limits = ValueLimits::new(8).expect("valid limit");
+        let string_pattern = |value: &str| proto::QueryPattern {
+            entity: Some(proto::query_pattern::Entity::EntityVariable(
+                proto::QueryPatternVariable {
+                    label: Some("e".to_owned()),
+                },
+            )),
+            attribute: Some(proto::query_pattern::Attribute::AttributeId(vec![1; 16])),
+            value_group: Some(proto::query_pattern::ValueGroup::Value(
+                proto::TripleValue {
+                    value: Some(proto::triple_value::Value::String(value.to_owned())),
+                },
+            )),
+        };
+
+        let at_limit = proto::QueryRequest {
+            r#where: vec![string_pattern(&"a".repeat(8))],
+            ..Default::default()
+        };
+        assert!(check_query_value_limits(&at_limit, &limits).is_ok());
+
+        let over_limit = proto::QueryRequest {
+            r#where: vec![string_pattern("a")],
+            where_not: vec![string_pattern("a"), string_pattern(&"a".repeat(9))],
+            ..Default::default()
+        };
+        let violation = check_query_value_limits(&over_limit, &limits).expect_err("over the limit");
+        assert_eq!(violation.location, "where_not pattern #1");
+        assert_eq!(
+            violation.to_string(),
+            "Invalid where_not pattern #1: Triple string value too long. Max: 8, got: 9"
+        );
+    }
+}
@@ -0,0 +1,145 @@
+//! Connection-scoped default triples.
+
+use std::collections::HashSet;
+
+use crate::proto;
+use crate::types::pending_triple::validate_proto_id;
+use crate::types::value_limits::ValueLimitViolation;
+use crate::types::{AttributeId, ProtoDeserializable, TripleValue, ValueLimits};
+
+/// An attribute and value attached to every entity a connection writes.
+#[derive(Debug, PartialEq)]
+pub struct DefaultTriple {
+    pub attribute_id: AttributeId,
+    pub value: TripleValue,
+}
+
+/// A validated request to replace a connection's default triples.
+///
+/// # Invariants
+///
+/// - Every `attribute_id` was exactly 16 bytes on the wire
+/// - No attribute appears twice
+#[derive(Debug)]
+pub struct SetDefaultsRequest {
+    pub defaults: Vec<DefaultTriple>,
+}
+
+impl ProtoDeserializable<proto::SetDefaultsRequest> for SetDefaultsRequest {
+    /// # Errors
+    ///
+    /// Returns an error if a default's `attribute_id` is missing or not
+    /// exactly 16 bytes, its value is missing or invalid, or two defaults
+    /// share an attribute.
+    fn from_proto(request: proto::SetDefaultsRequest) -> Result<Self, String> {
+        let mut defaults = Vec::with_capacity(request.defaults.len());
+        let mut seen = HashSet::with_capacity(request.defaults.len());
+
+        for (index, default) in request.defaults.into_iter().enumerate() {
+            let attribute_id = AttributeId(
+                validate_proto_id(default.attribute_id, "DefaultTriple", "attribute_id")
+                    .map_err(|err| format!("Invalid default #{index}: {err}"))?,
+            );
+            if !seen.insert(attribute_id) {
+                return Err(format!(
+                    "Invalid default #{index}: attribute {attribute_id} is already a default"
+                ));
+            }
+            let value = default
+                .value
+                .ok_or_else(|| format!("Invalid default #{index}: missing value"))
+                .and_then(|value| {
+                    TripleValue::from_proto(value)
+                        .map_err(|err| format!("Invalid default #{index}: {err}"))
+                })?;
+            defaults.push(DefaultTriple {
+                attribute_id,
+                value,
+            });
+        }
+
+        Ok(Self { defaults })
+    }
+}
+
+impl SetDefaultsRequest {
+    /// Check every default's value against the configured limits.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error naming the first default whose value exceeds `limits`.
+    pub fn check_value_limits(&self, limits: &ValueLimits) -> Result<(), ValueLimitViolation> {
+        for (index, default) in self.defaults.iter().enumerate() {
+            limits
+                .check(&default.value)
+                .map_err(|error| ValueLimitViolation {
+                    location: format!("default #{index}"),
+                    error,
+                })?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn string_value(value: &str) -> proto::TripleValue {
+        proto::TripleValue {
+            value: Some(proto::triple_value::Value::String(value.to_owned())),
+        }
+    }
+
+    #[test]
+    fn test_set_defaults_request_from_proto() {
+        let request = SetDefaultsRequest::from_proto(proto::SetDefaultsRequest {
+            defaults: vec![proto::DefaultTriple {
+                attribute_id: Some([1u8; 16].to_vec()),
+                value: Some(string_value("author")),
+            }],
+        })
+        .expect("valid request");
+
+        assert_eq!(
+            request.defaults,
+            vec![DefaultTriple {
+                attribute_id: AttributeId([1u8; 16]),
+                value: TripleValue::String("author".to_owned()),
+            }]
+        );
+    }
+
+    #[test]
+    fn test_set_defaults_request_rejects_invalid_defaults() {
+        let short_id = SetDefaultsRequest::from_proto(proto::SetDefaultsRequest {
+            defaults: vec![proto::DefaultTriple {
+                attribute_id: Some(vec![1, 2, 3]),
+                value: Some(string_value("author")),
+            }],
+        });
+        assert!(short_id.is_err());
+
+        let missing_value = SetDefaultsRequest::from_proto(proto::SetDefaultsRequest {
+            defaults: vec![proto::DefaultTriple {
+                attribute_id: Some([1u8; 16].to_vec()),
+                value: None,
+            }],
+        });
+        assert!(missing_value.is_err());
+
+        let duplicate = SetDefaultsRequest::from_proto(proto::SetDefaultsRequest {
+            defaults: vec![
+                proto::DefaultTriple {
+                    attribute_id: Some([1u8; 16].to_vec()),
+                    value: Some(string_value("a")),
+                },
+                proto::DefaultTriple {
+                    attribute_id: Some([1u8; 16].to_vec()),
+                    value: Some(string_value("b")),
+                },
+            ],
+        });
+        assert!(duplicate.is_err());
+    }
+}
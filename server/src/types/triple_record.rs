@@ -3,15 +3,123 @@
 //! Provides `TripleRecord` struct with MVCC metadata and `TripleError`
 //! for record-level errors.
 
+use crate::storage::btree::MAX_INLINE_VALUE_SIZE;
 use crate::types::ids::{AttributeId, EntityId};
-use crate::types::{HlcTimestamp, TripleValue, TripleValueError, ValueType};
+use crate::types::{Cardinality, HlcTimestamp, TripleValue, TripleValueError, ValueType};
 
 /// Transaction ID.
 pub type TxnId = u64;
 
 /// Fixed size of triple metadata (without value).
-/// `entity_id` (16) + `attribute_id` (16) + `created_txn` (8) + `deleted_txn` (8) + `created_hlc` (16) = 64
-const TRIPLE_METADATA_SIZE: usize = 64;
+/// `entity_id` (16) + `attribute_id` (16) + `created_txn` (8) + `deleted_txn` (8) +
+/// `expires_at_ms` (8) + `created_hlc` (16) + `deleted_hlc` (16) + `cardinality` (1) +
+/// `value_hash` (8) = 97
+const TRIPLE_METADATA_SIZE: usize = 97;
+
+/// Length of the serialized prefix holding a record's `TxnRange`.
+/// `entity_id` (16) + `attribute_id` (16) + `created_txn` (8) + `deleted_txn` (8) +
+/// `expires_at_ms` (8) = 56
+pub const TXN_RANGE_PREFIX_SIZE: usize = 56;
+
+/// The MVCC transaction range of a stored record, with its expiry.
+///
+/// Enough to decide whether a record exists and is visible, and decodable from
+/// the first `TXN_RANGE_PREFIX_SIZE` bytes of a serialized record without
+/// reading its value.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TxnRange {
+    /// Transaction ID that created the record.
+    pub created_txn: TxnId,
+    /// Transaction ID that deleted the record (0 = not deleted).
+    pub deleted_txn: TxnId,
+    /// Physical time (ms since the Unix epoch) the record expires at, if any.
+    pub expires_at_ms: Option<u64>,
+}
+
+impl TxnRange {
+    /// Check if the record is deleted.
+    #[must_use]
+    pub const fn is_deleted(&self) -> bool {
+        self.deleted_txn != 0
+    }
+
+    /// Check if the record is visible to a given transaction.
+    ///
+    /// A record is visible if:
+    /// - `created_txn` <= `snapshot_txn`
+    /// - `deleted_txn` == 0 OR `deleted_txn` > `snapshot_txn`
+    #[must_use]
+    pub const fn is_visible_to(&self, snapshot_txn: TxnId) -> bool {
+        self.created_txn <= snapshot_txn
+            && (self.deleted_txn == 0 || self.deleted_txn > snapshot_txn)
+    }
+
+    /// Check if the record has expired at physical time `now_ms`.
+    ///
+    /// A record expires at the first millisecond at or after its
+    /// `expires_at_ms`.
+    #[must_use]
+    pub const fn is_expired(&self, now_ms: u64) -> bool {
+        match self.expires_at_ms {
+            Some(expires_at_ms) => expires_at_ms <= now_ms,
+            None => false,
+        }
+    }
+
+    /// Decode the range from the start of a serialized `TripleRecord`.
+    ///
+    /// # Errors
+    ///
+    /// Returns `TripleError::InvalidRecord` if `bytes` is shorter than
+    /// `TXN_RANGE_PREFIX_SIZE`.
+    pub fn from_record_prefix(bytes: &[u8]) -> Result<Self, TripleError> {
+        if bytes.len() < TXN_RANGE_PREFIX_SIZE {
+            return Err(TripleError::InvalidRecord);
+        }
+
+        let created_txn = u64::from_le_bytes([
+            bytes[32], bytes[33], bytes[34], bytes[35], bytes[36], bytes[37], bytes[38], bytes[39],
+        ]);
+        let deleted_txn = u64::from_le_bytes([
+            bytes[40], bytes[41], bytes[42], bytes[43], bytes[44], bytes[45], bytes[46], bytes[47],
+        ]);
+        let expires_at_ms = u64::from_le_bytes([
+            bytes[48], bytes[49], bytes[50], bytes[51], bytes[52], bytes[53], bytes[54], bytes[55],
+        ]);
+
+        Ok(Self {
+            created_txn,
+            deleted_txn,
+            expires_at_ms: decode_expiry(expires_at_ms),
+        })
+    }
+}
+
+/// How much space a record takes in the primary index.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RecordStorageSize {
+    /// Length of the serialized record, metadata included.
+    pub serialized_bytes: usize,
+    /// Whether the record is larger than `MAX_INLINE_VALUE_SIZE`, so the
+    /// B-tree leaf holds an overflow reference and the record itself is
+    /// written to overflow pages.
+    pub overflow: bool,
+}
+
+impl RecordStorageSize {
+    /// The size of a record holding `value`.
+    ///
+    /// Every other field of a record has a fixed size, so the value alone
+    /// decides it.
+    #[must_use]
+    pub fn of_value(value: &TripleValue) -> Self {
+        let serialized_bytes = TRIPLE_METADATA_SIZE + value.serialized_size();
+        Self {
+            serialized_bytes,
+            overflow: serialized_bytes > MAX_INLINE_VALUE_SIZE,
+        }
+    }
+}
 
 /// A complete triple record with MVCC metadata.
 #[derive(Debug)]
@@ -26,8 +134,26 @@ pub struct TripleRecord {
     pub deleted_txn: TxnId,
     /// HLC timestamp when created.
     pub created_hlc: HlcTimestamp,
+    /// HLC timestamp of the delete (zero if not deleted).
+    ///
+    /// Kept on the tombstone so later writes resolve last-writer-wins
+    /// against the delete.
+    pub deleted_hlc: HlcTimestamp,
+    /// Physical time (ms since the Unix epoch) the triple expires at, or
+    /// `None` if it never expires.
+    ///
+    /// Reads treat an expired triple as absent until the expiry sweep
+    /// tombstones it.
+    pub expires_at_ms: Option<u64>,
+    /// Cardinality of the triple's attribute, which decides its key in the
+    /// primary index (see `value_hash`).
+    pub cardinality: Cardinality,
     /// The triple's value.
     pub value: TripleValue,
+    /// The value hash keying this triple in the primary index. Set with the
+    /// cardinality, and moved on by `with_value_hash` past keys holding
+    /// other values.
+    value_hash: u64,
 }
 
 impl TripleRecord {
@@ -46,25 +172,101 @@ impl TripleRecord {
             created_txn,
             deleted_txn: 0,
             created_hlc,
+            deleted_hlc: HlcTimestamp::new(0, 0),
+            expires_at_ms: None,
+            cardinality: Cardinality::One,
             value,
+            value_hash: 0,
+        }
+    }
+
+    /// Set the physical time (ms since the Unix epoch) the triple expires at.
+    #[must_use]
+    pub const fn with_expiry(mut self, expires_at_ms: Option<u64>) -> Self {
+        self.expires_at_ms = expires_at_ms;
+        self
+    }
+
+    /// Set the cardinality of the triple's attribute, keying the triple at
+    /// its home value hash.
+    #[must_use]
+    pub fn with_cardinality(mut self, cardinality: Cardinality) -> Self {
+        self.cardinality = cardinality;
+        self.value_hash = cardinality.value_hash(&self.value);
+        self
+    }
+
+    /// Key a cardinality-many triple at `value_hash`, a probe past keys
+    /// holding other values.
+    ///
+    /// # Pre-conditions
+    /// - The triple is cardinality-many
+    /// - `value_hash` is nonzero
+    #[must_use]
+    pub const fn with_value_hash(mut self, value_hash: u64) -> Self {
+        assert!(
+            matches!(self.cardinality, Cardinality::Many),
+            "only cardinality-many triples are probed"
+        );
+        assert!(value_hash != 0, "0 is the cardinality-one key");
+        self.value_hash = value_hash;
+        self
+    }
+
+    /// The value hash keying this triple in the primary index: 0 for a
+    /// cardinality-one attribute, else a hash of the value, or a later key
+    /// if that one held a different value when the triple was written.
+    #[must_use]
+    pub const fn value_hash(&self) -> u64 {
+        self.value_hash
+    }
+
+    /// Whether this triple was probed past its home value hash.
+    #[must_use]
+    pub fn is_displaced(&self) -> bool {
+        self.value_hash != self.cardinality.value_hash(&self.value)
+    }
+
+    /// The record's MVCC transaction range.
+    #[must_use]
+    pub const fn txn_range(&self) -> TxnRange {
+        TxnRange {
+            created_txn: self.created_txn,
+            deleted_txn: self.deleted_txn,
+            expires_at_ms: self.expires_at_ms,
         }
     }
 
     /// Check if this triple is deleted.
     #[must_use]
     pub const fn is_deleted(&self) -> bool {
-        self.deleted_txn != 0
+        self.txn_range().is_deleted()
+    }
+
+    /// HLC of the last write to this triple: the delete if deleted, else the creation.
+    #[must_use]
+    pub const fn last_write_hlc(&self) -> HlcTimestamp {
+        if self.is_deleted() {
+            self.deleted_hlc
+        } else {
+            self.created_hlc
+        }
     }
 
     /// Check if this triple is visible to a given transaction.
     ///
-    /// A triple is visible if:
-    /// - `created_txn` <= `snapshot_txn`
-    /// - `deleted_txn` == 0 OR `deleted_txn` > `snapshot_txn`
+    /// See `TxnRange::is_visible_to`.
     #[must_use]
     pub const fn is_visible_to(&self, snapshot_txn: TxnId) -> bool {
-        self.created_txn <= snapshot_txn
-            && (self.deleted_txn == 0 || self.deleted_txn > snapshot_txn)
+        self.txn_range().is_visible_to(snapshot_txn)
+    }
+
+    /// Check if this triple has expired at physical time `now_ms`.
+    ///
+    /// See `TxnRange::is_expired`.
+    #[must_use]
+    pub const fn is_expired(&self, now_ms: u64) -> bool {
+        self.txn_range().is_expired(now_ms)
     }
 
     /// Check if this triple is eligible for garbage collection.
@@ -100,6 +302,12 @@ impl TripleRecord {
         TRIPLE_METADATA_SIZE + self.value.serialized_size()
     }
 
+    /// The record's serialized size and whether it spills to overflow pages.
+    #[must_use]
+    pub fn storage_size(&self) -> RecordStorageSize {
+        RecordStorageSize::of_value(&self.value)
+    }
+
     /// Serialize this record to bytes.
     #[must_use]
     pub fn to_bytes(&self) -> Vec<u8> {
@@ -109,7 +317,11 @@ impl TripleRecord {
         bytes.extend_from_slice(&self.attribute_id.0);
         bytes.extend_from_slice(&self.created_txn.to_le_bytes());
         bytes.extend_from_slice(&self.deleted_txn.to_le_bytes());
+        bytes.extend_from_slice(&self.expires_at_ms.unwrap_or(NO_EXPIRY).to_le_bytes());
         bytes.extend_from_slice(&self.created_hlc.to_bytes());
+        bytes.extend_from_slice(&self.deleted_hlc.to_bytes());
+        bytes.push(self.cardinality.to_byte());
+        bytes.extend_from_slice(&self.value_hash.to_le_bytes());
         bytes.extend_from_slice(&self.value.to_bytes());
 
         bytes
@@ -137,11 +349,28 @@ impl TripleRecord {
             bytes[40], bytes[41], bytes[42], bytes[43], bytes[44], bytes[45], bytes[46], bytes[47],
         ]);
 
+        let expires_at_ms = u64::from_le_bytes([
+            bytes[48], bytes[49], bytes[50], bytes[51], bytes[52], bytes[53], bytes[54], bytes[55],
+        ]);
+
         let mut hlc_bytes = [0u8; 16];
-        hlc_bytes.copy_from_slice(&bytes[48..64]);
+        hlc_bytes.copy_from_slice(&bytes[56..72]);
         let created_hlc = HlcTimestamp::from_bytes(&hlc_bytes);
 
-        let (value, _) = TripleValue::from_bytes(&bytes[64..])?;
+        hlc_bytes.copy_from_slice(&bytes[72..88]);
+        let deleted_hlc = HlcTimestamp::from_bytes(&hlc_bytes);
+
+        let cardinality = Cardinality::from_byte(bytes[88]).ok_or(TripleError::InvalidRecord)?;
+
+        let value_hash = u64::from_le_bytes([
+            bytes[89], bytes[90], bytes[91], bytes[92], bytes[93], bytes[94], bytes[95], bytes[96],
+        ]);
+        // Only cardinality-one triples are keyed at 0
+        if (value_hash == 0) != (cardinality == Cardinality::One) {
+            return Err(TripleError::InvalidRecord);
+        }
+
+        let (value, _) = TripleValue::from_bytes(&bytes[TRIPLE_METADATA_SIZE..])?;
 
         Ok(Self {
             entity_id,
@@ -149,11 +378,27 @@ impl TripleRecord {
             created_txn,
             deleted_txn,
             created_hlc,
+            deleted_hlc,
+            expires_at_ms: decode_expiry(expires_at_ms),
+            cardinality,
             value,
+            value_hash,
         })
     }
 }
 
+/// Stored `expires_at_ms` of a triple that never expires.
+const NO_EXPIRY: u64 = 0;
+
+/// Decode a stored `expires_at_ms`.
+const fn decode_expiry(expires_at_ms: u64) -> Option<u64> {
+    if expires_at_ms == NO_EXPIRY {
+        None
+    } else {
+        Some(expires_at_ms)
+    }
+}
+
 /// Errors that can occur with triple operations.
 #[derive(Debug)]
 pub enum TripleError {
@@ -195,6 +440,44 @@ impl From<TripleValueError> for TripleError {
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_storage_size_matches_serialized_record() {
+        let values = [
+            TripleValue::Null,
+            TripleValue::Boolean(true),
+            TripleValue::Number(1.5),
+            TripleValue::String("hello".to_string()),
+            TripleValue::Ref(EntityId([3u8; 16])),
+        ];
+        for value in values {
+            let record = TripleRecord::new(
+                EntityId([1u8; 16]),
+                AttributeId([2u8; 16]),
+                1,
+                HlcTimestamp::new(1000, 1),
+                value,
+            );
+            let size = record.storage_size();
+            assert_eq!(size.serialized_bytes, record.to_bytes().len());
+            assert!(!size.overflow);
+        }
+    }
+
+    #[test]
+    fn test_storage_size_overflow_boundary() {
+        // Metadata, then a type byte and a 2-byte length before the string
+        let inline_max = MAX_INLINE_VALUE_SIZE - TRIPLE_METADATA_SIZE - 3;
+
+        let at_limit = RecordStorageSize::of_value(&TripleValue::String("x".repeat(inline_max)));
+        assert_eq!(at_limit.serialized_bytes, MAX_INLINE_VALUE_SIZE);
+        assert!(!at_limit.overflow);
+
+        let over_limit =
+            RecordStorageSize::of_value(&TripleValue::String("x".repeat(inline_max + 1)));
+        assert_eq!(over_limit.serialized_bytes, MAX_INLINE_VALUE_SIZE + 1);
+        assert!(over_limit.overflow);
+    }
+
     #[test]
     fn test_triple_record_roundtrip() {
         let record = TripleRecord::new(
@@ -223,6 +506,101 @@ mod tests {
         assert_eq!(decoded.value, record.value);
     }
 
+    #[test]
+    fn test_triple_record_roundtrip_tombstone() {
+        let mut record = TripleRecord::new(
+            EntityId([1u8; 16]),
+            AttributeId([2u8; 16]),
+            100,
+            HlcTimestamp::new(1000, 1),
+            TripleValue::Number(1.0),
+        );
+        record.deleted_txn = 120;
+        record.deleted_hlc = HlcTimestamp::new(2000, 3);
+
+        let decoded = TripleRecord::from_bytes(&record.to_bytes()).unwrap();
+
+        assert_eq!(decoded.deleted_txn, 120);
+        assert_eq!(decoded.deleted_hlc, HlcTimestamp::new(2000, 3));
+        assert_eq!(decoded.created_hlc, HlcTimestamp::new(1000, 1));
+        assert_eq!(decoded.value, TripleValue::Number(1.0));
+    }
+
+    #[test]
+    fn test_txn_range_from_record_prefix() {
+        let mut record = TripleRecord::new(
+            EntityId([1u8; 16]),
+            AttributeId([2u8; 16]),
+            100,
+            HlcTimestamp::new(1000, 1),
+            TripleValue::String("x".repeat(100)),
+        );
+        record.deleted_txn = 120;
+        let bytes = record.to_bytes();
+
+        let range = TxnRange::from_record_prefix(&bytes[..TXN_RANGE_PREFIX_SIZE]).unwrap();
+
+        assert_eq!(range, record.txn_range());
+        assert!(range.is_deleted());
+        assert!(matches!(
+            TxnRange::from_record_prefix(&bytes[..TXN_RANGE_PREFIX_SIZE - 1]),
+            Err(TripleError::InvalidRecord)
+        ));
+    }
+
+    #[test]
+    fn test_triple_record_roundtrip_cardinality() {
+        let record = TripleRecord::new(
+            EntityId([1u8; 16]),
+            AttributeId([2u8; 16]),
+            100,
+            HlcTimestamp::new(1000, 1),
+            TripleValue::String("tag".to_string()),
+        );
+        assert_eq!(record.value_hash(), 0);
+        let record = record.with_cardinality(Cardinality::Many);
+        assert_ne!(record.value_hash(), 0);
+
+        let mut bytes = record.to_bytes();
+        let decoded = TripleRecord::from_bytes(&bytes).unwrap();
+        assert_eq!(decoded.cardinality, Cardinality::Many);
+        assert_eq!(decoded.value_hash(), record.value_hash());
+        assert!(!decoded.is_displaced());
+
+        let probed = record.with_value_hash(3);
+        let decoded = TripleRecord::from_bytes(&probed.to_bytes()).unwrap();
+        assert_eq!(decoded.value_hash(), 3);
+        assert!(decoded.is_displaced());
+
+        bytes[88] = 7;
+        assert!(matches!(
+            TripleRecord::from_bytes(&bytes),
+            Err(TripleError::InvalidRecord)
+        ));
+        // A cardinality-one triple keyed off 0
+        bytes[88] = Cardinality::One.to_byte();
+        assert!(matches!(
+            TripleRecord::from_bytes(&bytes),
+            Err(TripleError::InvalidRecord)
+        ));
+    }
+
+    #[test]
+    fn test_last_write_hlc() {
+        let mut record = TripleRecord::new(
+            EntityId([1u8; 16]),
+            AttributeId([2u8; 16]),
+            10,
+            HlcTimestamp::new(1000, 0),
+            TripleValue::Null,
+        );
+        assert_eq!(record.last_write_hlc(), HlcTimestamp::new(1000, 0));
+
+        record.deleted_txn = 20;
+        record.deleted_hlc = HlcTimestamp::new(3000, 0);
+        assert_eq!(record.last_write_hlc(), HlcTimestamp::new(3000, 0));
+    }
+
     #[test]
     fn test_triple_visibility() {
         let mut record = TripleRecord::new(
@@ -258,8 +636,8 @@ mod tests {
             HlcTimestamp::new(0, 0),
             TripleValue::Null,
         );
-        // 64 bytes metadata + 1 byte for null value
-        assert_eq!(record.serialized_size(), 65);
+        // 97 bytes metadata + 1 byte for null value
+        assert_eq!(record.serialized_size(), 98);
 
         let record = TripleRecord::new(
             EntityId([0u8; 16]),
@@ -268,8 +646,29 @@ mod tests {
             HlcTimestamp::new(0, 0),
             TripleValue::String("hello".to_string()),
         );
-        // 64 bytes metadata + 1 type + 2 len + 5 data = 72
-        assert_eq!(record.serialized_size(), 72);
+        // 97 bytes metadata + 1 type + 2 len + 5 data = 105
+        assert_eq!(record.serialized_size(), 105);
+    }
+
+    #[test]
+    fn test_triple_expiry() {
+        let record = TripleRecord::new(
+            EntityId([1u8; 16]),
+            AttributeId([2u8; 16]),
+            10,
+            HlcTimestamp::new(1000, 0),
+            TripleValue::Null,
+        );
+        assert!(!record.is_expired(u64::MAX));
+
+        let record = record.with_expiry(Some(5000));
+        assert!(!record.is_expired(4999));
+        assert!(record.is_expired(5000));
+
+        let decoded = TripleRecord::from_bytes(&record.to_bytes()).unwrap();
+        assert_eq!(decoded.expires_at_ms, Some(5000));
+        let range = TxnRange::from_record_prefix(&record.to_bytes()).unwrap();
+        assert_eq!(range.expires_at_ms, Some(5000));
     }
 
     #[test]
@@ -1,13 +1,56 @@
+use std::collections::{HashMap, HashSet};
+
 use crate::proto;
-use crate::types::{PendingTripleData, ProtoDeserializable};
+use crate::types::pending_triple::validate_proto_id;
+use crate::types::set_defaults_request::DefaultTriple;
+use crate::types::value_limits::ValueLimitViolation;
+use crate::types::{EntityId, PendingTripleData, ProtoDeserializable, ValueLimits};
+
+/// How an update request treats triples that already exist.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WriteMode {
+    /// Insert absent triples and overwrite present ones.
+    Upsert,
+    /// Only insert; any present triple fails the request.
+    CreateOnly,
+    /// Only overwrite; any absent triple fails the request.
+    UpdateOnly,
+    /// Upsert, but any entity not at its expected version fails the request.
+    CommitIfVersion,
+}
+
+impl ProtoDeserializable<i32> for WriteMode {
+    fn from_proto(proto_mode: i32) -> Result<Self, String> {
+        match proto::WriteMode::try_from(proto_mode) {
+            Ok(proto::WriteMode::Upsert) => Ok(Self::Upsert),
+            Ok(proto::WriteMode::CreateOnly) => Ok(Self::CreateOnly),
+            Ok(proto::WriteMode::UpdateOnly) => Ok(Self::UpdateOnly),
+            Ok(proto::WriteMode::CommitIfVersion) => Ok(Self::CommitIfVersion),
+            Err(_) => Err(format!("Unknown write mode: {proto_mode}")),
+        }
+    }
+}
 
+/// A validated request to write triples.
+///
+/// # Invariants
+///
+/// - The first `sent_count` triples are the ones the client sent; any
+///   defaults attached by `with_defaults` follow them
+/// - `expected_versions` is empty unless `mode` is `CommitIfVersion`, and
+///   then has an entry for every entity the triples write
 #[derive(Debug)]
 pub struct TripleUpdateRequest {
     pub triples: Vec<PendingTripleData>,
+    pub mode: WriteMode,
+    pub sent_count: usize,
+    /// The version each written entity must be at, by entity.
+    pub expected_versions: HashMap<EntityId, u64>,
 }
 
 impl ProtoDeserializable<proto::TripleUpdateRequest> for TripleUpdateRequest {
     fn from_proto(request: proto::TripleUpdateRequest) -> Result<Self, String> {
+        let mode = WriteMode::from_proto(request.mode)?;
         let mut triples = Vec::with_capacity(request.triples.len());
 
         for (index, triple) in request.triples.into_iter().enumerate() {
@@ -17,6 +60,108 @@ impl ProtoDeserializable<proto::TripleUpdateRequest> for TripleUpdateRequest {
             }
         }
 
-        Ok(Self { triples })
+        if mode != WriteMode::CommitIfVersion && !request.expected_versions.is_empty() {
+            return Err(
+                "Expected versions are only allowed with WRITE_MODE_COMMIT_IF_VERSION".to_owned(),
+            );
+        }
+        let mut expected_versions = HashMap::with_capacity(request.expected_versions.len());
+        for (index, expected) in request.expected_versions.into_iter().enumerate() {
+            let entity_id = EntityId(
+                validate_proto_id(Some(expected.entity_id), "EntityVersion", "entity_id")
+                    .map_err(|err| format!("Failed to parse expected version #{index}: {err}"))?,
+            );
+            if expected_versions
+                .insert(entity_id, expected.version)
+                .is_some()
+            {
+                return Err(format!(
+                    "Expected version #{index} repeats an earlier entity"
+                ));
+            }
+        }
+        if mode == WriteMode::CommitIfVersion
+            && let Some(index) = triples
+                .iter()
+                .position(|triple| !expected_versions.contains_key(&triple.entity_id))
+        {
+            return Err(format!(
+                "Triple #{index} writes an entity with no expected version"
+            ));
+        }
+
+        let sent_count = triples.len();
+        Ok(Self {
+            triples,
+            mode,
+            sent_count,
+            expected_versions,
+        })
+    }
+}
+
+impl TripleUpdateRequest {
+    /// Check every triple's value against the configured limits.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error naming the first triple whose value exceeds `limits`.
+    pub fn check_value_limits(&self, limits: &ValueLimits) -> Result<(), ValueLimitViolation> {
+        for (index, triple) in self.triples.iter().enumerate() {
+            limits
+                .check(&triple.value)
+                .map_err(|error| ValueLimitViolation {
+                    location: format!("triple #{index}"),
+                    error,
+                })?;
+        }
+        Ok(())
+    }
+
+    /// Attach a connection's default triples to every entity the request
+    /// writes.
+    ///
+    /// # Post-conditions
+    ///
+    /// - Each written entity also gets every default whose attribute the
+    ///   request does not already write for it, carrying the HLC of the
+    ///   entity's first triple in the request.
+    /// - The request's own triples come first, unchanged; the added defaults
+    ///   follow, grouped by entity in the order entities first appear.
+    /// - `UpdateOnly` requests get no defaults, since they never create an
+    ///   entity's triples.
+    #[must_use]
+    pub fn with_defaults(mut self, defaults: &[DefaultTriple]) -> Self {
+        if defaults.is_empty() || self.mode == WriteMode::UpdateOnly {
+            return self;
+        }
+
+        let written: HashSet<_> = self
+            .triples
+            .iter()
+            .map(|triple| (triple.entity_id, triple.attribute_id))
+            .collect();
+        let mut seen_entities = HashSet::new();
+        let entities: Vec<_> = self
+            .triples
+            .iter()
+            .filter(|triple| seen_entities.insert(triple.entity_id))
+            .map(|triple| (triple.entity_id, triple.hlc))
+            .collect();
+
+        for (entity_id, hlc) in entities {
+            for default in defaults {
+                if written.contains(&(entity_id, default.attribute_id)) {
+                    continue;
+                }
+                self.triples.push(PendingTripleData {
+                    entity_id,
+                    attribute_id: default.attribute_id,
+                    value: default.value.clone_value(),
+                    hlc,
+                });
+            }
+        }
+        self
     }
 }
@@ -2,8 +2,20 @@
 //!
 //! Provides `TripleValue` enum and `ValueType` discriminant, along with
 //! serialization, deserialization, and proto conversion implementations.
+//!
+//! # Ordering
+//!
+//! `f64` has no total order, so `TripleValue` does not implement `Ord`. Code
+//! that sorts or compares values uses `TripleValue::cmp_total` instead, which
+//! ranks by type first and never panics:
+//!
+//! ```text
+//! Null < Boolean < Number < String < Bytes < Ref
+//! ```
 
-use crate::constants::MAX_TRIPLE_STRING_VALUE_LENGTH;
+use std::cmp::Ordering;
+
+use crate::constants::{MAX_TRIPLE_BYTES_VALUE_LENGTH, MAX_TRIPLE_STRING_VALUE_LENGTH};
 use crate::proto;
 use crate::types::ids::EntityId;
 use crate::types::{ProtoDeserializable, ProtoSerializable};
@@ -18,7 +30,7 @@ pub enum ValueType {
     StringInline = 0x04,
     StringOverflow = 0x05, // Not implemented in Phase 1
     Date = 0x06,           // Future
-    Blob = 0x07,           // Future
+    Blob = 0x07,           // Binary data
     Ref = 0x08,            // Reference to another entity
 }
 
@@ -47,7 +59,11 @@ pub enum TripleValue {
     Null,
     Boolean(bool),
     Number(f64),
+    /// A UTF-8 string, taken verbatim from a proto `string` or decoded
+    /// strictly from storage. Never produced by a lossy conversion.
     String(String),
+    /// Binary data, stored verbatim.
+    Bytes(Vec<u8>),
     /// Reference to another entity.
     Ref(EntityId),
 }
@@ -79,6 +95,13 @@ impl std::fmt::Display for TripleValue {
             Self::Boolean(b) => write!(f, "{b}"),
             Self::Number(n) => write!(f, "{n}"),
             Self::String(s) => write!(f, "\"{s}\""),
+            Self::Bytes(bytes) => {
+                write!(f, "0x")?;
+                for byte in bytes {
+                    write!(f, "{byte:02x}")?;
+                }
+                Ok(())
+            }
             Self::Ref(id) => write!(f, "#{id}"),
         }
     }
@@ -103,6 +126,12 @@ impl TripleValue {
         Self::Boolean(b)
     }
 
+    /// Create a bytes value.
+    #[must_use]
+    pub fn bytes(bytes: impl Into<Vec<u8>>) -> Self {
+        Self::Bytes(bytes.into())
+    }
+
     /// Create a reference value.
     #[must_use]
     pub const fn reference(id: EntityId) -> Self {
@@ -117,6 +146,7 @@ impl TripleValue {
             Self::Boolean(_) => ValueType::Boolean,
             Self::Number(_) => ValueType::Number,
             Self::String(_) => ValueType::StringInline,
+            Self::Bytes(_) => ValueType::Blob,
             Self::Ref(_) => ValueType::Ref,
         }
     }
@@ -131,10 +161,50 @@ impl TripleValue {
             Self::Boolean(b) => Self::Boolean(*b),
             Self::Number(n) => Self::Number(*n),
             Self::String(s) => Self::String(s.as_str().to_owned()),
+            Self::Bytes(bytes) => Self::Bytes(bytes.as_slice().to_vec()),
             Self::Ref(id) => Self::Ref(*id),
         }
     }
 
+    /// Compare two values under the canonical total order.
+    ///
+    /// Values of different types order by type rank (`Null < Boolean < Number
+    /// < String < Bytes < Ref`). Within a type:
+    /// - Booleans: `false < true`
+    /// - Numbers: numeric order, with `-0.0 == 0.0` and every NaN equal to
+    ///   every other NaN and greater than all other numbers, including infinity
+    /// - Strings: lexicographic by UTF-8 bytes
+    /// - Bytes: lexicographic
+    /// - Refs: lexicographic by entity ID bytes
+    ///
+    /// # Post-conditions
+    /// - The order is total: reflexive, antisymmetric, and transitive for all
+    ///   values, including NaN
+    #[must_use]
+    pub fn cmp_total(&self, other: &Self) -> Ordering {
+        match (self, other) {
+            (Self::Null, Self::Null) => Ordering::Equal,
+            (Self::Boolean(a), Self::Boolean(b)) => a.cmp(b),
+            (Self::Number(a), Self::Number(b)) => cmp_numbers(*a, *b),
+            (Self::String(a), Self::String(b)) => a.as_bytes().cmp(b.as_bytes()),
+            (Self::Bytes(a), Self::Bytes(b)) => a.cmp(b),
+            (Self::Ref(a), Self::Ref(b)) => a.0.cmp(&b.0),
+            _ => self.type_rank().cmp(&other.type_rank()),
+        }
+    }
+
+    /// Rank of this value's type in the canonical total order.
+    const fn type_rank(&self) -> u8 {
+        match self {
+            Self::Null => 0,
+            Self::Boolean(_) => 1,
+            Self::Number(_) => 2,
+            Self::String(_) => 3,
+            Self::Bytes(_) => 4,
+            Self::Ref(_) => 5,
+        }
+    }
+
     /// Calculate the serialized size of this value.
     #[must_use]
     #[allow(clippy::missing_const_for_fn)] // String::len() is not const-stable
@@ -144,6 +214,7 @@ impl TripleValue {
             Self::Boolean(_) => 1 + 1,          // type + 1 byte
             Self::Number(_) => 1 + 8,           // type + f64
             Self::String(s) => 1 + 2 + s.len(), // type + len (2 bytes) + data
+            Self::Bytes(b) => 1 + 4 + b.len(),  // type + len (4 bytes) + data
             Self::Ref(_) => 1 + 16,             // type + entity ID (16 bytes)
         }
     }
@@ -164,6 +235,13 @@ impl TripleValue {
                 bytes.extend_from_slice(&len.to_le_bytes());
                 bytes.extend_from_slice(s.as_bytes());
             }
+            Self::Bytes(data) => {
+                // Bounded by MAX_TRIPLE_BYTES_VALUE_LENGTH
+                #[allow(clippy::cast_possible_truncation)]
+                let len = data.len() as u32;
+                bytes.extend_from_slice(&len.to_le_bytes());
+                bytes.extend_from_slice(data);
+            }
             Self::Ref(id) => bytes.extend_from_slice(&id.0),
         }
 
@@ -218,13 +296,34 @@ impl TripleValue {
                 id_bytes.copy_from_slice(&bytes[1..17]);
                 Ok((Self::Ref(EntityId(id_bytes)), 17))
             }
-            ValueType::StringOverflow | ValueType::Date | ValueType::Blob => {
+            ValueType::Blob => {
+                if bytes.len() < 5 {
+                    return Err(TripleValueError::InvalidValue);
+                }
+                let len = u32::from_le_bytes([bytes[1], bytes[2], bytes[3], bytes[4]]) as usize;
+                if bytes.len() < 5 + len {
+                    return Err(TripleValueError::InvalidValue);
+                }
+                Ok((Self::Bytes(bytes[5..5 + len].to_vec()), 5 + len))
+            }
+            ValueType::StringOverflow | ValueType::Date => {
                 Err(TripleValueError::UnsupportedValueType(value_type))
             }
         }
     }
 }
 
+/// Total order over `f64`: NaN sorts last and `-0.0` equals `0.0`.
+fn cmp_numbers(a: f64, b: f64) -> Ordering {
+    match (a.is_nan(), b.is_nan()) {
+        (true, true) => Ordering::Equal,
+        (true, false) => Ordering::Greater,
+        (false, true) => Ordering::Less,
+        // Adding 0.0 maps -0.0 to 0.0 and leaves every other value unchanged
+        (false, false) => (a + 0.0).total_cmp(&(b + 0.0)),
+    }
+}
+
 impl ProtoDeserializable<proto::TripleValue> for TripleValue {
     /// Deserialize a `TripleValue` from a proto `TripleValue`.
     ///
@@ -233,6 +332,7 @@ impl ProtoDeserializable<proto::TripleValue> for TripleValue {
     /// - The proto value is missing (None)
     /// - A string value is empty
     /// - A string value exceeds `MAX_TRIPLE_STRING_VALUE_LENGTH`
+    /// - A bytes value exceeds `MAX_TRIPLE_BYTES_VALUE_LENGTH`
     fn from_proto(proto_value: proto::TripleValue) -> Result<Self, String> {
         match proto_value.value {
             Some(proto::triple_value::Value::String(s)) => {
@@ -249,11 +349,35 @@ impl ProtoDeserializable<proto::TripleValue> for TripleValue {
             }
             Some(proto::triple_value::Value::Boolean(b)) => Ok(Self::Boolean(b)),
             Some(proto::triple_value::Value::Number(n)) => Ok(Self::Number(n)),
+            Some(proto::triple_value::Value::Bytes(bytes)) => {
+                if bytes.len() > MAX_TRIPLE_BYTES_VALUE_LENGTH {
+                    return Err(format!(
+                        "Triple bytes value too long. Max: {MAX_TRIPLE_BYTES_VALUE_LENGTH}, got: {}",
+                        bytes.len()
+                    ));
+                }
+                Ok(Self::Bytes(bytes))
+            }
             None => Err("Triple proto did not contain a value".into()),
         }
     }
 }
 
+impl ProtoSerializable<i32> for ValueType {
+    fn to_proto(self) -> i32 {
+        match self {
+            Self::Null => proto::ValueType::Null.into(),
+            Self::Boolean => proto::ValueType::Boolean.into(),
+            Self::Number => proto::ValueType::Number.into(),
+            Self::StringInline | Self::StringOverflow => proto::ValueType::String.into(),
+            Self::Ref => proto::ValueType::Ref.into(),
+            Self::Blob => proto::ValueType::Bytes.into(),
+            // Reserved type no value is stored with
+            Self::Date => proto::ValueType::Unspecified.into(),
+        }
+    }
+}
+
 impl ProtoSerializable<Option<proto::TripleValue>> for TripleValue {
     fn to_proto(self) -> Option<proto::TripleValue> {
         match self {
@@ -267,6 +391,9 @@ impl ProtoSerializable<Option<proto::TripleValue>> for TripleValue {
             Self::String(s) => Some(proto::TripleValue {
                 value: Some(proto::triple_value::Value::String(s)),
             }),
+            Self::Bytes(bytes) => Some(proto::TripleValue {
+                value: Some(proto::triple_value::Value::Bytes(bytes)),
+            }),
             Self::Ref(id) => {
                 // Serialize Ref as a string representation of the entity ID.
                 // Try UTF-8 first, fall back to hex encoding.
@@ -302,6 +429,9 @@ impl ProtoSerializable<Option<proto::TripleValue>> for &TripleValue {
             TripleValue::String(s) => Some(proto::TripleValue {
                 value: Some(proto::triple_value::Value::String(s.as_str().to_owned())),
             }),
+            TripleValue::Bytes(bytes) => Some(proto::TripleValue {
+                value: Some(proto::triple_value::Value::Bytes(bytes.as_slice().to_vec())),
+            }),
             TripleValue::Ref(id) => {
                 // Serialize Ref as a string representation of the entity ID.
                 let s = std::str::from_utf8(&id.0).map_or_else(
@@ -368,6 +498,55 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_value_string_rejects_invalid_utf8() {
+        // A lone continuation byte is not valid UTF-8
+        let bytes = [ValueType::StringInline as u8, 2, 0, b'a', 0x80];
+        assert!(matches!(
+            TripleValue::from_bytes(&bytes),
+            Err(TripleValueError::InvalidValue)
+        ));
+    }
+
+    #[test]
+    fn test_value_bytes_roundtrip() {
+        for data in [Vec::new(), vec![0xFF, 0x00, 0x80], vec![7u8; 64 * 1024]] {
+            let value = TripleValue::Bytes(data);
+            let bytes = value.to_bytes();
+            assert_eq!(bytes.len(), value.serialized_size());
+            let (decoded, consumed) = TripleValue::from_bytes(&bytes).unwrap();
+            assert_eq!(decoded, value);
+            assert_eq!(consumed, bytes.len());
+        }
+    }
+
+    #[test]
+    fn test_value_bytes_rejects_truncated_data() {
+        let bytes = TripleValue::Bytes(vec![1, 2, 3]).to_bytes();
+        assert!(matches!(
+            TripleValue::from_bytes(&bytes[..bytes.len() - 1]),
+            Err(TripleValueError::InvalidValue)
+        ));
+    }
+
+    #[test]
+    fn test_bytes_proto_roundtrip() {
+        let value = TripleValue::Bytes(vec![0, 159, 146, 150]);
+        let proto_value: Option<proto::TripleValue> = (&value).to_proto();
+        let decoded =
+            TripleValue::from_proto(proto_value.expect("should be some")).expect("decode");
+        assert_eq!(decoded, value);
+
+        let too_long = proto::TripleValue {
+            value: Some(proto::triple_value::Value::Bytes(vec![
+                0;
+                MAX_TRIPLE_BYTES_VALUE_LENGTH
+                    + 1
+            ])),
+        };
+        assert!(TripleValue::from_proto(too_long).is_err());
+    }
+
     #[test]
     fn test_null_to_proto() {
         let value = TripleValue::Null;
@@ -455,4 +634,74 @@ mod tests {
         let value = TripleValue::Ref(id);
         assert_eq!(value.serialized_size(), 17);
     }
+
+    #[test]
+    fn test_cmp_total_orders_mixed_types() {
+        let mut values = vec![
+            TripleValue::Ref(EntityId([1u8; 16])),
+            TripleValue::string("b"),
+            TripleValue::Number(f64::NAN),
+            TripleValue::Number(1.5),
+            TripleValue::Boolean(true),
+            TripleValue::Null,
+            TripleValue::string("a"),
+            TripleValue::Number(f64::NEG_INFINITY),
+            TripleValue::Boolean(false),
+            TripleValue::Number(f64::INFINITY),
+            TripleValue::Ref(EntityId([0u8; 16])),
+            TripleValue::Bytes(vec![0]),
+        ];
+        values.sort_by(TripleValue::cmp_total);
+
+        assert_eq!(values[0], TripleValue::Null);
+        assert_eq!(values[1], TripleValue::Boolean(false));
+        assert_eq!(values[2], TripleValue::Boolean(true));
+        assert_eq!(values[3], TripleValue::Number(f64::NEG_INFINITY));
+        assert_eq!(values[4], TripleValue::Number(1.5));
+        assert_eq!(values[5], TripleValue::Number(f64::INFINITY));
+        assert!(matches!(values[6], TripleValue::Number(n) if n.is_nan()));
+        assert_eq!(values[7], TripleValue::string("a"));
+        assert_eq!(values[8], TripleValue::string("b"));
+        assert_eq!(values[9], TripleValue::Bytes(vec![0]));
+        assert_eq!(values[10], TripleValue::Ref(EntityId([0u8; 16])));
+        assert_eq!(values[11], TripleValue::Ref(EntityId([1u8; 16])));
+    }
+
+    #[test]
+    fn test_cmp_total_nan_and_signed_zero() {
+        let nan = TripleValue::Number(f64::NAN);
+        let negative_nan = TripleValue::Number(-f64::NAN);
+
+        assert_eq!(nan.cmp_total(&nan), Ordering::Equal);
+        assert_eq!(nan.cmp_total(&negative_nan), Ordering::Equal);
+        assert_eq!(
+            nan.cmp_total(&TripleValue::Number(f64::INFINITY)),
+            Ordering::Greater
+        );
+        assert_eq!(nan.cmp_total(&TripleValue::string("")), Ordering::Less);
+        assert_eq!(
+            TripleValue::Number(-0.0).cmp_total(&TripleValue::Number(0.0)),
+            Ordering::Equal
+        );
+    }
+
+    #[test]
+    fn test_cmp_total_is_consistent_for_all_pairs() {
+        let values = [
+            TripleValue::Null,
+            TripleValue::Boolean(false),
+            TripleValue::Number(f64::NAN),
+            TripleValue::Number(-1.0),
+            TripleValue::Number(0.0),
+            TripleValue::string("x"),
+            TripleValue::Bytes(vec![1, 2]),
+            TripleValue::Ref(EntityId([2u8; 16])),
+        ];
+        for a in &values {
+            assert_eq!(a.cmp_total(a), Ordering::Equal);
+            for b in &values {
+                assert_eq!(a.cmp_total(b), b.cmp_total(a).reverse());
+            }
+        }
+    }
 }
@@ -0,0 +1,225 @@
+//! Configurable limits on triple values accepted from clients.
+//!
+//! `TripleValue::from_proto` enforces only the hard limits of the storage
+//! encoding. Deployments can tighten or relax the accepted sizes within those
+//! bounds; values larger than a page are stored in overflow pages.
+
+use std::fmt;
+
+use crate::constants::{DEFAULT_MAX_TRIPLE_STRING_VALUE_LENGTH, MAX_TRIPLE_STRING_VALUE_LENGTH};
+use crate::proto;
+use crate::types::TripleValue;
+
+/// Why a value is outside the limits.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ValueLimitError {
+    /// A string value is empty.
+    EmptyString,
+    /// A string value is longer than `limit` bytes.
+    StringTooLong {
+        /// The configured maximum length in bytes.
+        limit: usize,
+        /// The string's length in bytes.
+        length: usize,
+    },
+}
+
+impl fmt::Display for ValueLimitError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::EmptyString => write!(f, "Triple string value was empty"),
+            Self::StringTooLong { limit, length } => {
+                write!(
+                    f,
+                    "Triple string value too long. Max: {limit}, got: {length}"
+                )
+            }
+        }
+    }
+}
+
+impl std::error::Error for ValueLimitError {}
+
+/// A value outside the limits, and where in its message it is.
+#[derive(Debug, PartialEq, Eq)]
+pub struct ValueLimitViolation {
+    /// Which value in the message, e.g. `triple #2` or `where pattern #0`.
+    pub location: String,
+    /// How the value breaks the limits.
+    pub error: ValueLimitError,
+}
+
+impl ValueLimitViolation {
+    /// The machine-readable reason for the violation, sent to the client in
+    /// the error status's details.
+    #[must_use]
+    #[allow(clippy::cast_possible_truncation)] // Limits are capped to u16::MAX bytes
+    pub fn error_reason(&self) -> proto::error_detail::Reason {
+        let location = self.location.as_str().to_owned();
+        match self.error {
+            ValueLimitError::EmptyString => {
+                proto::error_detail::Reason::StringEmpty(proto::StringEmpty { location })
+            }
+            ValueLimitError::StringTooLong { limit, length } => {
+                proto::error_detail::Reason::StringTooLong(proto::StringTooLong {
+                    location,
+                    limit: limit as u32,
+                    length: u32::try_from(length).unwrap_or(u32::MAX),
+                })
+            }
+        }
+    }
+}
+
+impl fmt::Display for ValueLimitViolation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Invalid {}: {}", self.location, self.error)
+    }
+}
+
+impl std::error::Error for ValueLimitViolation {}
+
+/// Limits on the values a client may write.
+///
+/// # Invariants
+///
+/// - `max_string_length` is between 1 and `MAX_TRIPLE_STRING_VALUE_LENGTH`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ValueLimits {
+    max_string_length: usize,
+}
+
+impl ValueLimits {
+    /// Create limits with the given maximum string length in bytes.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `max_string_length` is zero or exceeds
+    /// `MAX_TRIPLE_STRING_VALUE_LENGTH`.
+    pub fn new(max_string_length: usize) -> Result<Self, String> {
+        if max_string_length == 0 || max_string_length > MAX_TRIPLE_STRING_VALUE_LENGTH {
+            return Err(format!(
+                "max string length must be between 1 and {MAX_TRIPLE_STRING_VALUE_LENGTH}, got: {max_string_length}"
+            ));
+        }
+        Ok(Self { max_string_length })
+    }
+
+    /// Maximum string value length in bytes.
+    #[must_use]
+    pub const fn max_string_length(&self) -> usize {
+        self.max_string_length
+    }
+
+    /// Check a value against these limits.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error naming the configured limit if the value exceeds it.
+    pub fn check(&self, value: &TripleValue) -> Result<(), ValueLimitError> {
+        match value {
+            TripleValue::String(s) => self.check_string(s),
+            _ => Ok(()),
+        }
+    }
+
+    /// Check a string value against these limits.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the string is empty, or naming the configured limit
+    /// if its length in bytes exceeds it.
+    pub const fn check_string(&self, s: &str) -> Result<(), ValueLimitError> {
+        if s.is_empty() {
+            return Err(ValueLimitError::EmptyString);
+        }
+        if s.len() > self.max_string_length {
+            return Err(ValueLimitError::StringTooLong {
+                limit: self.max_string_length,
+                length: s.len(),
+            });
+        }
+        Ok(())
+    }
+}
+
+impl Default for ValueLimits {
+    fn default() -> Self {
+        Self {
+            max_string_length: DEFAULT_MAX_TRIPLE_STRING_VALUE_LENGTH,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_value_limits_string_boundaries() {
+        let limits = ValueLimits::new(10).expect("valid limit");
+
+        assert!(limits.check(&TripleValue::String("x".repeat(9))).is_ok());
+        assert!(limits.check(&TripleValue::String("x".repeat(10))).is_ok());
+
+        let err = limits
+            .check(&TripleValue::String("x".repeat(11)))
+            .expect_err("over the limit");
+        assert_eq!(
+            err,
+            ValueLimitError::StringTooLong {
+                limit: 10,
+                length: 11
+            }
+        );
+        assert_eq!(
+            err.to_string(),
+            "Triple string value too long. Max: 10, got: 11"
+        );
+    }
+
+    #[test]
+    fn test_value_limits_check_string() {
+        let limits = ValueLimits::new(4).expect("valid limit");
+
+        assert!(limits.check_string("abcd").is_ok());
+        // Length is counted in bytes, not characters
+        assert!(limits.check_string("\u{e9}\u{e9}").is_ok());
+        assert_eq!(
+            limits.check_string("\u{e9}\u{e9}\u{e9}"),
+            Err(ValueLimitError::StringTooLong {
+                limit: 4,
+                length: 6
+            })
+        );
+        assert_eq!(limits.check_string(""), Err(ValueLimitError::EmptyString));
+    }
+
+    #[test]
+    fn test_value_limits_ignore_non_string_values() {
+        let limits = ValueLimits::new(1).expect("valid limit");
+
+        assert!(limits.check(&TripleValue::Number(12345.0)).is_ok());
+        assert!(limits.check(&TripleValue::Boolean(true)).is_ok());
+    }
+
+    #[test]
+    fn test_value_limits_new_rejects_out_of_range() {
+        assert!(ValueLimits::new(0).is_err());
+        assert!(ValueLimits::new(MAX_TRIPLE_STRING_VALUE_LENGTH + 1).is_err());
+        assert_eq!(
+            ValueLimits::new(MAX_TRIPLE_STRING_VALUE_LENGTH)
+                .expect("hard limit is allowed")
+                .max_string_length(),
+            MAX_TRIPLE_STRING_VALUE_LENGTH
+        );
+    }
+
+    #[test]
+    fn test_value_limits_default() {
+        assert_eq!(
+            ValueLimits::default().max_string_length(),
+            DEFAULT_MAX_TRIPLE_STRING_VALUE_LENGTH
+        );
+    }
+}
@@ -0,0 +1,150 @@
+//! Ranges of triple values, for scans bounded by value.
+
+use std::cmp::Ordering;
+use std::ops::Bound;
+
+use crate::types::TripleValue;
+
+/// A range of values of one type, ordered by `TripleValue::cmp_total`.
+///
+/// A range holds only values of its bounds' type, so a Number range never
+/// matches a String. NaN falls in no range, as no comparison with NaN holds,
+/// and a NaN bound makes the range empty. With no bounds at all, the range
+/// holds every value except NaN.
+///
+/// # Invariants
+/// - Bounds of both ends, if present, are of the same type for the range to
+///   be non-empty
+#[derive(Debug)]
+pub struct ValueRange {
+    /// The lower bound.
+    pub lower: Bound<TripleValue>,
+    /// The upper bound.
+    pub upper: Bound<TripleValue>,
+}
+
+impl ValueRange {
+    /// Create a range between two bounds.
+    #[must_use]
+    pub const fn new(lower: Bound<TripleValue>, upper: Bound<TripleValue>) -> Self {
+        Self { lower, upper }
+    }
+
+    /// The range of values greater than `value`.
+    #[must_use]
+    pub const fn greater_than(value: TripleValue) -> Self {
+        Self::new(Bound::Excluded(value), Bound::Unbounded)
+    }
+
+    /// The range of values greater than or equal to `value`.
+    #[must_use]
+    pub const fn at_least(value: TripleValue) -> Self {
+        Self::new(Bound::Included(value), Bound::Unbounded)
+    }
+
+    /// The range of values less than `value`.
+    #[must_use]
+    pub const fn less_than(value: TripleValue) -> Self {
+        Self::new(Bound::Unbounded, Bound::Excluded(value))
+    }
+
+    /// The range of values less than or equal to `value`.
+    #[must_use]
+    pub const fn at_most(value: TripleValue) -> Self {
+        Self::new(Bound::Unbounded, Bound::Included(value))
+    }
+
+    /// Check whether `value` falls in the range.
+    #[must_use]
+    pub fn contains(&self, value: &TripleValue) -> bool {
+        if is_nan(value) {
+            return false;
+        }
+        let above_lower = match &self.lower {
+            Bound::Included(bound) => compare(value, bound).is_some_and(Ordering::is_ge),
+            Bound::Excluded(bound) => compare(value, bound).is_some_and(Ordering::is_gt),
+            Bound::Unbounded => true,
+        };
+        let below_upper = match &self.upper {
+            Bound::Included(bound) => compare(value, bound).is_some_and(Ordering::is_le),
+            Bound::Excluded(bound) => compare(value, bound).is_some_and(Ordering::is_lt),
+            Bound::Unbounded => true,
+        };
+        above_lower && below_upper
+    }
+
+    /// Create a copy of this range.
+    ///
+    /// This is used instead of Clone to comply with project policy.
+    #[must_use]
+    pub fn clone_range(&self) -> Self {
+        Self::new(clone_bound(&self.lower), clone_bound(&self.upper))
+    }
+}
+
+/// Compare a value with a bound, or `None` if they have different types or
+/// the bound is NaN.
+fn compare(value: &TripleValue, bound: &TripleValue) -> Option<Ordering> {
+    if value.value_type() != bound.value_type() || is_nan(bound) {
+        return None;
+    }
+    Some(value.cmp_total(bound))
+}
+
+/// Whether a value is a NaN number.
+const fn is_nan(value: &TripleValue) -> bool {
+    matches!(value, TripleValue::Number(n) if n.is_nan())
+}
+
+/// Copy a bound.
+fn clone_bound(bound: &Bound<TripleValue>) -> Bound<TripleValue> {
+    match bound {
+        Bound::Included(value) => Bound::Included(value.clone_value()),
+        Bound::Excluded(value) => Bound::Excluded(value.clone_value()),
+        Bound::Unbounded => Bound::Unbounded,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bounds_are_inclusive_or_exclusive() {
+        let range = ValueRange::new(
+            Bound::Included(TripleValue::Number(-1.0)),
+            Bound::Excluded(TripleValue::Number(2.0)),
+        );
+
+        assert!(!range.contains(&TripleValue::Number(-1.5)));
+        assert!(range.contains(&TripleValue::Number(-1.0)));
+        assert!(range.contains(&TripleValue::Number(0.0)));
+        assert!(range.contains(&TripleValue::Number(-0.0)));
+        assert!(!range.contains(&TripleValue::Number(2.0)));
+    }
+
+    #[test]
+    fn test_nan_falls_in_no_range() {
+        assert!(
+            !ValueRange::at_least(TripleValue::Number(0.0))
+                .contains(&TripleValue::Number(f64::NAN))
+        );
+        assert!(
+            !ValueRange::new(Bound::Unbounded, Bound::Unbounded)
+                .contains(&TripleValue::Number(f64::NAN))
+        );
+        assert!(
+            !ValueRange::at_most(TripleValue::Number(f64::NAN)).contains(&TripleValue::Number(1.0))
+        );
+    }
+
+    #[test]
+    fn test_range_holds_only_its_type() {
+        let range = ValueRange::at_least(TripleValue::String("b".to_string()));
+
+        assert!(range.contains(&TripleValue::String("ba".to_string())));
+        assert!(!range.contains(&TripleValue::String("a".to_string())));
+        assert!(!range.contains(&TripleValue::Number(1.0)));
+        assert!(!range.contains(&TripleValue::Ref(crate::types::EntityId([0xFF; 16]))));
+    }
+}
@@ -0,0 +1,682 @@
+//! WebSocket transport for the sync protocol.
+//!
+//! Each WebSocket connection gets its own `ClientConnection`, which routes to a
+//! database in the shared `DatabaseRegistry` once the client sends its
+//! `ConnectRequest`. Messages are binary-encoded `ClientMessage`/`ServerMessage`
+//! protobufs by default. Text frames carry the JSON encoding from
+//! `types::proto_json` instead; each request is answered in the format it
+//! arrived in, and subscription updates use the format of the most recent
+//! request.
+//!
+//! # Backpressure
+//!
+//! Outgoing frames go through a bounded per-connection queue drained by a
+//! writer task, so a slow client never stalls request handling. Every frame is
+//! critical: dropping a response or subscription update would leave the client
+//! silently out of sync. So when the queue fills, the connection is shed:
+//! queued frames are discarded, the client gets a close frame with code 1013
+//! (try again later), and it must reconnect and resubscribe from its last HLC.
+//! When the connection's change receiver lags behind the broadcast channel
+//! instead, the queued frames are still sent, followed by an `Unavailable`
+//! error with a `SubscriptionLagged` reason and the same close frame. Each
+//! shed is logged and counted in `shed_connection_count`.
+//!
+//! # Message Size Limit
+//!
+//! Messages from a client may be at most `ServerConfig::max_message_size`
+//! bytes. The WebSocket layer enforces the limit from the frame header, so an
+//! oversized frame is rejected before its payload is buffered, and `serve`
+//! checks each message's length again before decoding it. Either way the
+//! client gets a `ResourceExhausted` error response, then a close frame with
+//! code 1009 (message too big).
+
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+
+use axum::{
+    Router,
+    extract::{
+        State,
+        ws::{CloseFrame, Message, WebSocket, WebSocketUpgrade, close_code},
+    },
+    response::IntoResponse,
+    routing::{any, get, post},
+};
+use futures::stream::SplitStream;
+use futures::{Sink, SinkExt, StreamExt};
+use prost::Message as ProstMessage;
+use tokio::sync::mpsc::error::TrySendError;
+use tokio::sync::{broadcast, mpsc, oneshot};
+use tokio_tungstenite::tungstenite;
+
+use crate::admin::maintenance_handler;
+use crate::auth::ConfigRegistry;
+use crate::config::ServerConfig;
+use crate::constants::OUTBOUND_QUEUE_CAPACITY;
+use crate::rest::{entity_handler, query_handler, write_triples_handler};
+use crate::types::error_detail::pack_error_detail;
+use crate::types::proto_json;
+use crate::{ClientConnection, DatabaseRegistry, proto};
+
+/// How long a shed connection waits to send its close frame.
+const SHED_CLOSE_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// Connections closed so far because their client fell behind.
+static SHED_CONNECTIONS: AtomicU64 = AtomicU64::new(0);
+
+/// Number of connections closed so far because their client fell behind.
+pub fn shed_connection_count() -> u64 {
+    SHED_CONNECTIONS.load(Ordering::Relaxed)
+}
+
+/// Shared state for every WebSocket connection.
+#[derive(Clone)]
+#[allow(clippy::disallowed_methods)] // Arc::clone is safe and expected for shared state
+pub struct AppState {
+    /// Database registry - manages per-app databases.
+    /// Each WebSocket connection creates its own `ClientConnection` that
+    /// opens/creates the database based on the `app_api_key` in `ConnectRequest`.
+    pub registry: Arc<DatabaseRegistry>,
+    /// Server configuration.
+    pub config: Arc<ServerConfig>,
+    /// Per-app configurations; authenticates each `ConnectRequest` and
+    /// scopes the connection to its app's attributes.
+    pub configs: Arc<ConfigRegistry>,
+}
+
+/// Encoding of a WebSocket frame, chosen by the client per message.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum WireFormat {
+    /// Protobuf in a binary frame.
+    Binary,
+    /// JSON in a text frame.
+    Json,
+}
+
+impl WireFormat {
+    /// Decode a `ClientMessage` from a frame payload in this format.
+    fn decode(self, data: &[u8]) -> Result<proto::ClientMessage, String> {
+        match self {
+            Self::Binary => proto::ClientMessage::decode(data).map_err(|e| e.to_string()),
+            Self::Json => std::str::from_utf8(data)
+                .map_err(|e| e.to_string())
+                .and_then(|text| {
+                    proto_json::decode_client_message(text).map_err(|e| e.to_string())
+                }),
+        }
+    }
+
+    /// Encode a `ServerMessage` as a frame in this format.
+    fn encode(self, message: &proto::ServerMessage) -> Message {
+        match self {
+            Self::Binary => Message::Binary(message.encode_to_vec().into()),
+            Self::Json => match proto_json::encode_server_message(message) {
+                Ok(text) => Message::Text(text.into()),
+                Err(e) => {
+                    // Generated types always serialize; fall back to protobuf just in case
+                    tracing::error!("failed to encode ServerMessage as JSON: {e}");
+                    Message::Binary(message.encode_to_vec().into())
+                }
+            },
+        }
+    }
+}
+
+/// Build the router that serves the sync protocol at `/ws`, the REST gateway
+/// under `/v1` (see `rest`), and the admin routes under `/admin` (see
+/// `admin`).
+pub fn router(state: AppState) -> Router {
+    Router::new()
+        .route("/ws", any(ws_handler))
+        .route("/v1/triples", post(write_triples_handler))
+        .route("/v1/query", post(query_handler))
+        .route("/v1/entity/{id}", get(entity_handler))
+        .route(
+            "/admin/maintenance/{app_api_key}",
+            post(maintenance_handler),
+        )
+        .with_state(state)
+}
+
+async fn ws_handler(ws: WebSocketUpgrade, State(state): State<AppState>) -> impl IntoResponse {
+    tracing::debug!("got a websocket connection");
+    let max_message_size = state.config.max_message_size;
+    ws.max_message_size(max_message_size)
+        .max_frame_size(max_message_size)
+        .on_upgrade(move |socket| handle_socket(socket, state))
+}
+
+async fn handle_socket(socket: WebSocket, state: AppState) {
+    let (sink, stream) = socket.split();
+    let (frames, frame_rx) = mpsc::channel(OUTBOUND_QUEUE_CAPACITY);
+    let (shed, shed_rx) = oneshot::channel();
+    let writer = tokio::spawn(write_frames(sink, frame_rx, shed_rx));
+
+    // Dropping the queue when `serve` returns lets the writer flush and close
+    match serve(stream, Outbound { frames }, &state).await {
+        ConnectionEnd::Shed => {
+            SHED_CONNECTIONS.fetch_add(1, Ordering::Relaxed);
+            let _ = shed.send(());
+        }
+        ConnectionEnd::Lagged => {
+            SHED_CONNECTIONS.fetch_add(1, Ordering::Relaxed);
+        }
+        ConnectionEnd::Closed => {}
+    }
+    let _ = writer.await;
+}
+
+/// Why `serve` returned.
+#[derive(Debug, PartialEq, Eq)]
+enum ConnectionEnd {
+    /// The client disconnected, or the connection closed itself normally.
+    Closed,
+    /// The client fell behind and must reconnect to resync.
+    Shed,
+    /// The client's subscriptions fell behind. It was told why, and the
+    /// queued frames are flushed before the connection closes.
+    Lagged,
+}
+
+/// Bounded queue of frames waiting to be written to the client.
+struct Outbound {
+    frames: mpsc::Sender<Message>,
+}
+
+impl Outbound {
+    /// Queue a frame without waiting.
+    ///
+    /// # Errors
+    ///
+    /// Returns `ConnectionEnd::Shed` if the queue is full, or
+    /// `ConnectionEnd::Closed` if the writer has stopped because the client
+    /// went away.
+    fn push(&self, frame: Message) -> Result<(), ConnectionEnd> {
+        self.frames.try_send(frame).map_err(|e| match e {
+            TrySendError::Full(_) => {
+                tracing::warn!(
+                    "client has {OUTBOUND_QUEUE_CAPACITY} frames queued, closing connection"
+                );
+                ConnectionEnd::Shed
+            }
+            TrySendError::Closed(_) => {
+                tracing::debug!("client disconnected");
+                ConnectionEnd::Closed
+            }
+        })
+    }
+}
+
+/// Write queued frames to the client until the queue is dropped or the
+/// connection is shed.
+///
+/// On shedding, queued frames are discarded and the client gets a close frame
+/// with code 1013 (try again later).
+async fn write_frames<S>(
+    mut sink: S,
+    mut frames: mpsc::Receiver<Message>,
+    shed: oneshot::Receiver<()>,
+) where
+    S: Sink<Message> + Unpin,
+{
+    let drain = async {
+        while let Some(frame) = frames.recv().await {
+            if sink.send(frame).await.is_err() {
+                return;
+            }
+        }
+        let _ = sink.close().await;
+    };
+    let shed = tokio::select! {
+        () = drain => false,
+        Ok(()) = shed => true,
+    };
+
+    if shed {
+        let close = Message::Close(Some(CloseFrame {
+            code: close_code::AGAIN,
+            reason: "client too slow".into(),
+        }));
+        // A client too slow to drain its frames may not take this one either
+        let _ = tokio::time::timeout(SHED_CLOSE_TIMEOUT, sink.send(close)).await;
+    }
+}
+
+/// Relay client requests and subscription updates until the connection ends.
+#[allow(clippy::too_many_lines, clippy::disallowed_methods)]
+async fn serve(
+    mut stream: SplitStream<WebSocket>,
+    outbound: Outbound,
+    state: &AppState,
+) -> ConnectionEnd {
+    // Create a per-connection ClientConnection that awaits ConnectRequest
+    let mut client_connection = ClientConnection::new_awaiting_connect(Arc::clone(&state.registry))
+        .with_value_limits(state.config.value_limits)
+        .with_max_subscriptions(state.config.max_subscriptions_per_connection)
+        .with_config_registry(Arc::clone(&state.configs));
+
+    // Change receiver - will be set up after ConnectRequest is processed
+    let mut change_rx: Option<crate::storage::FilteredChangeReceiver> = None;
+
+    // Format for pushed subscription updates - follows the latest request
+    let mut push_format = WireFormat::Binary;
+    let max_message_size = state.config.max_message_size;
+
+    loop {
+        let transaction_deadline = client_connection.transaction_deadline();
+        tokio::select! {
+            // Handle incoming WebSocket messages
+            msg = stream.next() => {
+                let msg = match msg {
+                    Some(Ok(msg)) => msg,
+                    Some(Err(e)) if is_message_too_big(&e) => {
+                        reject_oversized_message(&outbound, push_format, None, max_message_size);
+                        return ConnectionEnd::Closed;
+                    }
+                    Some(Err(e)) => {
+                        tracing::warn!("websocket receive error: {e}");
+                        return ConnectionEnd::Closed;
+                    }
+                    None => {
+                        tracing::debug!("client disconnected");
+                        return ConnectionEnd::Closed;
+                    }
+                };
+
+                // Binary frames carry protobuf, text frames carry JSON
+                let (format, data) = match msg {
+                    Message::Binary(data) => (WireFormat::Binary, data),
+                    Message::Text(text) => (WireFormat::Json, text.into()),
+                    Message::Ping(data) => {
+                        if let Err(end) = outbound.push(Message::Pong(data)) {
+                            return end;
+                        }
+                        continue;
+                    }
+                    Message::Pong(_) => continue,
+                    Message::Close(_) => {
+                        tracing::debug!("client sent close");
+                        return ConnectionEnd::Closed;
+                    }
+                };
+
+                // Decoding, handling and queueing the responses share one span
+                let _frame = tracing::debug_span!("frame", format = ?format, bytes = data.len()).entered();
+
+                // Never decode a message over the limit
+                if data.len() > max_message_size {
+                    reject_oversized_message(&outbound, format, Some(data.len()), max_message_size);
+                    return ConnectionEnd::Closed;
+                }
+
+                // Decode the ClientMessage
+                let client_message = match format.decode(data.as_ref()) {
+                    Ok(msg) => msg,
+                    Err(e) => {
+                        tracing::warn!("failed to decode ClientMessage: {e}");
+                        if let Err(end) = send_error_response(&outbound, format, None, proto::google::rpc::Code::InvalidArgument, &format!("Failed to decode message: {e}"), None) {
+                            return end;
+                        }
+                        continue;
+                    }
+                };
+
+                // Handle the message through ClientConnection
+                push_format = format;
+                let request_id = client_message.request_id;
+                let messages = client_connection.handle_message(client_message);
+                for msg in messages {
+                    if let Err(end) = outbound.push(format.encode(&msg)) {
+                        return end;
+                    }
+                }
+
+                // A panic elsewhere poisoned the database lock. The request
+                // that hit it was already answered with an Internal error;
+                // every later one would fail too, so close the connection.
+                if client_connection.is_database_poisoned() {
+                    tracing::error!("database lock poisoned, closing connection");
+                    let _ = outbound.push(Message::Close(None));
+                    return ConnectionEnd::Closed;
+                }
+
+                // If we just connected, set up the change receiver for subscriptions
+                if change_rx.is_none() && client_connection.is_connected() {
+                    match client_connection.subscribe_to_changes() {
+                        Ok(rx) => {
+                            change_rx = Some(rx);
+                        }
+                        Err(e) => {
+                            tracing::error!("Failed to subscribe to changes: {e}");
+                            close_with_internal_error(&outbound, format, request_id, &format!("Failed to subscribe to changes: {e}"));
+                            return ConnectionEnd::Closed;
+                        }
+                    }
+                }
+            }
+
+            // Abort an explicit transaction the client abandoned, so its
+            // snapshot is unpinned without waiting for another message
+            () = async {
+                match transaction_deadline {
+                    Some(deadline) => tokio::time::sleep_until(deadline.into()).await,
+                    None => std::future::pending().await,
+                }
+            } => {
+                client_connection.abort_expired_transaction();
+            }
+
+            // Handle broadcast notifications for subscriptions
+            // (FilteredChangeReceiver automatically excludes this connection's own writes)
+            // Only active after connection is established
+            notification = async {
+                match &mut change_rx {
+                    Some(rx) => rx.recv().await,
+                    None => std::future::pending().await,
+                }
+            } => {
+                match notification {
+                    Ok(change) => {
+                        // Forward changes to all matching subscriptions, in
+                        // commit order (since_hlc filtering was already done
+                        // during initial backfill)
+                        for update in client_connection.updates_for(&change) {
+                            let msg = proto::ServerMessage {
+                                payload: Some(proto::server_message::Payload::SubscriptionUpdate(update)),
+                            };
+                            if let Err(end) = outbound.push(push_format.encode(&msg)) {
+                                return end;
+                            }
+                        }
+                    }
+                    Err(broadcast::error::RecvError::Lagged(count)) => {
+                        // Subscriptions missed changes; the client must resync
+                        tracing::warn!("subscription receiver lagged by {count} messages, closing connection");
+                        return report_lag(&outbound, push_format, count);
+                    }
+                    Err(broadcast::error::RecvError::Closed) => {
+                        tracing::debug!("broadcast channel closed");
+                        return ConnectionEnd::Closed;
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Queue an `Internal` error response, then close the connection.
+///
+/// Errors queueing either frame are ignored: the connection is going away.
+fn close_with_internal_error(
+    outbound: &Outbound,
+    format: WireFormat,
+    request_id: Option<u32>,
+    message: &str,
+) {
+    let _ = send_error_response(
+        outbound,
+        format,
+        request_id,
+        proto::google::rpc::Code::Internal,
+        message,
+        None,
+    );
+    let _ = outbound.push(Message::Close(None));
+}
+
+/// Tell a client its subscriptions missed `missed` changes, with an
+/// `Unavailable` error carrying a `SubscriptionLagged` reason, then close the
+/// connection with code 1013 (try again later) so it resyncs.
+///
+/// A lagging change receiver leaves the frame queue with room, so frames
+/// queued before the error still reach the client. If the error does not
+/// fit, the connection is shed instead.
+fn report_lag(outbound: &Outbound, format: WireFormat, missed: u64) -> ConnectionEnd {
+    let queued = send_error_response(
+        outbound,
+        format,
+        None,
+        proto::google::rpc::Code::Unavailable,
+        &format!("Subscriptions missed {missed} changes; resubscribe from the last HLC received"),
+        Some(proto::error_detail::Reason::SubscriptionLagged(
+            proto::SubscriptionLagged { missed },
+        )),
+    )
+    .and_then(|()| {
+        outbound.push(Message::Close(Some(CloseFrame {
+            code: close_code::AGAIN,
+            reason: "subscriptions lagged".into(),
+        })))
+    });
+    match queued {
+        Ok(()) => ConnectionEnd::Lagged,
+        Err(end) => end,
+    }
+}
+
+/// Queue a `ResourceExhausted` error response for a message over the size
+/// limit, then close the connection with code 1009 (message too big).
+///
+/// `size` is the message's size, if known; the WebSocket layer rejects
+/// oversized frames from their header, before the whole size is read.
+/// Errors queueing either frame are ignored: the connection is going away.
+fn reject_oversized_message(
+    outbound: &Outbound,
+    format: WireFormat,
+    size: Option<usize>,
+    max_message_size: usize,
+) {
+    let message = size.map_or_else(
+        || format!("Message exceeds the maximum size of {max_message_size} bytes"),
+        |size| {
+            format!("Message of {size} bytes exceeds the maximum size of {max_message_size} bytes")
+        },
+    );
+    tracing::warn!("{message}, closing connection");
+    let _ = send_error_response(
+        outbound,
+        format,
+        None,
+        proto::google::rpc::Code::ResourceExhausted,
+        &message,
+        Some(proto::error_detail::Reason::MessageTooLarge(
+            proto::MessageTooLarge {
+                limit: max_message_size as u64,
+            },
+        )),
+    );
+    let _ = outbound.push(Message::Close(Some(CloseFrame {
+        code: close_code::SIZE,
+        reason: "message too big".into(),
+    })));
+}
+
+/// Whether a receive error is the WebSocket layer rejecting a message over
+/// the size limit.
+fn is_message_too_big(error: &axum::Error) -> bool {
+    let source = std::error::Error::source(error);
+    matches!(
+        source.and_then(|e| e.downcast_ref::<tungstenite::Error>()),
+        Some(tungstenite::Error::Capacity(
+            tungstenite::error::CapacityError::MessageTooLong { .. }
+        ))
+    )
+}
+
+/// Queue an error response for the client.
+fn send_error_response(
+    outbound: &Outbound,
+    format: WireFormat,
+    request_id: Option<u32>,
+    code: proto::google::rpc::Code,
+    message: &str,
+    reason: Option<proto::error_detail::Reason>,
+) -> Result<(), ConnectionEnd> {
+    let error_response = proto::ServerMessage {
+        payload: Some(proto::server_message::Payload::Response(
+            proto::ServerResponse {
+                request_id,
+                status: Some(proto::google::rpc::Status {
+                    code: code.into(),
+                    message: message.to_string(),
+                    details: reason.into_iter().map(pack_error_detail).collect(),
+                }),
+                ..Default::default()
+            },
+        )),
+    };
+    outbound.push(format.encode(&error_response))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_binary_decode_rejects_invalid_utf8_strings() {
+        let message = proto::ClientMessage {
+            request_id: Some(1),
+            payload: Some(proto::client_message::Payload::TripleUpdateRequest(
+                proto::TripleUpdateRequest {
+                    triples: vec![proto::Triple {
+                        value: Some(proto::TripleValue {
+                            value: Some(proto::triple_value::Value::String("ok!".to_owned())),
+                        }),
+                        ..Default::default()
+                    }],
+                    mode: proto::WriteMode::Upsert.into(),
+                    expected_versions: vec![],
+                },
+            )),
+        };
+        let mut data = message.encode_to_vec();
+        assert!(WireFormat::Binary.decode(&data).is_ok());
+
+        // Replace the string's last byte with a lone continuation byte
+        let position = data
+            .windows(3)
+            .position(|window| window == b"ok!")
+            .expect("string is encoded verbatim");
+        data[position + 2] = 0x80;
+
+        let err = WireFormat::Binary
+            .decode(&data)
+            .expect_err("invalid UTF-8 is rejected");
+        assert!(err.contains("not UTF-8 encoded"), "{err}");
+    }
+
+    #[test]
+    fn test_outbound_push_sheds_when_full() {
+        let (frames, _frame_rx) = mpsc::channel(1);
+        let outbound = Outbound { frames };
+
+        assert_eq!(outbound.push(Message::Text("first".into())), Ok(()));
+        assert_eq!(
+            outbound.push(Message::Text("second".into())),
+            Err(ConnectionEnd::Shed)
+        );
+    }
+
+    #[test]
+    fn test_outbound_push_after_writer_stopped() {
+        let (frames, frame_rx) = mpsc::channel(1);
+        let outbound = Outbound { frames };
+        drop(frame_rx);
+
+        assert_eq!(
+            outbound.push(Message::Text("late".into())),
+            Err(ConnectionEnd::Closed)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_write_frames_flushes_queue_in_order() {
+        let (sink, mut written) = futures::channel::mpsc::unbounded();
+        let (frames, frame_rx) = mpsc::channel(4);
+        let (_shed, shed_rx) = oneshot::channel();
+
+        frames
+            .try_send(Message::Text("first".into()))
+            .expect("queue has room");
+        frames
+            .try_send(Message::Text("second".into()))
+            .expect("queue has room");
+        drop(frames);
+        write_frames(sink, frame_rx, shed_rx).await;
+
+        assert_eq!(written.next().await, Some(Message::Text("first".into())));
+        assert_eq!(written.next().await, Some(Message::Text("second".into())));
+        // The sink was closed once the queue was dropped
+        assert_eq!(written.next().await, None);
+    }
+
+    #[tokio::test]
+    async fn test_write_frames_sends_close_when_shed() {
+        let (sink, mut written) = futures::channel::mpsc::unbounded();
+        let (_frames, frame_rx) = mpsc::channel::<Message>(4);
+        let (shed, shed_rx) = oneshot::channel();
+
+        shed.send(()).expect("writer is listening");
+        write_frames(sink, frame_rx, shed_rx).await;
+
+        assert_eq!(
+            written.next().await,
+            Some(Message::Close(Some(CloseFrame {
+                code: close_code::AGAIN,
+                reason: "client too slow".into(),
+            })))
+        );
+    }
+
+    #[test]
+    fn test_report_lag_sends_reason_then_close() {
+        let (frames, mut frame_rx) = mpsc::channel(4);
+        let outbound = Outbound { frames };
+
+        assert_eq!(
+            report_lag(&outbound, WireFormat::Binary, 7),
+            ConnectionEnd::Lagged
+        );
+
+        let Ok(Message::Binary(data)) = frame_rx.try_recv() else {
+            panic!("expected an error response");
+        };
+        let Some(proto::server_message::Payload::Response(response)) =
+            proto::ServerMessage::decode(data.as_ref())
+                .expect("decode")
+                .payload
+        else {
+            panic!("expected a response");
+        };
+        let status = response.status.expect("status");
+        assert_eq!(status.code, proto::google::rpc::Code::Unavailable as i32);
+        assert_eq!(
+            crate::types::error_detail::error_reason(&status),
+            Some(proto::error_detail::Reason::SubscriptionLagged(
+                proto::SubscriptionLagged { missed: 7 }
+            ))
+        );
+        assert_eq!(
+            frame_rx.try_recv().ok(),
+            Some(Message::Close(Some(CloseFrame {
+                code: close_code::AGAIN,
+                reason: "subscriptions lagged".into(),
+            })))
+        );
+    }
+
+    #[test]
+    fn test_report_lag_sheds_when_queue_full() {
+        let (frames, _frame_rx) = mpsc::channel(1);
+        let outbound = Outbound { frames };
+        outbound
+            .push(Message::Text("queued".into()))
+            .expect("queue has room");
+
+        assert_eq!(
+            report_lag(&outbound, WireFormat::Binary, 7),
+            ConnectionEnd::Shed
+        );
+    }
+}